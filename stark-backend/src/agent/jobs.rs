@@ -0,0 +1,417 @@
+//! Background queue for agent runs
+//!
+//! Jobs are stored in the `agent_jobs` table and executed one at a time by a
+//! worker task. Progress is written back after every tool round so callers can
+//! poll a job while it runs, and jobs interrupted by a restart are picked up
+//! again when the worker starts, told which tool calls they already made. A
+//! job can be cancelled while queued or running; a running job stops before
+//! its next model call or tool call.
+//!
+//! On shutdown the queue stops taking jobs, gives the running one a grace
+//! period to finish and then interrupts it the same way as a cancel, except
+//! that it goes back on the queue with its progress instead of ending.
+
+use crate::agent::AgentRunner;
+use crate::ai::archetypes::profile;
+use crate::ai::{AiClient, ResponseFormat};
+use crate::context::ContextWindow;
+use crate::db::Database;
+use crate::execution::ProcessManager;
+use crate::models::{AgentJob, AgentJobStatus, AgentSettings};
+use crate::tools::{OutputStore, ToolContext, ToolRegistry};
+use crate::utils::truncate_str;
+use crate::workspace::{WorkspaceKind, WorkspaceManager};
+use dashmap::DashMap;
+use serde::Serialize;
+use serde_json::Value;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::{oneshot, Notify};
+use tokio_util::sync::CancellationToken;
+
+/// How often the worker re-checks the queue when it has not been notified
+const POLL_INTERVAL: Duration = Duration::from_secs(30);
+
+/// Most earlier tool calls listed to a job resuming after an interruption
+const MAX_RESUME_CALLS: usize = 30;
+
+/// Longest tool call arguments listed to a resuming job, in characters
+const MAX_RESUME_ARGS_CHARS: usize = 200;
+
+pub struct AgentJobQueue {
+    db: Arc<Database>,
+    tool_registry: Arc<ToolRegistry>,
+    process_manager: Arc<ProcessManager>,
+    burner_wallet_private_key: Option<String>,
+    notify: Notify,
+    /// Cancellation tokens of the jobs being run, by job id
+    running: DashMap<String, CancellationToken>,
+    /// Set on shutdown: no new jobs are queued or started
+    stopping: CancellationToken,
+    /// Parent of the running jobs' tokens; cancelled when shutdown stops them
+    interrupt: CancellationToken,
+}
+
+impl AgentJobQueue {
+    pub fn new(
+        db: Arc<Database>,
+        tool_registry: Arc<ToolRegistry>,
+        process_manager: Arc<ProcessManager>,
+        burner_wallet_private_key: Option<String>,
+    ) -> Self {
+        Self {
+            db,
+            tool_registry,
+            process_manager,
+            burner_wallet_private_key,
+            notify: Notify::new(),
+            running: DashMap::new(),
+            stopping: CancellationToken::new(),
+            interrupt: CancellationToken::new(),
+        }
+    }
+
+    /// Persist a job and wake the worker. An empty `tools` list means the
+    /// default CodeEngineer tool set; `planning` starts the run with a plan,
+    /// and a `response_format` makes it answer in JSON. `user_id` is the user
+    /// who queued it.
+    #[allow(clippy::too_many_arguments)]
+    pub async fn enqueue(
+        &self,
+        task: &str,
+        workspace: &str,
+        max_iterations: usize,
+        tools: &[String],
+        schedule_id: Option<i64>,
+        planning: bool,
+        response_format: Option<&ResponseFormat>,
+        user_id: Option<i64>,
+    ) -> Result<AgentJob, String> {
+        if self.is_stopping() {
+            return Err("The server is shutting down and not accepting jobs".to_string());
+        }
+        let job = self
+            .db
+            .create_agent_job(task, workspace, max_iterations as i64, tools, schedule_id, planning, response_format, user_id)
+            .await
+            .map_err(|e| format!("Failed to queue agent job: {}", e))?;
+        tracing::info!("[AGENT_JOB] Queued job {} in workspace '{}'", job.job_id, workspace);
+        self.notify.notify_one();
+        Ok(job)
+    }
+
+    /// Cancel a queued or running job and kill the background processes it
+    /// started. Returns false if the job doesn't exist or has already finished.
+    pub async fn cancel(&self, job_id: &str) -> Result<bool, String> {
+        let cancelled = self
+            .db
+            .cancel_agent_job(job_id).await
+            .map_err(|e| format!("Failed to cancel agent job: {}", e))?;
+        if !cancelled {
+            return Ok(false);
+        }
+
+        if let Some(token) = self.running.get(job_id) {
+            token.cancel();
+        }
+        self.process_manager.kill_all_for_job(job_id).await;
+        tracing::info!("[AGENT_JOB] Cancelled job {}", job_id);
+        Ok(true)
+    }
+
+    /// Stop taking jobs: `enqueue` refuses new ones, and the worker exits once
+    /// the running job is done
+    pub fn stop(&self) {
+        self.stopping.cancel();
+    }
+
+    pub fn is_stopping(&self) -> bool {
+        self.stopping.is_cancelled()
+    }
+
+    /// Stop the running job before its next model call or tool call and put
+    /// it back on the queue with its progress
+    pub fn interrupt(&self) {
+        self.interrupt.cancel();
+    }
+
+    /// Run queued jobs until shutdown is signalled
+    pub async fn start(self: Arc<Self>, mut shutdown_rx: oneshot::Receiver<()>) {
+        match self.db.requeue_interrupted_agent_jobs().await {
+            Ok(0) => {}
+            Ok(n) => tracing::info!("[AGENT_JOB] Requeued {} job(s) interrupted by restart", n),
+            Err(e) => tracing::error!("[AGENT_JOB] Failed to requeue interrupted jobs: {}", e),
+        }
+
+        loop {
+            while !self.is_stopping() {
+                match self.db.claim_next_agent_job().await {
+                    Ok(Some(job)) => self.run_job(job).await,
+                    Ok(None) => break,
+                    Err(e) => {
+                        tracing::error!("[AGENT_JOB] Failed to claim next job: {}", e);
+                        break;
+                    }
+                }
+            }
+
+            tokio::select! {
+                _ = &mut shutdown_rx => {
+                    tracing::info!("[AGENT_JOB] Worker shutting down");
+                    return;
+                }
+                _ = self.stopping.cancelled() => {
+                    tracing::info!("[AGENT_JOB] Worker stopped taking jobs");
+                    return;
+                }
+                _ = self.notify.notified() => {}
+                _ = tokio::time::sleep(POLL_INTERVAL) => {}
+            }
+        }
+    }
+
+    #[tracing::instrument(name = "agent_job", skip_all, fields(request_id = %job.job_id))]
+    async fn run_job(&self, job: AgentJob) {
+        tracing::info!("[AGENT_JOB] Running job {}", job.job_id);
+
+        let cancellation = self.interrupt.child_token();
+        self.running.insert(job.job_id.clone(), cancellation.clone());
+        // A cancel that came in between claiming the job and registering its token
+        if self.is_cancelled(&job.job_id).await {
+            cancellation.cancel();
+        }
+        self.run_job_until_cancelled(&job, &cancellation).await;
+        self.running.remove(&job.job_id);
+        if !self.interrupt.is_cancelled() {
+            // A job's background processes don't outlive it, including ones started
+            // after a cancel request by a tool that was already running
+            let killed = self.process_manager.kill_all_for_job(&job.job_id).await;
+            if killed > 0 && !cancellation.is_cancelled() {
+                tracing::info!("[AGENT_JOB] Stopped {} background process(es) left by job {}", killed, job.job_id);
+            }
+        }
+    }
+
+    async fn is_cancelled(&self, job_id: &str) -> bool {
+        matches!(self.db.get_agent_job(job_id).await, Ok(Some(j)) if j.status == AgentJobStatus::Cancelled)
+    }
+
+    async fn run_job_until_cancelled(&self, job: &AgentJob, cancellation: &CancellationToken) {
+        let runner = match self.build_runner(job).await {
+            Ok(runner) => runner,
+            Err(e) => {
+                tracing::error!("[AGENT_JOB] Job {} failed to start: {}", job.job_id, e);
+                let empty = serde_json::json!([]);
+                self.finish(job, AgentJobStatus::Failed, 0, &empty, &empty, None, Some(&e)).await;
+                return;
+            }
+        };
+
+        // The progress callback is sync, so updates are queued to a writer task
+        // that applies them in order
+        let (progress_tx, mut progress_rx) = tokio::sync::mpsc::unbounded_channel::<(i64, Value, Value, Value)>();
+        let db = Arc::clone(&self.db);
+        let job_id = job.job_id.clone();
+        let progress_writer = tokio::spawn(async move {
+            while let Some((iterations, transcript, attempts, plan)) = progress_rx.recv().await {
+                if let Err(e) = db.update_agent_job_progress(&job_id, iterations, &transcript, &attempts, &plan).await {
+                    tracing::warn!("[AGENT_JOB] Failed to record progress for {}: {}", job_id, e);
+                }
+            }
+        });
+        // A resumed job's records continue those of its earlier runs
+        let task = if is_resuming(job) { resume_task(job) } else { job.task.clone() };
+        let result = runner
+            .with_cancellation(cancellation.clone())
+            .run_with_progress(&task, |iterations, calls, attempts, plan| {
+                let transcript = appended(&job.transcript, calls);
+                let attempts = appended(&job.provider_attempts, attempts);
+                let plan = serde_json::to_value(plan).unwrap_or_default();
+                let _ = progress_tx.send((job.iterations + iterations as i64, transcript, attempts, plan));
+            })
+            .await;
+        drop(progress_tx);
+        let _ = progress_writer.await;
+        let iterations = job.iterations + result.iterations as i64;
+
+        // Stopped by shutdown rather than cancelled: the next start picks it up
+        if self.interrupt.is_cancelled() && !self.is_cancelled(&job.job_id).await {
+            match self.db.requeue_agent_job(&job.job_id).await {
+                Ok(_) => tracing::info!(
+                    "[AGENT_JOB] Job {} interrupted by shutdown after {} iteration(s); it resumes on the next start",
+                    job.job_id,
+                    iterations
+                ),
+                Err(e) => tracing::error!("[AGENT_JOB] Failed to requeue interrupted job {}: {}", job.job_id, e),
+            }
+            return;
+        }
+
+        // A run that finished while the cancel came in is still recorded as cancelled
+        let status = if result.cancelled || cancellation.is_cancelled() {
+            AgentJobStatus::Cancelled
+        } else if result.success {
+            AgentJobStatus::Completed
+        } else {
+            AgentJobStatus::Failed
+        };
+        tracing::info!(
+            "[AGENT_JOB] Job {} {} after {} iteration(s)",
+            job.job_id,
+            status.as_str(),
+            iterations
+        );
+
+        if !result.provider_attempts.is_empty() {
+            tracing::info!(
+                "[AGENT_JOB] Job {} had {} failed provider call(s)",
+                job.job_id,
+                result.provider_attempts.len()
+            );
+        }
+        let transcript = appended(&job.transcript, &result.tool_calls);
+        let attempts = appended(&job.provider_attempts, &result.provider_attempts);
+        let response = (!result.response.is_empty()).then_some(result.response.as_str());
+        self.finish(
+            job,
+            status,
+            iterations,
+            &transcript,
+            &attempts,
+            response,
+            result.error.as_deref(),
+        ).await;
+    }
+
+    async fn build_runner(&self, job: &AgentJob) -> Result<AgentRunner, String> {
+        let workspaces = WorkspaceManager::from_env();
+        let workspace_dir = workspaces.allocate(WorkspaceKind::AgentRun, &job.workspace)?;
+        let output_store = OutputStore::new(workspaces.outputs_dir(&job.job_id)?);
+
+        let settings = self
+            .db
+            .get_active_agent_settings().await
+            .map_err(|e| format!("Failed to load agent settings: {}", e))?
+            .unwrap_or_else(AgentSettings::default);
+
+        let response_format = job.response_format.as_ref();
+        let client = AiClient::from_settings_with_wallet(&settings, self.burner_wallet_private_key.as_deref())
+            .map_err(|e| format!("Failed to create AI client: {}", e))?
+            .with_response_format(response_format);
+        let fallback = AiClient::fallback_from_settings(&settings, self.burner_wallet_private_key.as_deref())
+            .map_err(|e| format!("Failed to create fallback AI client: {}", e))?
+            .map(|fallback| fallback.with_response_format(response_format));
+
+        let tool_context = ToolContext::new()
+            .with_job(job.job_id.clone())
+            .with_workspace(workspace_dir.to_string_lossy().to_string())
+            .with_process_manager(Arc::clone(&self.process_manager))
+            .with_database(Arc::clone(&self.db));
+
+        // A resumed job gets the iterations it has left, and keeps to the plan it already made
+        let max_iterations = (job.max_iterations - job.iterations).max(1) as usize;
+        Ok(AgentRunner::new(client, Arc::clone(&self.tool_registry), tool_context)
+            .with_max_iterations(max_iterations)
+            .with_planning(job.planning && !is_resuming(job))
+            .with_response_format(job.response_format.clone())
+            .with_fallback(fallback)
+            .with_context_window(ContextWindow::from_settings(&settings))
+            .with_prompt_suffix(profile::profile(AiClient::infer_archetype(&settings)).system_prompt_suffix)
+            .with_output_store(output_store)
+            .with_uploads(workspaces.uploads(WorkspaceKind::AgentRun, &job.workspace))
+            .with_tools(&job.tools))
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    async fn finish(
+        &self,
+        job: &AgentJob,
+        status: AgentJobStatus,
+        iterations: i64,
+        transcript: &Value,
+        provider_attempts: &Value,
+        response: Option<&str>,
+        error: Option<&str>,
+    ) {
+        if let Err(e) = self
+            .db
+            .finish_agent_job(&job.job_id, status, iterations, transcript, provider_attempts, response, error).await
+        {
+            tracing::error!("[AGENT_JOB] Failed to record result for {}: {}", job.job_id, e);
+        }
+    }
+}
+
+/// Whether the job ran before and was interrupted (by shutdown or a crash)
+fn is_resuming(job: &AgentJob) -> bool {
+    job.iterations > 0
+}
+
+/// Records of this run appended to those `prior` runs of the job left
+fn appended<T: Serialize>(prior: &Value, records: &[T]) -> Value {
+    let mut all = prior.as_array().cloned().unwrap_or_default();
+    all.extend(records.iter().filter_map(|r| serde_json::to_value(r).ok()));
+    Value::Array(all)
+}
+
+/// The task of an interrupted job, with the tool calls it already made so
+/// the model continues rather than starting over
+fn resume_task(job: &AgentJob) -> String {
+    let mut task = format!(
+        "{}\n\n---\nThis task was interrupted by a server restart after {} iteration(s). \
+         The workspace still holds the work done so far.",
+        job.task, job.iterations
+    );
+    let calls = job.transcript.as_array().map(Vec::as_slice).unwrap_or_default();
+    if !calls.is_empty() {
+        task.push_str(" Tool calls already made, oldest first:\n");
+        if calls.len() > MAX_RESUME_CALLS {
+            task.push_str(&format!("- ({} earlier calls)\n", calls.len() - MAX_RESUME_CALLS));
+        }
+        for call in &calls[calls.len().saturating_sub(MAX_RESUME_CALLS)..] {
+            task.push_str(&format!(
+                "- {} {} ({})\n",
+                call["name"].as_str().unwrap_or("?"),
+                truncate_str(&call["arguments"].to_string(), MAX_RESUME_ARGS_CHARS),
+                if call["success"].as_bool() == Some(true) { "ok" } else { "failed" }
+            ));
+        }
+    } else {
+        task.push('\n');
+    }
+    task.push_str("Check the workspace and continue from where the work stopped.");
+    task
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_resume_task_lists_earlier_calls() {
+        let mut job: AgentJob = serde_json::from_value(json!({
+            "job_id": "job_1", "task": "Fix the build", "workspace": "default", "max_iterations": 10,
+            "tools": [], "schedule_id": null, "planning": false, "response_format": null,
+            "status": "queued", "iterations": 0, "transcript": [], "provider_attempts": [], "plan": [],
+            "response": null, "error": null, "created_at": "2026-01-01T00:00:00Z",
+            "started_at": null, "completed_at": null
+        }))
+        .unwrap();
+        assert!(!is_resuming(&job));
+
+        job.iterations = 2;
+        job.transcript = json!([
+            { "name": "read_file", "arguments": { "path": "Cargo.toml" }, "success": true },
+            { "name": "exec", "arguments": { "command": "cargo build" }, "success": false },
+        ]);
+        let task = resume_task(&job);
+        assert!(is_resuming(&job));
+        assert!(task.starts_with("Fix the build\n\n---\n"));
+        assert!(task.contains("after 2 iteration(s)"));
+        assert!(task.contains("- read_file {\"path\":\"Cargo.toml\"} (ok)\n- exec {\"command\":\"cargo build\"} (failed)\n"));
+
+        let merged = appended(&job.transcript, &[json!({ "name": "write_file" })]);
+        assert_eq!(merged.as_array().unwrap().len(), 3);
+    }
+}
@@ -0,0 +1,12 @@
+//! Standalone agent runs (outside the chat dispatcher)
+
+pub mod jobs;
+pub mod plan;
+pub mod runner;
+pub mod schedules;
+pub mod templates;
+pub mod webhooks;
+
+pub use jobs::AgentJobQueue;
+pub use runner::{AgentRunResult, AgentRunner};
+pub use schedules::ScheduleRunner;
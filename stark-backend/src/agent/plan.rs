@@ -0,0 +1,168 @@
+//! Plan/act mode for agent runs
+//!
+//! A planning run spends its first model call on `define_tasks` alone, which
+//! turns the task into an ordered list of steps. From then on the model works
+//! with the regular tools plus `complete_step`, and sees the checklist in its
+//! system prompt. The steps use the dispatcher's task planner types, so a plan
+//! serializes the same way wherever it is shown.
+
+use crate::ai::multi_agent::tools::define_tasks_tool;
+use crate::ai::multi_agent::types::{PlannerTask, TaskQueue, TaskStatus};
+use crate::tools::{PropertySchema, ToolDefinition, ToolGroup, ToolInputSchema};
+use serde_json::Value;
+use std::collections::HashMap;
+
+pub const DEFINE_TASKS_TOOL: &str = "define_tasks";
+pub const COMPLETE_STEP_TOOL: &str = "complete_step";
+
+/// Added to the system prompt for the planning call
+pub const PLANNING_PROMPT: &str = "Before doing any work, plan it: call define_tasks with the ordered steps \
+    needed to complete the task. Keep each step specific and verifiable. The other tools become available \
+    once the plan is set.";
+
+/// Tools the model is offered for the planning call
+pub fn planning_tools() -> Vec<ToolDefinition> {
+    vec![define_tasks_tool()]
+}
+
+/// Tool that marks the current step of the plan done
+pub fn complete_step_tool() -> ToolDefinition {
+    let mut properties = HashMap::new();
+    properties.insert(
+        "summary".to_string(),
+        PropertySchema {
+            schema_type: "string".to_string(),
+            description: "One line on what was done in this step".to_string(),
+            default: None,
+            items: None,
+            enum_values: None,
+        },
+    );
+
+    ToolDefinition {
+        name: COMPLETE_STEP_TOOL.to_string(),
+        description: "Mark the current step of the plan as done and move on to the next one. Call it once the step's work is finished and verified.".to_string(),
+        input_schema: ToolInputSchema {
+            schema_type: "object".to_string(),
+            properties,
+            required: vec![],
+        },
+        group: ToolGroup::System,
+    }
+}
+
+/// The plan of one run
+#[derive(Debug, Default)]
+pub struct RunPlan {
+    queue: TaskQueue,
+}
+
+impl RunPlan {
+    pub fn steps(&self) -> &[PlannerTask] {
+        &self.queue.tasks
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.queue.is_empty()
+    }
+
+    /// Handle a `define_tasks` call, starting on the first step
+    pub fn define(&mut self, params: &Value) -> Result<String, String> {
+        if !self.is_empty() {
+            return Err("The plan is already set; work through it with complete_step".to_string());
+        }
+        let steps: Vec<String> = params
+            .get("tasks")
+            .and_then(|v| v.as_array())
+            .ok_or("Missing or invalid 'tasks' parameter")?
+            .iter()
+            .filter_map(|v| v.as_str())
+            .map(str::trim)
+            .filter(|s| !s.is_empty())
+            .map(str::to_string)
+            .collect();
+        if steps.is_empty() {
+            return Err("No valid tasks provided".to_string());
+        }
+
+        tracing::info!("[AGENT_RUN] Planned {} step(s)", steps.len());
+        self.queue = TaskQueue::from_descriptions(steps);
+        self.queue.pop_next();
+        Ok(format!("Plan set:\n{}\n\nStart with step 1.", self.checklist()))
+    }
+
+    /// Handle a `complete_step` call, moving on to the next step
+    pub fn complete_step(&mut self) -> Result<String, String> {
+        let Some(done) = self.queue.complete_current() else {
+            return Err(if self.is_empty() {
+                "There is no plan to complete a step of".to_string()
+            } else {
+                "All steps are already done".to_string()
+            });
+        };
+
+        tracing::info!("[AGENT_RUN] Completed step {}/{}", done, self.queue.total());
+        Ok(match self.queue.pop_next() {
+            Some(next) => format!("Step {} done. Next, step {}: {}", done, next.id, next.description),
+            None => format!(
+                "Step {} done. All {} steps are complete: reply with a short summary of what you did.",
+                done,
+                self.queue.total()
+            ),
+        })
+    }
+
+    /// The plan with each step's state, for the system prompt
+    pub fn prompt_section(&self) -> Option<String> {
+        (!self.is_empty()).then(|| {
+            format!(
+                "Plan ({} of {} steps done; call complete_step when the current step is finished):\n{}",
+                self.queue.completed_count(),
+                self.queue.total(),
+                self.checklist()
+            )
+        })
+    }
+
+    fn checklist(&self) -> String {
+        self.queue
+            .tasks
+            .iter()
+            .map(|step| {
+                let mark = match step.status {
+                    TaskStatus::Completed => "[x]",
+                    TaskStatus::InProgress => "[>]",
+                    TaskStatus::Pending => "[ ]",
+                };
+                format!("{} {}. {}", mark, step.id, step.description)
+            })
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_plan_steps_through_to_completion() {
+        let mut plan = RunPlan::default();
+        assert!(plan.complete_step().is_err());
+        assert!(plan.define(&json!({ "tasks": [" ", 3] })).is_err());
+
+        let result = plan.define(&json!({ "tasks": ["Write the parser", "Add tests"] })).unwrap();
+        assert!(result.contains("[>] 1. Write the parser\n[ ] 2. Add tests"));
+        assert!(plan.define(&json!({ "tasks": ["Again"] })).is_err());
+
+        assert!(plan.complete_step().unwrap().contains("Next, step 2: Add tests"));
+        let section = plan.prompt_section().unwrap();
+        assert!(section.starts_with("Plan (1 of 2 steps done"));
+        assert!(section.contains("[x] 1. Write the parser\n[>] 2. Add tests"));
+
+        assert!(plan.complete_step().unwrap().contains("All 2 steps are complete"));
+        assert!(plan.steps().iter().all(|s| s.status == TaskStatus::Completed));
+        assert_eq!(plan.complete_step().unwrap_err(), "All steps are already done");
+    }
+}
@@ -0,0 +1,962 @@
+//! Headless tool-calling loop for one-off CodeEngineer tasks
+//!
+//! Unlike the message dispatcher there is no session, memory or channel here:
+//! the runner takes a task, lets the model call development tools inside a
+//! single workspace directory, and returns the final answer with a record of
+//! every tool call made along the way. In plan/act mode (see [`super::plan`])
+//! the run starts by breaking the task into steps.
+
+use super::plan::{self, RunPlan, COMPLETE_STEP_TOOL, DEFINE_TASKS_TOOL};
+use crate::ai::multi_agent::types::PlannerTask;
+use crate::ai::structured::{ResponseFormat, MAX_REPAIR_ATTEMPTS};
+use crate::ai::{
+    AiClient, FailoverClient, Message, MessageRole, ProviderAttempt, ToolCall, ToolHistoryEntry, ToolResponse,
+};
+use crate::context::{ContextWindow, DEFAULT_MAX_CONTEXT_TOKENS};
+use crate::tools::output_store::{self, INLINE_OUTPUT_BYTES};
+use crate::tools::{
+    OutputStore, ToolConfig, ToolContext, ToolDefinition, ToolGroup, ToolProfile, ToolRegistry, ToolResult,
+};
+use crate::utils::truncate_str;
+use crate::workspace::{uploads_prompt, UploadedFile};
+use futures_util::future::join_all;
+use serde::Serialize;
+use serde_json::Value;
+use std::ops::Range;
+use std::sync::{Arc, Mutex};
+use std::time::Instant;
+use tokio_util::sync::CancellationToken;
+
+/// Default cap on model round-trips per run
+pub const DEFAULT_MAX_ITERATIONS: usize = 25;
+
+/// Tool output kept in the run record
+const RECORDED_OUTPUT_CHARS: usize = 2000;
+
+/// Tool that pages through stored outputs; its own pages are never stored again
+const FETCH_OUTPUT_TOOL: &str = "fetch_output";
+
+/// One tool call made during a run
+#[derive(Debug, Clone, Serialize)]
+pub struct AgentToolCall {
+    pub name: String,
+    pub arguments: Value,
+    pub success: bool,
+    /// Tool output, truncated
+    pub output: String,
+    /// Where the full output is stored, when it was too long to give the model
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub output_ref: Option<String>,
+    pub duration_ms: u64,
+}
+
+/// Outcome of an agent run
+#[derive(Debug, Clone, Serialize)]
+pub struct AgentRunResult {
+    pub success: bool,
+    /// Final assistant message (empty if the run failed before finishing)
+    pub response: String,
+    /// Number of model round-trips
+    pub iterations: usize,
+    pub tool_calls: Vec<AgentToolCall>,
+    /// Provider calls that failed and were retried or failed over
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub provider_attempts: Vec<ProviderAttempt>,
+    /// Steps planned in plan/act mode, with how far the run got
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub plan: Vec<PlannerTask>,
+    /// The run was stopped through its cancellation token
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub cancelled: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+/// Runs a task through the model with the CodeEngineer tool set
+pub struct AgentRunner {
+    client: FailoverClient,
+    tool_registry: Arc<ToolRegistry>,
+    tool_config: ToolConfig,
+    tool_context: ToolContext,
+    max_iterations: usize,
+    context_window: ContextWindow,
+    cancellation: CancellationToken,
+    /// Start with a planning call (plan/act mode)
+    planning: bool,
+    /// Plan of the run in progress
+    plan: Mutex<RunPlan>,
+    /// Archetype profile instructions appended to the system prompt
+    prompt_suffix: Option<String>,
+    /// JSON the final answer must be
+    response_format: Option<ResponseFormat>,
+    /// Files the user uploaded into the workspace, listed in the system prompt
+    uploads: Vec<UploadedFile>,
+}
+
+impl AgentRunner {
+    /// `tool_context` must carry the workspace directory the tools operate in
+    pub fn new(client: AiClient, tool_registry: Arc<ToolRegistry>, tool_context: ToolContext) -> Self {
+        Self {
+            client: FailoverClient::new(client),
+            tool_registry,
+            tool_config: Self::code_engineer_tool_config(),
+            tool_context,
+            max_iterations: DEFAULT_MAX_ITERATIONS,
+            context_window: ContextWindow::new(DEFAULT_MAX_CONTEXT_TOKENS as usize),
+            cancellation: CancellationToken::new(),
+            planning: false,
+            plan: Mutex::new(RunPlan::default()),
+            prompt_suffix: None,
+            response_format: None,
+            uploads: Vec::new(),
+        }
+    }
+
+    pub fn with_max_iterations(mut self, max_iterations: usize) -> Self {
+        self.max_iterations = max_iterations.max(1);
+        self
+    }
+
+    /// Provider to fail over to when the primary one keeps failing
+    pub fn with_fallback(mut self, fallback: Option<AiClient>) -> Self {
+        if let Some(fallback) = fallback {
+            self.client = self.client.with_fallback(fallback);
+        }
+        self
+    }
+
+    /// Token budget the run's messages and tool output are compacted to fit
+    pub fn with_context_window(mut self, context_window: ContextWindow) -> Self {
+        self.context_window = context_window;
+        self
+    }
+
+    /// Instructions for the model family, from the archetype profile
+    pub fn with_prompt_suffix(mut self, prompt_suffix: Option<String>) -> Self {
+        self.prompt_suffix = prompt_suffix;
+        self
+    }
+
+    /// Require the final answer to be JSON in this format. The client should
+    /// be set up with the same format, so providers with a JSON mode use it.
+    pub fn with_response_format(mut self, response_format: Option<ResponseFormat>) -> Self {
+        self.response_format = response_format;
+        self
+    }
+
+    /// Files uploaded into the workspace, so the model knows what it was given
+    pub fn with_uploads(mut self, uploads: Vec<UploadedFile>) -> Self {
+        self.uploads = uploads;
+        self
+    }
+
+    /// Plan/act mode: spend the first model call on breaking the task into steps
+    pub fn with_planning(mut self, planning: bool) -> Self {
+        self.planning = planning;
+        self
+    }
+
+    /// Token that stops the run: checked before every model round-trip and
+    /// every tool call
+    pub fn with_cancellation(mut self, cancellation: CancellationToken) -> Self {
+        self.cancellation = cancellation;
+        self
+    }
+
+    /// Store for outputs longer than the model is given in full. Without one,
+    /// outputs are passed on whole.
+    pub fn with_output_store(mut self, store: OutputStore) -> Self {
+        self.tool_context.output_store = Some(store);
+        self
+    }
+
+    /// Restrict the run to `tools`; an empty list keeps the full CodeEngineer set.
+    /// Names outside that set are ignored.
+    pub fn with_tools(mut self, tools: &[String]) -> Self {
+        if !tools.is_empty() {
+            self.tool_config.deny_list = Self::available_tools(&self.tool_registry)
+                .into_iter()
+                .filter(|name| !tools.contains(name))
+                .collect();
+        }
+        self
+    }
+
+    /// Names of the tools a run can use, sorted
+    pub fn available_tools(tool_registry: &ToolRegistry) -> Vec<String> {
+        let mut names: Vec<String> = tool_registry
+            .get_tool_definitions(&Self::code_engineer_tool_config())
+            .into_iter()
+            .map(|t| t.name)
+            .collect();
+        names.sort();
+        names
+    }
+
+    /// Developer profile minus System tools, which need a dispatcher session
+    /// (subtype switching, subagents, task planning)
+    fn code_engineer_tool_config() -> ToolConfig {
+        ToolConfig {
+            profile: ToolProfile::Developer,
+            denied_groups: vec![ToolGroup::System.as_str().to_string()],
+            ..Default::default()
+        }
+    }
+
+    fn tool_definitions(&self) -> Vec<ToolDefinition> {
+        let mut tools = self.tool_registry.get_tool_definitions(&self.tool_config);
+        tools.sort_by(|a, b| a.name.cmp(&b.name));
+        tools
+    }
+
+    fn system_prompt(&self, tools: &[ToolDefinition]) -> String {
+        let workspace = self.tool_context.workspace_dir.as_deref().unwrap_or(".");
+        let tool_names: Vec<&str> = tools.iter().map(|t| t.name.as_str()).collect();
+        let prompt = format!(
+            "You are a CodeEngineer agent working on a task in the workspace directory `{}`.\n\
+             All file paths are relative to that directory.\n\n\
+             Available tools: {}\n\n\
+             Work step by step: inspect the workspace, make the changes, and verify them \
+             (build, run or test where possible). When the task is complete, reply with a short \
+             summary of what you did and do not call any more tools.",
+            workspace,
+            tool_names.join(", ")
+        );
+        let prompt = match uploads_prompt(&self.uploads) {
+            Some(uploads) => format!("{}\n\n{}", prompt, uploads.trim_end()),
+            None => prompt,
+        };
+        let prompt = match &self.prompt_suffix {
+            Some(suffix) => format!("{}\n\n{}", prompt, suffix),
+            None => prompt,
+        };
+        match &self.response_format {
+            Some(format) => format!("{}\n\n{}", prompt, format.instructions()),
+            None => prompt,
+        }
+    }
+
+    /// Run a task to completion or until the iteration cap
+    pub async fn run(&self, task: &str) -> AgentRunResult {
+        self.run_with_progress(task, |_, _, _, _| {}).await
+    }
+
+    /// Like `run`, calling `on_progress` with the iteration count, the tool
+    /// calls made so far, the failed provider calls and the plan after every
+    /// round of tool execution
+    pub async fn run_with_progress<F>(&self, task: &str, mut on_progress: F) -> AgentRunResult
+    where
+        F: FnMut(usize, &[AgentToolCall], &[ProviderAttempt], &[PlannerTask]),
+    {
+        *self.plan.lock().unwrap() = RunPlan::default();
+        let base_tools = self.tool_definitions();
+        let base_prompt = self.system_prompt(&base_tools);
+        let mut messages = vec![
+            Message {
+                role: MessageRole::System,
+                content: base_prompt.clone(),
+                images: Vec::new(),
+            },
+            Message {
+                role: MessageRole::User,
+                content: task.to_string(),
+                images: Vec::new(),
+            },
+        ];
+
+        let mut request_index = 1;
+        let mut tool_history: Vec<ToolHistoryEntry> = Vec::new();
+        let mut records: Vec<AgentToolCall> = Vec::new();
+        let mut attempts: Vec<ProviderAttempt> = Vec::new();
+        let mut iterations = 0;
+
+        while iterations < self.max_iterations {
+            if self.cancellation.is_cancelled() {
+                tracing::info!("[AGENT_RUN] Cancelled after {} iteration(s)", iterations);
+                return AgentRunResult {
+                    success: false,
+                    response: String::new(),
+                    iterations,
+                    tool_calls: records,
+                    provider_attempts: attempts,
+                    plan: self.plan_steps(),
+                    cancelled: true,
+                    error: Some("Cancelled".to_string()),
+                };
+            }
+            iterations += 1;
+            tracing::info!("[AGENT_RUN] Iteration {}/{}", iterations, self.max_iterations);
+
+            let tools = if self.planning && iterations == 1 {
+                messages[0].content = format!("{}\n\n{}", base_prompt, plan::PLANNING_PROMPT);
+                plan::planning_tools()
+            } else if let Some(section) = self.plan.lock().unwrap().prompt_section() {
+                messages[0].content = format!("{}\n\n{}", base_prompt, section);
+                let mut tools = base_tools.clone();
+                tools.push(plan::complete_step_tool());
+                tools
+            } else {
+                messages[0].content = base_prompt.clone();
+                base_tools.clone()
+            };
+
+            if let Some(report) =
+                self.context_window
+                    .compact(&mut messages, &mut request_index, &mut tool_history, &tools)
+            {
+                tracing::info!(
+                    "[AGENT_RUN] Compacted context from {} to {} of {} tokens ({} stale, {} condensed, {} dropped)",
+                    report.tokens_before,
+                    report.tokens_after,
+                    self.context_window.budget(),
+                    report.stale_results,
+                    report.condensed,
+                    report.dropped
+                );
+            }
+
+            let response = match self
+                .client
+                .generate_with_tools(iterations, messages.clone(), tool_history.clone(), tools.clone(), &mut attempts)
+                .await
+            {
+                Ok(r) => r,
+                Err(e) => {
+                    return AgentRunResult {
+                        success: false,
+                        response: String::new(),
+                        iterations,
+                        tool_calls: records,
+                        provider_attempts: attempts,
+                        plan: self.plan_steps(),
+                        cancelled: false,
+                        error: Some(format!("AI request failed: {}", e)),
+                    }
+                }
+            };
+
+            if response.tool_calls.is_empty() {
+                let (response, error) = match &self.response_format {
+                    Some(format) => match self.check_answer(format, response.content, iterations, &mut attempts).await {
+                        Ok(output) => (output.to_string(), None),
+                        Err((answer, error)) => (answer, Some(error)),
+                    },
+                    None => (response.content, None),
+                };
+                return AgentRunResult {
+                    success: error.is_none(),
+                    response,
+                    iterations,
+                    tool_calls: records,
+                    provider_attempts: attempts,
+                    plan: self.plan_steps(),
+                    cancelled: false,
+                    error,
+                };
+            }
+
+            let mut responses = Vec::with_capacity(response.tool_calls.len());
+            for (tool_response, record) in self.execute_tool_calls(&response.tool_calls).await {
+                responses.push(tool_response);
+                records.push(record);
+            }
+            tool_history.push(ToolHistoryEntry::from_response(response, responses));
+            on_progress(iterations, &records, &attempts, &self.plan_steps());
+        }
+
+        AgentRunResult {
+            success: false,
+            response: String::new(),
+            iterations,
+            tool_calls: records,
+            provider_attempts: attempts,
+            plan: self.plan_steps(),
+            cancelled: false,
+            error: Some(format!("Max iterations ({}) reached", self.max_iterations)),
+        }
+    }
+
+    /// Check the final answer against the response format, asking the model
+    /// to repair it while it doesn't match. Fails with the last answer and
+    /// what is wrong with it.
+    async fn check_answer(
+        &self,
+        format: &ResponseFormat,
+        mut answer: String,
+        iteration: usize,
+        attempts: &mut Vec<ProviderAttempt>,
+    ) -> Result<Value, (String, String)> {
+        let mut repairs = 0;
+        loop {
+            let problems = match format.check(&answer) {
+                Ok(output) => return Ok(output),
+                Err(problems) => problems,
+            };
+            if repairs == MAX_REPAIR_ATTEMPTS {
+                return Err((answer, format!("The answer does not match the response_format:\n{}", problems)));
+            }
+            repairs += 1;
+            tracing::info!("[AGENT_RUN] Answer does not match the response format, repair {}/{}", repairs, MAX_REPAIR_ATTEMPTS);
+            match self
+                .client
+                .generate_with_tools(iteration, format.repair_messages(&answer, &problems), Vec::new(), Vec::new(), attempts)
+                .await
+            {
+                Ok(repaired) => answer = repaired.content,
+                Err(e) => return Err((answer, format!("AI request failed while repairing the answer: {}", e))),
+            }
+        }
+    }
+
+    fn plan_steps(&self) -> Vec<PlannerTask> {
+        self.plan.lock().unwrap().steps().to_vec()
+    }
+
+    /// Execute one round of tool calls, results in call order. Consecutive
+    /// read-only calls run concurrently; every other call runs on its own once
+    /// the calls before it have finished.
+    async fn execute_tool_calls(&self, calls: &[ToolCall]) -> Vec<(ToolResponse, AgentToolCall)> {
+        let mut results = Vec::with_capacity(calls.len());
+        for batch in concurrent_batches(calls, |name| self.tool_registry.is_read_only(name)) {
+            if batch.len() > 1 {
+                tracing::info!("[AGENT_RUN] Running {} read-only tool calls concurrently", batch.len());
+            }
+            results.extend(join_all(calls[batch].iter().map(|call| self.execute_tool_call(call))).await);
+        }
+        results
+    }
+
+    async fn execute_tool_call(&self, call: &ToolCall) -> (ToolResponse, AgentToolCall) {
+        let started = Instant::now();
+        let result = if self.cancellation.is_cancelled() {
+            tracing::info!("[AGENT_RUN] Skipping tool call {}: run cancelled", call.name);
+            ToolResult::error("Run cancelled before this tool call was executed")
+        } else if call.name == DEFINE_TASKS_TOOL || call.name == COMPLETE_STEP_TOOL {
+            let mut plan = self.plan.lock().unwrap();
+            let handled = if call.name == DEFINE_TASKS_TOOL {
+                plan.define(&call.arguments)
+            } else {
+                plan.complete_step()
+            };
+            handled.map_or_else(ToolResult::error, ToolResult::success)
+        } else {
+            tracing::info!("[AGENT_RUN] Tool call: {}", call.name);
+            self.tool_registry
+                .execute(&call.name, call.arguments.clone(), &self.tool_context, Some(&self.tool_config))
+                .await
+        };
+
+        let output = if result.success {
+            result.content.clone()
+        } else {
+            result.error.clone().unwrap_or_else(|| result.content.clone())
+        };
+
+        let output_ref = self.store_output(&call.name, &output).await;
+        let record = AgentToolCall {
+            name: call.name.clone(),
+            arguments: call.arguments.clone(),
+            success: result.success,
+            output: truncate_str(&output, RECORDED_OUTPUT_CHARS),
+            output_ref: output_ref.clone(),
+            duration_ms: started.elapsed().as_millis() as u64,
+        };
+        let output = match &output_ref {
+            Some(output_ref) => output_store::preview(&output, output_ref),
+            None => output,
+        };
+
+        let response = if result.success {
+            ToolResponse::success(call.id.clone(), output)
+        } else {
+            ToolResponse::error(call.id.clone(), output)
+        };
+
+        (response, record)
+    }
+
+    /// Store an output too long to give the model in full, returning its reference
+    async fn store_output(&self, tool_name: &str, output: &str) -> Option<String> {
+        let store = self.tool_context.output_store.as_ref()?;
+        if output.len() <= INLINE_OUTPUT_BYTES || tool_name == FETCH_OUTPUT_TOOL {
+            return None;
+        }
+        match store.put(output).await {
+            Ok(output_ref) => {
+                tracing::info!("[AGENT_RUN] Stored {} byte {} output as {}", output.len(), tool_name, output_ref);
+                Some(output_ref)
+            }
+            Err(e) => {
+                // The model gets the whole output instead
+                tracing::warn!("[AGENT_RUN] {}", e);
+                None
+            }
+        }
+    }
+}
+
+/// Split `calls` into batches that can run concurrently: runs of read-only
+/// calls, and each other call by itself
+fn concurrent_batches(calls: &[ToolCall], is_read_only: impl Fn(&str) -> bool) -> Vec<Range<usize>> {
+    let mut batches: Vec<Range<usize>> = Vec::new();
+    for (i, call) in calls.iter().enumerate() {
+        let read_only = is_read_only(&call.name);
+        match batches.last_mut() {
+            Some(batch) if read_only && is_read_only(&calls[batch.start].name) => batch.end = i + 1,
+            _ => batches.push(i..i + 1),
+        }
+    }
+    batches
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ai::multi_agent::types::TaskStatus;
+    use crate::ai::OpenAIClient;
+    use serde_json::json;
+    use tempfile::TempDir;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::TcpListener;
+
+    /// Read one HTTP request (headers + Content-Length body) from the socket
+    async fn read_request(socket: &mut tokio::net::TcpStream) -> String {
+        let mut data = Vec::new();
+        let mut buf = [0u8; 8192];
+        loop {
+            let n = socket.read(&mut buf).await.unwrap();
+            data.extend_from_slice(&buf[..n]);
+            let text = String::from_utf8_lossy(&data).to_string();
+            if let Some(header_end) = text.find("\r\n\r\n") {
+                let content_length = text[..header_end]
+                    .lines()
+                    .find_map(|l| l.to_lowercase().strip_prefix("content-length:").map(|v| v.trim().parse::<usize>().unwrap()))
+                    .unwrap_or(0);
+                if data.len() >= header_end + 4 + content_length {
+                    return text[header_end + 4..].to_string();
+                }
+            }
+            if n == 0 {
+                return text;
+            }
+        }
+    }
+
+    /// OpenAI-compatible server that replays canned completions, one per request
+    async fn fake_provider(replies: Vec<Value>) -> (String, tokio::task::JoinHandle<Vec<String>>) {
+        fake_provider_with_status(replies.into_iter().map(|reply| (200, reply)).collect()).await
+    }
+
+    /// Like `fake_provider`, with the HTTP status of each reply
+    async fn fake_provider_with_status(replies: Vec<(u16, Value)>) -> (String, tokio::task::JoinHandle<Vec<String>>) {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let endpoint = format!("http://{}/v1/chat/completions", listener.local_addr().unwrap());
+        let handle = tokio::spawn(async move {
+            let mut bodies = Vec::new();
+            for (status, reply) in replies {
+                let (mut socket, _) = listener.accept().await.unwrap();
+                bodies.push(read_request(&mut socket).await);
+                let body = reply.to_string();
+                let response = format!(
+                    "HTTP/1.1 {} Status\r\ncontent-type: application/json\r\nretry-after: 0\r\ncontent-length: {}\r\nconnection: close\r\n\r\n{}",
+                    status,
+                    body.len(),
+                    body
+                );
+                socket.write_all(response.as_bytes()).await.unwrap();
+            }
+            bodies
+        });
+        (endpoint, handle)
+    }
+
+    #[tokio::test]
+    async fn test_runner_executes_tools_until_final_answer() {
+        let workspace = TempDir::new().unwrap();
+        let (endpoint, provider) = fake_provider(vec![
+            json!({
+                "choices": [{
+                    "message": {
+                        "content": null,
+                        "tool_calls": [{
+                            "id": "call_1",
+                            "type": "function",
+                            "function": {
+                                "name": "write_file",
+                                "arguments": "{\"path\": \"hello.txt\", \"content\": \"hi\"}"
+                            }
+                        }]
+                    },
+                    "finish_reason": "tool_calls"
+                }]
+            }),
+            json!({
+                "choices": [{
+                    "message": { "content": "Wrote hello.txt" },
+                    "finish_reason": "stop"
+                }]
+            }),
+        ])
+        .await;
+
+        let client = AiClient::OpenAI(OpenAIClient::new("", Some(&endpoint), Some("test")).unwrap());
+        let context = ToolContext::new().with_workspace(workspace.path().to_string_lossy().to_string());
+        let runner = AgentRunner::new(client, Arc::new(crate::tools::create_default_registry()), context);
+
+        let result = runner.run("Create hello.txt containing hi").await;
+
+        assert!(result.success, "{:?}", result.error);
+        assert_eq!(result.response, "Wrote hello.txt");
+        assert_eq!(result.iterations, 2);
+        assert_eq!(result.tool_calls.len(), 1);
+        assert!(result.tool_calls[0].success);
+        assert_eq!(std::fs::read_to_string(workspace.path().join("hello.txt")).unwrap(), "hi");
+
+        // The second request carries the tool result back to the model
+        let bodies = provider.await.unwrap();
+        let second: Value = serde_json::from_str(&bodies[1]).unwrap();
+        let messages = second["messages"].as_array().unwrap();
+        assert!(messages.iter().any(|m| m["role"] == "tool" && m["tool_call_id"] == "call_1"));
+    }
+
+    #[tokio::test]
+    async fn test_runner_retries_then_fails_over() {
+        let error = json!({ "error": { "message": "upstream unavailable" } });
+        let (primary_endpoint, primary) = fake_provider_with_status(vec![
+            (429, json!({ "error": { "message": "rate limited" } })),
+            (500, error.clone()),
+            (503, error.clone()),
+            (502, error),
+        ])
+        .await;
+        let (fallback_endpoint, fallback) = fake_provider(vec![json!({
+            "choices": [{
+                "message": { "content": "Done on the fallback" },
+                "finish_reason": "stop"
+            }]
+        })])
+        .await;
+
+        let primary_client = AiClient::OpenAI(OpenAIClient::new("", Some(&primary_endpoint), Some("big")).unwrap());
+        let fallback_client = AiClient::OpenAI(OpenAIClient::new("", Some(&fallback_endpoint), Some("small")).unwrap());
+        let runner = AgentRunner::new(primary_client, Arc::new(crate::tools::create_default_registry()), ToolContext::new())
+            .with_fallback(Some(fallback_client));
+
+        let result = runner.run("Say done").await;
+
+        assert!(result.success, "{:?}", result.error);
+        assert_eq!(result.response, "Done on the fallback");
+        // Three retries on the primary, then it is given up on
+        let attempts = &result.provider_attempts;
+        assert_eq!(attempts.len(), 4);
+        assert_eq!(
+            attempts.iter().map(|a| a.status_code).collect::<Vec<_>>(),
+            vec![Some(429), Some(500), Some(503), Some(502)]
+        );
+        assert!(attempts.iter().all(|a| a.provider == "primary" && a.model == "big" && a.iteration == 1));
+        // Retry-After: 0 overrides the backoff
+        assert_eq!(attempts[0].retry_after_ms, Some(0));
+        assert_eq!(attempts[3].retry_after_ms, None);
+        assert_eq!(primary.await.unwrap().len(), 4);
+        assert_eq!(fallback.await.unwrap().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_runner_does_not_retry_bad_requests() {
+        let (endpoint, provider) =
+            fake_provider_with_status(vec![(400, json!({ "error": { "message": "invalid tool schema" } }))]).await;
+        let client = AiClient::OpenAI(OpenAIClient::new("", Some(&endpoint), Some("test")).unwrap());
+        let runner = AgentRunner::new(client, Arc::new(crate::tools::create_default_registry()), ToolContext::new());
+
+        let result = runner.run("Say done").await;
+
+        assert!(!result.success);
+        assert!(result.error.unwrap().contains("invalid tool schema"));
+        assert_eq!(result.provider_attempts.len(), 1);
+        assert_eq!(result.provider_attempts[0].retry_after_ms, None);
+        assert_eq!(provider.await.unwrap().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_runner_repairs_answers_that_miss_the_schema() {
+        let reply = |content: &str| {
+            json!({
+                "choices": [{
+                    "message": { "content": content },
+                    "finish_reason": "stop"
+                }]
+            })
+        };
+        let (endpoint, provider) = fake_provider(vec![
+            reply("The file has 3 lines."),
+            reply("```json\n{\"lines\": \"3\"}\n```"),
+            reply("{\"lines\": 3}"),
+        ])
+        .await;
+        let format: ResponseFormat = serde_json::from_value(json!({
+            "type": "json_schema",
+            "json_schema": {
+                "schema": {
+                    "type": "object",
+                    "properties": { "lines": { "type": "integer" } },
+                    "required": ["lines"]
+                }
+            }
+        }))
+        .unwrap();
+
+        let client = AiClient::OpenAI(OpenAIClient::new("", Some(&endpoint), Some("test")).unwrap());
+        let runner = AgentRunner::new(client, Arc::new(crate::tools::create_default_registry()), ToolContext::new())
+            .with_response_format(Some(format));
+
+        let result = runner.run("Count the lines").await;
+
+        assert!(result.success, "{:?}", result.error);
+        assert_eq!(result.response, "{\"lines\":3}");
+        let bodies = provider.await.unwrap();
+        assert_eq!(bodies.len(), 3);
+        let first: Value = serde_json::from_str(&bodies[0]).unwrap();
+        assert!(first["messages"][0]["content"].as_str().unwrap().contains("\"lines\""));
+        // The second repair is told what was still wrong
+        let last: Value = serde_json::from_str(&bodies[2]).unwrap();
+        assert!(last.to_string().contains("lines"));
+        assert!(last["tools"].is_null());
+    }
+
+    #[tokio::test]
+    async fn test_planning_run_plans_then_completes_steps() {
+        let workspace = TempDir::new().unwrap();
+        let tool_call = |id: &str, name: &str, arguments: Value| {
+            json!({
+                "id": id,
+                "type": "function",
+                "function": { "name": name, "arguments": arguments.to_string() }
+            })
+        };
+        let (endpoint, provider) = fake_provider(vec![
+            json!({
+                "choices": [{
+                    "message": {
+                        "content": null,
+                        "tool_calls": [tool_call("call_1", "define_tasks", json!({ "tasks": ["Write a.txt", "Check a.txt"] }))]
+                    },
+                    "finish_reason": "tool_calls"
+                }]
+            }),
+            json!({
+                "choices": [{
+                    "message": {
+                        "content": null,
+                        "tool_calls": [
+                            tool_call("call_2", "write_file", json!({ "path": "a.txt", "content": "a" })),
+                            tool_call("call_3", "complete_step", json!({ "summary": "Wrote a.txt" }))
+                        ]
+                    },
+                    "finish_reason": "tool_calls"
+                }]
+            }),
+            json!({
+                "choices": [{
+                    "message": { "content": "Wrote a.txt" },
+                    "finish_reason": "stop"
+                }]
+            }),
+        ])
+        .await;
+
+        let client = AiClient::OpenAI(OpenAIClient::new("", Some(&endpoint), Some("test")).unwrap());
+        let context = ToolContext::new().with_workspace(workspace.path().to_string_lossy().to_string());
+        let runner = AgentRunner::new(client, Arc::new(crate::tools::create_default_registry()), context)
+            .with_planning(true);
+
+        let mut progress = Vec::new();
+        let result = runner
+            .run_with_progress("Write a.txt", |_, _, _, plan| progress.push(plan.to_vec()))
+            .await;
+
+        assert!(result.success, "{:?}", result.error);
+        let statuses: Vec<_> = result.plan.iter().map(|step| step.status).collect();
+        assert_eq!(statuses, vec![TaskStatus::Completed, TaskStatus::InProgress]);
+        assert_eq!(result.plan[1].description, "Check a.txt");
+        // The plan is reported after the planning round already
+        assert_eq!(progress[0][0].status, TaskStatus::InProgress);
+        assert_eq!(result.tool_calls.len(), 3);
+
+        let bodies: Vec<Value> = provider.await.unwrap().iter().map(|b| serde_json::from_str(b).unwrap()).collect();
+        let tool_names = |body: &Value| -> Vec<String> {
+            body["tools"].as_array().unwrap().iter().map(|t| t["function"]["name"].as_str().unwrap().to_string()).collect()
+        };
+        // The planning call only offers define_tasks; later calls add complete_step
+        assert_eq!(tool_names(&bodies[0]), vec!["define_tasks"]);
+        assert!(tool_names(&bodies[1]).contains(&"complete_step".to_string()));
+        assert!(tool_names(&bodies[1]).contains(&"write_file".to_string()));
+        let system = bodies[2]["messages"][0]["content"].as_str().unwrap();
+        assert!(system.contains("[x] 1. Write a.txt\n[>] 2. Check a.txt"), "{}", system);
+    }
+
+    #[tokio::test]
+    async fn test_cancelled_run_stops_before_next_iteration() {
+        let workspace = TempDir::new().unwrap();
+        let (endpoint, provider) = fake_provider(vec![json!({
+            "choices": [{
+                "message": {
+                    "content": null,
+                    "tool_calls": [{
+                        "id": "call_1",
+                        "type": "function",
+                        "function": {
+                            "name": "write_file",
+                            "arguments": "{\"path\": \"a.txt\", \"content\": \"a\"}"
+                        }
+                    }]
+                },
+                "finish_reason": "tool_calls"
+            }]
+        })])
+        .await;
+
+        let client = AiClient::OpenAI(OpenAIClient::new("", Some(&endpoint), Some("test")).unwrap());
+        let context = ToolContext::new().with_workspace(workspace.path().to_string_lossy().to_string());
+        let cancellation = CancellationToken::new();
+        let runner = AgentRunner::new(client, Arc::new(crate::tools::create_default_registry()), context)
+            .with_cancellation(cancellation.clone());
+
+        let result = runner.run_with_progress("Write files", |_, _, _, _| cancellation.cancel()).await;
+
+        assert!(result.cancelled);
+        assert!(!result.success);
+        assert_eq!(result.iterations, 1);
+        assert_eq!(result.tool_calls.len(), 1);
+        assert_eq!(provider.await.unwrap().len(), 1);
+
+        // Tool calls are skipped once the run is cancelled
+        let call = ToolCall {
+            id: "call_2".to_string(),
+            name: "write_file".to_string(),
+            arguments: json!({ "path": "b.txt", "content": "b" }),
+        };
+        let (_, record) = runner.execute_tool_call(&call).await;
+        assert!(!record.success);
+        assert!(!workspace.path().join("b.txt").exists());
+    }
+
+    #[test]
+    fn test_concurrent_batches_keep_writes_in_order() {
+        let calls: Vec<ToolCall> = ["read_file", "grep", "write_file", "read_file", "glob", "list_files", "exec", "exec"]
+            .iter()
+            .enumerate()
+            .map(|(i, name)| ToolCall {
+                id: format!("call_{}", i),
+                name: name.to_string(),
+                arguments: json!({}),
+            })
+            .collect();
+        let registry = crate::tools::create_default_registry();
+
+        let batches = concurrent_batches(&calls, |name| registry.is_read_only(name));
+
+        assert_eq!(batches, vec![0..2, 2..3, 3..6, 6..7, 7..8]);
+        assert!(concurrent_batches(&[], |_| true).is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_concurrent_reads_return_in_call_order() {
+        let workspace = TempDir::new().unwrap();
+        for name in ["a.txt", "b.txt", "c.txt"] {
+            std::fs::write(workspace.path().join(name), format!("contents of {}", name)).unwrap();
+        }
+        let client = AiClient::OpenAI(OpenAIClient::new("", Some("http://127.0.0.1:1/v1"), Some("test")).unwrap());
+        let context = ToolContext::new().with_workspace(workspace.path().to_string_lossy().to_string());
+        let runner = AgentRunner::new(client, Arc::new(crate::tools::create_default_registry()), context);
+
+        let call = |id: &str, name: &str, arguments: Value| ToolCall {
+            id: id.to_string(),
+            name: name.to_string(),
+            arguments,
+        };
+        let calls = vec![
+            call("1", "read_file", json!({ "path": "a.txt" })),
+            call("2", "read_file", json!({ "path": "b.txt" })),
+            call("3", "write_file", json!({ "path": "b.txt", "content": "rewritten" })),
+            call("4", "read_file", json!({ "path": "b.txt" })),
+            call("5", "read_file", json!({ "path": "c.txt" })),
+        ];
+        let results = runner.execute_tool_calls(&calls).await;
+
+        let ids: Vec<&str> = results.iter().map(|(r, _)| r.tool_call_id.as_str()).collect();
+        assert_eq!(ids, vec!["1", "2", "3", "4", "5"]);
+        assert!(results[0].0.content.contains("contents of a.txt"));
+        assert!(results[1].0.content.contains("contents of b.txt"));
+        // The read after the write sees the new contents
+        assert!(results[3].0.content.contains("rewritten"));
+        assert!(results[4].0.content.contains("contents of c.txt"));
+        assert!(results.iter().all(|(_, record)| record.success));
+    }
+
+    #[tokio::test]
+    async fn test_long_outputs_are_stored_and_previewed() {
+        let workspace = TempDir::new().unwrap();
+        let content: String = (1..=5000).map(|i| format!("line {}\n", i)).collect();
+        std::fs::write(workspace.path().join("big.txt"), &content).unwrap();
+        let client = AiClient::OpenAI(OpenAIClient::new("", Some("http://127.0.0.1:1/v1"), Some("test")).unwrap());
+        let context = ToolContext::new().with_workspace(workspace.path().to_string_lossy().to_string());
+        let store = OutputStore::new(workspace.path().join(".outputs"));
+        let runner = AgentRunner::new(client, Arc::new(crate::tools::create_default_registry()), context)
+            .with_output_store(store.clone());
+
+        let calls = vec![
+            ToolCall {
+                id: "1".to_string(),
+                name: "read_file".to_string(),
+                arguments: json!({ "path": "big.txt", "max_lines": 5000, "max_bytes": 50000 }),
+            },
+            ToolCall {
+                id: "2".to_string(),
+                name: "read_file".to_string(),
+                arguments: json!({ "path": "big.txt", "max_lines": 3 }),
+            },
+        ];
+        let results = runner.execute_tool_calls(&calls).await;
+
+        // The long read is previewed, with the full output kept in the store
+        let (response, record) = &results[0];
+        let output_ref = record.output_ref.as_deref().unwrap();
+        assert!(response.content.len() <= INLINE_OUTPUT_BYTES + 300);
+        assert!(response.content.contains(&format!("output_ref \"{}\"", output_ref)));
+        assert!(store.get(output_ref).await.unwrap().unwrap().contains("line 2000\n"));
+        // Short outputs pass through untouched
+        assert_eq!(results[1].1.output_ref, None);
+        assert!(!results[1].0.content.contains("output_ref"));
+
+        let fetch = ToolCall {
+            id: "3".to_string(),
+            name: "fetch_output".to_string(),
+            arguments: json!({ "output_ref": output_ref, "offset": 0 }),
+        };
+        let (response, record) = runner.execute_tool_call(&fetch).await;
+        assert!(record.success, "{}", response.content);
+        assert_eq!(record.output_ref, None);
+        assert!(response.content.contains("    1│ line 1"));
+    }
+
+    #[test]
+    fn test_with_tools_restricts_tool_set() {
+        let client = AiClient::OpenAI(OpenAIClient::new("", Some("http://127.0.0.1:1/v1"), Some("test")).unwrap());
+        let registry = Arc::new(crate::tools::create_default_registry());
+        let runner = AgentRunner::new(client, Arc::clone(&registry), ToolContext::new())
+            .with_tools(&["read_file".to_string(), "grep".to_string(), "set_agent_subtype".to_string()]);
+
+        let names: Vec<String> = runner.tool_definitions().into_iter().map(|t| t.name).collect();
+        assert_eq!(names, vec!["grep", "read_file"]);
+        assert!(AgentRunner::available_tools(&registry).contains(&"write_file".to_string()));
+    }
+
+    #[test]
+    fn test_code_engineer_tools_exclude_system_group() {
+        let config = AgentRunner::code_engineer_tool_config();
+        assert!(config.is_tool_allowed("write_file", ToolGroup::Development));
+        assert!(config.is_tool_allowed("exec", ToolGroup::Exec));
+        assert!(!config.is_tool_allowed("set_agent_subtype", ToolGroup::System));
+        assert!(!config.is_tool_allowed("web3_tx", ToolGroup::Finance));
+    }
+}
@@ -0,0 +1,184 @@
+//! Runs scheduled tasks as background agent jobs
+//!
+//! Every poll, enabled tasks whose `next_run_at` has passed are queued on the
+//! [`AgentJobQueue`] with their prompt, tool set and workspace, and their next
+//! run time is moved to the following cron occurrence. A task whose previous
+//! job is still queued or running is skipped for that occurrence rather than
+//! piling up runs. Missed occurrences (e.g. while the server was down) fire
+//! once, not once per missed slot.
+//!
+//! Prompts are rendered as templates (see `agent::templates`), with
+//! `{{schedule}}` set to the task's name and `{{date}}` to the UTC date.
+
+use crate::agent::templates;
+use crate::agent::AgentJobQueue;
+use crate::db::Database;
+use crate::models::{AgentJob, ScheduledTask};
+use chrono::{DateTime, Utc};
+use std::collections::BTreeMap;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::oneshot;
+
+/// How often due tasks are checked for
+const POLL_INTERVAL: Duration = Duration::from_secs(30);
+
+pub struct ScheduleRunner {
+    db: Arc<Database>,
+    jobs: Arc<AgentJobQueue>,
+}
+
+impl ScheduleRunner {
+    pub fn new(db: Arc<Database>, jobs: Arc<AgentJobQueue>) -> Self {
+        Self { db, jobs }
+    }
+
+    /// Launch due tasks until shutdown is signalled
+    pub async fn start(self: Arc<Self>, mut shutdown_rx: oneshot::Receiver<()>) {
+        let mut poll = tokio::time::interval(POLL_INTERVAL);
+        loop {
+            tokio::select! {
+                _ = &mut shutdown_rx => {
+                    tracing::info!("[SCHEDULE] Runner shutting down");
+                    return;
+                }
+                _ = poll.tick() => {
+                    if let Err(e) = self.tick(Utc::now()).await {
+                        tracing::error!("[SCHEDULE] {}", e);
+                    }
+                }
+            }
+        }
+    }
+
+    /// Launch every task due at `now`. Returns the jobs queued.
+    pub async fn tick(&self, now: DateTime<Utc>) -> Result<Vec<AgentJob>, String> {
+        let due = self
+            .db
+            .list_due_scheduled_tasks(now).await
+            .map_err(|e| format!("Failed to list due scheduled tasks: {}", e))?;
+
+        let mut queued = Vec::new();
+        for task in due {
+            let next_run = task.next_run_after(now).map(|dt| dt.to_rfc3339());
+
+            let job = if self.previous_run_active(&task).await {
+                tracing::info!("[SCHEDULE] Skipping '{}': previous run is still in progress", task.name);
+                None
+            } else {
+                match self.launch(&task).await {
+                    Ok(job) => Some(job),
+                    Err(e) => {
+                        tracing::error!("[SCHEDULE] Failed to launch '{}': {}", task.name, e);
+                        None
+                    }
+                }
+            };
+
+            if let Err(e) = self
+                .db
+                .record_scheduled_task_run(
+                    task.id,
+                    &now.to_rfc3339(),
+                    next_run.as_deref(),
+                    job.as_ref().map(|j| j.job_id.as_str()),
+                ).await
+            {
+                tracing::error!("[SCHEDULE] Failed to record run of '{}': {}", task.name, e);
+            }
+            queued.extend(job);
+        }
+        Ok(queued)
+    }
+
+    /// Queue a job for `task` now, regardless of its schedule
+    pub async fn launch(&self, task: &ScheduledTask) -> Result<AgentJob, String> {
+        tracing::info!("[SCHEDULE] Launching '{}' (schedule {})", task.name, task.id);
+        let variables = BTreeMap::from([
+            ("schedule".to_string(), task.name.clone()),
+            ("date".to_string(), Utc::now().format("%Y-%m-%d").to_string()),
+        ]);
+        let prompt = templates::expand(&self.db, &task.prompt, &variables).await?.text;
+        self.jobs
+            .enqueue(
+                &prompt,
+                &task.workspace,
+                task.max_iterations.max(1) as usize,
+                &task.tools,
+                Some(task.id),
+                false,
+                None,
+                task.user_id,
+            )
+            .await
+    }
+
+    async fn previous_run_active(&self, task: &ScheduledTask) -> bool {
+        let Some(job_id) = task.last_job_id.as_deref() else {
+            return false;
+        };
+        matches!(self.db.get_agent_job(job_id).await, Ok(Some(job)) if !job.status.is_finished())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::execution::ProcessManager;
+    use crate::gateway::events::EventBroadcaster;
+    use crate::models::{AgentJobStatus, CreateScheduledTaskRequest};
+    use serde_json::json;
+
+    #[tokio::test]
+    async fn test_due_tasks_queue_jobs_without_overlap() {
+        let db = Arc::new(Database::new(":memory:").unwrap());
+        let queue = Arc::new(AgentJobQueue::new(
+            Arc::clone(&db),
+            Arc::new(crate::tools::create_default_registry()),
+            Arc::new(ProcessManager::new(Arc::new(EventBroadcaster::new()))),
+            None,
+        ));
+        let runner = ScheduleRunner::new(Arc::clone(&db), queue);
+
+        let request = CreateScheduledTaskRequest {
+            name: "hourly".to_string(),
+            cron_expression: "0 * * * *".to_string(),
+            prompt: "Check the build".to_string(),
+            tools: vec!["exec".to_string()],
+            workspace: None,
+            max_iterations: None,
+            enabled: true,
+        };
+        let task = db
+            .create_scheduled_task(&request, None, 5, Some("2026-01-01T10:00:00+00:00"), Some(7))
+            .await
+            .unwrap();
+
+        let at = |s: &str| DateTime::parse_from_rfc3339(s).unwrap().with_timezone(&Utc);
+        assert!(runner.tick(at("2026-01-01T09:59:00Z")).await.unwrap().is_empty());
+
+        let jobs = runner.tick(at("2026-01-01T10:00:05Z")).await.unwrap();
+        assert_eq!(jobs.len(), 1);
+        assert_eq!(jobs[0].task, "Check the build");
+        assert_eq!(jobs[0].workspace, format!("schedule-{}", task.id));
+        assert_eq!(jobs[0].tools, vec!["exec"]);
+        assert_eq!(jobs[0].schedule_id, Some(task.id));
+        // Runs as the schedule's owner
+        assert_eq!(jobs[0].user_id, Some(7));
+        let task = db.get_scheduled_task(task.id).await.unwrap().unwrap();
+        assert_eq!(task.next_run_at.as_deref(), Some("2026-01-01T11:00:00+00:00"));
+
+        // The first job hasn't run yet, so the next occurrence is skipped
+        assert!(runner.tick(at("2026-01-01T11:00:05Z")).await.unwrap().is_empty());
+        let task = db.get_scheduled_task(task.id).await.unwrap().unwrap();
+        assert_eq!(task.last_job_id.as_deref(), Some(jobs[0].job_id.as_str()));
+        assert_eq!(task.next_run_at.as_deref(), Some("2026-01-01T12:00:00+00:00"));
+
+        db.claim_next_agent_job().await.unwrap();
+        db.finish_agent_job(&jobs[0].job_id, AgentJobStatus::Completed, 1, &json!([]), &json!([]), Some("ok"), None)
+            .await
+            .unwrap();
+        assert_eq!(runner.tick(at("2026-01-01T12:00:05Z")).await.unwrap().len(), 1);
+        assert_eq!(db.list_agent_jobs_for_schedule(task.id, 10).await.unwrap().len(), 2);
+    }
+}
@@ -0,0 +1,179 @@
+//! Rendering of prompt templates
+//!
+//! `{{name}}` is replaced with the variable's value and `{{> other}}` with the
+//! template named `other`, rendered with the same variables over its own
+//! defaults. Values are inserted as-is and never scanned for placeholders, so
+//! a webhook payload can't pull in templates or variables. A placeholder with
+//! no value is left in place and reported in [`Rendered::missing`].
+//!
+//! Schedule prompts and webhook prompt templates are rendered the same way
+//! when they run, so they can include stored templates.
+
+use crate::db::Database;
+use crate::models::PromptTemplate;
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+
+/// Deepest chain of `{{> ...}}` includes
+pub const MAX_INCLUDE_DEPTH: usize = 8;
+
+/// The result of rendering
+#[derive(Debug, Default, PartialEq)]
+pub struct Rendered {
+    pub text: String,
+    /// Variables that were substituted
+    pub used: BTreeSet<String>,
+    /// Variables without a value, left as `{{name}}`
+    pub missing: BTreeSet<String>,
+}
+
+/// Whether `name` can be a template or variable name
+pub fn is_valid_name(name: &str) -> bool {
+    !name.is_empty()
+        && name.len() <= 64
+        && name.chars().all(|c| c.is_ascii_alphanumeric() || matches!(c, '_' | '-' | '.'))
+}
+
+/// Render `source` with `variables`, resolving includes from `templates`
+pub fn render(
+    source: &str,
+    variables: &BTreeMap<String, String>,
+    templates: &HashMap<String, PromptTemplate>,
+) -> Result<Rendered, String> {
+    let mut rendered = Rendered::default();
+    render_into(source, variables, templates, &mut Vec::new(), &mut rendered)?;
+    Ok(rendered)
+}
+
+/// Render the stored template `name`; `variables` override its defaults
+pub fn render_template(
+    name: &str,
+    variables: &BTreeMap<String, String>,
+    templates: &HashMap<String, PromptTemplate>,
+) -> Result<Rendered, String> {
+    render(&format!("{{{{> {}}}}}", name), variables, templates)
+}
+
+/// The stored templates by name, when `source` includes any (so prompts
+/// without includes don't touch the database)
+pub async fn templates_for(db: &Database, source: &str) -> Result<HashMap<String, PromptTemplate>, String> {
+    if !source.contains("{{>") {
+        return Ok(HashMap::new());
+    }
+    let templates = db
+        .list_prompt_templates()
+        .await
+        .map_err(|e| format!("Failed to load prompt templates: {}", e))?;
+    Ok(templates.into_iter().map(|t| (t.name.clone(), t)).collect())
+}
+
+/// Render `source`, loading the templates it includes
+pub async fn expand(db: &Database, source: &str, variables: &BTreeMap<String, String>) -> Result<Rendered, String> {
+    let templates = templates_for(db, source).await?;
+    render(source, variables, &templates)
+}
+
+/// Check that the templates `source` includes exist and don't loop
+pub async fn check_includes(db: &Database, source: &str) -> Result<(), String> {
+    expand(db, source, &BTreeMap::new()).await.map(|_| ())
+}
+
+fn render_into(
+    source: &str,
+    variables: &BTreeMap<String, String>,
+    templates: &HashMap<String, PromptTemplate>,
+    stack: &mut Vec<String>,
+    out: &mut Rendered,
+) -> Result<(), String> {
+    let mut rest = source;
+    while let Some(start) = rest.find("{{") {
+        out.text.push_str(&rest[..start]);
+        let Some(len) = rest[start + 2..].find("}}") else {
+            rest = &rest[start..];
+            break;
+        };
+        let placeholder = &rest[start..start + len + 4];
+        let inner = placeholder[2..placeholder.len() - 2].trim();
+        rest = &rest[start + len + 4..];
+
+        if let Some(name) = inner.strip_prefix('>') {
+            let name = name.trim();
+            let template = templates.get(name).ok_or_else(|| format!("Unknown template '{}'", name))?;
+            if stack.iter().any(|n| n == name) {
+                return Err(format!("Template '{}' includes itself", name));
+            }
+            if stack.len() >= MAX_INCLUDE_DEPTH {
+                return Err(format!("Templates are nested more than {} deep", MAX_INCLUDE_DEPTH));
+            }
+            // The includer's values win over the included template's defaults
+            let mut merged = template.variables.clone();
+            merged.extend(variables.iter().map(|(k, v)| (k.clone(), v.clone())));
+            stack.push(name.to_string());
+            render_into(&template.body, &merged, templates, stack, out)?;
+            stack.pop();
+        } else if is_valid_name(inner) {
+            match variables.get(inner) {
+                Some(value) => {
+                    out.text.push_str(value);
+                    out.used.insert(inner.to_string());
+                }
+                None => {
+                    out.text.push_str(placeholder);
+                    out.missing.insert(inner.to_string());
+                }
+            }
+        } else {
+            out.text.push_str(placeholder);
+        }
+    }
+    out.text.push_str(rest);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn template(name: &str, body: &str, defaults: &[(&str, &str)]) -> (String, PromptTemplate) {
+        let template = PromptTemplate {
+            id: 0,
+            name: name.to_string(),
+            description: String::new(),
+            body: body.to_string(),
+            variables: defaults.iter().map(|(k, v)| (k.to_string(), v.to_string())).collect(),
+            created_at: String::new(),
+            updated_at: String::new(),
+        };
+        (name.to_string(), template)
+    }
+
+    fn vars(pairs: &[(&str, &str)]) -> BTreeMap<String, String> {
+        pairs.iter().map(|(k, v)| (k.to_string(), v.to_string())).collect()
+    }
+
+    #[test]
+    fn test_render_variables_and_includes() {
+        let templates: HashMap<_, _> = [
+            template("style", "Be terse. Target branch: {{ branch }}.", &[("branch", "main")]),
+            template("review", "Review {{repo}}#{{pr}}.\n{{> style}}", &[("repo", "stark-bot")]),
+            template("loop-a", "{{> loop-b}}", &[]),
+            template("loop-b", "{{> loop-a}}", &[]),
+        ]
+        .into_iter()
+        .collect();
+
+        let rendered = render_template("review", &vars(&[("pr", "42"), ("branch", "dev")]), &templates).unwrap();
+        assert_eq!(rendered.text, "Review stark-bot#42.\nBe terse. Target branch: dev.");
+        assert!(rendered.missing.is_empty());
+
+        // Missing values stay visible; values are not rendered again
+        let rendered = render("{{> review}} {{}} {{a b}}", &vars(&[("repo", "{{> style}}")]), &templates).unwrap();
+        assert_eq!(rendered.text, "Review {{> style}}#{{pr}}.\nBe terse. Target branch: main. {{}} {{a b}}");
+        assert_eq!(rendered.missing.into_iter().collect::<Vec<_>>(), vec!["pr"]);
+        assert!(rendered.used.contains("repo") && rendered.used.contains("branch"));
+        assert_eq!(render("unclosed {{repo", &vars(&[("repo", "x")]), &templates).unwrap().text, "unclosed {{repo");
+
+        assert_eq!(render("{{> nope}}", &vars(&[]), &templates).unwrap_err(), "Unknown template 'nope'");
+        assert!(render_template("loop-a", &vars(&[]), &templates).unwrap_err().contains("includes itself"));
+        assert!(is_valid_name("release-notes.v2") && !is_valid_name("a b") && !is_valid_name(""));
+    }
+}
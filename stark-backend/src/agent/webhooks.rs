@@ -0,0 +1,190 @@
+//! Signature checks and prompt rendering for inbound webhooks
+//!
+//! Every delivery must carry an HMAC-SHA256 of the raw body keyed with the
+//! hook's secret, in one of the formats senders commonly use:
+//!
+//! - GitHub: `X-Hub-Signature-256: sha256=<hex>`
+//! - Stripe: `Stripe-Signature: t=<unix time>,v1=<hex>` over `<t>.<body>`,
+//!   rejected when `t` is more than [`STRIPE_TOLERANCE_SECS`] away from now
+//! - Anything else: `X-Signature-256: sha256=<hex>` (the prefix is optional)
+
+use crate::agent::templates;
+use crate::models::PromptTemplate;
+use actix_web::http::header::HeaderMap;
+use hmac::{Hmac, Mac};
+use serde_json::Value;
+use sha2::Sha256;
+use std::collections::{BTreeMap, HashMap};
+
+type HmacSha256 = Hmac<Sha256>;
+
+/// How far a Stripe signature timestamp may be from the server clock
+pub const STRIPE_TOLERANCE_SECS: i64 = 300;
+
+/// Payloads longer than this are cut before going into the prompt
+const MAX_PAYLOAD_CHARS: usize = 20_000;
+
+/// Check the delivery's signature against `secret`. `now` is a unix timestamp.
+pub fn verify_signature(secret: &str, headers: &HeaderMap, body: &[u8], now: i64) -> Result<(), String> {
+    let header = |name: &str| headers.get(name).and_then(|v| v.to_str().ok());
+
+    if let Some(signature) = header("x-hub-signature-256") {
+        return verify_hex(secret, body, signature.trim_start_matches("sha256="));
+    }
+
+    if let Some(signature) = header("stripe-signature") {
+        let mut timestamp = None;
+        let mut candidates = Vec::new();
+        for part in signature.split(',') {
+            match part.trim().split_once('=') {
+                Some(("t", t)) => timestamp = t.parse::<i64>().ok(),
+                Some(("v1", sig)) => candidates.push(sig),
+                _ => {}
+            }
+        }
+        let timestamp = timestamp.ok_or("Stripe-Signature has no timestamp")?;
+        if (now - timestamp).abs() > STRIPE_TOLERANCE_SECS {
+            return Err("Stripe-Signature timestamp is outside the tolerance window".to_string());
+        }
+        let mut signed = format!("{}.", timestamp).into_bytes();
+        signed.extend_from_slice(body);
+        return if candidates.iter().any(|sig| verify_hex(secret, &signed, sig).is_ok()) {
+            Ok(())
+        } else {
+            Err("Signature does not match".to_string())
+        };
+    }
+
+    if let Some(signature) = header("x-signature-256") {
+        return verify_hex(secret, body, signature.trim_start_matches("sha256="));
+    }
+
+    Err("Missing signature header".to_string())
+}
+
+/// Constant-time comparison of the HMAC of `message` with a hex signature
+fn verify_hex(secret: &str, message: &[u8], signature: &str) -> Result<(), String> {
+    let expected = hex::decode(signature.trim()).map_err(|_| "Signature is not valid hex".to_string())?;
+    let mut mac = HmacSha256::new_from_slice(secret.as_bytes()).map_err(|e| e.to_string())?;
+    mac.update(message);
+    mac.verify_slice(&expected).map_err(|_| "Signature does not match".to_string())
+}
+
+/// Event name from the sender's headers (GitHub), or the payload's `type` field (Stripe)
+pub fn event_name(headers: &HeaderMap, payload: Option<&Value>) -> String {
+    headers
+        .get("x-github-event")
+        .and_then(|v| v.to_str().ok())
+        .map(str::to_string)
+        .or_else(|| payload.and_then(|p| p.get("type")).and_then(Value::as_str).map(str::to_string))
+        .unwrap_or_default()
+}
+
+/// Fill in `{{payload}}`, `{{event}}` and `{{hook}}` and any `{{> template}}`
+/// includes. A template without `{{payload}}` gets the payload appended so the
+/// agent always sees it.
+pub fn render_prompt(
+    template: &str,
+    hook_name: &str,
+    event: &str,
+    body: &[u8],
+    templates: &HashMap<String, PromptTemplate>,
+) -> Result<String, String> {
+    let payload = match serde_json::from_slice::<Value>(body) {
+        Ok(json) => serde_json::to_string_pretty(&json).unwrap_or_default(),
+        Err(_) => String::from_utf8_lossy(body).into_owned(),
+    };
+    let payload = match payload.char_indices().nth(MAX_PAYLOAD_CHARS) {
+        Some((cut, _)) => format!("{}\n... (payload truncated)", &payload[..cut]),
+        None => payload,
+    };
+
+    let variables = BTreeMap::from([
+        ("event".to_string(), event.to_string()),
+        ("hook".to_string(), hook_name.to_string()),
+        ("payload".to_string(), payload.clone()),
+    ]);
+    let rendered = templates::render(template, &variables, templates)?;
+    if rendered.used.contains("payload") {
+        Ok(rendered.text)
+    } else {
+        Ok(format!("{}\n\nWebhook payload:\n```\n{}\n```", rendered.text, payload))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use actix_web::http::header::{HeaderName, HeaderValue};
+
+    fn headers(pairs: &[(&'static str, &str)]) -> HeaderMap {
+        let mut map = HeaderMap::new();
+        for (name, value) in pairs {
+            map.insert(HeaderName::from_static(name), HeaderValue::from_str(value).unwrap());
+        }
+        map
+    }
+
+    fn sign(secret: &str, body: &[u8]) -> String {
+        let mut mac = HmacSha256::new_from_slice(secret.as_bytes()).unwrap();
+        mac.update(body);
+        format!("sha256={}", hex::encode(mac.finalize().into_bytes()))
+    }
+
+    #[test]
+    fn test_verify_signature_formats() {
+        let body = br#"{"action":"opened"}"#;
+        let github = headers(&[("x-hub-signature-256", &sign("s3cret", body))]);
+        assert!(verify_signature("s3cret", &github, body, 0).is_ok());
+        assert!(verify_signature("wrong", &github, body, 0).is_err());
+        assert!(verify_signature("s3cret", &github, b"tampered", 0).is_err());
+
+        let bare = sign("s3cret", body).trim_start_matches("sha256=").to_string();
+        assert!(verify_signature("s3cret", &headers(&[("x-signature-256", &bare)]), body, 0).is_ok());
+        assert!(verify_signature("s3cret", &HeaderMap::new(), body, 0).is_err());
+
+        let signed = sign("whsec", &[b"1700000000.".as_slice(), body].concat());
+        let stripe = headers(&[(
+            "stripe-signature",
+            &format!("t=1700000000,v1=deadbeef,v1={}", signed.trim_start_matches("sha256=")),
+        )]);
+        assert!(verify_signature("whsec", &stripe, body, 1_700_000_100).is_ok());
+        assert!(verify_signature("whsec", &stripe, body, 1_700_000_000 + STRIPE_TOLERANCE_SECS + 1).is_err());
+    }
+
+    #[test]
+    fn test_render_prompt() {
+        let body = br#"{"type":"invoice.paid","id":"evt_1"}"#;
+        let payload: Value = serde_json::from_slice(body).unwrap();
+        let event = event_name(&HeaderMap::new(), Some(&payload));
+        assert_eq!(event, "invoice.paid");
+        assert_eq!(event_name(&headers(&[("x-github-event", "push")]), Some(&payload)), "push");
+
+        let none = HashMap::new();
+        let prompt = render_prompt("[{{hook}}] {{event}}: {{payload}}", "billing", &event, body, &none).unwrap();
+        assert!(prompt.starts_with("[billing] invoice.paid: {"));
+        assert!(prompt.contains("\"id\": \"evt_1\""));
+
+        let prompt = render_prompt("Triage this alert", "alerts", "", b"disk full on db-1", &none).unwrap();
+        assert!(prompt.starts_with("Triage this alert\n\nWebhook payload:"));
+        assert!(prompt.contains("disk full on db-1"));
+
+        // Included templates see the webhook's variables
+        let triage = PromptTemplate {
+            id: 1,
+            name: "triage".to_string(),
+            description: String::new(),
+            body: "Triage this {{event}}: {{payload}}".to_string(),
+            variables: BTreeMap::new(),
+            created_at: String::new(),
+            updated_at: String::new(),
+        };
+        let templates = HashMap::from([("triage".to_string(), triage)]);
+        let prompt = render_prompt("{{> triage}}", "alerts", "page", b"{{> triage}}", &templates).unwrap();
+        assert_eq!(prompt, "Triage this page: {{> triage}}");
+        assert!(render_prompt("{{> missing}}", "alerts", "", b"", &none).is_err());
+
+        let long = "x".repeat(MAX_PAYLOAD_CHARS + 10);
+        assert!(render_prompt("{{payload}}", "h", "", long.as_bytes(), &none).unwrap().ends_with("(payload truncated)"));
+    }
+}
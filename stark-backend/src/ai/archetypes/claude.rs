@@ -30,10 +30,26 @@ impl ModelArchetype for ClaudeArchetype {
         true
     }
 
+    fn default_supports_vision(&self) -> bool {
+        true
+    }
+
     fn default_model(&self) -> &'static str {
         "claude-sonnet-4-20250514"
     }
 
+    fn display_name(&self) -> &'static str {
+        "Claude (Native Tool Calling)"
+    }
+
+    fn description(&self) -> &'static str {
+        "Anthropic Claude native tool calling."
+    }
+
+    fn default_max_tokens(&self) -> u32 {
+        8192
+    }
+
     fn enhance_system_prompt(&self, base_prompt: &str, _tools: &[ToolDefinition]) -> String {
         // Don't list tools in the system prompt - they're passed via the API's `tools` parameter
         base_prompt.to_string()
@@ -0,0 +1,81 @@
+//! DeepSeek Archetype - Native tool calling against the DeepSeek API
+//!
+//! DeepSeek's chat completions API is OpenAI-compatible, so tools go through
+//! the API's `tools` parameter as with Kimi. It caps output at 8K tokens and
+//! does not take `tool_choice: "required"` for every model, so its profile
+//! lets the model decide when to call tools.
+
+use super::{AgentResponse, ArchetypeId, JsonMode, ModelArchetype, ToolChoiceMode};
+use crate::tools::ToolDefinition; // Required by ModelArchetype trait
+
+/// DeepSeek archetype for native tool calling against the DeepSeek API
+pub struct DeepSeekArchetype;
+
+impl DeepSeekArchetype {
+    pub fn new() -> Self {
+        Self
+    }
+}
+
+impl Default for DeepSeekArchetype {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ModelArchetype for DeepSeekArchetype {
+    fn id(&self) -> ArchetypeId {
+        ArchetypeId::DeepSeek
+    }
+
+    fn uses_native_tool_calling(&self) -> bool {
+        true
+    }
+
+    fn default_model(&self) -> &'static str {
+        "deepseek-chat"
+    }
+
+    fn display_name(&self) -> &'static str {
+        "DeepSeek (Native Tool Calling)"
+    }
+
+    fn description(&self) -> &'static str {
+        "OpenAI-compatible native tool calling for the DeepSeek API."
+    }
+
+    fn default_max_tokens(&self) -> u32 {
+        8192
+    }
+
+    fn default_tool_choice(&self) -> ToolChoiceMode {
+        ToolChoiceMode::Auto
+    }
+
+    fn default_json_mode(&self) -> JsonMode {
+        JsonMode::Object
+    }
+
+    fn default_prompt_suffix(&self) -> Option<&'static str> {
+        // DeepSeek models can leak their tool-call markup into the reply text
+        Some("Call tools only through the tool-calling interface. Never write tool calls, or tokens such as <｜tool▁calls▁begin｜>, in your reply.")
+    }
+
+    fn enhance_system_prompt(&self, base_prompt: &str, _tools: &[ToolDefinition]) -> String {
+        // Tools are passed via the API's `tools` parameter
+        base_prompt.to_string()
+    }
+
+    fn parse_response(&self, content: &str) -> Option<AgentResponse> {
+        // Native tool calling uses the API's tool_calls field, not text parsing
+        Some(AgentResponse {
+            body: content.to_string(),
+            tool_call: None,
+        })
+    }
+
+    fn format_tool_followup(&self, _tool_name: &str, _tool_result: &str, _success: bool) -> String {
+        // Native tool calling uses the API's message format for tool results
+        String::new()
+    }
+}
@@ -6,7 +6,7 @@
 //! Tools are passed via the API's `tools` parameter, and responses
 //! contain `tool_calls` in the message structure.
 
-use super::{AgentResponse, ArchetypeId, ModelArchetype};
+use super::{AgentResponse, ArchetypeId, JsonMode, ModelArchetype};
 use crate::tools::ToolDefinition; // Required by ModelArchetype trait
 
 /// Kimi archetype for native OpenAI-compatible tool calling
@@ -37,6 +37,23 @@ impl ModelArchetype for KimiArchetype {
         "kimi-k2-turbo-preview" // Kimi K2 turbo preview - supports native tool calling per docs
     }
 
+    fn default_json_mode(&self) -> JsonMode {
+        JsonMode::Object
+    }
+
+    fn display_name(&self) -> &'static str {
+        "Kimi (Native Tool Calling)"
+    }
+
+    fn description(&self) -> &'static str {
+        "OpenAI-compatible native tool calling. Best for Kimi, OpenAI, and similar endpoints."
+    }
+
+    fn default_prompt_suffix(&self) -> Option<&'static str> {
+        // Kimi models sometimes write a tool call out as text instead of making it
+        Some("Call tools only through the tool-calling interface. Never write a tool call out as text or JSON in your reply.")
+    }
+
     fn enhance_system_prompt(&self, base_prompt: &str, _tools: &[ToolDefinition]) -> String {
         // Don't list tools in the system prompt - they're passed via the API's `tools` parameter.
         // Listing them as text confuses some models into outputting tool calls as formatted text
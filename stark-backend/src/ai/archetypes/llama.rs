@@ -224,6 +224,19 @@ impl ModelArchetype for LlamaArchetype {
         "llama3.3" // Default Llama model
     }
 
+    fn display_name(&self) -> &'static str {
+        "Llama (Text-based Tool Calling)"
+    }
+
+    fn description(&self) -> &'static str {
+        "JSON-based tool calling via text. Best for generic Llama endpoints."
+    }
+
+    fn default_stop_sequences(&self) -> &'static [&'static str] {
+        // Raw Llama 3 endpoints can run past the end of the assistant turn
+        &["<|eot_id|>"]
+    }
+
     fn enhance_system_prompt(&self, base_prompt: &str, tools: &[ToolDefinition]) -> String {
         let mut prompt = base_prompt.to_string();
 
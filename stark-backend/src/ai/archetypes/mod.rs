@@ -5,10 +5,17 @@
 //! - Some models (Llama, generic endpoints) require text-based JSON tool calling
 //!
 //! This module provides a unified interface for handling both approaches.
+//! Each archetype also has a profile (see [`profile`]) with the request
+//! defaults and prompt tweaks for its model family, which can be customized.
 
 pub mod claude;
+pub mod deepseek;
 pub mod kimi;
 pub mod llama;
+pub mod openai;
+pub mod profile;
+
+pub use profile::{ArchetypeProfile, ArchetypeProfileOverrides, JsonMode, ToolChoiceMode};
 
 use crate::tools::ToolDefinition;
 use serde::{Deserialize, Serialize};
@@ -26,6 +33,8 @@ pub enum ArchetypeId {
     OpenAI,
     /// Native Claude tool calling
     Claude,
+    /// Native tool calling against the DeepSeek API
+    DeepSeek,
 }
 
 impl ArchetypeId {
@@ -34,8 +43,9 @@ impl ArchetypeId {
         match s.to_lowercase().as_str() {
             "llama" | "text" | "json" => Some(ArchetypeId::Llama),
             "kimi" | "moonshot" | "native" => Some(ArchetypeId::Kimi),
-            "openai" => Some(ArchetypeId::OpenAI),
+            "openai" | "gpt" => Some(ArchetypeId::OpenAI),
             "claude" | "anthropic" => Some(ArchetypeId::Claude),
+            "deepseek" => Some(ArchetypeId::DeepSeek),
             _ => None,
         }
     }
@@ -47,8 +57,18 @@ impl ArchetypeId {
             ArchetypeId::Kimi => "kimi",
             ArchetypeId::OpenAI => "openai",
             ArchetypeId::Claude => "claude",
+            ArchetypeId::DeepSeek => "deepseek",
         }
     }
+
+    /// Every archetype, in the order they are listed
+    pub const ALL: [ArchetypeId; 5] = [
+        ArchetypeId::Kimi,
+        ArchetypeId::Llama,
+        ArchetypeId::Claude,
+        ArchetypeId::OpenAI,
+        ArchetypeId::DeepSeek,
+    ];
 }
 
 impl std::fmt::Display for ArchetypeId {
@@ -83,9 +103,53 @@ pub trait ModelArchetype: Send + Sync {
     /// Used when model is not explicitly specified (x402 endpoints use "default")
     fn default_model(&self) -> &'static str;
 
+    /// Name shown when picking an archetype
+    fn display_name(&self) -> &'static str;
+
+    /// One line on what the archetype is for
+    fn description(&self) -> &'static str;
+
+    /// Output token limit requests are capped at by default
+    fn default_max_tokens(&self) -> u32 {
+        40000
+    }
+
+    /// Stop sequences sent with every request by default
+    fn default_stop_sequences(&self) -> &'static [&'static str] {
+        &[]
+    }
+
+    /// How the model is told to use the tools it is offered, by default
+    fn default_tool_choice(&self) -> ToolChoiceMode {
+        ToolChoiceMode::Required
+    }
+
+    /// How structured output is requested by default. Only OpenAI-compatible
+    /// endpoints have a JSON mode; the others are told in the prompt.
+    fn default_json_mode(&self) -> JsonMode {
+        JsonMode::Prompt
+    }
+
+    /// Whether the archetype's default model accepts images
+    fn default_supports_vision(&self) -> bool {
+        false
+    }
+
+    /// Instructions added to the system prompt by default, for the quirks of
+    /// the model family
+    fn default_prompt_suffix(&self) -> Option<&'static str> {
+        None
+    }
+
     /// Enhance system prompt with tool-calling instructions (for text-based archetypes)
     fn enhance_system_prompt(&self, base_prompt: &str, tools: &[ToolDefinition]) -> String;
 
+    /// The system prompt to send: `enhance_system_prompt`, followed by the
+    /// profile's prompt suffix
+    fn system_prompt(&self, base_prompt: &str, tools: &[ToolDefinition]) -> String {
+        profile::profile(self.id()).apply_to_prompt(self.enhance_system_prompt(base_prompt, tools))
+    }
+
     /// Parse AI response to extract tool calls (for text-based archetypes)
     /// Returns None if the response couldn't be parsed as a structured response
     fn parse_response(&self, content: &str) -> Option<AgentResponse>;
@@ -110,6 +174,8 @@ impl ArchetypeRegistry {
         registry.register(Box::new(llama::LlamaArchetype::new()));
         registry.register(Box::new(kimi::KimiArchetype::new()));
         registry.register(Box::new(claude::ClaudeArchetype::new()));
+        registry.register(Box::new(openai::OpenAIArchetype::new()));
+        registry.register(Box::new(deepseek::DeepSeekArchetype::new()));
 
         registry
     }
@@ -138,3 +204,23 @@ impl Default for ArchetypeRegistry {
     }
 }
 
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_every_archetype_is_registered() {
+        let registry = ArchetypeRegistry::new();
+        for id in ArchetypeId::ALL {
+            let archetype = registry.get(id).unwrap();
+            assert_eq!(archetype.id(), id);
+            assert_eq!(ArchetypeId::from_str(id.as_str()), Some(id));
+        }
+
+        let openai = registry.get(ArchetypeId::OpenAI).unwrap();
+        assert!(openai.uses_native_tool_calling());
+        assert_eq!(openai.default_model(), "gpt-4o");
+        assert_eq!(ArchetypeId::from_str("GPT"), Some(ArchetypeId::OpenAI));
+    }
+}
@@ -0,0 +1,76 @@
+//! OpenAI Archetype - Native OpenAI tool calling
+//!
+//! Same wire behaviour as the Kimi archetype (tools via the API's `tools`
+//! parameter, `tool_calls` in responses), but defaults to an OpenAI model
+//! so `model_archetype = "openai"` works against api.openai.com out of the box.
+
+use super::{AgentResponse, ArchetypeId, JsonMode, ModelArchetype};
+use crate::tools::ToolDefinition; // Required by ModelArchetype trait
+
+/// OpenAI archetype for native tool calling against the OpenAI API
+pub struct OpenAIArchetype;
+
+impl OpenAIArchetype {
+    pub fn new() -> Self {
+        Self
+    }
+}
+
+impl Default for OpenAIArchetype {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ModelArchetype for OpenAIArchetype {
+    fn id(&self) -> ArchetypeId {
+        ArchetypeId::OpenAI
+    }
+
+    fn uses_native_tool_calling(&self) -> bool {
+        true
+    }
+
+    fn default_supports_vision(&self) -> bool {
+        true
+    }
+
+    fn default_model(&self) -> &'static str {
+        "gpt-4o"
+    }
+
+    fn display_name(&self) -> &'static str {
+        "OpenAI (Native Tool Calling)"
+    }
+
+    fn description(&self) -> &'static str {
+        "OpenAI native tool calling for GPT models. Same wire format as Kimi."
+    }
+
+    fn default_max_tokens(&self) -> u32 {
+        // gpt-4o rejects requests asking for more output than this
+        16384
+    }
+
+    fn default_json_mode(&self) -> JsonMode {
+        JsonMode::Schema
+    }
+
+    fn enhance_system_prompt(&self, base_prompt: &str, _tools: &[ToolDefinition]) -> String {
+        // Tools are passed via the API's `tools` parameter
+        base_prompt.to_string()
+    }
+
+    fn parse_response(&self, content: &str) -> Option<AgentResponse> {
+        // Native tool calling uses the API's tool_calls field, not text parsing
+        Some(AgentResponse {
+            body: content.to_string(),
+            tool_call: None,
+        })
+    }
+
+    fn format_tool_followup(&self, _tool_name: &str, _tool_result: &str, _success: bool) -> String {
+        // Native tool calling uses the API's message format for tool results
+        String::new()
+    }
+}
@@ -0,0 +1,286 @@
+//! Archetype profiles
+//!
+//! A profile is what a model family needs on top of the agent settings: the
+//! model to use when none is given, the output token limit, stop sequences,
+//! how tool use and JSON answers are requested, and extra system prompt
+//! instructions. Each
+//! archetype defines its defaults; operators can override them through
+//! `/api/agent-settings/archetypes/{id}`. Overrides are kept in the
+//! `archetype_profiles` table and loaded into memory at startup, so clients
+//! built from settings pick them up without a database round-trip.
+
+use super::{ArchetypeId, ArchetypeRegistry, ModelArchetype};
+use crate::db::Database;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::RwLock;
+
+/// Largest output token limit a profile accepts
+pub const MAX_PROFILE_MAX_TOKENS: u32 = 1_000_000;
+/// Most stop sequences a profile may send (OpenAI accepts up to 4)
+pub const MAX_STOP_SEQUENCES: usize = 4;
+/// Longest system prompt suffix a profile accepts, in bytes
+pub const MAX_PROMPT_SUFFIX_BYTES: usize = 4000;
+
+/// Customized profiles, set by `load_overrides` and `set_overrides`
+static OVERRIDES: RwLock<Option<HashMap<ArchetypeId, ArchetypeProfileOverrides>>> = RwLock::new(None);
+
+/// How a request asks the model to use the tools it is offered
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ToolChoiceMode {
+    /// The model must call a tool ("required", or "any" for Claude)
+    Required,
+    /// The model decides whether to call a tool
+    Auto,
+    /// Leave tool choice out of the request, for providers that reject it
+    Omit,
+}
+
+/// How the provider is asked for a JSON answer when a request has a `response_format`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum JsonMode {
+    /// `response_format` with the JSON schema
+    Schema,
+    /// `response_format: {"type": "json_object"}`; the schema is only in the prompt
+    Object,
+    /// Instructions in the prompt only, for providers without a JSON mode
+    Prompt,
+}
+
+/// Request defaults and prompt tweaks of one archetype
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ArchetypeProfile {
+    pub id: ArchetypeId,
+    pub name: String,
+    pub description: String,
+    pub uses_native_tools: bool,
+    /// Model used when the settings don't pick one
+    pub default_model: String,
+    /// Output token limit; the agent's `max_tokens` is capped at it, and it is
+    /// the default for settings saved without one
+    pub max_tokens: u32,
+    pub stop_sequences: Vec<String>,
+    pub tool_choice: ToolChoiceMode,
+    /// How structured output is requested
+    pub json_mode: JsonMode,
+    /// Whether images attached to messages are sent to the model
+    pub supports_vision: bool,
+    /// Appended to the system prompt of chat and agent runs
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub system_prompt_suffix: Option<String>,
+    /// Whether any of the defaults are overridden
+    pub customized: bool,
+}
+
+/// Fields of a profile an operator has overridden; unset fields keep the
+/// archetype's defaults
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ArchetypeProfileOverrides {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub default_model: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_tokens: Option<u32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub stop_sequences: Option<Vec<String>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tool_choice: Option<ToolChoiceMode>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub json_mode: Option<JsonMode>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub supports_vision: Option<bool>,
+    /// An empty suffix turns the archetype's default one off
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub system_prompt_suffix: Option<String>,
+}
+
+impl ArchetypeProfileOverrides {
+    pub fn is_empty(&self) -> bool {
+        *self == Self::default()
+    }
+
+    /// Check the overrides, trimming the model name and prompt suffix
+    pub fn validate(mut self) -> Result<Self, String> {
+        if let Some(model) = &mut self.default_model {
+            *model = model.trim().to_string();
+            if model.is_empty() {
+                return Err("default_model cannot be empty".to_string());
+            }
+        }
+        if self.max_tokens.is_some_and(|t| !(1..=MAX_PROFILE_MAX_TOKENS).contains(&t)) {
+            return Err(format!("max_tokens must be between 1 and {}", MAX_PROFILE_MAX_TOKENS));
+        }
+        if let Some(stops) = &self.stop_sequences {
+            if stops.len() > MAX_STOP_SEQUENCES {
+                return Err(format!("At most {} stop_sequences are allowed", MAX_STOP_SEQUENCES));
+            }
+            if stops.iter().any(|s| s.is_empty()) {
+                return Err("stop_sequences cannot contain empty strings".to_string());
+            }
+        }
+        if let Some(suffix) = &mut self.system_prompt_suffix {
+            *suffix = suffix.trim().to_string();
+            if suffix.len() > MAX_PROMPT_SUFFIX_BYTES {
+                return Err(format!(
+                    "system_prompt_suffix must be at most {} bytes",
+                    MAX_PROMPT_SUFFIX_BYTES
+                ));
+            }
+        }
+        Ok(self)
+    }
+}
+
+impl ArchetypeProfile {
+    /// The archetype's built-in profile
+    pub fn defaults(archetype: &dyn ModelArchetype) -> Self {
+        Self {
+            id: archetype.id(),
+            name: archetype.display_name().to_string(),
+            description: archetype.description().to_string(),
+            uses_native_tools: archetype.uses_native_tool_calling(),
+            default_model: archetype.default_model().to_string(),
+            max_tokens: archetype.default_max_tokens(),
+            stop_sequences: archetype.default_stop_sequences().iter().map(|s| s.to_string()).collect(),
+            tool_choice: archetype.default_tool_choice(),
+            json_mode: archetype.default_json_mode(),
+            supports_vision: archetype.default_supports_vision(),
+            system_prompt_suffix: archetype.default_prompt_suffix().map(str::to_string),
+            customized: false,
+        }
+    }
+
+    /// The profile with `overrides` applied
+    pub fn with_overrides(mut self, overrides: &ArchetypeProfileOverrides) -> Self {
+        if let Some(model) = &overrides.default_model {
+            self.default_model = model.clone();
+        }
+        if let Some(max_tokens) = overrides.max_tokens {
+            self.max_tokens = max_tokens;
+        }
+        if let Some(stops) = &overrides.stop_sequences {
+            self.stop_sequences = stops.clone();
+        }
+        if let Some(tool_choice) = overrides.tool_choice {
+            self.tool_choice = tool_choice;
+        }
+        if let Some(json_mode) = overrides.json_mode {
+            self.json_mode = json_mode;
+        }
+        if let Some(supports_vision) = overrides.supports_vision {
+            self.supports_vision = supports_vision;
+        }
+        if let Some(suffix) = &overrides.system_prompt_suffix {
+            self.system_prompt_suffix = (!suffix.is_empty()).then(|| suffix.clone());
+        }
+        self.customized = !overrides.is_empty();
+        self
+    }
+
+    /// Output token limit for a request: the agent's `max_tokens`, capped at the profile's
+    pub fn request_max_tokens(&self, max_tokens: i32) -> u32 {
+        u32::try_from(max_tokens).unwrap_or(0).clamp(1, self.max_tokens)
+    }
+
+    /// `prompt` with the profile's suffix appended
+    pub fn apply_to_prompt(&self, prompt: String) -> String {
+        match &self.system_prompt_suffix {
+            Some(suffix) => format!("{}\n\n{}", prompt, suffix),
+            None => prompt,
+        }
+    }
+}
+
+/// An archetype's profile, with the operator's overrides applied
+pub fn profile(id: ArchetypeId) -> ArchetypeProfile {
+    let registry = ArchetypeRegistry::new();
+    let archetype = registry.get(id).unwrap_or_else(|| registry.default_archetype());
+    let mut profile = ArchetypeProfile::defaults(archetype);
+    // The deployment's default model sits between the built-in one and the operator's overrides
+    if let Some(model) = crate::config::default_model() {
+        profile.default_model = model;
+    }
+    match OVERRIDES.read().unwrap().as_ref().and_then(|o| o.get(&id)) {
+        Some(overrides) => profile.with_overrides(overrides),
+        None => profile,
+    }
+}
+
+/// Every archetype's profile
+pub fn profiles() -> Vec<ArchetypeProfile> {
+    ArchetypeId::ALL.into_iter().map(profile).collect()
+}
+
+/// Replace an archetype's overrides in memory; None resets it to its defaults
+pub fn set_overrides(id: ArchetypeId, overrides: Option<ArchetypeProfileOverrides>) {
+    let mut all = OVERRIDES.write().unwrap();
+    let all = all.get_or_insert_with(HashMap::new);
+    match overrides.filter(|o| !o.is_empty()) {
+        Some(overrides) => all.insert(id, overrides),
+        None => all.remove(&id),
+    };
+}
+
+/// Load the stored overrides into memory. Returns how many archetypes are customized.
+pub async fn load_overrides(db: &Database) -> Result<usize, String> {
+    let stored = db.list_archetype_profile_overrides().await.map_err(|e| e.to_string())?;
+    let count = stored.len();
+    *OVERRIDES.write().unwrap() = Some(stored.into_iter().collect());
+    Ok(count)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ai::archetypes::deepseek::DeepSeekArchetype;
+
+    #[test]
+    fn test_overrides_replace_defaults() {
+        let defaults = ArchetypeProfile::defaults(&DeepSeekArchetype::new());
+        assert_eq!(defaults.max_tokens, 8192);
+        assert_eq!(defaults.tool_choice, ToolChoiceMode::Auto);
+        assert_eq!(defaults.json_mode, JsonMode::Object);
+        assert!(defaults.system_prompt_suffix.is_some());
+        assert!(!defaults.supports_vision);
+        assert!(!defaults.customized);
+
+        let overrides = ArchetypeProfileOverrides {
+            default_model: Some(" deepseek-reasoner ".to_string()),
+            stop_sequences: Some(vec!["</answer>".to_string()]),
+            system_prompt_suffix: Some(String::new()),
+            supports_vision: Some(true),
+            ..Default::default()
+        }
+        .validate()
+        .unwrap();
+        let profile = defaults.with_overrides(&overrides);
+        assert_eq!(profile.default_model, "deepseek-reasoner");
+        assert_eq!(profile.stop_sequences, vec!["</answer>"]);
+        assert_eq!(profile.max_tokens, 8192);
+        assert_eq!(profile.system_prompt_suffix, None);
+        assert!(profile.supports_vision);
+        assert!(profile.customized);
+
+        assert_eq!(profile.request_max_tokens(40000), 8192);
+        assert_eq!(profile.request_max_tokens(1024), 1024);
+        assert_eq!(profile.apply_to_prompt("Base".to_string()), "Base");
+    }
+
+    #[test]
+    fn test_validate_rejects_bad_overrides() {
+        let bad = [
+            ArchetypeProfileOverrides { default_model: Some(" ".to_string()), ..Default::default() },
+            ArchetypeProfileOverrides { max_tokens: Some(0), ..Default::default() },
+            ArchetypeProfileOverrides { stop_sequences: Some(vec!["a".to_string(); 5]), ..Default::default() },
+            ArchetypeProfileOverrides { stop_sequences: Some(vec![String::new()]), ..Default::default() },
+            ArchetypeProfileOverrides {
+                system_prompt_suffix: Some("x".repeat(MAX_PROMPT_SUFFIX_BYTES + 1)),
+                ..Default::default()
+            },
+        ];
+        for overrides in bad {
+            assert!(overrides.validate().is_err());
+        }
+    }
+}
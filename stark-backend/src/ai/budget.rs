@@ -0,0 +1,367 @@
+//! Per-request spending budget
+//!
+//! A single chat request can fan out into many provider calls (agentic loop
+//! iterations, retries). `BudgetTracker` accumulates token usage across all of
+//! them and tells the loop when the next call would push the request over its
+//! configured ceiling.
+//!
+//! `SessionBudget` is the hard limit across all requests of a chat session.
+//! A session that has used it up is refused outright, and otherwise each
+//! request's ceiling is lowered to what the session has left.
+
+use crate::ai::types::UsageMetadata;
+use crate::config::BudgetConfig;
+use crate::models::{AgentSettings, UsageTotals};
+use serde::Serialize;
+
+/// Price of a prompt cache read, relative to the input token price
+const CACHE_READ_PRICE_FACTOR: f64 = 0.1;
+/// Price of a prompt cache write, relative to the input token price
+const CACHE_WRITE_PRICE_FACTOR: f64 = 1.25;
+
+/// What a chat request has spent so far (returned in response metadata)
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct BudgetUsage {
+    pub input_tokens: u64,
+    pub output_tokens: u64,
+    /// Prompt tokens served from the provider's prompt cache
+    pub cache_read_tokens: u64,
+    /// Prompt tokens written to the provider's prompt cache
+    pub cache_write_tokens: u64,
+    /// Input, output and cached prompt tokens
+    pub total_tokens: u64,
+    /// Estimated spend in USD, based on the configured per-token prices
+    pub cost_usd: f64,
+    pub provider_calls: u32,
+    /// True if any call's token counts were estimated because the provider didn't report them
+    pub estimated: bool,
+    pub budget_exceeded: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_tokens: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_cost_usd: Option<f64>,
+}
+
+/// Accumulates usage across the provider calls of one chat request
+#[derive(Debug, Clone)]
+pub struct BudgetTracker {
+    config: BudgetConfig,
+    input_tokens: u64,
+    output_tokens: u64,
+    cache_read_tokens: u64,
+    cache_write_tokens: u64,
+    calls: u32,
+    estimated: bool,
+    /// (tokens, cost) of the most recent call, used to project the next one
+    last_call: (u64, f64),
+    exceeded: bool,
+}
+
+impl BudgetTracker {
+    pub fn new(config: BudgetConfig) -> Self {
+        Self {
+            config,
+            input_tokens: 0,
+            output_tokens: 0,
+            cache_read_tokens: 0,
+            cache_write_tokens: 0,
+            calls: 0,
+            estimated: false,
+            last_call: (0, 0.0),
+            exceeded: false,
+        }
+    }
+
+    /// Record a completed provider call. Provider-reported token counts are
+    /// preferred; the estimates are used for whatever the provider left out.
+    pub fn record(
+        &mut self,
+        usage: Option<&UsageMetadata>,
+        estimated_input: u64,
+        estimated_output: u64,
+    ) {
+        let reported_input = usage.and_then(|u| u.input_tokens).map(u64::from);
+        let reported_output = usage.and_then(|u| u.output_tokens).map(u64::from);
+        if reported_input.is_none() || reported_output.is_none() {
+            self.estimated = true;
+        }
+
+        let input = reported_input.unwrap_or(estimated_input);
+        let output = reported_output.unwrap_or(estimated_output);
+        let cache_read = usage.and_then(|u| u.cache_read_input_tokens).map_or(0, u64::from);
+        let cache_write = usage.and_then(|u| u.cache_creation_input_tokens).map_or(0, u64::from);
+
+        self.input_tokens += input;
+        self.output_tokens += output;
+        self.cache_read_tokens += cache_read;
+        self.cache_write_tokens += cache_write;
+        self.calls += 1;
+        self.last_call = (
+            input + output + cache_read + cache_write,
+            self.cost_of(input, output, cache_read, cache_write),
+        );
+    }
+
+    fn cost_of(&self, input: u64, output: u64, cache_read: u64, cache_write: u64) -> f64 {
+        let prompt = input as f64
+            + cache_read as f64 * CACHE_READ_PRICE_FACTOR
+            + cache_write as f64 * CACHE_WRITE_PRICE_FACTOR;
+        (prompt * self.config.usd_per_million_input_tokens
+            + output as f64 * self.config.usd_per_million_output_tokens)
+            / 1_000_000.0
+    }
+
+    pub fn total_tokens(&self) -> u64 {
+        self.input_tokens + self.output_tokens + self.cache_read_tokens + self.cache_write_tokens
+    }
+
+    pub fn cost_usd(&self) -> f64 {
+        self.cost_of(
+            self.input_tokens,
+            self.output_tokens,
+            self.cache_read_tokens,
+            self.cache_write_tokens,
+        )
+    }
+
+    /// Whether another call, projected to cost at least as much as the previous
+    /// one (the conversation only grows), would take the request over budget.
+    pub fn next_call_would_exceed(&self) -> bool {
+        if self.calls == 0 {
+            return false;
+        }
+        let (tokens, cost) = self.last_call;
+
+        if let Some(max_tokens) = self.config.max_tokens
+            && self.total_tokens() + tokens > max_tokens
+        {
+            return true;
+        }
+        if let Some(max_cost) = self.config.max_cost_usd
+            && self.cost_usd() + cost > max_cost
+        {
+            return true;
+        }
+        false
+    }
+
+    /// Record that the loop stopped early because of the budget
+    pub fn mark_exceeded(&mut self) {
+        self.exceeded = true;
+    }
+
+    pub fn is_exceeded(&self) -> bool {
+        self.exceeded
+    }
+
+    /// Note appended to the partial answer when the loop was stopped by the budget
+    pub fn exceeded_note(&self) -> String {
+        let limit = match (self.config.max_tokens, self.config.max_cost_usd) {
+            (Some(tokens), Some(usd)) => format!("{} tokens / ${:.4}", tokens, usd),
+            (Some(tokens), None) => format!("{} tokens", tokens),
+            (None, Some(usd)) => format!("${:.4}", usd),
+            (None, None) => "unlimited".to_string(),
+        };
+        format!(
+            "⚠️ Budget exceeded: stopped after {} provider calls ({} tokens, ~${:.4}) because the next call would exceed this request's limit ({}). The answer above may be incomplete.",
+            self.calls,
+            self.total_tokens(),
+            self.cost_usd(),
+            limit
+        )
+    }
+
+    pub fn usage(&self) -> BudgetUsage {
+        BudgetUsage {
+            input_tokens: self.input_tokens,
+            output_tokens: self.output_tokens,
+            cache_read_tokens: self.cache_read_tokens,
+            cache_write_tokens: self.cache_write_tokens,
+            total_tokens: self.total_tokens(),
+            cost_usd: self.cost_usd(),
+            provider_calls: self.calls,
+            estimated: self.estimated,
+            budget_exceeded: self.exceeded,
+            max_tokens: self.config.max_tokens,
+            max_cost_usd: self.config.max_cost_usd,
+        }
+    }
+}
+
+/// Hard spending limit for one chat session, from the active agent profile
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SessionBudget {
+    pub max_tokens: Option<i64>,
+    pub max_cost_usd: Option<f64>,
+}
+
+impl SessionBudget {
+    pub fn from_settings(settings: &AgentSettings) -> Self {
+        Self {
+            max_tokens: settings.session_budget_max_tokens.filter(|&t| t > 0),
+            max_cost_usd: settings
+                .session_budget_max_usd
+                .filter(|&usd| usd.is_finite() && usd > 0.0),
+        }
+    }
+
+    pub fn is_unlimited(&self) -> bool {
+        self.max_tokens.is_none() && self.max_cost_usd.is_none()
+    }
+
+    /// Error returned instead of running a request when the session has nothing left
+    pub fn exhausted_reason(&self, spent: &UsageTotals) -> Option<String> {
+        if let Some(max_tokens) = self.max_tokens.filter(|&max| spent.total_tokens >= max) {
+            return Some(format!(
+                "Session budget exhausted: {} of {} tokens used. Start a new session or raise the session budget in agent settings.",
+                spent.total_tokens, max_tokens
+            ));
+        }
+        if let Some(max_usd) = self.max_cost_usd.filter(|&max| spent.cost_usd >= max) {
+            return Some(format!(
+                "Session budget exhausted: ~${:.4} of ${:.4} spent. Start a new session or raise the session budget in agent settings.",
+                spent.cost_usd, max_usd
+            ));
+        }
+        None
+    }
+
+    /// Lower a request's ceiling to what remains of the session budget
+    pub fn limit_request(&self, mut config: BudgetConfig, spent: &UsageTotals) -> BudgetConfig {
+        if let Some(max_tokens) = self.max_tokens {
+            let remaining = max_tokens.saturating_sub(spent.total_tokens).max(0) as u64;
+            config.max_tokens = Some(config.max_tokens.map_or(remaining, |t| t.min(remaining)));
+        }
+        if let Some(max_usd) = self.max_cost_usd {
+            let remaining = (max_usd - spent.cost_usd).max(0.0);
+            config.max_cost_usd = Some(config.max_cost_usd.map_or(remaining, |usd| usd.min(remaining)));
+        }
+        config
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config(max_tokens: Option<u64>, max_cost_usd: Option<f64>) -> BudgetConfig {
+        BudgetConfig {
+            max_tokens,
+            max_cost_usd,
+            usd_per_million_input_tokens: 3.0,
+            usd_per_million_output_tokens: 15.0,
+        }
+    }
+
+    fn reported(input: u32, output: u32) -> UsageMetadata {
+        UsageMetadata {
+            input_tokens: Some(input),
+            output_tokens: Some(output),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_accumulates_reported_usage_and_cost() {
+        let mut budget = BudgetTracker::new(config(None, None));
+        budget.record(Some(&reported(1_000, 200)), 0, 0);
+        budget.record(Some(&reported(2_000, 300)), 0, 0);
+
+        let usage = budget.usage();
+        assert_eq!(usage.input_tokens, 3_000);
+        assert_eq!(usage.output_tokens, 500);
+        assert_eq!(usage.total_tokens, 3_500);
+        assert_eq!(usage.provider_calls, 2);
+        assert!(!usage.estimated);
+        assert!((usage.cost_usd - 0.0165).abs() < 1e-9);
+        assert!(!budget.next_call_would_exceed());
+    }
+
+    #[test]
+    fn test_cached_prompt_tokens_are_priced_separately() {
+        let mut budget = BudgetTracker::new(config(None, None));
+        budget.record(
+            Some(&UsageMetadata {
+                cache_creation_input_tokens: Some(10_000),
+                ..reported(100, 200)
+            }),
+            0,
+            0,
+        );
+        budget.record(
+            Some(&UsageMetadata {
+                cache_read_input_tokens: Some(10_000),
+                ..reported(100, 200)
+            }),
+            0,
+            0,
+        );
+
+        let usage = budget.usage();
+        assert_eq!(usage.input_tokens, 200);
+        assert_eq!(usage.cache_write_tokens, 10_000);
+        assert_eq!(usage.cache_read_tokens, 10_000);
+        assert_eq!(usage.total_tokens, 20_600);
+        // 200 input + 12,500 write-equivalent + 1,000 read-equivalent at $3, 400 output at $15
+        assert!((usage.cost_usd - 0.0471).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_falls_back_to_estimates() {
+        let mut budget = BudgetTracker::new(config(None, None));
+        budget.record(None, 400, 100);
+        assert_eq!(budget.total_tokens(), 500);
+        assert!(budget.usage().estimated);
+    }
+
+    #[test]
+    fn test_projects_next_call_against_token_ceiling() {
+        let mut budget = BudgetTracker::new(config(Some(2_500), None));
+        assert!(!budget.next_call_would_exceed());
+
+        budget.record(Some(&reported(1_000, 100)), 0, 0);
+        // 1100 spent + 1100 projected fits under 2500
+        assert!(!budget.next_call_would_exceed());
+
+        budget.record(Some(&reported(1_000, 100)), 0, 0);
+        // 2200 spent + 1100 projected does not
+        assert!(budget.next_call_would_exceed());
+    }
+
+    #[test]
+    fn test_projects_next_call_against_cost_ceiling() {
+        let mut budget = BudgetTracker::new(config(None, Some(0.01)));
+        budget.record(Some(&reported(2_000, 200)), 0, 0); // $0.009
+        assert!(budget.next_call_would_exceed());
+
+        budget.mark_exceeded();
+        assert!(budget.usage().budget_exceeded);
+        assert!(budget.exceeded_note().contains("Budget exceeded"));
+    }
+
+    #[test]
+    fn test_session_budget_limits_requests_to_what_is_left() {
+        let session = SessionBudget {
+            max_tokens: Some(10_000),
+            max_cost_usd: Some(1.0),
+        };
+        let spent = UsageTotals {
+            requests: 3,
+            total_tokens: 8_000,
+            cost_usd: 0.25,
+            ..Default::default()
+        };
+
+        assert!(session.exhausted_reason(&spent).is_none());
+        let limited = session.limit_request(config(Some(5_000), None), &spent);
+        assert_eq!(limited.max_tokens, Some(2_000));
+        assert_eq!(limited.max_cost_usd, Some(0.75));
+
+        let spent = UsageTotals {
+            total_tokens: 10_000,
+            ..spent
+        };
+        assert!(session.exhausted_reason(&spent).unwrap().contains("Session budget exhausted"));
+        assert!(SessionBudget::default().is_unlimited());
+    }
+}
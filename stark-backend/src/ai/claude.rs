@@ -1,9 +1,14 @@
 use crate::ai::types::{
-    AiError, AiResponse, ClaudeContentBlock, ClaudeMessage as TypedClaudeMessage,
-    ClaudeMessageContent, ClaudeTool, ThinkingLevel, ToolCall, ToolResponse,
+    AiError, AiResponse, CacheControl, ClaudeContentBlock, ClaudeMessage as TypedClaudeMessage,
+    ClaudeMessageContent, ClaudeTool, ThinkingBlock, ThinkingLevel, ToolCall, UsageMetadata,
 };
+use crate::ai::archetypes::{ArchetypeProfile, ToolChoiceMode};
+use crate::ai::provider::LlmProvider;
+use crate::ai::retry::{self, RetryPolicy};
+use crate::ai::vision::without_images;
 use crate::ai::{Message, MessageRole};
 use crate::gateway::events::EventBroadcaster;
+use crate::models::ModelOverrides;
 use crate::gateway::protocol::GatewayEvent;
 use crate::tools::ToolDefinition;
 use reqwest::{header, Client};
@@ -17,12 +22,25 @@ pub struct ClaudeClient {
     client: Client,
     endpoint: String,
     model: String,
+    max_tokens: u32,
+    /// Sampling temperature; provider default when unset
+    temperature: Option<f32>,
     /// Thinking budget in tokens (0 = disabled)
     thinking_budget: AtomicU32,
+    /// Seed requested by the caller; the Messages API has no seed so it is only reported back
+    requested_seed: Option<u64>,
     /// Optional broadcaster for emitting retry events
     broadcaster: Option<Arc<EventBroadcaster>>,
     /// Channel ID for events
     channel_id: Option<i64>,
+    /// Backoff for transient errors
+    retry_policy: RetryPolicy,
+    /// Sent with every request, from the archetype profile
+    stop_sequences: Vec<String>,
+    /// How requests with tools ask for tool use
+    tool_choice: ToolChoiceMode,
+    /// Send attached images to the model, from the archetype profile
+    supports_vision: bool,
 }
 
 impl Clone for ClaudeClient {
@@ -31,9 +49,16 @@ impl Clone for ClaudeClient {
             client: self.client.clone(),
             endpoint: self.endpoint.clone(),
             model: self.model.clone(),
+            max_tokens: self.max_tokens,
+            temperature: self.temperature,
             thinking_budget: AtomicU32::new(self.thinking_budget.load(Ordering::SeqCst)),
+            requested_seed: self.requested_seed,
             broadcaster: self.broadcaster.clone(),
             channel_id: self.channel_id,
+            retry_policy: self.retry_policy,
+            stop_sequences: self.stop_sequences.clone(),
+            tool_choice: self.tool_choice,
+            supports_vision: self.supports_vision,
         }
     }
 }
@@ -46,23 +71,29 @@ struct ThinkingConfig {
     budget_tokens: u32,
 }
 
+/// Text block of the `system` parameter
+#[derive(Debug, Serialize)]
+struct SystemBlock {
+    #[serde(rename = "type")]
+    block_type: &'static str,
+    text: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    cache_control: Option<CacheControl>,
+}
+
 #[derive(Debug, Serialize)]
 struct ClaudeCompletionRequest {
     model: String,
-    messages: Vec<SimpleClaudeMessage>,
+    messages: Vec<TypedClaudeMessage>,
     max_tokens: u32,
     #[serde(skip_serializing_if = "Option::is_none")]
-    system: Option<String>,
+    system: Option<Vec<SystemBlock>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    temperature: Option<f32>,
     #[serde(skip_serializing_if = "Option::is_none")]
     thinking: Option<ThinkingConfig>,
 }
 
-#[derive(Debug, Serialize)]
-struct SimpleClaudeMessage {
-    role: String,
-    content: String,
-}
-
 /// Tool choice options for Claude API
 #[derive(Debug, Clone, Serialize)]
 #[serde(tag = "type")]
@@ -83,13 +114,17 @@ struct ClaudeToolRequest {
     messages: Vec<TypedClaudeMessage>,
     max_tokens: u32,
     #[serde(skip_serializing_if = "Option::is_none")]
-    system: Option<String>,
+    system: Option<Vec<SystemBlock>>,
     #[serde(skip_serializing_if = "Option::is_none")]
     tools: Option<Vec<ClaudeTool>>,
     #[serde(skip_serializing_if = "Option::is_none")]
     tool_choice: Option<ToolChoice>,
     #[serde(skip_serializing_if = "Option::is_none")]
+    temperature: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     thinking: Option<ThinkingConfig>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    stop_sequences: Vec<String>,
 }
 
 #[derive(Debug, Deserialize)]
@@ -97,6 +132,20 @@ struct ClaudeCompletionResponse {
     content: Vec<ClaudeResponseContent>,
     #[serde(default)]
     stop_reason: Option<String>,
+    #[serde(default)]
+    usage: Option<ClaudeUsage>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ClaudeUsage {
+    #[serde(default)]
+    input_tokens: u32,
+    #[serde(default)]
+    output_tokens: u32,
+    #[serde(default)]
+    cache_creation_input_tokens: Option<u32>,
+    #[serde(default)]
+    cache_read_input_tokens: Option<u32>,
 }
 
 #[derive(Debug, Deserialize)]
@@ -111,6 +160,82 @@ struct ClaudeResponseContent {
     name: Option<String>,
     #[serde(default)]
     input: Option<Value>,
+    #[serde(default)]
+    thinking: Option<String>,
+    #[serde(default)]
+    signature: Option<String>,
+    #[serde(default)]
+    data: Option<String>,
+}
+
+/// What a tool-enabled response amounts to once its blocks are read
+#[derive(Debug)]
+struct ToolResponseParts {
+    text: String,
+    tool_calls: Vec<ToolCall>,
+    thinking: Vec<ThinkingBlock>,
+}
+
+/// Read text, tool_use and thinking blocks in order. A response cut off at
+/// `max_tokens` can end in a tool_use with incomplete input, which is dropped;
+/// if that leaves nothing to act on the call fails so the caller can adjust.
+fn read_tool_response(
+    content: Vec<ClaudeResponseContent>,
+    stop_reason: Option<&str>,
+    max_tokens: u32,
+) -> Result<ToolResponseParts, AiError> {
+    let mut parts = ToolResponseParts { text: String::new(), tool_calls: Vec::new(), thinking: Vec::new() };
+    let ends_in_tool_use = content.last().is_some_and(|c| c.content_type == "tool_use");
+
+    for content in content {
+        match content.content_type.as_str() {
+            "text" => {
+                if let Some(text) = content.text {
+                    parts.text.push_str(&text);
+                }
+            }
+            "tool_use" => {
+                if let (Some(id), Some(name), Some(input)) = (content.id, content.name, content.input) {
+                    let block = ClaudeContentBlock::ToolUse { id, name, input };
+                    parts.tool_calls.extend(ClaudeClient::tool_call_from_wire(&block, parts.tool_calls.len()));
+                }
+            }
+            "thinking" => {
+                if let (Some(thinking), Some(signature)) = (content.thinking, content.signature) {
+                    parts.thinking.push(ThinkingBlock::Thinking { thinking, signature });
+                }
+            }
+            "redacted_thinking" => {
+                if let Some(data) = content.data {
+                    parts.thinking.push(ThinkingBlock::RedactedThinking { data });
+                }
+            }
+            other => tracing::debug!("[CLAUDE] Ignoring '{}' content block", other),
+        }
+    }
+
+    match stop_reason {
+        Some("max_tokens") if ends_in_tool_use => {
+            if let Some(call) = parts.tool_calls.pop() {
+                tracing::warn!(
+                    "[CLAUDE] Response hit max_tokens ({}) inside a '{}' call, dropping it",
+                    max_tokens,
+                    call.name
+                );
+                if parts.tool_calls.is_empty() && parts.text.trim().is_empty() {
+                    return Err(AiError::new(format!(
+                        "Claude ran out of output tokens (max_tokens {}) while writing a '{}' call",
+                        max_tokens, call.name
+                    )));
+                }
+            }
+        }
+        Some("refusal") if parts.text.trim().is_empty() => {
+            parts.text = "Claude declined to respond to this request.".to_string();
+        }
+        _ => {}
+    }
+    Ok(parts)
 }
 
 #[derive(Debug, Deserialize)]
@@ -123,8 +248,120 @@ struct ClaudeError {
     message: String,
 }
 
+/// Version and beta feature headers sent with every Anthropic request
+#[derive(Debug, Clone, PartialEq)]
+pub struct AnthropicHeaders {
+    /// Value of the `anthropic-version` header
+    pub version: String,
+    /// Flags joined into the `anthropic-beta` header (omitted when empty)
+    pub beta: Vec<String>,
+}
+
+impl AnthropicHeaders {
+    /// Build from `STARK_ANTHROPIC_VERSION` and the comma-separated `STARK_ANTHROPIC_BETA`
+    pub fn from_env() -> Result<Self, String> {
+        let beta = match crate::config::anthropic_beta() {
+            Some(flags) if !flags.trim().is_empty() => {
+                flags.split(',').map(|f| f.trim().to_string()).collect()
+            }
+            _ => Vec::new(),
+        };
+        Self::new(crate::config::anthropic_version(), beta)
+    }
+
+    /// Create headers config, rejecting empty version or beta flag strings
+    pub fn new(version: impl Into<String>, beta: Vec<String>) -> Result<Self, String> {
+        let version = version.into().trim().to_string();
+        if version.is_empty() {
+            return Err("anthropic-version must be a non-empty string".to_string());
+        }
+        let beta: Vec<String> = beta.into_iter().map(|f| f.trim().to_string()).collect();
+        if beta.iter().any(|f| f.is_empty()) {
+            return Err("anthropic-beta flags must be non-empty strings".to_string());
+        }
+        Ok(Self { version, beta })
+    }
+
+    fn apply(&self, headers: &mut header::HeaderMap) -> Result<(), String> {
+        let version = header::HeaderValue::from_str(&self.version)
+            .map_err(|e| format!("Invalid anthropic-version header: {}", e))?;
+        headers.insert("anthropic-version", version);
+
+        if !self.beta.is_empty() {
+            let beta = header::HeaderValue::from_str(&self.beta.join(","))
+                .map_err(|e| format!("Invalid anthropic-beta header: {}", e))?;
+            headers.insert("anthropic-beta", beta);
+        }
+        Ok(())
+    }
+}
+
+/// Move system messages into `system` blocks, the last one marked as a cache
+/// breakpoint so the prompt prefix (tools + system) is cached across the
+/// iterations of an agent run
+fn split_system_messages(messages: Vec<Message>) -> (Option<Vec<SystemBlock>>, Vec<Message>) {
+    let (system, messages): (Vec<Message>, Vec<Message>) = messages
+        .into_iter()
+        .partition(|m| m.role == MessageRole::System);
+
+    let mut blocks: Vec<SystemBlock> = system
+        .into_iter()
+        .filter(|m| !m.content.trim().is_empty())
+        .map(|m| SystemBlock {
+            block_type: "text",
+            text: m.content,
+            cache_control: None,
+        })
+        .collect();
+    if let Some(last) = blocks.last_mut() {
+        last.cache_control = Some(CacheControl::Ephemeral);
+    }
+    ((!blocks.is_empty()).then_some(blocks), messages)
+}
+
+/// A message in the Messages API format. Images go ahead of the text as image
+/// blocks, or are replaced by a note when the model can't view them.
+fn claude_message(message: Message, supports_vision: bool) -> TypedClaudeMessage {
+    let role = message.role.to_string();
+    if message.images.is_empty() || !supports_vision {
+        let content = ClaudeMessageContent::Text(without_images(&message.content, &message.images));
+        return TypedClaudeMessage { role, content };
+    }
+    let mut blocks: Vec<ClaudeContentBlock> = message.images.iter().map(ClaudeContentBlock::image).collect();
+    blocks.push(ClaudeContentBlock::text(message.content));
+    TypedClaudeMessage { role, content: ClaudeMessageContent::Blocks(blocks) }
+}
+
+/// Convert tool definitions to Claude format, with a cache breakpoint after the
+/// last one so the tool list stays cached even when the system prompt changes
+fn claude_tools(tools: Vec<ToolDefinition>) -> Vec<ClaudeTool> {
+    let mut claude_tools: Vec<ClaudeTool> = tools
+        .into_iter()
+        .map(|t| ClaudeTool {
+            name: t.name,
+            description: t.description,
+            input_schema: serde_json::to_value(t.input_schema).unwrap_or_default(),
+            cache_control: None,
+        })
+        .collect();
+    if let Some(last) = claude_tools.last_mut() {
+        last.cache_control = Some(CacheControl::Ephemeral);
+    }
+    claude_tools
+}
+
 impl ClaudeClient {
+    /// Create a client using the globally configured Anthropic version/beta headers
     pub fn new(api_key: &str, endpoint: Option<&str>, model: Option<&str>) -> Result<Self, String> {
+        Self::new_with_headers(api_key, endpoint, model, &AnthropicHeaders::from_env()?)
+    }
+
+    pub fn new_with_headers(
+        api_key: &str,
+        endpoint: Option<&str>,
+        model: Option<&str>,
+        anthropic_headers: &AnthropicHeaders,
+    ) -> Result<Self, String> {
         let mut headers = header::HeaderMap::new();
         headers.insert(
             header::CONTENT_TYPE,
@@ -134,10 +371,7 @@ impl ClaudeClient {
         let auth_value = header::HeaderValue::from_str(api_key)
             .map_err(|e| format!("Invalid API key format: {}", e))?;
         headers.insert("x-api-key", auth_value);
-        headers.insert(
-            "anthropic-version",
-            header::HeaderValue::from_static("2023-06-01"),
-        );
+        anthropic_headers.apply(&mut headers)?;
 
         let client = Client::builder()
             .default_headers(headers)
@@ -151,12 +385,41 @@ impl ClaudeClient {
                 .unwrap_or("https://api.anthropic.com/v1/messages")
                 .to_string(),
             model: model.unwrap_or("claude-sonnet-4-20250514").to_string(),
+            max_tokens: 4096,
+            temperature: None,
             thinking_budget: AtomicU32::new(0),
+            requested_seed: None,
             broadcaster: None,
             channel_id: None,
+            retry_policy: RetryPolicy::default(),
+            stop_sequences: Vec::new(),
+            tool_choice: ToolChoiceMode::Required,
+            supports_vision: true,
         })
     }
 
+    /// Apply an archetype profile's stop sequences, tool choice and vision support
+    pub fn with_profile(mut self, profile: &ArchetypeProfile) -> Self {
+        self.stop_sequences = profile.stop_sequences.clone();
+        self.tool_choice = profile.tool_choice;
+        self.supports_vision = profile.supports_vision;
+        self
+    }
+
+    /// Replace the default backoff for transient errors
+    pub fn with_retry_policy(mut self, retry_policy: RetryPolicy) -> Self {
+        self.retry_policy = retry_policy;
+        self
+    }
+
+    pub fn endpoint(&self) -> &str {
+        &self.endpoint
+    }
+
+    pub fn model(&self) -> &str {
+        &self.model
+    }
+
     /// Set the broadcaster for emitting retry events
     pub fn with_broadcaster(mut self, broadcaster: Arc<EventBroadcaster>, channel_id: i64) -> Self {
         self.broadcaster = Some(broadcaster);
@@ -164,6 +427,30 @@ impl ClaudeClient {
         self
     }
 
+    /// Record a requested sampling seed. Anthropic does not support seeding, so the
+    /// seed is never sent; responses carry a note explaining it was ignored.
+    pub fn with_seed(mut self, seed: Option<u64>) -> Self {
+        self.requested_seed = seed;
+        self
+    }
+
+    /// Apply per-request model parameters
+    pub fn with_overrides(mut self, overrides: &ModelOverrides) -> Self {
+        if let Some(model) = &overrides.model {
+            self.model = model.clone();
+        }
+        if let Some(max_tokens) = overrides.max_tokens {
+            self.max_tokens = max_tokens;
+        }
+        self.temperature = overrides.temperature.or(self.temperature);
+        self
+    }
+
+    /// Temperature to send; extended thinking only accepts the default
+    fn request_temperature(&self, thinking: &Option<ThinkingConfig>) -> Option<f32> {
+        if thinking.is_some() { None } else { self.temperature }
+    }
+
     /// Emit a retry event if broadcaster is configured
     fn emit_retry_event(&self, attempt: u32, max_attempts: u32, wait_seconds: u64, error: &str) {
         if let (Some(broadcaster), Some(channel_id)) = (&self.broadcaster, self.channel_id) {
@@ -182,7 +469,7 @@ impl ClaudeClient {
     pub fn set_thinking_level(&self, level: ThinkingLevel) {
         let budget = level.budget_tokens().unwrap_or(0);
         self.thinking_budget.store(budget, Ordering::SeqCst);
-        log::info!("Claude thinking level set to {} (budget: {} tokens)", level, budget);
+        tracing::info!("Claude thinking level set to {} (budget: {} tokens)", level, budget);
     }
 
     /// Get the current thinking budget
@@ -204,60 +491,46 @@ impl ClaudeClient {
     }
 
     pub async fn generate_text(&self, messages: Vec<Message>) -> Result<String, String> {
-        // Extract system message if present
-        let mut system_message = None;
-        let filtered_messages: Vec<Message> = messages
-            .into_iter()
-            .filter(|m| {
-                if m.role == MessageRole::System {
-                    system_message = Some(m.content.clone());
-                    false
-                } else {
-                    true
-                }
-            })
-            .collect();
+        let (system, filtered_messages) = split_system_messages(messages);
 
-        let api_messages: Vec<SimpleClaudeMessage> = filtered_messages
+        let api_messages: Vec<TypedClaudeMessage> = filtered_messages
             .into_iter()
-            .map(|m| SimpleClaudeMessage {
-                role: m.role.to_string(),
-                content: m.content,
-            })
+            .map(|m| claude_message(m, self.supports_vision))
             .collect();
 
         let thinking = self.build_thinking_config();
         let request = ClaudeCompletionRequest {
             model: self.model.clone(),
             messages: api_messages,
-            max_tokens: 4096,
-            system: system_message,
+            max_tokens: self.max_tokens,
+            system,
+            temperature: self.request_temperature(&thinking),
             thinking,
         };
 
-        log::debug!("Sending request to Claude API: {:?}", request);
+        tracing::debug!("Sending request to Claude API: {:?}", request);
 
-        // Retry configuration for transient errors
-        const MAX_RETRIES: u32 = 3;
-        const BASE_DELAY_MS: u64 = 2000;
+        // Retry transient errors per the client's retry policy
+        let max_retries = self.retry_policy.max_retries;
+        let mut retry_after: Option<Duration> = None;
 
         let mut last_error: Option<String> = None;
         let mut response_data_opt: Option<ClaudeCompletionResponse> = None;
 
-        for attempt in 0..=MAX_RETRIES {
+        for attempt in 0..=max_retries {
             if attempt > 0 {
-                let delay_ms = BASE_DELAY_MS * (1 << (attempt - 1));
+                let delay_ms = self.retry_policy.delay(attempt, retry_after).as_millis() as u64;
                 let wait_secs = delay_ms / 1000;
-                log::warn!(
+                tracing::warn!(
                     "[CLAUDE] Retry attempt {}/{} after {}ms delay",
                     attempt,
-                    MAX_RETRIES,
+                    max_retries,
                     delay_ms
                 );
                 // Emit retry event to frontend
                 self.emit_retry_event(
                     attempt,
-                    MAX_RETRIES,
+                    max_retries,
                     wait_secs,
                     last_error.as_deref().unwrap_or("Unknown error"),
                 );
@@ -275,8 +548,8 @@ impl ClaudeClient {
                 Ok(r) => r,
                 Err(e) => {
                     last_error = Some(format!("Claude API request failed: {}", e));
-                    if attempt < MAX_RETRIES {
-                        log::warn!("[CLAUDE] Request failed (attempt {}): {}, will retry", attempt + 1, e);
+                    if attempt < max_retries {
+                        tracing::warn!("[CLAUDE] Request failed (attempt {}): {}, will retry", attempt + 1, e);
                         continue;
                     }
                     return Err(last_error.unwrap());
@@ -285,7 +558,8 @@ impl ClaudeClient {
 
             let status = response.status();
             let status_code = status.as_u16();
-            let is_retryable = matches!(status_code, 429 | 502 | 503 | 504);
+            let is_retryable = retry::is_retryable_status(status_code);
+            retry_after = retry::retry_after(response.headers());
 
             if !status.is_success() {
                 let error_text = response.text().await.unwrap_or_default();
@@ -301,8 +575,8 @@ impl ClaudeClient {
                     error_text.contains("network error")
                 );
 
-                if (is_retryable || is_transient_402) && attempt < MAX_RETRIES {
-                    log::warn!(
+                if (is_retryable || is_transient_402) && attempt < max_retries {
+                    tracing::warn!(
                         "[CLAUDE] Received retryable status {} (attempt {}), will retry",
                         status,
                         attempt + 1
@@ -354,89 +628,71 @@ impl ClaudeClient {
         tool_messages: Vec<TypedClaudeMessage>,
         tools: Vec<ToolDefinition>,
     ) -> Result<AiResponse, AiError> {
-        // Extract system message if present
-        let mut system_message = None;
-        let filtered_messages: Vec<Message> = messages
-            .into_iter()
-            .filter(|m| {
-                if m.role == MessageRole::System {
-                    system_message = Some(m.content.clone());
-                    false
-                } else {
-                    true
-                }
-            })
-            .collect();
+        let (system, filtered_messages) = split_system_messages(messages);
 
         // Convert regular messages to typed messages
         let mut api_messages: Vec<TypedClaudeMessage> = filtered_messages
             .into_iter()
-            .map(|m| TypedClaudeMessage {
-                role: m.role.to_string(),
-                content: ClaudeMessageContent::Text(m.content),
-            })
+            .map(|m| claude_message(m, self.supports_vision))
             .collect();
 
         // Add tool messages (assistant tool_use + user tool_result pairs)
         api_messages.extend(tool_messages);
 
-        // Convert tool definitions to Claude format
-        let claude_tools: Vec<ClaudeTool> = tools
-            .into_iter()
-            .map(|t| ClaudeTool {
-                name: t.name,
-                description: t.description,
-                input_schema: serde_json::to_value(t.input_schema).unwrap_or_default(),
-            })
-            .collect();
+        let claude_tools = claude_tools(tools);
 
         let thinking = self.build_thinking_config();
         let has_tools = !claude_tools.is_empty();
         let request = ClaudeToolRequest {
             model: self.model.clone(),
             messages: api_messages,
-            max_tokens: 4096,
-            system: system_message,
+            max_tokens: self.max_tokens,
+            system,
+            temperature: self.request_temperature(&thinking),
             tools: if has_tools {
                 Some(claude_tools)
             } else {
                 None
             },
-            // Force tool use when tools are available
-            tool_choice: if has_tools {
-                Some(ToolChoice::Any)
-            } else {
-                None
+            // Force tool use when tools are available, unless the profile says otherwise.
+            // The API rejects forced tool use together with extended thinking.
+            tool_choice: match self.tool_choice {
+                _ if !has_tools => None,
+                ToolChoiceMode::Required if thinking.is_some() => Some(ToolChoice::Auto),
+                ToolChoiceMode::Required => Some(ToolChoice::Any),
+                ToolChoiceMode::Auto => Some(ToolChoice::Auto),
+                ToolChoiceMode::Omit => None,
             },
             thinking,
+            stop_sequences: self.stop_sequences.clone(),
         };
 
-        log::debug!(
+        tracing::debug!(
             "Sending tool request to Claude API: {}",
             serde_json::to_string_pretty(&request).unwrap_or_default()
         );
 
-        // Retry configuration for transient errors
-        const MAX_RETRIES: u32 = 3;
-        const BASE_DELAY_MS: u64 = 2000;
+        // Retry transient errors per the client's retry policy
+        let max_retries = self.retry_policy.max_retries;
+        let mut retry_after: Option<Duration> = None;
 
         let mut last_error: Option<(String, Option<u16>)> = None;
         let mut response_data_opt: Option<ClaudeCompletionResponse> = None;
 
-        for attempt in 0..=MAX_RETRIES {
+        for attempt in 0..=max_retries {
             if attempt > 0 {
-                let delay_ms = BASE_DELAY_MS * (1 << (attempt - 1));
+                let delay_ms = self.retry_policy.delay(attempt, retry_after).as_millis() as u64;
                 let wait_secs = delay_ms / 1000;
-                log::warn!(
+                tracing::warn!(
                     "[CLAUDE] Tool request retry attempt {}/{} after {}ms delay",
                     attempt,
-                    MAX_RETRIES,
+                    max_retries,
                     delay_ms
                 );
                 // Emit retry event to frontend
                 self.emit_retry_event(
                     attempt,
-                    MAX_RETRIES,
+                    max_retries,
                     wait_secs,
                     last_error.as_ref().map(|(m, _)| m.as_str()).unwrap_or("Unknown error"),
                 );
@@ -454,8 +710,8 @@ impl ClaudeClient {
                 Ok(r) => r,
                 Err(e) => {
                     last_error = Some((format!("Claude API request failed: {}", e), None));
-                    if attempt < MAX_RETRIES {
-                        log::warn!("[CLAUDE] Tool request failed (attempt {}): {}, will retry", attempt + 1, e);
+                    if attempt < max_retries {
+                        tracing::warn!("[CLAUDE] Tool request failed (attempt {}): {}, will retry", attempt + 1, e);
                         continue;
                     }
                     let (msg, code) = last_error.unwrap();
@@ -468,7 +724,8 @@ impl ClaudeClient {
 
             let status = response.status();
             let status_code = status.as_u16();
-            let is_retryable = matches!(status_code, 429 | 502 | 503 | 504);
+            let is_retryable = retry::is_retryable_status(status_code);
+            retry_after = retry::retry_after(response.headers());
 
             if !status.is_success() {
                 let error_text = response.text().await.unwrap_or_default();
@@ -484,8 +741,8 @@ impl ClaudeClient {
                     error_text.contains("network error")
                 );
 
-                if (is_retryable || is_transient_402) && attempt < MAX_RETRIES {
-                    log::warn!(
+                if (is_retryable || is_transient_402) && attempt < max_retries {
+                    tracing::warn!(
                         "[CLAUDE] Tool request received retryable status {} (attempt {}), will retry",
                         status,
                         attempt + 1
@@ -500,7 +757,7 @@ impl ClaudeClient {
                     format!("Claude API returned error status: {}, body: {}", status, error_text)
                 };
 
-                return Err(AiError::with_status(error_msg, status_code));
+                return Err(AiError::with_status(error_msg, status_code).with_retry_after(retry_after));
             }
 
             response_data_opt = Some(response
@@ -518,68 +775,177 @@ impl ClaudeClient {
             }
         })?;
 
-        // Parse the response content
-        let mut text_content = String::new();
-        let mut tool_calls = Vec::new();
-
-        for content in response_data.content {
-            match content.content_type.as_str() {
-                "text" => {
-                    if let Some(text) = content.text {
-                        text_content.push_str(&text);
-                    }
-                }
-                "tool_use" => {
-                    if let (Some(id), Some(name), Some(input)) =
-                        (content.id, content.name, content.input)
-                    {
-                        tool_calls.push(ToolCall {
-                            id,
-                            name,
-                            arguments: input,
-                        });
-                    }
-                }
-                _ => {}
-            }
-        }
+        let parts = read_tool_response(
+            response_data.content,
+            response_data.stop_reason.as_deref(),
+            self.max_tokens,
+        )?;
 
         Ok(AiResponse {
-            content: text_content,
-            tool_calls,
+            content: parts.text,
+            tool_calls: parts.tool_calls,
             stop_reason: response_data.stop_reason,
+            thinking: parts.thinking,
             x402_payment: None, // Claude doesn't use x402
+            usage: match (self.requested_seed, response_data.usage) {
+                (None, None) => None,
+                (seed, tokens) => {
+                    let mut usage = if seed.is_some() {
+                        UsageMetadata::seed_unsupported("claude")
+                    } else {
+                        UsageMetadata::default()
+                    };
+                    if let Some(tokens) = tokens {
+                        tracing::debug!(
+                            "[CLAUDE] Usage: {} input, {} output, {} cache read, {} cache write",
+                            tokens.input_tokens,
+                            tokens.output_tokens,
+                            tokens.cache_read_input_tokens.unwrap_or(0),
+                            tokens.cache_creation_input_tokens.unwrap_or(0)
+                        );
+                        usage.input_tokens = Some(tokens.input_tokens);
+                        usage.output_tokens = Some(tokens.output_tokens);
+                        usage.cache_creation_input_tokens = tokens.cache_creation_input_tokens;
+                        usage.cache_read_input_tokens = tokens.cache_read_input_tokens;
+                    }
+                    Some(usage)
+                }
+            },
         })
     }
+}
 
-    /// Build tool result messages to continue conversation after tool execution
-    pub fn build_tool_result_messages(
-        tool_calls: &[ToolCall],
-        tool_responses: &[ToolResponse],
-    ) -> Vec<TypedClaudeMessage> {
-        // First message: assistant with tool_use blocks
-        let tool_use_blocks: Vec<ClaudeContentBlock> = tool_calls
-            .iter()
-            .map(|tc| ClaudeContentBlock::ToolUse {
-                id: tc.id.clone(),
-                name: tc.name.clone(),
-                input: tc.arguments.clone(),
-            })
-            .collect();
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ai::ImageAttachment;
+
+    #[test]
+    fn test_anthropic_headers_applied() {
+        let config = AnthropicHeaders::new(
+            "2023-06-01",
+            vec!["prompt-caching-2024-07-31".to_string(), " token-efficient-tools-2025-02-19 ".to_string()],
+        )
+        .unwrap();
+        let mut headers = header::HeaderMap::new();
+        config.apply(&mut headers).unwrap();
 
-        // Second message: user with tool_result blocks
-        let tool_result_blocks: Vec<ClaudeContentBlock> = tool_responses
-            .iter()
-            .map(|tr| ClaudeContentBlock::tool_result(
-                tr.tool_call_id.clone(),
-                tr.content.clone(),
-                tr.is_error,
-            ))
-            .collect();
+        assert_eq!(headers["anthropic-version"], "2023-06-01");
+        assert_eq!(
+            headers["anthropic-beta"],
+            "prompt-caching-2024-07-31,token-efficient-tools-2025-02-19"
+        );
+    }
+
+    #[test]
+    fn test_anthropic_headers_without_beta() {
+        let config = AnthropicHeaders::new("2023-06-01", vec![]).unwrap();
+        let mut headers = header::HeaderMap::new();
+        config.apply(&mut headers).unwrap();
+        assert!(!headers.contains_key("anthropic-beta"));
+    }
+
+    #[test]
+    fn test_system_blocks_and_tools_carry_cache_breakpoints() {
+        let messages = vec![
+            Message { role: MessageRole::System, content: "You are StarkBot.".to_string(), images: Vec::new() },
+            Message { role: MessageRole::User, content: "hi".to_string(), images: Vec::new() },
+            Message { role: MessageRole::System, content: "Current time: noon".to_string(), images: Vec::new() },
+        ];
+        let (system, mut rest) = split_system_messages(messages);
+        assert_eq!(rest.len(), 1);
+        assert_eq!(
+            serde_json::to_value(system.unwrap()).unwrap(),
+            serde_json::json!([
+                {"type": "text", "text": "You are StarkBot."},
+                {"type": "text", "text": "Current time: noon", "cache_control": {"type": "ephemeral"}},
+            ])
+        );
+        assert!(split_system_messages(vec![]).0.is_none());
+
+        rest[0].images.push(ImageAttachment { media_type: "image/jpeg".to_string(), data: "/9j/4A==".to_string() });
+        assert_eq!(
+            serde_json::to_value(claude_message(rest[0].clone(), true)).unwrap(),
+            serde_json::json!({ "role": "user", "content": [
+                {"type": "image", "source": {"type": "base64", "media_type": "image/jpeg", "data": "/9j/4A=="}},
+                {"type": "text", "text": "hi"},
+            ]})
+        );
+        let without = serde_json::to_value(claude_message(rest.remove(0), false)).unwrap();
+        assert!(without["content"].as_str().unwrap().contains("cannot view images"));
+
+        let definition = |name: &str| ToolDefinition {
+            name: name.to_string(),
+            description: String::new(),
+            input_schema: Default::default(),
+            group: crate::tools::ToolGroup::Web,
+        };
+        let tools = claude_tools(vec![definition("web_fetch"), definition("exec")]);
+        assert!(tools[0].cache_control.is_none());
+        assert_eq!(tools[1].cache_control, Some(CacheControl::Ephemeral));
+    }
+
+    #[test]
+    fn test_usage_reports_cache_tokens() {
+        let usage: ClaudeUsage = serde_json::from_value(serde_json::json!({
+            "input_tokens": 12,
+            "output_tokens": 40,
+            "cache_creation_input_tokens": 0,
+            "cache_read_input_tokens": 5200,
+        }))
+        .unwrap();
+        assert_eq!(usage.cache_read_input_tokens, Some(5200));
+        assert_eq!(usage.cache_creation_input_tokens, Some(0));
+    }
+
+    #[test]
+    fn test_anthropic_headers_reject_empty_values() {
+        assert!(AnthropicHeaders::new("  ", vec![]).is_err());
+        assert!(AnthropicHeaders::new("2023-06-01", vec!["".to_string()]).is_err());
+    }
+
+    #[test]
+    fn test_read_tool_response_keeps_thinking_and_parallel_calls() {
+        let content: Vec<ClaudeResponseContent> = serde_json::from_value(serde_json::json!([
+            {"type": "thinking", "thinking": "Check both.", "signature": "sig"},
+            {"type": "redacted_thinking", "data": "opaque"},
+            {"type": "text", "text": "Looking up both."},
+            {"type": "tool_use", "id": "toolu_1", "name": "web_fetch", "input": {"url": "https://a.example"}},
+            {"type": "tool_use", "id": "toolu_2", "name": "web_fetch", "input": {"url": "https://b.example"}},
+        ]))
+        .unwrap();
+        let parts = read_tool_response(content, Some("tool_use"), 1024).unwrap();
+        assert_eq!(parts.text, "Looking up both.");
+        assert_eq!(parts.tool_calls.len(), 2);
+        assert_eq!(parts.tool_calls[1].id, "toolu_2");
+        assert_eq!(
+            parts.thinking,
+            vec![
+                ThinkingBlock::Thinking { thinking: "Check both.".to_string(), signature: "sig".to_string() },
+                ThinkingBlock::RedactedThinking { data: "opaque".to_string() },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_read_tool_response_stop_reasons() {
+        let truncated = || -> Vec<ClaudeResponseContent> {
+            serde_json::from_value(serde_json::json!([
+                {"type": "tool_use", "id": "toolu_1", "name": "read_file", "input": {"path": "a.rs"}},
+                {"type": "tool_use", "id": "toolu_2", "name": "write_file", "input": {"path": "b.rs"}},
+            ]))
+            .unwrap()
+        };
+        // The last call was cut off mid-input; the complete one still runs
+        let parts = read_tool_response(truncated(), Some("max_tokens"), 64).unwrap();
+        assert_eq!(parts.tool_calls.len(), 1);
+        assert_eq!(parts.tool_calls[0].name, "read_file");
+
+        let only_truncated = truncated().into_iter().skip(1).collect();
+        let err = read_tool_response(only_truncated, Some("max_tokens"), 64).unwrap_err();
+        assert!(err.to_string().contains("write_file"));
 
-        vec![
-            TypedClaudeMessage::assistant_with_blocks(tool_use_blocks),
-            TypedClaudeMessage::user_with_tool_results(tool_result_blocks),
-        ]
+        let parts = read_tool_response(Vec::new(), Some("refusal"), 64).unwrap();
+        assert!(parts.text.contains("declined"));
     }
 }
@@ -1,7 +1,11 @@
-use crate::ai::types::{AiResponse, ToolCall};
+use crate::ai::provider::LlmProvider;
+use crate::ai::retry::{self, RetryPolicy};
+use crate::ai::types::{AiResponse, UsageMetadata};
+use crate::ai::vision::without_images;
 use crate::ai::Message;
 use crate::gateway::events::EventBroadcaster;
 use crate::gateway::protocol::GatewayEvent;
+use crate::models::ModelOverrides;
 use crate::tools::ToolDefinition;
 use reqwest::{header, Client};
 use serde::{Deserialize, Serialize};
@@ -15,10 +19,18 @@ pub struct LlamaClient {
     client: Client,
     endpoint: String,
     model: String,
+    /// Optional sampling seed for reproducible outputs
+    seed: Option<u64>,
+    /// Maximum tokens to generate; model default when unset
+    num_predict: Option<u32>,
+    /// Sampling temperature; model default when unset
+    temperature: Option<f32>,
     /// Optional broadcaster for emitting retry events
     broadcaster: Option<Arc<EventBroadcaster>>,
     /// Channel ID for events
     channel_id: Option<i64>,
+    /// Backoff for transient errors
+    retry_policy: RetryPolicy,
 }
 
 #[derive(Debug, Serialize)]
@@ -28,6 +40,19 @@ struct OllamaChatRequest {
     stream: bool,
     #[serde(skip_serializing_if = "Option::is_none")]
     tools: Option<Vec<OllamaTool>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    options: Option<OllamaOptions>,
+}
+
+/// Model options for Ollama requests
+#[derive(Debug, Serialize)]
+struct OllamaOptions {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    seed: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    num_predict: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    temperature: Option<f32>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -70,6 +95,10 @@ struct OllamaChatResponse {
     message: OllamaResponseMessage,
     #[serde(default)]
     done_reason: Option<String>,
+    #[serde(default)]
+    prompt_eval_count: Option<u32>,
+    #[serde(default)]
+    eval_count: Option<u32>,
 }
 
 #[derive(Debug, Deserialize)]
@@ -105,11 +134,29 @@ impl LlamaClient {
                 .unwrap_or("http://localhost:11434/api/chat")
                 .to_string(),
             model: model.unwrap_or("llama3.3").to_string(),
+            seed: None,
+            num_predict: None,
+            temperature: None,
             broadcaster: None,
             channel_id: None,
+            retry_policy: RetryPolicy::default(),
         })
     }
 
+    /// Replace the default backoff for transient errors
+    pub fn with_retry_policy(mut self, retry_policy: RetryPolicy) -> Self {
+        self.retry_policy = retry_policy;
+        self
+    }
+
+    pub fn endpoint(&self) -> &str {
+        &self.endpoint
+    }
+
+    pub fn model(&self) -> &str {
+        &self.model
+    }
+
     /// Set the broadcaster for emitting retry events
     pub fn with_broadcaster(mut self, broadcaster: Arc<EventBroadcaster>, channel_id: i64) -> Self {
         self.broadcaster = Some(broadcaster);
@@ -117,6 +164,34 @@ impl LlamaClient {
         self
     }
 
+    /// Set the sampling seed sent with every request (for reproducible outputs)
+    pub fn with_seed(mut self, seed: Option<u64>) -> Self {
+        self.seed = seed;
+        self
+    }
+
+    /// Apply per-request model parameters
+    pub fn with_overrides(mut self, overrides: &ModelOverrides) -> Self {
+        if let Some(model) = &overrides.model {
+            self.model = model.clone();
+        }
+        self.num_predict = overrides.max_tokens.or(self.num_predict);
+        self.temperature = overrides.temperature.or(self.temperature);
+        self
+    }
+
+    /// Options block for a request, omitted when nothing is set
+    fn options(&self) -> Option<OllamaOptions> {
+        if self.seed.is_none() && self.num_predict.is_none() && self.temperature.is_none() {
+            return None;
+        }
+        Some(OllamaOptions {
+            seed: self.seed,
+            num_predict: self.num_predict,
+            temperature: self.temperature,
+        })
+    }
+
     /// Emit a retry event if broadcaster is configured
     fn emit_retry_event(&self, attempt: u32, max_attempts: u32, wait_seconds: u64, error: &str) {
         if let (Some(broadcaster), Some(channel_id)) = (&self.broadcaster, self.channel_id) {
@@ -136,7 +211,7 @@ impl LlamaClient {
             .into_iter()
             .map(|m| OllamaMessage {
                 role: m.role.to_string(),
-                content: m.content,
+                content: without_images(&m.content, &m.images),
                 tool_calls: None,
             })
             .collect();
@@ -146,31 +221,32 @@ impl LlamaClient {
             messages: api_messages,
             stream: false,
             tools: None,
+            options: self.options(),
         };
 
-        log::debug!("Sending request to Ollama API: {:?}", request);
+        tracing::debug!("Sending request to Ollama API: {:?}", request);
 
-        // Retry configuration for transient errors
-        const MAX_RETRIES: u32 = 3;
-        const BASE_DELAY_MS: u64 = 2000;
+        // Retry transient errors per the client's retry policy
+        let max_retries = self.retry_policy.max_retries;
+        let mut retry_after: Option<Duration> = None;
 
         let mut last_error: Option<String> = None;
         let mut response_data_opt: Option<OllamaChatResponse> = None;
 
-        for attempt in 0..=MAX_RETRIES {
+        for attempt in 0..=max_retries {
             if attempt > 0 {
-                let delay_ms = BASE_DELAY_MS * (1 << (attempt - 1));
+                let delay_ms = self.retry_policy.delay(attempt, retry_after).as_millis() as u64;
                 let wait_secs = delay_ms / 1000;
-                log::warn!(
+                tracing::warn!(
                     "[OLLAMA] Retry attempt {}/{} after {}ms delay",
                     attempt,
-                    MAX_RETRIES,
+                    max_retries,
                     delay_ms
                 );
                 // Emit retry event to frontend
                 self.emit_retry_event(
                     attempt,
-                    MAX_RETRIES,
+                    max_retries,
                     wait_secs,
                     last_error.as_deref().unwrap_or("Unknown error"),
                 );
@@ -188,8 +264,8 @@ impl LlamaClient {
                 Ok(r) => r,
                 Err(e) => {
                     last_error = Some(format!("Ollama API request failed: {}", e));
-                    if attempt < MAX_RETRIES {
-                        log::warn!("[OLLAMA] Request failed (attempt {}): {}, will retry", attempt + 1, e);
+                    if attempt < max_retries {
+                        tracing::warn!("[OLLAMA] Request failed (attempt {}): {}, will retry", attempt + 1, e);
                         continue;
                     }
                     return Err(last_error.unwrap());
@@ -198,7 +274,8 @@ impl LlamaClient {
 
             let status = response.status();
             let status_code = status.as_u16();
-            let is_retryable = matches!(status_code, 429 | 502 | 503 | 504);
+            let is_retryable = retry::is_retryable_status(status_code);
+            retry_after = retry::retry_after(response.headers());
 
             if !status.is_success() {
                 let error_text = response.text().await.unwrap_or_default();
@@ -214,8 +291,8 @@ impl LlamaClient {
                     error_text.contains("network error")
                 );
 
-                if (is_retryable || is_transient_402) && attempt < MAX_RETRIES {
-                    log::warn!(
+                if (is_retryable || is_transient_402) && attempt < max_retries {
+                    tracing::warn!(
                         "[OLLAMA] Received retryable status {} (attempt {}), will retry",
                         status,
                         attempt + 1
@@ -264,7 +341,7 @@ impl LlamaClient {
             .into_iter()
             .map(|m| OllamaMessage {
                 role: m.role.to_string(),
-                content: m.content,
+                content: without_images(&m.content, &m.images),
                 tool_calls: None,
             })
             .collect();
@@ -294,34 +371,35 @@ impl LlamaClient {
             } else {
                 Some(ollama_tools)
             },
+            options: self.options(),
         };
 
-        log::debug!(
+        tracing::debug!(
             "Sending tool request to Ollama API: {}",
             serde_json::to_string_pretty(&request).unwrap_or_default()
         );
 
-        // Retry configuration for transient errors
-        const MAX_RETRIES: u32 = 3;
-        const BASE_DELAY_MS: u64 = 2000;
+        // Retry transient errors per the client's retry policy
+        let max_retries = self.retry_policy.max_retries;
+        let mut retry_after: Option<Duration> = None;
 
         let mut last_error: Option<String> = None;
         let mut response_data_opt: Option<OllamaChatResponse> = None;
 
-        for attempt in 0..=MAX_RETRIES {
+        for attempt in 0..=max_retries {
             if attempt > 0 {
-                let delay_ms = BASE_DELAY_MS * (1 << (attempt - 1));
+                let delay_ms = self.retry_policy.delay(attempt, retry_after).as_millis() as u64;
                 let wait_secs = delay_ms / 1000;
-                log::warn!(
+                tracing::warn!(
                     "[OLLAMA] Tool request retry attempt {}/{} after {}ms delay",
                     attempt,
-                    MAX_RETRIES,
+                    max_retries,
                     delay_ms
                 );
                 // Emit retry event to frontend
                 self.emit_retry_event(
                     attempt,
-                    MAX_RETRIES,
+                    max_retries,
                     wait_secs,
                     last_error.as_deref().unwrap_or("Unknown error"),
                 );
@@ -339,8 +417,8 @@ impl LlamaClient {
                 Ok(r) => r,
                 Err(e) => {
                     last_error = Some(format!("Ollama API request failed: {}", e));
-                    if attempt < MAX_RETRIES {
-                        log::warn!("[OLLAMA] Tool request failed (attempt {}): {}, will retry", attempt + 1, e);
+                    if attempt < max_retries {
+                        tracing::warn!("[OLLAMA] Tool request failed (attempt {}): {}, will retry", attempt + 1, e);
                         continue;
                     }
                     return Err(last_error.unwrap());
@@ -349,7 +427,8 @@ impl LlamaClient {
 
             let status = response.status();
             let status_code = status.as_u16();
-            let is_retryable = matches!(status_code, 429 | 502 | 503 | 504);
+            let is_retryable = retry::is_retryable_status(status_code);
+            retry_after = retry::retry_after(response.headers());
 
             if !status.is_success() {
                 let error_text = response.text().await.unwrap_or_default();
@@ -365,8 +444,8 @@ impl LlamaClient {
                     error_text.contains("network error")
                 );
 
-                if (is_retryable || is_transient_402) && attempt < MAX_RETRIES {
-                    log::warn!(
+                if (is_retryable || is_transient_402) && attempt < max_retries {
+                    tracing::warn!(
                         "[OLLAMA] Tool request received retryable status {} (attempt {}), will retry",
                         status,
                         attempt + 1
@@ -398,14 +477,13 @@ impl LlamaClient {
 
         // Parse tool calls from response
         let mut tool_calls = Vec::new();
-        if let Some(calls) = response_data.message.tool_calls {
-            for (idx, call) in calls.into_iter().enumerate() {
-                tool_calls.push(ToolCall {
-                    id: call.id.unwrap_or_else(|| format!("call_{}", idx)),
-                    name: call.function.name,
-                    arguments: call.function.arguments,
-                });
-            }
+        if let Some(ref calls) = response_data.message.tool_calls {
+            tool_calls.extend(
+                calls
+                    .iter()
+                    .enumerate()
+                    .filter_map(|(idx, call)| Self::tool_call_from_wire(call, idx)),
+            );
         }
 
         // Determine stop reason
@@ -419,46 +497,20 @@ impl LlamaClient {
             content: response_data.message.content,
             tool_calls,
             stop_reason,
+            thinking: Vec::new(),
             x402_payment: None, // Llama doesn't use x402 directly (handled by OpenAI-compatible wrapper)
+            usage: if self.seed.is_some() || response_data.eval_count.is_some() {
+                Some(UsageMetadata {
+                    seed: self.seed,
+                    input_tokens: response_data.prompt_eval_count,
+                    output_tokens: response_data.eval_count,
+                    ..Default::default()
+                })
+            } else {
+                None
+            },
         })
     }
-
-    /// Build tool result messages for continuing conversation after tool execution
-    pub fn build_tool_result_messages(
-        tool_calls: &[ToolCall],
-        tool_responses: &[crate::ai::ToolResponse],
-    ) -> Vec<OllamaMessage> {
-        let mut messages = Vec::new();
-
-        // Assistant message with tool calls
-        let ollama_tool_calls: Vec<OllamaToolCall> = tool_calls
-            .iter()
-            .map(|tc| OllamaToolCall {
-                id: Some(tc.id.clone()),
-                function: OllamaFunctionCall {
-                    name: tc.name.clone(),
-                    arguments: tc.arguments.clone(),
-                },
-            })
-            .collect();
-
-        messages.push(OllamaMessage {
-            role: "assistant".to_string(),
-            content: String::new(),
-            tool_calls: Some(ollama_tool_calls),
-        });
-
-        // Tool response messages
-        for response in tool_responses {
-            messages.push(OllamaMessage {
-                role: "tool".to_string(),
-                content: response.content.clone(),
-                tool_calls: None,
-            });
-        }
-
-        messages
-    }
 }
 
 /// Re-export for use in AiClient
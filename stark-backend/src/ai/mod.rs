@@ -1,14 +1,23 @@
 pub mod archetypes;
+pub mod budget;
 pub mod claude;
 pub mod llama;
 pub mod multi_agent;
 pub mod openai;
+pub mod provider;
+pub mod retry;
 pub mod streaming;
+pub mod structured;
 pub mod types;
+pub mod vision;
 
 pub use claude::ClaudeClient;
 pub use llama::{LlamaClient, LlamaMessage};
 pub use openai::OpenAIClient;
+pub use provider::LlmProvider;
+pub use retry::{FailoverClient, ProviderAttempt, RetryPolicy};
+pub use structured::ResponseFormat;
+pub use vision::{ImageAttachment, ImageSource};
 pub use archetypes::{ArchetypeId, ArchetypeRegistry, ModelArchetype};
 pub use types::{
     AiError, AiResponse, ClaudeMessage as TypedClaudeMessage, ThinkingLevel, ToolCall,
@@ -17,11 +26,15 @@ pub use types::{
 
 use crate::gateway::events::EventBroadcaster;
 use crate::gateway::protocol::GatewayEvent;
-use crate::models::AgentSettings;
+use crate::metrics::Metrics;
+use crate::models::{AgentSettings, ModelOverrides};
 use crate::tools::ToolDefinition;
 use crate::x402::X402PaymentInfo;
 use serde::{Deserialize, Serialize};
+use std::fmt::Display;
 use std::sync::Arc;
+use std::time::Instant;
+use tracing::Instrument;
 
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 #[serde(rename_all = "lowercase")]
@@ -45,6 +58,9 @@ impl ToString for MessageRole {
 pub struct Message {
     pub role: MessageRole,
     pub content: String,
+    /// Images sent with a user message, for models that support vision
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub images: Vec<ImageAttachment>,
 }
 
 /// Unified AI client that works with any configured provider
@@ -63,18 +79,20 @@ impl AiClient {
     /// Create an AI client from agent settings with optional burner wallet for x402
     ///
     /// Uses ClaudeClient for Claude archetype (requires x-api-key auth),
-    /// OpenAI-compatible client for all other archetypes.
+    /// OpenAI-compatible client for all other archetypes. The archetype's
+    /// profile supplies the model, caps `max_tokens` and sets stop sequences
+    /// and tool choice.
     pub fn from_settings_with_wallet(
         settings: &AgentSettings,
         burner_private_key: Option<&str>,
     ) -> Result<Self, String> {
         use crate::x402::is_x402_endpoint;
 
-        // Get archetype to determine client type and default model
+        // Get archetype to determine client type, and its profile for the request defaults
         let archetype_id = Self::infer_archetype(settings);
-        let registry = ArchetypeRegistry::new();
-        let archetype = registry.get(archetype_id).unwrap_or_else(|| registry.default_archetype());
-        let model = archetype.default_model();
+        let profile = archetypes::profile::profile(archetype_id);
+        let model = profile.default_model.as_str();
+        let max_tokens = profile.request_max_tokens(settings.max_tokens);
 
         // Determine API key: x402 endpoints don't need one, others use secret_key
         let api_key = if is_x402_endpoint(&settings.endpoint) {
@@ -89,7 +107,12 @@ impl AiClient {
                 api_key,
                 Some(&settings.endpoint),
                 Some(model),
-            )?;
+            )?
+            .with_profile(&profile)
+            .with_overrides(&ModelOverrides {
+                max_tokens: Some(max_tokens),
+                ..Default::default()
+            });
             return Ok(AiClient::Claude(client));
         }
 
@@ -99,11 +122,27 @@ impl AiClient {
             Some(&settings.endpoint),
             Some(model),
             burner_private_key,
-            Some(settings.max_tokens as u32),
-        )?;
+            Some(max_tokens),
+        )?
+        .with_profile(&profile);
         Ok(AiClient::OpenAI(client))
     }
 
+    /// Client for the fallback provider configured in agent settings, if any
+    pub fn fallback_from_settings(
+        settings: &AgentSettings,
+        burner_private_key: Option<&str>,
+    ) -> Result<Option<Self>, String> {
+        let Some(fallback) = settings.fallback() else {
+            return Ok(None);
+        };
+        let overrides = ModelOverrides {
+            model: settings.fallback_model.clone(),
+            ..Default::default()
+        };
+        Ok(Some(Self::from_settings_with_wallet(&fallback, burner_private_key)?.with_overrides(&overrides)))
+    }
+
     /// Get the archetype ID from agent settings
     pub fn infer_archetype(settings: &AgentSettings) -> ArchetypeId {
         ArchetypeId::from_str(&settings.model_archetype).unwrap_or(ArchetypeId::Kimi)
@@ -111,11 +150,19 @@ impl AiClient {
 
     /// Generate text using the configured provider
     pub async fn generate_text(&self, messages: Vec<Message>) -> Result<String, String> {
-        match self {
-            AiClient::Claude(client) => client.generate_text(messages).await,
-            AiClient::OpenAI(client) => client.generate_text(messages).await,
-            AiClient::Llama(client) => client.generate_text(messages).await,
+        let span = self.call_span("generate_text");
+        let started = Instant::now();
+        let result = async {
+            match self {
+                AiClient::Claude(client) => client.generate_text(messages).await,
+                AiClient::OpenAI(client) => client.generate_text(messages).await,
+                AiClient::Llama(client) => client.generate_text(messages).await,
+            }
         }
+        .instrument(span.clone())
+        .await;
+        self.record_call(&span, started, result.as_ref().err());
+        result
     }
 
     /// Generate text and emit x402 payment event if applicable
@@ -126,26 +173,32 @@ impl AiClient {
         broadcaster: &Arc<EventBroadcaster>,
         channel_id: i64,
     ) -> Result<(String, Option<X402PaymentInfo>), String> {
-        match self {
-            AiClient::OpenAI(client) => {
-                let (content, payment) = client.generate_text_with_payment_info(messages).await?;
-                // Emit x402 payment event if payment was made
-                if let Some(ref payment_info) = payment {
-                    broadcaster.broadcast(GatewayEvent::x402_payment(
-                        channel_id,
-                        &payment_info.amount,
-                        &payment_info.amount_formatted,
-                        &payment_info.asset,
-                        &payment_info.pay_to,
-                        payment_info.resource.as_deref(),
-                    ));
-                }
-                Ok((content, payment))
+        let span = self.call_span("generate_text");
+        let started = Instant::now();
+        let result = async {
+            match self {
+                AiClient::OpenAI(client) => client.generate_text_with_payment_info(messages).await,
+                // Other providers don't support x402
+                AiClient::Claude(client) => client.generate_text(messages).await.map(|content| (content, None)),
+                AiClient::Llama(client) => client.generate_text(messages).await.map(|content| (content, None)),
             }
-            // Other providers don't support x402
-            AiClient::Claude(client) => Ok((client.generate_text(messages).await?, None)),
-            AiClient::Llama(client) => Ok((client.generate_text(messages).await?, None)),
         }
+        .instrument(span.clone())
+        .await;
+        self.record_call(&span, started, result.as_ref().err());
+        let (content, payment) = result?;
+        // Emit x402 payment event if payment was made
+        if let Some(ref payment_info) = payment {
+            broadcaster.broadcast(GatewayEvent::x402_payment(
+                channel_id,
+                &payment_info.amount,
+                &payment_info.amount_formatted,
+                &payment_info.asset,
+                &payment_info.pay_to,
+                payment_info.resource.as_deref(),
+            ));
+        }
+        Ok((content, payment))
     }
 
     /// Generate response with tool support (Claude, OpenAI, and Llama 3.1+)
@@ -155,30 +208,65 @@ impl AiClient {
         tool_history: Vec<ToolHistoryEntry>,
         tools: Vec<ToolDefinition>,
     ) -> Result<AiResponse, AiError> {
-        match self {
-            AiClient::Claude(client) => {
-                // Convert tool history to Claude format
-                let tool_messages = Self::tool_history_to_claude(&tool_history);
-                client
-                    .generate_with_tools(messages, tool_messages, tools)
-                    .await
-            }
-            AiClient::OpenAI(client) => {
-                // Convert tool history to OpenAI format
-                let tool_messages = Self::tool_history_to_openai(&tool_history);
-                client
-                    .generate_with_tools(messages, tool_messages, tools)
-                    .await
-            }
-            AiClient::Llama(client) => {
-                // Convert tool history to Llama/Ollama format
-                let tool_messages = Self::tool_history_to_llama(&tool_history);
-                client
-                    .generate_with_tools(messages, tool_messages, tools)
-                    .await
-                    .map_err(AiError::from)
+        let span = self.call_span("generate_with_tools");
+        let started = Instant::now();
+        let result = async {
+            match self {
+                AiClient::Claude(client) => {
+                    // Convert tool history to Claude format
+                    let tool_messages = ClaudeClient::tool_history_to_wire(&tool_history);
+                    client
+                        .generate_with_tools(messages, tool_messages, tools)
+                        .await
+                }
+                AiClient::OpenAI(client) => {
+                    // Convert tool history to OpenAI format
+                    let tool_messages = OpenAIClient::tool_history_to_wire(&tool_history);
+                    client
+                        .generate_with_tools(messages, tool_messages, tools)
+                        .await
+                }
+                AiClient::Llama(client) => {
+                    // Convert tool history to Llama/Ollama format
+                    let tool_messages = LlamaClient::tool_history_to_wire(&tool_history);
+                    client
+                        .generate_with_tools(messages, tool_messages, tools)
+                        .await
+                        .map_err(AiError::from)
+                }
             }
         }
+        .instrument(span.clone())
+        .await;
+        self.record_call(&span, started, result.as_ref().err());
+        result
+    }
+
+    fn provider_name(&self) -> &'static str {
+        match self {
+            AiClient::Claude(_) => "claude",
+            AiClient::OpenAI(_) => "openai",
+            AiClient::Llama(_) => "llama",
+        }
+    }
+
+    /// Span for one provider call, under the current agent run
+    fn call_span(&self, operation: &'static str) -> tracing::Span {
+        tracing::info_span!(
+            "provider_call",
+            provider = self.provider_name(),
+            model = self.model(),
+            operation,
+            error = tracing::field::Empty,
+        )
+    }
+
+    /// Add a finished provider call to the admin stats and its span
+    fn record_call(&self, span: &tracing::Span, started: Instant, error: Option<&impl Display>) {
+        if let Some(e) = error {
+            span.record("error", tracing::field::display(e));
+        }
+        Metrics::global().record_provider_call(self.provider_name(), self.model(), started.elapsed(), error.is_none());
     }
 
     /// Check if the current provider supports tools
@@ -214,73 +302,56 @@ impl AiClient {
         }
     }
 
-    /// Build a tool history entry from tool calls and responses
-    pub fn build_tool_history_entry(
-        tool_calls: Vec<ToolCall>,
-        tool_responses: Vec<ToolResponse>,
-    ) -> ToolHistoryEntry {
-        ToolHistoryEntry::new(tool_calls, tool_responses)
+    /// Replace the provider client's backoff for transient errors
+    pub fn with_retry_policy(self, retry_policy: RetryPolicy) -> Self {
+        match self {
+            AiClient::Claude(client) => AiClient::Claude(client.with_retry_policy(retry_policy)),
+            AiClient::OpenAI(client) => AiClient::OpenAI(client.with_retry_policy(retry_policy)),
+            AiClient::Llama(client) => AiClient::Llama(client.with_retry_policy(retry_policy)),
+        }
     }
 
-    /// Convert tool history to Claude format
-    fn tool_history_to_claude(history: &[ToolHistoryEntry]) -> Vec<TypedClaudeMessage> {
-        use types::{ClaudeContentBlock, ClaudeMessage, ClaudeMessageContent};
-
-        let mut messages = Vec::new();
-        for entry in history {
-            // Build assistant message with tool_use blocks
-            let tool_use_blocks: Vec<ClaudeContentBlock> = entry
-                .tool_calls
-                .iter()
-                .map(|tc| ClaudeContentBlock::ToolUse {
-                    id: tc.id.clone(),
-                    name: tc.name.clone(),
-                    input: tc.arguments.clone(),
-                })
-                .collect();
-
-            messages.push(ClaudeMessage {
-                role: "assistant".to_string(),
-                content: ClaudeMessageContent::Blocks(tool_use_blocks),
-            });
+    pub fn endpoint(&self) -> &str {
+        match self {
+            AiClient::Claude(client) => client.endpoint(),
+            AiClient::OpenAI(client) => client.endpoint(),
+            AiClient::Llama(client) => client.endpoint(),
+        }
+    }
 
-            // Build user message with tool_result blocks
-            let result_blocks: Vec<ClaudeContentBlock> = entry
-                .tool_responses
-                .iter()
-                .map(|tr| ClaudeContentBlock::tool_result(
-                    tr.tool_call_id.clone(),
-                    tr.content.clone(),
-                    tr.is_error,
-                ))
-                .collect();
+    pub fn model(&self) -> &str {
+        match self {
+            AiClient::Claude(client) => client.model(),
+            AiClient::OpenAI(client) => client.model(),
+            AiClient::Llama(client) => client.model(),
+        }
+    }
 
-            messages.push(ClaudeMessage::user_with_tool_results(result_blocks));
+    /// Set a sampling seed for reproducible outputs.
+    /// Passed through for OpenAI-compatible and Ollama providers; Claude reports it as ignored.
+    pub fn with_seed(self, seed: Option<u64>) -> Self {
+        match self {
+            AiClient::Claude(client) => AiClient::Claude(client.with_seed(seed)),
+            AiClient::OpenAI(client) => AiClient::OpenAI(client.with_seed(seed)),
+            AiClient::Llama(client) => AiClient::Llama(client.with_seed(seed)),
         }
-        messages
     }
 
-    /// Convert tool history to OpenAI format
-    fn tool_history_to_openai(
-        history: &[ToolHistoryEntry],
-    ) -> Vec<openai::OpenAIMessage> {
-        let mut messages = Vec::new();
-        for entry in history {
-            let openai_messages =
-                OpenAIClient::build_tool_result_messages(&entry.tool_calls, &entry.tool_responses);
-            messages.extend(openai_messages);
+    /// Ask for JSON answers in `format`. Only OpenAI-compatible providers have
+    /// a JSON mode; the others rely on the prompt and the answer check.
+    pub fn with_response_format(self, format: Option<&ResponseFormat>) -> Self {
+        match self {
+            AiClient::OpenAI(client) => AiClient::OpenAI(client.with_response_format(format)),
+            client => client,
         }
-        messages
     }
 
-    /// Convert tool history to Llama/Ollama format
-    fn tool_history_to_llama(history: &[ToolHistoryEntry]) -> Vec<LlamaMessage> {
-        let mut messages = Vec::new();
-        for entry in history {
-            let llama_messages =
-                LlamaClient::build_tool_result_messages(&entry.tool_calls, &entry.tool_responses);
-            messages.extend(llama_messages);
+    /// Apply per-request model parameters (already checked against the agent settings)
+    pub fn with_overrides(self, overrides: &ModelOverrides) -> Self {
+        match self {
+            AiClient::Claude(client) => AiClient::Claude(client.with_overrides(overrides)),
+            AiClient::OpenAI(client) => AiClient::OpenAI(client.with_overrides(overrides)),
+            AiClient::Llama(client) => AiClient::Llama(client.with_overrides(overrides)),
         }
-        messages
     }
 }
@@ -3,6 +3,7 @@
 use super::tools;
 use super::types::{AgentContext, AgentMode, TaskQueue};
 use crate::tools::ToolDefinition;
+use crate::utils::truncate_chars;
 use serde_json::Value;
 
 /// Maximum iterations before forcing completion
@@ -208,8 +209,9 @@ impl Orchestrator {
         // Add scratchpad if not empty (truncated)
         if !self.context.scratchpad.is_empty() {
             summary.push_str("### Scratchpad\n\n");
-            if self.context.scratchpad.len() > MAX_SCRATCHPAD_LEN {
-                summary.push_str(&self.context.scratchpad[..MAX_SCRATCHPAD_LEN]);
+            let scratchpad = truncate_chars(&self.context.scratchpad, MAX_SCRATCHPAD_LEN);
+            if scratchpad.len() < self.context.scratchpad.len() {
+                summary.push_str(scratchpad);
                 summary.push_str("\n_(truncated)_\n\n");
             } else {
                 summary.push_str(&self.context.scratchpad);
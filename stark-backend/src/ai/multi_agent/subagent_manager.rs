@@ -13,6 +13,8 @@ use crate::gateway::events::EventBroadcaster;
 use crate::gateway::protocol::GatewayEvent;
 use crate::models::{AgentSettings, SessionScope};
 use crate::tools::{ToolContext, ToolDefinition, ToolRegistry};
+use crate::utils::truncate_str;
+use crate::workspace::WorkspaceManager;
 use dashmap::DashMap;
 use serde_json::json;
 use std::sync::atomic::{AtomicU64, Ordering};
@@ -113,7 +115,7 @@ impl SubAgentManager {
         }
 
         // Persist the initial state
-        self.save_subagent(&context)?;
+        self.save_subagent(&context).await?;
 
         // Broadcast spawned event
         self.broadcaster.broadcast(GatewayEvent::subagent_spawned(
@@ -225,7 +227,7 @@ impl SubAgentManager {
             }
 
             // Persist final state
-            if let Err(e) = Self::save_subagent_direct(&db, &final_context) {
+            if let Err(e) = Self::save_subagent_direct(&db, &final_context).await {
                 log::error!("[SUBAGENT] Failed to save final state for {}: {}", final_context.id, e);
             }
 
@@ -258,7 +260,14 @@ impl SubAgentManager {
         log::info!("[SUBAGENT] Starting execution for {}", context.id);
 
         // Create an isolated session for the sub-agent
+        // It belongs to whoever owns the conversation that spawned it
         let session_key = format!("subagent:{}:{}", context.parent_channel_id, context.id);
+        let owner_id = db
+            .get_chat_session(context.parent_session_id)
+            .await
+            .ok()
+            .flatten()
+            .and_then(|parent| parent.user_id);
         let session = db
             .get_or_create_chat_session(
                 "subagent",
@@ -266,21 +275,22 @@ impl SubAgentManager {
                 &session_key,
                 SessionScope::Dm,
                 None,
-            )
+                owner_id,
+            ).await
             .map_err(|e| format!("Failed to create session: {}", e))?;
 
         // Mark as running with session ID
         context.mark_running(session.id);
-        Self::save_subagent_direct(&db, &context)?;
+        Self::save_subagent_direct(&db, &context).await?;
 
         // Get agent settings
         let settings = db
-            .get_active_agent_settings()
+            .get_active_agent_settings().await
             .map_err(|e| format!("Failed to get agent settings: {}", e))?
             .unwrap_or_default();
 
         // Apply model override if specified
-        let effective_settings = if let Some(ref model) = context.model_override {
+        let mut effective_settings = if let Some(ref model) = context.model_override {
             AgentSettings {
                 model_archetype: model.clone(),
                 ..settings
@@ -288,6 +298,10 @@ impl SubAgentManager {
         } else {
             settings
         };
+        // Same provider key as the conversation that spawned it
+        db.apply_provider_key(&mut effective_settings, session.user_id)
+            .await
+            .map_err(|e| format!("Failed to load provider key: {}", e))?;
 
         // Create AI client with broadcaster for retry events
         let client = AiClient::from_settings_with_wallet(
@@ -316,24 +330,33 @@ impl SubAgentManager {
             Message {
                 role: MessageRole::System,
                 content: system_prompt,
+                images: Vec::new(),
             },
             Message {
                 role: MessageRole::User,
                 content: task_prompt,
+                images: Vec::new(),
             },
         ];
 
-        // Build tool context
-        let workspace_dir = crate::config::workspace_dir();
+        // Build tool context. Subagents share their parent session's workspace.
+        let workspace_dir = match WorkspaceManager::from_env().for_session(context.parent_session_id) {
+            Ok(dir) => dir.to_string_lossy().to_string(),
+            Err(e) => {
+                log::error!("[SUBAGENT] {}", e);
+                crate::config::workspace_dir()
+            }
+        };
         let tool_context = ToolContext::new()
             .with_channel(context.parent_channel_id, "subagent".to_string())
             .with_session(session.id)
             .with_workspace(workspace_dir)
-            .with_broadcaster(broadcaster.clone());
+            .with_broadcaster(broadcaster.clone())
+            .with_database(Arc::clone(&db));
 
         // Get tool configuration
         let tool_config = db
-            .get_effective_tool_config(Some(context.parent_channel_id))
+            .get_effective_tool_config(Some(context.parent_channel_id)).await
             .unwrap_or_default();
 
         // Get available tools
@@ -442,16 +465,13 @@ impl SubAgentManager {
                 break;
             }
 
-            // Add to history
-            tool_history.push(AiClient::build_tool_history_entry(
-                response.tool_calls,
-                tool_responses,
-            ));
-
             // If there was content with the tool calls, save it
             if !response.content.is_empty() {
-                final_response = response.content;
+                final_response = response.content.clone();
             }
+
+            // Add to history
+            tool_history.push(ToolHistoryEntry::from_response(response, tool_responses));
         }
 
         if final_response.is_empty() {
@@ -462,13 +482,13 @@ impl SubAgentManager {
     }
 
     /// Save sub-agent state to database
-    fn save_subagent(&self, context: &SubAgentContext) -> Result<(), String> {
-        Self::save_subagent_direct(&self.db, context)
+    async fn save_subagent(&self, context: &SubAgentContext) -> Result<(), String> {
+        Self::save_subagent_direct(&self.db, context).await
     }
 
     /// Save sub-agent state to database (static version)
-    fn save_subagent_direct(db: &Database, context: &SubAgentContext) -> Result<(), String> {
-        let conn = db.conn.lock().unwrap();
+    async fn save_subagent_direct(db: &Database, context: &SubAgentContext) -> Result<(), String> {
+        let conn = db.conn().await.map_err(|e| format!("Database error: {}", e))?;
 
         // Check if record exists
         let exists: bool = conn
@@ -532,8 +552,8 @@ impl SubAgentManager {
     }
 
     /// Get the status of a sub-agent by ID
-    pub fn get_status(&self, subagent_id: &str) -> Result<Option<SubAgentContext>, String> {
-        let conn = self.db.conn.lock().unwrap();
+    pub async fn get_status(&self, subagent_id: &str) -> Result<Option<SubAgentContext>, String> {
+        let conn = self.db.conn().await.map_err(|e| format!("Database error: {}", e))?;
 
         let result = conn.query_row(
             "SELECT
@@ -576,8 +596,8 @@ impl SubAgentManager {
     }
 
     /// List all sub-agents for a channel
-    pub fn list_by_channel(&self, channel_id: i64) -> Result<Vec<SubAgentContext>, String> {
-        let conn = self.db.conn.lock().unwrap();
+    pub async fn list_by_channel(&self, channel_id: i64) -> Result<Vec<SubAgentContext>, String> {
+        let conn = self.db.conn().await.map_err(|e| format!("Database error: {}", e))?;
 
         let mut stmt = conn
             .prepare(
@@ -657,11 +677,11 @@ impl SubAgentManager {
 
     /// Cancel all running sub-agents for a specific channel
     /// Returns the number of agents cancelled
-    pub fn cancel_all_for_channel(&self, channel_id: i64) -> usize {
+    pub async fn cancel_all_for_channel(&self, channel_id: i64) -> usize {
         let mut count = 0;
         // We need to check which agents belong to this channel
         // Since we store channel_id in the handle context, we check the database
-        if let Ok(agents) = self.list_by_channel(channel_id) {
+        if let Ok(agents) = self.list_by_channel(channel_id).await {
             for agent in agents {
                 if agent.status == SubAgentStatus::Running {
                     if let Some((_, handle)) = self.active_agents.remove(&agent.id) {
@@ -677,10 +697,29 @@ impl SubAgentManager {
         count
     }
 
+    /// Cancel the running sub-agents a chat session spawned
+    /// Returns the number of agents cancelled
+    pub async fn cancel_all_for_session(&self, channel_id: i64, session_id: i64) -> usize {
+        let mut count = 0;
+        if let Ok(agents) = self.list_by_channel(channel_id).await {
+            for agent in agents {
+                if agent.status == SubAgentStatus::Running
+                    && agent.parent_session_id == session_id
+                    && let Some((_, handle)) = self.active_agents.remove(&agent.id)
+                {
+                    log::info!("[SUBAGENT_MANAGER] Cancelling subagent {} for session {}", agent.id, session_id);
+                    handle.cancel();
+                    count += 1;
+                }
+            }
+        }
+        count
+    }
+
     /// Cancel all running sub-agents for a specific channel and wait briefly for cleanup
     /// Returns the number of agents cancelled
     pub async fn cancel_all_for_channel_and_wait(&self, channel_id: i64, wait_duration: Duration) -> usize {
-        let count = self.cancel_all_for_channel(channel_id);
+        let count = self.cancel_all_for_channel(channel_id).await;
 
         if count > 0 {
             // Brief wait for cancellation signals to be processed
@@ -721,7 +760,7 @@ impl GatewayEvent {
                 "channel_id": channel_id,
                 "subagent_id": subagent_id,
                 "label": label,
-                "task": if task.len() > 200 { format!("{}...", &task[..200]) } else { task.to_string() },
+                "task": truncate_str(task, 200),
                 "timestamp": chrono::Utc::now().to_rfc3339()
             }),
         )
@@ -735,7 +774,7 @@ impl GatewayEvent {
                 "channel_id": channel_id,
                 "subagent_id": subagent_id,
                 "label": label,
-                "result": if result.len() > 500 { format!("{}...", &result[..500]) } else { result.to_string() },
+                "result": truncate_str(result, 500),
                 "timestamp": chrono::Utc::now().to_rfc3339()
             }),
         )
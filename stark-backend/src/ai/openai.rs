@@ -1,10 +1,18 @@
+use crate::ai::provider::LlmProvider;
+use crate::ai::retry::{self, RetryPolicy};
 use crate::ai::streaming::{StreamEvent, StreamSender};
-use crate::ai::types::{AiError, AiResponse, ToolCall};
+use crate::ai::types::{AiError, AiResponse, ToolCall, UsageMetadata};
+use crate::ai::archetypes::{ArchetypeProfile, JsonMode, ToolChoiceMode};
+use crate::ai::structured::ResponseFormat;
+use crate::ai::vision::without_images;
 use crate::ai::Message;
 use crate::gateway::events::EventBroadcaster;
 use crate::gateway::protocol::GatewayEvent;
+use crate::models::ModelOverrides;
 use crate::tools::ToolDefinition;
 use crate::x402::{X402Client, X402PaymentInfo, is_x402_endpoint};
+use crate::utils::truncate_chars;
+use crate::wallet::{LocalSigner, WalletSigner};
 use futures_util::StreamExt;
 use reqwest::{header, Client};
 use serde::{Deserialize, Serialize};
@@ -18,11 +26,27 @@ pub struct OpenAIClient {
     endpoint: String,
     model: String,
     max_tokens: u32,
+    /// Optional sampling seed for reproducible outputs
+    seed: Option<u64>,
+    /// Sampling temperature; provider default when unset
+    temperature: Option<f32>,
     x402_client: Option<Arc<X402Client>>,
     /// Optional broadcaster for emitting retry events
     broadcaster: Option<Arc<EventBroadcaster>>,
     /// Channel ID for events (set when broadcasting)
     channel_id: Option<i64>,
+    /// Backoff for transient errors
+    retry_policy: RetryPolicy,
+    /// Sent with every request, from the archetype profile
+    stop_sequences: Vec<String>,
+    /// How requests with tools ask for tool use
+    tool_choice: ToolChoiceMode,
+    /// How structured output is requested, from the archetype profile
+    json_mode: JsonMode,
+    /// Wire `response_format` sent with every request
+    response_format: Option<Value>,
+    /// Send attached images to the model, from the archetype profile
+    supports_vision: bool,
 }
 
 #[derive(Debug, Serialize)]
@@ -36,6 +60,14 @@ struct OpenAICompletionRequest {
     tool_choice: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
     stream: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    seed: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    temperature: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    stop: Option<Vec<String>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    response_format: Option<Value>,
 }
 
 /// Streaming chunk response from OpenAI API
@@ -44,6 +76,8 @@ struct OpenAIStreamChunk {
     choices: Vec<OpenAIStreamChoice>,
     #[serde(default)]
     usage: Option<OpenAIStreamUsage>,
+    #[serde(default)]
+    system_fingerprint: Option<String>,
 }
 
 #[derive(Debug, Deserialize)]
@@ -84,13 +118,56 @@ struct OpenAIStreamUsage {
 pub struct OpenAIMessage {
     pub role: String,
     #[serde(skip_serializing_if = "Option::is_none")]
-    pub content: Option<String>,
+    pub content: Option<OpenAIContent>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub tool_calls: Option<Vec<OpenAIToolCall>>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub tool_call_id: Option<String>,
 }
 
+/// Message content: plain text, or text and image parts for vision models
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(untagged)]
+pub enum OpenAIContent {
+    Text(String),
+    Parts(Vec<OpenAIContentPart>),
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum OpenAIContentPart {
+    Text { text: String },
+    ImageUrl { image_url: OpenAIImageUrl },
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct OpenAIImageUrl {
+    /// A `data:` URL with the base64 image
+    pub url: String,
+}
+
+impl OpenAIMessage {
+    /// A message in the chat completions format. Images follow the text as
+    /// image parts, or are replaced by a note when the model can't view them.
+    fn from_message(message: Message, supports_vision: bool) -> Self {
+        let content = if message.images.is_empty() || !supports_vision {
+            OpenAIContent::Text(without_images(&message.content, &message.images))
+        } else {
+            let mut parts = vec![OpenAIContentPart::Text { text: message.content }];
+            parts.extend(message.images.iter().map(|image| OpenAIContentPart::ImageUrl {
+                image_url: OpenAIImageUrl { url: image.data_url() },
+            }));
+            OpenAIContent::Parts(parts)
+        };
+        OpenAIMessage {
+            role: message.role.to_string(),
+            content: Some(content),
+            tool_calls: None,
+            tool_call_id: None,
+        }
+    }
+}
+
 #[derive(Debug, Clone, Serialize)]
 struct OpenAITool {
     #[serde(rename = "type")]
@@ -122,6 +199,10 @@ pub struct OpenAIFunctionCall {
 #[derive(Debug, Deserialize)]
 struct OpenAICompletionResponse {
     choices: Vec<OpenAIChoice>,
+    #[serde(default)]
+    system_fingerprint: Option<String>,
+    #[serde(default)]
+    usage: Option<OpenAIStreamUsage>,
 }
 
 #[derive(Debug, Deserialize)]
@@ -190,27 +271,28 @@ impl OpenAIClient {
             .build()
             .map_err(|e| format!("Failed to create HTTP client: {}", e))?;
 
-        // Create x402 client if private key is provided and endpoint uses x402
+        // Create x402 client if the endpoint uses x402, paying with the active wallet
+        // (or the private key passed in, if no wallet is configured)
         let x402_client = if is_x402_endpoint(&endpoint_url) {
-            if let Some(pk) = burner_private_key {
-                if !pk.is_empty() {
-                    match X402Client::new(pk) {
-                        Ok(c) => {
-                            log::info!("[AI] x402 enabled for endpoint {} with wallet {}", endpoint_url, c.wallet_address());
-                            Some(Arc::new(c))
-                        }
-                        Err(e) => {
-                            log::warn!("[AI] Failed to create x402 client: {}", e);
-                            None
-                        }
-                    }
-                } else {
-                    log::warn!("[AI] x402 endpoint {} requires BURNER_WALLET_BOT_PRIVATE_KEY", endpoint_url);
+            let signer = crate::wallet::active_signer().or_else(|| {
+                burner_private_key
+                    .filter(|pk| !pk.is_empty())
+                    .and_then(|pk| LocalSigner::new(pk).ok())
+                    .map(|s| Arc::new(s) as Arc<dyn WalletSigner>)
+            });
+            match signer.map(X402Client::with_signer) {
+                Some(Ok(c)) => {
+                    tracing::info!("[AI] x402 enabled for endpoint {} with wallet {}", endpoint_url, c.wallet_address());
+                    Some(Arc::new(c))
+                }
+                Some(Err(e)) => {
+                    tracing::warn!("[AI] Failed to create x402 client: {}", e);
+                    None
+                }
+                None => {
+                    tracing::warn!("[AI] x402 endpoint {} requires a wallet: {}", endpoint_url, crate::wallet::NO_WALLET);
                     None
                 }
-            } else {
-                log::warn!("[AI] x402 endpoint {} requires BURNER_WALLET_BOT_PRIVATE_KEY", endpoint_url);
-                None
             }
         } else {
             None
@@ -235,12 +317,62 @@ impl OpenAIClient {
             endpoint: endpoint_url,
             model: model_name,
             max_tokens: max_tokens.unwrap_or(40000),
+            seed: None,
+            temperature: None,
             x402_client,
             broadcaster: None,
             channel_id: None,
+            retry_policy: RetryPolicy::default(),
+            stop_sequences: Vec::new(),
+            tool_choice: ToolChoiceMode::Required,
+            json_mode: JsonMode::Object,
+            response_format: None,
+            supports_vision: false,
         })
     }
 
+    /// Apply an archetype profile's stop sequences, tool choice, JSON mode and vision support
+    pub fn with_profile(mut self, profile: &ArchetypeProfile) -> Self {
+        self.stop_sequences = profile.stop_sequences.clone();
+        self.tool_choice = profile.tool_choice;
+        self.json_mode = profile.json_mode;
+        self.supports_vision = profile.supports_vision;
+        self
+    }
+
+    /// Ask the provider for JSON answers, as far as its JSON mode allows
+    pub fn with_response_format(mut self, format: Option<&ResponseFormat>) -> Self {
+        self.response_format = match (format, self.json_mode) {
+            (None, _) | (_, JsonMode::Prompt) => None,
+            (Some(format), mode) => Some(format.to_openai(mode == JsonMode::Schema)),
+        };
+        self
+    }
+
+    /// `tool_choice` for a request, which is only sent along with tools
+    fn request_tool_choice(&self, has_tools: bool) -> Option<String> {
+        match self.tool_choice {
+            _ if !has_tools => None,
+            ToolChoiceMode::Required => Some("required".to_string()),
+            ToolChoiceMode::Auto => Some("auto".to_string()),
+            ToolChoiceMode::Omit => None,
+        }
+    }
+
+    /// Replace the default backoff for transient errors
+    pub fn with_retry_policy(mut self, retry_policy: RetryPolicy) -> Self {
+        self.retry_policy = retry_policy;
+        self
+    }
+
+    pub fn endpoint(&self) -> &str {
+        &self.endpoint
+    }
+
+    pub fn model(&self) -> &str {
+        &self.model
+    }
+
     /// Set the broadcaster for emitting retry events
     pub fn with_broadcaster(mut self, broadcaster: Arc<EventBroadcaster>, channel_id: i64) -> Self {
         self.broadcaster = Some(broadcaster);
@@ -248,6 +380,42 @@ impl OpenAIClient {
         self
     }
 
+    /// Set the sampling seed sent with every request (for reproducible outputs)
+    pub fn with_seed(mut self, seed: Option<u64>) -> Self {
+        self.seed = seed;
+        self
+    }
+
+    /// Apply per-request model parameters
+    pub fn with_overrides(mut self, overrides: &ModelOverrides) -> Self {
+        if let Some(model) = &overrides.model {
+            self.model = model.clone();
+        }
+        if let Some(max_tokens) = overrides.max_tokens {
+            self.max_tokens = max_tokens;
+        }
+        self.temperature = overrides.temperature.or(self.temperature);
+        self
+    }
+
+    /// Build usage metadata from the seed we sent and the fingerprint/token counts the provider returned
+    fn usage_metadata(
+        &self,
+        system_fingerprint: Option<String>,
+        tokens: Option<(u32, u32)>,
+    ) -> Option<UsageMetadata> {
+        if self.seed.is_none() && system_fingerprint.is_none() && tokens.is_none() {
+            return None;
+        }
+        Some(UsageMetadata {
+            seed: self.seed,
+            system_fingerprint,
+            input_tokens: tokens.map(|(input, _)| input),
+            output_tokens: tokens.map(|(_, output)| output),
+            ..Default::default()
+        })
+    }
+
     /// Emit a retry event if broadcaster is configured
     fn emit_retry_event(&self, attempt: u32, max_attempts: u32, wait_seconds: u64, error: &str) {
         if let (Some(broadcaster), Some(channel_id)) = (&self.broadcaster, self.channel_id) {
@@ -293,12 +461,7 @@ impl OpenAIClient {
         // Convert messages to OpenAI format
         let mut api_messages: Vec<OpenAIMessage> = messages
             .into_iter()
-            .map(|m| OpenAIMessage {
-                role: m.role.to_string(),
-                content: Some(m.content),
-                tool_calls: None,
-                tool_call_id: None,
-            })
+            .map(|m| OpenAIMessage::from_message(m, self.supports_vision))
             .collect();
 
         // Add tool history messages (previous tool calls and results)
@@ -349,46 +512,49 @@ impl OpenAIClient {
             messages: api_messages,
             max_tokens: self.max_tokens,
             tools: openai_tools.clone(),
-            tool_choice: if tools.is_empty() { None } else { Some("required".to_string()) },
+            tool_choice: self.request_tool_choice(!tools.is_empty()),
             stream: None,
+            seed: self.seed,
+            temperature: self.temperature,
+            stop: (!self.stop_sequences.is_empty()).then(|| self.stop_sequences.clone()),
+            response_format: self.response_format.clone(),
         };
 
         // Debug: Log full request details
-        log::info!(
+        tracing::info!(
             "[OPENAI] Sending request to {} with model {} and {} tools (x402: {})",
             self.endpoint,
             self.model,
             openai_tools.as_ref().map(|t| t.len()).unwrap_or(0),
             self.x402_client.is_some()
         );
-        log::debug!(
+        tracing::debug!(
             "[OPENAI] Full request:\n{}",
             serde_json::to_string_pretty(&request).unwrap_or_default()
         );
 
-        // Retry configuration for transient errors
-        const MAX_RETRIES: u32 = 3;
-        const BASE_DELAY_MS: u64 = 2000; // 2 seconds base delay
+        // Retry transient errors per the client's retry policy
+        let max_retries = self.retry_policy.max_retries;
+        let mut retry_after: Option<Duration> = None;
 
         let mut last_error: Option<(String, Option<u16>)> = None;
         let mut x402_payment: Option<X402PaymentInfo> = None;
         let mut response_text: Option<String> = None;
 
-        for attempt in 0..=MAX_RETRIES {
+        for attempt in 0..=max_retries {
             if attempt > 0 {
-                // Exponential backoff: 2s, 4s, 8s
-                let delay_ms = BASE_DELAY_MS * (1 << (attempt - 1));
+                let delay_ms = self.retry_policy.delay(attempt, retry_after).as_millis() as u64;
                 let wait_secs = delay_ms / 1000;
-                log::warn!(
+                tracing::warn!(
                     "[OPENAI] Retry attempt {}/{} after {}ms delay",
                     attempt,
-                    MAX_RETRIES,
+                    max_retries,
                     delay_ms
                 );
                 // Emit retry event to frontend
                 self.emit_retry_event(
                     attempt,
-                    MAX_RETRIES,
+                    max_retries,
                     wait_secs,
                     last_error.as_ref().map(|(m, _)| m.as_str()).unwrap_or("Unknown error"),
                 );
@@ -418,8 +584,8 @@ impl OpenAIClient {
                 Err(e) => {
                     // Network errors are retryable
                     last_error = Some((e.clone(), None));
-                    if attempt < MAX_RETRIES {
-                        log::warn!("[OPENAI] Request failed (attempt {}): {}, will retry", attempt + 1, e);
+                    if attempt < max_retries {
+                        tracing::warn!("[OPENAI] Request failed (attempt {}): {}, will retry", attempt + 1, e);
                         continue;
                     }
                     return Err(AiError::new(e));
@@ -429,8 +595,8 @@ impl OpenAIClient {
             let status = response.status();
             let status_code = status.as_u16();
 
-            // Check for retryable status codes: 429 (rate limit), 502, 503, 504 (gateway errors)
-            let is_retryable = matches!(status_code, 429 | 502 | 503 | 504);
+            let is_retryable = retry::is_retryable_status(status_code);
+            retry_after = retry::retry_after(response.headers());
 
             if !status.is_success() {
                 let error_text = response.text().await.unwrap_or_default();
@@ -447,12 +613,12 @@ impl OpenAIClient {
                     error_text.contains("network error")
                 );
 
-                if (is_retryable || is_transient_402) && attempt < MAX_RETRIES {
-                    log::warn!(
+                if (is_retryable || is_transient_402) && attempt < max_retries {
+                    tracing::warn!(
                         "[OPENAI] Received retryable status {} (attempt {}), will retry: {}",
                         status,
                         attempt + 1,
-                        if error_text.len() > 200 { &error_text[..200] } else { &error_text }
+                        truncate_chars(&error_text, 200)
                     );
                     last_error = Some((format!("HTTP {}: {}", status, error_text), Some(status_code)));
                     continue;
@@ -464,7 +630,7 @@ impl OpenAIClient {
                     format!("OpenAI API returned error status: {}, body: {}", status, error_text)
                 };
 
-                return Err(AiError::with_status(error_msg, status_code));
+                return Err(AiError::with_status(error_msg, status_code).with_retry_after(retry_after));
             }
 
             // Success - read response body
@@ -485,7 +651,7 @@ impl OpenAIClient {
         })?;
 
         // Debug: Log raw response
-        log::debug!("[OPENAI] Raw response:\n{}", response_text);
+        tracing::debug!("[OPENAI] Raw response:\n{}", response_text);
 
         let response_data: OpenAICompletionResponse = serde_json::from_str(&response_text)
             .map_err(|e| AiError::new(format!("Failed to parse OpenAI response: {} - body: {}", e, response_text)))?;
@@ -496,7 +662,7 @@ impl OpenAIClient {
             .ok_or_else(|| "OpenAI API returned no choices".to_string())?;
 
         // Debug: Log parsed response
-        log::info!(
+        tracing::info!(
             "[OPENAI] Response - content_len: {}, tool_calls: {}, finish_reason: {:?}",
             choice.message.content.as_ref().map(|c| c.len()).unwrap_or(0),
             choice.message.tool_calls.as_ref().map(|t| t.len()).unwrap_or(0),
@@ -505,6 +671,10 @@ impl OpenAIClient {
 
         let content = choice.message.content.clone().unwrap_or_default();
         let finish_reason = choice.finish_reason.clone();
+        let tokens = response_data.usage.as_ref().map(|u| {
+            (u.prompt_tokens.unwrap_or(0), u.completion_tokens.unwrap_or(0))
+        });
+        let usage = self.usage_metadata(response_data.system_fingerprint.clone(), tokens);
 
         // Convert tool calls if present
         let tool_calls: Vec<ToolCall> = choice
@@ -514,15 +684,8 @@ impl OpenAIClient {
             .map(|calls| {
                 calls
                     .iter()
-                    .filter_map(|tc| {
-                        let args: Value = serde_json::from_str(&tc.function.arguments)
-                            .unwrap_or(json!({}));
-                        Some(ToolCall {
-                            id: tc.id.clone(),
-                            name: tc.function.name.clone(),
-                            arguments: args,
-                        })
-                    })
+                    .enumerate()
+                    .filter_map(|(idx, tc)| Self::tool_call_from_wire(tc, idx))
                     .collect()
             })
             .unwrap_or_default();
@@ -537,50 +700,12 @@ impl OpenAIClient {
             } else {
                 Some("end_turn".to_string())
             },
+            thinking: Vec::new(),
             x402_payment,
+            usage,
         })
     }
 
-    /// Build tool result messages for continuing after tool execution
-    pub fn build_tool_result_messages(
-        tool_calls: &[ToolCall],
-        tool_responses: &[crate::ai::ToolResponse],
-    ) -> Vec<OpenAIMessage> {
-        let mut messages = Vec::new();
-
-        // First, add the assistant message with tool calls
-        let openai_tool_calls: Vec<OpenAIToolCall> = tool_calls
-            .iter()
-            .map(|tc| OpenAIToolCall {
-                id: tc.id.clone(),
-                call_type: "function".to_string(),
-                function: OpenAIFunctionCall {
-                    name: tc.name.clone(),
-                    arguments: serde_json::to_string(&tc.arguments).unwrap_or_default(),
-                },
-            })
-            .collect();
-
-        messages.push(OpenAIMessage {
-            role: "assistant".to_string(),
-            content: Some("".to_string()), // Kimi requires content field even if empty
-            tool_calls: Some(openai_tool_calls),
-            tool_call_id: None,
-        });
-
-        // Then add the tool results
-        for response in tool_responses {
-            messages.push(OpenAIMessage {
-                role: "tool".to_string(),
-                content: Some(response.content.clone()),
-                tool_calls: None,
-                tool_call_id: Some(response.tool_call_id.clone()),
-            });
-        }
-
-        messages
-    }
-
     /// Generate response with streaming support
     ///
     /// Sends stream events through the provided sender as they arrive.
@@ -595,12 +720,7 @@ impl OpenAIClient {
         // Convert messages to OpenAI format
         let mut api_messages: Vec<OpenAIMessage> = messages
             .into_iter()
-            .map(|m| OpenAIMessage {
-                role: m.role.to_string(),
-                content: Some(m.content),
-                tool_calls: None,
-                tool_call_id: None,
-            })
+            .map(|m| OpenAIMessage::from_message(m, self.supports_vision))
             .collect();
 
         // Add tool history messages
@@ -651,39 +771,43 @@ impl OpenAIClient {
             messages: api_messages,
             max_tokens: self.max_tokens,
             tools: openai_tools.clone(),
-            tool_choice: if tools.is_empty() { None } else { Some("required".to_string()) },
+            tool_choice: self.request_tool_choice(!tools.is_empty()),
             stream: Some(true),
+            seed: self.seed,
+            temperature: self.temperature,
+            stop: (!self.stop_sequences.is_empty()).then(|| self.stop_sequences.clone()),
+            response_format: self.response_format.clone(),
         };
 
-        log::info!(
+        tracing::info!(
             "[OPENAI] Streaming request to {} with model {} and {} tools",
             self.endpoint,
             self.model,
             openai_tools.as_ref().map(|t| t.len()).unwrap_or(0),
         );
 
-        // Retry configuration for transient errors
-        const MAX_RETRIES: u32 = 3;
-        const BASE_DELAY_MS: u64 = 2000;
+        // Retry transient errors per the client's retry policy
+        let max_retries = self.retry_policy.max_retries;
+        let mut retry_after: Option<Duration> = None;
 
         let mut last_error: Option<String> = None;
         let mut response_opt: Option<reqwest::Response> = None;
 
         // Note: x402 streaming not yet supported, fall back to regular client
-        for attempt in 0..=MAX_RETRIES {
+        for attempt in 0..=max_retries {
             if attempt > 0 {
-                let delay_ms = BASE_DELAY_MS * (1 << (attempt - 1));
+                let delay_ms = self.retry_policy.delay(attempt, retry_after).as_millis() as u64;
                 let wait_secs = delay_ms / 1000;
-                log::warn!(
+                tracing::warn!(
                     "[OPENAI] Streaming retry attempt {}/{} after {}ms delay",
                     attempt,
-                    MAX_RETRIES,
+                    max_retries,
                     delay_ms
                 );
                 // Emit retry event to frontend
                 self.emit_retry_event(
                     attempt,
-                    MAX_RETRIES,
+                    max_retries,
                     wait_secs,
                     last_error.as_deref().unwrap_or("Unknown error"),
                 );
@@ -700,12 +824,12 @@ impl OpenAIClient {
                 Ok(r) => r,
                 Err(e) => {
                     last_error = Some(format!("OpenAI API streaming request failed: {}", e));
-                    if attempt < MAX_RETRIES {
-                        log::warn!("[OPENAI] Streaming request failed (attempt {}): {}, will retry", attempt + 1, e);
+                    if attempt < max_retries {
+                        tracing::warn!("[OPENAI] Streaming request failed (attempt {}): {}, will retry", attempt + 1, e);
                         continue;
                     }
                     let _ = stream_sender.send(StreamEvent::Error {
-                        message: format!("Request failed after {} retries: {}", MAX_RETRIES, e),
+                        message: format!("Request failed after {} retries: {}", max_retries, e),
                         code: None,
                     }).await;
                     return Err(last_error.unwrap());
@@ -714,7 +838,8 @@ impl OpenAIClient {
 
             let status = response.status();
             let status_code = status.as_u16();
-            let is_retryable = matches!(status_code, 429 | 502 | 503 | 504);
+            let is_retryable = retry::is_retryable_status(status_code);
+            retry_after = retry::retry_after(response.headers());
 
             if !status.is_success() {
                 let error_text = response.text().await.unwrap_or_default();
@@ -730,8 +855,8 @@ impl OpenAIClient {
                     error_text.contains("network error")
                 );
 
-                if (is_retryable || is_transient_402) && attempt < MAX_RETRIES {
-                    log::warn!(
+                if (is_retryable || is_transient_402) && attempt < max_retries {
+                    tracing::warn!(
                         "[OPENAI] Streaming received retryable status {} (attempt {}), will retry",
                         status,
                         attempt + 1
@@ -763,6 +888,7 @@ impl OpenAIClient {
             std::collections::HashMap::new(); // index -> (id, name, arguments)
         let mut finish_reason: Option<String> = None;
         let mut usage: Option<(u32, u32)> = None;
+        let mut system_fingerprint: Option<String> = None;
 
         while let Some(chunk_result) = stream.next().await {
             let chunk = chunk_result
@@ -837,6 +963,10 @@ impl OpenAIClient {
                             }
                         }
 
+                        if chunk_data.system_fingerprint.is_some() {
+                            system_fingerprint = chunk_data.system_fingerprint;
+                        }
+
                         // Capture usage if present
                         if let Some(u) = chunk_data.usage {
                             usage = Some((
@@ -890,7 +1020,32 @@ impl OpenAIClient {
             } else {
                 Some("end_turn".to_string())
             },
+            thinking: Vec::new(),
             x402_payment: None, // Streaming doesn't support x402 yet
+            usage: self.usage_metadata(system_fingerprint, usage),
         })
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ai::{ImageAttachment, MessageRole};
+
+    #[test]
+    fn test_images_become_content_parts() {
+        let image = ImageAttachment { media_type: "image/png".to_string(), data: "iVBORw0KGgo=".to_string() };
+        let message = Message { role: MessageRole::User, content: "What broke?".to_string(), images: vec![image] };
+
+        let wire = serde_json::to_value(OpenAIMessage::from_message(message.clone(), true)).unwrap();
+        assert_eq!(
+            wire["content"],
+            json!([
+                { "type": "text", "text": "What broke?" },
+                { "type": "image_url", "image_url": { "url": "data:image/png;base64,iVBORw0KGgo=" } },
+            ])
+        );
+        let wire = serde_json::to_value(OpenAIMessage::from_message(message, false)).unwrap();
+        assert!(wire["content"].as_str().unwrap().starts_with("What broke?\n\n[1 image(s) attached"));
+    }
+}
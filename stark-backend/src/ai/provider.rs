@@ -0,0 +1,307 @@
+//! Provider-neutral tool calling
+//!
+//! The dispatcher, tool registry and history only deal in [`ToolCall`] and
+//! [`ToolResultMsg`]. Each LLM client implements [`LlmProvider`] to translate
+//! those into its own wire messages and to read tool calls back out of them.
+
+use crate::ai::llama::{OllamaFunctionCall, OllamaToolCall};
+use crate::ai::openai::{OpenAIContent, OpenAIFunctionCall, OpenAIMessage, OpenAIToolCall};
+use crate::ai::types::{ClaudeContentBlock, ClaudeMessageContent};
+use crate::ai::{
+    ClaudeClient, LlamaClient, LlamaMessage, OpenAIClient, ToolCall, ToolHistoryEntry,
+    ToolResponse, TypedClaudeMessage as ClaudeMessage,
+};
+use serde_json::{json, Value};
+
+/// Neutral result of executing a tool call
+pub type ToolResultMsg = ToolResponse;
+
+/// Translation between neutral tool calls/results and a provider's wire format
+pub trait LlmProvider {
+    /// Message type sent to the provider's chat endpoint
+    type WireMessage;
+    /// A single tool call as it appears in the provider's response
+    type WireToolCall;
+
+    /// Encode one round of tool calls and their results as wire messages
+    fn tool_round_to_wire(calls: &[ToolCall], results: &[ToolResultMsg]) -> Vec<Self::WireMessage>;
+
+    /// Decode a tool call from the provider's response.
+    /// `index` is the call's position in the response, for providers that omit ids.
+    fn tool_call_from_wire(call: &Self::WireToolCall, index: usize) -> Option<ToolCall>;
+
+    /// Encode one history entry. Providers that must replay the text or
+    /// reasoning that came with the calls override this.
+    fn tool_entry_to_wire(entry: &ToolHistoryEntry) -> Vec<Self::WireMessage> {
+        Self::tool_round_to_wire(&entry.tool_calls, &entry.tool_responses)
+    }
+
+    /// Encode the full tool history, oldest round first
+    fn tool_history_to_wire(history: &[ToolHistoryEntry]) -> Vec<Self::WireMessage> {
+        history.iter().flat_map(Self::tool_entry_to_wire).collect()
+    }
+}
+
+impl LlmProvider for ClaudeClient {
+    type WireMessage = ClaudeMessage;
+    type WireToolCall = ClaudeContentBlock;
+
+    fn tool_round_to_wire(calls: &[ToolCall], results: &[ToolResultMsg]) -> Vec<ClaudeMessage> {
+        // Assistant message with tool_use blocks
+        let tool_use_blocks: Vec<ClaudeContentBlock> = calls
+            .iter()
+            .map(|tc| ClaudeContentBlock::ToolUse {
+                id: tc.id.clone(),
+                name: tc.name.clone(),
+                input: tc.arguments.clone(),
+            })
+            .collect();
+
+        // User message with tool_result blocks
+        let tool_result_blocks: Vec<ClaudeContentBlock> = results
+            .iter()
+            .map(|tr| {
+                ClaudeContentBlock::tool_result(tr.tool_call_id.clone(), tr.content.clone(), tr.is_error)
+            })
+            .collect();
+
+        vec![
+            ClaudeMessage::assistant_with_blocks(tool_use_blocks),
+            ClaudeMessage::user_with_tool_results(tool_result_blocks),
+        ]
+    }
+
+    /// Thinking blocks, then the text, then the tool_use blocks: the order
+    /// Claude produced them in, which it requires when thinking is enabled
+    fn tool_entry_to_wire(entry: &ToolHistoryEntry) -> Vec<ClaudeMessage> {
+        let mut messages = Self::tool_round_to_wire(&entry.tool_calls, &entry.tool_responses);
+        let mut leading: Vec<ClaudeContentBlock> = entry.thinking.iter().map(ClaudeContentBlock::thinking).collect();
+        if !entry.content.trim().is_empty() {
+            leading.push(ClaudeContentBlock::text(entry.content.clone()));
+        }
+        if let ClaudeMessageContent::Blocks(blocks) = &mut messages[0].content {
+            blocks.splice(0..0, leading);
+        }
+        messages
+    }
+
+    fn tool_call_from_wire(call: &ClaudeContentBlock, _index: usize) -> Option<ToolCall> {
+        match call {
+            ClaudeContentBlock::ToolUse { id, name, input } => Some(ToolCall {
+                id: id.clone(),
+                name: name.clone(),
+                arguments: input.clone(),
+            }),
+            _ => None,
+        }
+    }
+}
+
+impl LlmProvider for OpenAIClient {
+    type WireMessage = OpenAIMessage;
+    type WireToolCall = OpenAIToolCall;
+
+    fn tool_round_to_wire(calls: &[ToolCall], results: &[ToolResultMsg]) -> Vec<OpenAIMessage> {
+        let openai_tool_calls: Vec<OpenAIToolCall> = calls
+            .iter()
+            .map(|tc| OpenAIToolCall {
+                id: tc.id.clone(),
+                call_type: "function".to_string(),
+                function: OpenAIFunctionCall {
+                    name: tc.name.clone(),
+                    // OpenAI carries arguments as a JSON-encoded string
+                    arguments: serde_json::to_string(&tc.arguments).unwrap_or_default(),
+                },
+            })
+            .collect();
+
+        let mut messages = vec![OpenAIMessage {
+            role: "assistant".to_string(),
+            content: Some(OpenAIContent::Text(String::new())), // Kimi requires content field even if empty
+            tool_calls: Some(openai_tool_calls),
+            tool_call_id: None,
+        }];
+
+        messages.extend(results.iter().map(|response| OpenAIMessage {
+            role: "tool".to_string(),
+            content: Some(OpenAIContent::Text(response.content.clone())),
+            tool_calls: None,
+            tool_call_id: Some(response.tool_call_id.clone()),
+        }));
+
+        messages
+    }
+
+    fn tool_call_from_wire(call: &OpenAIToolCall, _index: usize) -> Option<ToolCall> {
+        let arguments: Value = serde_json::from_str(&call.function.arguments).unwrap_or(json!({}));
+        Some(ToolCall {
+            id: call.id.clone(),
+            name: call.function.name.clone(),
+            arguments,
+        })
+    }
+}
+
+impl LlmProvider for LlamaClient {
+    type WireMessage = LlamaMessage;
+    type WireToolCall = OllamaToolCall;
+
+    fn tool_round_to_wire(calls: &[ToolCall], results: &[ToolResultMsg]) -> Vec<LlamaMessage> {
+        let ollama_tool_calls: Vec<OllamaToolCall> = calls
+            .iter()
+            .map(|tc| OllamaToolCall {
+                id: Some(tc.id.clone()),
+                function: OllamaFunctionCall {
+                    name: tc.name.clone(),
+                    arguments: tc.arguments.clone(),
+                },
+            })
+            .collect();
+
+        let mut messages = vec![LlamaMessage {
+            role: "assistant".to_string(),
+            content: String::new(),
+            tool_calls: Some(ollama_tool_calls),
+        }];
+
+        // Ollama matches tool results to calls by order, not id
+        messages.extend(results.iter().map(|response| LlamaMessage {
+            role: "tool".to_string(),
+            content: response.content.clone(),
+            tool_calls: None,
+        }));
+
+        messages
+    }
+
+    fn tool_call_from_wire(call: &OllamaToolCall, index: usize) -> Option<ToolCall> {
+        Some(ToolCall {
+            id: call.id.clone().unwrap_or_else(|| format!("call_{}", index)),
+            name: call.function.name.clone(),
+            arguments: call.function.arguments.clone(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ai::types::ThinkingBlock;
+
+    fn sample_round() -> (Vec<ToolCall>, Vec<ToolResultMsg>) {
+        let calls = vec![ToolCall {
+            id: "call_1".to_string(),
+            name: "read_file".to_string(),
+            arguments: json!({ "path": "src/main.rs", "limit": 20 }),
+        }];
+        let results = vec![ToolResponse::error("call_1".to_string(), "not found".to_string())];
+        (calls, results)
+    }
+
+    #[test]
+    fn test_claude_tool_round_trip() {
+        let (calls, results) = sample_round();
+        let wire = ClaudeClient::tool_round_to_wire(&calls, &results);
+
+        let json = serde_json::to_value(&wire).unwrap();
+        assert_eq!(
+            json,
+            json!([
+                {
+                    "role": "assistant",
+                    "content": [{
+                        "type": "tool_use",
+                        "id": "call_1",
+                        "name": "read_file",
+                        "input": { "path": "src/main.rs", "limit": 20 }
+                    }]
+                },
+                {
+                    "role": "user",
+                    "content": [{
+                        "type": "tool_result",
+                        "tool_use_id": "call_1",
+                        "content": "not found",
+                        "is_error": true
+                    }]
+                }
+            ])
+        );
+
+        let parsed: Vec<ClaudeMessage> = serde_json::from_value(json).unwrap();
+        let ClaudeMessageContent::Blocks(ref blocks) = parsed[0].content else {
+            panic!("expected content blocks");
+        };
+        let decoded = ClaudeClient::tool_call_from_wire(&blocks[0], 0).unwrap();
+        assert_eq!(decoded.id, calls[0].id);
+        assert_eq!(decoded.name, calls[0].name);
+        assert_eq!(decoded.arguments, calls[0].arguments);
+    }
+
+    #[test]
+    fn test_openai_tool_round_trip() {
+        let (calls, results) = sample_round();
+        let wire = OpenAIClient::tool_round_to_wire(&calls, &results);
+
+        let json = serde_json::to_value(&wire).unwrap();
+        assert_eq!(json[0]["role"], "assistant");
+        assert_eq!(json[0]["tool_calls"][0]["type"], "function");
+        assert_eq!(json[0]["tool_calls"][0]["function"]["name"], "read_file");
+        // Arguments travel as a JSON string, not an object
+        assert!(json[0]["tool_calls"][0]["function"]["arguments"].is_string());
+        assert_eq!(
+            json[1],
+            json!({ "role": "tool", "content": "not found", "tool_call_id": "call_1" })
+        );
+
+        let parsed: Vec<OpenAIMessage> = serde_json::from_value(json).unwrap();
+        let wire_call = &parsed[0].tool_calls.as_ref().unwrap()[0];
+        let decoded = OpenAIClient::tool_call_from_wire(wire_call, 0).unwrap();
+        assert_eq!(decoded.id, calls[0].id);
+        assert_eq!(decoded.name, calls[0].name);
+        assert_eq!(decoded.arguments, calls[0].arguments);
+    }
+
+    #[test]
+    fn test_history_flattens_rounds_in_order() {
+        let (calls, results) = sample_round();
+        let history = vec![
+            ToolHistoryEntry::new(calls.clone(), results.clone()),
+            ToolHistoryEntry::new(calls, results),
+        ];
+
+        assert_eq!(ClaudeClient::tool_history_to_wire(&history).len(), 4);
+        assert_eq!(OpenAIClient::tool_history_to_wire(&history).len(), 4);
+        assert_eq!(LlamaClient::tool_history_to_wire(&history).len(), 4);
+    }
+
+    #[test]
+    fn test_claude_replays_thinking_and_parallel_calls() {
+        let calls = vec![
+            ToolCall { id: "toolu_1".to_string(), name: "read_file".to_string(), arguments: json!({ "path": "a.rs" }) },
+            ToolCall { id: "toolu_2".to_string(), name: "read_file".to_string(), arguments: json!({ "path": "b.rs" }) },
+        ];
+        let results = vec![
+            ToolResponse::success("toolu_1".to_string(), "fn a() {}".to_string()),
+            ToolResponse::success("toolu_2".to_string(), "fn b() {}".to_string()),
+        ];
+        let mut entry = ToolHistoryEntry::new(calls, results);
+        entry.content = "Reading both files.".to_string();
+        entry.thinking = vec![
+            ThinkingBlock::Thinking { thinking: "Need both.".to_string(), signature: "sig".to_string() },
+            ThinkingBlock::RedactedThinking { data: "opaque".to_string() },
+        ];
+
+        let json = serde_json::to_value(ClaudeClient::tool_history_to_wire(&[entry.clone()])).unwrap();
+        let types: Vec<&str> = json[0]["content"].as_array().unwrap().iter().map(|b| b["type"].as_str().unwrap()).collect();
+        assert_eq!(types, ["thinking", "redacted_thinking", "text", "tool_use", "tool_use"]);
+        assert_eq!(json[0]["content"][0]["signature"], "sig");
+        // All results of a parallel round go back in a single user message
+        assert_eq!(json.as_array().unwrap().len(), 2);
+        assert_eq!(json[1]["content"].as_array().unwrap().len(), 2);
+
+        // Other providers only replay the calls
+        let openai = serde_json::to_value(OpenAIClient::tool_history_to_wire(&[entry])).unwrap();
+        assert_eq!(openai[0]["content"], "");
+    }
+}
@@ -0,0 +1,234 @@
+//! Retrying provider calls
+//!
+//! [`RetryPolicy`] is the backoff every client applies to transient failures
+//! (rate limits, overloaded or failing upstreams, dropped connections). A
+//! `Retry-After` header from the provider takes precedence over the computed
+//! delay.
+//!
+//! [`FailoverClient`] runs the retries itself instead, so it can see every
+//! attempt: it records them, and once the primary provider has used up its
+//! retries it moves on to a fallback endpoint or model for the rest of the run.
+
+use crate::ai::{AiClient, AiError, AiResponse, Message, ToolHistoryEntry};
+use crate::tools::ToolDefinition;
+use chrono::{DateTime, Utc};
+use reqwest::header::{HeaderMap, RETRY_AFTER};
+use serde::{Deserialize, Serialize};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::Duration;
+
+/// Status codes worth another try: rate limits, server errors and Anthropic's "overloaded"
+pub fn is_retryable_status(status_code: u16) -> bool {
+    matches!(status_code, 429 | 500 | 502 | 503 | 504 | 529)
+}
+
+/// Delay requested by a `Retry-After` header, given in seconds or as an HTTP date
+pub fn retry_after(headers: &HeaderMap) -> Option<Duration> {
+    let value = headers.get(RETRY_AFTER)?.to_str().ok()?.trim();
+    if let Ok(seconds) = value.parse::<u64>() {
+        return Some(Duration::from_secs(seconds));
+    }
+    let at = DateTime::parse_from_rfc2822(value).ok()?.with_timezone(&Utc);
+    Some((at - Utc::now()).to_std().unwrap_or(Duration::ZERO))
+}
+
+/// How often and how long to wait before retrying a failed provider call
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RetryPolicy {
+    /// Retries after the first attempt
+    pub max_retries: u32,
+    /// Wait before the first retry; doubles with every retry after it
+    pub base_delay: Duration,
+    /// Longest wait, including one asked for by `Retry-After`
+    pub max_delay: Duration,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_retries: 3,
+            base_delay: Duration::from_secs(2),
+            max_delay: Duration::from_secs(60),
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// A single attempt, for callers that retry on their own
+    pub fn none() -> Self {
+        Self {
+            max_retries: 0,
+            ..Self::default()
+        }
+    }
+
+    /// Wait before retry number `retry` (1-based)
+    pub fn delay(&self, retry: u32, retry_after: Option<Duration>) -> Duration {
+        let backoff = self
+            .base_delay
+            .saturating_mul(2u32.saturating_pow(retry.saturating_sub(1)));
+        retry_after.unwrap_or(backoff).min(self.max_delay)
+    }
+
+    /// Whether `error` is worth retrying. Errors without a status are network
+    /// failures or garbled responses.
+    pub fn should_retry(error: &AiError) -> bool {
+        error.status_code.is_none_or(is_retryable_status)
+    }
+}
+
+/// A provider call that failed, as kept in a run's attempt history
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ProviderAttempt {
+    /// Model round-trip of the run the attempt belonged to
+    pub iteration: usize,
+    /// "primary" or "fallback"
+    pub provider: String,
+    pub endpoint: String,
+    pub model: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub status_code: Option<u16>,
+    pub error: String,
+    /// Wait before the next attempt; absent when the provider was given up on
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub retry_after_ms: Option<u64>,
+    pub at: String,
+}
+
+/// A primary client and an optional fallback, with retries between attempts
+pub struct FailoverClient {
+    providers: Vec<(&'static str, AiClient)>,
+    policy: RetryPolicy,
+    /// Provider in use; failover lasts for the rest of the run
+    active: AtomicUsize,
+}
+
+impl FailoverClient {
+    pub fn new(primary: AiClient) -> Self {
+        Self {
+            // Retries happen here, where they can be recorded
+            providers: vec![("primary", primary.with_retry_policy(RetryPolicy::none()))],
+            policy: RetryPolicy::default(),
+            active: AtomicUsize::new(0),
+        }
+    }
+
+    /// Provider to switch to once the primary one keeps failing
+    pub fn with_fallback(mut self, fallback: AiClient) -> Self {
+        self.providers.truncate(1);
+        self.providers.push(("fallback", fallback.with_retry_policy(RetryPolicy::none())));
+        self
+    }
+
+    /// `generate_with_tools` on the active provider, retrying transient errors
+    /// and failing over when they persist. Failed attempts are appended to
+    /// `attempts`.
+    pub async fn generate_with_tools(
+        &self,
+        iteration: usize,
+        messages: Vec<Message>,
+        tool_history: Vec<ToolHistoryEntry>,
+        tools: Vec<ToolDefinition>,
+        attempts: &mut Vec<ProviderAttempt>,
+    ) -> Result<AiResponse, AiError> {
+        let mut index = self.active.load(Ordering::SeqCst);
+        let mut retry = 0;
+        loop {
+            let (label, client) = &self.providers[index];
+            let error = match client
+                .generate_with_tools(messages.clone(), tool_history.clone(), tools.clone())
+                .await
+            {
+                Ok(response) => return Ok(response),
+                Err(e) => e,
+            };
+
+            let retryable = RetryPolicy::should_retry(&error);
+            let wait = (retryable && retry < self.policy.max_retries)
+                .then(|| self.policy.delay(retry + 1, error.retry_after));
+            attempts.push(ProviderAttempt {
+                iteration,
+                provider: label.to_string(),
+                endpoint: client.endpoint().to_string(),
+                model: client.model().to_string(),
+                status_code: error.status_code,
+                error: error.message.clone(),
+                retry_after_ms: wait.map(|w| w.as_millis() as u64),
+                at: Utc::now().to_rfc3339(),
+            });
+
+            if let Some(wait) = wait {
+                retry += 1;
+                tracing::warn!(
+                    "[FAILOVER] {} provider failed ({}), retry {}/{} in {} ms",
+                    label,
+                    error,
+                    retry,
+                    self.policy.max_retries,
+                    wait.as_millis()
+                );
+                tokio::time::sleep(wait).await;
+                continue;
+            }
+            // Errors in the request itself would fail on the fallback just the same
+            if !retryable || index + 1 >= self.providers.len() {
+                return Err(error);
+            }
+            index += 1;
+            retry = 0;
+            self.active.store(index, Ordering::SeqCst);
+            tracing::warn!(
+                "[FAILOVER] Primary provider failed after {} retries ({}), switching to {} ({})",
+                self.policy.max_retries,
+                error,
+                self.providers[index].1.endpoint(),
+                self.providers[index].1.model()
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use reqwest::header::HeaderValue;
+
+    #[test]
+    fn test_delay_backs_off_and_honors_retry_after() {
+        let policy = RetryPolicy::default();
+        assert_eq!(policy.delay(1, None), Duration::from_secs(2));
+        assert_eq!(policy.delay(3, None), Duration::from_secs(8));
+        assert_eq!(policy.delay(10, None), Duration::from_secs(60));
+        assert_eq!(policy.delay(1, Some(Duration::from_secs(30))), Duration::from_secs(30));
+        assert_eq!(policy.delay(1, Some(Duration::from_secs(600))), Duration::from_secs(60));
+    }
+
+    #[test]
+    fn test_retry_after_header() {
+        let mut headers = HeaderMap::new();
+        assert_eq!(retry_after(&headers), None);
+
+        headers.insert(RETRY_AFTER, HeaderValue::from_static("7"));
+        assert_eq!(retry_after(&headers), Some(Duration::from_secs(7)));
+
+        let at = (Utc::now() + chrono::Duration::seconds(120)).to_rfc2822();
+        headers.insert(RETRY_AFTER, HeaderValue::from_str(&at).unwrap());
+        let wait = retry_after(&headers).unwrap();
+        assert!(wait > Duration::from_secs(100) && wait <= Duration::from_secs(120));
+
+        headers.insert(RETRY_AFTER, HeaderValue::from_static("Wed, 21 Oct 2015 07:28:00 GMT"));
+        assert_eq!(retry_after(&headers), Some(Duration::ZERO));
+
+        headers.insert(RETRY_AFTER, HeaderValue::from_static("soon"));
+        assert_eq!(retry_after(&headers), None);
+    }
+
+    #[test]
+    fn test_should_retry() {
+        assert!(RetryPolicy::should_retry(&AiError::with_status("rate limited", 429)));
+        assert!(RetryPolicy::should_retry(&AiError::with_status("internal error", 500)));
+        assert!(RetryPolicy::should_retry(&AiError::new("connection reset")));
+        assert!(!RetryPolicy::should_retry(&AiError::with_status("bad request", 400)));
+        assert!(!RetryPolicy::should_retry(&AiError::with_status("unauthorized", 401)));
+    }
+}
@@ -0,0 +1,374 @@
+//! Structured output
+//!
+//! A chat or agent request can ask for its answer as JSON with a
+//! [`ResponseFormat`]: any JSON object, or a value matching a JSON schema.
+//! Providers with a JSON mode are asked for it (see `JsonMode` in the
+//! archetype profile); every model also gets [`ResponseFormat::instructions`]
+//! in its system prompt. The final answer is then checked, and when it does
+//! not parse or match the schema the model is asked to repair it, up to
+//! [`MAX_REPAIR_ATTEMPTS`] times.
+//!
+//! Schemas are checked against a subset of JSON Schema: `type`, `enum`,
+//! `const`, `properties`, `required`, `additionalProperties: false`, `items`,
+//! the length and item count bounds, and `minimum`/`maximum`. Other keywords
+//! are passed to the provider but not checked here.
+
+use super::{Message, MessageRole};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+
+/// Repair rounds after the first answer fails the check
+pub const MAX_REPAIR_ATTEMPTS: usize = 2;
+
+/// Most problems listed when asking the model for a repair
+const MAX_REPORTED_ERRORS: usize = 10;
+
+/// Requested shape of the answer, in the shape of OpenAI's `response_format`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ResponseFormat {
+    /// Any JSON object
+    JsonObject,
+    /// A JSON value matching `json_schema.schema`
+    JsonSchema { json_schema: JsonSchemaFormat },
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct JsonSchemaFormat {
+    /// Name of the schema, as providers want one
+    #[serde(default = "default_schema_name")]
+    pub name: String,
+    pub schema: Value,
+}
+
+fn default_schema_name() -> String {
+    "response".to_string()
+}
+
+impl ResponseFormat {
+    /// Check the format itself, before any model is called
+    pub fn validate(&self) -> Result<(), String> {
+        let ResponseFormat::JsonSchema { json_schema } = self else {
+            return Ok(());
+        };
+        let name_ok = !json_schema.name.is_empty()
+            && json_schema.name.len() <= 64
+            && json_schema.name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-');
+        if !name_ok {
+            return Err("json_schema.name must be 1-64 letters, digits, '_' or '-'".to_string());
+        }
+        check_schema(&json_schema.schema, "schema")
+    }
+
+    /// The schema answers must match, if any
+    pub fn schema(&self) -> Option<&Value> {
+        match self {
+            ResponseFormat::JsonObject => None,
+            ResponseFormat::JsonSchema { json_schema } => Some(&json_schema.schema),
+        }
+    }
+
+    /// Added to the system prompt so any model knows what to answer with
+    pub fn instructions(&self) -> String {
+        match self.schema() {
+            None => "Your final answer must be a single JSON object and nothing else: no prose or code fences \
+                     around it."
+                .to_string(),
+            Some(schema) => format!(
+                "Your final answer must be a single JSON value matching this JSON schema, and nothing else: \
+                 no prose or code fences around it.\n{}",
+                schema
+            ),
+        }
+    }
+
+    /// Parse an answer and check it against the format, returning the value
+    /// or what is wrong with it
+    pub fn check(&self, answer: &str) -> Result<Value, String> {
+        let value = extract_json(answer).ok_or_else(|| "The answer is not valid JSON".to_string())?;
+        let mut errors = Vec::new();
+        match self.schema() {
+            None if !value.is_object() => errors.push("$: expected a JSON object".to_string()),
+            None => {}
+            Some(schema) => validate(&value, schema, "$", &mut errors),
+        }
+        if errors.is_empty() {
+            return Ok(value);
+        }
+        let more = errors.len().saturating_sub(MAX_REPORTED_ERRORS);
+        errors.truncate(MAX_REPORTED_ERRORS);
+        if more > 0 {
+            errors.push(format!("... and {} more", more));
+        }
+        Err(errors.join("\n"))
+    }
+
+    /// Messages asking the model to turn a failed answer into a valid one
+    pub fn repair_messages(&self, answer: &str, problems: &str) -> Vec<Message> {
+        vec![
+            Message {
+                role: MessageRole::System,
+                content: format!(
+                    "You fix answers that were supposed to be JSON. {} Keep the content of the answer; only \
+                     change what is needed to make it valid.",
+                    self.instructions()
+                ),
+                images: Vec::new(),
+            },
+            Message {
+                role: MessageRole::User,
+                content: format!("Answer:\n{}\n\nProblems:\n{}", answer, problems),
+                images: Vec::new(),
+            },
+        ]
+    }
+
+    /// `response_format` for an OpenAI-compatible request
+    pub fn to_openai(&self, schemas: bool) -> Value {
+        match self {
+            ResponseFormat::JsonSchema { json_schema } if schemas => json!({
+                "type": "json_schema",
+                "json_schema": { "name": json_schema.name, "schema": json_schema.schema }
+            }),
+            _ => json!({ "type": "json_object" }),
+        }
+    }
+}
+
+/// The JSON in a model's answer: the whole answer, the inside of a code
+/// fence, or the outermost object or array in it
+pub fn extract_json(answer: &str) -> Option<Value> {
+    let answer = answer.trim();
+    if let Ok(value) = serde_json::from_str(answer) {
+        return Some(value);
+    }
+    if let Some(start) = answer.find("```") {
+        let rest = &answer[start + 3..];
+        let body = rest.find('\n').map_or(rest, |newline| &rest[newline + 1..]);
+        if let Some(value) = body.find("```").and_then(|end| serde_json::from_str(body[..end].trim()).ok()) {
+            return Some(value);
+        }
+    }
+    let start = answer.find(['{', '['])?;
+    let close = if answer[start..].starts_with('{') { '}' } else { ']' };
+    let end = answer.rfind(close)?;
+    (end > start).then(|| serde_json::from_str(&answer[start..=end]).ok()).flatten()
+}
+
+const SCHEMA_TYPES: [&str; 7] = ["object", "array", "string", "number", "integer", "boolean", "null"];
+
+/// Check that `schema` is a schema this module can enforce
+fn check_schema(schema: &Value, path: &str) -> Result<(), String> {
+    let Some(object) = schema.as_object() else {
+        return Err(format!("{} must be a JSON object", path));
+    };
+    if let Some(types) = object.get("type") {
+        let names: Vec<&Value> = match types {
+            Value::Array(names) => names.iter().collect(),
+            name => vec![name],
+        };
+        for name in names {
+            if !name.as_str().is_some_and(|n| SCHEMA_TYPES.contains(&n)) {
+                return Err(format!("{}.type has an unknown type: {}", path, name));
+            }
+        }
+    }
+    if let Some(properties) = object.get("properties") {
+        let properties = properties
+            .as_object()
+            .ok_or_else(|| format!("{}.properties must be an object", path))?;
+        for (name, property) in properties {
+            check_schema(property, &format!("{}.properties.{}", path, name))?;
+        }
+    }
+    let required_ok = |r: &Value| r.as_array().is_some_and(|r| r.iter().all(Value::is_string));
+    if object.get("required").is_some_and(|r| !required_ok(r)) {
+        return Err(format!("{}.required must be an array of property names", path));
+    }
+    if let Some(items) = object.get("items") {
+        check_schema(items, &format!("{}.items", path))?;
+    }
+    if object.get("enum").is_some_and(|e| !e.is_array()) {
+        return Err(format!("{}.enum must be an array", path));
+    }
+    Ok(())
+}
+
+fn type_matches(value: &Value, name: &str) -> bool {
+    match name {
+        "object" => value.is_object(),
+        "array" => value.is_array(),
+        "string" => value.is_string(),
+        "number" => value.is_number(),
+        "integer" => value.is_i64() || value.is_u64() || value.as_f64().is_some_and(|n| n.fract() == 0.0),
+        "boolean" => value.is_boolean(),
+        "null" => value.is_null(),
+        _ => true,
+    }
+}
+
+/// Collect where `value` breaks `schema`, with JSONPath-like locations
+fn validate(value: &Value, schema: &Value, path: &str, errors: &mut Vec<String>) {
+    let Some(schema) = schema.as_object() else {
+        return;
+    };
+
+    if let Some(types) = schema.get("type") {
+        let names: Vec<&str> = match types {
+            Value::Array(names) => names.iter().filter_map(Value::as_str).collect(),
+            name => name.as_str().into_iter().collect(),
+        };
+        if !names.iter().any(|name| type_matches(value, name)) {
+            errors.push(format!("{}: expected {}, got {}", path, names.join(" or "), json_type(value)));
+            return;
+        }
+    }
+    if let Some(allowed) = schema.get("enum").and_then(Value::as_array).filter(|a| !a.contains(value)) {
+        errors.push(format!("{}: must be one of {}", path, Value::Array(allowed.clone())));
+    }
+    if let Some(expected) = schema.get("const").filter(|e| *e != value) {
+        errors.push(format!("{}: must be {}", path, expected));
+    }
+
+    match value {
+        Value::Object(object) => {
+            let properties = schema.get("properties").and_then(Value::as_object);
+            for name in schema.get("required").and_then(Value::as_array).into_iter().flatten() {
+                if let Some(name) = name.as_str().filter(|n| !object.contains_key(*n)) {
+                    errors.push(format!("{}: missing required property \"{}\"", path, name));
+                }
+            }
+            for (name, field) in object {
+                match properties.and_then(|p| p.get(name)) {
+                    Some(property) => validate(field, property, &format!("{}.{}", path, name), errors),
+                    None if schema.get("additionalProperties") == Some(&Value::Bool(false)) => {
+                        errors.push(format!("{}: unexpected property \"{}\"", path, name))
+                    }
+                    None => {}
+                }
+            }
+        }
+        Value::Array(items) => {
+            bounds(items.len(), schema, "minItems", "maxItems", "items", path, errors);
+            if let Some(item_schema) = schema.get("items") {
+                for (i, item) in items.iter().enumerate() {
+                    validate(item, item_schema, &format!("{}[{}]", path, i), errors);
+                }
+            }
+        }
+        Value::String(s) => bounds(s.chars().count(), schema, "minLength", "maxLength", "characters", path, errors),
+        Value::Number(n) => {
+            let n = n.as_f64().unwrap_or_default();
+            if schema.get("minimum").and_then(Value::as_f64).is_some_and(|min| n < min) {
+                errors.push(format!("{}: must be at least {}", path, schema["minimum"]));
+            }
+            if schema.get("maximum").and_then(Value::as_f64).is_some_and(|max| n > max) {
+                errors.push(format!("{}: must be at most {}", path, schema["maximum"]));
+            }
+        }
+        _ => {}
+    }
+}
+
+fn bounds(len: usize, schema: &serde_json::Map<String, Value>, min: &str, max: &str, unit: &str, path: &str, errors: &mut Vec<String>) {
+    if let Some(min) = schema.get(min).and_then(Value::as_u64).filter(|&m| (len as u64) < m) {
+        errors.push(format!("{}: needs at least {} {}", path, min, unit));
+    }
+    if let Some(max) = schema.get(max).and_then(Value::as_u64).filter(|&m| (len as u64) > m) {
+        errors.push(format!("{}: allows at most {} {}", path, max, unit));
+    }
+}
+
+fn json_type(value: &Value) -> &'static str {
+    match value {
+        Value::Object(_) => "object",
+        Value::Array(_) => "array",
+        Value::String(_) => "string",
+        Value::Number(_) => "number",
+        Value::Bool(_) => "boolean",
+        Value::Null => "null",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn quote_format() -> ResponseFormat {
+        serde_json::from_value(json!({
+            "type": "json_schema",
+            "json_schema": {
+                "name": "swap_quote",
+                "schema": {
+                    "type": "object",
+                    "properties": {
+                        "token": { "type": "string", "minLength": 1 },
+                        "amount": { "type": "number", "minimum": 0 },
+                        "route": { "type": "array", "items": { "type": "string" }, "maxItems": 2 },
+                        "side": { "enum": ["buy", "sell"] }
+                    },
+                    "required": ["token", "amount"],
+                    "additionalProperties": false
+                }
+            }
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn test_extract_json() {
+        assert_eq!(extract_json(" {\"a\": 1} "), Some(json!({ "a": 1 })));
+        assert_eq!(extract_json("Here it is:\n```json\n{\"a\": 1}\n```"), Some(json!({ "a": 1 })));
+        assert_eq!(extract_json("The result is {\"a\": [1, 2]}. Done."), Some(json!({ "a": [1, 2] })));
+        assert_eq!(extract_json("no json here"), None);
+    }
+
+    #[test]
+    fn test_check_against_schema() {
+        let format = quote_format();
+        format.validate().unwrap();
+
+        let value = format.check("{\"token\": \"ETH\", \"amount\": 1.5, \"side\": \"buy\"}").unwrap();
+        assert_eq!(value["token"], "ETH");
+
+        let errors = format
+            .check("{\"token\": \"\", \"amount\": -1, \"route\": [\"a\", 2, \"c\"], \"side\": \"hold\", \"fee\": 0}")
+            .unwrap_err();
+        for expected in [
+            "$.token: needs at least 1 characters",
+            "$.amount: must be at least 0",
+            "$.route: allows at most 2 items",
+            "$.route[1]: expected string, got number",
+            "$.side: must be one of [\"buy\",\"sell\"]",
+            "$: unexpected property \"fee\"",
+        ] {
+            assert!(errors.contains(expected), "{} not in:\n{}", expected, errors);
+        }
+        assert_eq!(format.check("[1]").unwrap_err(), "$: expected object, got array");
+        assert_eq!(format.check("{\"amount\": 3}").unwrap_err(), "$: missing required property \"token\"");
+
+        assert!(ResponseFormat::JsonObject.check("[1, 2]").is_err());
+        assert!(ResponseFormat::JsonObject.check("sure!").is_err());
+    }
+
+    #[test]
+    fn test_validate_format() {
+        let bad_schema: ResponseFormat = serde_json::from_value(json!({
+            "type": "json_schema",
+            "json_schema": { "schema": { "type": "object", "properties": { "a": { "type": "text" } } } }
+        }))
+        .unwrap();
+        assert_eq!(
+            bad_schema.validate().unwrap_err(),
+            "schema.properties.a.type has an unknown type: \"text\""
+        );
+
+        let bad_name = ResponseFormat::JsonSchema {
+            json_schema: JsonSchemaFormat { name: "swap quote".to_string(), schema: json!({}) },
+        };
+        assert!(bad_name.validate().is_err());
+        assert_eq!(ResponseFormat::JsonObject.to_openai(true), json!({ "type": "json_object" }));
+        assert_eq!(quote_format().to_openai(false), json!({ "type": "json_object" }));
+        assert_eq!(quote_format().to_openai(true)["json_schema"]["name"], "swap_quote");
+    }
+}
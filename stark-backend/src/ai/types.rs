@@ -1,6 +1,8 @@
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
 use std::fmt;
+use std::time::Duration;
+use crate::ai::vision::ImageAttachment;
 use crate::x402::X402PaymentInfo;
 
 /// AI API error with status code information
@@ -10,6 +12,8 @@ pub struct AiError {
     pub message: String,
     /// HTTP status code if available
     pub status_code: Option<u16>,
+    /// Wait the provider asked for before trying again (`Retry-After`)
+    pub retry_after: Option<Duration>,
 }
 
 impl AiError {
@@ -17,6 +21,7 @@ impl AiError {
         AiError {
             message: message.into(),
             status_code: None,
+            retry_after: None,
         }
     }
 
@@ -24,9 +29,15 @@ impl AiError {
         AiError {
             message: message.into(),
             status_code: Some(status_code),
+            retry_after: None,
         }
     }
 
+    pub fn with_retry_after(mut self, retry_after: Option<Duration>) -> Self {
+        self.retry_after = retry_after;
+        self
+    }
+
     /// Check if this is a client error (4xx status code)
     /// These errors indicate something wrong with the request that the AI might be able to fix
     pub fn is_client_error(&self) -> bool {
@@ -189,6 +200,12 @@ pub struct ToolHistoryEntry {
     pub tool_calls: Vec<ToolCall>,
     /// The responses from executing those tool calls
     pub tool_responses: Vec<ToolResponse>,
+    /// Text the AI wrote alongside the tool calls
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub content: String,
+    /// Reasoning the AI produced before the tool calls, replayed unchanged
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub thinking: Vec<ThinkingBlock>,
 }
 
 impl ToolHistoryEntry {
@@ -196,10 +213,32 @@ impl ToolHistoryEntry {
         ToolHistoryEntry {
             tool_calls,
             tool_responses,
+            content: String::new(),
+            thinking: Vec::new(),
+        }
+    }
+
+    /// Round for the tool calls of `response`, keeping its text and reasoning
+    pub fn from_response(response: AiResponse, tool_responses: Vec<ToolResponse>) -> Self {
+        ToolHistoryEntry {
+            tool_calls: response.tool_calls,
+            tool_responses,
+            content: response.content,
+            thinking: response.thinking,
         }
     }
 }
 
+/// Extended thinking output. Claude rejects a tool-use turn whose thinking
+/// isn't sent back exactly as returned, signature included.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ThinkingBlock {
+    Thinking { thinking: String, signature: String },
+    /// Thinking the provider encrypted; only `data` is returned
+    RedactedThinking { data: String },
+}
+
 /// Handle context overflow by clearing tool history and creating a recovery entry.
 /// Returns a ToolHistoryEntry with a summary of cleared work and recovery guidance.
 pub fn handle_context_overflow(
@@ -270,6 +309,42 @@ pub fn create_error_feedback(
     )
 }
 
+/// Provider-reported metadata about a single generation call
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct UsageMetadata {
+    /// Sampling seed that was sent to the provider (None if not requested or unsupported)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub seed: Option<u64>,
+    /// Backend configuration fingerprint reported by OpenAI-compatible providers
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub system_fingerprint: Option<String>,
+    /// Notes about requested options the provider could not honor
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub notes: Vec<String>,
+    /// Prompt tokens billed for this call, if the provider reported them
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub input_tokens: Option<u32>,
+    /// Completion tokens billed for this call, if the provider reported them
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub output_tokens: Option<u32>,
+    /// Prompt tokens written to the provider's prompt cache (not included in `input_tokens`)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cache_creation_input_tokens: Option<u32>,
+    /// Prompt tokens served from the provider's prompt cache (not included in `input_tokens`)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cache_read_input_tokens: Option<u32>,
+}
+
+impl UsageMetadata {
+    /// Metadata for a request whose seed was ignored because the provider has no seed support
+    pub fn seed_unsupported(provider: &str) -> Self {
+        UsageMetadata {
+            notes: vec![format!("seed ignored: {} does not support deterministic sampling", provider)],
+            ..Default::default()
+        }
+    }
+}
+
 /// Unified AI response that can contain both text and tool calls
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct AiResponse {
@@ -279,9 +354,15 @@ pub struct AiResponse {
     pub tool_calls: Vec<ToolCall>,
     /// The reason the AI stopped generating
     pub stop_reason: Option<String>,
+    /// Extended thinking blocks, for providers that return them with tool calls
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub thinking: Vec<ThinkingBlock>,
     /// x402 payment info if a payment was made for this request
     #[serde(skip_serializing_if = "Option::is_none")]
     pub x402_payment: Option<X402PaymentInfo>,
+    /// Seed / fingerprint metadata reported for this call
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub usage: Option<UsageMetadata>,
 }
 
 impl AiResponse {
@@ -290,7 +371,9 @@ impl AiResponse {
             content,
             tool_calls: vec![],
             stop_reason: Some("end_turn".to_string()),
+            thinking: Vec::new(),
             x402_payment: None,
+            usage: None,
         }
     }
 
@@ -299,7 +382,9 @@ impl AiResponse {
             content,
             tool_calls,
             stop_reason: Some("tool_use".to_string()),
+            thinking: Vec::new(),
             x402_payment: None,
+            usage: None,
         }
     }
 
@@ -320,12 +405,22 @@ impl AiResponse {
     }
 }
 
+/// Prompt cache breakpoint: everything up to and including the marked block is cached
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum CacheControl {
+    /// Short-lived cache entry (5 minutes, refreshed on every hit)
+    Ephemeral,
+}
+
 /// Tool definition in Claude API format
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ClaudeTool {
     pub name: String,
     pub description: String,
     pub input_schema: Value,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cache_control: Option<CacheControl>,
 }
 
 /// Content block types in Claude API responses
@@ -347,6 +442,21 @@ pub enum ClaudeContentBlock {
         #[serde(skip_serializing_if = "Option::is_none")]
         is_error: Option<bool>,
     },
+    #[serde(rename = "image")]
+    Image { source: ClaudeImageSource },
+    #[serde(rename = "thinking")]
+    Thinking { thinking: String, signature: String },
+    #[serde(rename = "redacted_thinking")]
+    RedactedThinking { data: String },
+}
+
+/// Source of an image block: base64 data and its media type
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ClaudeImageSource {
+    #[serde(rename = "type")]
+    pub source_type: String,
+    pub media_type: String,
+    pub data: String,
 }
 
 impl ClaudeContentBlock {
@@ -354,6 +464,26 @@ impl ClaudeContentBlock {
         ClaudeContentBlock::Text { text: text.into() }
     }
 
+    pub fn image(image: &ImageAttachment) -> Self {
+        ClaudeContentBlock::Image {
+            source: ClaudeImageSource {
+                source_type: "base64".to_string(),
+                media_type: image.media_type.clone(),
+                data: image.data.clone(),
+            },
+        }
+    }
+
+    pub fn thinking(block: &ThinkingBlock) -> Self {
+        match block {
+            ThinkingBlock::Thinking { thinking, signature } => ClaudeContentBlock::Thinking {
+                thinking: thinking.clone(),
+                signature: signature.clone(),
+            },
+            ThinkingBlock::RedactedThinking { data } => ClaudeContentBlock::RedactedThinking { data: data.clone() },
+        }
+    }
+
     pub fn tool_result(tool_use_id: String, content: String, is_error: bool) -> Self {
         ClaudeContentBlock::ToolResult {
             tool_use_id,
@@ -442,4 +572,20 @@ mod tests {
         let error = ToolResponse::error("call_456".to_string(), "Failed".to_string());
         assert!(error.is_error);
     }
+
+    #[test]
+    fn test_usage_metadata_seed_unsupported() {
+        let usage = UsageMetadata::seed_unsupported("claude");
+        assert!(usage.seed.is_none());
+        assert_eq!(usage.notes.len(), 1);
+        assert!(usage.notes[0].contains("claude"));
+
+        // Unset fields are omitted from serialized metadata
+        let json = serde_json::to_value(UsageMetadata {
+            seed: Some(42),
+            ..Default::default()
+        })
+        .unwrap();
+        assert_eq!(json, serde_json::json!({"seed": 42}));
+    }
 }
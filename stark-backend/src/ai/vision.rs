@@ -0,0 +1,152 @@
+//! Image input
+//!
+//! A user message can carry [`ImageAttachment`]s, such as a screenshot of a
+//! broken page or an error dialog. Clients send them inline as base64, or as
+//! the path of an image uploaded into the session's workspace
+//! ([`ImageSource::WorkspaceFile`]), resolved once the session is known.
+//! Models whose archetype profile has `supports_vision` get the images; the
+//! others get [`without_images`], a note that images were attached.
+
+use base64::Engine;
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+/// Largest image accepted, in bytes (the Anthropic API's per-image limit)
+pub const MAX_IMAGE_BYTES: usize = 5 * 1024 * 1024;
+/// Most images attached to one message
+pub const MAX_IMAGES: usize = 8;
+
+/// An image attached to a message
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ImageAttachment {
+    /// `image/png`, `image/jpeg`, `image/gif` or `image/webp`
+    pub media_type: String,
+    /// Base64-encoded image bytes
+    pub data: String,
+}
+
+/// Where an image sent with a message comes from
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ImageSource {
+    Inline(ImageAttachment),
+    /// Path relative to the session's workspace
+    WorkspaceFile(String),
+}
+
+impl ImageAttachment {
+    /// An attachment from raw image bytes; the type is taken from the bytes
+    /// themselves, not from a file name or a client's claim
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, String> {
+        if bytes.len() > MAX_IMAGE_BYTES {
+            return Err(format!("Image is larger than {} MB", MAX_IMAGE_BYTES / (1024 * 1024)));
+        }
+        let media_type = sniff_media_type(bytes).ok_or("Unsupported image type: expected PNG, JPEG, GIF or WebP")?;
+        Ok(Self {
+            media_type: media_type.to_string(),
+            data: base64::engine::general_purpose::STANDARD.encode(bytes),
+        })
+    }
+
+    /// An attachment from base64 data, optionally written as a `data:` URL
+    pub fn from_base64(data: &str) -> Result<Self, String> {
+        let data = match data.split_once(";base64,") {
+            Some((prefix, rest)) if prefix.starts_with("data:") => rest,
+            _ => data,
+        };
+        let cleaned: String = data.chars().filter(|c| !c.is_ascii_whitespace()).collect();
+        // Base64 is 4/3 the size of the bytes; refuse oversized input before decoding it
+        if cleaned.len() / 4 * 3 > MAX_IMAGE_BYTES + 3 {
+            return Err(format!("Image is larger than {} MB", MAX_IMAGE_BYTES / (1024 * 1024)));
+        }
+        let bytes = base64::engine::general_purpose::STANDARD
+            .decode(cleaned)
+            .map_err(|e| format!("Invalid base64 image data: {}", e))?;
+        Self::from_bytes(&bytes)
+    }
+
+    /// The image as a `data:` URL, as OpenAI-compatible APIs take it
+    pub fn data_url(&self) -> String {
+        format!("data:{};base64,{}", self.media_type, self.data)
+    }
+}
+
+impl ImageSource {
+    /// The image, reading workspace files from `workspace`
+    pub fn resolve(&self, workspace: &Path) -> Result<ImageAttachment, String> {
+        match self {
+            ImageSource::Inline(image) => Ok(image.clone()),
+            ImageSource::WorkspaceFile(path) => {
+                let resolved = crate::tools::workspace_path::WorkspacePath::resolve(workspace, path)?;
+                let metadata = std::fs::metadata(resolved.path()).map_err(|e| format!("Image '{}' not found: {}", path, e))?;
+                if !metadata.is_file() {
+                    return Err(format!("Image '{}' is not a file", path));
+                }
+                if metadata.len() > MAX_IMAGE_BYTES as u64 {
+                    return Err(format!("Image '{}' is larger than {} MB", path, MAX_IMAGE_BYTES / (1024 * 1024)));
+                }
+                let bytes = std::fs::read(resolved.path()).map_err(|e| format!("Failed to read image '{}': {}", path, e))?;
+                ImageAttachment::from_bytes(&bytes).map_err(|e| format!("{}: {}", path, e))
+            }
+        }
+    }
+}
+
+/// `content` with a note standing in for images the model can't view
+pub fn without_images(content: &str, images: &[ImageAttachment]) -> String {
+    if images.is_empty() {
+        return content.to_string();
+    }
+    format!(
+        "{}\n\n[{} image(s) attached, but the current model cannot view images]",
+        content,
+        images.len()
+    )
+}
+
+fn sniff_media_type(bytes: &[u8]) -> Option<&'static str> {
+    if bytes.starts_with(b"\x89PNG\r\n\x1a\n") {
+        Some("image/png")
+    } else if bytes.starts_with(&[0xFF, 0xD8, 0xFF]) {
+        Some("image/jpeg")
+    } else if bytes.starts_with(b"GIF87a") || bytes.starts_with(b"GIF89a") {
+        Some("image/gif")
+    } else if bytes.len() >= 12 && &bytes[..4] == b"RIFF" && &bytes[8..12] == b"WEBP" {
+        Some("image/webp")
+    } else {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    const PNG: &[u8] = b"\x89PNG\r\n\x1a\n\0\0\0\rIHDR";
+
+    #[test]
+    fn test_image_sources() {
+        let inline = ImageAttachment::from_base64(&format!(
+            "data:image/jpeg;base64,{}",
+            base64::engine::general_purpose::STANDARD.encode(PNG)
+        ))
+        .unwrap();
+        // The declared type is ignored in favour of the bytes
+        assert_eq!(inline.media_type, "image/png");
+        assert!(inline.data_url().starts_with("data:image/png;base64,iVBORw0KGgo"));
+        assert!(ImageAttachment::from_base64("aGVsbG8=").unwrap_err().contains("Unsupported image type"));
+        assert!(ImageAttachment::from_base64("not base64!").is_err());
+
+        let workspace = TempDir::new().unwrap();
+        std::fs::create_dir(workspace.path().join("shots")).unwrap();
+        std::fs::write(workspace.path().join("shots/error.png"), PNG).unwrap();
+        let file = ImageSource::WorkspaceFile("shots/error.png".to_string());
+        assert_eq!(file.resolve(workspace.path()).unwrap(), inline);
+        assert!(ImageSource::WorkspaceFile("../secret.png".to_string()).resolve(workspace.path()).is_err());
+        assert!(ImageSource::WorkspaceFile("shots".to_string()).resolve(workspace.path()).is_err());
+
+        assert_eq!(without_images("hi", &[]), "hi");
+        assert!(without_images("hi", &[inline]).ends_with("[1 image(s) attached, but the current model cannot view images]"));
+    }
+}
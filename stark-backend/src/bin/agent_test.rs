@@ -15,9 +15,16 @@
 //!   TEST_AGENT_ENDPOINT  - LLM API endpoint (OpenAI-compatible)
 //!   TEST_AGENT_SECRET    - API key for the LLM
 //!   TEST_AGENT_MODEL     - Model name (auto-detected from endpoint, or specify manually)
-//!   TEST_WORKSPACE       - Workspace directory for file operations
+//!   TEST_WORKSPACE       - Root directory for run workspaces (default: /tmp/agent-test-workspace)
+//!   TEST_RUN_ID          - Name of this run's workspace under the root (default: run-<timestamp>-<pid>).
+//!                          Reusing a name continues in that workspace instead of starting empty.
 //!   TEST_SKILLS_DIR      - Path to skills directory (default: ./skills)
 //!   TEST_MAX_ITERATIONS  - Max tool loop iterations (default: 25)
+//!   TEST_MAX_PROCESSES   - Max background processes running at once (default: 5)
+//!   TEST_TOOL_OUTPUT_CHARS     - Chars of tool output to print (default: 1000)
+//!   TEST_CONTENT_PREVIEW_CHARS - Chars of assistant content to preview (default: 300)
+//!
+//! The exec tool honours the same STARK_EXEC_* sandbox variables as the server.
 
 use reqwest::Client;
 use serde::{Deserialize, Serialize};
@@ -27,9 +34,49 @@ use std::env;
 use std::fs;
 use std::path::{Path, PathBuf};
 use std::process::{Child, Command as ProcessCommand};
-use std::sync::Mutex;
+use std::io::{BufRead, BufReader, Read};
+use std::sync::{Arc, Mutex};
 use std::time::Duration;
 
+// Shared with the server's exec tool so test runs are sandboxed the same way
+#[allow(dead_code)]
+#[path = "../tools/sandbox.rs"]
+mod sandbox;
+
+// Same workspace confinement as the server's file tools
+#[allow(dead_code)]
+#[path = "../tools/workspace_path.rs"]
+mod workspace_path;
+
+// Same diff and search/replace handling as the server's edit_file tool
+#[allow(dead_code)]
+#[path = "../tools/text_edit.rs"]
+mod text_edit;
+
+// Same native glob and grep as the server's search tools
+#[allow(dead_code)]
+#[path = "../tools/search.rs"]
+mod search;
+
+// Same output ring buffer as the server's process manager
+#[allow(dead_code)]
+#[path = "../execution/output_buffer.rs"]
+mod output_buffer;
+
+// Same secret scrubbing as the server's logs and transcripts
+#[allow(dead_code)]
+#[path = "../redaction.rs"]
+mod redaction;
+
+// Same UTF-8 safe truncation as the server's tool output and transcripts
+#[path = "../utils.rs"]
+mod utils;
+
+use output_buffer::OutputBuffer;
+use sandbox::SandboxConfig;
+use utils::{last_chars, truncate_chars, truncate_str};
+use workspace_path::WorkspacePath;
+
 // ============================================================================
 // Types for OpenAI-compatible API
 // ============================================================================
@@ -149,6 +196,40 @@ fn get_code_engineer_tools() -> Vec<ToolSpec> {
                 }),
             },
         },
+        // edit_file
+        ToolSpec {
+            tool_type: "function".to_string(),
+            function: ToolFunction {
+                name: "edit_file".to_string(),
+                description: "Edit part of a file instead of rewriting it with write_file. Give exactly one of: old_text + new_text (replace exact text once), diff (unified diff for this file), or blocks (SEARCH/REPLACE blocks). Edits apply all-or-nothing; if the file changed since you read it you get a conflict and nothing is written.".to_string(),
+                parameters: json!({
+                    "type": "object",
+                    "properties": {
+                        "path": {
+                            "type": "string",
+                            "description": "Path to the file to edit (relative to workspace)"
+                        },
+                        "old_text": {
+                            "type": "string",
+                            "description": "Exact text to replace; must occur exactly once"
+                        },
+                        "new_text": {
+                            "type": "string",
+                            "description": "Replacement for old_text"
+                        },
+                        "diff": {
+                            "type": "string",
+                            "description": "Unified diff for this file ('@@ -12,3 +12,4 @@' hunks with ' ', '-' and '+' lines)"
+                        },
+                        "blocks": {
+                            "type": "string",
+                            "description": "'<<<<<<< SEARCH', current lines, '=======', new lines, '>>>>>>> REPLACE'; repeat for several edits"
+                        }
+                    },
+                    "required": ["path"]
+                }),
+            },
+        },
         // list_files
         ToolSpec {
             tool_type: "function".to_string(),
@@ -214,6 +295,10 @@ fn get_code_engineer_tools() -> Vec<ToolSpec> {
                         "lines": {
                             "type": "integer",
                             "description": "Number of output lines to retrieve (default: 50)"
+                        },
+                        "since": {
+                            "type": "integer",
+                            "description": "Cursor from a previous output call; only newer lines are returned"
                         }
                     },
                     "required": ["operation"]
@@ -279,7 +364,7 @@ fn get_code_engineer_tools() -> Vec<ToolSpec> {
             tool_type: "function".to_string(),
             function: ToolFunction {
                 name: "grep".to_string(),
-                description: "Search for a pattern in files.".to_string(),
+                description: "Search for a regex in files. Returns JSON matches with file, line and match.".to_string(),
                 parameters: json!({
                     "type": "object",
                     "properties": {
@@ -290,6 +375,10 @@ fn get_code_engineer_tools() -> Vec<ToolSpec> {
                         "path": {
                             "type": "string",
                             "description": "Directory or file to search in"
+                        },
+                        "glob": {
+                            "type": "string",
+                            "description": "Only search files matching this glob, e.g. '*.rs'"
                         }
                     },
                     "required": ["pattern"]
@@ -405,20 +494,60 @@ fn get_code_engineer_tools() -> Vec<ToolSpec> {
     ]
 }
 
+// ============================================================================
+// Output display limits
+// ============================================================================
+
+/// How much tool output / assistant content to print to the console
+#[derive(Debug, Clone, Copy)]
+struct DisplayLimits {
+    tool_output_chars: usize,
+    content_preview_chars: usize,
+}
+
+impl DisplayLimits {
+    fn from_env() -> Self {
+        fn read(key: &str, default: usize) -> usize {
+            env::var(key)
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(default)
+        }
+
+        Self {
+            tool_output_chars: read("TEST_TOOL_OUTPUT_CHARS", 1000),
+            content_preview_chars: read("TEST_CONTENT_PREVIEW_CHARS", 300),
+        }
+    }
+}
+
 // ============================================================================
 // Tool Execution - REAL implementations
 // ============================================================================
 
-async fn execute_tool(name: &str, args: &Value, workspace: &Path) -> String {
+/// A tool result as printed, cut to `max_chars` characters
+fn result_display(result: &str, max_chars: usize) -> String {
+    let shown = truncate_chars(result, max_chars);
+    if shown.len() < result.len() {
+        format!("{}...[truncated, {} chars total]", shown, result.chars().count())
+    } else {
+        result.to_string()
+    }
+}
+
+async fn execute_tool(name: &str, args: &Value, workspace: &Path, limits: &DisplayLimits) -> String {
     println!("\n   🔧 Executing: {}", name);
-    println!("   📥 Args: {}", serde_json::to_string(args).unwrap_or_default());
+    let mut shown_args = args.clone();
+    redaction::redact_value(&mut shown_args);
+    println!("   📥 Args: {}", shown_args);
 
     let result = match name {
         "read_file" => execute_read_file(args, workspace),
         "write_file" => execute_write_file(args, workspace),
+        "edit_file" => execute_edit_file(args, workspace),
         "list_files" => execute_list_files(args, workspace),
         "exec" => execute_exec(args, workspace),
-        "process_status" => execute_process_status(args),
+        "process_status" => execute_process_status(args, workspace),
         "git" => execute_git(args, workspace),
         "glob" => execute_glob(args, workspace),
         "grep" => execute_grep(args, workspace),
@@ -429,22 +558,19 @@ async fn execute_tool(name: &str, args: &Value, workspace: &Path) -> String {
         _ => format!("Unknown tool: {}", name),
     };
 
-    // Truncate long output
-    let display = if result.len() > 1000 {
-        format!("{}...[truncated, {} chars total]", &result[..1000], result.len())
-    } else {
-        result.clone()
-    };
-    println!("   📤 Result: {}", display);
+    println!("   📤 Result: {}", redaction::redact(&result_display(&result, limits.tool_output_chars)));
 
     result
 }
 
 fn execute_read_file(args: &Value, workspace: &Path) -> String {
     let path = args.get("path").and_then(|v| v.as_str()).unwrap_or("");
-    let full_path = workspace.join(path);
+    let full_path = match WorkspacePath::resolve(workspace, path) {
+        Ok(p) => p,
+        Err(e) => return format!("Error: {}", e),
+    };
 
-    match fs::read_to_string(&full_path) {
+    match fs::read_to_string(full_path.path()) {
         Ok(content) => content,
         Err(e) => format!("Error reading file: {}", e),
     }
@@ -453,7 +579,10 @@ fn execute_read_file(args: &Value, workspace: &Path) -> String {
 fn execute_write_file(args: &Value, workspace: &Path) -> String {
     let path = args.get("path").and_then(|v| v.as_str()).unwrap_or("");
     let content = args.get("content").and_then(|v| v.as_str()).unwrap_or("");
-    let full_path = workspace.join(path);
+    let full_path = match WorkspacePath::resolve(workspace, path) {
+        Ok(p) => p.path().to_path_buf(),
+        Err(e) => return format!("Error: {}", e),
+    };
 
     // Create parent directories if needed
     if let Some(parent) = full_path.parent() {
@@ -468,11 +597,54 @@ fn execute_write_file(args: &Value, workspace: &Path) -> String {
     }
 }
 
+fn execute_edit_file(args: &Value, workspace: &Path) -> String {
+    let path = args.get("path").and_then(|v| v.as_str()).unwrap_or("");
+    let full_path = match WorkspacePath::resolve(workspace, path) {
+        Ok(p) => p.path().to_path_buf(),
+        Err(e) => return format!("Error: {}", e),
+    };
+    let content = match fs::read_to_string(&full_path) {
+        Ok(c) => c,
+        Err(e) => return format!("Error reading file: {}", e),
+    };
+
+    let str_arg = |name: &str| args.get(name).and_then(|v| v.as_str());
+    let outcome = match (str_arg("diff"), str_arg("blocks"), str_arg("old_text")) {
+        (Some(diff), None, None) => text_edit::apply_unified_diff(&content, diff),
+        (None, Some(blocks), None) => text_edit::parse_search_replace_blocks(blocks)
+            .and_then(|b| text_edit::apply_replacements(&content, &b)),
+        (None, None, Some(old)) => text_edit::apply_replacements(
+            &content,
+            &[text_edit::Replacement {
+                old: old.to_string(),
+                new: str_arg("new_text").unwrap_or("").to_string(),
+            }],
+        ),
+        _ => Err("Provide exactly one of: old_text + new_text, diff, or blocks".to_string()),
+    };
+
+    match outcome {
+        Ok(outcome) => match fs::write(&full_path, &outcome.content) {
+            Ok(_) => format!(
+                "Applied {} hunk(s) to {}.\n\nContext after edit:\n{}",
+                outcome.hunks.len(),
+                path,
+                outcome.render(3)
+            ),
+            Err(e) => format!("Error writing file: {}", e),
+        },
+        Err(e) => format!("Error: {}", e),
+    }
+}
+
 fn execute_list_files(args: &Value, workspace: &Path) -> String {
     let path = args.get("path").and_then(|v| v.as_str()).unwrap_or(".");
-    let full_path = workspace.join(path);
+    let full_path = match WorkspacePath::resolve(workspace, path) {
+        Ok(p) => p,
+        Err(e) => return format!("Error: {}", e),
+    };
 
-    match fs::read_dir(&full_path) {
+    match fs::read_dir(full_path.path()) {
         Ok(entries) => {
             let mut files: Vec<String> = entries
                 .filter_map(|e| e.ok())
@@ -498,17 +670,230 @@ lazy_static::lazy_static! {
     static ref PROCESS_COUNTER: Mutex<u32> = Mutex::new(0);
 }
 
+/// File under the workspace root recording every run's background processes
+const PROCESS_REGISTRY: &str = "processes.json";
+
+/// Most background processes a run may have running at once
+fn max_background_processes() -> usize {
+    env::var("TEST_MAX_PROCESSES")
+        .ok()
+        .and_then(|v| v.parse().ok())
+        .unwrap_or(5)
+}
+
 struct BackgroundProcess {
     id: String,
     pid: u32,
     command: String,
-    #[allow(dead_code)]
+    /// Run whose workspace the process was started in
+    run_id: String,
+    started_at: u64,
     child: Option<Child>,
-    output: Vec<String>,
+    /// Filled by the stdout/stderr reader threads
+    output: Arc<Mutex<OutputBuffer>>,
     completed: bool,
+    killed: bool,
     exit_code: Option<i32>,
 }
 
+impl BackgroundProcess {
+    /// Reap the child if it has exited
+    fn refresh(&mut self) {
+        if self.completed {
+            return;
+        }
+        if let Some(child) = self.child.as_mut()
+            && let Ok(Some(status)) = child.try_wait()
+        {
+            self.completed = true;
+            self.exit_code = status.code();
+        }
+    }
+
+    /// Kill the process and everything it started, then reap it
+    fn terminate(&mut self) -> std::io::Result<()> {
+        self.refresh();
+        if self.completed {
+            return Ok(());
+        }
+        let Some(child) = self.child.as_mut() else {
+            return Err(std::io::Error::other("no active child handle"));
+        };
+        kill_process_group(self.pid);
+        child.kill()?;
+        let status = child.wait()?;
+        self.completed = true;
+        self.killed = true;
+        self.exit_code = status.code();
+        Ok(())
+    }
+
+    fn status(&self) -> &'static str {
+        match (self.completed, self.killed) {
+            (false, _) => "running",
+            (true, true) => "killed",
+            (true, false) => "completed",
+        }
+    }
+
+    fn record(&self) -> ProcessRecord {
+        ProcessRecord {
+            id: self.id.clone(),
+            pid: self.pid,
+            command: self.command.clone(),
+            run_id: self.run_id.clone(),
+            owner_pid: std::process::id(),
+            started_at: self.started_at,
+            status: self.status().to_string(),
+            exit_code: self.exit_code,
+        }
+    }
+}
+
+/// What the registry keeps about a background process, so other runs can see
+/// who owns it and clean up after a harness that died without stopping it
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+struct ProcessRecord {
+    id: String,
+    pid: u32,
+    command: String,
+    run_id: String,
+    /// agent_test process that started it; once that is gone the process is orphaned
+    owner_pid: u32,
+    started_at: u64,
+    status: String,
+    exit_code: Option<i32>,
+}
+
+impl ProcessRecord {
+    fn orphaned(&self) -> bool {
+        self.status == "running" && !process_alive(self.owner_pid)
+    }
+}
+
+/// Name of the run a workspace belongs to (its directory under the root)
+fn run_id_of(workspace: &Path) -> String {
+    workspace
+        .file_name()
+        .map(|n| n.to_string_lossy().to_string())
+        .unwrap_or_default()
+}
+
+fn registry_path(workspace: &Path) -> PathBuf {
+    workspace.parent().unwrap_or(workspace).join(PROCESS_REGISTRY)
+}
+
+fn read_registry(workspace: &Path) -> Vec<ProcessRecord> {
+    fs::read_to_string(registry_path(workspace))
+        .ok()
+        .and_then(|s| serde_json::from_str(&s).ok())
+        .unwrap_or_default()
+}
+
+fn write_registry(workspace: &Path, records: &[ProcessRecord]) {
+    if let Ok(json) = serde_json::to_string_pretty(records)
+        && let Err(e) = fs::write(registry_path(workspace), json)
+    {
+        eprintln!("   ⚠️  Failed to save process registry: {}", e);
+    }
+}
+
+/// Registry with this run's entries replaced by `ours`
+fn merge_records(existing: Vec<ProcessRecord>, run_id: &str, ours: Vec<ProcessRecord>) -> Vec<ProcessRecord> {
+    existing
+        .into_iter()
+        .filter(|r| r.run_id != run_id)
+        .chain(ours)
+        .collect()
+}
+
+/// Write this run's processes to the registry, leaving other runs' entries alone
+fn save_registry(workspace: &Path, processes: &StdHashMap<String, BackgroundProcess>) {
+    let mut ours: Vec<ProcessRecord> = processes.values().map(BackgroundProcess::record).collect();
+    ours.sort_by(|a, b| (a.started_at, &a.id).cmp(&(b.started_at, &b.id)));
+    let records = merge_records(read_registry(workspace), &run_id_of(workspace), ours);
+    write_registry(workspace, &records);
+}
+
+/// Kill processes whose harness exited without stopping them, and forget
+/// finished entries of runs that are gone. Returns how many were killed.
+fn reap_orphaned_processes(workspace: &Path) -> usize {
+    let records = read_registry(workspace);
+    if records.is_empty() {
+        return 0;
+    }
+    let mut killed = 0;
+    let mut kept = Vec::new();
+    for record in records {
+        if record.orphaned() {
+            println!("   🧹 Killing {} (PID {}) left behind by run '{}'", record.id, record.pid, record.run_id);
+            kill_process_group(record.pid);
+            killed += 1;
+        } else if record.status == "running" || process_alive(record.owner_pid) {
+            kept.push(record);
+        }
+    }
+    write_registry(workspace, &kept);
+    killed
+}
+
+/// Kill every background process this run still has running. Returns how many were stopped.
+fn stop_background_processes(workspace: &Path) -> usize {
+    let mut processes = BACKGROUND_PROCESSES.lock().unwrap();
+    let mut stopped = 0;
+    for proc in processes.values_mut() {
+        proc.refresh();
+        if !proc.completed {
+            match proc.terminate() {
+                Ok(()) => stopped += 1,
+                Err(e) => eprintln!("   ⚠️  Failed to stop {}: {}", proc.id, e),
+            }
+        }
+    }
+    save_registry(workspace, &processes);
+    stopped
+}
+
+/// Whether a process with this PID still exists
+#[cfg(unix)]
+fn process_alive(pid: u32) -> bool {
+    // SAFETY: signal 0 only checks that the process exists
+    let result = unsafe { libc::kill(pid as libc::pid_t, 0) };
+    result == 0 || std::io::Error::last_os_error().raw_os_error() == Some(libc::EPERM)
+}
+
+/// Without a cheap check, assume the owner is alive so nothing is killed by mistake
+#[cfg(not(unix))]
+fn process_alive(_pid: u32) -> bool {
+    true
+}
+
+/// Kill the process group a background command leads
+#[cfg(unix)]
+fn kill_process_group(pid: u32) {
+    // SAFETY: signalling a process group has no memory safety preconditions
+    unsafe {
+        libc::killpg(pid as libc::pid_t, libc::SIGKILL);
+    }
+}
+
+#[cfg(not(unix))]
+fn kill_process_group(_pid: u32) {}
+
+/// Drain a child pipe into the shared output buffer on a background thread
+fn spawn_output_reader<R: Read + Send + 'static>(pipe: R, output: Arc<Mutex<OutputBuffer>>, stderr: bool) {
+    std::thread::spawn(move || {
+        for line in BufReader::new(pipe).lines().map_while(Result::ok) {
+            let mut output = output.lock().unwrap();
+            if stderr {
+                output.push_stderr(&line);
+            } else {
+                output.push_stdout(line);
+            }
+        }
+    });
+}
+
 fn execute_exec(args: &Value, workspace: &Path) -> String {
     let command = args.get("command").and_then(|v| v.as_str()).unwrap_or("");
     let background = args.get("background").and_then(|v| v.as_bool()).unwrap_or(false);
@@ -523,6 +908,11 @@ fn execute_exec(args: &Value, workspace: &Path) -> String {
     let lower_cmd = command.to_lowercase();
     let is_server = server_patterns.iter().any(|p| lower_cmd.contains(p));
 
+    let sandbox = SandboxConfig::from_env();
+    if let Some(reason) = sandbox.denied_reason(command) {
+        return format!("Command blocked: {}", reason);
+    }
+
     if is_server && !background {
         return format!(
             "Detected server/long-running command: `{}`\n\n\
@@ -535,33 +925,61 @@ fn execute_exec(args: &Value, workspace: &Path) -> String {
     }
 
     if background {
+        let mut processes = BACKGROUND_PROCESSES.lock().unwrap();
+        processes.values_mut().for_each(BackgroundProcess::refresh);
+        let running = processes.values().filter(|p| !p.completed).count();
+        let max = max_background_processes();
+        if running >= max {
+            return format!(
+                "Error: {} background processes are already running (max {}). \
+                Kill one with `process_status` operation=\"kill\" first.",
+                running, max
+            );
+        }
+
         println!("   🖥️  Starting background: {}", command);
 
-        match ProcessCommand::new("bash")
-            .arg("-c")
-            .arg(command)
-            .current_dir(workspace)
-            .stdout(std::process::Stdio::piped())
-            .stderr(std::process::Stdio::piped())
-            .spawn()
-        {
-            Ok(child) => {
+        let mut cmd = sandbox.command(command, workspace, &[]);
+        cmd.stdout(std::process::Stdio::piped())
+            .stderr(std::process::Stdio::piped());
+        // Own process group, so killing the command also kills what it started
+        #[cfg(unix)]
+        std::os::unix::process::CommandExt::process_group(&mut cmd, 0);
+
+        match cmd.spawn() {
+            Ok(mut child) => {
                 let pid = child.id();
                 let mut counter = PROCESS_COUNTER.lock().unwrap();
                 *counter += 1;
                 let process_id = format!("proc_{}", *counter);
+                let started_at = std::time::SystemTime::now()
+                    .duration_since(std::time::UNIX_EPOCH)
+                    .map(|d| d.as_secs())
+                    .unwrap_or(0);
+
+                let output = Arc::new(Mutex::new(OutputBuffer::new(OutputBuffer::max_lines_from_env())));
+                if let Some(stdout) = child.stdout.take() {
+                    spawn_output_reader(stdout, Arc::clone(&output), false);
+                }
+                if let Some(stderr) = child.stderr.take() {
+                    spawn_output_reader(stderr, Arc::clone(&output), true);
+                }
 
                 let bg_process = BackgroundProcess {
                     id: process_id.clone(),
                     pid,
                     command: command.to_string(),
+                    run_id: run_id_of(workspace),
+                    started_at,
                     child: Some(child),
-                    output: Vec::new(),
+                    output,
                     completed: false,
+                    killed: false,
                     exit_code: None,
                 };
 
-                BACKGROUND_PROCESSES.lock().unwrap().insert(process_id.clone(), bg_process);
+                processes.insert(process_id.clone(), bg_process);
+                save_registry(workspace, &processes);
 
                 format!(
                     "Started background process\n\
@@ -577,11 +995,7 @@ fn execute_exec(args: &Value, workspace: &Path) -> String {
     } else {
         println!("   🖥️  Running: {}", command);
 
-        let output = ProcessCommand::new("bash")
-            .arg("-c")
-            .arg(command)
-            .current_dir(workspace)
-            .output();
+        let output = sandbox.command(command, workspace, &[]).output();
 
         match output {
             Ok(output) => {
@@ -609,7 +1023,7 @@ fn execute_exec(args: &Value, workspace: &Path) -> String {
     }
 }
 
-fn execute_process_status(args: &Value) -> String {
+fn execute_process_status(args: &Value, workspace: &Path) -> String {
     let operation = args.get("operation").and_then(|v| v.as_str()).unwrap_or("list");
     let process_id = args.get("process_id").and_then(|v| v.as_str());
 
@@ -620,15 +1034,16 @@ fn execute_process_status(args: &Value) -> String {
                 None => return "Error: process_id is required for 'status' operation".to_string(),
             };
 
-            let processes = BACKGROUND_PROCESSES.lock().unwrap();
-            match processes.get(pid) {
+            let mut processes = BACKGROUND_PROCESSES.lock().unwrap();
+            match processes.get_mut(pid) {
                 Some(proc) => {
-                    let status = if proc.completed { "completed" } else { "running" };
+                    proc.refresh();
                     format!(
-                        "Process: {}\nStatus: {}\nPID: {}\nCommand: {}{}",
+                        "Process: {}\nStatus: {}\nPID: {}\nRun: {}\nCommand: {}{}",
                         proc.id,
-                        status,
+                        proc.status(),
                         proc.pid,
+                        proc.run_id,
                         proc.command,
                         if let Some(code) = proc.exit_code {
                             format!("\nExit code: {}", code)
@@ -648,19 +1063,28 @@ fn execute_process_status(args: &Value) -> String {
             };
 
             let lines = args.get("lines").and_then(|v| v.as_u64()).unwrap_or(50) as usize;
+            let since = args.get("since").and_then(|v| v.as_u64());
 
             let processes = BACKGROUND_PROCESSES.lock().unwrap();
             match processes.get(pid) {
                 Some(proc) => {
-                    if proc.output.is_empty() {
-                        format!("No output captured yet for process '{}'", pid)
+                    let output = proc.output.lock().unwrap();
+                    let (lines, cursor) = match since {
+                        Some(cursor) => {
+                            let chunk = output.since(cursor, lines);
+                            (chunk.lines, chunk.cursor)
+                        }
+                        None => (output.tail(lines), output.cursor()),
+                    };
+                    if lines.is_empty() {
+                        format!("No new output for process '{}' (cursor: {})", pid, cursor)
                     } else {
-                        let output: Vec<_> = proc.output.iter().rev().take(lines).collect();
                         format!(
-                            "Output from process '{}' (last {} lines):\n\n{}",
+                            "Output from process '{}' ({} lines, next cursor: {}):\n\n{}",
                             pid,
-                            output.len(),
-                            output.into_iter().rev().cloned().collect::<Vec<_>>().join("\n")
+                            lines.len(),
+                            cursor,
+                            lines.join("\n")
                         )
                     }
                 }
@@ -675,41 +1099,61 @@ fn execute_process_status(args: &Value) -> String {
             };
 
             let mut processes = BACKGROUND_PROCESSES.lock().unwrap();
-            match processes.get_mut(pid) {
+            let result = match processes.get_mut(pid) {
                 Some(proc) => {
-                    if let Some(ref mut child) = proc.child {
-                        match child.kill() {
-                            Ok(_) => {
-                                proc.completed = true;
-                                format!("Process '{}' has been killed", pid)
-                            }
+                    proc.refresh();
+                    if proc.completed {
+                        format!("Process '{}' is not running (status: {})", pid, proc.status())
+                    } else {
+                        match proc.terminate() {
+                            Ok(()) => format!("Process '{}' has been killed", pid),
                             Err(e) => format!("Failed to kill process '{}': {}", pid, e),
                         }
-                    } else {
-                        format!("Process '{}' has no active child handle", pid)
                     }
                 }
-                None => format!("Process '{}' not found", pid),
-            }
+                None => return format!("Process '{}' not found", pid),
+            };
+            save_registry(workspace, &processes);
+            result
         }
 
         "list" => {
-            let processes = BACKGROUND_PROCESSES.lock().unwrap();
-            if processes.is_empty() {
+            let mut processes = BACKGROUND_PROCESSES.lock().unwrap();
+            processes.values_mut().for_each(BackgroundProcess::refresh);
+            save_registry(workspace, &processes);
+
+            // Other runs sharing the workspace root, from the registry
+            let run_id = run_id_of(workspace);
+            let others: Vec<ProcessRecord> = read_registry(workspace)
+                .into_iter()
+                .filter(|r| r.run_id != run_id && r.status == "running")
+                .collect();
+            if processes.is_empty() && others.is_empty() {
                 return "No background processes found.".to_string();
             }
 
+            let mut ours: Vec<&BackgroundProcess> = processes.values().collect();
+            ours.sort_by(|a, b| (a.started_at, &a.id).cmp(&(b.started_at, &b.id)));
             let mut result = String::from("Background processes:\n\n");
-            for proc in processes.values() {
-                let status = if proc.completed { "completed" } else { "running" };
-                let short_cmd = if proc.command.len() > 50 {
-                    format!("{}...", &proc.command[..47])
-                } else {
-                    proc.command.clone()
-                };
+            for proc in ours {
+                result.push_str(&format!(
+                    "- {} (PID {}): {}\n  Run: {} (this run)\n  Command: {}\n\n",
+                    proc.id,
+                    proc.pid,
+                    proc.status(),
+                    proc.run_id,
+                    truncate_str(&proc.command, 47)
+                ));
+            }
+            for record in others {
                 result.push_str(&format!(
-                    "- {} (PID {}): {}\n  Command: {}\n\n",
-                    proc.id, proc.pid, status, short_cmd
+                    "- {} (PID {}): {}\n  Run: {}{}\n  Command: {}\n\n",
+                    record.id,
+                    record.pid,
+                    record.status,
+                    record.run_id,
+                    if record.orphaned() { " (orphaned)" } else { "" },
+                    truncate_str(&record.command, 47)
                 ));
             }
             result
@@ -803,21 +1247,9 @@ fn execute_git(args: &Value, workspace: &Path) -> String {
 fn execute_glob(args: &Value, workspace: &Path) -> String {
     let pattern = args.get("pattern").and_then(|v| v.as_str()).unwrap_or("*");
 
-    // Use find command for glob-like behavior
-    let output = ProcessCommand::new("find")
-        .args([".", "-name", pattern, "-type", "f"])
-        .current_dir(workspace)
-        .output();
-
-    match output {
-        Ok(o) => {
-            let stdout = String::from_utf8_lossy(&o.stdout);
-            if stdout.is_empty() {
-                "No files found matching pattern".to_string()
-            } else {
-                stdout.to_string()
-            }
-        }
+    match search::find_files(workspace, workspace, pattern, search::WalkOptions::default()) {
+        Ok(files) if files.is_empty() => "No files found matching pattern".to_string(),
+        Ok(files) => files.into_iter().map(|f| f.relative).collect::<Vec<_>>().join("\n"),
         Err(e) => format!("Error: {}", e),
     }
 }
@@ -826,20 +1258,19 @@ fn execute_grep(args: &Value, workspace: &Path) -> String {
     let pattern = args.get("pattern").and_then(|v| v.as_str()).unwrap_or("");
     let path = args.get("path").and_then(|v| v.as_str()).unwrap_or(".");
 
-    let output = ProcessCommand::new("grep")
-        .args(["-rn", pattern, path])
-        .current_dir(workspace)
-        .output();
+    let search_path = match WorkspacePath::resolve(workspace, path) {
+        Ok(p) => p,
+        Err(e) => return format!("Error: {}", e),
+    };
+    let options = search::GrepOptions {
+        glob: args.get("glob").and_then(|v| v.as_str()).map(String::from),
+        max_results: 100,
+        ..Default::default()
+    };
 
-    match output {
-        Ok(o) => {
-            let stdout = String::from_utf8_lossy(&o.stdout);
-            if stdout.is_empty() {
-                "No matches found".to_string()
-            } else {
-                stdout.to_string()
-            }
-        }
+    match search::grep(search_path.root(), search_path.path(), pattern, &options) {
+        Ok(outcome) if outcome.matches.is_empty() => "No matches found".to_string(),
+        Ok(outcome) => serde_json::to_string_pretty(&outcome.matches).unwrap_or_default(),
         Err(e) => format!("Error: {}", e),
     }
 }
@@ -877,8 +1308,8 @@ fn execute_api_keys_check(args: &Value) -> String {
     match std::env::var(key_name) {
         Ok(val) if !val.is_empty() => {
             // Mask the value for security
-            let masked = if val.len() > 8 {
-                format!("{}...{}", &val[..4], &val[val.len()-4..])
+            let masked = if val.chars().count() > 8 {
+                format!("{}...{}", truncate_chars(&val, 4), last_chars(&val, 4))
             } else {
                 "****".to_string()
             };
@@ -1149,7 +1580,7 @@ async fn execute_discord(args: &Value) -> String {
 // System Prompt
 // ============================================================================
 
-fn get_system_prompt(workspace: &Path, skills: &[String]) -> String {
+fn get_system_prompt(workspace: &Path, skills: &[SkillSummary]) -> String {
     format!(r#"You are an AI agent that can perform various tasks. Your workspace is: {}
 
 ## ⚠️ CRITICAL: You MUST Call Tools ⚠️
@@ -1180,6 +1611,7 @@ For ANY request about external services, APIs, registrations, balances, or data:
 
 ### File Operations
 - `write_file` - Create or overwrite files (path, content)
+- `edit_file` - Change part of a file (path + old_text/new_text, diff, or blocks); prefer this over rewriting large files
 - `read_file` - Read file contents (path)
 - `list_files` - List directory contents (path)
 - `glob` - Find files by pattern
@@ -1194,7 +1626,8 @@ For ANY request about external services, APIs, registrations, balances, or data:
 - `discord_lookup` - Look up Discord servers and channels
 - `discord` - Perform Discord actions (sendMessage, readMessages, react)
 
-## Skills Available: {}
+## Skills Available
+{}
 
 ## Workflow for Service Queries
 
@@ -1212,7 +1645,11 @@ For Moltbook specifically:
 
 Accomplish what the user asks for. Use the available tools."#,
         workspace.display(),
-        if skills.is_empty() { "None".to_string() } else { skills.join(", ") }
+        if skills.is_empty() {
+            "None".to_string()
+        } else {
+            skills.iter().map(SkillSummary::prompt_line).collect::<Vec<_>>().join("\n")
+        }
     )
 }
 
@@ -1220,18 +1657,82 @@ Accomplish what the user asks for. Use the available tools."#,
 // Skills
 // ============================================================================
 
-fn list_available_skills(skills_dir: &str) -> Vec<String> {
+/// Frontmatter of a skill file, enough to tell the model when to load it
+struct SkillSummary {
+    name: String,
+    description: String,
+    triggers: Vec<String>,
+}
+
+impl SkillSummary {
+    /// Read `name`, `description` and `triggers` from a SKILL.md-style frontmatter.
+    /// Files without frontmatter are listed under their file name.
+    fn parse(content: &str, fallback_name: &str) -> Self {
+        let mut summary = SkillSummary {
+            name: fallback_name.to_string(),
+            description: String::new(),
+            triggers: Vec::new(),
+        };
+        let Some(rest) = content.trim_start().strip_prefix("---") else {
+            return summary;
+        };
+        let frontmatter = rest.split("\n---").next().unwrap_or("");
+        let unquote = |v: &str| v.trim().trim_matches('"').trim_matches('\'').to_string();
+        let mut in_triggers = false;
+        for line in frontmatter.lines() {
+            if let Some(item) = line.trim().strip_prefix("- ") {
+                if in_triggers {
+                    summary.triggers.push(unquote(item));
+                }
+                continue;
+            }
+            in_triggers = false;
+            let Some((key, value)) = line.split_once(':') else {
+                continue;
+            };
+            match key.trim() {
+                "name" if !value.trim().is_empty() => summary.name = unquote(value),
+                "description" => summary.description = unquote(value),
+                "triggers" => {
+                    let value = value.trim();
+                    if let Some(list) = value.strip_prefix('[').and_then(|v| v.strip_suffix(']')) {
+                        summary.triggers = list.split(',').map(unquote).filter(|t| !t.is_empty()).collect();
+                    } else {
+                        in_triggers = value.is_empty();
+                    }
+                }
+                _ => {}
+            }
+        }
+        summary
+    }
+
+    fn prompt_line(&self) -> String {
+        let mut line = format!("- {}", self.name);
+        if !self.description.is_empty() {
+            line.push_str(&format!(": {}", self.description));
+        }
+        if !self.triggers.is_empty() {
+            line.push_str(&format!(" (triggers: {})", self.triggers.join(", ")));
+        }
+        line
+    }
+}
+
+fn list_available_skills(skills_dir: &str) -> Vec<SkillSummary> {
     let mut skills = Vec::new();
     if let Ok(entries) = fs::read_dir(skills_dir) {
         for entry in entries.flatten() {
             let path = entry.path();
             if path.extension().map(|e| e == "md").unwrap_or(false) {
                 if let Some(name) = path.file_stem().and_then(|s| s.to_str()) {
-                    skills.push(name.to_string());
+                    let content = fs::read_to_string(&path).unwrap_or_default();
+                    skills.push(SkillSummary::parse(&content, name));
                 }
             }
         }
     }
+    skills.sort_by(|a, b| a.name.cmp(&b.name));
     skills
 }
 
@@ -1246,8 +1747,9 @@ async fn run_agent_loop(
     model: &str,
     query: &str,
     workspace: &Path,
-    skills: &[String],
+    skills: &[SkillSummary],
     max_iterations: usize,
+    limits: &DisplayLimits,
 ) -> Result<String, String> {
     let tools = get_code_engineer_tools();
     let system_prompt = get_system_prompt(workspace, skills);
@@ -1316,8 +1818,7 @@ async fn run_agent_loop(
         println!("\n📊 Response:");
         println!("   finish_reason: {:?}", choice.finish_reason);
         if let Some(content) = &choice.message.content {
-            let preview = if content.len() > 300 { format!("{}...", &content[..300]) } else { content.clone() };
-            println!("   content: {}", preview);
+            println!("   content: {}", truncate_str(content, limits.content_preview_chars));
         }
         println!("   tool_calls: {:?}", choice.message.tool_calls.as_ref().map(|t| t.len()));
 
@@ -1340,7 +1841,7 @@ async fn run_agent_loop(
                     println!("\n   📍 Tool: {} (id: {})", tc.function.name, tc.id);
 
                     let args: Value = serde_json::from_str(&tc.function.arguments).unwrap_or(json!({}));
-                    let result = execute_tool(&tc.function.name, &args, workspace).await;
+                    let result = execute_tool(&tc.function.name, &args, workspace, limits).await;
 
                     messages.push(Message {
                         role: "tool".to_string(),
@@ -1371,6 +1872,7 @@ async fn run_agent_loop(
 #[tokio::main]
 async fn main() {
     dotenv::dotenv().ok();
+    redaction::register_env_secrets();
 
     println!("🤖 StarkBot Agent Test");
     println!("======================\n");
@@ -1380,6 +1882,12 @@ async fn main() {
         "Build a simple todo app with TypeScript. Create a basic CLI todo app with add, list, and remove commands.".to_string()
     });
 
+    if query.trim().is_empty() {
+        eprintln!("❌ TEST_QUERY is empty!");
+        eprintln!("   Provide a non-empty query or unset TEST_QUERY to use the default");
+        std::process::exit(1);
+    }
+
     let endpoint = env::var("TEST_AGENT_ENDPOINT").unwrap_or_else(|_| {
         eprintln!("❌ TEST_AGENT_ENDPOINT not set!");
         eprintln!("   Example: https://api.openai.com/v1/chat/completions");
@@ -1402,10 +1910,24 @@ async fn main() {
         }
     });
 
-    let workspace_str = env::var("TEST_WORKSPACE").unwrap_or_else(|_| {
+    let workspace_root = env::var("TEST_WORKSPACE").unwrap_or_else(|_| {
         "/tmp/agent-test-workspace".to_string()
     });
-    let workspace = PathBuf::from(&workspace_str);
+    // Each run gets its own directory, so concurrent runs don't clobber each other
+    let run_id = env::var("TEST_RUN_ID").unwrap_or_else(|_| {
+        let started = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or(0);
+        format!("run-{}-{}", started, std::process::id())
+    });
+    if run_id.is_empty()
+        || !run_id.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+    {
+        eprintln!("❌ TEST_RUN_ID may only contain letters, digits, '-' and '_'");
+        std::process::exit(1);
+    }
+    let workspace = PathBuf::from(&workspace_root).join(&run_id);
 
     let skills_dir = env::var("TEST_SKILLS_DIR").unwrap_or_else(|_| {
         if Path::new("skills").exists() {
@@ -1422,6 +1944,8 @@ async fn main() {
         .parse()
         .unwrap_or(25);
 
+    let limits = DisplayLimits::from_env();
+
     let skills = list_available_skills(&skills_dir);
 
     println!("📝 Configuration:");
@@ -1431,11 +1955,13 @@ async fn main() {
     println!("   Workspace:  {}", workspace.display());
     println!("   Skills:     {} ({} found)", skills_dir, skills.len());
     println!("   Max Iters:  {}", max_iterations);
+    println!(
+        "   Display:    {} chars tool output, {} chars content preview",
+        limits.tool_output_chars, limits.content_preview_chars
+    );
 
-    // Clean and create workspace
     if workspace.exists() {
-        println!("\n🧹 Cleaning existing workspace...");
-        let _ = fs::remove_dir_all(&workspace);
+        println!("\n♻️  Reusing existing workspace for run '{}'", run_id);
     }
     if let Err(e) = fs::create_dir_all(&workspace) {
         eprintln!("❌ Failed to create workspace: {}", e);
@@ -1443,6 +1969,11 @@ async fn main() {
     }
     println!("✅ Workspace ready: {}", workspace.display());
 
+    let orphans = reap_orphaned_processes(&workspace);
+    if orphans > 0 {
+        println!("🧹 Killed {} background process(es) left behind by earlier runs", orphans);
+    }
+
     // Create HTTP client
     let client = Client::builder()
         .timeout(Duration::from_secs(300))
@@ -1452,7 +1983,7 @@ async fn main() {
     // Run the agent loop
     println!("\n🚀 Starting agent loop...\n");
 
-    match run_agent_loop(
+    let outcome = run_agent_loop(
         &client,
         &endpoint,
         &secret,
@@ -1461,7 +1992,16 @@ async fn main() {
         &workspace,
         &skills,
         max_iterations,
-    ).await {
+        &limits,
+    ).await;
+
+    // Background processes don't outlive the run that started them
+    let stopped = stop_background_processes(&workspace);
+    if stopped > 0 {
+        println!("\n🧹 Stopped {} background process(es) started by this run", stopped);
+    }
+
+    match outcome {
         Ok(response) => {
             println!("\n============================================================");
             println!("🎉 SUCCESS");
@@ -1495,3 +2035,63 @@ async fn main() {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_result_display_respects_multibyte_boundaries() {
+        assert_eq!(result_display("hello", 10), "hello");
+        // Byte 1 falls inside "é"; slicing by bytes would panic here
+        assert_eq!(result_display("héllo", 2), "hé...[truncated, 5 chars total]");
+        assert_eq!(result_display("🦀🦀🦀", 1), "🦀...[truncated, 3 chars total]");
+        assert_eq!(result_display("日本語", 0), "...[truncated, 3 chars total]");
+    }
+
+    fn record(id: &str, run_id: &str, owner_pid: u32, status: &str) -> ProcessRecord {
+        ProcessRecord {
+            id: id.to_string(),
+            pid: 1,
+            command: "npm run dev".to_string(),
+            run_id: run_id.to_string(),
+            owner_pid,
+            started_at: 0,
+            status: status.to_string(),
+            exit_code: None,
+        }
+    }
+
+    #[test]
+    fn test_registry_merge_replaces_only_this_runs_entries() {
+        let existing = vec![record("proc_1", "run-a", 1, "running"), record("proc_1", "run-b", 1, "running")];
+        let merged = merge_records(existing, "run-a", vec![record("proc_2", "run-a", 1, "killed")]);
+        assert_eq!(merged, vec![record("proc_1", "run-b", 1, "running"), record("proc_2", "run-a", 1, "killed")]);
+
+        // Only running processes whose harness is gone are orphans
+        let gone = i32::MAX as u32;
+        assert!(record("proc_1", "run-a", gone, "running").orphaned());
+        assert!(!record("proc_1", "run-a", gone, "completed").orphaned());
+        assert!(!record("proc_1", "run-a", std::process::id(), "running").orphaned());
+    }
+
+    #[test]
+    fn test_background_processes_are_recorded_and_stopped_with_the_run() {
+        let root = tempfile::tempdir().unwrap();
+        let workspace = root.path().join("run-test");
+        fs::create_dir_all(&workspace).unwrap();
+
+        let started = execute_exec(&json!({ "command": "sleep 30", "background": true }), &workspace);
+        assert!(started.contains("Started background process"), "{}", started);
+
+        let listed = execute_process_status(&json!({ "operation": "list" }), &workspace);
+        assert!(listed.contains("running\n  Run: run-test (this run)"), "{}", listed);
+        let records = read_registry(&workspace);
+        assert_eq!(records.len(), 1);
+        assert_eq!((records[0].run_id.as_str(), records[0].status.as_str()), ("run-test", "running"));
+
+        assert_eq!(stop_background_processes(&workspace), 1);
+        assert_eq!(read_registry(&workspace)[0].status, "killed");
+        assert_eq!(stop_background_processes(&workspace), 0);
+    }
+}
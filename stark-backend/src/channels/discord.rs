@@ -1,39 +1,61 @@
+//! Discord channel
+//!
+//! Conversations start either from the `/stark <prompt>` slash command or from
+//! a plain message in a server channel, and each one gets its own thread (and
+//! so its own session, keyed by the thread id). Messages inside a thread and
+//! in DMs continue the conversation in place. While the agent works, a single
+//! reply message is edited with its progress and any streamed text, then
+//! replaced by the final answer. Files the agent wrote are attached at the end.
+
 use crate::channels::dispatcher::MessageDispatcher;
-use crate::channels::types::{ChannelType, NormalizedMessage};
+use crate::channels::types::{ChannelType, NormalizedMessage, ToolInvocation};
 use crate::gateway::events::EventBroadcaster;
 use crate::gateway::protocol::GatewayEvent;
 use crate::models::Channel;
+use crate::utils::{truncate_chars, truncate_str};
 use serenity::all::{
-    Client, Context, EventHandler, GatewayIntents, Message, Ready,
+    AutoArchiveDuration, ChannelId, Client, Command, CommandInteraction, CommandOptionType, Context,
+    CreateAttachment, CreateCommand, CreateCommandOption, CreateInteractionResponse,
+    CreateInteractionResponseMessage, CreateMessage, CreateThread, EditMessage, EventHandler,
+    GatewayIntents, Http, Interaction, Message, MessageId, Ready,
 };
+use serenity::all::ChannelType as DiscordChannelType;
+use std::path::{Path, PathBuf};
 use std::sync::Arc;
-use tokio::sync::oneshot;
+use std::time::{Duration, Instant};
+use tokio::sync::{oneshot, Mutex};
+
+/// Discord's per-message character limit
+const MESSAGE_LIMIT: usize = 2000;
+
+/// Minimum gap between edits of the live reply (Discord rate limits edits)
+const EDIT_INTERVAL: Duration = Duration::from_millis(1200);
+
+/// Discord allows at most 10 attachments per message
+const MAX_ATTACHMENTS: usize = 10;
+
+/// Files above the default upload limit are skipped rather than failing the send
+const MAX_ATTACHMENT_BYTES: u64 = 8 * 1024 * 1024;
+
+/// Tools whose `path` argument names a file worth sending back
+const ARTIFACT_TOOLS: &[&str] = &["write_file", "edit_file"];
 
-/// Format a tool call event for Discord display
+/// Name of the slash command
+const COMMAND_NAME: &str = "stark";
+
+/// Format a tool call event as one progress line
 fn format_tool_call_for_discord(tool_name: &str, parameters: &serde_json::Value) -> String {
-    let params_str = serde_json::to_string_pretty(parameters)
-        .unwrap_or_else(|_| parameters.to_string());
-    // Truncate params if too long for Discord
-    let params_display = if params_str.len() > 800 {
-        format!("{}...", &params_str[..800])
-    } else {
-        params_str
-    };
-    format!("🔧 **Tool Call:** `{}`\n```json\n{}\n```", tool_name, params_display)
+    let params = parameters.to_string();
+    format!("🔧 `{}` `{}`", tool_name, truncate_str(&params, 120))
 }
 
-/// Format a tool result event for Discord display
+/// Format a tool result event as one progress line
 fn format_tool_result_for_discord(tool_name: &str, success: bool, duration_ms: i64, content: &str) -> String {
     let status = if success { "✅" } else { "❌" };
-    // Truncate content if too long
-    let content_display = if content.len() > 1200 {
-        format!("{}...", &content[..1200])
-    } else {
-        content.to_string()
-    };
+    let first_line = content.lines().next().unwrap_or("");
     format!(
-        "{} **Tool Result:** `{}` ({} ms)\n```\n{}\n```",
-        status, tool_name, duration_ms, content_display
+        "{} `{}` ({} ms) {}",
+        status, tool_name, duration_ms, truncate_str(first_line, 120)
     )
 }
 
@@ -51,139 +73,321 @@ fn format_mode_change_for_discord(mode: &str, label: &str, reason: Option<&str>)
     }
 }
 
-struct DiscordHandler {
-    channel_id: i64,
-    dispatcher: Arc<MessageDispatcher>,
-    broadcaster: Arc<EventBroadcaster>,
+/// Progress line for a gateway event, if it's one shown in Discord
+fn progress_line(event: &str, data: &serde_json::Value) -> Option<String> {
+    let str_field = |key: &str, default: &'static str| -> String {
+        data.get(key).and_then(|v| v.as_str()).unwrap_or(default).to_string()
+    };
+    match event {
+        "agent.tool_call" => {
+            let params = data.get("parameters").cloned().unwrap_or(serde_json::json!({}));
+            Some(format_tool_call_for_discord(&str_field("tool_name", "unknown"), &params))
+        }
+        "tool.result" => Some(format_tool_result_for_discord(
+            &str_field("tool_name", "unknown"),
+            data.get("success").and_then(|v| v.as_bool()).unwrap_or(false),
+            data.get("duration_ms").and_then(|v| v.as_i64()).unwrap_or(0),
+            &str_field("content", ""),
+        )),
+        "agent.mode_change" => Some(format_mode_change_for_discord(
+            &str_field("mode", "unknown"),
+            &str_field("label", "Unknown"),
+            data.get("reason").and_then(|v| v.as_str()),
+        )),
+        "execution.task_started" => Some(format!(
+            "▶️ **{}:** {}",
+            str_field("type", "task"),
+            str_field("name", "Unknown task")
+        )),
+        "execution.task_completed" => {
+            let status = str_field("status", "completed");
+            let emoji = if status == "completed" { "✅" } else { "❌" };
+            Some(format!("{} Task {}", emoji, status))
+        }
+        _ => None,
+    }
 }
 
-#[serenity::async_trait]
-impl EventHandler for DiscordHandler {
-    async fn message(&self, ctx: Context, msg: Message) {
-        // Ignore messages from bots (including ourselves)
-        if msg.author.bot {
-            return;
+/// Text of the live reply: the latest progress lines, the current status and
+/// any streamed answer, kept under the message limit by dropping the oldest
+/// progress lines first
+fn render_live(progress: &[String], status: Option<&str>, streamed: &str) -> String {
+    let mut tail = String::new();
+    if let Some(status) = status {
+        tail.push_str(&format!("\n⏳ {}", status));
+    }
+    if !streamed.is_empty() {
+        tail.push_str("\n\n");
+        tail.push_str(streamed);
+    }
+    // Streamed text longer than a message shows its end, where the new text is
+    let tail_chars = tail.chars().count();
+    if tail_chars > MESSAGE_LIMIT {
+        let skip = tail_chars - (MESSAGE_LIMIT - 1);
+        return format!("…{}", tail.chars().skip(skip).collect::<String>());
+    }
+
+    let mut budget = MESSAGE_LIMIT - tail_chars;
+    let mut lines: Vec<&str> = Vec::new();
+    for line in progress.iter().rev() {
+        let cost = line.chars().count() + 1;
+        if cost > budget {
+            break;
         }
+        budget -= cost;
+        lines.push(line);
+    }
+    lines.reverse();
 
-        let text = msg.content.clone();
-        if text.is_empty() {
+    let rendered = format!("{}{}", lines.join("\n"), tail);
+    match rendered.trim() {
+        "" => "⏳ Working on it…".to_string(),
+        text => text.to_string(),
+    }
+}
+
+/// A reply that is edited in place while the agent works
+struct LiveReply {
+    http: Arc<Http>,
+    channel: ChannelId,
+    message: Option<MessageId>,
+    progress: Vec<String>,
+    status: Option<String>,
+    streamed: String,
+    last_edit: Option<Instant>,
+    dirty: bool,
+}
+
+impl LiveReply {
+    fn new(http: Arc<Http>, channel: ChannelId) -> Self {
+        Self {
+            http,
+            channel,
+            message: None,
+            progress: Vec::new(),
+            status: None,
+            streamed: String::new(),
+            last_edit: None,
+            dirty: true,
+        }
+    }
+
+    fn push_progress(&mut self, line: String) {
+        self.progress.push(line);
+        self.dirty = true;
+    }
+
+    fn set_status(&mut self, status: String) {
+        self.status = Some(status);
+        self.dirty = true;
+    }
+
+    fn push_delta(&mut self, content: &str) {
+        self.streamed.push_str(content);
+        self.dirty = true;
+    }
+
+    /// Send or edit the message if something changed and the last edit is old enough
+    async fn flush(&mut self) {
+        if !self.dirty || self.last_edit.is_some_and(|at| at.elapsed() < EDIT_INTERVAL) {
             return;
         }
+        let content = render_live(&self.progress, self.status.as_deref(), &self.streamed);
+        self.dirty = false;
+        self.last_edit = Some(Instant::now());
+
+        match self.message {
+            Some(id) => {
+                if let Err(e) = self.channel.edit_message(&self.http, id, EditMessage::new().content(content)).await {
+                    log::warn!("Discord: Failed to update reply: {}", e);
+                }
+            }
+            None => match self.channel.say(&self.http, content).await {
+                Ok(msg) => self.message = Some(msg.id),
+                Err(e) => log::error!("Discord: Failed to send reply: {}", e),
+            },
+        }
+    }
 
-        let user_id = msg.author.id.to_string();
-        // Discord moved away from discriminators, so just use the username
-        // If discriminator exists and is non-zero, include it for backwards compatibility
-        let user_name = match msg.author.discriminator {
-            Some(disc) => format!("{}#{}", msg.author.name, disc),
-            None => msg.author.name.clone(),
+    /// Replace the live message with the final text, sending any overflow and
+    /// the attachments as follow-up messages
+    async fn finish(&mut self, text: &str, attachments: Vec<CreateAttachment>) {
+        let mut chunks = split_message(text, MESSAGE_LIMIT).into_iter();
+        if let Some(first) = chunks.next() {
+            match self.message {
+                Some(id) => {
+                    if let Err(e) = self.channel.edit_message(&self.http, id, EditMessage::new().content(first)).await {
+                        log::error!("Discord: Failed to send final reply: {}", e);
+                    }
+                }
+                None => {
+                    if let Err(e) = self.channel.say(&self.http, first).await {
+                        log::error!("Discord: Failed to send final reply: {}", e);
+                    }
+                }
+            }
+        }
+        for chunk in chunks {
+            if let Err(e) = self.channel.say(&self.http, chunk).await {
+                log::error!("Discord: Failed to send reply: {}", e);
+            }
+        }
+
+        if !attachments.is_empty() {
+            let count = attachments.len();
+            let message = CreateMessage::new()
+                .content(format!("📎 {} file{}", count, if count == 1 { "" } else { "s" }))
+                .add_files(attachments);
+            if let Err(e) = self.channel.send_message(&self.http, message).await {
+                log::error!("Discord: Failed to send attachments: {}", e);
+            }
+        }
+    }
+}
+
+/// Files written by `tool_calls` inside `workspace`, in the order they were
+/// first written. Paths outside the workspace, missing files and files over
+/// the upload limit are skipped.
+fn collect_artifacts(workspace: &Path, tool_calls: &[ToolInvocation]) -> Vec<PathBuf> {
+    let Ok(root) = workspace.canonicalize() else {
+        return Vec::new();
+    };
+    let mut files: Vec<PathBuf> = Vec::new();
+    for call in tool_calls {
+        if !call.success || !ARTIFACT_TOOLS.contains(&call.name.as_str()) {
+            continue;
+        }
+        let Some(path) = call.arguments.get("path").and_then(|p| p.as_str()) else {
+            continue;
+        };
+        let Ok(resolved) = root.join(path).canonicalize() else {
+            continue;
         };
+        let within_limit = resolved
+            .metadata()
+            .is_ok_and(|m| m.is_file() && m.len() <= MAX_ATTACHMENT_BYTES);
+        if resolved.starts_with(&root) && within_limit && !files.contains(&resolved) {
+            files.push(resolved);
+        }
+    }
+    files.truncate(MAX_ATTACHMENTS);
+    files
+}
+
+/// Thread titles are the start of the prompt
+fn thread_name(prompt: &str) -> String {
+    let first_line = prompt.lines().next().unwrap_or("").trim();
+    match truncate_chars(first_line, 90) {
+        "" => "stark".to_string(),
+        name if name.len() < first_line.len() => format!("{}…", name),
+        name => name.to_string(),
+    }
+}
+
+fn stark_command() -> CreateCommand {
+    CreateCommand::new(COMMAND_NAME)
+        .description("Ask stark-bot something; the conversation continues in a thread")
+        .add_option(
+            CreateCommandOption::new(CommandOptionType::String, "prompt", "What should stark-bot do?")
+                .required(true),
+        )
+}
+
+/// Who started a conversation and what they asked
+struct Conversation {
+    /// Where replies go (a thread, DM or channel); also the session key
+    reply_channel: ChannelId,
+    user_id: String,
+    user_name: String,
+    text: String,
+    message_id: Option<String>,
+}
+
+struct DiscordHandler {
+    channel_id: i64,
+    dispatcher: Arc<MessageDispatcher>,
+    broadcaster: Arc<EventBroadcaster>,
+}
 
+impl DiscordHandler {
+    /// Run a conversation turn, streaming progress into a live reply
+    async fn converse(&self, http: Arc<Http>, conversation: Conversation) {
         log::info!(
             "Discord: Message from {} ({}): {}",
-            user_name,
-            user_id,
-            if text.len() > 50 { &text[..50] } else { &text }
+            conversation.user_name,
+            conversation.user_id,
+            truncate_chars(&conversation.text, 50)
         );
 
         let normalized = NormalizedMessage {
             channel_id: self.channel_id,
             channel_type: ChannelType::Discord.to_string(),
-            chat_id: msg.channel_id.to_string(),
-            user_id,
-            user_name: user_name.clone(),
-            text,
-            message_id: Some(msg.id.to_string()),
+            chat_id: conversation.reply_channel.to_string(),
+            user_id: conversation.user_id,
+            user_name: conversation.user_name.clone(),
+            text: conversation.text,
+            message_id: conversation.message_id,
             session_mode: None,
+            seed: None,
+            model_archetype: None,
+            model_overrides: None,
+            use_tools: None,
+            response_format: None,
+            images: Vec::new(),
+            scopes: None,
+            tool_policy: None,
+            owner_id: None,
         };
 
-        // Subscribe to events for real-time tool call forwarding
+        let reply = Arc::new(Mutex::new(LiveReply::new(http, conversation.reply_channel)));
+        reply.lock().await.flush().await;
+
+        // Subscribe to events for real-time progress
         let (client_id, mut event_rx) = self.broadcaster.subscribe();
         log::info!("Discord: Subscribed to events as client {}", client_id);
 
-        // Clone context and channel info for the event forwarder task
-        let http = ctx.http.clone();
-        let discord_channel_id = msg.channel_id;
         let channel_id_for_events = self.channel_id;
-
-        // Spawn task to forward events to Discord in real-time
+        let reply_for_events = Arc::clone(&reply);
         let event_task = tokio::spawn(async move {
-            while let Some(event) = event_rx.recv().await {
-                // Only forward events for this channel
-                if let Some(event_channel_id) = event.data.get("channel_id").and_then(|v| v.as_i64()) {
-                    if event_channel_id != channel_id_for_events {
-                        continue;
-                    }
-                }
-
-                let message_text = match event.event.as_str() {
-                    "agent.tool_call" => {
-                        let tool_name = event.data.get("tool_name")
-                            .and_then(|v| v.as_str())
-                            .unwrap_or("unknown");
-                        let params = event.data.get("parameters")
-                            .cloned()
-                            .unwrap_or(serde_json::json!({}));
-                        Some(format_tool_call_for_discord(tool_name, &params))
-                    }
-                    "tool.result" => {
-                        let tool_name = event.data.get("tool_name")
-                            .and_then(|v| v.as_str())
-                            .unwrap_or("unknown");
-                        let success = event.data.get("success")
-                            .and_then(|v| v.as_bool())
-                            .unwrap_or(false);
-                        let duration_ms = event.data.get("duration_ms")
-                            .and_then(|v| v.as_i64())
-                            .unwrap_or(0);
-                        let content = event.data.get("content")
-                            .and_then(|v| v.as_str())
-                            .unwrap_or("");
-                        Some(format_tool_result_for_discord(tool_name, success, duration_ms, content))
-                    }
-                    "agent.mode_change" => {
-                        let mode = event.data.get("mode")
-                            .and_then(|v| v.as_str())
-                            .unwrap_or("unknown");
-                        let label = event.data.get("label")
-                            .and_then(|v| v.as_str())
-                            .unwrap_or("Unknown");
-                        let reason = event.data.get("reason")
-                            .and_then(|v| v.as_str());
-                        Some(format_mode_change_for_discord(mode, label, reason))
-                    }
-                    "execution.task_started" => {
-                        let task_type = event.data.get("type")
-                            .and_then(|v| v.as_str())
-                            .unwrap_or("task");
-                        let name = event.data.get("name")
-                            .and_then(|v| v.as_str())
-                            .unwrap_or("Unknown task");
-                        Some(format!("▶️ **{}:** {}", task_type, name))
-                    }
-                    "execution.task_completed" => {
-                        let status = event.data.get("status")
-                            .and_then(|v| v.as_str())
-                            .unwrap_or("completed");
-                        let emoji = if status == "completed" { "✅" } else { "❌" };
-                        Some(format!("{} Task {}", emoji, status))
-                    }
-                    _ => None,
-                };
-
-                if let Some(text) = message_text {
-                    // Split message if too long for Discord
-                    let chunks = split_message(&text, 2000);
-                    for chunk in chunks {
-                        if let Err(e) = discord_channel_id.say(&http, &chunk).await {
-                            log::error!("Discord: Failed to send event message: {}", e);
+            // Ticks flush edits that were held back by the edit interval
+            let mut tick = tokio::time::interval(EDIT_INTERVAL);
+            loop {
+                tokio::select! {
+                    event = event_rx.recv() => {
+                        let Some(event) = event else { break };
+                        // Only forward events for this channel
+                        if let Some(event_channel_id) = event.data.get("channel_id").and_then(|v| v.as_i64())
+                            && event_channel_id != channel_id_for_events
+                        {
+                            continue;
                         }
+                        let mut reply = reply_for_events.lock().await;
+                        match event.event.as_str() {
+                            "stream.content_delta" => {
+                                if let Some(content) = event.data.get("content").and_then(|v| v.as_str()) {
+                                    reply.push_delta(content);
+                                }
+                            }
+                            "agent.thinking" => {
+                                if let Some(message) = event.data.get("message").and_then(|v| v.as_str()) {
+                                    reply.set_status(truncate_str(message, 200));
+                                }
+                            }
+                            name => {
+                                if let Some(line) = progress_line(name, &event.data) {
+                                    reply.push_progress(line);
+                                }
+                            }
+                        }
+                        reply.flush().await;
                     }
+                    _ = tick.tick() => reply_for_events.lock().await.flush().await,
                 }
             }
         });
 
         // Dispatch to AI
-        log::info!("Discord: Dispatching message to AI for user {}", user_name);
+        log::info!("Discord: Dispatching message to AI for user {}", conversation.user_name);
         let result = self.dispatcher.dispatch(normalized).await;
         log::info!("Discord: Dispatch complete, error={:?}", result.error);
 
@@ -192,25 +396,152 @@ impl EventHandler for DiscordHandler {
         event_task.abort();
         log::info!("Discord: Unsubscribed from events, client {}", client_id);
 
-        // Send final response
-        if result.error.is_none() && !result.response.is_empty() {
-            // Discord has a 2000 character limit per message
-            let response = &result.response;
-            let chunks = split_message(response, 2000);
+        let text = match &result.error {
+            Some(error) => format!("Sorry, I encountered an error: {}", error),
+            None if result.response.is_empty() => "✅ Done.".to_string(),
+            None => result.response.clone(),
+        };
 
-            for chunk in chunks {
-                if let Err(e) = msg.channel_id.say(&ctx.http, &chunk).await {
-                    log::error!("Failed to send Discord message: {}", e);
+        let mut attachments = Vec::new();
+        if let Some(workspace) = result.workspace.as_deref() {
+            for path in collect_artifacts(Path::new(workspace), &result.tool_calls) {
+                match CreateAttachment::path(&path).await {
+                    Ok(attachment) => attachments.push(attachment),
+                    Err(e) => log::warn!("Discord: Failed to read artifact {}: {}", path.display(), e),
                 }
             }
-        } else if let Some(error) = result.error {
-            let error_msg = format!("Sorry, I encountered an error: {}", error);
-            let _ = msg.channel_id.say(&ctx.http, &error_msg).await;
+        }
+
+        reply.lock().await.finish(&text, attachments).await;
+    }
+
+    /// Open a thread for a new conversation, or fall back to `channel` if that fails
+    async fn open_thread(&self, http: &Http, channel: ChannelId, message: MessageId, prompt: &str) -> ChannelId {
+        let builder = CreateThread::new(thread_name(prompt)).auto_archive_duration(AutoArchiveDuration::OneDay);
+        match channel.create_thread_from_message(http, message, builder).await {
+            Ok(thread) => thread.id,
+            Err(e) => {
+                log::warn!("Discord: Couldn't open a thread, replying in channel: {}", e);
+                channel
+            }
+        }
+    }
+
+    async fn handle_command(&self, ctx: Context, command: CommandInteraction) {
+        if command.data.name != COMMAND_NAME {
+            return;
+        }
+        let prompt = command
+            .data
+            .options
+            .iter()
+            .find(|o| o.name == "prompt")
+            .and_then(|o| o.value.as_str())
+            .unwrap_or("")
+            .trim()
+            .to_string();
+        if prompt.is_empty() {
+            return;
+        }
+
+        // The command's response becomes the first message of the thread
+        let opening = format!("**{}:** {}", command.user.name, truncate_str(&prompt, 1800));
+        let response = CreateInteractionResponse::Message(CreateInteractionResponseMessage::new().content(opening));
+        if let Err(e) = command.create_response(&ctx.http, response).await {
+            log::error!("Discord: Failed to answer /{}: {}", COMMAND_NAME, e);
+            return;
+        }
+
+        let reply_channel = if command.guild_id.is_some() {
+            match command.get_response(&ctx.http).await {
+                Ok(msg) => self.open_thread(&ctx.http, command.channel_id, msg.id, &prompt).await,
+                Err(e) => {
+                    log::warn!("Discord: Couldn't fetch /{} response: {}", COMMAND_NAME, e);
+                    command.channel_id
+                }
+            }
+        } else {
+            command.channel_id
+        };
+
+        self.converse(
+            ctx.http.clone(),
+            Conversation {
+                reply_channel,
+                user_id: command.user.id.to_string(),
+                user_name: command.user.name.clone(),
+                text: prompt,
+                message_id: None,
+            },
+        )
+        .await;
+    }
+}
+
+#[serenity::async_trait]
+impl EventHandler for DiscordHandler {
+    async fn message(&self, ctx: Context, msg: Message) {
+        // Ignore messages from bots (including ourselves)
+        if msg.author.bot {
+            return;
+        }
+
+        let text = msg.content.clone();
+        if text.is_empty() {
+            return;
+        }
+
+        // Discord moved away from discriminators, so just use the username
+        // If discriminator exists and is non-zero, include it for backwards compatibility
+        let user_name = match msg.author.discriminator {
+            Some(disc) => format!("{}#{}", msg.author.name, disc),
+            None => msg.author.name.clone(),
+        };
+
+        // DMs and threads continue in place; a message in a server channel starts a thread
+        let in_thread = match msg.channel_id.to_channel(&ctx.http).await {
+            Ok(channel) => channel.guild().is_some_and(|c| {
+                matches!(
+                    c.kind,
+                    DiscordChannelType::PublicThread | DiscordChannelType::PrivateThread | DiscordChannelType::NewsThread
+                )
+            }),
+            Err(e) => {
+                log::warn!("Discord: Couldn't look up channel {}: {}", msg.channel_id, e);
+                true
+            }
+        };
+        let reply_channel = if msg.guild_id.is_none() || in_thread {
+            msg.channel_id
+        } else {
+            self.open_thread(&ctx.http, msg.channel_id, msg.id, &text).await
+        };
+
+        self.converse(
+            ctx.http.clone(),
+            Conversation {
+                reply_channel,
+                user_id: msg.author.id.to_string(),
+                user_name,
+                text,
+                message_id: Some(msg.id.to_string()),
+            },
+        )
+        .await;
+    }
+
+    async fn interaction_create(&self, ctx: Context, interaction: Interaction) {
+        if let Interaction::Command(command) = interaction {
+            self.handle_command(ctx, command).await;
         }
     }
 
-    async fn ready(&self, _ctx: Context, ready: Ready) {
+    async fn ready(&self, ctx: Context, ready: Ready) {
         log::info!("Discord: Bot connected as {}", ready.user.name);
+        match Command::create_global_command(&ctx.http, stark_command()).await {
+            Ok(_) => log::info!("Discord: Registered /{} command", COMMAND_NAME),
+            Err(e) => log::error!("Discord: Failed to register /{} command: {}", COMMAND_NAME, e),
+        }
     }
 }
 
@@ -233,8 +564,13 @@ fn split_message(text: &str, max_len: usize) -> Vec<String> {
             if line.len() > max_len {
                 let mut remaining = line;
                 while remaining.len() > max_len {
-                    chunks.push(remaining[..max_len].to_string());
-                    remaining = &remaining[max_len..];
+                    // Back off to a char boundary so multi-byte text doesn't panic
+                    let mut split = max_len;
+                    while !remaining.is_char_boundary(split) {
+                        split -= 1;
+                    }
+                    chunks.push(remaining[..split].to_string());
+                    remaining = &remaining[split..];
                 }
                 if !remaining.is_empty() {
                     current = remaining.to_string();
@@ -332,3 +668,64 @@ pub async fn start_discord_listener(
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn invocation(name: &str, path: &str, success: bool) -> ToolInvocation {
+        ToolInvocation {
+            name: name.to_string(),
+            arguments: json!({ "path": path }),
+            success,
+            output: String::new(),
+            duration_ms: 1,
+        }
+    }
+
+    #[test]
+    fn test_collect_artifacts_stays_in_workspace() {
+        let dir = tempfile::tempdir().unwrap();
+        let workspace = dir.path().join("ws");
+        std::fs::create_dir_all(workspace.join("src")).unwrap();
+        std::fs::write(workspace.join("src/main.py"), "print(1)").unwrap();
+        std::fs::write(workspace.join("notes.md"), "# notes").unwrap();
+        std::fs::write(dir.path().join("secret.txt"), "nope").unwrap();
+
+        let calls = vec![
+            invocation("write_file", "src/main.py", true),
+            invocation("read_file", "notes.md", true),
+            invocation("edit_file", "notes.md", true),
+            invocation("write_file", "src/main.py", true),
+            invocation("write_file", "../secret.txt", true),
+            invocation("write_file", "missing.txt", true),
+            invocation("write_file", "failed.txt", false),
+        ];
+        let files = collect_artifacts(&workspace, &calls);
+        let names: Vec<_> = files.iter().map(|p| p.file_name().unwrap().to_str().unwrap()).collect();
+        assert_eq!(names, vec!["main.py", "notes.md"]);
+    }
+
+    #[test]
+    fn test_render_live_fits_message_limit() {
+        assert_eq!(render_live(&[], None, ""), "⏳ Working on it…");
+
+        let progress: Vec<String> = (0..200).map(|i| format!("🔧 `tool_{}` `{{}}`", i)).collect();
+        let text = render_live(&progress, Some("Thinking (10s)"), "Partial answer");
+        assert!(text.chars().count() <= MESSAGE_LIMIT);
+        assert!(text.contains("tool_199"));
+        assert!(!text.contains("tool_0`"));
+        assert!(text.ends_with("⏳ Thinking (10s)\n\nPartial answer"));
+
+        let long = "x".repeat(MESSAGE_LIMIT * 2);
+        assert!(render_live(&progress, None, &long).chars().count() <= MESSAGE_LIMIT);
+    }
+
+    #[test]
+    fn test_thread_name() {
+        assert_eq!(thread_name("Fix the build\nand more"), "Fix the build");
+        assert_eq!(thread_name("   "), "stark");
+        assert_eq!(thread_name(&"a".repeat(200)).chars().count(), 91);
+    }
+}
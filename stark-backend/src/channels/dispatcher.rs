@@ -1,20 +1,27 @@
+use crate::ai::budget::{BudgetTracker, BudgetUsage, SessionBudget};
 use crate::ai::{
     multi_agent::{types::{AgentSubtype, AgentMode}, Orchestrator, ProcessResult as OrchestratorResult, SubAgentManager},
+    structured::MAX_REPAIR_ATTEMPTS,
     AiClient, ArchetypeId, ArchetypeRegistry, AiResponse, Message, MessageRole, ModelArchetype,
-    ThinkingLevel, ToolHistoryEntry, ToolResponse,
+    ResponseFormat, ThinkingLevel, ToolHistoryEntry, ToolResponse,
 };
-use crate::channels::types::{DispatchResult, NormalizedMessage};
+use crate::channels::types::{DispatchResult, NormalizedMessage, ToolInvocation};
 use crate::config::MemoryConfig;
-use crate::context::{self, estimate_tokens, ContextManager};
+use crate::context::{self, estimate_tokens, ContextManager, ContextWindow};
 use crate::controllers::api_keys::ApiKeyId;
 use std::str::FromStr;
 use crate::db::Database;
 use crate::execution::ExecutionTracker;
 use crate::gateway::events::EventBroadcaster;
 use crate::gateway::protocol::GatewayEvent;
+use crate::memory::HybridSearcher;
 use crate::models::session_message::MessageRole as DbMessageRole;
-use crate::models::{AgentSettings, CompletionStatus, MemoryType, SessionScope, DEFAULT_MAX_TOOL_ITERATIONS};
-use crate::tools::{ToolConfig, ToolContext, ToolDefinition, ToolExecution, ToolRegistry};
+use crate::models::{AgentSettings, CompletionStatus, MemoryType, Persona, SessionScope, ToolPolicy, DEFAULT_MAX_TOOL_ITERATIONS};
+use crate::redaction::redact_value;
+use crate::tools::{RegisterStore, ToolConfig, ToolContext, ToolDefinition, ToolRegistry};
+use crate::x402::X402PaymentInfo;
+use crate::workspace::{uploads_prompt, WorkspaceKind, WorkspaceManager};
+use crate::utils::truncate_chars;
 use chrono::Utc;
 use once_cell::sync::Lazy;
 use regex::Regex;
@@ -38,6 +45,32 @@ const FALLBACK_MAX_TOOL_ITERATIONS: usize = DEFAULT_MAX_TOOL_ITERATIONS as usize
 /// How often to broadcast "still waiting" events during long AI calls
 const AI_PROGRESS_INTERVAL_SECS: u64 = 30;
 
+/// Rough token count for a provider request, used when the provider doesn't report usage
+fn estimate_request_tokens(conversation: &[Message], tool_history: &[ToolHistoryEntry]) -> u64 {
+    let messages: i32 = conversation.iter().map(|m| estimate_tokens(&m.content)).sum();
+    let tool_results: i32 = tool_history
+        .iter()
+        .flat_map(|entry| entry.tool_responses.iter())
+        .map(|r| estimate_tokens(&r.content))
+        .sum();
+    (messages + tool_results) as u64
+}
+
+/// Final answer when the loop was stopped by the request budget:
+/// the best content produced so far followed by a "budget exceeded" note
+fn budget_exceeded_response(last_content: &str, tool_call_log: &[String], budget: &BudgetTracker) -> String {
+    let best = if !last_content.is_empty() {
+        last_content.to_string()
+    } else {
+        tool_call_log.join("\n")
+    };
+    if best.is_empty() {
+        budget.exceeded_note()
+    } else {
+        format!("{}\n\n{}", best, budget.exceeded_note())
+    }
+}
+
 /// Configuration for a memory marker pattern
 struct MemoryMarkerConfig {
     pattern: Regex,
@@ -133,6 +166,8 @@ pub struct MessageDispatcher {
     skill_registry: Option<Arc<crate::skills::SkillRegistry>>,
     /// Hook manager for lifecycle events
     hook_manager: Option<Arc<crate::hooks::HookManager>>,
+    /// Process manager for background commands started by tools
+    process_manager: Option<Arc<crate::execution::ProcessManager>>,
 }
 
 impl MessageDispatcher {
@@ -182,7 +217,7 @@ impl MessageDispatcher {
             Default::default(),
             burner_wallet_private_key.clone(),
         ));
-        log::info!("[DISPATCHER] SubAgentManager initialized");
+        tracing::info!("[DISPATCHER] SubAgentManager initialized");
 
         Self {
             db,
@@ -197,6 +232,7 @@ impl MessageDispatcher {
             subagent_manager: Some(subagent_manager),
             skill_registry,
             hook_manager: None,
+            process_manager: None,
         }
     }
 
@@ -206,6 +242,12 @@ impl MessageDispatcher {
         self
     }
 
+    /// Set the process manager for background command execution
+    pub fn with_process_manager(mut self, process_manager: Arc<crate::execution::ProcessManager>) -> Self {
+        self.process_manager = Some(process_manager);
+        self
+    }
+
     /// Create a dispatcher without tool support (for backwards compatibility)
     pub fn new_without_tools(db: Arc<Database>, broadcaster: Arc<EventBroadcaster>) -> Self {
         // Create a minimal execution tracker for legacy use
@@ -226,6 +268,7 @@ impl MessageDispatcher {
             subagent_manager: None, // No tools = no subagent support
             skill_registry: None,   // No skills without tools
             hook_manager: None,     // No hooks without explicit setup
+            process_manager: None,  // No background processes without tools
         }
     }
 
@@ -235,6 +278,11 @@ impl MessageDispatcher {
     }
 
     /// Dispatch a normalized message to the AI and return the response
+    #[tracing::instrument(
+        name = "dispatch",
+        skip_all,
+        fields(channel_id = message.channel_id, channel_type = %message.channel_type, chat_id = %message.chat_id)
+    )]
     pub async fn dispatch(&self, message: NormalizedMessage) -> DispatchResult {
         // Emit message received event
         self.broadcaster.broadcast(GatewayEvent::channel_message(
@@ -271,11 +319,11 @@ impl MessageDispatcher {
             &message.channel_type,
             &message.user_id,
             Some(&message.user_name),
-        ) {
+        ).await {
             Ok(id) => id,
             Err(e) => {
                 let error_msg = format!("Identity error: {}", e);
-                log::error!("Failed to get/create identity: {}", e);
+                tracing::error!("Failed to get/create identity: {}", e);
                 self.broadcaster.broadcast(GatewayEvent::agent_error(
                     message.channel_id,
                     &error_msg,
@@ -316,11 +364,12 @@ impl MessageDispatcher {
             &message.chat_id,
             scope,
             None,
-        ) {
+            message.owner_id,
+        ).await {
             Ok(s) => s,
             Err(e) => {
                 let error_msg = format!("Session error: {}", e);
-                log::error!("Failed to get/create session: {}", e);
+                tracing::error!("Failed to get/create session: {}", e);
                 self.broadcaster.broadcast(GatewayEvent::agent_error(
                     message.channel_id,
                     &error_msg,
@@ -329,24 +378,25 @@ impl MessageDispatcher {
                 return DispatchResult::error(error_msg);
             }
         };
+        self.execution_tracker.set_execution_session(&execution_id, session.id);
 
         // Reset session state when a new message comes in on a previously-completed session
         // This allows the session to be reused for new requests
-        if let Ok(Some(status)) = self.db.get_session_completion_status(session.id) {
+        if let Ok(Some(status)) = self.db.get_session_completion_status(session.id).await {
             if status.should_stop() {
-                log::info!(
+                tracing::info!(
                     "[DISPATCH] Resetting session {} from {:?} to Active for new request",
                     session.id, status
                 );
-                if let Err(e) = self.db.update_session_completion_status(session.id, CompletionStatus::Active) {
-                    log::error!("[DISPATCH] Failed to reset session completion status: {}", e);
+                if let Err(e) = self.db.update_session_completion_status(session.id, CompletionStatus::Active).await {
+                    tracing::error!("[DISPATCH] Failed to reset session completion status: {}", e);
                 }
                 // Also reset total_iterations in AgentContext if it exists
-                if let Ok(Some(mut context)) = self.db.get_agent_context(session.id) {
+                if let Ok(Some(mut context)) = self.db.get_agent_context(session.id).await {
                     context.total_iterations = 0;
                     context.mode_iterations = 0;
-                    if let Err(e) = self.db.save_agent_context(session.id, &context) {
-                        log::error!("[DISPATCH] Failed to reset agent context iterations: {}", e);
+                    if let Err(e) = self.db.save_agent_context(session.id, &context).await {
+                        tracing::error!("[DISPATCH] Failed to reset agent context iterations: {}", e);
                     }
                 }
             }
@@ -367,31 +417,80 @@ impl MessageDispatcher {
             Some(&message.user_name),
             message.message_id.as_deref(),
             Some(user_tokens),
-        ) {
-            log::error!("Failed to store user message: {}", e);
+        ).await {
+            tracing::error!("Failed to store user message: {}", e);
         } else {
             // Update context tokens
-            self.context_manager.update_context_tokens(session.id, user_tokens);
+            self.context_manager.update_context_tokens(session.id, user_tokens).await;
         }
 
         // Get active agent settings from database, falling back to kimi defaults
-        let settings = match self.db.get_active_agent_settings() {
+        let mut settings = match self.db.get_active_agent_settings().await {
             Ok(Some(settings)) => settings,
             Ok(None) => {
-                log::info!("No agent configured, using default kimi settings");
+                tracing::info!("No agent configured, using default kimi settings");
                 AgentSettings::default()
             }
             Err(e) => {
                 let error = format!("Database error: {}", e);
-                log::error!("{}", error);
+                tracing::error!("{}", error);
                 self.execution_tracker.complete_execution(message.channel_id);
                 return DispatchResult::error(error);
             }
         };
 
+        // Per-request archetype override (web chat API)
+        if let Some(archetype_id) = message.model_archetype {
+            settings.model_archetype = archetype_id.to_string();
+        }
+
+        // The conversation owner's own provider key, if they brought one
+        let provider_key_id = match self.db.apply_provider_key(&mut settings, session.user_id).await {
+            Ok(id) => id,
+            Err(e) => {
+                tracing::error!("[DISPATCH] Failed to load provider key: {}", e);
+                None
+            }
+        };
+
+        // The conversation's persona, or the agent's default one
+        let persona = self
+            .resolve_persona(session.persona.as_deref().or(settings.default_persona.as_deref()))
+            .await;
+        let model_overrides = match &persona {
+            Some(persona) => persona.model_overrides(message.model_overrides.as_ref()),
+            None => message.model_overrides.clone(),
+        };
+
+        // Per-request spending ceiling (global config, overridable per agent profile)
+        let mut budget_config = crate::config::budget_config()
+            .with_overrides(settings.budget_max_tokens, settings.budget_max_usd);
+
+        // Session-wide hard budget: refuse once used up, otherwise cap this request at what is left
+        let session_budget = SessionBudget::from_settings(&settings);
+        if !session_budget.is_unlimited() {
+            match self.db.get_session_usage(session.id).await {
+                Ok(spent) => {
+                    if let Some(reason) = session_budget.exhausted_reason(&spent) {
+                        tracing::warn!("[DISPATCH] Session {}: {}", session.id, reason);
+                        self.broadcaster.broadcast(GatewayEvent::agent_error(
+                            message.channel_id,
+                            &reason,
+                        ));
+                        self.execution_tracker.complete_execution(message.channel_id);
+                        return DispatchResult::error(reason);
+                    }
+                    budget_config = session_budget.limit_request(budget_config, &spent);
+                }
+                Err(e) => tracing::error!("[DISPATCH] Failed to load usage for session {}: {}", session.id, e),
+            }
+        }
+        let mut budget = BudgetTracker::new(budget_config);
+        let context_window = ContextWindow::from_settings(&settings);
+
         // Infer archetype from settings
         let archetype_id = AiClient::infer_archetype(&settings);
-        log::info!(
+        tracing::info!(
             "Using endpoint {} for message dispatch (archetype={}, max_tokens={})",
             settings.endpoint,
             archetype_id,
@@ -403,10 +502,23 @@ impl MessageDispatcher {
             &settings,
             self.burner_wallet_private_key.as_deref(),
         ) {
-            Ok(c) => c.with_broadcaster(Arc::clone(&self.broadcaster), message.channel_id),
+            Ok(c) => {
+                let c = c
+                    .with_broadcaster(Arc::clone(&self.broadcaster), message.channel_id)
+                    .with_seed(message.seed)
+                    .with_response_format(message.response_format.as_ref());
+                // Per-request model parameters (web chat API), or the persona's model
+                match &model_overrides {
+                    Some(overrides) => {
+                        tracing::info!("[DISPATCH] Request overrides: {:?}", overrides);
+                        c.with_overrides(overrides)
+                    }
+                    None => c,
+                }
+            }
             Err(e) => {
                 let error = format!("Failed to create AI client: {}", e);
-                log::error!("{}", error);
+                tracing::error!("{}", error);
                 self.broadcaster.broadcast(GatewayEvent::agent_error(
                     message.channel_id,
                     &error,
@@ -420,36 +532,75 @@ impl MessageDispatcher {
         self.execution_tracker.add_thinking(message.channel_id, "Processing request...");
 
         // Get tool configuration for this channel (needed for system prompt)
-        let tool_config = self.db.get_effective_tool_config(Some(message.channel_id))
+        let mut tool_config = self.db.get_effective_tool_config(Some(message.channel_id)).await
             .unwrap_or_default();
 
+        // Withhold tools the sender's API token isn't scoped for. Subagents
+        // re-read the channel's tool config, so they go too.
+        if let Some(scopes) = &message.scopes {
+            let denied = self.tool_registry.tools_outside_scopes(scopes);
+            if !denied.is_empty() {
+                tracing::info!("[DISPATCH] Token scopes deny tools: {:?}", denied);
+                tool_config.deny_list.extend(denied);
+                tool_config.deny_list.push("subagent".to_string());
+            }
+        }
+
+        // So do the tool policies of the sender's API token and of the session
+        let tool_policies: Vec<ToolPolicy> =
+            message.tool_policy.iter().chain(session.tool_policy.iter()).cloned().collect();
+        for policy in &tool_policies {
+            let denied = self.tool_registry.tools_outside_policy(policy);
+            if !denied.is_empty() {
+                tracing::info!("[DISPATCH] Tool policy denies tools: {:?}", denied);
+                tool_config.deny_list.extend(denied);
+                tool_config.deny_list.push("subagent".to_string());
+            }
+        }
+
+        // A persona narrows the channel's tool groups to its own
+        if let Some(persona) = persona.as_ref().filter(|p| !p.tool_groups.is_empty()) {
+            tool_config.restrict_to_groups(&persona.tool_groups);
+        }
+
         // Debug: Log tool configuration
-        log::info!(
+        tracing::info!(
             "[DISPATCH] Tool config - profile: {:?}, allowed_groups: {:?}",
             tool_config.profile,
             tool_config.allowed_groups
         );
 
         // Build context from memories, tools, skills, and session history
-        let system_prompt = self.build_system_prompt(&message, &identity.identity_id, &tool_config);
+        let mut system_prompt = self
+            .build_system_prompt(&message, &identity.identity_id, &tool_config, persona.as_ref())
+            .await;
+        let uploads = WorkspaceManager::from_env().uploads(WorkspaceKind::Session, &session.id.to_string());
+        if let Some(section) = uploads_prompt(&uploads) {
+            system_prompt = format!("{}\n\n{}", system_prompt, section.trim_end());
+        }
+        if let Some(format) = &message.response_format {
+            system_prompt = format!("{}\n\n{}", system_prompt, format.instructions());
+        }
 
         // Debug: Log full system prompt
-        log::debug!("[DISPATCH] System prompt:\n{}", system_prompt);
+        tracing::debug!("[DISPATCH] System prompt:\n{}", system_prompt);
 
         // Get recent session messages for conversation context
-        let history = self.db.get_recent_session_messages(session.id, 20).unwrap_or_default();
+        let history = self.db.get_recent_session_messages(session.id, 20).await.unwrap_or_default();
 
         // Build messages for the AI
         let mut messages = vec![Message {
             role: MessageRole::System,
             content: system_prompt.clone(),
+            images: Vec::new(),
         }];
 
         // Add compaction summary if available (provides context from earlier in conversation)
-        if let Some(compaction_summary) = self.context_manager.get_compaction_summary(session.id) {
+        if let Some(compaction_summary) = self.context_manager.get_compaction_summary(session.id).await {
             messages.push(Message {
                 role: MessageRole::System,
                 content: format!("## Previous Conversation Summary\n{}", compaction_summary),
+                images: Vec::new(),
             });
         }
 
@@ -466,39 +617,73 @@ impl MessageDispatcher {
             messages.push(Message {
                 role,
                 content: msg.content.clone(),
+                images: Vec::new(),
             });
         }
 
+        // Images referring to uploaded files are read from the session's workspace
+        let images = if message.images.is_empty() {
+            Ok(Vec::new())
+        } else {
+            WorkspaceManager::from_env().for_session(session.id).and_then(|workspace| {
+                message.images.iter().map(|image| image.resolve(&workspace)).collect::<Result<Vec<_>, _>>()
+            })
+        };
+        let images = match images {
+            Ok(images) => images,
+            Err(e) => {
+                let error = format!("Invalid image: {}", e);
+                self.broadcaster.broadcast(GatewayEvent::agent_error(message.channel_id, &error));
+                self.execution_tracker.complete_execution(message.channel_id);
+                return DispatchResult::error(error);
+            }
+        };
+
         // Add current user message (use clean text without thinking directive)
         messages.push(Message {
             role: MessageRole::User,
             content: message_text.to_string(),
+            images,
         });
 
         // Debug: Log user message
-        log::info!("[DISPATCH] User message: {}", message_text);
+        tracing::info!("[DISPATCH] User message: {}", message_text);
 
         // Apply thinking level if set (for Claude models)
         if let Some(level) = thinking_level {
             if client.supports_thinking() {
-                log::info!("[DISPATCH] Applying thinking level: {}", level);
+                tracing::info!("[DISPATCH] Applying thinking level: {}", level);
                 client.set_thinking_level(level);
             }
         }
 
-        // Check if the client supports tools and tools are configured
-        let use_tools = client.supports_tools() && !self.tool_registry.is_empty();
+        // Check if the client supports tools and tools are configured (and not turned off for this message)
+        let use_tools = message.use_tools.unwrap_or(true)
+            && client.supports_tools()
+            && !self.tool_registry.is_empty();
 
         // Debug: Log tool availability
-        log::info!(
+        tracing::info!(
             "[DISPATCH] Tool support - client_supports: {}, registry_count: {}, use_tools: {}",
             client.supports_tools(),
             self.tool_registry.len(),
             use_tools
         );
 
-        // Build tool context with API keys from database
-        let workspace_dir = crate::config::workspace_dir();
+        // Build tool context with API keys from database. Each session works in
+        // its own directory so files from one conversation don't leak into another,
+        // unless a tool policy names one (the session's over the token's).
+        let policy_root = tool_policies.iter().rev().find_map(|p| p.workspace_root.clone());
+        let workspace_dir = match policy_root {
+            Some(root) => root,
+            None => match WorkspaceManager::from_env().for_session(session.id) {
+                Ok(dir) => dir.to_string_lossy().to_string(),
+                Err(e) => {
+                    tracing::error!("[DISPATCH] {}", e);
+                    crate::config::workspace_dir()
+                }
+            },
+        };
 
         let mut tool_context = ToolContext::new()
             .with_channel(message.channel_id, message.channel_type.clone())
@@ -506,18 +691,28 @@ impl MessageDispatcher {
             .with_session(session.id)
             .with_workspace(workspace_dir.clone())
             .with_broadcaster(self.broadcaster.clone())
-            .with_database(self.db.clone());
+            .with_database(self.db.clone())
+            .with_registers(RegisterStore::persistent(self.db.clone(), session.id).await);
+        for policy in tool_policies {
+            tool_context = tool_context.with_tool_policy(policy);
+        }
 
         // Add SubAgentManager for spawning background AI agents
         if let Some(ref manager) = self.subagent_manager {
             tool_context = tool_context.with_subagent_manager(manager.clone());
-            log::debug!("[DISPATCH] SubAgentManager attached to tool context");
+            tracing::debug!("[DISPATCH] SubAgentManager attached to tool context");
+        }
+
+        // Add ProcessManager for background command execution
+        if let Some(ref manager) = self.process_manager {
+            tool_context = tool_context.with_process_manager(manager.clone());
+            tracing::debug!("[DISPATCH] ProcessManager attached to tool context");
         }
 
         // Add SkillRegistry for skill management
         if let Some(ref registry) = self.skill_registry {
             tool_context = tool_context.with_skill_registry(registry.clone());
-            log::debug!("[DISPATCH] SkillRegistry attached to tool context");
+            tracing::debug!("[DISPATCH] SkillRegistry attached to tool context");
         }
 
         // Ensure workspace directory exists
@@ -525,12 +720,17 @@ impl MessageDispatcher {
 
         // Load API keys from database for tools that need them
         // Each key is stored individually (e.g., "GITHUB_TOKEN", "DISCORD_BOT_TOKEN")
-        // Keys are added to both ToolContext AND environment variables for maximum compatibility
+        // Keys are added to both ToolContext AND environment variables for maximum compatibility.
+        // The owner's personal keys replace the shared ones, in the tool context only:
+        // the process environment is shared by every conversation.
         let mut github_token_loaded = false;
-        if let Ok(keys) = self.db.list_api_keys() {
+        if let Ok(keys) = self.db.effective_api_keys(message.owner_id).await {
             for key in keys {
                 // Add to tool context (for tools that use context.get_api_key)
                 tool_context = tool_context.with_api_key(&key.service_name, key.api_key.clone());
+                if key.user_id.is_some() {
+                    continue;
+                }
 
                 // Also set as environment variables (for tools that use std::env)
                 // Use the ApiKeyId to get all env var names for this key
@@ -552,7 +752,7 @@ impl MessageDispatcher {
         // and set GITHUB_USER env var for use in git/gh commands
         if github_token_loaded {
             if let Ok(github_user) = self.get_github_authenticated_user().await {
-                log::info!("[DISPATCH] GitHub authenticated as: {}", github_user);
+                tracing::info!("[DISPATCH] GitHub authenticated as: {}", github_user);
                 unsafe { std::env::set_var("GITHUB_USER", &github_user); }
                 tool_context.extra.insert(
                     "github_user".to_string(),
@@ -562,7 +762,7 @@ impl MessageDispatcher {
         }
 
         // Load bot config from bot_settings for git commits etc.
-        if let Ok(bot_settings) = self.db.get_bot_settings() {
+        if let Ok(bot_settings) = self.db.get_bot_settings().await {
             tool_context = tool_context.with_bot_config(bot_settings.bot_name.clone(), bot_settings.bot_email.clone());
 
             // Add RPC configuration to context for x402_rpc tool
@@ -579,6 +779,7 @@ impl MessageDispatcher {
         }
 
         // Generate response with optional tool execution loop
+        let mut tool_calls = Vec::new();
         let final_response = if use_tools {
             self.generate_with_tool_loop(
                 &client,
@@ -589,32 +790,32 @@ impl MessageDispatcher {
                 session.id,
                 &message,
                 archetype_id,
+                &mut budget,
+                &context_window,
+                &mut tool_calls,
             ).await
         } else {
             // Simple generation without tools - with x402 event emission
+            let estimated_input = estimate_request_tokens(&messages, &[]);
             match client.generate_text_with_events(messages, &self.broadcaster, message.channel_id).await {
                 Ok((content, payment)) => {
+                    budget.record(None, estimated_input, estimate_tokens(&content) as u64);
                     // Save x402 payment if one was made
                     if let Some(ref payment_info) = payment {
-                        if let Err(e) = self.db.record_x402_payment(
-                            Some(message.channel_id),
-                            None,
-                            payment_info.resource.as_deref(),
-                            &payment_info.amount,
-                            &payment_info.amount_formatted,
-                            &payment_info.asset,
-                            &payment_info.pay_to,
-                            payment_info.tx_hash.as_deref(),
-                            &payment_info.status.to_string(),
-                        ) {
-                            log::error!("[DISPATCH] Failed to record x402 payment: {}", e);
-                        }
+                        self.record_ai_payment(session.id, message.channel_id, payment_info).await;
                     }
                     Ok(content)
                 }
                 Err(e) => Err(e),
             }
         };
+        let final_response = match (final_response, &message.response_format) {
+            (Ok(response), Some(format)) => {
+                self.enforce_response_format(&client, format, response, &mut budget, session.id, message.channel_id)
+                    .await
+            }
+            (result, _) => result,
+        };
 
         match final_response {
             Ok(response) => {
@@ -625,7 +826,7 @@ impl MessageDispatcher {
                     session.id,
                     &message.channel_type,
                     message.message_id.as_deref(),
-                );
+                ).await;
 
                 // Clean response by removing memory markers before storing/returning
                 let clean_response = self.clean_response(&response);
@@ -642,21 +843,21 @@ impl MessageDispatcher {
                     None,
                     None,
                     Some(response_tokens),
-                ) {
-                    log::error!("Failed to store AI response: {}", e);
+                ).await {
+                    tracing::error!("Failed to store AI response: {}", e);
                 } else {
                     // Update context tokens
-                    self.context_manager.update_context_tokens(session.id, response_tokens);
+                    self.context_manager.update_context_tokens(session.id, response_tokens).await;
 
                     // Check if compaction is needed
-                    if self.context_manager.needs_compaction(session.id) {
-                        log::info!("[COMPACTION] Context limit reached for session {}, triggering compaction", session.id);
+                    if self.context_manager.needs_compaction(session.id).await {
+                        tracing::info!("[COMPACTION] Context limit reached for session {}, triggering compaction", session.id);
                         if let Err(e) = self.context_manager.compact_session(
                             session.id,
                             &client,
                             Some(&identity.identity_id),
                         ).await {
-                            log::error!("[COMPACTION] Failed to compact session: {}", e);
+                            tracing::error!("[COMPACTION] Failed to compact session: {}", e);
                         }
                     }
                 }
@@ -668,7 +869,7 @@ impl MessageDispatcher {
                     &clean_response,
                 ));
 
-                log::info!(
+                tracing::info!(
                     "Generated response for {} on channel {} using {} archetype",
                     message.user_name,
                     message.channel_id,
@@ -678,11 +879,16 @@ impl MessageDispatcher {
                 // Complete execution tracking
                 self.execution_tracker.complete_execution(message.channel_id);
 
+                let usage = budget.usage();
+                self.record_usage(session.id, message.channel_id, &settings.model_archetype, provider_key_id, &usage).await;
                 DispatchResult::success(clean_response)
+                    .with_usage(usage)
+                    .with_tool_calls(tool_calls)
+                    .with_workspace(workspace_dir)
             }
             Err(e) => {
                 let error = format!("AI generation error ({}): {}", archetype_id, e);
-                log::error!("{}", error);
+                tracing::error!("{}", error);
 
                 // Broadcast error to frontend
                 self.broadcaster.broadcast(GatewayEvent::agent_error(
@@ -693,11 +899,93 @@ impl MessageDispatcher {
                 // Complete execution tracking on error
                 self.execution_tracker.complete_execution(message.channel_id);
 
+                let usage = budget.usage();
+                self.record_usage(session.id, message.channel_id, &settings.model_archetype, provider_key_id, &usage).await;
                 DispatchResult::error(error)
+                    .with_usage(usage)
+                    .with_tool_calls(tool_calls)
+                    .with_workspace(workspace_dir)
             }
         }
     }
 
+    /// Persist what a request spent (feeds /api/usage and session budgets)
+    async fn record_usage(
+        &self,
+        session_id: i64,
+        channel_id: i64,
+        model: &str,
+        api_key_id: Option<i64>,
+        usage: &BudgetUsage,
+    ) {
+        if usage.provider_calls == 0 {
+            return;
+        }
+        if let Err(e) = self.db.record_usage(Some(session_id), channel_id, model, api_key_id, usage).await {
+            tracing::error!("[DISPATCH] Failed to record usage for session {}: {}", session_id, e);
+        }
+    }
+
+    /// Persist an x402 payment the AI provider charged, in `x402_payments` and the transactions audit trail
+    /// Check a reply against the request's response format, asking the model
+    /// to repair it while it doesn't match. Memory markers are dropped from
+    /// the reply; a matching one is returned as compact JSON.
+    async fn enforce_response_format(
+        &self,
+        client: &AiClient,
+        format: &ResponseFormat,
+        mut response: String,
+        budget: &mut BudgetTracker,
+        session_id: i64,
+        channel_id: i64,
+    ) -> Result<String, String> {
+        let mut repairs = 0;
+        loop {
+            let answer = self.clean_response(&response);
+            let problems = match format.check(&answer) {
+                Ok(value) => return Ok(value.to_string()),
+                Err(problems) => problems,
+            };
+            if repairs == MAX_REPAIR_ATTEMPTS {
+                return Err(format!("The reply does not match the response_format:\n{}", problems));
+            }
+            repairs += 1;
+            tracing::info!("[DISPATCH] Reply does not match the response format, repair {}/{}", repairs, MAX_REPAIR_ATTEMPTS);
+
+            let messages = format.repair_messages(&answer, &problems);
+            let estimated_input = estimate_request_tokens(&messages, &[]);
+            let (content, payment) = client.generate_text_with_events(messages, &self.broadcaster, channel_id).await?;
+            budget.record(None, estimated_input, estimate_tokens(&content) as u64);
+            if let Some(ref payment_info) = payment {
+                self.record_ai_payment(session_id, channel_id, payment_info).await;
+            }
+            response = content;
+        }
+    }
+
+    async fn record_ai_payment(&self, session_id: i64, channel_id: i64, payment_info: &X402PaymentInfo) {
+        if let Err(e) = self.db.record_x402_payment(
+            Some(channel_id),
+            None,
+            payment_info.resource.as_deref(),
+            &payment_info.amount,
+            &payment_info.amount_formatted,
+            &payment_info.asset,
+            &payment_info.pay_to,
+            payment_info.tx_hash.as_deref(),
+            &payment_info.status.to_string(),
+        ).await {
+            tracing::error!("[DISPATCH] Failed to record x402 payment: {}", e);
+        }
+        // AI inference is paid in USDC on Base
+        let mut tx = payment_info.to_transaction("base");
+        tx.channel_id = Some(channel_id);
+        tx.session_id = Some(session_id);
+        if let Err(e) = self.db.record_transaction(&tx).await {
+            tracing::error!("[DISPATCH] Failed to record x402 payment transaction: {}", e);
+        }
+    }
+
     /// Generate a response with tool execution loop (supports both native and text-based tool calling)
     /// Now always runs in multi-agent mode with Explore → Plan → Perform flow
     async fn generate_with_tool_loop(
@@ -710,11 +998,14 @@ impl MessageDispatcher {
         session_id: i64,
         original_message: &NormalizedMessage,
         archetype_id: ArchetypeId,
+        budget: &mut BudgetTracker,
+        context_window: &ContextWindow,
+        tool_calls: &mut Vec<ToolInvocation>,
     ) -> Result<String, String> {
         // Load existing agent context or create new one
-        let mut orchestrator = match self.db.get_agent_context(session_id) {
+        let mut orchestrator = match self.db.get_agent_context(session_id).await {
             Ok(Some(context)) => {
-                log::info!(
+                tracing::info!(
                     "[MULTI_AGENT] Resuming session {} (iteration {})",
                     session_id,
                     context.mode_iterations
@@ -726,14 +1017,14 @@ impl MessageDispatcher {
                 orch
             }
             Ok(None) => {
-                log::info!(
+                tracing::info!(
                     "[MULTI_AGENT] Starting new orchestrator for session {}",
                     session_id
                 );
                 Orchestrator::new(original_message.text.clone())
             }
             Err(e) => {
-                log::warn!(
+                tracing::warn!(
                     "[MULTI_AGENT] Failed to load context for session {}: {}, starting fresh",
                     session_id, e
                 );
@@ -756,7 +1047,7 @@ impl MessageDispatcher {
         // Get the current subtype
         let subtype = orchestrator.current_subtype();
 
-        log::info!(
+        tracing::info!(
             "[MULTI_AGENT] Started in {} mode ({} subtype) for request: {}",
             initial_mode,
             subtype.label(),
@@ -774,7 +1065,7 @@ impl MessageDispatcher {
         // If there's an active skill with requires_tools, force-include those tools
         let mut tools = if let Some(ref active_skill) = orchestrator.context().active_skill {
             if !active_skill.requires_tools.is_empty() {
-                log::info!(
+                tracing::info!(
                     "[TOOL_LOOP] Active skill '{}' requires tools: {:?}",
                     active_skill.name,
                     active_skill.requires_tools
@@ -794,7 +1085,7 @@ impl MessageDispatcher {
 
         // Add skills as a "use_skill" pseudo-tool if any are enabled
         // Skills are also filtered by subtype tags
-        if let Some(skill_tool) = self.create_skill_tool_definition_for_subtype(subtype) {
+        if let Some(skill_tool) = self.create_skill_tool_definition_for_subtype(subtype).await {
             tools.push(skill_tool);
         }
 
@@ -803,7 +1094,7 @@ impl MessageDispatcher {
         tools.extend(mode_tools);
 
         // Debug: Log available tools
-        log::info!(
+        tracing::info!(
             "[TOOL_LOOP] Available tools ({}): {:?}",
             tools.len(),
             tools.iter().map(|t| &t.name).collect::<Vec<_>>()
@@ -818,23 +1109,13 @@ impl MessageDispatcher {
         );
 
         if tools.is_empty() {
-            log::warn!("[TOOL_LOOP] No tools available, falling back to text-only generation");
+            tracing::warn!("[TOOL_LOOP] No tools available, falling back to text-only generation");
+            let estimated_input = estimate_request_tokens(&messages, &[]);
             let (content, payment) = client.generate_text_with_events(messages, &self.broadcaster, original_message.channel_id).await?;
+            budget.record(None, estimated_input, estimate_tokens(&content) as u64);
             // Save x402 payment if one was made
             if let Some(ref payment_info) = payment {
-                if let Err(e) = self.db.record_x402_payment(
-                    Some(original_message.channel_id),
-                    None,
-                    payment_info.resource.as_deref(),
-                    &payment_info.amount,
-                    &payment_info.amount_formatted,
-                    &payment_info.asset,
-                    &payment_info.pay_to,
-                    payment_info.tx_hash.as_deref(),
-                    &payment_info.status.to_string(),
-                ) {
-                    log::error!("[TOOL_LOOP] Failed to record x402 payment: {}", e);
-                }
+                self.record_ai_payment(session_id, original_message.channel_id, payment_info).await;
             }
             return Ok(content);
         }
@@ -843,7 +1124,7 @@ impl MessageDispatcher {
         let archetype = self.archetype_registry.get(archetype_id)
             .unwrap_or_else(|| self.archetype_registry.default_archetype());
 
-        log::info!(
+        tracing::info!(
             "[TOOL_LOOP] Using archetype: {} (native_tool_calling: {})",
             archetype.id(),
             archetype.uses_native_tool_calling()
@@ -853,12 +1134,12 @@ impl MessageDispatcher {
         if archetype.uses_native_tool_calling() {
             self.generate_with_native_tools_orchestrated(
                 client, messages, tools, tool_config, tool_context,
-                original_message, archetype, &mut orchestrator, session_id
+                original_message, archetype, &mut orchestrator, session_id, budget, context_window, tool_calls
             ).await
         } else {
             self.generate_with_text_tools_orchestrated(
                 client, messages, tools, tool_config, tool_context,
-                original_message, archetype, &mut orchestrator, session_id
+                original_message, archetype, &mut orchestrator, session_id, budget, context_window, tool_calls
             ).await
         }
     }
@@ -891,20 +1172,20 @@ impl MessageDispatcher {
     }
 
     /// Create a "use_skill" tool definition if skills are enabled
-    fn create_skill_tool_definition(&self) -> Option<ToolDefinition> {
+    async fn create_skill_tool_definition(&self) -> Option<ToolDefinition> {
         // Default to Finance subtype for backwards compatibility
-        self.create_skill_tool_definition_for_subtype(AgentSubtype::Finance)
+        self.create_skill_tool_definition_for_subtype(AgentSubtype::Finance).await
     }
 
     /// Create a "use_skill" tool definition showing ALL enabled skills
     /// (no subtype filtering - AI can see all skills and switch subtypes if needed)
-    fn create_skill_tool_definition_for_subtype(
+    async fn create_skill_tool_definition_for_subtype(
         &self,
         _subtype: AgentSubtype,
     ) -> Option<ToolDefinition> {
         use crate::tools::{PropertySchema, ToolGroup, ToolInputSchema};
 
-        let skills = self.db.list_enabled_skills().ok()?;
+        let skills = self.db.list_enabled_skills().await.ok()?;
 
         if skills.is_empty() {
             return None;
@@ -937,7 +1218,13 @@ impl MessageDispatcher {
         // Format skill descriptions with newlines for better readability
         let formatted_skills = skills
             .iter()
-            .map(|s| format!("  - {}: {}", s.name, s.description))
+            .map(|s| {
+                if s.triggers.is_empty() {
+                    format!("  - {}: {}", s.name, s.description)
+                } else {
+                    format!("  - {}: {} (triggers: {})", s.name, s.description, s.triggers.join(", "))
+                }
+            })
             .collect::<Vec<_>>()
             .join("\n");
 
@@ -1035,9 +1322,12 @@ impl MessageDispatcher {
         archetype: &dyn ModelArchetype,
         orchestrator: &mut Orchestrator,
         session_id: i64,
+        budget: &mut BudgetTracker,
+        context_window: &ContextWindow,
+        tool_calls: &mut Vec<ToolInvocation>,
     ) -> Result<String, String> {
         // Get max tool iterations from bot settings
-        let max_tool_iterations = self.db.get_bot_settings()
+        let max_tool_iterations = self.db.get_bot_settings().await
             .map(|s| s.max_tool_iterations as usize)
             .unwrap_or(FALLBACK_MAX_TOOL_ITERATIONS);
 
@@ -1050,7 +1340,7 @@ impl MessageDispatcher {
                 system_msg.content = format!(
                     "{}\n\n---\n\n{}",
                     orchestrator_prompt,
-                    archetype.enhance_system_prompt(&system_msg.content, &tools)
+                    archetype.system_prompt(&system_msg.content, &tools)
                 );
             }
         }
@@ -1059,6 +1349,8 @@ impl MessageDispatcher {
         orchestrator.clear_waiting_for_user_context();
 
         let mut tool_history: Vec<ToolHistoryEntry> = Vec::new();
+        // The user's request is the last message; compaction never removes it
+        let mut request_index = conversation.len().saturating_sub(1);
         let mut iterations = 0;
         let mut tool_call_log: Vec<String> = Vec::new();
         let mut orchestrator_complete = false;
@@ -1066,10 +1358,12 @@ impl MessageDispatcher {
         let mut waiting_for_user_response = false;
         let mut user_question_content = String::new();
         let mut was_cancelled = false;
+        // Most recent non-empty model output, returned if the budget stops the loop
+        let mut last_content = String::new();
 
         loop {
             iterations += 1;
-            log::info!(
+            tracing::info!(
                 "[ORCHESTRATED_LOOP] Iteration {} in {} mode",
                 iterations,
                 orchestrator.current_mode()
@@ -1078,7 +1372,7 @@ impl MessageDispatcher {
             // === DETERMINE TOOLS FOR CURRENT MODE ===
             // In TaskPlanner mode (first iteration), use only define_tasks tool
             let current_tools = if orchestrator.current_mode() == AgentMode::TaskPlanner && !orchestrator.context().planner_completed {
-                log::info!("[ORCHESTRATED_LOOP] Using TaskPlanner mode tools (define_tasks only)");
+                tracing::info!("[ORCHESTRATED_LOOP] Using TaskPlanner mode tools (define_tasks only)");
                 // Update conversation with planner prompt
                 if let Some(system_msg) = conversation.first_mut() {
                     if system_msg.role == MessageRole::System {
@@ -1108,7 +1402,7 @@ impl MessageDispatcher {
 
             // Check if execution was cancelled (e.g., user sent /new or stop button)
             if self.execution_tracker.is_cancelled(original_message.channel_id) {
-                log::info!("[ORCHESTRATED_LOOP] Execution cancelled by user, stopping loop");
+                tracing::info!("[ORCHESTRATED_LOOP] Execution cancelled by user, stopping loop");
                 was_cancelled = true;
                 break;
             }
@@ -1118,16 +1412,16 @@ impl MessageDispatcher {
             for task_id in pending_deletions {
                 let (deleted, was_current) = orchestrator.delete_task(task_id);
                 if deleted {
-                    log::info!("[ORCHESTRATED_LOOP] Deleted task {}", task_id);
+                    tracing::info!("[ORCHESTRATED_LOOP] Deleted task {}", task_id);
                     // Broadcast the updated task queue
                     self.broadcast_task_queue_update(original_message.channel_id, orchestrator);
 
                     // If we deleted the current task, move to the next one
                     if was_current {
-                        log::info!("[ORCHESTRATED_LOOP] Deleted task was the current task, moving to next");
+                        tracing::info!("[ORCHESTRATED_LOOP] Deleted task was the current task, moving to next");
                         // Pop next task if available
                         if let Some(next_task) = orchestrator.pop_next_task() {
-                            log::info!(
+                            tracing::info!(
                                 "[ORCHESTRATED_LOOP] Starting next task after deletion: {} - {}",
                                 next_task.id,
                                 next_task.description
@@ -1141,25 +1435,25 @@ impl MessageDispatcher {
                             self.broadcast_task_queue_update(original_message.channel_id, orchestrator);
                         } else if orchestrator.task_queue_is_empty() || orchestrator.all_tasks_complete() {
                             // No more tasks, complete the session
-                            log::info!("[ORCHESTRATED_LOOP] No more tasks after deletion, completing session");
+                            tracing::info!("[ORCHESTRATED_LOOP] No more tasks after deletion, completing session");
                             orchestrator_complete = true;
-                            if let Err(e) = self.db.update_session_completion_status(session_id, CompletionStatus::Complete) {
-                                log::error!("[ORCHESTRATED_LOOP] Failed to update session completion status: {}", e);
+                            if let Err(e) = self.db.update_session_completion_status(session_id, CompletionStatus::Complete).await {
+                                tracing::error!("[ORCHESTRATED_LOOP] Failed to update session completion status: {}", e);
                             }
                             self.broadcast_session_complete(original_message.channel_id, session_id);
                             break;
                         }
                     }
                 } else {
-                    log::warn!("[ORCHESTRATED_LOOP] Task {} not found for deletion", task_id);
+                    tracing::warn!("[ORCHESTRATED_LOOP] Task {} not found for deletion", task_id);
                 }
             }
 
             // Check if session was marked as complete (defensive check against infinite loops)
             // This catches cases where task_fully_completed was called but the loop didn't break
-            if let Ok(Some(status)) = self.db.get_session_completion_status(session_id) {
+            if let Ok(Some(status)) = self.db.get_session_completion_status(session_id).await {
                 if status.should_stop() {
-                    log::info!("[ORCHESTRATED_LOOP] Session status is {:?}, stopping loop", status);
+                    tracing::info!("[ORCHESTRATED_LOOP] Session status is {:?}, stopping loop", status);
                     // Mark orchestrator as complete to avoid misleading error messages
                     if status == CompletionStatus::Complete {
                         orchestrator_complete = true;
@@ -1169,7 +1463,7 @@ impl MessageDispatcher {
             }
 
             if iterations > max_tool_iterations {
-                log::warn!("Orchestrated tool loop exceeded max iterations ({})", max_tool_iterations);
+                tracing::warn!("Orchestrated tool loop exceeded max iterations ({})", max_tool_iterations);
                 break;
             }
 
@@ -1177,7 +1471,7 @@ impl MessageDispatcher {
             // If planner just completed (define_tasks was called), pop first task and continue
             if orchestrator.context().planner_completed && orchestrator.context().task_queue.current_task().is_none() {
                 if let Some(first_task) = orchestrator.pop_next_task() {
-                    log::info!(
+                    tracing::info!(
                         "[ORCHESTRATED_LOOP] Starting first task: {} - {}",
                         first_task.id,
                         first_task.description
@@ -1202,7 +1496,7 @@ impl MessageDispatcher {
                     // Update tools for assistant mode
                     let subtype = orchestrator.current_subtype();
                     tools = self.tool_registry.get_tool_definitions_for_subtype(tool_config, subtype);
-                    if let Some(skill_tool) = self.create_skill_tool_definition_for_subtype(subtype) {
+                    if let Some(skill_tool) = self.create_skill_tool_definition_for_subtype(subtype).await {
                         tools.push(skill_tool);
                     }
                     tools.extend(orchestrator.get_mode_tools());
@@ -1222,7 +1516,7 @@ impl MessageDispatcher {
                             system_msg.content = format!(
                                 "{}\n\n---\n\n{}",
                                 orchestrator_prompt,
-                                archetype.enhance_system_prompt(&messages[0].content, &tools)
+                                archetype.system_prompt(&messages[0].content, &tools)
                             );
                         }
                     }
@@ -1231,7 +1525,7 @@ impl MessageDispatcher {
 
             // Check for forced mode transition
             if let Some(transition) = orchestrator.check_forced_transition() {
-                log::info!(
+                tracing::info!(
                     "[ORCHESTRATOR] Forced transition: {} → {} ({})",
                     transition.from, transition.to, transition.reason
                 );
@@ -1261,7 +1555,7 @@ impl MessageDispatcher {
                 tools = self
                     .tool_registry
                     .get_tool_definitions_for_subtype(tool_config, subtype);
-                if let Some(skill_tool) = self.create_skill_tool_definition_for_subtype(subtype) {
+                if let Some(skill_tool) = self.create_skill_tool_definition_for_subtype(subtype).await {
                     tools.push(skill_tool);
                 }
                 tools.extend(orchestrator.get_mode_tools());
@@ -1294,12 +1588,40 @@ impl MessageDispatcher {
                         system_msg.content = format!(
                             "{}\n\n---\n\n{}",
                             orchestrator_prompt,
-                            archetype.enhance_system_prompt(&messages[0].content, &tools)
+                            archetype.system_prompt(&messages[0].content, &tools)
                         );
                     }
                 }
             }
 
+            // Stop before a provider call that would take this request over its budget
+            if budget.next_call_would_exceed() {
+                tracing::warn!(
+                    "[ORCHESTRATED_LOOP] Budget ceiling reached ({} tokens, ~${:.4}), stopping loop",
+                    budget.total_tokens(),
+                    budget.cost_usd()
+                );
+                budget.mark_exceeded();
+                break;
+            }
+            if let Some(report) = context_window.compact(
+                &mut conversation,
+                &mut request_index,
+                &mut tool_history,
+                &current_tools,
+            ) {
+                tracing::info!(
+                    "[ORCHESTRATED_LOOP] Compacted context from {} to {} of {} tokens ({} stale, {} condensed, {} dropped)",
+                    report.tokens_before,
+                    report.tokens_after,
+                    context_window.budget(),
+                    report.stale_results,
+                    report.condensed,
+                    report.dropped
+                );
+            }
+            let estimated_input = estimate_request_tokens(&conversation, &tool_history);
+
             // Generate with native tool support and progress notifications
             let ai_response = match self.generate_with_progress(
                 &client,
@@ -1313,7 +1635,7 @@ impl MessageDispatcher {
                     // Check if this is a client error (4xx) that might be recoverable
                     if e.is_client_error() && iterations <= 2 {
                         if e.is_context_too_large() {
-                            log::warn!(
+                            tracing::warn!(
                                 "[ORCHESTRATED_LOOP] Context too large error ({}), clearing tool history ({} entries) and retrying",
                                 e.status_code.unwrap_or(0),
                                 tool_history.len()
@@ -1327,7 +1649,7 @@ impl MessageDispatcher {
                         }
 
                         // Other client errors - add guidance but don't clear history
-                        log::warn!(
+                        tracing::warn!(
                             "[ORCHESTRATED_LOOP] Client error ({}), feeding back to AI: {}",
                             e.status_code.unwrap_or(0),
                             e
@@ -1344,7 +1666,7 @@ impl MessageDispatcher {
                             tool_call_log.join("\n"),
                             error_str
                         );
-                        log::info!("[ORCHESTRATED_LOOP] Saving error summary with {} tool calls", tool_call_log.len());
+                        tracing::info!("[ORCHESTRATED_LOOP] Saving error summary with {} tool calls", tool_call_log.len());
                         let _ = self.db.add_session_message(
                             session_id,
                             DbMessageRole::Assistant,
@@ -1353,20 +1675,29 @@ impl MessageDispatcher {
                             None,
                             None,
                             None,
-                        );
+                        ).await;
                     }
                     // Save context before returning error
-                    let _ = self.db.save_agent_context(session_id, orchestrator.context());
+                    let _ = self.db.save_agent_context(session_id, orchestrator.context()).await;
                     return Err(error_str);
                 }
             };
 
-            log::info!(
+            tracing::info!(
                 "[ORCHESTRATED_LOOP] Response - content_len: {}, tool_calls: {}",
                 ai_response.content.len(),
                 ai_response.tool_calls.len()
             );
 
+            budget.record(
+                ai_response.usage.as_ref(),
+                estimated_input,
+                estimate_tokens(&ai_response.content) as u64,
+            );
+            if !ai_response.content.trim().is_empty() {
+                last_content = ai_response.content.clone();
+            }
+
             // Handle x402 payments
             if let Some(ref payment_info) = ai_response.x402_payment {
                 self.broadcaster.broadcast(GatewayEvent::x402_payment(
@@ -1377,24 +1708,14 @@ impl MessageDispatcher {
                     &payment_info.pay_to,
                     payment_info.resource.as_deref(),
                 ));
-                let _ = self.db.record_x402_payment(
-                    Some(original_message.channel_id),
-                    None,
-                    payment_info.resource.as_deref(),
-                    &payment_info.amount,
-                    &payment_info.amount_formatted,
-                    &payment_info.asset,
-                    &payment_info.pay_to,
-                    payment_info.tx_hash.as_deref(),
-                    &payment_info.status.to_string(),
-                );
+                self.record_ai_payment(session_id, original_message.channel_id, payment_info).await;
             }
 
             // If no tool calls, check if this is allowed
             if ai_response.tool_calls.is_empty() {
                 // Check if the agent should have called tools but didn't
                 if let Some((warning_msg, attempt)) = orchestrator.check_tool_call_required() {
-                    log::warn!(
+                    tracing::warn!(
                         "[ORCHESTRATED_LOOP] Agent skipped tool calls (attempt {}/5), forcing back into loop",
                         attempt
                     );
@@ -1414,6 +1735,7 @@ impl MessageDispatcher {
                     conversation.push(Message {
                         role: MessageRole::Assistant,
                         content: ai_response.content.clone(),
+                        images: Vec::new(),
                     });
                     conversation.push(Message {
                         role: MessageRole::User,
@@ -1421,6 +1743,7 @@ impl MessageDispatcher {
                             "[SYSTEM ERROR] {}\n\nYou MUST call tools to gather information. Do not respond with made-up data.",
                             warning_msg
                         ),
+                        images: Vec::new(),
                     });
 
                     // Continue the loop to force tool calling
@@ -1449,10 +1772,13 @@ impl MessageDispatcher {
             // Process tool calls
             let mut tool_responses = Vec::new();
             for call in &ai_response.tool_calls {
-                let args_pretty = serde_json::to_string_pretty(&call.arguments)
-                    .unwrap_or_else(|_| call.arguments.to_string());
+                // Logged and stored with credentials redacted; the tool itself gets the real arguments
+                let mut shown_args = call.arguments.clone();
+                redact_value(&mut shown_args);
+                let args_pretty = serde_json::to_string_pretty(&shown_args)
+                    .unwrap_or_else(|_| shown_args.to_string());
 
-                log::info!(
+                tracing::info!(
                     "[TOOL_CALL] Agent calling tool '{}' with args:\n{}",
                     call.name,
                     args_pretty
@@ -1484,8 +1810,8 @@ impl MessageDispatcher {
                     Some(&call.name),
                     None,
                     None,
-                ) {
-                    log::error!("Failed to save tool call to session: {}", e);
+                ).await {
+                    tracing::error!("Failed to save tool call to session: {}", e);
                 }
 
                 // Check if this is an orchestrator tool
@@ -1493,7 +1819,7 @@ impl MessageDispatcher {
 
                 match orchestrator_result {
                     OrchestratorResult::Complete(summary) => {
-                        log::info!("[ORCHESTRATOR] Execution complete: {}", summary);
+                        tracing::info!("[ORCHESTRATOR] Execution complete: {}", summary);
                         orchestrator_complete = true;
                         final_summary = summary.clone();
                         tool_responses.push(ToolResponse::success(
@@ -1523,13 +1849,13 @@ impl MessageDispatcher {
                             // Also set active skill directly on orchestrator (in-memory)
                             if skill_result.success {
                                 if let Some(skill_name) = call.arguments.get("skill_name").and_then(|v| v.as_str()) {
-                                    if let Ok(Some(skill)) = self.db.get_enabled_skill_by_name(skill_name) {
+                                    if let Ok(Some(skill)) = self.db.get_enabled_skill_by_name(skill_name).await {
                                         let skills_dir = crate::config::skills_dir();
                                         let skill_base_dir = format!("{}/{}", skills_dir, skill.name);
                                         let instructions = skill.body.replace("{baseDir}", &skill_base_dir);
 
                                         let requires_tools = skill.requires_tools.clone();
-                                        log::info!(
+                                        tracing::info!(
                                             "[SKILL] Activating skill '{}' with requires_tools: {:?}",
                                             skill.name,
                                             requires_tools
@@ -1552,11 +1878,11 @@ impl MessageDispatcher {
                                                     subtype,
                                                     &requires_tools,
                                                 );
-                                            if let Some(skill_tool) = self.create_skill_tool_definition_for_subtype(subtype) {
+                                            if let Some(skill_tool) = self.create_skill_tool_definition_for_subtype(subtype).await {
                                                 tools.push(skill_tool);
                                             }
                                             tools.extend(orchestrator.get_mode_tools());
-                                            log::info!(
+                                            tracing::info!(
                                                 "[SKILL] Refreshed toolset with {} tools (including {} required by skill)",
                                                 tools.len(),
                                                 requires_tools.len()
@@ -1570,7 +1896,7 @@ impl MessageDispatcher {
                             // Check if subtype is None - only allow set_agent_subtype in that case
                             let current_subtype = orchestrator.current_subtype();
                             if !current_subtype.is_selected() && call.name != "set_agent_subtype" {
-                                log::warn!(
+                                tracing::warn!(
                                     "[SUBTYPE] Blocked tool '{}' - no subtype selected. Must call set_agent_subtype first.",
                                     call.name
                                 );
@@ -1584,8 +1910,10 @@ impl MessageDispatcher {
                                 ))
                             } else {
                                 // Execute regular tool and record the call for skill tracking
+                                // Tag the context so on-chain actions can be traced back to this call
+                                let call_context = tool_context.clone().with_tool_call_id(&call.id);
                                 let tool_result = self.tool_registry
-                                    .execute(&call.name, call.arguments.clone(), tool_context, Some(tool_config))
+                                    .execute(&call.name, call.arguments.clone(), &call_context, Some(tool_config))
                                     .await;
 
                                 // Record this tool call for active skill tracking
@@ -1602,7 +1930,7 @@ impl MessageDispatcher {
                             if let Some(subtype_str) = call.arguments.get("subtype").and_then(|v| v.as_str()) {
                                 if let Some(new_subtype) = AgentSubtype::from_str(subtype_str) {
                                     orchestrator.set_subtype(new_subtype);
-                                    log::info!(
+                                    tracing::info!(
                                         "[SUBTYPE] Changed to {} mode",
                                         new_subtype.label()
                                     );
@@ -1612,7 +1940,7 @@ impl MessageDispatcher {
                                         .tool_registry
                                         .get_tool_definitions_for_subtype(tool_config, new_subtype);
                                     if let Some(skill_tool) =
-                                        self.create_skill_tool_definition_for_subtype(new_subtype)
+                                        self.create_skill_tool_definition_for_subtype(new_subtype).await
                                     {
                                         tools.push(skill_tool);
                                     }
@@ -1652,7 +1980,7 @@ impl MessageDispatcher {
                             if metadata.get("requires_user_response").and_then(|v| v.as_bool()).unwrap_or(false) {
                                 waiting_for_user_response = true;
                                 user_question_content = result.content.clone();
-                                log::info!("[ORCHESTRATED_LOOP] Tool requires user response, will break after processing");
+                                tracing::info!("[ORCHESTRATED_LOOP] Tool requires user response, will break after processing");
                             }
                             // Check if task_fully_completed was called - agent signals it's done
                             // CRITICAL: This tool MUST stop the loop immediately to prevent infinite iteration
@@ -1662,11 +1990,11 @@ impl MessageDispatcher {
                                     .unwrap_or(&result.content)
                                     .to_string();
 
-                                log::info!("[ORCHESTRATED_LOOP] task_fully_completed called, stopping loop");
+                                tracing::info!("[ORCHESTRATED_LOOP] task_fully_completed called, stopping loop");
 
                                 // Mark current task as completed and broadcast (if task queue exists)
                                 if let Some(completed_task_id) = orchestrator.complete_current_task() {
-                                    log::info!("[ORCHESTRATED_LOOP] Task {} completed", completed_task_id);
+                                    tracing::info!("[ORCHESTRATED_LOOP] Task {} completed", completed_task_id);
                                     self.broadcast_task_status_change(
                                         original_message.channel_id,
                                         completed_task_id,
@@ -1681,8 +2009,8 @@ impl MessageDispatcher {
                                 final_summary = summary.clone();
 
                                 // Mark session as complete in database
-                                if let Err(e) = self.db.update_session_completion_status(session_id, CompletionStatus::Complete) {
-                                    log::error!("[ORCHESTRATED_LOOP] Failed to update session completion status: {}", e);
+                                if let Err(e) = self.db.update_session_completion_status(session_id, CompletionStatus::Complete).await {
+                                    tracing::error!("[ORCHESTRATED_LOOP] Failed to update session completion status: {}", e);
                                 }
 
                                 // Broadcast session complete event
@@ -1703,6 +2031,7 @@ impl MessageDispatcher {
                             duration_ms,
                             &result.content,
                         ));
+                        tool_calls.push(ToolInvocation::new(&call.name, &call.arguments, &result, duration_ms));
 
                         // Execute AfterToolCall hooks (for auto-memory, etc.)
                         if let Some(hook_manager) = &self.hook_manager {
@@ -1716,7 +2045,7 @@ impl MessageDispatcher {
                                 }));
                             let hook_result = hook_manager.execute(HookEvent::AfterToolCall, &mut hook_context).await;
                             if let HookResult::Error(e) = hook_result {
-                                log::warn!("Hook execution failed for tool '{}': {}", call.name, e);
+                                tracing::warn!("Hook execution failed for tool '{}': {}", call.name, e);
                             }
                         }
 
@@ -1735,8 +2064,8 @@ impl MessageDispatcher {
                             Some(&call.name),
                             None,
                             None,
-                        ) {
-                            log::error!("Failed to save tool result to session: {}", e);
+                        ).await {
+                            tracing::error!("Failed to save tool result to session: {}", e);
                         }
 
                         tool_responses.push(if result.success {
@@ -1751,16 +2080,8 @@ impl MessageDispatcher {
                 self.broadcast_tasks_update(original_message.channel_id, orchestrator);
             }
 
-            // Add to tool history (keep only last N entries to prevent context bloat)
-            const MAX_TOOL_HISTORY: usize = 10;
-            tool_history.push(ToolHistoryEntry::new(
-                ai_response.tool_calls,
-                tool_responses,
-            ));
-            if tool_history.len() > MAX_TOOL_HISTORY {
-                // Remove oldest entries, keeping the most recent
-                tool_history.drain(0..tool_history.len() - MAX_TOOL_HISTORY);
-            }
+            // Add to tool history (compacted before the next call if it outgrows the context window)
+            tool_history.push(ToolHistoryEntry::from_response(ai_response, tool_responses));
 
             // If orchestrator is complete, break the loop
             if orchestrator_complete {
@@ -1770,14 +2091,14 @@ impl MessageDispatcher {
             // If a tool requires user response (e.g., ask_user), break the loop
             // and return the question content. Context is preserved for when user responds.
             if waiting_for_user_response {
-                log::info!("[ORCHESTRATED_LOOP] Breaking loop to wait for user response");
+                tracing::info!("[ORCHESTRATED_LOOP] Breaking loop to wait for user response");
                 break;
             }
         }
 
         // Save orchestrator context for next turn
-        if let Err(e) = self.db.save_agent_context(session_id, orchestrator.context()) {
-            log::warn!("[MULTI_AGENT] Failed to save context for session {}: {}", session_id, e);
+        if let Err(e) = self.db.save_agent_context(session_id, orchestrator.context()).await {
+            tracing::warn!("[MULTI_AGENT] Failed to save context for session {}: {}", session_id, e);
         }
 
         // If cancelled with work done, save a summary so context is preserved on resume
@@ -1786,7 +2107,7 @@ impl MessageDispatcher {
                 "[Session stopped by user. Work completed before stop:]\n{}",
                 tool_call_log.join("\n")
             );
-            log::info!("[ORCHESTRATED_LOOP] Saving cancellation summary with {} tool calls", tool_call_log.len());
+            tracing::info!("[ORCHESTRATED_LOOP] Saving cancellation summary with {} tool calls", tool_call_log.len());
             if let Err(e) = self.db.add_session_message(
                 session_id,
                 DbMessageRole::Assistant,
@@ -1795,11 +2116,16 @@ impl MessageDispatcher {
                 None,
                 None,
                 None,
-            ) {
-                log::error!("Failed to save cancellation summary: {}", e);
+            ).await {
+                tracing::error!("Failed to save cancellation summary: {}", e);
             }
         }
 
+        // Budget stop: return the best answer so far with a note rather than an error
+        if budget.is_exceeded() {
+            return Ok(budget_exceeded_response(&last_content, &tool_call_log, budget));
+        }
+
         // Return final response
         if waiting_for_user_response {
             // Save the tool call log to the orchestrator context so the AI knows what it already did
@@ -1811,8 +2137,8 @@ impl MessageDispatcher {
                 );
                 orchestrator.context_mut().waiting_for_user_context = Some(context_summary);
                 // Re-save context with the waiting_for_user_context
-                if let Err(e) = self.db.save_agent_context(session_id, orchestrator.context()) {
-                    log::warn!("[MULTI_AGENT] Failed to save context with user_context: {}", e);
+                if let Err(e) = self.db.save_agent_context(session_id, orchestrator.context()).await {
+                    tracing::warn!("[MULTI_AGENT] Failed to save context with user_context: {}", e);
                 }
             }
             // Return the question content - context is saved, will continue when user responds
@@ -1830,7 +2156,7 @@ impl MessageDispatcher {
                 "[Session hit max iterations. Work completed before limit:]\n{}",
                 tool_call_log.join("\n")
             );
-            log::info!("[ORCHESTRATED_LOOP] Saving max-iterations summary with {} tool calls", tool_call_log.len());
+            tracing::info!("[ORCHESTRATED_LOOP] Saving max-iterations summary with {} tool calls", tool_call_log.len());
             let _ = self.db.add_session_message(
                 session_id,
                 DbMessageRole::Assistant,
@@ -1839,7 +2165,7 @@ impl MessageDispatcher {
                 None,
                 None,
                 None,
-            );
+            ).await;
             Err(format!(
                 "Tool loop hit max iterations ({}). Work has been saved.",
                 max_tool_iterations
@@ -1859,9 +2185,12 @@ impl MessageDispatcher {
         archetype: &dyn ModelArchetype,
         orchestrator: &mut Orchestrator,
         session_id: i64,
+        budget: &mut BudgetTracker,
+        context_window: &ContextWindow,
+        tool_calls: &mut Vec<ToolInvocation>,
     ) -> Result<String, String> {
         // Get max tool iterations from bot settings
-        let max_tool_iterations = self.db.get_bot_settings()
+        let max_tool_iterations = self.db.get_bot_settings().await
             .map(|s| s.max_tool_iterations as usize)
             .unwrap_or(FALLBACK_MAX_TOOL_ITERATIONS);
 
@@ -1873,7 +2202,7 @@ impl MessageDispatcher {
                 system_msg.content = format!(
                     "{}\n\n---\n\n{}",
                     orchestrator_prompt,
-                    archetype.enhance_system_prompt(&system_msg.content, &tools)
+                    archetype.system_prompt(&system_msg.content, &tools)
                 );
             }
         }
@@ -1882,16 +2211,20 @@ impl MessageDispatcher {
         orchestrator.clear_waiting_for_user_context();
 
         let mut final_response = String::new();
+        // The user's request is the last message; compaction never removes it
+        let mut request_index = conversation.len().saturating_sub(1);
         let mut iterations = 0;
         let mut tool_call_log: Vec<String> = Vec::new();
         let mut orchestrator_complete = false;
         let mut waiting_for_user_response = false;
         let mut user_question_content = String::new();
         let mut was_cancelled = false;
+        // Most recent non-empty model output, returned if the budget stops the loop
+        let mut last_content = String::new();
 
         loop {
             iterations += 1;
-            log::info!(
+            tracing::info!(
                 "[TEXT_ORCHESTRATED] Iteration {} in {} mode",
                 iterations,
                 orchestrator.current_mode()
@@ -1899,15 +2232,15 @@ impl MessageDispatcher {
 
             // Check if execution was cancelled (e.g., user sent /new or stop button)
             if self.execution_tracker.is_cancelled(original_message.channel_id) {
-                log::info!("[TEXT_ORCHESTRATED] Execution cancelled by user, stopping loop");
+                tracing::info!("[TEXT_ORCHESTRATED] Execution cancelled by user, stopping loop");
                 was_cancelled = true;
                 break;
             }
 
             // Check if session was marked as complete (defensive check against infinite loops)
-            if let Ok(Some(status)) = self.db.get_session_completion_status(session_id) {
+            if let Ok(Some(status)) = self.db.get_session_completion_status(session_id).await {
                 if status.should_stop() {
-                    log::info!("[TEXT_ORCHESTRATED] Session status is {:?}, stopping loop", status);
+                    tracing::info!("[TEXT_ORCHESTRATED] Session status is {:?}, stopping loop", status);
                     // Mark orchestrator as complete to avoid misleading error messages
                     if status == CompletionStatus::Complete {
                         orchestrator_complete = true;
@@ -1917,7 +2250,7 @@ impl MessageDispatcher {
             }
 
             if iterations > max_tool_iterations {
-                log::warn!("Text orchestrated loop exceeded max iterations ({})", max_tool_iterations);
+                tracing::warn!("Text orchestrated loop exceeded max iterations ({})", max_tool_iterations);
                 break;
             }
 
@@ -1935,7 +2268,7 @@ impl MessageDispatcher {
                 tools = self
                     .tool_registry
                     .get_tool_definitions_for_subtype(tool_config, subtype);
-                if let Some(skill_tool) = self.create_skill_tool_definition_for_subtype(subtype) {
+                if let Some(skill_tool) = self.create_skill_tool_definition_for_subtype(subtype).await {
                     tools.push(skill_tool);
                 }
                 tools.extend(orchestrator.get_mode_tools());
@@ -1955,12 +2288,35 @@ impl MessageDispatcher {
                         system_msg.content = format!(
                             "{}\n\n---\n\n{}",
                             orchestrator_prompt,
-                            archetype.enhance_system_prompt(&messages[0].content, &tools)
+                            archetype.system_prompt(&messages[0].content, &tools)
                         );
                     }
                 }
             }
 
+            // Stop before a provider call that would take this request over its budget
+            if budget.next_call_would_exceed() {
+                tracing::warn!(
+                    "[TEXT_ORCHESTRATED] Budget ceiling reached ({} tokens, ~${:.4}), stopping loop",
+                    budget.total_tokens(),
+                    budget.cost_usd()
+                );
+                budget.mark_exceeded();
+                break;
+            }
+            // Tool definitions are part of the system prompt in text mode
+            if let Some(report) = context_window.compact(&mut conversation, &mut request_index, &mut Vec::new(), &[]) {
+                tracing::info!(
+                    "[TEXT_ORCHESTRATED] Compacted context from {} to {} of {} tokens ({} condensed, {} dropped)",
+                    report.tokens_before,
+                    report.tokens_after,
+                    context_window.budget(),
+                    report.condensed,
+                    report.dropped
+                );
+            }
+            let estimated_input = estimate_request_tokens(&conversation, &[]);
+
             let (ai_content, payment) = match client.generate_text_with_events(
                 conversation.clone(),
                 &self.broadcaster,
@@ -1975,7 +2331,7 @@ impl MessageDispatcher {
                             tool_call_log.join("\n"),
                             e
                         );
-                        log::info!("[TEXT_ORCHESTRATED] Saving error summary with {} tool calls", tool_call_log.len());
+                        tracing::info!("[TEXT_ORCHESTRATED] Saving error summary with {} tool calls", tool_call_log.len());
                         let _ = self.db.add_session_message(
                             session_id,
                             DbMessageRole::Assistant,
@@ -1984,35 +2340,32 @@ impl MessageDispatcher {
                             None,
                             None,
                             None,
-                        );
+                        ).await;
                     }
                     // Save context before returning error
-                    let _ = self.db.save_agent_context(session_id, orchestrator.context());
+                    let _ = self.db.save_agent_context(session_id, orchestrator.context()).await;
                     return Err(e);
                 }
             };
 
             if let Some(ref payment_info) = payment {
-                let _ = self.db.record_x402_payment(
-                    Some(original_message.channel_id),
-                    None,
-                    payment_info.resource.as_deref(),
-                    &payment_info.amount,
-                    &payment_info.amount_formatted,
-                    &payment_info.asset,
-                    &payment_info.pay_to,
-                    payment_info.tx_hash.as_deref(),
-                    &payment_info.status.to_string(),
-                );
+                self.record_ai_payment(session_id, original_message.channel_id, payment_info).await;
             }
 
+            budget.record(None, estimated_input, estimate_tokens(&ai_content) as u64);
+
             let parsed = archetype.parse_response(&ai_content);
 
             match parsed {
                 Some(agent_response) => {
+                    if !agent_response.body.trim().is_empty() {
+                        last_content = agent_response.body.clone();
+                    }
                     if let Some(tool_call) = agent_response.tool_call {
-                        let args_pretty = serde_json::to_string_pretty(&tool_call.tool_params)
-                            .unwrap_or_else(|_| tool_call.tool_params.to_string());
+                        let mut shown_args = tool_call.tool_params.clone();
+                        redact_value(&mut shown_args);
+                        let args_pretty = serde_json::to_string_pretty(&shown_args)
+                            .unwrap_or_else(|_| shown_args.to_string());
 
                         tool_call_log.push(format!(
                             "🔧 **Tool Call:** `{}`\n```json\n{}\n```",
@@ -2040,8 +2393,8 @@ impl MessageDispatcher {
                             Some(&tool_call.tool_name),
                             None,
                             None,
-                        ) {
-                            log::error!("Failed to save tool call to session: {}", e);
+                        ).await {
+                            tracing::error!("Failed to save tool call to session: {}", e);
                         }
 
                         // Check if orchestrator tool
@@ -2067,13 +2420,13 @@ impl MessageDispatcher {
                                     // Also set active skill directly on orchestrator (in-memory)
                                     if skill_result.success {
                                         if let Some(skill_name) = tool_call.tool_params.get("skill_name").and_then(|v| v.as_str()) {
-                                            if let Ok(Some(skill)) = self.db.get_enabled_skill_by_name(skill_name) {
+                                            if let Ok(Some(skill)) = self.db.get_enabled_skill_by_name(skill_name).await {
                                                 let skills_dir = crate::config::skills_dir();
                                                 let skill_base_dir = format!("{}/{}", skills_dir, skill.name);
                                                 let instructions = skill.body.replace("{baseDir}", &skill_base_dir);
 
                                                 let requires_tools = skill.requires_tools.clone();
-                                                log::info!(
+                                                tracing::info!(
                                                     "[SKILL] Activating skill '{}' with requires_tools: {:?}",
                                                     skill.name,
                                                     requires_tools
@@ -2096,11 +2449,11 @@ impl MessageDispatcher {
                                                             subtype,
                                                             &requires_tools,
                                                         );
-                                                    if let Some(skill_tool) = self.create_skill_tool_definition_for_subtype(subtype) {
+                                                    if let Some(skill_tool) = self.create_skill_tool_definition_for_subtype(subtype).await {
                                                         tools.push(skill_tool);
                                                     }
                                                     tools.extend(orchestrator.get_mode_tools());
-                                                    log::info!(
+                                                    tracing::info!(
                                                         "[SKILL] Refreshed toolset with {} tools (including {} required by skill)",
                                                         tools.len(),
                                                         requires_tools.len()
@@ -2114,7 +2467,7 @@ impl MessageDispatcher {
                                     // Check if subtype is None - only allow set_agent_subtype in that case
                                     let current_subtype = orchestrator.current_subtype();
                                     if !current_subtype.is_selected() && tool_call.tool_name != "set_agent_subtype" {
-                                        log::warn!(
+                                        tracing::warn!(
                                             "[SUBTYPE] Blocked tool '{}' - no subtype selected. Must call set_agent_subtype first.",
                                             tool_call.tool_name
                                         );
@@ -2149,7 +2502,7 @@ impl MessageDispatcher {
                                     if let Some(subtype_str) = tool_call.tool_params.get("subtype").and_then(|v| v.as_str()) {
                                         if let Some(new_subtype) = AgentSubtype::from_str(subtype_str) {
                                             orchestrator.set_subtype(new_subtype);
-                                            log::info!(
+                                            tracing::info!(
                                                 "[SUBTYPE] Changed to {} mode",
                                                 new_subtype.label()
                                             );
@@ -2159,7 +2512,7 @@ impl MessageDispatcher {
                                                 .tool_registry
                                                 .get_tool_definitions_for_subtype(tool_config, new_subtype);
                                             if let Some(skill_tool) =
-                                                self.create_skill_tool_definition_for_subtype(new_subtype)
+                                                self.create_skill_tool_definition_for_subtype(new_subtype).await
                                             {
                                                 tools.push(skill_tool);
                                             }
@@ -2181,7 +2534,7 @@ impl MessageDispatcher {
                                     if metadata.get("requires_user_response").and_then(|v| v.as_bool()).unwrap_or(false) {
                                         waiting_for_user_response = true;
                                         user_question_content = result.content.clone();
-                                        log::info!("[TEXT_ORCHESTRATED] Tool requires user response, will break after processing");
+                                        tracing::info!("[TEXT_ORCHESTRATED] Tool requires user response, will break after processing");
                                     }
                                     // Check if task_fully_completed was called - agent signals it's done
                                     if metadata.get("task_fully_completed").and_then(|v| v.as_bool()).unwrap_or(false) {
@@ -2191,7 +2544,7 @@ impl MessageDispatcher {
                                         } else {
                                             final_response = result.content.clone();
                                         }
-                                        log::info!("[TEXT_ORCHESTRATED] Task fully completed signal received");
+                                        tracing::info!("[TEXT_ORCHESTRATED] Task fully completed signal received");
                                     }
                                 }
 
@@ -2208,6 +2561,12 @@ impl MessageDispatcher {
                                     duration_ms,
                                     &result.content,
                                 ));
+                                tool_calls.push(ToolInvocation::new(
+                                    &tool_call.tool_name,
+                                    &tool_call.tool_params,
+                                    &result,
+                                    duration_ms,
+                                ));
 
                                 // Execute AfterToolCall hooks (for auto-memory, etc.)
                                 if let Some(hook_manager) = &self.hook_manager {
@@ -2221,7 +2580,7 @@ impl MessageDispatcher {
                                         }));
                                     let hook_result = hook_manager.execute(HookEvent::AfterToolCall, &mut hook_context).await;
                                     if let HookResult::Error(e) = hook_result {
-                                        log::warn!("Hook execution failed for tool '{}': {}", tool_call.tool_name, e);
+                                        tracing::warn!("Hook execution failed for tool '{}': {}", tool_call.tool_name, e);
                                     }
                                 }
 
@@ -2240,8 +2599,8 @@ impl MessageDispatcher {
                                     Some(&tool_call.tool_name),
                                     None,
                                     None,
-                                ) {
-                                    log::error!("Failed to save tool result to session: {}", e);
+                                ).await {
+                                    tracing::error!("Failed to save tool result to session: {}", e);
                                 }
 
                                 result.content
@@ -2255,6 +2614,7 @@ impl MessageDispatcher {
                         conversation.push(Message {
                             role: MessageRole::Assistant,
                             content: ai_content.clone(),
+                            images: Vec::new(),
                         });
                         conversation.push(Message {
                             role: MessageRole::User,
@@ -2263,32 +2623,22 @@ impl MessageDispatcher {
                                 &tool_result_content,
                                 true,
                             ),
+                            images: Vec::new(),
                         });
 
-                        // Truncate conversation to prevent context bloat
-                        // Keep system prompt(s) at start + last N message pairs
-                        const MAX_CONVERSATION_MESSAGES: usize = 20;
-                        let system_count = conversation.iter()
-                            .take_while(|m| m.role == MessageRole::System)
-                            .count();
-                        if conversation.len() > system_count + MAX_CONVERSATION_MESSAGES {
-                            let remove_count = conversation.len() - system_count - MAX_CONVERSATION_MESSAGES;
-                            conversation.drain(system_count..system_count + remove_count);
-                        }
-
                         if orchestrator_complete {
                             break;
                         }
                         // If a tool requires user response (e.g., ask_user), break the loop
                         if waiting_for_user_response {
-                            log::info!("[TEXT_ORCHESTRATED] Breaking loop to wait for user response");
+                            tracing::info!("[TEXT_ORCHESTRATED] Breaking loop to wait for user response");
                             break;
                         }
                         continue;
                     } else {
                         // No tool call - check if this is allowed
                         if let Some((warning_msg, attempt)) = orchestrator.check_tool_call_required() {
-                            log::warn!(
+                            tracing::warn!(
                                 "[TEXT_ORCHESTRATED] Agent skipped tool calls (attempt {}/5), forcing back into loop",
                                 attempt
                             );
@@ -2308,6 +2658,7 @@ impl MessageDispatcher {
                             conversation.push(Message {
                                 role: MessageRole::Assistant,
                                 content: agent_response.body.clone(),
+                                images: Vec::new(),
                             });
                             conversation.push(Message {
                                 role: MessageRole::User,
@@ -2315,6 +2666,7 @@ impl MessageDispatcher {
                                     "[SYSTEM ERROR] {}\n\nYou MUST call tools to gather information. Do not respond with made-up data.",
                                     warning_msg
                                 ),
+                                images: Vec::new(),
                             });
 
                             // Continue the loop to force tool calling
@@ -2332,10 +2684,10 @@ impl MessageDispatcher {
                 }
                 None => {
                     // Broadcast that parsing failed - show the raw AI content for debugging
-                    log::warn!("[TEXT_ORCHESTRATED] Failed to parse AI response, using raw content");
+                    tracing::warn!("[TEXT_ORCHESTRATED] Failed to parse AI response, using raw content");
                     self.broadcaster.broadcast(GatewayEvent::agent_thinking(
                         original_message.channel_id,
-                        &format!("Parse failed, raw AI response:\n{}", &ai_content[..ai_content.len().min(500)]),
+                        &format!("Parse failed, raw AI response:\n{}", truncate_chars(&ai_content, 500)),
                     ));
 
                     if tool_call_log.is_empty() {
@@ -2350,8 +2702,8 @@ impl MessageDispatcher {
         }
 
         // Save orchestrator context for next turn
-        if let Err(e) = self.db.save_agent_context(session_id, orchestrator.context()) {
-            log::warn!("[MULTI_AGENT] Failed to save context for session {}: {}", session_id, e);
+        if let Err(e) = self.db.save_agent_context(session_id, orchestrator.context()).await {
+            tracing::warn!("[MULTI_AGENT] Failed to save context for session {}: {}", session_id, e);
         }
 
         // If cancelled with work done, save a summary so context is preserved on resume
@@ -2360,7 +2712,7 @@ impl MessageDispatcher {
                 "[Session stopped by user. Work completed before stop:]\n{}",
                 tool_call_log.join("\n")
             );
-            log::info!("[TEXT_ORCHESTRATED] Saving cancellation summary with {} tool calls", tool_call_log.len());
+            tracing::info!("[TEXT_ORCHESTRATED] Saving cancellation summary with {} tool calls", tool_call_log.len());
             if let Err(e) = self.db.add_session_message(
                 session_id,
                 DbMessageRole::Assistant,
@@ -2369,11 +2721,16 @@ impl MessageDispatcher {
                 None,
                 None,
                 None,
-            ) {
-                log::error!("Failed to save cancellation summary: {}", e);
+            ).await {
+                tracing::error!("Failed to save cancellation summary: {}", e);
             }
         }
 
+        // Budget stop: return the best answer so far with a note rather than an error
+        if budget.is_exceeded() {
+            return Ok(budget_exceeded_response(&last_content, &tool_call_log, budget));
+        }
+
         // If waiting for user response, save context and return the question content
         if waiting_for_user_response {
             // Save the tool call log to the orchestrator context so the AI knows what it already did
@@ -2384,8 +2741,8 @@ impl MessageDispatcher {
                 );
                 orchestrator.context_mut().waiting_for_user_context = Some(context_summary);
                 // Re-save context with the waiting_for_user_context
-                if let Err(e) = self.db.save_agent_context(session_id, orchestrator.context()) {
-                    log::warn!("[MULTI_AGENT] Failed to save context with user_context: {}", e);
+                if let Err(e) = self.db.save_agent_context(session_id, orchestrator.context()).await {
+                    tracing::warn!("[MULTI_AGENT] Failed to save context with user_context: {}", e);
                 }
             }
             return Ok(user_question_content);
@@ -2398,7 +2755,7 @@ impl MessageDispatcher {
                     "[Session ended with empty response. Work completed:]\n{}",
                     tool_call_log.join("\n")
                 );
-                log::info!("[TEXT_ORCHESTRATED] Saving empty-response summary with {} tool calls", tool_call_log.len());
+                tracing::info!("[TEXT_ORCHESTRATED] Saving empty-response summary with {} tool calls", tool_call_log.len());
                 let _ = self.db.add_session_message(
                     session_id,
                     DbMessageRole::Assistant,
@@ -2407,7 +2764,7 @@ impl MessageDispatcher {
                     None,
                     None,
                     None,
-                );
+                ).await;
             }
             return Err("AI returned empty response".to_string());
         }
@@ -2427,10 +2784,10 @@ impl MessageDispatcher {
             .and_then(|v| v.as_str())
             .unwrap_or("");
 
-        log::info!("[SKILL] Executing skill '{}' with input: {}", skill_name, input);
+        tracing::info!("[SKILL] Executing skill '{}' with input: {}", skill_name, input);
 
         // Look up the specific skill by name (more efficient than loading all skills)
-        let skill = match self.db.get_enabled_skill_by_name(skill_name) {
+        let skill = match self.db.get_enabled_skill_by_name(skill_name).await {
             Ok(s) => s,
             Err(e) => {
                 return crate::tools::ToolResult::error(format!("Failed to load skill: {}", e));
@@ -2452,7 +2809,7 @@ impl MessageDispatcher {
 
                 // Save active skill to agent context for persistence
                 if let Some(sid) = session_id {
-                    if let Ok(Some(mut context)) = self.db.get_agent_context(sid) {
+                    if let Ok(Some(mut context)) = self.db.get_agent_context(sid).await {
                         context.active_skill = Some(ActiveSkill {
                             name: skill.name.clone(),
                             instructions: instructions.clone(),
@@ -2460,10 +2817,10 @@ impl MessageDispatcher {
                             tool_calls_made: 0, // Reset counter - agent must call actual tools
                             requires_tools: skill.requires_tools.clone(),
                         });
-                        if let Err(e) = self.db.save_agent_context(sid, &context) {
-                            log::warn!("[SKILL] Failed to save active skill to context: {}", e);
+                        if let Err(e) = self.db.save_agent_context(sid, &context).await {
+                            tracing::warn!("[SKILL] Failed to save active skill to context: {}", e);
                         } else {
-                            log::info!(
+                            tracing::info!(
                                 "[SKILL] Saved active skill '{}' to session {} (tool_calls_made=0, requires_tools={:?})",
                                 skill.name, sid, skill.requires_tools
                             );
@@ -2488,7 +2845,7 @@ impl MessageDispatcher {
             }
             None => {
                 // Fetch available skills for the error message
-                let available = self.db.list_enabled_skills()
+                let available = self.db.list_enabled_skills().await
                     .map(|skills| skills.iter().map(|s| s.name.clone()).collect::<Vec<_>>().join(", "))
                     .unwrap_or_else(|_| "unknown".to_string());
                 crate::tools::ToolResult::error(format!(
@@ -2511,27 +2868,50 @@ impl MessageDispatcher {
 
         for path in paths {
             if let Ok(content) = std::fs::read_to_string(path) {
-                log::debug!("[SOUL] Loaded from {}", path);
+                tracing::debug!("[SOUL] Loaded from {}", path);
                 return Some(content);
             }
         }
 
-        log::debug!("[SOUL] No SOUL.md found, using default personality");
+        tracing::debug!("[SOUL] No SOUL.md found, using default personality");
         None
     }
 
+    /// Load a persona by name; a missing one falls back to the default prompt
+    async fn resolve_persona(&self, name: Option<&str>) -> Option<Persona> {
+        let name = name?;
+        match self.db.get_persona(name).await {
+            Ok(Some(persona)) => {
+                tracing::info!("[DISPATCH] Using persona '{}'", name);
+                Some(persona)
+            }
+            Ok(None) => {
+                tracing::warn!("[DISPATCH] Persona '{}' not found, using the default prompt", name);
+                None
+            }
+            Err(e) => {
+                tracing::error!("[DISPATCH] Failed to load persona '{}': {}", name, e);
+                None
+            }
+        }
+    }
+
     /// Build the base system prompt with context from memories and user info
     /// Note: Tool-related instructions are added by the archetype's enhance_system_prompt
-    fn build_system_prompt(
+    async fn build_system_prompt(
         &self,
         message: &NormalizedMessage,
         identity_id: &str,
         _tool_config: &ToolConfig,
+        persona: Option<&Persona>,
     ) -> String {
         let mut prompt = String::new();
 
-        // Load SOUL.md if available, otherwise use default intro
-        if let Some(soul) = Self::load_soul() {
+        // The persona's prompt, else SOUL.md if available, otherwise the default intro
+        if let Some(persona) = persona {
+            prompt.push_str(persona.system_prompt.trim_end());
+            prompt.push_str("\n\n");
+        } else if let Some(soul) = Self::load_soul() {
             prompt.push_str(&soul);
             prompt.push_str("\n\n");
         } else {
@@ -2539,7 +2919,7 @@ impl MessageDispatcher {
         }
 
         // Add daily logs context
-        if let Ok(daily_logs) = self.db.get_todays_daily_logs(Some(identity_id)) {
+        if let Ok(daily_logs) = self.db.get_todays_daily_logs(Some(identity_id)).await {
             if !daily_logs.is_empty() {
                 prompt.push_str("## Today's Notes\n");
                 for log in daily_logs {
@@ -2550,7 +2930,7 @@ impl MessageDispatcher {
         }
 
         // Add relevant long-term memories
-        if let Ok(memories) = self.db.get_long_term_memories(Some(identity_id), Some(5), 10) {
+        if let Ok(memories) = self.db.get_long_term_memories(Some(identity_id), Some(5), 10).await {
             if !memories.is_empty() {
                 prompt.push_str("## User Context\n");
                 for mem in memories {
@@ -2560,8 +2940,30 @@ impl MessageDispatcher {
             }
         }
 
+        // Recall memories related to this message by meaning
+        if self.memory_config.enable_vector_search {
+            let searcher = HybridSearcher::configured(self.db.clone(), &self.memory_config).await;
+            if searcher.vector_search_enabled() {
+                match searcher.search(&message.text, None, Some(identity_id), 5).await {
+                    Ok(results) if !results.is_empty() => {
+                        prompt.push_str("## Relevant Memories\n");
+                        for result in results {
+                            prompt.push_str(&format!(
+                                "- [{}] {}\n",
+                                result.memory.memory_type.as_str(),
+                                result.memory.content
+                            ));
+                        }
+                        prompt.push('\n');
+                    }
+                    Ok(_) => {}
+                    Err(e) => tracing::warn!("[MEMORY] Semantic recall failed: {}", e),
+                }
+            }
+        }
+
         // Add recent session summaries (past conversations)
-        if let Ok(summaries) = self.db.get_session_summaries(Some(identity_id), 3) {
+        if let Ok(summaries) = self.db.get_session_summaries(Some(identity_id), 3).await {
             if !summaries.is_empty() {
                 prompt.push_str("## Previous Sessions\n");
                 for summary in summaries {
@@ -2576,7 +2978,7 @@ impl MessageDispatcher {
                 identity_id,
                 Some(&message.channel_type),
                 self.memory_config.cross_session_memory_limit,
-            ) {
+            ).await {
                 if !cross_memories.is_empty() {
                     prompt.push_str("## Context from Other Channels\n");
                     for mem in cross_memories {
@@ -2597,12 +2999,29 @@ impl MessageDispatcher {
         }
 
         // Add available API keys (so the agent knows what credentials are configured)
-        if let Ok(keys) = self.db.list_api_keys() {
-            if !keys.is_empty() {
-                prompt.push_str("## Available API Keys\n");
-                prompt.push_str("The following API keys are configured and available as environment variables when using the exec tool:\n");
-                for key in &keys {
-                    prompt.push_str(&format!("- ${}\n", key.service_name));
+        if let Ok(keys) = self.db.effective_api_keys(message.owner_id).await
+            && !keys.is_empty()
+        {
+            prompt.push_str("## Available API Keys\n");
+            prompt.push_str("The following API keys are configured and available as environment variables when using the exec tool:\n");
+            for key in &keys {
+                prompt.push_str(&format!("- ${}\n", key.service_name));
+            }
+            prompt.push('\n');
+        }
+
+        // Point at skills whose frontmatter triggers appear in the message
+        if let Ok(skills) = self.db.list_enabled_skills().await {
+            let matched: Vec<_> = skills
+                .into_iter()
+                .map(|s| s.into_skill())
+                .filter(|s| s.matches_trigger(&message.text))
+                .collect();
+            if !matched.is_empty() {
+                prompt.push_str("## Relevant Skills\n");
+                prompt.push_str("This request matches the following skills. Read one with load_skill or run it with use_skill before improvising:\n");
+                for skill in matched {
+                    prompt.push_str(&format!("- {}: {}\n", skill.metadata.name, skill.metadata.description));
                 }
                 prompt.push('\n');
             }
@@ -2618,7 +3037,7 @@ impl MessageDispatcher {
     }
 
     /// Process memory markers in the AI response
-    fn process_memory_markers(
+    async fn process_memory_markers(
         &self,
         response: &str,
         identity_id: &str,
@@ -2646,10 +3065,10 @@ impl MessageDispatcher {
                             message_id,
                             date,
                             None,
-                        ) {
-                            log::error!("Failed to create {}: {}", marker.name, e);
+                        ).await {
+                            tracing::error!("Failed to create {}: {}", marker.name, e);
                         } else {
-                            log::info!("Created {}: {}", marker.name, content_str);
+                            tracing::info!("Created {}: {}", marker.name, content_str);
                         }
                     }
                 }
@@ -2698,7 +3117,7 @@ impl MessageDispatcher {
                     &response,
                 ));
 
-                log::info!(
+                tracing::info!(
                     "Thinking level set to {} for user {} on channel {}",
                     level,
                     message.user_name,
@@ -2810,7 +3229,7 @@ impl MessageDispatcher {
             tokio::select! {
                 // Highest priority: check for cancellation via token (immediate)
                 _ = cancel_token.cancelled() => {
-                    log::info!("[AI_PROGRESS] Execution cancelled via token while waiting for AI response");
+                    tracing::info!("[AI_PROGRESS] Execution cancelled via token while waiting for AI response");
 
                     // Complete the thinking task
                     if let Some(ref task_id) = thinking_task_id {
@@ -2827,6 +3246,15 @@ impl MessageDispatcher {
 
                     match result {
                         Ok(response) => {
+                            if let Some(ref usage) = response.usage {
+                                tracing::info!(
+                                    "[AI_PROGRESS] Usage metadata - seed: {:?}, system_fingerprint: {:?}, notes: {:?}",
+                                    usage.seed,
+                                    usage.system_fingerprint,
+                                    usage.notes
+                                );
+                            }
+
                             // If there are tool calls, emit a planning task
                             if !response.tool_calls.is_empty() {
                                 if let Some(ref exec_id) = execution_id {
@@ -2848,7 +3276,7 @@ impl MessageDispatcher {
                             let error_msg = e.to_string();
                             // Check if it's a timeout error
                             if error_msg.contains("timed out") || error_msg.contains("timeout") {
-                                log::error!("[AI_PROGRESS] Request timed out after {}s: {}", elapsed_secs, error_msg);
+                                tracing::error!("[AI_PROGRESS] Request timed out after {}s: {}", elapsed_secs, error_msg);
                                 broadcaster.broadcast(GatewayEvent::agent_error(
                                     channel_id,
                                     &format!("AI request timed out after {} seconds. The AI service may be overloaded. Please try again.", elapsed_secs + AI_PROGRESS_INTERVAL_SECS),
@@ -2863,7 +3291,7 @@ impl MessageDispatcher {
                     let phase_msg = thinking_phases[phase_idx % thinking_phases.len()];
                     phase_idx += 1;
 
-                    log::info!("[AI_PROGRESS] Still waiting for AI response... ({}s elapsed)", elapsed_secs);
+                    tracing::info!("[AI_PROGRESS] Still waiting for AI response... ({}s elapsed)", elapsed_secs);
                     broadcaster.broadcast(GatewayEvent::agent_thinking(
                         channel_id,
                         &format!("{} ({}s)", phase_msg, elapsed_secs),
@@ -2888,9 +3316,9 @@ impl MessageDispatcher {
 
         // Cancel all subagents for this channel
         if let Some(ref manager) = self.subagent_manager {
-            let cancelled = manager.cancel_all_for_channel(message.channel_id);
+            let cancelled = manager.cancel_all_for_channel(message.channel_id).await;
             if cancelled > 0 {
-                log::info!(
+                tracing::info!(
                     "[RESET] Cancelled {} subagents for channel {}",
                     cancelled,
                     message.channel_id
@@ -2915,20 +3343,21 @@ impl MessageDispatcher {
             &message.chat_id,
             scope,
             None,
-        ) {
+            message.owner_id,
+        ).await {
             Ok(session) => {
                 // Get identity for memory storage
                 let identity_id = self.db.get_or_create_identity(
                     &message.channel_type,
                     &message.user_id,
                     Some(&message.user_name),
-                ).ok().map(|id| id.identity_id);
+                ).await.ok().map(|id| id.identity_id);
 
                 // Save session memory before reset (session memory hook)
-                let message_count = self.db.count_session_messages(session.id).unwrap_or(0);
+                let message_count = self.db.count_session_messages(session.id).await.unwrap_or(0);
                 if message_count >= 2 {
                     // Only save if there are meaningful messages
-                    if let Ok(Some(settings)) = self.db.get_active_agent_settings() {
+                    if let Ok(Some(settings)) = self.db.get_active_agent_settings().await {
                         if let Ok(client) = AiClient::from_settings(&settings) {
                             match context::save_session_memory(
                                 &self.db,
@@ -2938,10 +3367,10 @@ impl MessageDispatcher {
                                 15, // Save last 15 messages
                             ).await {
                                 Ok(memory_id) => {
-                                    log::info!("[SESSION_MEMORY] Saved session memory (id={}) before reset", memory_id);
+                                    tracing::info!("[SESSION_MEMORY] Saved session memory (id={}) before reset", memory_id);
                                 }
                                 Err(e) => {
-                                    log::warn!("[SESSION_MEMORY] Failed to save session memory: {}", e);
+                                    tracing::warn!("[SESSION_MEMORY] Failed to save session memory: {}", e);
                                 }
                             }
                         }
@@ -2949,7 +3378,7 @@ impl MessageDispatcher {
                 }
 
                 // Reset the session
-                match self.db.reset_chat_session(session.id) {
+                match self.db.reset_chat_session(session.id).await {
                     Ok(_) => {
                         let response = "Session reset. Let's start fresh!".to_string();
                         self.broadcaster.broadcast(GatewayEvent::agent_response(
@@ -2960,13 +3389,13 @@ impl MessageDispatcher {
                         DispatchResult::success(response)
                     }
                     Err(e) => {
-                        log::error!("Failed to reset session: {}", e);
+                        tracing::error!("Failed to reset session: {}", e);
                         DispatchResult::error(format!("Failed to reset session: {}", e))
                     }
                 }
             }
             Err(e) => {
-                log::error!("Failed to get session for reset: {}", e);
+                tracing::error!("Failed to get session for reset: {}", e);
                 DispatchResult::error(format!("Session error: {}", e))
             }
         }
@@ -2981,7 +3410,7 @@ impl MessageDispatcher {
         cmd.args(["api", "user", "--jq", ".login"]);
 
         // Set GitHub token if available from stored API keys
-        if let Ok(Some(key)) = self.db.get_api_key("GITHUB_TOKEN") {
+        if let Ok(Some(key)) = self.db.get_api_key("GITHUB_TOKEN").await {
             cmd.env("GH_TOKEN", key.api_key);
         }
 
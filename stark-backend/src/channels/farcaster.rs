@@ -0,0 +1,449 @@
+//! Farcaster channel, through the Neynar API
+//!
+//! Polls the bot account's mentions and replies, runs each new cast through
+//! the dispatcher and answers with a reply cast. A conversation is a Farcaster
+//! thread, so the session key is the thread hash. Answers longer than one cast
+//! are posted as a chain of replies. Explorer links for transactions the agent
+//! broadcast during the turn are attached to the answer as embeds.
+//!
+//! The channel's `bot_token` is a Neynar API key and `app_token` is the UUID
+//! of an approved Neynar signer for the bot account. Anyone on Farcaster can
+//! mention the bot, so casts are dispatched without the `tools:exec` scope;
+//! the channel's tool config narrows things further.
+
+use crate::channels::dispatcher::MessageDispatcher;
+use crate::channels::types::{ChannelType, NormalizedMessage};
+use crate::gateway::events::EventBroadcaster;
+use crate::gateway::protocol::GatewayEvent;
+use crate::models::{Channel, Scope, Scopes};
+use crate::utils::truncate_chars;
+use chrono::{DateTime, Utc};
+use serde::de::DeserializeOwned;
+use serde::Deserialize;
+use serde_json::json;
+use std::collections::VecDeque;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::sync::oneshot;
+
+const NEYNAR_API: &str = "https://api.neynar.com/v2/farcaster";
+
+/// How often to check for new mentions
+const POLL_INTERVAL: Duration = Duration::from_secs(15);
+
+/// Byte limit of a standard cast
+const MAX_CAST_BYTES: usize = 320;
+
+/// Farcaster allows two embeds per cast
+const MAX_EMBEDS: usize = 2;
+
+/// How many handled cast hashes to remember, so a cast is answered once
+const SEEN_CAPACITY: usize = 500;
+
+#[derive(Debug, Deserialize)]
+struct Signer {
+    status: String,
+    fid: Option<u64>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+struct Author {
+    fid: u64,
+    username: String,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+struct Cast {
+    hash: String,
+    #[serde(default)]
+    thread_hash: Option<String>,
+    author: Author,
+    text: String,
+    timestamp: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct Notification {
+    #[serde(default)]
+    cast: Option<Cast>,
+}
+
+#[derive(Debug, Deserialize)]
+struct NotificationPage {
+    #[serde(default)]
+    notifications: Vec<Notification>,
+}
+
+#[derive(Debug, Deserialize)]
+struct UserPage {
+    users: Vec<Author>,
+}
+
+#[derive(Debug, Deserialize)]
+struct CastRef {
+    hash: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct PublishedCast {
+    cast: CastRef,
+}
+
+/// Minimal Neynar client for reading notifications and publishing casts
+struct NeynarClient {
+    http: reqwest::Client,
+    api_key: String,
+    signer_uuid: String,
+}
+
+impl NeynarClient {
+    fn new(api_key: String, signer_uuid: String) -> Result<Self, String> {
+        let http = reqwest::Client::builder()
+            .timeout(Duration::from_secs(30))
+            .build()
+            .map_err(|e| format!("Failed to create HTTP client: {}", e))?;
+        Ok(Self { http, api_key, signer_uuid })
+    }
+
+    async fn get<T: DeserializeOwned>(&self, path: &str, query: &[(&str, String)]) -> Result<T, String> {
+        let response = self
+            .http
+            .get(format!("{}{}", NEYNAR_API, path))
+            .header("x-api-key", &self.api_key)
+            .query(query)
+            .send()
+            .await
+            .map_err(|e| format!("Neynar request failed: {}", e))?;
+        let status = response.status();
+        if !status.is_success() {
+            let body = response.text().await.unwrap_or_default();
+            return Err(format!("Neynar API error ({}): {}", status, truncate_chars(&body, 200)));
+        }
+        response.json().await.map_err(|e| format!("Invalid Neynar response: {}", e))
+    }
+
+    /// The bot account's fid and username, checking the signer can publish for it
+    async fn bot_account(&self) -> Result<Author, String> {
+        let signer: Signer = self.get("/signer", &[("signer_uuid", self.signer_uuid.clone())]).await?;
+        let fid = match (signer.status.as_str(), signer.fid) {
+            ("approved", Some(fid)) => fid,
+            (status, _) => return Err(format!("Neynar signer is not approved (status: {})", status)),
+        };
+        let page: UserPage = self.get("/user/bulk", &[("fids", fid.to_string())]).await?;
+        page.users
+            .into_iter()
+            .next()
+            .ok_or_else(|| format!("Farcaster user {} not found", fid))
+    }
+
+    /// Latest casts mentioning or replying to `fid`
+    async fn mentions(&self, fid: u64) -> Result<Vec<Cast>, String> {
+        let page: NotificationPage = self
+            .get(
+                "/notifications",
+                &[("fid", fid.to_string()), ("type", "mentions,replies".to_string())],
+            )
+            .await?;
+        Ok(page.notifications.into_iter().filter_map(|n| n.cast).collect())
+    }
+
+    /// Publish a cast replying to `parent`, returning its hash
+    async fn publish(&self, parent: &str, text: &str, embeds: &[String]) -> Result<String, String> {
+        let embeds: Vec<_> = embeds.iter().map(|url| json!({ "url": url })).collect();
+        let response = self
+            .http
+            .post(format!("{}/cast", NEYNAR_API))
+            .header("x-api-key", &self.api_key)
+            .json(&json!({
+                "signer_uuid": self.signer_uuid,
+                "text": text,
+                "parent": parent,
+                "embeds": embeds,
+            }))
+            .send()
+            .await
+            .map_err(|e| format!("Neynar request failed: {}", e))?;
+        let status = response.status();
+        if !status.is_success() {
+            let body = response.text().await.unwrap_or_default();
+            return Err(format!("Neynar API error ({}): {}", status, truncate_chars(&body, 200)));
+        }
+        let published: PublishedCast = response.json().await.map_err(|e| format!("Invalid Neynar response: {}", e))?;
+        Ok(published.cast.hash)
+    }
+
+    /// Reply to `parent` with `text`, chaining casts when it doesn't fit in one.
+    /// `links` go on the first cast as embeds; any beyond the embed limit are
+    /// added to the text.
+    async fn reply(&self, parent: &str, text: &str, links: &[String]) -> Result<(), String> {
+        let (embeds, extra) = links.split_at(links.len().min(MAX_EMBEDS));
+        let mut text = text.to_string();
+        for link in extra {
+            text.push_str(&format!("\n{}", link));
+        }
+
+        let mut parent = parent.to_string();
+        for (i, chunk) in split_cast(&text, MAX_CAST_BYTES).iter().enumerate() {
+            let embeds = if i == 0 { embeds } else { &[] };
+            parent = self.publish(&parent, chunk, embeds).await?;
+        }
+        Ok(())
+    }
+}
+
+/// Split text into cast-sized chunks, breaking at whitespace where possible
+fn split_cast(text: &str, max_bytes: usize) -> Vec<String> {
+    let mut chunks = Vec::new();
+    let mut rest = text.trim();
+    while rest.len() > max_bytes {
+        let mut cut = max_bytes;
+        while !rest.is_char_boundary(cut) {
+            cut -= 1;
+        }
+        if let Some(space) = rest[..cut].rfind(char::is_whitespace)
+            && space > 0
+        {
+            cut = space;
+        }
+        chunks.push(rest[..cut].trim_end().to_string());
+        rest = rest[cut..].trim_start();
+    }
+    if !rest.is_empty() {
+        chunks.push(rest.to_string());
+    }
+    chunks
+}
+
+/// The cast text with mentions of the bot removed
+fn prompt_text(text: &str, bot_username: &str) -> String {
+    let mention = format!("@{}", bot_username.to_lowercase());
+    text.split_whitespace()
+        .filter(|word| word.trim_end_matches([',', ':', '.', '!', '?']).to_lowercase() != mention)
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// Decides which polled casts still need an answer
+struct Inbox {
+    bot_fid: u64,
+    /// Casts from before the listener started are left alone
+    since: DateTime<Utc>,
+    seen: VecDeque<String>,
+}
+
+impl Inbox {
+    fn new(bot_fid: u64, since: DateTime<Utc>) -> Self {
+        Self {
+            bot_fid,
+            since,
+            seen: VecDeque::new(),
+        }
+    }
+
+    /// Whether `cast` is new and should be answered; marks it as seen
+    fn accept(&mut self, cast: &Cast) -> bool {
+        if cast.author.fid == self.bot_fid || self.seen.contains(&cast.hash) {
+            return false;
+        }
+        let is_new = DateTime::parse_from_rfc3339(&cast.timestamp)
+            .is_ok_and(|at| at.with_timezone(&Utc) >= self.since);
+        if !is_new {
+            return false;
+        }
+        if self.seen.len() >= SEEN_CAPACITY {
+            self.seen.pop_front();
+        }
+        self.seen.push_back(cast.hash.clone());
+        true
+    }
+}
+
+/// Run one cast through the agent and reply to it
+async fn answer(
+    client: &NeynarClient,
+    dispatcher: &MessageDispatcher,
+    broadcaster: &EventBroadcaster,
+    channel_id: i64,
+    bot_username: &str,
+    cast: Cast,
+) {
+    let text = prompt_text(&cast.text, bot_username);
+    if text.is_empty() {
+        return;
+    }
+
+    log::info!(
+        "Farcaster: Cast from @{} ({}): {}",
+        cast.author.username,
+        cast.author.fid,
+        truncate_chars(&text, 50)
+    );
+
+    let normalized = NormalizedMessage {
+        channel_id,
+        channel_type: ChannelType::Farcaster.to_string(),
+        chat_id: cast.thread_hash.clone().unwrap_or_else(|| cast.hash.clone()),
+        user_id: cast.author.fid.to_string(),
+        user_name: cast.author.username.clone(),
+        text,
+        message_id: Some(cast.hash.clone()),
+        session_mode: None,
+        seed: None,
+        model_archetype: None,
+        model_overrides: None,
+        use_tools: None,
+        response_format: None,
+        images: Vec::new(),
+        scopes: Some(Scopes::new([Scope::Read, Scope::Chat, Scope::WalletSign])),
+        tool_policy: None,
+        owner_id: None,
+    };
+
+    // Collect explorer links for transactions sent while answering
+    let (client_id, mut event_rx) = broadcaster.subscribe();
+    let links = Arc::new(Mutex::new(Vec::<String>::new()));
+    let links_for_events = Arc::clone(&links);
+    let event_task = tokio::spawn(async move {
+        while let Some(event) = event_rx.recv().await {
+            if event.event != "tx.pending"
+                || event.data.get("channel_id").and_then(|v| v.as_i64()) != Some(channel_id)
+            {
+                continue;
+            }
+            if let Some(url) = event.data.get("explorer_url").and_then(|v| v.as_str()) {
+                links_for_events.lock().unwrap().push(url.to_string());
+            }
+        }
+    });
+
+    let result = dispatcher.dispatch(normalized).await;
+    log::info!("Farcaster: Dispatch complete, error={:?}", result.error);
+
+    broadcaster.unsubscribe(&client_id);
+    event_task.abort();
+    let links = links.lock().unwrap().clone();
+
+    let reply = match &result.error {
+        Some(error) => format!("Sorry, I encountered an error: {}", error),
+        None if result.response.is_empty() => "Done.".to_string(),
+        None => result.response.clone(),
+    };
+    if let Err(e) = client.reply(&cast.hash, &reply, &links).await {
+        log::error!("Farcaster: Failed to reply to {}: {}", cast.hash, e);
+    }
+}
+
+/// Start a Farcaster listener
+pub async fn start_farcaster_listener(
+    channel: Channel,
+    dispatcher: Arc<MessageDispatcher>,
+    broadcaster: Arc<EventBroadcaster>,
+    mut shutdown_rx: oneshot::Receiver<()>,
+) -> Result<(), String> {
+    let channel_id = channel.id;
+    let channel_name = channel.name.clone();
+    let signer_uuid = channel
+        .app_token
+        .clone()
+        .filter(|t| !t.trim().is_empty())
+        .ok_or_else(|| "Farcaster channels require a Neynar signer UUID as app_token".to_string())?;
+
+    log::info!("Starting Farcaster listener for channel: {}", channel_name);
+
+    let client = NeynarClient::new(channel.bot_token.clone(), signer_uuid)?;
+    let bot = client.bot_account().await?;
+    log::info!("Farcaster: Signed in as @{} (fid {})", bot.username, bot.fid);
+
+    // Emit started event
+    broadcaster.broadcast(GatewayEvent::channel_started(
+        channel_id,
+        ChannelType::Farcaster.as_str(),
+        &channel_name,
+    ));
+
+    let mut inbox = Inbox::new(bot.fid, Utc::now());
+    let mut tick = tokio::time::interval(POLL_INTERVAL);
+    loop {
+        tokio::select! {
+            _ = &mut shutdown_rx => {
+                log::info!("Farcaster listener {} received shutdown signal", channel_name);
+                break;
+            }
+            _ = tick.tick() => {
+                let casts = match client.mentions(bot.fid).await {
+                    Ok(casts) => casts,
+                    Err(e) => {
+                        log::warn!("Farcaster: Failed to fetch mentions: {}", e);
+                        continue;
+                    }
+                };
+                // Notifications come newest first; answer in the order they were sent
+                for cast in casts.into_iter().rev() {
+                    if inbox.accept(&cast) {
+                        answer(&client, &dispatcher, &broadcaster, channel_id, &bot.username, cast).await;
+                    }
+                }
+            }
+        }
+    }
+
+    // Emit stopped event
+    broadcaster.broadcast(GatewayEvent::channel_stopped(
+        channel_id,
+        ChannelType::Farcaster.as_str(),
+        &channel_name,
+    ));
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn cast(hash: &str, fid: u64, timestamp: &str) -> Cast {
+        Cast {
+            hash: hash.to_string(),
+            thread_hash: None,
+            author: Author {
+                fid,
+                username: "alice".to_string(),
+            },
+            text: "@starkbot quote 1 ETH to USDC".to_string(),
+            timestamp: timestamp.to_string(),
+        }
+    }
+
+    #[test]
+    fn test_inbox_accepts_each_new_cast_once() {
+        let since = DateTime::parse_from_rfc3339("2026-01-01T00:00:00Z").unwrap().with_timezone(&Utc);
+        let mut inbox = Inbox::new(42, since);
+
+        assert!(inbox.accept(&cast("0xa", 7, "2026-01-01T00:00:05.000Z")));
+        assert!(!inbox.accept(&cast("0xa", 7, "2026-01-01T00:00:05.000Z")));
+        // Older than the listener, or written by the bot itself
+        assert!(!inbox.accept(&cast("0xb", 7, "2025-12-31T23:59:59.000Z")));
+        assert!(!inbox.accept(&cast("0xc", 42, "2026-01-01T00:00:06.000Z")));
+    }
+
+    #[test]
+    fn test_prompt_text_strips_bot_mention() {
+        assert_eq!(prompt_text("@StarkBot, quote 1 ETH to USDC", "starkbot"), "quote 1 ETH to USDC");
+        assert_eq!(prompt_text("hey @starkbot ask @alice", "starkbot"), "hey ask @alice");
+        assert_eq!(prompt_text("@starkbot", "starkbot"), "");
+    }
+
+    #[test]
+    fn test_split_cast() {
+        assert_eq!(split_cast("short", MAX_CAST_BYTES), vec!["short"]);
+
+        let text = "word ".repeat(100);
+        let chunks = split_cast(&text, MAX_CAST_BYTES);
+        assert!(chunks.len() > 1);
+        assert!(chunks.iter().all(|c| c.len() <= MAX_CAST_BYTES && !c.starts_with(' ')));
+        assert!(chunks.iter().all(|c| c.split(' ').all(|w| w == "word")));
+
+        let emoji = "🚀".repeat(100);
+        assert!(split_cast(&emoji, MAX_CAST_BYTES).iter().all(|c| c.len() <= MAX_CAST_BYTES));
+    }
+}
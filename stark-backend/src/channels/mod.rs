@@ -1,11 +1,12 @@
 pub mod discord;
 pub mod dispatcher;
+pub mod farcaster;
 pub mod slack;
 pub mod telegram;
 pub mod types;
 
 pub use dispatcher::MessageDispatcher;
-pub use types::{ChannelHandle, ChannelType, NormalizedMessage};
+pub use types::{ChannelHandle, NormalizedMessage, ToolInvocation};
 
 use crate::db::Database;
 use crate::execution::ExecutionTracker;
@@ -127,6 +128,18 @@ impl ChannelManager {
             }
         };
 
+        // Some channel types may leave the token empty and use a shared API key instead
+        let channel = match channel_type_enum.fallback_token_key() {
+            Some(key_name) => match resolve_bot_token(&self.db, channel, key_name).await {
+                Ok(channel) => channel,
+                Err(e) => {
+                    self.running_channels.remove(&channel_id);
+                    return Err(e);
+                }
+            },
+            None => channel,
+        };
+
         match channel_type_enum {
             types::ChannelType::Telegram => {
                 tokio::spawn(async move {
@@ -181,6 +194,25 @@ impl ChannelManager {
                         broadcaster.broadcast(GatewayEvent::channel_error(channel_id, &e));
                     }
 
+                    // Remove from running channels
+                    running_channels.remove(&channel_id);
+                });
+            }
+            types::ChannelType::Farcaster => {
+                tokio::spawn(async move {
+                    let result = farcaster::start_farcaster_listener(
+                        channel,
+                        dispatcher,
+                        broadcaster.clone(),
+                        shutdown_rx,
+                    )
+                    .await;
+
+                    if let Err(e) = result {
+                        log::error!("Farcaster listener error: {}", e);
+                        broadcaster.broadcast(GatewayEvent::channel_error(channel_id, &e));
+                    }
+
                     // Remove from running channels
                     running_channels.remove(&channel_id);
                 });
@@ -225,3 +257,21 @@ impl ChannelManager {
         }
     }
 }
+
+/// Use the channel's own bot token, or the `key_name` API key when it has none
+async fn resolve_bot_token(db: &Database, mut channel: Channel, key_name: &str) -> Result<Channel, String> {
+    if !channel.bot_token.trim().is_empty() {
+        return Ok(channel);
+    }
+    match db.get_api_key(key_name).await {
+        Ok(Some(key)) if !key.api_key.trim().is_empty() => {
+            channel.bot_token = key.api_key;
+            Ok(channel)
+        }
+        Ok(_) => Err(format!(
+            "Channel '{}' has no bot token and no {} API key is set",
+            channel.name, key_name
+        )),
+        Err(e) => Err(format!("Failed to load {}: {}", key_name, e)),
+    }
+}
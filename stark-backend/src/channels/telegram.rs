@@ -3,6 +3,7 @@ use crate::channels::types::{ChannelType, NormalizedMessage};
 use crate::gateway::events::EventBroadcaster;
 use crate::gateway::protocol::GatewayEvent;
 use crate::models::Channel;
+use crate::utils::{truncate_chars, truncate_str};
 use std::sync::Arc;
 use teloxide::prelude::*;
 use teloxide::requests::Requester;
@@ -13,11 +14,7 @@ fn format_tool_call_for_telegram(tool_name: &str, parameters: &serde_json::Value
     let params_str = serde_json::to_string_pretty(parameters)
         .unwrap_or_else(|_| parameters.to_string());
     // Truncate params if too long for Telegram
-    let params_display = if params_str.len() > 500 {
-        format!("{}...", &params_str[..500])
-    } else {
-        params_str
-    };
+    let params_display = truncate_str(&params_str, 500);
     format!("🔧 Tool Call: {}\n{}", tool_name, params_display)
 }
 
@@ -25,11 +22,7 @@ fn format_tool_call_for_telegram(tool_name: &str, parameters: &serde_json::Value
 fn format_tool_result_for_telegram(tool_name: &str, success: bool, duration_ms: i64, content: &str) -> String {
     let status = if success { "✅" } else { "❌" };
     // Truncate content if too long
-    let content_display = if content.len() > 1000 {
-        format!("{}...", &content[..1000])
-    } else {
-        content.to_string()
-    };
+    let content_display = truncate_str(content, 1000);
     format!(
         "{} Tool Result: {} ({} ms)\n{}",
         status, tool_name, duration_ms, content_display
@@ -118,7 +111,7 @@ pub async fn start_telegram_listener(
                         "Telegram: Message from {} ({}): {}",
                         user_name,
                         user_id,
-                        if text.len() > 50 { &text[..50] } else { text }
+                        truncate_chars(text, 50)
                     );
 
                     let normalized = NormalizedMessage {
@@ -130,6 +123,15 @@ pub async fn start_telegram_listener(
                         text: text.to_string(),
                         message_id: Some(msg.id.to_string()),
                         session_mode: None,
+                        seed: None,
+                        model_archetype: None,
+                        model_overrides: None,
+                        use_tools: None,
+                        response_format: None,
+                        images: Vec::new(),
+                        scopes: None,
+                        tool_policy: None,
+                        owner_id: None,
                     };
 
                     // Subscribe to events for real-time tool call forwarding
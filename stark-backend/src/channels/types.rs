@@ -1,3 +1,8 @@
+use crate::ai::budget::BudgetUsage;
+use crate::ai::{ArchetypeId, ImageSource, ResponseFormat};
+use crate::models::{ModelOverrides, Scopes, ToolPolicy};
+use crate::tools::ToolResult;
+use crate::utils::truncate_chars;
 use serde::{Deserialize, Serialize};
 
 /// Supported channel types
@@ -7,6 +12,7 @@ pub enum ChannelType {
     Telegram,
     Slack,
     Discord,
+    Farcaster,
 }
 
 impl ChannelType {
@@ -16,6 +22,7 @@ impl ChannelType {
             Self::Telegram => "telegram",
             Self::Slack => "slack",
             Self::Discord => "discord",
+            Self::Farcaster => "farcaster",
         }
     }
 
@@ -25,13 +32,14 @@ impl ChannelType {
             "telegram" => Some(Self::Telegram),
             "slack" => Some(Self::Slack),
             "discord" => Some(Self::Discord),
+            "farcaster" => Some(Self::Farcaster),
             _ => None,
         }
     }
 
     /// All supported channel types
     pub fn all() -> &'static [ChannelType] {
-        &[Self::Telegram, Self::Slack, Self::Discord]
+        &[Self::Telegram, Self::Slack, Self::Discord, Self::Farcaster]
     }
 
     /// Display name for UI
@@ -40,6 +48,16 @@ impl ChannelType {
             Self::Telegram => "Telegram",
             Self::Slack => "Slack",
             Self::Discord => "Discord",
+            Self::Farcaster => "Farcaster",
+        }
+    }
+
+    /// API key used when a channel of this type is created without a token
+    pub fn fallback_token_key(&self) -> Option<&'static str> {
+        match self {
+            Self::Discord => Some("DISCORD_BOT_TOKEN"),
+            Self::Farcaster => Some("NEYNAR_API_KEY"),
+            Self::Telegram | Self::Slack => None,
         }
     }
 }
@@ -70,6 +88,37 @@ pub struct NormalizedMessage {
     /// Session mode for cron jobs: "main" (shared with web) or "isolated" (separate session)
     #[serde(default)]
     pub session_mode: Option<String>,
+    /// Optional sampling seed for reproducible outputs (web chat API only)
+    #[serde(default)]
+    pub seed: Option<u64>,
+    /// Optional override of the agent's `model_archetype` for this message (web chat API only)
+    #[serde(default)]
+    pub model_archetype: Option<ArchetypeId>,
+    /// Optional model, max_tokens and temperature for this message, already
+    /// checked against the agent settings (web chat API only)
+    #[serde(default)]
+    pub model_overrides: Option<ModelOverrides>,
+    /// `Some(false)` answers without running tools (web chat API only)
+    #[serde(default)]
+    pub use_tools: Option<bool>,
+    /// Ask for the answer as JSON, already validated (web chat API only)
+    #[serde(default)]
+    pub response_format: Option<ResponseFormat>,
+    /// Images sent with the message, for vision-capable models (web chat API only)
+    #[serde(default)]
+    pub images: Vec<ImageSource>,
+    /// Scopes of the API token that sent the message; tools needing a scope
+    /// it lacks are withheld. `None` for channel and scheduler messages.
+    #[serde(default)]
+    pub scopes: Option<Scopes>,
+    /// Tool policy of the API token that sent the message (the session's own
+    /// policy is applied on top)
+    #[serde(default)]
+    pub tool_policy: Option<ToolPolicy>,
+    /// User a new conversation belongs to (web chat only). `None` for channel
+    /// and scheduler messages, whose conversations only admins can see.
+    #[serde(default)]
+    pub owner_id: Option<i64>,
 }
 
 /// Handle to a running channel listener
@@ -96,11 +145,42 @@ impl ChannelHandle {
     }
 }
 
+/// A tool the agent ran while producing a response
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ToolInvocation {
+    pub name: String,
+    pub arguments: serde_json::Value,
+    pub success: bool,
+    /// Tool output, cut to `MAX_OUTPUT_CHARS`
+    pub output: String,
+    pub duration_ms: i64,
+}
+
+impl ToolInvocation {
+    pub const MAX_OUTPUT_CHARS: usize = 4000;
+
+    pub fn new(name: &str, arguments: &serde_json::Value, result: &ToolResult, duration_ms: i64) -> Self {
+        Self {
+            name: name.to_string(),
+            arguments: arguments.clone(),
+            success: result.success,
+            output: truncate_chars(&result.content, Self::MAX_OUTPUT_CHARS).to_string(),
+            duration_ms,
+        }
+    }
+}
+
 /// Result of dispatching a message to the AI
 #[derive(Debug, Clone)]
 pub struct DispatchResult {
     pub response: String,
     pub error: Option<String>,
+    /// Tokens and estimated cost spent producing this result
+    pub usage: Option<BudgetUsage>,
+    /// Tools run while producing this result, in order
+    pub tool_calls: Vec<ToolInvocation>,
+    /// Directory the tools worked in, so channels can send back files they wrote
+    pub workspace: Option<String>,
 }
 
 impl DispatchResult {
@@ -108,6 +188,9 @@ impl DispatchResult {
         Self {
             response,
             error: None,
+            usage: None,
+            tool_calls: Vec::new(),
+            workspace: None,
         }
     }
 
@@ -115,6 +198,24 @@ impl DispatchResult {
         Self {
             response: String::new(),
             error: Some(error),
+            usage: None,
+            tool_calls: Vec::new(),
+            workspace: None,
         }
     }
+
+    pub fn with_usage(mut self, usage: BudgetUsage) -> Self {
+        self.usage = Some(usage);
+        self
+    }
+
+    pub fn with_tool_calls(mut self, tool_calls: Vec<ToolInvocation>) -> Self {
+        self.tool_calls = tool_calls;
+        self
+    }
+
+    pub fn with_workspace(mut self, workspace: String) -> Self {
+        self.workspace = Some(workspace);
+        self
+    }
 }
@@ -1,32 +1,434 @@
 use std::env;
 use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use serde::Deserialize;
+
+use crate::tools::ToolGroup;
 
 /// Environment variable names - single source of truth
 pub mod env_vars {
     pub const LOGIN_ADMIN_PUBLIC_ADDRESS: &str = "LOGIN_ADMIN_PUBLIC_ADDRESS";
     pub const BURNER_WALLET_PRIVATE_KEY: &str = "BURNER_WALLET_BOT_PRIVATE_KEY";
+    // Settings file (config.toml or config.ron); environment variables override it
+    pub const CONFIG_FILE: &str = "STARK_CONFIG_FILE";
+    pub const HOST: &str = "STARK_HOST";
     pub const PORT: &str = "PORT";
+    // Grace period on SIGTERM for requests and the running agent job to finish
+    pub const SHUTDOWN_TIMEOUT_SECS: &str = "STARK_SHUTDOWN_TIMEOUT_SECS";
+    // Comma-separated origins allowed to call the API from a browser (unset = any)
+    pub const CORS_ORIGINS: &str = "STARK_CORS_ORIGINS";
+    // Comma-separated HTTP methods allowed in cross-origin requests
+    pub const CORS_METHODS: &str = "STARK_CORS_METHODS";
+    // Content-Security-Policy for the dashboard's HTML (empty = no header)
+    pub const CONTENT_SECURITY_POLICY: &str = "STARK_CONTENT_SECURITY_POLICY";
+    // Strict-Transport-Security max-age in seconds (unset or 0 = no header)
+    pub const HSTS_MAX_AGE_SECS: &str = "STARK_HSTS_MAX_AGE_SECS";
+    // Model used instead of the archetypes' built-in defaults (a profile's own default_model still wins)
+    pub const DEFAULT_MODEL: &str = "STARK_DEFAULT_MODEL";
+    // Comma-separated tool groups disabled at startup
+    pub const DISABLED_TOOL_GROUPS: &str = "STARK_DISABLED_TOOL_GROUPS";
     pub const DATABASE_URL: &str = "DATABASE_URL";
+    // Local SQLite file used alongside a Postgres DATABASE_URL
+    pub const SQLITE_PATH: &str = "STARK_SQLITE_PATH";
+    pub const POSTGRES_POOL_SIZE: &str = "STARK_POSTGRES_POOL_SIZE";
+    pub const DB_POOL_SIZE: &str = "STARK_DB_POOL_SIZE";
+    // Master key for API keys stored in the database (base64, 32 bytes)
+    pub const MASTER_KEY: &str = "STARK_MASTER_KEY";
+    pub const MASTER_KEY_FILE: &str = "STARK_MASTER_KEY_FILE";
+    // Comma-separated retired master keys, still accepted for decryption
+    pub const MASTER_KEY_PREVIOUS: &str = "STARK_MASTER_KEY_PREVIOUS";
+    // Base32 TOTP secret; when set, sign-in and admin-scoped API tokens need a code
+    pub const ADMIN_TOTP_SECRET: &str = "STARK_ADMIN_TOTP_SECRET";
+    // Failed sign-ins from one IP allowed before it is locked out
+    pub const LOGIN_MAX_FAILURES: &str = "STARK_LOGIN_MAX_FAILURES";
+    // First lockout; each further failure doubles it
+    pub const LOGIN_LOCKOUT_SECS: &str = "STARK_LOGIN_LOCKOUT_SECS";
     pub const WORKSPACE_DIR: &str = "STARK_WORKSPACE_DIR";
+    // Size limit per session / agent-run workspace, in megabytes (unset or 0 = unlimited)
+    pub const WORKSPACE_QUOTA_MB: &str = "STARK_WORKSPACE_QUOTA_MB";
+    // Size limits for one workspace file upload, before and after archive extraction
+    pub const UPLOAD_MAX_MB: &str = "STARK_UPLOAD_MAX_MB";
+    pub const UPLOAD_MAX_EXTRACTED_MB: &str = "STARK_UPLOAD_MAX_EXTRACTED_MB";
+    // Free space on the workspace volume below which /readyz reports not ready
+    pub const MIN_FREE_DISK_MB: &str = "STARK_MIN_FREE_DISK_MB";
+    // How long /readyz reuses the last AI provider reachability check
+    pub const PROVIDER_CHECK_TTL_SECS: &str = "STARK_PROVIDER_CHECK_TTL_SECS";
     pub const SKILLS_DIR: &str = "STARK_SKILLS_DIR";
     pub const JOURNAL_DIR: &str = "STARK_JOURNAL_DIR";
+    // Git repository of skills shared across instances (skill marketplace)
+    pub const SKILLS_GIT_URL: &str = "STARK_SKILLS_GIT_URL";
+    pub const SKILLS_GIT_BRANCH: &str = "STARK_SKILLS_GIT_BRANCH";
+    pub const SKILLS_SYNC_INTERVAL_MINUTES: &str = "STARK_SKILLS_SYNC_INTERVAL_MINUTES";
     // Memory configuration
     pub const MEMORY_ENABLE_PRE_COMPACTION_FLUSH: &str = "STARK_MEMORY_ENABLE_PRE_COMPACTION_FLUSH";
     pub const MEMORY_ENABLE_ENTITY_EXTRACTION: &str = "STARK_MEMORY_ENABLE_ENTITY_EXTRACTION";
     pub const MEMORY_ENABLE_VECTOR_SEARCH: &str = "STARK_MEMORY_ENABLE_VECTOR_SEARCH";
     pub const MEMORY_EMBEDDING_PROVIDER: &str = "STARK_MEMORY_EMBEDDING_PROVIDER";
+    pub const MEMORY_EMBEDDING_MODEL: &str = "STARK_MEMORY_EMBEDDING_MODEL";
+    // Key for the embeddings API; without it the AI provider's endpoint and key are used
+    pub const MEMORY_EMBEDDING_API_KEY: &str = "STARK_MEMORY_EMBEDDING_API_KEY";
     pub const MEMORY_ENABLE_AUTO_CONSOLIDATION: &str = "STARK_MEMORY_ENABLE_AUTO_CONSOLIDATION";
     pub const MEMORY_ENABLE_CROSS_SESSION: &str = "STARK_MEMORY_ENABLE_CROSS_SESSION";
     pub const MEMORY_CROSS_SESSION_LIMIT: &str = "STARK_MEMORY_CROSS_SESSION_LIMIT";
+    // Per-request chat budget
+    pub const CHAT_BUDGET_MAX_TOKENS: &str = "STARK_CHAT_BUDGET_MAX_TOKENS";
+    pub const CHAT_BUDGET_MAX_USD: &str = "STARK_CHAT_BUDGET_MAX_USD";
+    pub const CHAT_BUDGET_USD_PER_MTOK_INPUT: &str = "STARK_CHAT_BUDGET_USD_PER_MTOK_INPUT";
+    pub const CHAT_BUDGET_USD_PER_MTOK_OUTPUT: &str = "STARK_CHAT_BUDGET_USD_PER_MTOK_OUTPUT";
+    // Anthropic API headers
+    pub const ANTHROPIC_VERSION: &str = "STARK_ANTHROPIC_VERSION";
+    pub const ANTHROPIC_BETA: &str = "STARK_ANTHROPIC_BETA";
+    // Take client IPs from X-Forwarded-For / Forwarded (only behind a trusted proxy)
+    pub const TRUST_PROXY: &str = "STARK_TRUST_PROXY";
+    // OTLP/HTTP trace export (standard OpenTelemetry variables)
+    pub const OTLP_ENDPOINT: &str = "OTEL_EXPORTER_OTLP_ENDPOINT";
+    pub const OTLP_TRACES_ENDPOINT: &str = "OTEL_EXPORTER_OTLP_TRACES_ENDPOINT";
+    pub const OTEL_SERVICE_NAME: &str = "OTEL_SERVICE_NAME";
 }
 
 /// Default values
 pub mod defaults {
+    pub const CONFIG_FILES: [&str; 2] = ["config.toml", "config.ron"];
+    pub const HOST: &str = "0.0.0.0";
     pub const PORT: u16 = 8080;
+    pub const SHUTDOWN_TIMEOUT_SECS: u64 = 30;
+    pub const CORS_METHODS: [&str; 5] = ["GET", "POST", "PUT", "PATCH", "DELETE"];
+    pub const CONTENT_SECURITY_POLICY: &str = "default-src 'self'; script-src 'self'; \
+        style-src 'self' 'unsafe-inline'; img-src 'self' data: blob: https:; font-src 'self' data:; \
+        connect-src 'self' https: wss: ws:; object-src 'none'; base-uri 'self'; form-action 'self'; \
+        frame-ancestors 'none'";
     pub const DATABASE_URL: &str = "./.db/stark.db";
+    pub const SQLITE_PATH: &str = "./.db/stark.db";
+    pub const POSTGRES_POOL_SIZE: usize = 4;
+    pub const DB_POOL_SIZE: u32 = 8;
+    pub const LOGIN_MAX_FAILURES: i64 = 5;
+    pub const LOGIN_LOCKOUT_SECS: u64 = 60;
     pub const WORKSPACE_DIR: &str = "./workspace";
+    pub const UPLOAD_MAX_MB: u64 = 50;
+    pub const UPLOAD_MAX_EXTRACTED_MB: u64 = 500;
+    pub const MIN_FREE_DISK_MB: u64 = 100;
+    pub const PROVIDER_CHECK_TTL_SECS: u64 = 60;
     pub const SKILLS_DIR: &str = "./skills";
     pub const JOURNAL_DIR: &str = "./journal";
+    pub const SKILLS_GIT_BRANCH: &str = "main";
+    pub const CHAT_BUDGET_USD_PER_MTOK_INPUT: f64 = 3.0;
+    pub const CHAT_BUDGET_USD_PER_MTOK_OUTPUT: f64 = 15.0;
+    pub const ANTHROPIC_VERSION: &str = "2023-06-01";
+    pub const OTEL_SERVICE_NAME: &str = "stark-backend";
+}
+
+/// Settings file read at startup. Each setting stands in for an environment
+/// variable, and a variable that is set wins over the file.
+#[derive(Debug, Default, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ConfigFile {
+    pub server: ServerSettings,
+    pub database: DatabaseSettings,
+    pub workspace: WorkspaceSettings,
+    pub agent: AgentDefaults,
+    pub tools: ToolSettings,
+}
+
+#[derive(Debug, Default, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ServerSettings {
+    /// `STARK_HOST`
+    pub host: Option<String>,
+    /// `PORT`
+    pub port: Option<u16>,
+    /// `STARK_CORS_ORIGINS`
+    pub cors_origins: Option<Vec<String>>,
+    /// `STARK_CORS_METHODS`
+    pub cors_methods: Option<Vec<String>>,
+    /// `STARK_CONTENT_SECURITY_POLICY`
+    pub content_security_policy: Option<String>,
+    /// `STARK_HSTS_MAX_AGE_SECS`
+    pub hsts_max_age_secs: Option<u64>,
+    /// `STARK_SHUTDOWN_TIMEOUT_SECS`
+    pub shutdown_timeout_secs: Option<u64>,
+}
+
+#[derive(Debug, Default, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct DatabaseSettings {
+    /// `DATABASE_URL`: SQLite path or `postgres://` URL
+    pub url: Option<String>,
+}
+
+#[derive(Debug, Default, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct WorkspaceSettings {
+    /// `STARK_WORKSPACE_DIR`
+    pub root: Option<String>,
+}
+
+#[derive(Debug, Default, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct AgentDefaults {
+    /// `STARK_DEFAULT_MODEL`
+    pub default_model: Option<String>,
+}
+
+#[derive(Debug, Default, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ToolSettings {
+    /// `STARK_DISABLED_TOOL_GROUPS`
+    pub disabled_groups: Option<Vec<String>>,
+}
+
+impl ConfigFile {
+    /// Parse a settings file, as TOML or RON by its extension
+    pub fn parse(path: &Path, content: &str) -> Result<Self, String> {
+        match path.extension().and_then(|e| e.to_str()) {
+            Some("toml") => toml::from_str(content).map_err(|e| e.to_string()),
+            Some("ron") => ron::from_str(content).map_err(|e| e.to_string()),
+            _ => Err("expected a .toml or .ron file".to_string()),
+        }
+    }
+
+    /// The settings as environment variables
+    pub fn env_vars(&self) -> Vec<(&'static str, String)> {
+        let list = |items: &Vec<String>| items.join(",");
+        [
+            (env_vars::HOST, self.server.host.clone()),
+            (env_vars::PORT, self.server.port.map(|p| p.to_string())),
+            (env_vars::CORS_ORIGINS, self.server.cors_origins.as_ref().map(list)),
+            (env_vars::CORS_METHODS, self.server.cors_methods.as_ref().map(list)),
+            (env_vars::CONTENT_SECURITY_POLICY, self.server.content_security_policy.clone()),
+            (env_vars::HSTS_MAX_AGE_SECS, self.server.hsts_max_age_secs.map(|s| s.to_string())),
+            (env_vars::SHUTDOWN_TIMEOUT_SECS, self.server.shutdown_timeout_secs.map(|s| s.to_string())),
+            (env_vars::DATABASE_URL, self.database.url.clone()),
+            (env_vars::WORKSPACE_DIR, self.workspace.root.clone()),
+            (env_vars::DEFAULT_MODEL, self.agent.default_model.clone()),
+            (env_vars::DISABLED_TOOL_GROUPS, self.tools.disabled_groups.as_ref().map(list)),
+        ]
+        .into_iter()
+        .filter_map(|(var, value)| value.map(|v| (var, v)))
+        .collect()
+    }
+}
+
+/// Get the settings file: `STARK_CONFIG_FILE`, else `config.toml` or `config.ron`
+/// in the working directory if present
+pub fn config_file_path() -> Option<PathBuf> {
+    match env::var(env_vars::CONFIG_FILE) {
+        Ok(path) => Some(PathBuf::from(path)),
+        Err(_) => defaults::CONFIG_FILES.iter().map(PathBuf::from).find(|p| p.is_file()),
+    }
+}
+
+/// Load the settings file into the environment, leaving variables that are
+/// already set alone. Returns the file that was loaded.
+///
+/// Must run at startup before other threads read the environment, like `dotenv`.
+pub fn load_config_file() -> Result<Option<PathBuf>, String> {
+    let Some(path) = config_file_path() else {
+        return Ok(None);
+    };
+    let content = std::fs::read_to_string(&path).map_err(|e| format!("{}: {}", path.display(), e))?;
+    let file = ConfigFile::parse(&path, &content).map_err(|e| format!("{}: {}", path.display(), e))?;
+    for (var, value) in file.env_vars() {
+        if env::var_os(var).is_none() {
+            unsafe { env::set_var(var, value) };
+        }
+    }
+    Ok(Some(path))
+}
+
+/// Check the server settings, so a bad value stops startup instead of
+/// failing on first use. Returns every problem found.
+pub fn validate() -> Result<(), Vec<String>> {
+    let mut errors = Vec::new();
+    let var = |name| env::var(name).ok();
+
+    if var(env_vars::LOGIN_ADMIN_PUBLIC_ADDRESS).is_none_or(|v| v.trim().is_empty()) {
+        errors.push(format!("{} must be set", env_vars::LOGIN_ADMIN_PUBLIC_ADDRESS));
+    }
+    if let Some(port) = var(env_vars::PORT)
+        && !port.parse::<u16>().is_ok_and(|p| p > 0)
+    {
+        errors.push(format!("{} must be a port number, got '{}'", env_vars::PORT, port));
+    }
+    if let Some(secs) = var(env_vars::SHUTDOWN_TIMEOUT_SECS)
+        && secs.parse::<u64>().is_err()
+    {
+        errors.push(format!("{} must be a number of seconds, got '{}'", env_vars::SHUTDOWN_TIMEOUT_SECS, secs));
+    }
+    if let Some(host) = var(env_vars::HOST)
+        && host.trim().is_empty()
+    {
+        errors.push(format!("{} must not be empty", env_vars::HOST));
+    }
+    for origin in cors_origins() {
+        if !is_valid_origin(&origin) {
+            errors.push(format!(
+                "{}: '{}' is not an origin like https://example.com",
+                env_vars::CORS_ORIGINS,
+                origin
+            ));
+        }
+    }
+    if var(env_vars::CORS_METHODS).is_some() && list_var(env_vars::CORS_METHODS).is_empty() {
+        errors.push(format!("{} must list at least one method", env_vars::CORS_METHODS));
+    }
+    for method in cors_methods() {
+        if !["GET", "HEAD", "POST", "PUT", "PATCH", "DELETE", "OPTIONS"].contains(&method.as_str()) {
+            errors.push(format!("{}: '{}' is not an HTTP method like GET", env_vars::CORS_METHODS, method));
+        }
+    }
+    if let Some(secs) = var(env_vars::HSTS_MAX_AGE_SECS)
+        && secs.parse::<u64>().is_err()
+    {
+        errors.push(format!("{} must be a number of seconds, got '{}'", env_vars::HSTS_MAX_AGE_SECS, secs));
+    }
+    if let Some(secret) = admin_totp_secret() {
+        match crate::totp::decode_base32(&secret) {
+            Some(key) if key.len() >= crate::totp::MIN_SECRET_BYTES => {}
+            Some(_) => errors.push(format!(
+                "{} must be at least {} bytes ({} base32 characters)",
+                env_vars::ADMIN_TOTP_SECRET,
+                crate::totp::MIN_SECRET_BYTES,
+                crate::totp::MIN_SECRET_BYTES * 8 / 5 + 1
+            )),
+            None => errors.push(format!("{} must be base32", env_vars::ADMIN_TOTP_SECRET)),
+        }
+    }
+    for (name, value) in [
+        (env_vars::LOGIN_MAX_FAILURES, var(env_vars::LOGIN_MAX_FAILURES)),
+        (env_vars::LOGIN_LOCKOUT_SECS, var(env_vars::LOGIN_LOCKOUT_SECS)),
+    ] {
+        if let Some(value) = value
+            && value.parse::<u64>().is_err()
+        {
+            errors.push(format!("{} must be a whole number, got '{}'", name, value));
+        }
+    }
+    for group in list_var(env_vars::DISABLED_TOOL_GROUPS) {
+        if ToolGroup::from_str(&group).is_none() {
+            errors.push(format!("{}: unknown tool group '{}'", env_vars::DISABLED_TOOL_GROUPS, group));
+        }
+    }
+    if workspace_dir().trim().is_empty() {
+        errors.push(format!("{} must not be empty", env_vars::WORKSPACE_DIR));
+    }
+    if database_url().trim().is_empty() {
+        errors.push(format!("{} must not be empty", env_vars::DATABASE_URL));
+    }
+
+    if errors.is_empty() { Ok(()) } else { Err(errors) }
+}
+
+/// Scheme and host (and port), without a path
+fn is_valid_origin(origin: &str) -> bool {
+    match url::Url::parse(origin) {
+        Ok(url) => {
+            matches!(url.scheme(), "http" | "https")
+                && url.host().is_some()
+                && url.path() == "/"
+                && !origin.ends_with('/')
+                && url.query().is_none()
+        }
+        Err(_) => false,
+    }
+}
+
+/// Comma-separated values of a variable, trimmed, empty entries dropped
+fn list_var(var: &str) -> Vec<String> {
+    env::var(var)
+        .map(|v| {
+            v.split(',')
+                .map(str::trim)
+                .filter(|s| !s.is_empty())
+                .map(str::to_string)
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+/// Get the address the HTTP server binds to
+pub fn server_host() -> String {
+    env::var(env_vars::HOST)
+        .ok()
+        .filter(|v| !v.trim().is_empty())
+        .unwrap_or_else(|| defaults::HOST.to_string())
+}
+
+/// Get how long shutdown waits for requests and the running agent job before
+/// interrupting them
+pub fn shutdown_timeout() -> Duration {
+    let secs = env::var(env_vars::SHUTDOWN_TIMEOUT_SECS)
+        .ok()
+        .and_then(|v| v.parse().ok())
+        .unwrap_or(defaults::SHUTDOWN_TIMEOUT_SECS);
+    Duration::from_secs(secs)
+}
+
+/// Get the origins allowed to call the API from a browser; empty allows any
+pub fn cors_origins() -> Vec<String> {
+    list_var(env_vars::CORS_ORIGINS)
+}
+
+/// Get the HTTP methods allowed in cross-origin requests
+pub fn cors_methods() -> Vec<String> {
+    let methods = list_var(env_vars::CORS_METHODS);
+    if methods.is_empty() {
+        defaults::CORS_METHODS.iter().map(|m| m.to_string()).collect()
+    } else {
+        methods
+    }
+}
+
+/// Get the Content-Security-Policy sent with the dashboard; None when set to empty
+pub fn content_security_policy() -> Option<String> {
+    match env::var(env_vars::CONTENT_SECURITY_POLICY) {
+        Ok(policy) if policy.trim().is_empty() => None,
+        Ok(policy) => Some(policy.trim().to_string()),
+        Err(_) => Some(defaults::CONTENT_SECURITY_POLICY.to_string()),
+    }
+}
+
+/// Get the Strict-Transport-Security max-age; None disables the header
+pub fn hsts_max_age_secs() -> Option<u64> {
+    env::var(env_vars::HSTS_MAX_AGE_SECS)
+        .ok()
+        .and_then(|v| v.parse().ok())
+        .filter(|&secs: &u64| secs > 0)
+}
+
+/// Get the admin TOTP secret (base32), if a second factor is configured
+pub fn admin_totp_secret() -> Option<String> {
+    env::var(env_vars::ADMIN_TOTP_SECRET).ok().map(|v| v.trim().to_string()).filter(|v| !v.is_empty())
+}
+
+/// Get how many failed sign-ins in a row one IP may make before a lockout
+pub fn login_max_failures() -> i64 {
+    env::var(env_vars::LOGIN_MAX_FAILURES)
+        .ok()
+        .and_then(|v| v.parse().ok())
+        .unwrap_or(defaults::LOGIN_MAX_FAILURES)
+}
+
+/// Get the first sign-in lockout; 0 disables lockouts
+pub fn login_lockout() -> Duration {
+    let secs = env::var(env_vars::LOGIN_LOCKOUT_SECS)
+        .ok()
+        .and_then(|v| v.parse().ok())
+        .unwrap_or(defaults::LOGIN_LOCKOUT_SECS);
+    Duration::from_secs(secs)
+}
+
+/// Get the model used instead of the archetype's built-in default, if any
+pub fn default_model() -> Option<String> {
+    env::var(env_vars::DEFAULT_MODEL).ok().map(|v| v.trim().to_string()).filter(|v| !v.is_empty())
+}
+
+/// Get the tool groups to disable at startup
+pub fn disabled_tool_groups() -> Vec<ToolGroup> {
+    list_var(env_vars::DISABLED_TOOL_GROUPS)
+        .iter()
+        .filter_map(|name| ToolGroup::from_str(name))
+        .collect()
 }
 
 /// Get the workspace directory from environment or default
@@ -34,16 +436,163 @@ pub fn workspace_dir() -> String {
     env::var(env_vars::WORKSPACE_DIR).unwrap_or_else(|_| defaults::WORKSPACE_DIR.to_string())
 }
 
+/// Get the size limit for a single workspace; None means unlimited
+pub fn workspace_quota_bytes() -> Option<u64> {
+    env::var(env_vars::WORKSPACE_QUOTA_MB)
+        .ok()
+        .and_then(|v| v.parse().ok())
+        .filter(|&mb: &u64| mb > 0)
+        .map(|mb| mb * 1024 * 1024)
+}
+
+/// Get the size limit for the files sent in one upload
+pub fn upload_max_bytes() -> u64 {
+    megabytes(env_vars::UPLOAD_MAX_MB, defaults::UPLOAD_MAX_MB)
+}
+
+/// Get the size limit for one upload once its archives are extracted
+pub fn upload_max_extracted_bytes() -> u64 {
+    megabytes(env_vars::UPLOAD_MAX_EXTRACTED_MB, defaults::UPLOAD_MAX_EXTRACTED_MB)
+}
+
+/// Get the free space the workspace volume needs for the server to be ready
+pub fn min_free_disk_bytes() -> u64 {
+    megabytes(env_vars::MIN_FREE_DISK_MB, defaults::MIN_FREE_DISK_MB)
+}
+
+/// Get how long an AI provider reachability check is reused
+pub fn provider_check_ttl() -> Duration {
+    let secs = env::var(env_vars::PROVIDER_CHECK_TTL_SECS)
+        .ok()
+        .and_then(|v| v.parse().ok())
+        .unwrap_or(defaults::PROVIDER_CHECK_TTL_SECS);
+    Duration::from_secs(secs)
+}
+
+fn megabytes(var: &str, default: u64) -> u64 {
+    env::var(var)
+        .ok()
+        .and_then(|v| v.parse().ok())
+        .filter(|&mb: &u64| mb > 0)
+        .unwrap_or(default)
+        * 1024
+        * 1024
+}
+
 /// Get the skills directory from environment or default
 pub fn skills_dir() -> String {
     env::var(env_vars::SKILLS_DIR).unwrap_or_else(|_| defaults::SKILLS_DIR.to_string())
 }
 
+/// Get the skill marketplace git repository URL, if one is configured
+pub fn skills_git_url() -> Option<String> {
+    env::var(env_vars::SKILLS_GIT_URL).ok().filter(|v| !v.trim().is_empty())
+}
+
+/// Get the branch of the skill marketplace repository to sync
+pub fn skills_git_branch() -> String {
+    env::var(env_vars::SKILLS_GIT_BRANCH)
+        .ok()
+        .filter(|v| !v.trim().is_empty())
+        .unwrap_or_else(|| defaults::SKILLS_GIT_BRANCH.to_string())
+}
+
+/// Get how often to sync the skill marketplace repository; None disables scheduled syncs
+pub fn skills_sync_interval() -> Option<Duration> {
+    env::var(env_vars::SKILLS_SYNC_INTERVAL_MINUTES)
+        .ok()
+        .and_then(|v| v.parse().ok())
+        .filter(|&m: &u64| m > 0)
+        .map(|m| Duration::from_secs(m * 60))
+}
+
 /// Get the journal directory from environment or default
 pub fn journal_dir() -> String {
     env::var(env_vars::JOURNAL_DIR).unwrap_or_else(|_| defaults::JOURNAL_DIR.to_string())
 }
 
+/// Get the database URL from environment or default
+pub fn database_url() -> String {
+    env::var(env_vars::DATABASE_URL).unwrap_or_else(|_| defaults::DATABASE_URL.to_string())
+}
+
+/// Get the local SQLite path used when DATABASE_URL points at Postgres
+pub fn sqlite_path() -> String {
+    env::var(env_vars::SQLITE_PATH).unwrap_or_else(|_| defaults::SQLITE_PATH.to_string())
+}
+
+/// Get the number of SQLite connections to pool
+pub fn db_pool_size() -> u32 {
+    env::var(env_vars::DB_POOL_SIZE)
+        .ok()
+        .and_then(|v| v.parse().ok())
+        .filter(|&n: &u32| n > 0)
+        .unwrap_or(defaults::DB_POOL_SIZE)
+}
+
+/// Get the master key for encrypting stored API keys, from the environment or a key file
+pub fn master_key() -> std::io::Result<Option<String>> {
+    if let Ok(key) = env::var(env_vars::MASTER_KEY) {
+        return Ok(Some(key));
+    }
+    match env::var(env_vars::MASTER_KEY_FILE) {
+        Ok(path) => std::fs::read_to_string(path).map(Some),
+        Err(_) => Ok(None),
+    }
+}
+
+/// Get retired master keys that may still be needed to decrypt stored API keys
+pub fn previous_master_keys() -> Vec<String> {
+    env::var(env_vars::MASTER_KEY_PREVIOUS)
+        .map(|v| {
+            v.split(',')
+                .map(str::trim)
+                .filter(|k| !k.is_empty())
+                .map(str::to_string)
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+/// Get the number of Postgres connections to keep open
+pub fn postgres_pool_size() -> usize {
+    env::var(env_vars::POSTGRES_POOL_SIZE)
+        .ok()
+        .and_then(|v| v.parse().ok())
+        .filter(|&n: &usize| n > 0)
+        .unwrap_or(defaults::POSTGRES_POOL_SIZE)
+}
+
+/// Get the `anthropic-version` header value from environment or default
+pub fn anthropic_version() -> String {
+    env::var(env_vars::ANTHROPIC_VERSION).unwrap_or_else(|_| defaults::ANTHROPIC_VERSION.to_string())
+}
+
+/// Get the comma-separated `anthropic-beta` flags from environment, if any
+pub fn anthropic_beta() -> Option<String> {
+    env::var(env_vars::ANTHROPIC_BETA).ok()
+}
+
+/// Whether to trust proxy headers for the client IP (rate limiting)
+pub fn trust_proxy() -> bool {
+    env::var(env_vars::TRUST_PROXY)
+        .map(|v| v == "true" || v == "1")
+        .unwrap_or(false)
+}
+
+/// Get the URL finished spans are posted to; None disables trace export
+pub fn otlp_traces_endpoint() -> Option<String> {
+    let non_empty = |var| env::var(var).ok().map(|v| v.trim().to_string()).filter(|v| !v.is_empty());
+    non_empty(env_vars::OTLP_TRACES_ENDPOINT).or_else(|| {
+        non_empty(env_vars::OTLP_ENDPOINT).map(|base| format!("{}/v1/traces", base.trim_end_matches('/')))
+    })
+}
+
+/// Get the service name traces are reported under
+pub fn otel_service_name() -> String {
+    env::var(env_vars::OTEL_SERVICE_NAME).unwrap_or_else(|_| defaults::OTEL_SERVICE_NAME.to_string())
+}
+
 /// Get the burner wallet private key from environment (for tools)
 pub fn burner_wallet_private_key() -> Option<String> {
     env::var(env_vars::BURNER_WALLET_PRIVATE_KEY).ok()
@@ -53,6 +602,7 @@ pub fn burner_wallet_private_key() -> Option<String> {
 pub struct Config {
     pub login_admin_public_address: String,
     pub burner_wallet_private_key: Option<String>,
+    pub host: String,
     pub port: u16,
     pub database_url: String,
 }
@@ -63,12 +613,12 @@ impl Config {
             login_admin_public_address: env::var(env_vars::LOGIN_ADMIN_PUBLIC_ADDRESS)
                 .expect("LOGIN_ADMIN_PUBLIC_ADDRESS must be set"),
             burner_wallet_private_key: env::var(env_vars::BURNER_WALLET_PRIVATE_KEY).ok(),
+            host: server_host(),
             port: env::var(env_vars::PORT)
                 .unwrap_or_else(|_| defaults::PORT.to_string())
                 .parse()
                 .expect("PORT must be a valid number"),
-            database_url: env::var(env_vars::DATABASE_URL)
-                .unwrap_or_else(|_| defaults::DATABASE_URL.to_string()),
+            database_url: database_url(),
         }
     }
 }
@@ -84,6 +634,10 @@ pub struct MemoryConfig {
     pub enable_vector_search: bool,
     /// Embedding provider: "openai", "local", or "none"
     pub embedding_provider: String,
+    /// Embedding model (None = the provider's default)
+    pub embedding_model: Option<String>,
+    /// Embeddings API key (None = reuse the configured AI provider's endpoint and key)
+    pub embedding_api_key: Option<String>,
     /// Enable automatic memory consolidation
     pub enable_auto_consolidation: bool,
     /// Enable cross-session memory sharing (same identity across channels)
@@ -99,6 +653,8 @@ impl Default for MemoryConfig {
             enable_entity_extraction: true,
             enable_vector_search: false,
             embedding_provider: "none".to_string(),
+            embedding_model: None,
+            embedding_api_key: None,
             enable_auto_consolidation: false,
             enable_cross_session_memory: true,
             cross_session_memory_limit: 5,
@@ -120,6 +676,8 @@ impl MemoryConfig {
                 .unwrap_or(false),
             embedding_provider: env::var(env_vars::MEMORY_EMBEDDING_PROVIDER)
                 .unwrap_or_else(|_| "none".to_string()),
+            embedding_model: env::var(env_vars::MEMORY_EMBEDDING_MODEL).ok().filter(|v| !v.is_empty()),
+            embedding_api_key: env::var(env_vars::MEMORY_EMBEDDING_API_KEY).ok().filter(|v| !v.is_empty()),
             enable_auto_consolidation: env::var(env_vars::MEMORY_ENABLE_AUTO_CONSOLIDATION)
                 .map(|v| v == "true" || v == "1")
                 .unwrap_or(false),
@@ -139,6 +697,58 @@ pub fn memory_config() -> MemoryConfig {
     MemoryConfig::from_env()
 }
 
+/// Spending ceiling applied to a single chat request across all of its provider calls
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct BudgetConfig {
+    /// Maximum input + output tokens per request (None = unlimited)
+    pub max_tokens: Option<u64>,
+    /// Maximum estimated spend in USD per request (None = unlimited)
+    pub max_cost_usd: Option<f64>,
+    /// Price used to estimate spend, per million input tokens
+    pub usd_per_million_input_tokens: f64,
+    /// Price used to estimate spend, per million output tokens
+    pub usd_per_million_output_tokens: f64,
+}
+
+impl BudgetConfig {
+    pub fn from_env() -> Self {
+        Self {
+            max_tokens: env::var(env_vars::CHAT_BUDGET_MAX_TOKENS)
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .filter(|&t| t > 0),
+            max_cost_usd: env::var(env_vars::CHAT_BUDGET_MAX_USD)
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .filter(|&usd: &f64| usd.is_finite() && usd > 0.0),
+            usd_per_million_input_tokens: env::var(env_vars::CHAT_BUDGET_USD_PER_MTOK_INPUT)
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(defaults::CHAT_BUDGET_USD_PER_MTOK_INPUT),
+            usd_per_million_output_tokens: env::var(env_vars::CHAT_BUDGET_USD_PER_MTOK_OUTPUT)
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(defaults::CHAT_BUDGET_USD_PER_MTOK_OUTPUT),
+        }
+    }
+
+    /// Apply the active agent profile's overrides on top of the global budget
+    pub fn with_overrides(mut self, max_tokens: Option<i64>, max_cost_usd: Option<f64>) -> Self {
+        if let Some(tokens) = max_tokens.filter(|&t| t > 0) {
+            self.max_tokens = Some(tokens as u64);
+        }
+        if let Some(usd) = max_cost_usd.filter(|&usd| usd.is_finite() && usd > 0.0) {
+            self.max_cost_usd = Some(usd);
+        }
+        self
+    }
+}
+
+/// Get the global chat budget configuration
+pub fn budget_config() -> BudgetConfig {
+    BudgetConfig::from_env()
+}
+
 /// Get the path to SOUL.md in the workspace
 pub fn soul_document_path() -> PathBuf {
     PathBuf::from(workspace_dir()).join("SOUL.md")
@@ -184,3 +794,48 @@ pub fn initialize_workspace() -> std::io::Result<()> {
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_config_file_formats() {
+        let toml = r#"
+            [server]
+            host = "127.0.0.1"
+            port = 9000
+            cors_origins = ["https://app.example.com", "http://localhost:5173"]
+            cors_methods = ["GET", "POST"]
+
+            [database]
+            url = "/var/lib/stark/stark.db"
+
+            [tools]
+            disabled_groups = ["exec", "dev"]
+        "#;
+        let ron = r#"(
+            server: (host: Some("127.0.0.1"), port: Some(9000), cors_origins: Some(["https://app.example.com", "http://localhost:5173"]), cors_methods: Some(["GET", "POST"])),
+            database: (url: Some("/var/lib/stark/stark.db")),
+            tools: (disabled_groups: Some(["exec", "dev"])),
+        )"#;
+        let from_toml = ConfigFile::parse(Path::new("config.toml"), toml).unwrap();
+        assert_eq!(from_toml, ConfigFile::parse(Path::new("config.ron"), ron).unwrap());
+
+        let vars = from_toml.env_vars();
+        assert!(vars.contains(&(env_vars::PORT, "9000".to_string())));
+        assert!(vars.contains(&(env_vars::CORS_ORIGINS, "https://app.example.com,http://localhost:5173".to_string())));
+        assert!(vars.contains(&(env_vars::CORS_METHODS, "GET,POST".to_string())));
+        assert!(vars.contains(&(env_vars::DISABLED_TOOL_GROUPS, "exec,dev".to_string())));
+        assert!(!vars.iter().any(|(var, _)| *var == env_vars::DEFAULT_MODEL));
+
+        assert!(ConfigFile::parse(Path::new("config.toml"), "[server]\nprot = 1").is_err());
+        assert!(ConfigFile::parse(Path::new("config.yaml"), "").is_err());
+
+        assert!(is_valid_origin("https://app.example.com"));
+        assert!(is_valid_origin("http://localhost:5173"));
+        assert!(!is_valid_origin("https://app.example.com/"));
+        assert!(!is_valid_origin("app.example.com"));
+        assert!(!is_valid_origin("ftp://example.com"));
+    }
+}
@@ -5,6 +5,13 @@
 //! - Context compaction (summarizing old messages when context grows too large)
 //! - Pre-compaction memory flush (AI extracts memories before summarization)
 //! - Session memory hooks (saving session summaries on reset)
+//! - Working-context compaction inside the agent loop ([`window`])
+
+pub mod tokenizer;
+pub mod window;
+
+pub use tokenizer::count_tokens;
+pub use window::ContextWindow;
 
 use crate::ai::{AiClient, Message, MessageRole};
 use crate::config::MemoryConfig;
@@ -27,13 +34,9 @@ pub const MIN_KEEP_RECENT_MESSAGES: i32 = 5;
 /// Default number of messages to keep after compaction
 pub const DEFAULT_KEEP_RECENT_MESSAGES: i32 = 10;
 
-/// Estimate token count for a string
-/// Uses a simple heuristic: ~4 characters per token for English text
-/// This is a rough approximation - actual tokenization varies by model
+/// Estimate token count for a string (see [`tokenizer`])
 pub fn estimate_tokens(text: &str) -> i32 {
-    // Average ~4 chars per token, but account for whitespace and punctuation
-    let chars = text.chars().count();
-    ((chars as f64) / 3.5).ceil() as i32
+    count_tokens(text) as i32
 }
 
 /// Estimate total tokens for a list of messages
@@ -91,8 +94,8 @@ impl ContextManager {
     }
 
     /// Check if compaction is needed for a session
-    pub fn needs_compaction(&self, session_id: i64) -> bool {
-        if let Ok(session) = self.db.get_chat_session(session_id) {
+    pub async fn needs_compaction(&self, session_id: i64) -> bool {
+        if let Ok(session) = self.db.get_chat_session(session_id).await {
             if let Some(session) = session {
                 let threshold = session.max_context_tokens - self.reserve_tokens;
                 return session.context_tokens > threshold;
@@ -102,25 +105,25 @@ impl ContextManager {
     }
 
     /// Get available context budget (after reserving tokens)
-    pub fn get_context_budget(&self, session_id: i64) -> i32 {
-        if let Ok(Some(session)) = self.db.get_chat_session(session_id) {
+    pub async fn get_context_budget(&self, session_id: i64) -> i32 {
+        if let Ok(Some(session)) = self.db.get_chat_session(session_id).await {
             return session.max_context_tokens - self.reserve_tokens - session.context_tokens;
         }
         self.max_context_tokens - self.reserve_tokens
     }
 
     /// Build conversation context for AI, including compaction summary if present
-    pub fn build_context(&self, session_id: i64, limit: i32) -> Vec<SessionMessage> {
+    pub async fn build_context(&self, session_id: i64, limit: i32) -> Vec<SessionMessage> {
         // Get recent messages
-        let messages = self.db.get_recent_session_messages(session_id, limit)
+        let messages = self.db.get_recent_session_messages(session_id, limit).await
             .unwrap_or_default();
 
         messages
     }
 
     /// Get compaction summary for a session (if any)
-    pub fn get_compaction_summary(&self, session_id: i64) -> Option<String> {
-        self.db.get_session_compaction_summary(session_id).ok().flatten()
+    pub async fn get_compaction_summary(&self, session_id: i64) -> Option<String> {
+        self.db.get_session_compaction_summary(session_id).await.ok().flatten()
     }
 
     /// Phase 1: Flush memories before compaction
@@ -175,10 +178,12 @@ impl ContextManager {
             Message {
                 role: MessageRole::System,
                 content: "You are a memory extraction assistant. Analyze conversations and extract important information that should be preserved as long-term memories.".to_string(),
+                images: Vec::new(),
             },
             Message {
                 role: MessageRole::User,
                 content: flush_prompt,
+                images: Vec::new(),
             },
         ];
 
@@ -195,12 +200,12 @@ impl ContextManager {
             &response,
             identity_id,
             session_id,
-        )?;
+        ).await?;
 
         log::info!("[PRE_FLUSH] Extracted {} memories for session {}", created_ids.len(), session_id);
 
         // Update last_flush_at timestamp
-        if let Err(e) = self.db.update_session_last_flush(session_id) {
+        if let Err(e) = self.db.update_session_last_flush(session_id).await {
             log::warn!("[PRE_FLUSH] Failed to update last_flush_at: {}", e);
         }
 
@@ -208,7 +213,7 @@ impl ContextManager {
     }
 
     /// Parse memory markers from flush response and create memories
-    fn parse_and_create_flush_memories(
+    async fn parse_and_create_flush_memories(
         &self,
         response: &str,
         identity_id: Option<&str>,
@@ -250,7 +255,7 @@ impl ContextManager {
                             None, // valid_from
                             None, // valid_until
                             None, // temporal_type
-                        ) {
+                        ).await {
                             Ok(memory) => {
                                 log::info!("[PRE_FLUSH] Created {} memory: {}", memory_type.as_str(), content_str);
                                 created_ids.push(memory.id);
@@ -276,7 +281,7 @@ impl ContextManager {
         identity_id: Option<&str>,
     ) -> Result<i32, String> {
         // Get messages to compact (all except recent ones)
-        let messages_to_compact = self.db.get_messages_for_compaction(session_id, self.keep_recent_messages)
+        let messages_to_compact = self.db.get_messages_for_compaction(session_id, self.keep_recent_messages).await
             .map_err(|e| format!("Failed to get messages for compaction: {}", e))?;
 
         if messages_to_compact.is_empty() {
@@ -334,10 +339,12 @@ impl ContextManager {
             Message {
                 role: MessageRole::System,
                 content: "You are a helpful assistant that summarizes conversations accurately and concisely.".to_string(),
+                images: Vec::new(),
             },
             Message {
                 role: MessageRole::User,
                 content: summary_prompt,
+                images: Vec::new(),
             },
         ];
 
@@ -359,32 +366,32 @@ impl ContextManager {
             None,
             None,
             None,
-        ).map_err(|e| format!("Failed to store compaction memory: {}", e))?;
+        ).await.map_err(|e| format!("Failed to store compaction memory: {}", e))?;
 
         // Update session with compaction reference
-        self.db.set_session_compaction(session_id, compaction_memory.id)
+        self.db.set_session_compaction(session_id, compaction_memory.id).await
             .map_err(|e| format!("Failed to update session compaction: {}", e))?;
 
         // Delete the compacted messages
-        let deleted = self.db.delete_compacted_messages(session_id, self.keep_recent_messages)
+        let deleted = self.db.delete_compacted_messages(session_id, self.keep_recent_messages).await
             .map_err(|e| format!("Failed to delete compacted messages: {}", e))?;
 
         log::info!("[COMPACTION] Deleted {} old messages for session {}", deleted, session_id);
 
         // Recalculate and update context tokens
-        let remaining = self.db.get_session_messages(session_id).unwrap_or_default();
+        let remaining = self.db.get_session_messages(session_id).await.unwrap_or_default();
         let new_token_count = estimate_messages_tokens(&remaining) + estimate_tokens(&summary);
-        self.db.update_session_context_tokens(session_id, new_token_count)
+        self.db.update_session_context_tokens(session_id, new_token_count).await
             .map_err(|e| format!("Failed to update context tokens: {}", e))?;
 
         Ok(message_count)
     }
 
     /// Update context tokens after adding a message
-    pub fn update_context_tokens(&self, session_id: i64, message_tokens: i32) {
-        if let Ok(Some(session)) = self.db.get_chat_session(session_id) {
+    pub async fn update_context_tokens(&self, session_id: i64, message_tokens: i32) {
+        if let Ok(Some(session)) = self.db.get_chat_session(session_id).await {
             let new_total = session.context_tokens + message_tokens;
-            let _ = self.db.update_session_context_tokens(session_id, new_total);
+            let _ = self.db.update_session_context_tokens(session_id, new_total).await;
         }
     }
 }
@@ -398,7 +405,7 @@ pub async fn save_session_memory(
     message_limit: i32,
 ) -> Result<i64, String> {
     // Get recent messages from the session
-    let messages = db.get_recent_session_messages(session_id, message_limit)
+    let messages = db.get_recent_session_messages(session_id, message_limit).await
         .map_err(|e| format!("Failed to get session messages: {}", e))?;
 
     if messages.is_empty() {
@@ -438,10 +445,12 @@ pub async fn save_session_memory(
         Message {
             role: MessageRole::System,
             content: "You summarize conversations concisely. Respond only with the requested TITLE and SUMMARY format.".to_string(),
+            images: Vec::new(),
         },
         Message {
             role: MessageRole::User,
             content: summary_prompt,
+            images: Vec::new(),
         },
     ];
 
@@ -467,7 +476,7 @@ pub async fn save_session_memory(
         None,
         Some(today),
         None,
-    ).map_err(|e| format!("Failed to create session memory: {}", e))?;
+    ).await.map_err(|e| format!("Failed to create session memory: {}", e))?;
 
     log::info!("[SESSION_MEMORY] Created session summary: {} (id={})", title, memory.id);
 
@@ -0,0 +1,71 @@
+//! Token counting
+//!
+//! Splits text the way BPE tokenizers (cl100k/o200k style) pre-tokenize it:
+//! words with their leading space, digit groups of up to three, punctuation
+//! runs and whitespace. Each piece is then priced by its shape. No vocabulary
+//! is shipped, so counts are approximate, but unlike a flat characters-per-token
+//! ratio they hold up on code, JSON and numbers, which is most of what tool
+//! output is made of.
+
+use once_cell::sync::Lazy;
+use regex::Regex;
+
+/// Pre-tokenization pattern (cl100k's, minus the look-ahead the regex crate lacks)
+static PIECES: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(
+        r"(?i:'s|'t|'re|'ve|'m|'ll|'d)|[^\r\n\p{L}\p{N}]?\p{L}+|\p{N}{1,3}| ?[^\s\p{L}\p{N}]+[\r\n]*|\s*[\r\n]+|\s+",
+    )
+    .unwrap()
+});
+
+/// ASCII letters a single word token typically covers before BPE splits it
+const WORD_CHARS_PER_TOKEN: usize = 6;
+
+/// Spaces a single whitespace token typically covers (indentation)
+const SPACES_PER_TOKEN: usize = 8;
+
+/// Count the tokens `text` encodes to
+pub fn count_tokens(text: &str) -> usize {
+    PIECES.find_iter(text).map(|m| piece_tokens(m.as_str())).sum()
+}
+
+fn piece_tokens(piece: &str) -> usize {
+    if piece.chars().any(char::is_alphabetic) {
+        // Leading space or punctuation merges into the word
+        let word = piece.trim_start_matches(|c: char| !c.is_alphabetic());
+        let ascii = word.chars().filter(char::is_ascii).count();
+        // Outside ASCII, BPE vocabularies rarely get more than a character per token
+        let other = word.chars().count() - ascii;
+        return ascii.div_ceil(WORD_CHARS_PER_TOKEN) + other;
+    }
+    if piece.chars().all(char::is_numeric) {
+        return 1;
+    }
+    if piece.chars().all(char::is_whitespace) {
+        return if piece.contains('\n') { 1 } else { piece.len().div_ceil(SPACES_PER_TOKEN) };
+    }
+    // Punctuation runs: common pairs like `()`, `",` or `});` are single tokens
+    let punctuation = piece.trim().chars().count();
+    punctuation.div_ceil(2).max(1)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_count_tokens() {
+        assert_eq!(count_tokens(""), 0);
+        assert_eq!(count_tokens("hello world"), 2);
+        // Digits group in threes
+        assert_eq!(count_tokens("12345678"), 3);
+        assert_eq!(count_tokens("fn main() {}"), 4);
+        assert_eq!(count_tokens("你好"), 2);
+        // Long identifiers split into several tokens
+        assert!(count_tokens("internationalization") > 1);
+
+        let json = r#"{"path": "src/main.rs", "start_line": 120}"#;
+        let tokens = count_tokens(json);
+        assert!((10..=25).contains(&tokens), "{} tokens", tokens);
+    }
+}
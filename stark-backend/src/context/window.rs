@@ -0,0 +1,488 @@
+//! Working-context compaction inside the agent loop
+//!
+//! `ContextManager` compacts a session's stored transcript between requests.
+//! Within one request the tool loop keeps its own working context: the
+//! conversation it sends, plus the calls and results of every tool round. A
+//! long CodeEngineer run can outgrow the model's window well before the request
+//! ends. Before each provider call, [`ContextWindow::compact`] brings that
+//! context back under budget, giving up the least useful content first:
+//!
+//! 1. Stale file contents: a `read_file` result superseded by a later identical
+//!    read, or by a write, edit, patch, rename or delete of the same file
+//! 2. Old tool results and messages, condensed to their first lines
+//! 3. The oldest tool rounds and messages, dropped. A note lists the tools that ran
+
+use std::collections::HashSet;
+
+use super::tokenizer::count_tokens;
+use super::{DEFAULT_MAX_CONTEXT_TOKENS, DEFAULT_RESERVE_TOKENS};
+use crate::ai::types::{ThinkingBlock, ToolCall, ToolHistoryEntry, ToolResponse};
+use crate::ai::{Message, MessageRole};
+use crate::models::AgentSettings;
+use crate::tools::ToolDefinition;
+
+/// Smallest `max_context_tokens` agent settings accept
+pub const MIN_CONTEXT_WINDOW_TOKENS: i64 = 8_000;
+
+/// Largest `max_context_tokens` agent settings accept
+pub const MAX_CONTEXT_WINDOW_TOKENS: i64 = 2_000_000;
+
+/// Tool rounds at the end of the history that are never condensed or dropped
+const KEEP_RECENT_ROUNDS: usize = 3;
+
+/// Messages at the end of the conversation that are never condensed or dropped
+const KEEP_RECENT_MESSAGES: usize = 6;
+
+/// Tokens an old tool result or message is condensed to
+const CONDENSED_TOKENS: usize = 200;
+
+/// Framing (role, ids, separators) each message or tool call adds on top of its content
+const MESSAGE_OVERHEAD_TOKENS: usize = 4;
+
+/// Most dropped tool calls listed by name in the compaction note
+const MAX_NOTED_CALLS: usize = 40;
+
+/// Id of the tool round that records what compaction dropped
+const COMPACTION_NOTE_ID: &str = "context_compaction";
+
+const STALE_PREFIX: &str = "[Stale file contents removed:";
+
+/// What one compaction pass did
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct CompactionReport {
+    pub tokens_before: usize,
+    pub tokens_after: usize,
+    /// `read_file` results replaced because the file was read again or changed
+    pub stale_results: usize,
+    /// Tool results and messages cut down to their first lines
+    pub condensed: usize,
+    /// Tool rounds and messages removed entirely
+    pub dropped: usize,
+}
+
+/// Rolling token budget for an agent run's working context
+#[derive(Debug, Clone, Copy)]
+pub struct ContextWindow {
+    /// Tokens the context may take up, after leaving room for the reply
+    budget: usize,
+}
+
+impl ContextWindow {
+    /// Window of `max_context_tokens`, part of which is reserved for the reply
+    pub fn new(max_context_tokens: usize) -> Self {
+        let reserve = (DEFAULT_RESERVE_TOKENS as usize).min(max_context_tokens / 4);
+        Self {
+            budget: max_context_tokens - reserve,
+        }
+    }
+
+    /// Window configured by the agent settings' `max_context_tokens`
+    pub fn from_settings(settings: &AgentSettings) -> Self {
+        let max = settings
+            .max_context_tokens
+            .filter(|&t| t > 0)
+            .unwrap_or(DEFAULT_MAX_CONTEXT_TOKENS as i64);
+        Self::new(max as usize)
+    }
+
+    pub fn budget(&self) -> usize {
+        self.budget
+    }
+
+    /// Tokens a provider call with this context would send
+    pub fn measure(conversation: &[Message], tool_history: &[ToolHistoryEntry], tools: &[ToolDefinition]) -> usize {
+        let messages: usize = conversation.iter().map(|m| text_tokens(&m.content)).sum();
+        let rounds: usize = tool_history.iter().map(round_tokens).sum();
+        let tools = serde_json::to_string(tools).map(|s| count_tokens(&s)).unwrap_or(0);
+        messages + rounds + tools
+    }
+
+    /// Bring the context under budget. `request_index` is the position of the
+    /// user's request in `conversation`; it is never removed and is updated when
+    /// earlier messages are. Returns what was done, or None if it already fit.
+    pub fn compact(
+        &self,
+        conversation: &mut Vec<Message>,
+        request_index: &mut usize,
+        tool_history: &mut Vec<ToolHistoryEntry>,
+        tools: &[ToolDefinition],
+    ) -> Option<CompactionReport> {
+        let tokens_before = Self::measure(conversation, tool_history, tools);
+        if tokens_before <= self.budget {
+            return None;
+        }
+
+        let mut report = CompactionReport {
+            tokens_before,
+            ..Default::default()
+        };
+        report.stale_results = drop_stale_file_contents(tool_history);
+        let mut total = Self::measure(conversation, tool_history, tools);
+
+        // Condense, oldest first
+        let old_rounds = tool_history.len().saturating_sub(KEEP_RECENT_ROUNDS);
+        for response in tool_history[..old_rounds]
+            .iter_mut()
+            .filter(|entry| !is_compaction_note(Some(entry)))
+            .flat_map(|entry| entry.tool_responses.iter_mut())
+        {
+            if total <= self.budget {
+                break;
+            }
+            if let Some(condensed) = condense(&response.content) {
+                total -= text_tokens(&response.content) - text_tokens(&condensed);
+                response.content = condensed;
+                report.condensed += 1;
+            }
+        }
+        let old_messages: Vec<usize> = droppable_messages(conversation, *request_index).collect();
+        for index in old_messages {
+            if total <= self.budget {
+                break;
+            }
+            if let Some(condensed) = condense(&conversation[index].content) {
+                total -= text_tokens(&conversation[index].content) - text_tokens(&condensed);
+                conversation[index].content = condensed;
+                report.condensed += 1;
+            }
+        }
+
+        // Drop, oldest first: tool rounds, then messages
+        let mut dropped_calls: Vec<ToolCall> = Vec::new();
+        let first_round = usize::from(is_compaction_note(tool_history.first()));
+        while total > self.budget && tool_history.len() > first_round + KEEP_RECENT_ROUNDS {
+            let entry = tool_history.remove(first_round);
+            total -= round_tokens(&entry);
+            dropped_calls.extend(entry.tool_calls);
+            report.dropped += 1;
+        }
+        while total > self.budget {
+            let Some(index) = droppable_messages(conversation, *request_index).next() else {
+                break;
+            };
+            let message = conversation.remove(index);
+            total -= text_tokens(&message.content);
+            if index < *request_index {
+                *request_index -= 1;
+            }
+            report.dropped += 1;
+        }
+        if !dropped_calls.is_empty() {
+            note_dropped_calls(tool_history, &dropped_calls);
+        }
+
+        report.tokens_after = Self::measure(conversation, tool_history, tools);
+        Some(report)
+    }
+}
+
+fn text_tokens(text: &str) -> usize {
+    count_tokens(text) + MESSAGE_OVERHEAD_TOKENS
+}
+
+fn round_tokens(entry: &ToolHistoryEntry) -> usize {
+    let calls: usize = entry
+        .tool_calls
+        .iter()
+        .map(|c| count_tokens(&c.name) + text_tokens(&c.arguments.to_string()))
+        .sum();
+    let responses: usize = entry.tool_responses.iter().map(|r| text_tokens(&r.content)).sum();
+    let thinking: usize = entry
+        .thinking
+        .iter()
+        .map(|t| match t {
+            ThinkingBlock::Thinking { thinking, .. } => count_tokens(thinking),
+            ThinkingBlock::RedactedThinking { data } => count_tokens(data),
+        })
+        .sum();
+    calls + responses + count_tokens(&entry.content) + thinking
+}
+
+/// Messages that may be condensed or dropped, oldest first: everything except
+/// system prompts, the request and the most recent messages
+fn droppable_messages(conversation: &[Message], request_index: usize) -> impl Iterator<Item = usize> + '_ {
+    let recent_start = conversation.len().saturating_sub(KEEP_RECENT_MESSAGES);
+    (0..recent_start).filter(move |&i| i != request_index && conversation[i].role != MessageRole::System)
+}
+
+/// First lines of `text` up to `CONDENSED_TOKENS`, if it is long enough to be worth it
+fn condense(text: &str) -> Option<String> {
+    if count_tokens(text) <= CONDENSED_TOKENS * 2 {
+        return None;
+    }
+
+    let total_lines = text.lines().count();
+    let mut kept = String::new();
+    let mut kept_lines = 0;
+    let mut tokens = 0;
+    for line in text.lines() {
+        tokens += count_tokens(line) + 1;
+        if tokens > CONDENSED_TOKENS {
+            break;
+        }
+        kept.push_str(line);
+        kept.push('\n');
+        kept_lines += 1;
+    }
+    if kept_lines == 0 {
+        // A single huge line (minified JSON, base64...)
+        kept = text.chars().take(CONDENSED_TOKENS * 3).collect();
+        return Some(format!("{}\n[... condensed to save context: output cut after {} characters ...]", kept, CONDENSED_TOKENS * 3));
+    }
+    Some(format!(
+        "{}[... condensed to save context: {} of {} lines omitted ...]",
+        kept,
+        total_lines - kept_lines,
+        total_lines
+    ))
+}
+
+/// Replace `read_file` results that a later call superseded. Returns how many were replaced.
+fn drop_stale_file_contents(tool_history: &mut [ToolHistoryEntry]) -> usize {
+    let mut changed_later: HashSet<String> = HashSet::new();
+    let mut read_later: HashSet<String> = HashSet::new();
+    let mut replaced = 0;
+
+    // Walk backwards so every call is checked against the ones that came after it
+    for entry in tool_history.iter_mut().rev() {
+        let ToolHistoryEntry { tool_calls, tool_responses, .. } = entry;
+        for call in tool_calls.iter().rev() {
+            if call.name != "read_file" {
+                changed_later.extend(changed_paths(call));
+                continue;
+            }
+            let Some(path) = call.arguments.get("path").and_then(|p| p.as_str()).map(normalize_path) else {
+                continue;
+            };
+            // Same file and range means the same contents
+            let mut read = call.arguments.clone();
+            read["path"] = path.clone().into();
+            let read = read.to_string();
+            if (changed_later.contains(&path) || read_later.contains(&read))
+                && let Some(response) = tool_responses.iter_mut().find(|r| r.tool_call_id == call.id)
+                && !response.content.starts_with(STALE_PREFIX)
+            {
+                response.content = format!(
+                    "{} `{}` was read again or changed later in this run; see the newer result]",
+                    STALE_PREFIX, path
+                );
+                replaced += 1;
+            }
+            read_later.insert(read);
+        }
+    }
+    replaced
+}
+
+/// Files a tool call writes, edits, moves or deletes
+fn changed_paths(call: &ToolCall) -> Vec<String> {
+    let arg = |name: &str| call.arguments.get(name).and_then(|v| v.as_str()).map(normalize_path);
+    match call.name.as_str() {
+        "write_file" | "edit_file" | "delete_file" => arg("path").into_iter().collect(),
+        "rename_file" => arg("source").into_iter().chain(arg("destination")).collect(),
+        "apply_patch" => call
+            .arguments
+            .get("patch")
+            .and_then(|p| p.as_str())
+            .unwrap_or_default()
+            .lines()
+            .filter_map(|line| {
+                ["*** Update File:", "*** Add File:", "*** Delete File:", "*** Move to:"]
+                    .iter()
+                    .find_map(|marker| line.strip_prefix(marker))
+            })
+            .map(normalize_path)
+            .collect(),
+        _ => Vec::new(),
+    }
+}
+
+fn normalize_path(path: &str) -> String {
+    path.trim().trim_start_matches("./").to_string()
+}
+
+fn is_compaction_note(entry: Option<&ToolHistoryEntry>) -> bool {
+    entry.is_some_and(|e| e.tool_calls.first().is_some_and(|c| c.id == COMPACTION_NOTE_ID))
+}
+
+/// Record dropped tool calls in the note at the start of the history, creating it if needed
+fn note_dropped_calls(tool_history: &mut Vec<ToolHistoryEntry>, dropped: &[ToolCall]) {
+    if !is_compaction_note(tool_history.first()) {
+        tool_history.insert(
+            0,
+            ToolHistoryEntry::new(
+                vec![ToolCall {
+                    id: COMPACTION_NOTE_ID.to_string(),
+                    name: "system_feedback".to_string(),
+                    arguments: serde_json::json!({"type": "context_compaction"}),
+                }],
+                vec![ToolResponse {
+                    tool_call_id: COMPACTION_NOTE_ID.to_string(),
+                    content: "CONTEXT COMPACTED: older tool rounds were removed to stay within the context window. \
+                              Calls made before this point:"
+                        .to_string(),
+                    is_error: false,
+                }],
+            ),
+        );
+    }
+
+    let note = &mut tool_history[0].tool_responses[0].content;
+    let listed = note.lines().filter(|l| l.starts_with("- ")).count();
+    for call in dropped.iter().take(MAX_NOTED_CALLS.saturating_sub(listed)) {
+        let target = call
+            .arguments
+            .get("path")
+            .or_else(|| call.arguments.get("command"))
+            .or_else(|| call.arguments.get("url"))
+            .and_then(|v| v.as_str())
+            .map(|t| format!(" {}", t.chars().take(80).collect::<String>()))
+            .unwrap_or_default();
+        note.push_str(&format!("\n- {}{}", call.name, target));
+    }
+    let unlisted = (listed + dropped.len()).saturating_sub(MAX_NOTED_CALLS);
+    if unlisted > 0 {
+        // Keep a single running tally line at the end
+        if let Some(pos) = note.rfind("\n(and ") {
+            note.truncate(pos);
+        }
+        note.push_str(&format!("\n(and {} more)", unlisted));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn round(id: &str, name: &str, arguments: serde_json::Value, output: &str) -> ToolHistoryEntry {
+        ToolHistoryEntry::new(
+            vec![ToolCall {
+                id: id.to_string(),
+                name: name.to_string(),
+                arguments,
+            }],
+            vec![ToolResponse::success(id.to_string(), output.to_string())],
+        )
+    }
+
+    fn message(role: MessageRole, content: &str) -> Message {
+        Message {
+            role,
+            content: content.to_string(),
+            images: Vec::new(),
+        }
+    }
+
+    fn file_contents(lines: usize) -> String {
+        (0..lines)
+            .map(|i| format!("    let value_{} = compute(input, {});", i, i))
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+
+    #[test]
+    fn test_fits_without_changes() {
+        let window = ContextWindow::new(100_000);
+        let mut conversation = vec![message(MessageRole::System, "sys"), message(MessageRole::User, "hi")];
+        let mut history = vec![round("1", "read_file", json!({"path": "a.rs"}), "fn a() {}")];
+        assert!(window.compact(&mut conversation, &mut 1, &mut history, &[]).is_none());
+        assert_eq!(history[0].tool_responses[0].content, "fn a() {}");
+    }
+
+    #[test]
+    fn test_stale_reads_go_first() {
+        let big = file_contents(400);
+        let mut history = vec![
+            round("1", "read_file", json!({"path": "src/lib.rs"}), &big),
+            round("2", "read_file", json!({"path": "./src/main.rs"}), &big),
+            round("3", "edit_file", json!({"path": "src/lib.rs", "old_string": "a", "new_string": "b"}), "ok"),
+            round("4", "read_file", json!({"path": "src/main.rs"}), &big),
+        ];
+        let mut conversation = vec![message(MessageRole::System, "sys"), message(MessageRole::User, "fix it")];
+        let size = ContextWindow::measure(&conversation, &history, &[]);
+        // Room for one copy of the file, not three
+        let window = ContextWindow { budget: size / 2 };
+
+        let report = window.compact(&mut conversation, &mut 1, &mut history, &[]).unwrap();
+        assert_eq!(report.stale_results, 2);
+        assert_eq!(report.condensed + report.dropped, 0);
+        assert!(report.tokens_after <= window.budget());
+        assert!(history[0].tool_responses[0].content.starts_with(STALE_PREFIX));
+        assert!(history[1].tool_responses[0].content.contains("src/main.rs"));
+        assert_eq!(history[3].tool_responses[0].content, big);
+    }
+
+    #[test]
+    fn test_condenses_then_drops_old_rounds() {
+        let big = file_contents(400);
+        let mut history: Vec<ToolHistoryEntry> = (0..8)
+            .map(|i| round(&i.to_string(), "exec", json!({"command": format!("cargo test {}", i)}), &big))
+            .collect();
+        let mut conversation = vec![message(MessageRole::System, "sys"), message(MessageRole::User, "run the tests")];
+
+        // Condensing the five old rounds is enough
+        let size = ContextWindow::measure(&conversation, &history, &[]);
+        let window = ContextWindow { budget: size / 2 };
+        let report = window.compact(&mut conversation, &mut 1, &mut history.clone(), &[]).unwrap();
+        assert!(report.condensed > 0 && report.condensed <= 5);
+        assert_eq!(report.dropped, 0);
+        assert!(report.tokens_after <= window.budget());
+
+        // Only the three recent rounds fit: the rest are dropped and noted
+        let recent = ContextWindow::measure(&conversation, &history[5..], &[]);
+        let window = ContextWindow { budget: recent + 50 };
+        let report = window.compact(&mut conversation, &mut 1, &mut history, &[]).unwrap();
+        assert_eq!(report.dropped, 5);
+        assert_eq!(history.len(), 4);
+        let note = &history[0].tool_responses[0].content;
+        assert!(note.starts_with("CONTEXT COMPACTED"));
+        assert!(note.contains("- exec cargo test 0"));
+        assert_eq!(history[3].tool_calls[0].id, "7");
+        assert_eq!(history[3].tool_responses[0].content, big);
+    }
+
+    #[test]
+    fn test_drops_old_messages_but_keeps_request() {
+        let big = file_contents(400);
+        let mut conversation = vec![message(MessageRole::System, "sys")];
+        for _ in 0..4 {
+            conversation.push(message(MessageRole::User, "earlier question"));
+            conversation.push(message(MessageRole::Assistant, &big));
+        }
+        conversation.push(message(MessageRole::User, "the request"));
+        let mut request_index = conversation.len() - 1;
+        for _ in 0..6 {
+            conversation.push(message(MessageRole::Assistant, "{\"tool_name\": \"exec\"}"));
+        }
+
+        let window = ContextWindow { budget: 600 };
+        let report = window.compact(&mut conversation, &mut request_index, &mut Vec::new(), &[]).unwrap();
+        assert!(report.dropped > 0);
+        assert!(report.tokens_after <= window.budget());
+        assert_eq!(conversation[0].role, MessageRole::System);
+        assert_eq!(conversation[request_index].content, "the request");
+        assert_eq!(conversation.len(), request_index + 7);
+    }
+
+    #[test]
+    fn test_condense_keeps_leading_lines() {
+        assert!(condense("short output").is_none());
+        let condensed = condense(&file_contents(400)).unwrap();
+        assert!(condensed.starts_with("    let value_0 = compute(input, 0);\n"));
+        assert!(condensed.ends_with("of 400 lines omitted ...]"));
+        assert!(count_tokens(&condensed) <= CONDENSED_TOKENS + 30);
+        // Already condensed text is left alone
+        assert!(condense(&condensed).is_none());
+    }
+
+    #[test]
+    fn test_changed_paths_from_patch() {
+        let call = ToolCall {
+            id: "p".to_string(),
+            name: "apply_patch".to_string(),
+            arguments: json!({"patch": "*** Begin Patch\n*** Update File: src/a.rs\n@@\n-x\n+y\n*** Add File: ./src/b.rs\n+z\n*** End Patch"}),
+        };
+        assert_eq!(changed_paths(&call), vec!["src/a.rs", "src/b.rs"]);
+    }
+}
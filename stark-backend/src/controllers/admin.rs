@@ -0,0 +1,293 @@
+use actix_web::{web, HttpRequest, HttpResponse, Responder};
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::time::Instant;
+
+use crate::controllers::health::VERSION;
+use crate::db::DatabaseStats;
+use crate::metrics::{Metrics, Window, WindowStats};
+use crate::models::{AgentJob, AgentJobStatus, RateLimitViolation, Scope};
+use crate::utils::truncate_str;
+use crate::AppState;
+
+/// Validate session token from request
+async fn validate_session_from_request(
+    state: &web::Data<AppState>,
+    req: &HttpRequest,
+    scope: Scope,
+) -> Result<(), HttpResponse> {
+    let token = req
+        .headers()
+        .get("Authorization")
+        .and_then(|h| h.to_str().ok())
+        .map(|s| s.trim_start_matches("Bearer ").to_string());
+
+    let token = match token {
+        Some(t) => t,
+        None => {
+            return Err(HttpResponse::Unauthorized().json(serde_json::json!({
+                "error": "No authorization token provided"
+            })));
+        }
+    };
+
+    match state.db.authorize(&token).await {
+        Ok(Some(scopes)) if scopes.allows(scope) => Ok(()),
+        Ok(Some(_)) => Err(HttpResponse::Forbidden().json(serde_json::json!({
+            "error": format!("Token lacks the {} scope", scope)
+        }))),
+        Ok(None) => Err(HttpResponse::Unauthorized().json(serde_json::json!({
+            "error": "Invalid or expired session"
+        }))),
+        Err(e) => {
+            log::error!("Session validation error: {}", e);
+            Err(HttpResponse::InternalServerError().json(serde_json::json!({
+                "error": "Internal server error"
+            })))
+        }
+    }
+}
+
+#[derive(Serialize)]
+struct MaintenanceResponse {
+    success: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    size_before_bytes: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    reclaimed_bytes: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    duration_ms: Option<u128>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    stats: Option<DatabaseStats>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<String>,
+}
+
+impl MaintenanceResponse {
+    fn error(message: String) -> Self {
+        Self {
+            success: false,
+            size_before_bytes: None,
+            reclaimed_bytes: None,
+            duration_ms: None,
+            stats: None,
+            error: Some(message),
+        }
+    }
+}
+
+/// Run VACUUM/ANALYZE on the database and report table sizes
+async fn run_maintenance(data: web::Data<AppState>, req: HttpRequest) -> impl Responder {
+    if let Err(resp) = validate_session_from_request(&data, &req, Scope::Admin).await {
+        return resp;
+    }
+
+    let db = &data.db;
+    let started = Instant::now();
+
+    let result = async {
+        let before = db.stats().await?;
+        db.vacuum_and_analyze().await?;
+        let after = db.stats().await?;
+        Ok::<_, rusqlite::Error>((before, after))
+    }
+    .await;
+
+    match result {
+        Ok((before, after)) => {
+            let size_before = before.size_bytes + before.wal_size_bytes.unwrap_or(0);
+            let size_after = after.size_bytes + after.wal_size_bytes.unwrap_or(0);
+            log::info!(
+                "Database maintenance complete: {} -> {} bytes in {}ms",
+                size_before,
+                size_after,
+                started.elapsed().as_millis()
+            );
+            HttpResponse::Ok().json(MaintenanceResponse {
+                success: true,
+                size_before_bytes: Some(size_before),
+                reclaimed_bytes: Some(size_before.saturating_sub(size_after)),
+                duration_ms: Some(started.elapsed().as_millis()),
+                stats: Some(after),
+                error: None,
+            })
+        }
+        Err(e) => {
+            log::error!("Database maintenance failed: {}", e);
+            HttpResponse::InternalServerError()
+                .json(MaintenanceResponse::error(format!("Database error: {}", e)))
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct ViolationsQuery {
+    limit: Option<i64>,
+}
+
+#[derive(Debug, Serialize)]
+struct ViolationsResponse {
+    success: bool,
+    violations: Vec<RateLimitViolation>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<String>,
+}
+
+/// Requests recently refused by the chat/agent rate limiter
+async fn list_rate_limit_violations(
+    data: web::Data<AppState>,
+    req: HttpRequest,
+    query: web::Query<ViolationsQuery>,
+) -> impl Responder {
+    if let Err(resp) = validate_session_from_request(&data, &req, Scope::Admin).await {
+        return resp;
+    }
+
+    let limit = query.limit.unwrap_or(100).clamp(1, 1000);
+    match data.db.list_rate_limit_violations(limit).await {
+        Ok(violations) => HttpResponse::Ok().json(ViolationsResponse {
+            success: true,
+            violations,
+            error: None,
+        }),
+        Err(e) => {
+            log::error!("Failed to list rate limit violations: {}", e);
+            HttpResponse::InternalServerError().json(ViolationsResponse {
+                success: false,
+                violations: vec![],
+                error: Some(format!("Database error: {}", e)),
+            })
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+struct SessionCounts {
+    active: i64,
+    /// Active sessions with a message in the last hour
+    active_last_hour: i64,
+}
+
+/// A queued or running background job, without its transcript
+#[derive(Debug, Serialize)]
+struct LiveJob {
+    job_id: String,
+    status: String,
+    task: String,
+    iterations: i64,
+    max_iterations: i64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    schedule_id: Option<i64>,
+    created_at: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    started_at: Option<String>,
+}
+
+impl From<AgentJob> for LiveJob {
+    fn from(job: AgentJob) -> Self {
+        LiveJob {
+            job_id: job.job_id,
+            status: job.status.as_str().to_string(),
+            task: truncate_str(&job.task, 200),
+            iterations: job.iterations,
+            max_iterations: job.max_iterations,
+            schedule_id: job.schedule_id,
+            created_at: job.created_at,
+            started_at: job.started_at,
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+struct JobStats {
+    queued: usize,
+    running: usize,
+    /// Chat and cron agent loops in progress
+    executions: usize,
+    live: Vec<LiveJob>,
+}
+
+#[derive(Debug, Serialize)]
+struct WindowReport {
+    #[serde(flatten)]
+    metrics: WindowStats,
+    /// Background jobs that finished in the window, by status
+    jobs_finished: BTreeMap<String, i64>,
+}
+
+#[derive(Debug, Serialize)]
+struct StatsResponse {
+    success: bool,
+    version: &'static str,
+    uptime_secs: u64,
+    generated_at: String,
+    database: DatabaseStats,
+    sessions: SessionCounts,
+    jobs: JobStats,
+    windows: Vec<WindowReport>,
+}
+
+/// Database size, live sessions and jobs, and provider, tool and API
+/// metrics over the last 5 minutes, hour and day
+async fn get_stats(data: web::Data<AppState>, req: HttpRequest) -> impl Responder {
+    if let Err(resp) = validate_session_from_request(&data, &req, Scope::Admin).await {
+        return resp;
+    }
+
+    let db = &data.db;
+    let now = Utc::now();
+    let result = async {
+        let database = db.stats().await?;
+        let hour_ago = (now - chrono::Duration::hours(1)).to_rfc3339();
+        let (active, active_last_hour) = db.count_active_chat_sessions(&hour_ago).await?;
+        let live = db.list_live_agent_jobs().await?;
+        let mut windows = Vec::new();
+        for window in Window::all() {
+            let since = now - chrono::Duration::from_std(window.duration()).unwrap_or_default();
+            windows.push(WindowReport {
+                metrics: Metrics::global().summary(window),
+                jobs_finished: db.count_finished_agent_jobs(&since.to_rfc3339()).await?,
+            });
+        }
+        Ok::<_, rusqlite::Error>((database, SessionCounts { active, active_last_hour }, live, windows))
+    }
+    .await;
+
+    match result {
+        Ok((database, sessions, live, windows)) => {
+            let running = live.iter().filter(|job| job.status == AgentJobStatus::Running).count();
+            HttpResponse::Ok().json(StatsResponse {
+                success: true,
+                version: VERSION,
+                uptime_secs: Metrics::global().uptime().as_secs(),
+                generated_at: now.to_rfc3339(),
+                database,
+                sessions,
+                jobs: JobStats {
+                    queued: live.len() - running,
+                    running,
+                    executions: data.execution_tracker.running_executions(),
+                    live: live.into_iter().map(LiveJob::from).collect(),
+                },
+                windows,
+            })
+        }
+        Err(e) => {
+            log::error!("Failed to collect admin stats: {}", e);
+            HttpResponse::InternalServerError().json(serde_json::json!({
+                "success": false,
+                "error": format!("Database error: {}", e)
+            }))
+        }
+    }
+}
+
+pub fn config(cfg: &mut web::ServiceConfig) {
+    cfg.service(
+        web::scope("/api/admin")
+            .route("/stats", web::get().to(get_stats))
+            .route("/maintenance", web::post().to(run_maintenance))
+            .route("/rate-limit-violations", web::get().to(list_rate_limit_violations)),
+    );
+}
@@ -0,0 +1,585 @@
+use actix_files::NamedFile;
+use actix_web::http::header::{ContentDisposition, DispositionParam, DispositionType};
+use actix_web::{web, HttpRequest, HttpResponse, Responder};
+use serde::{Deserialize, Serialize};
+use std::io::{self, BufWriter, Write};
+use std::sync::Arc;
+use tokio::sync::mpsc;
+
+use crate::agent::{runner::DEFAULT_MAX_ITERATIONS, AgentRunResult, AgentRunner};
+use crate::ai::archetypes::profile;
+use crate::ai::{AiClient, ResponseFormat};
+use crate::context::ContextWindow;
+use crate::models::{AgentJob, AgentSettings, Caller, Scope};
+use crate::tools::{OutputStore, ToolContext};
+use crate::AppState;
+use crate::workspace::{parse_filter, ArchiveFormat, WorkspaceKind, WorkspaceManager};
+
+/// Upper bound on `max_iterations` a caller may request
+const MAX_ITERATIONS_LIMIT: usize = 50;
+
+/// Validate session token from request
+async fn validate_session_from_request(
+    state: &web::Data<AppState>,
+    req: &HttpRequest,
+    scope: Scope,
+) -> Result<Caller, HttpResponse> {
+    let token = req
+        .headers()
+        .get("Authorization")
+        .and_then(|h| h.to_str().ok())
+        .map(|s| s.trim_start_matches("Bearer ").to_string());
+
+    let token = match token {
+        Some(t) => t,
+        None => {
+            return Err(HttpResponse::Unauthorized().json(serde_json::json!({
+                "error": "No authorization token provided"
+            })));
+        }
+    };
+
+    match state.db.authenticate(&token).await {
+        Ok(Some(caller)) if caller.scopes.allows(scope) => Ok(caller),
+        Ok(Some(_)) => Err(HttpResponse::Forbidden().json(serde_json::json!({
+            "error": format!("Token lacks the {} scope", scope)
+        }))),
+        Ok(None) => Err(HttpResponse::Unauthorized().json(serde_json::json!({
+            "error": "Invalid or expired session"
+        }))),
+        Err(e) => {
+            log::error!("Session validation error: {}", e);
+            Err(HttpResponse::InternalServerError().json(serde_json::json!({
+                "error": "Internal server error"
+            })))
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct AgentRunRequest {
+    /// Task description for the agent
+    pub task: String,
+    /// Name of a server-managed workspace to run in; reuse a name to continue
+    /// work from an earlier run. A fresh workspace is created when omitted.
+    #[serde(default)]
+    pub workspace: Option<String>,
+    #[serde(default)]
+    pub max_iterations: Option<usize>,
+    /// Plan/act mode: break the task into steps before working on it
+    #[serde(default)]
+    pub planning: bool,
+    /// Ask for the final answer as JSON, optionally matching a schema
+    #[serde(default)]
+    pub response_format: Option<ResponseFormat>,
+}
+
+#[derive(Serialize)]
+pub struct AgentRunResponse {
+    pub success: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub workspace: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub result: Option<AgentRunResult>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+impl AgentRunResponse {
+    fn error(error: impl Into<String>) -> Self {
+        AgentRunResponse {
+            success: false,
+            workspace: None,
+            result: None,
+            error: Some(error.into()),
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ArtifactsQuery {
+    /// `tar.gz` (default) or `zip`
+    #[serde(default)]
+    pub format: Option<String>,
+    /// Only include files matching this glob, e.g. `dist/**` or `*.py`
+    #[serde(default)]
+    pub glob: Option<String>,
+}
+
+#[derive(Serialize)]
+pub struct AgentJobResponse {
+    pub success: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub job: Option<AgentJob>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+impl AgentJobResponse {
+    fn error(error: impl Into<String>) -> Self {
+        AgentJobResponse {
+            success: false,
+            job: None,
+            error: Some(error.into()),
+        }
+    }
+}
+
+pub fn config(cfg: &mut web::ServiceConfig) {
+    cfg.service(
+        web::scope("/api/agent")
+            .route("/run", web::post().to(run_agent))
+            .route("/jobs", web::post().to(create_job))
+            .route("/jobs/{id}", web::get().to(get_job))
+            .route("/jobs/{id}/cancel", web::post().to(cancel_job))
+            .route("/jobs/{id}/artifacts", web::get().to(download_artifacts))
+            .route("/jobs/{id}/artifacts/{path:.*}", web::get().to(download_artifact_file))
+            .route("/jobs/{id}/outputs/{output_ref}", web::get().to(get_job_output)),
+    );
+}
+
+/// Workspace names become directory names, so keep them to a safe character set
+fn is_valid_workspace_name(name: &str) -> bool {
+    !name.is_empty()
+        && name.len() <= 64
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+}
+
+/// Start of every workspace name a non-admin user's runs and jobs use, so they
+/// can never name another user's workspace (like `chat::web_chat_id`)
+pub(crate) fn workspace_prefix(caller: &Caller) -> Option<String> {
+    (!caller.is_admin()).then(|| format!("user-{}-", caller.user.id))
+}
+
+/// Validate the requested workspace name, generating one when omitted.
+/// Non-admin users' names are moved under their prefix.
+pub(crate) fn resolve_workspace_name(caller: &Caller, requested: Option<&str>) -> Result<String, &'static str> {
+    let name = match requested {
+        Some(name) => name.to_string(),
+        None => uuid::Uuid::new_v4().to_string(),
+    };
+    let name = match workspace_prefix(caller) {
+        Some(prefix) if !name.starts_with(&prefix) => format!("{}{}", prefix, name),
+        _ => name,
+    };
+    if !is_valid_workspace_name(&name) {
+        return Err("Workspace name may only contain letters, digits, '-' and '_' (max 64 characters)");
+    }
+    Ok(name)
+}
+
+/// Reject tool names a CodeEngineer run can't use
+pub(crate) fn validate_tool_names(state: &AppState, tools: &[String]) -> Result<(), String> {
+    let available = AgentRunner::available_tools(&state.tool_registry);
+    let unknown: Vec<&str> = tools
+        .iter()
+        .filter(|name| !available.contains(name))
+        .map(String::as_str)
+        .collect();
+    if unknown.is_empty() {
+        Ok(())
+    } else {
+        Err(format!("Unknown or unavailable tools: {}", unknown.join(", ")))
+    }
+}
+
+pub(crate) fn resolve_max_iterations(requested: Option<usize>) -> usize {
+    requested
+        .unwrap_or(DEFAULT_MAX_ITERATIONS)
+        .min(MAX_ITERATIONS_LIMIT)
+}
+
+/// Run a CodeEngineer task to completion in a server-managed workspace
+async fn run_agent(
+    state: web::Data<AppState>,
+    req: HttpRequest,
+    body: web::Json<AgentRunRequest>,
+) -> impl Responder {
+    let caller = match validate_session_from_request(&state, &req, Scope::ToolsExec).await {
+        Ok(caller) => caller,
+        Err(resp) => return resp,
+    };
+
+    if body.task.trim().is_empty() {
+        return HttpResponse::BadRequest().json(AgentRunResponse::error("Task cannot be empty"));
+    }
+    if let Some(Err(e)) = body.response_format.as_ref().map(ResponseFormat::validate) {
+        return HttpResponse::BadRequest().json(AgentRunResponse::error(format!("Invalid response_format: {}", e)));
+    }
+
+    let workspace_name = match resolve_workspace_name(&caller, body.workspace.as_deref()) {
+        Ok(name) => name,
+        Err(e) => return HttpResponse::BadRequest().json(AgentRunResponse::error(e)),
+    };
+
+    let workspaces = WorkspaceManager::from_env();
+    let workspace_dir = match workspaces.allocate(WorkspaceKind::AgentRun, &workspace_name) {
+        Ok(dir) => dir,
+        Err(e) => {
+            log::error!("{}", e);
+            return HttpResponse::InternalServerError()
+                .json(AgentRunResponse::error("Failed to create workspace"));
+        }
+    };
+    // Only the model reads the outputs of a run that isn't a job, so they go with it
+    let output_store = match workspaces.outputs_dir(&uuid::Uuid::new_v4().to_string()) {
+        Ok(dir) => OutputStore::new(dir),
+        Err(e) => {
+            log::error!("{}", e);
+            return HttpResponse::InternalServerError()
+                .json(AgentRunResponse::error("Failed to create output store"));
+        }
+    };
+
+    let settings = match state.db.get_active_agent_settings().await {
+        Ok(Some(settings)) => settings,
+        Ok(None) => AgentSettings::default(),
+        Err(e) => {
+            log::error!("Failed to load agent settings: {}", e);
+            return HttpResponse::InternalServerError()
+                .json(AgentRunResponse::error("Internal server error"));
+        }
+    };
+
+    let client = match AiClient::from_settings_with_wallet(
+        &settings,
+        state.config.burner_wallet_private_key.as_deref(),
+    ) {
+        Ok(c) => c.with_response_format(body.response_format.as_ref()),
+        Err(e) => {
+            return HttpResponse::InternalServerError()
+                .json(AgentRunResponse::error(format!("Failed to create AI client: {}", e)));
+        }
+    };
+    let fallback = match AiClient::fallback_from_settings(
+        &settings,
+        state.config.burner_wallet_private_key.as_deref(),
+    ) {
+        Ok(c) => c.map(|c| c.with_response_format(body.response_format.as_ref())),
+        Err(e) => {
+            return HttpResponse::InternalServerError()
+                .json(AgentRunResponse::error(format!("Failed to create fallback AI client: {}", e)));
+        }
+    };
+
+    let tool_context = ToolContext::new()
+        .with_workspace(workspace_dir.to_string_lossy().to_string())
+        .with_process_manager(Arc::clone(&state.process_manager))
+        .with_database(Arc::clone(&state.db));
+
+    let max_iterations = resolve_max_iterations(body.max_iterations);
+
+    log::info!(
+        "[AGENT_RUN] Starting run in workspace '{}' (max {} iterations)",
+        workspace_name,
+        max_iterations
+    );
+
+    let runner = AgentRunner::new(client, Arc::clone(&state.tool_registry), tool_context)
+        .with_max_iterations(max_iterations)
+        .with_planning(body.planning)
+        .with_fallback(fallback)
+        .with_context_window(ContextWindow::from_settings(&settings))
+        .with_prompt_suffix(profile::profile(AiClient::infer_archetype(&settings)).system_prompt_suffix)
+        .with_response_format(body.response_format.clone())
+        .with_output_store(output_store.clone())
+        .with_uploads(workspaces.uploads(WorkspaceKind::AgentRun, &workspace_name));
+    let result = runner.run(&body.task).await;
+    if let Err(e) = output_store.remove().await {
+        log::warn!("[AGENT_RUN] {}", e);
+    }
+
+    HttpResponse::Ok().json(AgentRunResponse {
+        success: result.success,
+        workspace: Some(workspace_name),
+        error: result.error.clone(),
+        result: Some(result),
+    })
+}
+
+/// Queue a CodeEngineer task to run in the background
+async fn create_job(
+    state: web::Data<AppState>,
+    req: HttpRequest,
+    body: web::Json<AgentRunRequest>,
+) -> impl Responder {
+    let caller = match validate_session_from_request(&state, &req, Scope::ToolsExec).await {
+        Ok(caller) => caller,
+        Err(resp) => return resp,
+    };
+
+    if body.task.trim().is_empty() {
+        return HttpResponse::BadRequest().json(AgentJobResponse::error("Task cannot be empty"));
+    }
+    if let Some(Err(e)) = body.response_format.as_ref().map(ResponseFormat::validate) {
+        return HttpResponse::BadRequest().json(AgentJobResponse::error(format!("Invalid response_format: {}", e)));
+    }
+
+    let workspace_name = match resolve_workspace_name(&caller, body.workspace.as_deref()) {
+        Ok(name) => name,
+        Err(e) => return HttpResponse::BadRequest().json(AgentJobResponse::error(e)),
+    };
+
+    match state.agent_jobs.enqueue(
+        &body.task,
+        &workspace_name,
+        resolve_max_iterations(body.max_iterations),
+        &[],
+        None,
+        body.planning,
+        body.response_format.as_ref(),
+        Some(caller.user.id),
+    ).await {
+        Ok(job) => HttpResponse::Accepted().json(AgentJobResponse {
+            success: true,
+            job: Some(job),
+            error: None,
+        }),
+        Err(e) if state.agent_jobs.is_stopping() => HttpResponse::ServiceUnavailable().json(AgentJobResponse::error(e)),
+        Err(e) => {
+            log::error!("{}", e);
+            HttpResponse::InternalServerError().json(AgentJobResponse::error("Failed to queue job"))
+        }
+    }
+}
+
+/// Get the status, progress and transcript of a queued job
+async fn get_job(
+    state: web::Data<AppState>,
+    req: HttpRequest,
+    path: web::Path<String>,
+) -> impl Responder {
+    let caller = match validate_session_from_request(&state, &req, Scope::Read).await {
+        Ok(caller) => caller,
+        Err(resp) => return resp,
+    };
+
+    match find_job(&state, &caller, &path.into_inner()).await {
+        Ok(job) => HttpResponse::Ok().json(AgentJobResponse {
+            success: true,
+            job: Some(job),
+            error: None,
+        }),
+        Err(resp) => resp,
+    }
+}
+
+/// Cancel a queued or running job
+async fn cancel_job(
+    state: web::Data<AppState>,
+    req: HttpRequest,
+    path: web::Path<String>,
+) -> impl Responder {
+    let caller = match validate_session_from_request(&state, &req, Scope::ToolsExec).await {
+        Ok(caller) => caller,
+        Err(resp) => return resp,
+    };
+
+    let job_id = path.into_inner();
+    if let Err(resp) = find_job(&state, &caller, &job_id).await {
+        return resp;
+    }
+    let cancelled = match state.agent_jobs.cancel(&job_id).await {
+        Ok(cancelled) => cancelled,
+        Err(e) => {
+            log::error!("{}", e);
+            return HttpResponse::InternalServerError().json(AgentJobResponse::error("Failed to cancel job"));
+        }
+    };
+
+    match find_job(&state, &caller, &job_id).await {
+        Ok(job) if cancelled => HttpResponse::Ok().json(AgentJobResponse {
+            success: true,
+            job: Some(job),
+            error: None,
+        }),
+        Ok(job) => HttpResponse::Conflict()
+            .json(AgentJobResponse::error(format!("Job already {}", job.status.as_str()))),
+        Err(resp) => resp,
+    }
+}
+
+/// Look up a job the caller may see, or the error response to send. Other
+/// users' jobs are reported as not found.
+async fn find_job(state: &web::Data<AppState>, caller: &Caller, job_id: &str) -> Result<AgentJob, HttpResponse> {
+    match state.db.get_agent_job(job_id).await {
+        Ok(Some(job)) if caller.can_access(job.user_id) => Ok(job),
+        Ok(_) => Err(HttpResponse::NotFound().json(AgentJobResponse::error("Job not found"))),
+        Err(e) => {
+            log::error!("Failed to load agent job {}: {}", job_id, e);
+            Err(HttpResponse::InternalServerError().json(AgentJobResponse::error("Internal server error")))
+        }
+    }
+}
+
+/// Forwards archive bytes from a blocking writer to a streaming response
+struct ChunkSender(mpsc::Sender<Result<web::Bytes, io::Error>>);
+
+impl Write for ChunkSender {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.0
+            .blocking_send(Ok(web::Bytes::copy_from_slice(buf)))
+            .map_err(|_| io::Error::new(io::ErrorKind::BrokenPipe, "client disconnected"))?;
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// Download the files in a job's workspace as a tar.gz (streamed) or zip archive
+async fn download_artifacts(
+    state: web::Data<AppState>,
+    req: HttpRequest,
+    path: web::Path<String>,
+    query: web::Query<ArtifactsQuery>,
+) -> impl Responder {
+    let caller = match validate_session_from_request(&state, &req, Scope::Read).await {
+        Ok(caller) => caller,
+        Err(resp) => return resp,
+    };
+
+    let job = match find_job(&state, &caller, &path.into_inner()).await {
+        Ok(job) => job,
+        Err(resp) => return resp,
+    };
+
+    let format = match query.format.as_deref() {
+        None => ArchiveFormat::TarGz,
+        Some(f) => match ArchiveFormat::from_str(f) {
+            Some(format) => format,
+            None => {
+                return HttpResponse::BadRequest()
+                    .json(AgentJobResponse::error("format must be 'tar.gz' or 'zip'"));
+            }
+        },
+    };
+    let filter = match query.glob.as_deref().map(parse_filter).transpose() {
+        Ok(filter) => filter,
+        Err(e) => return HttpResponse::BadRequest().json(AgentJobResponse::error(e)),
+    };
+
+    let manager = WorkspaceManager::from_env();
+    match manager.path(WorkspaceKind::AgentRun, &job.workspace) {
+        Ok(dir) if dir.is_dir() => {}
+        _ => return HttpResponse::NotFound().json(AgentJobResponse::error("Job workspace not found")),
+    }
+
+    let mut response = HttpResponse::Ok();
+    response.content_type(format.content_type()).insert_header((
+        "Content-Disposition",
+        format!("attachment; filename=\"{}.{}\"", job.workspace, format.extension()),
+    ));
+
+    match format {
+        ArchiveFormat::Zip => {
+            let zipped = tokio::task::spawn_blocking(move || {
+                manager.zip(WorkspaceKind::AgentRun, &job.workspace, filter.as_ref())
+            })
+            .await;
+            match zipped {
+                Ok(Ok(archive)) => response.body(archive),
+                Ok(Err(e)) => {
+                    log::error!("Failed to archive job workspace: {}", e);
+                    HttpResponse::InternalServerError().json(AgentJobResponse::error(e))
+                }
+                Err(e) => {
+                    log::error!("Archive task failed: {}", e);
+                    HttpResponse::InternalServerError().json(AgentJobResponse::error("Internal server error"))
+                }
+            }
+        }
+        ArchiveFormat::TarGz => {
+            // The tarball is written on a blocking thread and sent as it's produced,
+            // so large workspaces aren't buffered in memory
+            let (tx, mut rx) = mpsc::channel(16);
+            tokio::task::spawn_blocking(move || {
+                let writer = BufWriter::with_capacity(64 * 1024, ChunkSender(tx.clone()));
+                if let Err(e) =
+                    manager.write_tar_gz(WorkspaceKind::AgentRun, &job.workspace, filter.as_ref(), writer)
+                {
+                    log::warn!("Artifact stream for job {} ended early: {}", job.job_id, e);
+                    let _ = tx.blocking_send(Err(io::Error::other(e)));
+                }
+            });
+            let stream = futures_util::stream::poll_fn(move |cx| rx.poll_recv(cx));
+            response.streaming(stream)
+        }
+    }
+}
+
+/// Download a single file from a job's workspace
+async fn download_artifact_file(
+    state: web::Data<AppState>,
+    req: HttpRequest,
+    path: web::Path<(String, String)>,
+) -> impl Responder {
+    let caller = match validate_session_from_request(&state, &req, Scope::Read).await {
+        Ok(caller) => caller,
+        Err(resp) => return resp,
+    };
+
+    let (job_id, file_path) = path.into_inner();
+    let job = match find_job(&state, &caller, &job_id).await {
+        Ok(job) => job,
+        Err(resp) => return resp,
+    };
+
+    let file = match WorkspaceManager::from_env().file(WorkspaceKind::AgentRun, &job.workspace, &file_path) {
+        Ok(file) => file,
+        Err(e) if e.contains("not found") => return HttpResponse::NotFound().json(AgentJobResponse::error(e)),
+        Err(e) => return HttpResponse::BadRequest().json(AgentJobResponse::error(e)),
+    };
+
+    let filename = file
+        .file_name()
+        .map(|n| n.to_string_lossy().to_string())
+        .unwrap_or_else(|| "artifact".to_string());
+    match NamedFile::open_async(&file).await {
+        Ok(named) => named
+            .set_content_disposition(ContentDisposition {
+                disposition: DispositionType::Attachment,
+                parameters: vec![DispositionParam::Filename(filename)],
+            })
+            .into_response(&req),
+        Err(e) => {
+            log::error!("Failed to open artifact {:?}: {}", file, e);
+            HttpResponse::InternalServerError().json(AgentJobResponse::error("Failed to read file"))
+        }
+    }
+}
+
+/// Full output of a tool call that the model only got a preview of
+async fn get_job_output(
+    state: web::Data<AppState>,
+    req: HttpRequest,
+    path: web::Path<(String, String)>,
+) -> impl Responder {
+    let caller = match validate_session_from_request(&state, &req, Scope::Read).await {
+        Ok(caller) => caller,
+        Err(resp) => return resp,
+    };
+
+    let (job_id, output_ref) = path.into_inner();
+    let job = match find_job(&state, &caller, &job_id).await {
+        Ok(job) => job,
+        Err(resp) => return resp,
+    };
+
+    let store = match WorkspaceManager::from_env().outputs_dir(&job.job_id) {
+        Ok(dir) => OutputStore::new(dir),
+        Err(e) => return HttpResponse::BadRequest().json(AgentJobResponse::error(e)),
+    };
+    match store.get(&output_ref).await {
+        Ok(Some(content)) => HttpResponse::Ok().content_type("text/plain; charset=utf-8").body(content),
+        Ok(None) => HttpResponse::NotFound().json(AgentJobResponse::error("Output not found")),
+        Err(e) if e.starts_with("Invalid") => HttpResponse::BadRequest().json(AgentJobResponse::error(e)),
+        Err(e) => {
+            log::error!("Failed to read output {} of job {}: {}", output_ref, job_id, e);
+            HttpResponse::InternalServerError().json(AgentJobResponse::error("Failed to read output"))
+        }
+    }
+}
@@ -1,13 +1,23 @@
 use actix_web::{web, HttpRequest, HttpResponse, Responder};
+use crate::ai::archetypes::{profile, ArchetypeProfileOverrides};
 use crate::ai::ArchetypeId;
-use crate::models::{AgentSettings, AgentSettingsResponse, UpdateAgentSettingsRequest, UpdateBotSettingsRequest};
+use crate::context::window::{MAX_CONTEXT_WINDOW_TOKENS, MIN_CONTEXT_WINDOW_TOKENS};
+use crate::models::{
+    AgentSettings, AgentSettingsResponse, Scope, UpdateAgentSettingsRequest,
+    UpdateBotSettingsRequest,
+};
+use crate::middleware::rate_limit::RateLimits;
 use crate::tools::rpc_config;
 use crate::AppState;
 
+/// Longest login session lifetime accepted in agent settings
+const MAX_SESSION_TTL_HOURS: i64 = 24 * 365;
+
 /// Validate session token from request
-fn validate_session_from_request(
+async fn validate_session_from_request(
     state: &web::Data<AppState>,
     req: &HttpRequest,
+    scope: Scope,
 ) -> Result<(), HttpResponse> {
     let token = req
         .headers()
@@ -24,8 +34,11 @@ fn validate_session_from_request(
         }
     };
 
-    match state.db.validate_session(&token) {
-        Ok(Some(_)) => Ok(()),
+    match state.db.authorize(&token).await {
+        Ok(Some(scopes)) if scopes.allows(scope) => Ok(()),
+        Ok(Some(_)) => Err(HttpResponse::Forbidden().json(serde_json::json!({
+            "error": format!("Token lacks the {} scope", scope)
+        }))),
         Ok(None) => Err(HttpResponse::Unauthorized().json(serde_json::json!({
             "error": "Invalid or expired session"
         }))),
@@ -43,10 +56,10 @@ pub async fn get_agent_settings(
     state: web::Data<AppState>,
     req: HttpRequest,
 ) -> impl Responder {
-    if let Err(resp) = validate_session_from_request(&state, &req) {
+    if let Err(resp) = validate_session_from_request(&state, &req, Scope::Read).await {
         return resp;
     }
-    match state.db.get_active_agent_settings() {
+    match state.db.get_active_agent_settings().await {
         Ok(Some(settings)) => {
             let response: AgentSettingsResponse = settings.into();
             HttpResponse::Ok().json(response)
@@ -70,10 +83,10 @@ pub async fn list_agent_settings(
     state: web::Data<AppState>,
     req: HttpRequest,
 ) -> impl Responder {
-    if let Err(resp) = validate_session_from_request(&state, &req) {
+    if let Err(resp) = validate_session_from_request(&state, &req, Scope::Read).await {
         return resp;
     }
-    match state.db.list_agent_settings() {
+    match state.db.list_agent_settings().await {
         Ok(settings) => {
             let responses: Vec<AgentSettingsResponse> = settings
                 .into_iter()
@@ -90,42 +103,76 @@ pub async fn list_agent_settings(
     }
 }
 
-/// Get available archetypes with descriptions
+/// List archetype profiles: each archetype with its request defaults and prompt tweaks
 pub async fn get_available_archetypes(
     state: web::Data<AppState>,
     req: HttpRequest,
 ) -> impl Responder {
-    if let Err(resp) = validate_session_from_request(&state, &req) {
+    if let Err(resp) = validate_session_from_request(&state, &req, Scope::Read).await {
+        return resp;
+    }
+    HttpResponse::Ok().json(profile::profiles())
+}
+
+/// Customize an archetype profile. Fields left out keep their defaults.
+pub async fn update_archetype_profile(
+    state: web::Data<AppState>,
+    req: HttpRequest,
+    path: web::Path<String>,
+    body: web::Json<ArchetypeProfileOverrides>,
+) -> impl Responder {
+    if let Err(resp) = validate_session_from_request(&state, &req, Scope::Admin).await {
+        return resp;
+    }
+    let Some(archetype) = ArchetypeId::from_str(&path) else {
+        return HttpResponse::NotFound().json(serde_json::json!({
+            "error": format!("Unknown archetype: {}", path)
+        }));
+    };
+    let overrides = match body.into_inner().validate() {
+        Ok(overrides) => overrides,
+        Err(e) => return HttpResponse::BadRequest().json(serde_json::json!({ "error": e })),
+    };
+
+    let saved = if overrides.is_empty() {
+        state.db.delete_archetype_profile_overrides(archetype).await.map(|_| ())
+    } else {
+        state.db.set_archetype_profile_overrides(archetype, &overrides).await
+    };
+    if let Err(e) = saved {
+        log::error!("Failed to save archetype profile: {}", e);
+        return HttpResponse::InternalServerError().json(serde_json::json!({
+            "error": format!("Database error: {}", e)
+        }));
+    }
+    profile::set_overrides(archetype, Some(overrides));
+    log::info!("Customized the {} archetype profile", archetype);
+    HttpResponse::Ok().json(profile::profile(archetype))
+}
+
+/// Reset an archetype profile to its defaults
+pub async fn reset_archetype_profile(
+    state: web::Data<AppState>,
+    req: HttpRequest,
+    path: web::Path<String>,
+) -> impl Responder {
+    if let Err(resp) = validate_session_from_request(&state, &req, Scope::Admin).await {
         return resp;
     }
-    let archetypes = vec![
-        serde_json::json!({
-            "id": "kimi",
-            "name": "Kimi (Native Tool Calling)",
-            "description": "OpenAI-compatible native tool calling. Best for Kimi, OpenAI, and similar endpoints.",
-            "uses_native_tools": true,
-        }),
-        serde_json::json!({
-            "id": "llama",
-            "name": "Llama (Text-based Tool Calling)",
-            "description": "JSON-based tool calling via text. Best for generic Llama endpoints.",
-            "uses_native_tools": false,
-        }),
-        serde_json::json!({
-            "id": "claude",
-            "name": "Claude (Native Tool Calling)",
-            "description": "Anthropic Claude native tool calling.",
-            "uses_native_tools": true,
-        }),
-        serde_json::json!({
-            "id": "openai",
-            "name": "OpenAI (Native Tool Calling)",
-            "description": "OpenAI native tool calling. Same as Kimi.",
-            "uses_native_tools": true,
-        }),
-    ];
-
-    HttpResponse::Ok().json(archetypes)
+    let Some(archetype) = ArchetypeId::from_str(&path) else {
+        return HttpResponse::NotFound().json(serde_json::json!({
+            "error": format!("Unknown archetype: {}", path)
+        }));
+    };
+    if let Err(e) = state.db.delete_archetype_profile_overrides(archetype).await {
+        log::error!("Failed to reset archetype profile: {}", e);
+        return HttpResponse::InternalServerError().json(serde_json::json!({
+            "error": format!("Database error: {}", e)
+        }));
+    }
+    profile::set_overrides(archetype, None);
+    log::info!("Reset the {} archetype profile", archetype);
+    HttpResponse::Ok().json(profile::profile(archetype))
 }
 
 /// Update agent settings (set active endpoint)
@@ -134,10 +181,10 @@ pub async fn update_agent_settings(
     req: HttpRequest,
     body: web::Json<UpdateAgentSettingsRequest>,
 ) -> impl Responder {
-    if let Err(resp) = validate_session_from_request(&state, &req) {
+    if let Err(resp) = validate_session_from_request(&state, &req, Scope::Admin).await {
         return resp;
     }
-    let request = body.into_inner();
+    let mut request = body.into_inner();
 
     // Validate endpoint
     if request.endpoint.is_empty() {
@@ -146,13 +193,118 @@ pub async fn update_agent_settings(
         }));
     }
 
-    // Validate archetype
-    if ArchetypeId::from_str(&request.model_archetype).is_none() {
+    // Validate archetype; without max_tokens its profile's limit is used
+    let Some(archetype) = ArchetypeId::from_str(&request.model_archetype) else {
+        return HttpResponse::BadRequest().json(serde_json::json!({
+            "error": format!("Invalid archetype: {}. Must be kimi, llama, claude, openai or deepseek.", request.model_archetype)
+        }));
+    };
+    if request.max_tokens <= 0 {
+        request.max_tokens = profile::profile(archetype).max_tokens.min(i32::MAX as u32) as i32;
+    }
+
+    // Validate budget overrides and session budgets
+    if request.budget_max_tokens.is_some_and(|t| t <= 0)
+        || request.budget_max_usd.is_some_and(|usd| !usd.is_finite() || usd <= 0.0)
+        || request.session_budget_max_tokens.is_some_and(|t| t <= 0)
+        || request.session_budget_max_usd.is_some_and(|usd| !usd.is_finite() || usd <= 0.0)
+    {
+        return HttpResponse::BadRequest().json(serde_json::json!({
+            "error": "Budget limits must be positive when set"
+        }));
+    }
+
+    // Validate spending limits
+    if [request.spend_max_tx_usd, request.spend_max_daily_usd, request.spend_approval_usd]
+        .iter()
+        .flatten()
+        .any(|usd| !usd.is_finite() || *usd < 0.0)
+    {
+        return HttpResponse::BadRequest().json(serde_json::json!({
+            "error": "Spending limits must be non-negative USD amounts when set"
+        }));
+    }
+
+    // Validate session lifetime
+    if request.session_ttl_hours.is_some_and(|h| !(1..=MAX_SESSION_TTL_HOURS).contains(&h)) {
+        return HttpResponse::BadRequest().json(serde_json::json!({
+            "error": format!("session_ttl_hours must be between 1 and {}", MAX_SESSION_TTL_HOURS)
+        }));
+    }
+
+    // Validate the context window
+    if request
+        .max_context_tokens
+        .is_some_and(|t| !(MIN_CONTEXT_WINDOW_TOKENS..=MAX_CONTEXT_WINDOW_TOKENS).contains(&t))
+    {
         return HttpResponse::BadRequest().json(serde_json::json!({
-            "error": format!("Invalid archetype: {}. Must be kimi, llama, claude, or openai.", request.model_archetype)
+            "error": format!(
+                "max_context_tokens must be between {} and {}",
+                MIN_CONTEXT_WINDOW_TOKENS, MAX_CONTEXT_WINDOW_TOKENS
+            )
         }));
     }
 
+    // Validate the fallback provider (blank fields are unset)
+    for field in [
+        &mut request.fallback_endpoint,
+        &mut request.fallback_model_archetype,
+        &mut request.fallback_model,
+        &mut request.fallback_secret_key,
+    ] {
+        if field.as_deref().is_some_and(|v| v.trim().is_empty()) {
+            *field = None;
+        }
+    }
+    if let Some(archetype) = &request.fallback_model_archetype {
+        if ArchetypeId::from_str(archetype).is_none() {
+            return HttpResponse::BadRequest().json(serde_json::json!({
+                "error": format!("Invalid fallback archetype: {}. Must be kimi, llama, claude, openai or deepseek.", archetype)
+            }));
+        }
+        if request.fallback_endpoint.is_none() && request.fallback_model.is_none() {
+            return HttpResponse::BadRequest().json(serde_json::json!({
+                "error": "fallback_model_archetype needs a fallback_endpoint or fallback_model"
+            }));
+        }
+    }
+
+    // Validate the model allowlist (stored comma-separated)
+    let mut allowed_models: Vec<String> = Vec::new();
+    for model in &request.allowed_models {
+        let model = model.trim();
+        if model.is_empty() || model.contains(',') {
+            return HttpResponse::BadRequest().json(serde_json::json!({
+                "error": format!("Invalid model name in allowed_models: '{}'", model)
+            }));
+        }
+        if !allowed_models.iter().any(|m| m == model) {
+            allowed_models.push(model.to_string());
+        }
+    }
+    request.allowed_models = allowed_models;
+
+    // Validate the default persona (blank is unset)
+    if request.default_persona.as_deref().is_some_and(|p| p.trim().is_empty()) {
+        request.default_persona = None;
+    }
+    if let Some(name) = &request.default_persona {
+        match state.db.get_persona(name).await {
+            Ok(Some(_)) => {}
+            Ok(None) => {
+                return HttpResponse::BadRequest().json(serde_json::json!({
+                    "error": format!("Unknown persona: {}", name)
+                }));
+            }
+            Err(e) => {
+                log::error!("Failed to load persona {}: {}", name, e);
+                return HttpResponse::InternalServerError().json(serde_json::json!({
+                    "error": "Internal server error"
+                }));
+            }
+        }
+    }
+
     // Save settings
     log::info!(
         "Saving agent settings: endpoint={}, archetype={}, max_tokens={}, has_secret_key={}",
@@ -162,7 +314,7 @@ pub async fn update_agent_settings(
         request.secret_key.is_some()
     );
 
-    match state.db.save_agent_settings(&request.endpoint, &request.model_archetype, request.max_tokens, request.secret_key.as_deref()) {
+    match state.db.save_agent_settings(&request).await {
         Ok(settings) => {
             log::info!("Updated agent settings to use {} endpoint with {} archetype", request.endpoint, request.model_archetype);
             let response: AgentSettingsResponse = settings.into();
@@ -182,10 +334,10 @@ pub async fn disable_agent(
     state: web::Data<AppState>,
     req: HttpRequest,
 ) -> impl Responder {
-    if let Err(resp) = validate_session_from_request(&state, &req) {
+    if let Err(resp) = validate_session_from_request(&state, &req, Scope::Admin).await {
         return resp;
     }
-    match state.db.disable_agent_settings() {
+    match state.db.disable_agent_settings().await {
         Ok(_) => {
             log::info!("Disabled AI agent");
             HttpResponse::Ok().json(serde_json::json!({
@@ -207,10 +359,10 @@ pub async fn get_bot_settings(
     state: web::Data<AppState>,
     req: HttpRequest,
 ) -> impl Responder {
-    if let Err(resp) = validate_session_from_request(&state, &req) {
+    if let Err(resp) = validate_session_from_request(&state, &req, Scope::Read).await {
         return resp;
     }
-    match state.db.get_bot_settings() {
+    match state.db.get_bot_settings().await {
         Ok(settings) => HttpResponse::Ok().json(settings),
         Err(e) => {
             log::error!("Failed to get bot settings: {}", e);
@@ -227,7 +379,7 @@ pub async fn update_bot_settings(
     req: HttpRequest,
     body: web::Json<UpdateBotSettingsRequest>,
 ) -> impl Responder {
-    if let Err(resp) = validate_session_from_request(&state, &req) {
+    if let Err(resp) = validate_session_from_request(&state, &req, Scope::Admin).await {
         return resp;
     }
     let request = body.into_inner();
@@ -241,15 +393,32 @@ pub async fn update_bot_settings(
         }
     }
 
-    match state.db.update_bot_settings_full(
-        request.bot_name.as_deref(),
-        request.bot_email.as_deref(),
-        request.web3_tx_requires_confirmation,
-        request.rpc_provider.as_deref(),
-        request.custom_rpc_endpoints.as_ref(),
-        request.max_tool_iterations,
-    ) {
+    // Validate rate limits
+    if request.rate_limit_per_minute.is_some_and(|n| n < 0) || request.rate_limit_burst.is_some_and(|n| n < 1) {
+        return HttpResponse::BadRequest().json(serde_json::json!({
+            "error": "rate_limit_per_minute must be 0 (off) or more, and rate_limit_burst at least 1"
+        }));
+    }
+
+    let result = async {
+        let settings = state.db.update_bot_settings_full(
+            request.bot_name.as_deref(),
+            request.bot_email.as_deref(),
+            request.web3_tx_requires_confirmation,
+            request.rpc_provider.as_deref(),
+            request.custom_rpc_endpoints.as_ref(),
+            request.max_tool_iterations,
+        ).await?;
+        if request.rate_limit_per_minute.is_none() && request.rate_limit_burst.is_none() {
+            return Ok(settings);
+        }
+        state.db.update_rate_limits(request.rate_limit_per_minute, request.rate_limit_burst).await
+    }
+    .await;
+
+    match result {
         Ok(settings) => {
+            state.rate_limiter.set_limits(RateLimits::from_settings(&settings));
             log::info!(
                 "Updated bot settings: name={}, email={}, rpc_provider={}",
                 settings.bot_name,
@@ -272,7 +441,7 @@ pub async fn get_rpc_providers(
     state: web::Data<AppState>,
     req: HttpRequest,
 ) -> impl Responder {
-    if let Err(resp) = validate_session_from_request(&state, &req) {
+    if let Err(resp) = validate_session_from_request(&state, &req, Scope::Read).await {
         return resp;
     }
 
@@ -309,6 +478,8 @@ pub fn configure(cfg: &mut web::ServiceConfig) {
             .route("", web::put().to(update_agent_settings))
             .route("/list", web::get().to(list_agent_settings))
             .route("/archetypes", web::get().to(get_available_archetypes))
+            .route("/archetypes/{id}", web::put().to(update_archetype_profile))
+            .route("/archetypes/{id}", web::delete().to(reset_archetype_profile))
             .route("/disable", web::post().to(disable_agent))
     );
     cfg.service(
@@ -0,0 +1,271 @@
+use actix_web::http::header::RETRY_AFTER;
+use actix_web::{web, HttpRequest, HttpResponse, Responder};
+use serde::Serialize;
+
+use crate::controllers::auth::{locked_out_message, lockout_remaining, record_login_failure};
+use crate::models::{
+    ApiToken, Caller, CreateApiTokenRequest, CreatedApiToken, LoginFailureReason, Scope, Scopes,
+    SetToolPolicyRequest,
+};
+use crate::totp;
+use crate::AppState;
+
+/// Validate session token from request
+async fn validate_session_from_request(
+    state: &web::Data<AppState>,
+    req: &HttpRequest,
+    scope: Scope,
+) -> Result<Caller, HttpResponse> {
+    let token = req
+        .headers()
+        .get("Authorization")
+        .and_then(|h| h.to_str().ok())
+        .map(|s| s.trim_start_matches("Bearer ").to_string());
+
+    let token = match token {
+        Some(t) => t,
+        None => {
+            return Err(HttpResponse::Unauthorized().json(serde_json::json!({
+                "error": "No authorization token provided"
+            })));
+        }
+    };
+
+    match state.db.authenticate(&token).await {
+        Ok(Some(caller)) if caller.scopes.allows(scope) => Ok(caller),
+        Ok(Some(_)) => Err(HttpResponse::Forbidden().json(serde_json::json!({
+            "error": format!("Token lacks the {} scope", scope)
+        }))),
+        Ok(None) => Err(HttpResponse::Unauthorized().json(serde_json::json!({
+            "error": "Invalid or expired session"
+        }))),
+        Err(e) => {
+            log::error!("Session validation error: {}", e);
+            Err(HttpResponse::InternalServerError().json(serde_json::json!({
+                "error": "Internal server error"
+            })))
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+struct ApiTokensResponse {
+    success: bool,
+    tokens: Vec<ApiToken>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+struct ApiTokenResponse {
+    success: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    token: Option<CreatedApiToken>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<String>,
+}
+
+impl ApiTokenResponse {
+    fn error(message: impl Into<String>) -> Self {
+        Self {
+            success: false,
+            token: None,
+            error: Some(message.into()),
+        }
+    }
+}
+
+pub fn config(cfg: &mut web::ServiceConfig) {
+    cfg.service(
+        web::scope("/api/tokens")
+            .route("", web::get().to(list_tokens))
+            .route("", web::post().to(create_token))
+            .route("/{id}", web::delete().to(delete_token))
+            .route("/{id}/tool-policy", web::put().to(set_tool_policy)),
+    );
+}
+
+/// List API tokens (without the tokens themselves)
+async fn list_tokens(data: web::Data<AppState>, req: HttpRequest) -> impl Responder {
+    if let Err(resp) = validate_session_from_request(&data, &req, Scope::Admin).await {
+        return resp;
+    }
+
+    match data.db.list_api_tokens().await {
+        Ok(tokens) => HttpResponse::Ok().json(ApiTokensResponse {
+            success: true,
+            tokens,
+            error: None,
+        }),
+        Err(e) => {
+            log::error!("Failed to list API tokens: {}", e);
+            HttpResponse::InternalServerError().json(ApiTokensResponse {
+                success: false,
+                tokens: vec![],
+                error: Some(format!("Database error: {}", e)),
+            })
+        }
+    }
+}
+
+/// Create a token acting as the caller; the response is the only time the
+/// token is returned
+async fn create_token(
+    data: web::Data<AppState>,
+    req: HttpRequest,
+    body: web::Json<CreateApiTokenRequest>,
+) -> impl Responder {
+    let caller = match validate_session_from_request(&data, &req, Scope::Admin).await {
+        Ok(caller) => caller,
+        Err(resp) => return resp,
+    };
+
+    let name = body.name.trim();
+    if name.is_empty() {
+        return HttpResponse::BadRequest().json(ApiTokenResponse::error("Token name is required"));
+    }
+    if body.scopes.is_empty() {
+        return HttpResponse::BadRequest().json(ApiTokenResponse::error("At least one scope is required"));
+    }
+
+    let mut scopes = Vec::new();
+    for scope in &body.scopes {
+        match Scope::from_str(scope) {
+            Some(s) => scopes.push(s),
+            None => {
+                let valid: Vec<&str> = Scope::all().iter().map(Scope::as_str).collect();
+                return HttpResponse::BadRequest().json(ApiTokenResponse::error(format!(
+                    "Unknown scope '{}'. Valid scopes: {}",
+                    scope,
+                    valid.join(", ")
+                )));
+            }
+        }
+    }
+
+    // An admin token is as powerful as a login session, so it needs the same second factor
+    if scopes.contains(&Scope::Admin) && totp::is_enabled() {
+        let ip = data.rate_limiter.client_ip(&req);
+        if let Some(retry_after) = lockout_remaining(&data, &ip).await {
+            record_login_failure(&data, &ip, None, LoginFailureReason::LockedOut).await;
+            return HttpResponse::TooManyRequests()
+                .insert_header((RETRY_AFTER, retry_after.to_string()))
+                .json(ApiTokenResponse::error(locked_out_message(retry_after)));
+        }
+        if let Err(e) = totp::verify_admin_code(&data.db, caller.user.id, body.totp_code.as_deref()).await {
+            record_login_failure(&data, &ip, None, LoginFailureReason::InvalidTotp).await;
+            return HttpResponse::Forbidden().json(ApiTokenResponse::error(e));
+        }
+    }
+
+    let tool_policy = match body.tool_policy.clone().map(|p| p.normalized()).transpose() {
+        Ok(policy) => policy,
+        Err(e) => return HttpResponse::BadRequest().json(ApiTokenResponse::error(e)),
+    };
+
+    match data.db.list_api_tokens().await {
+        Ok(existing) if existing.iter().any(|t| t.name == name) => {
+            return HttpResponse::Conflict().json(ApiTokenResponse::error(format!(
+                "A token named '{}' already exists",
+                name
+            )));
+        }
+        Ok(_) => {}
+        Err(e) => {
+            log::error!("Failed to list API tokens: {}", e);
+            return HttpResponse::InternalServerError()
+                .json(ApiTokenResponse::error(format!("Database error: {}", e)));
+        }
+    }
+
+    match data.db.create_api_token(caller.user.id, name, &Scopes::new(scopes), tool_policy.as_ref()).await {
+        Ok(created) => {
+            log::info!(
+                "Created API token '{}' with scopes [{}]",
+                created.token_info.name,
+                created.token_info.scopes.to_db_string()
+            );
+            HttpResponse::Ok().json(ApiTokenResponse {
+                success: true,
+                token: Some(created),
+                error: None,
+            })
+        }
+        Err(e) => {
+            log::error!("Failed to create API token: {}", e);
+            HttpResponse::InternalServerError()
+                .json(ApiTokenResponse::error(format!("Database error: {}", e)))
+        }
+    }
+}
+
+/// Set or clear the tool policy of a token; applies from the token's next request
+async fn set_tool_policy(
+    data: web::Data<AppState>,
+    req: HttpRequest,
+    path: web::Path<i64>,
+    body: web::Json<SetToolPolicyRequest>,
+) -> impl Responder {
+    if let Err(resp) = validate_session_from_request(&data, &req, Scope::Admin).await {
+        return resp;
+    }
+
+    let id = path.into_inner();
+    let tool_policy = match body.into_inner().tool_policy.map(|p| p.normalized()).transpose() {
+        Ok(policy) => policy,
+        Err(e) => {
+            return HttpResponse::BadRequest().json(serde_json::json!({
+                "success": false,
+                "error": e
+            }));
+        }
+    };
+
+    match data.db.set_api_token_tool_policy(id, tool_policy.as_ref()).await {
+        Ok(Some(token)) => {
+            log::info!("Updated the tool policy of API token '{}'", token.name);
+            HttpResponse::Ok().json(serde_json::json!({ "success": true, "token": token }))
+        }
+        Ok(None) => HttpResponse::NotFound().json(serde_json::json!({
+            "success": false,
+            "error": "Token not found"
+        })),
+        Err(e) => {
+            log::error!("Failed to update API token tool policy: {}", e);
+            HttpResponse::InternalServerError().json(serde_json::json!({
+                "success": false,
+                "error": format!("Database error: {}", e)
+            }))
+        }
+    }
+}
+
+/// Revoke a token
+async fn delete_token(
+    data: web::Data<AppState>,
+    req: HttpRequest,
+    path: web::Path<i64>,
+) -> impl Responder {
+    if let Err(resp) = validate_session_from_request(&data, &req, Scope::Admin).await {
+        return resp;
+    }
+
+    let id = path.into_inner();
+    match data.db.delete_api_token(id).await {
+        Ok(true) => {
+            log::info!("Revoked API token {}", id);
+            HttpResponse::Ok().json(serde_json::json!({ "success": true }))
+        }
+        Ok(false) => HttpResponse::NotFound().json(serde_json::json!({
+            "success": false,
+            "error": "Token not found"
+        })),
+        Err(e) => {
+            log::error!("Failed to delete API token: {}", e);
+            HttpResponse::InternalServerError().json(serde_json::json!({
+                "success": false,
+                "error": format!("Database error: {}", e)
+            }))
+        }
+    }
+}
@@ -0,0 +1,282 @@
+//! Operator approvals for value-moving tool calls
+
+use actix_web::{web, HttpRequest, HttpResponse, Responder};
+use serde::{Deserialize, Serialize};
+
+use crate::models::{Approval, ApprovalStatus, Caller, DecideApprovalRequest, Scope};
+use crate::AppState;
+
+/// Validate session token from request
+async fn validate_session_from_request(
+    state: &web::Data<AppState>,
+    req: &HttpRequest,
+    scope: Scope,
+) -> Result<Caller, HttpResponse> {
+    let token = req
+        .headers()
+        .get("Authorization")
+        .and_then(|h| h.to_str().ok())
+        .map(|s| s.trim_start_matches("Bearer ").to_string());
+
+    let token = match token {
+        Some(t) => t,
+        None => {
+            return Err(HttpResponse::Unauthorized().json(serde_json::json!({
+                "error": "No authorization token provided"
+            })));
+        }
+    };
+
+    match state.db.authenticate(&token).await {
+        Ok(Some(caller)) if caller.scopes.allows(scope) => Ok(caller),
+        Ok(Some(_)) => Err(HttpResponse::Forbidden().json(serde_json::json!({
+            "error": format!("Token lacks the {} scope", scope)
+        }))),
+        Ok(None) => Err(HttpResponse::Unauthorized().json(serde_json::json!({
+            "error": "Invalid or expired session"
+        }))),
+        Err(e) => {
+            log::error!("Session validation error: {}", e);
+            Err(HttpResponse::InternalServerError().json(serde_json::json!({
+                "error": "Internal server error"
+            })))
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct ApprovalQuery {
+    status: Option<String>,
+    limit: Option<i64>,
+}
+
+#[derive(Debug, Serialize)]
+struct ApprovalListResponse {
+    success: bool,
+    approvals: Vec<Approval>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+struct ApprovalResponse {
+    success: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    approval: Option<Approval>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<String>,
+}
+
+impl ApprovalResponse {
+    fn error(message: impl Into<String>) -> Self {
+        Self {
+            success: false,
+            approval: None,
+            error: Some(message.into()),
+        }
+    }
+}
+
+/// Approvals, newest first; a member's own only. Filters: status, limit (default 50).
+async fn list_approvals(
+    data: web::Data<AppState>,
+    req: HttpRequest,
+    query: web::Query<ApprovalQuery>,
+) -> impl Responder {
+    let caller = match validate_session_from_request(&data, &req, Scope::Read).await {
+        Ok(caller) => caller,
+        Err(resp) => return resp,
+    };
+
+    let status = match query.status.as_deref() {
+        None => None,
+        Some(s) => match ApprovalStatus::from_str(s) {
+            Some(status) => Some(status),
+            None => {
+                return HttpResponse::BadRequest().json(ApprovalListResponse {
+                    success: false,
+                    approvals: vec![],
+                    error: Some(format!(
+                        "Unknown status '{}'. Use pending, approved, rejected or expired",
+                        s
+                    )),
+                });
+            }
+        },
+    };
+    let limit = query.limit.unwrap_or(50).clamp(1, 500);
+
+    match data.db.list_approvals(status, caller.owner_filter(), limit).await {
+        Ok(approvals) => HttpResponse::Ok().json(ApprovalListResponse {
+            success: true,
+            approvals,
+            error: None,
+        }),
+        Err(e) => {
+            log::error!("Failed to list approvals: {}", e);
+            HttpResponse::InternalServerError().json(ApprovalListResponse {
+                success: false,
+                approvals: vec![],
+                error: Some(format!("Database error: {}", e)),
+            })
+        }
+    }
+}
+
+/// A single approval
+async fn get_approval(
+    data: web::Data<AppState>,
+    req: HttpRequest,
+    path: web::Path<i64>,
+) -> impl Responder {
+    let caller = match validate_session_from_request(&data, &req, Scope::Read).await {
+        Ok(caller) => caller,
+        Err(resp) => return resp,
+    };
+
+    let id = path.into_inner();
+    match data.db.get_approval(id).await {
+        // Other users' approvals look the same as missing ones
+        Ok(Some(approval)) if caller.can_access(approval.user_id) => HttpResponse::Ok().json(ApprovalResponse {
+            success: true,
+            approval: Some(approval),
+            error: None,
+        }),
+        Ok(_) => HttpResponse::NotFound().json(ApprovalResponse::error("Approval not found")),
+        Err(e) => {
+            log::error!("Failed to load approval {}: {}", id, e);
+            HttpResponse::InternalServerError()
+                .json(ApprovalResponse::error(format!("Database error: {}", e)))
+        }
+    }
+}
+
+/// Approve or reject a pending approval; the waiting tool picks it up.
+/// Only admins decide: a member could otherwise approve their own spend.
+async fn decide_approval(
+    data: web::Data<AppState>,
+    req: HttpRequest,
+    path: web::Path<i64>,
+    body: web::Json<DecideApprovalRequest>,
+) -> impl Responder {
+    if let Err(resp) = validate_session_from_request(&data, &req, Scope::Admin).await {
+        return resp;
+    }
+
+    let id = path.into_inner();
+    let status = if body.approve {
+        ApprovalStatus::Approved
+    } else {
+        ApprovalStatus::Rejected
+    };
+
+    let decided = match data.db.decide_approval(id, status).await {
+        Ok(decided) => decided,
+        Err(e) => {
+            log::error!("Failed to decide approval {}: {}", id, e);
+            return HttpResponse::InternalServerError()
+                .json(ApprovalResponse::error(format!("Database error: {}", e)));
+        }
+    };
+
+    match data.db.get_approval(id).await {
+        Ok(Some(approval)) if decided => {
+            log::info!("Approval #{} {} by operator", id, status.as_str());
+            HttpResponse::Ok().json(ApprovalResponse {
+                success: true,
+                approval: Some(approval),
+                error: None,
+            })
+        }
+        Ok(Some(approval)) => HttpResponse::Conflict().json(ApprovalResponse {
+            success: false,
+            error: Some(format!("Approval is already {}", approval.status.as_str())),
+            approval: Some(approval),
+        }),
+        Ok(None) => HttpResponse::NotFound().json(ApprovalResponse::error("Approval not found")),
+        Err(e) => {
+            log::error!("Failed to load approval {}: {}", id, e);
+            HttpResponse::InternalServerError()
+                .json(ApprovalResponse::error(format!("Database error: {}", e)))
+        }
+    }
+}
+
+pub fn config(cfg: &mut web::ServiceConfig) {
+    cfg.service(
+        web::scope("/api/approvals")
+            .route("", web::get().to(list_approvals))
+            .route("/{id}", web::get().to(get_approval))
+            .route("/{id}", web::post().to(decide_approval)),
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::Database;
+    use crate::models::{NewApproval, Scopes, UserRole};
+    use actix_web::{test, App};
+    use serde_json::{json, Value};
+    use std::sync::Arc;
+
+    /// A bearer token for a new user with `role`
+    async fn token(db: &Database, address: &str, role: UserRole, scopes: Scopes) -> (i64, String) {
+        let user = db.create_user(address, None, role).await.unwrap();
+        (user.id, db.create_api_token(user.id, address, &scopes, None).await.unwrap().token)
+    }
+
+    fn call(method: &str, uri: &str, token: &str) -> test::TestRequest {
+        let method = actix_web::http::Method::from_bytes(method.as_bytes()).unwrap();
+        test::TestRequest::default()
+            .method(method)
+            .uri(uri)
+            .insert_header(("Authorization", format!("Bearer {}", token)))
+    }
+
+    #[actix_web::test]
+    async fn test_members_see_their_own_approvals_and_only_admins_decide() {
+        let db = Arc::new(Database::new(":memory:").unwrap());
+        let member_scopes = || Scopes::new([Scope::Read, Scope::WalletSign]);
+        let (alice_id, alice) = token(&db, "0xa11ce", UserRole::Member, member_scopes()).await;
+        let (_, bob) = token(&db, "0xb0b", UserRole::Member, member_scopes()).await;
+        let (_, admin) = token(&db, "0xad", UserRole::Admin, Scopes::full()).await;
+        let app = test::init_service(
+            App::new().app_data(web::Data::new(AppState::for_tests(db.clone()).await)).configure(config),
+        )
+        .await;
+
+        let request = |user_id| NewApproval {
+            tool_name: "swap".to_string(),
+            tool_call_id: None,
+            channel_id: None,
+            session_id: None,
+            description: "Swap 500 USDC for WETH on base".to_string(),
+            amount_usd: Some(500.0),
+            reason: "$500.00 is above the $100.00 approval threshold".to_string(),
+            user_id,
+        };
+        let own = db.create_approval(&request(Some(alice_id))).await.unwrap();
+        db.create_approval(&request(None)).await.unwrap();
+
+        for (token, count) in [(&alice, 1), (&bob, 0), (&admin, 2)] {
+            let listed: Value =
+                test::call_and_read_body_json(&app, call("GET", "/api/approvals", token).to_request()).await;
+            assert_eq!(listed["approvals"].as_array().unwrap().len(), count);
+        }
+
+        let uri = format!("/api/approvals/{}", own.id);
+        assert_eq!(test::call_service(&app, call("GET", &uri, &alice).to_request()).await.status(), 200);
+        assert_eq!(test::call_service(&app, call("GET", &uri, &bob).to_request()).await.status(), 404);
+
+        // The member who asked can't approve their own spend
+        let approve = json!({ "approve": true });
+        let resp = test::call_service(&app, call("POST", &uri, &alice).set_json(&approve).to_request()).await;
+        assert_eq!(resp.status(), 403);
+        assert_eq!(db.get_approval(own.id).await.unwrap().unwrap().status, ApprovalStatus::Pending);
+
+        let decided: Value =
+            test::call_and_read_body_json(&app, call("POST", &uri, &admin).set_json(&approve).to_request()).await;
+        assert_eq!(decided["approval"]["status"], "approved");
+    }
+}
@@ -0,0 +1,210 @@
+//! Audit log API endpoints: tool invocations and failed sign-ins
+
+use actix_web::{web, HttpRequest, HttpResponse, Responder};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::models::{LoginFailure, Scope, ToolInvocation, ToolInvocationQuery, ToolOutcome};
+use crate::AppState;
+
+/// Validate session token from request
+async fn validate_session_from_request(
+    state: &web::Data<AppState>,
+    req: &HttpRequest,
+    scope: Scope,
+) -> Result<(), HttpResponse> {
+    let token = req
+        .headers()
+        .get("Authorization")
+        .and_then(|h| h.to_str().ok())
+        .map(|s| s.trim_start_matches("Bearer ").to_string());
+
+    let token = match token {
+        Some(t) => t,
+        None => {
+            return Err(HttpResponse::Unauthorized().json(serde_json::json!({
+                "error": "No authorization token provided"
+            })));
+        }
+    };
+
+    match state.db.authorize(&token).await {
+        Ok(Some(scopes)) if scopes.allows(scope) => Ok(()),
+        Ok(Some(_)) => Err(HttpResponse::Forbidden().json(serde_json::json!({
+            "error": format!("Token lacks the {} scope", scope)
+        }))),
+        Ok(None) => Err(HttpResponse::Unauthorized().json(serde_json::json!({
+            "error": "Invalid or expired session"
+        }))),
+        Err(e) => {
+            log::error!("Session validation error: {}", e);
+            Err(HttpResponse::InternalServerError().json(serde_json::json!({
+                "error": "Internal server error"
+            })))
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+struct InvocationListResponse {
+    success: bool,
+    invocations: Vec<ToolInvocation>,
+    total: i64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<String>,
+}
+
+impl InvocationListResponse {
+    fn error(error: String) -> Self {
+        InvocationListResponse {
+            success: false,
+            invocations: vec![],
+            total: 0,
+            error: Some(error),
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+struct InvocationResponse {
+    success: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    invocation: Option<ToolInvocation>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+struct LoginFailureListResponse {
+    success: bool,
+    failures: Vec<LoginFailure>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct LoginFailureQuery {
+    limit: Option<i64>,
+}
+
+/// Stored timestamps are UTC RFC 3339, so bounds are compared in that form
+fn normalize_timestamp(name: &str, value: &str) -> Result<String, String> {
+    DateTime::parse_from_rfc3339(value)
+        .map(|t| t.with_timezone(&Utc).to_rfc3339())
+        .map_err(|_| format!("Invalid {} '{}': expected an RFC 3339 timestamp", name, value))
+}
+
+/// Audited tool calls, newest first. Arguments may hold file contents and
+/// other private data, so this needs the admin scope.
+/// Filters: tool, group, outcome, session_id, job_id, channel_id, request_id,
+/// since, until, limit, offset.
+async fn list_invocations(
+    data: web::Data<AppState>,
+    req: HttpRequest,
+    query: web::Query<ToolInvocationQuery>,
+) -> impl Responder {
+    if let Err(resp) = validate_session_from_request(&data, &req, Scope::Admin).await {
+        return resp;
+    }
+
+    let mut query = query.into_inner();
+    if let Some(outcome) = &query.outcome
+        && ToolOutcome::from_str(outcome).is_none()
+    {
+        return HttpResponse::BadRequest().json(InvocationListResponse::error(format!(
+            "Unknown outcome '{}'. Use success, error or denied",
+            outcome
+        )));
+    }
+    for (name, bound) in [("since", &mut query.since), ("until", &mut query.until)] {
+        if let Some(value) = bound.as_deref() {
+            match normalize_timestamp(name, value) {
+                Ok(normalized) => *bound = Some(normalized),
+                Err(e) => return HttpResponse::BadRequest().json(InvocationListResponse::error(e)),
+            }
+        }
+    }
+
+    match data.db.list_tool_invocations(&query).await {
+        Ok((invocations, total)) => HttpResponse::Ok().json(InvocationListResponse {
+            success: true,
+            invocations,
+            total,
+            error: None,
+        }),
+        Err(e) => {
+            log::error!("Failed to list tool invocations: {}", e);
+            HttpResponse::InternalServerError().json(InvocationListResponse::error(format!("Database error: {}", e)))
+        }
+    }
+}
+
+/// A single audited tool call
+async fn get_invocation(
+    data: web::Data<AppState>,
+    req: HttpRequest,
+    path: web::Path<i64>,
+) -> impl Responder {
+    if let Err(resp) = validate_session_from_request(&data, &req, Scope::Admin).await {
+        return resp;
+    }
+
+    let id = path.into_inner();
+    match data.db.get_tool_invocation(id).await {
+        Ok(Some(invocation)) => HttpResponse::Ok().json(InvocationResponse {
+            success: true,
+            invocation: Some(invocation),
+            error: None,
+        }),
+        Ok(None) => HttpResponse::NotFound().json(InvocationResponse {
+            success: false,
+            invocation: None,
+            error: Some("Tool invocation not found".to_string()),
+        }),
+        Err(e) => {
+            log::error!("Failed to load tool invocation {}: {}", id, e);
+            HttpResponse::InternalServerError().json(InvocationResponse {
+                success: false,
+                invocation: None,
+                error: Some(format!("Database error: {}", e)),
+            })
+        }
+    }
+}
+
+/// Refused sign-ins and second-factor checks, newest first (`limit`, default 100)
+async fn list_login_failures(
+    data: web::Data<AppState>,
+    req: HttpRequest,
+    query: web::Query<LoginFailureQuery>,
+) -> impl Responder {
+    if let Err(resp) = validate_session_from_request(&data, &req, Scope::Admin).await {
+        return resp;
+    }
+
+    let limit = query.limit.unwrap_or(100).clamp(1, 1000);
+    match data.db.list_login_failures(limit).await {
+        Ok(failures) => HttpResponse::Ok().json(LoginFailureListResponse {
+            success: true,
+            failures,
+            error: None,
+        }),
+        Err(e) => {
+            log::error!("Failed to list sign-in failures: {}", e);
+            HttpResponse::InternalServerError().json(LoginFailureListResponse {
+                success: false,
+                failures: vec![],
+                error: Some(format!("Database error: {}", e)),
+            })
+        }
+    }
+}
+
+pub fn config(cfg: &mut web::ServiceConfig) {
+    cfg.service(
+        web::scope("/api/audit")
+            .route("", web::get().to(list_invocations))
+            .route("/logins", web::get().to(list_login_failures))
+            .route("/{id}", web::get().to(get_invocation)),
+    );
+}
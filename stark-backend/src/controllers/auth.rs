@@ -1,13 +1,21 @@
+use actix_web::http::header::RETRY_AFTER;
 use actix_web::{web, HttpRequest, HttpResponse, Responder};
 use chrono::Utc;
 use ethers::core::types::Signature;
 use ethers::utils::hash_message;
 use serde::{Deserialize, Serialize};
+use std::time::Duration;
 
+use crate::config;
+use crate::models::{LoginFailureReason, SessionResponse, UserRole};
+use crate::totp;
 use crate::AppState;
 
 const SERVICE_NAME: &str = "StarkBot";
 
+/// Longest sign-in lockout, however many failures came before it
+const MAX_LOCKOUT_SECS: u64 = 60 * 60;
+
 #[derive(Deserialize)]
 pub struct GenerateChallengeRequest {
     public_address: String,
@@ -18,6 +26,9 @@ pub struct ChallengeResponse {
     success: bool,
     #[serde(skip_serializing_if = "Option::is_none")]
     challenge: Option<String>,
+    /// Whether `validate_auth` will also need a TOTP code
+    #[serde(skip_serializing_if = "Option::is_none")]
+    totp_required: Option<bool>,
     #[serde(skip_serializing_if = "Option::is_none")]
     error: Option<String>,
 }
@@ -27,6 +38,9 @@ pub struct ValidateAuthRequest {
     public_address: String,
     challenge: String,
     signature: String,
+    /// Current code from the authenticator app, when a TOTP secret is configured
+    #[serde(default)]
+    totp_code: Option<String>,
 }
 
 #[derive(Serialize)]
@@ -53,6 +67,17 @@ pub struct LogoutResponse {
 #[derive(Serialize)]
 pub struct ValidateResponse {
     valid: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    session: Option<SessionResponse>,
+}
+
+impl ValidateResponse {
+    fn invalid() -> Self {
+        ValidateResponse {
+            valid: false,
+            session: None,
+        }
+    }
 }
 
 pub fn config(cfg: &mut web::ServiceConfig) {
@@ -63,6 +88,7 @@ pub fn config(cfg: &mut web::ServiceConfig) {
             .route("/logout", web::post().to(logout))
             .route("/validate", web::get().to(validate)),
     );
+    cfg.service(web::resource("/api/session/refresh").route(web::post().to(refresh)));
 }
 
 fn generate_challenge_text(public_address: &str, unix_timestamp: i64) -> String {
@@ -84,17 +110,83 @@ fn recover_address(msg: &str, signature: &str) -> Option<String> {
     Some(format!("{:?}", recovered).to_lowercase())
 }
 
+/// Lockout after `failures` in a row: none within the free allowance, then
+/// `base` doubled for every failure past it, up to `MAX_LOCKOUT_SECS`
+fn lockout_for(failures: i64, max_failures: i64, base: Duration) -> Option<Duration> {
+    if base.is_zero() || failures <= max_failures {
+        return None;
+    }
+    let doublings = (failures - max_failures - 1).min(16) as u32;
+    Some(base.saturating_mul(2u32.pow(doublings)).min(Duration::from_secs(MAX_LOCKOUT_SECS)))
+}
+
+/// Seconds until `ip` may try to sign in again, if it is locked out
+pub(crate) async fn lockout_remaining(state: &AppState, ip: &str) -> Option<u64> {
+    match state.db.get_login_lockout(ip).await {
+        Ok(until) => until.map(|until| (until - Utc::now()).num_seconds().max(1) as u64),
+        Err(e) => {
+            log::error!("Failed to read sign-in lockout for {}: {}", ip, e);
+            None
+        }
+    }
+}
+
+/// Audit a refused sign-in and lock `ip` out once it has used up its free failures
+pub(crate) async fn record_login_failure(
+    state: &AppState,
+    ip: &str,
+    public_address: Option<&str>,
+    reason: LoginFailureReason,
+) {
+    log::warn!("[AUTH] Refused sign-in from {} ({})", ip, reason.as_str());
+    let failures = match state.db.record_login_failure(ip, public_address, reason).await {
+        Ok(failures) => failures,
+        Err(e) => {
+            log::error!("Failed to record sign-in failure: {}", e);
+            return;
+        }
+    };
+    if reason == LoginFailureReason::LockedOut {
+        return;
+    }
+    if let Some(lockout) = lockout_for(failures, config::login_max_failures(), config::login_lockout()) {
+        log::warn!("[AUTH] Locking out {} for {}s after {} failed sign-ins", ip, lockout.as_secs(), failures);
+        let until = Utc::now() + chrono::Duration::seconds(lockout.as_secs() as i64);
+        if let Err(e) = state.db.set_login_lockout(ip, until).await {
+            log::error!("Failed to lock out {}: {}", ip, e);
+        }
+    }
+}
+
+pub(crate) fn locked_out_message(retry_after: u64) -> String {
+    format!("Too many failed sign-in attempts; try again in {} seconds", retry_after)
+}
+
 async fn generate_challenge(
     state: web::Data<AppState>,
+    req: HttpRequest,
     body: web::Json<GenerateChallengeRequest>,
 ) -> impl Responder {
     let public_address = body.public_address.trim().to_lowercase();
 
+    let ip = state.rate_limiter.client_ip(&req);
+    if let Some(retry_after) = lockout_remaining(&state, &ip).await {
+        return HttpResponse::TooManyRequests()
+            .insert_header((RETRY_AFTER, retry_after.to_string()))
+            .json(ChallengeResponse {
+                success: false,
+                challenge: None,
+                totp_required: None,
+                error: Some(locked_out_message(retry_after)),
+            });
+    }
+
     // Validate it looks like an Ethereum address
     if !public_address.starts_with("0x") || public_address.len() != 42 {
         return HttpResponse::BadRequest().json(ChallengeResponse {
             success: false,
             challenge: None,
+            totp_required: None,
             error: Some("Invalid public address".to_string()),
         });
     }
@@ -102,10 +194,17 @@ async fn generate_challenge(
     let unix_timestamp = Utc::now().timestamp();
     let challenge = generate_challenge_text(&public_address, unix_timestamp);
 
-    match state.db.create_or_update_challenge(&public_address, &challenge) {
+    // Only admins sign in with a second factor
+    let is_admin = matches!(
+        state.db.get_user_by_address(&public_address).await,
+        Ok(Some(user)) if user.role == UserRole::Admin
+    );
+
+    match state.db.create_or_update_challenge(&public_address, &challenge).await {
         Ok(_) => HttpResponse::Ok().json(ChallengeResponse {
             success: true,
             challenge: Some(challenge),
+            totp_required: Some(is_admin && totp::is_enabled()),
             error: None,
         }),
         Err(e) => {
@@ -113,6 +212,7 @@ async fn generate_challenge(
             HttpResponse::InternalServerError().json(ChallengeResponse {
                 success: false,
                 challenge: None,
+                totp_required: None,
                 error: Some("Database error".to_string()),
             })
         }
@@ -121,14 +221,29 @@ async fn generate_challenge(
 
 async fn validate_auth(
     state: web::Data<AppState>,
+    req: HttpRequest,
     body: web::Json<ValidateAuthRequest>,
 ) -> impl Responder {
     let public_address = body.public_address.trim().to_lowercase();
     let challenge = &body.challenge;
     let signature = &body.signature;
 
+    let ip = state.rate_limiter.client_ip(&req);
+    if let Some(retry_after) = lockout_remaining(&state, &ip).await {
+        record_login_failure(&state, &ip, Some(&public_address), LoginFailureReason::LockedOut).await;
+        return HttpResponse::TooManyRequests()
+            .insert_header((RETRY_AFTER, retry_after.to_string()))
+            .json(LoginResponse {
+                success: false,
+                token: None,
+                expires_at: None,
+                error: Some(locked_out_message(retry_after)),
+            });
+    }
+
     // Validate it looks like an Ethereum address
     if !public_address.starts_with("0x") || public_address.len() != 42 {
+        record_login_failure(&state, &ip, None, LoginFailureReason::InvalidAddress).await;
         return HttpResponse::BadRequest().json(LoginResponse {
             success: false,
             token: None,
@@ -137,21 +252,34 @@ async fn validate_auth(
         });
     }
 
-    // Check that this address is the admin address
-    let admin_address = state.config.login_admin_public_address.to_lowercase();
-    if public_address != admin_address {
-        return HttpResponse::Unauthorized().json(LoginResponse {
-            success: false,
-            token: None,
-            expires_at: None,
-            error: Some("Unauthorized wallet address".to_string()),
-        });
-    }
+    // Only addresses added as users may sign in
+    let user = match state.db.get_user_by_address(&public_address).await {
+        Ok(Some(user)) => user,
+        Ok(None) => {
+            record_login_failure(&state, &ip, Some(&public_address), LoginFailureReason::UnauthorizedAddress).await;
+            return HttpResponse::Unauthorized().json(LoginResponse {
+                success: false,
+                token: None,
+                expires_at: None,
+                error: Some("Unauthorized wallet address".to_string()),
+            });
+        }
+        Err(e) => {
+            log::error!("Failed to look up user: {}", e);
+            return HttpResponse::InternalServerError().json(LoginResponse {
+                success: false,
+                token: None,
+                expires_at: None,
+                error: Some("Database error".to_string()),
+            });
+        }
+    };
 
     // Verify the challenge exists and matches
-    match state.db.validate_challenge(&public_address, challenge) {
+    match state.db.validate_challenge(&public_address, challenge).await {
         Ok(true) => {}
         Ok(false) => {
+            record_login_failure(&state, &ip, Some(&public_address), LoginFailureReason::InvalidChallenge).await;
             return HttpResponse::Unauthorized().json(LoginResponse {
                 success: false,
                 token: None,
@@ -173,6 +301,7 @@ async fn validate_auth(
     // Verify signature
     let recovered_address = recover_address(challenge, signature);
     if recovered_address.as_deref() != Some(public_address.as_str()) {
+        record_login_failure(&state, &ip, Some(&public_address), LoginFailureReason::InvalidSignature).await;
         return HttpResponse::Unauthorized().json(LoginResponse {
             success: false,
             token: None,
@@ -181,11 +310,29 @@ async fn validate_auth(
         });
     }
 
+    // Admin login sessions carry every scope, admin included, so they need
+    // the second factor when one is configured. The challenge stays valid so
+    // a mistyped code can be retried without signing again.
+    if user.role == UserRole::Admin
+        && let Err(e) = totp::verify_admin_code(&state.db, user.id, body.totp_code.as_deref()).await
+    {
+        record_login_failure(&state, &ip, Some(&public_address), LoginFailureReason::InvalidTotp).await;
+        return HttpResponse::Unauthorized().json(LoginResponse {
+            success: false,
+            token: None,
+            expires_at: None,
+            error: Some(e),
+        });
+    }
+
     // Delete the used challenge
-    let _ = state.db.delete_challenge(&public_address);
+    let _ = state.db.delete_challenge(&public_address).await;
+    if let Err(e) = state.db.clear_login_failures(&ip).await {
+        log::error!("Failed to clear sign-in failures for {}: {}", ip, e);
+    }
 
     // Create session
-    match state.db.create_session_for_address(Some(&public_address)) {
+    match state.db.create_session_for_user(&user).await {
         Ok(session) => HttpResponse::Ok().json(LoginResponse {
             success: true,
             token: Some(session.token),
@@ -205,7 +352,7 @@ async fn validate_auth(
 }
 
 async fn logout(state: web::Data<AppState>, body: web::Json<LogoutRequest>) -> impl Responder {
-    match state.db.delete_session(&body.token) {
+    match state.db.delete_session(&body.token).await {
         Ok(_) => HttpResponse::Ok().json(LogoutResponse { success: true }),
         Err(e) => {
             log::error!("Failed to delete session: {}", e);
@@ -224,16 +371,78 @@ async fn validate(state: web::Data<AppState>, req: HttpRequest) -> impl Responde
     let token = match token {
         Some(t) => t,
         None => {
-            return HttpResponse::Ok().json(ValidateResponse { valid: false });
+            return HttpResponse::Ok().json(ValidateResponse::invalid());
         }
     };
 
-    match state.db.validate_session(&token) {
-        Ok(Some(_)) => HttpResponse::Ok().json(ValidateResponse { valid: true }),
-        Ok(None) => HttpResponse::Ok().json(ValidateResponse { valid: false }),
+    match state.db.validate_session(&token).await {
+        Ok(Some(session)) => HttpResponse::Ok().json(ValidateResponse {
+            valid: true,
+            session: Some(session.into()),
+        }),
+        Ok(None) => HttpResponse::Ok().json(ValidateResponse::invalid()),
         Err(e) => {
             log::error!("Failed to validate session: {}", e);
-            HttpResponse::Ok().json(ValidateResponse { valid: false })
+            HttpResponse::Ok().json(ValidateResponse::invalid())
         }
     }
 }
+
+/// Extend the caller's session by the configured TTL. Needed to stay logged in
+/// when sliding expiry is off; harmless when it is on.
+async fn refresh(state: web::Data<AppState>, req: HttpRequest) -> impl Responder {
+    let token = req
+        .headers()
+        .get("Authorization")
+        .and_then(|h| h.to_str().ok())
+        .map(|s| s.trim_start_matches("Bearer ").to_string());
+
+    let unauthorized = |error: &str| {
+        HttpResponse::Unauthorized().json(LoginResponse {
+            success: false,
+            token: None,
+            expires_at: None,
+            error: Some(error.to_string()),
+        })
+    };
+
+    let token = match token {
+        Some(t) => t,
+        None => return unauthorized("No authorization token provided"),
+    };
+
+    match state.db.refresh_session(&token).await {
+        Ok(Some(session)) => HttpResponse::Ok().json(LoginResponse {
+            success: true,
+            token: None,
+            expires_at: Some(session.expires_at.timestamp()),
+            error: None,
+        }),
+        Ok(None) => unauthorized("Invalid or expired session"),
+        Err(e) => {
+            log::error!("Failed to refresh session: {}", e);
+            HttpResponse::InternalServerError().json(LoginResponse {
+                success: false,
+                token: None,
+                expires_at: None,
+                error: Some("Database error".to_string()),
+            })
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_lockout_for() {
+        let base = Duration::from_secs(60);
+        assert_eq!(lockout_for(5, 5, base), None);
+        assert_eq!(lockout_for(6, 5, base), Some(Duration::from_secs(60)));
+        assert_eq!(lockout_for(7, 5, base), Some(Duration::from_secs(120)));
+        assert_eq!(lockout_for(9, 5, base), Some(Duration::from_secs(480)));
+        assert_eq!(lockout_for(100, 5, base), Some(Duration::from_secs(MAX_LOCKOUT_SECS)));
+        assert_eq!(lockout_for(100, 5, Duration::ZERO), None);
+    }
+}
@@ -1,7 +1,9 @@
 use actix_web::{web, HttpRequest, HttpResponse, Responder};
 use serde::Serialize;
 
-use crate::models::{ChannelResponse, ChannelType, CreateChannelRequest, UpdateChannelRequest};
+use crate::models::{
+    ChannelResponse, ChannelType, CreateChannelRequest, Scope, UpdateChannelRequest,
+};
 use crate::AppState;
 
 #[derive(Serialize)]
@@ -35,9 +37,10 @@ pub fn config(cfg: &mut web::ServiceConfig) {
     );
 }
 
-fn validate_session_from_request(
+async fn validate_session_from_request(
     state: &web::Data<AppState>,
     req: &HttpRequest,
+    scope: Scope,
 ) -> Result<(), HttpResponse> {
     let token = req
         .headers()
@@ -56,8 +59,13 @@ fn validate_session_from_request(
         }
     };
 
-    match state.db.validate_session(&token) {
-        Ok(Some(_)) => Ok(()),
+    match state.db.authorize(&token).await {
+        Ok(Some(scopes)) if scopes.allows(scope) => Ok(()),
+        Ok(Some(_)) => Err(HttpResponse::Forbidden().json(ChannelsListResponse {
+            success: false,
+            channels: None,
+            error: Some(format!("Token lacks the {} scope", scope)),
+        })),
         Ok(None) => Err(HttpResponse::Unauthorized().json(ChannelsListResponse {
             success: false,
             channels: None,
@@ -75,11 +83,11 @@ fn validate_session_from_request(
 }
 
 async fn list_channels(state: web::Data<AppState>, req: HttpRequest) -> impl Responder {
-    if let Err(resp) = validate_session_from_request(&state, &req) {
+    if let Err(resp) = validate_session_from_request(&state, &req, Scope::Read).await {
         return resp;
     }
 
-    match state.db.list_channels() {
+    match state.db.list_channels().await {
         Ok(channels) => {
             let channel_manager = state.gateway.channel_manager();
             let responses: Vec<ChannelResponse> = channels
@@ -112,13 +120,13 @@ async fn get_channel(
     req: HttpRequest,
     path: web::Path<i64>,
 ) -> impl Responder {
-    if let Err(resp) = validate_session_from_request(&state, &req) {
+    if let Err(resp) = validate_session_from_request(&state, &req, Scope::Read).await {
         return resp;
     }
 
     let id = path.into_inner();
 
-    match state.db.get_channel(id) {
+    match state.db.get_channel(id).await {
         Ok(Some(channel)) => {
             let channel_manager = state.gateway.channel_manager();
             let running = channel_manager.is_running(channel.id);
@@ -151,21 +159,21 @@ async fn create_channel(
     req: HttpRequest,
     body: web::Json<CreateChannelRequest>,
 ) -> impl Responder {
-    if let Err(resp) = validate_session_from_request(&state, &req) {
+    if let Err(resp) = validate_session_from_request(&state, &req, Scope::Admin).await {
         return resp;
     }
 
     // Validate channel type
-    if ChannelType::from_str(&body.channel_type).is_none() {
+    let Some(channel_type) = ChannelType::from_str(&body.channel_type) else {
         return HttpResponse::BadRequest().json(ChannelOperationResponse {
             success: false,
             channel: None,
-            error: Some("Invalid channel type. Valid options: telegram, slack, discord".to_string()),
+            error: Some("Invalid channel type. Valid options: telegram, slack, discord, farcaster".to_string()),
         });
-    }
+    };
 
-    // Validate bot token is not empty
-    if body.bot_token.trim().is_empty() {
+    // Validate bot token is not empty (Discord and Farcaster can fall back to a shared API key)
+    if body.bot_token.trim().is_empty() && !matches!(channel_type, ChannelType::Discord | ChannelType::Farcaster) {
         return HttpResponse::BadRequest().json(ChannelOperationResponse {
             success: false,
             channel: None,
@@ -192,12 +200,21 @@ async fn create_channel(
         });
     }
 
+    // Farcaster replies are published through a Neynar signer
+    if channel_type == ChannelType::Farcaster && body.app_token.as_ref().is_none_or(|t| t.trim().is_empty()) {
+        return HttpResponse::BadRequest().json(ChannelOperationResponse {
+            success: false,
+            channel: None,
+            error: Some("Farcaster channels require a Neynar signer UUID as app_token".to_string()),
+        });
+    }
+
     match state.db.create_channel(
         &body.channel_type,
         &body.name,
         &body.bot_token,
         body.app_token.as_deref(),
-    ) {
+    ).await {
         Ok(channel) => HttpResponse::Created().json(ChannelOperationResponse {
             success: true,
             channel: Some(channel.into()),
@@ -228,7 +245,7 @@ async fn update_channel(
     path: web::Path<i64>,
     body: web::Json<UpdateChannelRequest>,
 ) -> impl Responder {
-    if let Err(resp) = validate_session_from_request(&state, &req) {
+    if let Err(resp) = validate_session_from_request(&state, &req, Scope::Admin).await {
         return resp;
     }
 
@@ -265,7 +282,7 @@ async fn update_channel(
         body.enabled,
         body.bot_token.as_deref(),
         app_token_update,
-    ) {
+    ).await {
         Ok(Some(channel)) => {
             let channel_manager = state.gateway.channel_manager();
             let running = channel_manager.is_running(channel.id);
@@ -298,7 +315,7 @@ async fn delete_channel(
     req: HttpRequest,
     path: web::Path<i64>,
 ) -> impl Responder {
-    if let Err(resp) = validate_session_from_request(&state, &req) {
+    if let Err(resp) = validate_session_from_request(&state, &req, Scope::Admin).await {
         return resp;
     }
 
@@ -310,7 +327,7 @@ async fn delete_channel(
         let _ = channel_manager.stop_channel(id).await;
     }
 
-    match state.db.delete_channel(id) {
+    match state.db.delete_channel(id).await {
         Ok(deleted) => {
             if deleted {
                 HttpResponse::Ok().json(ChannelOperationResponse {
@@ -342,14 +359,14 @@ async fn start_channel(
     req: HttpRequest,
     path: web::Path<i64>,
 ) -> impl Responder {
-    if let Err(resp) = validate_session_from_request(&state, &req) {
+    if let Err(resp) = validate_session_from_request(&state, &req, Scope::Admin).await {
         return resp;
     }
 
     let id = path.into_inner();
 
     // Get channel from database
-    let channel = match state.db.get_channel(id) {
+    let channel = match state.db.get_channel(id).await {
         Ok(Some(ch)) => ch,
         Ok(None) => {
             return HttpResponse::NotFound().json(ChannelOperationResponse {
@@ -373,7 +390,7 @@ async fn start_channel(
     match channel_manager.start_channel(channel.clone()).await {
         Ok(()) => {
             // Update enabled status in database
-            let _ = state.db.set_channel_enabled(id, true);
+            let _ = state.db.set_channel_enabled(id, true).await;
 
             let response = ChannelResponse::from(channel).with_running(true);
             HttpResponse::Ok().json(ChannelOperationResponse {
@@ -398,14 +415,14 @@ async fn stop_channel(
     req: HttpRequest,
     path: web::Path<i64>,
 ) -> impl Responder {
-    if let Err(resp) = validate_session_from_request(&state, &req) {
+    if let Err(resp) = validate_session_from_request(&state, &req, Scope::Admin).await {
         return resp;
     }
 
     let id = path.into_inner();
 
     // Get channel from database
-    let channel = match state.db.get_channel(id) {
+    let channel = match state.db.get_channel(id).await {
         Ok(Some(ch)) => ch,
         Ok(None) => {
             return HttpResponse::NotFound().json(ChannelOperationResponse {
@@ -429,7 +446,7 @@ async fn stop_channel(
     match channel_manager.stop_channel(id).await {
         Ok(()) => {
             // Update enabled status in database
-            let _ = state.db.set_channel_enabled(id, false);
+            let _ = state.db.set_channel_enabled(id, false).await;
 
             let response = ChannelResponse::from(channel).with_running(false);
             HttpResponse::Ok().json(ChannelOperationResponse {
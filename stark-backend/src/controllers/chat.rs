@@ -1,14 +1,20 @@
 use actix_web::{web, HttpRequest, HttpResponse, Responder};
 use serde::{Deserialize, Serialize};
 
-use crate::channels::NormalizedMessage;
-use crate::models::SessionScope;
+use crate::ai::budget::BudgetUsage;
+use crate::ai::vision::MAX_IMAGES;
+use crate::ai::multi_agent::SubAgentStatus;
+use crate::ai::{ArchetypeId, ImageAttachment, ImageSource, ResponseFormat};
+use crate::channels::{NormalizedMessage, ToolInvocation};
+use crate::models::{Caller, ModelOverrides, Scope, SessionScope};
 use crate::AppState;
+use crate::utils::{truncate_chars, truncate_str};
+use crate::workspace::{WorkspaceKind, WorkspaceManager};
 
 /// Web channel ID - a reserved ID for web-based chat
 /// This is used to identify messages from the web frontend
-const WEB_CHANNEL_ID: i64 = 0;
-const WEB_CHANNEL_TYPE: &str = "web";
+pub(crate) const WEB_CHANNEL_ID: i64 = 0;
+pub(crate) const WEB_CHANNEL_TYPE: &str = "web";
 
 #[derive(Debug, Deserialize)]
 pub struct ChatRequest {
@@ -16,12 +22,61 @@ pub struct ChatRequest {
     /// Optional user identifier for the web session
     #[serde(default)]
     pub user_id: Option<String>,
+    /// Optional sampling seed for reproducible outputs (ignored by providers without seed support)
+    #[serde(default)]
+    pub seed: Option<u64>,
+    /// Optional model archetype for this request only (claude, openai, kimi, llama, deepseek);
+    /// overrides the active agent settings. The configured endpoint and key are still used.
+    #[serde(default)]
+    pub model_archetype: Option<String>,
+    /// Optional model for this request only; must be in the agent's `allowed_models`
+    #[serde(default)]
+    pub model: Option<String>,
+    /// Optional output token limit for this request, up to the agent's `max_tokens`
+    #[serde(default)]
+    pub max_tokens: Option<u32>,
+    /// Optional sampling temperature for this request
+    #[serde(default)]
+    pub temperature: Option<f32>,
+    /// Let the agent run tools (token_lookup, web search, ...) while answering; defaults to true
+    #[serde(default = "default_tools")]
+    pub tools: bool,
+    /// Ask for the reply as JSON, optionally matching a schema
+    #[serde(default)]
+    pub response_format: Option<ResponseFormat>,
+}
+
+fn default_tools() -> bool {
+    true
 }
 
 #[derive(Debug, Deserialize, Serialize, Clone)]
 pub struct ChatMessage {
     pub role: String,
     pub content: String,
+    /// Images for the model to look at (user messages only)
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub images: Vec<ChatImage>,
+}
+
+/// An image sent with a chat message: base64 `data` (or a `data:` URL), or
+/// the path of an image uploaded into the session's workspace
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct ChatImage {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub data: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub file: Option<String>,
+}
+
+impl ChatImage {
+    fn to_source(&self) -> Result<ImageSource, String> {
+        match (&self.data, &self.file) {
+            (Some(data), None) => ImageAttachment::from_base64(data).map(ImageSource::Inline),
+            (None, Some(file)) if !file.trim().is_empty() => Ok(ImageSource::WorkspaceFile(file.trim().to_string())),
+            _ => Err("Each image needs either data or file".to_string()),
+        }
+    }
 }
 
 #[derive(Serialize)]
@@ -34,6 +89,12 @@ pub struct ChatResponse {
     /// Session ID for persistent conversations
     #[serde(skip_serializing_if = "Option::is_none")]
     pub session_id: Option<i64>,
+    /// Tokens and estimated cost spent on this request
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub usage: Option<BudgetUsage>,
+    /// Tools the agent ran while answering, in order
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub tool_calls: Vec<ToolInvocation>,
 }
 
 #[derive(Serialize)]
@@ -124,7 +185,46 @@ pub fn config(cfg: &mut web::ServiceConfig) {
         .service(web::resource("/api/chat/tasks/{task_id}").route(web::delete().to(delete_task)))
         // Session management for web channel
         .service(web::resource("/api/chat/session").route(web::get().to(get_web_session)))
-        .service(web::resource("/api/chat/session/new").route(web::post().to(new_web_session)));
+        .service(web::resource("/api/chat/session/new").route(web::post().to(new_web_session)))
+        .service(web::resource("/api/session/reset").route(web::post().to(reset_session)));
+}
+
+/// The platform chat id of a caller's web conversation: `requested` if the
+/// client names one, otherwise derived from the token. Non-admin users' ids
+/// are prefixed with their user id so they can never name another user's
+/// conversation.
+pub(crate) fn web_chat_id(caller: &Caller, token: &str, requested: Option<&str>) -> String {
+    let id = match requested {
+        Some(id) => id.to_string(),
+        None => format!("web-{}", truncate_chars(token, 8)),
+    };
+    if caller.is_admin() {
+        id
+    } else {
+        format!("user-{}-{}", caller.user.id, id)
+    }
+}
+
+/// Whether `caller` owns a chat session; admins own all of them
+async fn owns_session(state: &AppState, caller: &Caller, session_id: i64) -> bool {
+    caller.is_admin()
+        || matches!(
+            state.db.get_chat_session(session_id).await,
+            Ok(Some(session)) if caller.can_access(session.user_id)
+        )
+}
+
+/// Whether `caller` may see and steer the web channel's current execution.
+/// Users share the channel, so other than admins they only reach an
+/// execution that runs in one of their own sessions.
+async fn owns_web_execution(state: &AppState, caller: &Caller) -> bool {
+    if caller.is_admin() {
+        return true;
+    }
+    match state.execution_tracker.execution_session(WEB_CHANNEL_ID) {
+        Some(session_id) => owns_session(state, caller, session_id).await,
+        None => false,
+    }
 }
 
 async fn chat(
@@ -147,34 +247,51 @@ async fn chat(
                 message: None,
                 error: Some("No authorization token provided".to_string()),
                 session_id: None,
+                usage: None,
+                tool_calls: Vec::new(),
             });
         }
     };
 
-    // Validate the session
-    match state.db.validate_session(&token) {
-        Ok(Some(_)) => {} // Session is valid
+    // Validate the session; the token's scopes and tool policy also decide which tools the agent may use
+    let caller = match state.db.authenticate(&token).await {
+        Ok(Some(caller)) if caller.scopes.allows(Scope::Chat) => caller,
+        Ok(Some(_)) => {
+            return HttpResponse::Forbidden().json(ChatResponse {
+                success: false,
+                message: None,
+                error: Some(format!("Token lacks the {} scope", Scope::Chat)),
+                session_id: None,
+                usage: None,
+                tool_calls: Vec::new(),
+            });
+        }
         Ok(None) => {
             return HttpResponse::Unauthorized().json(ChatResponse {
                 success: false,
                 message: None,
                 error: Some("Invalid or expired session".to_string()),
                 session_id: None,
+                usage: None,
+                tool_calls: Vec::new(),
             });
         }
         Err(e) => {
-            log::error!("Failed to validate session: {}", e);
+            tracing::error!("Failed to validate session: {}", e);
             return HttpResponse::InternalServerError().json(ChatResponse {
                 success: false,
                 message: None,
                 error: Some("Internal server error".to_string()),
                 session_id: None,
+                usage: None,
+                tool_calls: Vec::new(),
             });
         }
     };
 
     // Get the latest user message from the request
-    let user_message = match body.messages.iter().rev().find(|m| m.role == "user") {
+    let latest = body.messages.iter().rev().find(|m| m.role == "user");
+    let user_message = match latest {
         Some(msg) => msg.content.clone(),
         None => {
             return HttpResponse::BadRequest().json(ChatResponse {
@@ -182,14 +299,114 @@ async fn chat(
                 message: None,
                 error: Some("No user message provided".to_string()),
                 session_id: None,
+                usage: None,
+                tool_calls: Vec::new(),
+            });
+        }
+    };
+
+    // Reject empty or whitespace-only messages before they reach the provider
+    if user_message.trim().is_empty() {
+        return HttpResponse::UnprocessableEntity().json(ChatResponse {
+            success: false,
+            message: None,
+            error: Some("User message content cannot be empty".to_string()),
+            session_id: None,
+            usage: None,
+            tool_calls: Vec::new(),
+        });
+    }
+
+    let images = match latest.map(|msg| msg.images.as_slice()).unwrap_or_default() {
+        images if images.len() > MAX_IMAGES => Err(format!("At most {} images per message", MAX_IMAGES)),
+        images => images.iter().map(ChatImage::to_source).collect::<Result<Vec<_>, _>>(),
+    };
+    let images = match images {
+        Ok(images) => images,
+        Err(e) => {
+            return HttpResponse::BadRequest().json(ChatResponse {
+                success: false,
+                message: None,
+                error: Some(e),
+                session_id: None,
+                usage: None,
+                tool_calls: Vec::new(),
+            });
+        }
+    };
+
+    let model_archetype = match body.model_archetype.as_deref() {
+        None => None,
+        Some(name) => match ArchetypeId::from_str(name) {
+            Some(id) => Some(id),
+            None => {
+                return HttpResponse::BadRequest().json(ChatResponse {
+                    success: false,
+                    message: None,
+                    error: Some(format!(
+                        "Invalid archetype: {}. Must be kimi, llama, claude, openai or deepseek.",
+                        name
+                    )),
+                    session_id: None,
+                    usage: None,
+                    tool_calls: Vec::new(),
+                });
+            }
+        },
+    };
+
+    if let Some(Err(e)) = body.response_format.as_ref().map(ResponseFormat::validate) {
+        return HttpResponse::BadRequest().json(ChatResponse {
+            success: false,
+            message: None,
+            error: Some(format!("Invalid response_format: {}", e)),
+            session_id: None,
+            usage: None,
+            tool_calls: Vec::new(),
+        });
+    }
+
+    let overrides = ModelOverrides {
+        model: body.model.clone(),
+        max_tokens: body.max_tokens,
+        temperature: body.temperature,
+    };
+    let model_overrides = if overrides.is_empty() {
+        None
+    } else {
+        let mut settings = match state.db.get_active_agent_settings().await {
+            Ok(settings) => settings.unwrap_or_default(),
+            Err(e) => {
+                tracing::error!("Failed to load agent settings: {}", e);
+                return HttpResponse::InternalServerError().json(ChatResponse {
+                    success: false,
+                    message: None,
+                    error: Some("Internal server error".to_string()),
+                    session_id: None,
+                    usage: None,
+                    tool_calls: Vec::new(),
+                });
+            }
+        };
+        if let Some(archetype_id) = model_archetype {
+            settings.model_archetype = archetype_id.to_string();
+        }
+        if let Err(error) = settings.check_overrides(&overrides) {
+            return HttpResponse::BadRequest().json(ChatResponse {
+                success: false,
+                message: None,
+                error: Some(error),
+                session_id: None,
+                usage: None,
+                tool_calls: Vec::new(),
             });
         }
+        Some(overrides)
     };
 
     // Generate a user ID for the web session
     // Use the provided user_id, or derive from the session token
-    let user_id = body.user_id.clone()
-        .unwrap_or_else(|| format!("web-{}", &token[..8.min(token.len())]));
+    let user_id = web_chat_id(&caller, &token, body.user_id.as_deref());
 
     // Create a normalized message for the dispatcher
     // This makes web chat go through the same pipeline as Telegram/Slack
@@ -198,10 +415,19 @@ async fn chat(
         channel_type: WEB_CHANNEL_TYPE.to_string(),
         chat_id: user_id.clone(),  // For web, chat_id == user_id (always DM-like)
         user_id: user_id.clone(),
-        user_name: format!("web-user-{}", &user_id[..8.min(user_id.len())]),
+        user_name: format!("web-user-{}", truncate_chars(&user_id, 8)),
         text: user_message,
         message_id: None,
         session_mode: None,
+        seed: body.seed,
+        model_archetype,
+        model_overrides,
+        use_tools: Some(body.tools),
+        response_format: body.response_format.clone(),
+        images,
+        scopes: Some(caller.scopes),
+        tool_policy: caller.tool_policy,
+        owner_id: Some(caller.user.id),
     };
 
     // Dispatch through the unified pipeline
@@ -209,12 +435,14 @@ async fn chat(
     let result = state.dispatcher.dispatch(normalized).await;
 
     if let Some(error) = result.error {
-        log::error!("Chat dispatch error: {}", error);
+        tracing::error!("Chat dispatch error: {}", error);
         return HttpResponse::InternalServerError().json(ChatResponse {
             success: false,
             message: None,
             error: Some(error),
             session_id: None,
+            usage: result.usage,
+            tool_calls: result.tool_calls,
         });
     }
 
@@ -223,9 +451,12 @@ async fn chat(
         message: Some(ChatMessage {
             role: "assistant".to_string(),
             content: result.response,
+            images: Vec::new(),
         }),
         error: None,
         session_id: None, // Could return session ID if needed
+        usage: result.usage,
+        tool_calls: result.tool_calls,
     })
 }
 
@@ -234,8 +465,6 @@ async fn stop_execution(
     state: web::Data<AppState>,
     req: HttpRequest,
 ) -> impl Responder {
-    use std::time::Duration;
-
     // Validate session token
     let token = req
         .headers()
@@ -255,8 +484,15 @@ async fn stop_execution(
     };
 
     // Validate the session
-    match state.db.validate_session(&token) {
-        Ok(Some(_)) => {} // Session is valid
+    let caller = match state.db.authenticate(&token).await {
+        Ok(Some(caller)) if caller.scopes.allows(Scope::Chat) => caller,
+        Ok(Some(_)) => {
+            return HttpResponse::Forbidden().json(StopResponse {
+                success: false,
+                message: None,
+                error: Some(format!("Token lacks the {} scope", Scope::Chat)),
+            });
+        }
         Ok(None) => {
             return HttpResponse::Unauthorized().json(StopResponse {
                 success: false,
@@ -265,7 +501,7 @@ async fn stop_execution(
             });
         }
         Err(e) => {
-            log::error!("Failed to validate session: {}", e);
+            tracing::error!("Failed to validate session: {}", e);
             return HttpResponse::InternalServerError().json(StopResponse {
                 success: false,
                 message: None,
@@ -275,26 +511,7 @@ async fn stop_execution(
     };
 
     // Cancel the execution for the web channel
-    // This will:
-    // 1. Cancel via CancellationToken (immediate interruption of async ops)
-    // 2. Set the cancelled flag (for checkpoint compatibility)
-    // 3. Emit execution.stopped event for frontend confirmation
-    // 4. Complete/abort the current execution
-    log::info!("[CHAT_STOP] Stopping execution for web channel {}", WEB_CHANNEL_ID);
-    state.execution_tracker.cancel_execution(WEB_CHANNEL_ID);
-
-    // Also cancel any session-based executions running on this channel
-    // This ensures cron jobs running in "main" mode on channel 0 are also stopped
-    state.execution_tracker.cancel_all_sessions_for_channel(WEB_CHANNEL_ID);
-
-    // Also cancel any running subagents for this channel and wait for acknowledgment
-    let mut subagents_cancelled = 0;
-    if let Some(subagent_manager) = state.dispatcher.subagent_manager() {
-        subagents_cancelled = subagent_manager
-            .cancel_all_for_channel_and_wait(WEB_CHANNEL_ID, Duration::from_millis(100))
-            .await;
-        log::info!("[CHAT_STOP] Cancelled {} subagents for web channel", subagents_cancelled);
-    }
+    let subagents_cancelled = cancel_web_execution(&state, &caller).await;
 
     let message = if subagents_cancelled > 0 {
         format!("Execution stopped. {} subagent(s) cancelled.", subagents_cancelled)
@@ -309,6 +526,52 @@ async fn stop_execution(
     })
 }
 
+/// Cancel the web channel's execution and its subagents, waiting briefly for
+/// them to acknowledge. Admins stop everything on the channel, including cron
+/// jobs running in "main" mode; other users only what runs in their own
+/// sessions. Returns the number of subagents cancelled.
+pub(crate) async fn cancel_web_execution(state: &AppState, caller: &Caller) -> usize {
+    use std::time::Duration;
+
+    // This will:
+    // 1. Cancel via CancellationToken (immediate interruption of async ops)
+    // 2. Set the cancelled flag (for checkpoint compatibility)
+    // 3. Emit execution.stopped event for frontend confirmation
+    // 4. Complete/abort the current execution
+    if owns_web_execution(state, caller).await {
+        tracing::info!("[CHAT_STOP] Stopping execution for web channel {}", WEB_CHANNEL_ID);
+        state.execution_tracker.cancel_execution(WEB_CHANNEL_ID);
+    }
+
+    let Some(subagent_manager) = state.dispatcher.subagent_manager() else {
+        return 0;
+    };
+    let subagents_cancelled = if caller.is_admin() {
+        // Also cancel any session-based executions running on this channel
+        // This ensures cron jobs running in "main" mode on channel 0 are also stopped
+        state.execution_tracker.cancel_all_sessions_for_channel(WEB_CHANNEL_ID);
+        subagent_manager
+            .cancel_all_for_channel_and_wait(WEB_CHANNEL_ID, Duration::from_millis(100))
+            .await
+    } else {
+        let mut count = 0;
+        for agent in subagent_manager.list_by_channel(WEB_CHANNEL_ID).await.unwrap_or_default() {
+            if agent.status == SubAgentStatus::Running
+                && owns_session(state, caller, agent.parent_session_id).await
+                && subagent_manager.cancel(&agent.id) == Ok(true)
+            {
+                count += 1;
+            }
+        }
+        if count > 0 {
+            tokio::time::sleep(Duration::from_millis(100)).await;
+        }
+        count
+    };
+    tracing::info!("[CHAT_STOP] Cancelled {} subagents for web channel", subagents_cancelled);
+    subagents_cancelled
+}
+
 /// Get the current execution status for the web channel
 async fn get_execution_status(
     state: web::Data<AppState>,
@@ -332,15 +595,22 @@ async fn get_execution_status(
     };
 
     // Validate the session
-    if state.db.validate_session(&token).ok().flatten().is_none() {
-        return HttpResponse::Unauthorized().json(ExecutionStatusResponse {
-            running: false,
-            execution_id: None,
-        });
-    }
+    let caller = match state.db.authenticate(&token).await.ok().flatten() {
+        Some(caller) if caller.scopes.allows(Scope::Read) => caller,
+        _ => {
+            return HttpResponse::Unauthorized().json(ExecutionStatusResponse {
+                running: false,
+                execution_id: None,
+            });
+        }
+    };
 
-    // Get execution ID for the web channel
-    let execution_id = state.execution_tracker.get_execution_id(WEB_CHANNEL_ID);
+    // Get execution ID for the web channel, if it is the caller's
+    let execution_id = if owns_web_execution(&state, &caller).await {
+        state.execution_tracker.get_execution_id(WEB_CHANNEL_ID)
+    } else {
+        None
+    };
 
     HttpResponse::Ok().json(ExecutionStatusResponse {
         running: execution_id.is_some(),
@@ -371,36 +641,34 @@ async fn list_subagents(
     };
 
     // Validate the session
-    if state.db.validate_session(&token).ok().flatten().is_none() {
-        return HttpResponse::Unauthorized().json(SubagentListResponse {
-            success: false,
-            subagents: vec![],
-        });
-    }
-
-    // Get subagents for the web channel
-    let subagents = if let Some(subagent_manager) = state.dispatcher.subagent_manager() {
-        match subagent_manager.list_by_channel(WEB_CHANNEL_ID) {
-            Ok(agents) => agents
-                .into_iter()
-                .map(|ctx| SubagentInfo {
-                    id: ctx.id,
-                    label: ctx.label,
-                    task: if ctx.task.len() > 100 {
-                        format!("{}...", &ctx.task[..97])
-                    } else {
-                        ctx.task
-                    },
-                    status: format!("{:?}", ctx.status),
-                    started_at: ctx.started_at.to_rfc3339(),
-                })
-                .collect(),
-            Err(_) => vec![],
+    let caller = match state.db.authenticate(&token).await.ok().flatten() {
+        Some(caller) if caller.scopes.allows(Scope::Read) => caller,
+        _ => {
+            return HttpResponse::Unauthorized().json(SubagentListResponse {
+                success: false,
+                subagents: vec![],
+            });
         }
-    } else {
-        vec![]
     };
 
+    // Get the caller's subagents for the web channel
+    let agents = match state.dispatcher.subagent_manager() {
+        Some(subagent_manager) => subagent_manager.list_by_channel(WEB_CHANNEL_ID).await.unwrap_or_default(),
+        None => vec![],
+    };
+    let mut subagents = Vec::new();
+    for ctx in agents {
+        if owns_session(&state, &caller, ctx.parent_session_id).await {
+            subagents.push(SubagentInfo {
+                id: ctx.id,
+                label: ctx.label,
+                task: truncate_str(&ctx.task, 97),
+                status: format!("{:?}", ctx.status),
+                started_at: ctx.started_at.to_rfc3339(),
+            });
+        }
+    }
+
     HttpResponse::Ok().json(SubagentListResponse {
         success: true,
         subagents,
@@ -432,17 +700,31 @@ async fn cancel_subagent(
     };
 
     // Validate the session
-    if state.db.validate_session(&token).ok().flatten().is_none() {
-        return HttpResponse::Unauthorized().json(SubagentResponse {
-            success: false,
-            message: None,
-            error: Some("Invalid or expired session".to_string()),
-        });
-    }
+    let caller = match state.db.authenticate(&token).await.ok().flatten() {
+        Some(caller) if caller.scopes.allows(Scope::Chat) => caller,
+        _ => {
+            return HttpResponse::Unauthorized().json(SubagentResponse {
+                success: false,
+                message: None,
+                error: Some("Invalid or expired session".to_string()),
+            });
+        }
+    };
 
-    // Cancel the subagent
+    // Cancel the subagent, if it works for one of the caller's sessions
     if let Some(subagent_manager) = state.dispatcher.subagent_manager() {
-        log::info!("[CHAT] Cancelling subagent {}", body.subagent_id);
+        let owned = match subagent_manager.get_status(&body.subagent_id).await {
+            Ok(Some(ctx)) => owns_session(&state, &caller, ctx.parent_session_id).await,
+            _ => caller.is_admin(),
+        };
+        if !owned {
+            return HttpResponse::Ok().json(SubagentResponse {
+                success: false,
+                message: None,
+                error: Some(format!("Subagent {} not found or already completed", body.subagent_id)),
+            });
+        }
+        tracing::info!("[CHAT] Cancelling subagent {}", body.subagent_id);
         match subagent_manager.cancel(&body.subagent_id) {
             Ok(true) => {
                 HttpResponse::Ok().json(SubagentResponse {
@@ -498,15 +780,22 @@ async fn get_planner_tasks(
     };
 
     // Validate the session
-    if state.db.validate_session(&token).ok().flatten().is_none() {
-        return HttpResponse::Unauthorized().json(GetPlannerTasksResponse {
-            success: false,
-            tasks: vec![],
-        });
-    }
+    let caller = match state.db.authenticate(&token).await.ok().flatten() {
+        Some(caller) if caller.scopes.allows(Scope::Read) => caller,
+        _ => {
+            return HttpResponse::Unauthorized().json(GetPlannerTasksResponse {
+                success: false,
+                tasks: vec![],
+            });
+        }
+    };
 
-    // Get tasks from execution tracker
-    let tasks = state.execution_tracker.get_planner_tasks(WEB_CHANNEL_ID);
+    // Get tasks from execution tracker, if the execution is the caller's
+    let tasks = if owns_web_execution(&state, &caller).await {
+        state.execution_tracker.get_planner_tasks(WEB_CHANNEL_ID)
+    } else {
+        vec![]
+    };
     let task_infos: Vec<PlannerTaskInfo> = tasks
         .into_iter()
         .map(|t| PlannerTaskInfo {
@@ -548,17 +837,28 @@ async fn delete_task(
     };
 
     // Validate the session
-    if state.db.validate_session(&token).ok().flatten().is_none() {
-        return HttpResponse::Unauthorized().json(DeleteTaskResponse {
+    let caller = match state.db.authenticate(&token).await.ok().flatten() {
+        Some(caller) if caller.scopes.allows(Scope::Chat) => caller,
+        _ => {
+            return HttpResponse::Unauthorized().json(DeleteTaskResponse {
+                success: false,
+                message: None,
+                error: Some("Invalid or expired session".to_string()),
+                was_current_task: None,
+            });
+        }
+    };
+
+    let task_id = path.into_inner();
+    if !owns_web_execution(&state, &caller).await {
+        return HttpResponse::NotFound().json(DeleteTaskResponse {
             success: false,
             message: None,
-            error: Some("Invalid or expired session".to_string()),
+            error: Some(format!("Task {} not found", task_id)),
             was_current_task: None,
         });
     }
-
-    let task_id = path.into_inner();
-    log::info!("[CHAT] Deleting planner task {} for web channel", task_id);
+    tracing::info!("[CHAT] Deleting planner task {} for web channel", task_id);
 
     // Queue the task deletion - the dispatcher will handle it during the next checkpoint
     state.execution_tracker.queue_task_deletion(WEB_CHANNEL_ID, task_id);
@@ -619,7 +919,7 @@ async fn get_web_session(
     };
 
     // Validate the session
-    if state.db.validate_session(&token).ok().flatten().is_none() {
+    let Some(caller) = state.db.authenticate(&token).await.ok().flatten().filter(|c| c.scopes.allows(Scope::Read)) else {
         return HttpResponse::Unauthorized().json(WebSessionResponse {
             success: false,
             session_id: None,
@@ -628,11 +928,11 @@ async fn get_web_session(
             created_at: None,
             error: Some("Invalid or expired session".to_string()),
         });
-    }
+    };
 
     // Get or create the web session
     // Use token prefix as the platform_chat_id to tie session to the auth token
-    let chat_id = format!("web-{}", &token[..8.min(token.len())]);
+    let chat_id = web_chat_id(&caller, &token, None);
 
     match state.db.get_or_create_chat_session(
         WEB_CHANNEL_TYPE,
@@ -640,10 +940,11 @@ async fn get_web_session(
         &chat_id,
         SessionScope::Dm,
         None,
-    ) {
+        Some(caller.user.id),
+    ).await {
         Ok(session) => {
             // Get message count
-            let message_count = state.db.count_session_messages(session.id).ok();
+            let message_count = state.db.count_session_messages(session.id).await.ok();
 
             HttpResponse::Ok().json(WebSessionResponse {
                 success: true,
@@ -655,7 +956,7 @@ async fn get_web_session(
             })
         }
         Err(e) => {
-            log::error!("Failed to get or create web session: {}", e);
+            tracing::error!("Failed to get or create web session: {}", e);
             HttpResponse::InternalServerError().json(WebSessionResponse {
                 success: false,
                 session_id: None,
@@ -695,7 +996,7 @@ async fn new_web_session(
     };
 
     // Validate the session
-    if state.db.validate_session(&token).ok().flatten().is_none() {
+    let Some(caller) = state.db.authenticate(&token).await.ok().flatten().filter(|c| c.scopes.allows(Scope::Chat)) else {
         return HttpResponse::Unauthorized().json(WebSessionResponse {
             success: false,
             session_id: None,
@@ -704,10 +1005,10 @@ async fn new_web_session(
             created_at: None,
             error: Some("Invalid or expired session".to_string()),
         });
-    }
+    };
 
     // First get the current session
-    let chat_id = format!("web-{}", &token[..8.min(token.len())]);
+    let chat_id = web_chat_id(&caller, &token, None);
 
     let current_session = state.db.get_or_create_chat_session(
         WEB_CHANNEL_TYPE,
@@ -715,14 +1016,15 @@ async fn new_web_session(
         &chat_id,
         SessionScope::Dm,
         None,
-    );
+        Some(caller.user.id),
+    ).await;
 
     match current_session {
         Ok(session) => {
             // Reset the session (marks old as inactive, creates new)
-            match state.db.reset_chat_session(session.id) {
+            match state.db.reset_chat_session(session.id).await {
                 Ok(new_session) => {
-                    log::info!("[CHAT] Created new web session {} (replaced {})", new_session.id, session.id);
+                    tracing::info!("[CHAT] Created new web session {} (replaced {})", new_session.id, session.id);
 
                     HttpResponse::Ok().json(WebSessionResponse {
                         success: true,
@@ -734,7 +1036,7 @@ async fn new_web_session(
                     })
                 }
                 Err(e) => {
-                    log::error!("Failed to reset web session: {}", e);
+                    tracing::error!("Failed to reset web session: {}", e);
                     HttpResponse::InternalServerError().json(WebSessionResponse {
                         success: false,
                         session_id: None,
@@ -747,7 +1049,7 @@ async fn new_web_session(
             }
         }
         Err(e) => {
-            log::error!("Failed to get current web session: {}", e);
+            tracing::error!("Failed to get current web session: {}", e);
             HttpResponse::InternalServerError().json(WebSessionResponse {
                 success: false,
                 session_id: None,
@@ -759,3 +1061,286 @@ async fn new_web_session(
         }
     }
 }
+
+/// Summary returned by the session reset endpoint
+#[derive(Serialize, Default)]
+pub struct SessionResetResponse {
+    pub success: bool,
+    /// The session that was reset
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub previous_session_id: Option<i64>,
+    /// The fresh session that replaces it
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub session_id: Option<i64>,
+    pub messages_cleared: i64,
+    pub processes_killed: usize,
+    pub workspace_cleared: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+impl SessionResetResponse {
+    fn error(msg: impl Into<String>) -> Self {
+        SessionResetResponse {
+            error: Some(msg.into()),
+            ..Default::default()
+        }
+    }
+}
+
+/// Reset everything owned by the caller's web session: cancels its in-flight
+/// execution (dropping its registers), kills its background processes, removes
+/// its workspace directory and starts a fresh conversation.
+/// Only resources tagged with the caller's session id are touched.
+async fn reset_session(
+    state: web::Data<AppState>,
+    req: HttpRequest,
+) -> impl Responder {
+    // Validate session token
+    let token = req
+        .headers()
+        .get("Authorization")
+        .and_then(|h| h.to_str().ok())
+        .map(|s| s.trim_start_matches("Bearer ").to_string());
+
+    let token = match token {
+        Some(t) => t,
+        None => {
+            return HttpResponse::Unauthorized()
+                .json(SessionResetResponse::error("No authorization token provided"));
+        }
+    };
+
+    // Validate the session
+    let Some(caller) = state.db.authenticate(&token).await.ok().flatten().filter(|c| c.scopes.allows(Scope::Chat)) else {
+        return HttpResponse::Unauthorized()
+            .json(SessionResetResponse::error("Invalid or expired session"));
+    };
+
+    let chat_id = web_chat_id(&caller, &token, None);
+
+    let session = match state.db.get_or_create_chat_session(
+        WEB_CHANNEL_TYPE,
+        WEB_CHANNEL_ID,
+        &chat_id,
+        SessionScope::Dm,
+        None,
+        Some(caller.user.id),
+    ).await {
+        Ok(s) => s,
+        Err(e) => {
+            tracing::error!("Failed to get current web session: {}", e);
+            return HttpResponse::InternalServerError()
+                .json(SessionResetResponse::error(format!("Database error: {}", e)));
+        }
+    };
+
+    let messages_cleared = state.db.count_session_messages(session.id).await.unwrap_or(0);
+
+    // Cancelling the execution drops its in-memory registers; the persisted
+    // ones go with the session reset below
+    state.execution_tracker.cancel_execution_for_session(session.id);
+
+    let processes_killed = state.process_manager.kill_all_for_session(session.id).await;
+
+    let workspace = match WorkspaceManager::from_env().path(WorkspaceKind::Session, &session.id.to_string()) {
+        Ok(path) => path,
+        Err(e) => return HttpResponse::InternalServerError().json(SessionResetResponse {
+            previous_session_id: Some(session.id),
+            error: Some(e),
+            ..Default::default()
+        }),
+    };
+    let workspace_cleared = if workspace.exists() {
+        match tokio::fs::remove_dir_all(&workspace).await {
+            Ok(()) => true,
+            Err(e) => {
+                tracing::error!("Failed to remove session workspace {:?}: {}", workspace, e);
+                return HttpResponse::InternalServerError().json(SessionResetResponse {
+                    previous_session_id: Some(session.id),
+                    processes_killed,
+                    error: Some(format!("Failed to clear session workspace: {}", e)),
+                    ..Default::default()
+                });
+            }
+        }
+    } else {
+        false
+    };
+
+    match state.db.reset_chat_session(session.id).await {
+        Ok(new_session) => {
+            tracing::info!(
+                "[CHAT] Reset web session {} -> {} ({} messages, {} processes, workspace cleared: {})",
+                session.id,
+                new_session.id,
+                messages_cleared,
+                processes_killed,
+                workspace_cleared
+            );
+
+            HttpResponse::Ok().json(SessionResetResponse {
+                success: true,
+                previous_session_id: Some(session.id),
+                session_id: Some(new_session.id),
+                messages_cleared,
+                processes_killed,
+                workspace_cleared,
+                error: None,
+            })
+        }
+        Err(e) => {
+            tracing::error!("Failed to reset web session: {}", e);
+            HttpResponse::InternalServerError().json(SessionResetResponse {
+                previous_session_id: Some(session.id),
+                processes_killed,
+                workspace_cleared,
+                error: Some(format!("Failed to reset conversation: {}", e)),
+                ..Default::default()
+            })
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::Database;
+    use crate::models::{Scopes, ToolPolicy, UpdateAgentSettingsRequest, UserRole};
+    use actix_web::{test, App};
+    use serde_json::{json, Value};
+    use std::sync::Arc;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::TcpListener;
+
+    /// OpenAI-compatible server answering with `replies` in turn (the last one
+    /// repeats); returns its endpoint and the request bodies it has seen
+    async fn fake_provider(replies: Vec<Value>) -> (String, Arc<std::sync::Mutex<Vec<Value>>>) {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let endpoint = format!("http://{}/v1/chat/completions", listener.local_addr().unwrap());
+        let bodies = Arc::new(std::sync::Mutex::new(Vec::new()));
+        let seen = bodies.clone();
+        tokio::spawn(async move {
+            for i in 0.. {
+                let (mut socket, _) = listener.accept().await.unwrap();
+                let mut data = Vec::new();
+                let mut buf = [0u8; 8192];
+                let body = loop {
+                    let n = socket.read(&mut buf).await.unwrap();
+                    data.extend_from_slice(&buf[..n]);
+                    let text = String::from_utf8_lossy(&data).to_string();
+                    if let Some(end) = text.find("\r\n\r\n") {
+                        let length = text[..end]
+                            .lines()
+                            .find_map(|l| l.to_lowercase().strip_prefix("content-length:").map(|v| v.trim().parse().unwrap()))
+                            .unwrap_or(0usize);
+                        if data.len() >= end + 4 + length {
+                            break text[end + 4..].to_string();
+                        }
+                    }
+                };
+                seen.lock().unwrap().push(serde_json::from_str(&body).unwrap());
+                let reply = replies[i.min(replies.len() - 1)].to_string();
+                let response = format!(
+                    "HTTP/1.1 200 OK\r\ncontent-type: application/json\r\ncontent-length: {}\r\nconnection: close\r\n\r\n{}",
+                    reply.len(),
+                    reply
+                );
+                socket.write_all(response.as_bytes()).await.unwrap();
+            }
+        });
+        (endpoint, bodies)
+    }
+
+    fn answer(content: &str) -> Value {
+        json!({ "choices": [{ "message": { "content": content }, "finish_reason": "stop" }] })
+    }
+
+    /// A database whose agent talks to `endpoint`, and a chat token confined to `workspace`
+    async fn setup(endpoint: &str, workspace: &std::path::Path) -> (Arc<Database>, String) {
+        let db = Arc::new(Database::new(":memory:").unwrap());
+        let settings: UpdateAgentSettingsRequest = serde_json::from_value(json!({
+            "endpoint": endpoint,
+            "model_archetype": "openai",
+            "secret_key": "test",
+        }))
+        .unwrap();
+        db.save_agent_settings(&settings).await.unwrap();
+        let user = db.create_user("0xabc", None, UserRole::Member).await.unwrap();
+        let policy = ToolPolicy { workspace_root: Some(workspace.to_string_lossy().to_string()), ..Default::default() };
+        let token = db.create_api_token(user.id, "test", &Scopes::new([Scope::Chat, Scope::ToolsExec]), Some(&policy)).await.unwrap().token;
+        (db, token)
+    }
+
+    async fn post_chat(db: Arc<Database>, token: &str, body: Value) -> Value {
+        let app = test::init_service(
+            App::new().app_data(web::Data::new(AppState::for_tests(db).await)).configure(config),
+        )
+        .await;
+        let req = test::TestRequest::post()
+            .uri("/api/chat")
+            .insert_header(("Authorization", format!("Bearer {}", token)))
+            .set_json(body)
+            .to_request();
+        test::call_and_read_body_json(&app, req).await
+    }
+
+    #[actix_web::test]
+    async fn test_chat_runs_tools_by_default() {
+        let workspace = tempfile::TempDir::new().unwrap();
+        let (endpoint, requests) = fake_provider(vec![
+            json!({
+                "choices": [{
+                    "message": {
+                        "content": null,
+                        "tool_calls": [{
+                            "id": "call_1",
+                            "type": "function",
+                            "function": {
+                                "name": "set_agent_subtype",
+                                "arguments": "{\"subtype\": \"code_engineer\"}"
+                            }
+                        }]
+                    },
+                    "finish_reason": "tool_calls"
+                }]
+            }),
+            answer("Ready to code"),
+        ])
+        .await;
+        let (db, token) = setup(&endpoint, workspace.path()).await;
+
+        let response = post_chat(db, &token, json!({ "messages": [{ "role": "user", "content": "Fix my build" }] })).await;
+
+        assert_eq!(response["success"], true, "{}", response);
+        assert!(response["message"]["content"].as_str().unwrap().ends_with("Ready to code"));
+        let tool_calls = response["tool_calls"].as_array().unwrap();
+        assert_eq!(tool_calls.len(), 1);
+        assert_eq!(tool_calls[0]["name"], "set_agent_subtype");
+        assert_eq!(tool_calls[0]["success"], true, "{}", tool_calls[0]);
+        assert_eq!(tool_calls[0]["arguments"]["subtype"], "code_engineer");
+        assert!(requests.lock().unwrap()[0]["tools"].as_array().is_some_and(|tools| !tools.is_empty()));
+    }
+
+    #[actix_web::test]
+    async fn test_chat_without_tools() {
+        let workspace = tempfile::TempDir::new().unwrap();
+        let (endpoint, requests) = fake_provider(vec![answer("Just text")]).await;
+        let (db, token) = setup(&endpoint, workspace.path()).await;
+
+        let response = post_chat(
+            db,
+            &token,
+            json!({ "messages": [{ "role": "user", "content": "Fix my build" }], "tools": false }),
+        )
+        .await;
+
+        assert_eq!(response["success"], true, "{}", response);
+        assert_eq!(response["message"]["content"], "Just text");
+        // Omitted from the payload when nothing ran
+        assert!(response.get("tool_calls").is_none());
+        let requests = requests.lock().unwrap();
+        assert!(!requests.is_empty());
+        assert!(requests.iter().all(|request| request.get("tools").is_none()));
+    }
+}
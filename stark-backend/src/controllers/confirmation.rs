@@ -165,7 +165,7 @@ fn validate_auth(state: &web::Data<AppState>, req: &HttpRequest) -> Result<(), H
         }
     };
 
-    match state.db.validate_session(&token) {
+    match state.db.validate_session(&token).await {
         Ok(Some(_)) => Ok(()),
         Ok(None) => {
             Err(HttpResponse::Unauthorized().json(ConfirmationResponse {
@@ -2,15 +2,16 @@ use actix_web::{web, HttpRequest, HttpResponse};
 use std::sync::Arc;
 
 use crate::models::{
-    CreateCronJobRequest, CronJobResponse, HeartbeatConfigResponse,
-    UpdateCronJobRequest, UpdateHeartbeatConfigRequest,
+    CreateCronJobRequest, CronJobResponse, HeartbeatConfigResponse, Scope, UpdateCronJobRequest,
+    UpdateHeartbeatConfigRequest,
 };
 use crate::scheduler::Scheduler;
 use crate::AppState;
 
-fn validate_session_from_request(
+async fn validate_session_from_request(
     state: &web::Data<AppState>,
     req: &HttpRequest,
+    scope: Scope,
 ) -> Result<(), HttpResponse> {
     let token = req
         .headers()
@@ -30,8 +31,14 @@ fn validate_session_from_request(
         }
     };
 
-    match state.db.validate_session(&token) {
-        Ok(Some(_)) => Ok(()),
+    match state.db.authorize(&token).await {
+        Ok(Some(scopes)) if scopes.allows(scope) => Ok(()),
+        Ok(Some(_)) => Err(HttpResponse::Forbidden().json(CronJobResponse {
+            success: false,
+            job: None,
+            jobs: None,
+            error: Some(format!("Token lacks the {} scope", scope)),
+        })),
         Ok(None) => Err(HttpResponse::Unauthorized().json(CronJobResponse {
             success: false,
             job: None,
@@ -76,11 +83,11 @@ pub fn config(cfg: &mut web::ServiceConfig) {
 
 /// List all cron jobs
 async fn list_jobs(state: web::Data<AppState>, req: HttpRequest) -> HttpResponse {
-    if let Err(resp) = validate_session_from_request(&state, &req) {
+    if let Err(resp) = validate_session_from_request(&state, &req, Scope::Read).await {
         return resp;
     }
 
-    match state.db.list_cron_jobs() {
+    match state.db.list_cron_jobs().await {
         Ok(jobs) => HttpResponse::Ok().json(CronJobResponse {
             success: true,
             job: None,
@@ -102,7 +109,7 @@ async fn create_job(
     req: HttpRequest,
     body: web::Json<CreateCronJobRequest>,
 ) -> HttpResponse {
-    if let Err(resp) = validate_session_from_request(&state, &req) {
+    if let Err(resp) = validate_session_from_request(&state, &req, Scope::Admin).await {
         return resp;
     }
 
@@ -159,7 +166,7 @@ async fn create_job(
         body.thinking_level.as_deref(),
         body.timeout_seconds,
         body.delete_after_run,
-    ) {
+    ).await {
         Ok(job) => HttpResponse::Created().json(CronJobResponse {
             success: true,
             job: Some(job),
@@ -177,13 +184,13 @@ async fn create_job(
 
 /// Get a cron job by ID
 async fn get_job(state: web::Data<AppState>, req: HttpRequest, path: web::Path<i64>) -> HttpResponse {
-    if let Err(resp) = validate_session_from_request(&state, &req) {
+    if let Err(resp) = validate_session_from_request(&state, &req, Scope::Read).await {
         return resp;
     }
 
     let id = path.into_inner();
 
-    match state.db.get_cron_job(id) {
+    match state.db.get_cron_job(id).await {
         Ok(Some(job)) => HttpResponse::Ok().json(CronJobResponse {
             success: true,
             job: Some(job),
@@ -212,7 +219,7 @@ async fn update_job(
     path: web::Path<i64>,
     body: web::Json<UpdateCronJobRequest>,
 ) -> HttpResponse {
-    if let Err(resp) = validate_session_from_request(&state, &req) {
+    if let Err(resp) = validate_session_from_request(&state, &req, Scope::Admin).await {
         return resp;
     }
 
@@ -255,7 +262,7 @@ async fn update_job(
         body.timeout_seconds,
         body.delete_after_run,
         body.status.as_deref(),
-    ) {
+    ).await {
         Ok(job) => HttpResponse::Ok().json(CronJobResponse {
             success: true,
             job: Some(job),
@@ -273,13 +280,13 @@ async fn update_job(
 
 /// Delete a cron job
 async fn delete_job(state: web::Data<AppState>, req: HttpRequest, path: web::Path<i64>) -> HttpResponse {
-    if let Err(resp) = validate_session_from_request(&state, &req) {
+    if let Err(resp) = validate_session_from_request(&state, &req, Scope::Admin).await {
         return resp;
     }
 
     let id = path.into_inner();
 
-    match state.db.delete_cron_job(id) {
+    match state.db.delete_cron_job(id).await {
         Ok(true) => HttpResponse::Ok().json(CronJobResponse {
             success: true,
             job: None,
@@ -308,14 +315,14 @@ async fn run_job(
     scheduler: web::Data<Arc<Scheduler>>,
     path: web::Path<i64>,
 ) -> HttpResponse {
-    if let Err(resp) = validate_session_from_request(&state, &req) {
+    if let Err(resp) = validate_session_from_request(&state, &req, Scope::Admin).await {
         return resp;
     }
 
     let id = path.into_inner();
 
     // Get the job first
-    let job = match state.db.get_cron_job(id) {
+    let job = match state.db.get_cron_job(id).await {
         Ok(Some(job)) => job,
         Ok(None) => {
             return HttpResponse::NotFound().json(CronJobResponse {
@@ -358,14 +365,14 @@ async fn get_job_runs(
     path: web::Path<i64>,
     query: web::Query<LimitQuery>,
 ) -> HttpResponse {
-    if let Err(resp) = validate_session_from_request(&state, &req) {
+    if let Err(resp) = validate_session_from_request(&state, &req, Scope::Read).await {
         return resp;
     }
 
     let id = path.into_inner();
     let limit = query.limit.unwrap_or(20);
 
-    match state.db.get_cron_job_runs(id, limit) {
+    match state.db.get_cron_job_runs(id, limit).await {
         Ok(runs) => HttpResponse::Ok().json(serde_json::json!({
             "success": true,
             "runs": runs
@@ -384,7 +391,7 @@ struct LimitQuery {
 
 /// Pause a cron job
 async fn pause_job(state: web::Data<AppState>, req: HttpRequest, path: web::Path<i64>) -> HttpResponse {
-    if let Err(resp) = validate_session_from_request(&state, &req) {
+    if let Err(resp) = validate_session_from_request(&state, &req, Scope::Admin).await {
         return resp;
     }
 
@@ -394,7 +401,7 @@ async fn pause_job(state: web::Data<AppState>, req: HttpRequest, path: web::Path
         id,
         None, None, None, None, None, None, None, None, None, None, None, None, None, None, None,
         Some("paused"),
-    ) {
+    ).await {
         Ok(job) => HttpResponse::Ok().json(CronJobResponse {
             success: true,
             job: Some(job),
@@ -412,7 +419,7 @@ async fn pause_job(state: web::Data<AppState>, req: HttpRequest, path: web::Path
 
 /// Resume a paused cron job
 async fn resume_job(state: web::Data<AppState>, req: HttpRequest, path: web::Path<i64>) -> HttpResponse {
-    if let Err(resp) = validate_session_from_request(&state, &req) {
+    if let Err(resp) = validate_session_from_request(&state, &req, Scope::Admin).await {
         return resp;
     }
 
@@ -422,7 +429,7 @@ async fn resume_job(state: web::Data<AppState>, req: HttpRequest, path: web::Pat
         id,
         None, None, None, None, None, None, None, None, None, None, None, None, None, None, None,
         Some("active"),
-    ) {
+    ).await {
         Ok(job) => HttpResponse::Ok().json(CronJobResponse {
             success: true,
             job: Some(job),
@@ -438,9 +445,10 @@ async fn resume_job(state: web::Data<AppState>, req: HttpRequest, path: web::Pat
     }
 }
 
-fn validate_session_for_heartbeat(
+async fn validate_session_for_heartbeat(
     state: &web::Data<AppState>,
     req: &HttpRequest,
+    scope: Scope,
 ) -> Result<(), HttpResponse> {
     let token = req
         .headers()
@@ -459,8 +467,13 @@ fn validate_session_for_heartbeat(
         }
     };
 
-    match state.db.validate_session(&token) {
-        Ok(Some(_)) => Ok(()),
+    match state.db.authorize(&token).await {
+        Ok(Some(scopes)) if scopes.allows(scope) => Ok(()),
+        Ok(Some(_)) => Err(HttpResponse::Forbidden().json(HeartbeatConfigResponse {
+            success: false,
+            config: None,
+            error: Some(format!("Token lacks the {} scope", scope)),
+        })),
         Ok(None) => Err(HttpResponse::Unauthorized().json(HeartbeatConfigResponse {
             success: false,
             config: None,
@@ -479,11 +492,11 @@ fn validate_session_for_heartbeat(
 
 /// Get global heartbeat config
 async fn get_heartbeat_config(state: web::Data<AppState>, req: HttpRequest) -> HttpResponse {
-    if let Err(resp) = validate_session_for_heartbeat(&state, &req) {
+    if let Err(resp) = validate_session_for_heartbeat(&state, &req, Scope::Read).await {
         return resp;
     }
 
-    match state.db.get_or_create_heartbeat_config(None) {
+    match state.db.get_or_create_heartbeat_config(None).await {
         Ok(config) => HttpResponse::Ok().json(HeartbeatConfigResponse {
             success: true,
             config: Some(config),
@@ -503,12 +516,12 @@ async fn update_heartbeat_config(
     req: HttpRequest,
     body: web::Json<UpdateHeartbeatConfigRequest>,
 ) -> HttpResponse {
-    if let Err(resp) = validate_session_for_heartbeat(&state, &req) {
+    if let Err(resp) = validate_session_for_heartbeat(&state, &req, Scope::Admin).await {
         return resp;
     }
 
     // Get or create first
-    let config = match state.db.get_or_create_heartbeat_config(None) {
+    let config = match state.db.get_or_create_heartbeat_config(None).await {
         Ok(c) => c,
         Err(e) => {
             return HttpResponse::InternalServerError().json(HeartbeatConfigResponse {
@@ -527,7 +540,7 @@ async fn update_heartbeat_config(
         body.active_hours_end.as_deref(),
         body.active_days.as_deref(),
         body.enabled,
-    ) {
+    ).await {
         Ok(updated) => HttpResponse::Ok().json(HeartbeatConfigResponse {
             success: true,
             config: Some(updated),
@@ -547,13 +560,13 @@ async fn get_channel_heartbeat_config(
     req: HttpRequest,
     path: web::Path<i64>,
 ) -> HttpResponse {
-    if let Err(resp) = validate_session_for_heartbeat(&state, &req) {
+    if let Err(resp) = validate_session_for_heartbeat(&state, &req, Scope::Read).await {
         return resp;
     }
 
     let channel_id = path.into_inner();
 
-    match state.db.get_or_create_heartbeat_config(Some(channel_id)) {
+    match state.db.get_or_create_heartbeat_config(Some(channel_id)).await {
         Ok(config) => HttpResponse::Ok().json(HeartbeatConfigResponse {
             success: true,
             config: Some(config),
@@ -574,14 +587,14 @@ async fn update_channel_heartbeat_config(
     path: web::Path<i64>,
     body: web::Json<UpdateHeartbeatConfigRequest>,
 ) -> HttpResponse {
-    if let Err(resp) = validate_session_for_heartbeat(&state, &req) {
+    if let Err(resp) = validate_session_for_heartbeat(&state, &req, Scope::Admin).await {
         return resp;
     }
 
     let channel_id = path.into_inner();
 
     // Get or create first
-    let config = match state.db.get_or_create_heartbeat_config(Some(channel_id)) {
+    let config = match state.db.get_or_create_heartbeat_config(Some(channel_id)).await {
         Ok(c) => c,
         Err(e) => {
             return HttpResponse::InternalServerError().json(HeartbeatConfigResponse {
@@ -600,7 +613,7 @@ async fn update_channel_heartbeat_config(
         body.active_hours_end.as_deref(),
         body.active_days.as_deref(),
         body.enabled,
-    ) {
+    ).await {
         Ok(updated) => HttpResponse::Ok().json(HeartbeatConfigResponse {
             success: true,
             config: Some(updated),
@@ -1,6 +1,7 @@
 use actix_web::{web, HttpRequest, HttpResponse, Responder};
 use serde::Serialize;
 
+use crate::models::Scope;
 use crate::AppState;
 
 #[derive(Serialize)]
@@ -34,11 +35,14 @@ async fn get_dashboard(state: web::Data<AppState>, req: HttpRequest) -> impl Res
         }
     };
 
-    match state.db.validate_session(&token) {
-        Ok(Some(_session)) => HttpResponse::Ok().json(DashboardData {
+    match state.db.authorize(&token).await {
+        Ok(Some(scopes)) if scopes.allows(Scope::Read) => HttpResponse::Ok().json(DashboardData {
             message: "Welcome to StarkBot Dashboard!".to_string(),
             timestamp: chrono::Utc::now().to_rfc3339(),
         }),
+        Ok(Some(_)) => HttpResponse::Forbidden().json(ErrorResponse {
+            error: format!("Token lacks the {} scope", Scope::Read),
+        }),
         Ok(None) => HttpResponse::Unauthorized().json(ErrorResponse {
             error: "Invalid or expired session".to_string(),
         }),
@@ -12,6 +12,7 @@ use crate::eip8004::{
     reputation::ReputationRegistry,
     types::TrustLevel,
 };
+use crate::models::Scope;
 use crate::AppState;
 
 // =====================================================
@@ -127,7 +128,13 @@ async fn get_config(_req: HttpRequest) -> impl Responder {
 async fn get_our_identity(
     state: web::Data<AppState>,
 ) -> impl Responder {
-    let conn = state.db.conn.lock().unwrap();
+    let conn = match state.db.conn().await {
+        Ok(c) => c,
+        Err(e) => {
+            return HttpResponse::InternalServerError()
+                .json(ApiResponse::<()>::error(&format!("Database error: {}", e)));
+        }
+    };
 
     // Check if we have a stored identity
     let identity = conn.query_row(
@@ -176,7 +183,7 @@ async fn get_agent_identity(
     req: HttpRequest,
     path: web::Path<u64>,
 ) -> impl Responder {
-    if let Err(resp) = validate_auth(&state, &req) {
+    if let Err(resp) = validate_auth(&state, &req, Scope::Read).await {
         return resp;
     }
 
@@ -201,7 +208,7 @@ async fn create_registration_json(
     req: HttpRequest,
     body: web::Json<CreateRegistrationRequest>,
 ) -> impl Responder {
-    if let Err(resp) = validate_auth(&state, &req) {
+    if let Err(resp) = validate_auth(&state, &req, Scope::Admin).await {
         return resp;
     }
 
@@ -239,7 +246,7 @@ async fn get_agent_reputation(
     req: HttpRequest,
     path: web::Path<u64>,
 ) -> impl Responder {
-    if let Err(resp) = validate_auth(&state, &req) {
+    if let Err(resp) = validate_auth(&state, &req, Scope::Read).await {
         return resp;
     }
 
@@ -264,7 +271,7 @@ async fn check_trust(
     req: HttpRequest,
     path: web::Path<u64>,
 ) -> impl Responder {
-    if let Err(resp) = validate_auth(&state, &req) {
+    if let Err(resp) = validate_auth(&state, &req, Scope::Read).await {
         return resp;
     }
 
@@ -317,7 +324,7 @@ async fn discover_agents(
     req: HttpRequest,
     query: web::Query<DiscoverQuery>,
 ) -> impl Responder {
-    if let Err(resp) = validate_auth(&state, &req) {
+    if let Err(resp) = validate_auth(&state, &req, Scope::Read).await {
         return resp;
     }
 
@@ -352,7 +359,7 @@ async fn search_agents(
     req: HttpRequest,
     query: web::Query<DiscoverQuery>,
 ) -> impl Responder {
-    if let Err(resp) = validate_auth(&state, &req) {
+    if let Err(resp) = validate_auth(&state, &req, Scope::Read).await {
         return resp;
     }
 
@@ -390,7 +397,7 @@ async fn get_agent_details(
     req: HttpRequest,
     path: web::Path<u64>,
 ) -> impl Responder {
-    if let Err(resp) = validate_auth(&state, &req) {
+    if let Err(resp) = validate_auth(&state, &req, Scope::Read).await {
         return resp;
     }
 
@@ -413,7 +420,7 @@ async fn get_agent_details(
 // Auth Helper
 // =====================================================
 
-fn validate_auth(state: &web::Data<AppState>, req: &HttpRequest) -> Result<(), HttpResponse> {
+async fn validate_auth(state: &web::Data<AppState>, req: &HttpRequest, scope: Scope) -> Result<(), HttpResponse> {
     let token = req
         .headers()
         .get("Authorization")
@@ -430,8 +437,12 @@ fn validate_auth(state: &web::Data<AppState>, req: &HttpRequest) -> Result<(), H
         }
     };
 
-    match state.db.validate_session(&token) {
-        Ok(Some(_)) => Ok(()),
+    match state.db.authorize(&token).await {
+        Ok(Some(scopes)) if scopes.allows(scope) => Ok(()),
+        Ok(Some(_)) => Err(HttpResponse::Forbidden().json(serde_json::json!({
+            "success": false,
+            "error": format!("Token lacks the {} scope", scope)
+        }))),
         Ok(None) => Err(HttpResponse::Unauthorized().json(serde_json::json!({
             "success": false,
             "error": "Invalid or expired session"
@@ -0,0 +1,399 @@
+use actix_web::{web, HttpRequest, HttpResponse, Responder};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::memory::embeddings::{create_provider, EmbeddingProvider};
+use crate::memory::EmbeddingConfig;
+use crate::models::{EmbeddingMatch, Scope, StoredEmbedding};
+use crate::AppState;
+
+/// Most texts embedded, or documents reranked, per request
+const MAX_INPUTS: usize = 100;
+/// Longest text accepted, in bytes
+const MAX_INPUT_BYTES: usize = 32_000;
+/// Most matches a search returns
+const MAX_SEARCH_LIMIT: usize = 100;
+
+/// Validate session token from request
+async fn validate_session_from_request(
+    state: &web::Data<AppState>,
+    req: &HttpRequest,
+    scope: Scope,
+) -> Result<(), HttpResponse> {
+    let token = req
+        .headers()
+        .get("Authorization")
+        .and_then(|h| h.to_str().ok())
+        .map(|s| s.trim_start_matches("Bearer ").to_string());
+
+    let token = match token {
+        Some(t) => t,
+        None => {
+            return Err(HttpResponse::Unauthorized().json(EmbeddingsResponse::error("No authorization token provided")));
+        }
+    };
+
+    match state.db.authorize(&token).await {
+        Ok(Some(scopes)) if scopes.allows(scope) => Ok(()),
+        Ok(Some(_)) => Err(HttpResponse::Forbidden()
+            .json(EmbeddingsResponse::error(format!("Token lacks the {} scope", scope)))),
+        Ok(None) => Err(HttpResponse::Unauthorized().json(EmbeddingsResponse::error("Invalid or expired session"))),
+        Err(e) => {
+            log::error!("Session validation error: {}", e);
+            Err(HttpResponse::InternalServerError().json(EmbeddingsResponse::error("Internal server error")))
+        }
+    }
+}
+
+/// One text or a list of them
+#[derive(Debug, Deserialize)]
+#[serde(untagged)]
+pub enum EmbeddingInput {
+    One(String),
+    Many(Vec<String>),
+}
+
+impl EmbeddingInput {
+    fn into_vec(self) -> Vec<String> {
+        match self {
+            EmbeddingInput::One(text) => vec![text],
+            EmbeddingInput::Many(texts) => texts,
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct EmbedRequest {
+    pub input: EmbeddingInput,
+    /// Store the texts in this collection for `/api/embeddings/search`
+    #[serde(default)]
+    pub collection: Option<String>,
+    /// Caller's ids for the stored texts, one per input
+    #[serde(default)]
+    pub ids: Option<Vec<String>>,
+    /// JSON object stored with every text
+    #[serde(default)]
+    pub metadata: Option<Value>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct SearchRequest {
+    pub collection: String,
+    pub query: String,
+    #[serde(default = "default_limit")]
+    pub limit: usize,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct RerankRequest {
+    pub query: String,
+    pub documents: Vec<String>,
+    /// Return only the most relevant documents
+    #[serde(default)]
+    pub top_n: Option<usize>,
+}
+
+fn default_limit() -> usize {
+    10
+}
+
+#[derive(Debug, Serialize)]
+pub struct EmbeddingData {
+    pub index: usize,
+    pub embedding: Vec<f32>,
+    /// The stored row, when the request named a collection
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub stored: Option<StoredEmbedding>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct RankedDocument {
+    pub index: usize,
+    pub relevance_score: f64,
+    pub document: String,
+}
+
+#[derive(Serialize, Default)]
+pub struct EmbeddingsResponse {
+    pub success: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub model: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub data: Option<Vec<EmbeddingData>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub matches: Option<Vec<EmbeddingMatch>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub results: Option<Vec<RankedDocument>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub deleted: Option<usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+impl EmbeddingsResponse {
+    fn ok() -> Self {
+        EmbeddingsResponse {
+            success: true,
+            ..Default::default()
+        }
+    }
+
+    fn error(error: impl Into<String>) -> Self {
+        EmbeddingsResponse {
+            success: false,
+            error: Some(error.into()),
+            ..Default::default()
+        }
+    }
+}
+
+pub fn config(cfg: &mut web::ServiceConfig) {
+    cfg.service(
+        web::scope("/api/embeddings")
+            .route("", web::post().to(embed))
+            .route("/search", web::post().to(search))
+            .route("/collections/{collection}", web::delete().to(delete_collection))
+            .route("/{id}", web::delete().to(delete_embedding)),
+    )
+    .route("/api/rerank", web::post().to(rerank));
+}
+
+/// The embeddings API of the memory settings or the active AI provider
+/// (see `EmbeddingConfig::for_api`)
+async fn embedding_provider(state: &web::Data<AppState>) -> Result<Box<dyn EmbeddingProvider>, HttpResponse> {
+    let config = match EmbeddingConfig::for_api(&state.db).await {
+        Ok(config) => config,
+        Err(e) => {
+            log::error!("Failed to load agent settings: {}", e);
+            return Err(HttpResponse::InternalServerError().json(EmbeddingsResponse::error("Internal server error")));
+        }
+    };
+    if !config.is_enabled() {
+        return Err(HttpResponse::ServiceUnavailable().json(EmbeddingsResponse::error(
+            "No embeddings API configured: set STARK_MEMORY_EMBEDDING_API_KEY or use an OpenAI-compatible AI provider",
+        )));
+    }
+    Ok(create_provider(&config))
+}
+
+fn validate_texts(texts: &[String], what: &str) -> Result<(), String> {
+    if texts.is_empty() || texts.len() > MAX_INPUTS {
+        return Err(format!("Between 1 and {} {} are allowed", MAX_INPUTS, what));
+    }
+    if texts.iter().any(|t| t.trim().is_empty()) {
+        return Err(format!("The {} cannot be empty", what));
+    }
+    if texts.iter().any(|t| t.len() > MAX_INPUT_BYTES) {
+        return Err(format!("Each of the {} must be at most {} bytes", what, MAX_INPUT_BYTES));
+    }
+    Ok(())
+}
+
+fn validate_collection(collection: &str) -> Result<(), String> {
+    let valid = !collection.is_empty()
+        && collection.len() <= 64
+        && collection.chars().all(|c| c.is_ascii_alphanumeric() || matches!(c, '_' | '-' | '.'));
+    if valid {
+        Ok(())
+    } else {
+        Err("collection must be 1-64 letters, digits, '_', '-' or '.'".to_string())
+    }
+}
+
+/// Embed texts, storing them when a collection is given
+async fn embed(
+    state: web::Data<AppState>,
+    req: HttpRequest,
+    body: web::Json<EmbedRequest>,
+) -> impl Responder {
+    let body = body.into_inner();
+    // Embedding spends provider credit like chat; storing changes data
+    let scope = if body.collection.is_some() { Scope::Admin } else { Scope::Chat };
+    if let Err(resp) = validate_session_from_request(&state, &req, scope).await {
+        return resp;
+    }
+
+    let texts = body.input.into_vec();
+    let checked = validate_texts(&texts, "inputs").and_then(|_| {
+        if let Some(collection) = &body.collection {
+            validate_collection(collection)?;
+        } else if body.ids.is_some() || body.metadata.is_some() {
+            return Err("ids and metadata need a collection".to_string());
+        }
+        if body.ids.as_ref().is_some_and(|ids| ids.len() != texts.len()) {
+            return Err("ids must have one entry per input".to_string());
+        }
+        if body.metadata.as_ref().is_some_and(|m| !m.is_object()) {
+            return Err("metadata must be a JSON object".to_string());
+        }
+        Ok(())
+    });
+    if let Err(e) = checked {
+        return HttpResponse::BadRequest().json(EmbeddingsResponse::error(e));
+    }
+
+    let provider = match embedding_provider(&state).await {
+        Ok(provider) => provider,
+        Err(resp) => return resp,
+    };
+    let refs: Vec<&str> = texts.iter().map(String::as_str).collect();
+    let embeddings = match provider.embed_batch(&refs).await {
+        Ok(embeddings) if embeddings.len() == texts.len() => embeddings,
+        Ok(embeddings) => {
+            return HttpResponse::BadGateway().json(EmbeddingsResponse::error(format!(
+                "Expected {} embeddings, got {}",
+                texts.len(),
+                embeddings.len()
+            )));
+        }
+        Err(e) => return HttpResponse::BadGateway().json(EmbeddingsResponse::error(e)),
+    };
+
+    let mut data = Vec::with_capacity(embeddings.len());
+    for (index, embedding) in embeddings.iter().enumerate() {
+        let stored = match &body.collection {
+            None => None,
+            Some(collection) => {
+                let external_id = body.ids.as_ref().map(|ids| ids[index].as_str());
+                match state
+                    .db
+                    .store_embedding(collection, external_id, &texts[index], body.metadata.as_ref(), embedding)
+                    .await
+                {
+                    Ok(stored) => Some(stored),
+                    Err(e) => {
+                        log::error!("Failed to store embedding: {}", e);
+                        return HttpResponse::InternalServerError()
+                            .json(EmbeddingsResponse::error("Failed to store embeddings"));
+                    }
+                }
+            }
+        };
+        data.push(EmbeddingData { index, embedding: embedding.vector.clone(), stored });
+    }
+
+    HttpResponse::Ok().json(EmbeddingsResponse {
+        model: embeddings.first().map(|e| e.model.clone()),
+        data: Some(data),
+        ..EmbeddingsResponse::ok()
+    })
+}
+
+/// Find the stored texts of a collection most similar to a query
+async fn search(
+    state: web::Data<AppState>,
+    req: HttpRequest,
+    body: web::Json<SearchRequest>,
+) -> impl Responder {
+    if let Err(resp) = validate_session_from_request(&state, &req, Scope::Chat).await {
+        return resp;
+    }
+
+    let checked = validate_collection(&body.collection)
+        .and_then(|_| validate_texts(std::slice::from_ref(&body.query), "query"));
+    if let Err(e) = checked {
+        return HttpResponse::BadRequest().json(EmbeddingsResponse::error(e));
+    }
+
+    let provider = match embedding_provider(&state).await {
+        Ok(provider) => provider,
+        Err(resp) => return resp,
+    };
+    let query = match provider.embed(&body.query).await {
+        Ok(query) => query,
+        Err(e) => return HttpResponse::BadGateway().json(EmbeddingsResponse::error(e)),
+    };
+
+    // Stored vectors are compared only with queries of the model that made them
+    let limit = body.limit.clamp(1, MAX_SEARCH_LIMIT);
+    match state.db.search_embeddings(&body.collection, &query.model, &query.vector, limit).await {
+        Ok(matches) => HttpResponse::Ok().json(EmbeddingsResponse {
+            model: Some(query.model),
+            matches: Some(matches),
+            ..EmbeddingsResponse::ok()
+        }),
+        Err(e) => {
+            log::error!("Failed to search embeddings: {}", e);
+            HttpResponse::InternalServerError().json(EmbeddingsResponse::error("Failed to search embeddings"))
+        }
+    }
+}
+
+/// Order documents by relevance to a query
+async fn rerank(
+    state: web::Data<AppState>,
+    req: HttpRequest,
+    body: web::Json<RerankRequest>,
+) -> impl Responder {
+    if let Err(resp) = validate_session_from_request(&state, &req, Scope::Chat).await {
+        return resp;
+    }
+
+    let checked = validate_texts(std::slice::from_ref(&body.query), "query")
+        .and_then(|_| validate_texts(&body.documents, "documents"));
+    if let Err(e) = checked {
+        return HttpResponse::BadRequest().json(EmbeddingsResponse::error(e));
+    }
+
+    let provider = match embedding_provider(&state).await {
+        Ok(provider) => provider,
+        Err(resp) => return resp,
+    };
+    let documents: Vec<&str> = body.documents.iter().map(String::as_str).collect();
+    match provider.rerank(&body.query, &documents).await {
+        Ok(ranked) => {
+            let results = ranked
+                .into_iter()
+                .take(body.top_n.unwrap_or(documents.len()))
+                .map(|r| RankedDocument {
+                    document: body.documents[r.index].clone(),
+                    index: r.index,
+                    relevance_score: r.relevance_score,
+                })
+                .collect();
+            HttpResponse::Ok().json(EmbeddingsResponse {
+                model: Some(provider.model_name().to_string()),
+                results: Some(results),
+                ..EmbeddingsResponse::ok()
+            })
+        }
+        Err(e) => HttpResponse::BadGateway().json(EmbeddingsResponse::error(e)),
+    }
+}
+
+async fn delete_embedding(
+    state: web::Data<AppState>,
+    req: HttpRequest,
+    path: web::Path<i64>,
+) -> impl Responder {
+    if let Err(resp) = validate_session_from_request(&state, &req, Scope::Admin).await {
+        return resp;
+    }
+
+    match state.db.delete_embedding(path.into_inner()).await {
+        Ok(true) => HttpResponse::Ok().json(EmbeddingsResponse { deleted: Some(1), ..EmbeddingsResponse::ok() }),
+        Ok(false) => HttpResponse::NotFound().json(EmbeddingsResponse::error("Embedding not found")),
+        Err(e) => {
+            log::error!("Failed to delete embedding: {}", e);
+            HttpResponse::InternalServerError().json(EmbeddingsResponse::error("Failed to delete embedding"))
+        }
+    }
+}
+
+async fn delete_collection(
+    state: web::Data<AppState>,
+    req: HttpRequest,
+    path: web::Path<String>,
+) -> impl Responder {
+    if let Err(resp) = validate_session_from_request(&state, &req, Scope::Admin).await {
+        return resp;
+    }
+
+    match state.db.delete_embedding_collection(&path.into_inner()).await {
+        Ok(deleted) => HttpResponse::Ok().json(EmbeddingsResponse { deleted: Some(deleted), ..EmbeddingsResponse::ok() }),
+        Err(e) => {
+            log::error!("Failed to delete embedding collection: {}", e);
+            HttpResponse::InternalServerError().json(EmbeddingsResponse::error("Failed to delete collection"))
+        }
+    }
+}
@@ -4,12 +4,14 @@ use std::path::Path;
 use tokio::fs;
 
 use crate::config::workspace_dir;
+use crate::models::Scope;
 use crate::AppState;
 
 /// Validate session token from request
-fn validate_session_from_request(
+async fn validate_session_from_request(
     state: &web::Data<AppState>,
     req: &HttpRequest,
+    scope: Scope,
 ) -> Result<(), HttpResponse> {
     let token = req
         .headers()
@@ -26,8 +28,11 @@ fn validate_session_from_request(
         }
     };
 
-    match state.db.validate_session(&token) {
-        Ok(Some(_)) => Ok(()),
+    match state.db.authorize(&token).await {
+        Ok(Some(scopes)) if scopes.allows(scope) => Ok(()),
+        Ok(Some(_)) => Err(HttpResponse::Forbidden().json(serde_json::json!({
+            "error": format!("Token lacks the {} scope", scope)
+        }))),
         Ok(None) => Err(HttpResponse::Unauthorized().json(serde_json::json!({
             "error": "Invalid or expired session"
         }))),
@@ -69,7 +74,7 @@ async fn list_files(
     req: HttpRequest,
     query: web::Query<ListFilesQuery>,
 ) -> impl Responder {
-    if let Err(resp) = validate_session_from_request(&data, &req) {
+    if let Err(resp) = validate_session_from_request(&data, &req, Scope::Read).await {
         return resp;
     }
 
@@ -216,7 +221,7 @@ async fn read_file(
     req: HttpRequest,
     query: web::Query<ReadFileQuery>,
 ) -> impl Responder {
-    if let Err(resp) = validate_session_from_request(&data, &req) {
+    if let Err(resp) = validate_session_from_request(&data, &req, Scope::Read).await {
         return resp;
     }
 
@@ -354,7 +359,7 @@ async fn workspace_info(
     data: web::Data<AppState>,
     req: HttpRequest,
 ) -> impl Responder {
-    if let Err(resp) = validate_session_from_request(&data, &req) {
+    if let Err(resp) = validate_session_from_request(&data, &req, Scope::Read).await {
         return resp;
     }
 
@@ -15,7 +15,9 @@ use crate::integrations::gmail::{
     GmailClient, GmailConfig, GmailConfigResponse, GmailNotificationData,
     ParsedEmail, PubSubPushNotification, SetupGmailRequest, UpdateGmailRequest,
 };
+use crate::models::Scope;
 use crate::AppState;
+use crate::utils::truncate_chars;
 
 /// Configure Gmail routes
 pub fn config(cfg: &mut web::ServiceConfig) {
@@ -73,7 +75,7 @@ async fn handle_pubsub_push(
     );
 
     // Get Gmail config for this email
-    let config = match state.db.get_gmail_config_by_email(&notification.email_address) {
+    let config = match state.db.get_gmail_config_by_email(&notification.email_address).await {
         Ok(Some(c)) if c.enabled => c,
         Ok(Some(_)) => {
             log::warn!("[GMAIL] Integration disabled for {}", notification.email_address);
@@ -187,7 +189,7 @@ async fn process_gmail_notification(
 
     // Update history ID
     if let Some(new_history_id) = history.history_id {
-        if let Err(e) = db.update_gmail_history_id(config.id, &new_history_id) {
+        if let Err(e) = db.update_gmail_history_id(config.id, &new_history_id).await {
             log::error!("[GMAIL] Failed to update history ID: {}", e);
         }
     }
@@ -213,10 +215,13 @@ async fn dispatch_email(
         {}",
         email.from,
         email.subject,
-        if email.body.len() > 4000 {
-            format!("{}...\n\n[Body truncated]", &email.body[..4000])
-        } else {
-            email.body.clone()
+        {
+            let body = truncate_chars(&email.body, 4000);
+            if body.len() < email.body.len() {
+                format!("{}...\n\n[Body truncated]", body)
+            } else {
+                email.body.clone()
+            }
         }
     );
 
@@ -230,6 +235,15 @@ async fn dispatch_email(
         text: message_content,
         message_id: Some(email.message_id.clone()),
         session_mode: None,
+        seed: None,
+        model_archetype: None,
+        model_overrides: None,
+        use_tools: None,
+        response_format: None,
+        images: Vec::new(),
+        scopes: None,
+        tool_policy: None,
+        owner_id: None,
     };
 
     // Broadcast event
@@ -293,9 +307,10 @@ fn extract_name_from_email(email: &str) -> String {
 
 // === Management Endpoints ===
 
-fn validate_session_from_request(
+async fn validate_session_from_request(
     state: &web::Data<AppState>,
     req: &HttpRequest,
+    scope: Scope,
 ) -> Result<(), HttpResponse> {
     let token = req
         .headers()
@@ -314,8 +329,13 @@ fn validate_session_from_request(
         }
     };
 
-    match state.db.validate_session(&token) {
-        Ok(Some(_)) => Ok(()),
+    match state.db.authorize(&token).await {
+        Ok(Some(scopes)) if scopes.allows(scope) => Ok(()),
+        Ok(Some(_)) => Err(HttpResponse::Forbidden().json(GmailConfigResponse {
+            success: false,
+            config: None,
+            error: Some(format!("Token lacks the {} scope", scope)),
+        })),
         Ok(None) => Err(HttpResponse::Unauthorized().json(GmailConfigResponse {
             success: false,
             config: None,
@@ -334,11 +354,11 @@ fn validate_session_from_request(
 
 /// Get Gmail configuration
 async fn get_config(state: web::Data<AppState>, req: HttpRequest) -> HttpResponse {
-    if let Err(resp) = validate_session_from_request(&state, &req) {
+    if let Err(resp) = validate_session_from_request(&state, &req, Scope::Read).await {
         return resp;
     }
 
-    match state.db.get_gmail_config() {
+    match state.db.get_gmail_config().await {
         Ok(Some(config)) => HttpResponse::Ok().json(GmailConfigResponse {
             success: true,
             config: Some(config.into()),
@@ -363,7 +383,7 @@ async fn setup_gmail(
     req: HttpRequest,
     body: web::Json<SetupGmailRequest>,
 ) -> HttpResponse {
-    if let Err(resp) = validate_session_from_request(&state, &req) {
+    if let Err(resp) = validate_session_from_request(&state, &req, Scope::Admin).await {
         return resp;
     }
 
@@ -402,7 +422,7 @@ async fn setup_gmail(
         body.watch_labels.as_deref().unwrap_or("INBOX"),
         body.response_channel_id,
         body.auto_reply.unwrap_or(false),
-    ) {
+    ).await {
         Ok(config) => HttpResponse::Created().json(GmailConfigResponse {
             success: true,
             config: Some(config.into()),
@@ -422,7 +442,7 @@ async fn update_config(
     req: HttpRequest,
     body: web::Json<UpdateGmailRequest>,
 ) -> HttpResponse {
-    if let Err(resp) = validate_session_from_request(&state, &req) {
+    if let Err(resp) = validate_session_from_request(&state, &req, Scope::Admin).await {
         return resp;
     }
 
@@ -431,7 +451,7 @@ async fn update_config(
         body.response_channel_id,
         body.auto_reply,
         body.enabled,
-    ) {
+    ).await {
         Ok(config) => HttpResponse::Ok().json(GmailConfigResponse {
             success: true,
             config: Some(config.into()),
@@ -447,11 +467,11 @@ async fn update_config(
 
 /// Delete Gmail configuration
 async fn delete_config(state: web::Data<AppState>, req: HttpRequest) -> HttpResponse {
-    if let Err(resp) = validate_session_from_request(&state, &req) {
+    if let Err(resp) = validate_session_from_request(&state, &req, Scope::Admin).await {
         return resp;
     }
 
-    match state.db.delete_gmail_config() {
+    match state.db.delete_gmail_config().await {
         Ok(true) => HttpResponse::Ok().json(GmailConfigResponse {
             success: true,
             config: None,
@@ -472,11 +492,11 @@ async fn delete_config(state: web::Data<AppState>, req: HttpRequest) -> HttpResp
 
 /// Start Gmail watch
 async fn start_watch(state: web::Data<AppState>, req: HttpRequest) -> HttpResponse {
-    if let Err(resp) = validate_session_from_request(&state, &req) {
+    if let Err(resp) = validate_session_from_request(&state, &req, Scope::Admin).await {
         return resp;
     }
 
-    let config = match state.db.get_gmail_config() {
+    let config = match state.db.get_gmail_config().await {
         Ok(Some(c)) => c,
         Ok(None) => {
             return HttpResponse::NotFound().json(GmailConfigResponse {
@@ -515,7 +535,7 @@ async fn start_watch(state: web::Data<AppState>, req: HttpRequest) -> HttpRespon
                 config.id,
                 expiration,
                 Some(&watch_response.history_id),
-            ) {
+            ).await {
                 log::error!("[GMAIL] Failed to update watch info: {}", e);
             }
 
@@ -535,11 +555,11 @@ async fn start_watch(state: web::Data<AppState>, req: HttpRequest) -> HttpRespon
 
 /// Stop Gmail watch
 async fn stop_watch(state: web::Data<AppState>, req: HttpRequest) -> HttpResponse {
-    if let Err(resp) = validate_session_from_request(&state, &req) {
+    if let Err(resp) = validate_session_from_request(&state, &req, Scope::Admin).await {
         return resp;
     }
 
-    let config = match state.db.get_gmail_config() {
+    let config = match state.db.get_gmail_config().await {
         Ok(Some(c)) => c,
         Ok(None) => {
             return HttpResponse::NotFound().json(GmailConfigResponse {
@@ -562,7 +582,7 @@ async fn stop_watch(state: web::Data<AppState>, req: HttpRequest) -> HttpRespons
     match client.stop_watch("me").await {
         Ok(()) => {
             // Clear watch expiration
-            if let Err(e) = state.db.update_gmail_watch(config.id, None, None) {
+            if let Err(e) = state.db.update_gmail_watch(config.id, None, None).await {
                 log::error!("[GMAIL] Failed to clear watch info: {}", e);
             }
 
@@ -578,11 +598,11 @@ async fn stop_watch(state: web::Data<AppState>, req: HttpRequest) -> HttpRespons
 
 /// Test Gmail connection
 async fn test_connection(state: web::Data<AppState>, req: HttpRequest) -> HttpResponse {
-    if let Err(resp) = validate_session_from_request(&state, &req) {
+    if let Err(resp) = validate_session_from_request(&state, &req, Scope::Admin).await {
         return resp;
     }
 
-    let config = match state.db.get_gmail_config() {
+    let config = match state.db.get_gmail_config().await {
         Ok(Some(c)) => c,
         Ok(None) => {
             return HttpResponse::NotFound().json(serde_json::json!({
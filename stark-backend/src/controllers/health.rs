@@ -1,11 +1,38 @@
-use actix_web::{web, HttpResponse, Responder};
+//! Health and version endpoints, including the Kubernetes-style probes
+//!
+//! - `/healthz` (liveness): the process answers and its database responds
+//! - `/readyz` (readiness): also checks migrations, free disk space on the
+//!   workspace volume and whether the AI provider can be reached, and reports
+//!   not ready while the server is shutting down
+//!
+//! Both are unauthenticated and return a JSON body with one entry per check.
+//! A failed check makes the probe return `503`. An unreachable AI provider
+//! only marks the server `degraded`, since every instance shares the provider
+//! and taking them all out of rotation would not help.
+
+use actix_web::{http::StatusCode, web, HttpResponse, Responder};
+use chrono::{DateTime, Utc};
+use once_cell::sync::Lazy;
+use parking_lot::Mutex;
+use serde::Serialize;
+use serde_json::{json, Map, Value};
+use std::collections::BTreeMap;
+use std::time::{Duration, Instant};
+
+use crate::config;
+use crate::AppState;
 
 /// Version from Cargo.toml, available at compile time
 pub const VERSION: &str = env!("CARGO_PKG_VERSION");
 
+/// How long the AI provider has to answer the reachability check
+const PROVIDER_TIMEOUT: Duration = Duration::from_secs(5);
+
 pub fn config(cfg: &mut web::ServiceConfig) {
     cfg.service(web::resource("/api/health").route(web::get().to(health_check)));
     cfg.service(web::resource("/api/version").route(web::get().to(get_version)));
+    cfg.service(web::resource("/healthz").route(web::get().to(liveness)));
+    cfg.service(web::resource("/readyz").route(web::get().to(readiness)));
 }
 
 async fn health_check() -> impl Responder {
@@ -20,3 +47,224 @@ async fn get_version() -> impl Responder {
         "version": VERSION
     }))
 }
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "lowercase")]
+enum Status {
+    Ok,
+    /// Not configured, so not checked
+    Skipped,
+    /// Working, but something non-essential is not
+    Degraded,
+    Fail,
+}
+
+#[derive(Debug, Clone, Serialize)]
+struct Check {
+    status: Status,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<String>,
+    #[serde(flatten)]
+    details: Map<String, Value>,
+}
+
+impl Check {
+    fn ok(details: Value) -> Self {
+        Self::with_status(Status::Ok, None, details)
+    }
+
+    fn fail(error: impl Into<String>, details: Value) -> Self {
+        Self::with_status(Status::Fail, Some(error.into()), details)
+    }
+
+    fn with_status(status: Status, error: Option<String>, details: Value) -> Self {
+        let details = match details {
+            Value::Object(map) => map,
+            _ => Map::new(),
+        };
+        Self { status, error, details }
+    }
+}
+
+#[derive(Serialize)]
+struct ProbeResponse {
+    status: Status,
+    version: &'static str,
+    checks: BTreeMap<&'static str, Check>,
+}
+
+/// The worst status among the checks
+fn overall(checks: &BTreeMap<&'static str, Check>) -> Status {
+    match checks.values().map(|c| c.status).max() {
+        Some(Status::Skipped) | None => Status::Ok,
+        Some(status) => status,
+    }
+}
+
+fn respond(checks: BTreeMap<&'static str, Check>) -> HttpResponse {
+    let status = overall(&checks);
+    let code = if status == Status::Fail { StatusCode::SERVICE_UNAVAILABLE } else { StatusCode::OK };
+    HttpResponse::build(code).json(ProbeResponse { status, version: VERSION, checks })
+}
+
+/// Liveness: the process answers and its database responds
+async fn liveness(state: web::Data<AppState>) -> impl Responder {
+    let mut checks = BTreeMap::new();
+    checks.insert("database", check_database(&state).await);
+    respond(checks)
+}
+
+/// Readiness: every dependency needed to serve requests and run jobs
+async fn readiness(state: web::Data<AppState>) -> impl Responder {
+    let mut checks = BTreeMap::new();
+    checks.insert("database", check_database(&state).await);
+    checks.insert("migrations", check_migrations(&state).await);
+    checks.insert("disk", check_disk().await);
+    checks.insert("provider", check_provider(&state).await);
+    if state.agent_jobs.is_stopping() {
+        checks.insert("shutdown", Check::fail("The server is shutting down", Value::Null));
+    }
+    respond(checks)
+}
+
+async fn check_database(state: &AppState) -> Check {
+    let started = Instant::now();
+    let result = state.db.ping().await;
+    let details = json!({
+        "backend": state.db.backend_name(),
+        "latency_ms": started.elapsed().as_millis(),
+    });
+    match result {
+        Ok(()) => Check::ok(details),
+        Err(e) => {
+            log::warn!("[HEALTH] Database check failed: {}", e);
+            Check::fail(e.to_string(), details)
+        }
+    }
+}
+
+async fn check_migrations(state: &AppState) -> Check {
+    match state.db.migration_status().await {
+        Ok(status) if status.current < status.latest => Check::fail(
+            "Migrations are pending; run the server with --migrate",
+            json!(status),
+        ),
+        Ok(status) if status.current > status.latest => Check::fail(
+            "The database schema is newer than this build",
+            json!(status),
+        ),
+        Ok(status) => Check::ok(json!(status)),
+        Err(e) => Check::fail(e.to_string(), Value::Null),
+    }
+}
+
+async fn check_disk() -> Check {
+    let dir = config::workspace_dir();
+    let min_bytes = config::min_free_disk_bytes();
+    let available = tokio::task::spawn_blocking(move || fs2::available_space(&dir)).await;
+    match available {
+        Ok(Ok(available)) => {
+            let details = json!({ "available_bytes": available, "min_bytes": min_bytes });
+            if available < min_bytes {
+                Check::fail("Low disk space on the workspace volume", details)
+            } else {
+                Check::ok(details)
+            }
+        }
+        Ok(Err(e)) => Check::fail(format!("Cannot read the workspace volume: {}", e), Value::Null),
+        Err(e) => Check::fail(e.to_string(), Value::Null),
+    }
+}
+
+/// Last provider check, reused for `config::provider_check_ttl()`
+struct ProviderCheck {
+    endpoint: String,
+    at: Instant,
+    checked_at: DateTime<Utc>,
+    result: Result<u16, String>,
+}
+
+static PROVIDER_CACHE: Lazy<Mutex<Option<ProviderCheck>>> = Lazy::new(|| Mutex::new(None));
+
+/// Whether the active provider's endpoint answers at all; any HTTP response
+/// counts, since the probe sends no credentials
+async fn check_provider(state: &AppState) -> Check {
+    let endpoint = match state.db.get_active_agent_settings().await {
+        Ok(Some(settings)) => settings.endpoint,
+        Ok(None) => return Check::with_status(Status::Skipped, None, json!({ "configured": false })),
+        Err(e) => return Check::with_status(Status::Degraded, Some(e.to_string()), Value::Null),
+    };
+
+    let cached = PROVIDER_CACHE
+        .lock()
+        .as_ref()
+        .filter(|c| c.endpoint == endpoint && c.at.elapsed() < config::provider_check_ttl())
+        .map(|c| (c.checked_at, c.result.clone()));
+    let (checked_at, result, cached) = match cached {
+        Some((checked_at, result)) => (checked_at, result, true),
+        None => {
+            let result = probe_endpoint(&endpoint).await;
+            let checked_at = Utc::now();
+            *PROVIDER_CACHE.lock() = Some(ProviderCheck {
+                endpoint: endpoint.clone(),
+                at: Instant::now(),
+                checked_at,
+                result: result.clone(),
+            });
+            (checked_at, result, false)
+        }
+    };
+
+    let host = url::Url::parse(&endpoint)
+        .ok()
+        .and_then(|u| u.host_str().map(str::to_string));
+    match result {
+        Ok(http_status) => Check::ok(json!({
+            "host": host,
+            "http_status": http_status,
+            "checked_at": checked_at,
+            "cached": cached,
+        })),
+        Err(e) => Check::with_status(
+            Status::Degraded,
+            Some(e),
+            json!({ "host": host, "checked_at": checked_at, "cached": cached }),
+        ),
+    }
+}
+
+async fn probe_endpoint(endpoint: &str) -> Result<u16, String> {
+    let client = reqwest::Client::builder()
+        .timeout(PROVIDER_TIMEOUT)
+        .build()
+        .map_err(|e| e.to_string())?;
+    match client.get(endpoint).send().await {
+        Ok(response) => Ok(response.status().as_u16()),
+        Err(e) => {
+            log::warn!("[HEALTH] AI provider at {} is unreachable: {}", endpoint, e);
+            Err(format!("AI provider is unreachable: {}", e.without_url()))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_overall_status() {
+        let mut checks = BTreeMap::new();
+        checks.insert("database", Check::ok(json!({ "backend": "sqlite" })));
+        checks.insert("provider", Check::with_status(Status::Skipped, None, Value::Null));
+        assert_eq!(overall(&checks), Status::Ok);
+
+        checks.insert("provider", Check::with_status(Status::Degraded, Some("down".into()), Value::Null));
+        assert_eq!(overall(&checks), Status::Degraded);
+
+        checks.insert("disk", Check::fail("Low disk space", json!({ "available_bytes": 1 })));
+        assert_eq!(overall(&checks), Status::Fail);
+
+        let body = serde_json::to_value(&checks["disk"]).unwrap();
+        assert_eq!(body, json!({ "status": "fail", "error": "Low disk space", "available_bytes": 1 }));
+    }
+}
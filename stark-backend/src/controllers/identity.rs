@@ -2,14 +2,15 @@ use actix_web::{web, HttpRequest, HttpResponse, Responder};
 use serde::Deserialize;
 
 use crate::models::{
-    GetOrCreateIdentityRequest, IdentityResponse, LinkIdentityRequest, LinkedAccountInfo,
+    GetOrCreateIdentityRequest, IdentityResponse, LinkIdentityRequest, LinkedAccountInfo, Scope,
 };
 use crate::AppState;
 
 /// Validate session token from request
-fn validate_session_from_request(
+async fn validate_session_from_request(
     state: &web::Data<AppState>,
     req: &HttpRequest,
+    scope: Scope,
 ) -> Result<(), HttpResponse> {
     let token = req
         .headers()
@@ -26,8 +27,11 @@ fn validate_session_from_request(
         }
     };
 
-    match state.db.validate_session(&token) {
-        Ok(Some(_)) => Ok(()),
+    match state.db.authorize(&token).await {
+        Ok(Some(scopes)) if scopes.allows(scope) => Ok(()),
+        Ok(Some(_)) => Err(HttpResponse::Forbidden().json(serde_json::json!({
+            "error": format!("Token lacks the {} scope", scope)
+        }))),
         Ok(None) => Err(HttpResponse::Unauthorized().json(serde_json::json!({
             "error": "Invalid or expired session"
         }))),
@@ -45,11 +49,11 @@ async fn list_identities(
     data: web::Data<AppState>,
     req: HttpRequest,
 ) -> impl Responder {
-    if let Err(resp) = validate_session_from_request(&data, &req) {
+    if let Err(resp) = validate_session_from_request(&data, &req, Scope::Read).await {
         return resp;
     }
 
-    match data.db.list_identities() {
+    match data.db.list_identities().await {
         Ok(links) => {
             // Group by identity_id and return unique identities
             let mut seen = std::collections::HashSet::new();
@@ -83,17 +87,17 @@ async fn get_or_create_identity(
     req: HttpRequest,
     body: web::Json<GetOrCreateIdentityRequest>,
 ) -> impl Responder {
-    if let Err(resp) = validate_session_from_request(&data, &req) {
+    if let Err(resp) = validate_session_from_request(&data, &req, Scope::Admin).await {
         return resp;
     }
     match data.db.get_or_create_identity(
         &body.channel_type,
         &body.platform_user_id,
         body.platform_user_name.as_deref(),
-    ) {
+    ).await {
         Ok(link) => {
             // Get all linked accounts for this identity
-            let linked_accounts = match data.db.get_linked_identities(&link.identity_id) {
+            let linked_accounts = match data.db.get_linked_identities(&link.identity_id).await {
                 Ok(links) => links.iter().map(LinkedAccountInfo::from).collect(),
                 Err(_) => vec![LinkedAccountInfo::from(&link)],
             };
@@ -125,16 +129,16 @@ async fn get_identity(
     req: HttpRequest,
     query: web::Query<GetIdentityQuery>,
 ) -> impl Responder {
-    if let Err(resp) = validate_session_from_request(&data, &req) {
+    if let Err(resp) = validate_session_from_request(&data, &req, Scope::Read).await {
         return resp;
     }
     match data
         .db
-        .get_identity_by_platform(&query.channel_type, &query.platform_user_id)
+        .get_identity_by_platform(&query.channel_type, &query.platform_user_id).await
     {
         Ok(Some(link)) => {
             // Get all linked accounts for this identity
-            let linked_accounts = match data.db.get_linked_identities(&link.identity_id) {
+            let linked_accounts = match data.db.get_linked_identities(&link.identity_id).await {
                 Ok(links) => links.iter().map(LinkedAccountInfo::from).collect(),
                 Err(_) => vec![LinkedAccountInfo::from(&link)],
             };
@@ -163,13 +167,13 @@ async fn link_identity(
     req: HttpRequest,
     body: web::Json<LinkIdentityRequest>,
 ) -> impl Responder {
-    if let Err(resp) = validate_session_from_request(&data, &req) {
+    if let Err(resp) = validate_session_from_request(&data, &req, Scope::Admin).await {
         return resp;
     }
     // First check if this platform/user already has an identity
     if let Ok(Some(_)) = data
         .db
-        .get_identity_by_platform(&body.channel_type, &body.platform_user_id)
+        .get_identity_by_platform(&body.channel_type, &body.platform_user_id).await
     {
         return HttpResponse::Conflict().json(serde_json::json!({
             "error": "This platform user is already linked to an identity"
@@ -181,10 +185,10 @@ async fn link_identity(
         &body.channel_type,
         &body.platform_user_id,
         body.platform_user_name.as_deref(),
-    ) {
+    ).await {
         Ok(link) => {
             // Get all linked accounts for this identity
-            let linked_accounts = match data.db.get_linked_identities(&link.identity_id) {
+            let linked_accounts = match data.db.get_linked_identities(&link.identity_id).await {
                 Ok(links) => links.iter().map(LinkedAccountInfo::from).collect(),
                 Err(_) => vec![LinkedAccountInfo::from(&link)],
             };
@@ -210,12 +214,12 @@ async fn get_linked_identities(
     req: HttpRequest,
     path: web::Path<String>,
 ) -> impl Responder {
-    if let Err(resp) = validate_session_from_request(&data, &req) {
+    if let Err(resp) = validate_session_from_request(&data, &req, Scope::Read).await {
         return resp;
     }
     let identity_id = path.into_inner();
 
-    match data.db.get_linked_identities(&identity_id) {
+    match data.db.get_linked_identities(&identity_id).await {
         Ok(links) if !links.is_empty() => {
             let linked_accounts: Vec<LinkedAccountInfo> =
                 links.iter().map(LinkedAccountInfo::from).collect();
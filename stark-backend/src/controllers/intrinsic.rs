@@ -3,12 +3,14 @@ use serde::{Deserialize, Serialize};
 use std::path::Path;
 use tokio::fs;
 
+use crate::models::Scope;
 use crate::AppState;
 
 /// Validate session token from request
-fn validate_session_from_request(
+async fn validate_session_from_request(
     state: &web::Data<AppState>,
     req: &HttpRequest,
+    scope: Scope,
 ) -> Result<(), HttpResponse> {
     let token = req
         .headers()
@@ -25,8 +27,11 @@ fn validate_session_from_request(
         }
     };
 
-    match state.db.validate_session(&token) {
-        Ok(Some(_)) => Ok(()),
+    match state.db.authorize(&token).await {
+        Ok(Some(scopes)) if scopes.allows(scope) => Ok(()),
+        Ok(Some(_)) => Err(HttpResponse::Forbidden().json(serde_json::json!({
+            "error": format!("Token lacks the {} scope", scope)
+        }))),
         Ok(None) => Err(HttpResponse::Unauthorized().json(serde_json::json!({
             "error": "Invalid or expired session"
         }))),
@@ -96,7 +101,7 @@ async fn list_intrinsic(
     data: web::Data<AppState>,
     req: HttpRequest,
 ) -> impl Responder {
-    if let Err(resp) = validate_session_from_request(&data, &req) {
+    if let Err(resp) = validate_session_from_request(&data, &req, Scope::Read).await {
         return resp;
     }
 
@@ -132,7 +137,7 @@ async fn read_intrinsic(
     req: HttpRequest,
     path: web::Path<String>,
 ) -> impl Responder {
-    if let Err(resp) = validate_session_from_request(&data, &req) {
+    if let Err(resp) = validate_session_from_request(&data, &req, Scope::Read).await {
         return resp;
     }
 
@@ -199,7 +204,7 @@ async fn write_intrinsic(
     path: web::Path<String>,
     body: web::Json<WriteIntrinsicRequest>,
 ) -> impl Responder {
-    if let Err(resp) = validate_session_from_request(&data, &req) {
+    if let Err(resp) = validate_session_from_request(&data, &req, Scope::Admin).await {
         return resp;
     }
 
@@ -0,0 +1,213 @@
+use actix_web::{web, HttpRequest, HttpResponse, Responder};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::sync::Arc;
+use std::time::Duration;
+
+use crate::mcp::sse::event_frame;
+use crate::mcp::{error_response, PARSE_ERROR};
+use crate::models::{Caller, Scope};
+use crate::AppState;
+
+/// How often an idle stream gets a comment, so proxies keep it open and
+/// closed clients are noticed
+const KEEP_ALIVE_INTERVAL: Duration = Duration::from_secs(15);
+
+/// Validate session token from request
+async fn validate_session_from_request(
+    state: &web::Data<AppState>,
+    req: &HttpRequest,
+    scope: Scope,
+) -> Result<Caller, HttpResponse> {
+    let token = req
+        .headers()
+        .get("Authorization")
+        .and_then(|h| h.to_str().ok())
+        .map(|s| s.trim_start_matches("Bearer ").to_string());
+
+    let token = match token {
+        Some(t) => t,
+        None => {
+            return Err(HttpResponse::Unauthorized().json(McpResponse::error(
+                "No authorization token provided",
+            )));
+        }
+    };
+
+    match state.db.authenticate(&token).await {
+        Ok(Some(caller)) if caller.scopes.allows(scope) => Ok(caller),
+        Ok(Some(_)) => Err(HttpResponse::Forbidden().json(McpResponse::error(format!(
+            "Token lacks the {} scope",
+            scope
+        )))),
+        Ok(None) => Err(HttpResponse::Unauthorized().json(McpResponse::error(
+            "Invalid or expired session",
+        ))),
+        Err(e) => {
+            log::error!("Session validation error: {}", e);
+            Err(HttpResponse::InternalServerError().json(McpResponse::error("Internal server error")))
+        }
+    }
+}
+
+#[derive(Serialize)]
+pub struct McpResponse {
+    pub success: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+impl McpResponse {
+    fn error(error: impl Into<String>) -> Self {
+        McpResponse {
+            success: false,
+            error: Some(error.into()),
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct MessageQuery {
+    session_id: String,
+}
+
+pub fn config(cfg: &mut web::ServiceConfig) {
+    cfg.service(
+        web::scope("/api/mcp")
+            .route("/sse", web::get().to(open_stream))
+            .route("/messages", web::post().to(post_message)),
+    );
+}
+
+/// Open an MCP event stream. Tools are limited to what the token's scopes and
+/// tool policy allow, and work in the caller's own workspace.
+async fn open_stream(state: web::Data<AppState>, req: HttpRequest) -> impl Responder {
+    // Calling tools is what chat does on the user's behalf, so it needs the same scope
+    let caller = match validate_session_from_request(&state, &req, Scope::Chat).await {
+        Ok(caller) => caller,
+        Err(resp) => return resp,
+    };
+
+    let session = match state.mcp.session(Some(&caller)) {
+        Ok(session) => session,
+        Err(e) => {
+            log::error!("[MCP] Failed to start session: {}", e);
+            return HttpResponse::InternalServerError().json(McpResponse::error("Failed to start MCP session"));
+        }
+    };
+
+    let (session_id, mut frames) = state.mcp_sessions.open(session);
+    let stream_session = state.mcp_sessions.get(&session_id).expect("session was just opened");
+    log::info!("[MCP] SSE session {} opened", session_id);
+
+    // The first event tells the client where to post its messages
+    let endpoint = format!("/api/mcp/messages?session_id={}", session_id);
+    let sessions = Arc::clone(&state.mcp_sessions);
+    tokio::spawn(async move {
+        if stream_session.send_frame(event_frame("endpoint", &endpoint)).await.is_ok() {
+            let mut interval = tokio::time::interval(KEEP_ALIVE_INTERVAL);
+            interval.tick().await;
+            loop {
+                interval.tick().await;
+                if stream_session.send_frame(": keep-alive\n\n".to_string()).await.is_err() {
+                    break;
+                }
+            }
+        }
+        sessions.close(&session_id);
+        log::info!("[MCP] SSE session {} closed", session_id);
+    });
+
+    let stream = futures_util::stream::poll_fn(move |cx| {
+        frames
+            .poll_recv(cx)
+            .map(|frame| frame.map(|f| Ok::<_, std::io::Error>(web::Bytes::from(f))))
+    });
+    HttpResponse::Ok()
+        .content_type("text/event-stream")
+        .insert_header(("Cache-Control", "no-cache"))
+        .insert_header(("X-Accel-Buffering", "no"))
+        .streaming(stream)
+}
+
+/// Accept a JSON-RPC message for one of the caller's open streams; the
+/// response is sent on the stream
+async fn post_message(
+    state: web::Data<AppState>,
+    req: HttpRequest,
+    query: web::Query<MessageQuery>,
+    body: web::Bytes,
+) -> impl Responder {
+    let caller = match validate_session_from_request(&state, &req, Scope::Chat).await {
+        Ok(caller) => caller,
+        Err(resp) => return resp,
+    };
+
+    // Other users' sessions look the same as missing ones, admins' included:
+    // the tools would run with the opener's scopes and workspace
+    let stream = match state.mcp_sessions.get(&query.session_id) {
+        Some(stream) if stream.session.user_id() == Some(caller.user.id) => stream,
+        _ => return HttpResponse::NotFound().json(McpResponse::error("MCP session not found")),
+    };
+
+    let message = match serde_json::from_slice::<Value>(&body) {
+        Ok(message) => message,
+        Err(e) => {
+            return HttpResponse::BadRequest()
+                .json(error_response(Value::Null, PARSE_ERROR, format!("Parse error: {}", e)));
+        }
+    };
+
+    // Tool calls can take a while; answer on the stream once done
+    let server = Arc::clone(&state.mcp);
+    tokio::spawn(async move {
+        if let Some(response) = server.handle(&stream.session, message).await
+            && let Err(e) = stream.send(&response).await
+        {
+            log::warn!("[MCP] Dropped response: {}", e);
+        }
+    });
+
+    HttpResponse::Accepted().finish()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::Database;
+    use crate::models::{Scopes, ToolPolicy, UserRole};
+    use actix_web::{test, App};
+
+    #[actix_web::test]
+    async fn test_messages_only_reach_the_openers_session() {
+        let db = Arc::new(Database::new(":memory:").unwrap());
+        let workspace = tempfile::tempdir().unwrap();
+        let policy = ToolPolicy { workspace_root: Some(workspace.path().to_string_lossy().to_string()), ..Default::default() };
+        let mut tokens = Vec::new();
+        for (address, role) in [("0xa11ce", UserRole::Member), ("0xad", UserRole::Admin)] {
+            let user = db.create_user(address, None, role).await.unwrap();
+            let scopes = Scopes::new([Scope::Chat]);
+            tokens.push(db.create_api_token(user.id, address, &scopes, Some(&policy)).await.unwrap().token);
+        }
+        let (alice, admin) = (&tokens[0], &tokens[1]);
+
+        let state = web::Data::new(AppState::for_tests(db.clone()).await);
+        let caller = db.authenticate(alice).await.unwrap().unwrap();
+        let (session_id, mut frames) = state.mcp_sessions.open(state.mcp.session(Some(&caller)).unwrap());
+        let app = test::init_service(App::new().app_data(state.clone()).configure(config)).await;
+
+        let post = |token: &str| {
+            test::TestRequest::post()
+                .uri(&format!("/api/mcp/messages?session_id={}", session_id))
+                .insert_header(("Authorization", format!("Bearer {}", token)))
+                .set_payload(r#"{"jsonrpc": "2.0", "id": 1, "method": "ping"}"#)
+                .to_request()
+        };
+        assert_eq!(test::call_service(&app, post(admin)).await.status(), 404);
+        assert!(frames.try_recv().is_err());
+
+        assert_eq!(test::call_service(&app, post(alice)).await.status(), 202);
+        let frame = frames.recv().await.unwrap();
+        assert!(frame.starts_with("event: message\n") && frame.contains(r#""id":1"#));
+    }
+}
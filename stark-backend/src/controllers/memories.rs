@@ -2,14 +2,19 @@ use actix_web::{web, HttpRequest, HttpResponse, Responder};
 use chrono::Utc;
 use serde::{Deserialize, Serialize};
 
-use crate::models::{CreateMemoryRequest, MemoryResponse, MemoryType, SearchMemoriesRequest, UpdateMemoryRequest, MergeMemoriesRequest};
+use crate::memory::HybridSearcher;
+use crate::models::{
+    Caller, CreateMemoryRequest, MemoryResponse, MemoryType, MergeMemoriesRequest, Scope,
+    SearchMemoriesRequest, UpdateMemoryRequest,
+};
 use crate::AppState;
 
 /// Validate session token from request
-fn validate_session_from_request(
+async fn validate_session_from_request(
     state: &web::Data<AppState>,
     req: &HttpRequest,
-) -> Result<(), HttpResponse> {
+    scope: Scope,
+) -> Result<Caller, HttpResponse> {
     let token = req
         .headers()
         .get("Authorization")
@@ -25,8 +30,11 @@ fn validate_session_from_request(
         }
     };
 
-    match state.db.validate_session(&token) {
-        Ok(Some(_)) => Ok(()),
+    match state.db.authenticate(&token).await {
+        Ok(Some(caller)) if caller.scopes.allows(scope) => Ok(caller),
+        Ok(Some(_)) => Err(HttpResponse::Forbidden().json(serde_json::json!({
+            "error": format!("Token lacks the {} scope", scope)
+        }))),
         Ok(None) => Err(HttpResponse::Unauthorized().json(serde_json::json!({
             "error": "Invalid or expired session"
         }))),
@@ -39,16 +47,17 @@ fn validate_session_from_request(
     }
 }
 
-/// List all memories
+/// List all memories; other users' are hidden from members
 async fn list_memories(
     data: web::Data<AppState>,
     req: HttpRequest,
 ) -> impl Responder {
-    if let Err(resp) = validate_session_from_request(&data, &req) {
-        return resp;
-    }
+    let caller = match validate_session_from_request(&data, &req, Scope::Read).await {
+        Ok(caller) => caller,
+        Err(resp) => return resp,
+    };
 
-    match data.db.list_memories() {
+    match data.db.list_memories(caller.owner_filter()).await {
         Ok(memories) => {
             let responses: Vec<MemoryResponse> = memories.into_iter().map(|m| m.into()).collect();
             HttpResponse::Ok().json(responses)
@@ -68,7 +77,7 @@ async fn create_memory(
     req: HttpRequest,
     body: web::Json<CreateMemoryRequest>,
 ) -> impl Responder {
-    if let Err(resp) = validate_session_from_request(&data, &req) {
+    if let Err(resp) = validate_session_from_request(&data, &req, Scope::Admin).await {
         return resp;
     }
     // For daily logs, set log_date to today if not provided
@@ -90,7 +99,7 @@ async fn create_memory(
         body.source_message_id.as_deref(),
         log_date,
         body.expires_at,
-    ) {
+    ).await {
         Ok(memory) => {
             let response: MemoryResponse = memory.into();
             HttpResponse::Created().json(response)
@@ -110,9 +119,10 @@ async fn search_memories(
     req: HttpRequest,
     body: web::Json<SearchMemoriesRequest>,
 ) -> impl Responder {
-    if let Err(resp) = validate_session_from_request(&data, &req) {
-        return resp;
-    }
+    let caller = match validate_session_from_request(&data, &req, Scope::Read).await {
+        Ok(caller) => caller,
+        Err(resp) => return resp,
+    };
     match data.db.search_memories(
         &body.query,
         body.memory_type,
@@ -120,7 +130,8 @@ async fn search_memories(
         body.category.as_deref(),
         body.min_importance,
         body.limit,
-    ) {
+        caller.owner_filter(),
+    ).await {
         Ok(results) => HttpResponse::Ok().json(results),
         Err(e) => {
             log::error!("Failed to search memories: {}", e);
@@ -131,6 +142,72 @@ async fn search_memories(
     }
 }
 
+#[derive(Deserialize)]
+struct SemanticSearchRequest {
+    query: String,
+    memory_type: Option<MemoryType>,
+    identity_id: Option<String>,
+    #[serde(default = "default_semantic_limit")]
+    limit: i32,
+}
+
+fn default_semantic_limit() -> i32 {
+    10
+}
+
+#[derive(Serialize)]
+struct SemanticSearchResult {
+    memory: MemoryResponse,
+    /// Reciprocal rank fusion score (higher is better)
+    score: f64,
+    bm25_rank: Option<i32>,
+    vector_rank: Option<i32>,
+}
+
+/// Search memories by meaning: full-text results merged with embedding similarity.
+/// Falls back to full-text only when vector search is disabled.
+async fn semantic_search(
+    data: web::Data<AppState>,
+    req: HttpRequest,
+    body: web::Json<SemanticSearchRequest>,
+) -> impl Responder {
+    let caller = match validate_session_from_request(&data, &req, Scope::Read).await {
+        Ok(caller) => caller,
+        Err(resp) => return resp,
+    };
+
+    let searcher = HybridSearcher::configured(data.db.clone(), &crate::config::memory_config()).await;
+    match searcher.search(
+        &body.query,
+        body.memory_type,
+        body.identity_id.as_deref(),
+        body.limit.clamp(1, 50),
+    ).await {
+        Ok(results) => {
+            let results: Vec<SemanticSearchResult> = results
+                .into_iter()
+                .filter(|r| caller.can_access(r.memory.user_id))
+                .map(|r| SemanticSearchResult {
+                    memory: r.memory.into(),
+                    score: r.score,
+                    bm25_rank: r.bm25_rank,
+                    vector_rank: r.vector_rank,
+                })
+                .collect();
+            HttpResponse::Ok().json(serde_json::json!({
+                "vector_search": searcher.vector_search_enabled(),
+                "results": results
+            }))
+        }
+        Err(e) => {
+            log::error!("Failed to search memories: {}", e);
+            HttpResponse::InternalServerError().json(serde_json::json!({
+                "error": format!("Search failed: {}", e)
+            }))
+        }
+    }
+}
+
 /// Get today's daily logs
 #[derive(Deserialize)]
 struct DailyLogsQuery {
@@ -142,12 +219,17 @@ async fn get_daily_logs(
     req: HttpRequest,
     query: web::Query<DailyLogsQuery>,
 ) -> impl Responder {
-    if let Err(resp) = validate_session_from_request(&data, &req) {
-        return resp;
-    }
-    match data.db.get_todays_daily_logs(query.identity_id.as_deref()) {
+    let caller = match validate_session_from_request(&data, &req, Scope::Read).await {
+        Ok(caller) => caller,
+        Err(resp) => return resp,
+    };
+    match data.db.get_todays_daily_logs(query.identity_id.as_deref()).await {
         Ok(memories) => {
-            let responses: Vec<MemoryResponse> = memories.into_iter().map(|m| m.into()).collect();
+            let responses: Vec<MemoryResponse> = memories
+                .into_iter()
+                .filter(|m| caller.can_access(m.user_id))
+                .map(|m| m.into())
+                .collect();
             HttpResponse::Ok().json(responses)
         }
         Err(e) => {
@@ -177,16 +259,21 @@ async fn get_long_term_memories(
     req: HttpRequest,
     query: web::Query<LongTermQuery>,
 ) -> impl Responder {
-    if let Err(resp) = validate_session_from_request(&data, &req) {
-        return resp;
-    }
+    let caller = match validate_session_from_request(&data, &req, Scope::Read).await {
+        Ok(caller) => caller,
+        Err(resp) => return resp,
+    };
     match data.db.get_long_term_memories(
         query.identity_id.as_deref(),
         query.min_importance,
         query.limit,
-    ) {
+    ).await {
         Ok(memories) => {
-            let responses: Vec<MemoryResponse> = memories.into_iter().map(|m| m.into()).collect();
+            let responses: Vec<MemoryResponse> = memories
+                .into_iter()
+                .filter(|m| caller.can_access(m.user_id))
+                .map(|m| m.into())
+                .collect();
             HttpResponse::Ok().json(responses)
         }
         Err(e) => {
@@ -204,12 +291,12 @@ async fn delete_memory(
     req: HttpRequest,
     path: web::Path<i64>,
 ) -> impl Responder {
-    if let Err(resp) = validate_session_from_request(&data, &req) {
+    if let Err(resp) = validate_session_from_request(&data, &req, Scope::Admin).await {
         return resp;
     }
     let memory_id = path.into_inner();
 
-    match data.db.delete_memory(memory_id) {
+    match data.db.delete_memory(memory_id).await {
         Ok(true) => HttpResponse::Ok().json(serde_json::json!({
             "success": true,
             "message": "Memory deleted"
@@ -228,10 +315,10 @@ async fn delete_memory(
 
 /// Cleanup expired memories
 async fn cleanup_expired(data: web::Data<AppState>, req: HttpRequest) -> impl Responder {
-    if let Err(resp) = validate_session_from_request(&data, &req) {
+    if let Err(resp) = validate_session_from_request(&data, &req, Scope::Admin).await {
         return resp;
     }
-    match data.db.cleanup_expired_memories() {
+    match data.db.cleanup_expired_memories().await {
         Ok(count) => HttpResponse::Ok().json(serde_json::json!({
             "success": true,
             "deleted_count": count
@@ -255,17 +342,18 @@ async fn get_memory(
     req: HttpRequest,
     path: web::Path<i64>,
 ) -> impl Responder {
-    if let Err(resp) = validate_session_from_request(&data, &req) {
-        return resp;
-    }
+    let caller = match validate_session_from_request(&data, &req, Scope::Read).await {
+        Ok(caller) => caller,
+        Err(resp) => return resp,
+    };
     let memory_id = path.into_inner();
 
-    match data.db.get_memory(memory_id) {
-        Ok(Some(memory)) => {
+    match data.db.get_memory(memory_id).await {
+        Ok(Some(memory)) if caller.can_access(memory.user_id) => {
             let response: MemoryResponse = memory.into();
             HttpResponse::Ok().json(response)
         }
-        Ok(None) => HttpResponse::NotFound().json(serde_json::json!({
+        Ok(_) => HttpResponse::NotFound().json(serde_json::json!({
             "error": "Memory not found"
         })),
         Err(e) => {
@@ -284,12 +372,12 @@ async fn update_memory(
     path: web::Path<i64>,
     body: web::Json<UpdateMemoryRequest>,
 ) -> impl Responder {
-    if let Err(resp) = validate_session_from_request(&data, &req) {
+    if let Err(resp) = validate_session_from_request(&data, &req, Scope::Admin).await {
         return resp;
     }
     let memory_id = path.into_inner();
 
-    match data.db.update_memory(memory_id, &body.into_inner()) {
+    match data.db.update_memory(memory_id, &body.into_inner()).await {
         Ok(Some(memory)) => {
             let response: MemoryResponse = memory.into();
             HttpResponse::Ok().json(response)
@@ -312,7 +400,7 @@ async fn merge_memories(
     req: HttpRequest,
     body: web::Json<MergeMemoriesRequest>,
 ) -> impl Responder {
-    if let Err(resp) = validate_session_from_request(&data, &req) {
+    if let Err(resp) = validate_session_from_request(&data, &req, Scope::Admin).await {
         return resp;
     }
 
@@ -329,7 +417,7 @@ async fn merge_memories(
     let mut memory_type = MemoryType::LongTerm;
 
     for id in &body.memory_ids {
-        match data.db.get_memory(*id) {
+        match data.db.get_memory(*id).await {
             Ok(Some(mem)) => {
                 if body.use_max_importance.unwrap_or(true) && mem.importance > max_importance {
                     max_importance = mem.importance;
@@ -367,11 +455,11 @@ async fn merge_memories(
         Some(1.0),
         Some("merged"),
         None, None, None,
-    ) {
+    ).await {
         Ok(merged) => {
             // Mark original memories as superseded
             for mem in &memories {
-                let _ = data.db.supersede_memory(mem.id, merged.id);
+                let _ = data.db.supersede_memory(mem.id, merged.id).await;
             }
 
             let response: MemoryResponse = merged.into();
@@ -395,11 +483,12 @@ async fn get_stats(
     data: web::Data<AppState>,
     req: HttpRequest,
 ) -> impl Responder {
-    if let Err(resp) = validate_session_from_request(&data, &req) {
-        return resp;
-    }
+    let caller = match validate_session_from_request(&data, &req, Scope::Read).await {
+        Ok(caller) => caller,
+        Err(resp) => return resp,
+    };
 
-    match data.db.get_memory_stats() {
+    match data.db.get_memory_stats(caller.owner_filter()).await {
         Ok(stats) => HttpResponse::Ok().json(stats),
         Err(e) => {
             log::error!("Failed to get memory stats: {}", e);
@@ -421,11 +510,12 @@ async fn export_memories(
     req: HttpRequest,
     query: web::Query<ExportQuery>,
 ) -> impl Responder {
-    if let Err(resp) = validate_session_from_request(&data, &req) {
-        return resp;
-    }
+    let caller = match validate_session_from_request(&data, &req, Scope::Read).await {
+        Ok(caller) => caller,
+        Err(resp) => return resp,
+    };
 
-    match data.db.export_memories_markdown(query.identity_id.as_deref()) {
+    match data.db.export_memories_markdown(query.identity_id.as_deref(), caller.owner_filter()).await {
         Ok(markdown) => HttpResponse::Ok()
             .content_type("text/markdown")
             .insert_header(("Content-Disposition", "attachment; filename=\"memories.md\""))
@@ -457,9 +547,10 @@ async fn list_memories_filtered(
     req: HttpRequest,
     query: web::Query<ListMemoriesQuery>,
 ) -> impl Responder {
-    if let Err(resp) = validate_session_from_request(&data, &req) {
-        return resp;
-    }
+    let caller = match validate_session_from_request(&data, &req, Scope::Read).await {
+        Ok(caller) => caller,
+        Err(resp) => return resp,
+    };
 
     let memory_type = query.memory_type.as_ref().and_then(|t| MemoryType::from_str(t));
 
@@ -470,7 +561,8 @@ async fn list_memories_filtered(
         query.include_superseded.unwrap_or(false),
         query.limit,
         query.offset,
-    ) {
+        caller.owner_filter(),
+    ).await {
         Ok(memories) => {
             let responses: Vec<MemoryResponse> = memories.into_iter().map(|m| m.into()).collect();
             HttpResponse::Ok().json(responses)
@@ -490,6 +582,7 @@ pub fn config(cfg: &mut web::ServiceConfig) {
             .route("", web::get().to(list_memories))
             .route("", web::post().to(create_memory))
             .route("/search", web::post().to(search_memories))
+            .route("/semantic-search", web::post().to(semantic_search))
             .route("/daily", web::get().to(get_daily_logs))
             .route("/long-term", web::get().to(get_long_term_memories))
             .route("/cleanup", web::post().to(cleanup_expired))
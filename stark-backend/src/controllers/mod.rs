@@ -1,19 +1,37 @@
+pub mod admin;
+pub mod agent;
 pub mod agent_settings;
 pub mod api_keys;
+pub mod api_tokens;
+pub mod approvals;
+pub mod audit;
 pub mod auth;
 pub mod channels;
 pub mod chat;
 pub mod cron;
 pub mod dashboard;
 pub mod eip8004;
+pub mod embeddings;
 pub mod files;
 pub mod gmail;
 pub mod health;
 pub mod identity;
 pub mod intrinsic;
 pub mod journal;
+pub mod mcp;
 pub mod memories;
 pub mod payments;
+pub mod personas;
+pub mod preview;
+pub mod schedules;
 pub mod sessions;
 pub mod skills;
+pub mod templates;
 pub mod tools;
+pub mod transactions;
+pub mod usage;
+pub mod users;
+pub mod voice;
+pub mod wallets;
+pub mod webhooks;
+pub mod workspaces;
@@ -3,6 +3,7 @@
 use actix_web::{web, HttpRequest, HttpResponse, Responder};
 use serde::{Deserialize, Serialize};
 
+use crate::models::Scope;
 use crate::AppState;
 
 #[derive(Debug, Serialize)]
@@ -63,11 +64,21 @@ async fn list_payments(
     query: web::Query<PaymentListQuery>,
 ) -> impl Responder {
     // Validate auth
-    if let Err(resp) = validate_auth(&state, &req) {
+    if let Err(resp) = validate_auth(&state, &req, Scope::Read).await {
         return resp;
     }
 
-    let conn = state.db.conn.lock().unwrap();
+    let conn = match state.db.conn().await {
+        Ok(c) => c,
+        Err(e) => {
+            return HttpResponse::InternalServerError().json(PaymentResponse {
+                success: false,
+                payments: None,
+                total: None,
+                error: Some(format!("Database error: {}", e)),
+            });
+        }
+    };
     let limit = query.limit.unwrap_or(50).min(100);
     let offset = query.offset.unwrap_or(0);
 
@@ -156,11 +167,19 @@ async fn get_summary(
     req: HttpRequest,
 ) -> impl Responder {
     // Validate auth
-    if let Err(resp) = validate_auth(&state, &req) {
+    if let Err(resp) = validate_auth(&state, &req, Scope::Read).await {
         return resp;
     }
 
-    let conn = state.db.conn.lock().unwrap();
+    let conn = match state.db.conn().await {
+        Ok(c) => c,
+        Err(e) => {
+            return HttpResponse::InternalServerError().json(serde_json::json!({
+                "success": false,
+                "error": format!("Database error: {}", e)
+            }));
+        }
+    };
 
     let total_payments: i64 = conn
         .query_row("SELECT COUNT(*) FROM x402_payments", [], |row| row.get(0))
@@ -201,12 +220,20 @@ async fn get_payment(
     path: web::Path<i64>,
 ) -> impl Responder {
     // Validate auth
-    if let Err(resp) = validate_auth(&state, &req) {
+    if let Err(resp) = validate_auth(&state, &req, Scope::Read).await {
         return resp;
     }
 
     let payment_id = path.into_inner();
-    let conn = state.db.conn.lock().unwrap();
+    let conn = match state.db.conn().await {
+        Ok(c) => c,
+        Err(e) => {
+            return HttpResponse::InternalServerError().json(serde_json::json!({
+                "success": false,
+                "error": format!("Database error: {}", e)
+            }));
+        }
+    };
 
     let payment = conn.query_row(
         "SELECT id, channel_id, tool_name, resource, amount, amount_formatted, asset, pay_to, tx_hash, status, feedback_submitted, created_at
@@ -243,7 +270,7 @@ async fn get_payment(
 }
 
 /// Validate authorization header
-fn validate_auth(state: &web::Data<AppState>, req: &HttpRequest) -> Result<(), HttpResponse> {
+async fn validate_auth(state: &web::Data<AppState>, req: &HttpRequest, scope: Scope) -> Result<(), HttpResponse> {
     let token = req
         .headers()
         .get("Authorization")
@@ -260,8 +287,12 @@ fn validate_auth(state: &web::Data<AppState>, req: &HttpRequest) -> Result<(), H
         }
     };
 
-    match state.db.validate_session(&token) {
-        Ok(Some(_)) => Ok(()),
+    match state.db.authorize(&token).await {
+        Ok(Some(scopes)) if scopes.allows(scope) => Ok(()),
+        Ok(Some(_)) => Err(HttpResponse::Forbidden().json(serde_json::json!({
+            "success": false,
+            "error": format!("Token lacks the {} scope", scope)
+        }))),
         Ok(None) => Err(HttpResponse::Unauthorized().json(serde_json::json!({
             "success": false,
             "error": "Invalid or expired session"
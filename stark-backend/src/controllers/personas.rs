@@ -0,0 +1,296 @@
+use actix_web::{web, HttpRequest, HttpResponse, Responder};
+use serde::Serialize;
+
+use crate::agent::templates::is_valid_name;
+use crate::models::{CreatePersonaRequest, Persona, Scope, UpdatePersonaRequest};
+use crate::tools::ToolGroup;
+use crate::AppState;
+
+/// Longest system prompt accepted, in bytes
+const MAX_SYSTEM_PROMPT_BYTES: usize = 64 * 1024;
+
+/// Validate session token from request
+async fn validate_session_from_request(
+    state: &web::Data<AppState>,
+    req: &HttpRequest,
+    scope: Scope,
+) -> Result<(), HttpResponse> {
+    let token = req
+        .headers()
+        .get("Authorization")
+        .and_then(|h| h.to_str().ok())
+        .map(|s| s.trim_start_matches("Bearer ").to_string());
+
+    let token = match token {
+        Some(t) => t,
+        None => {
+            return Err(HttpResponse::Unauthorized().json(PersonaResponse::error("No authorization token provided")));
+        }
+    };
+
+    match state.db.authorize(&token).await {
+        Ok(Some(scopes)) if scopes.allows(scope) => Ok(()),
+        Ok(Some(_)) => Err(HttpResponse::Forbidden()
+            .json(PersonaResponse::error(format!("Token lacks the {} scope", scope)))),
+        Ok(None) => Err(HttpResponse::Unauthorized().json(PersonaResponse::error("Invalid or expired session"))),
+        Err(e) => {
+            log::error!("Session validation error: {}", e);
+            Err(HttpResponse::InternalServerError().json(PersonaResponse::error("Internal server error")))
+        }
+    }
+}
+
+#[derive(Serialize, Default)]
+pub struct PersonaResponse {
+    pub success: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub persona: Option<Persona>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub personas: Option<Vec<Persona>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+impl PersonaResponse {
+    fn ok() -> Self {
+        PersonaResponse {
+            success: true,
+            ..Default::default()
+        }
+    }
+
+    fn error(error: impl Into<String>) -> Self {
+        PersonaResponse {
+            success: false,
+            error: Some(error.into()),
+            ..Default::default()
+        }
+    }
+}
+
+pub fn config(cfg: &mut web::ServiceConfig) {
+    cfg.service(
+        web::scope("/api/personas")
+            .route("", web::get().to(list_personas))
+            .route("", web::post().to(create_persona))
+            .route("/{name}", web::get().to(get_persona))
+            .route("/{name}", web::put().to(update_persona))
+            .route("/{name}", web::delete().to(delete_persona)),
+    );
+}
+
+/// Check a persona as it would be stored, normalizing its tool group names
+/// and treating a blank model as none
+fn validate_persona(persona: &mut Persona) -> Result<(), String> {
+    if !is_valid_name(&persona.name) {
+        return Err("Name may only contain letters, digits, '-', '_' and '.' (max 64 characters)".to_string());
+    }
+    if persona.system_prompt.trim().is_empty() {
+        return Err("System prompt cannot be empty".to_string());
+    }
+    if persona.system_prompt.len() > MAX_SYSTEM_PROMPT_BYTES {
+        return Err(format!("System prompt is too long (max {} bytes)", MAX_SYSTEM_PROMPT_BYTES));
+    }
+    let mut groups: Vec<String> = Vec::new();
+    for name in &persona.tool_groups {
+        let group = ToolGroup::from_str(name).ok_or_else(|| format!("Unknown tool group '{}'", name))?;
+        if !groups.iter().any(|g| g == group.as_str()) {
+            groups.push(group.as_str().to_string());
+        }
+    }
+    persona.tool_groups = groups;
+    persona.model = persona
+        .model
+        .as_deref()
+        .map(str::trim)
+        .filter(|m| !m.is_empty())
+        .map(str::to_string);
+    Ok(())
+}
+
+/// Look up a persona, or the error response to send
+async fn find_persona(state: &web::Data<AppState>, name: &str) -> Result<Persona, HttpResponse> {
+    match state.db.get_persona(name).await {
+        Ok(Some(persona)) => Ok(persona),
+        Ok(None) => Err(HttpResponse::NotFound().json(PersonaResponse::error("Persona not found"))),
+        Err(e) => {
+            log::error!("Failed to load persona {}: {}", name, e);
+            Err(HttpResponse::InternalServerError().json(PersonaResponse::error("Internal server error")))
+        }
+    }
+}
+
+/// List every persona
+async fn list_personas(state: web::Data<AppState>, req: HttpRequest) -> impl Responder {
+    if let Err(resp) = validate_session_from_request(&state, &req, Scope::Read).await {
+        return resp;
+    }
+
+    match state.db.list_personas().await {
+        Ok(personas) => HttpResponse::Ok().json(PersonaResponse {
+            personas: Some(personas),
+            ..PersonaResponse::ok()
+        }),
+        Err(e) => {
+            log::error!("Failed to list personas: {}", e);
+            HttpResponse::InternalServerError().json(PersonaResponse::error("Internal server error"))
+        }
+    }
+}
+
+/// Create a persona
+async fn create_persona(
+    state: web::Data<AppState>,
+    req: HttpRequest,
+    body: web::Json<CreatePersonaRequest>,
+) -> impl Responder {
+    // A persona's model isn't held to the agent's allowed_models, so only
+    // admins define them
+    if let Err(resp) = validate_session_from_request(&state, &req, Scope::Admin).await {
+        return resp;
+    }
+
+    let body = body.into_inner();
+    let mut candidate = Persona {
+        id: 0,
+        name: body.name,
+        description: body.description,
+        system_prompt: body.system_prompt,
+        tool_groups: body.tool_groups,
+        model: body.model,
+        created_at: String::new(),
+        updated_at: String::new(),
+    };
+    if let Err(e) = validate_persona(&mut candidate) {
+        return HttpResponse::BadRequest().json(PersonaResponse::error(e));
+    }
+    match state.db.get_persona(&candidate.name).await {
+        Ok(None) => {}
+        Ok(Some(_)) => {
+            return HttpResponse::Conflict()
+                .json(PersonaResponse::error(format!("A persona named '{}' already exists", candidate.name)));
+        }
+        Err(e) => {
+            log::error!("Failed to load persona {}: {}", candidate.name, e);
+            return HttpResponse::InternalServerError().json(PersonaResponse::error("Internal server error"));
+        }
+    }
+
+    let request = CreatePersonaRequest {
+        name: candidate.name,
+        description: candidate.description,
+        system_prompt: candidate.system_prompt,
+        tool_groups: candidate.tool_groups,
+        model: candidate.model,
+    };
+    match state.db.create_persona(&request).await {
+        Ok(persona) => {
+            log::info!("[PERSONAS] Created '{}'", persona.name);
+            HttpResponse::Created().json(PersonaResponse {
+                persona: Some(persona),
+                ..PersonaResponse::ok()
+            })
+        }
+        Err(e) => {
+            log::error!("Failed to create persona: {}", e);
+            HttpResponse::InternalServerError().json(PersonaResponse::error("Failed to create persona"))
+        }
+    }
+}
+
+/// Get a persona by name
+async fn get_persona(state: web::Data<AppState>, req: HttpRequest, path: web::Path<String>) -> impl Responder {
+    if let Err(resp) = validate_session_from_request(&state, &req, Scope::Read).await {
+        return resp;
+    }
+
+    match find_persona(&state, &path.into_inner()).await {
+        Ok(persona) => HttpResponse::Ok().json(PersonaResponse {
+            persona: Some(persona),
+            ..PersonaResponse::ok()
+        }),
+        Err(resp) => resp,
+    }
+}
+
+/// Update a persona's description, system prompt, tool groups or model
+async fn update_persona(
+    state: web::Data<AppState>,
+    req: HttpRequest,
+    path: web::Path<String>,
+    body: web::Json<UpdatePersonaRequest>,
+) -> impl Responder {
+    if let Err(resp) = validate_session_from_request(&state, &req, Scope::Admin).await {
+        return resp;
+    }
+
+    let mut persona = match find_persona(&state, &path.into_inner()).await {
+        Ok(persona) => persona,
+        Err(resp) => return resp,
+    };
+
+    let body = body.into_inner();
+    if let Some(description) = body.description {
+        persona.description = description;
+    }
+    if let Some(system_prompt) = body.system_prompt {
+        persona.system_prompt = system_prompt;
+    }
+    if let Some(tool_groups) = body.tool_groups {
+        persona.tool_groups = tool_groups;
+    }
+    if let Some(model) = body.model {
+        persona.model = Some(model);
+    }
+    if let Err(e) = validate_persona(&mut persona) {
+        return HttpResponse::BadRequest().json(PersonaResponse::error(e));
+    }
+
+    match state.db.save_persona(&persona).await {
+        Ok(true) => match find_persona(&state, &persona.name).await {
+            Ok(persona) => HttpResponse::Ok().json(PersonaResponse {
+                persona: Some(persona),
+                ..PersonaResponse::ok()
+            }),
+            Err(resp) => resp,
+        },
+        Ok(false) => HttpResponse::NotFound().json(PersonaResponse::error("Persona not found")),
+        Err(e) => {
+            log::error!("Failed to update persona {}: {}", persona.name, e);
+            HttpResponse::InternalServerError().json(PersonaResponse::error("Failed to update persona"))
+        }
+    }
+}
+
+/// Delete a persona. Sessions that picked it go back to the default; the
+/// default persona itself can't be deleted until the agent settings drop it.
+async fn delete_persona(state: web::Data<AppState>, req: HttpRequest, path: web::Path<String>) -> impl Responder {
+    if let Err(resp) = validate_session_from_request(&state, &req, Scope::Admin).await {
+        return resp;
+    }
+
+    let name = path.into_inner();
+    match state.db.get_active_agent_settings().await {
+        Ok(Some(settings)) if settings.default_persona.as_deref() == Some(name.as_str()) => {
+            return HttpResponse::Conflict()
+                .json(PersonaResponse::error(format!("'{}' is the agent's default persona", name)));
+        }
+        Ok(_) => {}
+        Err(e) => {
+            log::error!("Failed to load agent settings: {}", e);
+            return HttpResponse::InternalServerError().json(PersonaResponse::error("Internal server error"));
+        }
+    }
+
+    match state.db.delete_persona(&name).await {
+        Ok(true) => {
+            log::info!("[PERSONAS] Deleted '{}'", name);
+            HttpResponse::Ok().json(PersonaResponse::ok())
+        }
+        Ok(false) => HttpResponse::NotFound().json(PersonaResponse::error("Persona not found")),
+        Err(e) => {
+            log::error!("Failed to delete persona {}: {}", name, e);
+            HttpResponse::InternalServerError().json(PersonaResponse::error("Failed to delete persona"))
+        }
+    }
+}
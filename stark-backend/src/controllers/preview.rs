@@ -0,0 +1,244 @@
+//! Reverse proxy to servers the agent started
+//!
+//! `/preview/{owner}/{key}/...` forwards to the newest server a job (or chat
+//! session, as `session-{id}`) started with a leased port; see
+//! `execution::ports`. Only answers when `STARK_PREVIEW_PROXY` is set.
+//!
+//! The lease's key in the path is the only credential, so every request the
+//! page makes with a relative URL carries it. The app is code the agent wrote
+//! and must not run with the dashboard's origin, where the dashboard keeps its
+//! token: when `STARK_PREVIEW_URL` gives previews a host of their own they are
+//! only served there, and otherwise every response is sandboxed by its
+//! Content-Security-Policy into an opaque origin. Cookies the app sets are
+//! limited to its preview's path.
+
+use actix_web::http::header::{self, HeaderName};
+use actix_web::http::StatusCode;
+use actix_web::{web, HttpRequest, HttpResponse};
+use once_cell::sync::Lazy;
+use std::time::Duration;
+
+use crate::AppState;
+
+/// Policy for previews served on the dashboard's origin: scripts run, but in an
+/// opaque origin without access to the dashboard's storage
+const SANDBOX_POLICY: &str = "sandbox allow-scripts allow-forms";
+
+/// Headers that only apply to one connection, or that are ours rather than the app's
+const SKIPPED_REQUEST_HEADERS: [HeaderName; 7] = [
+    header::HOST,
+    header::CONNECTION,
+    header::TE,
+    header::TRAILER,
+    header::TRANSFER_ENCODING,
+    header::UPGRADE,
+    header::AUTHORIZATION,
+];
+
+const SKIPPED_RESPONSE_HEADERS: [HeaderName; 5] = [
+    header::CONNECTION,
+    header::TRAILER,
+    header::TRANSFER_ENCODING,
+    header::UPGRADE,
+    header::CONTENT_LENGTH,
+];
+
+static CLIENT: Lazy<reqwest::Client> = Lazy::new(|| {
+    reqwest::Client::builder()
+        // Redirects go back to the browser, rewritten to stay under the preview
+        .redirect(reqwest::redirect::Policy::none())
+        .connect_timeout(Duration::from_secs(5))
+        .build()
+        .expect("Failed to create preview HTTP client")
+});
+
+pub fn config(cfg: &mut web::ServiceConfig) {
+    cfg.route("/preview/{owner}/{key}", web::to(add_trailing_slash))
+        .route("/preview/{owner}/{key}/{tail:.*}", web::to(proxy));
+}
+
+/// Relative links in the app only resolve under `/preview/{owner}/{key}/`
+async fn add_trailing_slash(req: HttpRequest, path: web::Path<(String, String)>) -> HttpResponse {
+    let (owner, key) = path.into_inner();
+    let query = req.query_string();
+    let location = format!("/preview/{}/{}/{}{}", owner, key, if query.is_empty() { "" } else { "?" }, query);
+    HttpResponse::PermanentRedirect()
+        .insert_header((header::LOCATION, location))
+        .finish()
+}
+
+async fn proxy(
+    state: web::Data<AppState>,
+    req: HttpRequest,
+    path: web::Path<(String, String, String)>,
+    body: web::Bytes,
+) -> HttpResponse {
+    let ports = state.process_manager.ports();
+    if !ports.proxy_enabled() {
+        return HttpResponse::NotFound().json(serde_json::json!({ "error": "Previews are disabled" }));
+    }
+
+    let (host, scheme) = {
+        let info = req.connection_info();
+        (info.host().to_lowercase(), info.scheme().to_string())
+    };
+    // With a preview host configured, the dashboard's own host never serves agent code
+    let preview_host = ports.preview_host();
+    if preview_host.as_ref().is_some_and(|preview_host| *preview_host != host) {
+        return HttpResponse::NotFound().json(serde_json::json!({
+            "error": "Previews are served on the host in STARK_PREVIEW_URL"
+        }));
+    }
+
+    let (owner, key, tail) = path.into_inner();
+    let lease = match ports.preview(&owner) {
+        Some(lease) if keys_match(&key, &lease.key) => lease,
+        _ => {
+            return HttpResponse::NotFound().json(serde_json::json!({
+                "error": format!("No running server for '{}' with this key", owner)
+            }));
+        }
+    };
+
+    let method = match reqwest::Method::from_bytes(req.method().as_str().as_bytes()) {
+        Ok(method) => method,
+        Err(_) => return HttpResponse::MethodNotAllowed().finish(),
+    };
+    let query = req.query_string();
+    let target = format!(
+        "http://127.0.0.1:{}/{}{}",
+        lease.port,
+        tail,
+        if query.is_empty() { String::new() } else { format!("?{}", query) }
+    );
+    let prefix = format!("/preview/{}/{}", owner, key);
+
+    let mut upstream = CLIENT.request(method, &target).body(body.to_vec());
+    for (name, value) in req.headers() {
+        if !SKIPPED_REQUEST_HEADERS.contains(name) {
+            upstream = upstream.header(name.as_str(), value.as_bytes());
+        }
+    }
+    upstream = upstream
+        .header("x-forwarded-prefix", prefix.as_str())
+        .header("x-forwarded-host", host)
+        .header("x-forwarded-proto", scheme);
+
+    let response = match upstream.send().await {
+        Ok(response) => response,
+        Err(e) => {
+            log::warn!("[PREVIEW] {} on port {} unreachable: {}", owner, lease.port, e);
+            return HttpResponse::BadGateway().json(serde_json::json!({
+                "error": format!("The server on port {} is not answering (yet)", lease.port)
+            }));
+        }
+    };
+
+    let status = StatusCode::from_u16(response.status().as_u16()).unwrap_or(StatusCode::BAD_GATEWAY);
+    let mut builder = HttpResponse::build(status);
+    for (name, value) in response.headers() {
+        let name = name.as_str();
+        if SKIPPED_RESPONSE_HEADERS.iter().any(|h| h.as_str() == name) {
+            continue;
+        }
+        if name == "location" || name == "set-cookie" {
+            if let Ok(value) = value.to_str() {
+                let value = if name == "location" {
+                    rewrite_location(value, lease.port, &prefix)
+                } else {
+                    scope_cookie(value, &prefix)
+                };
+                builder.append_header((name, value));
+            }
+            continue;
+        }
+        builder.append_header((name, value.as_bytes()));
+    }
+    if preview_host.is_none() {
+        // Enforced alongside any policy of the app's own
+        builder.append_header((header::CONTENT_SECURITY_POLICY, SANDBOX_POLICY));
+    }
+    builder.streaming(response.bytes_stream())
+}
+
+fn keys_match(given: &str, expected: &str) -> bool {
+    given.len() == expected.len()
+        && given.bytes().zip(expected.bytes()).fold(0u8, |diff, (a, b)| diff | (a ^ b)) == 0
+}
+
+/// A `Set-Cookie` value from the app, limited to its preview: the path is moved
+/// under the prefix and any `Domain` is dropped
+fn scope_cookie(cookie: &str, prefix: &str) -> String {
+    let mut parts = cookie.split(';').map(str::trim);
+    let mut scoped = vec![parts.next().unwrap_or_default().to_string()];
+    let mut path = "/".to_string();
+    for attribute in parts.filter(|a| !a.is_empty()) {
+        let name = attribute.split('=').next().unwrap_or_default().trim().to_lowercase();
+        match name.as_str() {
+            "domain" => {}
+            "path" => {
+                let value = attribute.split_once('=').map(|(_, v)| v.trim()).unwrap_or_default();
+                if value.starts_with('/') {
+                    path = value.to_string();
+                }
+            }
+            _ => scoped.push(attribute.to_string()),
+        }
+    }
+    scoped.push(format!("Path={}{}", prefix, path));
+    scoped.join("; ")
+}
+
+/// Keep redirects from the app under its preview path
+fn rewrite_location(location: &str, port: u16, prefix: &str) -> String {
+    for origin in [format!("http://127.0.0.1:{}", port), format!("http://localhost:{}", port)] {
+        if let Some(path) = location.strip_prefix(&origin) {
+            match path.chars().next() {
+                None => return format!("{}/", prefix),
+                Some('/') => return format!("{}{}", prefix, path),
+                Some('?') => return format!("{}/{}", prefix, path),
+                // Another port that starts with the same digits
+                _ => {}
+            }
+        }
+    }
+    if location.starts_with('/') && !location.starts_with("//") {
+        return format!("{}{}", prefix, location);
+    }
+    location.to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_keys_match() {
+        assert!(keys_match("abc", "abc"));
+        assert!(!keys_match("abd", "abc"));
+        assert!(!keys_match("ab", "abc"));
+    }
+
+    #[test]
+    fn test_app_cookies_stay_under_the_preview() {
+        let prefix = "/preview/job-1/k";
+        assert_eq!(scope_cookie("sid=1; Path=/; HttpOnly", prefix), "sid=1; HttpOnly; Path=/preview/job-1/k/");
+        assert_eq!(scope_cookie("sid=1", prefix), "sid=1; Path=/preview/job-1/k/");
+        assert_eq!(
+            scope_cookie("sid=1; Domain=example.com; path=/app; Max-Age=60", prefix),
+            "sid=1; Max-Age=60; Path=/preview/job-1/k/app"
+        );
+    }
+
+    #[test]
+    fn test_redirects_stay_under_the_preview() {
+        let prefix = "/preview/job-1";
+        assert_eq!(rewrite_location("/login", 4100, prefix), "/preview/job-1/login");
+        assert_eq!(rewrite_location("http://localhost:4100/a?b=1", 4100, prefix), "/preview/job-1/a?b=1");
+        assert_eq!(rewrite_location("http://127.0.0.1:4100", 4100, prefix), "/preview/job-1/");
+        assert_eq!(rewrite_location("http://localhost:41000/", 4100, prefix), "http://localhost:41000/");
+        assert_eq!(rewrite_location("next", 4100, prefix), "next");
+        assert_eq!(rewrite_location("https://example.com/", 4100, prefix), "https://example.com/");
+        assert_eq!(rewrite_location("//cdn.example.com/x", 4100, prefix), "//cdn.example.com/x");
+    }
+}
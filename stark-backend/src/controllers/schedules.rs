@@ -0,0 +1,455 @@
+use actix_web::{web, HttpRequest, HttpResponse, Responder};
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
+
+use crate::agent::templates;
+use crate::controllers::agent::{resolve_max_iterations, resolve_workspace_name, validate_tool_names};
+use crate::models::{
+    parse_cron_expression, AgentJob, Caller, CreateScheduledTaskRequest, Scope, ScheduledTask,
+    UpdateScheduledTaskRequest,
+};
+use crate::AppState;
+
+/// Validate session token from request
+async fn validate_session_from_request(
+    state: &web::Data<AppState>,
+    req: &HttpRequest,
+    scope: Scope,
+) -> Result<Caller, HttpResponse> {
+    let token = req
+        .headers()
+        .get("Authorization")
+        .and_then(|h| h.to_str().ok())
+        .map(|s| s.trim_start_matches("Bearer ").to_string());
+
+    let token = match token {
+        Some(t) => t,
+        None => {
+            return Err(HttpResponse::Unauthorized().json(ScheduleResponse::error(
+                "No authorization token provided",
+            )));
+        }
+    };
+
+    match state.db.authenticate(&token).await {
+        Ok(Some(caller)) if caller.scopes.allows(scope) => Ok(caller),
+        Ok(Some(_)) => Err(HttpResponse::Forbidden().json(ScheduleResponse::error(format!(
+            "Token lacks the {} scope",
+            scope
+        )))),
+        Ok(None) => Err(HttpResponse::Unauthorized().json(ScheduleResponse::error(
+            "Invalid or expired session",
+        ))),
+        Err(e) => {
+            log::error!("Session validation error: {}", e);
+            Err(HttpResponse::InternalServerError().json(ScheduleResponse::error("Internal server error")))
+        }
+    }
+}
+
+#[derive(Serialize, Default)]
+pub struct ScheduleResponse {
+    pub success: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub schedule: Option<ScheduledTask>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub schedules: Option<Vec<ScheduledTask>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub job: Option<AgentJob>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub jobs: Option<Vec<AgentJob>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+impl ScheduleResponse {
+    fn ok() -> Self {
+        ScheduleResponse {
+            success: true,
+            ..Default::default()
+        }
+    }
+
+    fn error(error: impl Into<String>) -> Self {
+        ScheduleResponse {
+            success: false,
+            error: Some(error.into()),
+            ..Default::default()
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct LimitQuery {
+    limit: Option<usize>,
+}
+
+pub fn config(cfg: &mut web::ServiceConfig) {
+    cfg.service(
+        web::scope("/api/schedules")
+            .route("", web::get().to(list_schedules))
+            .route("", web::post().to(create_schedule))
+            .route("/{id}", web::get().to(get_schedule))
+            .route("/{id}", web::put().to(update_schedule))
+            .route("/{id}", web::delete().to(delete_schedule))
+            .route("/{id}/run", web::post().to(run_schedule))
+            .route("/{id}/jobs", web::get().to(list_schedule_jobs)),
+    );
+}
+
+/// Check the fields a create or update can set, returning the first problem found
+fn validate_schedule(state: &AppState, task: &ScheduledTask) -> Result<(), String> {
+    if task.name.trim().is_empty() {
+        return Err("Name cannot be empty".to_string());
+    }
+    if task.prompt.trim().is_empty() {
+        return Err("Prompt cannot be empty".to_string());
+    }
+    parse_cron_expression(&task.cron_expression)?;
+    validate_tool_names(state, &task.tools)
+}
+
+/// Next run time for an enabled task, counted from now
+fn next_run_at(task: &ScheduledTask) -> Option<String> {
+    if !task.enabled {
+        return None;
+    }
+    task.next_run_after(Utc::now()).map(|dt| dt.to_rfc3339())
+}
+
+/// Look up a schedule the caller may see, or the error response to send
+async fn find_schedule(state: &web::Data<AppState>, caller: &Caller, id: i64) -> Result<ScheduledTask, HttpResponse> {
+    match state.db.get_scheduled_task(id).await {
+        // Other users' schedules look the same as missing ones
+        Ok(Some(task)) if caller.can_access(task.user_id) => Ok(task),
+        Ok(_) => Err(HttpResponse::NotFound().json(ScheduleResponse::error("Schedule not found"))),
+        Err(e) => {
+            log::error!("Failed to load scheduled task {}: {}", id, e);
+            Err(HttpResponse::InternalServerError().json(ScheduleResponse::error("Internal server error")))
+        }
+    }
+}
+
+/// List scheduled tasks: all of them for admins, otherwise the caller's own
+async fn list_schedules(state: web::Data<AppState>, req: HttpRequest) -> impl Responder {
+    let caller = match validate_session_from_request(&state, &req, Scope::Read).await {
+        Ok(caller) => caller,
+        Err(resp) => return resp,
+    };
+
+    match state.db.list_scheduled_tasks(caller.owner_filter()).await {
+        Ok(tasks) => HttpResponse::Ok().json(ScheduleResponse {
+            schedules: Some(tasks),
+            ..ScheduleResponse::ok()
+        }),
+        Err(e) => {
+            log::error!("Failed to list scheduled tasks: {}", e);
+            HttpResponse::InternalServerError().json(ScheduleResponse::error("Internal server error"))
+        }
+    }
+}
+
+/// Create a recurring agent task
+async fn create_schedule(
+    state: web::Data<AppState>,
+    req: HttpRequest,
+    body: web::Json<CreateScheduledTaskRequest>,
+) -> impl Responder {
+    // Scheduled jobs run the exec tools unattended, same as a queued agent job
+    let caller = match validate_session_from_request(&state, &req, Scope::ToolsExec).await {
+        Ok(caller) => caller,
+        Err(resp) => return resp,
+    };
+
+    // Admins' tasks default to the id-based workspace; everyone else's names go under their prefix
+    let workspace = match body.workspace.as_deref() {
+        None if caller.is_admin() => None,
+        requested => match resolve_workspace_name(&caller, requested) {
+            Ok(workspace) => Some(workspace),
+            Err(e) => return HttpResponse::BadRequest().json(ScheduleResponse::error(e)),
+        },
+    };
+    let max_iterations = resolve_max_iterations(body.max_iterations) as i64;
+    // Validate as the stored task would look
+    let candidate = ScheduledTask {
+        id: 0,
+        name: body.name.clone(),
+        cron_expression: body.cron_expression.clone(),
+        prompt: body.prompt.clone(),
+        tools: body.tools.clone(),
+        workspace: workspace.clone().unwrap_or_else(|| ScheduledTask::default_workspace(0)),
+        max_iterations,
+        enabled: body.enabled,
+        last_run_at: None,
+        next_run_at: None,
+        last_job_id: None,
+        created_at: String::new(),
+        updated_at: String::new(),
+        user_id: Some(caller.user.id),
+    };
+    if let Err(e) = validate_schedule(&state, &candidate) {
+        return HttpResponse::BadRequest().json(ScheduleResponse::error(e));
+    }
+    if let Err(e) = templates::check_includes(&state.db, &candidate.prompt).await {
+        return HttpResponse::BadRequest().json(ScheduleResponse::error(e));
+    }
+
+    let next_run = next_run_at(&candidate);
+    match state
+        .db
+        .create_scheduled_task(&body, workspace.as_deref(), max_iterations, next_run.as_deref(), candidate.user_id)
+        .await
+    {
+        Ok(task) => {
+            log::info!("[SCHEDULE] Created '{}' ({})", task.name, task.cron_expression);
+            HttpResponse::Created().json(ScheduleResponse {
+                schedule: Some(task),
+                ..ScheduleResponse::ok()
+            })
+        }
+        Err(e) => {
+            log::error!("Failed to create scheduled task: {}", e);
+            HttpResponse::InternalServerError().json(ScheduleResponse::error("Failed to create schedule"))
+        }
+    }
+}
+
+/// Get a scheduled task by ID
+async fn get_schedule(state: web::Data<AppState>, req: HttpRequest, path: web::Path<i64>) -> impl Responder {
+    let caller = match validate_session_from_request(&state, &req, Scope::Read).await {
+        Ok(caller) => caller,
+        Err(resp) => return resp,
+    };
+
+    match find_schedule(&state, &caller, path.into_inner()).await {
+        Ok(task) => HttpResponse::Ok().json(ScheduleResponse {
+            schedule: Some(task),
+            ..ScheduleResponse::ok()
+        }),
+        Err(resp) => resp,
+    }
+}
+
+/// Update a scheduled task; the next run is recomputed from the (possibly new) cron expression
+async fn update_schedule(
+    state: web::Data<AppState>,
+    req: HttpRequest,
+    path: web::Path<i64>,
+    body: web::Json<UpdateScheduledTaskRequest>,
+) -> impl Responder {
+    let caller = match validate_session_from_request(&state, &req, Scope::ToolsExec).await {
+        Ok(caller) => caller,
+        Err(resp) => return resp,
+    };
+
+    let mut task = match find_schedule(&state, &caller, path.into_inner()).await {
+        Ok(task) => task,
+        Err(resp) => return resp,
+    };
+
+    let body = body.into_inner();
+    if let Some(name) = body.name {
+        task.name = name;
+    }
+    if let Some(cron_expression) = body.cron_expression {
+        task.cron_expression = cron_expression;
+    }
+    if let Some(prompt) = body.prompt {
+        task.prompt = prompt;
+    }
+    if let Some(tools) = body.tools {
+        task.tools = tools;
+    }
+    if let Some(workspace) = body.workspace {
+        task.workspace = match resolve_workspace_name(&caller, Some(&workspace)) {
+            Ok(workspace) => workspace,
+            Err(e) => return HttpResponse::BadRequest().json(ScheduleResponse::error(e)),
+        };
+    }
+    if let Some(max_iterations) = body.max_iterations {
+        task.max_iterations = resolve_max_iterations(Some(max_iterations)) as i64;
+    }
+    if let Some(enabled) = body.enabled {
+        task.enabled = enabled;
+    }
+
+    if let Err(e) = validate_schedule(&state, &task) {
+        return HttpResponse::BadRequest().json(ScheduleResponse::error(e));
+    }
+    if let Err(e) = templates::check_includes(&state.db, &task.prompt).await {
+        return HttpResponse::BadRequest().json(ScheduleResponse::error(e));
+    }
+    task.next_run_at = next_run_at(&task);
+
+    match state.db.save_scheduled_task(&task).await {
+        Ok(true) => match find_schedule(&state, &caller, task.id).await {
+            Ok(task) => HttpResponse::Ok().json(ScheduleResponse {
+                schedule: Some(task),
+                ..ScheduleResponse::ok()
+            }),
+            Err(resp) => resp,
+        },
+        Ok(false) => HttpResponse::NotFound().json(ScheduleResponse::error("Schedule not found")),
+        Err(e) => {
+            log::error!("Failed to update scheduled task {}: {}", task.id, e);
+            HttpResponse::InternalServerError().json(ScheduleResponse::error("Failed to update schedule"))
+        }
+    }
+}
+
+/// Delete a scheduled task. Jobs it already queued are kept.
+async fn delete_schedule(state: web::Data<AppState>, req: HttpRequest, path: web::Path<i64>) -> impl Responder {
+    let caller = match validate_session_from_request(&state, &req, Scope::ToolsExec).await {
+        Ok(caller) => caller,
+        Err(resp) => return resp,
+    };
+
+    let id = match find_schedule(&state, &caller, path.into_inner()).await {
+        Ok(task) => task.id,
+        Err(resp) => return resp,
+    };
+    match state.db.delete_scheduled_task(id).await {
+        Ok(true) => HttpResponse::Ok().json(ScheduleResponse::ok()),
+        Ok(false) => HttpResponse::NotFound().json(ScheduleResponse::error("Schedule not found")),
+        Err(e) => {
+            log::error!("Failed to delete scheduled task {}: {}", id, e);
+            HttpResponse::InternalServerError().json(ScheduleResponse::error("Failed to delete schedule"))
+        }
+    }
+}
+
+/// Queue a run of a scheduled task now, without moving its next scheduled run
+async fn run_schedule(state: web::Data<AppState>, req: HttpRequest, path: web::Path<i64>) -> impl Responder {
+    let caller = match validate_session_from_request(&state, &req, Scope::ToolsExec).await {
+        Ok(caller) => caller,
+        Err(resp) => return resp,
+    };
+
+    let task = match find_schedule(&state, &caller, path.into_inner()).await {
+        Ok(task) => task,
+        Err(resp) => return resp,
+    };
+
+    match state.schedules.launch(&task).await {
+        Ok(job) => HttpResponse::Accepted().json(ScheduleResponse {
+            job: Some(job),
+            ..ScheduleResponse::ok()
+        }),
+        Err(e) => {
+            log::error!("{}", e);
+            HttpResponse::InternalServerError().json(ScheduleResponse::error("Failed to queue job"))
+        }
+    }
+}
+
+/// Jobs queued by a scheduled task, newest first
+async fn list_schedule_jobs(
+    state: web::Data<AppState>,
+    req: HttpRequest,
+    path: web::Path<i64>,
+    query: web::Query<LimitQuery>,
+) -> impl Responder {
+    let caller = match validate_session_from_request(&state, &req, Scope::Read).await {
+        Ok(caller) => caller,
+        Err(resp) => return resp,
+    };
+
+    let id = match find_schedule(&state, &caller, path.into_inner()).await {
+        Ok(task) => task.id,
+        Err(resp) => return resp,
+    };
+    let limit = query.limit.unwrap_or(20).min(100);
+    match state.db.list_agent_jobs_for_schedule(id, limit).await {
+        Ok(jobs) => HttpResponse::Ok().json(ScheduleResponse {
+            jobs: Some(jobs),
+            ..ScheduleResponse::ok()
+        }),
+        Err(e) => {
+            log::error!("Failed to list jobs for scheduled task {}: {}", id, e);
+            HttpResponse::InternalServerError().json(ScheduleResponse::error("Internal server error"))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::Database;
+    use crate::models::{Scopes, UserRole};
+    use actix_web::{test, App};
+    use serde_json::{json, Value};
+    use std::sync::Arc;
+
+    /// A bearer token for a new user with `role`
+    async fn token(db: &Database, address: &str, role: UserRole) -> (i64, String) {
+        let user = db.create_user(address, None, role).await.unwrap();
+        let scopes = Scopes::new([Scope::Read, Scope::ToolsExec]);
+        (user.id, db.create_api_token(user.id, address, &scopes, None).await.unwrap().token)
+    }
+
+    fn call(method: &str, uri: &str, token: &str) -> test::TestRequest {
+        let method = actix_web::http::Method::from_bytes(method.as_bytes()).unwrap();
+        test::TestRequest::default()
+            .method(method)
+            .uri(uri)
+            .insert_header(("Authorization", format!("Bearer {}", token)))
+    }
+
+    #[actix_web::test]
+    async fn test_schedules_belong_to_their_creator() {
+        let db = Arc::new(Database::new(":memory:").unwrap());
+        let (alice_id, alice) = token(&db, "0xa11ce", UserRole::Member).await;
+        let (_, bob) = token(&db, "0xb0b", UserRole::Member).await;
+        let (_, admin) = token(&db, "0xad", UserRole::Admin).await;
+        let app = test::init_service(
+            App::new().app_data(web::Data::new(AppState::for_tests(db.clone()).await)).configure(config),
+        )
+        .await;
+
+        let create = |token: &str, body: &Value| call("POST", "/api/schedules", token).set_json(body).to_request();
+        let body = json!({
+            "name": "nightly",
+            "cron_expression": "0 3 * * *",
+            "prompt": "Run the tests",
+            "workspace": "ci",
+        });
+        let created: Value = test::call_and_read_body_json(&app, create(&alice, &body)).await;
+        let id = created["schedule"]["id"].as_i64().unwrap();
+        assert_eq!(created["schedule"]["user_id"], alice_id);
+        assert_eq!(created["schedule"]["workspace"], format!("user-{}-ci", alice_id));
+
+        // Without a workspace a member still gets one under their prefix; an admin gets the id-based default
+        let body = json!({ "name": "hourly", "cron_expression": "0 * * * *", "prompt": "Check" });
+        let created: Value = test::call_and_read_body_json(&app, create(&bob, &body)).await;
+        assert!(created["schedule"]["workspace"].as_str().unwrap().starts_with("user-"));
+        let created: Value = test::call_and_read_body_json(&app, create(&admin, &body)).await;
+        let admin_task = created["schedule"]["id"].as_i64().unwrap();
+        assert_eq!(created["schedule"]["workspace"], format!("schedule-{}", admin_task));
+
+        let list = |token: &str| call("GET", "/api/schedules", token).to_request();
+        let listed: Value = test::call_and_read_body_json(&app, list(&bob)).await;
+        assert_eq!(listed["schedules"].as_array().unwrap().len(), 1);
+        let listed: Value = test::call_and_read_body_json(&app, list(&admin)).await;
+        assert_eq!(listed["schedules"].as_array().unwrap().len(), 3);
+
+        let uri = format!("/api/schedules/{}", id);
+        for req in [
+            call("GET", &uri, &bob),
+            call("PUT", &uri, &bob).set_json(json!({ "enabled": false })),
+            call("DELETE", &uri, &bob),
+            call("POST", &format!("{}/run", uri), &bob),
+            call("GET", &format!("{}/jobs", uri), &bob),
+        ] {
+            assert_eq!(test::call_service(&app, req.to_request()).await.status(), 404);
+        }
+        assert!(db.get_scheduled_task(id).await.unwrap().unwrap().enabled);
+
+        // Runs are queued as the schedule's owner
+        let run: Value =
+            test::call_and_read_body_json(&app, call("POST", &format!("{}/run", uri), &alice).to_request()).await;
+        assert_eq!(run["job"]["user_id"], alice_id);
+        assert_eq!(run["job"]["workspace"], format!("user-{}-ci", alice_id));
+
+        assert_eq!(test::call_service(&app, call("DELETE", &uri, &alice).to_request()).await.status(), 200);
+    }
+}
@@ -1,17 +1,20 @@
 use actix_web::{web, HttpRequest, HttpResponse, Responder};
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
 
 use crate::models::{
-    ChatSessionResponse, CompletionStatus, GetOrCreateSessionRequest, SessionScope,
-    SessionTranscriptResponse, UpdateResetPolicyRequest,
+    Caller, ChatSession, ChatSessionResponse, CompletionStatus, ConversationExport, ExportFormat,
+    GetOrCreateSessionRequest, Scope, SessionRegister, SessionScope, SessionTranscriptResponse,
+    SetSessionPersonaRequest, SetToolPolicyRequest, UpdateResetPolicyRequest,
 };
 use crate::AppState;
+use crate::utils::truncate_str;
 
 /// Validate session token from request
-fn validate_session_from_request(
+async fn validate_session_from_request(
     state: &web::Data<AppState>,
     req: &HttpRequest,
-) -> Result<(), HttpResponse> {
+    scope: Scope,
+) -> Result<Caller, HttpResponse> {
     let token = req
         .headers()
         .get("Authorization")
@@ -27,8 +30,11 @@ fn validate_session_from_request(
         }
     };
 
-    match state.db.validate_session(&token) {
-        Ok(Some(_)) => Ok(()),
+    match state.db.authenticate(&token).await {
+        Ok(Some(caller)) if caller.scopes.allows(scope) => Ok(caller),
+        Ok(Some(_)) => Err(HttpResponse::Forbidden().json(serde_json::json!({
+            "error": format!("Token lacks the {} scope", scope)
+        }))),
         Ok(None) => Err(HttpResponse::Unauthorized().json(serde_json::json!({
             "error": "Invalid or expired session"
         }))),
@@ -41,40 +47,64 @@ fn validate_session_from_request(
     }
 }
 
-/// List all chat sessions
+/// Validate the request and load a session the caller may see. Other users'
+/// sessions are reported as not found.
+async fn authorize_session(
+    state: &web::Data<AppState>,
+    req: &HttpRequest,
+    scope: Scope,
+    session_id: i64,
+) -> Result<ChatSession, HttpResponse> {
+    let caller = validate_session_from_request(state, req, scope).await?;
+
+    match state.db.get_chat_session(session_id).await {
+        Ok(Some(session)) if caller.can_access(session.user_id) => Ok(session),
+        Ok(_) => Err(HttpResponse::NotFound().json(serde_json::json!({
+            "error": "Session not found"
+        }))),
+        Err(e) => {
+            log::error!("Failed to get session {}: {}", session_id, e);
+            Err(HttpResponse::InternalServerError().json(serde_json::json!({
+                "error": format!("Database error: {}", e)
+            })))
+        }
+    }
+}
+
+/// List chat sessions: all of them for admins, their own for other users
 async fn list_sessions(
     data: web::Data<AppState>,
     req: HttpRequest,
 ) -> impl Responder {
-    if let Err(resp) = validate_session_from_request(&data, &req) {
-        return resp;
-    }
+    let caller = match validate_session_from_request(&data, &req, Scope::Read).await {
+        Ok(caller) => caller,
+        Err(resp) => return resp,
+    };
 
-    match data.db.list_chat_sessions() {
+    let sessions = if caller.is_admin() {
+        data.db.list_chat_sessions().await
+    } else {
+        data.db.list_user_chat_sessions(caller.user.id).await
+    };
+    match sessions {
         Ok(sessions) => {
-            let responses: Vec<ChatSessionResponse> = sessions
-                .into_iter()
-                .map(|s| {
-                    let is_web = s.channel_type == "web";
-                    let session_id = s.id;
-                    let mut response: ChatSessionResponse = s.into();
-                    if let Ok(count) = data.db.count_session_messages(session_id) {
-                        response.message_count = Some(count);
-                    }
-                    // For web sessions, get the initial query (first user message)
-                    if is_web {
-                        if let Ok(Some(first_msg)) = data.db.get_first_user_message(session_id) {
-                            // Truncate to 100 chars for the list view
-                            response.initial_query = Some(if first_msg.len() > 100 {
-                                format!("{}...", &first_msg[..100])
-                            } else {
-                                first_msg
-                            });
-                        }
+            let mut responses: Vec<ChatSessionResponse> = Vec::with_capacity(sessions.len());
+            for s in sessions {
+                let is_web = s.channel_type == "web";
+                let session_id = s.id;
+                let mut response: ChatSessionResponse = s.into();
+                if let Ok(count) = data.db.count_session_messages(session_id).await {
+                    response.message_count = Some(count);
+                }
+                // For web sessions, get the initial query (first user message)
+                if is_web {
+                    if let Ok(Some(first_msg)) = data.db.get_first_user_message(session_id).await {
+                        // Truncate to 100 chars for the list view
+                        response.initial_query = Some(truncate_str(&first_msg, 100));
                     }
-                    response
-                })
-                .collect();
+                }
+                responses.push(response);
+            }
             HttpResponse::Ok().json(responses)
         }
         Err(e) => {
@@ -92,9 +122,10 @@ async fn get_or_create_session(
     req: HttpRequest,
     body: web::Json<GetOrCreateSessionRequest>,
 ) -> impl Responder {
-    if let Err(resp) = validate_session_from_request(&data, &req) {
-        return resp;
-    }
+    let caller = match validate_session_from_request(&data, &req, Scope::Chat).await {
+        Ok(caller) => caller,
+        Err(resp) => return resp,
+    };
     let scope = body.scope.unwrap_or(SessionScope::Dm);
 
     match data.db.get_or_create_chat_session(
@@ -103,11 +134,15 @@ async fn get_or_create_session(
         &body.platform_chat_id,
         scope,
         body.agent_id.as_deref(),
-    ) {
+        Some(caller.user.id),
+    ).await {
+        Ok(session) if !caller.can_access(session.user_id) => HttpResponse::NotFound().json(serde_json::json!({
+            "error": "Session not found"
+        })),
         Ok(session) => {
             let mut response: ChatSessionResponse = session.into();
             // Get message count
-            if let Ok(count) = data.db.count_session_messages(response.id) {
+            if let Ok(count) = data.db.count_session_messages(response.id).await {
                 response.message_count = Some(count);
             }
             HttpResponse::Ok().json(response)
@@ -127,29 +162,16 @@ async fn get_session(
     req: HttpRequest,
     path: web::Path<i64>,
 ) -> impl Responder {
-    if let Err(resp) = validate_session_from_request(&data, &req) {
-        return resp;
-    }
-    let session_id = path.into_inner();
+    let session = match authorize_session(&data, &req, Scope::Read, path.into_inner()).await {
+        Ok(session) => session,
+        Err(resp) => return resp,
+    };
 
-    match data.db.get_chat_session(session_id) {
-        Ok(Some(session)) => {
-            let mut response: ChatSessionResponse = session.into();
-            if let Ok(count) = data.db.count_session_messages(response.id) {
-                response.message_count = Some(count);
-            }
-            HttpResponse::Ok().json(response)
-        }
-        Ok(None) => HttpResponse::NotFound().json(serde_json::json!({
-            "error": "Session not found"
-        })),
-        Err(e) => {
-            log::error!("Failed to get session: {}", e);
-            HttpResponse::InternalServerError().json(serde_json::json!({
-                "error": format!("Database error: {}", e)
-            }))
-        }
+    let mut response: ChatSessionResponse = session.into();
+    if let Ok(count) = data.db.count_session_messages(response.id).await {
+        response.message_count = Some(count);
     }
+    HttpResponse::Ok().json(response)
 }
 
 /// Reset a session
@@ -158,15 +180,15 @@ async fn reset_session(
     req: HttpRequest,
     path: web::Path<i64>,
 ) -> impl Responder {
-    if let Err(resp) = validate_session_from_request(&data, &req) {
+    let session_id = path.into_inner();
+    if let Err(resp) = authorize_session(&data, &req, Scope::Chat, session_id).await {
         return resp;
     }
-    let session_id = path.into_inner();
 
     // Clear any tasks associated with this session
     data.execution_tracker.clear_tasks_for_session(session_id);
 
-    match data.db.reset_chat_session(session_id) {
+    match data.db.reset_chat_session(session_id).await {
         Ok(session) => {
             let response: ChatSessionResponse = session.into();
             HttpResponse::Ok().json(response)
@@ -187,17 +209,17 @@ async fn update_reset_policy(
     path: web::Path<i64>,
     body: web::Json<UpdateResetPolicyRequest>,
 ) -> impl Responder {
-    if let Err(resp) = validate_session_from_request(&data, &req) {
+    let session_id = path.into_inner();
+    if let Err(resp) = authorize_session(&data, &req, Scope::Admin, session_id).await {
         return resp;
     }
-    let session_id = path.into_inner();
 
     match data.db.update_session_reset_policy(
         session_id,
         body.reset_policy,
         body.idle_timeout_minutes,
         body.daily_reset_hour,
-    ) {
+    ).await {
         Ok(Some(session)) => {
             let response: ChatSessionResponse = session.into();
             HttpResponse::Ok().json(response)
@@ -214,38 +236,109 @@ async fn update_reset_policy(
     }
 }
 
-/// Force delete a session and cancel any running agentic loops
-async fn delete_session(
+/// Pick the persona a session's conversation runs under; `null` goes back to
+/// the agent settings' default
+async fn set_session_persona(
     data: web::Data<AppState>,
     req: HttpRequest,
     path: web::Path<i64>,
+    body: web::Json<SetSessionPersonaRequest>,
 ) -> impl Responder {
-    if let Err(resp) = validate_session_from_request(&data, &req) {
+    let session_id = path.into_inner();
+    if let Err(resp) = authorize_session(&data, &req, Scope::Chat, session_id).await {
         return resp;
     }
-    let session_id = path.into_inner();
+    let persona = body.into_inner().persona.filter(|p| !p.trim().is_empty());
+
+    if let Some(name) = &persona {
+        match data.db.get_persona(name).await {
+            Ok(Some(_)) => {}
+            Ok(None) => {
+                return HttpResponse::BadRequest().json(serde_json::json!({
+                    "error": format!("Unknown persona: {}", name)
+                }));
+            }
+            Err(e) => {
+                log::error!("Failed to load persona {}: {}", name, e);
+                return HttpResponse::InternalServerError().json(serde_json::json!({
+                    "error": format!("Database error: {}", e)
+                }));
+            }
+        }
+    }
 
-    // First get the session to find its channel_id
-    let session = match data.db.get_chat_session(session_id) {
-        Ok(Some(s)) => s,
-        Ok(None) => {
-            return HttpResponse::NotFound().json(serde_json::json!({
-                "error": "Session not found"
-            }));
+    match data.db.set_session_persona(session_id, persona.as_deref()).await {
+        Ok(Some(session)) => {
+            let response: ChatSessionResponse = session.into();
+            HttpResponse::Ok().json(response)
         }
+        Ok(None) => HttpResponse::NotFound().json(serde_json::json!({
+            "error": "Session not found"
+        })),
         Err(e) => {
-            log::error!("Failed to get session for deletion: {}", e);
-            return HttpResponse::InternalServerError().json(serde_json::json!({
+            log::error!("Failed to set session persona: {}", e);
+            HttpResponse::InternalServerError().json(serde_json::json!({
                 "error": format!("Database error: {}", e)
+            }))
+        }
+    }
+}
+
+/// Set or clear the tool policy of a session's conversation. Needs the admin
+/// scope, since a chat user could otherwise lift their own restrictions.
+async fn set_session_tool_policy(
+    data: web::Data<AppState>,
+    req: HttpRequest,
+    path: web::Path<i64>,
+    body: web::Json<SetToolPolicyRequest>,
+) -> impl Responder {
+    let session_id = path.into_inner();
+    if let Err(resp) = authorize_session(&data, &req, Scope::Admin, session_id).await {
+        return resp;
+    }
+    let tool_policy = match body.into_inner().tool_policy.map(|p| p.normalized()).transpose() {
+        Ok(policy) => policy,
+        Err(e) => {
+            return HttpResponse::BadRequest().json(serde_json::json!({
+                "error": e
             }));
         }
     };
 
+    match data.db.set_session_tool_policy(session_id, tool_policy.as_ref()).await {
+        Ok(Some(session)) => {
+            let response: ChatSessionResponse = session.into();
+            HttpResponse::Ok().json(response)
+        }
+        Ok(None) => HttpResponse::NotFound().json(serde_json::json!({
+            "error": "Session not found"
+        })),
+        Err(e) => {
+            log::error!("Failed to set session tool policy: {}", e);
+            HttpResponse::InternalServerError().json(serde_json::json!({
+                "error": format!("Database error: {}", e)
+            }))
+        }
+    }
+}
+
+/// Force delete a session and cancel any running agentic loops
+async fn delete_session(
+    data: web::Data<AppState>,
+    req: HttpRequest,
+    path: web::Path<i64>,
+) -> impl Responder {
+    let session_id = path.into_inner();
+    let session = match authorize_session(&data, &req, Scope::Admin, session_id).await {
+        Ok(session) => session,
+        Err(resp) => return resp,
+    };
+
     let channel_id = session.channel_id;
 
     // Cancel all running subagents/agentic loops for this channel
     let cancelled_agents = if let Some(subagent_manager) = data.dispatcher.subagent_manager() {
-        let count = subagent_manager.cancel_all_for_channel(channel_id);
+        let count = subagent_manager.cancel_all_for_channel(channel_id).await;
         if count > 0 {
             log::info!(
                 "Force delete: Cancelled {} running agent(s) for channel {} (session {})",
@@ -263,7 +356,7 @@ async fn delete_session(
     data.execution_tracker.clear_tasks_for_session(session_id);
 
     // Now delete the session
-    match data.db.delete_chat_session(session_id) {
+    match data.db.delete_chat_session(session_id).await {
         Ok(true) => HttpResponse::Ok().json(serde_json::json!({
             "success": true,
             "message": "Session deleted",
@@ -287,31 +380,17 @@ async fn stop_session(
     req: HttpRequest,
     path: web::Path<i64>,
 ) -> impl Responder {
-    if let Err(resp) = validate_session_from_request(&data, &req) {
-        return resp;
-    }
     let session_id = path.into_inner();
-
-    // Get the session first
-    let session = match data.db.get_chat_session(session_id) {
-        Ok(Some(s)) => s,
-        Ok(None) => {
-            return HttpResponse::NotFound().json(serde_json::json!({
-                "error": "Session not found"
-            }));
-        }
-        Err(e) => {
-            log::error!("Failed to get session for stop: {}", e);
-            return HttpResponse::InternalServerError().json(serde_json::json!({
-                "error": format!("Database error: {}", e)
-            }));
-        }
+    let session = match authorize_session(&data, &req, Scope::Chat, session_id).await {
+        Ok(session) => session,
+        Err(resp) => return resp,
     };
 
-    // Cancel any running executions for this session
+    // Cancel any running executions for this session. Sessions share their
+    // channel, so only what runs in this one is stopped.
     let channel_id = session.channel_id;
     let cancelled_agents = if let Some(subagent_manager) = data.dispatcher.subagent_manager() {
-        let count = subagent_manager.cancel_all_for_channel(channel_id);
+        let count = subagent_manager.cancel_all_for_session(channel_id, session_id).await;
         if count > 0 {
             log::info!(
                 "Stop session: Cancelled {} running agent(s) for channel {} (session {})",
@@ -326,14 +405,16 @@ async fn stop_session(
     };
 
     // Also cancel execution tracker
-    data.execution_tracker.cancel_execution(channel_id);
-    data.execution_tracker.cancel_all_sessions_for_channel(channel_id);
+    if data.execution_tracker.execution_session(channel_id) == Some(session_id) {
+        data.execution_tracker.cancel_execution(channel_id);
+    }
+    data.execution_tracker.cancel_execution_for_session(session_id);
 
     // Clear any tasks associated with this session
     data.execution_tracker.clear_tasks_for_session(session_id);
 
     // Update completion status to cancelled
-    if let Err(e) = data.db.update_session_completion_status(session_id, CompletionStatus::Cancelled) {
+    if let Err(e) = data.db.update_session_completion_status(session_id, CompletionStatus::Cancelled).await {
         log::error!("Failed to update session status: {}", e);
         return HttpResponse::InternalServerError().json(serde_json::json!({
             "error": format!("Database error: {}", e)
@@ -341,10 +422,10 @@ async fn stop_session(
     }
 
     // Return updated session
-    match data.db.get_chat_session(session_id) {
+    match data.db.get_chat_session(session_id).await {
         Ok(Some(session)) => {
             let mut response: ChatSessionResponse = session.into();
-            if let Ok(count) = data.db.count_session_messages(response.id) {
+            if let Ok(count) = data.db.count_session_messages(response.id).await {
                 response.message_count = Some(count);
             }
             HttpResponse::Ok().json(serde_json::json!({
@@ -368,25 +449,10 @@ async fn resume_session(
     req: HttpRequest,
     path: web::Path<i64>,
 ) -> impl Responder {
-    if let Err(resp) = validate_session_from_request(&data, &req) {
-        return resp;
-    }
     let session_id = path.into_inner();
-
-    // Get the session first to validate it exists
-    let session = match data.db.get_chat_session(session_id) {
-        Ok(Some(s)) => s,
-        Ok(None) => {
-            return HttpResponse::NotFound().json(serde_json::json!({
-                "error": "Session not found"
-            }));
-        }
-        Err(e) => {
-            log::error!("Failed to get session for resume: {}", e);
-            return HttpResponse::InternalServerError().json(serde_json::json!({
-                "error": format!("Database error: {}", e)
-            }));
-        }
+    let session = match authorize_session(&data, &req, Scope::Chat, session_id).await {
+        Ok(session) => session,
+        Err(resp) => return resp,
     };
 
     // Don't allow resuming completed sessions
@@ -397,7 +463,7 @@ async fn resume_session(
     }
 
     // Update completion status to active
-    if let Err(e) = data.db.update_session_completion_status(session_id, CompletionStatus::Active) {
+    if let Err(e) = data.db.update_session_completion_status(session_id, CompletionStatus::Active).await {
         log::error!("Failed to update session status: {}", e);
         return HttpResponse::InternalServerError().json(serde_json::json!({
             "error": format!("Database error: {}", e)
@@ -405,10 +471,10 @@ async fn resume_session(
     }
 
     // Return updated session
-    match data.db.get_chat_session(session_id) {
+    match data.db.get_chat_session(session_id).await {
         Ok(Some(session)) => {
             let mut response: ChatSessionResponse = session.into();
-            if let Ok(count) = data.db.count_session_messages(response.id) {
+            if let Ok(count) = data.db.count_session_messages(response.id).await {
                 response.message_count = Some(count);
             }
             HttpResponse::Ok().json(serde_json::json!({
@@ -437,20 +503,20 @@ async fn get_transcript(
     path: web::Path<i64>,
     query: web::Query<TranscriptQuery>,
 ) -> impl Responder {
-    if let Err(resp) = validate_session_from_request(&data, &req) {
+    let session_id = path.into_inner();
+    if let Err(resp) = authorize_session(&data, &req, Scope::Read, session_id).await {
         return resp;
     }
-    let session_id = path.into_inner();
 
     let messages = if let Some(limit) = query.limit {
-        data.db.get_recent_session_messages(session_id, limit)
+        data.db.get_recent_session_messages(session_id, limit).await
     } else {
-        data.db.get_session_messages(session_id)
+        data.db.get_session_messages(session_id).await
     };
 
     match messages {
         Ok(msgs) => {
-            let total = data.db.count_session_messages(session_id).unwrap_or(msgs.len() as i64);
+            let total = data.db.count_session_messages(session_id).await.unwrap_or(msgs.len() as i64);
             HttpResponse::Ok().json(SessionTranscriptResponse {
                 session_id,
                 messages: msgs,
@@ -466,6 +532,144 @@ async fn get_transcript(
     }
 }
 
+#[derive(Serialize)]
+struct SessionRegistersResponse {
+    session_id: i64,
+    registers: Vec<SessionRegister>,
+}
+
+/// List the persisted tool registers of a session
+async fn list_registers(
+    data: web::Data<AppState>,
+    req: HttpRequest,
+    path: web::Path<i64>,
+) -> impl Responder {
+    let session_id = path.into_inner();
+    if let Err(resp) = authorize_session(&data, &req, Scope::Read, session_id).await {
+        return resp;
+    }
+
+    match data.db.list_session_registers(session_id).await {
+        Ok(registers) => HttpResponse::Ok().json(SessionRegistersResponse { session_id, registers }),
+        Err(e) => {
+            log::error!("Failed to list registers of session {}: {}", session_id, e);
+            HttpResponse::InternalServerError().json(serde_json::json!({
+                "error": format!("Database error: {}", e)
+            }))
+        }
+    }
+}
+
+/// Get one persisted tool register of a session
+async fn get_register(
+    data: web::Data<AppState>,
+    req: HttpRequest,
+    path: web::Path<(i64, String)>,
+) -> impl Responder {
+    let (session_id, key) = path.into_inner();
+    if let Err(resp) = authorize_session(&data, &req, Scope::Read, session_id).await {
+        return resp;
+    }
+
+    match data.db.get_session_register(session_id, &key).await {
+        Ok(Some(register)) => HttpResponse::Ok().json(register),
+        Ok(None) => HttpResponse::NotFound().json(serde_json::json!({
+            "error": "Register not found"
+        })),
+        Err(e) => {
+            log::error!("Failed to get register {} of session {}: {}", key, session_id, e);
+            HttpResponse::InternalServerError().json(serde_json::json!({
+                "error": format!("Database error: {}", e)
+            }))
+        }
+    }
+}
+
+/// Clear the persisted tool registers of a session. A dispatch that is
+/// already running keeps its copy; later messages start empty.
+async fn clear_registers(
+    data: web::Data<AppState>,
+    req: HttpRequest,
+    path: web::Path<i64>,
+) -> impl Responder {
+    let session_id = path.into_inner();
+    if let Err(resp) = authorize_session(&data, &req, Scope::Chat, session_id).await {
+        return resp;
+    }
+
+    match data.db.clear_session_registers(session_id).await {
+        Ok(cleared) => HttpResponse::Ok().json(serde_json::json!({
+            "success": true,
+            "cleared": cleared
+        })),
+        Err(e) => {
+            log::error!("Failed to clear registers of session {}: {}", session_id, e);
+            HttpResponse::InternalServerError().json(serde_json::json!({
+                "error": format!("Database error: {}", e)
+            }))
+        }
+    }
+}
+
+/// Export a conversation as JSON or Markdown
+#[derive(Deserialize)]
+struct ExportQuery {
+    format: Option<String>,
+}
+
+async fn export_conversation(
+    data: web::Data<AppState>,
+    req: HttpRequest,
+    path: web::Path<i64>,
+    query: web::Query<ExportQuery>,
+) -> impl Responder {
+    let session_id = path.into_inner();
+    let session = match authorize_session(&data, &req, Scope::Read, session_id).await {
+        Ok(session) => session,
+        Err(resp) => return resp,
+    };
+
+    let format = match query.format.as_deref() {
+        None => ExportFormat::Json,
+        Some(f) => match ExportFormat::from_str(f) {
+            Some(format) => format,
+            None => {
+                return HttpResponse::BadRequest().json(serde_json::json!({
+                    "error": format!("Unsupported export format '{}'. Use 'json' or 'md'", f)
+                }));
+            }
+        },
+    };
+
+
+    let messages = match data.db.get_session_messages(session_id).await {
+        Ok(msgs) => msgs,
+        Err(e) => {
+            log::error!("Failed to get messages for export: {}", e);
+            return HttpResponse::InternalServerError().json(serde_json::json!({
+                "error": format!("Database error: {}", e)
+            }));
+        }
+    };
+
+    let export = ConversationExport::new(&session, messages);
+    match format {
+        ExportFormat::Json => HttpResponse::Ok()
+            .insert_header((
+                "Content-Disposition",
+                format!("attachment; filename=\"conversation-{}.json\"", session_id),
+            ))
+            .json(export),
+        ExportFormat::Markdown => HttpResponse::Ok()
+            .content_type("text/markdown; charset=utf-8")
+            .insert_header((
+                "Content-Disposition",
+                format!("attachment; filename=\"conversation-{}.md\"", session_id),
+            ))
+            .body(export.to_markdown()),
+    }
+}
+
 pub fn config(cfg: &mut web::ServiceConfig) {
     cfg.service(
         web::scope("/api/sessions")
@@ -477,6 +681,15 @@ pub fn config(cfg: &mut web::ServiceConfig) {
             .route("/{id}/stop", web::post().to(stop_session))
             .route("/{id}/resume", web::post().to(resume_session))
             .route("/{id}/policy", web::put().to(update_reset_policy))
-            .route("/{id}/transcript", web::get().to(get_transcript)),
+            .route("/{id}/persona", web::put().to(set_session_persona))
+            .route("/{id}/tool-policy", web::put().to(set_session_tool_policy))
+            .route("/{id}/transcript", web::get().to(get_transcript))
+            .route("/{id}/registers", web::get().to(list_registers))
+            .route("/{id}/registers", web::delete().to(clear_registers))
+            .route("/{id}/registers/{key}", web::get().to(get_register)),
+    );
+    cfg.service(
+        web::scope("/api/conversations")
+            .route("/{id}/export", web::get().to(export_conversation)),
     );
 }
@@ -3,7 +3,8 @@ use actix_web::{web, HttpRequest, HttpResponse, Responder};
 use futures_util::StreamExt;
 use serde::{Deserialize, Serialize};
 
-use crate::skills::{DbSkillScript, Skill};
+use crate::skills::{parse_skill_md, sync_skills_from_git, DbSkillScript, Skill};
+use crate::models::{Scope, SkillSync};
 use crate::AppState;
 
 #[derive(Serialize)]
@@ -25,6 +26,7 @@ pub struct SkillInfo {
     pub requires_tools: Vec<String>,
     pub requires_binaries: Vec<String>,
     pub tags: Vec<String>,
+    pub triggers: Vec<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub homepage: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
@@ -42,6 +44,7 @@ impl From<&Skill> for SkillInfo {
             requires_tools: skill.metadata.requires_tools.clone(),
             requires_binaries: skill.metadata.requires_binaries.clone(),
             tags: skill.metadata.tags.clone(),
+            triggers: skill.metadata.triggers.clone(),
             homepage: skill.metadata.homepage.clone(),
             metadata: skill.metadata.metadata.clone(),
         }
@@ -69,6 +72,7 @@ pub struct SkillDetail {
     pub requires_binaries: Vec<String>,
     pub missing_binaries: Vec<String>,
     pub tags: Vec<String>,
+    pub triggers: Vec<String>,
     pub arguments: Vec<ArgumentInfo>,
     pub prompt_template: String,
     #[serde(skip_serializing_if = "Option::is_none")]
@@ -129,6 +133,7 @@ impl From<&Skill> for SkillDetail {
             requires_binaries: skill.metadata.requires_binaries.clone(),
             missing_binaries,
             tags: skill.metadata.tags.clone(),
+            triggers: skill.metadata.triggers.clone(),
             arguments,
             prompt_template: skill.prompt_template.clone(),
             scripts: None,
@@ -143,6 +148,12 @@ pub struct SetEnabledRequest {
     pub enabled: bool,
 }
 
+/// A skill as SKILL.md content (YAML frontmatter + instructions)
+#[derive(Deserialize)]
+pub struct SkillMarkdownRequest {
+    pub markdown: String,
+}
+
 #[derive(Serialize)]
 pub struct OperationResponse {
     pub success: bool,
@@ -172,22 +183,50 @@ pub struct ScriptsListResponse {
     pub error: Option<String>,
 }
 
+#[derive(Serialize)]
+pub struct SkillSyncResponse {
+    pub success: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub sync: Option<SkillSync>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+#[derive(Serialize)]
+pub struct SkillSyncListResponse {
+    pub success: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub syncs: Option<Vec<SkillSync>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+#[derive(Deserialize)]
+pub struct SkillSyncListQuery {
+    pub limit: Option<i64>,
+}
+
 pub fn config(cfg: &mut web::ServiceConfig) {
     cfg.service(
         web::scope("/api/skills")
             .route("", web::get().to(list_skills))
+            .route("", web::post().to(create_skill))
             .route("/upload", web::post().to(upload_skill))
             .route("/reload", web::post().to(reload_skills))
+            .route("/sync", web::get().to(list_skill_syncs))
+            .route("/sync", web::post().to(sync_skills))
             .route("/{name}", web::get().to(get_skill))
+            .route("/{name}", web::put().to(update_skill))
             .route("/{name}", web::delete().to(delete_skill))
             .route("/{name}/enabled", web::put().to(set_enabled))
             .route("/{name}/scripts", web::get().to(get_skill_scripts)),
     );
 }
 
-fn validate_session_from_request(
+async fn validate_session_from_request(
     state: &web::Data<AppState>,
     req: &HttpRequest,
+    scope: Scope,
 ) -> Result<(), HttpResponse> {
     let token = req
         .headers()
@@ -207,8 +246,14 @@ fn validate_session_from_request(
         }
     };
 
-    match state.db.validate_session(&token) {
-        Ok(Some(_)) => Ok(()),
+    match state.db.authorize(&token).await {
+        Ok(Some(scopes)) if scopes.allows(scope) => Ok(()),
+        Ok(Some(_)) => Err(HttpResponse::Forbidden().json(OperationResponse {
+            success: false,
+            message: None,
+            error: Some(format!("Token lacks the {} scope", scope)),
+            count: None,
+        })),
         Ok(None) => Err(HttpResponse::Unauthorized().json(OperationResponse {
             success: false,
             message: None,
@@ -228,13 +273,13 @@ fn validate_session_from_request(
 }
 
 async fn list_skills(state: web::Data<AppState>, req: HttpRequest) -> impl Responder {
-    if let Err(resp) = validate_session_from_request(&state, &req) {
+    if let Err(resp) = validate_session_from_request(&state, &req, Scope::Read).await {
         return resp;
     }
 
     let skills: Vec<SkillInfo> = state
         .skill_registry
-        .list()
+        .list().await
         .iter()
         .map(|s| s.into())
         .collect();
@@ -247,18 +292,18 @@ async fn get_skill(
     req: HttpRequest,
     path: web::Path<String>,
 ) -> impl Responder {
-    if let Err(resp) = validate_session_from_request(&state, &req) {
+    if let Err(resp) = validate_session_from_request(&state, &req, Scope::Read).await {
         return resp;
     }
 
     let name = path.into_inner();
 
-    match state.skill_registry.get(&name) {
+    match state.skill_registry.get(&name).await {
         Some(skill) => {
             let mut detail: SkillDetail = (&skill).into();
 
             // Get associated scripts
-            let scripts = state.skill_registry.get_skill_scripts(&name);
+            let scripts = state.skill_registry.get_skill_scripts(&name).await;
             if !scripts.is_empty() {
                 detail.scripts = Some(scripts.iter().map(|s| s.into()).collect());
             }
@@ -283,13 +328,13 @@ async fn set_enabled(
     path: web::Path<String>,
     body: web::Json<SetEnabledRequest>,
 ) -> impl Responder {
-    if let Err(resp) = validate_session_from_request(&state, &req) {
+    if let Err(resp) = validate_session_from_request(&state, &req, Scope::Admin).await {
         return resp;
     }
 
     let name = path.into_inner();
 
-    if !state.skill_registry.has_skill(&name) {
+    if !state.skill_registry.has_skill(&name).await {
         return HttpResponse::NotFound().json(OperationResponse {
             success: false,
             message: None,
@@ -299,7 +344,7 @@ async fn set_enabled(
     }
 
     // Update in registry (which updates the database)
-    state.skill_registry.set_enabled(&name, body.enabled);
+    state.skill_registry.set_enabled(&name, body.enabled).await;
 
     let status = if body.enabled { "enabled" } else { "disabled" };
     HttpResponse::Ok().json(OperationResponse {
@@ -311,7 +356,7 @@ async fn set_enabled(
 }
 
 async fn reload_skills(state: web::Data<AppState>, req: HttpRequest) -> impl Responder {
-    if let Err(resp) = validate_session_from_request(&state, &req) {
+    if let Err(resp) = validate_session_from_request(&state, &req, Scope::Admin).await {
         return resp;
     }
 
@@ -320,7 +365,7 @@ async fn reload_skills(state: web::Data<AppState>, req: HttpRequest) -> impl Res
             success: true,
             message: Some(format!("Loaded {} skills from disk", count)),
             error: None,
-            count: Some(state.skill_registry.len()),
+            count: Some(state.skill_registry.len().await),
         }),
         Err(e) => {
             log::error!("Failed to reload skills: {}", e);
@@ -334,12 +379,80 @@ async fn reload_skills(state: web::Data<AppState>, req: HttpRequest) -> impl Res
     }
 }
 
+/// Pull the configured skill marketplace repository and import its skills now
+async fn sync_skills(state: web::Data<AppState>, req: HttpRequest) -> impl Responder {
+    if let Err(resp) = validate_session_from_request(&state, &req, Scope::Admin).await {
+        return resp;
+    }
+
+    let Some(repo_url) = crate::config::skills_git_url() else {
+        return HttpResponse::BadRequest().json(SkillSyncResponse {
+            success: false,
+            sync: None,
+            error: Some(format!(
+                "No skill repository configured; set {}",
+                crate::config::env_vars::SKILLS_GIT_URL
+            )),
+        });
+    };
+    let branch = crate::config::skills_git_branch();
+
+    match sync_skills_from_git(&state.skill_registry, &state.db, &repo_url, &branch).await {
+        // A failed clone or pull is still recorded; report it as a gateway error
+        Ok(sync) if sync.commit_sha.is_none() => HttpResponse::BadGateway().json(SkillSyncResponse {
+            success: false,
+            error: Some(sync.errors.join("; ")),
+            sync: Some(sync),
+        }),
+        Ok(sync) => HttpResponse::Ok().json(SkillSyncResponse {
+            success: true,
+            sync: Some(sync),
+            error: None,
+        }),
+        Err(e) => {
+            log::error!("Failed to sync skills: {}", e);
+            HttpResponse::InternalServerError().json(SkillSyncResponse {
+                success: false,
+                sync: None,
+                error: Some(e),
+            })
+        }
+    }
+}
+
+/// Recent skill marketplace syncs, newest first
+async fn list_skill_syncs(
+    state: web::Data<AppState>,
+    req: HttpRequest,
+    query: web::Query<SkillSyncListQuery>,
+) -> impl Responder {
+    if let Err(resp) = validate_session_from_request(&state, &req, Scope::Read).await {
+        return resp;
+    }
+
+    match state.db.list_skill_syncs(query.limit.unwrap_or(20)).await {
+        Ok(syncs) => HttpResponse::Ok().json(SkillSyncListResponse {
+            success: true,
+            syncs: Some(syncs),
+            error: None,
+        }),
+        Err(e) => {
+            log::error!("Failed to list skill syncs: {}", e);
+            HttpResponse::InternalServerError().json(SkillSyncListResponse {
+                success: false,
+                syncs: None,
+                error: Some("Internal server error".to_string()),
+            })
+        }
+    }
+}
+
 async fn upload_skill(
     state: web::Data<AppState>,
     req: HttpRequest,
     mut payload: Multipart,
 ) -> impl Responder {
-    if let Err(resp) = validate_session_from_request(&state, &req) {
+    if let Err(resp) = validate_session_from_request(&state, &req, Scope::Admin).await {
         return resp;
     }
 
@@ -398,12 +511,12 @@ async fn upload_skill(
     let result = if is_markdown {
         // Parse as markdown file
         match String::from_utf8(file_data) {
-            Ok(content) => state.skill_registry.create_skill_from_markdown(&content),
+            Ok(content) => state.skill_registry.create_skill_from_markdown(&content).await,
             Err(e) => Err(format!("Invalid UTF-8 in markdown file: {}", e)),
         }
     } else {
         // Parse as ZIP file
-        state.skill_registry.create_skill_from_zip(&file_data)
+        state.skill_registry.create_skill_from_zip(&file_data).await
     };
 
     match result {
@@ -426,18 +539,109 @@ async fn upload_skill(
     }
 }
 
+fn upload_error(status: actix_web::http::StatusCode, error: impl Into<String>) -> HttpResponse {
+    HttpResponse::build(status).json(UploadResponse {
+        success: false,
+        skill: None,
+        error: Some(error.into()),
+    })
+}
+
+/// Create a skill from SKILL.md content
+async fn create_skill(
+    state: web::Data<AppState>,
+    req: HttpRequest,
+    body: web::Json<SkillMarkdownRequest>,
+) -> impl Responder {
+    use actix_web::http::StatusCode;
+
+    if let Err(resp) = validate_session_from_request(&state, &req, Scope::Admin).await {
+        return resp;
+    }
+
+    let name = match parse_skill_md(&body.markdown) {
+        Ok((metadata, _)) => metadata.name,
+        Err(e) => return upload_error(StatusCode::BAD_REQUEST, e),
+    };
+    if state.skill_registry.has_skill(&name).await {
+        return upload_error(
+            StatusCode::CONFLICT,
+            format!("Skill '{}' already exists; use PUT /api/skills/{} to update it", name, name),
+        );
+    }
+
+    match state.skill_registry.create_skill_from_markdown(&body.markdown).await {
+        Ok(db_skill) => {
+            let skill = db_skill.into_skill();
+            HttpResponse::Created().json(UploadResponse {
+                success: true,
+                skill: Some((&skill).into()),
+                error: None,
+            })
+        }
+        Err(e) => {
+            log::error!("Failed to create skill: {}", e);
+            upload_error(StatusCode::INTERNAL_SERVER_ERROR, e)
+        }
+    }
+}
+
+/// Replace a skill's frontmatter and instructions, keeping its enabled state and scripts
+async fn update_skill(
+    state: web::Data<AppState>,
+    req: HttpRequest,
+    path: web::Path<String>,
+    body: web::Json<SkillMarkdownRequest>,
+) -> impl Responder {
+    use actix_web::http::StatusCode;
+
+    if let Err(resp) = validate_session_from_request(&state, &req, Scope::Admin).await {
+        return resp;
+    }
+
+    let name = path.into_inner();
+    if !state.skill_registry.has_skill(&name).await {
+        return upload_error(StatusCode::NOT_FOUND, format!("Skill '{}' not found", name));
+    }
+    match parse_skill_md(&body.markdown) {
+        Ok((metadata, _)) if metadata.name != name => {
+            return upload_error(
+                StatusCode::BAD_REQUEST,
+                format!("Frontmatter name '{}' does not match skill '{}'", metadata.name, name),
+            );
+        }
+        Ok(_) => {}
+        Err(e) => return upload_error(StatusCode::BAD_REQUEST, e),
+    }
+
+    match state.skill_registry.create_skill_from_markdown(&body.markdown).await {
+        Ok(db_skill) => {
+            let skill = db_skill.into_skill();
+            HttpResponse::Ok().json(UploadResponse {
+                success: true,
+                skill: Some((&skill).into()),
+                error: None,
+            })
+        }
+        Err(e) => {
+            log::error!("Failed to update skill: {}", e);
+            upload_error(StatusCode::INTERNAL_SERVER_ERROR, e)
+        }
+    }
+}
+
 async fn delete_skill(
     state: web::Data<AppState>,
     req: HttpRequest,
     path: web::Path<String>,
 ) -> impl Responder {
-    if let Err(resp) = validate_session_from_request(&state, &req) {
+    if let Err(resp) = validate_session_from_request(&state, &req, Scope::Admin).await {
         return resp;
     }
 
     let name = path.into_inner();
 
-    if !state.skill_registry.has_skill(&name) {
+    if !state.skill_registry.has_skill(&name).await {
         return HttpResponse::NotFound().json(OperationResponse {
             success: false,
             message: None,
@@ -446,7 +650,7 @@ async fn delete_skill(
         });
     }
 
-    match state.skill_registry.delete_skill(&name) {
+    match state.skill_registry.delete_skill(&name).await {
         Ok(true) => HttpResponse::Ok().json(OperationResponse {
             success: true,
             message: Some(format!("Skill '{}' deleted", name)),
@@ -476,13 +680,13 @@ async fn get_skill_scripts(
     req: HttpRequest,
     path: web::Path<String>,
 ) -> impl Responder {
-    if let Err(resp) = validate_session_from_request(&state, &req) {
+    if let Err(resp) = validate_session_from_request(&state, &req, Scope::Read).await {
         return resp;
     }
 
     let name = path.into_inner();
 
-    if !state.skill_registry.has_skill(&name) {
+    if !state.skill_registry.has_skill(&name).await {
         return HttpResponse::NotFound().json(ScriptsListResponse {
             success: false,
             scripts: None,
@@ -490,7 +694,7 @@ async fn get_skill_scripts(
         });
     }
 
-    let scripts = state.skill_registry.get_skill_scripts(&name);
+    let scripts = state.skill_registry.get_skill_scripts(&name).await;
     let script_infos: Vec<ScriptInfo> = scripts.iter().map(|s| s.into()).collect();
 
     HttpResponse::Ok().json(ScriptsListResponse {
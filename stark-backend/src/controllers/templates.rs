@@ -0,0 +1,320 @@
+use actix_web::{web, HttpRequest, HttpResponse, Responder};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+
+use crate::agent::templates::{self, is_valid_name};
+use crate::models::{CreatePromptTemplateRequest, PromptTemplate, Scope, UpdatePromptTemplateRequest};
+use crate::AppState;
+
+/// Longest template body accepted, in bytes
+const MAX_BODY_BYTES: usize = 64 * 1024;
+
+/// Validate session token from request
+async fn validate_session_from_request(
+    state: &web::Data<AppState>,
+    req: &HttpRequest,
+    scope: Scope,
+) -> Result<(), HttpResponse> {
+    let token = req
+        .headers()
+        .get("Authorization")
+        .and_then(|h| h.to_str().ok())
+        .map(|s| s.trim_start_matches("Bearer ").to_string());
+
+    let token = match token {
+        Some(t) => t,
+        None => {
+            return Err(HttpResponse::Unauthorized().json(TemplateResponse::error("No authorization token provided")));
+        }
+    };
+
+    match state.db.authorize(&token).await {
+        Ok(Some(scopes)) if scopes.allows(scope) => Ok(()),
+        Ok(Some(_)) => Err(HttpResponse::Forbidden()
+            .json(TemplateResponse::error(format!("Token lacks the {} scope", scope)))),
+        Ok(None) => Err(HttpResponse::Unauthorized().json(TemplateResponse::error("Invalid or expired session"))),
+        Err(e) => {
+            log::error!("Session validation error: {}", e);
+            Err(HttpResponse::InternalServerError().json(TemplateResponse::error("Internal server error")))
+        }
+    }
+}
+
+#[derive(Debug, Default, Deserialize)]
+pub struct RenderRequest {
+    #[serde(default)]
+    pub variables: BTreeMap<String, String>,
+}
+
+#[derive(Serialize, Default)]
+pub struct TemplateResponse {
+    pub success: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub template: Option<PromptTemplate>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub templates: Option<Vec<PromptTemplate>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub rendered: Option<String>,
+    /// Variables the template uses that had no value
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub missing: Option<Vec<String>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+impl TemplateResponse {
+    fn ok() -> Self {
+        TemplateResponse {
+            success: true,
+            ..Default::default()
+        }
+    }
+
+    fn error(error: impl Into<String>) -> Self {
+        TemplateResponse {
+            success: false,
+            error: Some(error.into()),
+            ..Default::default()
+        }
+    }
+}
+
+pub fn config(cfg: &mut web::ServiceConfig) {
+    cfg.service(
+        web::scope("/api/templates")
+            .route("", web::get().to(list_templates))
+            .route("", web::post().to(create_template))
+            .route("/{name}", web::get().to(get_template))
+            .route("/{name}", web::put().to(update_template))
+            .route("/{name}", web::delete().to(delete_template))
+            .route("/{name}/render", web::post().to(render_template)),
+    );
+}
+
+/// Every stored template by name, or the error response to send
+async fn load_templates(state: &web::Data<AppState>) -> Result<HashMap<String, PromptTemplate>, HttpResponse> {
+    match state.db.list_prompt_templates().await {
+        Ok(templates) => Ok(templates.into_iter().map(|t| (t.name.clone(), t)).collect()),
+        Err(e) => {
+            log::error!("Failed to list prompt templates: {}", e);
+            Err(HttpResponse::InternalServerError().json(TemplateResponse::error("Internal server error")))
+        }
+    }
+}
+
+/// Check a template as it would be stored: its fields, and that its includes
+/// resolve against the other stored templates without looping
+fn validate_template(template: &PromptTemplate, mut templates: HashMap<String, PromptTemplate>) -> Result<(), String> {
+    if !is_valid_name(&template.name) {
+        return Err("Name may only contain letters, digits, '-', '_' and '.' (max 64 characters)".to_string());
+    }
+    if template.body.trim().is_empty() {
+        return Err("Body cannot be empty".to_string());
+    }
+    if template.body.len() > MAX_BODY_BYTES {
+        return Err(format!("Body is too long (max {} bytes)", MAX_BODY_BYTES));
+    }
+    if let Some(name) = template.variables.keys().find(|name| !is_valid_name(name)) {
+        return Err(format!("Invalid variable name '{}'", name));
+    }
+    templates.insert(template.name.clone(), template.clone());
+    templates::render_template(&template.name, &BTreeMap::new(), &templates).map(|_| ())
+}
+
+/// Look up a template, or the error response to send
+async fn find_template(state: &web::Data<AppState>, name: &str) -> Result<PromptTemplate, HttpResponse> {
+    match state.db.get_prompt_template(name).await {
+        Ok(Some(template)) => Ok(template),
+        Ok(None) => Err(HttpResponse::NotFound().json(TemplateResponse::error("Template not found"))),
+        Err(e) => {
+            log::error!("Failed to load prompt template {}: {}", name, e);
+            Err(HttpResponse::InternalServerError().json(TemplateResponse::error("Internal server error")))
+        }
+    }
+}
+
+/// List every template
+async fn list_templates(state: web::Data<AppState>, req: HttpRequest) -> impl Responder {
+    if let Err(resp) = validate_session_from_request(&state, &req, Scope::Read).await {
+        return resp;
+    }
+
+    match state.db.list_prompt_templates().await {
+        Ok(templates) => HttpResponse::Ok().json(TemplateResponse {
+            templates: Some(templates),
+            ..TemplateResponse::ok()
+        }),
+        Err(e) => {
+            log::error!("Failed to list prompt templates: {}", e);
+            HttpResponse::InternalServerError().json(TemplateResponse::error("Internal server error"))
+        }
+    }
+}
+
+/// Create a template
+async fn create_template(
+    state: web::Data<AppState>,
+    req: HttpRequest,
+    body: web::Json<CreatePromptTemplateRequest>,
+) -> impl Responder {
+    // Templates end up in schedule and webhook prompts, which run the exec tools unattended
+    if let Err(resp) = validate_session_from_request(&state, &req, Scope::ToolsExec).await {
+        return resp;
+    }
+
+    let templates = match load_templates(&state).await {
+        Ok(templates) => templates,
+        Err(resp) => return resp,
+    };
+    if templates.contains_key(&body.name) {
+        return HttpResponse::Conflict()
+            .json(TemplateResponse::error(format!("A template named '{}' already exists", body.name)));
+    }
+    let candidate = PromptTemplate {
+        id: 0,
+        name: body.name.clone(),
+        description: body.description.clone(),
+        body: body.body.clone(),
+        variables: body.variables.clone(),
+        created_at: String::new(),
+        updated_at: String::new(),
+    };
+    if let Err(e) = validate_template(&candidate, templates) {
+        return HttpResponse::BadRequest().json(TemplateResponse::error(e));
+    }
+
+    match state.db.create_prompt_template(&body).await {
+        Ok(template) => {
+            log::info!("[TEMPLATES] Created '{}'", template.name);
+            HttpResponse::Created().json(TemplateResponse {
+                template: Some(template),
+                ..TemplateResponse::ok()
+            })
+        }
+        Err(e) => {
+            log::error!("Failed to create prompt template: {}", e);
+            HttpResponse::InternalServerError().json(TemplateResponse::error("Failed to create template"))
+        }
+    }
+}
+
+/// Get a template by name
+async fn get_template(state: web::Data<AppState>, req: HttpRequest, path: web::Path<String>) -> impl Responder {
+    if let Err(resp) = validate_session_from_request(&state, &req, Scope::Read).await {
+        return resp;
+    }
+
+    match find_template(&state, &path.into_inner()).await {
+        Ok(template) => HttpResponse::Ok().json(TemplateResponse {
+            template: Some(template),
+            ..TemplateResponse::ok()
+        }),
+        Err(resp) => resp,
+    }
+}
+
+/// Update a template's description, body or default variables
+async fn update_template(
+    state: web::Data<AppState>,
+    req: HttpRequest,
+    path: web::Path<String>,
+    body: web::Json<UpdatePromptTemplateRequest>,
+) -> impl Responder {
+    if let Err(resp) = validate_session_from_request(&state, &req, Scope::ToolsExec).await {
+        return resp;
+    }
+
+    let mut templates = match load_templates(&state).await {
+        Ok(templates) => templates,
+        Err(resp) => return resp,
+    };
+    let Some(mut template) = templates.remove(&path.into_inner()) else {
+        return HttpResponse::NotFound().json(TemplateResponse::error("Template not found"));
+    };
+
+    let body = body.into_inner();
+    if let Some(description) = body.description {
+        template.description = description;
+    }
+    if let Some(text) = body.body {
+        template.body = text;
+    }
+    if let Some(variables) = body.variables {
+        template.variables = variables;
+    }
+    if let Err(e) = validate_template(&template, templates) {
+        return HttpResponse::BadRequest().json(TemplateResponse::error(e));
+    }
+
+    match state.db.save_prompt_template(&template).await {
+        Ok(true) => match find_template(&state, &template.name).await {
+            Ok(template) => HttpResponse::Ok().json(TemplateResponse {
+                template: Some(template),
+                ..TemplateResponse::ok()
+            }),
+            Err(resp) => resp,
+        },
+        Ok(false) => HttpResponse::NotFound().json(TemplateResponse::error("Template not found")),
+        Err(e) => {
+            log::error!("Failed to update prompt template {}: {}", template.name, e);
+            HttpResponse::InternalServerError().json(TemplateResponse::error("Failed to update template"))
+        }
+    }
+}
+
+/// Delete a template. Prompts that still include it fail to render until it
+/// is recreated.
+async fn delete_template(state: web::Data<AppState>, req: HttpRequest, path: web::Path<String>) -> impl Responder {
+    if let Err(resp) = validate_session_from_request(&state, &req, Scope::ToolsExec).await {
+        return resp;
+    }
+
+    let name = path.into_inner();
+    match state.db.delete_prompt_template(&name).await {
+        Ok(true) => HttpResponse::Ok().json(TemplateResponse::ok()),
+        Ok(false) => HttpResponse::NotFound().json(TemplateResponse::error("Template not found")),
+        Err(e) => {
+            log::error!("Failed to delete prompt template {}: {}", name, e);
+            HttpResponse::InternalServerError().json(TemplateResponse::error("Failed to delete template"))
+        }
+    }
+}
+
+/// Render a template with the given variables over its defaults. Every
+/// variable it uses must have a value.
+async fn render_template(
+    state: web::Data<AppState>,
+    req: HttpRequest,
+    path: web::Path<String>,
+    body: Option<web::Json<RenderRequest>>,
+) -> impl Responder {
+    if let Err(resp) = validate_session_from_request(&state, &req, Scope::Read).await {
+        return resp;
+    }
+
+    let name = path.into_inner();
+    let templates = match load_templates(&state).await {
+        Ok(templates) => templates,
+        Err(resp) => return resp,
+    };
+    if !templates.contains_key(&name) {
+        return HttpResponse::NotFound().json(TemplateResponse::error("Template not found"));
+    }
+    let variables = body.map(|b| b.into_inner().variables).unwrap_or_default();
+
+    match templates::render_template(&name, &variables, &templates) {
+        Ok(rendered) if rendered.missing.is_empty() => HttpResponse::Ok().json(TemplateResponse {
+            rendered: Some(rendered.text),
+            ..TemplateResponse::ok()
+        }),
+        Ok(rendered) => {
+            let missing: Vec<String> = rendered.missing.into_iter().collect();
+            HttpResponse::BadRequest().json(TemplateResponse {
+                error: Some(format!("Missing values for: {}", missing.join(", "))),
+                missing: Some(missing),
+                ..TemplateResponse::default()
+            })
+        }
+        Err(e) => HttpResponse::BadRequest().json(TemplateResponse::error(e)),
+    }
+}
@@ -2,6 +2,7 @@ use actix_web::{web, HttpRequest, HttpResponse, Responder};
 use serde::{Deserialize, Serialize};
 
 use crate::tools::{ToolConfig, ToolDefinition, ToolExecution, ToolGroup, ToolProfile};
+use crate::models::Scope;
 use crate::AppState;
 
 #[derive(Serialize)]
@@ -32,6 +33,23 @@ pub struct GroupInfo {
     pub key: String,
     pub label: String,
     pub description: String,
+    /// False when an operator has switched the whole group off
+    pub enabled: bool,
+    pub tools: Vec<String>,
+}
+
+#[derive(Serialize)]
+pub struct GroupResponse {
+    pub success: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub group: Option<GroupInfo>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+#[derive(Deserialize)]
+pub struct UpdateGroupRequest {
+    pub enabled: bool,
 }
 
 #[derive(Serialize)]
@@ -118,6 +136,8 @@ pub fn config(cfg: &mut web::ServiceConfig) {
         web::scope("/api/tools")
             .route("", web::get().to(list_tools))
             .route("/groups", web::get().to(list_groups))
+            .route("/groups/{group}", web::get().to(get_group))
+            .route("/groups/{group}", web::put().to(update_group))
             .route("/profiles", web::get().to(list_profiles))
             .route("/config", web::get().to(get_global_config))
             .route("/config", web::put().to(update_global_config))
@@ -127,9 +147,10 @@ pub fn config(cfg: &mut web::ServiceConfig) {
     );
 }
 
-fn validate_session_from_request(
+async fn validate_session_from_request(
     state: &web::Data<AppState>,
     req: &HttpRequest,
+    scope: Scope,
 ) -> Result<(), HttpResponse> {
     let token = req
         .headers()
@@ -148,8 +169,13 @@ fn validate_session_from_request(
         }
     };
 
-    match state.db.validate_session(&token) {
-        Ok(Some(_)) => Ok(()),
+    match state.db.authorize(&token).await {
+        Ok(Some(scopes)) if scopes.allows(scope) => Ok(()),
+        Ok(Some(_)) => Err(HttpResponse::Forbidden().json(ToolsListResponse {
+            success: false,
+            tools: None,
+            error: Some(format!("Token lacks the {} scope", scope)),
+        })),
         Ok(None) => Err(HttpResponse::Unauthorized().json(ToolsListResponse {
             success: false,
             tools: None,
@@ -167,11 +193,11 @@ fn validate_session_from_request(
 }
 
 async fn list_tools(state: web::Data<AppState>, req: HttpRequest) -> impl Responder {
-    if let Err(resp) = validate_session_from_request(&state, &req) {
+    if let Err(resp) = validate_session_from_request(&state, &req, Scope::Read).await {
         return resp;
     }
 
-    let tool_config = state.db.get_effective_tool_config(None).unwrap_or_default();
+    let tool_config = state.db.get_effective_tool_config(None).await.unwrap_or_default();
 
     let tools: Vec<ToolInfo> = state
         .tool_registry
@@ -184,7 +210,8 @@ async fn list_tools(state: web::Data<AppState>, req: HttpRequest) -> impl Respon
                 name: def.name.clone(),
                 description: def.description.clone(),
                 group: group.as_str().to_string(),
-                enabled: tool_config.is_tool_allowed(&def.name, group),
+                enabled: state.tool_registry.is_group_enabled(group)
+                    && tool_config.is_tool_allowed(&def.name, group),
             }
         })
         .collect();
@@ -196,15 +223,29 @@ async fn list_tools(state: web::Data<AppState>, req: HttpRequest) -> impl Respon
     })
 }
 
-/// List all tool groups with labels and descriptions
-async fn list_groups(_state: web::Data<AppState>, _req: HttpRequest) -> impl Responder {
+fn group_info(state: &web::Data<AppState>, group: ToolGroup) -> GroupInfo {
+    let mut tools: Vec<String> = state
+        .tool_registry
+        .list_group(group)
+        .iter()
+        .map(|tool| tool.name())
+        .collect();
+    tools.sort();
+
+    GroupInfo {
+        key: group.as_str().to_string(),
+        label: group.label().to_string(),
+        description: group.description().to_string(),
+        enabled: state.tool_registry.is_group_enabled(group),
+        tools,
+    }
+}
+
+/// List all tool groups with labels, descriptions and their tools
+async fn list_groups(state: web::Data<AppState>, _req: HttpRequest) -> impl Responder {
     let groups: Vec<GroupInfo> = ToolGroup::all()
         .into_iter()
-        .map(|g| GroupInfo {
-            key: g.as_str().to_string(),
-            label: g.label().to_string(),
-            description: g.description().to_string(),
-        })
+        .map(|g| group_info(&state, g))
         .collect();
 
     HttpResponse::Ok().json(GroupsListResponse {
@@ -213,6 +254,79 @@ async fn list_groups(_state: web::Data<AppState>, _req: HttpRequest) -> impl Res
     })
 }
 
+async fn get_group(
+    state: web::Data<AppState>,
+    req: HttpRequest,
+    path: web::Path<String>,
+) -> impl Responder {
+    if let Err(resp) = validate_session_from_request(&state, &req, Scope::Read).await {
+        return resp;
+    }
+
+    let key = path.into_inner();
+    match ToolGroup::from_str(&key) {
+        Some(group) => HttpResponse::Ok().json(GroupResponse {
+            success: true,
+            group: Some(group_info(&state, group)),
+            error: None,
+        }),
+        None => HttpResponse::NotFound().json(GroupResponse {
+            success: false,
+            group: None,
+            error: Some(format!("Unknown tool group: {}", key)),
+        }),
+    }
+}
+
+/// Enable or disable a whole tool group for every channel
+async fn update_group(
+    state: web::Data<AppState>,
+    req: HttpRequest,
+    path: web::Path<String>,
+    body: web::Json<UpdateGroupRequest>,
+) -> impl Responder {
+    if let Err(resp) = validate_session_from_request(&state, &req, Scope::Admin).await {
+        return resp;
+    }
+
+    let key = path.into_inner();
+    let group = match ToolGroup::from_str(&key) {
+        Some(g) => g,
+        None => {
+            return HttpResponse::NotFound().json(GroupResponse {
+                success: false,
+                group: None,
+                error: Some(format!("Unknown tool group: {}", key)),
+            });
+        }
+    };
+
+    // The agent loop depends on System tools (task completion, asking the user)
+    if group == ToolGroup::System && !body.enabled {
+        return HttpResponse::BadRequest().json(GroupResponse {
+            success: false,
+            group: None,
+            error: Some("System tools cannot be disabled".to_string()),
+        });
+    }
+
+    if let Err(e) = state.db.set_tool_group_enabled(group, body.enabled).await {
+        log::error!("Failed to save tool group setting: {}", e);
+        return HttpResponse::InternalServerError().json(GroupResponse {
+            success: false,
+            group: None,
+            error: Some("Failed to save tool group setting".to_string()),
+        });
+    }
+    state.tool_registry.set_group_enabled(group, body.enabled);
+
+    HttpResponse::Ok().json(GroupResponse {
+        success: true,
+        group: Some(group_info(&state, group)),
+        error: None,
+    })
+}
+
 async fn list_profiles(_state: web::Data<AppState>, _req: HttpRequest) -> impl Responder {
     let profiles = vec![
         ProfileInfo {
@@ -294,11 +408,11 @@ async fn list_profiles(_state: web::Data<AppState>, _req: HttpRequest) -> impl R
 }
 
 async fn get_global_config(state: web::Data<AppState>, req: HttpRequest) -> impl Responder {
-    if let Err(resp) = validate_session_from_request(&state, &req) {
+    if let Err(resp) = validate_session_from_request(&state, &req, Scope::Read).await {
         return resp;
     }
 
-    let config = state.db.get_effective_tool_config(None).unwrap_or_default();
+    let config = state.db.get_effective_tool_config(None).await.unwrap_or_default();
 
     HttpResponse::Ok().json(ConfigResponse {
         success: true,
@@ -312,11 +426,11 @@ async fn update_global_config(
     req: HttpRequest,
     body: web::Json<UpdateConfigRequest>,
 ) -> impl Responder {
-    if let Err(resp) = validate_session_from_request(&state, &req) {
+    if let Err(resp) = validate_session_from_request(&state, &req, Scope::Admin).await {
         return resp;
     }
 
-    let mut config = state.db.get_effective_tool_config(None).unwrap_or_default();
+    let mut config = state.db.get_effective_tool_config(None).await.unwrap_or_default();
     config.channel_id = None; // Ensure it's global
 
     // Update fields if provided
@@ -348,7 +462,7 @@ async fn update_global_config(
         config.denied_groups = denied_groups.clone();
     }
 
-    match state.db.save_tool_config(&config) {
+    match state.db.save_tool_config(&config).await {
         Ok(_) => HttpResponse::Ok().json(ConfigResponse {
             success: true,
             config: Some(config.into()),
@@ -370,14 +484,14 @@ async fn get_channel_config(
     req: HttpRequest,
     path: web::Path<i64>,
 ) -> impl Responder {
-    if let Err(resp) = validate_session_from_request(&state, &req) {
+    if let Err(resp) = validate_session_from_request(&state, &req, Scope::Read).await {
         return resp;
     }
 
     let channel_id = path.into_inner();
     let config = state
         .db
-        .get_effective_tool_config(Some(channel_id))
+        .get_effective_tool_config(Some(channel_id)).await
         .unwrap_or_default();
 
     HttpResponse::Ok().json(ConfigResponse {
@@ -393,7 +507,7 @@ async fn update_channel_config(
     path: web::Path<i64>,
     body: web::Json<UpdateConfigRequest>,
 ) -> impl Responder {
-    if let Err(resp) = validate_session_from_request(&state, &req) {
+    if let Err(resp) = validate_session_from_request(&state, &req, Scope::Admin).await {
         return resp;
     }
 
@@ -402,7 +516,7 @@ async fn update_channel_config(
     // Start with existing config or default
     let mut config = state
         .db
-        .get_channel_tool_config(channel_id)
+        .get_channel_tool_config(channel_id).await
         .ok()
         .flatten()
         .unwrap_or_else(|| {
@@ -442,7 +556,7 @@ async fn update_channel_config(
         config.denied_groups = denied_groups.clone();
     }
 
-    match state.db.save_tool_config(&config) {
+    match state.db.save_tool_config(&config).await {
         Ok(_) => HttpResponse::Ok().json(ConfigResponse {
             success: true,
             config: Some(config.into()),
@@ -464,7 +578,7 @@ async fn get_history(
     req: HttpRequest,
     query: web::Query<HistoryQuery>,
 ) -> impl Responder {
-    if let Err(resp) = validate_session_from_request(&state, &req) {
+    if let Err(resp) = validate_session_from_request(&state, &req, Scope::Read).await {
         return resp;
     }
 
@@ -472,9 +586,9 @@ async fn get_history(
     let offset = query.offset.unwrap_or(0);
 
     let executions = if let Some(channel_id) = query.channel_id {
-        state.db.get_tool_execution_history(channel_id, limit, offset)
+        state.db.get_tool_execution_history(channel_id, limit, offset).await
     } else {
-        state.db.get_all_tool_execution_history(limit, offset)
+        state.db.get_all_tool_execution_history(limit, offset).await
     };
 
     match executions {
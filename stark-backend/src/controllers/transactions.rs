@@ -0,0 +1,181 @@
+//! On-chain transaction audit trail API endpoints
+
+use actix_web::{web, HttpRequest, HttpResponse, Responder};
+use serde::Serialize;
+
+use crate::models::{Caller, Scope, Transaction, TransactionKind, TransactionQuery, TransactionStatus};
+use crate::AppState;
+
+/// Validate session token from request
+async fn validate_session_from_request(
+    state: &web::Data<AppState>,
+    req: &HttpRequest,
+    scope: Scope,
+) -> Result<Caller, HttpResponse> {
+    let token = req
+        .headers()
+        .get("Authorization")
+        .and_then(|h| h.to_str().ok())
+        .map(|s| s.trim_start_matches("Bearer ").to_string());
+
+    let token = match token {
+        Some(t) => t,
+        None => {
+            return Err(HttpResponse::Unauthorized().json(serde_json::json!({
+                "error": "No authorization token provided"
+            })));
+        }
+    };
+
+    match state.db.authenticate(&token).await {
+        Ok(Some(caller)) if caller.scopes.allows(scope) => Ok(caller),
+        Ok(Some(_)) => Err(HttpResponse::Forbidden().json(serde_json::json!({
+            "error": format!("Token lacks the {} scope", scope)
+        }))),
+        Ok(None) => Err(HttpResponse::Unauthorized().json(serde_json::json!({
+            "error": "Invalid or expired session"
+        }))),
+        Err(e) => {
+            log::error!("Session validation error: {}", e);
+            Err(HttpResponse::InternalServerError().json(serde_json::json!({
+                "error": "Internal server error"
+            })))
+        }
+    }
+}
+
+/// Whether `caller` may see `transaction`: admins see all of them, other users
+/// only those from their own chat sessions
+async fn owns_transaction(data: &web::Data<AppState>, caller: &Caller, transaction: &Transaction) -> bool {
+    if caller.is_admin() {
+        return true;
+    }
+    let Some(session_id) = transaction.session_id else {
+        return false;
+    };
+    matches!(
+        data.db.get_chat_session(session_id).await,
+        Ok(Some(session)) if caller.can_access(session.user_id)
+    )
+}
+
+#[derive(Debug, Serialize)]
+struct TransactionListResponse {
+    success: bool,
+    transactions: Vec<Transaction>,
+    total: i64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+struct TransactionResponse {
+    success: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    transaction: Option<Transaction>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<String>,
+}
+
+/// Audited on-chain actions, newest first; users other than admins only see
+/// their own chat sessions' actions.
+/// Filters: kind, status, network, session_id, channel_id, limit, offset.
+async fn list_transactions(
+    data: web::Data<AppState>,
+    req: HttpRequest,
+    query: web::Query<TransactionQuery>,
+) -> impl Responder {
+    let caller = match validate_session_from_request(&data, &req, Scope::Read).await {
+        Ok(caller) => caller,
+        Err(resp) => return resp,
+    };
+
+    if let Some(kind) = &query.kind
+        && TransactionKind::from_str(kind).is_none()
+    {
+        return HttpResponse::BadRequest().json(TransactionListResponse {
+            success: false,
+            transactions: vec![],
+            total: 0,
+            error: Some(format!(
+                "Unknown kind '{}'. Use swap, transfer, contract_call or x402_payment",
+                kind
+            )),
+        });
+    }
+    if let Some(status) = &query.status
+        && TransactionStatus::from_str(status).is_none()
+    {
+        return HttpResponse::BadRequest().json(TransactionListResponse {
+            success: false,
+            transactions: vec![],
+            total: 0,
+            error: Some(format!(
+                "Unknown status '{}'. Use pending, confirmed, reverted or failed",
+                status
+            )),
+        });
+    }
+
+    match data.db.list_transactions(&query, caller.owner_filter()).await {
+        Ok((transactions, total)) => HttpResponse::Ok().json(TransactionListResponse {
+            success: true,
+            transactions,
+            total,
+            error: None,
+        }),
+        Err(e) => {
+            log::error!("Failed to list transactions: {}", e);
+            HttpResponse::InternalServerError().json(TransactionListResponse {
+                success: false,
+                transactions: vec![],
+                total: 0,
+                error: Some(format!("Database error: {}", e)),
+            })
+        }
+    }
+}
+
+/// A single audited on-chain action
+async fn get_transaction(
+    data: web::Data<AppState>,
+    req: HttpRequest,
+    path: web::Path<i64>,
+) -> impl Responder {
+    let caller = match validate_session_from_request(&data, &req, Scope::Read).await {
+        Ok(caller) => caller,
+        Err(resp) => return resp,
+    };
+
+    let id = path.into_inner();
+    match data.db.get_transaction(id).await {
+        Ok(Some(transaction)) if owns_transaction(&data, &caller, &transaction).await => {
+            HttpResponse::Ok().json(TransactionResponse {
+                success: true,
+                transaction: Some(transaction),
+                error: None,
+            })
+        }
+        Ok(_) => HttpResponse::NotFound().json(TransactionResponse {
+            success: false,
+            transaction: None,
+            error: Some("Transaction not found".to_string()),
+        }),
+        Err(e) => {
+            log::error!("Failed to load transaction {}: {}", id, e);
+            HttpResponse::InternalServerError().json(TransactionResponse {
+                success: false,
+                transaction: None,
+                error: Some(format!("Database error: {}", e)),
+            })
+        }
+    }
+}
+
+pub fn config(cfg: &mut web::ServiceConfig) {
+    cfg.service(
+        web::scope("/api/transactions")
+            .route("", web::get().to(list_transactions))
+            .route("/{id}", web::get().to(get_transaction)),
+    );
+}
@@ -0,0 +1,274 @@
+use actix_web::{web, HttpRequest, HttpResponse, Responder};
+use serde::{Deserialize, Serialize};
+
+use crate::ai::budget::SessionBudget;
+use crate::models::{ApiKeyUsage, Caller, DailyUsage, Scope, SessionUsage, UsageTotals};
+use crate::AppState;
+
+/// Default reporting window in days
+const DEFAULT_USAGE_DAYS: i64 = 30;
+/// Longest reporting window accepted
+const MAX_USAGE_DAYS: i64 = 365;
+/// Default number of sessions listed
+const DEFAULT_SESSION_LIMIT: i64 = 50;
+
+/// Validate session token from request
+async fn validate_session_from_request(
+    state: &web::Data<AppState>,
+    req: &HttpRequest,
+    scope: Scope,
+) -> Result<Caller, HttpResponse> {
+    let token = req
+        .headers()
+        .get("Authorization")
+        .and_then(|h| h.to_str().ok())
+        .map(|s| s.trim_start_matches("Bearer ").to_string());
+
+    let token = match token {
+        Some(t) => t,
+        None => {
+            return Err(HttpResponse::Unauthorized().json(serde_json::json!({
+                "error": "No authorization token provided"
+            })));
+        }
+    };
+
+    match state.db.authenticate(&token).await {
+        Ok(Some(caller)) if caller.scopes.allows(scope) => Ok(caller),
+        Ok(Some(_)) => Err(HttpResponse::Forbidden().json(serde_json::json!({
+            "error": format!("Token lacks the {} scope", scope)
+        }))),
+        Ok(None) => Err(HttpResponse::Unauthorized().json(serde_json::json!({
+            "error": "Invalid or expired session"
+        }))),
+        Err(e) => {
+            log::error!("Session validation error: {}", e);
+            Err(HttpResponse::InternalServerError().json(serde_json::json!({
+                "error": "Internal server error"
+            })))
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct UsageQuery {
+    days: Option<i64>,
+    limit: Option<i64>,
+}
+
+#[derive(Debug, Serialize)]
+struct UsageResponse {
+    success: bool,
+    days: i64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    totals: Option<UsageTotals>,
+    by_day: Vec<DailyUsage>,
+    by_session: Vec<SessionUsage>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct KeyUsageQuery {
+    days: Option<i64>,
+}
+
+#[derive(Debug, Serialize)]
+struct KeyUsageResponse {
+    success: bool,
+    days: i64,
+    keys: Vec<ApiKeyUsage>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<String>,
+}
+
+/// Limits from the active agent profile, reported alongside a session's usage
+#[derive(Debug, Serialize)]
+struct SessionBudgetInfo {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    max_tokens: Option<i64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    max_cost_usd: Option<f64>,
+    exhausted: bool,
+}
+
+#[derive(Debug, Serialize)]
+struct SessionUsageResponse {
+    success: bool,
+    session_id: i64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    totals: Option<UsageTotals>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    budget: Option<SessionBudgetInfo>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<String>,
+}
+
+/// Usage totals, per day and per session over the last `days` days. Users
+/// other than admins only see their own sessions' usage.
+async fn get_usage(
+    data: web::Data<AppState>,
+    req: HttpRequest,
+    query: web::Query<UsageQuery>,
+) -> impl Responder {
+    let caller = match validate_session_from_request(&data, &req, Scope::Read).await {
+        Ok(caller) => caller,
+        Err(resp) => return resp,
+    };
+    let owner = caller.owner_filter();
+
+    let days = query.days.unwrap_or(DEFAULT_USAGE_DAYS).clamp(1, MAX_USAGE_DAYS);
+    let limit = query.limit.unwrap_or(DEFAULT_SESSION_LIMIT).clamp(1, 500);
+
+    let result = async {
+        let totals = data.db.get_usage_totals(days, owner).await?;
+        let by_day = data.db.list_usage_by_day(days, owner).await?;
+        let by_session = data.db.list_usage_by_session(days, limit, owner).await?;
+        Ok::<_, rusqlite::Error>((totals, by_day, by_session))
+    }
+    .await;
+
+    match result {
+        Ok((totals, by_day, by_session)) => HttpResponse::Ok().json(UsageResponse {
+            success: true,
+            days,
+            totals: Some(totals),
+            by_day,
+            by_session,
+            error: None,
+        }),
+        Err(e) => {
+            log::error!("Failed to load usage: {}", e);
+            HttpResponse::InternalServerError().json(UsageResponse {
+                success: false,
+                days,
+                totals: None,
+                by_day: vec![],
+                by_session: vec![],
+                error: Some(format!("Database error: {}", e)),
+            })
+        }
+    }
+}
+
+/// Lifetime usage of one chat session, with the session budget it counts against
+async fn get_session_usage(
+    data: web::Data<AppState>,
+    req: HttpRequest,
+    path: web::Path<i64>,
+) -> impl Responder {
+    let caller = match validate_session_from_request(&data, &req, Scope::Read).await {
+        Ok(caller) => caller,
+        Err(resp) => return resp,
+    };
+
+    let session_id = path.into_inner();
+    match data.db.get_chat_session(session_id).await {
+        Ok(Some(session)) if caller.can_access(session.user_id) => {}
+        Ok(_) => {
+            return HttpResponse::NotFound().json(SessionUsageResponse {
+                success: false,
+                session_id,
+                totals: None,
+                budget: None,
+                error: Some("Session not found".to_string()),
+            });
+        }
+        Err(e) => {
+            log::error!("Failed to load session {}: {}", session_id, e);
+            return HttpResponse::InternalServerError().json(SessionUsageResponse {
+                success: false,
+                session_id,
+                totals: None,
+                budget: None,
+                error: Some(format!("Database error: {}", e)),
+            });
+        }
+    }
+    let totals = match data.db.get_session_usage(session_id).await {
+        Ok(totals) => totals,
+        Err(e) => {
+            log::error!("Failed to load usage for session {}: {}", session_id, e);
+            return HttpResponse::InternalServerError().json(SessionUsageResponse {
+                success: false,
+                session_id,
+                totals: None,
+                budget: None,
+                error: Some(format!("Database error: {}", e)),
+            });
+        }
+    };
+
+    let settings = data
+        .db
+        .get_active_agent_settings().await
+        .ok()
+        .flatten()
+        .unwrap_or_default();
+    let session_budget = SessionBudget::from_settings(&settings);
+    let budget = (!session_budget.is_unlimited()).then(|| SessionBudgetInfo {
+        max_tokens: session_budget.max_tokens,
+        max_cost_usd: session_budget.max_cost_usd,
+        exhausted: session_budget.exhausted_reason(&totals).is_some(),
+    });
+
+    HttpResponse::Ok().json(SessionUsageResponse {
+        success: true,
+        session_id,
+        totals: Some(totals),
+        budget,
+        error: None,
+    })
+}
+
+/// Spend on each of the caller's own API keys over the last `days` days.
+/// Requests that ran on the instance's provider key aren't counted.
+async fn get_key_usage(
+    data: web::Data<AppState>,
+    req: HttpRequest,
+    query: web::Query<KeyUsageQuery>,
+) -> impl Responder {
+    let caller = match validate_session_from_request(&data, &req, Scope::Read).await {
+        Ok(caller) => caller,
+        Err(resp) => return resp,
+    };
+
+    let days = query.days.unwrap_or(DEFAULT_USAGE_DAYS).clamp(1, MAX_USAGE_DAYS);
+
+    let result = async {
+        let mut keys = Vec::new();
+        for key in data.db.list_api_keys_owned_by(Some(caller.user.id)).await? {
+            let totals = data.db.get_api_key_usage(key.id, days).await?;
+            keys.push(ApiKeyUsage { key: key.to_response(), totals });
+        }
+        Ok::<_, crate::db::DbError>(keys)
+    }
+    .await;
+
+    match result {
+        Ok(keys) => HttpResponse::Ok().json(KeyUsageResponse {
+            success: true,
+            days,
+            keys,
+            error: None,
+        }),
+        Err(e) => {
+            log::error!("Failed to load API key usage: {}", e);
+            HttpResponse::InternalServerError().json(KeyUsageResponse {
+                success: false,
+                days,
+                keys: vec![],
+                error: Some(format!("Database error: {}", e)),
+            })
+        }
+    }
+}
+
+pub fn config(cfg: &mut web::ServiceConfig) {
+    cfg.service(
+        web::scope("/api/usage")
+            .route("", web::get().to(get_usage))
+            .route("/keys", web::get().to(get_key_usage))
+            .route("/sessions/{id}", web::get().to(get_session_usage)),
+    );
+}
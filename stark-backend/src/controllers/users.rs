@@ -0,0 +1,209 @@
+use actix_web::{web, HttpRequest, HttpResponse, Responder};
+use serde::Serialize;
+
+use crate::models::{Caller, CreateUserRequest, Scope, UpdateUserRequest, User, UserRole};
+use crate::AppState;
+
+/// Validate session token from request
+async fn validate_session_from_request(
+    state: &web::Data<AppState>,
+    req: &HttpRequest,
+    scope: Scope,
+) -> Result<Caller, HttpResponse> {
+    let token = req
+        .headers()
+        .get("Authorization")
+        .and_then(|h| h.to_str().ok())
+        .map(|s| s.trim_start_matches("Bearer ").to_string());
+
+    let token = match token {
+        Some(t) => t,
+        None => {
+            return Err(HttpResponse::Unauthorized().json(UserResponse::error("No authorization token provided")));
+        }
+    };
+
+    match state.db.authenticate(&token).await {
+        Ok(Some(caller)) if caller.scopes.allows(scope) => Ok(caller),
+        Ok(Some(_)) => Err(HttpResponse::Forbidden()
+            .json(UserResponse::error(format!("Token lacks the {} scope", scope)))),
+        Ok(None) => Err(HttpResponse::Unauthorized().json(UserResponse::error("Invalid or expired session"))),
+        Err(e) => {
+            log::error!("Session validation error: {}", e);
+            Err(HttpResponse::InternalServerError().json(UserResponse::error("Internal server error")))
+        }
+    }
+}
+
+#[derive(Serialize, Default)]
+pub struct UserResponse {
+    pub success: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub user: Option<User>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub users: Option<Vec<User>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+impl UserResponse {
+    fn ok() -> Self {
+        UserResponse {
+            success: true,
+            ..Default::default()
+        }
+    }
+
+    fn error(error: impl Into<String>) -> Self {
+        UserResponse {
+            success: false,
+            error: Some(error.into()),
+            ..Default::default()
+        }
+    }
+}
+
+pub fn config(cfg: &mut web::ServiceConfig) {
+    cfg.service(
+        web::scope("/api/users")
+            .route("", web::get().to(list_users))
+            .route("", web::post().to(create_user))
+            .route("/me", web::get().to(get_me))
+            .route("/{id}", web::put().to(update_user))
+            .route("/{id}", web::delete().to(delete_user)),
+    );
+}
+
+/// The user behind the caller's session or token
+async fn get_me(state: web::Data<AppState>, req: HttpRequest) -> impl Responder {
+    match validate_session_from_request(&state, &req, Scope::Read).await {
+        Ok(caller) => HttpResponse::Ok().json(UserResponse {
+            user: Some(caller.user),
+            ..UserResponse::ok()
+        }),
+        Err(resp) => resp,
+    }
+}
+
+/// List every user
+async fn list_users(state: web::Data<AppState>, req: HttpRequest) -> impl Responder {
+    if let Err(resp) = validate_session_from_request(&state, &req, Scope::Admin).await {
+        return resp;
+    }
+
+    match state.db.list_users().await {
+        Ok(users) => HttpResponse::Ok().json(UserResponse {
+            users: Some(users),
+            ..UserResponse::ok()
+        }),
+        Err(e) => {
+            log::error!("Failed to list users: {}", e);
+            HttpResponse::InternalServerError().json(UserResponse::error("Internal server error"))
+        }
+    }
+}
+
+/// Allow a wallet address to sign in
+async fn create_user(
+    state: web::Data<AppState>,
+    req: HttpRequest,
+    body: web::Json<CreateUserRequest>,
+) -> impl Responder {
+    if let Err(resp) = validate_session_from_request(&state, &req, Scope::Admin).await {
+        return resp;
+    }
+
+    let public_address = body.public_address.trim().to_lowercase();
+    if !public_address.starts_with("0x")
+        || public_address.len() != 42
+        || !public_address[2..].chars().all(|c| c.is_ascii_hexdigit())
+    {
+        return HttpResponse::BadRequest().json(UserResponse::error("Invalid public address"));
+    }
+    let name = body.name.as_deref().map(str::trim).filter(|n| !n.is_empty());
+    let role = body.role.unwrap_or(UserRole::Member);
+
+    match state.db.get_user_by_address(&public_address).await {
+        Ok(None) => {}
+        Ok(Some(_)) => {
+            return HttpResponse::Conflict()
+                .json(UserResponse::error(format!("{} is already a user", public_address)));
+        }
+        Err(e) => {
+            log::error!("Failed to look up user {}: {}", public_address, e);
+            return HttpResponse::InternalServerError().json(UserResponse::error("Internal server error"));
+        }
+    }
+
+    match state.db.create_user(&public_address, name, role).await {
+        Ok(user) => {
+            log::info!("[USERS] Added {} as {}", user.public_address, user.role);
+            HttpResponse::Created().json(UserResponse {
+                user: Some(user),
+                ..UserResponse::ok()
+            })
+        }
+        Err(e) => {
+            log::error!("Failed to create user: {}", e);
+            HttpResponse::InternalServerError().json(UserResponse::error("Failed to create user"))
+        }
+    }
+}
+
+/// Change a user's role. Admins can't demote themselves, so there is always
+/// someone left to manage users.
+async fn update_user(
+    state: web::Data<AppState>,
+    req: HttpRequest,
+    path: web::Path<i64>,
+    body: web::Json<UpdateUserRequest>,
+) -> impl Responder {
+    let caller = match validate_session_from_request(&state, &req, Scope::Admin).await {
+        Ok(caller) => caller,
+        Err(resp) => return resp,
+    };
+    let id = path.into_inner();
+    if id == caller.user.id && body.role != UserRole::Admin {
+        return HttpResponse::BadRequest().json(UserResponse::error("You can't remove your own admin role"));
+    }
+
+    match state.db.set_user_role(id, body.role).await {
+        Ok(Some(user)) => {
+            log::info!("[USERS] {} is now {}", user.public_address, user.role);
+            HttpResponse::Ok().json(UserResponse {
+                user: Some(user),
+                ..UserResponse::ok()
+            })
+        }
+        Ok(None) => HttpResponse::NotFound().json(UserResponse::error("User not found")),
+        Err(e) => {
+            log::error!("Failed to update user {}: {}", id, e);
+            HttpResponse::InternalServerError().json(UserResponse::error("Internal server error"))
+        }
+    }
+}
+
+/// Remove a user, signing them out and deleting their API tokens and
+/// personal API keys. Their conversations are kept for admins.
+async fn delete_user(state: web::Data<AppState>, req: HttpRequest, path: web::Path<i64>) -> impl Responder {
+    let caller = match validate_session_from_request(&state, &req, Scope::Admin).await {
+        Ok(caller) => caller,
+        Err(resp) => return resp,
+    };
+    let id = path.into_inner();
+    if id == caller.user.id {
+        return HttpResponse::BadRequest().json(UserResponse::error("You can't delete yourself"));
+    }
+
+    match state.db.delete_user(id).await {
+        Ok(true) => {
+            log::info!("[USERS] Deleted user {}", id);
+            HttpResponse::Ok().json(UserResponse::ok())
+        }
+        Ok(false) => HttpResponse::NotFound().json(UserResponse::error("User not found")),
+        Err(e) => {
+            log::error!("Failed to delete user {}: {}", id, e);
+            HttpResponse::InternalServerError().json(UserResponse::error("Internal server error"))
+        }
+    }
+}
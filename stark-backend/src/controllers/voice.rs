@@ -0,0 +1,227 @@
+use actix_multipart::Multipart;
+use actix_web::{web, HttpRequest, HttpResponse, Responder};
+use futures_util::StreamExt;
+use serde::{Deserialize, Serialize};
+
+use crate::controllers::api_keys::ApiKeyId;
+use crate::integrations::voice::{AudioFormat, AudioUpload, Credentials, SpeechRequest, VoiceConfig, MAX_STT_BYTES};
+use crate::models::Scope;
+use crate::AppState;
+
+/// Validate session token from request
+async fn validate_session_from_request(
+    state: &web::Data<AppState>,
+    req: &HttpRequest,
+    scope: Scope,
+) -> Result<(), HttpResponse> {
+    let token = req
+        .headers()
+        .get("Authorization")
+        .and_then(|h| h.to_str().ok())
+        .map(|s| s.trim_start_matches("Bearer ").to_string());
+
+    let token = match token {
+        Some(t) => t,
+        None => {
+            return Err(HttpResponse::Unauthorized().json(VoiceResponse::error("No authorization token provided")));
+        }
+    };
+
+    match state.db.authorize(&token).await {
+        Ok(Some(scopes)) if scopes.allows(scope) => Ok(()),
+        Ok(Some(_)) => Err(HttpResponse::Forbidden()
+            .json(VoiceResponse::error(format!("Token lacks the {} scope", scope)))),
+        Ok(None) => Err(HttpResponse::Unauthorized().json(VoiceResponse::error("Invalid or expired session"))),
+        Err(e) => {
+            log::error!("Session validation error: {}", e);
+            Err(HttpResponse::InternalServerError().json(VoiceResponse::error("Internal server error")))
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct TtsRequest {
+    pub text: String,
+    #[serde(default)]
+    pub voice: Option<String>,
+    /// mp3 (default), opus, aac, flac, wav or pcm
+    #[serde(default)]
+    pub format: Option<String>,
+    #[serde(default)]
+    pub speed: Option<f32>,
+}
+
+#[derive(Serialize, Default)]
+pub struct VoiceResponse {
+    pub success: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub text: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub language: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+impl VoiceResponse {
+    fn error(error: impl Into<String>) -> Self {
+        VoiceResponse {
+            success: false,
+            error: Some(error.into()),
+            ..Default::default()
+        }
+    }
+}
+
+pub fn config(cfg: &mut web::ServiceConfig) {
+    cfg.route("/api/tts", web::post().to(text_to_speech))
+        .route("/api/stt", web::post().to(speech_to_text));
+}
+
+/// The voice settings and provider keys, or the error response when the
+/// settings are invalid
+async fn voice_config(state: &web::Data<AppState>) -> Result<(VoiceConfig, Credentials), HttpResponse> {
+    let config = VoiceConfig::from_env()
+        .map_err(|e| HttpResponse::ServiceUnavailable().json(VoiceResponse::error(e)))?;
+    let key = |id: ApiKeyId| {
+        let state = state.clone();
+        async move {
+            match state.db.get_api_key(id.as_str()).await {
+                Ok(key) => key.map(|k| k.api_key).filter(|k| !k.is_empty()),
+                Err(e) => {
+                    log::error!("Failed to load {}: {}", id.as_str(), e);
+                    None
+                }
+            }
+        }
+    };
+    let credentials = Credentials {
+        openai_api_key: key(ApiKeyId::OpenaiApiKey).await,
+        elevenlabs_api_key: key(ApiKeyId::ElevenlabsApiKey).await,
+    };
+    Ok((config, credentials))
+}
+
+/// Speak text, streaming the audio back as the provider generates it
+async fn text_to_speech(
+    state: web::Data<AppState>,
+    req: HttpRequest,
+    body: web::Json<TtsRequest>,
+) -> impl Responder {
+    // Speech spends provider credit like chat
+    if let Err(resp) = validate_session_from_request(&state, &req, Scope::Chat).await {
+        return resp;
+    }
+    let (config, credentials) = match voice_config(&state).await {
+        Ok(config) => config,
+        Err(resp) => return resp,
+    };
+
+    let body = body.into_inner();
+    let format = match body.format.as_deref().map(AudioFormat::parse).transpose() {
+        Ok(format) => format.unwrap_or_default(),
+        Err(e) => return HttpResponse::BadRequest().json(VoiceResponse::error(e)),
+    };
+    let request = SpeechRequest {
+        text: body.text,
+        voice: body.voice,
+        format,
+        speed: body.speed,
+    };
+    if let Err(e) = config.check_speech(&request) {
+        return HttpResponse::BadRequest().json(VoiceResponse::error(e));
+    }
+    if let Err(e) = config.check_tts(&credentials) {
+        return HttpResponse::ServiceUnavailable().json(VoiceResponse::error(e));
+    }
+
+    match config.speak(&credentials, &request).await {
+        Ok(audio) => HttpResponse::Ok()
+            .content_type(format.content_type())
+            .streaming(audio.bytes_stream().map(|chunk| chunk.map_err(actix_web::error::ErrorBadGateway))),
+        Err(e) => {
+            log::warn!("Text-to-speech failed: {}", e);
+            HttpResponse::BadGateway().json(VoiceResponse::error(e))
+        }
+    }
+}
+
+/// Transcribe an uploaded audio file
+async fn speech_to_text(state: web::Data<AppState>, req: HttpRequest, payload: Multipart) -> impl Responder {
+    if let Err(resp) = validate_session_from_request(&state, &req, Scope::Chat).await {
+        return resp;
+    }
+    let (config, credentials) = match voice_config(&state).await {
+        Ok(config) => config,
+        Err(resp) => return resp,
+    };
+    if let Err(e) = config.check_stt(&credentials) {
+        return HttpResponse::ServiceUnavailable().json(VoiceResponse::error(e));
+    }
+
+    let audio = match receive_audio(payload).await {
+        Ok(audio) => audio,
+        Err(resp) => return resp,
+    };
+    match config.transcribe(&credentials, audio).await {
+        Ok(transcript) => HttpResponse::Ok().json(VoiceResponse {
+            success: true,
+            text: Some(transcript.text),
+            language: transcript.language,
+            error: None,
+        }),
+        Err(e) => {
+            log::warn!("Speech-to-text failed: {}", e);
+            HttpResponse::BadGateway().json(VoiceResponse::error(e))
+        }
+    }
+}
+
+/// Read the `file`, `language` and `prompt` fields of an upload
+async fn receive_audio(mut payload: Multipart) -> Result<AudioUpload, HttpResponse> {
+    let bad_request = |message: String| HttpResponse::BadRequest().json(VoiceResponse::error(message));
+    let mut audio = AudioUpload::default();
+    let mut has_file = false;
+
+    while let Some(item) = payload.next().await {
+        let mut field = item.map_err(|e| bad_request(format!("Failed to process upload: {}", e)))?;
+        let field_name = field.content_disposition().get_name().unwrap_or_default().to_string();
+        let filename = field.content_disposition().get_filename().map(|f| f.to_string());
+        let content_type = field.content_type().map(|m| m.essence_str().to_string());
+
+        let limit = if field_name == "file" { MAX_STT_BYTES } else { 4096 };
+        let mut value = Vec::new();
+        while let Some(chunk) = field.next().await {
+            let chunk = chunk.map_err(|e| bad_request(format!("Failed to read upload data: {}", e)))?;
+            value.extend_from_slice(&chunk);
+            if value.len() > limit {
+                return Err(if field_name == "file" {
+                    HttpResponse::PayloadTooLarge().json(VoiceResponse::error(format!(
+                        "Audio exceeds the {} MB limit",
+                        MAX_STT_BYTES / (1024 * 1024)
+                    )))
+                } else {
+                    bad_request(format!("Field '{}' is too long", field_name))
+                });
+            }
+        }
+
+        let text = || Some(String::from_utf8_lossy(&value).trim().to_string()).filter(|v| !v.is_empty());
+        match field_name.as_str() {
+            "file" => {
+                // Providers tell formats apart by the file extension
+                audio.file_name = filename.filter(|f| !f.is_empty()).unwrap_or_else(|| "audio.wav".to_string());
+                audio.content_type = content_type.filter(|c| c != "application/octet-stream");
+                audio.bytes = value;
+                has_file = true;
+            }
+            "language" => audio.language = text(),
+            "prompt" => audio.prompt = text(),
+            _ => {}
+        }
+    }
+
+    if !has_file || audio.bytes.is_empty() {
+        return Err(bad_request("A non-empty 'file' field is required".to_string()));
+    }
+    Ok(audio)
+}
@@ -0,0 +1,274 @@
+use actix_web::{web, HttpRequest, HttpResponse, Responder};
+use ethers::types::Address;
+use serde::Serialize;
+use std::str::FromStr;
+
+use crate::models::{CreateWalletRequest, CreatedWallet, Scope, Wallet};
+use crate::wallet::{self, ExternalSigner, LocalSigner, WalletSigner};
+use crate::AppState;
+
+/// Validate session token from request
+async fn validate_session_from_request(
+    state: &web::Data<AppState>,
+    req: &HttpRequest,
+    scope: Scope,
+) -> Result<(), HttpResponse> {
+    let token = req
+        .headers()
+        .get("Authorization")
+        .and_then(|h| h.to_str().ok())
+        .map(|s| s.trim_start_matches("Bearer ").to_string());
+
+    let token = match token {
+        Some(t) => t,
+        None => {
+            return Err(HttpResponse::Unauthorized().json(serde_json::json!({
+                "error": "No authorization token provided"
+            })));
+        }
+    };
+
+    match state.db.authorize(&token).await {
+        Ok(Some(scopes)) if scopes.allows(scope) => Ok(()),
+        Ok(Some(_)) => Err(HttpResponse::Forbidden().json(serde_json::json!({
+            "error": format!("Token lacks the {} scope", scope)
+        }))),
+        Ok(None) => Err(HttpResponse::Unauthorized().json(serde_json::json!({
+            "error": "Invalid or expired session"
+        }))),
+        Err(e) => {
+            log::error!("Session validation error: {}", e);
+            Err(HttpResponse::InternalServerError().json(serde_json::json!({
+                "error": "Internal server error"
+            })))
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+struct WalletsResponse {
+    success: bool,
+    wallets: Vec<Wallet>,
+    /// Address currently signing (the default wallet or the env key)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    active_address: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+struct WalletResponse {
+    success: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    wallet: Option<CreatedWallet>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<String>,
+}
+
+impl WalletResponse {
+    fn error(message: impl Into<String>) -> Self {
+        Self {
+            success: false,
+            wallet: None,
+            error: Some(message.into()),
+        }
+    }
+}
+
+pub fn config(cfg: &mut web::ServiceConfig) {
+    cfg.service(
+        web::scope("/api/wallets")
+            .route("", web::get().to(list_wallets))
+            .route("", web::post().to(create_wallet))
+            .route("/{id}/default", web::post().to(set_default_wallet))
+            .route("/{id}", web::delete().to(delete_wallet)),
+    );
+}
+
+/// Reload the active signer after the default wallet changed
+async fn reactivate(data: &web::Data<AppState>) {
+    match wallet::activate_default(&data.db).await {
+        Ok(Some(w)) => log::info!("Active wallet is now '{}' ({})", w.name, w.address),
+        Ok(None) => log::info!("No default wallet; falling back to BURNER_WALLET_BOT_PRIVATE_KEY"),
+        Err(e) => log::error!("Failed to activate default wallet: {}", e),
+    }
+}
+
+/// List wallets (without private keys)
+async fn list_wallets(data: web::Data<AppState>, req: HttpRequest) -> impl Responder {
+    if let Err(resp) = validate_session_from_request(&data, &req, Scope::Read).await {
+        return resp;
+    }
+
+    let active_address = wallet::active_signer().map(|s| format!("{:?}", s.address()));
+    match data.db.list_wallets().await {
+        Ok(wallets) => HttpResponse::Ok().json(WalletsResponse {
+            success: true,
+            wallets,
+            active_address,
+            error: None,
+        }),
+        Err(e) => {
+            log::error!("Failed to list wallets: {}", e);
+            HttpResponse::InternalServerError().json(WalletsResponse {
+                success: false,
+                wallets: vec![],
+                active_address: None,
+                error: Some(format!("Database error: {}", e)),
+            })
+        }
+    }
+}
+
+/// Import a key, generate one, or connect an external signer. A generated
+/// key is returned in this response only.
+async fn create_wallet(
+    data: web::Data<AppState>,
+    req: HttpRequest,
+    body: web::Json<CreateWalletRequest>,
+) -> impl Responder {
+    if let Err(resp) = validate_session_from_request(&data, &req, Scope::Admin).await {
+        return resp;
+    }
+
+    let name = body.name.trim();
+    if name.is_empty() {
+        return HttpResponse::BadRequest().json(WalletResponse::error("Wallet name is required"));
+    }
+    let modes = [body.private_key.is_some(), body.generate, body.signer_url.is_some()];
+    if modes.iter().filter(|m| **m).count() != 1 {
+        return HttpResponse::BadRequest().json(WalletResponse::error(
+            "Provide exactly one of private_key, generate or signer_url",
+        ));
+    }
+
+    let result = if let Some(url) = body.signer_url.as_deref() {
+        let address = match body.address.as_deref().map(Address::from_str).transpose() {
+            Ok(address) => address,
+            Err(e) => {
+                return HttpResponse::BadRequest()
+                    .json(WalletResponse::error(format!("Invalid address: {}", e)));
+            }
+        };
+        let signer = match ExternalSigner::connect(url, address).await {
+            Ok(signer) => signer,
+            Err(e) => return HttpResponse::BadRequest().json(WalletResponse::error(e)),
+        };
+        data.db
+            .create_external_wallet(name, &format!("{:?}", signer.address()), url)
+            .await
+            .map(|wallet| CreatedWallet { wallet, private_key: None })
+    } else {
+        let signer = match body.private_key.as_deref() {
+            Some(key) => match LocalSigner::new(key) {
+                Ok(signer) => signer,
+                Err(e) => return HttpResponse::BadRequest().json(WalletResponse::error(e)),
+            },
+            None => LocalSigner::generate(),
+        };
+        let private_key = signer.private_key_hex();
+        data.db
+            .create_local_wallet(name, &format!("{:?}", signer.address()), &private_key)
+            .await
+            .map(|wallet| CreatedWallet {
+                wallet,
+                private_key: body.generate.then_some(private_key),
+            })
+    };
+
+    let mut created = match result {
+        Ok(created) => created,
+        Err(crate::db::DbError::Encryption(e)) => {
+            return HttpResponse::BadRequest().json(WalletResponse::error(e));
+        }
+        Err(e) => {
+            log::error!("Failed to create wallet: {}", e);
+            return HttpResponse::InternalServerError()
+                .json(WalletResponse::error(format!("Database error: {}", e)));
+        }
+    };
+    log::info!(
+        "Added {} wallet '{}' ({})",
+        created.wallet.kind.as_str(),
+        created.wallet.name,
+        created.wallet.address
+    );
+
+    if body.make_default && !created.wallet.is_default {
+        if let Err(e) = data.db.set_default_wallet(created.wallet.id).await {
+            log::error!("Failed to set default wallet: {}", e);
+            return HttpResponse::InternalServerError()
+                .json(WalletResponse::error(format!("Database error: {}", e)));
+        }
+        created.wallet.is_default = true;
+    }
+    if created.wallet.is_default {
+        reactivate(&data).await;
+    }
+
+    HttpResponse::Ok().json(WalletResponse {
+        success: true,
+        wallet: Some(created),
+        error: None,
+    })
+}
+
+/// Make a wallet the one that signs
+async fn set_default_wallet(
+    data: web::Data<AppState>,
+    req: HttpRequest,
+    path: web::Path<i64>,
+) -> impl Responder {
+    if let Err(resp) = validate_session_from_request(&data, &req, Scope::Admin).await {
+        return resp;
+    }
+
+    match data.db.set_default_wallet(path.into_inner()).await {
+        Ok(true) => {
+            reactivate(&data).await;
+            HttpResponse::Ok().json(serde_json::json!({ "success": true }))
+        }
+        Ok(false) => HttpResponse::NotFound().json(serde_json::json!({
+            "success": false,
+            "error": "Wallet not found"
+        })),
+        Err(e) => {
+            log::error!("Failed to set default wallet: {}", e);
+            HttpResponse::InternalServerError().json(serde_json::json!({
+                "success": false,
+                "error": format!("Database error: {}", e)
+            }))
+        }
+    }
+}
+
+/// Remove a wallet (and its sealed key)
+async fn delete_wallet(
+    data: web::Data<AppState>,
+    req: HttpRequest,
+    path: web::Path<i64>,
+) -> impl Responder {
+    if let Err(resp) = validate_session_from_request(&data, &req, Scope::Admin).await {
+        return resp;
+    }
+
+    let id = path.into_inner();
+    match data.db.delete_wallet(id).await {
+        Ok(true) => {
+            log::info!("Deleted wallet {}", id);
+            reactivate(&data).await;
+            HttpResponse::Ok().json(serde_json::json!({ "success": true }))
+        }
+        Ok(false) => HttpResponse::NotFound().json(serde_json::json!({
+            "success": false,
+            "error": "Wallet not found"
+        })),
+        Err(e) => {
+            log::error!("Failed to delete wallet: {}", e);
+            HttpResponse::InternalServerError().json(serde_json::json!({
+                "success": false,
+                "error": format!("Database error: {}", e)
+            }))
+        }
+    }
+}
@@ -0,0 +1,485 @@
+use actix_web::{web, HttpRequest, HttpResponse, Responder};
+use chrono::Utc;
+use serde::Serialize;
+
+use crate::agent::templates;
+use crate::agent::webhooks::{event_name, render_prompt, verify_signature};
+use crate::controllers::agent::{resolve_max_iterations, resolve_workspace_name, validate_tool_names};
+use crate::models::{AgentJob, Caller, CreateWebhookRequest, Scope, UpdateWebhookRequest, Webhook};
+use crate::AppState;
+
+/// Validate session token from request
+async fn validate_session_from_request(
+    state: &web::Data<AppState>,
+    req: &HttpRequest,
+    scope: Scope,
+) -> Result<Caller, HttpResponse> {
+    let token = req
+        .headers()
+        .get("Authorization")
+        .and_then(|h| h.to_str().ok())
+        .map(|s| s.trim_start_matches("Bearer ").to_string());
+
+    let token = match token {
+        Some(t) => t,
+        None => {
+            return Err(HttpResponse::Unauthorized().json(WebhookResponse::error(
+                "No authorization token provided",
+            )));
+        }
+    };
+
+    match state.db.authenticate(&token).await {
+        Ok(Some(caller)) if caller.scopes.allows(scope) => Ok(caller),
+        Ok(Some(_)) => Err(HttpResponse::Forbidden().json(WebhookResponse::error(format!(
+            "Token lacks the {} scope",
+            scope
+        )))),
+        Ok(None) => Err(HttpResponse::Unauthorized().json(WebhookResponse::error(
+            "Invalid or expired session",
+        ))),
+        Err(e) => {
+            log::error!("Session validation error: {}", e);
+            Err(HttpResponse::InternalServerError().json(WebhookResponse::error("Internal server error")))
+        }
+    }
+}
+
+#[derive(Serialize, Default)]
+pub struct WebhookResponse {
+    pub success: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub webhook: Option<Webhook>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub webhooks: Option<Vec<Webhook>>,
+    /// Only returned when a secret is created or replaced
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub secret: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub job: Option<AgentJob>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+impl WebhookResponse {
+    fn ok() -> Self {
+        WebhookResponse {
+            success: true,
+            ..Default::default()
+        }
+    }
+
+    fn error(error: impl Into<String>) -> Self {
+        WebhookResponse {
+            success: false,
+            error: Some(error.into()),
+            ..Default::default()
+        }
+    }
+}
+
+pub fn config(cfg: &mut web::ServiceConfig) {
+    cfg.service(
+        web::scope("/api/hooks")
+            .route("", web::get().to(list_webhooks))
+            .route("", web::post().to(create_webhook))
+            // Senders authenticate with the hook's HMAC signature, not a bearer token
+            .route("/{hook_id}", web::post().to(trigger_webhook))
+            .route("/{hook_id}", web::get().to(get_webhook))
+            .route("/{hook_id}", web::put().to(update_webhook))
+            .route("/{hook_id}", web::delete().to(delete_webhook)),
+    );
+}
+
+/// Random 64-char hex signing secret
+fn generate_secret() -> String {
+    use rand::Rng;
+    let bytes: [u8; 32] = rand::thread_rng().r#gen();
+    hex::encode(bytes)
+}
+
+/// Signing secrets shorter than this are rejected
+const MIN_SECRET_LEN: usize = 16;
+
+/// Check the fields a create or update can set, returning the first problem found
+fn validate_webhook(state: &AppState, hook: &Webhook) -> Result<(), String> {
+    if hook.name.trim().is_empty() {
+        return Err("Name cannot be empty".to_string());
+    }
+    if hook.prompt_template.trim().is_empty() {
+        return Err("Prompt template cannot be empty".to_string());
+    }
+    validate_tool_names(state, &hook.tools)
+}
+
+fn validate_secret(secret: &str) -> Result<(), String> {
+    if secret.len() < MIN_SECRET_LEN {
+        return Err(format!("Secret must be at least {} characters", MIN_SECRET_LEN));
+    }
+    Ok(())
+}
+
+/// Look up a webhook the caller may see, or the error response to send
+async fn find_webhook(state: &web::Data<AppState>, caller: &Caller, hook_id: &str) -> Result<Webhook, HttpResponse> {
+    match state.db.get_webhook(hook_id).await {
+        // Other users' hooks look the same as missing ones
+        Ok(Some(hook)) if caller.can_access(hook.user_id) => Ok(hook),
+        Ok(_) => Err(HttpResponse::NotFound().json(WebhookResponse::error("Webhook not found"))),
+        Err(e) => {
+            log::error!("Failed to load webhook {}: {}", hook_id, e);
+            Err(HttpResponse::InternalServerError().json(WebhookResponse::error("Internal server error")))
+        }
+    }
+}
+
+/// Receive a delivery: verify its signature and queue an agent job for it
+async fn trigger_webhook(
+    state: web::Data<AppState>,
+    req: HttpRequest,
+    path: web::Path<String>,
+    body: web::Bytes,
+) -> impl Responder {
+    let hook_id = path.into_inner();
+
+    // Unknown and disabled hooks look the same to the sender
+    let hook = match state.db.get_webhook(&hook_id).await {
+        Ok(Some(hook)) if hook.enabled => hook,
+        Ok(_) => return HttpResponse::NotFound().json(WebhookResponse::error("Webhook not found")),
+        Err(e) => {
+            log::error!("Failed to load webhook {}: {}", hook_id, e);
+            return HttpResponse::InternalServerError().json(WebhookResponse::error("Internal server error"));
+        }
+    };
+    let secret = match state.db.get_webhook_secret(&hook_id).await {
+        Ok(Some(secret)) => secret,
+        Ok(None) => return HttpResponse::NotFound().json(WebhookResponse::error("Webhook not found")),
+        Err(e) => {
+            log::error!("Failed to open secret for webhook {}: {}", hook_id, e);
+            return HttpResponse::InternalServerError().json(WebhookResponse::error("Internal server error"));
+        }
+    };
+
+    if let Err(e) = verify_signature(&secret, req.headers(), &body, Utc::now().timestamp()) {
+        log::warn!("[WEBHOOK] Rejected delivery to '{}': {}", hook.name, e);
+        return HttpResponse::Unauthorized().json(WebhookResponse::error(e));
+    }
+
+    let payload = serde_json::from_slice(&body).ok();
+    let event = event_name(req.headers(), payload.as_ref());
+    let prompt = match templates::templates_for(&state.db, &hook.prompt_template)
+        .await
+        .and_then(|templates| render_prompt(&hook.prompt_template, &hook.name, &event, &body, &templates))
+    {
+        Ok(prompt) => prompt,
+        Err(e) => {
+            log::error!("[WEBHOOK] Failed to render the prompt of '{}': {}", hook.name, e);
+            return HttpResponse::InternalServerError().json(WebhookResponse::error("Failed to render prompt"));
+        }
+    };
+
+    match state.agent_jobs.enqueue(
+        &prompt,
+        &hook.workspace,
+        hook.max_iterations.max(1) as usize,
+        &hook.tools,
+        None,
+        false,
+        None,
+        hook.user_id,
+    ).await {
+        Ok(job) => {
+            log::info!("[WEBHOOK] '{}' ({}) queued job {}", hook.name, event, job.job_id);
+            if let Err(e) = state.db.record_webhook_trigger(&hook_id, &Utc::now().to_rfc3339(), &job.job_id).await {
+                log::error!("Failed to record delivery to webhook {}: {}", hook_id, e);
+            }
+            HttpResponse::Accepted().json(WebhookResponse {
+                job: Some(job),
+                ..WebhookResponse::ok()
+            })
+        }
+        // 503 lets the sender retry against the next instance
+        Err(e) if state.agent_jobs.is_stopping() => HttpResponse::ServiceUnavailable().json(WebhookResponse::error(e)),
+        Err(e) => {
+            log::error!("{}", e);
+            HttpResponse::InternalServerError().json(WebhookResponse::error("Failed to queue job"))
+        }
+    }
+}
+
+/// List webhooks: all of them for admins, otherwise the caller's own
+async fn list_webhooks(state: web::Data<AppState>, req: HttpRequest) -> impl Responder {
+    let caller = match validate_session_from_request(&state, &req, Scope::Read).await {
+        Ok(caller) => caller,
+        Err(resp) => return resp,
+    };
+
+    match state.db.list_webhooks(caller.owner_filter()).await {
+        Ok(hooks) => HttpResponse::Ok().json(WebhookResponse {
+            webhooks: Some(hooks),
+            ..WebhookResponse::ok()
+        }),
+        Err(e) => {
+            log::error!("Failed to list webhooks: {}", e);
+            HttpResponse::InternalServerError().json(WebhookResponse::error("Internal server error"))
+        }
+    }
+}
+
+/// Create a webhook. The response carries the signing secret; it isn't shown again.
+async fn create_webhook(
+    state: web::Data<AppState>,
+    req: HttpRequest,
+    body: web::Json<CreateWebhookRequest>,
+) -> impl Responder {
+    // Deliveries run the exec tools unattended, same as a queued agent job
+    let caller = match validate_session_from_request(&state, &req, Scope::ToolsExec).await {
+        Ok(caller) => caller,
+        Err(resp) => return resp,
+    };
+
+    let hook_id = uuid::Uuid::new_v4().simple().to_string();
+    let requested = body.workspace.clone().unwrap_or_else(|| Webhook::default_workspace(&hook_id));
+    let workspace = match resolve_workspace_name(&caller, Some(&requested)) {
+        Ok(workspace) => workspace,
+        Err(e) => return HttpResponse::BadRequest().json(WebhookResponse::error(e)),
+    };
+    let max_iterations = resolve_max_iterations(body.max_iterations) as i64;
+    let candidate = Webhook {
+        id: 0,
+        hook_id: hook_id.clone(),
+        name: body.name.clone(),
+        prompt_template: body.prompt_template.clone(),
+        tools: body.tools.clone(),
+        workspace,
+        max_iterations,
+        enabled: body.enabled,
+        last_triggered_at: None,
+        last_job_id: None,
+        created_at: String::new(),
+        updated_at: String::new(),
+        user_id: Some(caller.user.id),
+    };
+    let secret = body.secret.clone().unwrap_or_else(generate_secret);
+    if let Err(e) = validate_webhook(&state, &candidate).and_then(|_| validate_secret(&secret)) {
+        return HttpResponse::BadRequest().json(WebhookResponse::error(e));
+    }
+    if let Err(e) = templates::check_includes(&state.db, &candidate.prompt_template).await {
+        return HttpResponse::BadRequest().json(WebhookResponse::error(e));
+    }
+
+    match state
+        .db
+        .create_webhook(&body, &hook_id, &secret, &candidate.workspace, max_iterations, candidate.user_id)
+        .await
+    {
+        Ok(hook) => {
+            log::info!("[WEBHOOK] Created '{}' ({})", hook.name, hook.hook_id);
+            HttpResponse::Created().json(WebhookResponse {
+                webhook: Some(hook),
+                secret: Some(secret),
+                ..WebhookResponse::ok()
+            })
+        }
+        Err(e) => {
+            log::error!("Failed to create webhook: {}", e);
+            HttpResponse::InternalServerError().json(WebhookResponse::error("Failed to create webhook"))
+        }
+    }
+}
+
+/// Get a webhook by its hook id
+async fn get_webhook(state: web::Data<AppState>, req: HttpRequest, path: web::Path<String>) -> impl Responder {
+    let caller = match validate_session_from_request(&state, &req, Scope::Read).await {
+        Ok(caller) => caller,
+        Err(resp) => return resp,
+    };
+
+    match find_webhook(&state, &caller, &path.into_inner()).await {
+        Ok(hook) => HttpResponse::Ok().json(WebhookResponse {
+            webhook: Some(hook),
+            ..WebhookResponse::ok()
+        }),
+        Err(resp) => resp,
+    }
+}
+
+/// Update a webhook, optionally replacing its signing secret
+async fn update_webhook(
+    state: web::Data<AppState>,
+    req: HttpRequest,
+    path: web::Path<String>,
+    body: web::Json<UpdateWebhookRequest>,
+) -> impl Responder {
+    let caller = match validate_session_from_request(&state, &req, Scope::ToolsExec).await {
+        Ok(caller) => caller,
+        Err(resp) => return resp,
+    };
+
+    let mut hook = match find_webhook(&state, &caller, &path.into_inner()).await {
+        Ok(hook) => hook,
+        Err(resp) => return resp,
+    };
+
+    let body = body.into_inner();
+    if let Some(name) = body.name {
+        hook.name = name;
+    }
+    if let Some(prompt_template) = body.prompt_template {
+        hook.prompt_template = prompt_template;
+    }
+    if let Some(tools) = body.tools {
+        hook.tools = tools;
+    }
+    if let Some(workspace) = body.workspace {
+        hook.workspace = match resolve_workspace_name(&caller, Some(&workspace)) {
+            Ok(workspace) => workspace,
+            Err(e) => return HttpResponse::BadRequest().json(WebhookResponse::error(e)),
+        };
+    }
+    if let Some(max_iterations) = body.max_iterations {
+        hook.max_iterations = resolve_max_iterations(Some(max_iterations)) as i64;
+    }
+    if let Some(enabled) = body.enabled {
+        hook.enabled = enabled;
+    }
+
+    let validated = validate_webhook(&state, &hook)
+        .and_then(|_| body.secret.as_deref().map_or(Ok(()), validate_secret));
+    if let Err(e) = validated {
+        return HttpResponse::BadRequest().json(WebhookResponse::error(e));
+    }
+    if let Err(e) = templates::check_includes(&state.db, &hook.prompt_template).await {
+        return HttpResponse::BadRequest().json(WebhookResponse::error(e));
+    }
+
+    if let Err(e) = state.db.save_webhook(&hook).await {
+        log::error!("Failed to update webhook {}: {}", hook.hook_id, e);
+        return HttpResponse::InternalServerError().json(WebhookResponse::error("Failed to update webhook"));
+    }
+    if let Some(secret) = &body.secret
+        && let Err(e) = state.db.set_webhook_secret(&hook.hook_id, secret).await
+    {
+        log::error!("Failed to replace secret for webhook {}: {}", hook.hook_id, e);
+        return HttpResponse::InternalServerError().json(WebhookResponse::error("Failed to update webhook"));
+    }
+
+    match find_webhook(&state, &caller, &hook.hook_id).await {
+        Ok(hook) => HttpResponse::Ok().json(WebhookResponse {
+            webhook: Some(hook),
+            secret: body.secret,
+            ..WebhookResponse::ok()
+        }),
+        Err(resp) => resp,
+    }
+}
+
+/// Delete a webhook. Jobs it already queued are kept.
+async fn delete_webhook(state: web::Data<AppState>, req: HttpRequest, path: web::Path<String>) -> impl Responder {
+    let caller = match validate_session_from_request(&state, &req, Scope::ToolsExec).await {
+        Ok(caller) => caller,
+        Err(resp) => return resp,
+    };
+
+    let hook_id = match find_webhook(&state, &caller, &path.into_inner()).await {
+        Ok(hook) => hook.hook_id,
+        Err(resp) => return resp,
+    };
+    match state.db.delete_webhook(&hook_id).await {
+        Ok(true) => HttpResponse::Ok().json(WebhookResponse::ok()),
+        Ok(false) => HttpResponse::NotFound().json(WebhookResponse::error("Webhook not found")),
+        Err(e) => {
+            log::error!("Failed to delete webhook {}: {}", hook_id, e);
+            HttpResponse::InternalServerError().json(WebhookResponse::error("Failed to delete webhook"))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::Database;
+    use crate::models::{Scopes, UserRole};
+    use actix_web::{test, App};
+    use hmac::{Hmac, Mac};
+    use serde_json::{json, Value};
+    use std::sync::Arc;
+
+    /// A bearer token for a new user with `role`
+    async fn token(db: &Database, address: &str, role: UserRole) -> (i64, String) {
+        let user = db.create_user(address, None, role).await.unwrap();
+        let scopes = Scopes::new([Scope::Read, Scope::ToolsExec]);
+        (user.id, db.create_api_token(user.id, address, &scopes, None).await.unwrap().token)
+    }
+
+    fn call(method: &str, uri: &str, token: &str) -> test::TestRequest {
+        let method = actix_web::http::Method::from_bytes(method.as_bytes()).unwrap();
+        test::TestRequest::default()
+            .method(method)
+            .uri(uri)
+            .insert_header(("Authorization", format!("Bearer {}", token)))
+    }
+
+    #[actix_web::test]
+    async fn test_webhooks_belong_to_their_creator() {
+        let db = Arc::new(Database::new(":memory:").unwrap());
+        let (alice_id, alice) = token(&db, "0xa11ce", UserRole::Member).await;
+        let (_, bob) = token(&db, "0xb0b", UserRole::Member).await;
+        let (_, admin) = token(&db, "0xad", UserRole::Admin).await;
+        let app = test::init_service(
+            App::new().app_data(web::Data::new(AppState::for_tests(db.clone()).await)).configure(config),
+        )
+        .await;
+
+        let body = json!({ "name": "deploys", "prompt_template": "Check {{payload}}", "secret": "0123456789abcdef" });
+        let created: Value =
+            test::call_and_read_body_json(&app, call("POST", "/api/hooks", &alice).set_json(&body).to_request()).await;
+        let hook = &created["webhook"];
+        let hook_id = hook["hook_id"].as_str().unwrap().to_string();
+        assert_eq!(hook["user_id"], alice_id);
+        assert_eq!(hook["workspace"], format!("user-{}-hook-{}", alice_id, hook_id));
+
+        // A member can't reach into another member's workspace
+        let body = json!({ "name": "x", "prompt_template": "y", "workspace": "user-99-loot" });
+        let created: Value =
+            test::call_and_read_body_json(&app, call("POST", "/api/hooks", &bob).set_json(&body).to_request()).await;
+        assert!(created["webhook"]["workspace"].as_str().unwrap().starts_with("user-"));
+        assert_ne!(created["webhook"]["workspace"], "user-99-loot");
+
+        let listed: Value = test::call_and_read_body_json(&app, call("GET", "/api/hooks", &bob).to_request()).await;
+        assert_eq!(listed["webhooks"].as_array().unwrap().len(), 1);
+        let listed: Value = test::call_and_read_body_json(&app, call("GET", "/api/hooks", &admin).to_request()).await;
+        assert_eq!(listed["webhooks"].as_array().unwrap().len(), 2);
+
+        let uri = format!("/api/hooks/{}", hook_id);
+        for req in [
+            call("GET", &uri, &bob),
+            call("PUT", &uri, &bob).set_json(json!({ "enabled": false })),
+            call("DELETE", &uri, &bob),
+        ] {
+            assert_eq!(test::call_service(&app, req.to_request()).await.status(), 404);
+        }
+        assert!(db.get_webhook(&hook_id).await.unwrap().unwrap().enabled);
+
+        let updated: Value = test::call_and_read_body_json(
+            &app,
+            call("PUT", &uri, &alice).set_json(json!({ "workspace": "shared" })).to_request(),
+        )
+        .await;
+        assert_eq!(updated["webhook"]["workspace"], format!("user-{}-shared", alice_id));
+
+        // Deliveries queue jobs owned by the hook's creator
+        let payload = br#"{"type":"deploy"}"#;
+        let mut mac = Hmac::<sha2::Sha256>::new_from_slice(b"0123456789abcdef").unwrap();
+        mac.update(payload);
+        let req = test::TestRequest::post()
+            .uri(&uri)
+            .insert_header(("X-Signature-256", hex::encode(mac.finalize().into_bytes())))
+            .set_payload(payload.as_slice())
+            .to_request();
+        let delivered: Value = test::call_and_read_body_json(&app, req).await;
+        assert_eq!(delivered["job"]["user_id"], alice_id);
+        assert_eq!(delivered["job"]["workspace"], format!("user-{}-shared", alice_id));
+
+        assert_eq!(test::call_service(&app, call("DELETE", &uri, &admin).to_request()).await.status(), 200);
+    }
+}
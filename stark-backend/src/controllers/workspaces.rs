@@ -0,0 +1,500 @@
+use std::path::{Path, PathBuf};
+
+use actix_multipart::Multipart;
+use actix_web::{web, HttpRequest, HttpResponse, Responder};
+use futures_util::StreamExt;
+use serde::Serialize;
+use tokio::io::AsyncWriteExt;
+
+use crate::controllers::agent::workspace_prefix;
+use crate::models::{Caller, Scope};
+use crate::workspace::{
+    safe_relative, SnapshotInfo, UploadLimits, UploadedFile, WorkspaceInfo, WorkspaceKind, WorkspaceManager,
+};
+use crate::AppState;
+
+#[derive(Serialize, Default)]
+pub struct WorkspacesResponse {
+    pub success: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub workspaces: Option<Vec<WorkspaceInfo>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub quota_bytes: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+#[derive(Serialize, Default)]
+pub struct WorkspaceResponse {
+    pub success: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub workspace: Option<WorkspaceInfo>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub snapshot: Option<SnapshotInfo>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub files: Option<Vec<UploadedFile>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+impl WorkspaceResponse {
+    fn error(message: impl Into<String>) -> Self {
+        WorkspaceResponse {
+            error: Some(message.into()),
+            ..Default::default()
+        }
+    }
+}
+
+pub fn config(cfg: &mut web::ServiceConfig) {
+    cfg.service(
+        web::scope("/api/workspaces")
+            .route("", web::get().to(list_workspaces))
+            .route("/{kind}/{name}", web::get().to(get_workspace))
+            .route("/{kind}/{name}", web::delete().to(delete_workspace))
+            .route("/{kind}/{name}/download", web::get().to(download_workspace))
+            .route("/{kind}/{name}/files", web::get().to(list_uploads))
+            .route("/{kind}/{name}/files", web::post().to(upload_files))
+            .route("/{kind}/{name}/snapshots", web::post().to(create_snapshot))
+            .route("/{kind}/{name}/snapshots/{id}", web::delete().to(delete_snapshot))
+            .route("/{kind}/{name}/snapshots/{id}/restore", web::post().to(restore_snapshot)),
+    );
+}
+
+async fn validate_session_from_request(
+    state: &web::Data<AppState>,
+    req: &HttpRequest,
+    scope: Scope,
+) -> Result<Caller, HttpResponse> {
+    let token = req
+        .headers()
+        .get("Authorization")
+        .and_then(|h| h.to_str().ok())
+        .map(|s| s.trim_start_matches("Bearer ").to_string());
+
+    let token = match token {
+        Some(t) => t,
+        None => {
+            return Err(HttpResponse::Unauthorized()
+                .json(WorkspaceResponse::error("No authorization token provided")));
+        }
+    };
+
+    match state.db.authenticate(&token).await {
+        Ok(Some(caller)) if caller.scopes.allows(scope) => Ok(caller),
+        Ok(Some(_)) => Err(HttpResponse::Forbidden()
+            .json(WorkspaceResponse::error(format!("Token lacks the {} scope", scope)))),
+        Ok(None) => Err(HttpResponse::Unauthorized()
+            .json(WorkspaceResponse::error("Invalid or expired session"))),
+        Err(e) => {
+            log::error!("Failed to validate session: {}", e);
+            Err(HttpResponse::InternalServerError()
+                .json(WorkspaceResponse::error("Internal server error")))
+        }
+    }
+}
+
+/// Parse the `{kind}` path segment (`sessions` or `agent-runs`)
+fn parse_kind(kind: &str) -> Result<WorkspaceKind, HttpResponse> {
+    WorkspaceKind::from_dir_name(kind).ok_or_else(|| {
+        HttpResponse::BadRequest().json(WorkspaceResponse::error(format!(
+            "Unknown workspace kind '{}': expected 'sessions' or 'agent-runs'",
+            kind
+        )))
+    })
+}
+
+/// Whether `caller` may use a workspace. Admins may use any; other users
+/// only the workspaces of their own sessions and agent runs.
+async fn can_use_workspace(state: &AppState, caller: &Caller, kind: WorkspaceKind, name: &str) -> bool {
+    if caller.is_admin() {
+        return true;
+    }
+    if kind == WorkspaceKind::AgentRun {
+        return workspace_prefix(caller).is_some_and(|prefix| name.starts_with(&prefix));
+    }
+    let Ok(session_id) = name.parse::<i64>() else {
+        return false;
+    };
+    kind == WorkspaceKind::Session
+        && matches!(
+            state.db.get_chat_session(session_id).await,
+            Ok(Some(session)) if caller.can_access(session.user_id)
+        )
+}
+
+/// Validate the request, parse `kind` and check the caller may use the
+/// workspace. Workspaces of other users are reported as not found.
+async fn authorize_workspace(
+    state: &web::Data<AppState>,
+    req: &HttpRequest,
+    scope: Scope,
+    kind: &str,
+    name: &str,
+) -> Result<WorkspaceKind, HttpResponse> {
+    let caller = validate_session_from_request(state, req, scope).await?;
+    let kind = parse_kind(kind)?;
+    if !can_use_workspace(state, &caller, kind, name).await {
+        return Err(HttpResponse::NotFound().json(WorkspaceResponse::error("Workspace not found")));
+    }
+    Ok(kind)
+}
+
+/// Run a filesystem-heavy manager call off the async runtime
+async fn blocking<T, F>(f: F) -> Result<T, String>
+where
+    T: Send + 'static,
+    F: FnOnce(WorkspaceManager) -> Result<T, String> + Send + 'static,
+{
+    let manager = WorkspaceManager::from_env();
+    tokio::task::spawn_blocking(move || f(manager))
+        .await
+        .map_err(|e| format!("Workspace task failed: {}", e))?
+}
+
+/// List session and agent-run workspaces with their size and snapshots:
+/// every one for admins, those of their own sessions and runs for other users
+async fn list_workspaces(state: web::Data<AppState>, req: HttpRequest) -> impl Responder {
+    let caller = match validate_session_from_request(&state, &req, Scope::Read).await {
+        Ok(caller) => caller,
+        Err(resp) => return resp,
+    };
+
+    let own_sessions: Option<Vec<String>> = if caller.is_admin() {
+        None
+    } else {
+        match state.db.list_user_chat_sessions(caller.user.id).await {
+            Ok(sessions) => Some(sessions.iter().map(|s| s.id.to_string()).collect()),
+            Err(e) => {
+                return HttpResponse::InternalServerError().json(WorkspacesResponse {
+                    error: Some(format!("Database error: {}", e)),
+                    ..Default::default()
+                });
+            }
+        }
+    };
+
+    match blocking(|manager| Ok((manager.list(), manager.quota_bytes()))).await {
+        Ok((mut workspaces, quota_bytes)) => {
+            if let Some(own) = own_sessions {
+                let prefix = workspace_prefix(&caller).unwrap_or_default();
+                workspaces.retain(|w| match w.kind {
+                    WorkspaceKind::Session => own.contains(&w.name),
+                    WorkspaceKind::AgentRun => w.name.starts_with(&prefix),
+                });
+            }
+            HttpResponse::Ok().json(WorkspacesResponse {
+                success: true,
+                workspaces: Some(workspaces),
+                quota_bytes,
+                error: None,
+            })
+        }
+        Err(e) => HttpResponse::InternalServerError().json(WorkspacesResponse {
+            error: Some(e),
+            ..Default::default()
+        }),
+    }
+}
+
+async fn get_workspace(
+    state: web::Data<AppState>,
+    req: HttpRequest,
+    path: web::Path<(String, String)>,
+) -> impl Responder {
+    let (kind, name) = path.into_inner();
+    let kind = match authorize_workspace(&state, &req, Scope::Read, &kind, &name).await {
+        Ok(kind) => kind,
+        Err(resp) => return resp,
+    };
+
+    match blocking(move |manager| manager.get(kind, &name)).await {
+        Ok(Some(workspace)) => HttpResponse::Ok().json(WorkspaceResponse {
+            success: true,
+            workspace: Some(workspace),
+            ..Default::default()
+        }),
+        Ok(None) => HttpResponse::NotFound().json(WorkspaceResponse::error("Workspace not found")),
+        Err(e) => HttpResponse::BadRequest().json(WorkspaceResponse::error(e)),
+    }
+}
+
+/// Download a workspace as a zip archive
+async fn download_workspace(
+    state: web::Data<AppState>,
+    req: HttpRequest,
+    path: web::Path<(String, String)>,
+) -> impl Responder {
+    let (kind, name) = path.into_inner();
+    let kind = match authorize_workspace(&state, &req, Scope::Read, &kind, &name).await {
+        Ok(kind) => kind,
+        Err(resp) => return resp,
+    };
+
+    let filename = format!("{}-{}.zip", kind.dir_name(), name);
+    match blocking(move |manager| manager.zip(kind, &name, None)).await {
+        Ok(archive) => HttpResponse::Ok()
+            .content_type("application/zip")
+            .insert_header((
+                "Content-Disposition",
+                format!("attachment; filename=\"{}\"", filename),
+            ))
+            .body(archive),
+        Err(e) if e.contains("not found") => HttpResponse::NotFound().json(WorkspaceResponse::error(e)),
+        Err(e) => {
+            log::error!("Failed to archive workspace: {}", e);
+            HttpResponse::InternalServerError().json(WorkspaceResponse::error(e))
+        }
+    }
+}
+
+/// Delete a workspace and its snapshots
+async fn delete_workspace(
+    state: web::Data<AppState>,
+    req: HttpRequest,
+    path: web::Path<(String, String)>,
+) -> impl Responder {
+    let (kind, name) = path.into_inner();
+    let kind = match authorize_workspace(&state, &req, Scope::Admin, &kind, &name).await {
+        Ok(kind) => kind,
+        Err(resp) => return resp,
+    };
+
+    match blocking(move |manager| manager.delete(kind, &name)).await {
+        Ok(true) => HttpResponse::Ok().json(WorkspaceResponse {
+            success: true,
+            ..Default::default()
+        }),
+        Ok(false) => HttpResponse::NotFound().json(WorkspaceResponse::error("Workspace not found")),
+        Err(e) => {
+            log::error!("Failed to delete workspace: {}", e);
+            HttpResponse::InternalServerError().json(WorkspaceResponse::error(e))
+        }
+    }
+}
+
+/// Save the workspace's current files as a snapshot
+async fn create_snapshot(
+    state: web::Data<AppState>,
+    req: HttpRequest,
+    path: web::Path<(String, String)>,
+) -> impl Responder {
+    let (kind, name) = path.into_inner();
+    let kind = match authorize_workspace(&state, &req, Scope::Admin, &kind, &name).await {
+        Ok(kind) => kind,
+        Err(resp) => return resp,
+    };
+
+    match blocking(move |manager| manager.snapshot(kind, &name)).await {
+        Ok(snapshot) => HttpResponse::Ok().json(WorkspaceResponse {
+            success: true,
+            snapshot: Some(snapshot),
+            ..Default::default()
+        }),
+        Err(e) if e.contains("not found") => HttpResponse::NotFound().json(WorkspaceResponse::error(e)),
+        Err(e) => {
+            log::error!("Failed to snapshot workspace: {}", e);
+            HttpResponse::InternalServerError().json(WorkspaceResponse::error(e))
+        }
+    }
+}
+
+/// Replace a workspace's files with those of one of its snapshots
+async fn restore_snapshot(
+    state: web::Data<AppState>,
+    req: HttpRequest,
+    path: web::Path<(String, String, String)>,
+) -> impl Responder {
+    let (kind, name, id) = path.into_inner();
+    let kind = match authorize_workspace(&state, &req, Scope::Admin, &kind, &name).await {
+        Ok(kind) => kind,
+        Err(resp) => return resp,
+    };
+
+    let result = blocking(move |manager| {
+        manager.restore(kind, &name, &id)?;
+        manager.get(kind, &name)
+    })
+    .await;
+    match result {
+        Ok(workspace) => HttpResponse::Ok().json(WorkspaceResponse {
+            success: true,
+            workspace,
+            ..Default::default()
+        }),
+        Err(e) if e.contains("not found") => HttpResponse::NotFound().json(WorkspaceResponse::error(e)),
+        Err(e) => {
+            log::error!("Failed to restore workspace snapshot: {}", e);
+            HttpResponse::InternalServerError().json(WorkspaceResponse::error(e))
+        }
+    }
+}
+
+async fn delete_snapshot(
+    state: web::Data<AppState>,
+    req: HttpRequest,
+    path: web::Path<(String, String, String)>,
+) -> impl Responder {
+    let (kind, name, id) = path.into_inner();
+    let kind = match authorize_workspace(&state, &req, Scope::Admin, &kind, &name).await {
+        Ok(kind) => kind,
+        Err(resp) => return resp,
+    };
+
+    match blocking(move |manager| manager.delete_snapshot(kind, &name, &id)).await {
+        Ok(true) => HttpResponse::Ok().json(WorkspaceResponse {
+            success: true,
+            ..Default::default()
+        }),
+        Ok(false) => HttpResponse::NotFound().json(WorkspaceResponse::error("Snapshot not found")),
+        Err(e) => HttpResponse::BadRequest().json(WorkspaceResponse::error(e)),
+    }
+}
+
+/// Files uploaded into a workspace that are still there
+async fn list_uploads(
+    state: web::Data<AppState>,
+    req: HttpRequest,
+    path: web::Path<(String, String)>,
+) -> impl Responder {
+    let (kind, name) = path.into_inner();
+    let kind = match authorize_workspace(&state, &req, Scope::Read, &kind, &name).await {
+        Ok(kind) => kind,
+        Err(resp) => return resp,
+    };
+
+    match blocking(move |manager| {
+        manager.path(kind, &name)?;
+        Ok(manager.uploads(kind, &name))
+    })
+    .await
+    {
+        Ok(files) => HttpResponse::Ok().json(WorkspaceResponse {
+            success: true,
+            files: Some(files),
+            ..Default::default()
+        }),
+        Err(e) => HttpResponse::BadRequest().json(WorkspaceResponse::error(e)),
+    }
+}
+
+/// Upload files into a workspace, creating it if needed. Multipart fields:
+/// any number of files, an optional `path` directory to put them under and
+/// `extract` (default true) to unpack zip and tar archives.
+async fn upload_files(
+    state: web::Data<AppState>,
+    req: HttpRequest,
+    path: web::Path<(String, String)>,
+    payload: Multipart,
+) -> impl Responder {
+    let (kind, name) = path.into_inner();
+    let kind = match authorize_workspace(&state, &req, Scope::Chat, &kind, &name).await {
+        Ok(kind) => kind,
+        Err(resp) => return resp,
+    };
+
+    let checked_name = name.clone();
+    let staging = match blocking(move |manager| {
+        manager.path(kind, &checked_name)?;
+        manager.staging_dir()
+    })
+    .await
+    {
+        Ok(dir) => dir,
+        Err(e) => return HttpResponse::BadRequest().json(WorkspaceResponse::error(e)),
+    };
+
+    let limits = UploadLimits::from_env();
+    let (dest, extract) = match receive_upload(payload, &staging, limits.max_bytes).await {
+        Ok(fields) => fields,
+        Err(resp) => {
+            let _ = tokio::fs::remove_dir_all(&staging).await;
+            return resp;
+        }
+    };
+
+    let workspace = name.clone();
+    match blocking(move |manager| manager.import_upload(kind, &workspace, &staging, &dest, extract, &limits)).await {
+        Ok(files) => {
+            log::info!("[WORKSPACE] Uploaded {} file(s) into {}/{}", files.len(), kind.dir_name(), name);
+            HttpResponse::Ok().json(WorkspaceResponse {
+                success: true,
+                files: Some(files),
+                ..Default::default()
+            })
+        }
+        Err(e) if e.contains("quota") || e.contains("size limit") => {
+            HttpResponse::PayloadTooLarge().json(WorkspaceResponse::error(e))
+        }
+        Err(e) => HttpResponse::BadRequest().json(WorkspaceResponse::error(e)),
+    }
+}
+
+/// Stream the multipart files into `staging`. Returns the `path` and
+/// `extract` fields.
+async fn receive_upload(
+    mut payload: Multipart,
+    staging: &Path,
+    max_bytes: u64,
+) -> Result<(String, bool), HttpResponse> {
+    let bad_request = |message: String| HttpResponse::BadRequest().json(WorkspaceResponse::error(message));
+    let mut dest = String::new();
+    let mut extract = true;
+    let mut received = 0u64;
+
+    while let Some(item) = payload.next().await {
+        let mut field = item.map_err(|e| bad_request(format!("Failed to process upload: {}", e)))?;
+        let filename = field.content_disposition().get_filename().map(|f| f.to_string());
+        let field_name = field.content_disposition().get_name().unwrap_or_default().to_string();
+
+        let Some(filename) = filename else {
+            let mut value = Vec::new();
+            while let Some(chunk) = field.next().await {
+                let chunk = chunk.map_err(|e| bad_request(format!("Failed to read upload data: {}", e)))?;
+                value.extend_from_slice(&chunk);
+                if value.len() > 4096 {
+                    return Err(bad_request(format!("Field '{}' is too long", field_name)));
+                }
+            }
+            let value = String::from_utf8_lossy(&value).trim().to_string();
+            match field_name.as_str() {
+                "path" => {
+                    if !value.is_empty() && safe_relative(&value).is_none() {
+                        return Err(bad_request(format!("Invalid path '{}'", value)));
+                    }
+                    dest = value;
+                }
+                "extract" => extract = !matches!(value.to_ascii_lowercase().as_str(), "false" | "0" | "no"),
+                _ => {}
+            }
+            continue;
+        };
+
+        let relative: PathBuf =
+            safe_relative(&filename).ok_or_else(|| bad_request(format!("Invalid file name '{}'", filename)))?;
+        let target = staging.join(&relative);
+        if let Some(parent) = target.parent() {
+            tokio::fs::create_dir_all(parent)
+                .await
+                .map_err(|e| bad_request(format!("Failed to store {}: {}", filename, e)))?;
+        }
+        let mut file = tokio::fs::File::create(&target)
+            .await
+            .map_err(|e| bad_request(format!("Failed to store {}: {}", filename, e)))?;
+        while let Some(chunk) = field.next().await {
+            let chunk = chunk.map_err(|e| bad_request(format!("Failed to read upload data: {}", e)))?;
+            received += chunk.len() as u64;
+            if received > max_bytes {
+                return Err(HttpResponse::PayloadTooLarge().json(WorkspaceResponse::error(format!(
+                    "Upload exceeds the {} MB limit",
+                    max_bytes / (1024 * 1024)
+                ))));
+            }
+            file.write_all(&chunk)
+                .await
+                .map_err(|e| bad_request(format!("Failed to store {}: {}", filename, e)))?;
+        }
+    }
+
+    Ok((dest, extract))
+}
@@ -0,0 +1,801 @@
+//! Storage backends for state shared between server instances
+//!
+//! Users, auth sessions, SIWE challenges, API tokens, API keys, agent settings
+//! and sign-in lockouts are what several instances behind a load balancer must
+//! agree on: a login on one instance has to be valid on the next, and a lockout
+//! earned on one has to hold on the others. These live behind the
+//! `DatabaseBackend` trait, implemented by `SqliteBackend` (the default, on the
+//! same file as everything else) and `PostgresBackend` (selected with a
+//! `postgres://` `DATABASE_URL`).
+//!
+//! `Database` owns the active backend and forwards these methods to it, so
+//! callers keep using `db.validate_session(..)` regardless of where the data
+//! lives. All other tables stay in the local SQLite file.
+
+use async_trait::async_trait;
+use chrono::{DateTime, Duration, Utc};
+use rusqlite::Result as SqliteResult;
+
+use crate::models::{
+    AgentSettings, ApiKey, ApiToken, Caller, CreatedApiToken, LoginFailure, LoginFailureReason, Scopes, Session,
+    SessionPolicy, ToolPolicy, UpdateAgentSettingsRequest, User, UserRole,
+};
+use crate::redaction;
+use super::secrets::KeyRing;
+use super::sqlite::{checkout, PooledConn, SqlitePool};
+use super::Database;
+
+/// Error from any storage backend
+#[derive(Debug)]
+pub enum DbError {
+    Sqlite(rusqlite::Error),
+    Postgres(tokio_postgres::Error),
+    /// The backend could not run the query at all (e.g. no connection could be made)
+    Unavailable(String),
+    /// A stored secret could not be encrypted or decrypted
+    Encryption(String),
+}
+
+impl std::fmt::Display for DbError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Sqlite(e) => write!(f, "{}", e),
+            Self::Postgres(e) => write!(f, "{}", e),
+            Self::Unavailable(msg) => write!(f, "database unavailable: {}", msg),
+            Self::Encryption(msg) => write!(f, "encryption: {}", msg),
+        }
+    }
+}
+
+impl std::error::Error for DbError {}
+
+impl From<rusqlite::Error> for DbError {
+    fn from(e: rusqlite::Error) -> Self {
+        Self::Sqlite(e)
+    }
+}
+
+impl From<tokio_postgres::Error> for DbError {
+    fn from(e: tokio_postgres::Error) -> Self {
+        Self::Postgres(e)
+    }
+}
+
+impl From<deadpool_postgres::PoolError> for DbError {
+    fn from(e: deadpool_postgres::PoolError) -> Self {
+        match e {
+            deadpool_postgres::PoolError::Backend(e) => Self::Postgres(e),
+            other => Self::Unavailable(other.to_string()),
+        }
+    }
+}
+
+pub type DbResult<T> = Result<T, DbError>;
+
+/// People allowed to sign in
+#[async_trait]
+pub trait UserStore {
+    async fn create_user(&self, public_address: &str, name: Option<&str>, role: UserRole) -> DbResult<User>;
+    async fn get_user(&self, id: i64) -> DbResult<Option<User>>;
+    async fn get_user_by_address(&self, public_address: &str) -> DbResult<Option<User>>;
+    async fn list_users(&self) -> DbResult<Vec<User>>;
+    /// Returns the updated user, or `None` if there is no user with this id
+    async fn set_user_role(&self, id: i64, role: UserRole) -> DbResult<Option<User>>;
+    /// Deletes the user with their login sessions, API tokens and personal API keys
+    async fn delete_user(&self, id: i64) -> DbResult<bool>;
+    /// Give `user_id` the sessions and API tokens created before there were
+    /// users. Returns how many were claimed.
+    async fn claim_unowned_credentials(&self, user_id: i64) -> DbResult<usize>;
+    /// Record that `user_id` signed with the TOTP code of `step`. False if
+    /// that step or a later one was already used, so each code works once.
+    async fn claim_totp_step(&self, user_id: i64, step: i64) -> DbResult<bool>;
+}
+
+/// Web login sessions and SIWE challenges
+#[async_trait]
+pub trait AuthStore {
+    async fn create_session_for_user(&self, user: &User, ttl: Duration) -> DbResult<Session>;
+    /// Returns the session if the token is valid, extending its expiry when `policy` is sliding
+    async fn validate_session(&self, token: &str, policy: &SessionPolicy) -> DbResult<Option<Session>>;
+    /// Sets a valid session to expire `ttl` from now
+    async fn refresh_session(&self, token: &str, ttl: Duration) -> DbResult<Option<Session>>;
+    async fn delete_session(&self, token: &str) -> DbResult<bool>;
+    /// Returns the number of sessions removed
+    async fn delete_expired_sessions(&self) -> DbResult<usize>;
+    async fn create_or_update_challenge(&self, public_address: &str, challenge: &str) -> DbResult<()>;
+    async fn get_challenge(&self, public_address: &str) -> DbResult<Option<String>>;
+    async fn validate_challenge(&self, public_address: &str, challenge: &str) -> DbResult<bool>;
+    async fn delete_challenge(&self, public_address: &str) -> DbResult<bool>;
+}
+
+/// Named, scoped bearer tokens (looked up by hash, never stored in the clear)
+#[async_trait]
+pub trait ApiTokenStore {
+    async fn create_api_token(
+        &self,
+        user_id: i64,
+        name: &str,
+        token_hash: &str,
+        prefix: &str,
+        scopes: &Scopes,
+        tool_policy: Option<&ToolPolicy>,
+    ) -> DbResult<ApiToken>;
+    async fn list_api_tokens(&self) -> DbResult<Vec<ApiToken>>;
+    /// Returns the updated token, or `None` if there is no token with this id
+    async fn set_api_token_tool_policy(&self, id: i64, tool_policy: Option<&ToolPolicy>) -> DbResult<Option<ApiToken>>;
+    /// Returns the token with this hash, recording the use
+    async fn validate_api_token(&self, token_hash: &str) -> DbResult<Option<ApiToken>>;
+    async fn delete_api_token(&self, id: i64) -> DbResult<bool>;
+}
+
+/// External service API keys: shared ones (no `user_id`) and users'
+/// personal ones, which take precedence in that user's conversations
+#[async_trait]
+pub trait ApiKeyStore {
+    async fn get_api_key(&self, service_name: &str, user_id: Option<i64>) -> DbResult<Option<ApiKey>>;
+    /// Every key, shared and personal
+    async fn list_api_keys(&self) -> DbResult<Vec<ApiKey>>;
+    async fn upsert_api_key(&self, service_name: &str, user_id: Option<i64>, api_key: &str) -> DbResult<ApiKey>;
+    async fn delete_api_key(&self, service_name: &str, user_id: Option<i64>) -> DbResult<bool>;
+}
+
+/// AI provider profiles (only one enabled at a time)
+#[async_trait]
+pub trait AgentSettingsStore {
+    async fn get_active_agent_settings(&self) -> DbResult<Option<AgentSettings>>;
+    async fn get_agent_settings_by_endpoint(&self, endpoint: &str) -> DbResult<Option<AgentSettings>>;
+    async fn list_agent_settings(&self) -> DbResult<Vec<AgentSettings>>;
+    /// Upsert by endpoint and make it the only enabled profile
+    async fn save_agent_settings(&self, request: &UpdateAgentSettingsRequest) -> DbResult<AgentSettings>;
+    async fn disable_agent_settings(&self) -> DbResult<()>;
+}
+
+/// Failures older than this no longer count towards a lockout
+pub(crate) const LOGIN_FAILURE_MEMORY_HOURS: i64 = 24;
+
+/// Sign-in lockout counters per client IP and the failed sign-in audit log
+#[async_trait]
+pub trait LoginAttemptStore {
+    /// When the lockout of `ip` ends, if it is locked out now
+    async fn get_login_lockout(&self, ip: &str) -> DbResult<Option<DateTime<Utc>>>;
+    /// Log a refused sign-in and return how many failures in a row `ip` now
+    /// has. Attempts refused because of a lockout are logged but not counted.
+    async fn record_login_failure(
+        &self,
+        ip: &str,
+        public_address: Option<&str>,
+        reason: LoginFailureReason,
+    ) -> DbResult<i64>;
+    /// Refuse sign-ins from `ip` until `until`
+    async fn set_login_lockout(&self, ip: &str, until: DateTime<Utc>) -> DbResult<()>;
+    /// Forget the failures of `ip` after it signs in
+    async fn clear_login_failures(&self, ip: &str) -> DbResult<()>;
+    /// Most recent failed sign-ins first
+    async fn list_login_failures(&self, limit: i64) -> DbResult<Vec<LoginFailure>>;
+}
+
+/// Everything a shared-state backend has to provide
+#[async_trait]
+pub trait DatabaseBackend:
+    UserStore + AuthStore + ApiTokenStore + ApiKeyStore + AgentSettingsStore + LoginAttemptStore + Send + Sync
+{
+    /// Short name for logs ("sqlite", "postgres")
+    fn backend_name(&self) -> &'static str;
+    /// Run a trivial query to check the backend answers
+    async fn ping(&self) -> DbResult<()>;
+}
+
+/// Default backend: the shared tables in the local SQLite file
+pub struct SqliteBackend {
+    pub(crate) pool: SqlitePool,
+}
+
+impl SqliteBackend {
+    pub(crate) async fn conn(&self) -> SqliteResult<PooledConn> {
+        checkout(&self.pool).await
+    }
+}
+
+#[async_trait]
+impl DatabaseBackend for SqliteBackend {
+    fn backend_name(&self) -> &'static str {
+        "sqlite"
+    }
+
+    async fn ping(&self) -> DbResult<()> {
+        let conn = self.conn().await?;
+        conn.query_row("SELECT 1", [], |_| Ok(()))?;
+        Ok(())
+    }
+}
+
+/// Random 32-char hex token for a new auth session
+pub(crate) fn generate_session_token() -> String {
+    use rand::Rng;
+    let mut rng = rand::thread_rng();
+    (0..32)
+        .map(|_| format!("{:x}", rng.r#gen::<u8>() % 16))
+        .collect()
+}
+
+/// Prefix that tells API tokens apart from login session tokens
+pub const API_TOKEN_PREFIX: &str = "stk_";
+
+/// Random API token: the prefix plus 64 hex chars
+fn generate_api_token() -> String {
+    use rand::Rng;
+    let bytes: [u8; 32] = rand::thread_rng().r#gen();
+    format!("{}{}", API_TOKEN_PREFIX, hex::encode(bytes))
+}
+
+/// Tokens are stored as their SHA-256 so a leaked database can't be replayed
+fn hash_api_token(token: &str) -> String {
+    use sha2::{Digest, Sha256};
+    hex::encode(Sha256::digest(token.as_bytes()))
+}
+
+/// Whether `DATABASE_URL` points at Postgres rather than a SQLite file
+pub fn is_postgres_url(database_url: &str) -> bool {
+    database_url.starts_with("postgres://") || database_url.starts_with("postgresql://")
+}
+
+impl Database {
+    /// Name of the backend holding auth sessions, API keys and agent settings
+    pub fn backend_name(&self) -> &'static str {
+        self.backend.backend_name()
+    }
+
+    /// Check that both the local SQLite file and the shared-state backend answer
+    pub async fn ping(&self) -> DbResult<()> {
+        let conn = self.conn().await?;
+        conn.query_row("SELECT 1", [], |_| Ok(()))?;
+        drop(conn);
+        self.backend.ping().await
+    }
+
+    // Forwarders so callers don't need the backend traits in scope
+
+    /// Session lifetime from the active agent settings (24 hours, sliding, if none)
+    pub async fn session_policy(&self) -> SessionPolicy {
+        match self.backend.get_active_agent_settings().await {
+            Ok(settings) => settings.map(|s| s.session_policy()).unwrap_or_default(),
+            Err(e) => {
+                log::warn!("Failed to load session settings, using defaults: {}", e);
+                SessionPolicy::default()
+            }
+        }
+    }
+
+    pub async fn create_session_for_user(&self, user: &User) -> DbResult<Session> {
+        let policy = self.session_policy().await;
+        self.backend.create_session_for_user(user, policy.ttl).await
+    }
+
+    pub async fn validate_session(&self, token: &str) -> DbResult<Option<Session>> {
+        let policy = self.session_policy().await;
+        self.backend.validate_session(token, &policy).await
+    }
+
+    /// Extend a valid session by the configured TTL, whether or not expiry is sliding
+    pub async fn refresh_session(&self, token: &str) -> DbResult<Option<Session>> {
+        let policy = self.session_policy().await;
+        self.backend.refresh_session(token, policy.ttl).await
+    }
+
+    pub async fn delete_session(&self, token: &str) -> DbResult<bool> {
+        self.backend.delete_session(token).await
+    }
+
+    pub async fn delete_expired_sessions(&self) -> DbResult<usize> {
+        self.backend.delete_expired_sessions().await
+    }
+
+    pub async fn create_or_update_challenge(&self, public_address: &str, challenge: &str) -> DbResult<()> {
+        self.backend.create_or_update_challenge(public_address, challenge).await
+    }
+
+    pub async fn get_challenge(&self, public_address: &str) -> DbResult<Option<String>> {
+        self.backend.get_challenge(public_address).await
+    }
+
+    pub async fn validate_challenge(&self, public_address: &str, challenge: &str) -> DbResult<bool> {
+        self.backend.validate_challenge(public_address, challenge).await
+    }
+
+    pub async fn delete_challenge(&self, public_address: &str) -> DbResult<bool> {
+        self.backend.delete_challenge(public_address).await
+    }
+
+    /// Resolve a bearer token to the user behind it and what it may do:
+    /// wallet login sessions get the scopes of the user's role, API tokens
+    /// the ones they were created with (less `admin` once their owner is no
+    /// longer an admin)
+    pub async fn authenticate(&self, token: &str) -> DbResult<Option<Caller>> {
+        let (user_id, token_scopes, tool_policy) = if token.starts_with(API_TOKEN_PREFIX) {
+            match self.backend.validate_api_token(&hash_api_token(token)).await? {
+                Some(t) => (t.user_id, Some(t.scopes), t.tool_policy),
+                None => return Ok(None),
+            }
+        } else {
+            match self.validate_session(token).await? {
+                Some(session) => (session.user_id, None, None),
+                None => return Ok(None),
+            }
+        };
+
+        let Some(user) = (match user_id {
+            Some(id) => self.backend.get_user(id).await?,
+            None => None,
+        }) else {
+            return Ok(None);
+        };
+        let role_scopes = user.role.login_scopes();
+        let scopes = match token_scopes {
+            Some(scopes) if !role_scopes.is_full() => {
+                Scopes::new(scopes.iter().copied().filter(|s| role_scopes.allows(*s)))
+            }
+            Some(scopes) => scopes,
+            None => role_scopes,
+        };
+        Ok(Some(Caller { user, scopes, tool_policy }))
+    }
+
+    /// Resolve a bearer token to the scopes it grants
+    pub async fn authorize(&self, token: &str) -> DbResult<Option<Scopes>> {
+        Ok(self.authenticate(token).await?.map(|caller| caller.scopes))
+    }
+
+    // Addresses are stored lowercase so sign-in can match them exactly
+
+    pub async fn create_user(&self, public_address: &str, name: Option<&str>, role: UserRole) -> DbResult<User> {
+        self.backend.create_user(&public_address.trim().to_lowercase(), name, role).await
+    }
+
+    pub async fn get_user(&self, id: i64) -> DbResult<Option<User>> {
+        self.backend.get_user(id).await
+    }
+
+    pub async fn get_user_by_address(&self, public_address: &str) -> DbResult<Option<User>> {
+        self.backend.get_user_by_address(&public_address.trim().to_lowercase()).await
+    }
+
+    pub async fn list_users(&self) -> DbResult<Vec<User>> {
+        self.backend.list_users().await
+    }
+
+    pub async fn set_user_role(&self, id: i64, role: UserRole) -> DbResult<Option<User>> {
+        self.backend.set_user_role(id, role).await
+    }
+
+    /// Delete a user with their credentials and personal API keys. Their
+    /// conversations are kept, without an owner, so only admins see them.
+    pub async fn delete_user(&self, id: i64) -> DbResult<bool> {
+        if !self.backend.delete_user(id).await? {
+            return Ok(false);
+        }
+        let conn = self.conn().await?;
+        conn.execute("UPDATE chat_sessions SET user_id = NULL WHERE user_id = ?1", [id])?;
+        Ok(true)
+    }
+
+    /// Local chat sessions refer to their owner by id. When users live in
+    /// another backend, copy the user's row into the local file so that
+    /// reference holds.
+    pub(crate) async fn mirror_user(&self, user_id: i64) -> DbResult<()> {
+        if self.backend_name() == "sqlite" {
+            return Ok(());
+        }
+        if let Some(user) = self.backend.get_user(user_id).await? {
+            let conn = self.conn().await?;
+            conn.execute(
+                "INSERT OR IGNORE INTO users (id, public_address, name, role, created_at) VALUES (?1, ?2, ?3, ?4, ?5)",
+                rusqlite::params![
+                    user.id,
+                    &user.public_address,
+                    &user.name,
+                    user.role.as_str(),
+                    user.created_at.to_rfc3339(),
+                ],
+            )?;
+        }
+        Ok(())
+    }
+
+    pub async fn claim_totp_step(&self, user_id: i64, step: i64) -> DbResult<bool> {
+        self.backend.claim_totp_step(user_id, step).await
+    }
+
+    /// Make `public_address` (`LOGIN_ADMIN_PUBLIC_ADDRESS`) an admin user,
+    /// giving it the sessions and API tokens from before there were users
+    pub async fn ensure_admin_user(&self, public_address: &str) -> DbResult<User> {
+        let user = match self.get_user_by_address(public_address).await? {
+            Some(user) if user.role == UserRole::Admin => user,
+            Some(user) => self.set_user_role(user.id, UserRole::Admin).await?.unwrap_or(user),
+            None => match self.create_user(public_address, None, UserRole::Admin).await {
+                Ok(user) => user,
+                // Another instance created it first
+                Err(e) => self.get_user_by_address(public_address).await?.ok_or(e)?,
+            },
+        };
+        let claimed = self.backend.claim_unowned_credentials(user.id).await?;
+        if claimed > 0 {
+            log::info!("Assigned {} existing sessions and API tokens to admin user {}", claimed, user.id);
+        }
+        Ok(user)
+    }
+
+    /// Create a named token acting as `user_id`; the returned value is the
+    /// only copy of the token itself
+    pub async fn create_api_token(
+        &self,
+        user_id: i64,
+        name: &str,
+        scopes: &Scopes,
+        tool_policy: Option<&ToolPolicy>,
+    ) -> DbResult<CreatedApiToken> {
+        let token = generate_api_token();
+        let prefix: String = token.chars().take(API_TOKEN_PREFIX.len() + 8).collect();
+        let token_info = self
+            .backend
+            .create_api_token(user_id, name, &hash_api_token(&token), &prefix, scopes, tool_policy)
+            .await?;
+        Ok(CreatedApiToken { token_info, token })
+    }
+
+    pub async fn list_api_tokens(&self) -> DbResult<Vec<ApiToken>> {
+        self.backend.list_api_tokens().await
+    }
+
+    pub async fn set_api_token_tool_policy(&self, id: i64, tool_policy: Option<&ToolPolicy>) -> DbResult<Option<ApiToken>> {
+        self.backend.set_api_token_tool_policy(id, tool_policy).await
+    }
+
+    pub async fn delete_api_token(&self, id: i64) -> DbResult<bool> {
+        self.backend.delete_api_token(id).await
+    }
+
+    // API keys are sealed here rather than in each backend, so every backend
+    // only ever sees ciphertext (see `db::secrets`)
+
+    fn open_api_key(&self, mut key: ApiKey) -> DbResult<ApiKey> {
+        key.api_key = self.keys.open(&key.api_key).map_err(DbError::Encryption)?;
+        redaction::register_secret(&key.api_key);
+        Ok(key)
+    }
+
+    /// The shared key for a service
+    pub async fn get_api_key(&self, service_name: &str) -> DbResult<Option<ApiKey>> {
+        self.backend
+            .get_api_key(service_name, None)
+            .await?
+            .map(|key| self.open_api_key(key))
+            .transpose()
+    }
+
+    /// Keys owned by `user_id` (`None`: the shared keys)
+    pub async fn list_api_keys_owned_by(&self, user_id: Option<i64>) -> DbResult<Vec<ApiKey>> {
+        self.backend
+            .list_api_keys()
+            .await?
+            .into_iter()
+            .filter(|key| key.user_id == user_id)
+            .map(|key| self.open_api_key(key))
+            .collect()
+    }
+
+    /// The shared keys
+    pub async fn list_api_keys(&self) -> DbResult<Vec<ApiKey>> {
+        self.list_api_keys_owned_by(None).await
+    }
+
+    /// The keys a conversation of `user_id` runs with: the shared keys, each
+    /// replaced by the user's personal key for the same service if they have one
+    pub async fn effective_api_keys(&self, user_id: Option<i64>) -> DbResult<Vec<ApiKey>> {
+        let mut keys = self.list_api_keys().await?;
+        if user_id.is_none() {
+            return Ok(keys);
+        }
+        let personal = self.list_api_keys_owned_by(user_id).await?;
+        keys.retain(|shared| !personal.iter().any(|p| p.service_name == shared.service_name));
+        keys.extend(personal);
+        keys.sort_by(|a, b| a.service_name.cmp(&b.service_name));
+        Ok(keys)
+    }
+
+    /// A user's personal key for a service, without falling back to the shared one
+    pub async fn get_personal_api_key(&self, service_name: &str, user_id: i64) -> DbResult<Option<ApiKey>> {
+        self.backend
+            .get_api_key(service_name, Some(user_id))
+            .await?
+            .map(|key| self.open_api_key(key))
+            .transpose()
+    }
+
+    /// Pick the key `settings` authenticate with in a conversation of
+    /// `user_id`: the user's personal key for the provider, else the
+    /// settings' own `secret_key`, else the shared key for the provider.
+    /// Returns the id of the personal key used, to attribute usage to it.
+    pub async fn apply_provider_key(&self, settings: &mut AgentSettings, user_id: Option<i64>) -> DbResult<Option<i64>> {
+        let Some(service_name) = settings.provider_key_name() else {
+            return Ok(None);
+        };
+        if let Some(user_id) = user_id
+            && let Some(key) = self.get_personal_api_key(service_name, user_id).await?
+        {
+            settings.use_secret_key(key.api_key);
+            return Ok(Some(key.id));
+        }
+        if settings.secret_key.is_none()
+            && let Some(key) = self.get_api_key(service_name).await?
+        {
+            settings.use_secret_key(key.api_key);
+        }
+        Ok(None)
+    }
+
+    /// Save the shared key (`user_id` None) or a user's personal key for a service
+    pub async fn upsert_api_key(&self, service_name: &str, user_id: Option<i64>, api_key: &str) -> DbResult<ApiKey> {
+        let sealed = self.keys.seal(api_key).map_err(DbError::Encryption)?;
+        let stored = self.backend.upsert_api_key(service_name, user_id, &sealed).await?;
+        self.open_api_key(stored)
+    }
+
+    /// Encrypt API keys stored before a master key was configured.
+    /// Returns how many were sealed.
+    pub async fn seal_plaintext_api_keys(&self) -> DbResult<usize> {
+        if !self.keys.is_enabled() {
+            return Ok(0);
+        }
+        let mut sealed = 0;
+        for key in self.backend.list_api_keys().await? {
+            if KeyRing::is_sealed(&key.api_key) {
+                continue;
+            }
+            let value = self.keys.seal(&key.api_key).map_err(DbError::Encryption)?;
+            self.backend.upsert_api_key(&key.service_name, key.user_id, &value).await?;
+            sealed += 1;
+        }
+        Ok(sealed)
+    }
+
+    /// Re-wrap every API key under the current master key (sealing any
+    /// plaintext ones). Returns how many were rewritten.
+    pub async fn rotate_api_keys(&self) -> DbResult<usize> {
+        if !self.keys.is_enabled() {
+            return Err(DbError::Encryption("no master key configured".to_string()));
+        }
+        let mut rotated = 0;
+        for key in self.backend.list_api_keys().await? {
+            if !self.keys.needs_rotation(&key.api_key) {
+                continue;
+            }
+            let value = self.keys.rotate(&key.api_key).map_err(DbError::Encryption)?;
+            self.backend.upsert_api_key(&key.service_name, key.user_id, &value).await?;
+            rotated += 1;
+        }
+        Ok(rotated)
+    }
+
+    pub async fn delete_api_key(&self, service_name: &str, user_id: Option<i64>) -> DbResult<bool> {
+        self.backend.delete_api_key(service_name, user_id).await
+    }
+
+    // Endpoint keys read here are registered for redaction like API keys
+
+    fn register_settings_secrets(settings: AgentSettings) -> AgentSettings {
+        for key in [&settings.secret_key, &settings.fallback_secret_key].into_iter().flatten() {
+            redaction::register_secret(key);
+        }
+        settings
+    }
+
+    pub async fn get_active_agent_settings(&self) -> DbResult<Option<AgentSettings>> {
+        Ok(self.backend.get_active_agent_settings().await?.map(Self::register_settings_secrets))
+    }
+
+    pub async fn get_agent_settings_by_endpoint(&self, endpoint: &str) -> DbResult<Option<AgentSettings>> {
+        Ok(self.backend.get_agent_settings_by_endpoint(endpoint).await?.map(Self::register_settings_secrets))
+    }
+
+    pub async fn list_agent_settings(&self) -> DbResult<Vec<AgentSettings>> {
+        Ok(self.backend.list_agent_settings().await?.into_iter().map(Self::register_settings_secrets).collect())
+    }
+
+    pub async fn save_agent_settings(&self, request: &UpdateAgentSettingsRequest) -> DbResult<AgentSettings> {
+        self.backend.save_agent_settings(request).await.map(Self::register_settings_secrets)
+    }
+
+    pub async fn disable_agent_settings(&self) -> DbResult<()> {
+        self.backend.disable_agent_settings().await
+    }
+
+    pub async fn get_login_lockout(&self, ip: &str) -> DbResult<Option<DateTime<Utc>>> {
+        self.backend.get_login_lockout(ip).await
+    }
+
+    pub async fn record_login_failure(
+        &self,
+        ip: &str,
+        public_address: Option<&str>,
+        reason: LoginFailureReason,
+    ) -> DbResult<i64> {
+        self.backend.record_login_failure(ip, public_address, reason).await
+    }
+
+    pub async fn set_login_lockout(&self, ip: &str, until: DateTime<Utc>) -> DbResult<()> {
+        self.backend.set_login_lockout(ip, until).await
+    }
+
+    pub async fn clear_login_failures(&self, ip: &str) -> DbResult<()> {
+        self.backend.clear_login_failures(ip).await
+    }
+
+    pub async fn list_login_failures(&self, limit: i64) -> DbResult<Vec<LoginFailure>> {
+        self.backend.list_login_failures(limit).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::Scope;
+
+    #[tokio::test]
+    async fn test_authorize_sessions_and_tokens() {
+        let db = Database::new(":memory:").unwrap();
+        let admin = db.ensure_admin_user("0xAbC").await.unwrap();
+
+        let session = db.create_session_for_user(&admin).await.unwrap();
+        assert!(db.authorize(&session.token).await.unwrap().unwrap().is_full());
+
+        let created = db.create_api_token(admin.id, "dashboard", &Scopes::new([Scope::Read]), None).await.unwrap();
+        assert!(created.token.starts_with(API_TOKEN_PREFIX));
+        assert!(created.token.starts_with(&created.token_info.prefix));
+
+        let scopes = db.authorize(&created.token).await.unwrap().unwrap();
+        assert!(scopes.allows(Scope::Read));
+        assert!(!scopes.allows(Scope::Chat));
+        assert!(db.list_api_tokens().await.unwrap()[0].last_used_at.is_some());
+
+        assert!(db.authorize("stk_unknown").await.unwrap().is_none());
+        assert!(db.create_api_token(admin.id, "dashboard", &Scopes::full(), None).await.is_err());
+
+        let policy = ToolPolicy { deny: vec!["exec".to_string()], ..Default::default() };
+        let updated = db.set_api_token_tool_policy(created.token_info.id, Some(&policy)).await.unwrap().unwrap();
+        assert_eq!(updated.tool_policy.as_ref(), Some(&policy));
+        let caller = db.authenticate(&created.token).await.unwrap().unwrap();
+        assert_eq!(caller.tool_policy, Some(policy));
+        assert!(db.authenticate(&session.token).await.unwrap().unwrap().tool_policy.is_none());
+        assert!(db.set_api_token_tool_policy(999, None).await.unwrap().is_none());
+
+        assert!(db.delete_api_token(created.token_info.id).await.unwrap());
+        assert!(db.authorize(&created.token).await.unwrap().is_none());
+    }
+
+    #[tokio::test]
+    async fn test_session_ttl_and_sliding() {
+        let db = Database::new(":memory:").unwrap();
+        db.save_agent_settings(&UpdateAgentSettingsRequest {
+            endpoint: "https://a.example".to_string(),
+            model_archetype: "kimi".to_string(),
+            max_tokens: 1000,
+            secret_key: None,
+            budget_max_tokens: None,
+            budget_max_usd: None,
+            session_budget_max_tokens: None,
+            session_budget_max_usd: None,
+            session_ttl_hours: Some(2),
+            session_sliding: false,
+            allowed_models: Vec::new(),
+            spend_max_tx_usd: None,
+            spend_max_daily_usd: None,
+            spend_approval_usd: None,
+            max_context_tokens: None,
+            fallback_endpoint: None,
+            fallback_model_archetype: None,
+            fallback_model: None,
+            fallback_secret_key: None,
+            default_persona: None,
+        })
+        .await
+        .unwrap();
+
+        let user = db.create_user("0xabc", None, UserRole::Member).await.unwrap();
+        let session = db.create_session_for_user(&user).await.unwrap();
+        assert_eq!(session.expires_at - session.created_at, Duration::hours(2));
+
+        // Not sliding: validating leaves the expiry alone, refreshing moves it
+        let validated = db.validate_session(&session.token).await.unwrap().unwrap();
+        assert_eq!(validated.expires_at, session.expires_at);
+        let refreshed = db.refresh_session(&session.token).await.unwrap().unwrap();
+        assert!(refreshed.expires_at > session.expires_at);
+
+        let expired = db.backend.create_session_for_user(&user, Duration::seconds(-1)).await.unwrap();
+        assert!(db.validate_session(&expired.token).await.unwrap().is_none());
+        assert!(db.refresh_session(&expired.token).await.unwrap().is_none());
+        assert_eq!(db.delete_expired_sessions().await.unwrap(), 1);
+        assert!(db.validate_session(&session.token).await.unwrap().is_some());
+    }
+
+    #[tokio::test]
+    async fn test_members_and_personal_api_keys() {
+        let db = Database::new(":memory:").unwrap();
+        let admin = db.ensure_admin_user("0xADMIN").await.unwrap();
+        assert_eq!(admin.public_address, "0xadmin");
+        assert_eq!(db.ensure_admin_user("0xadmin").await.unwrap().id, admin.id);
+        let member = db.create_user("0xmember", Some("Sam"), UserRole::Member).await.unwrap();
+        assert!(db.create_user("0xMEMBER", None, UserRole::Member).await.is_err());
+
+        // A member's login and tokens never carry the admin scope
+        let session = db.create_session_for_user(&member).await.unwrap();
+        let caller = db.authenticate(&session.token).await.unwrap().unwrap();
+        assert_eq!(caller.user.id, member.id);
+        assert!(caller.scopes.allows(Scope::Chat) && !caller.scopes.allows(Scope::Admin));
+        let token = db.create_api_token(member.id, "ci", &Scopes::full(), None).await.unwrap();
+        assert!(!db.authorize(&token.token).await.unwrap().unwrap().allows(Scope::Admin));
+
+        db.upsert_api_key("GITHUB_TOKEN", None, "shared").await.unwrap();
+        db.upsert_api_key("NEYNAR_API_KEY", None, "shared-neynar").await.unwrap();
+        db.upsert_api_key("GITHUB_TOKEN", Some(member.id), "mine").await.unwrap();
+        assert_eq!(db.get_api_key("GITHUB_TOKEN").await.unwrap().unwrap().api_key, "shared");
+        assert_eq!(db.list_api_keys().await.unwrap().len(), 2);
+
+        let effective = db.effective_api_keys(Some(member.id)).await.unwrap();
+        let github = effective.iter().find(|k| k.service_name == "GITHUB_TOKEN").unwrap();
+        assert_eq!((github.api_key.as_str(), github.user_id), ("mine", Some(member.id)));
+        assert_eq!(effective.len(), 2);
+        let admin_keys = db.effective_api_keys(Some(admin.id)).await.unwrap();
+        assert!(admin_keys.iter().all(|k| k.user_id.is_none()));
+
+        // Deleting a user signs them out and drops their tokens and personal keys
+        assert!(db.delete_user(member.id).await.unwrap());
+        assert!(db.authenticate(&session.token).await.unwrap().is_none());
+        assert!(db.authenticate(&token.token).await.unwrap().is_none());
+        assert!(db.list_api_keys_owned_by(Some(member.id)).await.unwrap().is_empty());
+        assert!(!db.delete_user(member.id).await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_personal_provider_keys() {
+        let db = Database::new(":memory:").unwrap();
+        let member = db.create_user("0xmember", None, UserRole::Member).await.unwrap();
+        let claude = AgentSettings {
+            model_archetype: "claude".to_string(),
+            secret_key: Some("sk-instance".to_string()),
+            ..AgentSettings::default()
+        };
+
+        // Without a personal key the settings' own key is kept
+        let mut settings = claude.clone();
+        assert_eq!(db.apply_provider_key(&mut settings, Some(member.id)).await.unwrap(), None);
+        assert_eq!(settings.secret_key.as_deref(), Some("sk-instance"));
+
+        let mine = db.upsert_api_key("ANTHROPIC_API_KEY", Some(member.id), "sk-mine").await.unwrap();
+        let mut settings = claude.clone();
+        assert_eq!(db.apply_provider_key(&mut settings, Some(member.id)).await.unwrap(), Some(mine.id));
+        assert_eq!(settings.secret_key.as_deref(), Some("sk-mine"));
+        let mut settings = claude.clone();
+        assert_eq!(db.apply_provider_key(&mut settings, None).await.unwrap(), None);
+        assert_eq!(settings.secret_key.as_deref(), Some("sk-instance"));
+
+        // The shared key stands in for a missing instance key
+        db.upsert_api_key("ANTHROPIC_API_KEY", None, "sk-shared").await.unwrap();
+        let mut settings = AgentSettings { secret_key: None, ..claude };
+        assert_eq!(db.apply_provider_key(&mut settings, None).await.unwrap(), None);
+        assert_eq!(settings.secret_key.as_deref(), Some("sk-shared"));
+    }
+
+    #[tokio::test]
+    async fn test_totp_steps_are_per_user() {
+        let db = Database::new(":memory:").unwrap();
+        let alice = db.create_user("0xaaa", None, UserRole::Admin).await.unwrap();
+        let bob = db.create_user("0xbbb", None, UserRole::Admin).await.unwrap();
+
+        assert!(db.claim_totp_step(alice.id, 100).await.unwrap());
+        assert!(!db.claim_totp_step(alice.id, 100).await.unwrap());
+        assert!(!db.claim_totp_step(alice.id, 99).await.unwrap());
+        // Another admin's use of the same step doesn't count against them
+        assert!(db.claim_totp_step(bob.id, 100).await.unwrap());
+        assert!(db.claim_totp_step(alice.id, 101).await.unwrap());
+    }
+}
@@ -0,0 +1,444 @@
+//! Versioned schema migrations
+//!
+//! `Database::init` creates the baseline schema with `CREATE TABLE IF NOT EXISTS`,
+//! which can't change a table that already exists. Schema changes from here on
+//! are SQL files in `db/migrations/`, embedded at compile time and applied in
+//! version order. Each one runs in its own transaction together with its row in
+//! `schema_migrations`, so a failed migration leaves nothing half-applied.
+//!
+//! To add one: create `NNNN_description.sql` with the next version number and
+//! append it to `MIGRATIONS`. Never edit a migration that has shipped.
+//!
+//! The shared tables on a Postgres backend have their own numbered series in
+//! `db/migrations/postgres/`, listed in `POSTGRES_MIGRATIONS` and applied the
+//! same way under an advisory lock, so instances starting together take turns.
+
+use rusqlite::{Connection, Result as SqliteResult, TransactionBehavior};
+use serde::Serialize;
+
+use super::Database;
+
+/// One embedded migration
+#[derive(Debug)]
+pub struct Migration {
+    pub version: i64,
+    pub name: &'static str,
+    sql: &'static str,
+}
+
+/// Applied and known schema versions
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct MigrationStatus {
+    /// Highest applied migration
+    pub current: i64,
+    /// Highest migration embedded in this build
+    pub latest: i64,
+}
+
+/// All migrations, in ascending version order
+const MIGRATIONS: &[Migration] = &[
+    Migration {
+        version: 1,
+        name: "session_messages_index",
+        sql: include_str!("migrations/0001_session_messages_index.sql"),
+    },
+    Migration {
+        version: 2,
+        name: "api_tokens",
+        sql: include_str!("migrations/0002_api_tokens.sql"),
+    },
+    Migration {
+        version: 3,
+        name: "session_settings",
+        sql: include_str!("migrations/0003_session_settings.sql"),
+    },
+    Migration {
+        version: 4,
+        name: "rate_limits",
+        sql: include_str!("migrations/0004_rate_limits.sql"),
+    },
+    Migration {
+        version: 5,
+        name: "allowed_models",
+        sql: include_str!("migrations/0005_allowed_models.sql"),
+    },
+    Migration {
+        version: 6,
+        name: "token_cache",
+        sql: include_str!("migrations/0006_token_cache.sql"),
+    },
+    Migration {
+        version: 7,
+        name: "wallets",
+        sql: include_str!("migrations/0007_wallets.sql"),
+    },
+    Migration {
+        version: 8,
+        name: "transactions",
+        sql: include_str!("migrations/0008_transactions.sql"),
+    },
+    Migration {
+        version: 9,
+        name: "spending_policy",
+        sql: include_str!("migrations/0009_spending_policy.sql"),
+    },
+    Migration {
+        version: 10,
+        name: "registers",
+        sql: include_str!("migrations/0010_registers.sql"),
+    },
+    Migration {
+        version: 11,
+        name: "skill_triggers",
+        sql: include_str!("migrations/0011_skill_triggers.sql"),
+    },
+    Migration {
+        version: 12,
+        name: "skill_syncs",
+        sql: include_str!("migrations/0012_skill_syncs.sql"),
+    },
+    Migration {
+        version: 13,
+        name: "scheduled_tasks",
+        sql: include_str!("migrations/0013_scheduled_tasks.sql"),
+    },
+    Migration {
+        version: 14,
+        name: "webhooks",
+        sql: include_str!("migrations/0014_webhooks.sql"),
+    },
+    Migration {
+        version: 15,
+        name: "usage_cache_tokens",
+        sql: include_str!("migrations/0015_usage_cache_tokens.sql"),
+    },
+    Migration {
+        version: 16,
+        name: "max_context_tokens",
+        sql: include_str!("migrations/0016_max_context_tokens.sql"),
+    },
+    Migration {
+        version: 17,
+        name: "provider_failover",
+        sql: include_str!("migrations/0017_provider_failover.sql"),
+    },
+    Migration {
+        version: 18,
+        name: "agent_plans",
+        sql: include_str!("migrations/0018_agent_plans.sql"),
+    },
+    Migration {
+        version: 19,
+        name: "archetype_profiles",
+        sql: include_str!("migrations/0019_archetype_profiles.sql"),
+    },
+    Migration {
+        version: 20,
+        name: "agent_job_response_format",
+        sql: include_str!("migrations/0020_agent_job_response_format.sql"),
+    },
+    Migration {
+        version: 21,
+        name: "embeddings",
+        sql: include_str!("migrations/0021_embeddings.sql"),
+    },
+    Migration {
+        version: 22,
+        name: "prompt_templates",
+        sql: include_str!("migrations/0022_prompt_templates.sql"),
+    },
+    Migration {
+        version: 23,
+        name: "personas",
+        sql: include_str!("migrations/0023_personas.sql"),
+    },
+    Migration {
+        version: 24,
+        name: "tool_invocations",
+        sql: include_str!("migrations/0024_tool_invocations.sql"),
+    },
+    Migration {
+        version: 25,
+        name: "tool_policies",
+        sql: include_str!("migrations/0025_tool_policies.sql"),
+    },
+    Migration {
+        version: 26,
+        name: "login_attempts",
+        sql: include_str!("migrations/0026_login_attempts.sql"),
+    },
+    Migration {
+        version: 27,
+        name: "users",
+        sql: include_str!("migrations/0027_users.sql"),
+    },
+    Migration {
+        version: 28,
+        name: "usage_api_key",
+        sql: include_str!("migrations/0028_usage_api_key.sql"),
+    },
+    Migration {
+        version: 29,
+        name: "job_and_memory_owners",
+        sql: include_str!("migrations/0029_job_and_memory_owners.sql"),
+    },
+    Migration {
+        version: 30,
+        name: "totp_last_step",
+        sql: include_str!("migrations/0030_totp_last_step.sql"),
+    },
+    Migration {
+        version: 31,
+        name: "webhook_and_schedule_owners",
+        sql: include_str!("migrations/0031_webhook_and_schedule_owners.sql"),
+    },
+    Migration {
+        version: 32,
+        name: "approval_owners",
+        sql: include_str!("migrations/0032_approval_owners.sql"),
+    },
+];
+
+/// Migrations for the shared tables on a Postgres backend, in ascending version order
+const POSTGRES_MIGRATIONS: &[Migration] = &[
+    Migration {
+        version: 1,
+        name: "shared_state",
+        sql: include_str!("migrations/postgres/0001_shared_state.sql"),
+    },
+    Migration {
+        version: 2,
+        name: "login_attempts",
+        sql: include_str!("migrations/postgres/0002_login_attempts.sql"),
+    },
+];
+
+/// Arbitrary key for the advisory lock serializing Postgres migrations across instances
+const POSTGRES_LOCK_KEY: i64 = 0x5354_4152_4b00;
+
+/// The newest version in `migrations`, when the applied schema is already past it
+fn ahead_of(current: i64, migrations: &[Migration]) -> Option<i64> {
+    let latest_known = migrations.last().map_or(0, |m| m.version);
+    (current > latest_known).then_some(latest_known)
+}
+
+/// Create the bookkeeping table and apply every pending migration
+pub(super) fn apply_pending(conn: &mut Connection) -> SqliteResult<Vec<&'static Migration>> {
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS schema_migrations (
+            version INTEGER PRIMARY KEY,
+            name TEXT NOT NULL,
+            applied_at TEXT NOT NULL
+        )",
+        [],
+    )?;
+
+    let current = current_version(conn)?;
+    if let Some(latest_known) = ahead_of(current, MIGRATIONS) {
+        log::warn!(
+            "Database schema is at version {} but this build only knows up to {}; was it migrated by a newer release?",
+            current,
+            latest_known
+        );
+    }
+
+    let mut applied = Vec::new();
+    for migration in MIGRATIONS {
+        // IMMEDIATE takes the write lock up front, so two processes starting on
+        // the same file can't both apply a migration
+        let tx = conn.transaction_with_behavior(TransactionBehavior::Immediate)?;
+        let done: bool = tx.query_row(
+            "SELECT COUNT(*) FROM schema_migrations WHERE version = ?1",
+            [migration.version],
+            |row| row.get::<_, i64>(0).map(|c| c > 0),
+        )?;
+        if done {
+            continue;
+        }
+
+        log::info!("[MIGRATE] Applying {:04}_{}", migration.version, migration.name);
+        tx.execute_batch(migration.sql)?;
+        tx.execute(
+            "INSERT INTO schema_migrations (version, name, applied_at) VALUES (?1, ?2, ?3)",
+            rusqlite::params![migration.version, migration.name, chrono::Utc::now().to_rfc3339()],
+        )?;
+        tx.commit()?;
+        applied.push(migration);
+    }
+
+    Ok(applied)
+}
+
+fn current_version(conn: &Connection) -> SqliteResult<i64> {
+    conn.query_row("SELECT COALESCE(MAX(version), 0) FROM schema_migrations", [], |row| row.get(0))
+}
+
+/// Postgres counterpart of `apply_pending`, for the shared tables
+pub(super) async fn apply_pending_postgres(
+    client: &mut tokio_postgres::Client,
+) -> Result<Vec<&'static Migration>, tokio_postgres::Error> {
+    // Concurrent CREATE TABLE IF NOT EXISTS can still collide, so this takes the lock too
+    let tx = client.transaction().await?;
+    tx.execute("SELECT pg_advisory_xact_lock($1)", &[&POSTGRES_LOCK_KEY]).await?;
+    tx.batch_execute(
+        "CREATE TABLE IF NOT EXISTS schema_migrations (
+            version BIGINT PRIMARY KEY,
+            name TEXT NOT NULL,
+            applied_at TIMESTAMPTZ NOT NULL
+        )",
+    )
+    .await?;
+    tx.commit().await?;
+
+    let current = current_version_postgres(client).await?;
+    if let Some(latest_known) = ahead_of(current, POSTGRES_MIGRATIONS) {
+        log::warn!(
+            "Postgres schema is at version {} but this build only knows up to {}; was it migrated by a newer release?",
+            current,
+            latest_known
+        );
+    }
+
+    let mut applied = Vec::new();
+    for migration in POSTGRES_MIGRATIONS {
+        let tx = client.transaction().await?;
+        tx.execute("SELECT pg_advisory_xact_lock($1)", &[&POSTGRES_LOCK_KEY]).await?;
+        let done = tx
+            .query_opt("SELECT 1 FROM schema_migrations WHERE version = $1", &[&migration.version])
+            .await?
+            .is_some();
+        if done {
+            continue;
+        }
+
+        log::info!("[MIGRATE] Applying Postgres {:04}_{}", migration.version, migration.name);
+        tx.batch_execute(migration.sql).await?;
+        tx.execute(
+            "INSERT INTO schema_migrations (version, name, applied_at) VALUES ($1, $2, $3)",
+            &[&migration.version, &migration.name, &chrono::Utc::now()],
+        )
+        .await?;
+        tx.commit().await?;
+        applied.push(migration);
+    }
+
+    Ok(applied)
+}
+
+pub(super) async fn current_version_postgres(client: &tokio_postgres::Client) -> Result<i64, tokio_postgres::Error> {
+    let row = client
+        .query_one("SELECT COALESCE(MAX(version), 0)::BIGINT FROM schema_migrations", &[])
+        .await?;
+    Ok(row.get(0))
+}
+
+impl Database {
+    /// Apply pending migrations, returning the ones that ran
+    pub fn migrate(&self) -> SqliteResult<Vec<&'static Migration>> {
+        let mut conn = self.conn_blocking()?;
+        apply_pending(&mut conn)
+    }
+
+    /// Highest applied migration version (0 if none)
+    pub fn schema_version(&self) -> SqliteResult<i64> {
+        let conn = self.conn_blocking()?;
+        current_version(&conn)
+    }
+    /// Applied schema version next to the newest one this build knows
+    pub async fn migration_status(&self) -> SqliteResult<MigrationStatus> {
+        let conn = self.conn().await?;
+        Ok(MigrationStatus {
+            current: current_version(&conn)?,
+            latest: MIGRATIONS.last().map_or(0, |m| m.version),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_versions_are_ascending() {
+        for migrations in [MIGRATIONS, POSTGRES_MIGRATIONS] {
+            for pair in migrations.windows(2) {
+                assert!(pair[0].version < pair[1].version, "{} out of order", pair[1].name);
+            }
+        }
+    }
+
+    #[test]
+    fn test_versions_are_contiguous_and_named_once() {
+        for migrations in [MIGRATIONS, POSTGRES_MIGRATIONS] {
+            for (i, migration) in migrations.iter().enumerate() {
+                assert_eq!(migration.version, i as i64 + 1, "gap before {}", migration.name);
+            }
+            let names: std::collections::HashSet<_> = migrations.iter().map(|m| m.name).collect();
+            assert_eq!(names.len(), migrations.len());
+        }
+    }
+
+    #[test]
+    fn test_ahead_of_latest_known() {
+        let latest = POSTGRES_MIGRATIONS.last().unwrap().version;
+        assert_eq!(ahead_of(0, POSTGRES_MIGRATIONS), None);
+        assert_eq!(ahead_of(latest, POSTGRES_MIGRATIONS), None);
+        assert_eq!(ahead_of(latest + 1, POSTGRES_MIGRATIONS), Some(latest));
+        assert_eq!(ahead_of(1, &[]), Some(0));
+        assert_eq!(ahead_of(0, &[]), None);
+    }
+
+    #[test]
+    fn test_schema_version_detects_newer_release() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let db = Database::new(dir.path().join("stark.db").to_str().unwrap()).unwrap();
+        let latest = MIGRATIONS.last().unwrap().version;
+
+        // A row left behind by a newer build
+        db.conn_blocking()
+            .unwrap()
+            .execute(
+                "INSERT INTO schema_migrations (version, name, applied_at) VALUES (?1, 'from_the_future', '')",
+                [latest + 1],
+            )
+            .unwrap();
+        assert_eq!(db.schema_version().unwrap(), latest + 1);
+        assert_eq!(ahead_of(db.schema_version().unwrap(), MIGRATIONS), Some(latest));
+        // Still starts, with nothing to apply
+        assert!(db.migrate().unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_migrations_apply_once() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let path = dir.path().join("stark.db");
+        let path = path.to_str().unwrap();
+
+        // Database::new applies everything on open
+        let db = Database::new(path).unwrap();
+        let latest = MIGRATIONS.last().unwrap().version;
+        assert_eq!(db.schema_version().unwrap(), latest);
+        assert!(db.migrate().unwrap().is_empty());
+
+        let index_exists: i64 = db
+            .conn_blocking()
+            .unwrap()
+            .query_row(
+                "SELECT COUNT(*) FROM sqlite_master WHERE type = 'index' AND name = 'idx_session_messages_session'",
+                [],
+                |row| row.get(0),
+            )
+            .unwrap();
+        assert_eq!(index_exists, 1);
+        drop(db);
+
+        // Reopening doesn't reapply
+        let db = Database::new(path).unwrap();
+        let recorded: i64 = db
+            .conn_blocking()
+            .unwrap()
+            .query_row("SELECT COUNT(*) FROM schema_migrations", [], |row| row.get(0))
+            .unwrap();
+        assert_eq!(recorded, MIGRATIONS.len() as i64);
+    }
+}
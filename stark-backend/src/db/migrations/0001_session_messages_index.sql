@@ -0,0 +1,2 @@
+-- Transcript loads, counts and pruning all filter session_messages by session
+CREATE INDEX IF NOT EXISTS idx_session_messages_session ON session_messages(session_id, created_at);
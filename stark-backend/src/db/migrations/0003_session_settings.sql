@@ -0,0 +1,5 @@
+-- Login session lifetime and sliding expiry, per agent settings profile
+ALTER TABLE agent_settings ADD COLUMN session_ttl_hours INTEGER;
+ALTER TABLE agent_settings ADD COLUMN session_sliding INTEGER NOT NULL DEFAULT 1;
+
+CREATE INDEX IF NOT EXISTS idx_auth_sessions_expires_at ON auth_sessions(expires_at);
@@ -0,0 +1,16 @@
+-- Rate limits for chat and agent endpoints, and a record of requests they rejected
+ALTER TABLE bot_settings ADD COLUMN rate_limit_per_minute INTEGER NOT NULL DEFAULT 30;
+ALTER TABLE bot_settings ADD COLUMN rate_limit_burst INTEGER NOT NULL DEFAULT 10;
+
+CREATE TABLE IF NOT EXISTS rate_limit_violations (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    -- "token" or "ip"
+    key_type TEXT NOT NULL,
+    -- Client IP, or the first characters of the bearer token
+    key TEXT NOT NULL,
+    method TEXT NOT NULL,
+    path TEXT NOT NULL,
+    occurred_at TEXT NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS idx_rate_limit_violations_occurred ON rate_limit_violations(occurred_at);
@@ -0,0 +1,2 @@
+-- Models a chat request may select per agent settings profile (comma-separated)
+ALTER TABLE agent_settings ADD COLUMN allowed_models TEXT NOT NULL DEFAULT '';
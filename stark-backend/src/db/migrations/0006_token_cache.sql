@@ -0,0 +1,14 @@
+-- ERC-20 metadata read on-chain by token_lookup for tokens missing from config/tokens.ron
+CREATE TABLE IF NOT EXISTS token_cache (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    network TEXT NOT NULL,
+    -- Lowercase contract address
+    address TEXT NOT NULL,
+    symbol TEXT NOT NULL,
+    name TEXT NOT NULL,
+    decimals INTEGER NOT NULL,
+    created_at TEXT NOT NULL,
+    UNIQUE(network, address)
+);
+
+CREATE INDEX IF NOT EXISTS idx_token_cache_symbol ON token_cache(network, symbol COLLATE NOCASE);
@@ -0,0 +1,16 @@
+-- Wallets the bot can sign with: a private key sealed under the master key,
+-- or an external JSON-RPC signer holding the key itself
+CREATE TABLE IF NOT EXISTS wallets (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    name TEXT NOT NULL UNIQUE,
+    -- 'local' or 'external'
+    kind TEXT NOT NULL,
+    -- Checksummed address
+    address TEXT NOT NULL,
+    -- Sealed private key (local wallets only, see db::secrets)
+    encrypted_key TEXT,
+    -- JSON-RPC endpoint (external wallets only)
+    signer_url TEXT,
+    is_default INTEGER NOT NULL DEFAULT 0,
+    created_at TEXT NOT NULL
+);
@@ -0,0 +1,30 @@
+-- Audit trail of on-chain actions the bot performs (swaps, transfers,
+-- contract calls, x402 payments), with the session and tool call behind each
+CREATE TABLE IF NOT EXISTS transactions (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    -- 'swap', 'transfer', 'contract_call' or 'x402_payment'
+    kind TEXT NOT NULL,
+    -- 'pending', 'confirmed', 'reverted' or 'failed'
+    status TEXT NOT NULL,
+    network TEXT NOT NULL,
+    -- NULL until broadcast (or when an x402 facilitator returned no hash)
+    tx_hash TEXT,
+    from_address TEXT,
+    to_address TEXT,
+    -- Native value in wei, or the token amount for x402 payments
+    value TEXT NOT NULL DEFAULT '0',
+    gas_used TEXT,
+    block_number INTEGER,
+    channel_id INTEGER,
+    session_id INTEGER,
+    tool_name TEXT,
+    tool_call_id TEXT,
+    -- JSON with kind-specific details (quote, asset, resource, error, ...)
+    details TEXT,
+    created_at TEXT NOT NULL,
+    updated_at TEXT NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS idx_transactions_created ON transactions(created_at);
+CREATE INDEX IF NOT EXISTS idx_transactions_session ON transactions(session_id);
+CREATE INDEX IF NOT EXISTS idx_transactions_tx_hash ON transactions(tx_hash);
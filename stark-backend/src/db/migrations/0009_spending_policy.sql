@@ -0,0 +1,28 @@
+-- Spending limits for value-moving tools, per agent settings profile (USD)
+ALTER TABLE agent_settings ADD COLUMN spend_max_tx_usd REAL;
+ALTER TABLE agent_settings ADD COLUMN spend_max_daily_usd REAL;
+ALTER TABLE agent_settings ADD COLUMN spend_approval_usd REAL;
+
+-- USD value of a transaction when it was authorized (counts toward the daily limit)
+ALTER TABLE transactions ADD COLUMN value_usd REAL;
+
+-- Value-moving tool calls above the approval threshold, waiting on an operator
+CREATE TABLE IF NOT EXISTS approvals (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    -- 'pending', 'approved', 'rejected' or 'expired'
+    status TEXT NOT NULL DEFAULT 'pending',
+    tool_name TEXT NOT NULL,
+    tool_call_id TEXT,
+    channel_id INTEGER,
+    session_id INTEGER,
+    -- What the tool is about to do, e.g. "Swap 1000 USDC for WETH on base"
+    description TEXT NOT NULL,
+    -- NULL when the amount could not be priced
+    amount_usd REAL,
+    -- Why the spend needs approval
+    reason TEXT NOT NULL,
+    created_at TEXT NOT NULL,
+    decided_at TEXT
+);
+
+CREATE INDEX IF NOT EXISTS idx_approvals_status ON approvals(status);
@@ -0,0 +1,11 @@
+-- Tool registers, kept per chat session so multi-turn flows survive restarts
+CREATE TABLE IF NOT EXISTS registers (
+    session_id INTEGER NOT NULL,
+    key TEXT NOT NULL,
+    -- JSON value
+    value TEXT NOT NULL,
+    -- Tool that last set the register
+    source_tool TEXT NOT NULL,
+    updated_at TEXT NOT NULL,
+    PRIMARY KEY (session_id, key)
+);
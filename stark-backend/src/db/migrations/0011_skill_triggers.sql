@@ -0,0 +1,2 @@
+-- Phrases that suggest a skill applies to a message (JSON array from the SKILL.md frontmatter)
+ALTER TABLE skills ADD COLUMN triggers TEXT NOT NULL DEFAULT '[]';
@@ -0,0 +1,16 @@
+-- History of skill marketplace syncs from a git repository, with the commit
+-- and skill versions each one imported
+CREATE TABLE IF NOT EXISTS skill_syncs (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    repo_url TEXT NOT NULL,
+    branch TEXT NOT NULL,
+    -- NULL when the clone or pull failed
+    commit_sha TEXT,
+    -- JSON array of {name, version} for every skill imported
+    skills TEXT NOT NULL DEFAULT '[]',
+    -- JSON array of error messages (git failures, invalid frontmatter)
+    errors TEXT NOT NULL DEFAULT '[]',
+    synced_at TEXT NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS idx_skill_syncs_synced ON skill_syncs(synced_at);
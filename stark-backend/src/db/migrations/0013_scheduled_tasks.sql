@@ -0,0 +1,26 @@
+-- Recurring CodeEngineer tasks: each time the cron expression fires a job is
+-- queued in agent_jobs with the schedule's prompt and tool set
+CREATE TABLE IF NOT EXISTS scheduled_tasks (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    name TEXT NOT NULL,
+    -- 5-field cron expression (or 6/7 fields with seconds), evaluated in UTC
+    cron_expression TEXT NOT NULL,
+    prompt TEXT NOT NULL,
+    -- JSON array of tool names; empty means the default CodeEngineer tools
+    tools TEXT NOT NULL DEFAULT '[]',
+    -- Workspace shared by every run; NULL means schedule-<id>
+    workspace TEXT,
+    max_iterations INTEGER NOT NULL,
+    enabled INTEGER NOT NULL DEFAULT 1,
+    last_run_at TEXT,
+    next_run_at TEXT,
+    last_job_id TEXT,
+    created_at TEXT NOT NULL,
+    updated_at TEXT NOT NULL
+);
+
+-- Jobs remember their tool set and the schedule that queued them
+ALTER TABLE agent_jobs ADD COLUMN tools TEXT NOT NULL DEFAULT '[]';
+ALTER TABLE agent_jobs ADD COLUMN schedule_id INTEGER;
+
+CREATE INDEX IF NOT EXISTS idx_agent_jobs_schedule ON agent_jobs(schedule_id);
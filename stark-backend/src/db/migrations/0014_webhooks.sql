@@ -0,0 +1,21 @@
+-- Inbound webhooks: a signed POST to /api/hooks/<hook_id> queues an agent job
+-- with the payload rendered into the hook's prompt template
+CREATE TABLE IF NOT EXISTS webhooks (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    -- Random public id used in the trigger URL
+    hook_id TEXT NOT NULL UNIQUE,
+    name TEXT NOT NULL,
+    -- HMAC-SHA256 signing secret, sealed under the master key when one is set
+    secret TEXT NOT NULL,
+    prompt_template TEXT NOT NULL,
+    -- JSON array of tool names; empty means the default CodeEngineer tools
+    tools TEXT NOT NULL DEFAULT '[]',
+    -- Workspace shared by every run; NULL means hook-<hook_id>
+    workspace TEXT,
+    max_iterations INTEGER NOT NULL,
+    enabled INTEGER NOT NULL DEFAULT 1,
+    last_triggered_at TEXT,
+    last_job_id TEXT,
+    created_at TEXT NOT NULL,
+    updated_at TEXT NOT NULL
+);
@@ -0,0 +1,3 @@
+-- Prompt tokens read from / written to the provider's prompt cache, per chat request
+ALTER TABLE usage ADD COLUMN cache_read_tokens INTEGER NOT NULL DEFAULT 0;
+ALTER TABLE usage ADD COLUMN cache_write_tokens INTEGER NOT NULL DEFAULT 0;
@@ -0,0 +1,2 @@
+-- Token budget for an agent run's working context, per agent settings profile
+ALTER TABLE agent_settings ADD COLUMN max_context_tokens INTEGER;
@@ -0,0 +1,8 @@
+-- Secondary provider agent runs fail over to (all optional; unset parts reuse the primary's)
+ALTER TABLE agent_settings ADD COLUMN fallback_endpoint TEXT;
+ALTER TABLE agent_settings ADD COLUMN fallback_model_archetype TEXT;
+ALTER TABLE agent_settings ADD COLUMN fallback_model TEXT;
+ALTER TABLE agent_settings ADD COLUMN fallback_secret_key TEXT;
+
+-- Failed provider calls of a background agent run (JSON list of attempts)
+ALTER TABLE agent_jobs ADD COLUMN provider_attempts TEXT NOT NULL DEFAULT '[]';
@@ -0,0 +1,4 @@
+-- Plan/act mode for agent jobs: whether the job starts with a planning call,
+-- and the resulting plan (JSON list of steps with their status)
+ALTER TABLE agent_jobs ADD COLUMN planning INTEGER NOT NULL DEFAULT 0;
+ALTER TABLE agent_jobs ADD COLUMN plan TEXT NOT NULL DEFAULT '[]';
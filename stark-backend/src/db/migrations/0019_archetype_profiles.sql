@@ -0,0 +1,8 @@
+-- Operator overrides of model archetype profiles (request defaults and prompt tweaks)
+CREATE TABLE IF NOT EXISTS archetype_profiles (
+    -- Archetype id, e.g. "deepseek"
+    archetype TEXT PRIMARY KEY,
+    -- JSON object of the overridden fields
+    overrides TEXT NOT NULL,
+    updated_at TEXT NOT NULL
+);
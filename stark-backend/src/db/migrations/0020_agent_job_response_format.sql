@@ -0,0 +1,2 @@
+-- Structured output for agent jobs: the response_format their answer must match (JSON, NULL for free text)
+ALTER TABLE agent_jobs ADD COLUMN response_format TEXT;
@@ -0,0 +1,19 @@
+-- Texts embedded through /api/embeddings, grouped into collections for
+-- similarity search
+CREATE TABLE IF NOT EXISTS embeddings (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    collection TEXT NOT NULL,
+    -- Caller's id for the text; storing it again replaces the row
+    external_id TEXT,
+    content TEXT NOT NULL,
+    -- JSON object kept with the text
+    metadata TEXT,
+    -- Little-endian f32 array
+    embedding BLOB NOT NULL,
+    model TEXT NOT NULL,
+    dimensions INTEGER NOT NULL,
+    created_at TEXT NOT NULL,
+    UNIQUE (collection, external_id)
+);
+
+CREATE INDEX IF NOT EXISTS idx_embeddings_collection ON embeddings(collection, model);
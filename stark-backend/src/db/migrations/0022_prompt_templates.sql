@@ -0,0 +1,12 @@
+-- Reusable prompts with {{variable}} placeholders, rendered through
+-- /api/templates and included in schedule and webhook prompts with {{> name}}
+CREATE TABLE IF NOT EXISTS prompt_templates (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    name TEXT NOT NULL UNIQUE,
+    description TEXT NOT NULL DEFAULT '',
+    body TEXT NOT NULL,
+    -- JSON object of default variable values
+    variables TEXT NOT NULL DEFAULT '{}',
+    created_at TEXT NOT NULL,
+    updated_at TEXT NOT NULL
+);
@@ -0,0 +1,16 @@
+-- Named system prompts with their own tool groups and model, picked per
+-- conversation (chat_sessions.persona) or by default (agent_settings.default_persona)
+CREATE TABLE IF NOT EXISTS personas (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    name TEXT NOT NULL UNIQUE,
+    description TEXT NOT NULL DEFAULT '',
+    system_prompt TEXT NOT NULL,
+    -- JSON array of tool group names; empty means the channel's own groups
+    tool_groups TEXT NOT NULL DEFAULT '[]',
+    model TEXT,
+    created_at TEXT NOT NULL,
+    updated_at TEXT NOT NULL
+);
+
+ALTER TABLE chat_sessions ADD COLUMN persona TEXT;
+ALTER TABLE agent_settings ADD COLUMN default_persona TEXT;
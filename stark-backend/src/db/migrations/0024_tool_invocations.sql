@@ -0,0 +1,28 @@
+-- Audit log of every tool execution, including calls refused by the tool
+-- policy, with the run (session, job, request) that made it
+CREATE TABLE IF NOT EXISTS tool_invocations (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    tool_name TEXT NOT NULL,
+    -- NULL when no tool has that name
+    tool_group TEXT,
+    -- JSON arguments with credentials redacted and long strings cut
+    arguments TEXT NOT NULL,
+    -- 'success', 'error' or 'denied'
+    outcome TEXT NOT NULL,
+    -- Start of the result or error, with secrets scrubbed
+    result_summary TEXT NOT NULL,
+    duration_ms INTEGER NOT NULL,
+    channel_id INTEGER,
+    channel_type TEXT,
+    session_id INTEGER,
+    job_id TEXT,
+    user_id TEXT,
+    tool_call_id TEXT,
+    request_id TEXT,
+    created_at TEXT NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS idx_tool_invocations_created ON tool_invocations(created_at);
+CREATE INDEX IF NOT EXISTS idx_tool_invocations_tool ON tool_invocations(tool_name, created_at);
+CREATE INDEX IF NOT EXISTS idx_tool_invocations_session ON tool_invocations(session_id);
+CREATE INDEX IF NOT EXISTS idx_tool_invocations_job ON tool_invocations(job_id);
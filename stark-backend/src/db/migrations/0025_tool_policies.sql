@@ -0,0 +1,3 @@
+-- Per-token and per-conversation tool policies (JSON `ToolPolicy`; NULL: no policy)
+ALTER TABLE api_tokens ADD COLUMN tool_policy TEXT;
+ALTER TABLE chat_sessions ADD COLUMN tool_policy TEXT;
@@ -0,0 +1,22 @@
+-- Consecutive failed sign-ins per client IP and the lockout they earned
+CREATE TABLE IF NOT EXISTS login_attempts (
+    ip TEXT PRIMARY KEY,
+    failures INTEGER NOT NULL,
+    last_failure_at TEXT NOT NULL,
+    -- NULL until the failures pass the free allowance
+    locked_until TEXT
+);
+
+-- Audit log of every failed sign-in or second-factor check
+CREATE TABLE IF NOT EXISTS login_failures (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    ip TEXT NOT NULL,
+    public_address TEXT,
+    -- 'invalid_address', 'unauthorized_address', 'invalid_challenge',
+    -- 'invalid_signature', 'invalid_totp' or 'locked_out'
+    reason TEXT NOT NULL,
+    occurred_at TEXT NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS idx_login_failures_occurred ON login_failures(occurred_at);
+CREATE INDEX IF NOT EXISTS idx_login_failures_ip ON login_failures(ip);
@@ -0,0 +1,35 @@
+-- People allowed to sign in; sessions, tokens, personal API keys and
+-- conversations belong to one of them
+CREATE TABLE IF NOT EXISTS users (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    public_address TEXT UNIQUE NOT NULL,
+    name TEXT,
+    -- 'admin' or 'member'
+    role TEXT NOT NULL DEFAULT 'member',
+    created_at TEXT NOT NULL
+);
+
+-- Rows from before users existed are claimed by the admin at startup
+ALTER TABLE auth_sessions ADD COLUMN user_id INTEGER REFERENCES users(id) ON DELETE CASCADE;
+ALTER TABLE api_tokens ADD COLUMN user_id INTEGER REFERENCES users(id) ON DELETE CASCADE;
+
+-- NULL: a channel or scheduler conversation, visible to admins only
+ALTER TABLE chat_sessions ADD COLUMN user_id INTEGER REFERENCES users(id) ON DELETE SET NULL;
+CREATE INDEX IF NOT EXISTS idx_chat_sessions_user ON chat_sessions(user_id);
+
+-- API keys: NULL user_id is the shared key, otherwise a user's personal key
+-- for the same service. Rebuilt to make service_name unique per owner.
+CREATE TABLE external_api_keys_new (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    service_name TEXT NOT NULL,
+    api_key TEXT NOT NULL,
+    user_id INTEGER REFERENCES users(id) ON DELETE CASCADE,
+    created_at TEXT NOT NULL,
+    updated_at TEXT NOT NULL
+);
+INSERT INTO external_api_keys_new (id, service_name, api_key, created_at, updated_at)
+    SELECT id, service_name, api_key, created_at, updated_at FROM external_api_keys;
+DROP TABLE external_api_keys;
+ALTER TABLE external_api_keys_new RENAME TO external_api_keys;
+CREATE UNIQUE INDEX IF NOT EXISTS idx_external_api_keys_owner
+    ON external_api_keys(service_name, IFNULL(user_id, 0));
@@ -0,0 +1,3 @@
+-- The user's own provider key a chat request ran on; NULL: the instance's key
+ALTER TABLE usage ADD COLUMN api_key_id INTEGER;
+CREATE INDEX IF NOT EXISTS idx_usage_api_key ON usage(api_key_id);
@@ -0,0 +1,9 @@
+-- The user an agent job was started by, or whose conversation a memory came
+-- from. NULL: scheduled, webhook and channel work, visible to admins only.
+ALTER TABLE agent_jobs ADD COLUMN user_id INTEGER;
+CREATE INDEX IF NOT EXISTS idx_agent_jobs_user ON agent_jobs(user_id);
+
+ALTER TABLE memories ADD COLUMN user_id INTEGER;
+CREATE INDEX IF NOT EXISTS idx_memories_user ON memories(user_id);
+UPDATE memories SET user_id = (SELECT user_id FROM chat_sessions WHERE chat_sessions.id = memories.session_id)
+    WHERE session_id IS NOT NULL;
@@ -0,0 +1,3 @@
+-- Step of the last TOTP code each admin signed in with, so every code works
+-- once per user without one admin's code refusing another's
+ALTER TABLE users ADD COLUMN totp_last_step INTEGER;
@@ -0,0 +1,7 @@
+-- The user who created a webhook or scheduled task; the jobs it queues run as
+-- them. NULL: created before owners were recorded, visible to admins only.
+ALTER TABLE webhooks ADD COLUMN user_id INTEGER;
+CREATE INDEX IF NOT EXISTS idx_webhooks_user ON webhooks(user_id);
+
+ALTER TABLE scheduled_tasks ADD COLUMN user_id INTEGER;
+CREATE INDEX IF NOT EXISTS idx_scheduled_tasks_user ON scheduled_tasks(user_id);
@@ -0,0 +1,4 @@
+-- The user whose conversation or job asked for the approval. NULL: a channel
+-- conversation, or requested before owners were recorded; admins only.
+ALTER TABLE approvals ADD COLUMN user_id INTEGER;
+CREATE INDEX IF NOT EXISTS idx_approvals_user ON approvals(user_id);
@@ -0,0 +1,80 @@
+-- State that instances behind one load balancer share. Databases set up by
+-- builds before versioned migrations already have these tables, hence
+-- IF NOT EXISTS here and nowhere after.
+CREATE TABLE IF NOT EXISTS users (
+    id BIGSERIAL PRIMARY KEY,
+    public_address TEXT UNIQUE NOT NULL,
+    name TEXT,
+    -- 'admin' or 'member'
+    role TEXT NOT NULL DEFAULT 'member',
+    created_at TIMESTAMPTZ NOT NULL
+);
+
+CREATE TABLE IF NOT EXISTS auth_sessions (
+    id BIGSERIAL PRIMARY KEY,
+    token TEXT UNIQUE NOT NULL,
+    public_address TEXT,
+    user_id BIGINT REFERENCES users(id) ON DELETE CASCADE,
+    created_at TIMESTAMPTZ NOT NULL,
+    expires_at TIMESTAMPTZ NOT NULL
+);
+CREATE INDEX IF NOT EXISTS idx_auth_sessions_expires_at ON auth_sessions(expires_at);
+
+CREATE TABLE IF NOT EXISTS auth_challenges (
+    id BIGSERIAL PRIMARY KEY,
+    public_address TEXT UNIQUE NOT NULL,
+    challenge TEXT NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL
+);
+
+CREATE TABLE IF NOT EXISTS api_tokens (
+    id BIGSERIAL PRIMARY KEY,
+    name TEXT UNIQUE NOT NULL,
+    token_hash TEXT UNIQUE NOT NULL,
+    prefix TEXT NOT NULL,
+    scopes TEXT NOT NULL,
+    tool_policy TEXT,
+    user_id BIGINT REFERENCES users(id) ON DELETE CASCADE,
+    created_at TIMESTAMPTZ NOT NULL,
+    last_used_at TIMESTAMPTZ
+);
+
+-- NULL user_id is the shared key, otherwise a user's personal key for the
+-- same service
+CREATE TABLE IF NOT EXISTS external_api_keys (
+    id BIGSERIAL PRIMARY KEY,
+    service_name TEXT NOT NULL,
+    api_key TEXT NOT NULL,
+    user_id BIGINT REFERENCES users(id) ON DELETE CASCADE,
+    created_at TIMESTAMPTZ NOT NULL,
+    updated_at TIMESTAMPTZ NOT NULL
+);
+CREATE UNIQUE INDEX IF NOT EXISTS idx_external_api_keys_owner
+    ON external_api_keys(service_name, COALESCE(user_id, 0));
+
+CREATE TABLE IF NOT EXISTS agent_settings (
+    id BIGSERIAL PRIMARY KEY,
+    endpoint TEXT UNIQUE NOT NULL,
+    model_archetype TEXT NOT NULL DEFAULT 'kimi',
+    max_tokens INTEGER NOT NULL DEFAULT 40000,
+    enabled BOOLEAN NOT NULL DEFAULT FALSE,
+    secret_key TEXT,
+    budget_max_tokens BIGINT,
+    budget_max_usd DOUBLE PRECISION,
+    session_budget_max_tokens BIGINT,
+    session_budget_max_usd DOUBLE PRECISION,
+    session_ttl_hours BIGINT,
+    session_sliding BOOLEAN NOT NULL DEFAULT TRUE,
+    allowed_models TEXT NOT NULL DEFAULT '',
+    spend_max_tx_usd DOUBLE PRECISION,
+    spend_max_daily_usd DOUBLE PRECISION,
+    spend_approval_usd DOUBLE PRECISION,
+    max_context_tokens BIGINT,
+    fallback_endpoint TEXT,
+    fallback_model_archetype TEXT,
+    fallback_model TEXT,
+    fallback_secret_key TEXT,
+    default_persona TEXT,
+    created_at TIMESTAMPTZ NOT NULL,
+    updated_at TIMESTAMPTZ NOT NULL
+);
@@ -0,0 +1,27 @@
+-- Step of the last TOTP code each admin signed in with, so a code can't be
+-- replayed on another instance
+ALTER TABLE users ADD COLUMN totp_last_step BIGINT;
+
+-- Consecutive failed sign-ins per client IP and the lockout they earned,
+-- shared so a lockout holds on every instance
+CREATE TABLE login_attempts (
+    ip TEXT PRIMARY KEY,
+    failures BIGINT NOT NULL,
+    last_failure_at TIMESTAMPTZ NOT NULL,
+    -- NULL until the failures pass the free allowance
+    locked_until TIMESTAMPTZ
+);
+
+-- Audit log of every failed sign-in or second-factor check
+CREATE TABLE login_failures (
+    id BIGSERIAL PRIMARY KEY,
+    ip TEXT NOT NULL,
+    public_address TEXT,
+    -- 'invalid_address', 'unauthorized_address', 'invalid_challenge',
+    -- 'invalid_signature', 'invalid_totp' or 'locked_out'
+    reason TEXT NOT NULL,
+    occurred_at TIMESTAMPTZ NOT NULL
+);
+
+CREATE INDEX idx_login_failures_occurred ON login_failures(occurred_at);
+CREATE INDEX idx_login_failures_ip ON login_failures(ip);
@@ -1,4 +1,36 @@
+pub mod backend;
+mod migrations;
+mod postgres;
+pub mod secrets;
 pub mod sqlite;
 mod tables;
 
+use std::sync::Arc;
+
+pub use backend::{DbError, DbResult};
+pub use postgres::{tls_connector, PostgresBackend};
+pub use secrets::KeyRing;
 pub use sqlite::Database;
+pub use tables::maintenance::DatabaseStats;
+
+/// Open the database named by `DATABASE_URL`
+///
+/// A SQLite path keeps everything in that file. A `postgres://` URL moves
+/// auth sessions, API keys and agent settings to Postgres so several instances
+/// can share them; everything else stays in the local SQLite file at
+/// `STARK_SQLITE_PATH`.
+///
+/// Pending migrations are applied to both, and API keys are encrypted with the
+/// master keys from the environment, if set.
+pub async fn connect(database_url: &str) -> DbResult<Database> {
+    let keys = KeyRing::from_env().map_err(DbError::Encryption)?;
+    if !backend::is_postgres_url(database_url) {
+        return Ok(Database::new(database_url)?.with_keys(keys));
+    }
+
+    let shared = PostgresBackend::connect(database_url).await?;
+    shared.migrate().await?;
+    Ok(Database::new(&crate::config::sqlite_path())?
+        .with_backend(Arc::new(shared))
+        .with_keys(keys))
+}
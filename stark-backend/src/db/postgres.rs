@@ -0,0 +1,864 @@
+//! Postgres backend for shared state (users, auth, API tokens, API keys, agent
+//! settings, sign-in lockouts)
+//!
+//! Queries run on a `deadpool-postgres` pool of `config::postgres_pool_size()`
+//! async connections. The schema comes from the numbered migrations in
+//! `db/migrations/postgres/`, applied by `migrate` when the backend is opened.
+//!
+//! Connections use TLS whenever the server offers it, verifying its certificate
+//! against the system trust store. `sslmode=require` in the URL refuses to fall
+//! back to plaintext; `sslmode=disable` never tries TLS.
+
+use std::str::FromStr;
+
+use async_trait::async_trait;
+use chrono::{DateTime, Duration, Utc};
+use deadpool_postgres::{Manager, ManagerConfig, Object, Pool, RecyclingMethod};
+use postgres_native_tls::MakeTlsConnector;
+use tokio_postgres::Row;
+
+use crate::config;
+use crate::models::{
+    parse_model_list, AgentSettings, ApiKey, ApiToken, LoginFailure, LoginFailureReason, Scopes, Session,
+    SessionPolicy, ToolPolicy, UpdateAgentSettingsRequest, User, UserRole,
+};
+use super::backend::{
+    generate_session_token, AgentSettingsStore, ApiKeyStore, ApiTokenStore, AuthStore,
+    DatabaseBackend, DbError, DbResult, LoginAttemptStore, UserStore, LOGIN_FAILURE_MEMORY_HOURS,
+};
+use super::migrations::{self, Migration};
+
+const USER_COLUMNS: &str = "id, public_address, name, role, created_at";
+
+const SESSION_COLUMNS: &str = "id, token, created_at, expires_at, user_id";
+
+const API_TOKEN_COLUMNS: &str = "id, name, scopes, prefix, created_at, last_used_at, tool_policy, user_id";
+
+const API_KEY_COLUMNS: &str = "id, service_name, api_key, created_at, updated_at, user_id";
+
+const AGENT_SETTINGS_COLUMNS: &str = "id, endpoint, model_archetype, max_tokens, enabled, secret_key,
+    budget_max_tokens, budget_max_usd, session_budget_max_tokens, session_budget_max_usd, created_at, updated_at,
+    session_ttl_hours, session_sliding, allowed_models, spend_max_tx_usd, spend_max_daily_usd, spend_approval_usd,
+    max_context_tokens, fallback_endpoint, fallback_model_archetype, fallback_model, fallback_secret_key,
+    default_persona";
+
+/// How long to wait for the server when the URL doesn't set `connect_timeout`
+const DEFAULT_CONNECT_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(10);
+
+/// Parse a Postgres URL, bounding the connect time so a dead server fails a
+/// checkout instead of hanging it
+fn pg_config(database_url: &str) -> DbResult<tokio_postgres::Config> {
+    let mut pg_config = tokio_postgres::Config::from_str(database_url)?;
+    if pg_config.get_connect_timeout().is_none() {
+        pg_config.connect_timeout(DEFAULT_CONNECT_TIMEOUT);
+    }
+    Ok(pg_config)
+}
+
+/// Connector for Postgres connections: TLS whenever the server offers it, with
+/// its certificate checked against the system trust store. The URL's `sslmode`
+/// decides whether plaintext is acceptable.
+pub fn tls_connector() -> Result<MakeTlsConnector, native_tls::Error> {
+    Ok(MakeTlsConnector::new(native_tls::TlsConnector::new()?))
+}
+
+/// Shared-state backend on a Postgres server
+pub struct PostgresBackend {
+    pool: Pool,
+}
+
+impl PostgresBackend {
+    /// Build the connection pool and check that the server answers
+    ///
+    /// Doesn't touch the schema; see `migrate`.
+    pub async fn connect(database_url: &str) -> DbResult<Self> {
+        let pg_config = pg_config(database_url)?;
+        let tls = tls_connector().map_err(|e| DbError::Unavailable(format!("TLS setup failed: {}", e)))?;
+        let manager = Manager::from_config(pg_config, tls, ManagerConfig { recycling_method: RecyclingMethod::Fast });
+        let pool = Pool::builder(manager)
+            .max_size(config::postgres_pool_size())
+            .build()
+            .map_err(|e| DbError::Unavailable(format!("failed to build pool: {}", e)))?;
+
+        let backend = Self { pool };
+        // Fail at startup rather than on the first login when the server is unreachable
+        drop(backend.client().await?);
+        Ok(backend)
+    }
+
+    /// Apply pending Postgres migrations, returning the ones that ran
+    pub async fn migrate(&self) -> DbResult<Vec<&'static Migration>> {
+        let mut client = self.client().await?;
+        Ok(migrations::apply_pending_postgres(&mut client).await?)
+    }
+
+    /// Highest applied Postgres migration version
+    pub async fn schema_version(&self) -> DbResult<i64> {
+        let client = self.client().await?;
+        Ok(migrations::current_version_postgres(&client).await?)
+    }
+
+    /// A pooled connection; closed ones are replaced when they come back to the pool
+    async fn client(&self) -> DbResult<Object> {
+        Ok(self.pool.get().await?)
+    }
+
+    fn row_to_user(row: &Row) -> User {
+        User {
+            id: row.get(0),
+            public_address: row.get(1),
+            name: row.get(2),
+            role: UserRole::from_str(row.get(3)).unwrap_or(UserRole::Member),
+            created_at: row.get(4),
+        }
+    }
+
+    fn row_to_session(row: &Row) -> Session {
+        Session {
+            id: row.get(0),
+            token: row.get(1),
+            user_id: row.get(4),
+            created_at: row.get(2),
+            expires_at: row.get(3),
+        }
+    }
+
+    fn row_to_api_token(row: &Row) -> ApiToken {
+        ApiToken {
+            id: row.get(0),
+            user_id: row.get(7),
+            name: row.get(1),
+            scopes: Scopes::from_db_string(row.get(2)),
+            prefix: row.get(3),
+            tool_policy: row.get::<_, Option<String>>(6).and_then(|p| serde_json::from_str(&p).ok()),
+            created_at: row.get(4),
+            last_used_at: row.get(5),
+        }
+    }
+
+    fn row_to_api_key(row: &Row) -> ApiKey {
+        ApiKey {
+            id: row.get(0),
+            service_name: row.get(1),
+            user_id: row.get(5),
+            api_key: row.get(2),
+            created_at: row.get(3),
+            updated_at: row.get(4),
+        }
+    }
+
+    fn row_to_agent_settings(row: &Row) -> AgentSettings {
+        AgentSettings {
+            id: row.get(0),
+            endpoint: row.get(1),
+            model_archetype: row.get(2),
+            max_tokens: row.get(3),
+            enabled: row.get(4),
+            secret_key: row.get(5),
+            budget_max_tokens: row.get(6),
+            budget_max_usd: row.get(7),
+            session_budget_max_tokens: row.get(8),
+            session_budget_max_usd: row.get(9),
+            session_ttl_hours: row.get(12),
+            session_sliding: row.get(13),
+            allowed_models: parse_model_list(row.get(14)),
+            spend_max_tx_usd: row.get(15),
+            spend_max_daily_usd: row.get(16),
+            spend_approval_usd: row.get(17),
+            max_context_tokens: row.get(18),
+            fallback_endpoint: row.get(19),
+            fallback_model_archetype: row.get(20),
+            fallback_model: row.get(21),
+            fallback_secret_key: row.get(22),
+            default_persona: row.get(23),
+            created_at: row.get(10),
+            updated_at: row.get(11),
+        }
+    }
+}
+
+#[async_trait]
+impl DatabaseBackend for PostgresBackend {
+    fn backend_name(&self) -> &'static str {
+        "postgres"
+    }
+
+    async fn ping(&self) -> DbResult<()> {
+        let client = self.client().await?;
+        client.simple_query("SELECT 1").await?;
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl UserStore for PostgresBackend {
+    async fn create_user(&self, public_address: &str, name: Option<&str>, role: UserRole) -> DbResult<User> {
+        let client = self.client().await?;
+        let row = client.query_one(
+            &format!(
+                "INSERT INTO users (public_address, name, role, created_at) VALUES ($1, $2, $3, $4) RETURNING {}",
+                USER_COLUMNS
+            ),
+            &[&public_address, &name, &role.as_str(), &Utc::now()],
+        ).await?;
+        Ok(Self::row_to_user(&row))
+    }
+
+    async fn get_user(&self, id: i64) -> DbResult<Option<User>> {
+        let client = self.client().await?;
+        let row = client.query_opt(&format!("SELECT {} FROM users WHERE id = $1", USER_COLUMNS), &[&id]).await?;
+        Ok(row.as_ref().map(Self::row_to_user))
+    }
+
+    async fn get_user_by_address(&self, public_address: &str) -> DbResult<Option<User>> {
+        let client = self.client().await?;
+        let row = client.query_opt(
+            &format!("SELECT {} FROM users WHERE public_address = $1", USER_COLUMNS),
+            &[&public_address],
+        ).await?;
+        Ok(row.as_ref().map(Self::row_to_user))
+    }
+
+    async fn list_users(&self) -> DbResult<Vec<User>> {
+        let client = self.client().await?;
+        let rows = client.query(&format!("SELECT {} FROM users ORDER BY id", USER_COLUMNS), &[]).await?;
+        Ok(rows.iter().map(Self::row_to_user).collect())
+    }
+
+    async fn set_user_role(&self, id: i64, role: UserRole) -> DbResult<Option<User>> {
+        let client = self.client().await?;
+        let row = client.query_opt(
+            &format!("UPDATE users SET role = $1 WHERE id = $2 RETURNING {}", USER_COLUMNS),
+            &[&role.as_str(), &id],
+        ).await?;
+        Ok(row.as_ref().map(Self::row_to_user))
+    }
+
+    async fn delete_user(&self, id: i64) -> DbResult<bool> {
+        // Sessions, tokens and personal keys go with it through ON DELETE CASCADE
+        let client = self.client().await?;
+        let rows = client.execute("DELETE FROM users WHERE id = $1", &[&id]).await?;
+        Ok(rows > 0)
+    }
+
+    async fn claim_unowned_credentials(&self, user_id: i64) -> DbResult<usize> {
+        let client = self.client().await?;
+        let sessions = client.execute("UPDATE auth_sessions SET user_id = $1 WHERE user_id IS NULL", &[&user_id]).await?;
+        let tokens = client.execute("UPDATE api_tokens SET user_id = $1 WHERE user_id IS NULL", &[&user_id]).await?;
+        Ok((sessions + tokens) as usize)
+    }
+
+    async fn claim_totp_step(&self, user_id: i64, step: i64) -> DbResult<bool> {
+        let client = self.client().await?;
+        let claimed = client.execute(
+            "UPDATE users SET totp_last_step = $2
+             WHERE id = $1 AND (totp_last_step IS NULL OR totp_last_step < $2)",
+            &[&user_id, &step],
+        ).await?;
+        Ok(claimed > 0)
+    }
+}
+
+#[async_trait]
+impl AuthStore for PostgresBackend {
+    async fn create_session_for_user(&self, user: &User, ttl: Duration) -> DbResult<Session> {
+        let client = self.client().await?;
+        let token = generate_session_token();
+        let created_at = Utc::now();
+        let expires_at = created_at + ttl;
+        let row = client.query_one(
+            &format!(
+                "INSERT INTO auth_sessions (token, public_address, user_id, created_at, expires_at)
+                 VALUES ($1, $2, $3, $4, $5) RETURNING {}",
+                SESSION_COLUMNS
+            ),
+            &[&token, &user.public_address, &user.id, &created_at, &expires_at],
+        ).await?;
+        Ok(Self::row_to_session(&row))
+    }
+
+    async fn validate_session(&self, token: &str, policy: &SessionPolicy) -> DbResult<Option<Session>> {
+        if policy.sliding {
+            // Sliding expiry: every use keeps the session alive for another TTL
+            return self.refresh_session(token, policy.ttl).await;
+        }
+        let client = self.client().await?;
+        let row = client.query_opt(
+            &format!("SELECT {} FROM auth_sessions WHERE token = $1 AND expires_at > $2", SESSION_COLUMNS),
+            &[&token, &Utc::now()],
+        ).await?;
+        Ok(row.as_ref().map(Self::row_to_session))
+    }
+
+    async fn refresh_session(&self, token: &str, ttl: Duration) -> DbResult<Option<Session>> {
+        let client = self.client().await?;
+        let now = Utc::now();
+        let new_expires: DateTime<Utc> = now + ttl;
+        let row = client.query_opt(
+            &format!(
+                "UPDATE auth_sessions SET expires_at = $1 WHERE token = $2 AND expires_at > $3 RETURNING {}",
+                SESSION_COLUMNS
+            ),
+            &[&new_expires, &token, &now],
+        ).await?;
+        Ok(row.as_ref().map(Self::row_to_session))
+    }
+
+    async fn delete_session(&self, token: &str) -> DbResult<bool> {
+        let client = self.client().await?;
+        let rows = client.execute("DELETE FROM auth_sessions WHERE token = $1", &[&token]).await?;
+        Ok(rows > 0)
+    }
+
+    async fn delete_expired_sessions(&self) -> DbResult<usize> {
+        let client = self.client().await?;
+        let rows = client.execute("DELETE FROM auth_sessions WHERE expires_at <= $1", &[&Utc::now()]).await?;
+        Ok(rows as usize)
+    }
+
+    async fn create_or_update_challenge(&self, public_address: &str, challenge: &str) -> DbResult<()> {
+        let client = self.client().await?;
+        client.execute(
+            "INSERT INTO auth_challenges (public_address, challenge, created_at) VALUES ($1, $2, $3)
+             ON CONFLICT (public_address) DO UPDATE SET challenge = EXCLUDED.challenge, created_at = EXCLUDED.created_at",
+            &[&public_address, &challenge, &Utc::now()],
+        ).await?;
+        Ok(())
+    }
+
+    async fn get_challenge(&self, public_address: &str) -> DbResult<Option<String>> {
+        let client = self.client().await?;
+        let row = client.query_opt(
+            "SELECT challenge FROM auth_challenges WHERE public_address = $1",
+            &[&public_address],
+        ).await?;
+        Ok(row.map(|r| r.get(0)))
+    }
+
+    async fn validate_challenge(&self, public_address: &str, challenge: &str) -> DbResult<bool> {
+        let client = self.client().await?;
+        let row = client.query_opt(
+            "SELECT 1 FROM auth_challenges WHERE public_address = $1 AND challenge = $2",
+            &[&public_address, &challenge],
+        ).await?;
+        Ok(row.is_some())
+    }
+
+    async fn delete_challenge(&self, public_address: &str) -> DbResult<bool> {
+        let client = self.client().await?;
+        let rows = client.execute(
+            "DELETE FROM auth_challenges WHERE public_address = $1",
+            &[&public_address],
+        ).await?;
+        Ok(rows > 0)
+    }
+}
+
+#[async_trait]
+impl ApiTokenStore for PostgresBackend {
+    async fn create_api_token(
+        &self,
+        user_id: i64,
+        name: &str,
+        token_hash: &str,
+        prefix: &str,
+        scopes: &Scopes,
+        tool_policy: Option<&ToolPolicy>,
+    ) -> DbResult<ApiToken> {
+        let scopes = scopes.to_db_string();
+        let tool_policy = tool_policy.map(|p| serde_json::to_string(p).unwrap_or_default());
+        let client = self.client().await?;
+        let row = client.query_one(
+            &format!(
+                "INSERT INTO api_tokens (user_id, name, token_hash, prefix, scopes, tool_policy, created_at)
+                 VALUES ($1, $2, $3, $4, $5, $6, $7)
+                 RETURNING {}",
+                API_TOKEN_COLUMNS
+            ),
+            &[&user_id, &name, &token_hash, &prefix, &scopes, &tool_policy, &Utc::now()],
+        ).await?;
+        Ok(Self::row_to_api_token(&row))
+    }
+
+    async fn list_api_tokens(&self) -> DbResult<Vec<ApiToken>> {
+        let client = self.client().await?;
+        let rows = client.query(
+            &format!("SELECT {} FROM api_tokens ORDER BY name", API_TOKEN_COLUMNS),
+            &[],
+        ).await?;
+        Ok(rows.iter().map(Self::row_to_api_token).collect())
+    }
+
+    async fn set_api_token_tool_policy(&self, id: i64, tool_policy: Option<&ToolPolicy>) -> DbResult<Option<ApiToken>> {
+        let tool_policy = tool_policy.map(|p| serde_json::to_string(p).unwrap_or_default());
+        let client = self.client().await?;
+        let row = client.query_opt(
+            &format!("UPDATE api_tokens SET tool_policy = $1 WHERE id = $2 RETURNING {}", API_TOKEN_COLUMNS),
+            &[&tool_policy, &id],
+        ).await?;
+        Ok(row.as_ref().map(Self::row_to_api_token))
+    }
+
+    async fn validate_api_token(&self, token_hash: &str) -> DbResult<Option<ApiToken>> {
+        let client = self.client().await?;
+        let row = client.query_opt(
+            &format!(
+                "UPDATE api_tokens SET last_used_at = $1 WHERE token_hash = $2 RETURNING {}",
+                API_TOKEN_COLUMNS
+            ),
+            &[&Utc::now(), &token_hash],
+        ).await?;
+        Ok(row.as_ref().map(Self::row_to_api_token))
+    }
+
+    async fn delete_api_token(&self, id: i64) -> DbResult<bool> {
+        let client = self.client().await?;
+        let rows = client.execute("DELETE FROM api_tokens WHERE id = $1", &[&id]).await?;
+        Ok(rows > 0)
+    }
+}
+
+#[async_trait]
+impl ApiKeyStore for PostgresBackend {
+    async fn get_api_key(&self, service_name: &str, user_id: Option<i64>) -> DbResult<Option<ApiKey>> {
+        let client = self.client().await?;
+        let row = client.query_opt(
+            &format!(
+                "SELECT {} FROM external_api_keys WHERE service_name = $1 AND user_id IS NOT DISTINCT FROM $2",
+                API_KEY_COLUMNS
+            ),
+            &[&service_name, &user_id],
+        ).await?;
+        Ok(row.as_ref().map(Self::row_to_api_key))
+    }
+
+    async fn list_api_keys(&self) -> DbResult<Vec<ApiKey>> {
+        let client = self.client().await?;
+        let rows = client.query(
+            &format!("SELECT {} FROM external_api_keys ORDER BY service_name, user_id", API_KEY_COLUMNS),
+            &[],
+        ).await?;
+        Ok(rows.iter().map(Self::row_to_api_key).collect())
+    }
+
+    async fn upsert_api_key(&self, service_name: &str, user_id: Option<i64>, api_key: &str) -> DbResult<ApiKey> {
+        let client = self.client().await?;
+        let now = Utc::now();
+        let row = client.query_one(
+            &format!(
+                "INSERT INTO external_api_keys (service_name, user_id, api_key, created_at, updated_at)
+                 VALUES ($1, $2, $3, $4, $4)
+                 ON CONFLICT (service_name, COALESCE(user_id, 0))
+                 DO UPDATE SET api_key = EXCLUDED.api_key, updated_at = EXCLUDED.updated_at
+                 RETURNING {}",
+                API_KEY_COLUMNS
+            ),
+            &[&service_name, &user_id, &api_key, &now],
+        ).await?;
+        Ok(Self::row_to_api_key(&row))
+    }
+
+    async fn delete_api_key(&self, service_name: &str, user_id: Option<i64>) -> DbResult<bool> {
+        let client = self.client().await?;
+        let rows = client.execute(
+            "DELETE FROM external_api_keys WHERE service_name = $1 AND user_id IS NOT DISTINCT FROM $2",
+            &[&service_name, &user_id],
+        ).await?;
+        Ok(rows > 0)
+    }
+}
+
+#[async_trait]
+impl AgentSettingsStore for PostgresBackend {
+    async fn get_active_agent_settings(&self) -> DbResult<Option<AgentSettings>> {
+        let client = self.client().await?;
+        let row = client.query_opt(
+            &format!("SELECT {} FROM agent_settings WHERE enabled LIMIT 1", AGENT_SETTINGS_COLUMNS),
+            &[],
+        ).await?;
+        Ok(row.as_ref().map(Self::row_to_agent_settings))
+    }
+
+    async fn get_agent_settings_by_endpoint(&self, endpoint: &str) -> DbResult<Option<AgentSettings>> {
+        let client = self.client().await?;
+        let row = client.query_opt(
+            &format!("SELECT {} FROM agent_settings WHERE endpoint = $1", AGENT_SETTINGS_COLUMNS),
+            &[&endpoint],
+        ).await?;
+        Ok(row.as_ref().map(Self::row_to_agent_settings))
+    }
+
+    async fn list_agent_settings(&self) -> DbResult<Vec<AgentSettings>> {
+        let client = self.client().await?;
+        let rows = client.query(
+            &format!("SELECT {} FROM agent_settings ORDER BY id", AGENT_SETTINGS_COLUMNS),
+            &[],
+        ).await?;
+        Ok(rows.iter().map(Self::row_to_agent_settings).collect())
+    }
+
+    async fn save_agent_settings(&self, request: &UpdateAgentSettingsRequest) -> DbResult<AgentSettings> {
+        let mut client = self.client().await?;
+        let now = Utc::now();
+        // One transaction so concurrent instances never leave two profiles enabled
+        let tx = client.transaction().await?;
+        tx.execute("UPDATE agent_settings SET enabled = FALSE, updated_at = $1", &[&now]).await?;
+        let row = tx.query_one(
+            &format!(
+                "INSERT INTO agent_settings (endpoint, model_archetype, max_tokens, secret_key, budget_max_tokens,
+                                             budget_max_usd, session_budget_max_tokens, session_budget_max_usd,
+                                             session_ttl_hours, session_sliding, allowed_models, spend_max_tx_usd,
+                                             spend_max_daily_usd, spend_approval_usd, max_context_tokens,
+                                             fallback_endpoint, fallback_model_archetype, fallback_model,
+                                             fallback_secret_key, default_persona, enabled, created_at,
+                                             updated_at)
+                 VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13, $14, $15, $16, $17, $18, $19,
+                         $20, TRUE, $21, $21)
+                 ON CONFLICT (endpoint) DO UPDATE SET
+                    model_archetype = EXCLUDED.model_archetype, max_tokens = EXCLUDED.max_tokens,
+                    secret_key = EXCLUDED.secret_key, budget_max_tokens = EXCLUDED.budget_max_tokens,
+                    budget_max_usd = EXCLUDED.budget_max_usd,
+                    session_budget_max_tokens = EXCLUDED.session_budget_max_tokens,
+                    session_budget_max_usd = EXCLUDED.session_budget_max_usd,
+                    session_ttl_hours = EXCLUDED.session_ttl_hours,
+                    session_sliding = EXCLUDED.session_sliding,
+                    allowed_models = EXCLUDED.allowed_models,
+                    spend_max_tx_usd = EXCLUDED.spend_max_tx_usd,
+                    spend_max_daily_usd = EXCLUDED.spend_max_daily_usd,
+                    spend_approval_usd = EXCLUDED.spend_approval_usd,
+                    max_context_tokens = EXCLUDED.max_context_tokens,
+                    fallback_endpoint = EXCLUDED.fallback_endpoint,
+                    fallback_model_archetype = EXCLUDED.fallback_model_archetype,
+                    fallback_model = EXCLUDED.fallback_model,
+                    fallback_secret_key = EXCLUDED.fallback_secret_key,
+                    default_persona = EXCLUDED.default_persona,
+                    enabled = TRUE, updated_at = EXCLUDED.updated_at
+                 RETURNING {}",
+                AGENT_SETTINGS_COLUMNS
+            ),
+            &[
+                &request.endpoint,
+                &request.model_archetype,
+                &request.max_tokens,
+                &request.secret_key,
+                &request.budget_max_tokens,
+                &request.budget_max_usd,
+                &request.session_budget_max_tokens,
+                &request.session_budget_max_usd,
+                &request.session_ttl_hours,
+                &request.session_sliding,
+                &request.allowed_models.join(","),
+                &request.spend_max_tx_usd,
+                &request.spend_max_daily_usd,
+                &request.spend_approval_usd,
+                &request.max_context_tokens,
+                &request.fallback_endpoint,
+                &request.fallback_model_archetype,
+                &request.fallback_model,
+                &request.fallback_secret_key,
+                &request.default_persona,
+                &now,
+            ],
+        ).await?;
+        tx.commit().await?;
+        Ok(Self::row_to_agent_settings(&row))
+    }
+
+    async fn disable_agent_settings(&self) -> DbResult<()> {
+        let client = self.client().await?;
+        client.execute(
+            "UPDATE agent_settings SET enabled = FALSE, updated_at = $1",
+            &[&Utc::now()],
+        ).await?;
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl LoginAttemptStore for PostgresBackend {
+    async fn get_login_lockout(&self, ip: &str) -> DbResult<Option<DateTime<Utc>>> {
+        let client = self.client().await?;
+        let row = client.query_opt(
+            "SELECT locked_until FROM login_attempts WHERE ip = $1 AND locked_until > $2",
+            &[&ip, &Utc::now()],
+        ).await?;
+        Ok(row.map(|r| r.get(0)))
+    }
+
+    async fn record_login_failure(
+        &self,
+        ip: &str,
+        public_address: Option<&str>,
+        reason: LoginFailureReason,
+    ) -> DbResult<i64> {
+        let client = self.client().await?;
+        let now = Utc::now();
+        client.execute(
+            "INSERT INTO login_failures (ip, public_address, reason, occurred_at) VALUES ($1, $2, $3, $4)",
+            &[&ip, &public_address, &reason.as_str(), &now],
+        ).await?;
+        if reason == LoginFailureReason::LockedOut {
+            let row = client.query_opt("SELECT failures FROM login_attempts WHERE ip = $1", &[&ip]).await?;
+            return Ok(row.map_or(0, |r| r.get(0)));
+        }
+
+        let forget_before = now - Duration::hours(LOGIN_FAILURE_MEMORY_HOURS);
+        let row = client.query_one(
+            "INSERT INTO login_attempts (ip, failures, last_failure_at) VALUES ($1, 1, $2)
+             ON CONFLICT (ip) DO UPDATE SET
+                failures = CASE WHEN login_attempts.last_failure_at < $3 THEN 1 ELSE login_attempts.failures + 1 END,
+                last_failure_at = EXCLUDED.last_failure_at
+             RETURNING failures",
+            &[&ip, &now, &forget_before],
+        ).await?;
+        Ok(row.get(0))
+    }
+
+    async fn set_login_lockout(&self, ip: &str, until: DateTime<Utc>) -> DbResult<()> {
+        let client = self.client().await?;
+        client.execute("UPDATE login_attempts SET locked_until = $2 WHERE ip = $1", &[&ip, &until]).await?;
+        Ok(())
+    }
+
+    async fn clear_login_failures(&self, ip: &str) -> DbResult<()> {
+        let client = self.client().await?;
+        client.execute("DELETE FROM login_attempts WHERE ip = $1", &[&ip]).await?;
+        Ok(())
+    }
+
+    async fn list_login_failures(&self, limit: i64) -> DbResult<Vec<LoginFailure>> {
+        let client = self.client().await?;
+        let rows = client.query(
+            "SELECT id, ip, public_address, reason, occurred_at FROM login_failures ORDER BY id DESC LIMIT $1",
+            &[&limit],
+        ).await?;
+        Ok(rows
+            .iter()
+            .map(|row| LoginFailure {
+                id: row.get(0),
+                ip: row.get(1),
+                public_address: row.get(2),
+                reason: row.get(3),
+                occurred_at: row.get(4),
+            })
+            .collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::Scope;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio_postgres::config::SslMode;
+
+    #[test]
+    fn test_sslmode_from_url() {
+        let config = pg_config("postgres://stark@db.example/stark").unwrap();
+        assert_eq!(config.get_ssl_mode(), SslMode::Prefer);
+        assert_eq!(config.get_connect_timeout(), Some(&DEFAULT_CONNECT_TIMEOUT));
+
+        let config = pg_config("postgres://stark@db.example/stark?sslmode=require&connect_timeout=3").unwrap();
+        assert_eq!(config.get_ssl_mode(), SslMode::Require);
+        assert_eq!(config.get_connect_timeout(), Some(&std::time::Duration::from_secs(3)));
+
+        let config = pg_config("host=db.example user=stark sslmode=disable").unwrap();
+        assert_eq!(config.get_ssl_mode(), SslMode::Disable);
+
+        assert!(matches!(
+            pg_config("postgres://stark@db.example/stark?sslmode=sometimes"),
+            Err(DbError::Postgres(_))
+        ));
+        assert!(tls_connector().is_ok());
+    }
+
+    /// A server that answers the SSLRequest with `reply` and returns the next
+    /// bytes the client sends
+    async fn fake_server(reply: u8) -> (u16, tokio::task::JoinHandle<Vec<u8>>) {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let handle = tokio::spawn(async move {
+            let (mut socket, _) = listener.accept().await.unwrap();
+            let mut ssl_request = [0u8; 8];
+            socket.read_exact(&mut ssl_request).await.unwrap();
+            assert_eq!(ssl_request, [0, 0, 0, 8, 4, 210, 22, 47]);
+            socket.write_all(&[reply]).await.unwrap();
+            let mut next = vec![0u8; 8];
+            let n = socket.read(&mut next).await.unwrap_or(0);
+            next.truncate(n);
+            next
+        });
+        (port, handle)
+    }
+
+    #[tokio::test]
+    async fn test_require_refuses_plaintext() {
+        let (port, server) = fake_server(b'N').await;
+        let url = format!("postgres://stark@127.0.0.1:{}/stark?sslmode=require", port);
+        assert!(matches!(PostgresBackend::connect(&url).await, Err(DbError::Postgres(_))));
+        // Nothing follows the refusal: no plaintext startup message
+        assert!(server.await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_prefer_starts_tls_when_offered() {
+        let (port, server) = fake_server(b'S').await;
+        let url = format!("postgres://stark@127.0.0.1:{}/stark?connect_timeout=2", port);
+        assert!(PostgresBackend::connect(&url).await.is_err());
+        // 0x16 opens a TLS handshake record
+        assert_eq!(server.await.unwrap().first(), Some(&0x16));
+    }
+
+    #[tokio::test]
+    async fn test_checkout_errors() {
+        // Nothing listens on a port we just released
+        let port = std::net::TcpListener::bind("127.0.0.1:0").unwrap().local_addr().unwrap().port();
+        let url = format!("postgres://stark@127.0.0.1:{}/stark", port);
+        assert!(matches!(PostgresBackend::connect(&url).await, Err(DbError::Postgres(_))));
+
+        let closed = DbError::from(deadpool_postgres::PoolError::Closed);
+        assert!(matches!(closed, DbError::Unavailable(_)));
+        let timeout = DbError::from(deadpool_postgres::PoolError::Timeout(deadpool_postgres::TimeoutType::Wait));
+        assert!(matches!(timeout, DbError::Unavailable(_)));
+    }
+
+    /// Needs a scratch database, e.g.
+    /// `STARK_TEST_POSTGRES_URL=postgres://postgres@localhost/stark_test cargo test`
+    async fn test_backend() -> Option<PostgresBackend> {
+        let url = std::env::var("STARK_TEST_POSTGRES_URL").ok()?;
+        let backend = PostgresBackend::connect(&url).await.expect("connect to STARK_TEST_POSTGRES_URL");
+        backend.migrate().await.unwrap();
+        backend
+            .client()
+            .await
+            .unwrap()
+            .batch_execute(
+                "TRUNCATE users, auth_sessions, auth_challenges, api_tokens, external_api_keys, agent_settings,
+                 login_attempts, login_failures",
+            )
+            .await
+            .unwrap();
+        Some(backend)
+    }
+
+    #[tokio::test]
+    async fn test_migrate_is_idempotent() {
+        let Some(db) = test_backend().await else {
+            return;
+        };
+        let version = db.schema_version().await.unwrap();
+        assert!(version >= 2);
+        assert!(db.migrate().await.unwrap().is_empty());
+        assert_eq!(db.schema_version().await.unwrap(), version);
+    }
+
+    #[tokio::test]
+    async fn test_shared_state_roundtrip() {
+        let Some(db) = test_backend().await else {
+            return;
+        };
+
+        let user = db.create_user("0xabc", None, UserRole::Member).await.unwrap();
+        assert_eq!(db.get_user_by_address("0xabc").await.unwrap(), Some(user.clone()));
+        let admin = db.set_user_role(user.id, UserRole::Admin).await.unwrap().unwrap();
+        assert_eq!(admin.role, UserRole::Admin);
+
+        let policy = SessionPolicy::default();
+        let session = db.create_session_for_user(&user, policy.ttl).await.unwrap();
+        assert_eq!(session.user_id, Some(user.id));
+        assert_eq!(db.validate_session(&session.token, &policy).await.unwrap().unwrap().id, session.id);
+        let refreshed = db.refresh_session(&session.token, Duration::hours(48)).await.unwrap().unwrap();
+        assert!(refreshed.expires_at > session.expires_at);
+        assert!(db.delete_session(&session.token).await.unwrap());
+        assert!(db.validate_session(&session.token, &policy).await.unwrap().is_none());
+
+        let expired = db.create_session_for_user(&user, Duration::seconds(-1)).await.unwrap();
+        assert!(db.refresh_session(&expired.token, policy.ttl).await.unwrap().is_none());
+        assert_eq!(db.delete_expired_sessions().await.unwrap(), 1);
+
+        db.create_or_update_challenge("0xabc", "first").await.unwrap();
+        db.create_or_update_challenge("0xabc", "second").await.unwrap();
+        assert_eq!(db.get_challenge("0xabc").await.unwrap().as_deref(), Some("second"));
+        assert!(!db.validate_challenge("0xabc", "first").await.unwrap());
+        assert!(db.delete_challenge("0xabc").await.unwrap());
+
+        let scopes = Scopes::new([Scope::Read]);
+        let token = db.create_api_token(user.id, "dashboard", "hash", "stk_abcd", &scopes, None).await.unwrap();
+        assert!(token.last_used_at.is_none());
+        let used = db.validate_api_token("hash").await.unwrap().unwrap();
+        assert_eq!(used.scopes, scopes);
+        assert!(used.last_used_at.is_some());
+        assert!(db.validate_api_token("other").await.unwrap().is_none());
+        assert_eq!(db.list_api_tokens().await.unwrap().len(), 1);
+        assert!(db.delete_api_token(token.id).await.unwrap());
+
+        let created = db.upsert_api_key("GITHUB_TOKEN", None, "one").await.unwrap();
+        let updated = db.upsert_api_key("GITHUB_TOKEN", None, "two").await.unwrap();
+        assert_eq!(created.id, updated.id);
+        let personal = db.upsert_api_key("GITHUB_TOKEN", Some(user.id), "mine").await.unwrap();
+        assert_ne!(personal.id, created.id);
+        assert_eq!(db.list_api_keys().await.unwrap().len(), 2);
+        assert_eq!(db.get_api_key("GITHUB_TOKEN", None).await.unwrap().unwrap().api_key, "two");
+        assert_eq!(db.get_api_key("GITHUB_TOKEN", Some(user.id)).await.unwrap().unwrap().api_key, "mine");
+
+        // Each code works once per user, without blocking other users
+        let other = db.create_user("0xdef", None, UserRole::Admin).await.unwrap();
+        assert!(db.claim_totp_step(user.id, 10).await.unwrap());
+        assert!(!db.claim_totp_step(user.id, 10).await.unwrap());
+        assert!(!db.claim_totp_step(user.id, 9).await.unwrap());
+        assert!(db.claim_totp_step(other.id, 10).await.unwrap());
+
+        assert!(db.delete_user(user.id).await.unwrap());
+        assert!(db.get_api_key("GITHUB_TOKEN", Some(user.id)).await.unwrap().is_none());
+        assert_eq!(db.list_api_keys().await.unwrap().len(), 1);
+
+        let request = |endpoint: &str| UpdateAgentSettingsRequest {
+            endpoint: endpoint.to_string(),
+            model_archetype: "claude".to_string(),
+            max_tokens: 1000,
+            secret_key: None,
+            budget_max_tokens: None,
+            budget_max_usd: Some(0.5),
+            session_budget_max_tokens: Some(10_000),
+            session_budget_max_usd: None,
+            session_ttl_hours: Some(8),
+            session_sliding: false,
+            allowed_models: vec!["claude-3-5-haiku-latest".to_string()],
+            spend_max_tx_usd: None,
+            spend_max_daily_usd: Some(100.0),
+            spend_approval_usd: None,
+            max_context_tokens: Some(50_000),
+            fallback_endpoint: None,
+            fallback_model_archetype: None,
+            fallback_model: Some("claude-3-5-haiku-latest".to_string()),
+            fallback_secret_key: None,
+            default_persona: Some("reviewer".to_string()),
+        };
+        db.save_agent_settings(&request("https://a.example")).await.unwrap();
+        let b = db.save_agent_settings(&request("https://b.example")).await.unwrap();
+        let active = db.get_active_agent_settings().await.unwrap().unwrap();
+        assert_eq!(active.id, b.id);
+        assert_eq!(active.session_budget_max_tokens, Some(10_000));
+        assert_eq!(active.session_policy(), SessionPolicy { ttl: Duration::hours(8), sliding: false });
+        assert_eq!(active.allowed_models, vec!["claude-3-5-haiku-latest"]);
+        assert_eq!(active.spend_max_daily_usd, Some(100.0));
+        assert_eq!(active.max_context_tokens, Some(50_000));
+        assert_eq!(active.fallback_model.as_deref(), Some("claude-3-5-haiku-latest"));
+        assert_eq!(active.default_persona.as_deref(), Some("reviewer"));
+        assert_eq!(db.list_agent_settings().await.unwrap().iter().filter(|s| s.enabled).count(), 1);
+
+        db.disable_agent_settings().await.unwrap();
+        assert!(db.get_active_agent_settings().await.unwrap().is_none());
+
+        let ip = "203.0.113.7";
+        assert_eq!(db.record_login_failure(ip, None, LoginFailureReason::InvalidSignature).await.unwrap(), 1);
+        assert_eq!(db.record_login_failure(ip, Some("0xabc"), LoginFailureReason::InvalidTotp).await.unwrap(), 2);
+        assert_eq!(db.record_login_failure(ip, None, LoginFailureReason::LockedOut).await.unwrap(), 2);
+        assert_eq!(db.list_login_failures(10).await.unwrap()[0].reason, "locked_out");
+        assert!(db.get_login_lockout(ip).await.unwrap().is_none());
+        db.set_login_lockout(ip, Utc::now() + Duration::minutes(1)).await.unwrap();
+        assert!(db.get_login_lockout(ip).await.unwrap().is_some());
+        db.clear_login_failures(ip).await.unwrap();
+        assert!(db.get_login_lockout(ip).await.unwrap().is_none());
+    }
+}
@@ -0,0 +1,305 @@
+//! Envelope encryption for API keys stored in the database
+//!
+//! Every value gets its own random data key. The value is sealed with
+//! AES-256-GCM under that data key, and the data key is sealed under the
+//! master key (`STARK_MASTER_KEY`). Rotating the master key only re-wraps the
+//! data keys; the values themselves are never re-encrypted. Stored form:
+//!
+//! `enc:v1:<master key id>:<base64 wrapped data key>:<base64 sealed value>`
+//!
+//! Values without the `enc:` prefix are plaintext from before encryption was
+//! enabled. They are still read as-is and get sealed on the next startup with
+//! a master key configured.
+
+use aes_gcm::aead::{Aead, AeadCore, KeyInit, OsRng};
+use aes_gcm::{Aes256Gcm, Key, Nonce};
+use base64::{engine::general_purpose::STANDARD as BASE64, Engine};
+use sha2::{Digest, Sha256};
+
+use crate::config;
+
+const PREFIX: &str = "enc:v1:";
+const NONCE_LEN: usize = 12;
+const KEY_LEN: usize = 32;
+
+/// One AES-256 master key, identified by a short hash so stored values
+/// record which key wrapped them
+pub struct MasterKey {
+    id: String,
+    cipher: Aes256Gcm,
+}
+
+impl MasterKey {
+    /// Parse a base64-encoded 32-byte key (e.g. from `openssl rand -base64 32`)
+    pub fn from_base64(encoded: &str) -> Result<Self, String> {
+        let bytes = BASE64
+            .decode(encoded.trim())
+            .map_err(|e| format!("master key is not valid base64: {}", e))?;
+        if bytes.len() != KEY_LEN {
+            return Err(format!("master key must be {} bytes, got {}", KEY_LEN, bytes.len()));
+        }
+        Ok(Self {
+            id: hex::encode(&Sha256::digest(&bytes)[..4]),
+            cipher: Aes256Gcm::new(Key::<Aes256Gcm>::from_slice(&bytes)),
+        })
+    }
+
+    pub fn id(&self) -> &str {
+        &self.id
+    }
+}
+
+/// Encrypt under `cipher` with a fresh nonce, returning nonce || ciphertext
+fn seal_with(cipher: &Aes256Gcm, plaintext: &[u8]) -> Result<Vec<u8>, String> {
+    let nonce = Aes256Gcm::generate_nonce(&mut OsRng);
+    let ciphertext = cipher
+        .encrypt(&nonce, plaintext)
+        .map_err(|_| "encryption failed".to_string())?;
+    let mut sealed = nonce.to_vec();
+    sealed.extend(ciphertext);
+    Ok(sealed)
+}
+
+fn open_with(cipher: &Aes256Gcm, sealed: &[u8]) -> Result<Vec<u8>, String> {
+    if sealed.len() < NONCE_LEN {
+        return Err("encrypted value is truncated".to_string());
+    }
+    let (nonce, ciphertext) = sealed.split_at(NONCE_LEN);
+    cipher
+        .decrypt(Nonce::from_slice(nonce), ciphertext)
+        .map_err(|_| "decryption failed (wrong master key or corrupted value)".to_string())
+}
+
+/// Parts of a sealed value: (master key id, wrapped data key, sealed value)
+fn split_sealed(stored: &str) -> Option<Result<(&str, &str, &str), String>> {
+    let rest = stored.strip_prefix(PREFIX)?;
+    let mut parts = rest.splitn(3, ':');
+    Some(match (parts.next(), parts.next(), parts.next()) {
+        (Some(key_id), Some(wrapped), Some(sealed)) => Ok((key_id, wrapped, sealed)),
+        _ => Err("malformed encrypted value".to_string()),
+    })
+}
+
+/// The master keys this process can use
+///
+/// `current` seals new values; `previous` keys are only used to open values
+/// that haven't been rotated yet. With no current key, values are stored as
+/// plaintext.
+#[derive(Default)]
+pub struct KeyRing {
+    current: Option<MasterKey>,
+    previous: Vec<MasterKey>,
+}
+
+impl KeyRing {
+    pub fn new(current: Option<MasterKey>, previous: Vec<MasterKey>) -> Self {
+        Self { current, previous }
+    }
+
+    /// Load `STARK_MASTER_KEY` (or `STARK_MASTER_KEY_FILE`) and `STARK_MASTER_KEY_PREVIOUS`
+    pub fn from_env() -> Result<Self, String> {
+        let current = config::master_key()
+            .map_err(|e| format!("failed to read master key file: {}", e))?
+            .map(|key| MasterKey::from_base64(&key))
+            .transpose()?;
+        let previous = config::previous_master_keys()
+            .iter()
+            .map(|key| MasterKey::from_base64(key))
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(Self::new(current, previous))
+    }
+
+    /// Whether new values are encrypted
+    pub fn is_enabled(&self) -> bool {
+        self.current.is_some()
+    }
+
+    /// Whether a stored value is encrypted (as opposed to legacy plaintext)
+    pub fn is_sealed(stored: &str) -> bool {
+        stored.starts_with(PREFIX)
+    }
+
+    pub fn current_id(&self) -> Option<&str> {
+        self.current.as_ref().map(MasterKey::id)
+    }
+
+    fn find(&self, key_id: &str) -> Result<&MasterKey, String> {
+        self.current
+            .iter()
+            .chain(&self.previous)
+            .find(|k| k.id == key_id)
+            .ok_or_else(|| format!("value is encrypted with master key {} which is not configured", key_id))
+    }
+
+    /// Encrypt a value for storage (unchanged when no master key is configured)
+    pub fn seal(&self, plaintext: &str) -> Result<String, String> {
+        let Some(master) = &self.current else {
+            return Ok(plaintext.to_string());
+        };
+        let data_key = Aes256Gcm::generate_key(&mut OsRng);
+        let sealed = seal_with(&Aes256Gcm::new(&data_key), plaintext.as_bytes())?;
+        let wrapped = seal_with(&master.cipher, &data_key)?;
+        Ok(format!(
+            "{}{}:{}:{}",
+            PREFIX,
+            master.id,
+            BASE64.encode(wrapped),
+            BASE64.encode(sealed)
+        ))
+    }
+
+    /// Decrypt a stored value; plaintext values are returned as-is
+    pub fn open(&self, stored: &str) -> Result<String, String> {
+        let (key_id, wrapped, sealed) = match split_sealed(stored) {
+            None => return Ok(stored.to_string()),
+            Some(parts) => parts?,
+        };
+        let data_key = self.unwrap_data_key(key_id, wrapped)?;
+        let sealed = BASE64
+            .decode(sealed)
+            .map_err(|e| format!("malformed encrypted value: {}", e))?;
+        let plaintext = open_with(&Aes256Gcm::new(Key::<Aes256Gcm>::from_slice(&data_key)), &sealed)?;
+        String::from_utf8(plaintext).map_err(|_| "decrypted value is not UTF-8".to_string())
+    }
+
+    fn unwrap_data_key(&self, key_id: &str, wrapped: &str) -> Result<Vec<u8>, String> {
+        let wrapped = BASE64
+            .decode(wrapped)
+            .map_err(|e| format!("malformed encrypted value: {}", e))?;
+        let data_key = open_with(&self.find(key_id)?.cipher, &wrapped)?;
+        if data_key.len() != KEY_LEN {
+            return Err("malformed data key".to_string());
+        }
+        Ok(data_key)
+    }
+
+    /// Whether a stored value is plaintext or wrapped by a key other than the current one
+    pub fn needs_rotation(&self, stored: &str) -> bool {
+        let Some(current) = self.current_id() else {
+            return false;
+        };
+        match split_sealed(stored) {
+            Some(Ok((key_id, _, _))) => key_id != current,
+            Some(Err(_)) => false,
+            None => true,
+        }
+    }
+
+    /// Re-wrap a value's data key under the current master key, sealing it if
+    /// it was plaintext. The sealed value itself is carried over unchanged.
+    pub fn rotate(&self, stored: &str) -> Result<String, String> {
+        let Some(master) = &self.current else {
+            return Err("no master key configured".to_string());
+        };
+        let (key_id, wrapped, sealed) = match split_sealed(stored) {
+            None => return self.seal(stored),
+            Some(parts) => parts?,
+        };
+        let data_key = self.unwrap_data_key(key_id, wrapped)?;
+        let rewrapped = seal_with(&master.cipher, &data_key)?;
+        Ok(format!("{}{}:{}:{}", PREFIX, master.id, BASE64.encode(rewrapped), sealed))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::Database;
+
+    fn key(byte: u8) -> MasterKey {
+        MasterKey::from_base64(&BASE64.encode([byte; KEY_LEN])).unwrap()
+    }
+
+    #[test]
+    fn test_seal_and_open() {
+        let ring = KeyRing::new(Some(key(1)), Vec::new());
+        let sealed = ring.seal("sk-secret").unwrap();
+        assert!(sealed.starts_with(PREFIX));
+        assert!(!sealed.contains("sk-secret"));
+        assert_ne!(sealed, ring.seal("sk-secret").unwrap());
+        assert_eq!(ring.open(&sealed).unwrap(), "sk-secret");
+    }
+
+    #[test]
+    fn test_plaintext_passthrough() {
+        let disabled = KeyRing::default();
+        assert_eq!(disabled.seal("sk-secret").unwrap(), "sk-secret");
+
+        let ring = KeyRing::new(Some(key(1)), Vec::new());
+        assert_eq!(ring.open("sk-legacy").unwrap(), "sk-legacy");
+        assert!(ring.needs_rotation("sk-legacy"));
+        assert!(!disabled.needs_rotation("sk-legacy"));
+    }
+
+    #[test]
+    fn test_wrong_key_and_tampering() {
+        let sealed = KeyRing::new(Some(key(1)), Vec::new()).seal("sk-secret").unwrap();
+
+        let other = KeyRing::new(Some(key(2)), Vec::new());
+        assert!(other.open(&sealed).unwrap_err().contains("not configured"));
+
+        let ring = KeyRing::new(Some(key(1)), Vec::new());
+        let mut tampered = sealed.clone();
+        let last = tampered.pop().unwrap();
+        tampered.push(if last == 'A' { 'B' } else { 'A' });
+        assert!(ring.open(&tampered).is_err());
+    }
+
+    #[test]
+    fn test_rotate() {
+        let old = KeyRing::new(Some(key(1)), Vec::new());
+        let sealed = old.seal("sk-secret").unwrap();
+
+        let ring = KeyRing::new(Some(key(2)), vec![key(1)]);
+        assert!(ring.needs_rotation(&sealed));
+        assert_eq!(ring.open(&sealed).unwrap(), "sk-secret");
+
+        let rotated = ring.rotate(&sealed).unwrap();
+        assert!(!ring.needs_rotation(&rotated));
+        // Only the data key is re-wrapped
+        assert_eq!(rotated.rsplit(':').next(), sealed.rsplit(':').next());
+
+        let new_only = KeyRing::new(Some(key(2)), Vec::new());
+        assert_eq!(new_only.open(&rotated).unwrap(), "sk-secret");
+    }
+
+    #[test]
+    fn test_rejects_bad_master_key() {
+        assert!(MasterKey::from_base64("not base64!").is_err());
+        assert!(MasterKey::from_base64(&BASE64.encode([0u8; 16])).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_database_encrypts_api_keys() {
+        let db = Database::new(":memory:").unwrap();
+        db.upsert_api_key("legacy_service", None, "sk-legacy").await.unwrap();
+
+        let db = db.with_keys(KeyRing::new(Some(key(1)), Vec::new()));
+        assert_eq!(db.seal_plaintext_api_keys().await.unwrap(), 1);
+        db.upsert_api_key("new_service", None, "sk-new").await.unwrap();
+
+        let raw: Vec<String> = db
+            .conn()
+            .await
+            .unwrap()
+            .prepare("SELECT api_key FROM external_api_keys")
+            .unwrap()
+            .query_map([], |row| row.get(0))
+            .unwrap()
+            .collect::<rusqlite::Result<_>>()
+            .unwrap();
+        assert!(raw.iter().all(|v| v.starts_with(PREFIX)));
+
+        assert_eq!(db.get_api_key("legacy_service").await.unwrap().unwrap().api_key, "sk-legacy");
+        assert_eq!(db.get_api_key("new_service").await.unwrap().unwrap().api_key, "sk-new");
+
+        let db = db.with_keys(KeyRing::new(Some(key(2)), vec![key(1)]));
+        assert_eq!(db.rotate_api_keys().await.unwrap(), 2);
+        assert_eq!(db.rotate_api_keys().await.unwrap(), 0);
+
+        let db = db.with_keys(KeyRing::new(Some(key(2)), Vec::new()));
+        let keys = db.list_api_keys().await.unwrap();
+        assert_eq!(keys.len(), 2);
+        assert_eq!(keys[0].api_key, "sk-legacy");
+    }
+}
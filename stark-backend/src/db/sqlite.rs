@@ -3,22 +3,79 @@
 //! This file contains:
 //! - Database struct definition
 //! - Connection management (new, init)
-//! - Schema creation and migrations
+//! - Baseline schema creation and legacy column migrations
+//!
+//! New schema changes go in versioned migrations (see migrations.rs), which run
+//! right after `init`.
 //!
 //! All database operations are in the models/ subdirectory.
 
-use rusqlite::{Connection, Result as SqliteResult};
+use r2d2::{Pool, PooledConnection};
+use r2d2_sqlite::SqliteConnectionManager;
+use rusqlite::{ffi, Result as SqliteResult};
 use std::path::Path;
-use std::sync::Mutex;
+use std::sync::Arc;
+
+use super::backend::{DatabaseBackend, SqliteBackend};
+use super::secrets::KeyRing;
+use crate::config;
+
+pub(crate) type SqlitePool = Pool<SqliteConnectionManager>;
+pub(crate) type PooledConn = PooledConnection<SqliteConnectionManager>;
 
-/// Main database wrapper with connection pooling via Mutex
+/// Main database wrapper over a pool of SQLite connections
+///
+/// The file runs in WAL mode, so reads on different connections proceed in
+/// parallel and only writes are serialized (by SQLite, with a busy timeout).
+/// Query methods are async: checking out a connection never blocks the
+/// runtime, and the query itself runs inline since SQLite calls are short.
 pub struct Database {
-    pub(crate) conn: Mutex<Connection>,
+    pub(crate) pool: SqlitePool,
+    /// Where auth sessions, API keys and agent settings live (see `db::backend`)
+    pub(crate) backend: Arc<dyn DatabaseBackend>,
+    /// Master keys for API keys at rest (see `db::secrets`)
+    pub(crate) keys: KeyRing,
+}
+
+/// Pool errors surface as SQLITE_BUSY: all connections stayed checked out
+/// for the whole pool timeout
+fn pool_error(e: impl std::fmt::Display) -> rusqlite::Error {
+    rusqlite::Error::SqliteFailure(
+        ffi::Error::new(ffi::SQLITE_BUSY),
+        Some(format!("connection pool: {}", e)),
+    )
+}
+
+/// A blocking database task panicked or was cancelled
+pub(crate) fn task_failed(e: tokio::task::JoinError) -> rusqlite::Error {
+    rusqlite::Error::SqliteFailure(
+        ffi::Error::new(ffi::SQLITE_ABORT),
+        Some(format!("blocking task failed: {}", e)),
+    )
+}
+
+/// Check out a pooled connection, waiting off the async runtime if none is free
+pub(crate) async fn checkout(pool: &SqlitePool) -> SqliteResult<PooledConn> {
+    if let Some(conn) = pool.try_get() {
+        return Ok(conn);
+    }
+    let pool = pool.clone();
+    tokio::task::spawn_blocking(move || pool.get())
+        .await
+        .map_err(task_failed)?
+        .map_err(pool_error)
 }
 
 impl Database {
-    /// Create a new database connection and initialize schema
+    /// Create a new database connection, initialize schema and apply pending migrations
     pub fn new(database_url: &str) -> SqliteResult<Self> {
+        let db = Self::open(database_url)?;
+        db.migrate()?;
+        Ok(db)
+    }
+
+    /// Open the database with its baseline schema but without running migrations
+    pub fn open(database_url: &str) -> SqliteResult<Self> {
         // Create parent directory if it doesn't exist
         if let Some(parent) = Path::new(database_url).parent() {
             if !parent.as_os_str().is_empty() {
@@ -26,17 +83,62 @@ impl Database {
             }
         }
 
-        let conn = Connection::open(database_url)?;
+        // Every in-memory connection is its own database, so those get a single one
+        let (manager, max_size) = if database_url == ":memory:" {
+            (SqliteConnectionManager::memory(), 1)
+        } else {
+            (SqliteConnectionManager::file(database_url), config::db_pool_size())
+        };
+        let manager = manager.with_init(|conn| {
+            conn.execute_batch("PRAGMA journal_mode = WAL; PRAGMA busy_timeout = 5000;")
+        });
+        let pool = Pool::builder()
+            .max_size(max_size)
+            .build(manager)
+            .map_err(pool_error)?;
+
         let db = Self {
-            conn: Mutex::new(conn),
+            backend: Arc::new(SqliteBackend { pool: pool.clone() }),
+            pool,
+            keys: KeyRing::default(),
         };
         db.init()?;
         Ok(db)
     }
 
+    /// Write the WAL back into the database file, so a shutdown leaves a
+    /// single consistent file behind
+    pub async fn flush(&self) -> SqliteResult<()> {
+        let conn = self.conn().await?;
+        conn.execute_batch("PRAGMA wal_checkpoint(TRUNCATE);")
+    }
+
+    /// Keep shared state (auth, API keys, agent settings) in `backend` instead
+    /// of the local file
+    pub fn with_backend(mut self, backend: Arc<dyn DatabaseBackend>) -> Self {
+        self.backend = backend;
+        self
+    }
+
+    /// Encrypt API keys at rest with these master keys
+    pub fn with_keys(mut self, keys: KeyRing) -> Self {
+        self.keys = keys;
+        self
+    }
+
+    /// Check out a connection for one query method
+    pub(crate) async fn conn(&self) -> SqliteResult<PooledConn> {
+        checkout(&self.pool).await
+    }
+
+    /// Check out a connection from synchronous startup code
+    pub(crate) fn conn_blocking(&self) -> SqliteResult<PooledConn> {
+        self.pool.get().map_err(pool_error)
+    }
+
     /// Initialize all database tables and run migrations
     fn init(&self) -> SqliteResult<()> {
-        let conn = self.conn.lock().unwrap();
+        let conn = self.conn_blocking()?;
 
         // Migrate: rename sessions -> auth_sessions if the old table exists
         let old_table_exists: bool = conn
@@ -113,6 +215,10 @@ impl Database {
                 max_tokens INTEGER NOT NULL DEFAULT 40000,
                 enabled INTEGER NOT NULL DEFAULT 0,
                 secret_key TEXT,
+                budget_max_tokens INTEGER,
+                budget_max_usd REAL,
+                session_budget_max_tokens INTEGER,
+                session_budget_max_usd REAL,
                 created_at TEXT NOT NULL,
                 updated_at TEXT NOT NULL
             )",
@@ -174,6 +280,36 @@ impl Database {
             conn.execute("ALTER TABLE agent_settings ADD COLUMN secret_key TEXT", [])?;
         }
 
+        // Migration: Add per-request budget override columns if they don't exist
+        let has_budget_columns: bool = conn
+            .query_row(
+                "SELECT COUNT(*) FROM pragma_table_info('agent_settings') WHERE name='budget_max_tokens'",
+                [],
+                |row| row.get::<_, i64>(0),
+            )
+            .map(|c| c > 0)
+            .unwrap_or(false);
+
+        if !has_budget_columns {
+            conn.execute("ALTER TABLE agent_settings ADD COLUMN budget_max_tokens INTEGER", [])?;
+            conn.execute("ALTER TABLE agent_settings ADD COLUMN budget_max_usd REAL", [])?;
+        }
+
+        // Migration: Add per-session budget columns if they don't exist
+        let has_session_budget_columns: bool = conn
+            .query_row(
+                "SELECT COUNT(*) FROM pragma_table_info('agent_settings') WHERE name='session_budget_max_tokens'",
+                [],
+                |row| row.get::<_, i64>(0),
+            )
+            .map(|c| c > 0)
+            .unwrap_or(false);
+
+        if !has_session_budget_columns {
+            conn.execute("ALTER TABLE agent_settings ADD COLUMN session_budget_max_tokens INTEGER", [])?;
+            conn.execute("ALTER TABLE agent_settings ADD COLUMN session_budget_max_usd REAL", [])?;
+        }
+
         // Migration: Add web3_tx_requires_confirmation column to bot_settings if it doesn't exist
         let has_web3_tx_confirmation: bool = conn
             .query_row(
@@ -433,6 +569,16 @@ impl Database {
             [],
         )?;
 
+        // Tool group switches (operator-level enable/disable, applies to all channels)
+        conn.execute(
+            "CREATE TABLE IF NOT EXISTS tool_group_settings (
+                group_name TEXT PRIMARY KEY,
+                enabled INTEGER NOT NULL DEFAULT 1,
+                updated_at TEXT NOT NULL
+            )",
+            [],
+        )?;
+
         // Drop old installed_skills table if it exists (migration)
         conn.execute("DROP TABLE IF EXISTS installed_skills", [])?;
 
@@ -845,6 +991,58 @@ impl Database {
             [],
         )?;
 
+        // Agent jobs table (background CodeEngineer runs)
+        conn.execute(
+            "CREATE TABLE IF NOT EXISTS agent_jobs (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                job_id TEXT UNIQUE NOT NULL,
+                task TEXT NOT NULL,
+                workspace TEXT NOT NULL,
+                max_iterations INTEGER NOT NULL,
+                status TEXT NOT NULL DEFAULT 'queued',
+                iterations INTEGER NOT NULL DEFAULT 0,
+                transcript TEXT NOT NULL DEFAULT '[]',
+                response TEXT,
+                error TEXT,
+                created_at TEXT NOT NULL,
+                started_at TEXT,
+                completed_at TEXT
+            )",
+            [],
+        )?;
+
+        conn.execute(
+            "CREATE INDEX IF NOT EXISTS idx_agent_jobs_status ON agent_jobs(status)",
+            [],
+        )?;
+
+        // Usage table (tokens and estimated cost per chat request)
+        conn.execute(
+            "CREATE TABLE IF NOT EXISTS usage (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                session_id INTEGER,
+                channel_id INTEGER NOT NULL,
+                model TEXT NOT NULL,
+                input_tokens INTEGER NOT NULL DEFAULT 0,
+                output_tokens INTEGER NOT NULL DEFAULT 0,
+                cost_usd REAL NOT NULL DEFAULT 0,
+                provider_calls INTEGER NOT NULL DEFAULT 0,
+                estimated INTEGER NOT NULL DEFAULT 0,
+                created_at TEXT NOT NULL
+            )",
+            [],
+        )?;
+
+        conn.execute(
+            "CREATE INDEX IF NOT EXISTS idx_usage_session ON usage(session_id)",
+            [],
+        )?;
+
+        conn.execute(
+            "CREATE INDEX IF NOT EXISTS idx_usage_created_at ON usage(created_at)",
+            [],
+        )?;
+
         // Migration: Add subtype column to agent_contexts if it doesn't exist
         let _ = conn.execute(
             "ALTER TABLE agent_contexts ADD COLUMN subtype TEXT NOT NULL DEFAULT 'finance'",
@@ -861,7 +1059,7 @@ impl Database {
     }
 
     /// Record an x402 payment to the database
-    pub fn record_x402_payment(
+    pub async fn record_x402_payment(
         &self,
         channel_id: Option<i64>,
         tool_name: Option<&str>,
@@ -873,7 +1071,7 @@ impl Database {
         tx_hash: Option<&str>,
         status: &str,
     ) -> Result<i64, rusqlite::Error> {
-        let conn = self.conn.lock().unwrap();
+        let conn = self.conn().await?;
         conn.execute(
             "INSERT INTO x402_payments (channel_id, tool_name, resource, amount, amount_formatted, asset, pay_to, tx_hash, status)
              VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)",
@@ -883,13 +1081,13 @@ impl Database {
     }
 
     /// Update payment status and tx_hash
-    pub fn update_x402_payment_status(
+    pub async fn update_x402_payment_status(
         &self,
         payment_id: i64,
         status: &str,
         tx_hash: Option<&str>,
     ) -> Result<(), rusqlite::Error> {
-        let conn = self.conn.lock().unwrap();
+        let conn = self.conn().await?;
         conn.execute(
             "UPDATE x402_payments SET status = ?1, tx_hash = COALESCE(?2, tx_hash) WHERE id = ?3",
             rusqlite::params![status, tx_hash, payment_id],
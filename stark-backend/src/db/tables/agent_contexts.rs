@@ -10,8 +10,8 @@ use rusqlite::{params, Result as SqliteResult};
 
 impl Database {
     /// Get agent context for a session (if exists)
-    pub fn get_agent_context(&self, session_id: i64) -> SqliteResult<Option<AgentContext>> {
-        let conn = self.conn.lock().unwrap();
+    pub async fn get_agent_context(&self, session_id: i64) -> SqliteResult<Option<AgentContext>> {
+        let conn = self.conn().await?;
 
         let mut stmt = conn.prepare(
             "SELECT original_request, mode, mode_iterations, total_iterations,
@@ -71,12 +71,12 @@ impl Database {
     }
 
     /// Create or update agent context for a session
-    pub fn save_agent_context(
+    pub async fn save_agent_context(
         &self,
         session_id: i64,
         context: &AgentContext,
     ) -> SqliteResult<()> {
-        let conn = self.conn.lock().unwrap();
+        let conn = self.conn().await?;
         let now = Utc::now().to_rfc3339();
 
         // Serialize JSON fields
@@ -117,8 +117,8 @@ impl Database {
     }
 
     /// Delete agent context for a session (e.g., on session reset)
-    pub fn delete_agent_context(&self, session_id: i64) -> SqliteResult<()> {
-        let conn = self.conn.lock().unwrap();
+    pub async fn delete_agent_context(&self, session_id: i64) -> SqliteResult<()> {
+        let conn = self.conn().await?;
         conn.execute(
             "DELETE FROM agent_contexts WHERE session_id = ?",
             params![session_id],
@@ -127,8 +127,8 @@ impl Database {
     }
 
     /// Check if a session has an agent context
-    pub fn has_agent_context(&self, session_id: i64) -> SqliteResult<bool> {
-        let conn = self.conn.lock().unwrap();
+    pub async fn has_agent_context(&self, session_id: i64) -> SqliteResult<bool> {
+        let conn = self.conn().await?;
         let count: i64 = conn.query_row(
             "SELECT COUNT(*) FROM agent_contexts WHERE session_id = ?",
             params![session_id],
@@ -0,0 +1,363 @@
+//! Agent job database operations (background CodeEngineer runs)
+
+use chrono::Utc;
+use rusqlite::{OptionalExtension, Result as SqliteResult};
+use serde_json::Value;
+use std::collections::BTreeMap;
+
+use crate::ai::ResponseFormat;
+use crate::models::{AgentJob, AgentJobStatus};
+use crate::redaction::{redact, redact_value};
+use super::super::Database;
+
+const AGENT_JOB_COLUMNS: &str = "job_id, task, workspace, max_iterations, status, iterations,
+    transcript, response, error, created_at, started_at, completed_at, tools, schedule_id, provider_attempts,
+    planning, plan, response_format, user_id";
+
+/// The transcript as stored: tool arguments and outputs with secrets redacted
+fn redacted_transcript(transcript: &Value) -> String {
+    let mut transcript = transcript.clone();
+    redact_value(&mut transcript);
+    transcript.to_string()
+}
+
+impl Database {
+    /// Queue a new agent job. An empty `tools` list means the default tool set;
+    /// `user_id` is the user who started it, if not a schedule or integration.
+    #[allow(clippy::too_many_arguments)]
+    pub async fn create_agent_job(
+        &self,
+        task: &str,
+        workspace: &str,
+        max_iterations: i64,
+        tools: &[String],
+        schedule_id: Option<i64>,
+        planning: bool,
+        response_format: Option<&ResponseFormat>,
+        user_id: Option<i64>,
+    ) -> SqliteResult<AgentJob> {
+        let conn = self.conn().await?;
+        let job_id = uuid::Uuid::new_v4().to_string();
+        let now = Utc::now().to_rfc3339();
+        let tools_json = serde_json::to_string(tools).unwrap_or_else(|_| "[]".to_string());
+        let response_format_json = response_format.and_then(|f| serde_json::to_string(f).ok());
+
+        conn.execute(
+            "INSERT INTO agent_jobs (job_id, task, workspace, max_iterations, tools, schedule_id, planning,
+                 response_format, user_id, status, created_at)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, 'queued', ?10)",
+            rusqlite::params![
+                job_id,
+                task,
+                workspace,
+                max_iterations,
+                tools_json,
+                schedule_id,
+                planning,
+                response_format_json,
+                user_id,
+                now
+            ],
+        )?;
+
+        Ok(AgentJob {
+            job_id,
+            task: task.to_string(),
+            workspace: workspace.to_string(),
+            max_iterations,
+            tools: tools.to_vec(),
+            schedule_id,
+            planning,
+            response_format: response_format.cloned(),
+            user_id,
+            status: AgentJobStatus::Queued,
+            iterations: 0,
+            transcript: Value::Array(vec![]),
+            provider_attempts: Value::Array(vec![]),
+            plan: Value::Array(vec![]),
+            response: None,
+            error: None,
+            created_at: now,
+            started_at: None,
+            completed_at: None,
+        })
+    }
+
+    /// Get an agent job by its public id
+    pub async fn get_agent_job(&self, job_id: &str) -> SqliteResult<Option<AgentJob>> {
+        let conn = self.conn().await?;
+        conn.query_row(
+            &format!("SELECT {} FROM agent_jobs WHERE job_id = ?1", AGENT_JOB_COLUMNS),
+            [job_id],
+            Self::map_agent_job_row,
+        )
+        .optional()
+    }
+
+    /// Most recent jobs queued by a scheduled task, newest first
+    pub async fn list_agent_jobs_for_schedule(&self, schedule_id: i64, limit: usize) -> SqliteResult<Vec<AgentJob>> {
+        let conn = self.conn().await?;
+        let mut stmt = conn.prepare(&format!(
+            "SELECT {} FROM agent_jobs WHERE schedule_id = ?1 ORDER BY id DESC LIMIT ?2",
+            AGENT_JOB_COLUMNS
+        ))?;
+        let jobs = stmt
+            .query_map(rusqlite::params![schedule_id, limit as i64], Self::map_agent_job_row)?
+            .collect::<SqliteResult<Vec<_>>>()?;
+        Ok(jobs)
+    }
+
+    /// Mark the oldest queued job as running and return it
+    pub async fn claim_next_agent_job(&self) -> SqliteResult<Option<AgentJob>> {
+        let conn = self.conn().await?;
+        loop {
+            let job = conn
+                .query_row(
+                    &format!(
+                        "SELECT {} FROM agent_jobs WHERE status = 'queued' ORDER BY id ASC LIMIT 1",
+                        AGENT_JOB_COLUMNS
+                    ),
+                    [],
+                    Self::map_agent_job_row,
+                )
+                .optional()?;
+
+            let Some(mut job) = job else {
+                return Ok(None);
+            };
+
+            let now = Utc::now().to_rfc3339();
+            // A job cancelled since the SELECT stays cancelled
+            let claimed = conn.execute(
+                "UPDATE agent_jobs SET status = 'running', started_at = ?1 WHERE job_id = ?2 AND status = 'queued'",
+                rusqlite::params![now, job.job_id],
+            )?;
+            if claimed == 0 {
+                continue;
+            }
+            job.status = AgentJobStatus::Running;
+            job.started_at = Some(now);
+            return Ok(Some(job));
+        }
+    }
+
+    /// Record progress of a running job
+    pub async fn update_agent_job_progress(
+        &self,
+        job_id: &str,
+        iterations: i64,
+        transcript: &Value,
+        provider_attempts: &Value,
+        plan: &Value,
+    ) -> SqliteResult<()> {
+        let conn = self.conn().await?;
+        conn.execute(
+            "UPDATE agent_jobs SET iterations = ?1, transcript = ?2, provider_attempts = ?3, plan = ?4 WHERE job_id = ?5",
+            rusqlite::params![iterations, redacted_transcript(transcript), provider_attempts.to_string(), plan.to_string(), job_id],
+        )?;
+        Ok(())
+    }
+
+    /// Record the final outcome of a job
+    #[allow(clippy::too_many_arguments)]
+    pub async fn finish_agent_job(
+        &self,
+        job_id: &str,
+        status: AgentJobStatus,
+        iterations: i64,
+        transcript: &Value,
+        provider_attempts: &Value,
+        response: Option<&str>,
+        error: Option<&str>,
+    ) -> SqliteResult<()> {
+        let conn = self.conn().await?;
+        let now = Utc::now().to_rfc3339();
+        conn.execute(
+            "UPDATE agent_jobs SET status = ?1, iterations = ?2, transcript = ?3, provider_attempts = ?4,
+                    response = ?5, error = ?6, completed_at = ?7
+             WHERE job_id = ?8",
+            rusqlite::params![
+                status.as_str(),
+                iterations,
+                redacted_transcript(transcript),
+                provider_attempts.to_string(),
+                response.map(redact),
+                error.map(redact),
+                now,
+                job_id
+            ],
+        )?;
+        Ok(())
+    }
+
+    /// Mark a queued or running job as cancelled. Returns false if the job
+    /// doesn't exist or has already finished.
+    pub async fn cancel_agent_job(&self, job_id: &str) -> SqliteResult<bool> {
+        let conn = self.conn().await?;
+        let now = Utc::now().to_rfc3339();
+        let changed = conn.execute(
+            "UPDATE agent_jobs SET status = 'cancelled', completed_at = ?1
+             WHERE job_id = ?2 AND status IN ('queued', 'running')",
+            rusqlite::params![now, job_id],
+        )?;
+        Ok(changed > 0)
+    }
+
+    /// Put a running job back on the queue, keeping its recorded progress so
+    /// the next run picks up from there. Returns false if it isn't running.
+    pub async fn requeue_agent_job(&self, job_id: &str) -> SqliteResult<bool> {
+        let conn = self.conn().await?;
+        let changed = conn.execute(
+            "UPDATE agent_jobs SET status = 'queued', started_at = NULL WHERE job_id = ?1 AND status = 'running'",
+            [job_id],
+        )?;
+        Ok(changed > 0)
+    }
+
+    /// Jobs that are queued or running, oldest first
+    pub async fn list_live_agent_jobs(&self) -> SqliteResult<Vec<AgentJob>> {
+        let conn = self.conn().await?;
+        let mut stmt = conn.prepare(&format!(
+            "SELECT {} FROM agent_jobs WHERE status IN ('queued', 'running') ORDER BY id ASC",
+            AGENT_JOB_COLUMNS
+        ))?;
+        let jobs = stmt
+            .query_map([], Self::map_agent_job_row)?
+            .collect::<SqliteResult<Vec<_>>>()?;
+        Ok(jobs)
+    }
+
+    /// Jobs that finished since `since` (RFC 3339), counted by status
+    pub async fn count_finished_agent_jobs(&self, since: &str) -> SqliteResult<BTreeMap<String, i64>> {
+        let conn = self.conn().await?;
+        let mut stmt = conn.prepare(
+            "SELECT status, COUNT(*) FROM agent_jobs WHERE completed_at >= ?1 GROUP BY status",
+        )?;
+        let counts = stmt
+            .query_map([since], |row| Ok((row.get(0)?, row.get(1)?)))?
+            .collect::<SqliteResult<BTreeMap<_, _>>>()?;
+        Ok(counts)
+    }
+
+    /// Put jobs left running by a previous process back on the queue.
+    /// Returns the number of jobs requeued.
+    pub async fn requeue_interrupted_agent_jobs(&self) -> SqliteResult<usize> {
+        let conn = self.conn().await?;
+        conn.execute(
+            "UPDATE agent_jobs SET status = 'queued', started_at = NULL WHERE status = 'running'",
+            [],
+        )
+    }
+
+    fn map_agent_job_row(row: &rusqlite::Row) -> SqliteResult<AgentJob> {
+        let status: String = row.get(4)?;
+        let transcript: String = row.get(6)?;
+        let tools: String = row.get(12)?;
+        let provider_attempts: String = row.get(14)?;
+        let plan: String = row.get(16)?;
+        let response_format: Option<String> = row.get(17)?;
+        Ok(AgentJob {
+            job_id: row.get(0)?,
+            task: row.get(1)?,
+            workspace: row.get(2)?,
+            max_iterations: row.get(3)?,
+            tools: serde_json::from_str(&tools).unwrap_or_default(),
+            schedule_id: row.get(13)?,
+            planning: row.get(15)?,
+            response_format: response_format.and_then(|f| serde_json::from_str(&f).ok()),
+            user_id: row.get(18)?,
+            status: AgentJobStatus::from_str(&status).unwrap_or(AgentJobStatus::Failed),
+            iterations: row.get(5)?,
+            transcript: serde_json::from_str(&transcript).unwrap_or_else(|_| Value::Array(vec![])),
+            provider_attempts: serde_json::from_str(&provider_attempts).unwrap_or_else(|_| Value::Array(vec![])),
+            plan: serde_json::from_str(&plan).unwrap_or_else(|_| Value::Array(vec![])),
+            response: row.get(7)?,
+            error: row.get(8)?,
+            created_at: row.get(9)?,
+            started_at: row.get(10)?,
+            completed_at: row.get(11)?,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[tokio::test]
+    async fn test_agent_job_lifecycle_survives_reopen() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let path = dir.path().join("stark.db");
+        let db = Database::new(path.to_str().unwrap()).unwrap();
+
+        let first = db.create_agent_job("first task", "ws-a", 10, &[], None, true, Some(&ResponseFormat::JsonObject), Some(3)).await.unwrap();
+        let second = db.create_agent_job("second task", "ws-b", 10, &["read_file".to_string()], Some(7), false, None, None).await.unwrap();
+
+        // Jobs are claimed oldest first
+        let claimed = db.claim_next_agent_job().await.unwrap().unwrap();
+        assert_eq!(claimed.job_id, first.job_id);
+        assert_eq!(claimed.status, AgentJobStatus::Running);
+
+        let transcript = json!([{ "name": "write_file", "success": true }]);
+        let attempts = json!([{ "provider": "primary", "status_code": 429, "error": "rate limited" }]);
+        let plan = json!([{ "id": 1, "description": "Write the test", "status": "in_progress" }]);
+        db.update_agent_job_progress(&first.job_id, 2, &transcript, &attempts, &plan).await.unwrap();
+
+        // Reopening the database simulates a restart mid-run
+        drop(db);
+        let db = Database::new(path.to_str().unwrap()).unwrap();
+        let job = db.get_agent_job(&first.job_id).await.unwrap().unwrap();
+        assert_eq!(job.status, AgentJobStatus::Running);
+        assert_eq!(job.iterations, 2);
+        assert_eq!(job.transcript, transcript);
+        assert_eq!(job.provider_attempts, attempts);
+        assert!(job.planning);
+        assert_eq!(job.plan, plan);
+        assert_eq!(job.response_format, Some(ResponseFormat::JsonObject));
+        assert_eq!(job.user_id, Some(3));
+
+        assert_eq!(db.requeue_interrupted_agent_jobs().await.unwrap(), 1);
+        assert_eq!(db.claim_next_agent_job().await.unwrap().unwrap().job_id, first.job_id);
+
+        db.finish_agent_job(&first.job_id, AgentJobStatus::Completed, 3, &transcript, &attempts, Some("done"), None).await
+            .unwrap();
+        let job = db.get_agent_job(&first.job_id).await.unwrap().unwrap();
+        assert_eq!(job.status, AgentJobStatus::Completed);
+        assert_eq!(job.response.as_deref(), Some("done"));
+        assert!(job.completed_at.is_some());
+        let live = db.list_live_agent_jobs().await.unwrap();
+        assert_eq!(live.iter().map(|j| j.job_id.as_str()).collect::<Vec<_>>(), vec![second.job_id.as_str()]);
+        let hour_ago = (Utc::now() - chrono::Duration::hours(1)).to_rfc3339();
+        assert_eq!(db.count_finished_agent_jobs(&hour_ago).await.unwrap().get("completed"), Some(&1));
+
+        let claimed = db.claim_next_agent_job().await.unwrap().unwrap();
+        assert_eq!(claimed.job_id, second.job_id);
+        assert_eq!(claimed.tools, vec!["read_file"]);
+        assert_eq!(db.list_agent_jobs_for_schedule(7, 10).await.unwrap().len(), 1);
+        assert!(db.claim_next_agent_job().await.unwrap().is_none());
+        assert!(db.get_agent_job("missing").await.unwrap().is_none());
+    }
+
+    #[tokio::test]
+    async fn test_cancelled_jobs_are_not_claimed() {
+        let db = Database::new(":memory:").unwrap();
+        let queued = db.create_agent_job("queued task", "ws-a", 10, &[], None, false, None, None).await.unwrap();
+        let running = db.create_agent_job("running task", "ws-b", 10, &[], None, false, None, None).await.unwrap();
+
+        assert!(db.cancel_agent_job(&queued.job_id).await.unwrap());
+        let claimed = db.claim_next_agent_job().await.unwrap().unwrap();
+        assert_eq!(claimed.job_id, running.job_id);
+
+        assert!(db.cancel_agent_job(&running.job_id).await.unwrap());
+        let job = db.get_agent_job(&running.job_id).await.unwrap().unwrap();
+        assert_eq!(job.status, AgentJobStatus::Cancelled);
+        assert!(job.status.is_finished());
+        assert!(job.completed_at.is_some());
+
+        // Finished and unknown jobs can't be cancelled
+        assert!(!db.cancel_agent_job(&running.job_id).await.unwrap());
+        assert!(!db.cancel_agent_job("missing").await.unwrap());
+        assert!(db.claim_next_agent_job().await.unwrap().is_none());
+    }
+}
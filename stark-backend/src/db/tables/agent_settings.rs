@@ -1,55 +1,68 @@
 //! Agent settings database operations
 
+use async_trait::async_trait;
 use chrono::{DateTime, Utc};
-use rusqlite::Result as SqliteResult;
 
-use crate::models::AgentSettings;
-use super::super::Database;
+use crate::models::{parse_model_list, AgentSettings, UpdateAgentSettingsRequest};
+use super::super::backend::{AgentSettingsStore, DbResult, SqliteBackend};
 
-impl Database {
+#[async_trait]
+impl AgentSettingsStore for SqliteBackend {
     /// Get the currently enabled agent settings (only one can be enabled)
-    pub fn get_active_agent_settings(&self) -> SqliteResult<Option<AgentSettings>> {
-        let conn = self.conn.lock().unwrap();
+    async fn get_active_agent_settings(&self) -> DbResult<Option<AgentSettings>> {
+        let conn = self.conn().await?;
 
         let mut stmt = conn.prepare(
-            "SELECT id, endpoint, model_archetype, max_tokens, enabled, secret_key, created_at, updated_at
+            "SELECT id, endpoint, model_archetype, max_tokens, enabled, secret_key, created_at, updated_at,
+                    budget_max_tokens, budget_max_usd, session_budget_max_tokens, session_budget_max_usd,
+                    session_ttl_hours, session_sliding, allowed_models, spend_max_tx_usd, spend_max_daily_usd,
+                    spend_approval_usd, max_context_tokens, fallback_endpoint, fallback_model_archetype,
+                    fallback_model, fallback_secret_key, default_persona
              FROM agent_settings WHERE enabled = 1 LIMIT 1",
         )?;
 
         let settings = stmt
-            .query_row([], |row| Self::row_to_agent_settings(row))
+            .query_row([], SqliteBackend::row_to_agent_settings)
             .ok();
 
         Ok(settings)
     }
 
     /// Get agent settings by endpoint
-    pub fn get_agent_settings_by_endpoint(&self, endpoint: &str) -> SqliteResult<Option<AgentSettings>> {
-        let conn = self.conn.lock().unwrap();
+    async fn get_agent_settings_by_endpoint(&self, endpoint: &str) -> DbResult<Option<AgentSettings>> {
+        let conn = self.conn().await?;
 
         let mut stmt = conn.prepare(
-            "SELECT id, endpoint, model_archetype, max_tokens, enabled, secret_key, created_at, updated_at
+            "SELECT id, endpoint, model_archetype, max_tokens, enabled, secret_key, created_at, updated_at,
+                    budget_max_tokens, budget_max_usd, session_budget_max_tokens, session_budget_max_usd,
+                    session_ttl_hours, session_sliding, allowed_models, spend_max_tx_usd, spend_max_daily_usd,
+                    spend_approval_usd, max_context_tokens, fallback_endpoint, fallback_model_archetype,
+                    fallback_model, fallback_secret_key, default_persona
              FROM agent_settings WHERE endpoint = ?1",
         )?;
 
         let settings = stmt
-            .query_row([endpoint], |row| Self::row_to_agent_settings(row))
+            .query_row([endpoint], SqliteBackend::row_to_agent_settings)
             .ok();
 
         Ok(settings)
     }
 
     /// List all agent settings
-    pub fn list_agent_settings(&self) -> SqliteResult<Vec<AgentSettings>> {
-        let conn = self.conn.lock().unwrap();
+    async fn list_agent_settings(&self) -> DbResult<Vec<AgentSettings>> {
+        let conn = self.conn().await?;
 
         let mut stmt = conn.prepare(
-            "SELECT id, endpoint, model_archetype, max_tokens, enabled, secret_key, created_at, updated_at
+            "SELECT id, endpoint, model_archetype, max_tokens, enabled, secret_key, created_at, updated_at,
+                    budget_max_tokens, budget_max_usd, session_budget_max_tokens, session_budget_max_usd,
+                    session_ttl_hours, session_sliding, allowed_models, spend_max_tx_usd, spend_max_daily_usd,
+                    spend_approval_usd, max_context_tokens, fallback_endpoint, fallback_model_archetype,
+                    fallback_model, fallback_secret_key, default_persona
              FROM agent_settings ORDER BY id",
         )?;
 
         let settings = stmt
-            .query_map([], |row| Self::row_to_agent_settings(row))?
+            .query_map([], SqliteBackend::row_to_agent_settings)?
             .filter_map(|r| r.ok())
             .collect();
 
@@ -57,14 +70,9 @@ impl Database {
     }
 
     /// Save agent settings (upsert by endpoint, and set as the only enabled one)
-    pub fn save_agent_settings(
-        &self,
-        endpoint: &str,
-        model_archetype: &str,
-        max_tokens: i32,
-        secret_key: Option<&str>,
-    ) -> SqliteResult<AgentSettings> {
-        let conn = self.conn.lock().unwrap();
+    async fn save_agent_settings(&self, request: &UpdateAgentSettingsRequest) -> DbResult<AgentSettings> {
+        let endpoint = request.endpoint.as_str();
+        let conn = self.conn().await?;
         let now = Utc::now().to_rfc3339();
 
         // First, disable all existing settings
@@ -82,33 +90,89 @@ impl Database {
         if let Some(id) = existing {
             // Update existing
             conn.execute(
-                "UPDATE agent_settings SET model_archetype = ?1, max_tokens = ?2, secret_key = ?3, enabled = 1, updated_at = ?4 WHERE id = ?5",
-                rusqlite::params![model_archetype, max_tokens, secret_key, &now, id],
+                "UPDATE agent_settings SET model_archetype = ?1, max_tokens = ?2, secret_key = ?3, budget_max_tokens = ?4, budget_max_usd = ?5,
+                        session_budget_max_tokens = ?6, session_budget_max_usd = ?7, session_ttl_hours = ?8, session_sliding = ?9,
+                        allowed_models = ?10, spend_max_tx_usd = ?11, spend_max_daily_usd = ?12, spend_approval_usd = ?13,
+                        max_context_tokens = ?14, fallback_endpoint = ?15, fallback_model_archetype = ?16,
+                        fallback_model = ?17, fallback_secret_key = ?18, default_persona = ?19, enabled = 1,
+                        updated_at = ?20 WHERE id = ?21",
+                rusqlite::params![
+                    request.model_archetype,
+                    request.max_tokens,
+                    request.secret_key,
+                    request.budget_max_tokens,
+                    request.budget_max_usd,
+                    request.session_budget_max_tokens,
+                    request.session_budget_max_usd,
+                    request.session_ttl_hours,
+                    request.session_sliding,
+                    request.allowed_models.join(","),
+                    request.spend_max_tx_usd,
+                    request.spend_max_daily_usd,
+                    request.spend_approval_usd,
+                    request.max_context_tokens,
+                    request.fallback_endpoint,
+                    request.fallback_model_archetype,
+                    request.fallback_model,
+                    request.fallback_secret_key,
+                    request.default_persona,
+                    &now,
+                    id
+                ],
             )?;
         } else {
             // Insert new
             conn.execute(
-                "INSERT INTO agent_settings (endpoint, model_archetype, max_tokens, secret_key, enabled, created_at, updated_at)
-                 VALUES (?1, ?2, ?3, ?4, 1, ?5, ?6)",
-                rusqlite::params![endpoint, model_archetype, max_tokens, secret_key, &now, &now],
+                "INSERT INTO agent_settings (endpoint, model_archetype, max_tokens, secret_key, budget_max_tokens, budget_max_usd,
+                                             session_budget_max_tokens, session_budget_max_usd, session_ttl_hours, session_sliding,
+                                             allowed_models, spend_max_tx_usd, spend_max_daily_usd, spend_approval_usd,
+                                             max_context_tokens, fallback_endpoint, fallback_model_archetype, fallback_model,
+                                             fallback_secret_key, default_persona, enabled, created_at, updated_at)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15, ?16, ?17, ?18, ?19, ?20, 1, ?21, ?22)",
+                rusqlite::params![
+                    endpoint,
+                    request.model_archetype,
+                    request.max_tokens,
+                    request.secret_key,
+                    request.budget_max_tokens,
+                    request.budget_max_usd,
+                    request.session_budget_max_tokens,
+                    request.session_budget_max_usd,
+                    request.session_ttl_hours,
+                    request.session_sliding,
+                    request.allowed_models.join(","),
+                    request.spend_max_tx_usd,
+                    request.spend_max_daily_usd,
+                    request.spend_approval_usd,
+                    request.max_context_tokens,
+                    request.fallback_endpoint,
+                    request.fallback_model_archetype,
+                    request.fallback_model,
+                    request.fallback_secret_key,
+                    request.default_persona,
+                    &now,
+                    &now
+                ],
             )?;
         }
 
         drop(conn);
 
         // Return the saved settings
-        self.get_agent_settings_by_endpoint(endpoint)
+        self.get_agent_settings_by_endpoint(endpoint).await
             .map(|opt| opt.unwrap())
     }
 
     /// Disable all agent settings (no AI provider active)
-    pub fn disable_agent_settings(&self) -> SqliteResult<()> {
-        let conn = self.conn.lock().unwrap();
+    async fn disable_agent_settings(&self) -> DbResult<()> {
+        let conn = self.conn().await?;
         let now = Utc::now().to_rfc3339();
         conn.execute("UPDATE agent_settings SET enabled = 0, updated_at = ?1", [&now])?;
         Ok(())
     }
+}
 
+impl SqliteBackend {
     fn row_to_agent_settings(row: &rusqlite::Row) -> rusqlite::Result<AgentSettings> {
         let created_at_str: String = row.get(6)?;
         let updated_at_str: String = row.get(7)?;
@@ -120,6 +184,22 @@ impl Database {
             max_tokens: row.get::<_, Option<i32>>(3)?.unwrap_or(40000),
             enabled: row.get::<_, i32>(4)? != 0,
             secret_key: row.get(5)?,
+            budget_max_tokens: row.get(8)?,
+            budget_max_usd: row.get(9)?,
+            session_budget_max_tokens: row.get(10)?,
+            session_budget_max_usd: row.get(11)?,
+            session_ttl_hours: row.get(12)?,
+            session_sliding: row.get::<_, i32>(13)? != 0,
+            allowed_models: parse_model_list(&row.get::<_, String>(14)?),
+            spend_max_tx_usd: row.get(15)?,
+            spend_max_daily_usd: row.get(16)?,
+            spend_approval_usd: row.get(17)?,
+            max_context_tokens: row.get(18)?,
+            fallback_endpoint: row.get(19)?,
+            fallback_model_archetype: row.get(20)?,
+            fallback_model: row.get(21)?,
+            fallback_secret_key: row.get(22)?,
+            default_persona: row.get(23)?,
             created_at: DateTime::parse_from_rfc3339(&created_at_str)
                 .unwrap()
                 .with_timezone(&Utc),
@@ -1,67 +1,60 @@
 //! API key database operations
 
+use async_trait::async_trait;
 use chrono::{DateTime, Utc};
-use rusqlite::Result as SqliteResult;
+use rusqlite::{OptionalExtension, Row};
 
 use crate::models::ApiKey;
-use super::super::Database;
-
-impl Database {
-    /// Get an API key by service name
-    pub fn get_api_key(&self, service_name: &str) -> SqliteResult<Option<ApiKey>> {
-        let conn = self.conn.lock().unwrap();
+use super::super::backend::{ApiKeyStore, DbResult, SqliteBackend};
+
+const COLUMNS: &str = "id, service_name, api_key, created_at, updated_at, user_id";
+
+fn row_to_api_key(row: &Row) -> rusqlite::Result<ApiKey> {
+    let created_at_str: String = row.get(3)?;
+    let updated_at_str: String = row.get(4)?;
+
+    Ok(ApiKey {
+        id: row.get(0)?,
+        service_name: row.get(1)?,
+        user_id: row.get(5)?,
+        api_key: row.get(2)?,
+        created_at: DateTime::parse_from_rfc3339(&created_at_str)
+            .unwrap()
+            .with_timezone(&Utc),
+        updated_at: DateTime::parse_from_rfc3339(&updated_at_str)
+            .unwrap()
+            .with_timezone(&Utc),
+    })
+}
 
-        let mut stmt = conn.prepare(
-            "SELECT id, service_name, api_key, created_at, updated_at FROM external_api_keys WHERE service_name = ?1",
-        )?;
+#[async_trait]
+impl ApiKeyStore for SqliteBackend {
+    /// Get the shared (`user_id` None) or a user's personal API key by service name
+    async fn get_api_key(&self, service_name: &str, user_id: Option<i64>) -> DbResult<Option<ApiKey>> {
+        let conn = self.conn().await?;
 
-        let api_key = stmt
-            .query_row([service_name], |row| {
-                let created_at_str: String = row.get(3)?;
-                let updated_at_str: String = row.get(4)?;
-
-                Ok(ApiKey {
-                    id: row.get(0)?,
-                    service_name: row.get(1)?,
-                    api_key: row.get(2)?,
-                    created_at: DateTime::parse_from_rfc3339(&created_at_str)
-                        .unwrap()
-                        .with_timezone(&Utc),
-                    updated_at: DateTime::parse_from_rfc3339(&updated_at_str)
-                        .unwrap()
-                        .with_timezone(&Utc),
-                })
-            })
-            .ok();
+        let api_key = conn
+            .query_row(
+                &format!("SELECT {} FROM external_api_keys WHERE service_name = ?1 AND user_id IS ?2", COLUMNS),
+                rusqlite::params![service_name, user_id],
+                row_to_api_key,
+            )
+            .optional()?;
 
         Ok(api_key)
     }
 
     /// List all API keys
-    pub fn list_api_keys(&self) -> SqliteResult<Vec<ApiKey>> {
-        let conn = self.conn.lock().unwrap();
+    async fn list_api_keys(&self) -> DbResult<Vec<ApiKey>> {
+        let conn = self.conn().await?;
 
-        let mut stmt = conn.prepare(
-            "SELECT id, service_name, api_key, created_at, updated_at FROM external_api_keys ORDER BY service_name",
-        )?;
+        let mut stmt = conn.prepare(&format!(
+            "SELECT {} FROM external_api_keys ORDER BY service_name, user_id",
+            COLUMNS
+        ))?;
 
         let api_keys = stmt
-            .query_map([], |row| {
-                let created_at_str: String = row.get(3)?;
-                let updated_at_str: String = row.get(4)?;
-
-                Ok(ApiKey {
-                    id: row.get(0)?,
-                    service_name: row.get(1)?,
-                    api_key: row.get(2)?,
-                    created_at: DateTime::parse_from_rfc3339(&created_at_str)
-                        .unwrap()
-                        .with_timezone(&Utc),
-                    updated_at: DateTime::parse_from_rfc3339(&updated_at_str)
-                        .unwrap()
-                        .with_timezone(&Utc),
-                })
-            })?
+            .query_map([], row_to_api_key)?
             .filter_map(|r| r.ok())
             .collect();
 
@@ -69,36 +62,36 @@ impl Database {
     }
 
     /// Insert or update an API key
-    pub fn upsert_api_key(&self, service_name: &str, api_key: &str) -> SqliteResult<ApiKey> {
-        let conn = self.conn.lock().unwrap();
+    async fn upsert_api_key(&self, service_name: &str, user_id: Option<i64>, api_key: &str) -> DbResult<ApiKey> {
+        let conn = self.conn().await?;
         let now = Utc::now().to_rfc3339();
 
         // Try to update first
         let rows_affected = conn.execute(
-            "UPDATE external_api_keys SET api_key = ?1, updated_at = ?2 WHERE service_name = ?3",
-            [api_key, &now, service_name],
+            "UPDATE external_api_keys SET api_key = ?1, updated_at = ?2 WHERE service_name = ?3 AND user_id IS ?4",
+            rusqlite::params![api_key, &now, service_name, user_id],
         )?;
 
         if rows_affected == 0 {
             // Insert new
             conn.execute(
-                "INSERT INTO external_api_keys (service_name, api_key, created_at, updated_at) VALUES (?1, ?2, ?3, ?4)",
-                [service_name, api_key, &now, &now],
+                "INSERT INTO external_api_keys (service_name, user_id, api_key, created_at, updated_at) VALUES (?1, ?2, ?3, ?4, ?5)",
+                rusqlite::params![service_name, user_id, api_key, &now, &now],
             )?;
         }
 
         drop(conn);
 
         // Return the upserted key
-        self.get_api_key(service_name).map(|opt| opt.unwrap())
+        self.get_api_key(service_name, user_id).await.map(|opt| opt.unwrap())
     }
 
     /// Delete an API key by service name
-    pub fn delete_api_key(&self, service_name: &str) -> SqliteResult<bool> {
-        let conn = self.conn.lock().unwrap();
+    async fn delete_api_key(&self, service_name: &str, user_id: Option<i64>) -> DbResult<bool> {
+        let conn = self.conn().await?;
         let rows_affected = conn.execute(
-            "DELETE FROM external_api_keys WHERE service_name = ?1",
-            [service_name],
+            "DELETE FROM external_api_keys WHERE service_name = ?1 AND user_id IS ?2",
+            rusqlite::params![service_name, user_id],
         )?;
         Ok(rows_affected > 0)
     }
@@ -0,0 +1,120 @@
+//! Scoped API token database operations
+
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use rusqlite::Row;
+
+use crate::models::{ApiToken, Scopes, ToolPolicy};
+use super::super::backend::{ApiTokenStore, DbResult, SqliteBackend};
+
+const COLUMNS: &str = "id, name, scopes, prefix, created_at, last_used_at, tool_policy, user_id";
+
+fn parse_timestamp(s: &str) -> DateTime<Utc> {
+    DateTime::parse_from_rfc3339(s).unwrap().with_timezone(&Utc)
+}
+
+fn row_to_api_token(row: &Row) -> rusqlite::Result<ApiToken> {
+    let scopes: String = row.get(2)?;
+    let created_at: String = row.get(4)?;
+    let last_used_at: Option<String> = row.get(5)?;
+    let tool_policy: Option<String> = row.get(6)?;
+
+    Ok(ApiToken {
+        id: row.get(0)?,
+        user_id: row.get(7)?,
+        name: row.get(1)?,
+        scopes: Scopes::from_db_string(&scopes),
+        prefix: row.get(3)?,
+        tool_policy: tool_policy.and_then(|p| serde_json::from_str(&p).ok()),
+        created_at: parse_timestamp(&created_at),
+        last_used_at: last_used_at.as_deref().map(parse_timestamp),
+    })
+}
+
+#[async_trait]
+impl ApiTokenStore for SqliteBackend {
+    async fn create_api_token(
+        &self,
+        user_id: i64,
+        name: &str,
+        token_hash: &str,
+        prefix: &str,
+        scopes: &Scopes,
+        tool_policy: Option<&ToolPolicy>,
+    ) -> DbResult<ApiToken> {
+        let conn = self.conn().await?;
+        let created_at = Utc::now();
+        let tool_policy_json = tool_policy.map(|p| serde_json::to_string(p).unwrap_or_default());
+
+        conn.execute(
+            "INSERT INTO api_tokens (user_id, name, token_hash, prefix, scopes, tool_policy, created_at) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+            rusqlite::params![user_id, name, token_hash, prefix, scopes.to_db_string(), tool_policy_json, created_at.to_rfc3339()],
+        )?;
+
+        Ok(ApiToken {
+            id: conn.last_insert_rowid(),
+            user_id: Some(user_id),
+            name: name.to_string(),
+            scopes: scopes.clone(),
+            prefix: prefix.to_string(),
+            tool_policy: tool_policy.cloned(),
+            created_at,
+            last_used_at: None,
+        })
+    }
+
+    async fn list_api_tokens(&self) -> DbResult<Vec<ApiToken>> {
+        let conn = self.conn().await?;
+        let mut stmt = conn.prepare(&format!("SELECT {} FROM api_tokens ORDER BY name", COLUMNS))?;
+        let tokens = stmt
+            .query_map([], row_to_api_token)?
+            .filter_map(|r| r.ok())
+            .collect();
+        Ok(tokens)
+    }
+
+    async fn set_api_token_tool_policy(&self, id: i64, tool_policy: Option<&ToolPolicy>) -> DbResult<Option<ApiToken>> {
+        let conn = self.conn().await?;
+        let tool_policy_json = tool_policy.map(|p| serde_json::to_string(p).unwrap_or_default());
+        let updated = conn.execute(
+            "UPDATE api_tokens SET tool_policy = ?1 WHERE id = ?2",
+            rusqlite::params![tool_policy_json, id],
+        )?;
+        if updated == 0 {
+            return Ok(None);
+        }
+
+        let token = conn.query_row(
+            &format!("SELECT {} FROM api_tokens WHERE id = ?1", COLUMNS),
+            [id],
+            row_to_api_token,
+        )?;
+        Ok(Some(token))
+    }
+
+    async fn validate_api_token(&self, token_hash: &str) -> DbResult<Option<ApiToken>> {
+        let conn = self.conn().await?;
+        let now = Utc::now().to_rfc3339();
+
+        let updated = conn.execute(
+            "UPDATE api_tokens SET last_used_at = ?1 WHERE token_hash = ?2",
+            [&now, token_hash],
+        )?;
+        if updated == 0 {
+            return Ok(None);
+        }
+
+        let token = conn.query_row(
+            &format!("SELECT {} FROM api_tokens WHERE token_hash = ?1", COLUMNS),
+            [token_hash],
+            row_to_api_token,
+        )?;
+        Ok(Some(token))
+    }
+
+    async fn delete_api_token(&self, id: i64) -> DbResult<bool> {
+        let conn = self.conn().await?;
+        let rows_affected = conn.execute("DELETE FROM api_tokens WHERE id = ?1", [id])?;
+        Ok(rows_affected > 0)
+    }
+}
@@ -0,0 +1,141 @@
+//! Approvals for value-moving tool calls above the spending threshold
+//!
+//! The tool waits on its row while it is pending; an operator approves or
+//! rejects it through `POST /api/approvals/{id}`.
+
+use chrono::Utc;
+use rusqlite::OptionalExtension;
+
+use crate::db::DbResult;
+use crate::models::{Approval, ApprovalStatus, NewApproval};
+use super::super::Database;
+
+const APPROVAL_COLUMNS: &str = "id, status, tool_name, tool_call_id, channel_id, session_id, description, \
+     amount_usd, reason, user_id, created_at, decided_at";
+
+impl Database {
+    /// Request an approval, returning the pending row
+    pub async fn create_approval(&self, approval: &NewApproval) -> DbResult<Approval> {
+        let conn = self.conn().await?;
+        conn.execute(
+            "INSERT INTO approvals (status, tool_name, tool_call_id, channel_id, session_id, description,
+                 amount_usd, reason, user_id, created_at)
+             VALUES ('pending', ?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)",
+            rusqlite::params![
+                approval.tool_name,
+                approval.tool_call_id,
+                approval.channel_id,
+                approval.session_id,
+                approval.description,
+                approval.amount_usd,
+                approval.reason,
+                approval.user_id,
+                Utc::now().to_rfc3339()
+            ],
+        )?;
+        let id = conn.last_insert_rowid();
+        Ok(conn.query_row(
+            &format!("SELECT {} FROM approvals WHERE id = ?1", APPROVAL_COLUMNS),
+            [id],
+            Self::row_to_approval,
+        )?)
+    }
+
+    pub async fn get_approval(&self, id: i64) -> DbResult<Option<Approval>> {
+        let conn = self.conn().await?;
+        Ok(conn
+            .query_row(
+                &format!("SELECT {} FROM approvals WHERE id = ?1", APPROVAL_COLUMNS),
+                [id],
+                Self::row_to_approval,
+            )
+            .optional()?)
+    }
+
+    /// Newest first, optionally only those in `status` and asked for by `owner`
+    pub async fn list_approvals(
+        &self,
+        status: Option<ApprovalStatus>,
+        owner: Option<i64>,
+        limit: i64,
+    ) -> DbResult<Vec<Approval>> {
+        let conn = self.conn().await?;
+        let mut stmt = conn.prepare(&format!(
+            "SELECT {} FROM approvals WHERE (?1 IS NULL OR status = ?1) AND (?2 IS NULL OR user_id = ?2)
+             ORDER BY id DESC LIMIT ?3",
+            APPROVAL_COLUMNS
+        ))?;
+        let approvals = stmt
+            .query_map(rusqlite::params![status.map(|s| s.as_str()), owner, limit], Self::row_to_approval)?
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(approvals)
+    }
+
+    /// Move a pending approval to `status`. Returns false if it was already decided.
+    pub async fn decide_approval(&self, id: i64, status: ApprovalStatus) -> DbResult<bool> {
+        let conn = self.conn().await?;
+        let updated = conn.execute(
+            "UPDATE approvals SET status = ?1, decided_at = ?2 WHERE id = ?3 AND status = 'pending'",
+            rusqlite::params![status.as_str(), Utc::now().to_rfc3339(), id],
+        )?;
+        Ok(updated > 0)
+    }
+
+    fn row_to_approval(row: &rusqlite::Row) -> rusqlite::Result<Approval> {
+        let status: String = row.get(1)?;
+        Ok(Approval {
+            id: row.get(0)?,
+            status: ApprovalStatus::from_str(&status).unwrap_or(ApprovalStatus::Pending),
+            tool_name: row.get(2)?,
+            tool_call_id: row.get(3)?,
+            channel_id: row.get(4)?,
+            session_id: row.get(5)?,
+            description: row.get(6)?,
+            amount_usd: row.get(7)?,
+            reason: row.get(8)?,
+            user_id: row.get(9)?,
+            created_at: row.get(10)?,
+            decided_at: row.get(11)?,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::db::Database;
+    use crate::models::{ApprovalStatus, NewApproval};
+
+    #[tokio::test]
+    async fn test_approvals() {
+        let db = Database::new(":memory:").unwrap();
+        let request = NewApproval {
+            tool_name: "swap".to_string(),
+            tool_call_id: Some("call_1".to_string()),
+            channel_id: Some(1),
+            session_id: Some(7),
+            description: "Swap 500 USDC for WETH on base".to_string(),
+            amount_usd: Some(500.0),
+            reason: "$500.00 is above the $100.00 approval threshold".to_string(),
+            user_id: Some(3),
+        };
+        let first = db.create_approval(&request).await.unwrap();
+        let second = db.create_approval(&request).await.unwrap();
+        assert_eq!(first.status, ApprovalStatus::Pending);
+        assert!(first.decided_at.is_none());
+
+        assert!(db.decide_approval(first.id, ApprovalStatus::Approved).await.unwrap());
+        // Decisions are final
+        assert!(!db.decide_approval(first.id, ApprovalStatus::Rejected).await.unwrap());
+        let decided = db.get_approval(first.id).await.unwrap().unwrap();
+        assert_eq!(decided.status, ApprovalStatus::Approved);
+        assert!(decided.decided_at.is_some());
+
+        let pending = db.list_approvals(Some(ApprovalStatus::Pending), None, 50).await.unwrap();
+        assert_eq!(pending.len(), 1);
+        assert_eq!(pending[0].id, second.id);
+        assert_eq!(db.list_approvals(None, None, 50).await.unwrap().len(), 2);
+        assert_eq!(db.list_approvals(None, Some(3), 50).await.unwrap().len(), 2);
+        assert!(db.list_approvals(None, Some(4), 50).await.unwrap().is_empty());
+        assert!(db.get_approval(999).await.unwrap().is_none());
+    }
+}
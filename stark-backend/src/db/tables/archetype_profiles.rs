@@ -0,0 +1,91 @@
+//! Overrides of model archetype profiles
+//!
+//! Only the fields an operator changed are stored; `ai::archetypes::profile`
+//! merges them over the archetype's defaults.
+
+use chrono::Utc;
+
+use crate::ai::archetypes::{ArchetypeId, ArchetypeProfileOverrides};
+use crate::db::DbResult;
+use super::super::Database;
+
+impl Database {
+    /// Every stored override. Rows that no longer parse are skipped.
+    pub async fn list_archetype_profile_overrides(
+        &self,
+    ) -> DbResult<Vec<(ArchetypeId, ArchetypeProfileOverrides)>> {
+        let conn = self.conn().await?;
+        let mut stmt = conn.prepare("SELECT archetype, overrides FROM archetype_profiles ORDER BY archetype")?;
+        let rows = stmt
+            .query_map([], |row| Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?)))?
+            .collect::<Result<Vec<_>, _>>()?;
+
+        Ok(rows
+            .into_iter()
+            .filter_map(|(archetype, overrides)| {
+                let parsed = ArchetypeId::from_str(&archetype)
+                    .zip(serde_json::from_str::<ArchetypeProfileOverrides>(&overrides).ok());
+                if parsed.is_none() {
+                    log::warn!("[ARCHETYPES] Ignoring unreadable profile override for '{}'", archetype);
+                }
+                parsed
+            })
+            .collect())
+    }
+
+    /// Store an archetype's overrides, replacing earlier ones
+    pub async fn set_archetype_profile_overrides(
+        &self,
+        archetype: ArchetypeId,
+        overrides: &ArchetypeProfileOverrides,
+    ) -> DbResult<()> {
+        let overrides = serde_json::to_string(overrides).unwrap_or_else(|_| "{}".to_string());
+        let conn = self.conn().await?;
+        conn.execute(
+            "INSERT INTO archetype_profiles (archetype, overrides, updated_at) VALUES (?1, ?2, ?3)
+             ON CONFLICT(archetype) DO UPDATE SET overrides = excluded.overrides, updated_at = excluded.updated_at",
+            rusqlite::params![archetype.as_str(), overrides, Utc::now().to_rfc3339()],
+        )?;
+        Ok(())
+    }
+
+    /// Drop an archetype's overrides. Returns whether there were any.
+    pub async fn delete_archetype_profile_overrides(&self, archetype: ArchetypeId) -> DbResult<bool> {
+        let conn = self.conn().await?;
+        let deleted = conn.execute(
+            "DELETE FROM archetype_profiles WHERE archetype = ?1",
+            [archetype.as_str()],
+        )?;
+        Ok(deleted > 0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ai::archetypes::ToolChoiceMode;
+
+    #[tokio::test]
+    async fn test_archetype_profile_overrides_round_trip() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let db = Database::new(dir.path().join("stark.db").to_str().unwrap()).unwrap();
+        assert!(db.list_archetype_profile_overrides().await.unwrap().is_empty());
+
+        let overrides = ArchetypeProfileOverrides {
+            max_tokens: Some(4096),
+            tool_choice: Some(ToolChoiceMode::Omit),
+            ..Default::default()
+        };
+        db.set_archetype_profile_overrides(ArchetypeId::DeepSeek, &overrides).await.unwrap();
+        let replaced = ArchetypeProfileOverrides { max_tokens: Some(2048), ..overrides.clone() };
+        db.set_archetype_profile_overrides(ArchetypeId::DeepSeek, &replaced).await.unwrap();
+        assert_eq!(
+            db.list_archetype_profile_overrides().await.unwrap(),
+            vec![(ArchetypeId::DeepSeek, replaced)]
+        );
+
+        assert!(db.delete_archetype_profile_overrides(ArchetypeId::DeepSeek).await.unwrap());
+        assert!(!db.delete_archetype_profile_overrides(ArchetypeId::DeepSeek).await.unwrap());
+        assert!(db.list_archetype_profile_overrides().await.unwrap().is_empty());
+    }
+}
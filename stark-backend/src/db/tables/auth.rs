@@ -1,31 +1,29 @@
 //! Auth sessions and auth challenges database operations
 
+use async_trait::async_trait;
 use chrono::{DateTime, Duration, Utc};
-use rusqlite::Result as SqliteResult;
 
-use crate::models::Session;
-use super::super::Database;
+use crate::models::{Session, SessionPolicy, User};
+use super::super::backend::{generate_session_token, AuthStore, DbResult, SqliteBackend};
 
-impl Database {
+#[async_trait]
+impl AuthStore for SqliteBackend {
     // ============================================
     // Auth Session methods (for web login sessions)
     // ============================================
 
-    pub fn create_session(&self) -> SqliteResult<Session> {
-        self.create_session_for_address(None)
-    }
-
-    pub fn create_session_for_address(&self, public_address: Option<&str>) -> SqliteResult<Session> {
-        let conn = self.conn.lock().unwrap();
-        let token = Self::generate_session_token();
+    async fn create_session_for_user(&self, user: &User, ttl: Duration) -> DbResult<Session> {
+        let conn = self.conn().await?;
+        let token = generate_session_token();
         let created_at = Utc::now();
-        let expires_at = created_at + Duration::hours(24);
+        let expires_at = created_at + ttl;
 
         conn.execute(
-            "INSERT INTO auth_sessions (token, public_address, created_at, expires_at) VALUES (?1, ?2, ?3, ?4)",
+            "INSERT INTO auth_sessions (token, public_address, user_id, created_at, expires_at) VALUES (?1, ?2, ?3, ?4, ?5)",
             rusqlite::params![
                 &token,
-                public_address,
+                &user.public_address,
+                user.id,
                 &created_at.to_rfc3339(),
                 &expires_at.to_rfc3339(),
             ],
@@ -36,29 +34,22 @@ impl Database {
         Ok(Session {
             id,
             token,
+            user_id: Some(user.id),
             created_at,
             expires_at,
         })
     }
 
-    fn generate_session_token() -> String {
-        use rand::Rng;
-        let mut rng = rand::thread_rng();
-        (0..32)
-            .map(|_| format!("{:x}", rng.r#gen::<u8>() % 16))
-            .collect()
-    }
-
-    pub fn validate_session(&self, token: &str) -> SqliteResult<Option<Session>> {
-        let conn = self.conn.lock().unwrap();
+    async fn validate_session(&self, token: &str, policy: &SessionPolicy) -> DbResult<Option<Session>> {
+        let conn = self.conn().await?;
         let now = Utc::now();
         let now_str = now.to_rfc3339();
 
         let mut stmt = conn.prepare(
-            "SELECT id, token, created_at, expires_at FROM auth_sessions WHERE token = ?1 AND expires_at > ?2",
+            "SELECT id, token, created_at, expires_at, user_id FROM auth_sessions WHERE token = ?1 AND expires_at > ?2",
         )?;
 
-        let session = stmt
+        let mut session = stmt
             .query_row([token, &now_str], |row| {
                 let created_at_str: String = row.get(2)?;
                 let expires_at_str: String = row.get(3)?;
@@ -66,6 +57,7 @@ impl Database {
                 Ok(Session {
                     id: row.get(0)?,
                     token: row.get(1)?,
+                    user_id: row.get(4)?,
                     created_at: DateTime::parse_from_rfc3339(&created_at_str)
                         .unwrap()
                         .with_timezone(&Utc),
@@ -76,20 +68,62 @@ impl Database {
             })
             .ok();
 
-        // Extend session expiry on successful validation (keep active sessions alive)
-        if session.is_some() {
-            let new_expires = (now + Duration::hours(24)).to_rfc3339();
+        // Sliding expiry: every use keeps the session alive for another TTL
+        if let Some(session) = session.as_mut().filter(|_| policy.sliding) {
+            let new_expires = now + policy.ttl;
             let _ = conn.execute(
                 "UPDATE auth_sessions SET expires_at = ?1 WHERE token = ?2",
-                [&new_expires, token],
+                [&new_expires.to_rfc3339(), token],
             );
+            session.expires_at = new_expires;
         }
 
         Ok(session)
     }
 
-    pub fn delete_session(&self, token: &str) -> SqliteResult<bool> {
-        let conn = self.conn.lock().unwrap();
+    async fn refresh_session(&self, token: &str, ttl: Duration) -> DbResult<Option<Session>> {
+        let conn = self.conn().await?;
+        let now = Utc::now();
+        let expires_at = now + ttl;
+
+        let rows = conn.execute(
+            "UPDATE auth_sessions SET expires_at = ?1 WHERE token = ?2 AND expires_at > ?3",
+            [&expires_at.to_rfc3339(), token, &now.to_rfc3339()],
+        )?;
+        if rows == 0 {
+            return Ok(None);
+        }
+
+        let session = conn.query_row(
+            "SELECT id, token, created_at, expires_at, user_id FROM auth_sessions WHERE token = ?1",
+            [token],
+            |row| {
+                let created_at_str: String = row.get(2)?;
+                Ok(Session {
+                    id: row.get(0)?,
+                    token: row.get(1)?,
+                    user_id: row.get(4)?,
+                    created_at: DateTime::parse_from_rfc3339(&created_at_str)
+                        .unwrap()
+                        .with_timezone(&Utc),
+                    expires_at,
+                })
+            },
+        )?;
+        Ok(Some(session))
+    }
+
+    async fn delete_expired_sessions(&self) -> DbResult<usize> {
+        let conn = self.conn().await?;
+        let rows = conn.execute(
+            "DELETE FROM auth_sessions WHERE expires_at <= ?1",
+            [&Utc::now().to_rfc3339()],
+        )?;
+        Ok(rows)
+    }
+
+    async fn delete_session(&self, token: &str) -> DbResult<bool> {
+        let conn = self.conn().await?;
         let rows_affected = conn.execute("DELETE FROM auth_sessions WHERE token = ?1", [token])?;
         Ok(rows_affected > 0)
     }
@@ -98,8 +132,8 @@ impl Database {
     // Auth Challenge methods (for SIWE)
     // ============================================
 
-    pub fn create_or_update_challenge(&self, public_address: &str, challenge: &str) -> SqliteResult<()> {
-        let conn = self.conn.lock().unwrap();
+    async fn create_or_update_challenge(&self, public_address: &str, challenge: &str) -> DbResult<()> {
+        let conn = self.conn().await?;
         let now = Utc::now().to_rfc3339();
 
         // Upsert: insert or replace existing challenge for this address
@@ -113,8 +147,8 @@ impl Database {
         Ok(())
     }
 
-    pub fn get_challenge(&self, public_address: &str) -> SqliteResult<Option<String>> {
-        let conn = self.conn.lock().unwrap();
+    async fn get_challenge(&self, public_address: &str) -> DbResult<Option<String>> {
+        let conn = self.conn().await?;
 
         let mut stmt = conn.prepare(
             "SELECT challenge FROM auth_challenges WHERE public_address = ?1",
@@ -127,8 +161,8 @@ impl Database {
         Ok(challenge)
     }
 
-    pub fn validate_challenge(&self, public_address: &str, challenge: &str) -> SqliteResult<bool> {
-        let conn = self.conn.lock().unwrap();
+    async fn validate_challenge(&self, public_address: &str, challenge: &str) -> DbResult<bool> {
+        let conn = self.conn().await?;
 
         let count: i64 = conn.query_row(
             "SELECT COUNT(*) FROM auth_challenges WHERE public_address = ?1 AND challenge = ?2",
@@ -139,8 +173,8 @@ impl Database {
         Ok(count > 0)
     }
 
-    pub fn delete_challenge(&self, public_address: &str) -> SqliteResult<bool> {
-        let conn = self.conn.lock().unwrap();
+    async fn delete_challenge(&self, public_address: &str) -> DbResult<bool> {
+        let conn = self.conn().await?;
         let rows_affected = conn.execute(
             "DELETE FROM auth_challenges WHERE public_address = ?1",
             [public_address],
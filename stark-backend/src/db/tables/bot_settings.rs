@@ -9,11 +9,13 @@ use super::super::Database;
 
 impl Database {
     /// Get bot settings (there's only one row)
-    pub fn get_bot_settings(&self) -> SqliteResult<BotSettings> {
-        let conn = self.conn.lock().unwrap();
+    pub async fn get_bot_settings(&self) -> SqliteResult<BotSettings> {
+        let conn = self.conn().await?;
 
         let result = conn.query_row(
-            "SELECT id, bot_name, bot_email, web3_tx_requires_confirmation, rpc_provider, custom_rpc_endpoints, max_tool_iterations, created_at, updated_at FROM bot_settings LIMIT 1",
+            "SELECT id, bot_name, bot_email, web3_tx_requires_confirmation, rpc_provider, custom_rpc_endpoints, max_tool_iterations, created_at, updated_at,
+                    rate_limit_per_minute, rate_limit_burst
+             FROM bot_settings LIMIT 1",
             [],
             |row| {
                 let web3_tx_confirmation: i64 = row.get(3)?;
@@ -34,6 +36,8 @@ impl Database {
                     rpc_provider,
                     custom_rpc_endpoints,
                     max_tool_iterations,
+                    rate_limit_per_minute: row.get(9)?,
+                    rate_limit_burst: row.get(10)?,
                     created_at: DateTime::parse_from_rfc3339(&created_at_str)
                         .unwrap()
                         .with_timezone(&Utc),
@@ -51,17 +55,17 @@ impl Database {
     }
 
     /// Update bot settings
-    pub fn update_bot_settings(
+    pub async fn update_bot_settings(
         &self,
         bot_name: Option<&str>,
         bot_email: Option<&str>,
         web3_tx_requires_confirmation: Option<bool>,
     ) -> SqliteResult<BotSettings> {
-        self.update_bot_settings_full(bot_name, bot_email, web3_tx_requires_confirmation, None, None, None)
+        self.update_bot_settings_full(bot_name, bot_email, web3_tx_requires_confirmation, None, None, None).await
     }
 
     /// Update bot settings with all fields including RPC config
-    pub fn update_bot_settings_full(
+    pub async fn update_bot_settings_full(
         &self,
         bot_name: Option<&str>,
         bot_email: Option<&str>,
@@ -70,7 +74,7 @@ impl Database {
         custom_rpc_endpoints: Option<&HashMap<String, String>>,
         max_tool_iterations: Option<i32>,
     ) -> SqliteResult<BotSettings> {
-        let conn = self.conn.lock().unwrap();
+        let conn = self.conn().await?;
         let now = Utc::now().to_rfc3339();
 
         // Check if settings exist
@@ -136,6 +140,33 @@ impl Database {
         }
 
         drop(conn);
-        self.get_bot_settings()
+        self.get_bot_settings().await
+    }
+    /// Update the chat/agent rate limits, creating the settings row if needed
+    pub async fn update_rate_limits(
+        &self,
+        per_minute: Option<i32>,
+        burst: Option<i32>,
+    ) -> SqliteResult<BotSettings> {
+        // Make sure the row exists so the UPDATEs below have something to change
+        self.update_bot_settings_full(None, None, None, None, None, None).await?;
+
+        let conn = self.conn().await?;
+        let now = Utc::now().to_rfc3339();
+        if let Some(per_minute) = per_minute {
+            conn.execute(
+                "UPDATE bot_settings SET rate_limit_per_minute = ?1, updated_at = ?2",
+                rusqlite::params![per_minute, &now],
+            )?;
+        }
+        if let Some(burst) = burst {
+            conn.execute(
+                "UPDATE bot_settings SET rate_limit_burst = ?1, updated_at = ?2",
+                rusqlite::params![burst, &now],
+            )?;
+        }
+
+        drop(conn);
+        self.get_bot_settings().await
     }
 }
@@ -8,14 +8,14 @@ use super::super::Database;
 
 impl Database {
     /// Create a new external channel
-    pub fn create_channel(
+    pub async fn create_channel(
         &self,
         channel_type: &str,
         name: &str,
         bot_token: &str,
         app_token: Option<&str>,
     ) -> SqliteResult<Channel> {
-        let conn = self.conn.lock().unwrap();
+        let conn = self.conn().await?;
         let now = Utc::now().to_rfc3339();
 
         conn.execute(
@@ -39,8 +39,8 @@ impl Database {
     }
 
     /// Get a channel by ID
-    pub fn get_channel(&self, id: i64) -> SqliteResult<Option<Channel>> {
-        let conn = self.conn.lock().unwrap();
+    pub async fn get_channel(&self, id: i64) -> SqliteResult<Option<Channel>> {
+        let conn = self.conn().await?;
 
         let mut stmt = conn.prepare(
             "SELECT id, channel_type, name, enabled, bot_token, app_token, created_at, updated_at
@@ -55,8 +55,8 @@ impl Database {
     }
 
     /// List all channels
-    pub fn list_channels(&self) -> SqliteResult<Vec<Channel>> {
-        let conn = self.conn.lock().unwrap();
+    pub async fn list_channels(&self) -> SqliteResult<Vec<Channel>> {
+        let conn = self.conn().await?;
 
         let mut stmt = conn.prepare(
             "SELECT id, channel_type, name, enabled, bot_token, app_token, created_at, updated_at
@@ -72,8 +72,8 @@ impl Database {
     }
 
     /// List only enabled channels
-    pub fn list_enabled_channels(&self) -> SqliteResult<Vec<Channel>> {
-        let conn = self.conn.lock().unwrap();
+    pub async fn list_enabled_channels(&self) -> SqliteResult<Vec<Channel>> {
+        let conn = self.conn().await?;
 
         let mut stmt = conn.prepare(
             "SELECT id, channel_type, name, enabled, bot_token, app_token, created_at, updated_at
@@ -89,7 +89,7 @@ impl Database {
     }
 
     /// Update a channel
-    pub fn update_channel(
+    pub async fn update_channel(
         &self,
         id: i64,
         name: Option<&str>,
@@ -97,7 +97,7 @@ impl Database {
         bot_token: Option<&str>,
         app_token: Option<Option<&str>>,
     ) -> SqliteResult<Option<Channel>> {
-        let conn = self.conn.lock().unwrap();
+        let conn = self.conn().await?;
         let now = Utc::now().to_rfc3339();
 
         // Build dynamic update query
@@ -148,12 +148,12 @@ impl Database {
         conn.execute(&sql, params_ref.as_slice())?;
 
         drop(conn);
-        self.get_channel(id)
+        self.get_channel(id).await
     }
 
     /// Enable or disable a channel
-    pub fn set_channel_enabled(&self, id: i64, enabled: bool) -> SqliteResult<bool> {
-        let conn = self.conn.lock().unwrap();
+    pub async fn set_channel_enabled(&self, id: i64, enabled: bool) -> SqliteResult<bool> {
+        let conn = self.conn().await?;
         let now = Utc::now().to_rfc3339();
 
         let rows_affected = conn.execute(
@@ -165,8 +165,8 @@ impl Database {
     }
 
     /// Delete a channel
-    pub fn delete_channel(&self, id: i64) -> SqliteResult<bool> {
-        let conn = self.conn.lock().unwrap();
+    pub async fn delete_channel(&self, id: i64) -> SqliteResult<bool> {
+        let conn = self.conn().await?;
         let rows_affected = conn.execute(
             "DELETE FROM external_channels WHERE id = ?1",
             [id],
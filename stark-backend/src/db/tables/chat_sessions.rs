@@ -3,7 +3,8 @@
 use chrono::{DateTime, Timelike, Utc};
 use rusqlite::Result as SqliteResult;
 
-use crate::models::{ChatSession, CompletionStatus, MessageRole, ResetPolicy, SessionMessage, SessionScope};
+use crate::models::{ChatSession, CompletionStatus, MessageRole, ResetPolicy, SessionMessage, SessionScope, ToolPolicy};
+use crate::redaction::redact;
 use super::super::Database;
 
 impl Database {
@@ -16,21 +17,23 @@ impl Database {
         format!("{}:{}:{}", channel_type, channel_id, platform_chat_id)
     }
 
-    /// Get or create a chat session, handling reset policy
-    pub fn get_or_create_chat_session(
+    /// Get or create a chat session, handling reset policy. `user_id` owns
+    /// a newly created session; an existing one keeps its owner.
+    pub async fn get_or_create_chat_session(
         &self,
         channel_type: &str,
         channel_id: i64,
         platform_chat_id: &str,
         scope: SessionScope,
         agent_id: Option<&str>,
+        user_id: Option<i64>,
     ) -> SqliteResult<ChatSession> {
         let session_key = Self::generate_session_key(channel_type, channel_id, platform_chat_id);
         let now = Utc::now();
         let now_str = now.to_rfc3339();
 
         // Try to get existing active session
-        if let Some(mut session) = self.get_chat_session_by_key(&session_key)? {
+        if let Some(mut session) = self.get_chat_session_by_key(&session_key).await? {
             // Check if session needs reset based on policy
             let should_reset = match session.reset_policy {
                 ResetPolicy::Daily => {
@@ -59,11 +62,11 @@ impl Database {
 
             if should_reset {
                 // Reset the session
-                self.reset_chat_session(session.id)?;
-                session = self.get_chat_session(session.id)?.unwrap();
+                self.reset_chat_session(session.id).await?;
+                session = self.get_chat_session(session.id).await?.unwrap();
             } else {
                 // Update last activity
-                let conn = self.conn.lock().unwrap();
+                let conn = self.conn().await?;
                 conn.execute(
                     "UPDATE chat_sessions SET last_activity_at = ?1, updated_at = ?1 WHERE id = ?2",
                     rusqlite::params![&now_str, session.id],
@@ -75,8 +78,14 @@ impl Database {
             return Ok(session);
         }
 
+        if let Some(user_id) = user_id
+            && let Err(e) = self.mirror_user(user_id).await
+        {
+            log::warn!("Failed to copy user {} into the local database: {}", user_id, e);
+        }
+
         // No active session found - check if there's an inactive one we can reactivate
-        let conn = self.conn.lock().unwrap();
+        let conn = self.conn().await?;
         let inactive_session_id: Option<i64> = conn.query_row(
             "SELECT id FROM chat_sessions WHERE session_key = ?1 AND is_active = 0 ORDER BY updated_at DESC LIMIT 1",
             [&session_key],
@@ -90,14 +99,14 @@ impl Database {
                 rusqlite::params![&now_str, inactive_id],
             )?;
             drop(conn);
-            return self.get_chat_session(inactive_id).map(|opt| opt.unwrap());
+            return self.get_chat_session(inactive_id).await.map(|opt| opt.unwrap());
         }
 
         // Create new session
         conn.execute(
             "INSERT INTO chat_sessions (session_key, agent_id, scope, channel_type, channel_id, platform_chat_id,
-             is_active, reset_policy, idle_timeout_minutes, daily_reset_hour, created_at, updated_at, last_activity_at)
-             VALUES (?1, ?2, ?3, ?4, ?5, ?6, 1, ?7, ?8, ?9, ?10, ?10, ?10)",
+             is_active, reset_policy, idle_timeout_minutes, daily_reset_hour, user_id, created_at, updated_at, last_activity_at)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, 1, ?7, ?8, ?9, ?10, ?11, ?11, ?11)",
             rusqlite::params![
                 &session_key,
                 agent_id,
@@ -108,6 +117,7 @@ impl Database {
                 ResetPolicy::default().as_str(),
                 Option::<i32>::None,
                 Some(0i32),
+                user_id,
                 &now_str,
             ],
         )?;
@@ -115,17 +125,18 @@ impl Database {
         let id = conn.last_insert_rowid();
         drop(conn);
 
-        self.get_chat_session(id).map(|opt| opt.unwrap())
+        self.get_chat_session(id).await.map(|opt| opt.unwrap())
     }
 
     /// Get a chat session by ID
-    pub fn get_chat_session(&self, id: i64) -> SqliteResult<Option<ChatSession>> {
-        let conn = self.conn.lock().unwrap();
+    pub async fn get_chat_session(&self, id: i64) -> SqliteResult<Option<ChatSession>> {
+        let conn = self.conn().await?;
 
         let mut stmt = conn.prepare(
             "SELECT id, session_key, agent_id, scope, channel_type, channel_id, platform_chat_id,
              is_active, reset_policy, idle_timeout_minutes, daily_reset_hour,
-             created_at, updated_at, last_activity_at, expires_at, context_tokens, max_context_tokens, compaction_id, completion_status
+             created_at, updated_at, last_activity_at, expires_at, context_tokens, max_context_tokens, compaction_id, completion_status,
+             persona, tool_policy, user_id
              FROM chat_sessions WHERE id = ?1",
         )?;
 
@@ -137,13 +148,14 @@ impl Database {
     }
 
     /// List all chat sessions
-    pub fn list_chat_sessions(&self) -> SqliteResult<Vec<ChatSession>> {
-        let conn = self.conn.lock().unwrap();
+    pub async fn list_chat_sessions(&self) -> SqliteResult<Vec<ChatSession>> {
+        let conn = self.conn().await?;
 
         let mut stmt = conn.prepare(
             "SELECT id, session_key, agent_id, scope, channel_type, channel_id, platform_chat_id,
              is_active, reset_policy, idle_timeout_minutes, daily_reset_hour,
-             created_at, updated_at, last_activity_at, expires_at, context_tokens, max_context_tokens, compaction_id, completion_status
+             created_at, updated_at, last_activity_at, expires_at, context_tokens, max_context_tokens, compaction_id, completion_status,
+             persona, tool_policy, user_id
              FROM chat_sessions ORDER BY last_activity_at DESC LIMIT 100",
         )?;
 
@@ -155,14 +167,35 @@ impl Database {
         Ok(sessions)
     }
 
+    /// List the chat sessions owned by `user_id`
+    pub async fn list_user_chat_sessions(&self, user_id: i64) -> SqliteResult<Vec<ChatSession>> {
+        let conn = self.conn().await?;
+
+        let mut stmt = conn.prepare(
+            "SELECT id, session_key, agent_id, scope, channel_type, channel_id, platform_chat_id,
+             is_active, reset_policy, idle_timeout_minutes, daily_reset_hour,
+             created_at, updated_at, last_activity_at, expires_at, context_tokens, max_context_tokens, compaction_id, completion_status,
+             persona, tool_policy, user_id
+             FROM chat_sessions WHERE user_id = ?1 ORDER BY last_activity_at DESC LIMIT 100",
+        )?;
+
+        let sessions = stmt
+            .query_map([user_id], Self::row_to_chat_session)?
+            .filter_map(|r| r.ok())
+            .collect();
+
+        Ok(sessions)
+    }
+
     /// Get a chat session by session key
-    pub fn get_chat_session_by_key(&self, session_key: &str) -> SqliteResult<Option<ChatSession>> {
-        let conn = self.conn.lock().unwrap();
+    pub async fn get_chat_session_by_key(&self, session_key: &str) -> SqliteResult<Option<ChatSession>> {
+        let conn = self.conn().await?;
 
         let mut stmt = conn.prepare(
             "SELECT id, session_key, agent_id, scope, channel_type, channel_id, platform_chat_id,
              is_active, reset_policy, idle_timeout_minutes, daily_reset_hour,
-             created_at, updated_at, last_activity_at, expires_at, context_tokens, max_context_tokens, compaction_id, completion_status
+             created_at, updated_at, last_activity_at, expires_at, context_tokens, max_context_tokens, compaction_id, completion_status,
+             persona, tool_policy, user_id
              FROM chat_sessions WHERE session_key = ?1 AND is_active = 1",
         )?;
 
@@ -173,23 +206,35 @@ impl Database {
         Ok(session)
     }
 
+    /// Count active sessions, and those of them with activity since `since` (RFC 3339)
+    pub async fn count_active_chat_sessions(&self, since: &str) -> SqliteResult<(i64, i64)> {
+        let conn = self.conn().await?;
+        conn.query_row(
+            "SELECT COUNT(*), COALESCE(SUM(last_activity_at >= ?1), 0) FROM chat_sessions WHERE is_active = 1",
+            [since],
+            |row| Ok((row.get(0)?, row.get(1)?)),
+        )
+    }
+
     /// Reset a chat session (mark old as inactive, create new)
-    pub fn reset_chat_session(&self, id: i64) -> SqliteResult<ChatSession> {
-        let conn = self.conn.lock().unwrap();
+    pub async fn reset_chat_session(&self, id: i64) -> SqliteResult<ChatSession> {
+        let conn = self.conn().await?;
         let now = Utc::now();
         let now_str = now.to_rfc3339();
 
         // Get the old session info
-        let old_session: Option<(String, Option<String>, String, String, i64, String, String, Option<i32>, Option<i32>)> = conn
+        #[allow(clippy::type_complexity)]
+        let old_session: Option<(String, Option<String>, String, String, i64, String, String, Option<i32>, Option<i32>, Option<String>, Option<String>, Option<i64>)> = conn
             .query_row(
-                "SELECT session_key, agent_id, scope, channel_type, channel_id, platform_chat_id, reset_policy, idle_timeout_minutes, daily_reset_hour
+                "SELECT session_key, agent_id, scope, channel_type, channel_id, platform_chat_id, reset_policy, idle_timeout_minutes, daily_reset_hour,
+                        persona, tool_policy, user_id
                  FROM chat_sessions WHERE id = ?1",
                 [id],
-                |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?, row.get(3)?, row.get(4)?, row.get(5)?, row.get(6)?, row.get(7)?, row.get(8)?)),
+                |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?, row.get(3)?, row.get(4)?, row.get(5)?, row.get(6)?, row.get(7)?, row.get(8)?, row.get(9)?, row.get(10)?, row.get(11)?)),
             )
             .ok();
 
-        let Some((_old_session_key, agent_id, scope, channel_type, channel_id, _platform_chat_id, reset_policy, idle_timeout, daily_hour)) = old_session else {
+        let Some((_old_session_key, agent_id, scope, channel_type, channel_id, _platform_chat_id, reset_policy, idle_timeout, daily_hour, persona, tool_policy, user_id)) = old_session else {
             return Err(rusqlite::Error::QueryReturnedNoRows);
         };
 
@@ -205,6 +250,12 @@ impl Database {
             rusqlite::params![id],
         )?;
 
+        // The new session starts without the old one's registers
+        conn.execute(
+            "DELETE FROM registers WHERE session_id = ?1",
+            rusqlite::params![id],
+        )?;
+
         // Generate new unique session key with timestamp
         let timestamp = now.timestamp_millis();
         let new_platform_chat_id = format!("reset-{}", timestamp);
@@ -213,8 +264,8 @@ impl Database {
         // Create new session with same settings but new unique key
         conn.execute(
             "INSERT INTO chat_sessions (session_key, agent_id, scope, channel_type, channel_id, platform_chat_id,
-             is_active, reset_policy, idle_timeout_minutes, daily_reset_hour, created_at, updated_at, last_activity_at)
-             VALUES (?1, ?2, ?3, ?4, ?5, ?6, 1, ?7, ?8, ?9, ?10, ?10, ?10)",
+             is_active, reset_policy, idle_timeout_minutes, daily_reset_hour, persona, tool_policy, user_id, created_at, updated_at, last_activity_at)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, 1, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?13, ?13)",
             rusqlite::params![
                 &new_session_key,
                 agent_id,
@@ -225,6 +276,9 @@ impl Database {
                 &reset_policy,
                 idle_timeout,
                 daily_hour,
+                persona,
+                tool_policy,
+                user_id,
                 &now_str,
             ],
         )?;
@@ -232,12 +286,12 @@ impl Database {
         let new_id = conn.last_insert_rowid();
         drop(conn);
 
-        self.get_chat_session(new_id).map(|opt| opt.unwrap())
+        self.get_chat_session(new_id).await.map(|opt| opt.unwrap())
     }
 
     /// Delete a chat session and all its messages
-    pub fn delete_chat_session(&self, id: i64) -> SqliteResult<bool> {
-        let conn = self.conn.lock().unwrap();
+    pub async fn delete_chat_session(&self, id: i64) -> SqliteResult<bool> {
+        let conn = self.conn().await?;
 
         // Delete agent context for the session
         conn.execute(
@@ -245,6 +299,11 @@ impl Database {
             rusqlite::params![id],
         )?;
 
+        conn.execute(
+            "DELETE FROM registers WHERE session_id = ?1",
+            rusqlite::params![id],
+        )?;
+
         // Delete the session (messages are cascade deleted via FK constraint)
         let deleted = conn.execute(
             "DELETE FROM chat_sessions WHERE id = ?1",
@@ -255,14 +314,14 @@ impl Database {
     }
 
     /// Update session reset policy
-    pub fn update_session_reset_policy(
+    pub async fn update_session_reset_policy(
         &self,
         id: i64,
         reset_policy: ResetPolicy,
         idle_timeout_minutes: Option<i32>,
         daily_reset_hour: Option<i32>,
     ) -> SqliteResult<Option<ChatSession>> {
-        let conn = self.conn.lock().unwrap();
+        let conn = self.conn().await?;
         let now = Utc::now().to_rfc3339();
 
         conn.execute(
@@ -271,7 +330,36 @@ impl Database {
         )?;
 
         drop(conn);
-        self.get_chat_session(id)
+        self.get_chat_session(id).await
+    }
+
+    /// Pick the persona a session's conversation runs under (`None`: the default)
+    pub async fn set_session_persona(&self, id: i64, persona: Option<&str>) -> SqliteResult<Option<ChatSession>> {
+        let conn = self.conn().await?;
+        let now = Utc::now().to_rfc3339();
+
+        conn.execute(
+            "UPDATE chat_sessions SET persona = ?1, updated_at = ?2 WHERE id = ?3",
+            rusqlite::params![persona, &now, id],
+        )?;
+
+        drop(conn);
+        self.get_chat_session(id).await
+    }
+
+    /// Set or clear the tool policy of a session's conversation
+    pub async fn set_session_tool_policy(&self, id: i64, tool_policy: Option<&ToolPolicy>) -> SqliteResult<Option<ChatSession>> {
+        let conn = self.conn().await?;
+        let now = Utc::now().to_rfc3339();
+        let tool_policy_json = tool_policy.map(|p| serde_json::to_string(p).unwrap_or_default());
+
+        conn.execute(
+            "UPDATE chat_sessions SET tool_policy = ?1, updated_at = ?2 WHERE id = ?3",
+            rusqlite::params![tool_policy_json, &now, id],
+        )?;
+
+        drop(conn);
+        self.get_chat_session(id).await
     }
 
     fn row_to_chat_session(row: &rusqlite::Row) -> rusqlite::Result<ChatSession> {
@@ -315,6 +403,11 @@ impl Database {
                 let status_str: String = row.get(18).unwrap_or_else(|_| "active".to_string());
                 CompletionStatus::from_str(&status_str).unwrap_or_default()
             },
+            persona: row.get(19)?,
+            tool_policy: row
+                .get::<_, Option<String>>(20)?
+                .and_then(|p| serde_json::from_str(&p).ok()),
+            user_id: row.get(21)?,
         })
     }
 
@@ -323,7 +416,7 @@ impl Database {
     // ============================================
 
     /// Add a message to a session
-    pub fn add_session_message(
+    pub async fn add_session_message(
         &self,
         session_id: i64,
         role: MessageRole,
@@ -333,9 +426,11 @@ impl Database {
         platform_message_id: Option<&str>,
         tokens_used: Option<i32>,
     ) -> SqliteResult<SessionMessage> {
-        let conn = self.conn.lock().unwrap();
+        let conn = self.conn().await?;
         let now = Utc::now();
         let now_str = now.to_rfc3339();
+        // Tool calls and results may echo keys the model was given
+        let content = &redact(content);
 
         conn.execute(
             "INSERT INTO session_messages (session_id, role, content, user_id, user_name, platform_message_id, tokens_used, created_at)
@@ -368,8 +463,8 @@ impl Database {
     }
 
     /// Get all messages for a session
-    pub fn get_session_messages(&self, session_id: i64) -> SqliteResult<Vec<SessionMessage>> {
-        let conn = self.conn.lock().unwrap();
+    pub async fn get_session_messages(&self, session_id: i64) -> SqliteResult<Vec<SessionMessage>> {
+        let conn = self.conn().await?;
 
         let mut stmt = conn.prepare(
             "SELECT id, session_id, role, content, user_id, user_name, platform_message_id, tokens_used, created_at
@@ -385,8 +480,8 @@ impl Database {
     }
 
     /// Get recent messages for a session (limited)
-    pub fn get_recent_session_messages(&self, session_id: i64, limit: i32) -> SqliteResult<Vec<SessionMessage>> {
-        let conn = self.conn.lock().unwrap();
+    pub async fn get_recent_session_messages(&self, session_id: i64, limit: i32) -> SqliteResult<Vec<SessionMessage>> {
+        let conn = self.conn().await?;
 
         let mut stmt = conn.prepare(
             "SELECT id, session_id, role, content, user_id, user_name, platform_message_id, tokens_used, created_at
@@ -404,8 +499,8 @@ impl Database {
     }
 
     /// Count messages in a session
-    pub fn count_session_messages(&self, session_id: i64) -> SqliteResult<i64> {
-        let conn = self.conn.lock().unwrap();
+    pub async fn count_session_messages(&self, session_id: i64) -> SqliteResult<i64> {
+        let conn = self.conn().await?;
         conn.query_row(
             "SELECT COUNT(*) FROM session_messages WHERE session_id = ?1",
             [session_id],
@@ -414,8 +509,8 @@ impl Database {
     }
 
     /// Get the first user message for a session (for showing initial query)
-    pub fn get_first_user_message(&self, session_id: i64) -> SqliteResult<Option<String>> {
-        let conn = self.conn.lock().unwrap();
+    pub async fn get_first_user_message(&self, session_id: i64) -> SqliteResult<Option<String>> {
+        let conn = self.conn().await?;
         conn.query_row(
             "SELECT content FROM session_messages
              WHERE session_id = ?1 AND role = 'user'
@@ -455,8 +550,8 @@ impl Database {
     // ============================================
 
     /// Update the context token count for a session
-    pub fn update_session_context_tokens(&self, session_id: i64, context_tokens: i32) -> SqliteResult<()> {
-        let conn = self.conn.lock().unwrap();
+    pub async fn update_session_context_tokens(&self, session_id: i64, context_tokens: i32) -> SqliteResult<()> {
+        let conn = self.conn().await?;
         let now = Utc::now().to_rfc3339();
         conn.execute(
             "UPDATE chat_sessions SET context_tokens = ?1, updated_at = ?2 WHERE id = ?3",
@@ -466,8 +561,8 @@ impl Database {
     }
 
     /// Set the compaction ID for a session (after compaction occurs)
-    pub fn set_session_compaction(&self, session_id: i64, compaction_id: i64) -> SqliteResult<()> {
-        let conn = self.conn.lock().unwrap();
+    pub async fn set_session_compaction(&self, session_id: i64, compaction_id: i64) -> SqliteResult<()> {
+        let conn = self.conn().await?;
         let now = Utc::now().to_rfc3339();
         conn.execute(
             "UPDATE chat_sessions SET compaction_id = ?1, updated_at = ?2 WHERE id = ?3",
@@ -477,8 +572,8 @@ impl Database {
     }
 
     /// Get oldest messages for compaction (excludes most recent messages)
-    pub fn get_messages_for_compaction(&self, session_id: i64, keep_recent: i32) -> SqliteResult<Vec<SessionMessage>> {
-        let conn = self.conn.lock().unwrap();
+    pub async fn get_messages_for_compaction(&self, session_id: i64, keep_recent: i32) -> SqliteResult<Vec<SessionMessage>> {
+        let conn = self.conn().await?;
 
         // Get total count first
         let total: i64 = conn.query_row(
@@ -523,8 +618,8 @@ impl Database {
     }
 
     /// Delete old messages after compaction (keeps the most recent messages)
-    pub fn delete_compacted_messages(&self, session_id: i64, keep_recent: i32) -> SqliteResult<i32> {
-        let conn = self.conn.lock().unwrap();
+    pub async fn delete_compacted_messages(&self, session_id: i64, keep_recent: i32) -> SqliteResult<i32> {
+        let conn = self.conn().await?;
 
         // Get IDs of messages to delete (all except the most recent)
         let deleted = conn.execute(
@@ -538,8 +633,8 @@ impl Database {
     }
 
     /// Get the compaction summary for a session (if any)
-    pub fn get_session_compaction_summary(&self, session_id: i64) -> SqliteResult<Option<String>> {
-        let conn = self.conn.lock().unwrap();
+    pub async fn get_session_compaction_summary(&self, session_id: i64) -> SqliteResult<Option<String>> {
+        let conn = self.conn().await?;
 
         // First get the compaction_id from the session
         let compaction_id: Option<i64> = conn.query_row(
@@ -563,8 +658,8 @@ impl Database {
     }
 
     /// Update the last_flush_at timestamp for a session (Phase 1: pre-compaction flush)
-    pub fn update_session_last_flush(&self, session_id: i64) -> SqliteResult<()> {
-        let conn = self.conn.lock().unwrap();
+    pub async fn update_session_last_flush(&self, session_id: i64) -> SqliteResult<()> {
+        let conn = self.conn().await?;
         let now = Utc::now().to_rfc3339();
         conn.execute(
             "UPDATE chat_sessions SET last_flush_at = ?1, updated_at = ?1 WHERE id = ?2",
@@ -574,8 +669,8 @@ impl Database {
     }
 
     /// Get the last flush timestamp for a session
-    pub fn get_session_last_flush(&self, session_id: i64) -> SqliteResult<Option<chrono::DateTime<Utc>>> {
-        let conn = self.conn.lock().unwrap();
+    pub async fn get_session_last_flush(&self, session_id: i64) -> SqliteResult<Option<chrono::DateTime<Utc>>> {
+        let conn = self.conn().await?;
         let flush_str: Option<String> = conn.query_row(
             "SELECT last_flush_at FROM chat_sessions WHERE id = ?1",
             [session_id],
@@ -592,8 +687,8 @@ impl Database {
     // ============================================
 
     /// Update the completion status of a session
-    pub fn update_session_completion_status(&self, session_id: i64, status: CompletionStatus) -> SqliteResult<()> {
-        let conn = self.conn.lock().unwrap();
+    pub async fn update_session_completion_status(&self, session_id: i64, status: CompletionStatus) -> SqliteResult<()> {
+        let conn = self.conn().await?;
         let now = Utc::now().to_rfc3339();
         conn.execute(
             "UPDATE chat_sessions SET completion_status = ?1, updated_at = ?2 WHERE id = ?3",
@@ -603,8 +698,8 @@ impl Database {
     }
 
     /// Get the completion status of a session
-    pub fn get_session_completion_status(&self, session_id: i64) -> SqliteResult<Option<CompletionStatus>> {
-        let conn = self.conn.lock().unwrap();
+    pub async fn get_session_completion_status(&self, session_id: i64) -> SqliteResult<Option<CompletionStatus>> {
+        let conn = self.conn().await?;
         conn.query_row(
             "SELECT completion_status FROM chat_sessions WHERE id = ?1",
             [session_id],
@@ -9,7 +9,7 @@ use super::super::Database;
 
 impl Database {
     /// Create a new cron job
-    pub fn create_cron_job(
+    pub async fn create_cron_job(
         &self,
         name: &str,
         description: Option<&str>,
@@ -27,7 +27,7 @@ impl Database {
         timeout_seconds: Option<i32>,
         delete_after_run: bool,
     ) -> SqliteResult<CronJob> {
-        let conn = self.conn.lock().unwrap();
+        let conn = self.conn().await?;
         let job_id = Uuid::new_v4().to_string();
         let now = Utc::now().to_rfc3339();
 
@@ -94,8 +94,8 @@ impl Database {
     }
 
     /// Get a cron job by ID
-    pub fn get_cron_job(&self, id: i64) -> SqliteResult<Option<CronJob>> {
-        let conn = self.conn.lock().unwrap();
+    pub async fn get_cron_job(&self, id: i64) -> SqliteResult<Option<CronJob>> {
+        let conn = self.conn().await?;
         match self.get_cron_job_by_id_internal(&conn, id) {
             Ok(job) => Ok(Some(job)),
             Err(rusqlite::Error::QueryReturnedNoRows) => Ok(None),
@@ -104,8 +104,8 @@ impl Database {
     }
 
     /// Get a cron job by job_id (UUID string)
-    pub fn get_cron_job_by_job_id(&self, job_id: &str) -> SqliteResult<Option<CronJob>> {
-        let conn = self.conn.lock().unwrap();
+    pub async fn get_cron_job_by_job_id(&self, job_id: &str) -> SqliteResult<Option<CronJob>> {
+        let conn = self.conn().await?;
         match conn.query_row(
             "SELECT id, job_id, name, description, schedule_type, schedule_value, timezone,
                     session_mode, message, system_event, channel_id, deliver_to, deliver,
@@ -123,8 +123,8 @@ impl Database {
     }
 
     /// List all cron jobs
-    pub fn list_cron_jobs(&self) -> SqliteResult<Vec<CronJob>> {
-        let conn = self.conn.lock().unwrap();
+    pub async fn list_cron_jobs(&self) -> SqliteResult<Vec<CronJob>> {
+        let conn = self.conn().await?;
         let mut stmt = conn.prepare(
             "SELECT id, job_id, name, description, schedule_type, schedule_value, timezone,
                     session_mode, message, system_event, channel_id, deliver_to, deliver,
@@ -143,8 +143,8 @@ impl Database {
     }
 
     /// List active cron jobs that are due to run
-    pub fn list_due_cron_jobs(&self) -> SqliteResult<Vec<CronJob>> {
-        let conn = self.conn.lock().unwrap();
+    pub async fn list_due_cron_jobs(&self) -> SqliteResult<Vec<CronJob>> {
+        let conn = self.conn().await?;
         let now = Utc::now().to_rfc3339();
 
         let mut stmt = conn.prepare(
@@ -168,7 +168,7 @@ impl Database {
 
     /// Update a cron job
     #[allow(clippy::too_many_arguments)]
-    pub fn update_cron_job(
+    pub async fn update_cron_job(
         &self,
         id: i64,
         name: Option<&str>,
@@ -188,7 +188,7 @@ impl Database {
         delete_after_run: Option<bool>,
         status: Option<&str>,
     ) -> SqliteResult<CronJob> {
-        let conn = self.conn.lock().unwrap();
+        let conn = self.conn().await?;
         let now = Utc::now().to_rfc3339();
 
         // Build dynamic update query
@@ -245,7 +245,7 @@ impl Database {
     }
 
     /// Update cron job run status
-    pub fn update_cron_job_run_status(
+    pub async fn update_cron_job_run_status(
         &self,
         id: i64,
         last_run_at: &str,
@@ -253,7 +253,7 @@ impl Database {
         success: bool,
         error: Option<&str>,
     ) -> SqliteResult<()> {
-        let conn = self.conn.lock().unwrap();
+        let conn = self.conn().await?;
         let now = Utc::now().to_rfc3339();
 
         if success {
@@ -279,8 +279,8 @@ impl Database {
 
     /// Mark a cron job as started by setting next_run_at to prevent duplicate execution
     /// This should be called BEFORE the job executes to prevent race conditions
-    pub fn mark_cron_job_started(&self, id: i64, next_run_at: Option<&str>) -> SqliteResult<()> {
-        let conn = self.conn.lock().unwrap();
+    pub async fn mark_cron_job_started(&self, id: i64, next_run_at: Option<&str>) -> SqliteResult<()> {
+        let conn = self.conn().await?;
         let now = Utc::now().to_rfc3339();
 
         conn.execute(
@@ -292,14 +292,14 @@ impl Database {
     }
 
     /// Delete a cron job
-    pub fn delete_cron_job(&self, id: i64) -> SqliteResult<bool> {
-        let conn = self.conn.lock().unwrap();
+    pub async fn delete_cron_job(&self, id: i64) -> SqliteResult<bool> {
+        let conn = self.conn().await?;
         let rows_affected = conn.execute("DELETE FROM cron_jobs WHERE id = ?1", [id])?;
         Ok(rows_affected > 0)
     }
 
     /// Log a cron job run
-    pub fn log_cron_job_run(
+    pub async fn log_cron_job_run(
         &self,
         job_id: i64,
         started_at: &str,
@@ -309,7 +309,7 @@ impl Database {
         error: Option<&str>,
         duration_ms: Option<i64>,
     ) -> SqliteResult<CronJobRun> {
-        let conn = self.conn.lock().unwrap();
+        let conn = self.conn().await?;
 
         conn.execute(
             "INSERT INTO cron_job_runs (job_id, started_at, completed_at, success, result, error, duration_ms)
@@ -332,8 +332,8 @@ impl Database {
     }
 
     /// Get recent runs for a cron job
-    pub fn get_cron_job_runs(&self, job_id: i64, limit: i32) -> SqliteResult<Vec<CronJobRun>> {
-        let conn = self.conn.lock().unwrap();
+    pub async fn get_cron_job_runs(&self, job_id: i64, limit: i32) -> SqliteResult<Vec<CronJobRun>> {
+        let conn = self.conn().await?;
         let mut stmt = conn.prepare(
             "SELECT id, job_id, started_at, completed_at, success, result, error, duration_ms
              FROM cron_job_runs WHERE job_id = ?1 ORDER BY started_at DESC LIMIT ?2"
@@ -0,0 +1,175 @@
+//! Stored embeddings for similarity search
+//!
+//! Search is a linear scan over one collection's vectors of the query's model,
+//! scored by cosine similarity in Rust.
+
+use chrono::Utc;
+
+use crate::db::DbResult;
+use crate::memory::embeddings::{blob_to_vector, cosine_similarity, vector_to_blob, Embedding};
+use crate::models::{EmbeddingMatch, StoredEmbedding};
+use super::super::Database;
+
+const EMBEDDING_COLUMNS: &str = "id, collection, external_id, content, metadata, model, dimensions, created_at";
+
+impl Database {
+    /// Store a text and its embedding. With an `external_id`, an earlier text
+    /// stored under the same id in the collection is replaced.
+    pub async fn store_embedding(
+        &self,
+        collection: &str,
+        external_id: Option<&str>,
+        content: &str,
+        metadata: Option<&serde_json::Value>,
+        embedding: &Embedding,
+    ) -> DbResult<StoredEmbedding> {
+        let metadata = metadata.map(|m| m.to_string());
+        let conn = self.conn().await?;
+        Ok(conn.query_row(
+            &format!(
+                "INSERT INTO embeddings (collection, external_id, content, metadata, embedding, model, dimensions, created_at)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)
+                 ON CONFLICT(collection, external_id) DO UPDATE SET
+                     content = excluded.content, metadata = excluded.metadata, embedding = excluded.embedding,
+                     model = excluded.model, dimensions = excluded.dimensions, created_at = excluded.created_at
+                 RETURNING {}",
+                EMBEDDING_COLUMNS
+            ),
+            rusqlite::params![
+                collection,
+                external_id,
+                content,
+                metadata,
+                vector_to_blob(&embedding.vector),
+                embedding.model,
+                embedding.dimensions as i64,
+                Utc::now().to_rfc3339(),
+            ],
+            Self::map_embedding_row,
+        )?)
+    }
+
+    /// The `limit` texts of a collection most similar to `vector`, best first.
+    /// Only vectors made by `model` are compared.
+    pub async fn search_embeddings(
+        &self,
+        collection: &str,
+        model: &str,
+        vector: &[f32],
+        limit: usize,
+    ) -> DbResult<Vec<EmbeddingMatch>> {
+        let conn = self.conn().await?;
+        let mut stmt = conn.prepare(&format!(
+            "SELECT {}, embedding FROM embeddings WHERE collection = ?1 AND model = ?2",
+            EMBEDDING_COLUMNS
+        ))?;
+        let mut matches = stmt
+            .query_map([collection, model], |row| {
+                let stored: Vec<u8> = row.get(8)?;
+                Ok(EmbeddingMatch {
+                    embedding: Self::map_embedding_row(row)?,
+                    score: cosine_similarity(vector, &blob_to_vector(&stored)),
+                })
+            })?
+            .collect::<rusqlite::Result<Vec<_>>>()?;
+
+        matches.sort_by(|a, b| b.score.total_cmp(&a.score));
+        matches.truncate(limit);
+        Ok(matches)
+    }
+
+    /// Every text of a collection, without the vectors, in id order
+    pub async fn list_embeddings(&self, collection: &str) -> DbResult<Vec<StoredEmbedding>> {
+        let conn = self.conn().await?;
+        let mut stmt = conn.prepare(&format!(
+            "SELECT {} FROM embeddings WHERE collection = ?1 ORDER BY id",
+            EMBEDDING_COLUMNS
+        ))?;
+        let rows = stmt
+            .query_map([collection], Self::map_embedding_row)?
+            .collect::<rusqlite::Result<Vec<_>>>()?;
+        Ok(rows)
+    }
+
+    /// Delete texts by id. Returns how many were found.
+    pub async fn delete_embeddings(&self, ids: &[i64]) -> DbResult<usize> {
+        let conn = self.conn().await?;
+        let mut stmt = conn.prepare("DELETE FROM embeddings WHERE id = ?1")?;
+        let mut deleted = 0;
+        for id in ids {
+            deleted += stmt.execute([id])?;
+        }
+        Ok(deleted)
+    }
+
+    pub async fn delete_embedding(&self, id: i64) -> DbResult<bool> {
+        let conn = self.conn().await?;
+        Ok(conn.execute("DELETE FROM embeddings WHERE id = ?1", [id])? > 0)
+    }
+
+    /// Drop every text of a collection. Returns how many there were.
+    pub async fn delete_embedding_collection(&self, collection: &str) -> DbResult<usize> {
+        let conn = self.conn().await?;
+        Ok(conn.execute("DELETE FROM embeddings WHERE collection = ?1", [collection])?)
+    }
+
+    fn map_embedding_row(row: &rusqlite::Row) -> rusqlite::Result<StoredEmbedding> {
+        let metadata: Option<String> = row.get(4)?;
+        Ok(StoredEmbedding {
+            id: row.get(0)?,
+            collection: row.get(1)?,
+            external_id: row.get(2)?,
+            content: row.get(3)?,
+            metadata: metadata.and_then(|m| serde_json::from_str(&m).ok()),
+            model: row.get(5)?,
+            dimensions: row.get(6)?,
+            created_at: row.get(7)?,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn embedding(vector: Vec<f32>, model: &str) -> Embedding {
+        Embedding { dimensions: vector.len(), vector, model: model.to_string() }
+    }
+
+    #[tokio::test]
+    async fn test_embeddings_store_search_and_delete() {
+        let db = Database::new(":memory:").unwrap();
+        let meta = json!({ "path": "README.md" });
+        let readme = db
+            .store_embedding("docs", Some("readme"), "Read me", Some(&meta), &embedding(vec![1.0, 0.0], "m1"))
+            .await
+            .unwrap();
+        assert_eq!(readme.metadata, Some(meta));
+        db.store_embedding("docs", None, "Mostly x", None, &embedding(vec![0.9, 0.1], "m1")).await.unwrap();
+        db.store_embedding("docs", None, "Other model", None, &embedding(vec![1.0, 0.0], "m2")).await.unwrap();
+        db.store_embedding("code", None, "Other collection", None, &embedding(vec![1.0, 0.0], "m1")).await.unwrap();
+
+        // Storing the same id again replaces the text in place
+        let replaced = db
+            .store_embedding("docs", Some("readme"), "Read me first", None, &embedding(vec![0.0, 1.0], "m1"))
+            .await
+            .unwrap();
+        assert_eq!(replaced.id, readme.id);
+        assert_eq!(replaced.metadata, None);
+
+        let matches = db.search_embeddings("docs", "m1", &[1.0, 0.0], 10).await.unwrap();
+        let contents: Vec<&str> = matches.iter().map(|m| m.embedding.content.as_str()).collect();
+        assert_eq!(contents, vec!["Mostly x", "Read me first"]);
+        assert!(matches[0].score > 0.99 && matches[1].score.abs() < 0.0001);
+        assert_eq!(db.search_embeddings("docs", "m1", &[1.0, 0.0], 1).await.unwrap().len(), 1);
+
+        assert_eq!(db.list_embeddings("docs").await.unwrap().len(), 3);
+        assert_eq!(db.delete_embeddings(&[readme.id + 100]).await.unwrap(), 0);
+        assert!(db.delete_embedding(readme.id).await.unwrap());
+        assert!(!db.delete_embedding(readme.id).await.unwrap());
+        assert_eq!(db.delete_embedding_collection("docs").await.unwrap(), 2);
+        assert!(db.search_embeddings("docs", "m1", &[1.0, 0.0], 10).await.unwrap().is_empty());
+        assert_eq!(db.search_embeddings("code", "m1", &[1.0, 0.0], 10).await.unwrap().len(), 1);
+    }
+}
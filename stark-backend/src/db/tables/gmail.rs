@@ -8,8 +8,8 @@ use super::super::Database;
 
 impl Database {
     /// Get Gmail configuration (only one config supported currently)
-    pub fn get_gmail_config(&self) -> SqliteResult<Option<GmailConfig>> {
-        let conn = self.conn.lock().unwrap();
+    pub async fn get_gmail_config(&self) -> SqliteResult<Option<GmailConfig>> {
+        let conn = self.conn().await?;
         let mut stmt = conn.prepare(
             "SELECT id, email, access_token, refresh_token, token_expires_at,
                     watch_labels, project_id, topic_name, watch_expires_at, history_id,
@@ -25,8 +25,8 @@ impl Database {
     }
 
     /// Get Gmail configuration by email
-    pub fn get_gmail_config_by_email(&self, email: &str) -> SqliteResult<Option<GmailConfig>> {
-        let conn = self.conn.lock().unwrap();
+    pub async fn get_gmail_config_by_email(&self, email: &str) -> SqliteResult<Option<GmailConfig>> {
+        let conn = self.conn().await?;
         let mut stmt = conn.prepare(
             "SELECT id, email, access_token, refresh_token, token_expires_at,
                     watch_labels, project_id, topic_name, watch_expires_at, history_id,
@@ -42,7 +42,7 @@ impl Database {
     }
 
     /// Create Gmail configuration
-    pub fn create_gmail_config(
+    pub async fn create_gmail_config(
         &self,
         email: &str,
         access_token: &str,
@@ -53,7 +53,7 @@ impl Database {
         response_channel_id: Option<i64>,
         auto_reply: bool,
     ) -> SqliteResult<GmailConfig> {
-        let conn = self.conn.lock().unwrap();
+        let conn = self.conn().await?;
         let now = Utc::now().to_rfc3339();
 
         conn.execute(
@@ -67,18 +67,18 @@ impl Database {
         )?;
 
         drop(conn);
-        self.get_gmail_config().map(|opt| opt.unwrap())
+        self.get_gmail_config().await.map(|opt| opt.unwrap())
     }
 
     /// Update Gmail configuration
-    pub fn update_gmail_config(
+    pub async fn update_gmail_config(
         &self,
         watch_labels: Option<&str>,
         response_channel_id: Option<i64>,
         auto_reply: Option<bool>,
         enabled: Option<bool>,
     ) -> SqliteResult<GmailConfig> {
-        let conn = self.conn.lock().unwrap();
+        let conn = self.conn().await?;
         let now = Utc::now().to_rfc3339();
 
         // Build dynamic update
@@ -102,17 +102,17 @@ impl Database {
         )?;
 
         drop(conn);
-        self.get_gmail_config().map(|opt| opt.unwrap())
+        self.get_gmail_config().await.map(|opt| opt.unwrap())
     }
 
     /// Update Gmail watch info
-    pub fn update_gmail_watch(
+    pub async fn update_gmail_watch(
         &self,
         id: i64,
         watch_expires_at: Option<DateTime<Utc>>,
         history_id: Option<&str>,
     ) -> SqliteResult<()> {
-        let conn = self.conn.lock().unwrap();
+        let conn = self.conn().await?;
         let now = Utc::now().to_rfc3339();
         let expires_str = watch_expires_at.map(|dt| dt.to_rfc3339());
 
@@ -125,8 +125,8 @@ impl Database {
     }
 
     /// Update Gmail history ID
-    pub fn update_gmail_history_id(&self, id: i64, history_id: &str) -> SqliteResult<()> {
-        let conn = self.conn.lock().unwrap();
+    pub async fn update_gmail_history_id(&self, id: i64, history_id: &str) -> SqliteResult<()> {
+        let conn = self.conn().await?;
         let now = Utc::now().to_rfc3339();
 
         conn.execute(
@@ -138,13 +138,13 @@ impl Database {
     }
 
     /// Update Gmail tokens
-    pub fn update_gmail_tokens(
+    pub async fn update_gmail_tokens(
         &self,
         id: i64,
         access_token: &str,
         token_expires_at: Option<DateTime<Utc>>,
     ) -> SqliteResult<()> {
-        let conn = self.conn.lock().unwrap();
+        let conn = self.conn().await?;
         let now = Utc::now().to_rfc3339();
         let expires_str = token_expires_at.map(|dt| dt.to_rfc3339());
 
@@ -157,8 +157,8 @@ impl Database {
     }
 
     /// Delete Gmail configuration
-    pub fn delete_gmail_config(&self) -> SqliteResult<bool> {
-        let conn = self.conn.lock().unwrap();
+    pub async fn delete_gmail_config(&self) -> SqliteResult<bool> {
+        let conn = self.conn().await?;
         let deleted = conn.execute("DELETE FROM gmail_configs", [])?;
         Ok(deleted > 0)
     }
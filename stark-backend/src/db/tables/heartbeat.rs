@@ -8,8 +8,8 @@ use super::super::Database;
 
 impl Database {
     /// Get or create heartbeat config for a channel (or global if channel_id is None)
-    pub fn get_or_create_heartbeat_config(&self, channel_id: Option<i64>) -> SqliteResult<HeartbeatConfig> {
-        let conn = self.conn.lock().unwrap();
+    pub async fn get_or_create_heartbeat_config(&self, channel_id: Option<i64>) -> SqliteResult<HeartbeatConfig> {
+        let conn = self.conn().await?;
 
         // Try to get existing config
         let existing = if let Some(cid) = channel_id {
@@ -78,7 +78,7 @@ impl Database {
     }
 
     /// Update heartbeat config
-    pub fn update_heartbeat_config(
+    pub async fn update_heartbeat_config(
         &self,
         id: i64,
         interval_minutes: Option<i32>,
@@ -88,7 +88,7 @@ impl Database {
         active_days: Option<&str>,
         enabled: Option<bool>,
     ) -> SqliteResult<HeartbeatConfig> {
-        let conn = self.conn.lock().unwrap();
+        let conn = self.conn().await?;
         let now = Utc::now().to_rfc3339();
 
         let mut updates = vec!["updated_at = ?1".to_string()];
@@ -129,8 +129,8 @@ impl Database {
     }
 
     /// Update heartbeat last run time
-    pub fn update_heartbeat_last_beat(&self, id: i64, last_beat_at: &str, next_beat_at: &str) -> SqliteResult<()> {
-        let conn = self.conn.lock().unwrap();
+    pub async fn update_heartbeat_last_beat(&self, id: i64, last_beat_at: &str, next_beat_at: &str) -> SqliteResult<()> {
+        let conn = self.conn().await?;
         let now = Utc::now().to_rfc3339();
 
         conn.execute(
@@ -142,8 +142,8 @@ impl Database {
     }
 
     /// List all heartbeat configs
-    pub fn list_heartbeat_configs(&self) -> SqliteResult<Vec<HeartbeatConfig>> {
-        let conn = self.conn.lock().unwrap();
+    pub async fn list_heartbeat_configs(&self) -> SqliteResult<Vec<HeartbeatConfig>> {
+        let conn = self.conn().await?;
         let mut stmt = conn.prepare(
             "SELECT id, channel_id, interval_minutes, target, active_hours_start, active_hours_end,
                     active_days, enabled, last_beat_at, next_beat_at, created_at, updated_at
@@ -159,8 +159,8 @@ impl Database {
     }
 
     /// Get enabled heartbeat configs that are due to run
-    pub fn list_due_heartbeat_configs(&self) -> SqliteResult<Vec<HeartbeatConfig>> {
-        let conn = self.conn.lock().unwrap();
+    pub async fn list_due_heartbeat_configs(&self) -> SqliteResult<Vec<HeartbeatConfig>> {
+        let conn = self.conn().await?;
         let now = Utc::now().to_rfc3339();
 
         let mut stmt = conn.prepare(
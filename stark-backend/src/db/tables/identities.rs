@@ -9,29 +9,29 @@ use super::super::Database;
 
 impl Database {
     /// Get or create an identity for a platform user
-    pub fn get_or_create_identity(
+    pub async fn get_or_create_identity(
         &self,
         channel_type: &str,
         platform_user_id: &str,
         platform_user_name: Option<&str>,
     ) -> SqliteResult<IdentityLink> {
         // Try to get existing
-        if let Some(link) = self.get_identity_by_platform(channel_type, platform_user_id)? {
+        if let Some(link) = self.get_identity_by_platform(channel_type, platform_user_id).await? {
             // Update username if changed
             if platform_user_name.is_some() && link.platform_user_name.as_deref() != platform_user_name {
-                let conn = self.conn.lock().unwrap();
+                let conn = self.conn().await?;
                 let now = Utc::now().to_rfc3339();
                 conn.execute(
                     "UPDATE identity_links SET platform_user_name = ?1, updated_at = ?2 WHERE id = ?3",
                     rusqlite::params![platform_user_name, &now, link.id],
                 )?;
             }
-            return self.get_identity_by_platform(channel_type, platform_user_id).map(|opt| opt.unwrap());
+            return self.get_identity_by_platform(channel_type, platform_user_id).await.map(|opt| opt.unwrap());
         }
 
         // Create new identity
         let identity_id = Uuid::new_v4().to_string();
-        let conn = self.conn.lock().unwrap();
+        let conn = self.conn().await?;
         let now = Utc::now();
         let now_str = now.to_rfc3339();
 
@@ -57,14 +57,14 @@ impl Database {
     }
 
     /// Link an existing identity to a new platform
-    pub fn link_identity(
+    pub async fn link_identity(
         &self,
         identity_id: &str,
         channel_type: &str,
         platform_user_id: &str,
         platform_user_name: Option<&str>,
     ) -> SqliteResult<IdentityLink> {
-        let conn = self.conn.lock().unwrap();
+        let conn = self.conn().await?;
         let now = Utc::now();
         let now_str = now.to_rfc3339();
 
@@ -90,8 +90,8 @@ impl Database {
     }
 
     /// Get identity by platform credentials
-    pub fn get_identity_by_platform(&self, channel_type: &str, platform_user_id: &str) -> SqliteResult<Option<IdentityLink>> {
-        let conn = self.conn.lock().unwrap();
+    pub async fn get_identity_by_platform(&self, channel_type: &str, platform_user_id: &str) -> SqliteResult<Option<IdentityLink>> {
+        let conn = self.conn().await?;
 
         let mut stmt = conn.prepare(
             "SELECT id, identity_id, channel_type, platform_user_id, platform_user_name, is_verified, verified_at, created_at, updated_at
@@ -106,8 +106,8 @@ impl Database {
     }
 
     /// Get all linked identities for an identity_id
-    pub fn get_linked_identities(&self, identity_id: &str) -> SqliteResult<Vec<IdentityLink>> {
-        let conn = self.conn.lock().unwrap();
+    pub async fn get_linked_identities(&self, identity_id: &str) -> SqliteResult<Vec<IdentityLink>> {
+        let conn = self.conn().await?;
 
         let mut stmt = conn.prepare(
             "SELECT id, identity_id, channel_type, platform_user_id, platform_user_name, is_verified, verified_at, created_at, updated_at
@@ -123,8 +123,8 @@ impl Database {
     }
 
     /// List all identity links (unique identities)
-    pub fn list_identities(&self) -> SqliteResult<Vec<IdentityLink>> {
-        let conn = self.conn.lock().unwrap();
+    pub async fn list_identities(&self) -> SqliteResult<Vec<IdentityLink>> {
+        let conn = self.conn().await?;
 
         let mut stmt = conn.prepare(
             "SELECT id, identity_id, channel_type, platform_user_id, platform_user_name, is_verified, verified_at, created_at, updated_at
@@ -0,0 +1,124 @@
+//! Sign-in lockout counters and the failed sign-in audit log
+
+use async_trait::async_trait;
+use chrono::{DateTime, Duration, Utc};
+use rusqlite::{OptionalExtension, Result as SqliteResult};
+
+use crate::models::{LoginFailure, LoginFailureReason};
+use super::super::backend::{DbResult, LoginAttemptStore, SqliteBackend, LOGIN_FAILURE_MEMORY_HOURS};
+
+fn parse_time(value: &str) -> DateTime<Utc> {
+    DateTime::parse_from_rfc3339(value)
+        .map(|dt| dt.with_timezone(&Utc))
+        .unwrap_or_else(|_| Utc::now())
+}
+
+#[async_trait]
+impl LoginAttemptStore for SqliteBackend {
+    async fn get_login_lockout(&self, ip: &str) -> DbResult<Option<DateTime<Utc>>> {
+        let conn = self.conn().await?;
+        let locked_until: Option<String> = conn
+            .query_row(
+                "SELECT locked_until FROM login_attempts WHERE ip = ?1",
+                [ip],
+                |row| row.get(0),
+            )
+            .optional()?
+            .flatten();
+        Ok(locked_until.map(|t| parse_time(&t)).filter(|until| *until > Utc::now()))
+    }
+
+    async fn record_login_failure(
+        &self,
+        ip: &str,
+        public_address: Option<&str>,
+        reason: LoginFailureReason,
+    ) -> DbResult<i64> {
+        let conn = self.conn().await?;
+        let now = Utc::now();
+        conn.execute(
+            "INSERT INTO login_failures (ip, public_address, reason, occurred_at) VALUES (?1, ?2, ?3, ?4)",
+            rusqlite::params![ip, public_address, reason.as_str(), now.to_rfc3339()],
+        )?;
+        if reason == LoginFailureReason::LockedOut {
+            let failures = conn
+                .query_row("SELECT failures FROM login_attempts WHERE ip = ?1", [ip], |row| row.get(0))
+                .optional()?;
+            return Ok(failures.unwrap_or(0));
+        }
+
+        let forget_before = now - Duration::hours(LOGIN_FAILURE_MEMORY_HOURS);
+        let failures = conn.query_row(
+            "INSERT INTO login_attempts (ip, failures, last_failure_at) VALUES (?1, 1, ?2)
+             ON CONFLICT(ip) DO UPDATE SET
+                failures = CASE WHEN last_failure_at < ?3 THEN 1 ELSE failures + 1 END,
+                last_failure_at = ?2
+             RETURNING failures",
+            rusqlite::params![ip, now.to_rfc3339(), forget_before.to_rfc3339()],
+            |row| row.get(0),
+        )?;
+        Ok(failures)
+    }
+
+    async fn set_login_lockout(&self, ip: &str, until: DateTime<Utc>) -> DbResult<()> {
+        let conn = self.conn().await?;
+        conn.execute(
+            "UPDATE login_attempts SET locked_until = ?2 WHERE ip = ?1",
+            rusqlite::params![ip, until.to_rfc3339()],
+        )?;
+        Ok(())
+    }
+
+    async fn clear_login_failures(&self, ip: &str) -> DbResult<()> {
+        let conn = self.conn().await?;
+        conn.execute("DELETE FROM login_attempts WHERE ip = ?1", [ip])?;
+        Ok(())
+    }
+
+    async fn list_login_failures(&self, limit: i64) -> DbResult<Vec<LoginFailure>> {
+        let conn = self.conn().await?;
+        let mut stmt = conn.prepare(
+            "SELECT id, ip, public_address, reason, occurred_at
+             FROM login_failures ORDER BY id DESC LIMIT ?1",
+        )?;
+        let failures = stmt
+            .query_map([limit], |row| {
+                let occurred_at: String = row.get(4)?;
+                Ok(LoginFailure {
+                    id: row.get(0)?,
+                    ip: row.get(1)?,
+                    public_address: row.get(2)?,
+                    reason: row.get(3)?,
+                    occurred_at: parse_time(&occurred_at),
+                })
+            })?
+            .collect::<SqliteResult<Vec<_>>>()?;
+        Ok(failures)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::Database;
+
+    #[tokio::test]
+    async fn test_login_failures_count_and_clear() {
+        let db = Database::new(":memory:").unwrap();
+        let ip = "203.0.113.7";
+
+        assert_eq!(db.record_login_failure(ip, None, LoginFailureReason::InvalidSignature).await.unwrap(), 1);
+        assert_eq!(db.record_login_failure(ip, Some("0xabc"), LoginFailureReason::InvalidTotp).await.unwrap(), 2);
+        // Refusals during a lockout are logged without extending it
+        assert_eq!(db.record_login_failure(ip, None, LoginFailureReason::LockedOut).await.unwrap(), 2);
+        assert_eq!(db.list_login_failures(10).await.unwrap().len(), 3);
+
+        assert!(db.get_login_lockout(ip).await.unwrap().is_none());
+        db.set_login_lockout(ip, Utc::now() + Duration::minutes(1)).await.unwrap();
+        assert!(db.get_login_lockout(ip).await.unwrap().is_some());
+
+        db.clear_login_failures(ip).await.unwrap();
+        assert!(db.get_login_lockout(ip).await.unwrap().is_none());
+        assert_eq!(db.record_login_failure(ip, None, LoginFailureReason::InvalidSignature).await.unwrap(), 1);
+    }
+}
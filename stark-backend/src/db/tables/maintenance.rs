@@ -0,0 +1,118 @@
+//! Database maintenance operations (stats, VACUUM, ANALYZE)
+
+use rusqlite::Result as SqliteResult;
+use serde::Serialize;
+
+use super::super::sqlite::task_failed;
+use super::super::Database;
+
+/// Row count for a single table
+#[derive(Debug, Clone, Serialize)]
+pub struct TableStats {
+    pub name: String,
+    pub row_count: i64,
+}
+
+/// Size and row counts of the database
+#[derive(Debug, Clone, Serialize)]
+pub struct DatabaseStats {
+    pub tables: Vec<TableStats>,
+    pub page_size: i64,
+    pub page_count: i64,
+    /// Unused pages that VACUUM would reclaim
+    pub freelist_count: i64,
+    /// Size of the main database file (page_size * page_count for in-memory databases)
+    pub size_bytes: u64,
+    /// Size of the write-ahead log, if the database is in WAL mode
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub wal_size_bytes: Option<u64>,
+}
+
+impl Database {
+    /// Row counts per table plus on-disk size
+    pub async fn stats(&self) -> SqliteResult<DatabaseStats> {
+        let conn = self.conn().await?;
+
+        let mut stmt = conn.prepare(
+            "SELECT name FROM sqlite_master WHERE type = 'table' AND name NOT LIKE 'sqlite_%' ORDER BY name",
+        )?;
+        let names: Vec<String> = stmt
+            .query_map([], |row| row.get(0))?
+            .collect::<SqliteResult<Vec<_>>>()?;
+
+        let mut tables = Vec::with_capacity(names.len());
+        for name in names {
+            let row_count: i64 = conn.query_row(
+                &format!("SELECT COUNT(*) FROM \"{}\"", name.replace('"', "\"\"")),
+                [],
+                |row| row.get(0),
+            )?;
+            tables.push(TableStats { name, row_count });
+        }
+
+        let page_size: i64 = conn.query_row("PRAGMA page_size", [], |row| row.get(0))?;
+        let page_count: i64 = conn.query_row("PRAGMA page_count", [], |row| row.get(0))?;
+        let freelist_count: i64 = conn.query_row("PRAGMA freelist_count", [], |row| row.get(0))?;
+
+        let file_path = conn.path().filter(|p| !p.is_empty()).map(str::to_string);
+        let size_bytes = file_path
+            .as_ref()
+            .and_then(|p| std::fs::metadata(p).ok())
+            .map(|m| m.len())
+            .unwrap_or((page_size * page_count) as u64);
+        let wal_size_bytes = file_path
+            .and_then(|p| std::fs::metadata(format!("{}-wal", p)).ok())
+            .map(|m| m.len());
+
+        Ok(DatabaseStats {
+            tables,
+            page_size,
+            page_count,
+            freelist_count,
+            size_bytes,
+            wal_size_bytes,
+        })
+    }
+
+    /// Checkpoint the WAL, then VACUUM and ANALYZE.
+    /// VACUUM rewrites the whole file and blocks writers on other pooled
+    /// connections until it finishes, so it runs on a blocking thread.
+    pub async fn vacuum_and_analyze(&self) -> SqliteResult<()> {
+        let conn = self.conn().await?;
+
+        tokio::task::spawn_blocking(move || {
+            // Fold the WAL back into the main file first (no-op when not in WAL mode)
+            conn.query_row("PRAGMA wal_checkpoint(TRUNCATE)", [], |_| Ok(()))?;
+            conn.execute_batch("VACUUM; ANALYZE;")
+        })
+        .await
+        .map_err(task_failed)?
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_stats_and_vacuum() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let path = dir.path().join("stark.db");
+        let db = Database::new(path.to_str().unwrap()).unwrap();
+
+        db.upsert_api_key("test_service", None, "value").await.unwrap();
+
+        let stats = db.stats().await.unwrap();
+        let api_keys = stats
+            .tables
+            .iter()
+            .find(|t| t.name == "external_api_keys")
+            .unwrap();
+        assert_eq!(api_keys.row_count, 1);
+        assert!(stats.tables.iter().all(|t| !t.name.starts_with("sqlite_")));
+        assert!(stats.size_bytes > 0);
+
+        db.vacuum_and_analyze().await.unwrap();
+        assert_eq!(db.stats().await.unwrap().freelist_count, 0);
+    }
+}
@@ -10,7 +10,7 @@ use std::collections::HashMap;
 impl Database {
     /// Create a memory (daily_log, long_term, session_summary, compaction, preference, fact, entity, task)
     #[allow(clippy::too_many_arguments)]
-    pub fn create_memory(
+    pub async fn create_memory(
         &self,
         memory_type: MemoryType,
         content: &str,
@@ -37,12 +37,12 @@ impl Database {
             log_date,
             expires_at,
             None, None, None, None, None, None, None,
-        )
+        ).await
     }
 
     /// Create a memory with all extended fields (Phase 2, 7)
     #[allow(clippy::too_many_arguments)]
-    pub fn create_memory_extended(
+    pub async fn create_memory_extended(
         &self,
         memory_type: MemoryType,
         content: &str,
@@ -63,7 +63,7 @@ impl Database {
         valid_until: Option<DateTime<Utc>>,
         temporal_type: Option<&str>,
     ) -> SqliteResult<Memory> {
-        let conn = self.conn.lock().unwrap();
+        let conn = self.conn().await?;
         let now = Utc::now();
         let now_str = now.to_rfc3339();
         let log_date_str = log_date.map(|d| d.to_string());
@@ -76,8 +76,9 @@ impl Database {
         conn.execute(
             "INSERT INTO memories (memory_type, content, category, tags, importance, identity_id, session_id,
              source_channel_type, source_message_id, log_date, created_at, updated_at, expires_at,
-             entity_type, entity_name, confidence, source_type, valid_from, valid_until, temporal_type)
-             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?11, ?12, ?13, ?14, ?15, ?16, ?17, ?18, ?19)",
+             entity_type, entity_name, confidence, source_type, valid_from, valid_until, temporal_type, user_id)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?11, ?12, ?13, ?14, ?15, ?16, ?17, ?18, ?19,
+                     (SELECT user_id FROM chat_sessions WHERE id = ?7))",
             rusqlite::params![
                 memory_type.as_str(),
                 content,
@@ -102,6 +103,7 @@ impl Database {
         )?;
 
         let id = conn.last_insert_rowid();
+        let user_id: Option<i64> = conn.query_row("SELECT user_id FROM memories WHERE id = ?1", [id], |row| row.get(0))?;
 
         Ok(Memory {
             id,
@@ -128,11 +130,13 @@ impl Database {
             valid_from,
             valid_until,
             temporal_type: temporal_type.map(|s| s.to_string()),
+            user_id,
         })
     }
 
     /// Search memories using FTS5
-    pub fn search_memories(
+    #[allow(clippy::too_many_arguments)]
+    pub async fn search_memories(
         &self,
         query: &str,
         memory_type: Option<MemoryType>,
@@ -140,8 +144,9 @@ impl Database {
         category: Option<&str>,
         min_importance: Option<i32>,
         limit: i32,
+        owner: Option<i64>,
     ) -> SqliteResult<Vec<MemorySearchResult>> {
-        let conn = self.conn.lock().unwrap();
+        let conn = self.conn().await?;
 
         // Build the query with filters - includes all new columns
         let mut sql = String::from(
@@ -149,7 +154,7 @@ impl Database {
              m.session_id, m.source_channel_type, m.source_message_id, m.log_date,
              m.created_at, m.updated_at, m.expires_at,
              m.entity_type, m.entity_name, m.confidence, m.source_type, m.last_referenced_at,
-             m.superseded_by, m.superseded_at, m.valid_from, m.valid_until, m.temporal_type,
+             m.superseded_by, m.superseded_at, m.valid_from, m.valid_until, m.temporal_type, m.user_id,
              bm25(memories_fts) as rank
              FROM memories m
              JOIN memories_fts ON m.id = memories_fts.rowid
@@ -172,6 +177,11 @@ impl Database {
             let idx = 2 + (memory_type.is_some() as usize) + (identity_id.is_some() as usize) + (category.is_some() as usize);
             conditions.push(format!("m.importance >= ?{}", idx));
         }
+        if owner.is_some() {
+            let idx = 2 + (memory_type.is_some() as usize) + (identity_id.is_some() as usize)
+                + (category.is_some() as usize) + (min_importance.is_some() as usize);
+            conditions.push(format!("m.user_id = ?{}", idx));
+        }
 
         if !conditions.is_empty() {
             sql.push_str(" AND ");
@@ -180,7 +190,7 @@ impl Database {
 
         sql.push_str(" ORDER BY rank LIMIT ?");
         let limit_idx = 2 + (memory_type.is_some() as usize) + (identity_id.is_some() as usize)
-            + (category.is_some() as usize) + (min_importance.is_some() as usize);
+            + (category.is_some() as usize) + (min_importance.is_some() as usize) + (owner.is_some() as usize);
         sql = sql.replace("LIMIT ?", &format!("LIMIT ?{}", limit_idx));
 
         let mut stmt = conn.prepare(&sql)?;
@@ -199,6 +209,9 @@ impl Database {
         if let Some(mi) = min_importance {
             params.push(Box::new(mi));
         }
+        if let Some(owner) = owner {
+            params.push(Box::new(owner));
+        }
         params.push(Box::new(limit));
 
         let params_ref: Vec<&dyn rusqlite::ToSql> = params.iter().map(|p| p.as_ref()).collect();
@@ -206,7 +219,7 @@ impl Database {
         let results = stmt
             .query_map(params_ref.as_slice(), |row| {
                 let memory = Self::row_to_memory(row)?;
-                let rank: f64 = row.get(24)?; // rank is now at index 24
+                let rank: f64 = row.get(25)?; // rank is now at index 25
                 Ok(MemorySearchResult {
                     memory: memory.into(),
                     rank,
@@ -219,22 +232,22 @@ impl Database {
     }
 
     /// Get today's daily logs
-    pub fn get_todays_daily_logs(&self, identity_id: Option<&str>) -> SqliteResult<Vec<Memory>> {
-        let conn = self.conn.lock().unwrap();
+    pub async fn get_todays_daily_logs(&self, identity_id: Option<&str>) -> SqliteResult<Vec<Memory>> {
+        let conn = self.conn().await?;
         let today = Utc::now().date_naive().to_string();
 
         let sql = if identity_id.is_some() {
             "SELECT id, memory_type, content, category, tags, importance, identity_id, session_id,
              source_channel_type, source_message_id, log_date, created_at, updated_at, expires_at,
              entity_type, entity_name, confidence, source_type, last_referenced_at,
-             superseded_by, superseded_at, valid_from, valid_until, temporal_type
+             superseded_by, superseded_at, valid_from, valid_until, temporal_type, user_id
              FROM memories WHERE memory_type = 'daily_log' AND log_date = ?1 AND identity_id = ?2
              AND superseded_by IS NULL ORDER BY created_at ASC"
         } else {
             "SELECT id, memory_type, content, category, tags, importance, identity_id, session_id,
              source_channel_type, source_message_id, log_date, created_at, updated_at, expires_at,
              entity_type, entity_name, confidence, source_type, last_referenced_at,
-             superseded_by, superseded_at, valid_from, valid_until, temporal_type
+             superseded_by, superseded_at, valid_from, valid_until, temporal_type, user_id
              FROM memories WHERE memory_type = 'daily_log' AND log_date = ?1
              AND superseded_by IS NULL ORDER BY created_at ASC"
         };
@@ -255,8 +268,8 @@ impl Database {
     }
 
     /// Get long-term memories for an identity (includes preference, fact, entity, task types)
-    pub fn get_long_term_memories(&self, identity_id: Option<&str>, min_importance: Option<i32>, limit: i32) -> SqliteResult<Vec<Memory>> {
-        let conn = self.conn.lock().unwrap();
+    pub async fn get_long_term_memories(&self, identity_id: Option<&str>, min_importance: Option<i32>, limit: i32) -> SqliteResult<Vec<Memory>> {
+        let conn = self.conn().await?;
         let min_imp = min_importance.unwrap_or(0);
         let now = Utc::now().to_rfc3339();
 
@@ -265,7 +278,7 @@ impl Database {
             "SELECT id, memory_type, content, category, tags, importance, identity_id, session_id,
              source_channel_type, source_message_id, log_date, created_at, updated_at, expires_at,
              entity_type, entity_name, confidence, source_type, last_referenced_at,
-             superseded_by, superseded_at, valid_from, valid_until, temporal_type
+             superseded_by, superseded_at, valid_from, valid_until, temporal_type, user_id
              FROM memories WHERE memory_type IN ('long_term', 'preference', 'fact', 'entity', 'task')
              AND identity_id = ?1 AND importance >= ?2
              AND superseded_by IS NULL
@@ -276,7 +289,7 @@ impl Database {
             "SELECT id, memory_type, content, category, tags, importance, identity_id, session_id,
              source_channel_type, source_message_id, log_date, created_at, updated_at, expires_at,
              entity_type, entity_name, confidence, source_type, last_referenced_at,
-             superseded_by, superseded_at, valid_from, valid_until, temporal_type
+             superseded_by, superseded_at, valid_from, valid_until, temporal_type, user_id
              FROM memories WHERE memory_type IN ('long_term', 'preference', 'fact', 'entity', 'task')
              AND importance >= ?1
              AND superseded_by IS NULL
@@ -301,21 +314,21 @@ impl Database {
     }
 
     /// Get session summaries (past conversation summaries)
-    pub fn get_session_summaries(&self, identity_id: Option<&str>, limit: i32) -> SqliteResult<Vec<Memory>> {
-        let conn = self.conn.lock().unwrap();
+    pub async fn get_session_summaries(&self, identity_id: Option<&str>, limit: i32) -> SqliteResult<Vec<Memory>> {
+        let conn = self.conn().await?;
 
         let sql = if identity_id.is_some() {
             "SELECT id, memory_type, content, category, tags, importance, identity_id, session_id,
              source_channel_type, source_message_id, log_date, created_at, updated_at, expires_at,
              entity_type, entity_name, confidence, source_type, last_referenced_at,
-             superseded_by, superseded_at, valid_from, valid_until, temporal_type
+             superseded_by, superseded_at, valid_from, valid_until, temporal_type, user_id
              FROM memories WHERE memory_type = 'session_summary' AND identity_id = ?1
              ORDER BY created_at DESC LIMIT ?2"
         } else {
             "SELECT id, memory_type, content, category, tags, importance, identity_id, session_id,
              source_channel_type, source_message_id, log_date, created_at, updated_at, expires_at,
              entity_type, entity_name, confidence, source_type, last_referenced_at,
-             superseded_by, superseded_at, valid_from, valid_until, temporal_type
+             superseded_by, superseded_at, valid_from, valid_until, temporal_type, user_id
              FROM memories WHERE memory_type = 'session_summary'
              ORDER BY created_at DESC LIMIT ?1"
         };
@@ -335,33 +348,35 @@ impl Database {
         Ok(memories)
     }
 
-    /// List all memories (with pagination support)
-    pub fn list_memories(&self) -> SqliteResult<Vec<Memory>> {
-        self.list_memories_paginated(100, 0)
+    /// List all memories, or only those of `owner` (with pagination support)
+    pub async fn list_memories(&self, owner: Option<i64>) -> SqliteResult<Vec<Memory>> {
+        self.list_memories_paginated(100, 0, owner).await
     }
 
-    /// List memories with pagination
-    pub fn list_memories_paginated(&self, limit: i32, offset: i32) -> SqliteResult<Vec<Memory>> {
-        let conn = self.conn.lock().unwrap();
+    /// List memories with pagination, optionally only those of `owner`
+    pub async fn list_memories_paginated(&self, limit: i32, offset: i32, owner: Option<i64>) -> SqliteResult<Vec<Memory>> {
+        let conn = self.conn().await?;
 
         let mut stmt = conn.prepare(
             "SELECT id, memory_type, content, category, tags, importance, identity_id, session_id,
              source_channel_type, source_message_id, log_date, created_at, updated_at, expires_at,
              entity_type, entity_name, confidence, source_type, last_referenced_at,
-             superseded_by, superseded_at, valid_from, valid_until, temporal_type
-             FROM memories ORDER BY created_at DESC LIMIT ?1 OFFSET ?2",
+             superseded_by, superseded_at, valid_from, valid_until, temporal_type, user_id
+             FROM memories WHERE ?3 IS NULL OR user_id = ?3
+             ORDER BY created_at DESC LIMIT ?1 OFFSET ?2",
         )?;
 
         let memories = stmt
-            .query_map(rusqlite::params![limit, offset], |row| Self::row_to_memory(row))?
+            .query_map(rusqlite::params![limit, offset, owner], Self::row_to_memory)?
             .filter_map(|r| r.ok())
             .collect();
 
         Ok(memories)
     }
 
-    /// List memories with filters (Phase 5: UI)
-    pub fn list_memories_filtered(
+    /// List memories with filters (Phase 5: UI), optionally only those of `owner`
+    #[allow(clippy::too_many_arguments)]
+    pub async fn list_memories_filtered(
         &self,
         memory_type: Option<MemoryType>,
         identity_id: Option<&str>,
@@ -369,8 +384,9 @@ impl Database {
         include_superseded: bool,
         limit: i32,
         offset: i32,
+        owner: Option<i64>,
     ) -> SqliteResult<Vec<Memory>> {
-        let conn = self.conn.lock().unwrap();
+        let conn = self.conn().await?;
 
         let mut conditions = Vec::new();
         if memory_type.is_some() { conditions.push("memory_type = ?1".to_string()); }
@@ -382,6 +398,10 @@ impl Database {
             let idx = 1 + memory_type.is_some() as usize + identity_id.is_some() as usize;
             conditions.push(format!("importance >= ?{}", idx));
         }
+        if owner.is_some() {
+            let idx = 1 + memory_type.is_some() as usize + identity_id.is_some() as usize + min_importance.is_some() as usize;
+            conditions.push(format!("user_id = ?{}", idx));
+        }
         if !include_superseded {
             conditions.push("superseded_by IS NULL".to_string());
         }
@@ -392,14 +412,15 @@ impl Database {
             format!("WHERE {}", conditions.join(" AND "))
         };
 
-        let limit_idx = 1 + memory_type.is_some() as usize + identity_id.is_some() as usize + min_importance.is_some() as usize;
+        let limit_idx = 1 + memory_type.is_some() as usize + identity_id.is_some() as usize
+            + min_importance.is_some() as usize + owner.is_some() as usize;
         let offset_idx = limit_idx + 1;
 
         let sql = format!(
             "SELECT id, memory_type, content, category, tags, importance, identity_id, session_id,
              source_channel_type, source_message_id, log_date, created_at, updated_at, expires_at,
              entity_type, entity_name, confidence, source_type, last_referenced_at,
-             superseded_by, superseded_at, valid_from, valid_until, temporal_type
+             superseded_by, superseded_at, valid_from, valid_until, temporal_type, user_id
              FROM memories {} ORDER BY created_at DESC LIMIT ?{} OFFSET ?{}",
             where_clause, limit_idx, offset_idx
         );
@@ -411,6 +432,7 @@ impl Database {
         if let Some(mt) = memory_type { params.push(Box::new(mt.as_str().to_string())); }
         if let Some(iid) = identity_id { params.push(Box::new(iid.to_string())); }
         if let Some(mi) = min_importance { params.push(Box::new(mi)); }
+        if let Some(owner) = owner { params.push(Box::new(owner)); }
         params.push(Box::new(limit));
         params.push(Box::new(offset));
 
@@ -425,26 +447,32 @@ impl Database {
     }
 
     /// Delete a memory
-    pub fn delete_memory(&self, id: i64) -> SqliteResult<bool> {
-        let conn = self.conn.lock().unwrap();
+    pub async fn delete_memory(&self, id: i64) -> SqliteResult<bool> {
+        let conn = self.conn().await?;
         let rows_affected = conn.execute("DELETE FROM memories WHERE id = ?1", [id])?;
+        // Foreign keys aren't enforced, so the embedding has to go explicitly
+        conn.execute("DELETE FROM memory_embeddings WHERE memory_id = ?1", [id])?;
         Ok(rows_affected > 0)
     }
 
     /// Cleanup expired memories
-    pub fn cleanup_expired_memories(&self) -> SqliteResult<i64> {
-        let conn = self.conn.lock().unwrap();
+    pub async fn cleanup_expired_memories(&self) -> SqliteResult<i64> {
+        let conn = self.conn().await?;
         let now = Utc::now().to_rfc3339();
         let rows_affected = conn.execute(
             "DELETE FROM memories WHERE expires_at IS NOT NULL AND expires_at < ?1",
             [&now],
         )?;
+        conn.execute(
+            "DELETE FROM memory_embeddings WHERE memory_id NOT IN (SELECT id FROM memories)",
+            [],
+        )?;
         Ok(rows_affected as i64)
     }
 
     /// Update a memory's fields
-    pub fn update_memory(&self, id: i64, update: &UpdateMemoryRequest) -> SqliteResult<Option<Memory>> {
-        let conn = self.conn.lock().unwrap();
+    pub async fn update_memory(&self, id: i64, update: &UpdateMemoryRequest) -> SqliteResult<Option<Memory>> {
+        let conn = self.conn().await?;
         let now = Utc::now().to_rfc3339();
 
         // Build dynamic update query
@@ -478,19 +506,23 @@ impl Database {
 
         let params_ref: Vec<&dyn rusqlite::ToSql> = params.iter().map(|p| p.as_ref()).collect();
         conn.execute(&sql, params_ref.as_slice())?;
+        // New content needs a new embedding; the backfill picks it up
+        if update.content.is_some() {
+            conn.execute("DELETE FROM memory_embeddings WHERE memory_id = ?1", [id])?;
+        }
 
         drop(conn);
-        self.get_memory(id)
+        self.get_memory(id).await
     }
 
     /// Get a single memory by ID
-    pub fn get_memory(&self, id: i64) -> SqliteResult<Option<Memory>> {
-        let conn = self.conn.lock().unwrap();
+    pub async fn get_memory(&self, id: i64) -> SqliteResult<Option<Memory>> {
+        let conn = self.conn().await?;
         let mut stmt = conn.prepare(
             "SELECT id, memory_type, content, category, tags, importance, identity_id, session_id,
              source_channel_type, source_message_id, log_date, created_at, updated_at, expires_at,
              entity_type, entity_name, confidence, source_type, last_referenced_at,
-             superseded_by, superseded_at, valid_from, valid_until, temporal_type
+             superseded_by, superseded_at, valid_from, valid_until, temporal_type, user_id
              FROM memories WHERE id = ?1",
         )?;
 
@@ -499,8 +531,8 @@ impl Database {
     }
 
     /// Mark a memory as superseded by another (Phase 4: consolidation)
-    pub fn supersede_memory(&self, memory_id: i64, superseded_by: i64) -> SqliteResult<()> {
-        let conn = self.conn.lock().unwrap();
+    pub async fn supersede_memory(&self, memory_id: i64, superseded_by: i64) -> SqliteResult<()> {
+        let conn = self.conn().await?;
         let now = Utc::now().to_rfc3339();
         conn.execute(
             "UPDATE memories SET superseded_by = ?1, superseded_at = ?2, updated_at = ?2 WHERE id = ?3",
@@ -510,8 +542,8 @@ impl Database {
     }
 
     /// Update last_referenced_at for a memory
-    pub fn touch_memory(&self, id: i64) -> SqliteResult<()> {
-        let conn = self.conn.lock().unwrap();
+    pub async fn touch_memory(&self, id: i64) -> SqliteResult<()> {
+        let conn = self.conn().await?;
         let now = Utc::now().to_rfc3339();
         conn.execute(
             "UPDATE memories SET last_referenced_at = ?1 WHERE id = ?2",
@@ -521,41 +553,41 @@ impl Database {
     }
 
     /// Get memories by entity (Phase 2)
-    pub fn get_memories_by_entity(
+    pub async fn get_memories_by_entity(
         &self,
         entity_type: &str,
         entity_name: Option<&str>,
         identity_id: Option<&str>,
         limit: i32,
     ) -> SqliteResult<Vec<Memory>> {
-        let conn = self.conn.lock().unwrap();
+        let conn = self.conn().await?;
 
         let sql = if entity_name.is_some() && identity_id.is_some() {
             "SELECT id, memory_type, content, category, tags, importance, identity_id, session_id,
              source_channel_type, source_message_id, log_date, created_at, updated_at, expires_at,
              entity_type, entity_name, confidence, source_type, last_referenced_at,
-             superseded_by, superseded_at, valid_from, valid_until, temporal_type
+             superseded_by, superseded_at, valid_from, valid_until, temporal_type, user_id
              FROM memories WHERE entity_type = ?1 AND entity_name = ?2 AND identity_id = ?3
              AND superseded_by IS NULL AND (expires_at IS NULL OR expires_at > datetime('now')) ORDER BY importance DESC, created_at DESC LIMIT ?4"
         } else if entity_name.is_some() {
             "SELECT id, memory_type, content, category, tags, importance, identity_id, session_id,
              source_channel_type, source_message_id, log_date, created_at, updated_at, expires_at,
              entity_type, entity_name, confidence, source_type, last_referenced_at,
-             superseded_by, superseded_at, valid_from, valid_until, temporal_type
+             superseded_by, superseded_at, valid_from, valid_until, temporal_type, user_id
              FROM memories WHERE entity_type = ?1 AND entity_name = ?2
              AND superseded_by IS NULL AND (expires_at IS NULL OR expires_at > datetime('now')) ORDER BY importance DESC, created_at DESC LIMIT ?3"
         } else if identity_id.is_some() {
             "SELECT id, memory_type, content, category, tags, importance, identity_id, session_id,
              source_channel_type, source_message_id, log_date, created_at, updated_at, expires_at,
              entity_type, entity_name, confidence, source_type, last_referenced_at,
-             superseded_by, superseded_at, valid_from, valid_until, temporal_type
+             superseded_by, superseded_at, valid_from, valid_until, temporal_type, user_id
              FROM memories WHERE entity_type = ?1 AND identity_id = ?2
              AND superseded_by IS NULL AND (expires_at IS NULL OR expires_at > datetime('now')) ORDER BY importance DESC, created_at DESC LIMIT ?3"
         } else {
             "SELECT id, memory_type, content, category, tags, importance, identity_id, session_id,
              source_channel_type, source_message_id, log_date, created_at, updated_at, expires_at,
              entity_type, entity_name, confidence, source_type, last_referenced_at,
-             superseded_by, superseded_at, valid_from, valid_until, temporal_type
+             superseded_by, superseded_at, valid_from, valid_until, temporal_type, user_id
              FROM memories WHERE entity_type = ?1
              AND superseded_by IS NULL AND (expires_at IS NULL OR expires_at > datetime('now')) ORDER BY importance DESC, created_at DESC LIMIT ?2"
         };
@@ -581,13 +613,13 @@ impl Database {
     }
 
     /// Get temporally valid memories (Phase 7)
-    pub fn get_valid_memories(
+    pub async fn get_valid_memories(
         &self,
         identity_id: Option<&str>,
         memory_types: Option<&[MemoryType]>,
         limit: i32,
     ) -> SqliteResult<Vec<Memory>> {
-        let conn = self.conn.lock().unwrap();
+        let conn = self.conn().await?;
         let now = Utc::now().to_rfc3339();
 
         let type_filter = memory_types.map(|types| {
@@ -599,7 +631,7 @@ impl Database {
             "SELECT id, memory_type, content, category, tags, importance, identity_id, session_id,
              source_channel_type, source_message_id, log_date, created_at, updated_at, expires_at,
              entity_type, entity_name, confidence, source_type, last_referenced_at,
-             superseded_by, superseded_at, valid_from, valid_until, temporal_type
+             superseded_by, superseded_at, valid_from, valid_until, temporal_type, user_id
              FROM memories
              WHERE superseded_by IS NULL
              AND (valid_from IS NULL OR valid_from <= ?1)
@@ -623,20 +655,20 @@ impl Database {
     }
 
     /// Get cross-channel memories for an identity (Phase 6)
-    pub fn get_cross_channel_memories(
+    pub async fn get_cross_channel_memories(
         &self,
         identity_id: &str,
         exclude_channel_type: Option<&str>,
         limit: i32,
     ) -> SqliteResult<Vec<Memory>> {
-        let conn = self.conn.lock().unwrap();
+        let conn = self.conn().await?;
         let now = Utc::now().to_rfc3339();
 
         let sql = if exclude_channel_type.is_some() {
             "SELECT id, memory_type, content, category, tags, importance, identity_id, session_id,
              source_channel_type, source_message_id, log_date, created_at, updated_at, expires_at,
              entity_type, entity_name, confidence, source_type, last_referenced_at,
-             superseded_by, superseded_at, valid_from, valid_until, temporal_type
+             superseded_by, superseded_at, valid_from, valid_until, temporal_type, user_id
              FROM memories
              WHERE identity_id = ?1
              AND source_channel_type IS NOT NULL
@@ -650,7 +682,7 @@ impl Database {
             "SELECT id, memory_type, content, category, tags, importance, identity_id, session_id,
              source_channel_type, source_message_id, log_date, created_at, updated_at, expires_at,
              entity_type, entity_name, confidence, source_type, last_referenced_at,
-             superseded_by, superseded_at, valid_from, valid_until, temporal_type
+             superseded_by, superseded_at, valid_from, valid_until, temporal_type, user_id
              FROM memories
              WHERE identity_id = ?1
              AND superseded_by IS NULL
@@ -672,17 +704,23 @@ impl Database {
         Ok(memories)
     }
 
-    /// Get memory statistics (Phase 5: UI)
-    pub fn get_memory_stats(&self) -> SqliteResult<MemoryStats> {
-        let conn = self.conn.lock().unwrap();
+    /// Get memory statistics (Phase 5: UI), optionally only over the memories of `owner`
+    pub async fn get_memory_stats(&self, owner: Option<i64>) -> SqliteResult<MemoryStats> {
+        let conn = self.conn().await?;
         let now = Utc::now().to_rfc3339();
 
-        let total_count: i64 = conn.query_row("SELECT COUNT(*) FROM memories", [], |row| row.get(0))?;
+        let total_count: i64 = conn.query_row(
+            "SELECT COUNT(*) FROM memories WHERE ?1 IS NULL OR user_id = ?1",
+            [owner],
+            |row| row.get(0),
+        )?;
 
         // Count by type
         let mut by_type = HashMap::new();
-        let mut stmt = conn.prepare("SELECT memory_type, COUNT(*) FROM memories GROUP BY memory_type")?;
-        let rows = stmt.query_map([], |row| {
+        let mut stmt = conn.prepare(
+            "SELECT memory_type, COUNT(*) FROM memories WHERE ?1 IS NULL OR user_id = ?1 GROUP BY memory_type",
+        )?;
+        let rows = stmt.query_map([owner], |row| {
             let type_str: String = row.get(0)?;
             let count: i64 = row.get(1)?;
             Ok((type_str, count))
@@ -693,8 +731,11 @@ impl Database {
 
         // Count by identity
         let mut by_identity = HashMap::new();
-        let mut stmt = conn.prepare("SELECT COALESCE(identity_id, 'anonymous'), COUNT(*) FROM memories GROUP BY identity_id")?;
-        let rows = stmt.query_map([], |row| {
+        let mut stmt = conn.prepare(
+            "SELECT COALESCE(identity_id, 'anonymous'), COUNT(*) FROM memories
+             WHERE ?1 IS NULL OR user_id = ?1 GROUP BY identity_id",
+        )?;
+        let rows = stmt.query_map([owner], |row| {
             let id: String = row.get(0)?;
             let count: i64 = row.get(1)?;
             Ok((id, count))
@@ -704,32 +745,40 @@ impl Database {
         }
 
         let avg_importance: f64 = conn.query_row(
-            "SELECT COALESCE(AVG(importance), 0) FROM memories",
-            [],
+            "SELECT COALESCE(AVG(importance), 0) FROM memories WHERE ?1 IS NULL OR user_id = ?1",
+            [owner],
             |row| row.get(0),
         )?;
 
         let oldest: Option<String> = conn.query_row(
-            "SELECT MIN(created_at) FROM memories",
-            [],
+            "SELECT MIN(created_at) FROM memories WHERE ?1 IS NULL OR user_id = ?1",
+            [owner],
             |row| row.get(0),
         ).ok().flatten();
 
         let newest: Option<String> = conn.query_row(
-            "SELECT MAX(created_at) FROM memories",
-            [],
+            "SELECT MAX(created_at) FROM memories WHERE ?1 IS NULL OR user_id = ?1",
+            [owner],
             |row| row.get(0),
         ).ok().flatten();
 
         let superseded_count: i64 = conn.query_row(
-            "SELECT COUNT(*) FROM memories WHERE superseded_by IS NOT NULL",
-            [],
+            "SELECT COUNT(*) FROM memories WHERE superseded_by IS NOT NULL AND (?1 IS NULL OR user_id = ?1)",
+            [owner],
+            |row| row.get(0),
+        )?;
+
+        let embedded_count: i64 = conn.query_row(
+            "SELECT COUNT(*) FROM memory_embeddings e JOIN memories m ON e.memory_id = m.id
+             WHERE ?1 IS NULL OR m.user_id = ?1",
+            [owner],
             |row| row.get(0),
         )?;
 
         let temporal_active_count: i64 = conn.query_row(
-            "SELECT COUNT(*) FROM memories WHERE (valid_from IS NULL OR valid_from <= ?1) AND (valid_until IS NULL OR valid_until >= ?1)",
-            [&now],
+            "SELECT COUNT(*) FROM memories WHERE (valid_from IS NULL OR valid_from <= ?1) AND (valid_until IS NULL OR valid_until >= ?1)
+             AND (?2 IS NULL OR user_id = ?2)",
+            rusqlite::params![&now, owner],
             |row| row.get(0),
         )?;
 
@@ -742,16 +791,20 @@ impl Database {
             newest_memory_at: newest.and_then(|s| DateTime::parse_from_rfc3339(&s).ok().map(|dt| dt.with_timezone(&Utc))),
             superseded_count,
             temporal_active_count,
+            embedded_count,
         })
     }
 
-    /// Export memories as markdown (Phase 5: UI)
-    pub fn export_memories_markdown(&self, identity_id: Option<&str>) -> SqliteResult<String> {
-        let memories = if let Some(iid) = identity_id {
-            self.get_long_term_memories(Some(iid), Some(0), 1000)?
+    /// Export memories as markdown (Phase 5: UI), optionally only those of `owner`
+    pub async fn export_memories_markdown(&self, identity_id: Option<&str>, owner: Option<i64>) -> SqliteResult<String> {
+        let mut memories = if let Some(iid) = identity_id {
+            self.get_long_term_memories(Some(iid), Some(0), 1000).await?
         } else {
-            self.list_memories()?
+            self.list_memories(owner).await?
         };
+        if owner.is_some() {
+            memories.retain(|m| m.user_id == owner);
+        }
 
         let mut md = String::from("# Exported Memories\n\n");
         for memory in memories {
@@ -817,6 +870,7 @@ impl Database {
                 DateTime::parse_from_rfc3339(&s).ok().map(|dt| dt.with_timezone(&Utc))
             }),
             temporal_type: row.get(23)?,
+            user_id: row.get("user_id")?,
         })
     }
 }
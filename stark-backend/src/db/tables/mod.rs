@@ -1,8 +1,12 @@
 //! Database model modules - extends Database with domain-specific methods
 //!
 //! Each module adds `impl Database` blocks with methods for a specific table group.
+//! The shared-state groups (users, auth, api_tokens, api_keys, agent_settings, login_attempts) instead
+//! implement the `db::backend` store traits for `SqliteBackend`.
 
+mod users;          // users
 mod auth;           // auth_sessions, auth_challenges
+mod api_tokens;     // api_tokens
 mod api_keys;       // external_api_keys
 mod channels;       // external_channels
 mod agent_settings; // agent_settings
@@ -16,3 +20,21 @@ mod cron_jobs;      // cron_jobs, cron_job_runs
 mod heartbeat;      // heartbeat_configs
 mod gmail;          // gmail_configs
 mod agent_contexts; // agent_contexts (multi-agent orchestrator state)
+mod agent_jobs;     // agent_jobs (background CodeEngineer runs)
+mod usage;          // usage (tokens and cost per chat request)
+mod rate_limits;    // rate_limit_violations
+mod login_attempts; // login_attempts, login_failures (sign-in lockout and its audit log)
+mod token_cache;    // token_cache (on-chain ERC-20 metadata for token_lookup)
+mod wallets;        // wallets (sealed private keys and external signers)
+mod transactions;   // transactions (audit trail of on-chain actions)
+mod approvals;      // approvals (value-moving tool calls waiting on an operator)
+mod registers;      // registers (tool registers per chat session)
+mod skill_syncs;    // skill_syncs (git skill marketplace sync history)
+mod scheduled_tasks; // scheduled_tasks (recurring agent jobs)
+mod webhooks;       // webhooks (inbound triggers for agent jobs)
+mod archetype_profiles; // archetype_profiles (overrides of model archetype profiles)
+mod embeddings;     // embeddings (stored vectors for similarity search)
+mod prompt_templates; // prompt_templates (reusable prompts for schedules and webhooks)
+mod personas;       // personas (named system prompts picked per conversation)
+mod tool_invocations; // tool_invocations (audit log of every tool execution)
+pub(crate) mod maintenance; // VACUUM/ANALYZE and table stats
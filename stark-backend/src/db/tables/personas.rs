@@ -0,0 +1,130 @@
+//! Persona database operations (named system prompts picked per conversation)
+
+use chrono::Utc;
+use rusqlite::{OptionalExtension, Result as SqliteResult};
+
+use crate::models::{CreatePersonaRequest, Persona};
+use super::super::Database;
+
+const PERSONA_COLUMNS: &str = "id, name, description, system_prompt, tool_groups, model, created_at, updated_at";
+
+impl Database {
+    pub async fn create_persona(&self, request: &CreatePersonaRequest) -> SqliteResult<Persona> {
+        let conn = self.conn().await?;
+        let now = Utc::now().to_rfc3339();
+        let tool_groups = serde_json::to_string(&request.tool_groups).unwrap_or_else(|_| "[]".to_string());
+
+        conn.execute(
+            "INSERT INTO personas (name, description, system_prompt, tool_groups, model, created_at, updated_at)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?6)",
+            rusqlite::params![request.name, request.description, request.system_prompt, tool_groups, request.model, now],
+        )?;
+        drop(conn);
+
+        self.get_persona(&request.name)
+            .await?
+            .ok_or(rusqlite::Error::QueryReturnedNoRows)
+    }
+
+    pub async fn get_persona(&self, name: &str) -> SqliteResult<Option<Persona>> {
+        let conn = self.conn().await?;
+        conn.query_row(
+            &format!("SELECT {} FROM personas WHERE name = ?1", PERSONA_COLUMNS),
+            [name],
+            Self::map_persona_row,
+        )
+        .optional()
+    }
+
+    pub async fn list_personas(&self) -> SqliteResult<Vec<Persona>> {
+        let conn = self.conn().await?;
+        let mut stmt = conn.prepare(&format!("SELECT {} FROM personas ORDER BY name ASC", PERSONA_COLUMNS))?;
+        let personas = stmt
+            .query_map([], Self::map_persona_row)?
+            .collect::<SqliteResult<Vec<_>>>()?;
+        Ok(personas)
+    }
+
+    /// Write back every editable field of a persona (after applying an update request)
+    pub async fn save_persona(&self, persona: &Persona) -> SqliteResult<bool> {
+        let conn = self.conn().await?;
+        let tool_groups = serde_json::to_string(&persona.tool_groups).unwrap_or_else(|_| "[]".to_string());
+        let rows = conn.execute(
+            "UPDATE personas SET description = ?1, system_prompt = ?2, tool_groups = ?3, model = ?4, updated_at = ?5
+             WHERE name = ?6",
+            rusqlite::params![
+                persona.description,
+                persona.system_prompt,
+                tool_groups,
+                persona.model,
+                Utc::now().to_rfc3339(),
+                persona.name,
+            ],
+        )?;
+        Ok(rows > 0)
+    }
+
+    /// Delete a persona. Sessions that picked it go back to the default.
+    pub async fn delete_persona(&self, name: &str) -> SqliteResult<bool> {
+        let conn = self.conn().await?;
+        let deleted = conn.execute("DELETE FROM personas WHERE name = ?1", [name])? > 0;
+        if deleted {
+            conn.execute("UPDATE chat_sessions SET persona = NULL WHERE persona = ?1", [name])?;
+        }
+        Ok(deleted)
+    }
+
+    fn map_persona_row(row: &rusqlite::Row) -> SqliteResult<Persona> {
+        let tool_groups: String = row.get(4)?;
+        Ok(Persona {
+            id: row.get(0)?,
+            name: row.get(1)?,
+            description: row.get(2)?,
+            system_prompt: row.get(3)?,
+            tool_groups: serde_json::from_str(&tool_groups).unwrap_or_default(),
+            model: row.get(5)?,
+            created_at: row.get(6)?,
+            updated_at: row.get(7)?,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::db::Database;
+    use crate::models::{CreatePersonaRequest, SessionScope};
+
+    #[tokio::test]
+    async fn test_persona_crud_and_session_choice() {
+        let db = Database::new(":memory:").unwrap();
+        let request = CreatePersonaRequest {
+            name: "reviewer".to_string(),
+            description: "Terse code reviewer".to_string(),
+            system_prompt: "You review pull requests.".to_string(),
+            tool_groups: vec!["web".to_string(), "development".to_string()],
+            model: Some("claude-3-5-haiku-latest".to_string()),
+        };
+        let mut persona = db.create_persona(&request).await.unwrap();
+        assert_eq!(persona.tool_groups, vec!["web", "development"]);
+        assert!(db.create_persona(&request).await.is_err());
+
+        persona.model = None;
+        persona.tool_groups.clear();
+        assert!(db.save_persona(&persona).await.unwrap());
+        let stored = db.get_persona("reviewer").await.unwrap().unwrap();
+        assert!(stored.model.is_none() && stored.tool_groups.is_empty());
+        assert_eq!(db.list_personas().await.unwrap().len(), 1);
+
+        // A session keeps its persona across a reset, and loses it with the persona
+        let session = db.get_or_create_chat_session("web", 0, "chat-1", SessionScope::Dm, None, None).await.unwrap();
+        assert!(session.persona.is_none());
+        let session = db.set_session_persona(session.id, Some("reviewer")).await.unwrap().unwrap();
+        assert_eq!(session.persona.as_deref(), Some("reviewer"));
+        let session = db.reset_chat_session(session.id).await.unwrap();
+        assert_eq!(session.persona.as_deref(), Some("reviewer"));
+
+        assert!(db.delete_persona("reviewer").await.unwrap());
+        assert!(db.get_persona("reviewer").await.unwrap().is_none());
+        assert!(db.get_chat_session(session.id).await.unwrap().unwrap().persona.is_none());
+    }
+}
@@ -0,0 +1,118 @@
+//! Prompt template database operations (reusable prompts with variables)
+
+use chrono::Utc;
+use rusqlite::{OptionalExtension, Result as SqliteResult};
+
+use crate::models::{CreatePromptTemplateRequest, PromptTemplate};
+use super::super::Database;
+
+const PROMPT_TEMPLATE_COLUMNS: &str = "id, name, description, body, variables, created_at, updated_at";
+
+impl Database {
+    pub async fn create_prompt_template(&self, request: &CreatePromptTemplateRequest) -> SqliteResult<PromptTemplate> {
+        let conn = self.conn().await?;
+        let now = Utc::now().to_rfc3339();
+        let variables = serde_json::to_string(&request.variables).unwrap_or_else(|_| "{}".to_string());
+
+        conn.execute(
+            "INSERT INTO prompt_templates (name, description, body, variables, created_at, updated_at)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?5)",
+            rusqlite::params![request.name, request.description, request.body, variables, now],
+        )?;
+        drop(conn);
+
+        self.get_prompt_template(&request.name)
+            .await?
+            .ok_or(rusqlite::Error::QueryReturnedNoRows)
+    }
+
+    pub async fn get_prompt_template(&self, name: &str) -> SqliteResult<Option<PromptTemplate>> {
+        let conn = self.conn().await?;
+        conn.query_row(
+            &format!("SELECT {} FROM prompt_templates WHERE name = ?1", PROMPT_TEMPLATE_COLUMNS),
+            [name],
+            Self::map_prompt_template_row,
+        )
+        .optional()
+    }
+
+    pub async fn list_prompt_templates(&self) -> SqliteResult<Vec<PromptTemplate>> {
+        let conn = self.conn().await?;
+        let mut stmt = conn.prepare(&format!(
+            "SELECT {} FROM prompt_templates ORDER BY name ASC",
+            PROMPT_TEMPLATE_COLUMNS
+        ))?;
+        let templates = stmt
+            .query_map([], Self::map_prompt_template_row)?
+            .collect::<SqliteResult<Vec<_>>>()?;
+        Ok(templates)
+    }
+
+    /// Write back every editable field of a template (after applying an update request)
+    pub async fn save_prompt_template(&self, template: &PromptTemplate) -> SqliteResult<bool> {
+        let conn = self.conn().await?;
+        let variables = serde_json::to_string(&template.variables).unwrap_or_else(|_| "{}".to_string());
+        let rows = conn.execute(
+            "UPDATE prompt_templates SET description = ?1, body = ?2, variables = ?3, updated_at = ?4
+             WHERE name = ?5",
+            rusqlite::params![
+                template.description,
+                template.body,
+                variables,
+                Utc::now().to_rfc3339(),
+                template.name,
+            ],
+        )?;
+        Ok(rows > 0)
+    }
+
+    pub async fn delete_prompt_template(&self, name: &str) -> SqliteResult<bool> {
+        let conn = self.conn().await?;
+        Ok(conn.execute("DELETE FROM prompt_templates WHERE name = ?1", [name])? > 0)
+    }
+
+    fn map_prompt_template_row(row: &rusqlite::Row) -> SqliteResult<PromptTemplate> {
+        let variables: String = row.get(4)?;
+        Ok(PromptTemplate {
+            id: row.get(0)?,
+            name: row.get(1)?,
+            description: row.get(2)?,
+            body: row.get(3)?,
+            variables: serde_json::from_str(&variables).unwrap_or_default(),
+            created_at: row.get(5)?,
+            updated_at: row.get(6)?,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::db::Database;
+    use crate::models::CreatePromptTemplateRequest;
+    use std::collections::BTreeMap;
+
+    #[tokio::test]
+    async fn test_prompt_template_crud() {
+        let db = Database::new(":memory:").unwrap();
+        let request = CreatePromptTemplateRequest {
+            name: "release-notes".to_string(),
+            description: "Weekly release notes".to_string(),
+            body: "Write release notes for {{repo}} since {{since}}".to_string(),
+            variables: BTreeMap::from([("since".to_string(), "last week".to_string())]),
+        };
+        let mut template = db.create_prompt_template(&request).await.unwrap();
+        assert_eq!(template.variables["since"], "last week");
+        assert!(db.create_prompt_template(&request).await.is_err());
+
+        template.body = "Summarize {{repo}}".to_string();
+        template.variables.clear();
+        assert!(db.save_prompt_template(&template).await.unwrap());
+        let stored = db.get_prompt_template("release-notes").await.unwrap().unwrap();
+        assert_eq!(stored.body, "Summarize {{repo}}");
+        assert!(stored.variables.is_empty());
+
+        assert_eq!(db.list_prompt_templates().await.unwrap().len(), 1);
+        assert!(db.delete_prompt_template("release-notes").await.unwrap());
+        assert!(db.get_prompt_template("release-notes").await.unwrap().is_none());
+    }
+}
@@ -0,0 +1,51 @@
+//! Rate limit violation database operations
+
+use chrono::{DateTime, Utc};
+use rusqlite::Result as SqliteResult;
+
+use crate::models::RateLimitViolation;
+use super::super::Database;
+
+impl Database {
+    /// Record a request the rate limiter rejected
+    pub async fn record_rate_limit_violation(
+        &self,
+        key_type: &str,
+        key: &str,
+        method: &str,
+        path: &str,
+    ) -> SqliteResult<i64> {
+        let conn = self.conn().await?;
+        conn.execute(
+            "INSERT INTO rate_limit_violations (key_type, key, method, path, occurred_at)
+             VALUES (?1, ?2, ?3, ?4, ?5)",
+            rusqlite::params![key_type, key, method, path, Utc::now().to_rfc3339()],
+        )?;
+        Ok(conn.last_insert_rowid())
+    }
+
+    /// Most recent violations first
+    pub async fn list_rate_limit_violations(&self, limit: i64) -> SqliteResult<Vec<RateLimitViolation>> {
+        let conn = self.conn().await?;
+        let mut stmt = conn.prepare(
+            "SELECT id, key_type, key, method, path, occurred_at
+             FROM rate_limit_violations ORDER BY id DESC LIMIT ?1",
+        )?;
+        let violations = stmt
+            .query_map([limit], |row| {
+                let occurred_at: String = row.get(5)?;
+                Ok(RateLimitViolation {
+                    id: row.get(0)?,
+                    key_type: row.get(1)?,
+                    key: row.get(2)?,
+                    method: row.get(3)?,
+                    path: row.get(4)?,
+                    occurred_at: DateTime::parse_from_rfc3339(&occurred_at)
+                        .map(|dt| dt.with_timezone(&Utc))
+                        .unwrap_or_else(|_| Utc::now()),
+                })
+            })?
+            .collect::<SqliteResult<Vec<_>>>()?;
+        Ok(violations)
+    }
+}
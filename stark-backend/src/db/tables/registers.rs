@@ -0,0 +1,119 @@
+//! Tool registers persisted per chat session
+//!
+//! The in-memory `RegisterStore` of a dispatch writes through to this table and
+//! is loaded back from it, so values cached by one turn (a token_lookup result,
+//! a swap quote) are still there for the next, even after a restart.
+
+use chrono::Utc;
+use rusqlite::OptionalExtension;
+use serde_json::Value;
+
+use crate::db::DbResult;
+use crate::models::SessionRegister;
+use super::super::Database;
+
+const REGISTER_COLUMNS: &str = "session_id, key, value, source_tool, updated_at";
+
+impl Database {
+    /// Set a register for a session, replacing any previous value
+    pub async fn set_session_register(
+        &self,
+        session_id: i64,
+        key: &str,
+        value: &Value,
+        source_tool: &str,
+    ) -> DbResult<()> {
+        let conn = self.conn().await?;
+        conn.execute(
+            "INSERT INTO registers (session_id, key, value, source_tool, updated_at)
+             VALUES (?1, ?2, ?3, ?4, ?5)
+             ON CONFLICT(session_id, key) DO UPDATE SET
+                 value = excluded.value, source_tool = excluded.source_tool, updated_at = excluded.updated_at",
+            rusqlite::params![session_id, key, value.to_string(), source_tool, Utc::now().to_rfc3339()],
+        )?;
+        Ok(())
+    }
+
+    /// A session's registers, by key
+    pub async fn list_session_registers(&self, session_id: i64) -> DbResult<Vec<SessionRegister>> {
+        let conn = self.conn().await?;
+        let mut stmt = conn.prepare(&format!(
+            "SELECT {} FROM registers WHERE session_id = ?1 ORDER BY key",
+            REGISTER_COLUMNS
+        ))?;
+        let registers = stmt
+            .query_map([session_id], Self::row_to_session_register)?
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(registers)
+    }
+
+    pub async fn get_session_register(&self, session_id: i64, key: &str) -> DbResult<Option<SessionRegister>> {
+        let conn = self.conn().await?;
+        let register = conn
+            .query_row(
+                &format!("SELECT {} FROM registers WHERE session_id = ?1 AND key = ?2", REGISTER_COLUMNS),
+                rusqlite::params![session_id, key],
+                Self::row_to_session_register,
+            )
+            .optional()?;
+        Ok(register)
+    }
+
+    /// Remove one register of a session
+    pub async fn delete_session_register(&self, session_id: i64, key: &str) -> DbResult<bool> {
+        let conn = self.conn().await?;
+        let deleted = conn.execute(
+            "DELETE FROM registers WHERE session_id = ?1 AND key = ?2",
+            rusqlite::params![session_id, key],
+        )?;
+        Ok(deleted > 0)
+    }
+
+    /// Remove all registers of a session, returning how many there were
+    pub async fn clear_session_registers(&self, session_id: i64) -> DbResult<usize> {
+        let conn = self.conn().await?;
+        let deleted = conn.execute("DELETE FROM registers WHERE session_id = ?1", [session_id])?;
+        Ok(deleted)
+    }
+
+    fn row_to_session_register(row: &rusqlite::Row) -> rusqlite::Result<SessionRegister> {
+        let value: String = row.get(2)?;
+        Ok(SessionRegister {
+            session_id: row.get(0)?,
+            key: row.get(1)?,
+            value: serde_json::from_str(&value).unwrap_or(Value::String(value)),
+            source_tool: row.get(3)?,
+            updated_at: row.get(4)?,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::db::Database;
+    use serde_json::json;
+
+    #[tokio::test]
+    async fn test_session_registers() {
+        let db = Database::new(":memory:").unwrap();
+        db.set_session_register(1, "sell_token", &json!("0xabc"), "token_lookup").await.unwrap();
+        db.set_session_register(1, "buy_token", &json!("0xdef"), "token_lookup").await.unwrap();
+        db.set_session_register(2, "sell_token", &json!("0x123"), "token_lookup").await.unwrap();
+
+        // Setting again replaces the value
+        db.set_session_register(1, "sell_token", &json!({"address": "0xaaa"}), "register_set").await.unwrap();
+        let register = db.get_session_register(1, "sell_token").await.unwrap().unwrap();
+        assert_eq!(register.value, json!({"address": "0xaaa"}));
+        assert_eq!(register.source_tool, "register_set");
+
+        let keys: Vec<String> = db.list_session_registers(1).await.unwrap().into_iter().map(|r| r.key).collect();
+        assert_eq!(keys, vec!["buy_token", "sell_token"]);
+
+        assert!(db.delete_session_register(1, "buy_token").await.unwrap());
+        assert!(!db.delete_session_register(1, "buy_token").await.unwrap());
+        assert_eq!(db.clear_session_registers(1).await.unwrap(), 1);
+        assert!(db.list_session_registers(1).await.unwrap().is_empty());
+        // Other sessions are untouched
+        assert!(db.get_session_register(2, "sell_token").await.unwrap().is_some());
+    }
+}
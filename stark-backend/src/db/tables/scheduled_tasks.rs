@@ -0,0 +1,214 @@
+//! Scheduled task database operations (recurring CodeEngineer jobs)
+
+use chrono::{DateTime, Utc};
+use rusqlite::{OptionalExtension, Result as SqliteResult};
+
+use crate::models::{CreateScheduledTaskRequest, ScheduledTask};
+use super::super::Database;
+
+const SCHEDULED_TASK_COLUMNS: &str = "id, name, cron_expression, prompt, tools, workspace, max_iterations,
+    enabled, last_run_at, next_run_at, last_job_id, created_at, updated_at, user_id";
+
+impl Database {
+    /// Create a scheduled task owned by `user_id`. `next_run_at` is computed by the
+    /// caller from the cron expression; without a `workspace` the task gets its
+    /// id-based default.
+    pub async fn create_scheduled_task(
+        &self,
+        request: &CreateScheduledTaskRequest,
+        workspace: Option<&str>,
+        max_iterations: i64,
+        next_run_at: Option<&str>,
+        user_id: Option<i64>,
+    ) -> SqliteResult<ScheduledTask> {
+        let conn = self.conn().await?;
+        let now = Utc::now().to_rfc3339();
+        let tools = serde_json::to_string(&request.tools).unwrap_or_else(|_| "[]".to_string());
+
+        conn.execute(
+            "INSERT INTO scheduled_tasks (name, cron_expression, prompt, tools, workspace, max_iterations,
+                                          enabled, next_run_at, created_at, updated_at, user_id)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?9, ?10)",
+            rusqlite::params![
+                request.name,
+                request.cron_expression,
+                request.prompt,
+                tools,
+                workspace,
+                max_iterations,
+                request.enabled,
+                next_run_at,
+                now,
+                user_id,
+            ],
+        )?;
+        let id = conn.last_insert_rowid();
+        drop(conn);
+
+        self.get_scheduled_task(id).await?.ok_or(rusqlite::Error::QueryReturnedNoRows)
+    }
+
+    pub async fn get_scheduled_task(&self, id: i64) -> SqliteResult<Option<ScheduledTask>> {
+        let conn = self.conn().await?;
+        conn.query_row(
+            &format!("SELECT {} FROM scheduled_tasks WHERE id = ?1", SCHEDULED_TASK_COLUMNS),
+            [id],
+            Self::map_scheduled_task_row,
+        )
+        .optional()
+    }
+
+    /// Every scheduled task, or only those `owner` created
+    pub async fn list_scheduled_tasks(&self, owner: Option<i64>) -> SqliteResult<Vec<ScheduledTask>> {
+        let conn = self.conn().await?;
+        let mut stmt = conn.prepare(&format!(
+            "SELECT {} FROM scheduled_tasks WHERE (?1 IS NULL OR user_id = ?1) ORDER BY id ASC",
+            SCHEDULED_TASK_COLUMNS
+        ))?;
+        let tasks = stmt
+            .query_map([owner], Self::map_scheduled_task_row)?
+            .collect::<SqliteResult<Vec<_>>>()?;
+        Ok(tasks)
+    }
+
+    /// Enabled tasks whose next run time is at or before `now`, soonest first
+    pub async fn list_due_scheduled_tasks(&self, now: DateTime<Utc>) -> SqliteResult<Vec<ScheduledTask>> {
+        let mut due: Vec<ScheduledTask> = self
+            .list_scheduled_tasks(None)
+            .await?
+            .into_iter()
+            .filter(|task| task.is_due(now))
+            .collect();
+        due.sort_by(|a, b| a.next_run_at.cmp(&b.next_run_at));
+        Ok(due)
+    }
+
+    /// Write back every editable field of a task (after applying an update request)
+    pub async fn save_scheduled_task(&self, task: &ScheduledTask) -> SqliteResult<bool> {
+        let conn = self.conn().await?;
+        let tools = serde_json::to_string(&task.tools).unwrap_or_else(|_| "[]".to_string());
+        let rows = conn.execute(
+            "UPDATE scheduled_tasks
+             SET name = ?1, cron_expression = ?2, prompt = ?3, tools = ?4, workspace = ?5,
+                 max_iterations = ?6, enabled = ?7, next_run_at = ?8, updated_at = ?9
+             WHERE id = ?10",
+            rusqlite::params![
+                task.name,
+                task.cron_expression,
+                task.prompt,
+                tools,
+                task.workspace,
+                task.max_iterations,
+                task.enabled,
+                task.next_run_at,
+                Utc::now().to_rfc3339(),
+                task.id,
+            ],
+        )?;
+        Ok(rows > 0)
+    }
+
+    /// Record that a task fired. `job_id` is None when the run was skipped,
+    /// in which case the previous job id is kept.
+    pub async fn record_scheduled_task_run(
+        &self,
+        id: i64,
+        ran_at: &str,
+        next_run_at: Option<&str>,
+        job_id: Option<&str>,
+    ) -> SqliteResult<()> {
+        let conn = self.conn().await?;
+        conn.execute(
+            "UPDATE scheduled_tasks
+             SET last_run_at = ?1, next_run_at = ?2, last_job_id = COALESCE(?3, last_job_id)
+             WHERE id = ?4",
+            rusqlite::params![ran_at, next_run_at, job_id, id],
+        )?;
+        Ok(())
+    }
+
+    pub async fn delete_scheduled_task(&self, id: i64) -> SqliteResult<bool> {
+        let conn = self.conn().await?;
+        let rows = conn.execute("DELETE FROM scheduled_tasks WHERE id = ?1", [id])?;
+        Ok(rows > 0)
+    }
+
+    fn map_scheduled_task_row(row: &rusqlite::Row) -> SqliteResult<ScheduledTask> {
+        let id: i64 = row.get(0)?;
+        let tools: String = row.get(4)?;
+        let workspace: Option<String> = row.get(5)?;
+        Ok(ScheduledTask {
+            id,
+            name: row.get(1)?,
+            cron_expression: row.get(2)?,
+            prompt: row.get(3)?,
+            tools: serde_json::from_str(&tools).unwrap_or_default(),
+            workspace: workspace.unwrap_or_else(|| ScheduledTask::default_workspace(id)),
+            max_iterations: row.get(6)?,
+            enabled: row.get(7)?,
+            last_run_at: row.get(8)?,
+            next_run_at: row.get(9)?,
+            last_job_id: row.get(10)?,
+            created_at: row.get(11)?,
+            updated_at: row.get(12)?,
+            user_id: row.get(13)?,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_scheduled_task_crud_and_due() {
+        let db = Database::new(":memory:").unwrap();
+        let request = CreateScheduledTaskRequest {
+            name: "nightly tests".to_string(),
+            cron_expression: "0 3 * * *".to_string(),
+            prompt: "Run the test suite".to_string(),
+            tools: vec!["exec".to_string(), "read_file".to_string()],
+            workspace: None,
+            max_iterations: None,
+            enabled: true,
+        };
+        let task = db
+            .create_scheduled_task(&request, None, 10, Some("2026-01-01T03:00:00+00:00"), Some(2))
+            .await
+            .unwrap();
+        assert_eq!(task.workspace, format!("schedule-{}", task.id));
+        assert_eq!(task.user_id, Some(2));
+        let other = db.create_scheduled_task(&request, Some("user-3-nightly"), 10, None, Some(3)).await.unwrap();
+        assert_eq!(other.workspace, "user-3-nightly");
+        assert_eq!(db.list_scheduled_tasks(Some(2)).await.unwrap().len(), 1);
+        assert_eq!(db.list_scheduled_tasks(None).await.unwrap().len(), 2);
+        assert!(db.delete_scheduled_task(other.id).await.unwrap());
+        assert_eq!(task.tools, vec!["exec", "read_file"]);
+
+        let before = DateTime::parse_from_rfc3339("2026-01-01T02:59:00Z").unwrap().with_timezone(&Utc);
+        let after = DateTime::parse_from_rfc3339("2026-01-01T03:00:30Z").unwrap().with_timezone(&Utc);
+        assert!(db.list_due_scheduled_tasks(before).await.unwrap().is_empty());
+        assert_eq!(db.list_due_scheduled_tasks(after).await.unwrap().len(), 1);
+
+        db.record_scheduled_task_run(task.id, &after.to_rfc3339(), Some("2026-01-02T03:00:00+00:00"), Some("job-1"))
+            .await
+            .unwrap();
+        db.record_scheduled_task_run(task.id, &after.to_rfc3339(), Some("2026-01-03T03:00:00+00:00"), None)
+            .await
+            .unwrap();
+        let mut task = db.get_scheduled_task(task.id).await.unwrap().unwrap();
+        assert_eq!(task.last_job_id.as_deref(), Some("job-1"));
+        assert_eq!(task.next_run_at.as_deref(), Some("2026-01-03T03:00:00+00:00"));
+
+        task.enabled = false;
+        task.workspace = "shared".to_string();
+        assert!(db.save_scheduled_task(&task).await.unwrap());
+        let task = db.get_scheduled_task(task.id).await.unwrap().unwrap();
+        assert!(!task.enabled);
+        assert_eq!(task.workspace, "shared");
+
+        assert!(db.delete_scheduled_task(task.id).await.unwrap());
+        assert!(db.list_scheduled_tasks(None).await.unwrap().is_empty());
+        assert!(!db.delete_scheduled_task(task.id).await.unwrap());
+    }
+}
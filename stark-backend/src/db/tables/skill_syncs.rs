@@ -0,0 +1,106 @@
+//! Skill marketplace sync history
+//!
+//! Every run of `skills::git_sync` records the commit it pulled and the skill
+//! versions it imported, so an operator can tell which skill set each
+//! instance of a fleet is running.
+
+use chrono::Utc;
+
+use crate::db::DbResult;
+use crate::models::{SkillSync, SyncedSkill};
+use super::super::Database;
+
+const SKILL_SYNC_COLUMNS: &str = "id, repo_url, branch, commit_sha, skills, errors, synced_at";
+
+impl Database {
+    /// Record a sync, returning it with its id
+    pub async fn record_skill_sync(
+        &self,
+        repo_url: &str,
+        branch: &str,
+        commit_sha: Option<&str>,
+        skills: &[SyncedSkill],
+        errors: &[String],
+    ) -> DbResult<SkillSync> {
+        let conn = self.conn().await?;
+        let now = Utc::now().to_rfc3339();
+        conn.execute(
+            "INSERT INTO skill_syncs (repo_url, branch, commit_sha, skills, errors, synced_at)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+            rusqlite::params![
+                repo_url,
+                branch,
+                commit_sha,
+                serde_json::to_string(skills).unwrap_or_else(|_| "[]".to_string()),
+                serde_json::to_string(errors).unwrap_or_else(|_| "[]".to_string()),
+                now
+            ],
+        )?;
+        Ok(SkillSync {
+            id: conn.last_insert_rowid(),
+            repo_url: repo_url.to_string(),
+            branch: branch.to_string(),
+            commit_sha: commit_sha.map(str::to_string),
+            skills: skills.to_vec(),
+            errors: errors.to_vec(),
+            synced_at: now,
+        })
+    }
+
+    /// Most recent syncs, newest first
+    pub async fn list_skill_syncs(&self, limit: i64) -> DbResult<Vec<SkillSync>> {
+        let conn = self.conn().await?;
+        let mut stmt = conn.prepare(&format!(
+            "SELECT {} FROM skill_syncs ORDER BY id DESC LIMIT ?1",
+            SKILL_SYNC_COLUMNS
+        ))?;
+        let syncs = stmt
+            .query_map([limit.clamp(1, 200)], Self::row_to_skill_sync)?
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(syncs)
+    }
+
+    fn row_to_skill_sync(row: &rusqlite::Row) -> rusqlite::Result<SkillSync> {
+        let skills: String = row.get(4)?;
+        let errors: String = row.get(5)?;
+        Ok(SkillSync {
+            id: row.get(0)?,
+            repo_url: row.get(1)?,
+            branch: row.get(2)?,
+            commit_sha: row.get(3)?,
+            skills: serde_json::from_str(&skills).unwrap_or_default(),
+            errors: serde_json::from_str(&errors).unwrap_or_default(),
+            synced_at: row.get(6)?,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::db::Database;
+    use crate::models::SyncedSkill;
+
+    #[tokio::test]
+    async fn test_skill_syncs() {
+        let db = Database::new(":memory:").unwrap();
+        let skills = vec![SyncedSkill { name: "weather".to_string(), version: "1.2.0".to_string() }];
+        db.record_skill_sync("https://example.com/skills.git", "main", Some("abc123"), &skills, &[])
+            .await
+            .unwrap();
+        let failed = db
+            .record_skill_sync("https://example.com/skills.git", "main", None, &[], &["git fetch failed".to_string()])
+            .await
+            .unwrap();
+
+        let syncs = db.list_skill_syncs(10).await.unwrap();
+        assert_eq!(syncs.len(), 2);
+        // Newest first
+        assert_eq!(syncs[0].id, failed.id);
+        assert_eq!(syncs[0].commit_sha, None);
+        assert_eq!(syncs[0].errors, vec!["git fetch failed"]);
+        assert_eq!(syncs[1].commit_sha.as_deref(), Some("abc123"));
+        assert_eq!(syncs[1].skills, skills);
+
+        assert_eq!(db.list_skill_syncs(1).await.unwrap().len(), 1);
+    }
+}
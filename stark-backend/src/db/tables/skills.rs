@@ -12,18 +12,19 @@ impl Database {
     // ============================================
 
     /// Create a new skill in the database
-    pub fn create_skill(&self, skill: &DbSkill) -> SqliteResult<i64> {
-        let conn = self.conn.lock().unwrap();
+    pub async fn create_skill(&self, skill: &DbSkill) -> SqliteResult<i64> {
+        let conn = self.conn().await?;
         let now = Utc::now().to_rfc3339();
 
         let requires_tools_json = serde_json::to_string(&skill.requires_tools).unwrap_or_default();
         let requires_binaries_json = serde_json::to_string(&skill.requires_binaries).unwrap_or_default();
         let arguments_json = serde_json::to_string(&skill.arguments).unwrap_or_default();
         let tags_json = serde_json::to_string(&skill.tags).unwrap_or_default();
+        let triggers_json = serde_json::to_string(&skill.triggers).unwrap_or_default();
 
         conn.execute(
-            "INSERT INTO skills (name, description, body, version, author, homepage, metadata, enabled, requires_tools, requires_binaries, arguments, tags, created_at, updated_at)
-             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?13)
+            "INSERT INTO skills (name, description, body, version, author, homepage, metadata, enabled, requires_tools, requires_binaries, arguments, tags, created_at, updated_at, triggers)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?13, ?14)
              ON CONFLICT(name) DO UPDATE SET
                 description = excluded.description,
                 body = excluded.body,
@@ -35,6 +36,7 @@ impl Database {
                 requires_binaries = excluded.requires_binaries,
                 arguments = excluded.arguments,
                 tags = excluded.tags,
+                triggers = excluded.triggers,
                 updated_at = excluded.updated_at",
             rusqlite::params![
                 skill.name,
@@ -49,7 +51,8 @@ impl Database {
                 requires_binaries_json,
                 arguments_json,
                 tags_json,
-                now
+                now,
+                triggers_json
             ],
         )?;
 
@@ -57,10 +60,10 @@ impl Database {
     }
 
     /// Get a skill by name
-    pub fn get_skill(&self, name: &str) -> SqliteResult<Option<DbSkill>> {
-        let conn = self.conn.lock().unwrap();
+    pub async fn get_skill(&self, name: &str) -> SqliteResult<Option<DbSkill>> {
+        let conn = self.conn().await?;
         let mut stmt = conn.prepare(
-            "SELECT id, name, description, body, version, author, homepage, metadata, enabled, requires_tools, requires_binaries, arguments, tags, created_at, updated_at
+            "SELECT id, name, description, body, version, author, homepage, metadata, enabled, requires_tools, requires_binaries, arguments, tags, created_at, updated_at, triggers
              FROM skills WHERE name = ?1"
         )?;
 
@@ -72,10 +75,10 @@ impl Database {
     }
 
     /// Get a skill by ID
-    pub fn get_skill_by_id(&self, id: i64) -> SqliteResult<Option<DbSkill>> {
-        let conn = self.conn.lock().unwrap();
+    pub async fn get_skill_by_id(&self, id: i64) -> SqliteResult<Option<DbSkill>> {
+        let conn = self.conn().await?;
         let mut stmt = conn.prepare(
-            "SELECT id, name, description, body, version, author, homepage, metadata, enabled, requires_tools, requires_binaries, arguments, tags, created_at, updated_at
+            "SELECT id, name, description, body, version, author, homepage, metadata, enabled, requires_tools, requires_binaries, arguments, tags, created_at, updated_at, triggers
              FROM skills WHERE id = ?1"
         )?;
 
@@ -87,10 +90,10 @@ impl Database {
     }
 
     /// Get an enabled skill by name (more efficient than loading all skills)
-    pub fn get_enabled_skill_by_name(&self, name: &str) -> SqliteResult<Option<DbSkill>> {
-        let conn = self.conn.lock().unwrap();
+    pub async fn get_enabled_skill_by_name(&self, name: &str) -> SqliteResult<Option<DbSkill>> {
+        let conn = self.conn().await?;
         let mut stmt = conn.prepare(
-            "SELECT id, name, description, body, version, author, homepage, metadata, enabled, requires_tools, requires_binaries, arguments, tags, created_at, updated_at
+            "SELECT id, name, description, body, version, author, homepage, metadata, enabled, requires_tools, requires_binaries, arguments, tags, created_at, updated_at, triggers
              FROM skills WHERE name = ?1 AND enabled = 1 LIMIT 1"
         )?;
 
@@ -102,10 +105,10 @@ impl Database {
     }
 
     /// List all skills
-    pub fn list_skills(&self) -> SqliteResult<Vec<DbSkill>> {
-        let conn = self.conn.lock().unwrap();
+    pub async fn list_skills(&self) -> SqliteResult<Vec<DbSkill>> {
+        let conn = self.conn().await?;
         let mut stmt = conn.prepare(
-            "SELECT id, name, description, body, version, author, homepage, metadata, enabled, requires_tools, requires_binaries, arguments, tags, created_at, updated_at
+            "SELECT id, name, description, body, version, author, homepage, metadata, enabled, requires_tools, requires_binaries, arguments, tags, created_at, updated_at, triggers
              FROM skills ORDER BY name"
         )?;
 
@@ -118,10 +121,10 @@ impl Database {
     }
 
     /// List enabled skills
-    pub fn list_enabled_skills(&self) -> SqliteResult<Vec<DbSkill>> {
-        let conn = self.conn.lock().unwrap();
+    pub async fn list_enabled_skills(&self) -> SqliteResult<Vec<DbSkill>> {
+        let conn = self.conn().await?;
         let mut stmt = conn.prepare(
-            "SELECT id, name, description, body, version, author, homepage, metadata, enabled, requires_tools, requires_binaries, arguments, tags, created_at, updated_at
+            "SELECT id, name, description, body, version, author, homepage, metadata, enabled, requires_tools, requires_binaries, arguments, tags, created_at, updated_at, triggers
              FROM skills WHERE enabled = 1 ORDER BY name"
         )?;
 
@@ -134,8 +137,8 @@ impl Database {
     }
 
     /// Update skill enabled status
-    pub fn set_skill_enabled(&self, name: &str, enabled: bool) -> SqliteResult<bool> {
-        let conn = self.conn.lock().unwrap();
+    pub async fn set_skill_enabled(&self, name: &str, enabled: bool) -> SqliteResult<bool> {
+        let conn = self.conn().await?;
         let now = Utc::now().to_rfc3339();
         let rows_affected = conn.execute(
             "UPDATE skills SET enabled = ?1, updated_at = ?2 WHERE name = ?3",
@@ -145,8 +148,8 @@ impl Database {
     }
 
     /// Delete a skill (cascade deletes scripts)
-    pub fn delete_skill(&self, name: &str) -> SqliteResult<bool> {
-        let conn = self.conn.lock().unwrap();
+    pub async fn delete_skill(&self, name: &str) -> SqliteResult<bool> {
+        let conn = self.conn().await?;
         let rows_affected = conn.execute(
             "DELETE FROM skills WHERE name = ?1",
             [name],
@@ -159,6 +162,7 @@ impl Database {
         let requires_binaries_str: String = row.get(10)?;
         let arguments_str: String = row.get(11)?;
         let tags_str: String = row.get(12)?;
+        let triggers_str: String = row.get(15)?;
 
         Ok(DbSkill {
             id: row.get(0)?,
@@ -177,6 +181,7 @@ impl Database {
             tags: serde_json::from_str(&tags_str).unwrap_or_default(),
             created_at: row.get(13)?,
             updated_at: row.get(14)?,
+            triggers: serde_json::from_str(&triggers_str).unwrap_or_default(),
         })
     }
 
@@ -185,8 +190,8 @@ impl Database {
     // ============================================
 
     /// Create a skill script
-    pub fn create_skill_script(&self, script: &DbSkillScript) -> SqliteResult<i64> {
-        let conn = self.conn.lock().unwrap();
+    pub async fn create_skill_script(&self, script: &DbSkillScript) -> SqliteResult<i64> {
+        let conn = self.conn().await?;
         let now = Utc::now().to_rfc3339();
 
         conn.execute(
@@ -208,8 +213,8 @@ impl Database {
     }
 
     /// Get all scripts for a skill
-    pub fn get_skill_scripts(&self, skill_id: i64) -> SqliteResult<Vec<DbSkillScript>> {
-        let conn = self.conn.lock().unwrap();
+    pub async fn get_skill_scripts(&self, skill_id: i64) -> SqliteResult<Vec<DbSkillScript>> {
+        let conn = self.conn().await?;
         let mut stmt = conn.prepare(
             "SELECT id, skill_id, name, code, language, created_at
              FROM skill_scripts WHERE skill_id = ?1 ORDER BY name"
@@ -233,8 +238,8 @@ impl Database {
     }
 
     /// Get scripts for a skill by skill name
-    pub fn get_skill_scripts_by_name(&self, skill_name: &str) -> SqliteResult<Vec<DbSkillScript>> {
-        let conn = self.conn.lock().unwrap();
+    pub async fn get_skill_scripts_by_name(&self, skill_name: &str) -> SqliteResult<Vec<DbSkillScript>> {
+        let conn = self.conn().await?;
         let mut stmt = conn.prepare(
             "SELECT ss.id, ss.skill_id, ss.name, ss.code, ss.language, ss.created_at
              FROM skill_scripts ss
@@ -260,8 +265,8 @@ impl Database {
     }
 
     /// Delete all scripts for a skill
-    pub fn delete_skill_scripts(&self, skill_id: i64) -> SqliteResult<i64> {
-        let conn = self.conn.lock().unwrap();
+    pub async fn delete_skill_scripts(&self, skill_id: i64) -> SqliteResult<i64> {
+        let conn = self.conn().await?;
         let rows_affected = conn.execute(
             "DELETE FROM skill_scripts WHERE skill_id = ?1",
             [skill_id],
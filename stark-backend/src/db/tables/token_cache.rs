@@ -0,0 +1,103 @@
+//! Token metadata cache database operations
+
+use chrono::{DateTime, Utc};
+use rusqlite::{OptionalExtension, Result as SqliteResult};
+
+use crate::models::CachedToken;
+use super::super::Database;
+
+impl Database {
+    /// Store (or refresh) a token's on-chain metadata
+    pub async fn cache_token(
+        &self,
+        network: &str,
+        address: &str,
+        symbol: &str,
+        name: &str,
+        decimals: u8,
+    ) -> SqliteResult<()> {
+        let conn = self.conn().await?;
+        conn.execute(
+            "INSERT INTO token_cache (network, address, symbol, name, decimals, created_at)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6)
+             ON CONFLICT(network, address) DO UPDATE SET
+                symbol = excluded.symbol, name = excluded.name, decimals = excluded.decimals",
+            rusqlite::params![
+                network,
+                address.to_lowercase(),
+                symbol,
+                name,
+                decimals,
+                Utc::now().to_rfc3339()
+            ],
+        )?;
+        Ok(())
+    }
+
+    pub async fn get_cached_token_by_address(
+        &self,
+        network: &str,
+        address: &str,
+    ) -> SqliteResult<Option<CachedToken>> {
+        let conn = self.conn().await?;
+        conn.query_row(
+            "SELECT network, address, symbol, name, decimals, created_at
+             FROM token_cache WHERE network = ?1 AND address = ?2",
+            rusqlite::params![network, address.to_lowercase()],
+            Self::row_to_cached_token,
+        )
+        .optional()
+    }
+
+    /// Case-insensitive; the earliest cached token wins if several share a symbol
+    pub async fn get_cached_token_by_symbol(
+        &self,
+        network: &str,
+        symbol: &str,
+    ) -> SqliteResult<Option<CachedToken>> {
+        let conn = self.conn().await?;
+        conn.query_row(
+            "SELECT network, address, symbol, name, decimals, created_at
+             FROM token_cache WHERE network = ?1 AND symbol = ?2 COLLATE NOCASE
+             ORDER BY id LIMIT 1",
+            rusqlite::params![network, symbol],
+            Self::row_to_cached_token,
+        )
+        .optional()
+    }
+
+    fn row_to_cached_token(row: &rusqlite::Row) -> SqliteResult<CachedToken> {
+        let created_at: String = row.get(5)?;
+        Ok(CachedToken {
+            network: row.get(0)?,
+            address: row.get(1)?,
+            symbol: row.get(2)?,
+            name: row.get(3)?,
+            decimals: row.get(4)?,
+            created_at: DateTime::parse_from_rfc3339(&created_at)
+                .map(|dt| dt.with_timezone(&Utc))
+                .unwrap_or_else(|_| Utc::now()),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_token_cache() {
+        let db = Database::new(":memory:").unwrap();
+        let address = "0xAbC0000000000000000000000000000000000001";
+        db.cache_token("arbitrum", address, "MOON", "Moon Token", 18).await.unwrap();
+
+        let token = db.get_cached_token_by_symbol("arbitrum", "moon").await.unwrap().unwrap();
+        assert_eq!(token.address, address.to_lowercase());
+        assert_eq!(token.decimals, 18);
+        assert!(db.get_cached_token_by_symbol("base", "MOON").await.unwrap().is_none());
+
+        db.cache_token("arbitrum", &address.to_lowercase(), "MOON", "Moon", 9).await.unwrap();
+        let token = db.get_cached_token_by_address("arbitrum", address).await.unwrap().unwrap();
+        assert_eq!((token.name.as_str(), token.decimals), ("Moon", 9));
+    }
+}
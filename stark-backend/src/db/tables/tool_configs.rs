@@ -3,13 +3,13 @@
 use chrono::Utc;
 use rusqlite::Result as SqliteResult;
 
-use crate::tools::{ToolConfig, ToolExecution, ToolProfile};
+use crate::tools::{ToolConfig, ToolExecution, ToolGroup, ToolProfile};
 use super::super::Database;
 
 impl Database {
     /// Get global tool config (channel_id = NULL)
-    pub fn get_global_tool_config(&self) -> SqliteResult<Option<ToolConfig>> {
-        let conn = self.conn.lock().unwrap();
+    pub async fn get_global_tool_config(&self) -> SqliteResult<Option<ToolConfig>> {
+        let conn = self.conn().await?;
         let mut stmt = conn.prepare(
             "SELECT id, channel_id, profile, allow_list, deny_list, allowed_groups, denied_groups
              FROM tool_configs WHERE channel_id IS NULL"
@@ -39,8 +39,8 @@ impl Database {
     }
 
     /// Get tool config for a specific channel
-    pub fn get_channel_tool_config(&self, channel_id: i64) -> SqliteResult<Option<ToolConfig>> {
-        let conn = self.conn.lock().unwrap();
+    pub async fn get_channel_tool_config(&self, channel_id: i64) -> SqliteResult<Option<ToolConfig>> {
+        let conn = self.conn().await?;
         let mut stmt = conn.prepare(
             "SELECT id, channel_id, profile, allow_list, deny_list, allowed_groups, denied_groups
              FROM tool_configs WHERE channel_id = ?1"
@@ -70,19 +70,46 @@ impl Database {
     }
 
     /// Get effective tool config for a channel (falls back to global if channel config doesn't exist)
-    pub fn get_effective_tool_config(&self, channel_id: Option<i64>) -> SqliteResult<ToolConfig> {
+    pub async fn get_effective_tool_config(&self, channel_id: Option<i64>) -> SqliteResult<ToolConfig> {
         if let Some(cid) = channel_id {
-            if let Some(config) = self.get_channel_tool_config(cid)? {
+            if let Some(config) = self.get_channel_tool_config(cid).await? {
                 return Ok(config);
             }
         }
 
-        Ok(self.get_global_tool_config()?.unwrap_or_default())
+        Ok(self.get_global_tool_config().await?.unwrap_or_default())
+    }
+
+    /// Get the tool groups an operator has disabled
+    pub async fn get_disabled_tool_groups(&self) -> SqliteResult<Vec<ToolGroup>> {
+        let conn = self.conn().await?;
+        let mut stmt = conn.prepare("SELECT group_name FROM tool_group_settings WHERE enabled = 0")?;
+        let groups = stmt
+            .query_map([], |row| row.get::<_, String>(0))?
+            .filter_map(|r| r.ok())
+            .filter_map(|name| ToolGroup::from_str(&name))
+            .collect();
+        Ok(groups)
+    }
+
+    /// Persist whether a tool group is enabled
+    pub async fn set_tool_group_enabled(&self, group: ToolGroup, enabled: bool) -> SqliteResult<()> {
+        let conn = self.conn().await?;
+        let now = Utc::now().to_rfc3339();
+        conn.execute(
+            "INSERT INTO tool_group_settings (group_name, enabled, updated_at)
+             VALUES (?1, ?2, ?3)
+             ON CONFLICT(group_name) DO UPDATE SET
+                enabled = excluded.enabled,
+                updated_at = excluded.updated_at",
+            rusqlite::params![group.as_str(), enabled as i32, now],
+        )?;
+        Ok(())
     }
 
     /// Save tool config (upsert)
-    pub fn save_tool_config(&self, config: &ToolConfig) -> SqliteResult<i64> {
-        let conn = self.conn.lock().unwrap();
+    pub async fn save_tool_config(&self, config: &ToolConfig) -> SqliteResult<i64> {
+        let conn = self.conn().await?;
         let now = Utc::now().to_rfc3339();
 
         let profile_str = match &config.profile {
@@ -147,8 +174,8 @@ impl Database {
     }
 
     /// Log a tool execution
-    pub fn log_tool_execution(&self, execution: &ToolExecution) -> SqliteResult<i64> {
-        let conn = self.conn.lock().unwrap();
+    pub async fn log_tool_execution(&self, execution: &ToolExecution) -> SqliteResult<i64> {
+        let conn = self.conn().await?;
         let params_json = serde_json::to_string(&execution.parameters).unwrap_or_default();
 
         conn.execute(
@@ -170,13 +197,13 @@ impl Database {
     }
 
     /// Get tool execution history for a channel
-    pub fn get_tool_execution_history(
+    pub async fn get_tool_execution_history(
         &self,
         channel_id: i64,
         limit: i32,
         offset: i32,
     ) -> SqliteResult<Vec<ToolExecution>> {
-        let conn = self.conn.lock().unwrap();
+        let conn = self.conn().await?;
         let mut stmt = conn.prepare(
             "SELECT id, channel_id, tool_name, parameters, success, result, duration_ms, executed_at
              FROM tool_executions WHERE channel_id = ?1 ORDER BY executed_at DESC LIMIT ?2 OFFSET ?3"
@@ -203,12 +230,12 @@ impl Database {
     }
 
     /// Get all tool execution history
-    pub fn get_all_tool_execution_history(
+    pub async fn get_all_tool_execution_history(
         &self,
         limit: i32,
         offset: i32,
     ) -> SqliteResult<Vec<ToolExecution>> {
-        let conn = self.conn.lock().unwrap();
+        let conn = self.conn().await?;
         let mut stmt = conn.prepare(
             "SELECT id, channel_id, tool_name, parameters, success, result, duration_ms, executed_at
              FROM tool_executions ORDER BY executed_at DESC LIMIT ?1 OFFSET ?2"
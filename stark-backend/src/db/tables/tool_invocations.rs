@@ -0,0 +1,205 @@
+//! Tool invocation audit log
+//!
+//! One row per `ToolRegistry::execute` call whose context has a database,
+//! written when the call returns.
+
+use chrono::Utc;
+use rusqlite::OptionalExtension;
+
+use crate::db::DbResult;
+use crate::models::{NewToolInvocation, ToolInvocation, ToolInvocationQuery, ToolOutcome};
+use super::super::Database;
+
+const TOOL_INVOCATION_COLUMNS: &str = "id, tool_name, tool_group, arguments, outcome, result_summary, duration_ms, \
+     channel_id, channel_type, session_id, job_id, user_id, tool_call_id, request_id, created_at";
+
+impl Database {
+    /// Record a finished tool call, returning its id
+    pub async fn record_tool_invocation(&self, invocation: &NewToolInvocation) -> DbResult<i64> {
+        let conn = self.conn().await?;
+        conn.execute(
+            "INSERT INTO tool_invocations (tool_name, tool_group, arguments, outcome, result_summary, duration_ms,
+                 channel_id, channel_type, session_id, job_id, user_id, tool_call_id, request_id, created_at)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14)",
+            rusqlite::params![
+                invocation.tool_name,
+                invocation.tool_group,
+                invocation.arguments.to_string(),
+                invocation.outcome.as_str(),
+                invocation.result_summary,
+                invocation.duration_ms,
+                invocation.channel_id,
+                invocation.channel_type,
+                invocation.session_id,
+                invocation.job_id,
+                invocation.user_id,
+                invocation.tool_call_id,
+                invocation.request_id,
+                Utc::now().to_rfc3339(),
+            ],
+        )?;
+        Ok(conn.last_insert_rowid())
+    }
+
+    pub async fn get_tool_invocation(&self, id: i64) -> DbResult<Option<ToolInvocation>> {
+        let conn = self.conn().await?;
+        Ok(conn
+            .query_row(
+                &format!("SELECT {} FROM tool_invocations WHERE id = ?1", TOOL_INVOCATION_COLUMNS),
+                [id],
+                Self::row_to_tool_invocation,
+            )
+            .optional()?)
+    }
+
+    /// Newest first, filtered by `query`. Returns the page and the total matching count.
+    pub async fn list_tool_invocations(&self, query: &ToolInvocationQuery) -> DbResult<(Vec<ToolInvocation>, i64)> {
+        let mut conditions = Vec::new();
+        let mut params: Vec<Box<dyn rusqlite::ToSql>> = Vec::new();
+        if let Some(tool) = &query.tool {
+            params.push(Box::new(tool.clone()));
+            conditions.push(format!("tool_name = ?{}", params.len()));
+        }
+        if let Some(group) = &query.group {
+            params.push(Box::new(group.clone()));
+            conditions.push(format!("tool_group = ?{}", params.len()));
+        }
+        if let Some(outcome) = &query.outcome {
+            params.push(Box::new(outcome.clone()));
+            conditions.push(format!("outcome = ?{}", params.len()));
+        }
+        if let Some(session_id) = query.session_id {
+            params.push(Box::new(session_id));
+            conditions.push(format!("session_id = ?{}", params.len()));
+        }
+        if let Some(job_id) = &query.job_id {
+            params.push(Box::new(job_id.clone()));
+            conditions.push(format!("job_id = ?{}", params.len()));
+        }
+        if let Some(channel_id) = query.channel_id {
+            params.push(Box::new(channel_id));
+            conditions.push(format!("channel_id = ?{}", params.len()));
+        }
+        if let Some(request_id) = &query.request_id {
+            params.push(Box::new(request_id.clone()));
+            conditions.push(format!("request_id = ?{}", params.len()));
+        }
+        if let Some(since) = &query.since {
+            params.push(Box::new(since.clone()));
+            conditions.push(format!("created_at >= ?{}", params.len()));
+        }
+        if let Some(until) = &query.until {
+            params.push(Box::new(until.clone()));
+            conditions.push(format!("created_at < ?{}", params.len()));
+        }
+        let filter = if conditions.is_empty() {
+            String::new()
+        } else {
+            format!(" WHERE {}", conditions.join(" AND "))
+        };
+
+        let conn = self.conn().await?;
+        let refs: Vec<&dyn rusqlite::ToSql> = params.iter().map(|p| p.as_ref()).collect();
+        let total: i64 = conn.query_row(
+            &format!("SELECT COUNT(*) FROM tool_invocations{}", filter),
+            refs.as_slice(),
+            |row| row.get(0),
+        )?;
+
+        let limit = query.limit.unwrap_or(50).clamp(1, 200);
+        let offset = query.offset.unwrap_or(0).max(0);
+        let mut stmt = conn.prepare(&format!(
+            "SELECT {} FROM tool_invocations{} ORDER BY id DESC LIMIT {} OFFSET {}",
+            TOOL_INVOCATION_COLUMNS, filter, limit, offset
+        ))?;
+        let invocations = stmt
+            .query_map(refs.as_slice(), Self::row_to_tool_invocation)?
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok((invocations, total))
+    }
+
+    fn row_to_tool_invocation(row: &rusqlite::Row) -> rusqlite::Result<ToolInvocation> {
+        let arguments: String = row.get(3)?;
+        let outcome: String = row.get(4)?;
+        Ok(ToolInvocation {
+            id: row.get(0)?,
+            tool_name: row.get(1)?,
+            tool_group: row.get(2)?,
+            arguments: serde_json::from_str(&arguments).unwrap_or_default(),
+            outcome: ToolOutcome::from_str(&outcome).unwrap_or(ToolOutcome::Error),
+            result_summary: row.get(5)?,
+            duration_ms: row.get(6)?,
+            channel_id: row.get(7)?,
+            channel_type: row.get(8)?,
+            session_id: row.get(9)?,
+            job_id: row.get(10)?,
+            user_id: row.get(11)?,
+            tool_call_id: row.get(12)?,
+            request_id: row.get(13)?,
+            created_at: row.get(14)?,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::db::Database;
+    use crate::models::{NewToolInvocation, ToolInvocationQuery, ToolOutcome};
+    use chrono::{Duration, Utc};
+    use serde_json::json;
+
+    fn invocation(tool_name: &str, outcome: ToolOutcome, session_id: Option<i64>) -> NewToolInvocation {
+        NewToolInvocation {
+            tool_name: tool_name.to_string(),
+            tool_group: Some("exec".to_string()),
+            arguments: json!({ "command": "ls" }),
+            outcome,
+            result_summary: "done".to_string(),
+            duration_ms: 12,
+            channel_id: Some(1),
+            channel_type: Some("web".to_string()),
+            session_id,
+            job_id: None,
+            user_id: Some("admin".to_string()),
+            tool_call_id: Some("call_1".to_string()),
+            request_id: Some("req-1".to_string()),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_tool_invocations() {
+        let db = Database::new(":memory:").unwrap();
+        let first = db.record_tool_invocation(&invocation("exec", ToolOutcome::Success, Some(7))).await.unwrap();
+        db.record_tool_invocation(&invocation("exec", ToolOutcome::Denied, Some(8))).await.unwrap();
+        let mut job_call = invocation("write_file", ToolOutcome::Error, None);
+        job_call.job_id = Some("job-1".to_string());
+        db.record_tool_invocation(&job_call).await.unwrap();
+
+        let recorded = db.get_tool_invocation(first).await.unwrap().unwrap();
+        assert_eq!(recorded.arguments, json!({ "command": "ls" }));
+        assert_eq!(recorded.outcome, ToolOutcome::Success);
+        assert_eq!(recorded.request_id.as_deref(), Some("req-1"));
+        assert!(db.get_tool_invocation(999).await.unwrap().is_none());
+
+        let (all, total) = db.list_tool_invocations(&ToolInvocationQuery::default()).await.unwrap();
+        assert_eq!(total, 3);
+        assert_eq!(all[0].tool_name, "write_file");
+
+        let exec = ToolInvocationQuery { tool: Some("exec".to_string()), ..Default::default() };
+        assert_eq!(db.list_tool_invocations(&exec).await.unwrap().1, 2);
+        let denied = ToolInvocationQuery { outcome: Some("denied".to_string()), ..Default::default() };
+        let (rows, _) = db.list_tool_invocations(&denied).await.unwrap();
+        assert_eq!(rows[0].session_id, Some(8));
+        let job = ToolInvocationQuery { job_id: Some("job-1".to_string()), ..Default::default() };
+        assert_eq!(db.list_tool_invocations(&job).await.unwrap().1, 1);
+
+        let future = ToolInvocationQuery {
+            since: Some((Utc::now() + Duration::hours(1)).to_rfc3339()),
+            ..Default::default()
+        };
+        assert_eq!(db.list_tool_invocations(&future).await.unwrap().1, 0);
+        let page = ToolInvocationQuery { limit: Some(1), offset: Some(1), ..Default::default() };
+        let (rows, total) = db.list_tool_invocations(&page).await.unwrap();
+        assert_eq!((rows.len(), total, rows[0].outcome), (1, 3, ToolOutcome::Denied));
+    }
+}
@@ -0,0 +1,249 @@
+//! On-chain transaction audit trail
+//!
+//! Rows are written when the bot broadcasts a transaction or pays over x402,
+//! and updated once the receipt arrives.
+
+use chrono::{DateTime, Utc};
+use rusqlite::OptionalExtension;
+
+use crate::db::DbResult;
+use crate::models::{NewTransaction, Transaction, TransactionKind, TransactionQuery, TransactionStatus};
+use super::super::Database;
+
+const TRANSACTION_COLUMNS: &str = "id, kind, status, network, tx_hash, from_address, to_address, value, gas_used, \
+     block_number, channel_id, session_id, tool_name, tool_call_id, details, created_at, updated_at, value_usd";
+
+impl Database {
+    /// Record an on-chain action, returning its id
+    pub async fn record_transaction(&self, tx: &NewTransaction) -> DbResult<i64> {
+        let conn = self.conn().await?;
+        let now = Utc::now().to_rfc3339();
+        conn.execute(
+            "INSERT INTO transactions (kind, status, network, tx_hash, from_address, to_address, value,
+                 channel_id, session_id, tool_name, tool_call_id, details, created_at, updated_at, value_usd)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?13, ?14)",
+            rusqlite::params![
+                tx.kind.as_str(),
+                tx.status.as_str(),
+                tx.network,
+                tx.tx_hash,
+                tx.from_address,
+                tx.to_address,
+                tx.value,
+                tx.channel_id,
+                tx.session_id,
+                tx.tool_name,
+                tx.tool_call_id,
+                tx.details.as_ref().map(|d| d.to_string()),
+                now,
+                tx.value_usd
+            ],
+        )?;
+        Ok(conn.last_insert_rowid())
+    }
+
+    /// Set the outcome of a recorded transaction once its receipt is known
+    pub async fn update_transaction_status(
+        &self,
+        id: i64,
+        status: TransactionStatus,
+        gas_used: Option<&str>,
+        block_number: Option<i64>,
+    ) -> DbResult<bool> {
+        let conn = self.conn().await?;
+        let updated = conn.execute(
+            "UPDATE transactions SET status = ?1, gas_used = COALESCE(?2, gas_used),
+                 block_number = COALESCE(?3, block_number), updated_at = ?4
+             WHERE id = ?5",
+            rusqlite::params![status.as_str(), gas_used, block_number, Utc::now().to_rfc3339(), id],
+        )?;
+        Ok(updated > 0)
+    }
+
+    pub async fn get_transaction(&self, id: i64) -> DbResult<Option<Transaction>> {
+        let conn = self.conn().await?;
+        Ok(conn
+            .query_row(
+                &format!("SELECT {} FROM transactions WHERE id = ?1", TRANSACTION_COLUMNS),
+                [id],
+                Self::row_to_transaction,
+            )
+            .optional()?)
+    }
+
+    /// Newest first, filtered by `query` and, with `owner`, to that user's
+    /// chat sessions. Returns the page and the total matching count.
+    pub async fn list_transactions(
+        &self,
+        query: &TransactionQuery,
+        owner: Option<i64>,
+    ) -> DbResult<(Vec<Transaction>, i64)> {
+        let mut conditions = Vec::new();
+        let mut params: Vec<Box<dyn rusqlite::ToSql>> = Vec::new();
+        if let Some(owner) = owner {
+            params.push(Box::new(owner));
+            conditions.push(format!(
+                "session_id IN (SELECT id FROM chat_sessions WHERE user_id = ?{})",
+                params.len()
+            ));
+        }
+        if let Some(kind) = &query.kind {
+            params.push(Box::new(kind.clone()));
+            conditions.push(format!("kind = ?{}", params.len()));
+        }
+        if let Some(status) = &query.status {
+            params.push(Box::new(status.clone()));
+            conditions.push(format!("status = ?{}", params.len()));
+        }
+        if let Some(network) = &query.network {
+            params.push(Box::new(network.clone()));
+            conditions.push(format!("network = ?{}", params.len()));
+        }
+        if let Some(session_id) = query.session_id {
+            params.push(Box::new(session_id));
+            conditions.push(format!("session_id = ?{}", params.len()));
+        }
+        if let Some(channel_id) = query.channel_id {
+            params.push(Box::new(channel_id));
+            conditions.push(format!("channel_id = ?{}", params.len()));
+        }
+        let filter = if conditions.is_empty() {
+            String::new()
+        } else {
+            format!(" WHERE {}", conditions.join(" AND "))
+        };
+
+        let conn = self.conn().await?;
+        let refs: Vec<&dyn rusqlite::ToSql> = params.iter().map(|p| p.as_ref()).collect();
+        let total: i64 = conn.query_row(
+            &format!("SELECT COUNT(*) FROM transactions{}", filter),
+            refs.as_slice(),
+            |row| row.get(0),
+        )?;
+
+        let limit = query.limit.unwrap_or(50).clamp(1, 200);
+        let offset = query.offset.unwrap_or(0).max(0);
+        let mut stmt = conn.prepare(&format!(
+            "SELECT {} FROM transactions{} ORDER BY id DESC LIMIT {} OFFSET {}",
+            TRANSACTION_COLUMNS, filter, limit, offset
+        ))?;
+        let transactions = stmt
+            .query_map(refs.as_slice(), Self::row_to_transaction)?
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok((transactions, total))
+    }
+
+    /// USD that tools spent since `since`, from transactions that were not
+    /// failed or reverted. Payments the AI provider charged are not counted.
+    pub async fn spent_usd_since(&self, since: DateTime<Utc>) -> DbResult<f64> {
+        let conn = self.conn().await?;
+        Ok(conn.query_row(
+            "SELECT COALESCE(SUM(value_usd), 0) FROM transactions
+             WHERE tool_name IS NOT NULL AND status NOT IN ('failed', 'reverted') AND created_at >= ?1",
+            [since.to_rfc3339()],
+            |row| row.get(0),
+        )?)
+    }
+
+    fn row_to_transaction(row: &rusqlite::Row) -> rusqlite::Result<Transaction> {
+        let kind: String = row.get(1)?;
+        let status: String = row.get(2)?;
+        let details: Option<String> = row.get(14)?;
+        Ok(Transaction {
+            id: row.get(0)?,
+            kind: TransactionKind::from_str(&kind).unwrap_or(TransactionKind::ContractCall),
+            status: TransactionStatus::from_str(&status).unwrap_or(TransactionStatus::Pending),
+            network: row.get(3)?,
+            tx_hash: row.get(4)?,
+            from_address: row.get(5)?,
+            to_address: row.get(6)?,
+            value: row.get(7)?,
+            value_usd: row.get(17)?,
+            gas_used: row.get(8)?,
+            block_number: row.get(9)?,
+            channel_id: row.get(10)?,
+            session_id: row.get(11)?,
+            tool_name: row.get(12)?,
+            tool_call_id: row.get(13)?,
+            details: details.and_then(|d| serde_json::from_str(&d).ok()),
+            created_at: row.get(15)?,
+            updated_at: row.get(16)?,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::db::Database;
+    use crate::models::{NewTransaction, SessionScope, TransactionKind, TransactionQuery, TransactionStatus, UserRole};
+    use chrono::{Duration, Utc};
+    use serde_json::json;
+
+    #[tokio::test]
+    async fn test_transactions() {
+        let db = Database::new(":memory:").unwrap();
+
+        let mut swap = NewTransaction::new(TransactionKind::Swap, TransactionStatus::Pending, "base");
+        swap.tx_hash = Some("0xabc".to_string());
+        swap.session_id = Some(7);
+        swap.tool_name = Some("swap".to_string());
+        swap.tool_call_id = Some("call_1".to_string());
+        swap.details = Some(json!({ "buy_amount": "100" }));
+        swap.value_usd = Some(250.0);
+        let swap_id = db.record_transaction(&swap).await.unwrap();
+
+        let mut payment = NewTransaction::new(TransactionKind::X402Payment, TransactionStatus::Confirmed, "base");
+        payment.value = "1000".to_string();
+        payment.session_id = Some(8);
+        payment.value_usd = Some(0.001);
+        db.record_transaction(&payment).await.unwrap();
+
+        assert!(db
+            .update_transaction_status(swap_id, TransactionStatus::Confirmed, Some("21000"), Some(123))
+            .await
+            .unwrap());
+        assert!(!db.update_transaction_status(999, TransactionStatus::Failed, None, None).await.unwrap());
+
+        let recorded = db.get_transaction(swap_id).await.unwrap().unwrap();
+        assert_eq!(recorded.status, TransactionStatus::Confirmed);
+        assert_eq!(recorded.gas_used.as_deref(), Some("21000"));
+        assert_eq!(recorded.block_number, Some(123));
+        assert_eq!(recorded.tool_call_id.as_deref(), Some("call_1"));
+        assert_eq!(recorded.details, Some(json!({ "buy_amount": "100" })));
+
+        let (all, total) = db.list_transactions(&TransactionQuery::default(), None).await.unwrap();
+        assert_eq!(all[1].value_usd, Some(250.0));
+        assert_eq!(total, 2);
+        assert_eq!(all[0].kind, TransactionKind::X402Payment);
+
+        let query = TransactionQuery {
+            kind: Some("swap".to_string()),
+            session_id: Some(7),
+            ..Default::default()
+        };
+        let (swaps, total) = db.list_transactions(&query, None).await.unwrap();
+        assert_eq!((swaps.len(), total), (1, 1));
+        assert_eq!(swaps[0].id, swap_id);
+
+        let query = TransactionQuery { limit: Some(1), offset: Some(1), ..Default::default() };
+        let (page, total) = db.list_transactions(&query, None).await.unwrap();
+        assert_eq!((page.len(), total), (1, 2));
+        assert_eq!(page[0].id, swap_id);
+
+        let me = db.create_user("0xaaa", None, UserRole::Member).await.unwrap();
+        let session = db.get_or_create_chat_session("web", 0, "web-user-3", SessionScope::Dm, None, Some(me.id)).await.unwrap();
+        let mut transfer = NewTransaction::new(TransactionKind::Transfer, TransactionStatus::Pending, "base");
+        transfer.session_id = Some(session.id);
+        let transfer_id = db.record_transaction(&transfer).await.unwrap();
+        let (owned, total) = db.list_transactions(&TransactionQuery::default(), Some(me.id)).await.unwrap();
+        assert_eq!((owned.len(), total), (1, 1));
+        assert_eq!(owned[0].id, transfer_id);
+        assert_eq!(db.list_transactions(&TransactionQuery::default(), Some(me.id + 1)).await.unwrap().1, 0);
+
+        // Only tool spend counts; the AI provider's payment has no tool_name
+        let hour_ago = Utc::now() - Duration::hours(1);
+        assert_eq!(db.spent_usd_since(hour_ago).await.unwrap(), 250.0);
+        db.update_transaction_status(swap_id, TransactionStatus::Reverted, None, None).await.unwrap();
+        assert_eq!(db.spent_usd_since(hour_ago).await.unwrap(), 0.0);
+    }
+}
@@ -0,0 +1,251 @@
+//! Token usage database operations (per-request spend, aggregated per session and per day)
+
+use chrono::{Duration, Utc};
+use rusqlite::Result as SqliteResult;
+
+use crate::ai::budget::BudgetUsage;
+use crate::models::{DailyUsage, SessionUsage, UsageTotals};
+use super::super::Database;
+
+/// Aggregate columns shared by the usage queries (indexes 0-5)
+const USAGE_TOTAL_COLUMNS: &str = "COUNT(*), COALESCE(SUM(input_tokens), 0),
+    COALESCE(SUM(output_tokens), 0), COALESCE(SUM(cost_usd), 0.0),
+    COALESCE(SUM(cache_read_tokens), 0), COALESCE(SUM(cache_write_tokens), 0)";
+
+/// Limits a usage query to the sessions of the user bound to `?2`, or to none if it's NULL
+const USAGE_OWNER_FILTER: &str =
+    "(?2 IS NULL OR session_id IN (SELECT id FROM chat_sessions WHERE user_id = ?2))";
+
+impl Database {
+    /// Record what one chat request spent. `api_key_id` is the user's own
+    /// provider key it ran on, if not the instance's.
+    pub async fn record_usage(
+        &self,
+        session_id: Option<i64>,
+        channel_id: i64,
+        model: &str,
+        api_key_id: Option<i64>,
+        usage: &BudgetUsage,
+    ) -> SqliteResult<i64> {
+        let conn = self.conn().await?;
+        conn.execute(
+            "INSERT INTO usage (session_id, channel_id, model, input_tokens, output_tokens, cost_usd,
+                                provider_calls, estimated, created_at, cache_read_tokens, cache_write_tokens, api_key_id)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12)",
+            rusqlite::params![
+                session_id,
+                channel_id,
+                model,
+                usage.input_tokens as i64,
+                usage.output_tokens as i64,
+                usage.cost_usd,
+                usage.provider_calls,
+                usage.estimated as i32,
+                Utc::now().to_rfc3339(),
+                usage.cache_read_tokens as i64,
+                usage.cache_write_tokens as i64,
+                api_key_id,
+            ],
+        )?;
+        Ok(conn.last_insert_rowid())
+    }
+
+    /// Total spend of one chat session
+    pub async fn get_session_usage(&self, session_id: i64) -> SqliteResult<UsageTotals> {
+        let conn = self.conn().await?;
+        conn.query_row(
+            &format!("SELECT {} FROM usage WHERE session_id = ?1", USAGE_TOTAL_COLUMNS),
+            [session_id],
+            Self::map_usage_totals,
+        )
+    }
+
+    /// Total spend over the last `days` days, of everyone or only `owner`'s sessions
+    pub async fn get_usage_totals(&self, days: i64, owner: Option<i64>) -> SqliteResult<UsageTotals> {
+        let conn = self.conn().await?;
+        conn.query_row(
+            &format!(
+                "SELECT {} FROM usage WHERE created_at >= ?1 AND {}",
+                USAGE_TOTAL_COLUMNS, USAGE_OWNER_FILTER
+            ),
+            rusqlite::params![Self::usage_cutoff(days), owner],
+            Self::map_usage_totals,
+        )
+    }
+
+    /// Spend on one of a user's own provider keys over the last `days` days
+    pub async fn get_api_key_usage(&self, api_key_id: i64, days: i64) -> SqliteResult<UsageTotals> {
+        let conn = self.conn().await?;
+        conn.query_row(
+            &format!("SELECT {} FROM usage WHERE api_key_id = ?1 AND created_at >= ?2", USAGE_TOTAL_COLUMNS),
+            rusqlite::params![api_key_id, Self::usage_cutoff(days)],
+            Self::map_usage_totals,
+        )
+    }
+
+    /// Spend per session over the last `days` days, most recently active first
+    pub async fn list_usage_by_session(&self, days: i64, limit: i64, owner: Option<i64>) -> SqliteResult<Vec<SessionUsage>> {
+        let conn = self.conn().await?;
+        let mut stmt = conn.prepare(&format!(
+            "SELECT {}, session_id, MAX(created_at) FROM usage
+             WHERE session_id IS NOT NULL AND created_at >= ?1 AND {}
+             GROUP BY session_id ORDER BY MAX(created_at) DESC, MAX(id) DESC LIMIT ?3",
+            USAGE_TOTAL_COLUMNS, USAGE_OWNER_FILTER
+        ))?;
+
+        let rows = stmt.query_map(rusqlite::params![Self::usage_cutoff(days), owner, limit], |row| {
+            Ok(SessionUsage {
+                totals: Self::map_usage_totals(row)?,
+                session_id: row.get(6)?,
+                last_used_at: row.get(7)?,
+            })
+        })?;
+        rows.collect()
+    }
+
+    /// Spend per UTC day over the last `days` days, newest first
+    pub async fn list_usage_by_day(&self, days: i64, owner: Option<i64>) -> SqliteResult<Vec<DailyUsage>> {
+        let conn = self.conn().await?;
+        let mut stmt = conn.prepare(&format!(
+            "SELECT {}, substr(created_at, 1, 10) AS day FROM usage
+             WHERE created_at >= ?1 AND {}
+             GROUP BY day ORDER BY day DESC",
+            USAGE_TOTAL_COLUMNS, USAGE_OWNER_FILTER
+        ))?;
+
+        let rows = stmt.query_map(rusqlite::params![Self::usage_cutoff(days), owner], |row| {
+            Ok(DailyUsage {
+                totals: Self::map_usage_totals(row)?,
+                day: row.get(6)?,
+            })
+        })?;
+        rows.collect()
+    }
+
+    /// Start of the reporting window: midnight UTC, `days - 1` days ago
+    fn usage_cutoff(days: i64) -> String {
+        let start = Utc::now().date_naive() - Duration::days(days.max(1) - 1);
+        start.and_hms_opt(0, 0, 0).unwrap().and_utc().to_rfc3339()
+    }
+
+    fn map_usage_totals(row: &rusqlite::Row) -> SqliteResult<UsageTotals> {
+        let input_tokens: i64 = row.get(1)?;
+        let output_tokens: i64 = row.get(2)?;
+        let cache_read_tokens: i64 = row.get(4)?;
+        let cache_write_tokens: i64 = row.get(5)?;
+        Ok(UsageTotals {
+            requests: row.get(0)?,
+            input_tokens,
+            output_tokens,
+            cache_read_tokens,
+            cache_write_tokens,
+            total_tokens: input_tokens + output_tokens + cache_read_tokens + cache_write_tokens,
+            cost_usd: row.get(3)?,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::{SessionScope, UserRole};
+
+    fn usage(input_tokens: u64, output_tokens: u64, cost_usd: f64) -> BudgetUsage {
+        BudgetUsage {
+            input_tokens,
+            output_tokens,
+            total_tokens: input_tokens + output_tokens,
+            cost_usd,
+            provider_calls: 1,
+            ..Default::default()
+        }
+    }
+
+    #[tokio::test]
+    async fn test_usage_aggregates_per_session_and_day() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let db = Database::new(dir.path().join("stark.db").to_str().unwrap()).unwrap();
+
+        db.record_usage(Some(1), 10, "claude", None, &usage(1_000, 200, 0.006)).await.unwrap();
+        db.record_usage(Some(1), 10, "claude", None, &usage(2_000, 300, 0.0105)).await.unwrap();
+        db.record_usage(Some(2), 11, "kimi", None, &usage(500, 50, 0.002)).await.unwrap();
+        db.record_usage(None, 12, "kimi", None, &usage(100, 10, 0.0005)).await.unwrap();
+
+        let session = db.get_session_usage(1).await.unwrap();
+        assert_eq!(session.requests, 2);
+        assert_eq!(session.input_tokens, 3_000);
+        assert_eq!(session.output_tokens, 500);
+        assert_eq!(session.total_tokens, 3_500);
+        assert!((session.cost_usd - 0.0165).abs() < 1e-9);
+
+        assert_eq!(db.get_session_usage(99).await.unwrap(), UsageTotals::default());
+
+        let by_session = db.list_usage_by_session(30, 10, None).await.unwrap();
+        assert_eq!(by_session.len(), 2);
+        assert_eq!(by_session[0].session_id, 2);
+        assert_eq!(by_session[1].totals.requests, 2);
+
+        let by_day = db.list_usage_by_day(30, None).await.unwrap();
+        assert_eq!(by_day.len(), 1);
+        assert_eq!(by_day[0].day, Utc::now().format("%Y-%m-%d").to_string());
+        assert_eq!(by_day[0].totals.requests, 4);
+
+        assert_eq!(db.get_usage_totals(1, None).await.unwrap().total_tokens, 4_160);
+    }
+
+    #[tokio::test]
+    async fn test_usage_limited_to_owner() {
+        let db = Database::new(":memory:").unwrap();
+        let me = db.create_user("0xaaa", None, UserRole::Member).await.unwrap();
+        let other = db.create_user("0xbbb", None, UserRole::Member).await.unwrap();
+        let mine = db.get_or_create_chat_session("web", 0, "web-user-3", SessionScope::Dm, None, Some(me.id)).await.unwrap();
+        let theirs = db.get_or_create_chat_session("web", 0, "web-user-4", SessionScope::Dm, None, Some(other.id)).await.unwrap();
+
+        db.record_usage(Some(mine.id), 0, "claude", None, &usage(1_000, 200, 0.006)).await.unwrap();
+        db.record_usage(Some(theirs.id), 0, "claude", None, &usage(500, 50, 0.002)).await.unwrap();
+        db.record_usage(None, 12, "kimi", None, &usage(100, 10, 0.0005)).await.unwrap();
+
+        assert_eq!(db.get_usage_totals(30, Some(me.id)).await.unwrap().total_tokens, 1_200);
+        assert_eq!(db.get_usage_totals(30, None).await.unwrap().requests, 3);
+        let by_session = db.list_usage_by_session(30, 10, Some(me.id)).await.unwrap();
+        assert_eq!(by_session.len(), 1);
+        assert_eq!(by_session[0].session_id, mine.id);
+        assert_eq!(db.list_usage_by_day(30, Some(me.id)).await.unwrap()[0].totals.requests, 1);
+        assert!(db.list_usage_by_day(30, Some(me.id + other.id)).await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_usage_per_api_key() {
+        let db = Database::new(":memory:").unwrap();
+
+        db.record_usage(Some(1), 10, "claude", Some(7), &usage(1_000, 200, 0.006)).await.unwrap();
+        db.record_usage(Some(2), 10, "claude", Some(7), &usage(500, 100, 0.003)).await.unwrap();
+        db.record_usage(Some(1), 10, "claude", None, &usage(100, 10, 0.0005)).await.unwrap();
+
+        let totals = db.get_api_key_usage(7, 30).await.unwrap();
+        assert_eq!(totals.requests, 2);
+        assert_eq!(totals.total_tokens, 1_800);
+        assert_eq!(db.get_api_key_usage(8, 30).await.unwrap(), UsageTotals::default());
+        assert_eq!(db.get_usage_totals(30, None).await.unwrap().requests, 3);
+    }
+
+    #[tokio::test]
+    async fn test_usage_includes_prompt_cache_tokens() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let db = Database::new(dir.path().join("stark.db").to_str().unwrap()).unwrap();
+
+        let cached = BudgetUsage {
+            cache_read_tokens: 4_000,
+            cache_write_tokens: 1_000,
+            ..usage(50, 50, 0.003)
+        };
+        db.record_usage(Some(1), 10, "claude", None, &cached).await.unwrap();
+
+        let totals = db.get_session_usage(1).await.unwrap();
+        assert_eq!(totals.input_tokens, 50);
+        assert_eq!(totals.cache_read_tokens, 4_000);
+        assert_eq!(totals.cache_write_tokens, 1_000);
+        assert_eq!(totals.total_tokens, 5_100);
+        assert_eq!(db.list_usage_by_day(1, None).await.unwrap()[0].totals.cache_read_tokens, 4_000);
+    }
+}
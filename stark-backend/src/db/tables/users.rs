@@ -0,0 +1,108 @@
+//! User database operations
+
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use rusqlite::{OptionalExtension, Row};
+
+use crate::models::{User, UserRole};
+use super::super::backend::{DbResult, SqliteBackend, UserStore};
+
+const COLUMNS: &str = "id, public_address, name, role, created_at";
+
+fn row_to_user(row: &Row) -> rusqlite::Result<User> {
+    let role: String = row.get(3)?;
+    let created_at: String = row.get(4)?;
+    Ok(User {
+        id: row.get(0)?,
+        public_address: row.get(1)?,
+        name: row.get(2)?,
+        role: UserRole::from_str(&role).unwrap_or(UserRole::Member),
+        created_at: DateTime::parse_from_rfc3339(&created_at)
+            .unwrap()
+            .with_timezone(&Utc),
+    })
+}
+
+#[async_trait]
+impl UserStore for SqliteBackend {
+    async fn create_user(&self, public_address: &str, name: Option<&str>, role: UserRole) -> DbResult<User> {
+        let conn = self.conn().await?;
+        let created_at = Utc::now();
+        conn.execute(
+            "INSERT INTO users (public_address, name, role, created_at) VALUES (?1, ?2, ?3, ?4)",
+            rusqlite::params![public_address, name, role.as_str(), created_at.to_rfc3339()],
+        )?;
+        Ok(User {
+            id: conn.last_insert_rowid(),
+            public_address: public_address.to_string(),
+            name: name.map(str::to_string),
+            role,
+            created_at,
+        })
+    }
+
+    async fn get_user(&self, id: i64) -> DbResult<Option<User>> {
+        let conn = self.conn().await?;
+        let user = conn
+            .query_row(&format!("SELECT {} FROM users WHERE id = ?1", COLUMNS), [id], row_to_user)
+            .optional()?;
+        Ok(user)
+    }
+
+    async fn get_user_by_address(&self, public_address: &str) -> DbResult<Option<User>> {
+        let conn = self.conn().await?;
+        let user = conn
+            .query_row(
+                &format!("SELECT {} FROM users WHERE public_address = ?1", COLUMNS),
+                [public_address],
+                row_to_user,
+            )
+            .optional()?;
+        Ok(user)
+    }
+
+    async fn list_users(&self) -> DbResult<Vec<User>> {
+        let conn = self.conn().await?;
+        let mut stmt = conn.prepare(&format!("SELECT {} FROM users ORDER BY id", COLUMNS))?;
+        let users = stmt.query_map([], row_to_user)?.filter_map(|r| r.ok()).collect();
+        Ok(users)
+    }
+
+    async fn set_user_role(&self, id: i64, role: UserRole) -> DbResult<Option<User>> {
+        let conn = self.conn().await?;
+        conn.execute("UPDATE users SET role = ?1 WHERE id = ?2", rusqlite::params![role.as_str(), id])?;
+        let user = conn
+            .query_row(&format!("SELECT {} FROM users WHERE id = ?1", COLUMNS), [id], row_to_user)
+            .optional()?;
+        Ok(user)
+    }
+
+    async fn delete_user(&self, id: i64) -> DbResult<bool> {
+        let mut conn = self.conn().await?;
+        // SQLite doesn't enforce the foreign keys, so cascade by hand
+        let tx = conn.transaction()?;
+        tx.execute("DELETE FROM auth_sessions WHERE user_id = ?1", [id])?;
+        tx.execute("DELETE FROM api_tokens WHERE user_id = ?1", [id])?;
+        tx.execute("DELETE FROM external_api_keys WHERE user_id = ?1", [id])?;
+        let deleted = tx.execute("DELETE FROM users WHERE id = ?1", [id])?;
+        tx.commit()?;
+        Ok(deleted > 0)
+    }
+
+    async fn claim_unowned_credentials(&self, user_id: i64) -> DbResult<usize> {
+        let conn = self.conn().await?;
+        let sessions = conn.execute("UPDATE auth_sessions SET user_id = ?1 WHERE user_id IS NULL", [user_id])?;
+        let tokens = conn.execute("UPDATE api_tokens SET user_id = ?1 WHERE user_id IS NULL", [user_id])?;
+        Ok(sessions + tokens)
+    }
+
+    async fn claim_totp_step(&self, user_id: i64, step: i64) -> DbResult<bool> {
+        let conn = self.conn().await?;
+        let claimed = conn.execute(
+            "UPDATE users SET totp_last_step = ?2
+             WHERE id = ?1 AND (totp_last_step IS NULL OR totp_last_step < ?2)",
+            [user_id, step],
+        )?;
+        Ok(claimed > 0)
+    }
+}
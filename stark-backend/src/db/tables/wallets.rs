@@ -0,0 +1,233 @@
+//! Wallet database operations
+//!
+//! Private keys are sealed with the master key before they are written and are
+//! only opened by `get_wallet_private_key`. Unlike API keys there is no
+//! plaintext fallback: storing a key requires `STARK_MASTER_KEY`.
+
+use chrono::{DateTime, Utc};
+use rusqlite::OptionalExtension;
+
+use crate::db::{DbError, DbResult};
+use crate::models::{Wallet, WalletKind};
+use crate::redaction;
+use super::super::Database;
+
+const WALLET_COLUMNS: &str = "id, name, kind, address, signer_url, is_default, created_at";
+
+impl Database {
+    /// Add a local wallet; the key is sealed under the current master key
+    pub async fn create_local_wallet(&self, name: &str, address: &str, private_key: &str) -> DbResult<Wallet> {
+        if !self.keys.is_enabled() {
+            return Err(DbError::Encryption(format!(
+                "{} must be set to store wallet keys",
+                crate::config::env_vars::MASTER_KEY
+            )));
+        }
+        let sealed = self.keys.seal(private_key).map_err(DbError::Encryption)?;
+        self.insert_wallet(name, WalletKind::Local, address, Some(&sealed), None).await
+    }
+
+    /// Add a wallet whose key is held by an external signer
+    pub async fn create_external_wallet(&self, name: &str, address: &str, signer_url: &str) -> DbResult<Wallet> {
+        self.insert_wallet(name, WalletKind::External, address, None, Some(signer_url)).await
+    }
+
+    async fn insert_wallet(
+        &self,
+        name: &str,
+        kind: WalletKind,
+        address: &str,
+        encrypted_key: Option<&str>,
+        signer_url: Option<&str>,
+    ) -> DbResult<Wallet> {
+        let conn = self.conn().await?;
+        // The first wallet becomes the default
+        let has_default: bool = conn.query_row(
+            "SELECT COUNT(*) FROM wallets WHERE is_default = 1",
+            [],
+            |row| row.get::<_, i64>(0),
+        )? > 0;
+        conn.execute(
+            "INSERT INTO wallets (name, kind, address, encrypted_key, signer_url, is_default, created_at)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+            rusqlite::params![
+                name,
+                kind.as_str(),
+                address,
+                encrypted_key,
+                signer_url,
+                !has_default,
+                Utc::now().to_rfc3339()
+            ],
+        )?;
+        let id = conn.last_insert_rowid();
+        drop(conn);
+
+        self.get_wallet(id)
+            .await?
+            .ok_or_else(|| DbError::Sqlite(rusqlite::Error::QueryReturnedNoRows))
+    }
+
+    pub async fn list_wallets(&self) -> DbResult<Vec<Wallet>> {
+        let conn = self.conn().await?;
+        let mut stmt = conn.prepare(&format!("SELECT {} FROM wallets ORDER BY id", WALLET_COLUMNS))?;
+        let wallets = stmt
+            .query_map([], Self::row_to_wallet)?
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(wallets)
+    }
+
+    pub async fn get_wallet(&self, id: i64) -> DbResult<Option<Wallet>> {
+        let conn = self.conn().await?;
+        Ok(conn
+            .query_row(
+                &format!("SELECT {} FROM wallets WHERE id = ?1", WALLET_COLUMNS),
+                [id],
+                Self::row_to_wallet,
+            )
+            .optional()?)
+    }
+
+    pub async fn get_default_wallet(&self) -> DbResult<Option<Wallet>> {
+        let conn = self.conn().await?;
+        Ok(conn
+            .query_row(
+                &format!("SELECT {} FROM wallets WHERE is_default = 1 LIMIT 1", WALLET_COLUMNS),
+                [],
+                Self::row_to_wallet,
+            )
+            .optional()?)
+    }
+
+    /// Make a wallet the default. Returns false if it doesn't exist.
+    pub async fn set_default_wallet(&self, id: i64) -> DbResult<bool> {
+        let mut conn = self.conn().await?;
+        let tx = conn.transaction()?;
+        let exists: bool = tx.query_row("SELECT COUNT(*) FROM wallets WHERE id = ?1", [id], |row| {
+            row.get::<_, i64>(0)
+        })? > 0;
+        if !exists {
+            return Ok(false);
+        }
+        tx.execute("UPDATE wallets SET is_default = (id = ?1)", [id])?;
+        tx.commit()?;
+        Ok(true)
+    }
+
+    pub async fn delete_wallet(&self, id: i64) -> DbResult<bool> {
+        let conn = self.conn().await?;
+        Ok(conn.execute("DELETE FROM wallets WHERE id = ?1", [id])? > 0)
+    }
+
+    /// Open a local wallet's private key
+    pub async fn get_wallet_private_key(&self, id: i64) -> DbResult<Option<String>> {
+        let conn = self.conn().await?;
+        let sealed: Option<String> = conn
+            .query_row("SELECT encrypted_key FROM wallets WHERE id = ?1", [id], |row| row.get(0))
+            .optional()?
+            .flatten();
+        drop(conn);
+        let key = sealed
+            .map(|s| self.keys.open(&s).map_err(DbError::Encryption))
+            .transpose()?;
+        if let Some(key) = &key {
+            redaction::register_secret(key.trim_start_matches("0x"));
+        }
+        Ok(key)
+    }
+
+    /// Re-wrap every wallet key under the current master key. Returns how many were rewritten.
+    pub async fn rotate_wallet_keys(&self) -> DbResult<usize> {
+        if !self.keys.is_enabled() {
+            return Err(DbError::Encryption("no master key configured".to_string()));
+        }
+        let conn = self.conn().await?;
+        let sealed: Vec<(i64, String)> = conn
+            .prepare("SELECT id, encrypted_key FROM wallets WHERE encrypted_key IS NOT NULL")?
+            .query_map([], |row| Ok((row.get(0)?, row.get(1)?)))?
+            .collect::<Result<_, _>>()?;
+
+        let mut rotated = 0;
+        for (id, value) in sealed {
+            if !self.keys.needs_rotation(&value) {
+                continue;
+            }
+            let value = self.keys.rotate(&value).map_err(DbError::Encryption)?;
+            conn.execute("UPDATE wallets SET encrypted_key = ?1 WHERE id = ?2", rusqlite::params![value, id])?;
+            rotated += 1;
+        }
+        Ok(rotated)
+    }
+
+    fn row_to_wallet(row: &rusqlite::Row) -> rusqlite::Result<Wallet> {
+        let kind: String = row.get(2)?;
+        let created_at: String = row.get(6)?;
+        Ok(Wallet {
+            id: row.get(0)?,
+            name: row.get(1)?,
+            kind: WalletKind::from_str(&kind).unwrap_or(WalletKind::Local),
+            address: row.get(3)?,
+            signer_url: row.get(4)?,
+            is_default: row.get(5)?,
+            created_at: DateTime::parse_from_rfc3339(&created_at)
+                .map(|dt| dt.with_timezone(&Utc))
+                .unwrap_or_else(|_| Utc::now()),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::db::secrets::MasterKey;
+    use crate::db::{Database, KeyRing};
+    use base64::{engine::general_purpose::STANDARD as BASE64, Engine};
+
+    fn key(byte: u8) -> MasterKey {
+        MasterKey::from_base64(&BASE64.encode([byte; 32])).unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_wallets() {
+        let db = Database::new(":memory:").unwrap();
+        let pk = "0xac0974bec39a17e36ba4a6b4d238ff944bacb478cbed5efcae784d7bf4f2ff80";
+        let address = "0xf39Fd6e51aad88F6F4ce6aB8827279cffFb92266";
+
+        // Keys are never stored unencrypted
+        assert!(db.create_local_wallet("hot", address, pk).await.is_err());
+
+        let db = db.with_keys(KeyRing::new(Some(key(1)), Vec::new()));
+        let hot = db.create_local_wallet("hot", address, pk).await.unwrap();
+        assert!(hot.is_default);
+        let signer = db
+            .create_external_wallet("clef", "0x70997970C51812dc3A010C7d01b50e0d17dc79C8", "http://localhost:8550")
+            .await
+            .unwrap();
+        assert!(!signer.is_default);
+        assert!(db.create_external_wallet("clef", address, "http://localhost:8550").await.is_err());
+
+        let raw: String = db
+            .conn()
+            .await
+            .unwrap()
+            .query_row("SELECT encrypted_key FROM wallets WHERE id = ?1", [hot.id], |row| row.get(0))
+            .unwrap();
+        assert!(KeyRing::is_sealed(&raw));
+        assert_eq!(db.get_wallet_private_key(hot.id).await.unwrap().as_deref(), Some(pk));
+        assert_eq!(db.get_wallet_private_key(signer.id).await.unwrap(), None);
+
+        assert!(db.set_default_wallet(signer.id).await.unwrap());
+        assert!(!db.set_default_wallet(999).await.unwrap());
+        assert_eq!(db.get_default_wallet().await.unwrap().unwrap().name, "clef");
+        assert_eq!(db.list_wallets().await.unwrap().iter().filter(|w| w.is_default).count(), 1);
+
+        let db = db.with_keys(KeyRing::new(Some(key(2)), vec![key(1)]));
+        assert_eq!(db.rotate_wallet_keys().await.unwrap(), 1);
+        assert_eq!(db.rotate_wallet_keys().await.unwrap(), 0);
+        let db = db.with_keys(KeyRing::new(Some(key(2)), Vec::new()));
+        assert_eq!(db.get_wallet_private_key(hot.id).await.unwrap().as_deref(), Some(pk));
+
+        assert!(db.delete_wallet(signer.id).await.unwrap());
+        assert!(db.get_default_wallet().await.unwrap().is_none());
+        assert_eq!(db.list_wallets().await.unwrap().len(), 1);
+    }
+}
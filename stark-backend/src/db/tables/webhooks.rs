@@ -0,0 +1,239 @@
+//! Webhook database operations (inbound triggers for agent jobs)
+//!
+//! Signing secrets are sealed with the master key like API keys, falling back
+//! to plaintext when no master key is configured. They are only opened by
+//! `get_webhook_secret`.
+
+use chrono::Utc;
+use rusqlite::OptionalExtension;
+
+use crate::db::{DbError, DbResult};
+use crate::models::{CreateWebhookRequest, Webhook};
+use super::super::Database;
+
+const WEBHOOK_COLUMNS: &str = "id, hook_id, name, prompt_template, tools, workspace, max_iterations,
+    enabled, last_triggered_at, last_job_id, created_at, updated_at, user_id";
+
+impl Database {
+    /// Create a webhook owned by `user_id`. `hook_id`, `secret` and the
+    /// (already validated) `workspace` are chosen by the caller.
+    #[allow(clippy::too_many_arguments)]
+    pub async fn create_webhook(
+        &self,
+        request: &CreateWebhookRequest,
+        hook_id: &str,
+        secret: &str,
+        workspace: &str,
+        max_iterations: i64,
+        user_id: Option<i64>,
+    ) -> DbResult<Webhook> {
+        let sealed = self.keys.seal(secret).map_err(DbError::Encryption)?;
+        let tools = serde_json::to_string(&request.tools).unwrap_or_else(|_| "[]".to_string());
+        let now = Utc::now().to_rfc3339();
+
+        let conn = self.conn().await?;
+        conn.execute(
+            "INSERT INTO webhooks (hook_id, name, secret, prompt_template, tools, workspace, max_iterations,
+                                   enabled, created_at, updated_at, user_id)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?9, ?10)",
+            rusqlite::params![
+                hook_id,
+                request.name,
+                sealed,
+                request.prompt_template,
+                tools,
+                workspace,
+                max_iterations,
+                request.enabled,
+                now,
+                user_id,
+            ],
+        )?;
+        drop(conn);
+
+        self.get_webhook(hook_id)
+            .await?
+            .ok_or(DbError::Sqlite(rusqlite::Error::QueryReturnedNoRows))
+    }
+
+    pub async fn get_webhook(&self, hook_id: &str) -> DbResult<Option<Webhook>> {
+        let conn = self.conn().await?;
+        Ok(conn
+            .query_row(
+                &format!("SELECT {} FROM webhooks WHERE hook_id = ?1", WEBHOOK_COLUMNS),
+                [hook_id],
+                Self::map_webhook_row,
+            )
+            .optional()?)
+    }
+
+    /// Every webhook, or only those `owner` created
+    pub async fn list_webhooks(&self, owner: Option<i64>) -> DbResult<Vec<Webhook>> {
+        let conn = self.conn().await?;
+        let mut stmt = conn.prepare(&format!(
+            "SELECT {} FROM webhooks WHERE (?1 IS NULL OR user_id = ?1) ORDER BY id ASC",
+            WEBHOOK_COLUMNS
+        ))?;
+        let hooks = stmt
+            .query_map([owner], Self::map_webhook_row)?
+            .collect::<rusqlite::Result<Vec<_>>>()?;
+        Ok(hooks)
+    }
+
+    /// Write back every editable field of a webhook (after applying an update request)
+    pub async fn save_webhook(&self, hook: &Webhook) -> DbResult<bool> {
+        let conn = self.conn().await?;
+        let tools = serde_json::to_string(&hook.tools).unwrap_or_else(|_| "[]".to_string());
+        let rows = conn.execute(
+            "UPDATE webhooks
+             SET name = ?1, prompt_template = ?2, tools = ?3, workspace = ?4, max_iterations = ?5,
+                 enabled = ?6, updated_at = ?7
+             WHERE hook_id = ?8",
+            rusqlite::params![
+                hook.name,
+                hook.prompt_template,
+                tools,
+                hook.workspace,
+                hook.max_iterations,
+                hook.enabled,
+                Utc::now().to_rfc3339(),
+                hook.hook_id,
+            ],
+        )?;
+        Ok(rows > 0)
+    }
+
+    /// Open a webhook's signing secret
+    pub async fn get_webhook_secret(&self, hook_id: &str) -> DbResult<Option<String>> {
+        let conn = self.conn().await?;
+        let sealed: Option<String> = conn
+            .query_row("SELECT secret FROM webhooks WHERE hook_id = ?1", [hook_id], |row| row.get(0))
+            .optional()?;
+        drop(conn);
+        sealed
+            .map(|s| self.keys.open(&s).map_err(DbError::Encryption))
+            .transpose()
+    }
+
+    pub async fn set_webhook_secret(&self, hook_id: &str, secret: &str) -> DbResult<bool> {
+        let sealed = self.keys.seal(secret).map_err(DbError::Encryption)?;
+        let conn = self.conn().await?;
+        let rows = conn.execute(
+            "UPDATE webhooks SET secret = ?1, updated_at = ?2 WHERE hook_id = ?3",
+            rusqlite::params![sealed, Utc::now().to_rfc3339(), hook_id],
+        )?;
+        Ok(rows > 0)
+    }
+
+    /// Record a delivery and the job it queued
+    pub async fn record_webhook_trigger(&self, hook_id: &str, triggered_at: &str, job_id: &str) -> DbResult<()> {
+        let conn = self.conn().await?;
+        conn.execute(
+            "UPDATE webhooks SET last_triggered_at = ?1, last_job_id = ?2 WHERE hook_id = ?3",
+            rusqlite::params![triggered_at, job_id, hook_id],
+        )?;
+        Ok(())
+    }
+
+    pub async fn delete_webhook(&self, hook_id: &str) -> DbResult<bool> {
+        let conn = self.conn().await?;
+        Ok(conn.execute("DELETE FROM webhooks WHERE hook_id = ?1", [hook_id])? > 0)
+    }
+
+    /// Re-wrap every webhook secret under the current master key (sealing any
+    /// plaintext ones). Returns how many were rewritten.
+    pub async fn rotate_webhook_secrets(&self) -> DbResult<usize> {
+        if !self.keys.is_enabled() {
+            return Err(DbError::Encryption("no master key configured".to_string()));
+        }
+        let conn = self.conn().await?;
+        let sealed: Vec<(i64, String)> = conn
+            .prepare("SELECT id, secret FROM webhooks")?
+            .query_map([], |row| Ok((row.get(0)?, row.get(1)?)))?
+            .collect::<Result<_, _>>()?;
+
+        let mut rotated = 0;
+        for (id, value) in sealed {
+            if !self.keys.needs_rotation(&value) {
+                continue;
+            }
+            let value = self.keys.rotate(&value).map_err(DbError::Encryption)?;
+            conn.execute("UPDATE webhooks SET secret = ?1 WHERE id = ?2", rusqlite::params![value, id])?;
+            rotated += 1;
+        }
+        Ok(rotated)
+    }
+
+    fn map_webhook_row(row: &rusqlite::Row) -> rusqlite::Result<Webhook> {
+        let hook_id: String = row.get(1)?;
+        let tools: String = row.get(4)?;
+        let workspace: Option<String> = row.get(5)?;
+        Ok(Webhook {
+            id: row.get(0)?,
+            workspace: workspace.unwrap_or_else(|| Webhook::default_workspace(&hook_id)),
+            hook_id,
+            name: row.get(2)?,
+            prompt_template: row.get(3)?,
+            tools: serde_json::from_str(&tools).unwrap_or_default(),
+            max_iterations: row.get(6)?,
+            enabled: row.get(7)?,
+            last_triggered_at: row.get(8)?,
+            last_job_id: row.get(9)?,
+            created_at: row.get(10)?,
+            updated_at: row.get(11)?,
+            user_id: row.get(12)?,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::db::secrets::MasterKey;
+    use crate::db::{Database, KeyRing};
+    use crate::models::CreateWebhookRequest;
+    use base64::{engine::general_purpose::STANDARD as BASE64, Engine};
+
+    #[tokio::test]
+    async fn test_webhook_crud_and_sealed_secret() {
+        let key = MasterKey::from_base64(&BASE64.encode([3u8; 32])).unwrap();
+        let db = Database::new(":memory:").unwrap().with_keys(KeyRing::new(Some(key), Vec::new()));
+        let request = CreateWebhookRequest {
+            name: "github pushes".to_string(),
+            prompt_template: "Review this push: {{payload}}".to_string(),
+            secret: None,
+            tools: vec!["read_file".to_string()],
+            workspace: None,
+            max_iterations: None,
+            enabled: true,
+        };
+        let hook = db.create_webhook(&request, "abc123", "s3cret", "user-2-hook-abc123", 10, Some(2)).await.unwrap();
+        assert_eq!(hook.workspace, "user-2-hook-abc123");
+        assert_eq!(hook.user_id, Some(2));
+        assert_eq!(hook.tools, vec!["read_file"]);
+        assert_eq!(db.get_webhook_secret("abc123").await.unwrap().as_deref(), Some("s3cret"));
+
+        // Stored sealed, not as plaintext
+        let conn = db.conn().await.unwrap();
+        let stored: String = conn.query_row("SELECT secret FROM webhooks", [], |row| row.get(0)).unwrap();
+        drop(conn);
+        assert!(KeyRing::is_sealed(&stored));
+
+        assert!(db.set_webhook_secret("abc123", "rotated").await.unwrap());
+        assert_eq!(db.get_webhook_secret("abc123").await.unwrap().as_deref(), Some("rotated"));
+
+        db.record_webhook_trigger("abc123", "2026-01-01T00:00:00+00:00", "job-1").await.unwrap();
+        let mut hook = db.get_webhook("abc123").await.unwrap().unwrap();
+        assert_eq!(hook.last_job_id.as_deref(), Some("job-1"));
+
+        hook.enabled = false;
+        assert!(db.save_webhook(&hook).await.unwrap());
+        assert!(!db.get_webhook("abc123").await.unwrap().unwrap().enabled);
+
+        assert_eq!(db.list_webhooks(None).await.unwrap().len(), 1);
+        assert_eq!(db.list_webhooks(Some(2)).await.unwrap().len(), 1);
+        assert!(db.list_webhooks(Some(3)).await.unwrap().is_empty());
+        assert!(db.delete_webhook("abc123").await.unwrap());
+        assert!(db.get_webhook("abc123").await.unwrap().is_none());
+        assert!(db.get_webhook_secret("abc123").await.unwrap().is_none());
+    }
+}
@@ -31,12 +31,11 @@ impl IdentityRegistry {
 
     /// Get or create RPC client
     fn get_rpc(&self) -> Result<X402EvmRpc, String> {
-        let private_key = crate::config::burner_wallet_private_key()
-            .ok_or("BURNER_WALLET_BOT_PRIVATE_KEY not set")?;
+        let signer = crate::wallet::require_signer()?;
 
         // Use "base" for Base mainnet (8453), "mainnet" for Ethereum mainnet
         let network = if self.config.chain_id == 1 { "mainnet" } else { "base" };
-        X402EvmRpc::new(&private_key, network)
+        X402EvmRpc::new(signer, network)
     }
 
     /// Get the registry contract address
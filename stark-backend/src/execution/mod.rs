@@ -8,11 +8,14 @@
 //! multiple requests arrive for the same session.
 
 mod tracker;
+mod output_buffer;
 mod pending_confirmation;
+mod ports;
 mod process_manager;
 mod session_lanes;
 
 pub use tracker::ExecutionTracker;
 pub use pending_confirmation::{PendingConfirmation, PendingConfirmationManager};
-pub use process_manager::{ProcessInfo, ProcessManager, ProcessStatus};
+pub use ports::preview_owner;
+pub use process_manager::{isolate, kill_tree, stream_lines, OutputLine, ProcessManager, ProcessStatus};
 pub use session_lanes::{SessionLaneGuard, SessionLaneManager, SessionLaneStats};
@@ -0,0 +1,165 @@
+//! Captured output of background processes
+//!
+//! `OutputBuffer` keeps the most recent lines a process wrote to stdout and
+//! stderr, interleaved in the order they arrived. Every line gets a sequence
+//! number, so a caller can follow output incrementally by passing back the
+//! cursor it received from the previous read.
+//!
+//! This module only depends on std so the standalone `agent_test` binary can
+//! include it with `#[path]`.
+
+use std::collections::VecDeque;
+use std::env;
+
+/// Environment variables read by `OutputBuffer::max_lines_from_env`
+pub mod env_vars {
+    /// Number of output lines kept per background process
+    pub const MAX_LINES: &str = "STARK_PROCESS_OUTPUT_LINES";
+}
+
+/// Lines kept per process when `STARK_PROCESS_OUTPUT_LINES` is not set
+pub const DEFAULT_MAX_LINES: usize = 1000;
+
+/// Prefix marking lines read from stderr
+pub const STDERR_PREFIX: &str = "[stderr] ";
+
+/// A slice of output returned by `OutputBuffer::since`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OutputChunk {
+    pub lines: Vec<String>,
+    /// Pass this back as `since` to continue after the last returned line
+    pub cursor: u64,
+    /// Lines that were evicted from the buffer before they could be read
+    pub skipped: u64,
+}
+
+/// Ring buffer of combined stdout/stderr lines
+#[derive(Debug, Clone)]
+pub struct OutputBuffer {
+    lines: VecDeque<String>,
+    max_lines: usize,
+    /// Sequence number the next pushed line will get
+    next_seq: u64,
+}
+
+impl OutputBuffer {
+    pub fn new(max_lines: usize) -> Self {
+        Self {
+            lines: VecDeque::new(),
+            max_lines: max_lines.max(1),
+            next_seq: 0,
+        }
+    }
+
+    /// Buffer size configured via `STARK_PROCESS_OUTPUT_LINES`
+    pub fn max_lines_from_env() -> usize {
+        env::var(env_vars::MAX_LINES)
+            .ok()
+            .and_then(|v| v.trim().parse().ok())
+            .filter(|n| *n > 0)
+            .unwrap_or(DEFAULT_MAX_LINES)
+    }
+
+    /// Append a stdout line, evicting the oldest line when full
+    pub fn push_stdout(&mut self, line: String) {
+        self.push(line);
+    }
+
+    /// Append a stderr line, evicting the oldest line when full
+    pub fn push_stderr(&mut self, line: &str) {
+        self.push(format!("{}{}", STDERR_PREFIX, line));
+    }
+
+    fn push(&mut self, line: String) {
+        if self.lines.len() >= self.max_lines {
+            self.lines.pop_front();
+        }
+        self.lines.push_back(line);
+        self.next_seq += 1;
+    }
+
+    /// Cursor positioned after the newest line
+    pub fn cursor(&self) -> u64 {
+        self.next_seq
+    }
+
+    /// The last `n` lines, oldest first
+    pub fn tail(&self, n: usize) -> Vec<String> {
+        let skip = self.lines.len().saturating_sub(n);
+        self.lines.iter().skip(skip).cloned().collect()
+    }
+
+    /// Up to `limit` lines written after `cursor`, oldest first
+    pub fn since(&self, cursor: u64, limit: usize) -> OutputChunk {
+        let first_seq = self.next_seq - self.lines.len() as u64;
+        let start = cursor.clamp(first_seq, self.next_seq);
+        let lines: Vec<String> = self
+            .lines
+            .iter()
+            .skip((start - first_seq) as usize)
+            .take(limit)
+            .cloned()
+            .collect();
+
+        OutputChunk {
+            cursor: start + lines.len() as u64,
+            skipped: first_seq.saturating_sub(cursor),
+            lines,
+        }
+    }
+}
+
+impl Default for OutputBuffer {
+    fn default() -> Self {
+        Self::new(DEFAULT_MAX_LINES)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_interleaves_streams_and_evicts_oldest() {
+        let mut buffer = OutputBuffer::new(3);
+        buffer.push_stdout("one".to_string());
+        buffer.push_stderr("two");
+        buffer.push_stdout("three".to_string());
+        buffer.push_stdout("four".to_string());
+
+        assert_eq!(buffer.tail(10), vec!["[stderr] two", "three", "four"]);
+        assert_eq!(buffer.tail(1), vec!["four"]);
+        assert_eq!(buffer.cursor(), 4);
+    }
+
+    #[test]
+    fn test_follow_with_cursor() {
+        let mut buffer = OutputBuffer::new(4);
+        for i in 0..3 {
+            buffer.push_stdout(format!("line{}", i));
+        }
+
+        let chunk = buffer.since(0, 2);
+        assert_eq!(chunk.lines, vec!["line0", "line1"]);
+        assert_eq!(chunk.cursor, 2);
+        assert_eq!(chunk.skipped, 0);
+
+        let chunk = buffer.since(chunk.cursor, 10);
+        assert_eq!(chunk.lines, vec!["line2"]);
+        assert_eq!(chunk.cursor, 3);
+
+        // Nothing new yet
+        let empty = buffer.since(chunk.cursor, 10);
+        assert!(empty.lines.is_empty());
+        assert_eq!(empty.cursor, 3);
+
+        // Reader fell behind: lines 0-2 were evicted
+        for i in 3..7 {
+            buffer.push_stdout(format!("line{}", i));
+        }
+        let chunk = buffer.since(1, 10);
+        assert_eq!(chunk.skipped, 2);
+        assert_eq!(chunk.lines, vec!["line3", "line4", "line5", "line6"]);
+        assert_eq!(chunk.cursor, 7);
+    }
+}
@@ -7,6 +7,7 @@ use dashmap::DashMap;
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
 use std::time::{Duration, Instant};
+use crate::utils::{last_chars, truncate_chars};
 
 /// Tools that require user confirmation before execution
 pub const CONFIRMATION_REQUIRED_TOOLS: &[&str] = &[
@@ -136,8 +137,8 @@ impl PendingConfirmation {
 
     /// Shorten an address for display
     fn short_address(addr: &str) -> String {
-        if addr.len() > 12 {
-            format!("{}...{}", &addr[..6], &addr[addr.len()-4..])
+        if addr.chars().count() > 12 {
+            format!("{}...{}", truncate_chars(addr, 6), last_chars(addr, 4))
         } else {
             addr.to_string()
         }
@@ -0,0 +1,216 @@
+//! Ports for servers the agent starts
+//!
+//! A background command that serves something (a dev server) gets a free
+//! port from the configured range, passed to it as `PORT`. The lease belongs
+//! to the process and is released when the process exits.
+//!
+//! With the preview proxy enabled, the newest server of a job or chat
+//! session is reachable at `/preview/{owner}/{key}/`, the key being the
+//! lease's secret (see `controllers::preview`). Without it the server is only
+//! reachable on the host, at `http://127.0.0.1:{port}/`.
+
+use std::env;
+use std::net::TcpListener;
+use std::ops::RangeInclusive;
+use std::sync::Mutex;
+
+/// Environment variables read by `PortManager::from_env`
+pub mod env_vars {
+    /// Ports handed out to servers, as `first-last`
+    pub const PORTS: &str = "STARK_PREVIEW_PORTS";
+    /// Serve leased ports under `/preview/{owner}/`
+    pub const PROXY: &str = "STARK_PREVIEW_PROXY";
+    /// Base URL preview links start with (default: relative `/preview/...` links).
+    /// When set, previews are only served on its host.
+    pub const URL: &str = "STARK_PREVIEW_URL";
+}
+
+/// Ports handed out when `STARK_PREVIEW_PORTS` is not set
+pub const DEFAULT_PORTS: RangeInclusive<u16> = 4100..=4199;
+
+/// A port reserved for one server
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PortLease {
+    pub port: u16,
+    /// Job ID, or `session-{id}` for chat sessions; the `{owner}` in preview URLs
+    pub owner: String,
+    /// Secret that opens the preview without an API token
+    pub key: String,
+    /// Background process listening on the port, once it has started
+    pub process_id: Option<String>,
+}
+
+/// Hands out free ports and remembers which server holds each
+pub struct PortManager {
+    range: RangeInclusive<u16>,
+    /// In the order they were allocated
+    leases: Mutex<Vec<PortLease>>,
+    proxy: bool,
+    base_url: String,
+}
+
+impl PortManager {
+    pub fn new(range: RangeInclusive<u16>, proxy: bool, base_url: &str) -> Self {
+        Self {
+            range,
+            leases: Mutex::new(Vec::new()),
+            proxy,
+            base_url: base_url.trim_end_matches('/').to_string(),
+        }
+    }
+
+    pub fn from_env() -> Self {
+        let range = env::var(env_vars::PORTS)
+            .ok()
+            .and_then(|v| parse_range(&v))
+            .unwrap_or(DEFAULT_PORTS);
+        let proxy = env::var(env_vars::PROXY).map(|v| v == "true" || v == "1").unwrap_or(false);
+        let base_url = env::var(env_vars::URL).unwrap_or_default();
+        Self::new(range, proxy, base_url.trim())
+    }
+
+    /// Whether leased ports are served under `/preview/`
+    pub fn proxy_enabled(&self) -> bool {
+        self.proxy
+    }
+
+    /// The `host[:port]` previews are served on, when `STARK_PREVIEW_URL` gives
+    /// them an origin of their own
+    pub fn preview_host(&self) -> Option<String> {
+        let url = url::Url::parse(&self.base_url).ok()?;
+        let host = url.host_str()?.to_lowercase();
+        Some(match url.port() {
+            Some(port) => format!("{}:{}", host, port),
+            None => host,
+        })
+    }
+
+    /// Reserve a port that is neither leased nor in use on the host
+    pub fn allocate(&self, owner: &str) -> Result<PortLease, String> {
+        let mut leases = self.leases.lock().unwrap();
+        let port = self
+            .range
+            .clone()
+            .find(|port| !leases.iter().any(|l| l.port == *port) && port_free(*port))
+            .ok_or_else(|| {
+                format!(
+                    "No free port in {}-{}. Kill a background server first.",
+                    self.range.start(),
+                    self.range.end()
+                )
+            })?;
+        let lease = PortLease {
+            port,
+            owner: owner.to_string(),
+            key: uuid::Uuid::new_v4().simple().to_string(),
+            process_id: None,
+        };
+        leases.push(lease.clone());
+        Ok(lease)
+    }
+
+    /// Record the process that was started on a leased port
+    pub fn attach(&self, port: u16, process_id: &str) {
+        if let Some(lease) = self.leases.lock().unwrap().iter_mut().find(|l| l.port == port) {
+            lease.process_id = Some(process_id.to_string());
+        }
+    }
+
+    /// Give back a port whose process never started
+    pub fn release_port(&self, port: u16) {
+        self.leases.lock().unwrap().retain(|l| l.port != port);
+    }
+
+    /// Give back the port of a process that has exited
+    pub fn release_process(&self, process_id: &str) {
+        self.leases
+            .lock()
+            .unwrap()
+            .retain(|l| l.process_id.as_deref() != Some(process_id));
+    }
+
+    /// The newest running server of a job or chat session
+    pub fn preview(&self, owner: &str) -> Option<PortLease> {
+        self.leases
+            .lock()
+            .unwrap()
+            .iter()
+            .rev()
+            .find(|l| l.owner == owner && l.process_id.is_some())
+            .cloned()
+    }
+
+    /// Where a user can open the server on a lease
+    pub fn url(&self, lease: &PortLease) -> String {
+        if self.proxy {
+            format!("{}/preview/{}/{}/", self.base_url, lease.owner, lease.key)
+        } else {
+            format!("http://127.0.0.1:{}/", lease.port)
+        }
+    }
+}
+
+/// Preview owner for a job or, failing that, a chat session or channel
+pub fn preview_owner(job_id: Option<&str>, session_id: Option<i64>, channel_id: i64) -> String {
+    match (job_id, session_id) {
+        (Some(job_id), _) => job_id.to_string(),
+        (None, Some(session_id)) => format!("session-{}", session_id),
+        (None, None) => format!("channel-{}", channel_id),
+    }
+}
+
+fn parse_range(value: &str) -> Option<RangeInclusive<u16>> {
+    let (first, last) = value.trim().split_once('-')?;
+    let (first, last): (u16, u16) = (first.trim().parse().ok()?, last.trim().parse().ok()?);
+    (first > 0 && first <= last).then_some(first..=last)
+}
+
+fn port_free(port: u16) -> bool {
+    TcpListener::bind(("127.0.0.1", port)).is_ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_leases_skip_taken_ports_and_follow_their_process() {
+        // Something else already listens on the first port of the range
+        let taken = TcpListener::bind(("127.0.0.1", 0)).unwrap();
+        let first = taken.local_addr().unwrap().port();
+        let ports = PortManager::new(first..=first.saturating_add(20), true, "https://bot.example.com/");
+
+        let a = ports.allocate("job-1").unwrap();
+        let b = ports.allocate("job-1").unwrap();
+        assert_ne!(a.port, first);
+        assert_ne!(a.port, b.port);
+
+        // Only leases with a running process are previewed, newest first
+        assert_eq!(ports.preview("job-1"), None);
+        ports.attach(a.port, "proc_1");
+        assert_eq!(ports.preview("job-1").unwrap().port, a.port);
+        ports.attach(b.port, "proc_2");
+        assert_eq!(ports.preview("job-1").unwrap().port, b.port);
+        assert_eq!(ports.preview("job-2"), None);
+
+        let url = ports.url(&ports.preview("job-1").unwrap());
+        assert_eq!(url, format!("https://bot.example.com/preview/job-1/{}/", b.key));
+        assert_eq!(ports.preview_host().as_deref(), Some("bot.example.com"));
+        assert_eq!(PortManager::new(DEFAULT_PORTS, true, "http://Preview.local:8081").preview_host().as_deref(), Some("preview.local:8081"));
+        assert_eq!(PortManager::new(DEFAULT_PORTS, true, "").preview_host(), None);
+
+        ports.release_process("proc_2");
+        assert_eq!(ports.preview("job-1").unwrap().port, a.port);
+        ports.release_process("proc_1");
+        assert_eq!(ports.preview("job-1"), None);
+    }
+
+    #[test]
+    fn test_parse_range() {
+        assert_eq!(parse_range("5000-5009"), Some(5000..=5009));
+        assert_eq!(parse_range(" 5000 - 5000 "), Some(5000..=5000));
+        assert_eq!(parse_range("5009-5000"), None);
+        assert_eq!(parse_range("0-10"), None);
+        assert_eq!(parse_range("5000"), None);
+    }
+}
@@ -4,27 +4,30 @@
 //! background processes from exec commands. It enables:
 //! - Async command execution that returns immediately with a process ID
 //! - Real-time output streaming via gateway events
+//! - Buffered output that can be followed incrementally with a cursor
 //! - Process status checking and termination
 //! - Resource limits (max concurrent processes)
 
 use crate::gateway::events::EventBroadcaster;
 use crate::gateway::protocol::GatewayEvent;
+use crate::execution::output_buffer::{OutputBuffer, OutputChunk};
+use crate::execution::ports::PortManager;
+use crate::tools::sandbox::SandboxConfig;
 use dashmap::DashMap;
-use std::collections::VecDeque;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::process::Stdio;
 use std::sync::atomic::{AtomicU64, Ordering};
 use std::sync::Arc;
-use std::time::Instant;
-use tokio::io::{AsyncBufReadExt, BufReader};
+use std::time::{Duration, Instant};
+use tokio::io::{AsyncBufReadExt, AsyncRead, BufReader};
 use tokio::process::{Child, Command};
-use tokio::sync::{mpsc, Semaphore};
+use tokio::sync::{mpsc, OwnedSemaphorePermit, Semaphore};
 
 /// Maximum number of concurrent background processes
 const MAX_CONCURRENT_PROCESSES: usize = 5;
 
-/// Maximum number of output lines to buffer per process
-const MAX_OUTPUT_BUFFER: usize = 1000;
+/// How long `kill_all` waits for killed processes to exit
+const KILL_WAIT: Duration = Duration::from_secs(5);
 
 /// Status of a background process
 #[derive(Debug, Clone, PartialEq, Eq)]
@@ -64,16 +67,18 @@ pub struct ProcessHandle {
     pub workdir: PathBuf,
     /// Channel ID that started this process
     pub channel_id: i64,
+    /// Chat session that started this process (if known)
+    pub session_id: Option<i64>,
+    /// Background agent job that started this process (if any)
+    pub job_id: Option<String>,
     /// Current status
     pub status: ProcessStatus,
     /// Start time
     pub started_at: Instant,
     /// End time (if completed)
     pub ended_at: Option<Instant>,
-    /// Buffered stdout lines (ring buffer)
-    pub stdout_buffer: VecDeque<String>,
-    /// Buffered stderr lines (ring buffer)
-    pub stderr_buffer: VecDeque<String>,
+    /// Combined stdout/stderr lines (ring buffer)
+    pub output: OutputBuffer,
     /// Channel to send kill signal
     kill_tx: Option<mpsc::Sender<()>>,
 }
@@ -85,24 +90,18 @@ impl ProcessHandle {
         end.duration_since(self.started_at).as_millis() as i64
     }
 
-    /// Get recent output lines (combined stdout + stderr)
-    pub fn recent_output(&self, lines: usize) -> Vec<String> {
-        let mut output: Vec<String> = Vec::new();
-
-        // Interleave stdout and stderr, taking most recent
-        let stdout_iter = self.stdout_buffer.iter().rev().take(lines);
-        let stderr_iter = self.stderr_buffer.iter().rev().take(lines);
-
-        for line in stdout_iter {
-            output.push(line.clone());
-        }
-        for line in stderr_iter {
-            output.push(format!("[stderr] {}", line));
+    /// Lightweight snapshot for listing
+    pub fn info(&self) -> ProcessInfo {
+        ProcessInfo {
+            id: self.id.clone(),
+            pid: self.pid,
+            command: self.command.clone(),
+            status: self.status.clone(),
+            duration_ms: self.duration_ms(),
+            channel_id: self.channel_id,
+            session_id: self.session_id,
+            job_id: self.job_id.clone(),
         }
-
-        output.truncate(lines);
-        output.reverse();
-        output
     }
 }
 
@@ -116,6 +115,12 @@ pub struct ProcessManager {
     semaphore: Arc<Semaphore>,
     /// Counter for generating unique process IDs
     id_counter: AtomicU64,
+    /// Isolation and quotas applied to every spawned command
+    sandbox: SandboxConfig,
+    /// Output lines buffered per process
+    output_max_lines: usize,
+    /// Ports leased to background servers, released when they exit
+    ports: Arc<PortManager>,
 }
 
 impl ProcessManager {
@@ -126,9 +131,17 @@ impl ProcessManager {
             processes: Arc::new(DashMap::new()),
             semaphore: Arc::new(Semaphore::new(MAX_CONCURRENT_PROCESSES)),
             id_counter: AtomicU64::new(1),
+            sandbox: SandboxConfig::from_env(),
+            output_max_lines: OutputBuffer::max_lines_from_env(),
+            ports: Arc::new(PortManager::from_env()),
         }
     }
 
+    /// Ports leased to the servers this manager runs
+    pub fn ports(&self) -> &Arc<PortManager> {
+        &self.ports
+    }
+
     /// Generate a unique process ID
     fn next_id(&self) -> String {
         let id = self.id_counter.fetch_add(1, Ordering::SeqCst);
@@ -146,61 +159,136 @@ impl ProcessManager {
         channel_id: i64,
         env_vars: Option<&std::collections::HashMap<String, String>>,
     ) -> Result<String, String> {
-        // Check if we can acquire a permit (don't block, just check)
-        let permit = self
-            .semaphore
-            .clone()
-            .try_acquire_owned()
-            .map_err(|_| format!("Maximum concurrent processes ({}) reached. Kill an existing process first.", MAX_CONCURRENT_PROCESSES))?;
+        self.spawn_for_session(command, workdir, channel_id, None, env_vars).await
+    }
 
-        let process_id = self.next_id();
+    /// Spawn a command in the background, tagged with the chat session that owns it
+    ///
+    /// Session-tagged processes can be torn down with `kill_all_for_session`.
+    pub async fn spawn_for_session(
+        &self,
+        command: &str,
+        workdir: &Path,
+        channel_id: i64,
+        session_id: Option<i64>,
+        env_vars: Option<&std::collections::HashMap<String, String>>,
+    ) -> Result<String, String> {
+        self.spawn_owned(command, workdir, channel_id, session_id, None, env_vars).await
+    }
 
-        // Build the command
-        let shell = if cfg!(target_os = "windows") {
-            "cmd"
-        } else {
-            "sh"
-        };
-        let shell_arg = if cfg!(target_os = "windows") {
-            "/C"
-        } else {
-            "-c"
-        };
+    /// Spawn a command in the background, tagged with the agent job that owns it
+    ///
+    /// Job-tagged processes can be torn down with `kill_all_for_job`.
+    pub async fn spawn_for_job(
+        &self,
+        command: &str,
+        workdir: &Path,
+        job_id: &str,
+        env_vars: Option<&std::collections::HashMap<String, String>>,
+    ) -> Result<String, String> {
+        self.spawn_owned(command, workdir, 0, None, Some(job_id), env_vars).await
+    }
 
-        let mut cmd = Command::new(shell);
-        cmd.arg(shell_arg)
-            .arg(command)
-            .current_dir(workdir)
-            .stdout(Stdio::piped())
-            .stderr(Stdio::piped());
-
-        // Add environment variables if provided
-        if let Some(vars) = env_vars {
-            for (key, value) in vars {
-                cmd.env(key, value);
-            }
-        }
+    async fn spawn_owned(
+        &self,
+        command: &str,
+        workdir: &Path,
+        channel_id: i64,
+        session_id: Option<i64>,
+        job_id: Option<&str>,
+        env_vars: Option<&std::collections::HashMap<String, String>>,
+    ) -> Result<String, String> {
+        // Check if we can acquire a permit (don't block, just check)
+        let permit = self.try_permit()?;
+
+        // Build the command inside the configured sandbox
+        let env: Vec<(String, String)> = env_vars
+            .map(|vars| vars.iter().map(|(k, v)| (k.clone(), v.clone())).collect())
+            .unwrap_or_default();
+        let mut cmd = self.sandbox.command(command, workdir, &env);
+        isolate(&mut cmd);
+        let mut cmd = Command::from(cmd);
+        cmd.stdout(Stdio::piped()).stderr(Stdio::piped());
 
         // Spawn the process
         let mut child = cmd.spawn().map_err(|e| format!("Failed to spawn process: {}", e))?;
+        let lines = stream_lines(&mut child);
 
+        Ok(self.track(child, lines, permit, command, workdir, channel_id, session_id, job_id, Vec::new()))
+    }
+
+    /// Take over a command that was started in the foreground (and has run
+    /// past its timeout), keeping the output it already wrote. If no slot is
+    /// free the process is killed instead.
+    #[allow(clippy::too_many_arguments)]
+    pub async fn adopt(
+        &self,
+        mut child: Child,
+        lines: mpsc::UnboundedReceiver<OutputLine>,
+        command: &str,
+        workdir: &Path,
+        channel_id: i64,
+        session_id: Option<i64>,
+        job_id: Option<&str>,
+        earlier_output: Vec<OutputLine>,
+    ) -> Result<String, String> {
+        let permit = match self.try_permit() {
+            Ok(permit) => permit,
+            Err(e) => {
+                kill_tree(&mut child).await;
+                return Err(e);
+            }
+        };
+        Ok(self.track(child, lines, permit, command, workdir, channel_id, session_id, job_id, earlier_output))
+    }
+
+    fn try_permit(&self) -> Result<OwnedSemaphorePermit, String> {
+        self.semaphore.clone().try_acquire_owned().map_err(|_| {
+            format!(
+                "Maximum concurrent processes ({}) reached. Kill an existing process first.",
+                MAX_CONCURRENT_PROCESSES
+            )
+        })
+    }
+
+    /// Register a running process and monitor it until it exits or is killed
+    #[allow(clippy::too_many_arguments)]
+    fn track(
+        &self,
+        child: Child,
+        lines: mpsc::UnboundedReceiver<OutputLine>,
+        permit: OwnedSemaphorePermit,
+        command: &str,
+        workdir: &Path,
+        channel_id: i64,
+        session_id: Option<i64>,
+        job_id: Option<&str>,
+        earlier_output: Vec<OutputLine>,
+    ) -> String {
+        let process_id = self.next_id();
         let pid = child.id();
 
         // Create kill channel
         let (kill_tx, kill_rx) = mpsc::channel(1);
 
+        let mut output = OutputBuffer::new(self.output_max_lines);
+        for line in earlier_output {
+            line.push_to(&mut output);
+        }
+
         // Create the process handle
         let handle = ProcessHandle {
             id: process_id.clone(),
             pid,
             command: command.to_string(),
-            workdir: workdir.clone(),
+            workdir: workdir.to_path_buf(),
             channel_id,
+            session_id,
+            job_id: job_id.map(str::to_string),
             status: ProcessStatus::Running,
             started_at: Instant::now(),
             ended_at: None,
-            stdout_buffer: VecDeque::new(),
-            stderr_buffer: VecDeque::new(),
+            output,
             kill_tx: Some(kill_tx),
         };
 
@@ -225,111 +313,77 @@ impl ProcessManager {
         let process_id_clone = process_id.clone();
         let processes = self.processes.clone();
         let broadcaster = self.broadcaster.clone();
+        let job_id = job_id.map(str::to_string);
+        let ports = self.ports.clone();
 
         tokio::spawn(async move {
             Self::monitor_process(
                 child,
-                process_id_clone,
+                lines,
+                process_id_clone.clone(),
                 channel_id,
+                job_id,
                 processes,
                 broadcaster,
                 kill_rx,
                 permit,
             )
             .await;
+            ports.release_process(&process_id_clone);
         });
 
-        Ok(process_id)
+        process_id
     }
 
     /// Monitor a running process, streaming its output
+    #[allow(clippy::too_many_arguments)]
     async fn monitor_process(
         mut child: Child,
+        mut lines: mpsc::UnboundedReceiver<OutputLine>,
         process_id: String,
         channel_id: i64,
+        job_id: Option<String>,
         processes: Arc<DashMap<String, ProcessHandle>>,
         broadcaster: Arc<EventBroadcaster>,
         mut kill_rx: mpsc::Receiver<()>,
-        _permit: tokio::sync::OwnedSemaphorePermit,
+        _permit: OwnedSemaphorePermit,
     ) {
-        let stdout = child.stdout.take();
-        let stderr = child.stderr.take();
-
-        // Spawn stdout reader
-        let stdout_process_id = process_id.clone();
-        let stdout_processes = processes.clone();
-        let stdout_broadcaster = broadcaster.clone();
-        let stdout_handle = tokio::spawn(async move {
-            if let Some(stdout) = stdout {
-                let mut reader = BufReader::new(stdout).lines();
-                while let Ok(Some(line)) = reader.next_line().await {
-                    // Buffer the line
-                    if let Some(mut handle) = stdout_processes.get_mut(&stdout_process_id) {
-                        if handle.stdout_buffer.len() >= MAX_OUTPUT_BUFFER {
-                            handle.stdout_buffer.pop_front();
-                        }
-                        handle.stdout_buffer.push_back(line.clone());
-                    }
-                    // Broadcast the line
-                    stdout_broadcaster.broadcast(GatewayEvent::exec_output(
-                        channel_id,
-                        &line,
-                        "stdout",
-                    ));
-                }
+        let record = |line: OutputLine| {
+            broadcaster.broadcast(line.event(channel_id, job_id.as_deref()));
+            if let Some(mut handle) = processes.get_mut(&process_id) {
+                line.push_to(&mut handle.output);
             }
-        });
+        };
 
-        // Spawn stderr reader
-        let stderr_process_id = process_id.clone();
-        let stderr_processes = processes.clone();
-        let stderr_broadcaster = broadcaster.clone();
-        let stderr_handle = tokio::spawn(async move {
-            if let Some(stderr) = stderr {
-                let mut reader = BufReader::new(stderr).lines();
-                while let Ok(Some(line)) = reader.next_line().await {
-                    // Buffer the line
-                    if let Some(mut handle) = stderr_processes.get_mut(&stderr_process_id) {
-                        if handle.stderr_buffer.len() >= MAX_OUTPUT_BUFFER {
-                            handle.stderr_buffer.pop_front();
-                        }
-                        handle.stderr_buffer.push_back(line.clone());
+        // Wait for process to complete or be killed, buffering output as it arrives
+        let exit_status = loop {
+            tokio::select! {
+                Some(line) = lines.recv() => record(line),
+                status = child.wait() => break status,
+                _ = kill_rx.recv() => {
+                    // Kill signal received
+                    kill_tree(&mut child).await;
+                    if let Some(mut handle) = processes.get_mut(&process_id) {
+                        handle.status = ProcessStatus::Killed;
+                        handle.ended_at = Some(Instant::now());
+                        let duration = handle.duration_ms();
+                        broadcaster.broadcast(GatewayEvent::process_completed(
+                            channel_id,
+                            &process_id,
+                            None,
+                            duration,
+                        ));
                     }
-                    // Broadcast the line
-                    stderr_broadcaster.broadcast(GatewayEvent::exec_output(
-                        channel_id,
-                        &line,
-                        "stderr",
-                    ));
+                    log::info!("[PROCESS_MANAGER] Process {} killed", process_id);
+                    return;
                 }
             }
-        });
-
-        // Wait for process to complete or be killed
-        let exit_status = tokio::select! {
-            status = child.wait() => status,
-            _ = kill_rx.recv() => {
-                // Kill signal received
-                let _ = child.kill().await;
-                if let Some(mut handle) = processes.get_mut(&process_id) {
-                    handle.status = ProcessStatus::Killed;
-                    handle.ended_at = Some(Instant::now());
-                    let duration = handle.duration_ms();
-                    broadcaster.broadcast(GatewayEvent::process_completed(
-                        channel_id,
-                        &process_id,
-                        None,
-                        duration,
-                    ));
-                }
-                log::info!("[PROCESS_MANAGER] Process {} killed", process_id);
-                return;
-            }
         };
 
-        // Wait for output readers to finish
-        let _ = stdout_handle.await;
-        let _ = stderr_handle.await;
+        // Read what is left in the pipes
+        while let Some(line) = lines.recv().await {
+            record(line);
+        }
 
         // Update process status
         match exit_status {
@@ -385,14 +439,7 @@ impl ProcessManager {
 
     /// Get full process info
     pub fn get(&self, process_id: &str) -> Option<ProcessInfo> {
-        self.processes.get(process_id).map(|h| ProcessInfo {
-            id: h.id.clone(),
-            pid: h.pid,
-            command: h.command.clone(),
-            status: h.status.clone(),
-            duration_ms: h.duration_ms(),
-            channel_id: h.channel_id,
-        })
+        self.processes.get(process_id).map(|h| h.info())
     }
 
     /// Kill a background process
@@ -408,11 +455,81 @@ impl ProcessManager {
         false
     }
 
+    /// Kill all running processes started by a chat session
+    ///
+    /// Returns the number of processes that were signalled.
+    pub async fn kill_all_for_session(&self, session_id: i64) -> usize {
+        let killed = self.kill_where(|h| h.session_id == Some(session_id)).await;
+        if killed > 0 {
+            log::info!(
+                "[PROCESS_MANAGER] Killed {} background process(es) for session {}",
+                killed,
+                session_id
+            );
+        }
+        killed
+    }
+
+    /// Kill all running processes started by an agent job
+    ///
+    /// Returns the number of processes that were signalled.
+    pub async fn kill_all_for_job(&self, job_id: &str) -> usize {
+        let killed = self.kill_where(|h| h.job_id.as_deref() == Some(job_id)).await;
+        if killed > 0 {
+            log::info!("[PROCESS_MANAGER] Killed {} background process(es) for job {}", killed, job_id);
+        }
+        killed
+    }
+
+    /// Kill every running process and wait (up to `KILL_WAIT`) for them to
+    /// exit, so none outlive the server
+    ///
+    /// Returns the number of processes that were signalled.
+    pub async fn kill_all(&self) -> usize {
+        let killed = self.kill_where(|_| true).await;
+        if killed > 0 {
+            log::info!("[PROCESS_MANAGER] Killing {} background process(es) on shutdown", killed);
+            let deadline = Instant::now() + KILL_WAIT;
+            while self.processes.iter().any(|p| p.status == ProcessStatus::Running) && Instant::now() < deadline {
+                tokio::time::sleep(Duration::from_millis(50)).await;
+            }
+        }
+        killed
+    }
+
+    async fn kill_where(&self, owned: impl Fn(&ProcessHandle) -> bool) -> usize {
+        let process_ids: Vec<String> = self
+            .processes
+            .iter()
+            .filter(|entry| owned(entry.value()) && entry.value().status == ProcessStatus::Running)
+            .map(|entry| entry.key().clone())
+            .collect();
+
+        let mut killed = 0;
+        for process_id in process_ids {
+            if self.kill(&process_id).await {
+                killed += 1;
+            }
+        }
+        killed
+    }
+
     /// Get recent output from a process
-    pub fn output(&self, process_id: &str, lines: usize) -> Option<Vec<String>> {
+    ///
+    /// The returned cursor can be passed to `output_since` to follow new output.
+    pub fn output(&self, process_id: &str, lines: usize) -> Option<OutputChunk> {
+        self.processes.get(process_id).map(|h| OutputChunk {
+            lines: h.output.tail(lines),
+            cursor: h.output.cursor(),
+            skipped: 0,
+        })
+    }
+
+    /// Get output written after `cursor`, for following a process incrementally
+    pub fn output_since(&self, process_id: &str, cursor: u64, limit: usize) -> Option<OutputChunk> {
         self.processes
             .get(process_id)
-            .map(|h| h.recent_output(lines))
+            .map(|h| h.output.since(cursor, limit))
     }
 
     /// List all processes for a channel
@@ -420,36 +537,37 @@ impl ProcessManager {
         self.processes
             .iter()
             .filter(|entry| entry.value().channel_id == channel_id)
-            .map(|entry| {
-                let h = entry.value();
-                ProcessInfo {
-                    id: h.id.clone(),
-                    pid: h.pid,
-                    command: h.command.clone(),
-                    status: h.status.clone(),
-                    duration_ms: h.duration_ms(),
-                    channel_id: h.channel_id,
-                }
-            })
+            .map(|entry| entry.value().info())
             .collect()
     }
 
+    /// List all processes (running and completed) started by a background job
+    pub fn list_for_job(&self, job_id: &str) -> Vec<ProcessInfo> {
+        self.processes
+            .iter()
+            .filter(|entry| entry.value().job_id.as_deref() == Some(job_id))
+            .map(|entry| entry.value().info())
+            .collect()
+    }
+
+    /// Whether a job (or, outside a job, a chat session) still has a process running
+    pub fn has_running(&self, job_id: Option<&str>, session_id: Option<i64>) -> bool {
+        self.processes.iter().any(|entry| {
+            let handle = entry.value();
+            let owned = match job_id {
+                Some(job_id) => handle.job_id.as_deref() == Some(job_id),
+                None => session_id.is_some() && handle.session_id == session_id,
+            };
+            owned && handle.status == ProcessStatus::Running
+        })
+    }
+
     /// List all active (running) processes
     pub fn list_active(&self) -> Vec<ProcessInfo> {
         self.processes
             .iter()
             .filter(|entry| entry.value().status == ProcessStatus::Running)
-            .map(|entry| {
-                let h = entry.value();
-                ProcessInfo {
-                    id: h.id.clone(),
-                    pid: h.pid,
-                    command: h.command.clone(),
-                    status: h.status.clone(),
-                    duration_ms: h.duration_ms(),
-                    channel_id: h.channel_id,
-                }
-            })
+            .map(|entry| entry.value().info())
             .collect()
     }
 
@@ -475,6 +593,75 @@ impl ProcessManager {
     }
 }
 
+/// A line a process wrote, and whether it came from stderr
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OutputLine {
+    pub line: String,
+    pub stderr: bool,
+}
+
+impl OutputLine {
+    fn push_to(self, buffer: &mut OutputBuffer) {
+        if self.stderr {
+            buffer.push_stderr(&self.line);
+        } else {
+            buffer.push_stdout(self.line);
+        }
+    }
+
+    /// Gateway event streaming this line
+    pub fn event(&self, channel_id: i64, job_id: Option<&str>) -> GatewayEvent {
+        let stream = if self.stderr { "stderr" } else { "stdout" };
+        GatewayEvent::exec_output(channel_id, job_id, &self.line, stream)
+    }
+}
+
+/// Read a child's stdout and stderr line by line, in the order the lines
+/// arrive. The channel closes once both pipes are closed.
+pub fn stream_lines(child: &mut Child) -> mpsc::UnboundedReceiver<OutputLine> {
+    let (tx, rx) = mpsc::unbounded_channel();
+    if let Some(stdout) = child.stdout.take() {
+        tokio::spawn(forward_lines(stdout, false, tx.clone()));
+    }
+    if let Some(stderr) = child.stderr.take() {
+        tokio::spawn(forward_lines(stderr, true, tx));
+    }
+    rx
+}
+
+async fn forward_lines(pipe: impl AsyncRead + Unpin, stderr: bool, tx: mpsc::UnboundedSender<OutputLine>) {
+    let mut reader = BufReader::new(pipe).lines();
+    while let Ok(Some(line)) = reader.next_line().await {
+        if tx.send(OutputLine { line, stderr }).is_err() {
+            break;
+        }
+    }
+}
+
+/// Start the command in its own process group, so that `kill_tree` also
+/// stops whatever it spawned
+pub fn isolate(cmd: &mut std::process::Command) {
+    #[cfg(unix)]
+    {
+        use std::os::unix::process::CommandExt;
+        cmd.process_group(0);
+    }
+    #[cfg(not(unix))]
+    let _ = cmd;
+}
+
+/// Kill a process started with `isolate`, and its process group, and reap it
+pub async fn kill_tree(child: &mut Child) {
+    #[cfg(unix)]
+    if let Some(pid) = child.id() {
+        // SAFETY: signalling a process group has no memory safety preconditions
+        unsafe {
+            libc::killpg(pid as libc::pid_t, libc::SIGKILL);
+        }
+    }
+    let _ = child.kill().await;
+}
+
 /// Lightweight process info for listing
 #[derive(Debug, Clone)]
 pub struct ProcessInfo {
@@ -484,9 +671,20 @@ pub struct ProcessInfo {
     pub status: ProcessStatus,
     pub duration_ms: i64,
     pub channel_id: i64,
+    pub session_id: Option<i64>,
+    pub job_id: Option<String>,
 }
 
 impl ProcessInfo {
+    /// Who the process belongs to, for display
+    pub fn owner(&self) -> String {
+        match (&self.job_id, self.session_id) {
+            (Some(job_id), _) => format!("job {}", job_id),
+            (None, Some(session_id)) => format!("session {}", session_id),
+            (None, None) => format!("channel {}", self.channel_id),
+        }
+    }
+
     /// Convert to JSON value
     pub fn to_json(&self) -> serde_json::Value {
         serde_json::json!({
@@ -495,7 +693,9 @@ impl ProcessInfo {
             "command": self.command,
             "status": self.status.to_string(),
             "duration_ms": self.duration_ms,
-            "channel_id": self.channel_id
+            "channel_id": self.channel_id,
+            "session_id": self.session_id,
+            "job_id": self.job_id
         })
     }
 }
@@ -545,8 +745,31 @@ mod tests {
 
         let output = manager.output(&process_id, 10);
         assert!(output.is_some());
-        let lines = output.unwrap();
-        assert!(!lines.is_empty());
+        let chunk = output.unwrap();
+        assert_eq!(chunk.lines, vec!["line1", "line2", "line3"]);
+        assert_eq!(chunk.cursor, 3);
+    }
+
+    #[tokio::test]
+    async fn test_follow_output_with_cursor() {
+        let manager = create_test_manager();
+        let workdir = PathBuf::from("/tmp");
+
+        let process_id = manager
+            .spawn("echo first; echo oops >&2; sleep 0.3; echo second", &workdir, 1, None)
+            .await
+            .unwrap();
+
+        tokio::time::sleep(tokio::time::Duration::from_millis(150)).await;
+        let chunk = manager.output_since(&process_id, 0, 100).unwrap();
+        assert_eq!(chunk.lines.len(), 2);
+        assert!(chunk.lines.contains(&"[stderr] oops".to_string()));
+
+        tokio::time::sleep(tokio::time::Duration::from_millis(400)).await;
+        let next = manager.output_since(&process_id, chunk.cursor, 100).unwrap();
+        assert_eq!(next.lines, vec!["second"]);
+        assert_eq!(next.skipped, 0);
+        assert!(manager.output_since("proc_missing", 0, 100).is_none());
     }
 
     #[tokio::test]
@@ -574,4 +797,79 @@ mod tests {
         let status = manager.status(&process_id);
         assert_eq!(status, Some(ProcessStatus::Killed));
     }
+
+    #[tokio::test]
+    async fn test_kill_all_for_session_only_affects_that_session() {
+        let manager = create_test_manager();
+        let workdir = PathBuf::from("/tmp");
+
+        let mine = manager
+            .spawn_for_session("sleep 10", &workdir, 0, Some(7), None)
+            .await
+            .unwrap();
+        let other = manager
+            .spawn_for_session("sleep 10", &workdir, 0, Some(8), None)
+            .await
+            .unwrap();
+
+        assert_eq!(manager.kill_all_for_session(7).await, 1);
+
+        tokio::time::sleep(tokio::time::Duration::from_millis(100)).await;
+
+        assert_eq!(manager.status(&mine), Some(ProcessStatus::Killed));
+        assert_eq!(manager.status(&other), Some(ProcessStatus::Running));
+
+        manager.kill(&other).await;
+    }
+
+    #[tokio::test]
+    async fn test_has_running_follows_the_owner() {
+        let manager = create_test_manager();
+        let workdir = PathBuf::from("/tmp");
+
+        let job = manager.spawn_for_job("sleep 10", &workdir, "job-a", None).await.unwrap();
+        manager
+            .spawn_for_session("true", &workdir, 0, Some(7), None)
+            .await
+            .unwrap();
+        tokio::time::sleep(tokio::time::Duration::from_millis(200)).await;
+
+        assert!(manager.has_running(Some("job-a"), None));
+        assert!(!manager.has_running(Some("job-b"), None));
+        assert!(!manager.has_running(None, Some(7)));
+        assert!(!manager.has_running(None, None));
+
+        manager.kill(&job).await;
+        tokio::time::sleep(tokio::time::Duration::from_millis(100)).await;
+        assert!(!manager.has_running(Some("job-a"), None));
+    }
+
+    #[tokio::test]
+    async fn test_kill_all_for_job_only_affects_that_job() {
+        let manager = create_test_manager();
+        let workdir = PathBuf::from("/tmp");
+
+        let mine = manager.spawn_for_job("sleep 10", &workdir, "job-a", None).await.unwrap();
+        let other = manager.spawn_for_job("sleep 10", &workdir, "job-b", None).await.unwrap();
+        let session = manager
+            .spawn_for_session("sleep 10", &workdir, 0, Some(7), None)
+            .await
+            .unwrap();
+
+        assert_eq!(manager.kill_all_for_job("job-a").await, 1);
+
+        tokio::time::sleep(tokio::time::Duration::from_millis(100)).await;
+
+        assert_eq!(manager.status(&mine), Some(ProcessStatus::Killed));
+        assert_eq!(manager.status(&other), Some(ProcessStatus::Running));
+        assert_eq!(manager.status(&session), Some(ProcessStatus::Running));
+
+        let listed = manager.list_for_job("job-b");
+        assert_eq!(listed.len(), 1);
+        assert_eq!(listed[0].id, other);
+        assert_eq!(listed[0].owner(), "job job-b");
+
+        manager.kill(&other).await;
+        manager.kill(&session).await;
+    }
 }
@@ -1,6 +1,7 @@
 use crate::gateway::events::EventBroadcaster;
 use crate::gateway::protocol::GatewayEvent;
 use crate::models::{ExecutionTask, TaskMetrics, TaskStatus, TaskType};
+use crate::utils::truncate_str;
 use dashmap::DashMap;
 use std::sync::Arc;
 use tokio_util::sync::CancellationToken;
@@ -296,16 +297,8 @@ impl ExecutionTracker {
         // Create descriptive execution task based on user message
         let (description, active_form) = match user_message {
             Some(msg) => {
-                let truncated = if msg.len() > 60 {
-                    format!("{}...", &msg[..57])
-                } else {
-                    msg.to_string()
-                };
-                let short = if msg.len() > 30 {
-                    format!("{}...", &msg[..27])
-                } else {
-                    msg.to_string()
-                };
+                let truncated = truncate_str(msg, 57);
+                let short = truncate_str(msg, 27);
                 (truncated, short)
             }
             None => {
@@ -346,6 +339,20 @@ impl ExecutionTracker {
         self.channel_executions.get(&channel_id).map(|v| v.clone())
     }
 
+    /// Record the chat session an execution runs in, once it is known; tasks
+    /// started under it inherit the session
+    pub fn set_execution_session(&self, execution_id: &str, session_id: i64) {
+        if let Some(mut task) = self.tasks.get_mut(execution_id) {
+            task.session_id = Some(session_id);
+        }
+    }
+
+    /// The chat session of a channel's current execution
+    pub fn execution_session(&self, channel_id: i64) -> Option<i64> {
+        let execution_id = self.get_execution_id(channel_id)?;
+        self.tasks.get(&execution_id).and_then(|task| task.session_id)
+    }
+
     /// Add a thinking event to the current execution
     pub fn add_thinking(&self, channel_id: i64, text: &str) {
         if let Some(execution_id) = self.get_execution_id(channel_id) {
@@ -443,6 +450,22 @@ impl ExecutionTracker {
                     .unwrap_or(path);
                 (format!("Reading {}", filename), format!("Reading {}", filename))
             }
+            "file_stat" => {
+                let path = args.get("path").and_then(|v| v.as_str()).unwrap_or("file");
+                let filename = std::path::Path::new(path)
+                    .file_name()
+                    .and_then(|n| n.to_str())
+                    .unwrap_or(path);
+                (format!("Inspecting {}", filename), format!("Inspecting {}", filename))
+            }
+            "checksum" => {
+                let path = args.get("path").and_then(|v| v.as_str()).unwrap_or("file");
+                let filename = std::path::Path::new(path)
+                    .file_name()
+                    .and_then(|n| n.to_str())
+                    .unwrap_or(path);
+                (format!("Hashing {}", filename), format!("Hashing {}", filename))
+            }
             "write_file" | "write" => {
                 let path = args.get("path")
                     .or_else(|| args.get("file_path"))
@@ -484,6 +507,10 @@ impl ExecutionTracker {
                 (format!("Fetching {}", host), format!("Fetching {}", host))
             }
             // Shell/exec operations
+            "doctor" => (
+                "Checking installed toolchains".to_string(),
+                "Checking installed toolchains".to_string(),
+            ),
             "exec" | "shell" | "bash" => {
                 let cmd = args.get("command")
                     .or_else(|| args.get("cmd"))
@@ -503,21 +530,13 @@ impl ExecutionTracker {
                             .split('/')
                             .next()
                             .unwrap_or(&url);
-                        let short_url = if url.len() > 60 {
-                            format!("{}...", &url[..57])
-                        } else {
-                            url.to_string()
-                        };
+                        let short_url = truncate_str(&url, 57);
                         (format!("curl {}", short_url), format!("Calling {}", host))
                     } else {
                         ("Running curl".to_string(), "Running curl".to_string())
                     }
                 } else {
-                    let short_cmd = if cmd.len() > 50 {
-                        format!("{}...", &cmd[..47])
-                    } else {
-                        cmd.to_string()
-                    };
+                    let short_cmd = truncate_str(cmd, 47);
                     (format!("Running: {}", short_cmd), format!("Running {}", first_word))
                 }
             }
@@ -532,11 +551,7 @@ impl ExecutionTracker {
                 let input = args.get("input")
                     .and_then(|v| v.as_str())
                     .unwrap_or("");
-                let short_input = if input.len() > 40 {
-                    format!("{}...", &input[..37])
-                } else {
-                    input.to_string()
-                };
+                let short_input = truncate_str(input, 37);
                 if input.is_empty() {
                     (format!("Using skill: {}", skill), format!("Using {}", skill))
                 } else {
@@ -550,11 +565,7 @@ impl ExecutionTracker {
                     .or_else(|| args.get("description"))
                     .and_then(|v| v.as_str())
                     .unwrap_or("task");
-                let short_task = if task.len() > 40 {
-                    format!("{}...", &task[..37])
-                } else {
-                    task.to_string()
-                };
+                let short_task = truncate_str(task, 37);
                 (format!("Agent: {}", short_task), "Running agent".to_string())
             }
 
@@ -567,11 +578,7 @@ impl ExecutionTracker {
                     .and_then(|v| v.as_str())
                     .or_else(|| args.get("queries").and_then(|v| v.as_array()).map(|_| "multiple queries"))
                     .unwrap_or("...");
-                let short = if query.len() > 30 {
-                    format!("{}...", &query[..27])
-                } else {
-                    query.to_string()
-                };
+                let short = truncate_str(query, 27);
                 (format!("Recalling: {}", short), "Searching memory".to_string())
             }
 
@@ -587,11 +594,7 @@ impl ExecutionTracker {
             // User interaction
             "ask_user" => {
                 let question = args.get("question").and_then(|v| v.as_str()).unwrap_or("question");
-                let short_q = if question.len() > 30 {
-                    format!("{}...", &question[..30])
-                } else {
-                    question.to_string()
-                };
+                let short_q = truncate_str(question, 30);
                 (format!("Asking: {}", short_q), "Asking user".to_string())
             }
 
@@ -601,6 +604,10 @@ impl ExecutionTracker {
                 let network = args.get("network").and_then(|v| v.as_str()).unwrap_or("base");
                 (format!("RPC {} on {}", method, network), format!("RPC {}", method))
             }
+            "tx_lookup" => {
+                let network = args.get("network").and_then(|v| v.as_str()).unwrap_or("base");
+                (format!("Looking up transaction on {}", network), "Looking up transaction".to_string())
+            }
 
             // Default fallback
             _ => {
@@ -765,6 +772,11 @@ impl ExecutionTracker {
         }
     }
 
+    /// Executions in progress, on channels and in isolated (cron) sessions
+    pub fn running_executions(&self) -> usize {
+        self.channel_executions.len() + self.session_executions.len()
+    }
+
     /// Get a task by ID
     pub fn get_task(&self, task_id: &str) -> Option<ExecutionTask> {
         self.tasks.get(task_id).map(|t| t.clone())
@@ -817,6 +829,19 @@ mod tests {
         assert!(tracker.get_execution_id(1).is_none());
     }
 
+    #[test]
+    fn test_execution_session() {
+        let tracker = create_test_tracker();
+
+        let execution_id = tracker.start_execution(0, "execute", Some("Hello"));
+        assert_eq!(tracker.execution_session(0), None);
+        tracker.set_execution_session(&execution_id, 42);
+        assert_eq!(tracker.execution_session(0), Some(42));
+
+        tracker.complete_execution(0);
+        assert_eq!(tracker.execution_session(0), None);
+    }
+
     #[test]
     fn test_metrics_aggregation() {
         let tracker = create_test_tracker();
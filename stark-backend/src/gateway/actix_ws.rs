@@ -7,6 +7,7 @@ use crate::db::Database;
 use crate::gateway::events::EventBroadcaster;
 use crate::gateway::methods;
 use crate::gateway::protocol::{ChannelIdParams, RpcError, RpcRequest, RpcResponse};
+use crate::models::{Scope, Scopes};
 use actix_web::{web, HttpRequest, HttpResponse};
 use actix_ws::AggregatedMessage;
 use futures_util::StreamExt;
@@ -65,14 +66,14 @@ async fn handle_ws_connection(
         .max_continuation_size(64 * 1024);
 
     // Phase 1: Authentication required before full access
-    let authenticated = match tokio::time::timeout(
+    let scopes = match tokio::time::timeout(
         Duration::from_secs(AUTH_TIMEOUT_SECS),
         wait_for_auth(&mut session, &mut msg_stream, &db),
     )
     .await
     {
-        Ok(Ok(true)) => true,
-        Ok(Ok(false)) => {
+        Ok(Ok(Some(scopes))) => scopes,
+        Ok(Ok(None)) => {
             log::warn!("Gateway client failed authentication");
             let _ = session.close(None).await;
             return;
@@ -96,11 +97,6 @@ async fn handle_ws_connection(
         }
     };
 
-    if !authenticated {
-        let _ = session.close(None).await;
-        return;
-    }
-
     log::info!("Gateway client authenticated successfully");
 
     // Phase 2: Full access after authentication
@@ -154,7 +150,7 @@ async fn handle_ws_connection(
         match msg_result {
             Ok(AggregatedMessage::Text(text)) => {
                 log::debug!("[DATAGRAM] <<< FROM AGENT (RPC request):\n{}", text);
-                let response = process_request(&text, &db, &channel_manager, &broadcaster).await;
+                let response = process_request(&text, &scopes, &db, &channel_manager, &broadcaster).await;
                 if let Ok(json) = serde_json::to_string(&response) {
                     let _ = tx.send(json).await;
                 }
@@ -182,12 +178,13 @@ async fn handle_ws_connection(
     log::info!("Gateway client {} disconnected", client_id);
 }
 
-/// Wait for authentication from the client
+/// Wait for authentication from the client; returns the token's scopes on success.
+/// The event stream carries every conversation, so it needs an admin user.
 async fn wait_for_auth(
     session: &mut actix_ws::Session,
     msg_stream: &mut (impl StreamExt<Item = Result<AggregatedMessage, actix_ws::ProtocolError>> + Unpin),
     db: &Arc<Database>,
-) -> Result<bool, Box<dyn std::error::Error + Send + Sync>> {
+) -> Result<Option<Scopes>, Box<dyn std::error::Error + Send + Sync>> {
     while let Some(msg_result) = msg_stream.next().await {
         match msg_result {
             Ok(AggregatedMessage::Text(text)) => {
@@ -227,8 +224,8 @@ async fn wait_for_auth(
                         };
 
                         // Validate token against database
-                        match db.validate_session(&params.token) {
-                            Ok(Some(_session)) => {
+                        match db.authenticate(&params.token).await {
+                            Ok(Some(caller)) if caller.scopes.allows(Scope::Read) && caller.is_admin() => {
                                 let response = RpcResponse::success(
                                     request.id,
                                     serde_json::json!({"authenticated": true}),
@@ -236,7 +233,27 @@ async fn wait_for_auth(
                                 if let Ok(json) = serde_json::to_string(&response) {
                                     let _ = session.text(json).await;
                                 }
-                                return Ok(true);
+                                return Ok(Some(caller.scopes));
+                            }
+                            Ok(Some(caller)) if !caller.is_admin() => {
+                                let response = RpcResponse::error(
+                                    request.id,
+                                    RpcError::new(-32001, "The event stream is only open to admin users".to_string()),
+                                );
+                                if let Ok(json) = serde_json::to_string(&response) {
+                                    let _ = session.text(json).await;
+                                }
+                                return Ok(None);
+                            }
+                            Ok(Some(_)) => {
+                                let response = RpcResponse::error(
+                                    request.id,
+                                    RpcError::new(-32001, format!("Token lacks the {} scope", Scope::Read)),
+                                );
+                                if let Ok(json) = serde_json::to_string(&response) {
+                                    let _ = session.text(json).await;
+                                }
+                                return Ok(None);
                             }
                             Ok(None) => {
                                 let response = RpcResponse::error(
@@ -246,7 +263,7 @@ async fn wait_for_auth(
                                 if let Ok(json) = serde_json::to_string(&response) {
                                     let _ = session.text(json).await;
                                 }
-                                return Ok(false);
+                                return Ok(None);
                             }
                             Err(e) => {
                                 log::error!("Database error validating token: {}", e);
@@ -257,7 +274,7 @@ async fn wait_for_auth(
                                 if let Ok(json) = serde_json::to_string(&response) {
                                     let _ = session.text(json).await;
                                 }
-                                return Ok(false);
+                                return Ok(None);
                             }
                         }
                     }
@@ -286,7 +303,7 @@ async fn wait_for_auth(
                 let _ = session.pong(&data).await;
             }
             Ok(AggregatedMessage::Close(_)) => {
-                return Ok(false);
+                return Ok(None);
             }
             Err(e) => {
                 log::error!("WebSocket error during auth: {:?}", e);
@@ -296,11 +313,12 @@ async fn wait_for_auth(
         }
     }
 
-    Ok(false)
+    Ok(None)
 }
 
 async fn process_request(
     text: &str,
+    scopes: &Scopes,
     db: &Arc<Database>,
     channel_manager: &Arc<ChannelManager>,
     broadcaster: &Arc<EventBroadcaster>,
@@ -314,7 +332,7 @@ async fn process_request(
 
     let id = request.id.clone();
 
-    let result = dispatch_method(&request, db, channel_manager, broadcaster).await;
+    let result = dispatch_method(&request, scopes, db, channel_manager, broadcaster).await;
 
     match result {
         Ok(value) => RpcResponse::success(id, value),
@@ -324,10 +342,16 @@ async fn process_request(
 
 async fn dispatch_method(
     request: &RpcRequest,
+    scopes: &Scopes,
     db: &Arc<Database>,
     channel_manager: &Arc<ChannelManager>,
     broadcaster: &Arc<EventBroadcaster>,
 ) -> Result<serde_json::Value, RpcError> {
+    // Starting and stopping channels changes bot state; everything else is read-only
+    if request.method.starts_with("channels.") && request.method != "channels.status" && !scopes.allows(Scope::Admin) {
+        return Err(RpcError::new(-32001, format!("Token lacks the {} scope", Scope::Admin)));
+    }
+
     match request.method.as_str() {
         "ping" => methods::handle_ping().await,
         "status" => methods::handle_status(broadcaster.clone()).await,
@@ -0,0 +1,131 @@
+//! Tracks open WebSocket chat connections and the agent run each one has in flight
+
+use chrono::{DateTime, Utc};
+use dashmap::DashMap;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use tokio::task::JoinHandle;
+use uuid::Uuid;
+
+/// An agent run started from a chat connection
+pub struct ChatRun {
+    pub handle: JoinHandle<()>,
+    /// Set when the user interrupts the run, so its late result is not delivered
+    pub interrupted: Arc<AtomicBool>,
+}
+
+impl ChatRun {
+    pub fn is_finished(&self) -> bool {
+        self.handle.is_finished()
+    }
+
+    pub fn mark_interrupted(&self) {
+        self.interrupted.store(true, Ordering::SeqCst);
+    }
+}
+
+struct ChatConnection {
+    user_id: String,
+    connected_at: DateTime<Utc>,
+    run: Option<ChatRun>,
+}
+
+/// Registry of live chat connections, keyed by connection id
+pub struct ChatConnectionManager {
+    connections: DashMap<String, ChatConnection>,
+}
+
+impl ChatConnectionManager {
+    pub fn new() -> Self {
+        Self {
+            connections: DashMap::new(),
+        }
+    }
+
+    /// Register a new connection and return its id
+    pub fn register(&self, user_id: &str) -> String {
+        let connection_id = Uuid::new_v4().to_string();
+        self.connections.insert(
+            connection_id.clone(),
+            ChatConnection {
+                user_id: user_id.to_string(),
+                connected_at: Utc::now(),
+                run: None,
+            },
+        );
+        log::debug!("Chat connection {} registered for {}", connection_id, user_id);
+        connection_id
+    }
+
+    /// Remove a connection. A run still in flight keeps going; its session is persisted.
+    pub fn unregister(&self, connection_id: &str) {
+        if let Some((_, conn)) = self.connections.remove(connection_id) {
+            log::debug!(
+                "Chat connection {} for {} closed after {}s",
+                connection_id,
+                conn.user_id,
+                (Utc::now() - conn.connected_at).num_seconds()
+            );
+        }
+    }
+
+    /// Record the run now in flight for a connection
+    pub fn set_run(&self, connection_id: &str, run: ChatRun) {
+        if let Some(mut conn) = self.connections.get_mut(connection_id) {
+            conn.run = Some(run);
+        }
+    }
+
+    /// Take the connection's run if it is still in flight
+    pub fn take_active_run(&self, connection_id: &str) -> Option<ChatRun> {
+        let mut conn = self.connections.get_mut(connection_id)?;
+        conn.run.take().filter(|run| !run.is_finished())
+    }
+
+    /// Number of open chat connections
+    pub fn connection_count(&self) -> usize {
+        self.connections.len()
+    }
+}
+
+impl Default for ChatConnectionManager {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn run(handle: JoinHandle<()>) -> ChatRun {
+        ChatRun {
+            handle,
+            interrupted: Arc::new(AtomicBool::new(false)),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_register_and_run_tracking() {
+        let manager = ChatConnectionManager::new();
+        let id = manager.register("web-abc");
+        assert_eq!(manager.connection_count(), 1);
+
+        // A finished run is not reported as active
+        let done = tokio::spawn(async {});
+        tokio::time::sleep(std::time::Duration::from_millis(10)).await;
+        manager.set_run(&id, run(done));
+        assert!(manager.take_active_run(&id).is_none());
+
+        // A pending run is handed back exactly once
+        let pending = tokio::spawn(std::future::pending::<()>());
+        manager.set_run(&id, run(pending));
+        let active = manager.take_active_run(&id).unwrap();
+        assert!(manager.take_active_run(&id).is_none());
+        active.handle.abort();
+
+        manager.unregister(&id);
+        assert_eq!(manager.connection_count(), 0);
+        assert!(manager.take_active_run(&id).is_none());
+    }
+}
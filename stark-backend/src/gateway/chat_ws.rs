@@ -0,0 +1,465 @@
+//! WebSocket chat channel
+//!
+//! A persistent alternative to `POST /api/chat`: the client authenticates once,
+//! sends messages, and receives the agent's tool progress and stream events for
+//! the web channel as they happen, followed by the final response. Sending
+//! `stop`, or a new message while a run is in flight, interrupts that run.
+//!
+//! Events of the web channel aren't tagged with the user they belong to, so
+//! only admins get them; other users receive just their own responses.
+
+use crate::ai::budget::BudgetUsage;
+use crate::ai::ArchetypeId;
+use crate::channels::NormalizedMessage;
+use crate::controllers::chat::{cancel_web_execution, web_chat_id, WEB_CHANNEL_ID, WEB_CHANNEL_TYPE};
+use crate::gateway::chat_connections::ChatRun;
+use crate::gateway::protocol::GatewayEvent;
+use crate::models::{Caller, Scope};
+use crate::utils::truncate_chars;
+use crate::AppState;
+use actix_web::{web, HttpRequest, HttpResponse};
+use actix_ws::AggregatedMessage;
+use futures_util::StreamExt;
+use serde::{Deserialize, Serialize};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::mpsc;
+
+/// Client must authenticate within this time
+const AUTH_TIMEOUT_SECS: u64 = 30;
+
+/// How long an interrupted run gets to wind down before it is aborted
+const INTERRUPT_GRACE_SECS: u64 = 10;
+
+/// Messages sent by the client
+#[derive(Debug, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum ChatClientMessage {
+    Auth {
+        token: String,
+        #[serde(default)]
+        user_id: Option<String>,
+    },
+    Message {
+        content: String,
+        #[serde(default)]
+        seed: Option<u64>,
+        #[serde(default)]
+        model_archetype: Option<String>,
+    },
+    Stop,
+    Ping,
+}
+
+/// Messages sent by the server (gateway events are forwarded as-is, with `"type": "event"`)
+#[derive(Debug, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum ChatServerMessage {
+    Ready {
+        connection_id: String,
+    },
+    Response {
+        content: String,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        usage: Option<BudgetUsage>,
+    },
+    Interrupted {
+        reason: String,
+    },
+    Error {
+        message: String,
+    },
+    Pong,
+}
+
+impl ChatServerMessage {
+    fn error(message: impl Into<String>) -> Self {
+        ChatServerMessage::Error {
+            message: message.into(),
+        }
+    }
+
+    fn to_json(&self) -> String {
+        serde_json::to_string(self).unwrap_or_default()
+    }
+}
+
+/// Whether an event belongs to the web chat channel
+fn is_web_chat_event(event: &GatewayEvent) -> bool {
+    event.data.get("channel_id").and_then(|v| v.as_i64()) == Some(WEB_CHANNEL_ID)
+}
+
+/// WebSocket handler for `/ws/chat`
+pub async fn chat_ws_handler(
+    req: HttpRequest,
+    stream: web::Payload,
+    state: web::Data<AppState>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let (response, session, msg_stream) = actix_ws::handle(&req, stream)?;
+    actix_web::rt::spawn(handle_chat_connection(session, msg_stream, state));
+    Ok(response)
+}
+
+async fn handle_chat_connection(
+    mut session: actix_ws::Session,
+    msg_stream: actix_ws::MessageStream,
+    state: web::Data<AppState>,
+) {
+    let mut msg_stream = msg_stream
+        .aggregate_continuations()
+        .max_continuation_size(64 * 1024);
+
+    // Phase 1: authenticate
+    let (user_id, caller) = match tokio::time::timeout(
+        Duration::from_secs(AUTH_TIMEOUT_SECS),
+        wait_for_auth(&mut session, &mut msg_stream, &state),
+    )
+    .await
+    {
+        Ok(Some(auth)) => auth,
+        Ok(None) => {
+            let _ = session.close(None).await;
+            return;
+        }
+        Err(_) => {
+            tracing::warn!("[CHAT_WS] Client auth timeout after {}s", AUTH_TIMEOUT_SECS);
+            let _ = session
+                .text(ChatServerMessage::error("Authentication timeout").to_json())
+                .await;
+            let _ = session.close(None).await;
+            return;
+        }
+    };
+
+    // Phase 2: register the connection and start forwarding events
+    let connections = state.gateway.chat_connections();
+    let connection_id = connections.register(&user_id);
+    let (client_id, mut event_rx) = state.broadcaster.subscribe();
+    tracing::info!(
+        "[CHAT_WS] Connection {} opened for {} ({} open)",
+        connection_id,
+        user_id,
+        connections.connection_count()
+    );
+
+    let forward_events = caller.is_admin();
+    let (tx, mut rx) = mpsc::channel::<String>(100);
+    let mut send_session = session.clone();
+    let send_task = tokio::spawn(async move {
+        loop {
+            tokio::select! {
+                Some(msg) = rx.recv() => {
+                    if send_session.text(msg).await.is_err() {
+                        break;
+                    }
+                }
+                Some(event) = event_rx.recv() => {
+                    if !forward_events || !is_web_chat_event(&event) {
+                        continue;
+                    }
+                    if let Ok(json) = serde_json::to_string(&event)
+                        && send_session.text(json).await.is_err()
+                    {
+                        break;
+                    }
+                }
+                else => break,
+            }
+        }
+    });
+
+    let _ = tx
+        .send(
+            ChatServerMessage::Ready {
+                connection_id: connection_id.clone(),
+            }
+            .to_json(),
+        )
+        .await;
+
+    while let Some(msg_result) = msg_stream.next().await {
+        let text = match msg_result {
+            Ok(AggregatedMessage::Text(text)) => text,
+            Ok(AggregatedMessage::Ping(data)) => {
+                if session.pong(&data).await.is_err() {
+                    break;
+                }
+                continue;
+            }
+            Ok(AggregatedMessage::Close(_)) => break,
+            Err(e) => {
+                tracing::error!("[CHAT_WS] WebSocket error: {:?}", e);
+                break;
+            }
+            _ => continue,
+        };
+
+        let message = match serde_json::from_str::<ChatClientMessage>(&text) {
+            Ok(m) => m,
+            Err(e) => {
+                let _ = tx
+                    .send(ChatServerMessage::error(format!("Invalid message: {}", e)).to_json())
+                    .await;
+                continue;
+            }
+        };
+
+        match message {
+            ChatClientMessage::Ping => {
+                let _ = tx.send(ChatServerMessage::Pong.to_json()).await;
+            }
+            ChatClientMessage::Auth { .. } => {
+                let _ = tx
+                    .send(ChatServerMessage::error("Already authenticated").to_json())
+                    .await;
+            }
+            ChatClientMessage::Stop => match connections.take_active_run(&connection_id) {
+                Some(run) => {
+                    interrupt_run(&state, &caller, run).await;
+                    let _ = tx
+                        .send(ChatServerMessage::Interrupted { reason: "stop".to_string() }.to_json())
+                        .await;
+                }
+                None => {
+                    let _ = tx
+                        .send(ChatServerMessage::error("Nothing is running").to_json())
+                        .await;
+                }
+            },
+            ChatClientMessage::Message { content, seed, model_archetype } => {
+                if content.trim().is_empty() {
+                    let _ = tx
+                        .send(ChatServerMessage::error("Message content cannot be empty").to_json())
+                        .await;
+                    continue;
+                }
+
+                let model_archetype = match model_archetype.as_deref().map(|name| (name, ArchetypeId::from_str(name))) {
+                    None => None,
+                    Some((_, Some(id))) => Some(id),
+                    Some((name, None)) => {
+                        let _ = tx
+                            .send(ChatServerMessage::error(format!("Invalid archetype: {}", name)).to_json())
+                            .await;
+                        continue;
+                    }
+                };
+
+                // A new message mid-run changes direction: stop the current run first
+                if let Some(run) = connections.take_active_run(&connection_id) {
+                    interrupt_run(&state, &caller, run).await;
+                    let _ = tx
+                        .send(
+                            ChatServerMessage::Interrupted {
+                                reason: "new_message".to_string(),
+                            }
+                            .to_json(),
+                        )
+                        .await;
+                }
+
+                let run = start_run(
+                    &state,
+                    &user_id,
+                    &caller,
+                    content,
+                    seed,
+                    model_archetype,
+                    tx.clone(),
+                );
+                connections.set_run(&connection_id, run);
+            }
+        }
+    }
+
+    // Cleanup
+    connections.unregister(&connection_id);
+    state.broadcaster.unsubscribe(&client_id);
+    send_task.abort();
+    let _ = session.close(None).await;
+    tracing::info!("[CHAT_WS] Connection {} closed", connection_id);
+}
+
+/// Wait for the `auth` message; returns the chat user id and who the token
+/// belongs to on success
+async fn wait_for_auth(
+    session: &mut actix_ws::Session,
+    msg_stream: &mut (impl StreamExt<Item = Result<AggregatedMessage, actix_ws::ProtocolError>> + Unpin),
+    state: &web::Data<AppState>,
+) -> Option<(String, Caller)> {
+    while let Some(msg_result) = msg_stream.next().await {
+        let text = match msg_result {
+            Ok(AggregatedMessage::Text(text)) => text,
+            Ok(AggregatedMessage::Ping(data)) => {
+                let _ = session.pong(&data).await;
+                continue;
+            }
+            Ok(AggregatedMessage::Close(_)) => return None,
+            Err(e) => {
+                tracing::error!("[CHAT_WS] WebSocket error during auth: {:?}", e);
+                return None;
+            }
+            _ => continue,
+        };
+
+        let reply = match serde_json::from_str::<ChatClientMessage>(&text) {
+            Ok(ChatClientMessage::Auth { token, user_id }) => match state.db.authenticate(&token).await {
+                Ok(Some(caller)) if caller.scopes.allows(Scope::Chat) => {
+                    // Same derivation as the REST chat endpoint, so both share a session
+                    return Some((web_chat_id(&caller, &token, user_id.as_deref()), caller));
+                }
+                Ok(Some(_)) => {
+                    let _ = session
+                        .text(ChatServerMessage::error(format!("Token lacks the {} scope", Scope::Chat)).to_json())
+                        .await;
+                    return None;
+                }
+                Ok(None) => {
+                    let _ = session
+                        .text(ChatServerMessage::error("Invalid or expired session").to_json())
+                        .await;
+                    return None;
+                }
+                Err(e) => {
+                    tracing::error!("[CHAT_WS] Failed to validate session: {}", e);
+                    let _ = session
+                        .text(ChatServerMessage::error("Internal server error").to_json())
+                        .await;
+                    return None;
+                }
+            },
+            Ok(ChatClientMessage::Ping) => ChatServerMessage::Pong,
+            Ok(_) => ChatServerMessage::error("Authentication required. Send an 'auth' message first."),
+            Err(e) => ChatServerMessage::error(format!("Invalid message: {}", e)),
+        };
+        let _ = session.text(reply.to_json()).await;
+    }
+
+    None
+}
+
+/// Dispatch a message through the unified pipeline in the background
+fn start_run(
+    state: &web::Data<AppState>,
+    user_id: &str,
+    caller: &Caller,
+    text: String,
+    seed: Option<u64>,
+    model_archetype: Option<ArchetypeId>,
+    tx: mpsc::Sender<String>,
+) -> ChatRun {
+    let normalized = NormalizedMessage {
+        channel_id: WEB_CHANNEL_ID,
+        channel_type: WEB_CHANNEL_TYPE.to_string(),
+        chat_id: user_id.to_string(),
+        user_id: user_id.to_string(),
+        user_name: format!("web-user-{}", truncate_chars(user_id, 8)),
+        text,
+        message_id: None,
+        session_mode: None,
+        seed,
+        model_archetype,
+        model_overrides: None,
+        use_tools: None,
+        response_format: None,
+        images: Vec::new(),
+        scopes: Some(caller.scopes.clone()),
+        tool_policy: caller.tool_policy.clone(),
+        owner_id: Some(caller.user.id),
+    };
+
+    let dispatcher = state.dispatcher.clone();
+    let interrupted = Arc::new(AtomicBool::new(false));
+    let run_interrupted = interrupted.clone();
+
+    let handle = tokio::spawn(async move {
+        let result = dispatcher.dispatch(normalized).await;
+
+        // The client was already told this run was interrupted
+        if run_interrupted.load(Ordering::SeqCst) {
+            return;
+        }
+
+        let reply = match result.error {
+            Some(error) => {
+                tracing::error!("[CHAT_WS] Dispatch error: {}", error);
+                ChatServerMessage::error(error)
+            }
+            None => ChatServerMessage::Response {
+                content: result.response,
+                usage: result.usage,
+            },
+        };
+        let _ = tx.send(reply.to_json()).await;
+    });
+
+    ChatRun { handle, interrupted }
+}
+
+/// Cancel the web channel's execution, as far as `caller` may, and wait for
+/// the run to wind down
+async fn interrupt_run(state: &web::Data<AppState>, caller: &Caller, run: ChatRun) {
+    run.mark_interrupted();
+
+    tracing::info!("[CHAT_WS] Interrupting execution for web channel {}", WEB_CHANNEL_ID);
+    cancel_web_execution(state, caller).await;
+
+    // The dispatcher checks for cancellation between steps; give it time to
+    // persist what it has before forcing the task down
+    let abort = run.handle.abort_handle();
+    if tokio::time::timeout(Duration::from_secs(INTERRUPT_GRACE_SECS), run.handle)
+        .await
+        .is_err()
+    {
+        tracing::warn!(
+            "[CHAT_WS] Run did not stop within {}s, aborting",
+            INTERRUPT_GRACE_SECS
+        );
+        abort.abort();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_client_message_parsing() {
+        let msg: ChatClientMessage =
+            serde_json::from_value(json!({ "type": "message", "content": "hi" })).unwrap();
+        assert!(matches!(msg, ChatClientMessage::Message { ref content, seed: None, model_archetype: None } if content == "hi"));
+
+        let msg: ChatClientMessage = serde_json::from_value(json!({ "type": "stop" })).unwrap();
+        assert!(matches!(msg, ChatClientMessage::Stop));
+
+        assert!(serde_json::from_value::<ChatClientMessage>(json!({ "type": "shout" })).is_err());
+    }
+
+    #[test]
+    fn test_server_message_serialization() {
+        let json: serde_json::Value = serde_json::from_str(
+            &ChatServerMessage::Interrupted { reason: "stop".to_string() }.to_json(),
+        )
+        .unwrap();
+        assert_eq!(json, json!({ "type": "interrupted", "reason": "stop" }));
+
+        let json: serde_json::Value = serde_json::from_str(
+            &ChatServerMessage::Response { content: "done".to_string(), usage: None }.to_json(),
+        )
+        .unwrap();
+        assert_eq!(json, json!({ "type": "response", "content": "done" }));
+    }
+
+    #[test]
+    fn test_only_web_channel_events_are_forwarded() {
+        assert!(is_web_chat_event(&GatewayEvent::agent_tool_call(
+            WEB_CHANNEL_ID,
+            "read_file",
+            &json!({})
+        )));
+        assert!(!is_web_chat_event(&GatewayEvent::agent_tool_call(5, "read_file", &json!({}))));
+        assert!(!is_web_chat_event(&GatewayEvent::custom("status", json!({}))));
+    }
+}
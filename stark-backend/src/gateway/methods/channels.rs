@@ -10,7 +10,7 @@ pub async fn handle_channels_status(
     channel_manager: Arc<ChannelManager>,
 ) -> Result<Value, RpcError> {
     let channels = db
-        .list_channels()
+        .list_channels().await
         .map_err(|e| RpcError::internal_error(format!("Database error: {}", e)))?;
 
     let responses: Vec<ChannelResponse> = channels
@@ -31,7 +31,7 @@ pub async fn handle_channels_start(
 ) -> Result<Value, RpcError> {
     // Get the channel from database
     let channel = db
-        .get_channel(params.id)
+        .get_channel(params.id).await
         .map_err(|e| RpcError::internal_error(format!("Database error: {}", e)))?
         .ok_or_else(|| RpcError::invalid_params(format!("Channel {} not found", params.id)))?;
 
@@ -42,7 +42,7 @@ pub async fn handle_channels_start(
         .map_err(|e| RpcError::internal_error(e))?;
 
     // Update enabled status in database
-    db.set_channel_enabled(params.id, true)
+    db.set_channel_enabled(params.id, true).await
         .map_err(|e| RpcError::internal_error(format!("Database error: {}", e)))?;
 
     Ok(serde_json::json!({
@@ -63,7 +63,7 @@ pub async fn handle_channels_stop(
         .map_err(|e| RpcError::internal_error(e))?;
 
     // Update enabled status in database
-    db.set_channel_enabled(params.id, false)
+    db.set_channel_enabled(params.id, false).await
         .map_err(|e| RpcError::internal_error(format!("Database error: {}", e)))?;
 
     Ok(serde_json::json!({
@@ -82,7 +82,7 @@ pub async fn handle_channels_restart(
 
     // Get the channel from database
     let channel = db
-        .get_channel(params.id)
+        .get_channel(params.id).await
         .map_err(|e| RpcError::internal_error(format!("Database error: {}", e)))?
         .ok_or_else(|| RpcError::invalid_params(format!("Channel {} not found", params.id)))?;
 
@@ -1,8 +1,11 @@
 pub mod actix_ws;
+pub mod chat_connections;
+pub mod chat_ws;
 pub mod events;
 pub mod methods;
 pub mod protocol;
 
+pub use chat_connections::ChatConnectionManager;
 pub use events::EventBroadcaster;
 
 use crate::channels::ChannelManager;
@@ -15,6 +18,7 @@ pub struct Gateway {
     db: Arc<Database>,
     channel_manager: Arc<ChannelManager>,
     broadcaster: Arc<EventBroadcaster>,
+    chat_connections: Arc<ChatConnectionManager>,
 }
 
 impl Gateway {
@@ -26,6 +30,7 @@ impl Gateway {
             db,
             channel_manager,
             broadcaster,
+            chat_connections: Arc::new(ChatConnectionManager::new()),
         }
     }
 
@@ -52,12 +57,13 @@ impl Gateway {
             db,
             channel_manager,
             broadcaster,
+            chat_connections: Arc::new(ChatConnectionManager::new()),
         }
     }
 
     /// Start all channels that are marked as enabled in the database
     pub async fn start_enabled_channels(&self) {
-        match self.db.list_enabled_channels() {
+        match self.db.list_enabled_channels().await {
             Ok(channels) => {
                 for channel in channels {
                     let id = channel.id;
@@ -76,7 +82,7 @@ impl Gateway {
                                 e
                             );
                             // Disable the channel in DB since it failed to start
-                            let _ = self.db.set_channel_enabled(id, false);
+                            let _ = self.db.set_channel_enabled(id, false).await;
                         }
                     }
                 }
@@ -92,6 +98,11 @@ impl Gateway {
         self.broadcaster.clone()
     }
 
+    /// Get the registry of open WebSocket chat connections
+    pub fn chat_connections(&self) -> Arc<ChatConnectionManager> {
+        self.chat_connections.clone()
+    }
+
     /// Get the channel manager
     pub fn channel_manager(&self) -> Arc<ChannelManager> {
         self.channel_manager.clone()
@@ -875,12 +875,13 @@ impl GatewayEvent {
     // Process Execution Events
     // =====================================================
 
-    /// Real-time output line from exec command
-    pub fn exec_output(channel_id: i64, line: &str, stream: &str) -> Self {
+    /// Real-time output line from exec command, tagged with the agent job running it
+    pub fn exec_output(channel_id: i64, job_id: Option<&str>, line: &str, stream: &str) -> Self {
         Self::new(
             EventType::ExecOutput,
             serde_json::json!({
                 "channel_id": channel_id,
+                "job_id": job_id,
                 "line": line,
                 "stream": stream,  // "stdout" or "stderr"
                 "timestamp": chrono::Utc::now().to_rfc3339()
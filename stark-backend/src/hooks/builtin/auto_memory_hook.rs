@@ -155,7 +155,7 @@ impl AutoMemoryHook {
     }
 
     /// Create the ephemeral memory in the database
-    fn create_memory(&self, context: &HookContext, content: String) {
+    async fn create_memory(&self, context: &HookContext, content: String) {
         let tool_name = context.tool_name.clone().unwrap_or_default();
         let expires_at = Utc::now() + Duration::seconds(self.config.ttl_secs);
 
@@ -171,7 +171,7 @@ impl AutoMemoryHook {
             None, // source_message_id
             Some(Utc::now().date_naive()),
             Some(expires_at),
-        );
+        ).await;
 
         match result {
             Ok(memory) => {
@@ -253,7 +253,7 @@ impl Hook for AutoMemoryHook {
 
         // Create the memory (synchronously, but should be fast)
         log::info!("[AutoMemoryHook] Creating ephemeral memory: {}", content);
-        self.create_memory(context, content);
+        self.create_memory(context, content).await;
 
         HookResult::Continue(None)
     }
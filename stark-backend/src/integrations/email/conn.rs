@@ -0,0 +1,93 @@
+//! Line-oriented TCP/TLS connections shared by the SMTP and IMAP clients
+
+use std::time::Duration;
+use tokio::io::{AsyncBufReadExt, AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt, BufReader};
+use tokio::net::TcpStream;
+
+/// Limit on any single network read or connect
+const IO_TIMEOUT: Duration = Duration::from_secs(30);
+/// Longest response line accepted from a server
+const MAX_LINE: usize = 64 * 1024;
+
+pub(super) trait Io: AsyncRead + AsyncWrite + Unpin + Send {}
+impl<T: AsyncRead + AsyncWrite + Unpin + Send> Io for T {}
+
+pub(super) struct Conn {
+    stream: BufReader<Box<dyn Io>>,
+}
+
+impl Conn {
+    pub fn new(stream: Box<dyn Io>) -> Self {
+        Self { stream: BufReader::new(stream) }
+    }
+
+    /// Connect to `host:port`, with TLS from the start when `tls` is set
+    pub async fn connect(host: &str, port: u16, tls: bool) -> Result<Self, String> {
+        let tcp = tokio::time::timeout(IO_TIMEOUT, TcpStream::connect((host, port)))
+            .await
+            .map_err(|_| format!("Timed out connecting to {}:{}", host, port))?
+            .map_err(|e| format!("Failed to connect to {}:{}: {}", host, port, e))?;
+        let stream: Box<dyn Io> = if tls { Box::new(tls_handshake(tcp, host).await?) } else { Box::new(tcp) };
+        Ok(Self::new(stream))
+    }
+
+    /// Upgrade the connection to TLS (SMTP STARTTLS)
+    pub async fn starttls(self, host: &str) -> Result<Self, String> {
+        if !self.stream.buffer().is_empty() {
+            return Err("Server sent data before the TLS handshake".to_string());
+        }
+        let inner = self.stream.into_inner();
+        Ok(Self::new(Box::new(tls_handshake(inner, host).await?)))
+    }
+
+    /// Read one line, without its CRLF
+    pub async fn read_line(&mut self) -> Result<String, String> {
+        let mut buf = Vec::new();
+        let read = tokio::time::timeout(IO_TIMEOUT, (&mut self.stream).take(MAX_LINE as u64).read_until(b'\n', &mut buf))
+            .await
+            .map_err(|_| "Timed out waiting for the server".to_string())?
+            .map_err(|e| format!("Read failed: {}", e))?;
+        if read == 0 {
+            return Err("Connection closed by the server".to_string());
+        }
+        if !buf.ends_with(b"\n") {
+            return Err("Server response line too long".to_string());
+        }
+        while buf.last().is_some_and(|b| *b == b'\n' || *b == b'\r') {
+            buf.pop();
+        }
+        Ok(String::from_utf8_lossy(&buf).into_owned())
+    }
+
+    /// Read exactly `len` bytes (IMAP literals)
+    pub async fn read_exact(&mut self, len: usize) -> Result<Vec<u8>, String> {
+        let mut buf = vec![0; len];
+        tokio::time::timeout(IO_TIMEOUT, self.stream.read_exact(&mut buf))
+            .await
+            .map_err(|_| "Timed out waiting for the server".to_string())?
+            .map_err(|e| format!("Read failed: {}", e))?;
+        Ok(buf)
+    }
+
+    pub async fn write_all(&mut self, data: &[u8]) -> Result<(), String> {
+        let stream = self.stream.get_mut();
+        tokio::time::timeout(IO_TIMEOUT, async {
+            stream.write_all(data).await?;
+            stream.flush().await
+        })
+        .await
+        .map_err(|_| "Timed out writing to the server".to_string())?
+        .map_err(|e| format!("Write failed: {}", e))
+    }
+}
+
+async fn tls_handshake<S>(stream: S, host: &str) -> Result<tokio_native_tls::TlsStream<S>, String>
+where
+    S: AsyncRead + AsyncWrite + Unpin,
+{
+    let connector = native_tls::TlsConnector::new().map_err(|e| format!("TLS setup failed: {}", e))?;
+    tokio::time::timeout(IO_TIMEOUT, tokio_native_tls::TlsConnector::from(connector).connect(host, stream))
+        .await
+        .map_err(|_| format!("Timed out during the TLS handshake with {}", host))?
+        .map_err(|e| format!("TLS handshake with {} failed: {}", host, e))
+}
@@ -0,0 +1,181 @@
+//! Minimal IMAP client for the task inbox
+//!
+//! Just enough of IMAP4rev1 to log in, find unseen messages, fetch them and
+//! flag them as seen. Connections use implicit TLS (port 993) except to
+//! loopback hosts such as a local mail bridge.
+
+use super::conn::Conn;
+use super::is_loopback;
+
+/// Bytes fetched per message; text parts come before attachments in practice
+const FETCH_BYTES: usize = 2 * 1024 * 1024;
+
+/// An untagged response line, with the literals embedded in it
+#[derive(Debug, Default)]
+struct Untagged {
+    line: String,
+    literals: Vec<Vec<u8>>,
+}
+
+pub(super) struct ImapClient {
+    conn: Conn,
+    next_tag: u32,
+}
+
+impl ImapClient {
+    pub async fn connect(host: &str, port: u16) -> Result<Self, String> {
+        let conn = Conn::connect(host, port, !is_loopback(host)).await?;
+        Self::start(conn).await
+    }
+
+    async fn start(mut conn: Conn) -> Result<Self, String> {
+        let greeting = conn.read_line().await?;
+        if !greeting.starts_with("* OK") && !greeting.starts_with("* PREAUTH") {
+            return Err(format!("Unexpected IMAP greeting: {}", greeting));
+        }
+        Ok(Self { conn, next_tag: 1 })
+    }
+
+    pub async fn login(&mut self, user: &str, password: &str) -> Result<(), String> {
+        self.command(&format!("LOGIN {} {}", quote(user)?, quote(password)?))
+            .await
+            .map(|_| ())
+            .map_err(|e| format!("IMAP login failed: {}", e))
+    }
+
+    pub async fn select(&mut self, mailbox: &str) -> Result<(), String> {
+        self.command(&format!("SELECT {}", quote(mailbox)?)).await.map(|_| ())
+    }
+
+    /// UIDs of the messages not yet flagged as seen
+    pub async fn unseen(&mut self) -> Result<Vec<u32>, String> {
+        let responses = self.command("UID SEARCH UNSEEN").await?;
+        Ok(responses
+            .iter()
+            .filter_map(|r| r.line.strip_prefix("* SEARCH"))
+            .flat_map(|ids| ids.split_whitespace().filter_map(|id| id.parse().ok()))
+            .collect())
+    }
+
+    /// The raw message, without setting its seen flag
+    pub async fn fetch(&mut self, uid: u32) -> Result<Vec<u8>, String> {
+        let responses = self.command(&format!("UID FETCH {} (BODY.PEEK[]<0.{}>)", uid, FETCH_BYTES)).await?;
+        responses
+            .into_iter()
+            .find(|r| r.line.contains(" FETCH ") && !r.literals.is_empty())
+            .and_then(|r| r.literals.into_iter().next())
+            .ok_or_else(|| format!("Message {} not found", uid))
+    }
+
+    pub async fn mark_seen(&mut self, uid: u32) -> Result<(), String> {
+        self.command(&format!("UID STORE {} +FLAGS.SILENT (\\Seen)", uid)).await.map(|_| ())
+    }
+
+    pub async fn logout(mut self) {
+        let _ = self.command("LOGOUT").await;
+    }
+
+    /// Run a command and collect its untagged responses until the tagged one
+    async fn command(&mut self, command: &str) -> Result<Vec<Untagged>, String> {
+        let tag = format!("a{}", self.next_tag);
+        self.next_tag += 1;
+        self.conn.write_all(format!("{} {}\r\n", tag, command).as_bytes()).await?;
+
+        let mut responses = Vec::new();
+        loop {
+            let mut response = Untagged { line: self.conn.read_line().await?, literals: Vec::new() };
+            while let Some(len) = literal_len(&response.line) {
+                if len > FETCH_BYTES + 1024 {
+                    return Err(format!("IMAP literal too large ({} bytes)", len));
+                }
+                response.literals.push(self.conn.read_exact(len).await?);
+                let rest = self.conn.read_line().await?;
+                response.line.push_str(&rest);
+            }
+
+            if let Some(status) = response.line.strip_prefix(&tag).map(str::trim_start) {
+                return if status.starts_with("OK") {
+                    Ok(responses)
+                } else {
+                    Err(status.to_string())
+                };
+            }
+            if response.line.starts_with('+') {
+                return Err(format!("Unexpected continuation request: {}", response.line));
+            }
+            responses.push(response);
+        }
+    }
+}
+
+/// Length of the literal announced at the end of a line (`{123}`)
+fn literal_len(line: &str) -> Option<usize> {
+    let inner = line.strip_suffix('}')?;
+    let start = inner.rfind('{')?;
+    inner[start + 1..].trim_end_matches('+').parse().ok()
+}
+
+/// An IMAP quoted string
+fn quote(value: &str) -> Result<String, String> {
+    if value.contains(['\r', '\n', '\0']) {
+        return Err("IMAP strings may not contain line breaks".to_string());
+    }
+    Ok(format!("\"{}\"", value.replace('\\', "\\\\").replace('"', "\\\"")))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+
+    #[tokio::test]
+    async fn test_imap_session() {
+        let (client, server) = tokio::io::duplex(64 * 1024);
+        let server = tokio::spawn(async move {
+            let (read, mut write) = tokio::io::split(server);
+            let mut lines = BufReader::new(read).lines();
+            let mut received = Vec::new();
+            write.write_all(b"* OK IMAP4rev1 ready\r\n").await.unwrap();
+            while let Some(line) = lines.next_line().await.unwrap() {
+                let (tag, command) = line.split_once(' ').unwrap();
+                let body = match command.split(' ').take(2).collect::<Vec<_>>().join(" ").as_str() {
+                    "UID SEARCH" => "* SEARCH 7 9\r\n".to_string(),
+                    "UID FETCH" => "* 1 FETCH (UID 7 BODY[]<0> {17}\r\nSubject: hi\r\n\r\nyo)\r\n".to_string(),
+                    "LOGIN \"bot\"" if command.contains("wrong") => {
+                        write.write_all(format!("{} NO bad credentials\r\n", tag).as_bytes()).await.unwrap();
+                        continue;
+                    }
+                    _ => String::new(),
+                };
+                write.write_all(format!("{}{} OK done\r\n", body, tag).as_bytes()).await.unwrap();
+                received.push(command.to_string());
+                if command == "LOGOUT" {
+                    break;
+                }
+            }
+            received
+        });
+
+        let mut imap = ImapClient::start(Conn::new(Box::new(client))).await.unwrap();
+        assert!(imap.login("bot", "wrong").await.unwrap_err().contains("bad credentials"));
+        imap.login("bot", "p\"w").await.unwrap();
+        imap.select("INBOX").await.unwrap();
+        assert_eq!(imap.unseen().await.unwrap(), vec![7, 9]);
+        assert_eq!(imap.fetch(7).await.unwrap(), b"Subject: hi\r\n\r\nyo".to_vec());
+        imap.mark_seen(7).await.unwrap();
+        imap.logout().await;
+
+        let received = server.await.unwrap();
+        assert_eq!(received[0], "LOGIN \"bot\" \"p\\\"w\"");
+        assert_eq!(received[3], format!("UID FETCH 7 (BODY.PEEK[]<0.{}>)", FETCH_BYTES));
+        assert_eq!(received[4], "UID STORE 7 +FLAGS.SILENT (\\Seen)");
+    }
+
+    #[test]
+    fn test_literal_len() {
+        assert_eq!(literal_len("* 1 FETCH (BODY[] {342}"), Some(342));
+        assert_eq!(literal_len("a1 LOGIN {5+}"), Some(5));
+        assert_eq!(literal_len("* OK {not}"), None);
+        assert_eq!(literal_len("* OK done"), None);
+    }
+}
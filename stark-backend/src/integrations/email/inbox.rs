@@ -0,0 +1,282 @@
+//! Turns email into agent jobs and mails back their results
+//!
+//! Every poll, unseen mail in the configured IMAP mailbox is fetched. Mail
+//! from an allowlisted sender with a passing DMARC result (unless that check
+//! is turned off) is queued on the [`AgentJobQueue`] with its subject and body
+//! as the task, each in a fresh workspace; all fetched mail is then flagged as
+//! seen so it is handled once. When a queued job finishes, its response or
+//! error is sent back to the sender as a reply in the same thread.
+//!
+//! Pending replies are kept in memory: a job still running at shutdown is
+//! resumed after a restart, but its result is not mailed.
+
+use super::imap::ImapClient;
+use super::message::{address, OutgoingEmail, ReceivedEmail};
+use super::{Credentials, EmailConfig, InboxConfig};
+use crate::agent::runner::DEFAULT_MAX_ITERATIONS;
+use crate::agent::AgentJobQueue;
+use crate::controllers::api_keys::ApiKeyId;
+use crate::db::Database;
+use crate::models::{AgentJob, AgentJobStatus};
+use crate::utils::truncate_chars;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::{oneshot, Mutex};
+
+/// Longest email body passed to the agent, in characters
+const MAX_TASK_CHARS: usize = 8000;
+/// Attempts at mailing a result before it is dropped
+const MAX_REPLY_ATTEMPTS: u32 = 3;
+
+struct PendingReply {
+    job_id: String,
+    reply: OutgoingEmail,
+    attempts: u32,
+}
+
+pub struct EmailInbox {
+    db: Arc<Database>,
+    jobs: Arc<AgentJobQueue>,
+    config: InboxConfig,
+    email: EmailConfig,
+    pending: Mutex<Vec<PendingReply>>,
+}
+
+impl EmailInbox {
+    pub fn new(db: Arc<Database>, jobs: Arc<AgentJobQueue>, config: InboxConfig, email: EmailConfig) -> Self {
+        Self { db, jobs, config, email, pending: Mutex::new(Vec::new()) }
+    }
+
+    /// Poll the mailbox and send replies until shutdown is signalled
+    pub async fn start(self: Arc<Self>, mut shutdown_rx: oneshot::Receiver<()>) {
+        log::info!(
+            "[EMAIL] Polling {}@{} every {}s for tasks",
+            self.config.user,
+            self.config.host,
+            self.config.poll_secs
+        );
+        let mut poll = tokio::time::interval(Duration::from_secs(self.config.poll_secs));
+        loop {
+            tokio::select! {
+                _ = &mut shutdown_rx => {
+                    log::info!("[EMAIL] Inbox shutting down");
+                    return;
+                }
+                _ = poll.tick() => {
+                    if let Err(e) = self.check_mail().await {
+                        log::error!("[EMAIL] Failed to check mail: {}", e);
+                    }
+                    self.send_replies().await;
+                }
+            }
+        }
+    }
+
+    /// Queue a job for every new allowlisted email. Returns the jobs queued.
+    async fn check_mail(&self) -> Result<Vec<AgentJob>, String> {
+        let password = self
+            .secret(ApiKeyId::ImapPassword)
+            .await
+            .ok_or("IMAP_PASSWORD is not set on the API Keys page")?;
+        let mut imap = ImapClient::connect(&self.config.host, self.config.port).await?;
+        imap.login(&self.config.user, &password).await?;
+        imap.select(&self.config.mailbox).await?;
+
+        let mut queued = Vec::new();
+        for uid in imap.unseen().await? {
+            let raw = imap.fetch(uid).await?;
+            match self.accept(&ReceivedEmail::parse(&raw)).await {
+                Ok(Some(job)) => queued.push(job),
+                Ok(None) => {}
+                Err(e) => log::error!("[EMAIL] Failed to queue message {}: {}", uid, e),
+            }
+            imap.mark_seen(uid).await?;
+        }
+        imap.logout().await;
+        Ok(queued)
+    }
+
+    /// Queue `email` as a job if its sender may submit tasks
+    async fn accept(&self, email: &ReceivedEmail) -> Result<Option<AgentJob>, String> {
+        let Some(sender) = address(&email.from).filter(|s| self.config.sender_allowed(s)) else {
+            log::warn!("[EMAIL] Ignoring mail from non-allowlisted sender {}", email.from);
+            return Ok(None);
+        };
+        if self.config.require_dmarc && !email.dmarc_passed() {
+            log::warn!("[EMAIL] Ignoring mail from {}: no DMARC pass recorded by the receiving server", sender);
+            return Ok(None);
+        }
+
+        log::info!("[EMAIL] Task from {}: {}", sender, email.subject);
+        let workspace = format!("email-{}", uuid::Uuid::new_v4());
+        let job = self
+            .jobs
+            .enqueue(&task_prompt(email), &workspace, DEFAULT_MAX_ITERATIONS, &[], None, false, None, None)
+            .await?;
+        self.pending.lock().await.push(PendingReply {
+            job_id: job.job_id.clone(),
+            reply: reply_to(email, sender),
+            attempts: 0,
+        });
+        Ok(Some(job))
+    }
+
+    /// Mail the results of finished jobs
+    async fn send_replies(&self) {
+        let finished = self.take_finished().await;
+        if finished.is_empty() {
+            return;
+        }
+        let credentials = Credentials {
+            resend_api_key: self.secret(ApiKeyId::ResendApiKey).await,
+            smtp_password: self.secret(ApiKeyId::SmtpPassword).await,
+        };
+        for mut pending in finished {
+            match self.email.send(&credentials, &pending.reply).await {
+                Ok(_) => log::info!("[EMAIL] Sent result of job {} to {}", pending.job_id, pending.reply.to.join(", ")),
+                Err(e) => {
+                    pending.attempts += 1;
+                    log::error!("[EMAIL] Failed to send result of job {} (attempt {}): {}", pending.job_id, pending.attempts, e);
+                    if pending.attempts < MAX_REPLY_ATTEMPTS {
+                        self.pending.lock().await.push(pending);
+                    }
+                }
+            }
+        }
+    }
+
+    /// Remove and return the pending replies whose jobs have finished, with their bodies filled in
+    async fn take_finished(&self) -> Vec<PendingReply> {
+        let mut pending = self.pending.lock().await;
+        let mut finished = Vec::new();
+        let mut waiting = Vec::new();
+        for mut entry in pending.drain(..) {
+            match self.db.get_agent_job(&entry.job_id).await {
+                Ok(Some(job)) if job.status.is_finished() => {
+                    if entry.reply.body.is_empty() {
+                        entry.reply.body = reply_body(&job);
+                    }
+                    finished.push(entry);
+                }
+                Ok(Some(_)) => waiting.push(entry),
+                Ok(None) => log::warn!("[EMAIL] Job {} disappeared; no reply sent", entry.job_id),
+                Err(e) => {
+                    log::error!("[EMAIL] Failed to look up job {}: {}", entry.job_id, e);
+                    waiting.push(entry);
+                }
+            }
+        }
+        *pending = waiting;
+        finished
+    }
+
+    async fn secret(&self, id: ApiKeyId) -> Option<String> {
+        match self.db.get_api_key(id.as_str()).await {
+            Ok(key) => key.map(|k| k.api_key).filter(|k| !k.is_empty()),
+            Err(e) => {
+                log::error!("[EMAIL] Failed to read {}: {}", id.as_str(), e);
+                None
+            }
+        }
+    }
+}
+
+/// The agent's task for an email
+fn task_prompt(email: &ReceivedEmail) -> String {
+    let body = truncate_chars(&email.body, MAX_TASK_CHARS);
+    let truncated = if body.len() < email.body.len() { "\n\n[Body truncated]" } else { "" };
+    format!(
+        "Task received by email from {}.\nSubject: {}\n---\n{}{}\n---\nYour final response will be emailed back to the sender as the reply.",
+        email.from, email.subject, body, truncated
+    )
+}
+
+/// The reply to `email`, without its body
+fn reply_to(email: &ReceivedEmail, sender: &str) -> OutgoingEmail {
+    let subject = if email.subject.to_ascii_lowercase().starts_with("re:") {
+        email.subject.clone()
+    } else {
+        format!("Re: {}", email.subject)
+    };
+    let references = match (&email.references, &email.message_id) {
+        (Some(refs), Some(id)) => Some(format!("{} {}", refs, id)),
+        (None, Some(id)) => Some(id.clone()),
+        (refs, None) => refs.clone(),
+    };
+    OutgoingEmail {
+        to: vec![sender.to_string()],
+        subject,
+        in_reply_to: email.message_id.clone(),
+        references,
+        ..Default::default()
+    }
+}
+
+fn reply_body(job: &AgentJob) -> String {
+    let outcome = match job.status {
+        AgentJobStatus::Completed => job.response.clone().filter(|r| !r.trim().is_empty()).unwrap_or_else(|| "Done.".to_string()),
+        AgentJobStatus::Cancelled => "The task was cancelled.".to_string(),
+        _ => format!("The task failed: {}", job.error.as_deref().unwrap_or("unknown error")),
+    };
+    format!("{}\n\n--\nAgent job {} ({} iterations)", outcome, job.job_id, job.iterations)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::execution::ProcessManager;
+    use crate::gateway::events::EventBroadcaster;
+    use serde_json::json;
+
+    #[tokio::test]
+    async fn test_accepted_mail_queues_job_and_reply() {
+        let db = Arc::new(Database::new(":memory:").unwrap());
+        let queue = Arc::new(AgentJobQueue::new(
+            Arc::clone(&db),
+            Arc::new(crate::tools::create_default_registry()),
+            Arc::new(ProcessManager::new(Arc::new(EventBroadcaster::new()))),
+            None,
+        ));
+        let config = InboxConfig {
+            host: "localhost".to_string(),
+            port: 143,
+            user: "bot".to_string(),
+            mailbox: "INBOX".to_string(),
+            poll_secs: 60,
+            allowed_senders: vec!["ann@example.com".to_string()],
+            require_dmarc: true,
+        };
+        let inbox = EmailInbox::new(Arc::clone(&db), queue, config, EmailConfig::default());
+
+        let raw = |from: &str, auth: &str| {
+            format!(
+                "Authentication-Results: mx.stark.dev; {}\r\nFrom: {}\r\nSubject: Build report\r\nMessage-ID: <m1@example.com>\r\nReferences: <m0@example.com>\r\n\r\nSummarize the last build.\r\n",
+                auth, from
+            )
+        };
+        let email = ReceivedEmail::parse(raw("Eve <eve@evil.com>", "dmarc=pass").as_bytes());
+        assert!(inbox.accept(&email).await.unwrap().is_none());
+        let email = ReceivedEmail::parse(raw("Ann <ann@example.com>", "dmarc=fail").as_bytes());
+        assert!(inbox.accept(&email).await.unwrap().is_none());
+
+        let email = ReceivedEmail::parse(raw("Ann <ann@example.com>", "dmarc=pass").as_bytes());
+        let job = inbox.accept(&email).await.unwrap().unwrap();
+        assert!(job.task.contains("Subject: Build report\n---\nSummarize the last build."));
+        assert!(job.workspace.starts_with("email-"));
+        assert!(inbox.take_finished().await.is_empty());
+
+        db.claim_next_agent_job().await.unwrap();
+        db.finish_agent_job(&job.job_id, AgentJobStatus::Completed, 2, &json!([]), &json!([]), Some("All green."), None)
+            .await
+            .unwrap();
+        let finished = inbox.take_finished().await;
+        assert_eq!(finished.len(), 1);
+        let reply = &finished[0].reply;
+        assert_eq!(reply.to, vec!["ann@example.com"]);
+        assert_eq!(reply.subject, "Re: Build report");
+        assert_eq!(reply.in_reply_to.as_deref(), Some("<m1@example.com>"));
+        assert_eq!(reply.references.as_deref(), Some("<m0@example.com> <m1@example.com>"));
+        assert!(reply.body.starts_with("All green.\n\n--\nAgent job "));
+        assert!(inbox.pending.lock().await.is_empty());
+    }
+}
@@ -0,0 +1,438 @@
+//! Building outgoing messages and parsing received ones
+//!
+//! Outgoing mail is a single text/plain part, base64-encoded so any UTF-8 body
+//! survives servers without 8BITMIME. Received mail is parsed just far enough
+//! to get the sender, threading headers and a plain-text body.
+
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD as BASE64;
+use chrono::Utc;
+
+/// A plain-text email to send
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct OutgoingEmail {
+    pub to: Vec<String>,
+    pub cc: Vec<String>,
+    pub reply_to: Option<String>,
+    pub subject: String,
+    pub body: String,
+    /// Message-ID of the email being answered, with angle brackets
+    pub in_reply_to: Option<String>,
+    /// References header of a reply
+    pub references: Option<String>,
+}
+
+impl OutgoingEmail {
+    /// Bare addresses of every To and Cc recipient
+    pub fn recipients(&self) -> impl Iterator<Item = &str> {
+        self.to.iter().chain(&self.cc).filter_map(|a| address(a))
+    }
+
+    /// Reject header injection and malformed addresses
+    pub fn validate(&self) -> Result<(), String> {
+        let headers = self.to.iter().chain(&self.cc).chain(&self.reply_to).chain(std::iter::once(&self.subject));
+        if headers.chain(&self.in_reply_to).chain(&self.references).any(|h| h.contains(['\r', '\n'])) {
+            return Err("Headers may not contain line breaks".to_string());
+        }
+        for addr in self.to.iter().chain(&self.cc).chain(&self.reply_to) {
+            if address(addr).is_none() {
+                return Err(format!("Invalid email address: {}", addr));
+            }
+        }
+        Ok(())
+    }
+
+    /// The full RFC 5322 message, with CRLF line endings
+    pub fn render(&self, from: &str, message_id: &str) -> String {
+        let mut out = String::new();
+        let mut header = |name: &str, value: &str| out.push_str(&format!("{}: {}\r\n", name, value));
+        header("From", from);
+        header("To", &self.to.join(", "));
+        if !self.cc.is_empty() {
+            header("Cc", &self.cc.join(", "));
+        }
+        if let Some(ref reply_to) = self.reply_to {
+            header("Reply-To", reply_to);
+        }
+        header("Subject", &encode_header(&self.subject));
+        header("Date", &Utc::now().to_rfc2822());
+        header("Message-ID", message_id);
+        if let Some(ref in_reply_to) = self.in_reply_to {
+            header("In-Reply-To", in_reply_to);
+        }
+        if let Some(ref references) = self.references {
+            header("References", references);
+        }
+        header("MIME-Version", "1.0");
+        header("Content-Type", "text/plain; charset=utf-8");
+        header("Content-Transfer-Encoding", "base64");
+        out.push_str("\r\n");
+
+        let encoded = BASE64.encode(self.body.replace("\r\n", "\n").replace('\n', "\r\n"));
+        for chunk in encoded.as_bytes().chunks(76) {
+            out.push_str(std::str::from_utf8(chunk).unwrap_or_default());
+            out.push_str("\r\n");
+        }
+        out
+    }
+}
+
+/// A new Message-ID in the sender's domain
+pub fn new_message_id(from: &str) -> String {
+    let domain = address(from).and_then(|a| a.rsplit_once('@')).map(|(_, d)| d).unwrap_or("localhost");
+    format!("<{}@{}>", uuid::Uuid::new_v4(), domain)
+}
+
+/// The bare address in `Name <user@host>` or `user@host`
+pub fn address(value: &str) -> Option<&str> {
+    let value = value.trim();
+    let addr = match (value.rfind('<'), value.rfind('>')) {
+        (Some(start), Some(end)) if start < end => &value[start + 1..end],
+        _ => value,
+    };
+    let addr = addr.trim();
+    let (local, domain) = addr.split_once('@')?;
+    let valid = !local.is_empty()
+        && domain.contains('.')
+        && !domain.starts_with('.')
+        && !domain.ends_with('.')
+        && !addr.contains(|c: char| c.is_whitespace() || c.is_control() || matches!(c, '<' | '>' | ',' | '"'))
+        && !domain.contains('@');
+    valid.then_some(addr)
+}
+
+/// RFC 2047-encode a header value that isn't plain ASCII
+fn encode_header(value: &str) -> String {
+    if value.is_ascii() {
+        value.to_string()
+    } else {
+        format!("=?UTF-8?B?{}?=", BASE64.encode(value))
+    }
+}
+
+/// The parts of a received email the inbox needs
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct ReceivedEmail {
+    /// From header as written
+    pub from: String,
+    pub subject: String,
+    pub message_id: Option<String>,
+    pub references: Option<String>,
+    /// Topmost Authentication-Results header, added by the receiving server
+    pub authentication_results: Option<String>,
+    pub body: String,
+}
+
+impl ReceivedEmail {
+    pub fn parse(raw: &[u8]) -> Self {
+        let (headers, body) = split_message(raw);
+        let header = |name: &str| {
+            headers
+                .iter()
+                .find(|(n, _)| n.eq_ignore_ascii_case(name))
+                .map(|(_, v)| v.clone())
+        };
+        Self {
+            from: header("From").map(|v| decode_header(&v)).unwrap_or_default(),
+            subject: header("Subject").map(|v| decode_header(&v)).unwrap_or_default(),
+            message_id: header("Message-ID"),
+            references: header("References"),
+            authentication_results: header("Authentication-Results"),
+            body: text_body(&headers, body).trim().to_string(),
+        }
+    }
+
+    /// Whether the receiving server recorded a DMARC pass for this message
+    pub fn dmarc_passed(&self) -> bool {
+        self.authentication_results
+            .as_deref()
+            .is_some_and(|ar| ar.to_ascii_lowercase().contains("dmarc=pass"))
+    }
+}
+
+type Headers = Vec<(String, String)>;
+
+/// Split a message into unfolded headers and its body
+fn split_message(raw: &[u8]) -> (Headers, &[u8]) {
+    let (head, body) = match find(raw, b"\r\n\r\n") {
+        Some(i) => (&raw[..i], &raw[i + 4..]),
+        None => match find(raw, b"\n\n") {
+            Some(i) => (&raw[..i], &raw[i + 2..]),
+            None => (raw, &raw[raw.len()..]),
+        },
+    };
+
+    let mut headers: Headers = Vec::new();
+    for line in String::from_utf8_lossy(head).lines() {
+        if line.starts_with([' ', '\t']) {
+            if let Some((_, value)) = headers.last_mut() {
+                value.push(' ');
+                value.push_str(line.trim());
+            }
+        } else if let Some((name, value)) = line.split_once(':') {
+            headers.push((name.trim().to_string(), value.trim().to_string()));
+        }
+    }
+    (headers, body)
+}
+
+fn find(haystack: &[u8], needle: &[u8]) -> Option<usize> {
+    haystack.windows(needle.len()).position(|w| w == needle)
+}
+
+/// A parameter of a structured header like Content-Type (`boundary`, `charset`)
+fn header_param(value: &str, param: &str) -> Option<String> {
+    value.split(';').skip(1).find_map(|part| {
+        let (name, v) = part.split_once('=')?;
+        name.trim().eq_ignore_ascii_case(param).then(|| v.trim().trim_matches('"').to_string())
+    })
+}
+
+/// The best plain-text rendering of a (possibly multipart) body
+fn text_body(headers: &Headers, body: &[u8]) -> String {
+    let get = |name: &str| {
+        headers
+            .iter()
+            .find(|(n, _)| n.eq_ignore_ascii_case(name))
+            .map(|(_, v)| v.as_str())
+    };
+    let content_type = get("Content-Type").unwrap_or("text/plain");
+    let mime = content_type.split(';').next().unwrap_or("").trim().to_ascii_lowercase();
+
+    if mime.starts_with("multipart/") {
+        let Some(boundary) = header_param(content_type, "boundary") else {
+            return String::new();
+        };
+        let parts: Vec<(Headers, &[u8])> = multipart_parts(body, &boundary).into_iter().map(split_message).collect();
+        let is = |h: &Headers, t: &str| {
+            h.iter()
+                .find(|(n, _)| n.eq_ignore_ascii_case("Content-Type"))
+                .map(|(_, v)| v.to_ascii_lowercase().starts_with(t))
+                .unwrap_or(t == "text/plain")
+        };
+        // Prefer a plain-text part, then nested multiparts (alternative inside mixed), then HTML
+        for wanted in ["text/plain", "multipart/", "text/html"] {
+            for (h, b) in &parts {
+                if is(h, wanted) {
+                    let text = text_body(h, b);
+                    if !text.trim().is_empty() {
+                        return text;
+                    }
+                }
+            }
+        }
+        return String::new();
+    }
+    if !mime.starts_with("text/") {
+        return String::new();
+    }
+
+    let encoding = get("Content-Transfer-Encoding").unwrap_or("7bit").to_ascii_lowercase();
+    let bytes = match encoding.as_str() {
+        "base64" => {
+            let compact: Vec<u8> = body.iter().copied().filter(|b| !b.is_ascii_whitespace()).collect();
+            BASE64.decode(compact).unwrap_or_default()
+        }
+        "quoted-printable" => decode_quoted_printable(body, false),
+        _ => body.to_vec(),
+    };
+    let text = decode_charset(&bytes, header_param(content_type, "charset").as_deref());
+    if mime == "text/html" { strip_html(&text) } else { text }
+}
+
+/// The raw parts of a multipart body
+fn multipart_parts<'a>(body: &'a [u8], boundary: &str) -> Vec<&'a [u8]> {
+    let delimiter = format!("--{}", boundary);
+    let mut parts = Vec::new();
+    let mut rest = body;
+    let Some(first) = find(rest, delimiter.as_bytes()) else {
+        return parts;
+    };
+    rest = &rest[first + delimiter.len()..];
+    while !rest.starts_with(b"--") {
+        let start = rest.iter().position(|&b| b == b'\n').map(|i| i + 1).unwrap_or(rest.len());
+        rest = &rest[start..];
+        let end = find(rest, delimiter.as_bytes()).unwrap_or(rest.len());
+        let mut part = &rest[..end];
+        // The line break before the delimiter belongs to the delimiter
+        part = part.strip_suffix(b"\n").unwrap_or(part);
+        part = part.strip_suffix(b"\r").unwrap_or(part);
+        parts.push(part);
+        if end == rest.len() {
+            break;
+        }
+        rest = &rest[end + delimiter.len()..];
+    }
+    parts
+}
+
+/// Decode quoted-printable; `header` also turns `_` into a space (RFC 2047 Q encoding)
+fn decode_quoted_printable(input: &[u8], header: bool) -> Vec<u8> {
+    let mut out = Vec::with_capacity(input.len());
+    let mut i = 0;
+    while i < input.len() {
+        match input[i] {
+            b'=' if input[i + 1..].starts_with(b"\r\n") => i += 3,
+            b'=' if input[i + 1..].starts_with(b"\n") => i += 2,
+            b'=' if i + 2 < input.len() => {
+                match std::str::from_utf8(&input[i + 1..i + 3]).ok().and_then(|h| u8::from_str_radix(h, 16).ok()) {
+                    Some(byte) => {
+                        out.push(byte);
+                        i += 3;
+                    }
+                    None => {
+                        out.push(b'=');
+                        i += 1;
+                    }
+                }
+            }
+            b'_' if header => {
+                out.push(b' ');
+                i += 1;
+            }
+            b => {
+                out.push(b);
+                i += 1;
+            }
+        }
+    }
+    out
+}
+
+/// UTF-8 and ASCII pass through; Latin-1 maps byte for byte; anything else is decoded lossily as UTF-8
+fn decode_charset(bytes: &[u8], charset: Option<&str>) -> String {
+    match charset.map(|c| c.to_ascii_lowercase()) {
+        Some(c) if c == "iso-8859-1" || c == "latin1" || c == "windows-1252" => {
+            bytes.iter().map(|&b| b as char).collect()
+        }
+        _ => String::from_utf8_lossy(bytes).into_owned(),
+    }
+}
+
+/// Decode RFC 2047 encoded words (`=?UTF-8?B?...?=`) in a header value
+fn decode_header(value: &str) -> String {
+    let mut out = String::new();
+    let mut rest = value;
+    let mut after_word = false;
+    while let Some(start) = rest.find("=?") {
+        let decoded = rest[start + 2..].splitn(3, '?').collect::<Vec<_>>();
+        let word = match decoded.as_slice() {
+            [charset, enc, tail] => tail.find("?=").map(|end| (*charset, *enc, &tail[..end], end)),
+            _ => None,
+        };
+        let Some((charset, enc, text, end)) = word else {
+            break;
+        };
+        let bytes = match enc.to_ascii_uppercase().as_str() {
+            "B" => BASE64.decode(text).ok(),
+            "Q" => Some(decode_quoted_printable(text.as_bytes(), true)),
+            _ => None,
+        };
+        let Some(bytes) = bytes else {
+            break;
+        };
+        // Whitespace between adjacent encoded words is dropped
+        let between = &rest[..start];
+        if !(after_word && between.trim().is_empty()) {
+            out.push_str(between);
+        }
+        out.push_str(&decode_charset(&bytes, Some(charset)));
+        after_word = true;
+        let consumed = start + 2 + charset.len() + 1 + enc.len() + 1 + end + 2;
+        rest = &rest[consumed..];
+    }
+    out.push_str(rest);
+    out
+}
+
+/// Crude HTML-to-text for mail with no plain-text part
+fn strip_html(html: &str) -> String {
+    let mut out = String::new();
+    let mut in_tag = false;
+    for c in html.chars() {
+        match c {
+            '<' => in_tag = true,
+            '>' => in_tag = false,
+            c if !in_tag => out.push(c),
+            _ => {}
+        }
+    }
+    out.replace("&nbsp;", " ").replace("&lt;", "<").replace("&gt;", ">").replace("&quot;", "\"").replace("&amp;", "&")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_render_and_validate() {
+        let email = OutgoingEmail {
+            to: vec!["Ann <ann@example.com>".to_string()],
+            cc: vec!["bob@example.com".to_string()],
+            subject: "Résumé".to_string(),
+            body: "Line one\nLine two".to_string(),
+            in_reply_to: Some("<1@example.com>".to_string()),
+            ..Default::default()
+        };
+        assert!(email.validate().is_ok());
+        assert_eq!(email.recipients().collect::<Vec<_>>(), vec!["ann@example.com", "bob@example.com"]);
+
+        let raw = email.render("Bot <bot@stark.dev>", "<x@stark.dev>");
+        assert!(raw.contains("To: Ann <ann@example.com>\r\nCc: bob@example.com\r\n"));
+        assert!(raw.contains("Subject: =?UTF-8?B?UsOpc3Vtw6k=?=\r\n"));
+        assert!(raw.contains("In-Reply-To: <1@example.com>\r\n"));
+        let parsed = ReceivedEmail::parse(raw.as_bytes());
+        assert_eq!(parsed.subject, "Résumé");
+        assert_eq!(parsed.body, "Line one\r\nLine two");
+        assert_eq!(parsed.message_id.as_deref(), Some("<x@stark.dev>"));
+
+        let injected = OutgoingEmail { subject: "Hi\r\nBcc: eve@evil.com".to_string(), ..email.clone() };
+        assert!(injected.validate().is_err());
+        let bad = OutgoingEmail { to: vec!["ann@localhost".to_string()], ..email };
+        assert!(bad.validate().is_err());
+        assert_eq!(new_message_id("Bot <bot@stark.dev>").split_once('@').unwrap().1, "stark.dev>");
+    }
+
+    #[test]
+    fn test_parse_multipart() {
+        let raw = concat!(
+            "Authentication-Results: mx.stark.dev; dkim=pass; dmarc=pass (p=REJECT)\r\n",
+            "From: =?utf-8?Q?Ann_M=C3=BCller?= <ann@example.com>\r\n",
+            "Subject: Deploy the\r\n =?UTF-8?B?c3RhZ2luZw==?= build\r\n",
+            "Message-ID: <abc@example.com>\r\n",
+            "Content-Type: multipart/mixed; boundary=\"outer\"\r\n",
+            "\r\n",
+            "preamble\r\n",
+            "--outer\r\n",
+            "Content-Type: multipart/alternative; boundary=inner\r\n",
+            "\r\n",
+            "--inner\r\n",
+            "Content-Type: text/html\r\n",
+            "\r\n",
+            "<p>ignored</p>\r\n",
+            "--inner\r\n",
+            "Content-Type: text/plain; charset=utf-8\r\n",
+            "Content-Transfer-Encoding: quoted-printable\r\n",
+            "\r\n",
+            "Please deploy caf=C3=A9 to sta=\r\n",
+            "ging.\r\n",
+            "--inner--\r\n",
+            "--outer\r\n",
+            "Content-Type: application/pdf\r\n",
+            "\r\n",
+            "%PDF\r\n",
+            "--outer--\r\n",
+        );
+        let email = ReceivedEmail::parse(raw.as_bytes());
+        assert_eq!(email.from, "Ann Müller <ann@example.com>");
+        assert_eq!(address(&email.from), Some("ann@example.com"));
+        assert_eq!(email.subject, "Deploy the staging build");
+        assert_eq!(email.body, "Please deploy café to staging.");
+        assert!(email.dmarc_passed());
+
+        let html_only = b"From: ann@example.com\nContent-Type: text/html; charset=iso-8859-1\n\n<b>caf\xe9</b> &amp; more";
+        let email = ReceivedEmail::parse(html_only);
+        assert_eq!(email.body, "caf\u{e9} & more");
+        assert!(!email.dmarc_passed());
+    }
+}
@@ -0,0 +1,231 @@
+//! Email integration
+//!
+//! Outgoing mail is sent through the Resend API when `RESEND_API_KEY` is set,
+//! otherwise over SMTP, and only to recipients whose domain is on the
+//! allowlist. The optional inbox polls an IMAP mailbox, turns mail from
+//! allowlisted senders into agent jobs and replies in-thread with each job's
+//! result.
+//!
+//! ## Setup
+//! 1. Set `STARK_EMAIL_FROM` and `STARK_EMAIL_ALLOWED_DOMAINS`
+//! 2. Add `RESEND_API_KEY`, or set `STARK_SMTP_HOST` / `STARK_SMTP_USER` and add
+//!    `SMTP_PASSWORD` on the API Keys page
+//! 3. For the inbox, set `STARK_IMAP_HOST`, `STARK_IMAP_USER` and
+//!    `STARK_EMAIL_ALLOWED_SENDERS`, and add `IMAP_PASSWORD`
+
+mod conn;
+mod imap;
+mod inbox;
+mod message;
+mod resend;
+mod smtp;
+
+pub use inbox::EmailInbox;
+pub use message::OutgoingEmail;
+
+use std::env;
+
+/// Environment variables read by `EmailConfig::from_env` and `InboxConfig::from_env`
+pub mod env_vars {
+    /// Sender address, optionally with a display name: `Stark Bot <bot@example.com>`
+    pub const FROM: &str = "STARK_EMAIL_FROM";
+    /// Comma-separated recipient domains the send_email tool may mail (`*.example.com` for subdomains)
+    pub const ALLOWED_DOMAINS: &str = "STARK_EMAIL_ALLOWED_DOMAINS";
+    pub const SMTP_HOST: &str = "STARK_SMTP_HOST";
+    /// 465 for implicit TLS; any other port must offer STARTTLS (unless the host is loopback)
+    pub const SMTP_PORT: &str = "STARK_SMTP_PORT";
+    pub const SMTP_USER: &str = "STARK_SMTP_USER";
+    pub const IMAP_HOST: &str = "STARK_IMAP_HOST";
+    pub const IMAP_PORT: &str = "STARK_IMAP_PORT";
+    pub const IMAP_USER: &str = "STARK_IMAP_USER";
+    pub const IMAP_MAILBOX: &str = "STARK_IMAP_MAILBOX";
+    pub const IMAP_POLL_SECS: &str = "STARK_IMAP_POLL_SECS";
+    /// Comma-separated addresses (or `@domain`) whose mail becomes agent jobs
+    pub const ALLOWED_SENDERS: &str = "STARK_EMAIL_ALLOWED_SENDERS";
+    /// Set to false to accept mail without a passing DMARC result
+    pub const REQUIRE_DMARC: &str = "STARK_EMAIL_REQUIRE_DMARC";
+}
+
+const DEFAULT_SMTP_PORT: u16 = 587;
+const DEFAULT_IMAP_PORT: u16 = 993;
+const DEFAULT_IMAP_MAILBOX: &str = "INBOX";
+const DEFAULT_POLL_SECS: u64 = 60;
+
+/// Settings for sending mail
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct EmailConfig {
+    /// From header value
+    pub from: Option<String>,
+    /// Recipient domains, lowercased
+    pub allowed_domains: Vec<String>,
+    pub smtp: Option<SmtpConfig>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct SmtpConfig {
+    pub host: String,
+    pub port: u16,
+    pub user: Option<String>,
+}
+
+/// Secrets for sending, taken from the API keys
+#[derive(Clone, Debug, Default)]
+pub struct Credentials {
+    pub resend_api_key: Option<String>,
+    pub smtp_password: Option<String>,
+}
+
+impl EmailConfig {
+    pub fn from_env() -> Self {
+        Self {
+            from: non_empty(env_vars::FROM),
+            allowed_domains: list(env_vars::ALLOWED_DOMAINS),
+            smtp: non_empty(env_vars::SMTP_HOST).map(|host| SmtpConfig {
+                host,
+                port: parsed(env_vars::SMTP_PORT).unwrap_or(DEFAULT_SMTP_PORT),
+                user: non_empty(env_vars::SMTP_USER),
+            }),
+        }
+    }
+
+    /// Whether mail may be sent to `addr`. Entries match the domain exactly,
+    /// or any subdomain when written as `*.example.com`.
+    pub fn recipient_allowed(&self, addr: &str) -> bool {
+        let Some(domain) = addr.rsplit_once('@').map(|(_, d)| d.to_ascii_lowercase()) else {
+            return false;
+        };
+        self.allowed_domains.iter().any(|allowed| match allowed.strip_prefix("*.") {
+            Some(parent) => domain.ends_with(&format!(".{}", parent)),
+            None => domain == *allowed,
+        })
+    }
+
+    /// Send `email` through Resend or SMTP. Returns the message's id.
+    pub async fn send(&self, credentials: &Credentials, email: &OutgoingEmail) -> Result<String, String> {
+        let from = self
+            .from
+            .as_deref()
+            .ok_or_else(|| format!("Email is not configured: set {}", env_vars::FROM))?;
+        if email.recipients().next().is_none() {
+            return Err("No recipients".to_string());
+        }
+
+        if let Some(key) = credentials.resend_api_key.as_deref() {
+            resend::send(key, from, email).await
+        } else if let Some(ref smtp) = self.smtp {
+            smtp::send(smtp, credentials.smtp_password.as_deref(), from, email).await
+        } else {
+            Err(format!(
+                "No email transport configured: add RESEND_API_KEY on the API Keys page or set {}",
+                env_vars::SMTP_HOST
+            ))
+        }
+    }
+}
+
+/// Settings for the IMAP task inbox
+#[derive(Clone, Debug, PartialEq)]
+pub struct InboxConfig {
+    pub host: String,
+    pub port: u16,
+    pub user: String,
+    pub mailbox: String,
+    pub poll_secs: u64,
+    /// Sender addresses and `@domain` entries, lowercased
+    pub allowed_senders: Vec<String>,
+    pub require_dmarc: bool,
+}
+
+impl InboxConfig {
+    /// The inbox settings, or None when `STARK_IMAP_HOST` is unset. Errors when
+    /// the host is set but the user or the sender allowlist is missing.
+    pub fn from_env() -> Result<Option<Self>, String> {
+        let Some(host) = non_empty(env_vars::IMAP_HOST) else {
+            return Ok(None);
+        };
+        let user = non_empty(env_vars::IMAP_USER)
+            .ok_or_else(|| format!("{} is set but {} is not", env_vars::IMAP_HOST, env_vars::IMAP_USER))?;
+        let allowed_senders = list(env_vars::ALLOWED_SENDERS);
+        if allowed_senders.is_empty() {
+            return Err(format!(
+                "{} is set but {} is empty; refusing to run tasks from any sender",
+                env_vars::IMAP_HOST,
+                env_vars::ALLOWED_SENDERS
+            ));
+        }
+        Ok(Some(Self {
+            host,
+            port: parsed(env_vars::IMAP_PORT).unwrap_or(DEFAULT_IMAP_PORT),
+            user,
+            mailbox: non_empty(env_vars::IMAP_MAILBOX).unwrap_or_else(|| DEFAULT_IMAP_MAILBOX.to_string()),
+            poll_secs: parsed(env_vars::IMAP_POLL_SECS).filter(|&s| s > 0).unwrap_or(DEFAULT_POLL_SECS),
+            allowed_senders,
+            require_dmarc: env::var(env_vars::REQUIRE_DMARC).map(|v| v != "false" && v != "0").unwrap_or(true),
+        }))
+    }
+
+    /// Whether mail from `addr` may become an agent job
+    pub fn sender_allowed(&self, addr: &str) -> bool {
+        let addr = addr.to_ascii_lowercase();
+        self.allowed_senders.iter().any(|allowed| match allowed.strip_prefix('@') {
+            Some(domain) => addr.rsplit_once('@').is_some_and(|(_, d)| d == domain),
+            None => addr == *allowed,
+        })
+    }
+}
+
+/// Loopback hosts (local relays and bridges) may be reached without TLS
+fn is_loopback(host: &str) -> bool {
+    matches!(host, "localhost" | "127.0.0.1" | "::1" | "[::1]")
+}
+
+fn non_empty(var: &str) -> Option<String> {
+    env::var(var).ok().map(|v| v.trim().to_string()).filter(|v| !v.is_empty())
+}
+
+fn parsed<T: std::str::FromStr>(var: &str) -> Option<T> {
+    non_empty(var).and_then(|v| v.parse().ok())
+}
+
+fn list(var: &str) -> Vec<String> {
+    env::var(var)
+        .unwrap_or_default()
+        .split(',')
+        .map(|s| s.trim().to_ascii_lowercase())
+        .filter(|s| !s.is_empty())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_allowlists() {
+        let config = EmailConfig {
+            allowed_domains: vec!["example.com".to_string(), "*.corp.io".to_string()],
+            ..Default::default()
+        };
+        assert!(config.recipient_allowed("ann@example.com"));
+        assert!(config.recipient_allowed("Ann@EXAMPLE.com"));
+        assert!(config.recipient_allowed("bob@eu.corp.io"));
+        assert!(!config.recipient_allowed("bob@corp.io"));
+        assert!(!config.recipient_allowed("eve@example.com.evil.net"));
+        assert!(!config.recipient_allowed("not-an-address"));
+        assert!(!EmailConfig::default().recipient_allowed("ann@example.com"));
+
+        let inbox = InboxConfig {
+            host: "imap.example.com".to_string(),
+            port: 993,
+            user: "bot".to_string(),
+            mailbox: "INBOX".to_string(),
+            poll_secs: 60,
+            allowed_senders: vec!["ann@example.com".to_string(), "@team.dev".to_string()],
+            require_dmarc: true,
+        };
+        assert!(inbox.sender_allowed("ANN@example.com"));
+        assert!(inbox.sender_allowed("carl@team.dev"));
+        assert!(!inbox.sender_allowed("bob@example.com"));
+        assert!(!inbox.sender_allowed("carl@evil-team.dev"));
+    }
+}
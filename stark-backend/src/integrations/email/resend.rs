@@ -0,0 +1,50 @@
+//! Sending through the Resend HTTP API
+
+use super::message::OutgoingEmail;
+use serde_json::{json, Map, Value};
+use std::time::Duration;
+
+const RESEND_API_URL: &str = "https://api.resend.com/emails";
+
+/// Send `email` through Resend. Returns Resend's id for the message.
+pub(super) async fn send(api_key: &str, from: &str, email: &OutgoingEmail) -> Result<String, String> {
+    let mut headers = Map::new();
+    if let Some(ref in_reply_to) = email.in_reply_to {
+        headers.insert("In-Reply-To".to_string(), json!(in_reply_to));
+    }
+    if let Some(ref references) = email.references {
+        headers.insert("References".to_string(), json!(references));
+    }
+    let mut body = json!({
+        "from": from,
+        "to": email.to,
+        "subject": email.subject,
+        "text": email.body,
+    });
+    if !email.cc.is_empty() {
+        body["cc"] = json!(email.cc);
+    }
+    if let Some(ref reply_to) = email.reply_to {
+        body["reply_to"] = json!(reply_to);
+    }
+    if !headers.is_empty() {
+        body["headers"] = Value::Object(headers);
+    }
+
+    let response = reqwest::Client::new()
+        .post(RESEND_API_URL)
+        .bearer_auth(api_key)
+        .timeout(Duration::from_secs(30))
+        .json(&body)
+        .send()
+        .await
+        .map_err(|e| format!("Failed to reach Resend: {}", e))?;
+
+    let status = response.status();
+    let result: Value = response.json().await.unwrap_or(Value::Null);
+    if !status.is_success() {
+        let message = result["message"].as_str().unwrap_or("no details");
+        return Err(format!("Resend error ({}): {}", status, message));
+    }
+    Ok(result["id"].as_str().unwrap_or_default().to_string())
+}
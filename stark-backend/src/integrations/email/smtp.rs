@@ -0,0 +1,226 @@
+//! Minimal SMTP submission client
+//!
+//! Port 465 uses implicit TLS; any other port must offer STARTTLS, except on
+//! loopback hosts (local relays). Authenticates with AUTH PLAIN or LOGIN when
+//! a user is configured.
+
+use super::conn::Conn;
+use super::message::{address, new_message_id, OutgoingEmail};
+use super::{is_loopback, SmtpConfig};
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD as BASE64;
+
+const IMPLICIT_TLS_PORT: u16 = 465;
+
+/// Send `email` through the configured server. Returns its Message-ID.
+pub(super) async fn send(
+    config: &SmtpConfig,
+    password: Option<&str>,
+    from: &str,
+    email: &OutgoingEmail,
+) -> Result<String, String> {
+    let implicit_tls = config.port == IMPLICIT_TLS_PORT;
+    let conn = Conn::connect(&config.host, config.port, implicit_tls).await?;
+    let mut session = Session { conn, host: config.host.clone(), tls: implicit_tls };
+
+    let login = match (config.user.as_deref(), password) {
+        (Some(user), Some(password)) => Some((user, password)),
+        (Some(_), None) => return Err("SMTP_PASSWORD is not set on the API Keys page".to_string()),
+        (None, _) => None,
+    };
+    let message_id = new_message_id(from);
+    let result = session.deliver(login, from, email, &message_id).await;
+    if result.is_ok() {
+        let _ = session.command("QUIT").await;
+    }
+    result.map(|_| message_id)
+}
+
+struct Session {
+    conn: Conn,
+    host: String,
+    tls: bool,
+}
+
+impl Session {
+    async fn deliver(
+        &mut self,
+        login: Option<(&str, &str)>,
+        from: &str,
+        email: &OutgoingEmail,
+        message_id: &str,
+    ) -> Result<(), String> {
+        expect(self.reply().await?, 220, "greeting")?;
+        let mut extensions = self.ehlo().await?;
+
+        if !self.tls {
+            if extensions.iter().any(|e| e.eq_ignore_ascii_case("STARTTLS")) {
+                expect(self.command("STARTTLS").await?, 220, "STARTTLS")?;
+                // Swap in a dummy stream while the real one goes through the handshake
+                let plain = std::mem::replace(&mut self.conn, Conn::new(Box::new(tokio::io::duplex(1).0)));
+                self.conn = plain.starttls(&self.host).await?;
+                self.tls = true;
+                extensions = self.ehlo().await?;
+            } else if !is_loopback(&self.host) {
+                return Err(format!("{} does not offer STARTTLS; refusing to send in plain text", self.host));
+            }
+        }
+
+        if let Some((user, password)) = login {
+            let auth = extensions
+                .iter()
+                .find_map(|e| e.to_ascii_uppercase().strip_prefix("AUTH ").map(str::to_string))
+                .unwrap_or_default();
+            if auth.split_whitespace().any(|m| m == "PLAIN") {
+                let token = BASE64.encode(format!("\0{}\0{}", user, password));
+                expect(self.command(&format!("AUTH PLAIN {}", token)).await?, 235, "authentication")?;
+            } else if auth.split_whitespace().any(|m| m == "LOGIN") {
+                expect(self.command("AUTH LOGIN").await?, 334, "authentication")?;
+                expect(self.command(&BASE64.encode(user)).await?, 334, "authentication")?;
+                expect(self.command(&BASE64.encode(password)).await?, 235, "authentication")?;
+            } else {
+                return Err("Server offers no supported AUTH mechanism (PLAIN or LOGIN)".to_string());
+            }
+        }
+
+        let sender = address(from).ok_or_else(|| format!("Invalid sender address: {}", from))?;
+        expect(self.command(&format!("MAIL FROM:<{}>", sender)).await?, 250, "MAIL FROM")?;
+        for rcpt in email.recipients() {
+            let reply = self.command(&format!("RCPT TO:<{}>", rcpt)).await?;
+            if reply.0 != 250 && reply.0 != 251 {
+                return Err(format!("Recipient {} rejected: {} {}", rcpt, reply.0, reply.1));
+            }
+        }
+        expect(self.command("DATA").await?, 354, "DATA")?;
+
+        let mut data = String::new();
+        for line in email.render(from, message_id).split_terminator("\r\n") {
+            // Dot-stuffing: a line starting with '.' gets a second one
+            if line.starts_with('.') {
+                data.push('.');
+            }
+            data.push_str(line);
+            data.push_str("\r\n");
+        }
+        data.push_str(".\r\n");
+        self.conn.write_all(data.as_bytes()).await?;
+        expect(self.reply().await?, 250, "message")
+    }
+
+    /// EHLO, returning the advertised extensions
+    async fn ehlo(&mut self) -> Result<Vec<String>, String> {
+        self.conn.write_all(b"EHLO stark-bot\r\n").await?;
+        let mut extensions = Vec::new();
+        loop {
+            let line = self.conn.read_line().await?;
+            let (code, more, text) = parse_reply_line(&line)?;
+            if code != 250 {
+                return Err(format!("EHLO rejected: {}", line));
+            }
+            extensions.push(text.to_string());
+            if !more {
+                // The first line is the server's greeting, not an extension
+                extensions.remove(0);
+                return Ok(extensions);
+            }
+        }
+    }
+
+    async fn command(&mut self, line: &str) -> Result<(u16, String), String> {
+        self.conn.write_all(format!("{}\r\n", line).as_bytes()).await?;
+        self.reply().await
+    }
+
+    /// Read a possibly multi-line reply
+    async fn reply(&mut self) -> Result<(u16, String), String> {
+        let mut text = Vec::new();
+        loop {
+            let line = self.conn.read_line().await?;
+            let (code, more, rest) = parse_reply_line(&line)?;
+            text.push(rest.to_string());
+            if !more {
+                return Ok((code, text.join(" ")));
+            }
+        }
+    }
+}
+
+/// Split `250-text` / `250 text` into code, continuation flag and text
+fn parse_reply_line(line: &str) -> Result<(u16, bool, &str), String> {
+    let code = line
+        .get(..3)
+        .and_then(|c| c.parse().ok())
+        .ok_or_else(|| format!("Malformed SMTP reply: {}", line))?;
+    let more = line.as_bytes().get(3) == Some(&b'-');
+    Ok((code, more, line.get(4..).unwrap_or("")))
+}
+
+fn expect((code, text): (u16, String), wanted: u16, step: &str) -> Result<(), String> {
+    if code == wanted {
+        Ok(())
+    } else {
+        Err(format!("SMTP {} failed: {} {}", step, code, text))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+
+    #[tokio::test]
+    async fn test_smtp_session() {
+        let (client, server) = tokio::io::duplex(64 * 1024);
+        let server = tokio::spawn(async move {
+            let (read, mut write) = tokio::io::split(server);
+            let mut lines = BufReader::new(read).lines();
+            let mut received = Vec::new();
+            write.write_all(b"220 mx.example.com ESMTP\r\n").await.unwrap();
+            while let Some(line) = lines.next_line().await.unwrap() {
+                received.push(line.clone());
+                let reply: &[u8] = match line.as_str() {
+                    "EHLO stark-bot" => b"250-mx.example.com\r\n250-SIZE 1000000\r\n250 AUTH LOGIN PLAIN\r\n",
+                    l if l.starts_with("AUTH PLAIN") => b"235 ok\r\n",
+                    "DATA" => b"354 go ahead\r\n",
+                    "." => b"250 queued\r\n",
+                    "QUIT" => {
+                        write.write_all(b"221 bye\r\n").await.unwrap();
+                        break;
+                    }
+                    l if l.starts_with("MAIL") || l.starts_with("RCPT") => b"250 ok\r\n",
+                    _ => continue,
+                };
+                write.write_all(reply).await.unwrap();
+            }
+            received
+        });
+
+        let mut session = Session { conn: Conn::new(Box::new(client)), host: "localhost".to_string(), tls: false };
+        let email = OutgoingEmail {
+            to: vec!["Ann <ann@example.com>".to_string()],
+            subject: "Report".to_string(),
+            body: "done".to_string(),
+            ..Default::default()
+        };
+        session
+            .deliver(Some(("bot", "secret")), "Bot <bot@stark.dev>", &email, "<1@stark.dev>")
+            .await
+            .unwrap();
+        session.command("QUIT").await.unwrap();
+
+        let received = server.await.unwrap();
+        assert_eq!(received[1], format!("AUTH PLAIN {}", BASE64.encode("\0bot\0secret")));
+        assert_eq!(received[2], "MAIL FROM:<bot@stark.dev>");
+        assert_eq!(received[3], "RCPT TO:<ann@example.com>");
+        assert!(received.contains(&"Subject: Report".to_string()));
+        assert_eq!(received[received.len() - 2], ".");
+    }
+
+    #[test]
+    fn test_parse_reply_line() {
+        assert_eq!(parse_reply_line("250-SIZE 10").unwrap(), (250, true, "SIZE 10"));
+        assert_eq!(parse_reply_line("250 OK").unwrap(), (250, false, "OK"));
+        assert_eq!(parse_reply_line("354").unwrap(), (354, false, ""));
+        assert!(parse_reply_line("hello").is_err());
+    }
+}
@@ -1,5 +1,8 @@
 //! External integrations module
 //!
-//! This module contains integrations with external services like Gmail.
+//! This module contains integrations with external services like Gmail, email
+//! over SMTP/IMAP and text-to-speech/speech-to-text providers.
 
+pub mod email;
 pub mod gmail;
+pub mod voice;
@@ -0,0 +1,71 @@
+//! ElevenLabs text-to-speech and speech-to-text
+
+use super::{file_part, provider_error, AudioFormat, AudioUpload, SpeechRequest, Transcript};
+use reqwest::multipart::Form;
+use serde_json::{json, Value};
+use std::time::Duration;
+
+const ELEVENLABS_API_URL: &str = "https://api.elevenlabs.io/v1";
+
+pub(super) async fn speak(
+    api_key: &str,
+    model: &str,
+    voice: &str,
+    request: &SpeechRequest,
+) -> Result<reqwest::Response, String> {
+    // 24 kHz PCM matches what OpenAI returns for `pcm`
+    let output_format = match request.format {
+        AudioFormat::Pcm => "pcm_24000",
+        _ => "mp3_44100_128",
+    };
+    let mut body = json!({
+        "text": request.text,
+        "model_id": model,
+    });
+    if let Some(speed) = request.speed {
+        body["voice_settings"] = json!({ "speed": speed });
+    }
+
+    let response = reqwest::Client::new()
+        .post(format!("{}/text-to-speech/{}/stream", ELEVENLABS_API_URL, voice))
+        .query(&[("output_format", output_format)])
+        .header("xi-api-key", api_key)
+        .timeout(Duration::from_secs(120))
+        .json(&body)
+        .send()
+        .await
+        .map_err(|e| format!("Failed to reach ElevenLabs: {}", e))?;
+    if !response.status().is_success() {
+        return Err(provider_error("ElevenLabs", response).await);
+    }
+    Ok(response)
+}
+
+pub(super) async fn transcribe(api_key: &str, model: &str, audio: AudioUpload) -> Result<Transcript, String> {
+    let mut form = Form::new()
+        .part("file", file_part(audio.bytes, audio.file_name, audio.content_type.as_deref())?)
+        .text("model_id", model.to_string());
+    if let Some(language) = audio.language {
+        form = form.text("language_code", language);
+    }
+
+    let response = reqwest::Client::new()
+        .post(format!("{}/speech-to-text", ELEVENLABS_API_URL))
+        .header("xi-api-key", api_key)
+        .timeout(Duration::from_secs(300))
+        .multipart(form)
+        .send()
+        .await
+        .map_err(|e| format!("Failed to reach ElevenLabs: {}", e))?;
+    if !response.status().is_success() {
+        return Err(provider_error("ElevenLabs", response).await);
+    }
+    let result: Value = response
+        .json()
+        .await
+        .map_err(|e| format!("Invalid ElevenLabs transcription response: {}", e))?;
+    Ok(Transcript {
+        text: result["text"].as_str().unwrap_or_default().trim().to_string(),
+        language: result["language_code"].as_str().map(str::to_string),
+    })
+}
@@ -0,0 +1,413 @@
+//! Voice integration
+//!
+//! Text-to-speech and speech-to-text for the `/api/tts` and `/api/stt`
+//! endpoints, so voice front-ends can talk to the agent. Speech comes from
+//! OpenAI or ElevenLabs and is streamed to the client as it is generated;
+//! transcription can also run on a local whisper.cpp server.
+//!
+//! ## Setup
+//! 1. Pick the providers with `STARK_TTS_PROVIDER` and `STARK_STT_PROVIDER`
+//!    (both default to `openai`)
+//! 2. Add `OPENAI_API_KEY` or `ELEVENLABS_API_KEY` on the API Keys page, or
+//!    set `STARK_WHISPER_CPP_URL` for `STARK_STT_PROVIDER=whisper_cpp`
+
+mod elevenlabs;
+mod openai;
+mod whisper;
+
+use std::env;
+
+/// Environment variables read by `VoiceConfig::from_env`
+pub mod env_vars {
+    /// `openai` or `elevenlabs`
+    pub const TTS_PROVIDER: &str = "STARK_TTS_PROVIDER";
+    pub const TTS_MODEL: &str = "STARK_TTS_MODEL";
+    /// Voice name (OpenAI) or voice id (ElevenLabs) used when a request names none
+    pub const TTS_VOICE: &str = "STARK_TTS_VOICE";
+    /// `openai`, `elevenlabs` or `whisper_cpp`
+    pub const STT_PROVIDER: &str = "STARK_STT_PROVIDER";
+    pub const STT_MODEL: &str = "STARK_STT_MODEL";
+    /// Base URL of an OpenAI-compatible audio API; the API key is optional for custom URLs
+    pub const OPENAI_AUDIO_URL: &str = "STARK_OPENAI_AUDIO_URL";
+    /// Base URL of a whisper.cpp server, e.g. `http://127.0.0.1:8080`
+    pub const WHISPER_CPP_URL: &str = "STARK_WHISPER_CPP_URL";
+}
+
+const DEFAULT_OPENAI_AUDIO_URL: &str = "https://api.openai.com/v1";
+const DEFAULT_OPENAI_TTS_MODEL: &str = "gpt-4o-mini-tts";
+const DEFAULT_OPENAI_VOICE: &str = "alloy";
+const DEFAULT_OPENAI_STT_MODEL: &str = "whisper-1";
+const DEFAULT_ELEVENLABS_TTS_MODEL: &str = "eleven_multilingual_v2";
+/// ElevenLabs' "George" premade voice
+const DEFAULT_ELEVENLABS_VOICE: &str = "JBFqnCBsd6RMkjVDRZzb";
+const DEFAULT_ELEVENLABS_STT_MODEL: &str = "scribe_v1";
+
+/// Longest text spoken per request, in characters
+pub const MAX_TTS_CHARS: usize = 4096;
+/// Largest audio file transcribed per request, in bytes
+pub const MAX_STT_BYTES: usize = 25 * 1024 * 1024;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum TtsProvider {
+    OpenAi,
+    ElevenLabs,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum SttProvider {
+    OpenAi,
+    ElevenLabs,
+    WhisperCpp,
+}
+
+impl TtsProvider {
+    pub fn parse(name: &str) -> Result<Self, String> {
+        match name.trim().to_ascii_lowercase().as_str() {
+            "openai" => Ok(Self::OpenAi),
+            "elevenlabs" => Ok(Self::ElevenLabs),
+            other => Err(format!("Unknown {} '{}': expected openai or elevenlabs", env_vars::TTS_PROVIDER, other)),
+        }
+    }
+}
+
+impl SttProvider {
+    pub fn parse(name: &str) -> Result<Self, String> {
+        match name.trim().to_ascii_lowercase().as_str() {
+            "openai" => Ok(Self::OpenAi),
+            "elevenlabs" => Ok(Self::ElevenLabs),
+            "whisper_cpp" | "whisper.cpp" | "whispercpp" => Ok(Self::WhisperCpp),
+            other => Err(format!(
+                "Unknown {} '{}': expected openai, elevenlabs or whisper_cpp",
+                env_vars::STT_PROVIDER,
+                other
+            )),
+        }
+    }
+}
+
+/// Encoding of generated speech
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum AudioFormat {
+    #[default]
+    Mp3,
+    Opus,
+    Aac,
+    Flac,
+    Wav,
+    /// Raw 16-bit little-endian mono samples at 24 kHz
+    Pcm,
+}
+
+impl AudioFormat {
+    pub fn parse(name: &str) -> Result<Self, String> {
+        match name.trim().to_ascii_lowercase().as_str() {
+            "mp3" => Ok(Self::Mp3),
+            "opus" => Ok(Self::Opus),
+            "aac" => Ok(Self::Aac),
+            "flac" => Ok(Self::Flac),
+            "wav" => Ok(Self::Wav),
+            "pcm" => Ok(Self::Pcm),
+            other => Err(format!("Unknown audio format '{}': expected mp3, opus, aac, flac, wav or pcm", other)),
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Mp3 => "mp3",
+            Self::Opus => "opus",
+            Self::Aac => "aac",
+            Self::Flac => "flac",
+            Self::Wav => "wav",
+            Self::Pcm => "pcm",
+        }
+    }
+
+    /// The Content-Type of audio in this format
+    pub fn content_type(&self) -> &'static str {
+        match self {
+            Self::Mp3 => "audio/mpeg",
+            Self::Opus => "audio/ogg",
+            Self::Aac => "audio/aac",
+            Self::Flac => "audio/flac",
+            Self::Wav => "audio/wav",
+            Self::Pcm => "audio/pcm",
+        }
+    }
+}
+
+/// Text to speak
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct SpeechRequest {
+    pub text: String,
+    /// Overrides `STARK_TTS_VOICE`
+    pub voice: Option<String>,
+    pub format: AudioFormat,
+    /// Playback speed, 1.0 being normal
+    pub speed: Option<f32>,
+}
+
+/// Audio to transcribe
+#[derive(Clone, Debug, Default)]
+pub struct AudioUpload {
+    pub bytes: Vec<u8>,
+    pub file_name: String,
+    pub content_type: Option<String>,
+    /// ISO-639-1 code of the spoken language; detected when None
+    pub language: Option<String>,
+    /// Text to steer spelling and style (ignored by ElevenLabs)
+    pub prompt: Option<String>,
+}
+
+/// A transcription
+#[derive(Clone, Debug, PartialEq)]
+pub struct Transcript {
+    pub text: String,
+    /// The language the provider reported, if any
+    pub language: Option<String>,
+}
+
+/// Secrets for the voice providers, taken from the API keys
+#[derive(Clone, Debug, Default)]
+pub struct Credentials {
+    pub openai_api_key: Option<String>,
+    pub elevenlabs_api_key: Option<String>,
+}
+
+/// Voice provider settings
+#[derive(Clone, Debug, PartialEq)]
+pub struct VoiceConfig {
+    pub tts: TtsProvider,
+    pub tts_model: String,
+    pub tts_voice: String,
+    pub stt: SttProvider,
+    pub stt_model: String,
+    pub openai_url: String,
+    pub whisper_cpp_url: Option<String>,
+}
+
+impl VoiceConfig {
+    /// The voice settings. Errors when a provider name is not recognized.
+    pub fn from_env() -> Result<Self, String> {
+        let tts = match non_empty(env_vars::TTS_PROVIDER) {
+            Some(name) => TtsProvider::parse(&name)?,
+            None => TtsProvider::OpenAi,
+        };
+        let stt = match non_empty(env_vars::STT_PROVIDER) {
+            Some(name) => SttProvider::parse(&name)?,
+            None => SttProvider::OpenAi,
+        };
+        let (default_tts_model, default_voice) = match tts {
+            TtsProvider::OpenAi => (DEFAULT_OPENAI_TTS_MODEL, DEFAULT_OPENAI_VOICE),
+            TtsProvider::ElevenLabs => (DEFAULT_ELEVENLABS_TTS_MODEL, DEFAULT_ELEVENLABS_VOICE),
+        };
+        let default_stt_model = match stt {
+            SttProvider::ElevenLabs => DEFAULT_ELEVENLABS_STT_MODEL,
+            SttProvider::OpenAi | SttProvider::WhisperCpp => DEFAULT_OPENAI_STT_MODEL,
+        };
+        Ok(Self {
+            tts,
+            tts_model: non_empty(env_vars::TTS_MODEL).unwrap_or_else(|| default_tts_model.to_string()),
+            tts_voice: non_empty(env_vars::TTS_VOICE).unwrap_or_else(|| default_voice.to_string()),
+            stt,
+            stt_model: non_empty(env_vars::STT_MODEL).unwrap_or_else(|| default_stt_model.to_string()),
+            openai_url: non_empty(env_vars::OPENAI_AUDIO_URL)
+                .map(|url| url.trim_end_matches('/').to_string())
+                .unwrap_or_else(|| DEFAULT_OPENAI_AUDIO_URL.to_string()),
+            whisper_cpp_url: non_empty(env_vars::WHISPER_CPP_URL).map(|url| url.trim_end_matches('/').to_string()),
+        })
+    }
+
+    /// Why `request` can't be spoken by the configured provider, if it can't
+    pub fn check_speech(&self, request: &SpeechRequest) -> Result<(), String> {
+        if request.text.trim().is_empty() {
+            return Err("text cannot be empty".to_string());
+        }
+        if request.text.chars().count() > MAX_TTS_CHARS {
+            return Err(format!("text is too long (max {} characters)", MAX_TTS_CHARS));
+        }
+        if request.voice.as_deref().is_some_and(|v| v.trim().is_empty()) {
+            return Err("voice cannot be empty".to_string());
+        }
+        match self.tts {
+            TtsProvider::OpenAi => {
+                if request.speed.is_some_and(|s| !(0.25..=4.0).contains(&s)) {
+                    return Err("speed must be between 0.25 and 4.0".to_string());
+                }
+            }
+            TtsProvider::ElevenLabs => {
+                if !matches!(request.format, AudioFormat::Mp3 | AudioFormat::Pcm) {
+                    return Err(format!("ElevenLabs can't produce {} audio: use mp3 or pcm", request.format.as_str()));
+                }
+                // Voice ids go into the request path
+                if request.voice.as_deref().is_some_and(|v| !v.chars().all(|c| c.is_ascii_alphanumeric())) {
+                    return Err("voice must be an ElevenLabs voice id".to_string());
+                }
+                if request.speed.is_some_and(|s| !(0.7..=1.2).contains(&s)) {
+                    return Err("speed must be between 0.7 and 1.2 for ElevenLabs".to_string());
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Why text-to-speech can't run with `credentials`, if it can't
+    pub fn check_tts(&self, credentials: &Credentials) -> Result<(), String> {
+        match self.tts {
+            TtsProvider::OpenAi => self.check_openai(credentials),
+            TtsProvider::ElevenLabs => check_elevenlabs(credentials),
+        }
+    }
+
+    /// Why speech-to-text can't run with `credentials`, if it can't
+    pub fn check_stt(&self, credentials: &Credentials) -> Result<(), String> {
+        match self.stt {
+            SttProvider::OpenAi => self.check_openai(credentials),
+            SttProvider::ElevenLabs => check_elevenlabs(credentials),
+            SttProvider::WhisperCpp => match self.whisper_cpp_url {
+                Some(_) => Ok(()),
+                None => Err(format!("Speech-to-text is not configured: set {}", env_vars::WHISPER_CPP_URL)),
+            },
+        }
+    }
+
+    /// Start generating speech. The response body is the audio, still
+    /// streaming in from the provider.
+    pub async fn speak(&self, credentials: &Credentials, request: &SpeechRequest) -> Result<reqwest::Response, String> {
+        let voice = request.voice.as_deref().unwrap_or(&self.tts_voice);
+        match self.tts {
+            TtsProvider::OpenAi => {
+                let key = credentials.openai_api_key.as_deref();
+                openai::speak(&self.openai_url, key, &self.tts_model, voice, request).await
+            }
+            TtsProvider::ElevenLabs => {
+                let key = credentials.elevenlabs_api_key.as_deref().unwrap_or_default();
+                elevenlabs::speak(key, &self.tts_model, voice, request).await
+            }
+        }
+    }
+
+    /// Transcribe `audio`
+    pub async fn transcribe(&self, credentials: &Credentials, audio: AudioUpload) -> Result<Transcript, String> {
+        match self.stt {
+            SttProvider::OpenAi => {
+                let key = credentials.openai_api_key.as_deref();
+                openai::transcribe(&self.openai_url, key, &self.stt_model, audio).await
+            }
+            SttProvider::ElevenLabs => {
+                let key = credentials.elevenlabs_api_key.as_deref().unwrap_or_default();
+                elevenlabs::transcribe(key, &self.stt_model, audio).await
+            }
+            SttProvider::WhisperCpp => {
+                let url = self.whisper_cpp_url.as_deref().unwrap_or_default();
+                whisper::transcribe(url, audio).await
+            }
+        }
+    }
+
+    /// The OpenAI API needs a key; a custom URL (a local OpenAI-compatible
+    /// server) may not
+    fn check_openai(&self, credentials: &Credentials) -> Result<(), String> {
+        if credentials.openai_api_key.is_none() && self.openai_url == DEFAULT_OPENAI_AUDIO_URL {
+            return Err("OpenAI voice is not configured: add OPENAI_API_KEY on the API Keys page".to_string());
+        }
+        Ok(())
+    }
+}
+
+fn check_elevenlabs(credentials: &Credentials) -> Result<(), String> {
+    match credentials.elevenlabs_api_key {
+        Some(_) => Ok(()),
+        None => Err("ElevenLabs voice is not configured: add ELEVENLABS_API_KEY on the API Keys page".to_string()),
+    }
+}
+
+/// The file part of a transcription upload
+fn file_part(
+    bytes: Vec<u8>,
+    file_name: String,
+    content_type: Option<&str>,
+) -> Result<reqwest::multipart::Part, String> {
+    let part = reqwest::multipart::Part::bytes(bytes).file_name(file_name);
+    match content_type {
+        Some(content_type) => part
+            .mime_str(content_type)
+            .map_err(|e| format!("Invalid audio content type '{}': {}", content_type, e)),
+        None => Ok(part),
+    }
+}
+
+/// A provider's error response as a message, from its JSON error fields when
+/// it has them
+async fn provider_error(provider: &str, response: reqwest::Response) -> String {
+    let status = response.status();
+    let body = response.text().await.unwrap_or_default();
+    let json: serde_json::Value = serde_json::from_str(&body).unwrap_or_default();
+    let message = json["error"]["message"]
+        .as_str()
+        .or_else(|| json["detail"]["message"].as_str())
+        .or_else(|| json["detail"].as_str())
+        .or_else(|| json["error"].as_str())
+        .map(str::to_string)
+        .unwrap_or_else(|| body.chars().take(300).collect());
+    format!("{} error ({}): {}", provider, status, message)
+}
+
+fn non_empty(var: &str) -> Option<String> {
+    env::var(var).ok().map(|v| v.trim().to_string()).filter(|v| !v.is_empty())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config(tts: TtsProvider, stt: SttProvider) -> VoiceConfig {
+        VoiceConfig {
+            tts,
+            tts_model: DEFAULT_OPENAI_TTS_MODEL.to_string(),
+            tts_voice: DEFAULT_OPENAI_VOICE.to_string(),
+            stt,
+            stt_model: DEFAULT_OPENAI_STT_MODEL.to_string(),
+            openai_url: DEFAULT_OPENAI_AUDIO_URL.to_string(),
+            whisper_cpp_url: None,
+        }
+    }
+
+    #[test]
+    fn test_voice_checks() {
+        assert_eq!(SttProvider::parse(" Whisper.cpp "), Ok(SttProvider::WhisperCpp));
+        assert!(TtsProvider::parse("whisper_cpp").is_err());
+        assert_eq!(AudioFormat::parse("OPUS").unwrap().content_type(), "audio/ogg");
+        assert!(AudioFormat::parse("ogg").is_err());
+
+        let openai = config(TtsProvider::OpenAi, SttProvider::WhisperCpp);
+        let speech = |format, speed| SpeechRequest {
+            text: "Hello".to_string(),
+            format,
+            speed,
+            ..Default::default()
+        };
+        assert!(openai.check_speech(&speech(AudioFormat::Flac, Some(2.0))).is_ok());
+        assert!(openai.check_speech(&speech(AudioFormat::Mp3, Some(5.0))).is_err());
+        assert!(openai.check_speech(&SpeechRequest::default()).unwrap_err().contains("empty"));
+        let long = SpeechRequest {
+            text: "é".repeat(MAX_TTS_CHARS + 1),
+            ..Default::default()
+        };
+        assert!(openai.check_speech(&long).unwrap_err().contains("too long"));
+
+        let elevenlabs = config(TtsProvider::ElevenLabs, SttProvider::ElevenLabs);
+        assert!(elevenlabs.check_speech(&speech(AudioFormat::Pcm, Some(1.1))).is_ok());
+        assert!(elevenlabs.check_speech(&speech(AudioFormat::Wav, None)).unwrap_err().contains("mp3 or pcm"));
+
+        let none = Credentials::default();
+        assert!(openai.check_tts(&none).unwrap_err().contains("OPENAI_API_KEY"));
+        assert!(openai.check_stt(&none).unwrap_err().contains(env_vars::WHISPER_CPP_URL));
+        assert!(elevenlabs.check_stt(&none).unwrap_err().contains("ELEVENLABS_API_KEY"));
+        // A local OpenAI-compatible server needs no key
+        let local = VoiceConfig {
+            openai_url: "http://127.0.0.1:8000/v1".to_string(),
+            ..openai
+        };
+        assert!(local.check_tts(&none).is_ok());
+    }
+}
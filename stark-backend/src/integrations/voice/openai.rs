@@ -0,0 +1,175 @@
+//! OpenAI audio API (`/audio/speech` and `/audio/transcriptions`), or a
+//! compatible local server
+
+use super::{file_part, provider_error, AudioUpload, SpeechRequest, Transcript};
+use reqwest::multipart::Form;
+use serde_json::{json, Value};
+use std::time::Duration;
+
+pub(super) async fn speak(
+    base_url: &str,
+    api_key: Option<&str>,
+    model: &str,
+    voice: &str,
+    request: &SpeechRequest,
+) -> Result<reqwest::Response, String> {
+    let mut body = json!({
+        "model": model,
+        "input": request.text,
+        "voice": voice,
+        "response_format": request.format.as_str(),
+    });
+    if let Some(speed) = request.speed {
+        body["speed"] = json!(speed);
+    }
+
+    let mut builder = reqwest::Client::new()
+        .post(format!("{}/audio/speech", base_url))
+        .timeout(Duration::from_secs(120))
+        .json(&body);
+    if let Some(key) = api_key {
+        builder = builder.bearer_auth(key);
+    }
+    let response = builder.send().await.map_err(|e| format!("Failed to reach OpenAI: {}", e))?;
+    if !response.status().is_success() {
+        return Err(provider_error("OpenAI", response).await);
+    }
+    Ok(response)
+}
+
+pub(super) async fn transcribe(
+    base_url: &str,
+    api_key: Option<&str>,
+    model: &str,
+    audio: AudioUpload,
+) -> Result<Transcript, String> {
+    let mut form = Form::new()
+        .part("file", file_part(audio.bytes, audio.file_name, audio.content_type.as_deref())?)
+        .text("model", model.to_string())
+        .text("response_format", "json");
+    if let Some(language) = audio.language {
+        form = form.text("language", language);
+    }
+    if let Some(prompt) = audio.prompt {
+        form = form.text("prompt", prompt);
+    }
+
+    let mut builder = reqwest::Client::new()
+        .post(format!("{}/audio/transcriptions", base_url))
+        .timeout(Duration::from_secs(300))
+        .multipart(form);
+    if let Some(key) = api_key {
+        builder = builder.bearer_auth(key);
+    }
+    let response = builder.send().await.map_err(|e| format!("Failed to reach OpenAI: {}", e))?;
+    if !response.status().is_success() {
+        return Err(provider_error("OpenAI", response).await);
+    }
+    let result: Value = response
+        .json()
+        .await
+        .map_err(|e| format!("Invalid OpenAI transcription response: {}", e))?;
+    Ok(Transcript {
+        text: result["text"].as_str().unwrap_or_default().trim().to_string(),
+        language: result["language"].as_str().map(str::to_string),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::integrations::voice::AudioFormat;
+    use futures_util::StreamExt;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::TcpListener;
+
+    /// Serve one request with `response`, handing back what the client sent
+    async fn serve_once(response: String) -> (String, tokio::task::JoinHandle<String>) {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}/v1", listener.local_addr().unwrap());
+        let handle = tokio::spawn(async move {
+            let (mut socket, _) = listener.accept().await.unwrap();
+            let mut buf = vec![0u8; 64 * 1024];
+            let mut received = Vec::new();
+            // Read until the body (announced by Content-Length) has arrived
+            loop {
+                let n = socket.read(&mut buf).await.unwrap();
+                received.extend_from_slice(&buf[..n]);
+                let text = String::from_utf8_lossy(&received).to_string();
+                if let Some((head, body)) = text.split_once("\r\n\r\n") {
+                    let length = head
+                        .to_ascii_lowercase()
+                        .lines()
+                        .find_map(|l| l.strip_prefix("content-length:").map(|v| v.trim().parse::<usize>().unwrap()))
+                        .unwrap_or(0);
+                    if body.len() >= length {
+                        break;
+                    }
+                }
+                if n == 0 {
+                    break;
+                }
+            }
+            socket.write_all(response.as_bytes()).await.unwrap();
+            String::from_utf8_lossy(&received).to_string()
+        });
+        (url, handle)
+    }
+
+    #[tokio::test]
+    async fn test_speak_and_transcribe() {
+        let (url, server) = serve_once(
+            "HTTP/1.1 200 OK\r\ncontent-type: audio/mpeg\r\ntransfer-encoding: chunked\r\n\r\n3\r\nID3\r\n2\r\n\x01\x02\r\n0\r\n\r\n"
+                .to_string(),
+        )
+        .await;
+        let request = SpeechRequest {
+            text: "Hello there".to_string(),
+            format: AudioFormat::Opus,
+            speed: Some(1.5),
+            ..Default::default()
+        };
+        let response = speak(&url, None, "tts-1", "nova", &request).await.unwrap();
+        let audio: Vec<u8> = response
+            .bytes_stream()
+            .map(|chunk| chunk.unwrap().to_vec())
+            .concat()
+            .await;
+        assert_eq!(audio, b"ID3\x01\x02");
+        let sent = server.await.unwrap();
+        assert!(sent.starts_with("POST /v1/audio/speech"));
+        assert!(!sent.to_ascii_lowercase().contains("authorization"));
+        assert!(sent.contains("\"response_format\":\"opus\"") && sent.contains("\"voice\":\"nova\""));
+
+        let body = r#"{"text":" Hello there. ","language":"english"}"#;
+        let (url, server) = serve_once(format!(
+            "HTTP/1.1 200 OK\r\ncontent-type: application/json\r\ncontent-length: {}\r\n\r\n{}",
+            body.len(),
+            body
+        ))
+        .await;
+        let audio = AudioUpload {
+            bytes: b"RIFF....WAVE".to_vec(),
+            file_name: "note.wav".to_string(),
+            content_type: Some("audio/wav".to_string()),
+            language: Some("en".to_string()),
+            prompt: None,
+        };
+        let transcript = transcribe(&url, Some("sk-test"), "whisper-1", audio).await.unwrap();
+        assert_eq!(transcript.text, "Hello there.");
+        assert_eq!(transcript.language.as_deref(), Some("english"));
+        let sent = server.await.unwrap();
+        assert!(sent.starts_with("POST /v1/audio/transcriptions"));
+        assert!(sent.contains("Bearer sk-test") && sent.contains("filename=\"note.wav\""));
+
+        let body = r#"{"error":{"message":"Invalid voice"}}"#;
+        let (url, _server) = serve_once(format!(
+            "HTTP/1.1 400 Bad Request\r\ncontent-type: application/json\r\ncontent-length: {}\r\n\r\n{}",
+            body.len(),
+            body
+        ))
+        .await;
+        let error = speak(&url, None, "tts-1", "bogus", &request).await.unwrap_err();
+        assert_eq!(error, "OpenAI error (400 Bad Request): Invalid voice");
+    }
+}
@@ -0,0 +1,43 @@
+//! Transcription on a whisper.cpp server (`whisper-server`)
+//!
+//! The server only decodes WAV unless it was started with `--convert`, which
+//! uses ffmpeg to accept other formats.
+
+use super::{file_part, provider_error, AudioUpload, Transcript};
+use reqwest::multipart::Form;
+use serde_json::Value;
+use std::time::Duration;
+
+pub(super) async fn transcribe(base_url: &str, audio: AudioUpload) -> Result<Transcript, String> {
+    let mut form = Form::new()
+        .part("file", file_part(audio.bytes, audio.file_name, audio.content_type.as_deref())?)
+        .text("response_format", "json");
+    if let Some(language) = audio.language {
+        form = form.text("language", language);
+    }
+    if let Some(prompt) = audio.prompt {
+        form = form.text("prompt", prompt);
+    }
+
+    let response = reqwest::Client::new()
+        .post(format!("{}/inference", base_url))
+        .timeout(Duration::from_secs(300))
+        .multipart(form)
+        .send()
+        .await
+        .map_err(|e| format!("Failed to reach whisper.cpp at {}: {}", base_url, e))?;
+    if !response.status().is_success() {
+        return Err(provider_error("whisper.cpp", response).await);
+    }
+    let result: Value = response
+        .json()
+        .await
+        .map_err(|e| format!("Invalid whisper.cpp response: {}", e))?;
+    if let Some(error) = result["error"].as_str() {
+        return Err(format!("whisper.cpp error: {}", error));
+    }
+    Ok(Transcript {
+        text: result["text"].as_str().unwrap_or_default().trim().to_string(),
+        language: None,
+    })
+}
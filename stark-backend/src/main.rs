@@ -1,9 +1,9 @@
-use actix_cors::Cors;
 use actix_files::{Files, NamedFile};
-use actix_web::{middleware::Logger, web, App, HttpServer};
+use actix_web::{middleware::{from_fn, Logger}, web, App, HttpServer};
 use dotenv::dotenv;
 use std::sync::Arc;
 
+mod agent;
 mod ai;
 mod channels;
 mod config;
@@ -14,22 +14,33 @@ mod domain_types;
 mod execution;
 mod gateway;
 mod integrations;
+mod mcp;
 mod memory;
+mod metrics;
 mod middleware;
 mod models;
+mod redaction;
 mod scheduler;
 mod skills;
+mod telemetry;
 mod tools;
+mod totp;
+mod utils;
+mod wallet;
+mod workspace;
 mod x402;
 mod eip8004;
 mod hooks;
 
+use agent::{AgentJobQueue, ScheduleRunner};
 use channels::{ChannelManager, MessageDispatcher};
 use config::Config;
 use db::Database;
-use execution::ExecutionTracker;
+use execution::{ExecutionTracker, ProcessManager};
 use gateway::{events::EventBroadcaster, Gateway};
 use hooks::{HookManager, builtin::AutoMemoryHook};
+use mcp::{sse::SseSessions, McpServer};
+use middleware::rate_limit::{RateLimiter, RateLimits};
 use scheduler::{Scheduler, SchedulerConfig};
 use skills::SkillRegistry;
 use tools::ToolRegistry;
@@ -46,8 +57,84 @@ pub struct AppState {
     pub channel_manager: Arc<ChannelManager>,
     pub broadcaster: Arc<EventBroadcaster>,
     pub hook_manager: Arc<HookManager>,
+    pub process_manager: Arc<ProcessManager>,
+    pub agent_jobs: Arc<AgentJobQueue>,
+    pub schedules: Arc<ScheduleRunner>,
+    pub rate_limiter: Arc<RateLimiter>,
+    pub mcp: Arc<McpServer>,
+    pub mcp_sessions: Arc<SseSessions>,
 }
 
+#[cfg(test)]
+impl AppState {
+    /// State wired like `main` around `db`, without background tasks or channels
+    pub async fn for_tests(db: Arc<Database>) -> Self {
+        let tool_registry = Arc::new(tools::create_default_registry());
+        let skill_registry = Arc::new(skills::create_default_registry(db.clone()));
+        let gateway = Arc::new(Gateway::new_with_tools_and_wallet(db.clone(), tool_registry.clone(), None));
+        let broadcaster = gateway.broadcaster();
+        let execution_tracker = Arc::new(ExecutionTracker::new(broadcaster.clone()));
+        let hook_manager = Arc::new(HookManager::new());
+        let process_manager = Arc::new(ProcessManager::new(broadcaster.clone()));
+        let dispatcher = Arc::new(
+            MessageDispatcher::new_with_wallet_and_skills(
+                db.clone(),
+                broadcaster.clone(),
+                tool_registry.clone(),
+                execution_tracker.clone(),
+                None,
+                Some(skill_registry.clone()),
+            )
+            .with_hook_manager(hook_manager.clone())
+            .with_process_manager(process_manager.clone()),
+        );
+        let scheduler =
+            Arc::new(Scheduler::new(db.clone(), dispatcher.clone(), broadcaster.clone(), SchedulerConfig::default()));
+        let agent_jobs =
+            Arc::new(AgentJobQueue::new(db.clone(), tool_registry.clone(), process_manager.clone(), None));
+        Self {
+            config: Config {
+                login_admin_public_address: String::new(),
+                burner_wallet_private_key: None,
+                host: "127.0.0.1".to_string(),
+                port: 0,
+                database_url: ":memory:".to_string(),
+            },
+            channel_manager: gateway.channel_manager(),
+            schedules: Arc::new(ScheduleRunner::new(db.clone(), agent_jobs.clone())),
+            rate_limiter: Arc::new(RateLimiter::new(RateLimits::from_settings(&Default::default()), false)),
+            mcp: Arc::new(McpServer::new(
+                db.clone(),
+                tool_registry.clone(),
+                broadcaster.clone(),
+                process_manager.clone(),
+            )),
+            mcp_sessions: Arc::new(SseSessions::new()),
+            db,
+            gateway,
+            tool_registry,
+            skill_registry,
+            dispatcher,
+            execution_tracker,
+            scheduler,
+            broadcaster,
+            hook_manager,
+            process_manager,
+            agent_jobs,
+        }
+    }
+}
+
+/// How often expired login sessions are deleted
+const SESSION_PRUNE_INTERVAL_SECS: u64 = 60 * 60;
+
+/// How often memories without an embedding are embedded, and how many per pass
+const MEMORY_EMBED_INTERVAL_SECS: u64 = 60;
+const MEMORY_EMBED_BATCH_SIZE: usize = 50;
+
+/// How long shutdown waits for an interrupted agent job to reach a stopping point
+const JOB_INTERRUPT_WAIT_SECS: u64 = 10;
+
 /// SPA fallback handler - serves index.html for client-side routing
 async fn spa_fallback() -> actix_web::Result<NamedFile> {
     // Check both possible locations for frontend dist
@@ -58,10 +145,89 @@ async fn spa_fallback() -> actix_web::Result<NamedFile> {
     }
 }
 
+/// Apply pending schema migrations and report what ran
+async fn run_migrations() -> std::io::Result<()> {
+    let database_url = config::database_url();
+    let sqlite_path = if db::backend::is_postgres_url(&database_url) {
+        let shared = db::PostgresBackend::connect(&database_url).await.map_err(std::io::Error::other)?;
+        let applied = shared.migrate().await.map_err(std::io::Error::other)?;
+        for migration in &applied {
+            println!("Applied Postgres migration {:04}_{}", migration.version, migration.name);
+        }
+        let version = shared.schema_version().await.map_err(std::io::Error::other)?;
+        println!("Postgres: {} migration(s) applied, schema at version {}", applied.len(), version);
+        config::sqlite_path()
+    } else {
+        database_url
+    };
+
+    let db = Database::open(&sqlite_path).map_err(std::io::Error::other)?;
+    let applied = db.migrate().map_err(std::io::Error::other)?;
+    for migration in &applied {
+        println!("Applied migration {:04}_{}", migration.version, migration.name);
+    }
+    let version = db.schema_version().map_err(std::io::Error::other)?;
+    println!("{}: {} migration(s) applied, schema at version {}", sqlite_path, applied.len(), version);
+    Ok(())
+}
+
+/// Re-wrap every stored API key, wallet key and webhook secret under the current master key
+async fn rotate_api_keys() -> std::io::Result<()> {
+    let db = db::connect(&config::database_url()).await.map_err(std::io::Error::other)?;
+    let Some(key_id) = db.keys.current_id().map(str::to_string) else {
+        return Err(std::io::Error::other(format!(
+            "{} is not set; nothing to rotate to",
+            config::env_vars::MASTER_KEY
+        )));
+    };
+    let rotated = db.rotate_api_keys().await.map_err(std::io::Error::other)?;
+    println!("Re-encrypted {} API key(s) under master key {}", rotated, key_id);
+    let rotated = db.rotate_wallet_keys().await.map_err(std::io::Error::other)?;
+    println!("Re-encrypted {} wallet key(s) under master key {}", rotated, key_id);
+    let rotated = db.rotate_webhook_secrets().await.map_err(std::io::Error::other)?;
+    println!("Re-encrypted {} webhook secret(s) under master key {}", rotated, key_id);
+    Ok(())
+}
+
+/// `env_logger` with secrets scrubbed from every line (tracing events are forwarded here too)
+fn init_logger() {
+    use std::io::Write;
+
+    env_logger::Builder::from_env(env_logger::Env::default())
+        .format(|buf, record| {
+            writeln!(
+                buf,
+                "[{} {:<5} {}] {}",
+                buf.timestamp(),
+                record.level(),
+                record.target(),
+                redaction::redact(&record.args().to_string())
+            )
+        })
+        .init();
+}
+
 #[actix_web::main]
 async fn main() -> std::io::Result<()> {
     dotenv().ok();
-    env_logger::init();
+    let config_file = config::load_config_file().map_err(std::io::Error::other)?;
+    redaction::register_env_secrets();
+    init_logger();
+    telemetry::init();
+    if let Some(path) = &config_file {
+        log::info!("Loaded settings from {}", path.display());
+    }
+
+    // `--migrate`: apply pending schema migrations and exit without serving
+    if std::env::args().any(|arg| arg == "--migrate") {
+        return run_migrations().await;
+    }
+
+    // `keys rotate`: re-encrypt stored API keys, wallet keys and webhook secrets after changing STARK_MASTER_KEY
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if args.iter().map(String::as_str).eq(["keys", "rotate"]) {
+        return rotate_api_keys().await;
+    }
 
     // Load presets and tokens from config directory
     // Check ./config first, then ../config (for running from subdirectory)
@@ -80,6 +246,12 @@ async fn main() -> std::io::Result<()> {
     log::info!("Loading RPC provider configs from config directory");
     tools::rpc_config::load_rpc_providers(config_dir);
 
+    if let Err(errors) = config::validate() {
+        for error in &errors {
+            log::error!("Invalid configuration: {}", error);
+        }
+        return Err(std::io::Error::other(format!("invalid configuration: {}", errors.join("; "))));
+    }
     let config = Config::from_env();
     let port = config.port;
 
@@ -89,14 +261,66 @@ async fn main() -> std::io::Result<()> {
         log::error!("Failed to initialize workspace: {}", e);
     }
 
-    log::info!("Initializing database at {}", config.database_url);
-    let db = Database::new(&config.database_url).expect("Failed to initialize database");
+    if db::backend::is_postgres_url(&config.database_url) {
+        log::info!("Initializing database: shared state in Postgres, local state at {}", config::sqlite_path());
+    } else {
+        log::info!("Initializing database at {}", config.database_url);
+    }
+    let db = db::connect(&config.database_url).await.expect("Failed to initialize database");
+    match db.keys.current_id() {
+        Some(key_id) => match db.seal_plaintext_api_keys().await {
+            Ok(0) => log::info!("API keys encrypted at rest with master key {}", key_id),
+            Ok(n) => log::info!("Encrypted {} plaintext API key(s) with master key {}", n, key_id),
+            Err(e) => log::error!("Failed to encrypt stored API keys: {}", e),
+        },
+        None => log::warn!(
+            "{} is not set; API keys are stored unencrypted",
+            config::env_vars::MASTER_KEY
+        ),
+    }
+    match db.ensure_admin_user(&config.login_admin_public_address).await {
+        Ok(admin) => log::info!("Admin user: {} (id {})", admin.public_address, admin.id),
+        Err(e) => log::error!("Failed to set up the admin user: {}", e),
+    }
+    match wallet::activate_default(&db).await {
+        Ok(Some(w)) => log::info!("Signing with wallet '{}' ({})", w.name, w.address),
+        Ok(None) => match wallet::active_signer() {
+            Some(signer) => log::info!("Signing with BURNER_WALLET_BOT_PRIVATE_KEY ({:?})", wallet::WalletSigner::address(signer.as_ref())),
+            None => log::info!("No wallet configured; x402 payments and transactions are unavailable"),
+        },
+        Err(e) => log::error!("Failed to load default wallet: {}", e),
+    }
+    match ai::archetypes::profile::load_overrides(&db).await {
+        Ok(0) => {}
+        Ok(count) => log::info!("Loaded {} customized archetype profile(s)", count),
+        Err(e) => log::error!("Failed to load archetype profiles: {}", e),
+    }
     let db = Arc::new(db);
 
     // Initialize Tool Registry with built-in tools
     log::info!("Initializing tool registry");
-    let tool_registry = Arc::new(tools::create_default_registry());
+    let mut disabled_tool_groups = db.get_disabled_tool_groups().await.unwrap_or_else(|e| {
+        log::warn!("Failed to load disabled tool groups: {}", e);
+        Vec::new()
+    });
+    disabled_tool_groups.extend(config::disabled_tool_groups());
+    let tool_registry = Arc::new(
+        tools::ToolRegistryBuilder::new()
+            .with_builtin_tools()
+            .with_disabled_groups(disabled_tool_groups)
+            .build(),
+    );
     log::info!("Registered {} tools", tool_registry.len());
+    for group in tool_registry.disabled_groups() {
+        log::info!("Tool group '{}' is disabled", group.as_str());
+    }
+
+    // `mcp`: serve the tool registry to an MCP client over stdin/stdout instead of starting the server
+    if args.iter().map(String::as_str).eq(["mcp"]) {
+        let broadcaster = Arc::new(EventBroadcaster::new());
+        let process_manager = Arc::new(ProcessManager::new(broadcaster.clone()));
+        return mcp::stdio::serve(McpServer::new(db, tool_registry, broadcaster, process_manager)).await;
+    }
 
     // Initialize Skill Registry (database-backed)
     log::info!("Initializing skill registry");
@@ -107,7 +331,7 @@ async fn main() -> std::io::Result<()> {
         log::warn!("Failed to load skills from disk: {}", e);
         0
     });
-    log::info!("Loaded {} skills from disk, {} total in database", skill_count, skill_registry.len());
+    log::info!("Loaded {} skills from disk, {} total in database", skill_count, skill_registry.len().await);
 
     // Initialize Gateway with tool registry and wallet for x402 payment support
     log::info!("Initializing Gateway");
@@ -127,6 +351,10 @@ async fn main() -> std::io::Result<()> {
     hook_manager.register(Arc::new(AutoMemoryHook::new(db.clone())));
     log::info!("Registered {} hooks", hook_manager.hook_count());
 
+    // Initialize Process Manager for background commands
+    log::info!("Initializing process manager");
+    let process_manager = Arc::new(ProcessManager::new(gateway.broadcaster().clone()));
+
     // Create the shared MessageDispatcher for all message processing
     log::info!("Initializing message dispatcher");
     let dispatcher = Arc::new(
@@ -137,9 +365,20 @@ async fn main() -> std::io::Result<()> {
             execution_tracker.clone(),
             config.burner_wallet_private_key.clone(),
             Some(skill_registry.clone()),
-        ).with_hook_manager(hook_manager.clone())
+        )
+        .with_hook_manager(hook_manager.clone())
+        .with_process_manager(process_manager.clone())
     );
 
+    // MCP clients connecting over HTTP (/api/mcp) share the registry with chat
+    let mcp_server = Arc::new(McpServer::new(
+        db.clone(),
+        tool_registry.clone(),
+        gateway.broadcaster().clone(),
+        process_manager.clone(),
+    ));
+    let mcp_sessions = Arc::new(SseSessions::new());
+
     // Get broadcaster and channel_manager for the /ws route
     let broadcaster = gateway.broadcaster();
     let channel_manager = gateway.channel_manager();
@@ -165,6 +404,115 @@ async fn main() -> std::io::Result<()> {
         scheduler_handle.start(scheduler_shutdown_rx).await;
     });
 
+    // Start background agent job worker
+    log::info!("Initializing agent job queue");
+    let agent_jobs = Arc::new(AgentJobQueue::new(
+        db.clone(),
+        tool_registry.clone(),
+        process_manager.clone(),
+        config.burner_wallet_private_key.clone(),
+    ));
+    let agent_jobs_handle = Arc::clone(&agent_jobs);
+    // Held for the life of the server; dropping it stops the worker
+    let (_agent_jobs_shutdown_tx, agent_jobs_shutdown_rx) = tokio::sync::oneshot::channel();
+    let mut agent_jobs_worker = tokio::spawn(async move {
+        agent_jobs_handle.start(agent_jobs_shutdown_rx).await;
+    });
+
+    // Queue scheduled tasks as agent jobs when their cron expressions fire
+    let schedules = Arc::new(ScheduleRunner::new(db.clone(), agent_jobs.clone()));
+    let schedules_handle = Arc::clone(&schedules);
+    let (schedules_shutdown_tx, schedules_shutdown_rx) = tokio::sync::oneshot::channel();
+    tokio::spawn(async move {
+        schedules_handle.start(schedules_shutdown_rx).await;
+    });
+
+    // Turn mail from allowlisted senders into agent jobs (only when IMAP is configured)
+    let (email_shutdown_tx, email_shutdown_rx) = tokio::sync::oneshot::channel();
+    match integrations::email::InboxConfig::from_env() {
+        Ok(Some(inbox_config)) => {
+            let inbox = Arc::new(integrations::email::EmailInbox::new(
+                db.clone(),
+                agent_jobs.clone(),
+                inbox_config,
+                integrations::email::EmailConfig::from_env(),
+            ));
+            tokio::spawn(async move {
+                inbox.start(email_shutdown_rx).await;
+            });
+        }
+        Ok(None) => {}
+        Err(e) => log::error!("Email inbox disabled: {}", e),
+    }
+
+    // Rate limits for chat and agent endpoints (updated live from bot settings)
+    let bot_settings = db.get_bot_settings().await.unwrap_or_default();
+    let rate_limits = RateLimits::from_settings(&bot_settings);
+    if rate_limits.per_minute == 0 {
+        log::info!("Rate limiting disabled");
+    } else {
+        log::info!(
+            "Rate limiting chat and agent requests to {}/min (burst {}) per token and per IP",
+            rate_limits.per_minute,
+            rate_limits.burst
+        );
+    }
+    let rate_limiter = Arc::new(RateLimiter::new(rate_limits, config::trust_proxy()));
+
+    // Prune expired login sessions; with sliding expiry they'd otherwise pile up forever
+    let prune_db = db.clone();
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(std::time::Duration::from_secs(SESSION_PRUNE_INTERVAL_SECS));
+        loop {
+            interval.tick().await;
+            match prune_db.delete_expired_sessions().await {
+                Ok(0) => {}
+                Ok(n) => log::info!("Pruned {} expired login session(s)", n),
+                Err(e) => log::warn!("Failed to prune expired sessions: {}", e),
+            }
+        }
+    });
+
+    // Embed new and edited memories for semantic recall
+    if config::memory_config().enable_vector_search {
+        let embed_db = db.clone();
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(std::time::Duration::from_secs(MEMORY_EMBED_INTERVAL_SECS));
+            loop {
+                interval.tick().await;
+                let searcher = memory::HybridSearcher::configured(embed_db.clone(), &config::memory_config()).await;
+                match searcher.backfill_embeddings(MEMORY_EMBED_BATCH_SIZE).await {
+                    Ok(0) => {}
+                    Ok(n) => log::info!("Embedded {} memor{}", n, if n == 1 { "y" } else { "ies" }),
+                    Err(e) => log::warn!("Failed to embed memories: {}", e),
+                }
+            }
+        });
+    }
+
+    // Keep the skill marketplace repository in sync, if one is configured
+    if let Some(repo_url) = config::skills_git_url() {
+        match config::skills_sync_interval() {
+            Some(period) => {
+                let branch = config::skills_git_branch();
+                log::info!("Syncing skills from {}@{} every {}m", repo_url, branch, period.as_secs() / 60);
+                let sync_registry = skill_registry.clone();
+                let sync_db = db.clone();
+                tokio::spawn(async move {
+                    // The first tick fires immediately, so a fresh instance syncs at startup
+                    let mut interval = tokio::time::interval(period);
+                    loop {
+                        interval.tick().await;
+                        if let Err(e) = skills::sync_skills_from_git(&sync_registry, &sync_db, &repo_url, &branch).await {
+                            log::warn!("Skill sync failed: {}", e);
+                        }
+                    }
+                });
+            }
+            None => log::info!("Skill repository {} configured; syncs only run via POST /api/skills/sync", repo_url),
+        }
+    }
+
     // Determine frontend dist path (check both locations)
     // Set DISABLE_FRONTEND=1 to disable static file serving (for separate dev server)
     let frontend_dist = if std::env::var("DISABLE_FRONTEND").map(|v| v == "1" || v.to_lowercase() == "true").unwrap_or(false) {
@@ -194,18 +542,23 @@ async fn main() -> std::io::Result<()> {
     let bcast = broadcaster.clone();
     let chan_mgr = channel_manager.clone();
     let hook_mgr = hook_manager.clone();
+    let proc_mgr = process_manager.clone();
+    let jobs = agent_jobs.clone();
+    let scheds = schedules.clone();
     let frontend_dist = frontend_dist.to_string();
 
-    HttpServer::new(move || {
-        let cors = Cors::default()
-            .allow_any_origin()
-            .allow_any_method()
-            .allow_any_header()
-            .max_age(3600);
+    let cors_origins = config::cors_origins();
+    let cors_methods = config::cors_methods();
+    let host = config.host.clone();
+    let shutdown_timeout = config::shutdown_timeout();
+    let app_db = Arc::clone(&db);
+
+    let mut server = HttpServer::new(move || {
+        let cors = middleware::security::cors(&cors_origins, &cors_methods);
 
         let mut app = App::new()
             .app_data(web::Data::new(AppState {
-                db: Arc::clone(&db),
+                db: Arc::clone(&app_db),
                 config: config.clone(),
                 gateway: Arc::clone(&gateway),
                 tool_registry: Arc::clone(&tool_reg),
@@ -216,35 +569,64 @@ async fn main() -> std::io::Result<()> {
                 channel_manager: Arc::clone(&chan_mgr),
                 broadcaster: Arc::clone(&bcast),
                 hook_manager: Arc::clone(&hook_mgr),
+                process_manager: Arc::clone(&proc_mgr),
+                agent_jobs: Arc::clone(&jobs),
+                schedules: Arc::clone(&scheds),
+                rate_limiter: Arc::clone(&rate_limiter),
+                mcp: Arc::clone(&mcp_server),
+                mcp_sessions: Arc::clone(&mcp_sessions),
             }))
             .app_data(web::Data::new(Arc::clone(&sched)))
             // WebSocket data for /ws route
-            .app_data(web::Data::new(Arc::clone(&db)))
+            .app_data(web::Data::new(Arc::clone(&app_db)))
             .app_data(web::Data::new(Arc::clone(&chan_mgr)))
             .app_data(web::Data::new(Arc::clone(&bcast)))
-            .wrap(Logger::default())
+            .wrap(from_fn(middleware::rate_limit::rate_limit))
+            .wrap(from_fn(middleware::metrics::track_responses))
+            .wrap(from_fn(middleware::security::security_headers))
+            .wrap(from_fn(middleware::request_id::request_id))
+            .wrap(Logger::new(r#"%a "%r" %s %b "%{Referer}i" "%{User-Agent}i" %T %{x-request-id}o"#))
             .wrap(cors)
             .configure(controllers::health::config)
+            .configure(controllers::admin::config)
+            .configure(controllers::agent::config)
             .configure(controllers::auth::config)
             .configure(controllers::dashboard::config)
             .configure(controllers::chat::config)
             .configure(controllers::api_keys::config)
+            .configure(controllers::api_tokens::config)
             .configure(controllers::channels::config)
             .configure(controllers::agent_settings::configure)
             .configure(controllers::sessions::config)
             .configure(controllers::memories::config)
+            .configure(controllers::embeddings::config)
             .configure(controllers::identity::config)
             .configure(controllers::tools::config)
             .configure(controllers::skills::config)
             .configure(controllers::cron::config)
+            .configure(controllers::schedules::config)
+            .configure(controllers::templates::config)
+            .configure(controllers::personas::config)
+            .configure(controllers::webhooks::config)
             .configure(controllers::gmail::config)
             .configure(controllers::payments::config)
             .configure(controllers::eip8004::config)
             .configure(controllers::files::config)
             .configure(controllers::intrinsic::config)
             .configure(controllers::journal::config)
+            .configure(controllers::mcp::config)
+            .configure(controllers::usage::config)
+            .configure(controllers::users::config)
+            .configure(controllers::transactions::config)
+            .configure(controllers::audit::config)
+            .configure(controllers::approvals::config)
+            .configure(controllers::wallets::config)
+            .configure(controllers::workspaces::config)
+            .configure(controllers::voice::config)
+            .configure(controllers::preview::config)
             // WebSocket Gateway route (same port as HTTP, required for single-port platforms)
-            .route("/ws", web::get().to(gateway::actix_ws::ws_handler));
+            .route("/ws", web::get().to(gateway::actix_ws::ws_handler))
+            .route("/ws/chat", web::get().to(gateway::chat_ws::chat_ws_handler));
 
         // Serve static files only if frontend dist exists
         if !frontend_dist.is_empty() {
@@ -257,7 +639,70 @@ async fn main() -> std::io::Result<()> {
 
         app
     })
-    .bind(("0.0.0.0", port))?
-    .run()
-    .await
+    .bind((host.as_str(), port))?
+    // Signals are handled below, so the job queue stops before connections drain
+    .disable_signals()
+    .shutdown_timeout(shutdown_timeout.as_secs())
+    .run();
+    let server_handle = server.handle();
+
+    tokio::select! {
+        result = &mut server => return result,
+        _ = shutdown_signal() => {}
+    }
+
+    // Stop taking work: new jobs are refused and scheduled tasks, the inbox
+    // and channel listeners stop, while in-flight requests and the running
+    // agent job get the grace period to finish
+    log::info!("Shutting down; waiting up to {}s for requests and the running agent job", shutdown_timeout.as_secs());
+    agent_jobs.stop();
+    let _ = scheduler_shutdown_tx.send(());
+    let _ = schedules_shutdown_tx.send(());
+    let _ = email_shutdown_tx.send(());
+    channel_manager.stop_all().await;
+
+    // `server` is the server's event loop, so it has to be polled for the stop to complete
+    let (_, server_result, drained) = tokio::join!(
+        server_handle.stop(true),
+        server,
+        tokio::time::timeout(shutdown_timeout, &mut agent_jobs_worker),
+    );
+    if drained.is_err() {
+        // The job stops before its next model or tool call and is requeued with its progress
+        log::warn!("Agent job still running after {}s; interrupting it", shutdown_timeout.as_secs());
+        agent_jobs.interrupt();
+        if tokio::time::timeout(std::time::Duration::from_secs(JOB_INTERRUPT_WAIT_SECS), &mut agent_jobs_worker).await.is_err() {
+            log::warn!("Agent job did not stop in time; it is requeued on the next start");
+        }
+    }
+
+    process_manager.kill_all().await;
+    if let Err(e) = db.flush().await {
+        log::warn!("Failed to flush the database: {}", e);
+    }
+    log::info!("Shutdown complete");
+    server_result
+}
+
+/// Resolves on SIGTERM or Ctrl-C
+async fn shutdown_signal() {
+    #[cfg(unix)]
+    let terminate = async {
+        match tokio::signal::unix::signal(tokio::signal::unix::SignalKind::terminate()) {
+            Ok(mut sigterm) => {
+                sigterm.recv().await;
+            }
+            Err(e) => {
+                log::warn!("Failed to listen for SIGTERM: {}", e);
+                std::future::pending::<()>().await;
+            }
+        }
+    };
+    #[cfg(not(unix))]
+    let terminate = std::future::pending::<()>();
+
+    tokio::select! {
+        _ = tokio::signal::ctrl_c() => {}
+        _ = terminate => {}
+    }
 }
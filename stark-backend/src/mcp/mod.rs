@@ -0,0 +1,363 @@
+//! MCP (Model Context Protocol) server mode
+//!
+//! Exposes the builtin tool registry to MCP clients such as Claude Desktop.
+//! [`McpServer`] answers MCP's JSON-RPC methods (`initialize`, `tools/list`,
+//! `tools/call`, `ping`); the transports only move messages:
+//!
+//! - [`stdio`]: one client over stdin/stdout (`stark-backend mcp`), trusted
+//!   like the rest of the CLI
+//! - [`sse`]: clients over HTTP with server-sent events (`/api/mcp/sse`),
+//!   limited to what their API token's scopes and tool policy allow
+//!
+//! The global tool config applies as it does to chat. System tools are left
+//! out because they drive a chat session (subtypes, subagents, ask_user).
+
+pub mod sse;
+pub mod stdio;
+
+use crate::db::Database;
+use crate::execution::ProcessManager;
+use crate::gateway::events::EventBroadcaster;
+use crate::controllers::agent::workspace_prefix;
+use crate::models::{Caller, Scopes};
+use crate::tools::{RegisterStore, ToolConfig, ToolContext, ToolDefinition, ToolGroup, ToolRegistry};
+use crate::workspace::{WorkspaceKind, WorkspaceManager};
+use serde_json::{json, Value};
+use std::sync::Arc;
+use std::time::Instant;
+
+/// Protocol revisions this server speaks, newest first
+const PROTOCOL_VERSIONS: &[&str] = &["2025-03-26", "2024-11-05"];
+
+/// Workspace the tools of an MCP session work in
+const MCP_WORKSPACE: &str = "mcp";
+
+// JSON-RPC error codes
+pub const PARSE_ERROR: i64 = -32700;
+const INVALID_REQUEST: i64 = -32600;
+const METHOD_NOT_FOUND: i64 = -32601;
+const INVALID_PARAMS: i64 = -32602;
+
+/// A JSON-RPC error response
+pub fn error_response(id: Value, code: i64, message: impl Into<String>) -> Value {
+    json!({
+        "jsonrpc": "2.0",
+        "id": id,
+        "error": { "code": code, "message": message.into() },
+    })
+}
+
+fn result_response(id: Value, result: Value) -> Value {
+    json!({ "jsonrpc": "2.0", "id": id, "result": result })
+}
+
+/// MCP tool entry for a tool definition
+pub fn to_mcp_tool(definition: &ToolDefinition) -> Value {
+    json!({
+        "name": definition.name,
+        "description": definition.description,
+        "inputSchema": definition.input_schema,
+    })
+}
+
+/// Workspace of a session: members get their own, under their `workspace_prefix`
+fn workspace_name(caller: Option<&Caller>) -> String {
+    format!("{}{}", caller.and_then(workspace_prefix).unwrap_or_default(), MCP_WORKSPACE)
+}
+
+/// One connected client: what it may use, and the tool state kept between its calls
+#[derive(Clone)]
+pub struct McpSession {
+    scopes: Scopes,
+    context: ToolContext,
+    /// User who opened the session (None: the local CLI)
+    user_id: Option<i64>,
+}
+
+impl McpSession {
+    pub fn user_id(&self) -> Option<i64> {
+        self.user_id
+    }
+}
+
+pub struct McpServer {
+    db: Arc<Database>,
+    tool_registry: Arc<ToolRegistry>,
+    broadcaster: Arc<EventBroadcaster>,
+    process_manager: Arc<ProcessManager>,
+}
+
+impl McpServer {
+    pub fn new(
+        db: Arc<Database>,
+        tool_registry: Arc<ToolRegistry>,
+        broadcaster: Arc<EventBroadcaster>,
+        process_manager: Arc<ProcessManager>,
+    ) -> Self {
+        Self {
+            db,
+            tool_registry,
+            broadcaster,
+            process_manager,
+        }
+    }
+
+    /// Start a session for `caller`, limited to its token's scopes and tool
+    /// policy. Without a caller it is the local CLI, which gets every scope.
+    pub fn session(&self, caller: Option<&Caller>) -> Result<McpSession, String> {
+        let scopes = caller.map_or_else(Scopes::full, |c| c.scopes.clone());
+        let tool_policy = caller.and_then(|c| c.tool_policy.clone());
+        let workspace = match tool_policy.as_ref().and_then(|p| p.workspace_root.clone()) {
+            Some(root) => root,
+            None => WorkspaceManager::from_env()
+                .allocate(WorkspaceKind::AgentRun, &workspace_name(caller))?
+                .to_string_lossy()
+                .to_string(),
+        };
+        let mut context = ToolContext::new()
+            .with_workspace(workspace)
+            .with_broadcaster(Arc::clone(&self.broadcaster))
+            .with_database(Arc::clone(&self.db))
+            .with_process_manager(Arc::clone(&self.process_manager))
+            .with_registers(RegisterStore::new());
+        if let Some(policy) = tool_policy {
+            context = context.with_tool_policy(policy);
+        }
+        Ok(McpSession {
+            scopes,
+            context,
+            user_id: caller.map(|c| c.user.id),
+        })
+    }
+
+    /// Handle a JSON-RPC message or batch. Returns the response to send, if any
+    /// (notifications get none).
+    pub async fn handle(&self, session: &McpSession, message: Value) -> Option<Value> {
+        match message {
+            Value::Array(batch) if batch.is_empty() => {
+                Some(error_response(Value::Null, INVALID_REQUEST, "Empty batch"))
+            }
+            Value::Array(batch) => {
+                let mut responses = Vec::new();
+                for message in batch {
+                    if let Some(response) = self.handle_one(session, message).await {
+                        responses.push(response);
+                    }
+                }
+                (!responses.is_empty()).then_some(Value::Array(responses))
+            }
+            message => self.handle_one(session, message).await,
+        }
+    }
+
+    async fn handle_one(&self, session: &McpSession, message: Value) -> Option<Value> {
+        let Some(method) = message.get("method").and_then(Value::as_str) else {
+            // Responses to requests we never send, or malformed input
+            return message
+                .get("id")
+                .map(|id| error_response(id.clone(), INVALID_REQUEST, "Expected a request"));
+        };
+        // Without an id it's a notification, which never gets a reply
+        let id = message.get("id").cloned()?;
+        let params = message.get("params").cloned().unwrap_or(Value::Null);
+
+        let result = match method {
+            "initialize" => Ok(self.initialize(&params)),
+            "ping" => Ok(json!({})),
+            "tools/list" => Ok(self.list_tools(session).await),
+            "tools/call" => self.call_tool(session, &params).await,
+            _ => Err((METHOD_NOT_FOUND, format!("Method '{}' not found", method))),
+        };
+        Some(match result {
+            Ok(result) => result_response(id, result),
+            Err((code, message)) => error_response(id, code, message),
+        })
+    }
+
+    fn initialize(&self, params: &Value) -> Value {
+        let requested = params.get("protocolVersion").and_then(Value::as_str);
+        let version = requested
+            .filter(|v| PROTOCOL_VERSIONS.contains(v))
+            .unwrap_or(PROTOCOL_VERSIONS[0]);
+        json!({
+            "protocolVersion": version,
+            "capabilities": { "tools": { "listChanged": false } },
+            "serverInfo": { "name": "stark-bot", "version": env!("CARGO_PKG_VERSION") },
+        })
+    }
+
+    /// Global tool config, minus System tools and tools the session's scopes
+    /// and tool policy don't cover
+    async fn tool_config(&self, session: &McpSession) -> ToolConfig {
+        let mut config = self.db.get_effective_tool_config(None).await.unwrap_or_default();
+        config.denied_groups.push(ToolGroup::System.as_str().to_string());
+        config.deny_list.extend(self.tool_registry.tools_outside_scopes(&session.scopes));
+        for policy in &session.context.tool_policies {
+            config.deny_list.extend(self.tool_registry.tools_outside_policy(policy));
+        }
+        config
+    }
+
+    async fn list_tools(&self, session: &McpSession) -> Value {
+        let config = self.tool_config(session).await;
+        let mut tools = self.tool_registry.get_tool_definitions(&config);
+        tools.sort_by(|a, b| a.name.cmp(&b.name));
+        json!({ "tools": tools.iter().map(to_mcp_tool).collect::<Vec<_>>() })
+    }
+
+    async fn call_tool(&self, session: &McpSession, params: &Value) -> Result<Value, (i64, String)> {
+        let name = params
+            .get("name")
+            .and_then(Value::as_str)
+            .ok_or((INVALID_PARAMS, "Missing tool name".to_string()))?;
+        let arguments = params.get("arguments").cloned().unwrap_or_else(|| json!({}));
+        if !arguments.is_object() {
+            return Err((INVALID_PARAMS, "Tool arguments must be an object".to_string()));
+        }
+        if !self.tool_registry.has_tool(name) {
+            return Err((INVALID_PARAMS, format!("Unknown tool '{}'", name)));
+        }
+
+        // API keys can change between calls, so load them each time
+        let mut context = session.context.clone();
+        if let Ok(keys) = self.db.list_api_keys().await {
+            for key in keys {
+                context = context.with_api_key(&key.service_name, key.api_key);
+            }
+        }
+
+        let config = self.tool_config(session).await;
+        let started = Instant::now();
+        let result = self.tool_registry.execute(name, arguments, &context, Some(&config)).await;
+        log::info!(
+            "[MCP] {} {} in {} ms",
+            name,
+            if result.success { "succeeded" } else { "failed" },
+            started.elapsed().as_millis()
+        );
+
+        let text = match (&result.error, result.content.is_empty()) {
+            (Some(error), true) => error.clone(),
+            _ => result.content.clone(),
+        };
+        let mut content = vec![json!({ "type": "text", "text": text })];
+        if let Some(metadata) = &result.metadata {
+            content.push(json!({ "type": "text", "text": metadata.to_string() }));
+        }
+        Ok(json!({ "content": content, "isError": !result.success }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::{Scope, UserRole};
+
+    fn server() -> McpServer {
+        let broadcaster = Arc::new(EventBroadcaster::new());
+        McpServer::new(
+            Arc::new(Database::new(":memory:").unwrap()),
+            Arc::new(crate::tools::ToolRegistryBuilder::new().with_builtin_tools().build()),
+            Arc::clone(&broadcaster),
+            Arc::new(ProcessManager::new(broadcaster)),
+        )
+    }
+
+    fn session(scopes: Scopes, workspace: &std::path::Path) -> McpSession {
+        McpSession {
+            scopes,
+            context: ToolContext::new().with_workspace(workspace.to_string_lossy().to_string()),
+            user_id: None,
+        }
+    }
+
+    fn tool_names(response: &Value) -> Vec<String> {
+        response["result"]["tools"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|t| t["name"].as_str().unwrap().to_string())
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn test_initialize_and_notifications() {
+        let server = server();
+        let dir = tempfile::tempdir().unwrap();
+        let session = session(Scopes::full(), dir.path());
+
+        let init = json!({"jsonrpc": "2.0", "id": 1, "method": "initialize", "params": {"protocolVersion": "2024-11-05"}});
+        let response = server.handle(&session, init).await.unwrap();
+        assert_eq!(response["result"]["protocolVersion"], "2024-11-05");
+        assert_eq!(response["result"]["serverInfo"]["name"], "stark-bot");
+
+        let initialized = json!({"jsonrpc": "2.0", "method": "notifications/initialized"});
+        assert!(server.handle(&session, initialized).await.is_none());
+
+        let unknown = json!({"jsonrpc": "2.0", "id": "x", "method": "resources/list"});
+        let response = server.handle(&session, unknown).await.unwrap();
+        assert_eq!(response["id"], "x");
+        assert_eq!(response["error"]["code"], METHOD_NOT_FOUND);
+    }
+
+    #[tokio::test]
+    async fn test_tools_follow_scopes() {
+        let server = server();
+        let dir = tempfile::tempdir().unwrap();
+        let list = json!({"jsonrpc": "2.0", "id": 2, "method": "tools/list"});
+
+        let full = tool_names(&server.handle(&session(Scopes::full(), dir.path()), list.clone()).await.unwrap());
+        assert!(full.contains(&"exec".to_string()));
+        assert!(full.contains(&"token_lookup".to_string()));
+        assert!(!full.contains(&"set_agent_subtype".to_string()));
+
+        let chat = session(Scopes::new([Scope::Chat]), dir.path());
+        let limited = tool_names(&server.handle(&chat, list).await.unwrap());
+        assert!(!limited.contains(&"exec".to_string()));
+        assert!(!limited.contains(&"swap".to_string()));
+        assert!(limited.contains(&"read_file".to_string()));
+
+        let call = json!({"jsonrpc": "2.0", "id": 3, "method": "tools/call", "params": {"name": "exec", "arguments": {"command": "echo hi"}}});
+        let response = server.handle(&chat, call).await.unwrap();
+        assert_eq!(response["result"]["isError"], true);
+    }
+
+    #[tokio::test]
+    async fn test_call_tool() {
+        let server = server();
+        let dir = tempfile::tempdir().unwrap();
+        let session = session(Scopes::full(), dir.path());
+        std::fs::write(dir.path().join("hello.txt"), "hello from mcp").unwrap();
+
+        let call = json!({"jsonrpc": "2.0", "id": 4, "method": "tools/call", "params": {"name": "read_file", "arguments": {"path": "hello.txt"}}});
+        let response = server.handle(&session, call).await.unwrap();
+        assert_eq!(response["result"]["isError"], false);
+        assert!(response["result"]["content"][0]["text"].as_str().unwrap().contains("hello from mcp"));
+
+        let batch = json!([
+            {"jsonrpc": "2.0", "id": 5, "method": "ping"},
+            {"jsonrpc": "2.0", "id": 6, "method": "tools/call", "params": {"name": "no_such_tool"}},
+        ]);
+        let responses = server.handle(&session, batch).await.unwrap();
+        assert_eq!(responses[0]["result"], json!({}));
+        assert_eq!(responses[1]["error"]["code"], INVALID_PARAMS);
+    }
+
+    #[test]
+    fn test_members_get_their_own_workspace() {
+        let caller = |id, role| Caller {
+            user: crate::models::User {
+                id,
+                public_address: format!("0x{:040x}", id),
+                name: None,
+                role,
+                created_at: chrono::Utc::now(),
+            },
+            scopes: Scopes::full(),
+            tool_policy: None,
+        };
+        assert_eq!(workspace_name(None), "mcp");
+        assert_eq!(workspace_name(Some(&caller(1, UserRole::Admin))), "mcp");
+        assert_eq!(workspace_name(Some(&caller(2, UserRole::Member))), "user-2-mcp");
+    }
+}
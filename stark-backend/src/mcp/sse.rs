@@ -0,0 +1,63 @@
+//! Sessions for MCP over server-sent events
+//!
+//! A client opens `GET /api/mcp/sse` and is sent an `endpoint` event with the
+//! URL to POST its JSON-RPC messages to. Responses come back on the stream as
+//! `message` events, so each open stream is kept here under a random id.
+
+use super::McpSession;
+use dashmap::DashMap;
+use serde_json::Value;
+use tokio::sync::mpsc;
+
+/// Frames buffered per stream before senders wait for the client to catch up
+const STREAM_BUFFER: usize = 64;
+
+/// One SSE frame
+pub fn event_frame(event: &str, data: &str) -> String {
+    format!("event: {}\ndata: {}\n\n", event, data)
+}
+
+/// An open event stream and the MCP session behind it
+#[derive(Clone)]
+pub struct SseSession {
+    pub session: McpSession,
+    sender: mpsc::Sender<String>,
+}
+
+impl SseSession {
+    /// Send a JSON-RPC message to the client. Fails once the client has gone.
+    pub async fn send(&self, message: &Value) -> Result<(), String> {
+        self.send_frame(event_frame("message", &message.to_string())).await
+    }
+
+    pub async fn send_frame(&self, frame: String) -> Result<(), String> {
+        self.sender.send(frame).await.map_err(|_| "Event stream closed".to_string())
+    }
+}
+
+#[derive(Default)]
+pub struct SseSessions {
+    sessions: DashMap<String, SseSession>,
+}
+
+impl SseSessions {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Register a stream for `session`, returning its id and the frames to send
+    pub fn open(&self, session: McpSession) -> (String, mpsc::Receiver<String>) {
+        let id = uuid::Uuid::new_v4().simple().to_string();
+        let (sender, receiver) = mpsc::channel(STREAM_BUFFER);
+        self.sessions.insert(id.clone(), SseSession { session, sender });
+        (id, receiver)
+    }
+
+    pub fn get(&self, id: &str) -> Option<SseSession> {
+        self.sessions.get(id).map(|s| s.clone())
+    }
+
+    pub fn close(&self, id: &str) {
+        self.sessions.remove(id);
+    }
+}
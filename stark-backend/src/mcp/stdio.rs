@@ -0,0 +1,33 @@
+//! MCP over stdin/stdout: newline-delimited JSON-RPC, one message per line
+//!
+//! Stdout carries only protocol messages; logs go to stderr.
+
+use super::{error_response, McpServer, PARSE_ERROR};
+use serde_json::Value;
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+
+/// Serve one client until stdin closes. The caller runs the process, so it
+/// gets every scope, like the other CLI commands.
+pub async fn serve(server: McpServer) -> std::io::Result<()> {
+    let session = server.session(None).map_err(std::io::Error::other)?;
+    let mut lines = BufReader::new(tokio::io::stdin()).lines();
+    let mut stdout = tokio::io::stdout();
+    log::info!("[MCP] Serving tools over stdio");
+
+    while let Some(line) = lines.next_line().await? {
+        if line.trim().is_empty() {
+            continue;
+        }
+        let response = match serde_json::from_str::<Value>(&line) {
+            Ok(message) => server.handle(&session, message).await,
+            Err(e) => Some(error_response(Value::Null, PARSE_ERROR, format!("Parse error: {}", e))),
+        };
+        if let Some(response) = response {
+            stdout.write_all(format!("{}\n", response).as_bytes()).await?;
+            stdout.flush().await?;
+        }
+    }
+
+    log::info!("[MCP] stdin closed, exiting");
+    Ok(())
+}
@@ -139,10 +139,12 @@ impl MemoryConsolidator {
             Message {
                 role: MessageRole::System,
                 content: "You consolidate related memories into single comprehensive entries. Be concise but preserve all important facts.".to_string(),
+                images: Vec::new(),
             },
             Message {
                 role: MessageRole::User,
                 content: merge_prompt,
+                images: Vec::new(),
             },
         ];
 
@@ -176,11 +178,11 @@ impl MemoryConsolidator {
             Some(1.0),
             Some("consolidated"),
             None, None, None,
-        ).map_err(|e| format!("Failed to create consolidated memory: {}", e))?;
+        ).await.map_err(|e| format!("Failed to create consolidated memory: {}", e))?;
 
         // Mark original memories as superseded
         for mem in &cluster.memories {
-            if let Err(e) = self.db.supersede_memory(mem.id, consolidated.id) {
+            if let Err(e) = self.db.supersede_memory(mem.id, consolidated.id).await {
                 log::warn!("Failed to supersede memory {}: {}", mem.id, e);
             }
         }
@@ -239,7 +241,7 @@ impl MemoryConsolidator {
 
         if !dry_run {
             for (keep_id, remove_id, _) in &duplicates {
-                if let Err(e) = self.db.supersede_memory(*remove_id, *keep_id) {
+                if let Err(e) = self.db.supersede_memory(*remove_id, *keep_id).await {
                     log::warn!("Failed to supersede duplicate memory {}: {}", remove_id, e);
                 }
             }
@@ -259,7 +261,7 @@ impl MemoryConsolidator {
         memory_type: Option<MemoryType>,
         limit: i32,
     ) -> Result<Vec<(Memory, Vec<f32>)>, String> {
-        let conn = self.db.conn.lock().unwrap();
+        let conn = self.db.conn().await.map_err(|e| format!("Database error: {}", e))?;
 
         let type_filter = memory_type
             .map(|t| format!("AND m.memory_type = '{}'", t.as_str()))
@@ -271,7 +273,7 @@ impl MemoryConsolidator {
              m.created_at, m.updated_at, m.expires_at,
              m.entity_type, m.entity_name, m.confidence, m.source_type, m.last_referenced_at,
              m.superseded_by, m.superseded_at, m.valid_from, m.valid_until, m.temporal_type,
-             e.embedding
+             e.embedding, m.user_id
              FROM memories m
              JOIN memory_embeddings e ON m.id = e.memory_id
              WHERE m.identity_id = ?1 AND m.superseded_by IS NULL {}
@@ -427,6 +429,7 @@ impl Database {
                 DateTime::parse_from_rfc3339(&s).ok().map(|dt| dt.with_timezone(&Utc))
             }),
             temporal_type: row.get(23)?,
+            user_id: row.get("user_id")?,
         })
     }
 }
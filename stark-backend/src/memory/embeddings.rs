@@ -4,10 +4,25 @@
 //! - "openai" - OpenAI's text-embedding-ada-002 or text-embedding-3-small
 //! - "local" - Local fastembed (future implementation)
 //! - "none" - Disabled (fallback to BM25 only)
+//!
+//! "openai" works with any OpenAI-compatible `/embeddings` API. Without a dedicated
+//! key it reuses the endpoint and key of the configured AI provider.
+//!
+//! Vectors are stored as little-endian f32 blobs (see [`vector_to_blob`]) and
+//! compared by cosine similarity.
 
 use async_trait::async_trait;
 use serde::{Deserialize, Serialize};
 
+use crate::ai::archetypes::ArchetypeId;
+use crate::ai::AiClient;
+use crate::config::MemoryConfig;
+use crate::db::Database;
+use crate::models::AgentSettings;
+
+/// OpenAI's embeddings API, used when a dedicated embedding key is configured
+const OPENAI_EMBEDDINGS_URL: &str = "https://api.openai.com/v1/embeddings";
+
 /// Configuration for embedding provider
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct EmbeddingConfig {
@@ -17,6 +32,9 @@ pub struct EmbeddingConfig {
     pub model: Option<String>,
     /// API key (for remote providers)
     pub api_key: Option<String>,
+    /// Embeddings URL (None = the provider's public API)
+    #[serde(default)]
+    pub endpoint: Option<String>,
     /// Batch size for embedding generation
     pub batch_size: usize,
     /// Embedding dimensions (depends on model)
@@ -29,6 +47,7 @@ impl Default for EmbeddingConfig {
             provider: "none".to_string(),
             model: None,
             api_key: None,
+            endpoint: None,
             batch_size: 100,
             dimensions: 1536, // OpenAI default
         }
@@ -41,11 +60,55 @@ impl EmbeddingConfig {
             provider: "openai".to_string(),
             model: Some("text-embedding-3-small".to_string()),
             api_key: Some(api_key),
+            endpoint: None,
             batch_size: 100,
             dimensions: 1536,
         }
     }
 
+    /// Resolve the embedding provider for the memory settings.
+    ///
+    /// Returns a disabled config unless vector search is on. For "openai", a
+    /// dedicated `embedding_api_key` goes to OpenAI; otherwise the active agent
+    /// settings' endpoint and key are used if they point at an OpenAI-compatible API.
+    pub fn from_memory_config(memory: &MemoryConfig, settings: Option<&AgentSettings>) -> Self {
+        if !memory.enable_vector_search || memory.embedding_provider != "openai" {
+            return Self::none();
+        }
+        Self::from_provider_settings(memory, settings)
+    }
+
+    /// The OpenAI-compatible embeddings API for the settings, whether or not
+    /// memory vector search is on: the dedicated `embedding_api_key` if set,
+    /// else the AI provider's endpoint and key. Used by `/api/embeddings`.
+    pub fn from_provider_settings(memory: &MemoryConfig, settings: Option<&AgentSettings>) -> Self {
+        let (api_key, endpoint) = match (&memory.embedding_api_key, settings) {
+            (Some(key), _) => (key.clone(), None),
+            (None, Some(settings)) => match provider_embeddings_endpoint(settings) {
+                Some(endpoint) => (settings.secret_key.clone().unwrap_or_default(), Some(endpoint)),
+                None => {
+                    log::debug!("AI provider {} has no OpenAI-compatible embeddings API", settings.endpoint);
+                    return Self::none();
+                }
+            },
+            (None, None) => return Self::none(),
+        };
+
+        let mut config = Self::openai(api_key);
+        config.endpoint = endpoint;
+        if let Some(model) = &memory.embedding_model {
+            config.model = Some(model.clone());
+        }
+        config
+    }
+
+    /// [`Self::from_provider_settings`] for the current memory settings and
+    /// the active agent settings
+    pub async fn for_api(db: &Database) -> Result<Self, String> {
+        let settings = db.get_active_agent_settings().await.map_err(|e| e.to_string())?;
+        Ok(Self::from_provider_settings(&crate::config::memory_config(), settings.as_ref()))
+    }
+
     pub fn none() -> Self {
         Self::default()
     }
@@ -63,6 +126,14 @@ pub struct Embedding {
     pub dimensions: usize,
 }
 
+/// Relevance of one document to a rerank query
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct RerankResult {
+    /// Position of the document in the request
+    pub index: usize,
+    pub relevance_score: f64,
+}
+
 /// Trait for embedding providers
 #[async_trait]
 pub trait EmbeddingProvider: Send + Sync {
@@ -72,6 +143,33 @@ pub trait EmbeddingProvider: Send + Sync {
     /// Generate embeddings for multiple texts (batch)
     async fn embed_batch(&self, texts: &[&str]) -> Result<Vec<Embedding>, String>;
 
+    /// Order `documents` by relevance to `query`, most relevant first. By
+    /// default the query and documents are embedded in one batch and scored
+    /// by cosine similarity; providers with a rerank API can use it instead.
+    async fn rerank(&self, query: &str, documents: &[&str]) -> Result<Vec<RerankResult>, String> {
+        let mut texts = Vec::with_capacity(documents.len() + 1);
+        texts.push(query);
+        texts.extend_from_slice(documents);
+        let embeddings = self.embed_batch(&texts).await?;
+        let Some((query, documents)) = embeddings.split_first() else {
+            return Err("No embedding returned".to_string());
+        };
+        if documents.len() != texts.len() - 1 {
+            return Err(format!("Expected {} embeddings, got {}", texts.len(), embeddings.len()));
+        }
+
+        let mut results: Vec<RerankResult> = documents
+            .iter()
+            .enumerate()
+            .map(|(index, document)| RerankResult {
+                index,
+                relevance_score: cosine_similarity(&query.vector, &document.vector),
+            })
+            .collect();
+        results.sort_by(|a, b| b.relevance_score.total_cmp(&a.relevance_score));
+        Ok(results)
+    }
+
     /// Get the model name being used
     fn model_name(&self) -> &str;
 
@@ -79,18 +177,68 @@ pub trait EmbeddingProvider: Send + Sync {
     fn dimensions(&self) -> usize;
 }
 
+/// Serialize a vector for storage as a BLOB
+pub fn vector_to_blob(vector: &[f32]) -> Vec<u8> {
+    vector.iter().flat_map(|f| f.to_le_bytes()).collect()
+}
+
+/// Read back a vector stored by [`vector_to_blob`]
+pub fn blob_to_vector(blob: &[u8]) -> Vec<f32> {
+    blob.chunks_exact(4)
+        .map(|chunk| f32::from_le_bytes([chunk[0], chunk[1], chunk[2], chunk[3]]))
+        .collect()
+}
+
+/// Calculate cosine similarity between two vectors
+pub fn cosine_similarity(a: &[f32], b: &[f32]) -> f64 {
+    if a.len() != b.len() || a.is_empty() {
+        return 0.0;
+    }
+
+    let dot_product: f64 = a.iter().zip(b.iter())
+        .map(|(x, y)| (*x as f64) * (*y as f64))
+        .sum();
+
+    let norm_a: f64 = a.iter().map(|x| (*x as f64).powi(2)).sum::<f64>().sqrt();
+    let norm_b: f64 = b.iter().map(|x| (*x as f64).powi(2)).sum::<f64>().sqrt();
+
+    if norm_a == 0.0 || norm_b == 0.0 {
+        return 0.0;
+    }
+
+    dot_product / (norm_a * norm_b)
+}
+
+/// The `/embeddings` URL next to an OpenAI-compatible chat endpoint, if the
+/// agent settings point at one with an API key (not Claude, not x402)
+fn provider_embeddings_endpoint(settings: &AgentSettings) -> Option<String> {
+    if AiClient::infer_archetype(settings) == ArchetypeId::Claude
+        || crate::x402::is_x402_endpoint(&settings.endpoint)
+        || settings.secret_key.as_deref().unwrap_or("").is_empty()
+    {
+        return None;
+    }
+    settings
+        .endpoint
+        .trim_end_matches('/')
+        .strip_suffix("/chat/completions")
+        .map(|base| format!("{}/embeddings", base))
+}
+
 /// OpenAI embedding provider
 pub struct OpenAIEmbedding {
     api_key: String,
     model: String,
+    endpoint: String,
     client: reqwest::Client,
 }
 
 impl OpenAIEmbedding {
-    pub fn new(api_key: String, model: Option<String>) -> Self {
+    pub fn new(api_key: String, model: Option<String>, endpoint: Option<String>) -> Self {
         Self {
             api_key,
             model: model.unwrap_or_else(|| "text-embedding-3-small".to_string()),
+            endpoint: endpoint.unwrap_or_else(|| OPENAI_EMBEDDINGS_URL.to_string()),
             client: reqwest::Client::new(),
         }
     }
@@ -132,7 +280,7 @@ impl EmbeddingProvider for OpenAIEmbedding {
         };
 
         let response = self.client
-            .post("https://api.openai.com/v1/embeddings")
+            .post(&self.endpoint)
             .header("Authorization", format!("Bearer {}", self.api_key))
             .header("Content-Type", "application/json")
             .json(&request)
@@ -143,7 +291,7 @@ impl EmbeddingProvider for OpenAIEmbedding {
         if !response.status().is_success() {
             let status = response.status();
             let body = response.text().await.unwrap_or_default();
-            return Err(format!("OpenAI API error {}: {}", status, body));
+            return Err(format!("Embeddings API error {}: {}", status, body));
         }
 
         let result: OpenAIEmbeddingResponse = response
@@ -205,6 +353,7 @@ pub fn create_provider(config: &EmbeddingConfig) -> Box<dyn EmbeddingProvider> {
             Box::new(OpenAIEmbedding::new(
                 config.api_key.clone().unwrap(),
                 config.model.clone(),
+                config.endpoint.clone(),
             ))
         }
         // Future: "local" provider using fastembed
@@ -216,6 +365,61 @@ pub fn create_provider(config: &EmbeddingConfig) -> Box<dyn EmbeddingProvider> {
 mod tests {
     use super::*;
 
+    /// Embeds each text as its counts of 'a', 'b' and 'c'
+    struct LetterCounts;
+
+    #[async_trait]
+    impl EmbeddingProvider for LetterCounts {
+        async fn embed(&self, text: &str) -> Result<Embedding, String> {
+            let vector: Vec<f32> = ['a', 'b', 'c'].iter().map(|l| text.matches(*l).count() as f32).collect();
+            Ok(Embedding { dimensions: vector.len(), vector, model: "letters".to_string() })
+        }
+
+        async fn embed_batch(&self, texts: &[&str]) -> Result<Vec<Embedding>, String> {
+            let mut embeddings = Vec::new();
+            for text in texts {
+                embeddings.push(self.embed(text).await?);
+            }
+            Ok(embeddings)
+        }
+
+        fn model_name(&self) -> &str {
+            "letters"
+        }
+
+        fn dimensions(&self) -> usize {
+            3
+        }
+    }
+
+    #[test]
+    fn test_cosine_similarity() {
+        let a = vec![1.0, 0.0, 0.0];
+        let b = vec![1.0, 0.0, 0.0];
+        assert!((cosine_similarity(&a, &b) - 1.0).abs() < 0.0001);
+
+        let c = vec![0.0, 1.0, 0.0];
+        assert!((cosine_similarity(&a, &c) - 0.0).abs() < 0.0001);
+
+        let d = vec![-1.0, 0.0, 0.0];
+        assert!((cosine_similarity(&a, &d) - (-1.0)).abs() < 0.0001);
+    }
+
+    #[test]
+    fn test_vector_blob_round_trip() {
+        let vector = vec![0.5, -1.25, 3.0e-7];
+        assert_eq!(blob_to_vector(&vector_to_blob(&vector)), vector);
+    }
+
+    #[tokio::test]
+    async fn test_default_rerank_orders_by_similarity() {
+        let results = LetterCounts.rerank("bb", &["aaa", "abb", "bbb", "cab"]).await.unwrap();
+        let order: Vec<usize> = results.iter().map(|r| r.index).collect();
+        assert_eq!(order, vec![2, 1, 3, 0]);
+        assert!((results[0].relevance_score - 1.0).abs() < 0.0001);
+        assert!(results[3].relevance_score.abs() < 0.0001);
+    }
+
     #[test]
     fn test_config_default() {
         let config = EmbeddingConfig::default();
@@ -229,4 +433,44 @@ mod tests {
         assert_eq!(config.provider, "openai");
         assert!(config.is_enabled());
     }
+
+    #[test]
+    fn test_config_from_agent_settings() {
+        let memory = MemoryConfig {
+            enable_vector_search: true,
+            embedding_provider: "openai".to_string(),
+            ..MemoryConfig::default()
+        };
+        let mut settings = AgentSettings {
+            model_archetype: "openai".to_string(),
+            endpoint: "https://api.together.xyz/v1/chat/completions".to_string(),
+            secret_key: Some("sk-test".to_string()),
+            ..AgentSettings::default()
+        };
+
+        let config = EmbeddingConfig::from_memory_config(&memory, Some(&settings));
+        assert!(config.is_enabled());
+        assert_eq!(config.endpoint.as_deref(), Some("https://api.together.xyz/v1/embeddings"));
+        assert_eq!(config.api_key.as_deref(), Some("sk-test"));
+
+        // Anthropic has no embeddings API
+        settings.model_archetype = "claude".to_string();
+        settings.endpoint = "https://api.anthropic.com/v1/messages".to_string();
+        assert!(!EmbeddingConfig::from_memory_config(&memory, Some(&settings)).is_enabled());
+
+        // A dedicated key goes to OpenAI regardless of the AI provider
+        let memory = MemoryConfig {
+            embedding_api_key: Some("sk-embed".to_string()),
+            embedding_model: Some("text-embedding-3-large".to_string()),
+            ..memory
+        };
+        let config = EmbeddingConfig::from_memory_config(&memory, Some(&settings));
+        assert_eq!(config.api_key.as_deref(), Some("sk-embed"));
+        assert_eq!(config.endpoint, None);
+        assert_eq!(config.model.as_deref(), Some("text-embedding-3-large"));
+
+        // Nothing is embedded while vector search is off
+        let memory = MemoryConfig { enable_vector_search: false, ..memory };
+        assert!(!EmbeddingConfig::from_memory_config(&memory, Some(&settings)).is_enabled());
+    }
 }
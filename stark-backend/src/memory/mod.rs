@@ -9,6 +9,6 @@ pub mod embeddings;
 pub mod search;
 pub mod consolidation;
 
-pub use embeddings::{EmbeddingProvider, EmbeddingConfig};
+pub use embeddings::EmbeddingConfig;
 pub use search::{HybridSearcher, SearchResult};
 pub use consolidation::MemoryConsolidator;
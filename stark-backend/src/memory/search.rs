@@ -2,9 +2,10 @@
 //!
 //! Uses Reciprocal Rank Fusion (RRF) to merge results from both search methods.
 
+use crate::config::MemoryConfig;
 use crate::db::Database;
 use crate::models::{Memory, MemorySearchResult, MemoryType};
-use super::embeddings::{EmbeddingConfig, EmbeddingProvider, create_provider};
+use super::embeddings::{blob_to_vector, cosine_similarity, create_provider, vector_to_blob, EmbeddingConfig, EmbeddingProvider};
 use std::collections::HashMap;
 use std::sync::Arc;
 
@@ -40,6 +41,18 @@ impl HybridSearcher {
         }
     }
 
+    /// Searcher for the memory settings and the active AI provider (see
+    /// [`EmbeddingConfig::from_memory_config`]). Built per use, so provider changes apply immediately.
+    pub async fn configured(db: Arc<Database>, memory_config: &MemoryConfig) -> Self {
+        let settings = if memory_config.enable_vector_search {
+            db.get_active_agent_settings().await.ok().flatten()
+        } else {
+            None
+        };
+        let config = EmbeddingConfig::from_memory_config(memory_config, settings.as_ref());
+        Self::new(db, config)
+    }
+
     /// Check if vector search is enabled
     pub fn vector_search_enabled(&self) -> bool {
         self.config.is_enabled()
@@ -55,13 +68,14 @@ impl HybridSearcher {
     ) -> Result<Vec<SearchResult>, String> {
         // Always run BM25 search
         let bm25_results = self.db.search_memories(
-            query,
+            &escape_fts5_query(query),
             memory_type,
             identity_id,
             None, // category
             None, // min_importance
             limit * 2, // Get more results for merging
-        ).map_err(|e| format!("BM25 search failed: {}", e))?;
+            None, // any owner
+        ).await.map_err(|e| format!("BM25 search failed: {}", e))?;
 
         // If vector search is disabled, just return BM25 results
         if !self.vector_search_enabled() {
@@ -100,7 +114,7 @@ impl HybridSearcher {
         ).await?;
 
         // Merge results using RRF
-        let merged = self.reciprocal_rank_fusion(bm25_results, vector_results, limit);
+        let merged = self.reciprocal_rank_fusion(bm25_results, vector_results, limit).await;
 
         Ok(merged)
     }
@@ -116,7 +130,7 @@ impl HybridSearcher {
         // For now, we do a simple linear scan of embeddings
         // In production, this should use sqlite-vec or a dedicated vector DB
 
-        let conn = self.db.conn.lock().unwrap();
+        let conn = self.db.conn().await.map_err(|e| format!("Database error: {}", e))?;
 
         let type_filter = memory_type.map(|t| format!("AND m.memory_type = '{}'", t.as_str())).unwrap_or_default();
         let identity_filter = identity_id.map(|id| format!("AND m.identity_id = '{}'", id)).unwrap_or_default();
@@ -143,11 +157,7 @@ impl HybridSearcher {
         for row in rows.flatten() {
             let (memory_id, embedding_blob) = row;
 
-            // Deserialize embedding from blob (f32 array stored as bytes)
-            let stored_vector: Vec<f32> = embedding_blob
-                .chunks_exact(4)
-                .map(|chunk| f32::from_le_bytes([chunk[0], chunk[1], chunk[2], chunk[3]]))
-                .collect();
+            let stored_vector = blob_to_vector(&embedding_blob);
 
             // Calculate cosine similarity
             let similarity = cosine_similarity(query_vector, &stored_vector);
@@ -162,7 +172,7 @@ impl HybridSearcher {
     }
 
     /// Merge BM25 and vector results using Reciprocal Rank Fusion
-    fn reciprocal_rank_fusion(
+    async fn reciprocal_rank_fusion(
         &self,
         bm25_results: Vec<MemorySearchResult>,
         vector_results: Vec<(i64, f64)>,
@@ -188,21 +198,24 @@ impl HybridSearcher {
         }
 
         // Convert to SearchResult and sort by combined score
-        let mut results: Vec<SearchResult> = scores.into_iter()
-            .filter_map(|(id, (score, bm25_rank, vector_rank, memory))| {
-                // If we don't have the memory from BM25, fetch it
-                let memory = memory.or_else(|| {
-                    self.db.get_memory(id).ok().flatten()
-                })?;
-
-                Some(SearchResult {
-                    memory,
-                    score,
-                    bm25_rank,
-                    vector_rank,
-                })
-            })
-            .collect();
+        let mut results: Vec<SearchResult> = Vec::with_capacity(scores.len());
+        for (id, (score, bm25_rank, vector_rank, memory)) in scores {
+            // If we don't have the memory from BM25, fetch it
+            let memory = match memory {
+                Some(memory) => memory,
+                None => match self.db.get_memory(id).await.ok().flatten() {
+                    Some(memory) => memory,
+                    None => continue,
+                },
+            };
+
+            results.push(SearchResult {
+                memory,
+                score,
+                bm25_rank,
+                vector_rank,
+            });
+        }
 
         results.sort_by(|a, b| b.score.partial_cmp(&a.score).unwrap_or(std::cmp::Ordering::Equal));
         results.truncate(limit as usize);
@@ -237,6 +250,7 @@ impl HybridSearcher {
             valid_from: result.memory.valid_from,
             valid_until: result.memory.valid_until,
             temporal_type: result.memory.temporal_type.clone(),
+            user_id: result.memory.user_id,
         }
     }
 
@@ -249,12 +263,9 @@ impl HybridSearcher {
         let embedding = self.embedding_provider.embed(content).await?;
 
         // Store embedding in database
-        let conn = self.db.conn.lock().unwrap();
+        let conn = self.db.conn().await.map_err(|e| format!("Database error: {}", e))?;
 
-        // Serialize embedding to bytes
-        let embedding_bytes: Vec<u8> = embedding.vector.iter()
-            .flat_map(|f| f.to_le_bytes())
-            .collect();
+        let embedding_bytes = vector_to_blob(&embedding.vector);
 
         conn.execute(
             "INSERT OR REPLACE INTO memory_embeddings (memory_id, embedding, model, dimensions, created_at)
@@ -276,26 +287,25 @@ impl HybridSearcher {
             return Ok(0);
         }
 
-        let conn = self.db.conn.lock().unwrap();
+        // Scoped so the connection isn't held across the embedding request
+        let memories: Vec<(i64, String)> = {
+            let conn = self.db.conn().await.map_err(|e| format!("Database error: {}", e))?;
 
-        // Find memories without embeddings
-        let mut stmt = conn.prepare(
-            "SELECT m.id, m.content FROM memories m
-             LEFT JOIN memory_embeddings e ON m.id = e.memory_id
-             WHERE e.memory_id IS NULL AND m.superseded_by IS NULL
-             LIMIT ?"
-        ).map_err(|e| format!("Failed to find memories: {}", e))?;
+            // Find memories without embeddings
+            let mut stmt = conn.prepare(
+                "SELECT m.id, m.content FROM memories m
+                 LEFT JOIN memory_embeddings e ON m.id = e.memory_id
+                 WHERE e.memory_id IS NULL AND m.superseded_by IS NULL
+                 LIMIT ?"
+            ).map_err(|e| format!("Failed to find memories: {}", e))?;
 
-        let memories: Vec<(i64, String)> = stmt
-            .query_map([batch_size as i32], |row| {
-                Ok((row.get(0)?, row.get(1)?))
-            })
-            .map_err(|e| format!("Query failed: {}", e))?
-            .filter_map(|r| r.ok())
-            .collect();
-
-        drop(stmt);
-        drop(conn);
+            stmt.query_map([batch_size as i32], |row| {
+                    Ok((row.get(0)?, row.get(1)?))
+                })
+                .map_err(|e| format!("Query failed: {}", e))?
+                .filter_map(|r| r.ok())
+                .collect()
+        };
 
         if memories.is_empty() {
             return Ok(0);
@@ -306,11 +316,9 @@ impl HybridSearcher {
         let embeddings = self.embedding_provider.embed_batch(&texts).await?;
 
         // Store embeddings
-        let conn = self.db.conn.lock().unwrap();
+        let conn = self.db.conn().await.map_err(|e| format!("Database error: {}", e))?;
         for ((memory_id, _), embedding) in memories.iter().zip(embeddings.iter()) {
-            let embedding_bytes: Vec<u8> = embedding.vector.iter()
-                .flat_map(|f| f.to_le_bytes())
-                .collect();
+            let embedding_bytes = vector_to_blob(&embedding.vector);
 
             let _ = conn.execute(
                 "INSERT OR REPLACE INTO memory_embeddings (memory_id, embedding, model, dimensions, created_at)
@@ -328,40 +336,19 @@ impl HybridSearcher {
     }
 }
 
-/// Calculate cosine similarity between two vectors
-fn cosine_similarity(a: &[f32], b: &[f32]) -> f64 {
-    if a.len() != b.len() || a.is_empty() {
-        return 0.0;
-    }
-
-    let dot_product: f64 = a.iter().zip(b.iter())
-        .map(|(x, y)| (*x as f64) * (*y as f64))
-        .sum();
-
-    let norm_a: f64 = a.iter().map(|x| (*x as f64).powi(2)).sum::<f64>().sqrt();
-    let norm_b: f64 = b.iter().map(|x| (*x as f64).powi(2)).sum::<f64>().sqrt();
-
-    if norm_a == 0.0 || norm_b == 0.0 {
-        return 0.0;
-    }
-
-    dot_product / (norm_a * norm_b)
-}
-
-#[cfg(test)]
-mod tests {
-    use super::*;
-
-    #[test]
-    fn test_cosine_similarity() {
-        let a = vec![1.0, 0.0, 0.0];
-        let b = vec![1.0, 0.0, 0.0];
-        assert!((cosine_similarity(&a, &b) - 1.0).abs() < 0.0001);
-
-        let c = vec![0.0, 1.0, 0.0];
-        assert!((cosine_similarity(&a, &c) - 0.0).abs() < 0.0001);
-
-        let d = vec![-1.0, 0.0, 0.0];
-        assert!((cosine_similarity(&a, &d) - (-1.0)).abs() < 0.0001);
+/// Turn free text into an FTS5 query: drop operators and punctuation, and OR
+/// the words together so any of them can match
+pub fn escape_fts5_query(query: &str) -> String {
+    let cleaned: String = query
+        .chars()
+        .filter(|c| c.is_alphanumeric() || c.is_whitespace() || *c == '-' || *c == '_')
+        .collect();
+
+    // If the query has multiple words, use OR for more flexible matching
+    let words: Vec<&str> = cleaned.split_whitespace().collect();
+    if words.len() > 1 {
+        words.join(" OR ")
+    } else {
+        cleaned
     }
 }
@@ -0,0 +1,335 @@
+//! In-process metrics behind `/api/admin/stats`
+//!
+//! Provider calls, tool invocations and API responses are kept as timestamped
+//! samples for [`RETENTION`] (at most [`MAX_SAMPLES`] of each kind) and
+//! summarized over the [`Window`]s on request. Nothing is persisted, so the
+//! numbers start over when the process restarts.
+
+use serde::Serialize;
+use std::collections::{BTreeMap, VecDeque};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// How long samples are kept; the longest window
+pub const RETENTION: Duration = Duration::from_secs(24 * 60 * 60);
+
+/// Most samples kept of each kind; the oldest are dropped first
+pub const MAX_SAMPLES: usize = 50_000;
+
+/// A span of recent time the stats are summarized over
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Window {
+    FiveMinutes,
+    Hour,
+    Day,
+}
+
+impl Window {
+    pub fn all() -> [Window; 3] {
+        [Window::FiveMinutes, Window::Hour, Window::Day]
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Window::FiveMinutes => "5m",
+            Window::Hour => "1h",
+            Window::Day => "24h",
+        }
+    }
+
+    pub fn duration(&self) -> Duration {
+        match self {
+            Window::FiveMinutes => Duration::from_secs(5 * 60),
+            Window::Hour => Duration::from_secs(60 * 60),
+            Window::Day => RETENTION,
+        }
+    }
+}
+
+struct ProviderSample {
+    at: Instant,
+    /// `provider:model`
+    key: String,
+    latency_ms: u64,
+    ok: bool,
+}
+
+struct ToolSample {
+    at: Instant,
+    tool: String,
+    ok: bool,
+}
+
+struct ResponseSample {
+    at: Instant,
+    status: u16,
+}
+
+/// Latency percentiles in milliseconds
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Percentiles {
+    pub p50: u64,
+    pub p90: u64,
+    pub p99: u64,
+    pub max: u64,
+}
+
+/// Provider calls in a window, failed ones included in the latencies
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct CallStats {
+    pub calls: u64,
+    pub errors: u64,
+    pub error_rate: f64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub latency_ms: Option<Percentiles>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct ToolCount {
+    pub invocations: u64,
+    pub errors: u64,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct ToolStats {
+    pub invocations: u64,
+    pub errors: u64,
+    pub error_rate: f64,
+    pub by_tool: BTreeMap<String, ToolCount>,
+}
+
+/// API responses in a window; `error_rate` counts 5xx only
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct HttpStats {
+    pub requests: u64,
+    pub client_errors: u64,
+    pub server_errors: u64,
+    pub error_rate: f64,
+}
+
+/// Everything recorded within one window
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct WindowStats {
+    pub window: &'static str,
+    pub provider_calls: CallStats,
+    /// Keyed by `provider:model`
+    pub providers: BTreeMap<String, CallStats>,
+    pub tools: ToolStats,
+    pub http: HttpStats,
+}
+
+/// Recent samples of provider calls, tool invocations and API responses
+pub struct Metrics {
+    started: Instant,
+    provider_calls: Mutex<VecDeque<ProviderSample>>,
+    tool_calls: Mutex<VecDeque<ToolSample>>,
+    responses: Mutex<VecDeque<ResponseSample>>,
+}
+
+impl Metrics {
+    pub fn new() -> Self {
+        Metrics {
+            started: Instant::now(),
+            provider_calls: Mutex::new(VecDeque::new()),
+            tool_calls: Mutex::new(VecDeque::new()),
+            responses: Mutex::new(VecDeque::new()),
+        }
+    }
+
+    /// Get the global instance the server records into
+    pub fn global() -> &'static Metrics {
+        use std::sync::OnceLock;
+        static INSTANCE: OnceLock<Metrics> = OnceLock::new();
+        INSTANCE.get_or_init(Metrics::new)
+    }
+
+    pub fn uptime(&self) -> Duration {
+        self.started.elapsed()
+    }
+
+    /// Record one call to an AI provider; its latency includes the client's retries
+    pub fn record_provider_call(&self, provider: &str, model: &str, latency: Duration, ok: bool) {
+        self.record_provider_call_at(Instant::now(), provider, model, latency, ok);
+    }
+
+    /// Record one tool invocation
+    pub fn record_tool_call(&self, tool: &str, ok: bool) {
+        self.record_tool_call_at(Instant::now(), tool, ok);
+    }
+
+    /// Record the status of one API response
+    pub fn record_response(&self, status: u16) {
+        self.record_response_at(Instant::now(), status);
+    }
+
+    /// Summarize what was recorded within `window`
+    pub fn summary(&self, window: Window) -> WindowStats {
+        self.summary_at(Instant::now(), window)
+    }
+
+    fn record_provider_call_at(&self, at: Instant, provider: &str, model: &str, latency: Duration, ok: bool) {
+        let sample = ProviderSample {
+            at,
+            key: format!("{}:{}", provider, model),
+            latency_ms: latency.as_millis() as u64,
+            ok,
+        };
+        push(&self.provider_calls, sample, at, |s| s.at);
+    }
+
+    fn record_tool_call_at(&self, at: Instant, tool: &str, ok: bool) {
+        push(&self.tool_calls, ToolSample { at, tool: tool.to_string(), ok }, at, |s| s.at);
+    }
+
+    fn record_response_at(&self, at: Instant, status: u16) {
+        push(&self.responses, ResponseSample { at, status }, at, |s| s.at);
+    }
+
+    fn summary_at(&self, now: Instant, window: Window) -> WindowStats {
+        let since = now.checked_sub(window.duration());
+        let within = |at: Instant| since.is_none_or(|since| at >= since);
+
+        let mut latencies: Vec<u64> = Vec::new();
+        let mut errors = 0;
+        let mut by_provider: BTreeMap<String, (Vec<u64>, u64)> = BTreeMap::new();
+        for sample in lock(&self.provider_calls).iter().filter(|s| within(s.at)) {
+            latencies.push(sample.latency_ms);
+            let entry = by_provider.entry(sample.key.clone()).or_default();
+            entry.0.push(sample.latency_ms);
+            if !sample.ok {
+                errors += 1;
+                entry.1 += 1;
+            }
+        }
+
+        let mut tools = ToolStats::default();
+        for sample in lock(&self.tool_calls).iter().filter(|s| within(s.at)) {
+            let count = tools.by_tool.entry(sample.tool.clone()).or_default();
+            count.invocations += 1;
+            tools.invocations += 1;
+            if !sample.ok {
+                count.errors += 1;
+                tools.errors += 1;
+            }
+        }
+        tools.error_rate = rate(tools.errors, tools.invocations);
+
+        let mut http = HttpStats::default();
+        for sample in lock(&self.responses).iter().filter(|s| within(s.at)) {
+            http.requests += 1;
+            match sample.status {
+                400..=499 => http.client_errors += 1,
+                500.. => http.server_errors += 1,
+                _ => {}
+            }
+        }
+        http.error_rate = rate(http.server_errors, http.requests);
+
+        WindowStats {
+            window: window.as_str(),
+            provider_calls: call_stats(latencies, errors),
+            providers: by_provider
+                .into_iter()
+                .map(|(key, (latencies, errors))| (key, call_stats(latencies, errors)))
+                .collect(),
+            tools,
+            http,
+        }
+    }
+}
+
+impl Default for Metrics {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// A poisoned lock still holds usable samples
+fn lock<T>(queue: &Mutex<VecDeque<T>>) -> std::sync::MutexGuard<'_, VecDeque<T>> {
+    queue.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+/// Append a sample, dropping those past the retention period or the cap
+fn push<T>(queue: &Mutex<VecDeque<T>>, sample: T, now: Instant, at: fn(&T) -> Instant) {
+    let mut queue = lock(queue);
+    if let Some(cutoff) = now.checked_sub(RETENTION) {
+        while queue.front().is_some_and(|s| at(s) < cutoff) {
+            queue.pop_front();
+        }
+    }
+    if queue.len() >= MAX_SAMPLES {
+        queue.pop_front();
+    }
+    queue.push_back(sample);
+}
+
+fn rate(errors: u64, total: u64) -> f64 {
+    if total == 0 { 0.0 } else { errors as f64 / total as f64 }
+}
+
+fn call_stats(mut latencies: Vec<u64>, errors: u64) -> CallStats {
+    let calls = latencies.len() as u64;
+    latencies.sort_unstable();
+    // Nearest-rank percentile
+    let percentile = |p: f64| latencies[((p * calls as f64).ceil() as usize).clamp(1, latencies.len()) - 1];
+    CallStats {
+        calls,
+        errors,
+        error_rate: rate(errors, calls),
+        latency_ms: (!latencies.is_empty()).then(|| Percentiles {
+            p50: percentile(0.50),
+            p90: percentile(0.90),
+            p99: percentile(0.99),
+            max: latencies[latencies.len() - 1],
+        }),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_windows_percentiles_and_error_rates() {
+        let metrics = Metrics::new();
+        let now = Instant::now() + Duration::from_secs(2 * 60 * 60);
+        let hour_ago = now - Duration::from_secs(50 * 60);
+
+        for latency in 1..=100 {
+            let ok = latency % 10 != 0;
+            metrics.record_provider_call_at(now, "claude", "claude-sonnet-4", Duration::from_millis(latency), ok);
+        }
+        metrics.record_provider_call_at(hour_ago, "openai", "kimi-k2", Duration::from_millis(5_000), false);
+        metrics.record_tool_call_at(now, "read_file", true);
+        metrics.record_tool_call_at(now, "exec", false);
+        metrics.record_tool_call_at(hour_ago, "exec", true);
+        for status in [200, 201, 404, 500] {
+            metrics.record_response_at(now, status);
+        }
+
+        let recent = metrics.summary_at(now, Window::FiveMinutes);
+        assert_eq!(recent.window, "5m");
+        assert_eq!(recent.provider_calls.calls, 100);
+        assert_eq!(recent.provider_calls.errors, 10);
+        assert_eq!(
+            recent.provider_calls.latency_ms,
+            Some(Percentiles { p50: 50, p90: 90, p99: 99, max: 100 })
+        );
+        assert_eq!(recent.providers.keys().collect::<Vec<_>>(), vec!["claude:claude-sonnet-4"]);
+        assert_eq!(recent.tools.by_tool["exec"], ToolCount { invocations: 1, errors: 1 });
+        assert_eq!(recent.tools.error_rate, 0.5);
+        assert_eq!((recent.http.requests, recent.http.client_errors, recent.http.server_errors), (4, 1, 1));
+        assert_eq!(recent.http.error_rate, 0.25);
+
+        let hour = metrics.summary_at(now, Window::Hour);
+        assert_eq!(hour.providers["openai:kimi-k2"].error_rate, 1.0);
+        assert_eq!(hour.provider_calls.latency_ms.unwrap().max, 5_000);
+        assert_eq!(hour.tools.by_tool["exec"].invocations, 2);
+
+        // Samples past the retention period are dropped on the next record
+        let later = now + RETENTION + Duration::from_secs(1);
+        metrics.record_tool_call_at(later, "read_file", true);
+        assert_eq!(lock(&metrics.tool_calls).len(), 1);
+        assert_eq!(metrics.summary_at(later, Window::Day).tools.invocations, 1);
+    }
+}
@@ -0,0 +1,23 @@
+//! Records the status of every `/api` response for the admin stats
+
+use actix_web::body::MessageBody;
+use actix_web::dev::{ServiceRequest, ServiceResponse};
+use actix_web::middleware::Next;
+
+use crate::metrics::Metrics;
+
+pub async fn track_responses(
+    req: ServiceRequest,
+    next: Next<impl MessageBody>,
+) -> Result<ServiceResponse<impl MessageBody>, actix_web::Error> {
+    if !req.path().starts_with("/api/") {
+        return next.call(req).await;
+    }
+    let result = next.call(req).await;
+    let status = match &result {
+        Ok(response) => response.status(),
+        Err(e) => e.as_response_error().status_code(),
+    };
+    Metrics::global().record_response(status.as_u16());
+    result
+}
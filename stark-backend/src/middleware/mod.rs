@@ -1 +1,5 @@
+pub mod metrics;
+pub mod rate_limit;
+pub mod request_id;
+pub mod security;
 pub mod session_auth;
@@ -0,0 +1,319 @@
+//! Token-bucket rate limiting for the chat, agent and webhook endpoints
+//!
+//! Each request that starts work (any non-GET request under `/api/chat`,
+//! `/api/agent` or `/api/hooks`) takes one token from the bucket of its client IP and, if it
+//! carries a bearer token, one from that token's bucket. Buckets hold up to
+//! `burst` tokens and refill at `per_minute` tokens per minute. When either
+//! bucket is empty the request is refused with 429 and a `Retry-After` header.
+//! Polling endpoints (GET) are never limited.
+
+use actix_web::body::{EitherBody, MessageBody};
+use actix_web::dev::{ServiceRequest, ServiceResponse};
+use actix_web::http::header::RETRY_AFTER;
+use actix_web::http::Method;
+use actix_web::middleware::Next;
+use actix_web::{web, HttpRequest, HttpResponse};
+use std::collections::HashMap;
+use std::sync::{Mutex, RwLock};
+use std::time::{Duration, Instant};
+
+use crate::middleware::session_auth::extract_token;
+use crate::models::BotSettings;
+use crate::utils::truncate_chars;
+use crate::AppState;
+
+/// Path prefixes whose non-GET requests are rate limited
+const LIMITED_PREFIXES: &[&str] = &["/api/chat", "/api/agent", "/api/hooks", "/api/mcp"];
+
+/// A bucket that keeps running dry is recorded at most this often
+const REPORT_INTERVAL: Duration = Duration::from_secs(60);
+
+/// Past this many tracked clients, buckets that have refilled are dropped
+const MAX_BUCKETS: usize = 10_000;
+
+/// Sustained rate and burst size, from the bot settings
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RateLimits {
+    /// Requests per minute; 0 disables rate limiting
+    pub per_minute: u32,
+    pub burst: u32,
+}
+
+impl RateLimits {
+    pub fn from_settings(settings: &BotSettings) -> Self {
+        Self {
+            per_minute: settings.rate_limit_per_minute.max(0) as u32,
+            burst: settings.rate_limit_burst.max(1) as u32,
+        }
+    }
+
+    fn per_second(&self) -> f64 {
+        self.per_minute as f64 / 60.0
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+enum KeyType {
+    Token,
+    Ip,
+}
+
+impl KeyType {
+    fn as_str(&self) -> &'static str {
+        match self {
+            KeyType::Token => "token",
+            KeyType::Ip => "ip",
+        }
+    }
+}
+
+struct Bucket {
+    tokens: f64,
+    updated: Instant,
+    last_reported: Option<Instant>,
+}
+
+impl Bucket {
+    /// Tokens the bucket would hold at `now`
+    fn level(&self, now: Instant, limits: RateLimits) -> f64 {
+        let elapsed = now.saturating_duration_since(self.updated).as_secs_f64();
+        (self.tokens + elapsed * limits.per_second()).min(limits.burst as f64)
+    }
+}
+
+/// A refused request
+#[derive(Debug)]
+pub struct Rejection {
+    pub key_type: &'static str,
+    /// Client IP, or the first characters of the bearer token
+    pub key: String,
+    pub retry_after: Duration,
+    /// Whether this violation should be recorded (throttled per bucket)
+    pub report: bool,
+}
+
+pub struct RateLimiter {
+    limits: RwLock<RateLimits>,
+    /// Take the client IP from X-Forwarded-For / Forwarded instead of the socket
+    trust_proxy: bool,
+    buckets: Mutex<HashMap<(KeyType, String), Bucket>>,
+}
+
+impl RateLimiter {
+    pub fn new(limits: RateLimits, trust_proxy: bool) -> Self {
+        Self {
+            limits: RwLock::new(limits),
+            trust_proxy,
+            buckets: Mutex::new(HashMap::new()),
+        }
+    }
+
+    pub fn limits(&self) -> RateLimits {
+        *self.limits.read().unwrap()
+    }
+
+    /// Apply new limits; existing buckets keep their level, capped at the new burst
+    pub fn set_limits(&self, limits: RateLimits) {
+        *self.limits.write().unwrap() = limits;
+    }
+
+    /// Take one request from the IP's bucket and the token's, if both have room
+    pub fn check(&self, ip: &str, token: Option<&str>) -> Result<(), Rejection> {
+        self.check_at(Instant::now(), ip, token)
+    }
+
+    fn check_at(&self, now: Instant, ip: &str, token: Option<&str>) -> Result<(), Rejection> {
+        let limits = self.limits();
+        if limits.per_minute == 0 {
+            return Ok(());
+        }
+
+        let mut keys = vec![(KeyType::Ip, ip.to_string())];
+        if let Some(token) = token {
+            keys.push((KeyType::Token, token.to_string()));
+        }
+
+        let mut buckets = self.buckets.lock().unwrap();
+        if buckets.len() > MAX_BUCKETS {
+            buckets.retain(|_, bucket| bucket.level(now, limits) < limits.burst as f64);
+        }
+
+        // Refill every bucket first so a request refused by one doesn't drain the other
+        for key in &keys {
+            let bucket = buckets.entry(key.clone()).or_insert(Bucket {
+                tokens: limits.burst as f64,
+                updated: now,
+                last_reported: None,
+            });
+            bucket.tokens = bucket.level(now, limits);
+            bucket.updated = now;
+        }
+
+        for key in &keys {
+            let bucket = buckets.get_mut(key).expect("bucket inserted above");
+            if bucket.tokens < 1.0 {
+                let report = bucket
+                    .last_reported
+                    .is_none_or(|at| now.saturating_duration_since(at) >= REPORT_INTERVAL);
+                if report {
+                    bucket.last_reported = Some(now);
+                }
+                let wait_secs = (1.0 - bucket.tokens) / limits.per_second();
+                return Err(Rejection {
+                    key_type: key.0.as_str(),
+                    key: match key.0 {
+                        KeyType::Token => truncate_chars(&key.1, 8).to_string(),
+                        KeyType::Ip => key.1.clone(),
+                    },
+                    retry_after: Duration::from_secs_f64(wait_secs.ceil().max(1.0)),
+                    report,
+                });
+            }
+        }
+
+        for key in &keys {
+            if let Some(bucket) = buckets.get_mut(key) {
+                bucket.tokens -= 1.0;
+            }
+        }
+        Ok(())
+    }
+
+    /// The client's IP, from the proxy headers when `trust_proxy` is on
+    pub fn client_ip(&self, req: &HttpRequest) -> String {
+        if self.trust_proxy && let Some(ip) = req.connection_info().realip_remote_addr() {
+            return ip.to_string();
+        }
+        req.peer_addr()
+            .map(|addr| addr.ip().to_string())
+            .unwrap_or_else(|| "unknown".to_string())
+    }
+}
+
+fn is_limited(method: &Method, path: &str) -> bool {
+    *method != Method::GET
+        && LIMITED_PREFIXES.iter().any(|prefix| {
+            path == *prefix || path.strip_prefix(prefix).is_some_and(|rest| rest.starts_with('/'))
+        })
+}
+
+/// Middleware (for `actix_web::middleware::from_fn`) applying `AppState::rate_limiter`
+pub async fn rate_limit(
+    req: ServiceRequest,
+    next: Next<impl MessageBody>,
+) -> Result<ServiceResponse<EitherBody<impl MessageBody>>, actix_web::Error> {
+    if !is_limited(req.method(), req.path()) {
+        return next.call(req).await.map(ServiceResponse::map_into_left_body);
+    }
+    let Some(state) = req.app_data::<web::Data<AppState>>().cloned() else {
+        return next.call(req).await.map(ServiceResponse::map_into_left_body);
+    };
+
+    let ip = state.rate_limiter.client_ip(req.request());
+    let token = extract_token(req.request());
+    let rejection = match state.rate_limiter.check(&ip, token.as_deref()) {
+        Ok(()) => return next.call(req).await.map(ServiceResponse::map_into_left_body),
+        Err(rejection) => rejection,
+    };
+
+    let retry_after = rejection.retry_after.as_secs();
+    log::warn!(
+        "[RATE_LIMIT] {} {} refused for {} {} (retry in {}s)",
+        req.method(),
+        req.path(),
+        rejection.key_type,
+        rejection.key,
+        retry_after
+    );
+    if rejection.report {
+        let (method, path) = (req.method().to_string(), req.path().to_string());
+        tokio::spawn(async move {
+            if let Err(e) = state
+                .db
+                .record_rate_limit_violation(rejection.key_type, &rejection.key, &method, &path)
+                .await
+            {
+                log::error!("Failed to record rate limit violation: {}", e);
+            }
+        });
+    }
+
+    let response = HttpResponse::TooManyRequests()
+        .insert_header((RETRY_AFTER, retry_after.to_string()))
+        .json(serde_json::json!({
+            "error": format!("Rate limit exceeded, retry in {} seconds", retry_after)
+        }));
+    Ok(req.into_response(response).map_into_right_body())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn limiter(per_minute: u32, burst: u32) -> RateLimiter {
+        RateLimiter::new(RateLimits { per_minute, burst }, false)
+    }
+
+    #[test]
+    fn test_burst_then_refill() {
+        let limiter = limiter(60, 3);
+        let start = Instant::now();
+        for _ in 0..3 {
+            assert!(limiter.check_at(start, "1.2.3.4", None).is_ok());
+        }
+        let rejection = limiter.check_at(start, "1.2.3.4", None).unwrap_err();
+        assert_eq!(rejection.key_type, "ip");
+        assert_eq!(rejection.retry_after, Duration::from_secs(1));
+        assert!(rejection.report);
+
+        // Repeat violations within the interval aren't reported again
+        assert!(!limiter.check_at(start, "1.2.3.4", None).unwrap_err().report);
+
+        // Another client has its own bucket; one token refills per second
+        assert!(limiter.check_at(start, "5.6.7.8", None).is_ok());
+        assert!(limiter.check_at(start + Duration::from_secs(1), "1.2.3.4", None).is_ok());
+    }
+
+    #[test]
+    fn test_token_and_ip_buckets() {
+        let limiter = limiter(60, 2);
+        let now = Instant::now();
+        assert!(limiter.check_at(now, "1.2.3.4", Some("token-a")).is_ok());
+        assert!(limiter.check_at(now, "5.6.7.8", Some("token-a")).is_ok());
+
+        // The token is spent even from a fresh IP
+        let rejection = limiter.check_at(now, "9.9.9.9", Some("token-a")).unwrap_err();
+        assert_eq!(rejection.key_type, "token");
+        assert_eq!(rejection.key, "token-a");
+
+        // The refused request didn't take from the new IP's bucket
+        assert!(limiter.check_at(now, "9.9.9.9", None).is_ok());
+        assert!(limiter.check_at(now, "9.9.9.9", None).is_ok());
+        assert!(limiter.check_at(now, "9.9.9.9", None).is_err());
+    }
+
+    #[test]
+    fn test_disabled_and_updated_limits() {
+        let limiter = limiter(0, 1);
+        let now = Instant::now();
+        for _ in 0..100 {
+            assert!(limiter.check_at(now, "1.2.3.4", None).is_ok());
+        }
+
+        limiter.set_limits(RateLimits { per_minute: 60, burst: 1 });
+        assert!(limiter.check_at(now, "1.2.3.4", None).is_ok());
+        assert!(limiter.check_at(now, "1.2.3.4", None).is_err());
+    }
+
+    #[test]
+    fn test_limited_paths() {
+        assert!(is_limited(&Method::POST, "/api/chat"));
+        assert!(is_limited(&Method::POST, "/api/agent/run"));
+        assert!(is_limited(&Method::DELETE, "/api/chat/tasks/3"));
+        assert!(is_limited(&Method::POST, "/api/hooks/4f0c9a"));
+        assert!(is_limited(&Method::POST, "/api/mcp/messages"));
+        assert!(!is_limited(&Method::GET, "/api/chat/execution-status"));
+        assert!(!is_limited(&Method::PUT, "/api/agent-settings"));
+        assert!(!is_limited(&Method::POST, "/api/auth/validate_auth"));
+    }
+}
@@ -0,0 +1,235 @@
+//! CORS policy and security headers
+//!
+//! The API authenticates with `Authorization: Bearer <token>` only; it never
+//! sets or reads cookies. Cross-origin frontends therefore make plain
+//! (non-credentialed) requests carrying the header, and CORS allows exactly
+//! that: the configured origins and methods, the headers the API reads, and no
+//! credentials.
+//!
+//! Every response gets `nosniff` and `no-referrer`, plus HSTS when a max-age
+//! is configured. HTML (the dashboard) also gets the Content-Security-Policy
+//! and is refused framing; agent app previews under `/preview/` are refused
+//! framing and sandboxed by the preview proxy instead (see
+//! `controllers::preview`). A `401` names the scheme in `WWW-Authenticate`.
+
+use actix_cors::Cors;
+use actix_web::body::MessageBody;
+use actix_web::dev::{ServiceRequest, ServiceResponse};
+use actix_web::http::header::{self, HeaderValue};
+use actix_web::http::StatusCode;
+use actix_web::middleware::Next;
+use once_cell::sync::Lazy;
+
+use crate::config;
+use crate::middleware::request_id::REQUEST_ID_HEADER;
+
+/// Request headers a browser may send cross-origin
+const ALLOWED_HEADERS: [header::HeaderName; 4] =
+    [header::AUTHORIZATION, header::CONTENT_TYPE, header::ACCEPT, REQUEST_ID_HEADER];
+
+/// Response headers a cross-origin frontend may read
+const EXPOSED_HEADERS: [header::HeaderName; 3] =
+    [REQUEST_ID_HEADER, header::RETRY_AFTER, header::CONTENT_DISPOSITION];
+
+/// How long browsers may cache a preflight response
+const PREFLIGHT_MAX_AGE_SECS: usize = 3600;
+
+/// Header values, built once from the config
+struct SecurityHeaders {
+    content_security_policy: Option<HeaderValue>,
+    strict_transport_security: Option<HeaderValue>,
+}
+
+static SECURITY_HEADERS: Lazy<SecurityHeaders> = Lazy::new(|| SecurityHeaders {
+    content_security_policy: config::content_security_policy().and_then(|p| HeaderValue::from_str(&p).ok()),
+    strict_transport_security: config::hsts_max_age_secs()
+        .and_then(|secs| HeaderValue::from_str(&format!("max-age={}; includeSubDomains", secs)).ok()),
+});
+
+/// CORS for `origins` (any origin when empty) and `methods`
+pub fn cors(origins: &[String], methods: &[String]) -> Cors {
+    let cors = origins.iter().fold(Cors::default(), |cors, origin| cors.allowed_origin(origin));
+    let cors = if origins.is_empty() { cors.allow_any_origin() } else { cors };
+    cors.allowed_methods(methods.iter().map(String::as_str))
+        .allowed_headers(ALLOWED_HEADERS)
+        .expose_headers(EXPOSED_HEADERS)
+        .max_age(PREFLIGHT_MAX_AGE_SECS)
+}
+
+pub async fn security_headers(
+    req: ServiceRequest,
+    next: Next<impl MessageBody>,
+) -> Result<ServiceResponse<impl MessageBody>, actix_web::Error> {
+    // Previews are the agent's app, not the dashboard; the dashboard's CSP would block its
+    // scripts, and the proxy sets its own
+    let is_preview = req.path().starts_with("/preview/");
+    let mut response = next.call(req).await?;
+    let policy = &*SECURITY_HEADERS;
+    let is_html = response
+        .headers()
+        .get(header::CONTENT_TYPE)
+        .and_then(|v| v.to_str().ok())
+        .is_some_and(|v| v.starts_with("text/html"));
+    let unauthorized = response.status() == StatusCode::UNAUTHORIZED;
+
+    let headers = response.headers_mut();
+    headers.insert(header::X_CONTENT_TYPE_OPTIONS, HeaderValue::from_static("nosniff"));
+    headers.insert(header::REFERRER_POLICY, HeaderValue::from_static("no-referrer"));
+    if let Some(hsts) = &policy.strict_transport_security {
+        headers.insert(header::STRICT_TRANSPORT_SECURITY, hsts.clone());
+    }
+    if is_html {
+        headers.insert(header::X_FRAME_OPTIONS, HeaderValue::from_static("DENY"));
+        if let Some(csp) = policy.content_security_policy.as_ref().filter(|_| !is_preview) {
+            headers.insert(header::CONTENT_SECURITY_POLICY, csp.clone());
+        }
+    }
+    if unauthorized && !headers.contains_key(header::WWW_AUTHENTICATE) {
+        headers.insert(header::WWW_AUTHENTICATE, HeaderValue::from_static("Bearer realm=\"starkbot\""));
+    }
+    Ok(response)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use actix_web::dev::ServiceFactory;
+    use actix_web::http::Method;
+    use actix_web::middleware::from_fn;
+    use actix_web::{test, web, App, HttpResponse};
+
+    const ORIGIN: &str = "https://app.example.com";
+
+    fn routes(cfg: &mut web::ServiceConfig) {
+        let page = || async { HttpResponse::Ok().content_type("text/html").body("<html>") };
+        cfg.route("/", web::get().to(page))
+            .route("/preview/1/", web::get().to(page))
+            .route("/api/x", web::get().to(|| async { HttpResponse::Ok().json("ok") }))
+            .route("/api/denied", web::get().to(|| async { HttpResponse::Unauthorized().finish() }))
+            .route(
+                "/api/basic",
+                web::get().to(|| async {
+                    HttpResponse::Unauthorized().insert_header((header::WWW_AUTHENTICATE, "Basic")).finish()
+                }),
+            );
+    }
+
+    fn app(
+        origins: &[&str],
+    ) -> App<
+        impl ServiceFactory<
+            ServiceRequest,
+            Config = (),
+            Response = ServiceResponse<impl MessageBody>,
+            Error = actix_web::Error,
+            InitError = (),
+        >,
+    > {
+        let origins: Vec<String> = origins.iter().map(|o| o.to_string()).collect();
+        App::new()
+            .wrap(from_fn(security_headers))
+            .wrap(cors(&origins, &["GET".to_string(), "POST".to_string()]))
+            .configure(routes)
+    }
+
+    fn preflight(origin: &str, method: &str) -> test::TestRequest {
+        test::TestRequest::default()
+            .method(Method::OPTIONS)
+            .uri("/api/x")
+            .insert_header((header::ORIGIN, origin))
+            .insert_header((header::ACCESS_CONTROL_REQUEST_METHOD, method))
+    }
+
+    #[actix_web::test]
+    async fn test_security_headers() {
+        let app = test::init_service(app(&[ORIGIN])).await;
+
+        let res = test::call_service(&app, test::TestRequest::get().uri("/").to_request()).await;
+        assert_eq!(res.headers().get(header::X_CONTENT_TYPE_OPTIONS).unwrap(), "nosniff");
+        assert_eq!(res.headers().get(header::REFERRER_POLICY).unwrap(), "no-referrer");
+        assert!(res.headers().contains_key(header::CONTENT_SECURITY_POLICY));
+        assert_eq!(res.headers().get(header::X_FRAME_OPTIONS).unwrap(), "DENY");
+
+        // JSON gets the baseline headers but no page policy
+        let res = test::call_service(&app, test::TestRequest::get().uri("/api/x").to_request()).await;
+        assert_eq!(res.headers().get(header::X_CONTENT_TYPE_OPTIONS).unwrap(), "nosniff");
+        assert_eq!(res.headers().get(header::REFERRER_POLICY).unwrap(), "no-referrer");
+        assert!(!res.headers().contains_key(header::CONTENT_SECURITY_POLICY));
+        assert!(!res.headers().contains_key(header::X_FRAME_OPTIONS));
+
+        // Previews keep their own CSP but still can't be framed
+        let res = test::call_service(&app, test::TestRequest::get().uri("/preview/1/").to_request()).await;
+        assert!(!res.headers().contains_key(header::CONTENT_SECURITY_POLICY));
+        assert_eq!(res.headers().get(header::X_FRAME_OPTIONS).unwrap(), "DENY");
+
+        let res = test::call_service(&app, test::TestRequest::get().uri("/api/denied").to_request()).await;
+        assert_eq!(res.status(), StatusCode::UNAUTHORIZED);
+        assert!(res.headers().get(header::WWW_AUTHENTICATE).unwrap().to_str().unwrap().starts_with("Bearer"));
+        let res = test::call_service(&app, test::TestRequest::get().uri("/api/basic").to_request()).await;
+        assert_eq!(res.headers().get(header::WWW_AUTHENTICATE).unwrap(), "Basic");
+    }
+
+    #[actix_web::test]
+    async fn test_cors_allows_configured_origin() {
+        let app = test::init_service(app(&[ORIGIN])).await;
+
+        let req = preflight(ORIGIN, "POST")
+            .insert_header((header::ACCESS_CONTROL_REQUEST_HEADERS, "authorization, content-type"))
+            .to_request();
+        let res = test::call_service(&app, req).await;
+        assert!(res.status().is_success());
+        assert_eq!(res.headers().get(header::ACCESS_CONTROL_ALLOW_ORIGIN).unwrap(), ORIGIN);
+        assert_eq!(res.headers().get(header::ACCESS_CONTROL_MAX_AGE).unwrap(), "3600");
+        assert!(!res.headers().contains_key(header::ACCESS_CONTROL_ALLOW_CREDENTIALS));
+        let methods = res.headers().get(header::ACCESS_CONTROL_ALLOW_METHODS).unwrap().to_str().unwrap();
+        assert!(methods.contains("POST") && !methods.contains("DELETE"));
+
+        let req = test::TestRequest::get().uri("/api/x").insert_header((header::ORIGIN, ORIGIN)).to_request();
+        let res = test::call_service(&app, req).await;
+        assert!(res.status().is_success());
+        assert_eq!(res.headers().get(header::ACCESS_CONTROL_ALLOW_ORIGIN).unwrap(), ORIGIN);
+        let exposed = res.headers().get(header::ACCESS_CONTROL_EXPOSE_HEADERS).unwrap().to_str().unwrap();
+        assert!(exposed.contains(REQUEST_ID_HEADER.as_str()));
+        assert_eq!(res.headers().get(header::X_CONTENT_TYPE_OPTIONS).unwrap(), "nosniff");
+    }
+
+    #[actix_web::test]
+    async fn test_cors_rejects_other_origins_methods_and_headers() {
+        let app = test::init_service(app(&[ORIGIN])).await;
+
+        let res = test::call_service(&app, preflight("https://evil.example.com", "GET").to_request()).await;
+        assert!(res.status().is_client_error());
+        assert!(!res.headers().contains_key(header::ACCESS_CONTROL_ALLOW_ORIGIN));
+
+        // A simple request still runs, but without the allow header the browser hides the response
+        let req = test::TestRequest::get()
+            .uri("/api/x")
+            .insert_header((header::ORIGIN, "https://evil.example.com"))
+            .to_request();
+        let res = test::call_service(&app, req).await;
+        assert!(!res.headers().contains_key(header::ACCESS_CONTROL_ALLOW_ORIGIN));
+
+        let res = test::call_service(&app, preflight(ORIGIN, "DELETE").to_request()).await;
+        assert!(res.status().is_client_error());
+
+        let req = preflight(ORIGIN, "POST")
+            .insert_header((header::ACCESS_CONTROL_REQUEST_HEADERS, "x-api-key"))
+            .to_request();
+        let res = test::call_service(&app, req).await;
+        assert!(res.status().is_client_error());
+    }
+
+    #[actix_web::test]
+    async fn test_cors_any_origin_when_unconfigured() {
+        let app = test::init_service(app(&[])).await;
+
+        let res = test::call_service(&app, preflight("https://anywhere.example.org", "GET").to_request()).await;
+        assert!(res.status().is_success());
+        assert!(res.headers().contains_key(header::ACCESS_CONTROL_ALLOW_ORIGIN));
+        assert!(!res.headers().contains_key(header::ACCESS_CONTROL_ALLOW_CREDENTIALS));
+
+        // The method list still applies
+        let res = test::call_service(&app, preflight("https://anywhere.example.org", "PUT").to_request()).await;
+        assert!(res.status().is_client_error());
+    }
+}
@@ -22,7 +22,7 @@ pub async fn validate_request(db: &Arc<Database>, req: &HttpRequest) -> Result<(
         }))
     })?;
 
-    match db.validate_session(&token) {
+    match db.validate_session(&token).await {
         Ok(Some(_)) => Ok(()),
         Ok(None) => Err(HttpResponse::Unauthorized().json(serde_json::json!({
             "error": "Invalid or expired session"
@@ -0,0 +1,76 @@
+use crate::ai::ResponseFormat;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+/// Status of a background agent job
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum AgentJobStatus {
+    Queued,
+    Running,
+    Completed,
+    Failed,
+    Cancelled,
+}
+
+impl AgentJobStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            AgentJobStatus::Queued => "queued",
+            AgentJobStatus::Running => "running",
+            AgentJobStatus::Completed => "completed",
+            AgentJobStatus::Failed => "failed",
+            AgentJobStatus::Cancelled => "cancelled",
+        }
+    }
+
+    pub fn from_str(s: &str) -> Option<Self> {
+        match s.to_lowercase().as_str() {
+            "queued" => Some(AgentJobStatus::Queued),
+            "running" => Some(AgentJobStatus::Running),
+            "completed" => Some(AgentJobStatus::Completed),
+            "failed" => Some(AgentJobStatus::Failed),
+            "cancelled" => Some(AgentJobStatus::Cancelled),
+            _ => None,
+        }
+    }
+
+    pub fn is_finished(&self) -> bool {
+        matches!(self, AgentJobStatus::Completed | AgentJobStatus::Failed | AgentJobStatus::Cancelled)
+    }
+}
+
+/// A CodeEngineer task queued for background execution
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AgentJob {
+    pub job_id: String,
+    pub task: String,
+    pub workspace: String,
+    pub max_iterations: i64,
+    /// Tools the job may use; empty means the default CodeEngineer tools
+    pub tools: Vec<String>,
+    /// Scheduled task that queued this job, if any
+    pub schedule_id: Option<i64>,
+    /// Whether the job starts by planning its steps (plan/act mode)
+    pub planning: bool,
+    /// JSON the job's answer must be; its `response` is then that JSON
+    pub response_format: Option<ResponseFormat>,
+    /// User who queued the job; None for schedules, webhooks and integrations,
+    /// whose jobs only admins see
+    #[serde(default)]
+    pub user_id: Option<i64>,
+    pub status: AgentJobStatus,
+    /// Model round-trips completed so far
+    pub iterations: i64,
+    /// Tool calls made so far (list of agent tool call records)
+    pub transcript: Value,
+    /// Provider calls that failed and were retried or failed over (list of attempt records)
+    pub provider_attempts: Value,
+    /// Steps planned in plan/act mode with their status (list of planner tasks)
+    pub plan: Value,
+    pub response: Option<String>,
+    pub error: Option<String>,
+    pub created_at: String,
+    pub started_at: Option<String>,
+    pub completed_at: Option<String>,
+}
@@ -1,6 +1,8 @@
-use chrono::{DateTime, Utc};
+use chrono::{DateTime, Duration, Utc};
 use serde::{Deserialize, Serialize};
 
+use super::SessionPolicy;
+
 /// Agent settings stored in database (x402 endpoint configuration)
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct AgentSettings {
@@ -10,10 +12,151 @@ pub struct AgentSettings {
     pub max_tokens: i32,
     pub enabled: bool,
     pub secret_key: Option<String>,
+    /// Per-request token ceiling (overrides the global chat budget)
+    pub budget_max_tokens: Option<i64>,
+    /// Per-request cost ceiling in USD (overrides the global chat budget)
+    pub budget_max_usd: Option<f64>,
+    /// Hard token limit across all requests of one chat session
+    pub session_budget_max_tokens: Option<i64>,
+    /// Hard cost limit in USD across all requests of one chat session
+    pub session_budget_max_usd: Option<f64>,
+    /// Login session lifetime in hours (defaults to 24)
+    pub session_ttl_hours: Option<i64>,
+    /// Extend a login session's expiry every time it is used
+    pub session_sliding: bool,
+    /// Models a chat request may pick instead of the archetype's default
+    pub allowed_models: Vec<String>,
+    /// Largest USD amount a single value-moving tool call may spend
+    pub spend_max_tx_usd: Option<f64>,
+    /// USD the agent's value-moving tools may spend per UTC day
+    pub spend_max_daily_usd: Option<f64>,
+    /// Spends above this USD amount wait for operator approval
+    pub spend_approval_usd: Option<f64>,
+    /// Token budget for an agent run's working context; older tool output is
+    /// compacted to stay under it (defaults to the context module's window)
+    pub max_context_tokens: Option<i64>,
+    /// Endpoint agent runs fail over to when this one keeps failing
+    /// (defaults to `endpoint`, for failing over to another model only)
+    pub fallback_endpoint: Option<String>,
+    /// Archetype of the fallback endpoint (defaults to `model_archetype`)
+    pub fallback_model_archetype: Option<String>,
+    /// Model to fail over to (defaults to the fallback archetype's model)
+    pub fallback_model: Option<String>,
+    /// API key for the fallback endpoint (defaults to `secret_key`)
+    pub fallback_secret_key: Option<String>,
+    /// Persona for conversations that haven't picked one (see `models::Persona`)
+    pub default_persona: Option<String>,
     pub created_at: DateTime<Utc>,
     pub updated_at: DateTime<Utc>,
 }
 
+impl AgentSettings {
+    /// How long login sessions last under these settings
+    pub fn session_policy(&self) -> SessionPolicy {
+        SessionPolicy {
+            ttl: self
+                .session_ttl_hours
+                .map(Duration::hours)
+                .unwrap_or(SessionPolicy::default().ttl),
+            sliding: self.session_sliding,
+        }
+    }
+
+    /// Settings for the fallback provider, if one is configured. Unset fallback
+    /// fields take the primary provider's values.
+    pub fn fallback(&self) -> Option<AgentSettings> {
+        if self.fallback_endpoint.is_none() && self.fallback_model.is_none() {
+            return None;
+        }
+        Some(AgentSettings {
+            endpoint: self.fallback_endpoint.clone().unwrap_or_else(|| self.endpoint.clone()),
+            model_archetype: self
+                .fallback_model_archetype
+                .clone()
+                .unwrap_or_else(|| self.model_archetype.clone()),
+            secret_key: self.fallback_secret_key.clone().or_else(|| self.secret_key.clone()),
+            ..self.clone()
+        })
+    }
+
+    /// Name of the API key (in `external_api_keys`) that authenticates with
+    /// this provider, for providers users can bring their own key for
+    pub fn provider_key_name(&self) -> Option<&'static str> {
+        if self.model_archetype == "claude" {
+            Some("ANTHROPIC_API_KEY")
+        } else if self.endpoint.contains("api.openai.com") {
+            Some("OPENAI_API_KEY")
+        } else {
+            None
+        }
+    }
+
+    /// Authenticate with `key` instead of `secret_key`. A fallback provider
+    /// without a key of its own keeps using the original one.
+    pub fn use_secret_key(&mut self, key: String) {
+        if self.fallback_secret_key.is_none() {
+            self.fallback_secret_key = self.secret_key.clone();
+        }
+        self.secret_key = Some(key);
+    }
+
+    /// Check a chat request's model parameters against these settings
+    pub fn check_overrides(&self, overrides: &ModelOverrides) -> Result<(), String> {
+        if let Some(model) = &overrides.model
+            && !self.allowed_models.iter().any(|m| m == model)
+        {
+            return Err(if self.allowed_models.is_empty() {
+                "Model overrides are not enabled; add models to allowed_models in agent settings".to_string()
+            } else {
+                format!(
+                    "Model '{}' is not allowed. Allowed models: {}",
+                    model,
+                    self.allowed_models.join(", ")
+                )
+            });
+        }
+        if let Some(max_tokens) = overrides.max_tokens
+            && (max_tokens == 0 || i64::from(max_tokens) > i64::from(self.max_tokens))
+        {
+            return Err(format!("max_tokens must be between 1 and {}", self.max_tokens));
+        }
+        if let Some(temperature) = overrides.temperature {
+            // Anthropic caps temperature at 1.0, OpenAI-compatible APIs at 2.0
+            let max = if self.model_archetype == "claude" { 1.0 } else { 2.0 };
+            if !(0.0..=max).contains(&temperature) {
+                return Err(format!("temperature must be between 0 and {}", max));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Parse the comma-separated form `allowed_models` is stored in
+pub fn parse_model_list(s: &str) -> Vec<String> {
+    s.split(',')
+        .map(str::trim)
+        .filter(|m| !m.is_empty())
+        .map(String::from)
+        .collect()
+}
+
+/// Model parameters a single chat request may set, within the limits of the
+/// active agent settings
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ModelOverrides {
+    /// One of the agent's `allowed_models`
+    pub model: Option<String>,
+    /// At most the agent's `max_tokens`
+    pub max_tokens: Option<u32>,
+    pub temperature: Option<f32>,
+}
+
+impl ModelOverrides {
+    pub fn is_empty(&self) -> bool {
+        self.model.is_none() && self.max_tokens.is_none() && self.temperature.is_none()
+    }
+}
+
 impl Default for AgentSettings {
     /// Returns default kimi agent settings (used when no agent is configured)
     fn default() -> Self {
@@ -25,6 +168,22 @@ impl Default for AgentSettings {
             max_tokens: 40000,
             enabled: true,
             secret_key: None,
+            budget_max_tokens: None,
+            budget_max_usd: None,
+            session_budget_max_tokens: None,
+            session_budget_max_usd: None,
+            session_ttl_hours: None,
+            session_sliding: true,
+            allowed_models: Vec::new(),
+            spend_max_tx_usd: None,
+            spend_max_daily_usd: None,
+            spend_approval_usd: None,
+            max_context_tokens: None,
+            fallback_endpoint: None,
+            fallback_model_archetype: None,
+            fallback_model: None,
+            fallback_secret_key: None,
+            default_persona: None,
             created_at: now,
             updated_at: now,
         }
@@ -40,6 +199,35 @@ pub struct AgentSettingsResponse {
     pub max_tokens: i32,
     pub enabled: bool,
     pub has_secret_key: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub budget_max_tokens: Option<i64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub budget_max_usd: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub session_budget_max_tokens: Option<i64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub session_budget_max_usd: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub session_ttl_hours: Option<i64>,
+    pub session_sliding: bool,
+    pub allowed_models: Vec<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub spend_max_tx_usd: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub spend_max_daily_usd: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub spend_approval_usd: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_context_tokens: Option<i64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub fallback_endpoint: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub fallback_model_archetype: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub fallback_model: Option<String>,
+    pub has_fallback_secret_key: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub default_persona: Option<String>,
     pub created_at: DateTime<Utc>,
     pub updated_at: DateTime<Utc>,
 }
@@ -53,6 +241,22 @@ impl From<AgentSettings> for AgentSettingsResponse {
             max_tokens: settings.max_tokens,
             enabled: settings.enabled,
             has_secret_key: settings.secret_key.is_some(),
+            budget_max_tokens: settings.budget_max_tokens,
+            budget_max_usd: settings.budget_max_usd,
+            session_budget_max_tokens: settings.session_budget_max_tokens,
+            session_budget_max_usd: settings.session_budget_max_usd,
+            session_ttl_hours: settings.session_ttl_hours,
+            session_sliding: settings.session_sliding,
+            allowed_models: settings.allowed_models,
+            spend_max_tx_usd: settings.spend_max_tx_usd,
+            spend_max_daily_usd: settings.spend_max_daily_usd,
+            spend_approval_usd: settings.spend_approval_usd,
+            max_context_tokens: settings.max_context_tokens,
+            fallback_endpoint: settings.fallback_endpoint,
+            fallback_model_archetype: settings.fallback_model_archetype,
+            fallback_model: settings.fallback_model,
+            has_fallback_secret_key: settings.fallback_secret_key.is_some(),
+            default_persona: settings.default_persona,
             created_at: settings.created_at,
             updated_at: settings.updated_at,
         }
@@ -65,15 +269,139 @@ pub struct UpdateAgentSettingsRequest {
     pub endpoint: String,
     #[serde(default = "default_archetype")]
     pub model_archetype: String,
-    #[serde(default = "default_max_tokens")]
+    /// Omitted or 0: the archetype profile's `max_tokens`
+    #[serde(default)]
     pub max_tokens: i32,
     pub secret_key: Option<String>,
+    #[serde(default)]
+    pub budget_max_tokens: Option<i64>,
+    #[serde(default)]
+    pub budget_max_usd: Option<f64>,
+    #[serde(default)]
+    pub session_budget_max_tokens: Option<i64>,
+    #[serde(default)]
+    pub session_budget_max_usd: Option<f64>,
+    #[serde(default)]
+    pub session_ttl_hours: Option<i64>,
+    #[serde(default = "default_session_sliding")]
+    pub session_sliding: bool,
+    #[serde(default)]
+    pub allowed_models: Vec<String>,
+    #[serde(default)]
+    pub spend_max_tx_usd: Option<f64>,
+    #[serde(default)]
+    pub spend_max_daily_usd: Option<f64>,
+    #[serde(default)]
+    pub spend_approval_usd: Option<f64>,
+    #[serde(default)]
+    pub max_context_tokens: Option<i64>,
+    #[serde(default)]
+    pub fallback_endpoint: Option<String>,
+    #[serde(default)]
+    pub fallback_model_archetype: Option<String>,
+    #[serde(default)]
+    pub fallback_model: Option<String>,
+    #[serde(default)]
+    pub fallback_secret_key: Option<String>,
+    #[serde(default)]
+    pub default_persona: Option<String>,
 }
 
 fn default_archetype() -> String {
     "kimi".to_string()
 }
 
-fn default_max_tokens() -> i32 {
-    40000
+fn default_session_sliding() -> bool {
+    true
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_check_overrides() {
+        let settings = AgentSettings {
+            model_archetype: "claude".to_string(),
+            max_tokens: 4096,
+            allowed_models: parse_model_list("claude-sonnet-4-20250514, claude-3-5-haiku-latest,"),
+            ..AgentSettings::default()
+        };
+        assert_eq!(settings.allowed_models.len(), 2);
+
+        let overrides = |model: Option<&str>, max_tokens, temperature| ModelOverrides {
+            model: model.map(String::from),
+            max_tokens,
+            temperature,
+        };
+        assert!(settings.check_overrides(&ModelOverrides::default()).is_ok());
+        assert!(settings
+            .check_overrides(&overrides(Some("claude-3-5-haiku-latest"), Some(4096), Some(0.2)))
+            .is_ok());
+        assert!(settings.check_overrides(&overrides(Some("gpt-4o"), None, None)).is_err());
+        assert!(settings.check_overrides(&overrides(None, Some(0), None)).is_err());
+        assert!(settings.check_overrides(&overrides(None, Some(8192), None)).is_err());
+        assert!(settings.check_overrides(&overrides(None, None, Some(1.5))).is_err());
+        assert!(settings.check_overrides(&overrides(None, None, Some(f32::NAN))).is_err());
+
+        let openai = AgentSettings { model_archetype: "openai".to_string(), ..settings };
+        assert!(openai.check_overrides(&overrides(None, None, Some(1.5))).is_ok());
+
+        let err = AgentSettings::default()
+            .check_overrides(&overrides(Some("gpt-4o"), None, None))
+            .unwrap_err();
+        assert!(err.contains("not enabled"));
+    }
+
+    #[test]
+    fn test_fallback_inherits_unset_fields() {
+        let settings = AgentSettings {
+            model_archetype: "claude".to_string(),
+            secret_key: Some("sk-primary".to_string()),
+            ..AgentSettings::default()
+        };
+        assert!(settings.fallback().is_none());
+
+        let model_only = AgentSettings {
+            fallback_model: Some("claude-3-5-haiku-latest".to_string()),
+            ..settings.clone()
+        };
+        let fallback = model_only.fallback().unwrap();
+        assert_eq!(fallback.endpoint, settings.endpoint);
+        assert_eq!(fallback.model_archetype, "claude");
+        assert_eq!(fallback.secret_key.as_deref(), Some("sk-primary"));
+
+        let other_provider = AgentSettings {
+            fallback_endpoint: Some("https://api.openai.com/v1/chat/completions".to_string()),
+            fallback_model_archetype: Some("openai".to_string()),
+            fallback_secret_key: Some("sk-fallback".to_string()),
+            ..settings
+        };
+        let fallback = other_provider.fallback().unwrap();
+        assert_eq!(fallback.endpoint, "https://api.openai.com/v1/chat/completions");
+        assert_eq!(fallback.model_archetype, "openai");
+        assert_eq!(fallback.secret_key.as_deref(), Some("sk-fallback"));
+    }
+
+    #[test]
+    fn test_provider_key() {
+        let mut settings = AgentSettings {
+            model_archetype: "claude".to_string(),
+            secret_key: Some("sk-instance".to_string()),
+            fallback_model: Some("claude-3-5-haiku-latest".to_string()),
+            ..AgentSettings::default()
+        };
+        assert_eq!(settings.provider_key_name(), Some("ANTHROPIC_API_KEY"));
+        settings.use_secret_key("sk-mine".to_string());
+        assert_eq!(settings.secret_key.as_deref(), Some("sk-mine"));
+        assert_eq!(settings.fallback().unwrap().secret_key.as_deref(), Some("sk-instance"));
+
+        let openai = AgentSettings {
+            endpoint: "https://api.openai.com/v1/chat/completions".to_string(),
+            model_archetype: "openai".to_string(),
+            ..AgentSettings::default()
+        };
+        assert_eq!(openai.provider_key_name(), Some("OPENAI_API_KEY"));
+        assert_eq!(AgentSettings::default().provider_key_name(), None);
+    }
 }
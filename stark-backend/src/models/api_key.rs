@@ -2,11 +2,15 @@ use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 
 use crate::controllers::api_keys::get_key_config;
+use crate::utils::{last_chars, truncate_chars};
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ApiKey {
     pub id: i64,
     pub service_name: String,  // Stores key names like "GITHUB_TOKEN", "MOLTX_API_KEY"
+    /// Owner of a personal key; shared keys have none
+    #[serde(default)]
+    pub user_id: Option<i64>,
     #[serde(skip_serializing)]
     pub api_key: String,
     pub created_at: DateTime<Utc>,
@@ -34,6 +38,7 @@ impl ApiKey {
             key_name: self.service_name.clone(),
             key_preview,
             is_secret,
+            personal: self.user_id.is_some(),
             created_at: self.created_at,
             updated_at: self.updated_at,
         }
@@ -42,10 +47,8 @@ impl ApiKey {
 
 /// Mask a key value for display
 fn mask_key(value: &str) -> String {
-    if value.len() > 12 {
-        let start = &value[..4];
-        let end = &value[value.len() - 4..];
-        format!("{}...{}", start, end)
+    if value.chars().count() > 12 {
+        format!("{}...{}", truncate_chars(value, 4), last_chars(value, 4))
     } else {
         "****".to_string()
     }
@@ -58,6 +61,8 @@ pub struct ApiKeyResponse {
     pub key_name: String,
     pub key_preview: String,
     pub is_secret: bool,
+    /// The caller's own key, used instead of the shared one in their conversations
+    pub personal: bool,
     pub created_at: DateTime<Utc>,
     pub updated_at: DateTime<Utc>,
 }
@@ -0,0 +1,167 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use super::ToolPolicy;
+
+/// What a bearer token may do
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum Scope {
+    /// GET endpoints: dashboards, history, settings (without secrets)
+    #[serde(rename = "read")]
+    Read,
+    /// Send chat messages and manage the conversation
+    #[serde(rename = "chat")]
+    Chat,
+    /// Let the agent run shell commands (the exec tool group)
+    #[serde(rename = "tools:exec")]
+    ToolsExec,
+    /// Let the agent sign transactions and payments with the bot wallet
+    #[serde(rename = "wallet:sign")]
+    WalletSign,
+    /// Everything, including configuration changes and token management
+    #[serde(rename = "admin")]
+    Admin,
+}
+
+impl Scope {
+    pub fn all() -> &'static [Scope] {
+        &[Scope::Read, Scope::Chat, Scope::ToolsExec, Scope::WalletSign, Scope::Admin]
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Scope::Read => "read",
+            Scope::Chat => "chat",
+            Scope::ToolsExec => "tools:exec",
+            Scope::WalletSign => "wallet:sign",
+            Scope::Admin => "admin",
+        }
+    }
+
+    pub fn from_str(s: &str) -> Option<Self> {
+        match s.trim().to_lowercase().as_str() {
+            "read" => Some(Scope::Read),
+            "chat" => Some(Scope::Chat),
+            "tools:exec" => Some(Scope::ToolsExec),
+            "wallet:sign" => Some(Scope::WalletSign),
+            "admin" => Some(Scope::Admin),
+            _ => None,
+        }
+    }
+}
+
+impl std::fmt::Display for Scope {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// The scopes granted to an authenticated request
+#[derive(Debug, Clone, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct Scopes(Vec<Scope>);
+
+impl Scopes {
+    pub fn new(scopes: impl IntoIterator<Item = Scope>) -> Self {
+        let mut list: Vec<Scope> = Vec::new();
+        for scope in scopes {
+            if !list.contains(&scope) {
+                list.push(scope);
+            }
+        }
+        Scopes(list)
+    }
+
+    /// Wallet logins hold every scope
+    pub fn full() -> Self {
+        Scopes(vec![Scope::Admin])
+    }
+
+    /// `admin` implies every other scope
+    pub fn allows(&self, scope: Scope) -> bool {
+        self.0.contains(&Scope::Admin) || self.0.contains(&scope)
+    }
+
+    pub fn is_full(&self) -> bool {
+        self.0.contains(&Scope::Admin)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &Scope> {
+        self.0.iter()
+    }
+
+    /// Comma-separated form used for storage
+    pub fn to_db_string(&self) -> String {
+        self.0.iter().map(Scope::as_str).collect::<Vec<_>>().join(",")
+    }
+
+    /// Parse the stored form, skipping scopes this build doesn't know
+    pub fn from_db_string(s: &str) -> Self {
+        Scopes::new(s.split(',').filter_map(Scope::from_str))
+    }
+}
+
+/// A named bearer token with a fixed set of scopes
+///
+/// Only a hash of the token is stored; the token itself is shown once, when
+/// it is created.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ApiToken {
+    pub id: i64,
+    /// The user the token acts as
+    #[serde(default)]
+    pub user_id: Option<i64>,
+    pub name: String,
+    pub scopes: Scopes,
+    /// First characters of the token, to tell tokens apart in listings
+    pub prefix: String,
+    /// Narrows the tools the agent may use for this token
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tool_policy: Option<ToolPolicy>,
+    pub created_at: DateTime<Utc>,
+    pub last_used_at: Option<DateTime<Utc>>,
+}
+
+/// Request to create a token
+#[derive(Debug, Clone, Deserialize)]
+pub struct CreateApiTokenRequest {
+    pub name: String,
+    pub scopes: Vec<String>,
+    #[serde(default)]
+    pub tool_policy: Option<ToolPolicy>,
+    /// Authenticator code, needed for an admin-scoped token when a TOTP secret is configured
+    #[serde(default)]
+    pub totp_code: Option<String>,
+}
+
+/// Response for a newly created token, the only time the token is returned
+#[derive(Debug, Clone, Serialize)]
+pub struct CreatedApiToken {
+    #[serde(flatten)]
+    pub token_info: ApiToken,
+    pub token: String,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_admin_implies_everything() {
+        let admin = Scopes::full();
+        assert!(Scope::all().iter().all(|s| admin.allows(*s)));
+
+        let read_only = Scopes::new([Scope::Read]);
+        assert!(read_only.allows(Scope::Read));
+        assert!(!read_only.allows(Scope::Chat));
+        assert!(!read_only.allows(Scope::ToolsExec));
+    }
+
+    #[test]
+    fn test_db_string_round_trip() {
+        let scopes = Scopes::new([Scope::Chat, Scope::ToolsExec, Scope::Chat]);
+        assert_eq!(scopes.to_db_string(), "chat,tools:exec");
+        assert_eq!(Scopes::from_db_string("chat,tools:exec,unknown"), scopes);
+        assert_eq!(serde_json::to_value(&scopes).unwrap(), serde_json::json!(["chat", "tools:exec"]));
+    }
+}
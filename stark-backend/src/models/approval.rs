@@ -0,0 +1,71 @@
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ApprovalStatus {
+    /// Waiting on an operator
+    Pending,
+    Approved,
+    Rejected,
+    /// Nobody decided before the tool gave up waiting
+    Expired,
+}
+
+impl ApprovalStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ApprovalStatus::Pending => "pending",
+            ApprovalStatus::Approved => "approved",
+            ApprovalStatus::Rejected => "rejected",
+            ApprovalStatus::Expired => "expired",
+        }
+    }
+
+    pub fn from_str(s: &str) -> Option<Self> {
+        match s {
+            "pending" => Some(ApprovalStatus::Pending),
+            "approved" => Some(ApprovalStatus::Approved),
+            "rejected" => Some(ApprovalStatus::Rejected),
+            "expired" => Some(ApprovalStatus::Expired),
+            _ => None,
+        }
+    }
+}
+
+/// A value-moving tool call held for operator approval
+#[derive(Debug, Clone, Serialize)]
+pub struct Approval {
+    pub id: i64,
+    pub status: ApprovalStatus,
+    pub tool_name: String,
+    pub tool_call_id: Option<String>,
+    pub channel_id: Option<i64>,
+    pub session_id: Option<i64>,
+    pub description: String,
+    /// None when the spend could not be priced
+    pub amount_usd: Option<f64>,
+    pub reason: String,
+    /// User whose conversation or job asked (None: admins only)
+    pub user_id: Option<i64>,
+    pub created_at: String,
+    pub decided_at: Option<String>,
+}
+
+/// An approval to request
+#[derive(Debug, Clone)]
+pub struct NewApproval {
+    pub tool_name: String,
+    pub tool_call_id: Option<String>,
+    pub channel_id: Option<i64>,
+    pub session_id: Option<i64>,
+    pub description: String,
+    pub amount_usd: Option<f64>,
+    pub reason: String,
+    pub user_id: Option<i64>,
+}
+
+/// Body of `POST /api/approvals/{id}`
+#[derive(Debug, Clone, Deserialize)]
+pub struct DecideApprovalRequest {
+    pub approve: bool,
+}
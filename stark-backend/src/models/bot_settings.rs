@@ -5,6 +5,12 @@ use std::collections::HashMap;
 /// Default max tool iterations
 pub const DEFAULT_MAX_TOOL_ITERATIONS: i32 = 50;
 
+/// Default sustained rate for chat and agent endpoints, per token and per IP
+pub const DEFAULT_RATE_LIMIT_PER_MINUTE: i32 = 30;
+
+/// Default number of requests allowed in a burst above the sustained rate
+pub const DEFAULT_RATE_LIMIT_BURST: i32 = 10;
+
 /// Bot settings stored in database
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct BotSettings {
@@ -18,6 +24,10 @@ pub struct BotSettings {
     pub custom_rpc_endpoints: Option<HashMap<String, String>>,
     /// Maximum number of tool execution iterations per request
     pub max_tool_iterations: i32,
+    /// Requests per minute allowed on chat and agent endpoints (0 disables rate limiting)
+    pub rate_limit_per_minute: i32,
+    /// Requests that may be made at once before the per-minute rate applies
+    pub rate_limit_burst: i32,
     pub created_at: DateTime<Utc>,
     pub updated_at: DateTime<Utc>,
 }
@@ -32,6 +42,8 @@ impl Default for BotSettings {
             rpc_provider: "defirelay".to_string(),
             custom_rpc_endpoints: None,
             max_tool_iterations: DEFAULT_MAX_TOOL_ITERATIONS,
+            rate_limit_per_minute: DEFAULT_RATE_LIMIT_PER_MINUTE,
+            rate_limit_burst: DEFAULT_RATE_LIMIT_BURST,
             created_at: Utc::now(),
             updated_at: Utc::now(),
         }
@@ -47,4 +59,6 @@ pub struct UpdateBotSettingsRequest {
     pub rpc_provider: Option<String>,
     pub custom_rpc_endpoints: Option<HashMap<String, String>>,
     pub max_tool_iterations: Option<i32>,
+    pub rate_limit_per_minute: Option<i32>,
+    pub rate_limit_burst: Option<i32>,
 }
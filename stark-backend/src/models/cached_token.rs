@@ -0,0 +1,14 @@
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+
+/// ERC-20 metadata read from the chain for a token not in config/tokens.ron
+#[derive(Debug, Clone, Serialize)]
+pub struct CachedToken {
+    pub network: String,
+    /// Lowercase contract address
+    pub address: String,
+    pub symbol: String,
+    pub name: String,
+    pub decimals: u8,
+    pub created_at: DateTime<Utc>,
+}
@@ -7,6 +7,7 @@ pub enum ChannelType {
     Telegram,
     Slack,
     Discord,
+    Farcaster,
 }
 
 impl ChannelType {
@@ -15,6 +16,7 @@ impl ChannelType {
             ChannelType::Telegram => "telegram",
             ChannelType::Slack => "slack",
             ChannelType::Discord => "discord",
+            ChannelType::Farcaster => "farcaster",
         }
     }
 
@@ -23,6 +25,7 @@ impl ChannelType {
             "telegram" => Some(ChannelType::Telegram),
             "slack" => Some(ChannelType::Slack),
             "discord" => Some(ChannelType::Discord),
+            "farcaster" => Some(ChannelType::Farcaster),
             _ => None,
         }
     }
@@ -98,6 +101,8 @@ impl ChannelResponse {
 pub struct CreateChannelRequest {
     pub channel_type: String,
     pub name: String,
+    /// May be left empty for Discord and Farcaster to use the shared API key
+    #[serde(default)]
     pub bot_token: String,
     pub app_token: Option<String>,
 }
@@ -1,6 +1,8 @@
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 
+use super::ToolPolicy;
+
 /// Session scope determines the context type
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 #[serde(rename_all = "lowercase")]
@@ -158,6 +160,15 @@ pub struct ChatSession {
     /// Completion status of the session
     #[serde(default)]
     pub completion_status: CompletionStatus,
+    /// Persona the conversation runs under (None: the agent settings' default)
+    #[serde(default)]
+    pub persona: Option<String>,
+    /// Narrows the tools the agent may use in this conversation
+    #[serde(default)]
+    pub tool_policy: Option<ToolPolicy>,
+    /// User the conversation belongs to (None: a channel or scheduler conversation)
+    #[serde(default)]
+    pub user_id: Option<i64>,
 }
 
 /// Request to get or create a chat session
@@ -203,6 +214,12 @@ pub struct ChatSessionResponse {
     pub compaction_id: Option<i64>,
     // Completion status
     pub completion_status: CompletionStatus,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub persona: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tool_policy: Option<ToolPolicy>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub user_id: Option<i64>,
     // Initial query (first user message) - for web sessions
     #[serde(skip_serializing_if = "Option::is_none")]
     pub initial_query: Option<String>,
@@ -230,6 +247,9 @@ impl From<ChatSession> for ChatSessionResponse {
             max_context_tokens: session.max_context_tokens,
             compaction_id: session.compaction_id,
             completion_status: session.completion_status,
+            persona: session.persona,
+            tool_policy: session.tool_policy,
+            user_id: session.user_id,
             initial_query: None,
         }
     }
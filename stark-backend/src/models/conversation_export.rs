@@ -0,0 +1,299 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use super::chat_session::{ChatSession, SessionScope};
+use super::session_message::{AddMessageRequest, MessageRole, SessionMessage};
+use crate::redaction::{redact, redact_value};
+
+/// Bumped whenever the JSON export layout changes incompatibly
+pub const EXPORT_FORMAT_VERSION: u32 = 1;
+
+/// Output format for conversation exports
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExportFormat {
+    Json,
+    Markdown,
+}
+
+impl ExportFormat {
+    pub fn from_str(s: &str) -> Option<Self> {
+        match s.to_lowercase().as_str() {
+            "json" => Some(ExportFormat::Json),
+            "md" | "markdown" => Some(ExportFormat::Markdown),
+            _ => None,
+        }
+    }
+}
+
+/// Portable snapshot of a single conversation (chat session)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ConversationExport {
+    pub version: u32,
+    pub exported_at: DateTime<Utc>,
+    pub session: ExportedSession,
+    pub messages: Vec<ExportedMessage>,
+}
+
+/// Session metadata included in an export
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ExportedSession {
+    pub id: i64,
+    pub session_key: String,
+    pub agent_id: Option<String>,
+    pub scope: SessionScope,
+    pub channel_type: String,
+    pub platform_chat_id: String,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+/// A message in an export - the fields of `SessionMessage` that survive re-import
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ExportedMessage {
+    pub role: MessageRole,
+    pub content: String,
+    pub user_id: Option<String>,
+    pub user_name: Option<String>,
+    pub platform_message_id: Option<String>,
+    pub tokens_used: Option<i32>,
+    pub created_at: DateTime<Utc>,
+}
+
+impl From<ExportedMessage> for AddMessageRequest {
+    fn from(msg: ExportedMessage) -> Self {
+        AddMessageRequest {
+            role: msg.role,
+            content: msg.content,
+            user_id: msg.user_id,
+            user_name: msg.user_name,
+            platform_message_id: msg.platform_message_id,
+            tokens_used: msg.tokens_used,
+        }
+    }
+}
+
+impl ConversationExport {
+    /// Build an export from a session and its messages, redacting secrets in tool arguments
+    pub fn new(session: &ChatSession, messages: Vec<SessionMessage>) -> Self {
+        let messages = messages
+            .into_iter()
+            .map(|m| ExportedMessage {
+                content: if m.role == MessageRole::ToolCall {
+                    redact_tool_call_content(&m.content)
+                } else {
+                    m.content
+                },
+                role: m.role,
+                user_id: m.user_id,
+                user_name: m.user_name,
+                platform_message_id: m.platform_message_id,
+                tokens_used: m.tokens_used,
+                created_at: m.created_at,
+            })
+            .collect();
+
+        ConversationExport {
+            version: EXPORT_FORMAT_VERSION,
+            exported_at: Utc::now(),
+            session: ExportedSession {
+                id: session.id,
+                session_key: session.session_key.clone(),
+                agent_id: session.agent_id.clone(),
+                scope: session.scope,
+                channel_type: session.channel_type.clone(),
+                platform_chat_id: session.platform_chat_id.clone(),
+                created_at: session.created_at,
+                updated_at: session.updated_at,
+            },
+            messages,
+        }
+    }
+
+    /// Render the conversation as human-readable Markdown
+    pub fn to_markdown(&self) -> String {
+        let mut out = format!("# Conversation {}\n\n", self.session.session_key);
+        out.push_str(&format!("- **Session:** {}\n", self.session.id));
+        out.push_str(&format!(
+            "- **Channel:** {} ({})\n",
+            self.session.channel_type, self.session.platform_chat_id
+        ));
+        if let Some(ref agent_id) = self.session.agent_id {
+            out.push_str(&format!("- **Agent:** {}\n", agent_id));
+        }
+        out.push_str(&format!(
+            "- **Started:** {}\n",
+            self.session.created_at.format("%Y-%m-%d %H:%M:%S UTC")
+        ));
+        out.push_str(&format!(
+            "- **Exported:** {}\n",
+            self.exported_at.format("%Y-%m-%d %H:%M:%S UTC")
+        ));
+        out.push_str(&format!("- **Messages:** {}\n", self.messages.len()));
+
+        for msg in &self.messages {
+            let heading = match msg.role {
+                MessageRole::User => match msg.user_name {
+                    Some(ref name) => format!("👤 User ({})", name),
+                    None => "👤 User".to_string(),
+                },
+                MessageRole::Assistant => "🤖 Assistant".to_string(),
+                MessageRole::System => "⚙️ System".to_string(),
+                // Tool messages store the tool name in user_name
+                MessageRole::ToolCall => format!(
+                    "🔧 Tool call: {}",
+                    msg.user_name.as_deref().unwrap_or("tool")
+                ),
+                MessageRole::ToolResult => format!(
+                    "📋 Tool result: {}",
+                    msg.user_name.as_deref().unwrap_or("tool")
+                ),
+            };
+            out.push_str(&format!(
+                "\n---\n\n### {} · {}\n\n{}\n",
+                heading,
+                msg.created_at.format("%Y-%m-%d %H:%M:%S"),
+                msg.content.trim_end()
+            ));
+        }
+
+        out
+    }
+}
+
+/// Redact the JSON argument block of a stored tool call message
+/// ("🔧 **Tool Call:** `name`\n```json\n{...}\n```")
+fn redact_tool_call_content(content: &str) -> String {
+    let block = content
+        .find("```json\n")
+        .map(|start| start + "```json\n".len())
+        .and_then(|start| content.rfind("\n```").filter(|&end| end >= start).map(|end| (start, end)));
+
+    let Some((start, end)) = block else {
+        return redact(content);
+    };
+
+    match serde_json::from_str::<Value>(&content[start..end]) {
+        Ok(mut args) => {
+            redact_value(&mut args);
+            let pretty = serde_json::to_string_pretty(&args).unwrap_or_default();
+            format!("{}{}{}", &content[..start], pretty, &content[end..])
+        }
+        Err(_) => redact(content),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::chat_session::{CompletionStatus, ResetPolicy};
+
+    fn session() -> ChatSession {
+        let now = Utc::now();
+        ChatSession {
+            id: 7,
+            session_key: "web:default".to_string(),
+            agent_id: None,
+            scope: SessionScope::Dm,
+            channel_type: "web".to_string(),
+            channel_id: 0,
+            platform_chat_id: "default".to_string(),
+            is_active: true,
+            reset_policy: ResetPolicy::default(),
+            idle_timeout_minutes: None,
+            daily_reset_hour: None,
+            created_at: now,
+            updated_at: now,
+            last_activity_at: now,
+            expires_at: None,
+            context_tokens: 0,
+            max_context_tokens: 100000,
+            compaction_id: None,
+            completion_status: CompletionStatus::default(),
+            persona: None,
+            tool_policy: None,
+            user_id: None,
+        }
+    }
+
+    fn message(role: MessageRole, content: &str, user_name: Option<&str>) -> SessionMessage {
+        SessionMessage {
+            id: 1,
+            session_id: 7,
+            role,
+            content: content.to_string(),
+            user_id: None,
+            user_name: user_name.map(str::to_string),
+            platform_message_id: None,
+            tokens_used: None,
+            created_at: Utc::now(),
+        }
+    }
+
+    #[test]
+    fn test_tool_call_args_are_redacted() {
+        let content = "🔧 **Tool Call:** `exec`\n```json\n{\n  \"command\": \"curl -H 'Authorization: Bearer abc123def' https://x\",\n  \"api_key\": \"hunter2\",\n  \"token\": \"USDC\",\n  \"headers\": {\"X-Access-Token\": \"t0k3n\"}\n}\n```";
+        let redacted = redact_tool_call_content(content);
+
+        assert!(redacted.starts_with("🔧 **Tool Call:** `exec`\n```json\n"));
+        assert!(redacted.ends_with("\n```"));
+        assert!(!redacted.contains("hunter2"));
+        assert!(!redacted.contains("abc123def"));
+        assert!(!redacted.contains("t0k3n"));
+        assert!(redacted.contains("Bearer [REDACTED]"));
+        assert!(redacted.contains("USDC"));
+    }
+
+    #[test]
+    fn test_json_export_round_trips() {
+        let export = ConversationExport::new(
+            &session(),
+            vec![
+                message(MessageRole::User, "hello", Some("alice")),
+                message(MessageRole::Assistant, "hi there", None),
+            ],
+        );
+
+        let json = serde_json::to_string(&export).unwrap();
+        let parsed: ConversationExport = serde_json::from_str(&json).unwrap();
+        assert_eq!(parsed.version, EXPORT_FORMAT_VERSION);
+        assert_eq!(parsed.session.session_key, "web:default");
+        assert_eq!(parsed.messages.len(), 2);
+
+        let request: AddMessageRequest = parsed.messages[0].clone().into();
+        assert_eq!(request.role, MessageRole::User);
+        assert_eq!(request.content, "hello");
+        assert_eq!(request.user_name.as_deref(), Some("alice"));
+    }
+
+    #[test]
+    fn test_markdown_render() {
+        let export = ConversationExport::new(
+            &session(),
+            vec![
+                message(MessageRole::User, "list files", None),
+                message(
+                    MessageRole::ToolCall,
+                    "🔧 **Tool Call:** `list_files`\n```json\n{\"path\": \".\"}\n```",
+                    Some("list_files"),
+                ),
+                message(MessageRole::Assistant, "Done.", None),
+            ],
+        );
+
+        let md = export.to_markdown();
+        assert!(md.starts_with("# Conversation web:default\n"));
+        assert!(md.contains("### 👤 User · "));
+        assert!(md.contains("### 🔧 Tool call: list_files · "));
+        assert!(md.contains("### 🤖 Assistant · "));
+        assert!(md.contains("\nDone.\n"));
+    }
+
+    #[test]
+    fn test_export_format_parsing() {
+        assert_eq!(ExportFormat::from_str("json"), Some(ExportFormat::Json));
+        assert_eq!(ExportFormat::from_str("MD"), Some(ExportFormat::Markdown));
+        assert_eq!(ExportFormat::from_str("markdown"), Some(ExportFormat::Markdown));
+        assert_eq!(ExportFormat::from_str("pdf"), None);
+    }
+}
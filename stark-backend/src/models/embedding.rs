@@ -0,0 +1,27 @@
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+/// A text stored with its embedding through `/api/embeddings`. The vector
+/// itself stays in the database.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct StoredEmbedding {
+    pub id: i64,
+    /// Group the text is searched in, e.g. "docs" or "skills"
+    pub collection: String,
+    /// Caller's id for the text; storing the same id again replaces it
+    pub external_id: Option<String>,
+    pub content: String,
+    pub metadata: Option<Value>,
+    /// Embedding model; only vectors of the same model are compared
+    pub model: String,
+    pub dimensions: i64,
+    pub created_at: String,
+}
+
+/// A stored text and its cosine similarity to a search query
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct EmbeddingMatch {
+    #[serde(flatten)]
+    pub embedding: StoredEmbedding,
+    pub score: f64,
+}
@@ -0,0 +1,41 @@
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+
+/// Why a sign-in attempt was refused
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LoginFailureReason {
+    InvalidAddress,
+    /// A valid address that isn't the admin's
+    UnauthorizedAddress,
+    /// No challenge was issued for the address, or it didn't match
+    InvalidChallenge,
+    InvalidSignature,
+    /// Missing or wrong second-factor code
+    InvalidTotp,
+    /// Tried again while the IP was locked out
+    LockedOut,
+}
+
+impl LoginFailureReason {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::InvalidAddress => "invalid_address",
+            Self::UnauthorizedAddress => "unauthorized_address",
+            Self::InvalidChallenge => "invalid_challenge",
+            Self::InvalidSignature => "invalid_signature",
+            Self::InvalidTotp => "invalid_totp",
+            Self::LockedOut => "locked_out",
+        }
+    }
+}
+
+/// A refused sign-in, from the audit log
+#[derive(Debug, Clone, Serialize)]
+pub struct LoginFailure {
+    pub id: i64,
+    pub ip: String,
+    pub public_address: Option<String>,
+    /// A `LoginFailureReason` name, e.g. "invalid_signature"
+    pub reason: String,
+    pub occurred_at: DateTime<Utc>,
+}
@@ -101,6 +101,8 @@ pub struct Memory {
     pub valid_until: Option<DateTime<Utc>>,
     /// Temporal type: "permanent", "temporary", "scheduled"
     pub temporal_type: Option<String>,
+    /// User whose conversation the memory came from; None: visible to admins only
+    pub user_id: Option<i64>,
 }
 
 /// Request to create a memory
@@ -179,6 +181,7 @@ pub struct MemoryResponse {
     pub valid_from: Option<DateTime<Utc>>,
     pub valid_until: Option<DateTime<Utc>>,
     pub temporal_type: Option<String>,
+    pub user_id: Option<i64>,
 }
 
 impl From<Memory> for MemoryResponse {
@@ -204,6 +207,7 @@ impl From<Memory> for MemoryResponse {
             valid_from: memory.valid_from,
             valid_until: memory.valid_until,
             temporal_type: memory.temporal_type,
+            user_id: memory.user_id,
         }
     }
 }
@@ -244,6 +248,8 @@ pub struct MemoryStats {
     pub newest_memory_at: Option<DateTime<Utc>>,
     pub superseded_count: i64,
     pub temporal_active_count: i64,
+    /// Memories with a stored embedding (searchable by vector similarity)
+    pub embedded_count: i64,
 }
 
 /// Memory search result with relevance score
@@ -1,23 +1,51 @@
+pub mod agent_job;
 pub mod agent_settings;
 pub mod api_key;
+pub mod api_token;
+pub mod approval;
 pub mod bot_settings;
+pub mod cached_token;
 pub mod channel;
 pub mod chat_session;
+pub mod conversation_export;
 pub mod cron_job;
+pub mod embedding;
 pub mod execution;
 pub mod identity;
+pub mod login_failure;
 pub mod memory;
+pub mod persona;
+pub mod prompt_template;
+pub mod rate_limit;
+pub mod register;
+pub mod scheduled_task;
 pub mod session;
 pub mod session_message;
+pub mod skill_sync;
+pub mod tool_invocation;
+pub mod tool_policy;
+pub mod transaction;
+pub mod usage;
+pub mod user;
+pub mod wallet;
+pub mod webhook;
 
-pub use agent_settings::{AgentSettings, AgentSettingsResponse, UpdateAgentSettingsRequest};
+pub use agent_job::{AgentJob, AgentJobStatus};
+pub use agent_settings::{
+    parse_model_list, AgentSettings, AgentSettingsResponse, ModelOverrides, UpdateAgentSettingsRequest,
+};
 pub use bot_settings::{BotSettings, UpdateBotSettingsRequest, DEFAULT_MAX_TOOL_ITERATIONS};
 pub use api_key::{ApiKey, ApiKeyResponse};
+pub use api_token::{ApiToken, CreateApiTokenRequest, CreatedApiToken, Scope, Scopes};
+pub use approval::{Approval, ApprovalStatus, DecideApprovalRequest, NewApproval};
+pub use cached_token::CachedToken;
 pub use channel::{Channel, ChannelResponse, ChannelType, CreateChannelRequest, UpdateChannelRequest};
 pub use chat_session::{
     ChatSession, ChatSessionResponse, CompletionStatus, GetOrCreateSessionRequest, ResetPolicy,
     SessionScope, UpdateResetPolicyRequest,
 };
+pub use conversation_export::{ConversationExport, ExportFormat};
+pub use embedding::{EmbeddingMatch, StoredEmbedding};
 pub use identity::{
     GetOrCreateIdentityRequest, IdentityLink, IdentityResponse, LinkIdentityRequest,
     LinkedAccountInfo,
@@ -26,11 +54,27 @@ pub use memory::{
     CreateMemoryRequest, Memory, MemoryResponse, MemorySearchResult, MemoryStats, MemoryType,
     MergeMemoriesRequest, SearchMemoriesRequest, UpdateMemoryRequest,
 };
-pub use session::Session;
+pub use persona::{CreatePersonaRequest, Persona, SetSessionPersonaRequest, UpdatePersonaRequest};
+pub use prompt_template::{CreatePromptTemplateRequest, PromptTemplate, UpdatePromptTemplateRequest};
+pub use login_failure::{LoginFailure, LoginFailureReason};
+pub use rate_limit::RateLimitViolation;
+pub use register::SessionRegister;
+pub use scheduled_task::{
+    parse_cron_expression, CreateScheduledTaskRequest, ScheduledTask, UpdateScheduledTaskRequest,
+};
+pub use session::{Session, SessionPolicy, SessionResponse};
 pub use session_message::{AddMessageRequest, MessageRole, SessionMessage, SessionTranscriptResponse};
+pub use skill_sync::{SkillSync, SyncedSkill};
 pub use cron_job::{
     CreateCronJobRequest, CronJob, CronJobResponse, CronJobRun, HeartbeatConfig,
     HeartbeatConfigResponse, JobStatus, ScheduleType, SessionMode, UpdateCronJobRequest,
     UpdateHeartbeatConfigRequest,
 };
 pub use execution::{ExecutionTask, TaskMetrics, TaskStatus, TaskType};
+pub use tool_invocation::{NewToolInvocation, ToolInvocation, ToolInvocationQuery, ToolOutcome};
+pub use tool_policy::{SetToolPolicyRequest, ToolPolicy};
+pub use transaction::{NewTransaction, Transaction, TransactionKind, TransactionQuery, TransactionStatus};
+pub use usage::{ApiKeyUsage, DailyUsage, SessionUsage, UsageTotals};
+pub use user::{Caller, CreateUserRequest, UpdateUserRequest, User, UserRole};
+pub use wallet::{CreateWalletRequest, CreatedWallet, Wallet, WalletKind};
+pub use webhook::{CreateWebhookRequest, UpdateWebhookRequest, Webhook};
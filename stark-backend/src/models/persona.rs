@@ -0,0 +1,110 @@
+use serde::{Deserialize, Serialize};
+
+use super::ModelOverrides;
+
+/// A named system prompt, with the tool groups and model that go with it.
+/// Conversations pick one with `chat_sessions.persona`; the rest use the
+/// agent settings' `default_persona`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Persona {
+    pub id: i64,
+    /// Unique; how sessions and agent settings refer to the persona
+    pub name: String,
+    pub description: String,
+    /// Replaces SOUL.md / the default intro at the top of the system prompt
+    pub system_prompt: String,
+    /// Tool groups the persona may use, within what the channel allows
+    /// (empty: the channel's groups)
+    pub tool_groups: Vec<String>,
+    /// Model used unless the request picks one (none: the archetype's model)
+    pub model: Option<String>,
+    pub created_at: String,
+    pub updated_at: String,
+}
+
+impl Persona {
+    /// The model parameters for a request under this persona: the request's
+    /// own, with the persona's model when the request doesn't name one
+    pub fn model_overrides(&self, requested: Option<&ModelOverrides>) -> Option<ModelOverrides> {
+        let mut overrides = requested.cloned().unwrap_or_default();
+        if overrides.model.is_none() {
+            overrides.model = self.model.clone();
+        }
+        (!overrides.is_empty()).then_some(overrides)
+    }
+}
+
+/// Request to create a persona
+#[derive(Debug, Clone, Deserialize)]
+pub struct CreatePersonaRequest {
+    pub name: String,
+    #[serde(default)]
+    pub description: String,
+    pub system_prompt: String,
+    #[serde(default)]
+    pub tool_groups: Vec<String>,
+    #[serde(default)]
+    pub model: Option<String>,
+}
+
+/// Request to update a persona; omitted fields are left unchanged. The name
+/// can't change, since sessions and agent settings refer to it.
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct UpdatePersonaRequest {
+    #[serde(default)]
+    pub description: Option<String>,
+    #[serde(default)]
+    pub system_prompt: Option<String>,
+    #[serde(default)]
+    pub tool_groups: Option<Vec<String>>,
+    /// `""` clears the model
+    #[serde(default)]
+    pub model: Option<String>,
+}
+
+/// Request to pick a chat session's persona; `null` goes back to the default
+#[derive(Debug, Clone, Deserialize)]
+pub struct SetSessionPersonaRequest {
+    pub persona: Option<String>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tools::{ToolConfig, ToolGroup};
+
+    #[test]
+    fn test_persona_model_and_tool_groups() {
+        let persona = Persona {
+            id: 1,
+            name: "researcher".to_string(),
+            description: String::new(),
+            system_prompt: "You research things.".to_string(),
+            tool_groups: vec!["web".to_string(), "memory".to_string()],
+            model: Some("claude-3-5-haiku-latest".to_string()),
+            created_at: String::new(),
+            updated_at: String::new(),
+        };
+
+        // The request's model wins; otherwise the persona's fills in
+        let requested = ModelOverrides {
+            temperature: Some(0.2),
+            ..Default::default()
+        };
+        let overrides = persona.model_overrides(Some(&requested)).unwrap();
+        assert_eq!(overrides.model.as_deref(), Some("claude-3-5-haiku-latest"));
+        assert_eq!(overrides.temperature, Some(0.2));
+        let requested = ModelOverrides {
+            model: Some("claude-sonnet-4".to_string()),
+            ..Default::default()
+        };
+        assert_eq!(persona.model_overrides(Some(&requested)), Some(requested));
+        assert_eq!(Persona { model: None, ..persona.clone() }.model_overrides(None), None);
+
+        let mut config = ToolConfig::default();
+        config.restrict_to_groups(&persona.tool_groups);
+        assert!(config.is_tool_allowed("web_fetch", ToolGroup::Web));
+        assert!(config.is_tool_allowed("ask_user", ToolGroup::System));
+        assert!(!config.is_tool_allowed("exec", ToolGroup::Exec));
+    }
+}
@@ -0,0 +1,41 @@
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+/// A reusable prompt. `{{variable}}` placeholders are filled in when it is
+/// rendered, and `{{> other}}` includes another template; see
+/// `agent::templates`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PromptTemplate {
+    pub id: i64,
+    /// Unique; how the template is addressed and included
+    pub name: String,
+    pub description: String,
+    pub body: String,
+    /// Default values for the body's variables
+    pub variables: BTreeMap<String, String>,
+    pub created_at: String,
+    pub updated_at: String,
+}
+
+/// Request to create a prompt template
+#[derive(Debug, Clone, Deserialize)]
+pub struct CreatePromptTemplateRequest {
+    pub name: String,
+    #[serde(default)]
+    pub description: String,
+    pub body: String,
+    #[serde(default)]
+    pub variables: BTreeMap<String, String>,
+}
+
+/// Request to update a prompt template; omitted fields are left unchanged.
+/// The name can't change, since other prompts include the template by name.
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct UpdatePromptTemplateRequest {
+    #[serde(default)]
+    pub description: Option<String>,
+    #[serde(default)]
+    pub body: Option<String>,
+    #[serde(default)]
+    pub variables: Option<BTreeMap<String, String>>,
+}
@@ -0,0 +1,15 @@
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+
+/// A request rejected by the chat/agent rate limiter
+#[derive(Debug, Clone, Serialize)]
+pub struct RateLimitViolation {
+    pub id: i64,
+    /// Which bucket ran dry: "token" or "ip"
+    pub key_type: String,
+    /// Client IP, or the first characters of the bearer token
+    pub key: String,
+    pub method: String,
+    pub path: String,
+    pub occurred_at: DateTime<Utc>,
+}
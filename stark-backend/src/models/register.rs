@@ -0,0 +1,13 @@
+use serde::Serialize;
+use serde_json::Value;
+
+/// A tool register persisted for a chat session
+#[derive(Debug, Clone, Serialize)]
+pub struct SessionRegister {
+    pub session_id: i64,
+    pub key: String,
+    pub value: Value,
+    /// Tool that last set the register
+    pub source_tool: String,
+    pub updated_at: String,
+}
@@ -0,0 +1,137 @@
+use chrono::{DateTime, Utc};
+use cron::Schedule;
+use serde::{Deserialize, Serialize};
+use std::str::FromStr;
+
+/// Parse a cron expression. Standard 5-field expressions (`*/15 * * * *`) run
+/// at second 0; 6- and 7-field expressions with seconds (and a year) are
+/// accepted as-is.
+pub fn parse_cron_expression(expression: &str) -> Result<Schedule, String> {
+    let expression = expression.trim();
+    let normalized = if expression.split_whitespace().count() == 5 {
+        format!("0 {}", expression)
+    } else {
+        expression.to_string()
+    };
+    Schedule::from_str(&normalized).map_err(|e| format!("Invalid cron expression '{}': {}", expression, e))
+}
+
+/// A CodeEngineer task queued as an agent job every time its cron expression fires
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ScheduledTask {
+    pub id: i64,
+    pub name: String,
+    /// Evaluated in UTC
+    pub cron_expression: String,
+    pub prompt: String,
+    /// Tools the job may use; empty means the default CodeEngineer tools
+    pub tools: Vec<String>,
+    /// Workspace shared by every run, so a run can build on the last one's files
+    pub workspace: String,
+    pub max_iterations: i64,
+    pub enabled: bool,
+    pub last_run_at: Option<String>,
+    pub next_run_at: Option<String>,
+    /// Agent job queued by the most recent run
+    pub last_job_id: Option<String>,
+    pub created_at: String,
+    pub updated_at: String,
+    /// User who created the task; its jobs run as them. None: admins only.
+    pub user_id: Option<i64>,
+}
+
+impl ScheduledTask {
+    /// Workspace name used when a schedule doesn't set one
+    pub fn default_workspace(id: i64) -> String {
+        format!("schedule-{}", id)
+    }
+
+    /// The first time after `after` that the cron expression fires
+    pub fn next_run_after(&self, after: DateTime<Utc>) -> Option<DateTime<Utc>> {
+        parse_cron_expression(&self.cron_expression).ok()?.after(&after).next()
+    }
+
+    /// Whether the schedule is enabled and its next run time has passed
+    pub fn is_due(&self, now: DateTime<Utc>) -> bool {
+        self.enabled
+            && self
+                .next_run_at
+                .as_deref()
+                .and_then(|s| DateTime::parse_from_rfc3339(s).ok())
+                .is_some_and(|next| next.with_timezone(&Utc) <= now)
+    }
+}
+
+fn default_enabled() -> bool {
+    true
+}
+
+/// Request to create a scheduled task
+#[derive(Debug, Clone, Deserialize)]
+pub struct CreateScheduledTaskRequest {
+    pub name: String,
+    pub cron_expression: String,
+    pub prompt: String,
+    #[serde(default)]
+    pub tools: Vec<String>,
+    #[serde(default)]
+    pub workspace: Option<String>,
+    #[serde(default)]
+    pub max_iterations: Option<usize>,
+    #[serde(default = "default_enabled")]
+    pub enabled: bool,
+}
+
+/// Request to update a scheduled task; omitted fields are left unchanged
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct UpdateScheduledTaskRequest {
+    #[serde(default)]
+    pub name: Option<String>,
+    #[serde(default)]
+    pub cron_expression: Option<String>,
+    #[serde(default)]
+    pub prompt: Option<String>,
+    #[serde(default)]
+    pub tools: Option<Vec<String>>,
+    #[serde(default)]
+    pub workspace: Option<String>,
+    #[serde(default)]
+    pub max_iterations: Option<usize>,
+    #[serde(default)]
+    pub enabled: Option<bool>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_cron_expressions_and_next_run() {
+        assert!(parse_cron_expression("*/15 * * * *").is_ok());
+        assert!(parse_cron_expression("30 0 9 * * Mon-Fri").is_ok());
+        assert!(parse_cron_expression("every day").is_err());
+
+        let task = ScheduledTask {
+            id: 1,
+            name: "nightly".to_string(),
+            cron_expression: "0 3 * * *".to_string(),
+            prompt: "Run the test suite".to_string(),
+            tools: vec![],
+            workspace: ScheduledTask::default_workspace(1),
+            max_iterations: 10,
+            enabled: true,
+            last_run_at: None,
+            next_run_at: Some("2026-01-01T03:00:00+00:00".to_string()),
+            last_job_id: None,
+            created_at: String::new(),
+            updated_at: String::new(),
+            user_id: None,
+        };
+        let after = DateTime::parse_from_rfc3339("2026-01-01T03:00:00Z").unwrap().with_timezone(&Utc);
+        assert_eq!(task.next_run_after(after).unwrap().to_rfc3339(), "2026-01-02T03:00:00+00:00");
+
+        assert!(task.is_due(after));
+        assert!(!task.is_due(after - chrono::Duration::seconds(1)));
+        assert!(!ScheduledTask { enabled: false, ..task }.is_due(after));
+    }
+}
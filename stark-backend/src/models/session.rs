@@ -1,10 +1,75 @@
-use chrono::{DateTime, Utc};
+use chrono::{DateTime, Duration, Utc};
 use serde::{Deserialize, Serialize};
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Session {
     pub id: i64,
+    #[serde(skip_serializing)]
     pub token: String,
+    /// The user who signed in
+    #[serde(default)]
+    pub user_id: Option<i64>,
     pub created_at: DateTime<Utc>,
     pub expires_at: DateTime<Utc>,
 }
+
+/// Response version of a session without the bearer token.
+/// Only the login response should ever carry the token itself.
+#[derive(Debug, Clone, Serialize)]
+pub struct SessionResponse {
+    pub id: i64,
+    pub created_at: DateTime<Utc>,
+    pub expires_at: DateTime<Utc>,
+}
+
+/// How long login sessions last, from the active agent settings
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SessionPolicy {
+    pub ttl: Duration,
+    /// Push `expires_at` out to now + `ttl` on every validated request.
+    /// Otherwise sessions expire `ttl` after login unless explicitly refreshed.
+    pub sliding: bool,
+}
+
+impl Default for SessionPolicy {
+    fn default() -> Self {
+        Self {
+            ttl: Duration::hours(24),
+            sliding: true,
+        }
+    }
+}
+
+impl From<Session> for SessionResponse {
+    fn from(session: Session) -> Self {
+        SessionResponse {
+            id: session.id,
+            created_at: session.created_at,
+            expires_at: session.expires_at,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_session_serialization_omits_token() {
+        let session = Session {
+            id: 1,
+            token: "secret-token".to_string(),
+            user_id: Some(1),
+            created_at: Utc::now(),
+            expires_at: Utc::now(),
+        };
+
+        let raw = serde_json::to_string(&session).unwrap();
+        assert!(!raw.contains("secret-token"));
+
+        let response = serde_json::to_value(SessionResponse::from(session)).unwrap();
+        assert_eq!(response["id"], 1);
+        assert!(response.get("token").is_none());
+        assert!(response["expires_at"].is_string());
+    }
+}
@@ -0,0 +1,22 @@
+use serde::{Deserialize, Serialize};
+
+/// A skill imported by a marketplace sync, at the version it had in the repo
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SyncedSkill {
+    pub name: String,
+    pub version: String,
+}
+
+/// One sync of the skill marketplace git repository
+#[derive(Debug, Clone, Serialize)]
+pub struct SkillSync {
+    pub id: i64,
+    pub repo_url: String,
+    pub branch: String,
+    /// Commit the skills were imported from; None when the clone or pull failed
+    pub commit_sha: Option<String>,
+    pub skills: Vec<SyncedSkill>,
+    /// Git failures and skill files that failed validation
+    pub errors: Vec<String>,
+    pub synced_at: String,
+}
@@ -0,0 +1,148 @@
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::redaction::{redact, redact_value};
+use crate::utils::truncate_str;
+
+/// Longest string argument kept in the audit log, in characters
+const MAX_ARGUMENT_CHARS: usize = 1000;
+
+/// Longest result summary kept in the audit log, in characters
+const MAX_SUMMARY_CHARS: usize = 500;
+
+/// How an audited tool call ended
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ToolOutcome {
+    Success,
+    Error,
+    /// Refused before running: unknown tool, disabled group or tool policy
+    Denied,
+}
+
+impl ToolOutcome {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ToolOutcome::Success => "success",
+            ToolOutcome::Error => "error",
+            ToolOutcome::Denied => "denied",
+        }
+    }
+
+    pub fn from_str(s: &str) -> Option<Self> {
+        match s {
+            "success" => Some(ToolOutcome::Success),
+            "error" => Some(ToolOutcome::Error),
+            "denied" => Some(ToolOutcome::Denied),
+            _ => None,
+        }
+    }
+}
+
+/// An audited tool call
+#[derive(Debug, Clone, Serialize)]
+pub struct ToolInvocation {
+    pub id: i64,
+    pub tool_name: String,
+    pub tool_group: Option<String>,
+    /// Arguments with credentials redacted and long strings cut
+    pub arguments: Value,
+    pub outcome: ToolOutcome,
+    pub result_summary: String,
+    pub duration_ms: i64,
+    pub channel_id: Option<i64>,
+    pub channel_type: Option<String>,
+    pub session_id: Option<i64>,
+    pub job_id: Option<String>,
+    pub user_id: Option<String>,
+    pub tool_call_id: Option<String>,
+    /// Request ID of the run, as in the logs and traces
+    pub request_id: Option<String>,
+    pub created_at: String,
+}
+
+/// A tool call to audit
+#[derive(Debug, Clone)]
+pub struct NewToolInvocation {
+    pub tool_name: String,
+    pub tool_group: Option<String>,
+    pub arguments: Value,
+    pub outcome: ToolOutcome,
+    pub result_summary: String,
+    pub duration_ms: i64,
+    pub channel_id: Option<i64>,
+    pub channel_type: Option<String>,
+    pub session_id: Option<i64>,
+    pub job_id: Option<String>,
+    pub user_id: Option<String>,
+    pub tool_call_id: Option<String>,
+    pub request_id: Option<String>,
+}
+
+/// Arguments as they are stored: credentials redacted, secret-looking
+/// strings scrubbed and long strings (file contents, scripts) cut
+pub fn sanitize_arguments(arguments: &Value) -> Value {
+    fn cut(value: &mut Value) {
+        match value {
+            Value::String(s) if s.chars().count() > MAX_ARGUMENT_CHARS => *s = truncate_str(s, MAX_ARGUMENT_CHARS),
+            Value::Array(items) => items.iter_mut().for_each(cut),
+            Value::Object(map) => map.values_mut().for_each(cut),
+            _ => {}
+        }
+    }
+
+    let mut arguments = arguments.clone();
+    redact_value(&mut arguments);
+    cut(&mut arguments);
+    arguments
+}
+
+/// The start of a tool's output or error, with secrets scrubbed
+pub fn summarize_result(text: &str) -> String {
+    redact(&truncate_str(text, MAX_SUMMARY_CHARS))
+}
+
+/// Filters for `GET /api/audit`
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct ToolInvocationQuery {
+    pub tool: Option<String>,
+    pub group: Option<String>,
+    pub outcome: Option<String>,
+    pub session_id: Option<i64>,
+    pub job_id: Option<String>,
+    pub channel_id: Option<i64>,
+    pub request_id: Option<String>,
+    /// RFC 3339 bounds on `created_at`
+    pub since: Option<String>,
+    pub until: Option<String>,
+    pub limit: Option<i64>,
+    pub offset: Option<i64>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_sanitize_arguments_and_summary() {
+        let arguments = json!({
+            "command": "curl -H 'Authorization: Bearer sk-ant-REDACTED' https://example.com",
+            "api_key": "hunter2",
+            "files": [{ "path": "big.txt", "content": "x".repeat(5000) }],
+            "token": "0x833589fcd6edb6e08f4c7c32d4f71b54bda02913",
+        });
+
+        let sanitized = sanitize_arguments(&arguments);
+        assert_eq!(sanitized["api_key"], "[REDACTED]");
+        assert!(!sanitized["command"].as_str().unwrap().contains("abcdefghijklmnop"));
+        assert_eq!(sanitized["files"][0]["content"].as_str().unwrap().chars().count(), MAX_ARGUMENT_CHARS + 3);
+        assert_eq!(sanitized["files"][0]["path"], "big.txt");
+        // A token address is not a credential
+        assert_eq!(sanitized["token"], arguments["token"]);
+
+        let summary = summarize_result(&format!("OPENAI_API_KEY=sk-proj-abcdefghijklmnopqrstuvwx {}", "y".repeat(900)));
+        assert!(!summary.contains("abcdefghijklmnop"));
+        assert!(summary.chars().count() < 520);
+    }
+}
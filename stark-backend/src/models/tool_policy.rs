@@ -0,0 +1,126 @@
+use std::collections::BTreeMap;
+use std::path::{Component, Path};
+
+use serde::{Deserialize, Serialize};
+
+use crate::tools::ToolGroup;
+
+/// Tools the agent may use on behalf of one API token or chat session
+///
+/// A policy only narrows what the channel's tool config and the token's
+/// scopes already allow; it is checked again by the registry before each call.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ToolPolicy {
+    /// Only these tools, plus the groups toggled on (both empty: every tool)
+    #[serde(default)]
+    pub allow: Vec<String>,
+    /// Never these tools, even when allowed
+    #[serde(default)]
+    pub deny: Vec<String>,
+    /// Group toggles: `false` switches a group off, `true` adds it to the allowlist
+    #[serde(default)]
+    pub groups: BTreeMap<String, bool>,
+    /// Workspace for file and exec tools instead of the session's own directory
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub workspace_root: Option<String>,
+}
+
+impl ToolPolicy {
+    /// Whether the policy lets the agent call `tool_name`. System tools pass
+    /// an allowlist, as the agent loop needs them, but can still be denied.
+    pub fn is_tool_allowed(&self, tool_name: &str, group: ToolGroup) -> bool {
+        if self.deny.iter().any(|t| t == tool_name) {
+            return false;
+        }
+        if self.allow.iter().any(|t| t == tool_name) {
+            return true;
+        }
+        match self.groups.get(group.as_str()) {
+            Some(enabled) => *enabled,
+            None => group == ToolGroup::System || !self.has_allowlist(),
+        }
+    }
+
+    fn has_allowlist(&self) -> bool {
+        !self.allow.is_empty() || self.groups.values().any(|enabled| *enabled)
+    }
+
+    /// Check the policy, with group aliases (`dev`, `fs`, ...) resolved to their names
+    pub fn normalized(mut self) -> Result<Self, String> {
+        let mut groups = BTreeMap::new();
+        for (name, enabled) in self.groups {
+            let group = ToolGroup::from_str(&name).ok_or_else(|| format!("Unknown tool group: {}", name))?;
+            groups.insert(group.as_str().to_string(), enabled);
+        }
+        self.groups = groups;
+
+        self.workspace_root = self.workspace_root.map(|root| root.trim().to_string()).filter(|root| !root.is_empty());
+        if let Some(root) = &self.workspace_root {
+            let path = Path::new(root);
+            if !path.is_absolute() || path.components().any(|c| c == Component::ParentDir) {
+                return Err(format!("workspace_root must be an absolute path without '..': {}", root));
+            }
+        }
+        Ok(self)
+    }
+}
+
+/// Request to set or clear the tool policy of a token or session
+#[derive(Debug, Clone, Deserialize)]
+pub struct SetToolPolicyRequest {
+    /// `null` removes the policy
+    pub tool_policy: Option<ToolPolicy>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_policy_narrows_tools() {
+        let open = ToolPolicy::default();
+        assert!(open.is_tool_allowed("exec", ToolGroup::Exec));
+
+        // Public deployment: chat and lookups only
+        let public: ToolPolicy = serde_json::from_value(serde_json::json!({
+            "allow": ["token_lookup"],
+            "deny": ["web_fetch"],
+            "groups": { "web": true, "memory": true, "exec": false }
+        }))
+        .unwrap();
+        assert!(public.is_tool_allowed("web_search", ToolGroup::Web));
+        assert!(!public.is_tool_allowed("web_fetch", ToolGroup::Web));
+        assert!(public.is_tool_allowed("token_lookup", ToolGroup::Finance));
+        assert!(!public.is_tool_allowed("swap", ToolGroup::Finance));
+        assert!(!public.is_tool_allowed("exec", ToolGroup::Exec));
+        assert!(!public.is_tool_allowed("git", ToolGroup::Development));
+        assert!(public.is_tool_allowed("ask_user", ToolGroup::System));
+
+        // A denylist alone leaves the other tools alone
+        let no_exec = ToolPolicy {
+            groups: BTreeMap::from([("exec".to_string(), false)]),
+            ..Default::default()
+        };
+        assert!(no_exec.is_tool_allowed("swap", ToolGroup::Finance));
+        assert!(!no_exec.is_tool_allowed("exec", ToolGroup::Exec));
+    }
+
+    #[test]
+    fn test_normalized() {
+        let policy = ToolPolicy {
+            groups: BTreeMap::from([("dev".to_string(), false)]),
+            workspace_root: Some(" /srv/public ".to_string()),
+            ..Default::default()
+        };
+        let policy = policy.normalized().unwrap();
+        assert_eq!(policy.groups.get("development"), Some(&false));
+        assert_eq!(policy.workspace_root.as_deref(), Some("/srv/public"));
+
+        let unknown = ToolPolicy { groups: BTreeMap::from([("nope".to_string(), true)]), ..Default::default() };
+        assert!(unknown.normalized().is_err());
+        let relative = ToolPolicy { workspace_root: Some("work".to_string()), ..Default::default() };
+        assert!(relative.normalized().is_err());
+        let escaping = ToolPolicy { workspace_root: Some("/srv/../etc".to_string()), ..Default::default() };
+        assert!(escaping.normalized().is_err());
+    }
+}
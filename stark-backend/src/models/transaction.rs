@@ -0,0 +1,142 @@
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+/// What an audited on-chain action was
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TransactionKind {
+    Swap,
+    /// Native or token transfer
+    Transfer,
+    /// Any other contract interaction (approvals, wraps, ...)
+    ContractCall,
+    X402Payment,
+}
+
+impl TransactionKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            TransactionKind::Swap => "swap",
+            TransactionKind::Transfer => "transfer",
+            TransactionKind::ContractCall => "contract_call",
+            TransactionKind::X402Payment => "x402_payment",
+        }
+    }
+
+    pub fn from_str(s: &str) -> Option<Self> {
+        match s {
+            "swap" => Some(TransactionKind::Swap),
+            "transfer" => Some(TransactionKind::Transfer),
+            "contract_call" => Some(TransactionKind::ContractCall),
+            "x402_payment" => Some(TransactionKind::X402Payment),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TransactionStatus {
+    /// Broadcast (or paid), no receipt yet
+    Pending,
+    Confirmed,
+    /// Mined but reverted
+    Reverted,
+    /// Never made it on-chain
+    Failed,
+}
+
+impl TransactionStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            TransactionStatus::Pending => "pending",
+            TransactionStatus::Confirmed => "confirmed",
+            TransactionStatus::Reverted => "reverted",
+            TransactionStatus::Failed => "failed",
+        }
+    }
+
+    pub fn from_str(s: &str) -> Option<Self> {
+        match s {
+            "pending" => Some(TransactionStatus::Pending),
+            "confirmed" => Some(TransactionStatus::Confirmed),
+            "reverted" => Some(TransactionStatus::Reverted),
+            "failed" => Some(TransactionStatus::Failed),
+            _ => None,
+        }
+    }
+}
+
+/// An audited on-chain action
+#[derive(Debug, Clone, Serialize)]
+pub struct Transaction {
+    pub id: i64,
+    pub kind: TransactionKind,
+    pub status: TransactionStatus,
+    pub network: String,
+    pub tx_hash: Option<String>,
+    pub from_address: Option<String>,
+    pub to_address: Option<String>,
+    pub value: String,
+    /// USD value when the spend was authorized
+    pub value_usd: Option<f64>,
+    pub gas_used: Option<String>,
+    pub block_number: Option<i64>,
+    pub channel_id: Option<i64>,
+    pub session_id: Option<i64>,
+    pub tool_name: Option<String>,
+    pub tool_call_id: Option<String>,
+    pub details: Option<Value>,
+    pub created_at: String,
+    pub updated_at: String,
+}
+
+/// A transaction to record
+#[derive(Debug, Clone)]
+pub struct NewTransaction {
+    pub kind: TransactionKind,
+    pub status: TransactionStatus,
+    pub network: String,
+    pub tx_hash: Option<String>,
+    pub from_address: Option<String>,
+    pub to_address: Option<String>,
+    pub value: String,
+    pub value_usd: Option<f64>,
+    pub channel_id: Option<i64>,
+    pub session_id: Option<i64>,
+    pub tool_name: Option<String>,
+    pub tool_call_id: Option<String>,
+    pub details: Option<Value>,
+}
+
+impl NewTransaction {
+    pub fn new(kind: TransactionKind, status: TransactionStatus, network: &str) -> Self {
+        Self {
+            kind,
+            status,
+            network: network.to_string(),
+            tx_hash: None,
+            from_address: None,
+            to_address: None,
+            value: "0".to_string(),
+            value_usd: None,
+            channel_id: None,
+            session_id: None,
+            tool_name: None,
+            tool_call_id: None,
+            details: None,
+        }
+    }
+}
+
+/// Filters for `GET /api/transactions`
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct TransactionQuery {
+    pub kind: Option<String>,
+    pub status: Option<String>,
+    pub network: Option<String>,
+    pub session_id: Option<i64>,
+    pub channel_id: Option<i64>,
+    pub limit: Option<i64>,
+    pub offset: Option<i64>,
+}
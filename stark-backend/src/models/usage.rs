@@ -0,0 +1,45 @@
+use serde::Serialize;
+
+use super::ApiKeyResponse;
+
+/// Summed token usage over a set of chat requests
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct UsageTotals {
+    pub requests: i64,
+    pub input_tokens: i64,
+    pub output_tokens: i64,
+    /// Prompt tokens served from the provider's prompt cache
+    pub cache_read_tokens: i64,
+    /// Prompt tokens written to the provider's prompt cache
+    pub cache_write_tokens: i64,
+    /// Input, output and cached prompt tokens
+    pub total_tokens: i64,
+    /// Estimated spend in USD, based on the configured per-token prices
+    pub cost_usd: f64,
+}
+
+/// Usage of one chat session
+#[derive(Debug, Clone, Serialize)]
+pub struct SessionUsage {
+    pub session_id: i64,
+    #[serde(flatten)]
+    pub totals: UsageTotals,
+    pub last_used_at: String,
+}
+
+/// Usage of one calendar day (UTC)
+#[derive(Debug, Clone, Serialize)]
+pub struct DailyUsage {
+    /// YYYY-MM-DD
+    pub day: String,
+    #[serde(flatten)]
+    pub totals: UsageTotals,
+}
+
+/// Usage of one of a user's own provider keys
+#[derive(Debug, Clone, Serialize)]
+pub struct ApiKeyUsage {
+    pub key: ApiKeyResponse,
+    #[serde(flatten)]
+    pub totals: UsageTotals,
+}
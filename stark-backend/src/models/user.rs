@@ -0,0 +1,134 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use super::{Scope, Scopes, ToolPolicy};
+
+/// What a user may do beyond their own conversations
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum UserRole {
+    /// Sees every conversation and manages settings, users and tokens
+    Admin,
+    /// Sees only their own conversations, workspaces and API keys
+    Member,
+}
+
+impl UserRole {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            UserRole::Admin => "admin",
+            UserRole::Member => "member",
+        }
+    }
+
+    pub fn from_str(s: &str) -> Option<Self> {
+        match s.trim().to_lowercase().as_str() {
+            "admin" => Some(UserRole::Admin),
+            "member" => Some(UserRole::Member),
+            _ => None,
+        }
+    }
+
+    /// Scopes of a wallet login with this role
+    pub fn login_scopes(&self) -> Scopes {
+        match self {
+            UserRole::Admin => Scopes::full(),
+            UserRole::Member => Scopes::new(Scope::all().iter().copied().filter(|s| *s != Scope::Admin)),
+        }
+    }
+}
+
+impl std::fmt::Display for UserRole {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// A person allowed to sign in, identified by their wallet address
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct User {
+    pub id: i64,
+    /// Lowercase 0x address
+    pub public_address: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    pub role: UserRole,
+    pub created_at: DateTime<Utc>,
+}
+
+/// Request to add a user
+#[derive(Debug, Clone, Deserialize)]
+pub struct CreateUserRequest {
+    pub public_address: String,
+    #[serde(default)]
+    pub name: Option<String>,
+    /// Defaults to `member`
+    #[serde(default)]
+    pub role: Option<UserRole>,
+}
+
+/// Request to change a user's role
+#[derive(Debug, Clone, Deserialize)]
+pub struct UpdateUserRequest {
+    pub role: UserRole,
+}
+
+/// Who sent an authenticated request: the user behind the session or API
+/// token, and what the token allows
+#[derive(Debug, Clone)]
+pub struct Caller {
+    pub user: User,
+    pub scopes: Scopes,
+    /// Tool policy of the API token (wallet login sessions have none)
+    pub tool_policy: Option<ToolPolicy>,
+}
+
+impl Caller {
+    pub fn is_admin(&self) -> bool {
+        self.user.role == UserRole::Admin
+    }
+
+    /// Whether data owned by `owner` is visible to this caller. Admins see
+    /// everything, including data without an owner (channel conversations).
+    pub fn can_access(&self, owner: Option<i64>) -> bool {
+        self.is_admin() || owner == Some(self.user.id)
+    }
+
+    /// The owner listings are limited to: none for admins, who see everything,
+    /// otherwise the caller
+    pub fn owner_filter(&self) -> Option<i64> {
+        (!self.is_admin()).then_some(self.user.id)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_roles_and_access() {
+        let user = |id, role| User {
+            id,
+            public_address: format!("0x{:040x}", id),
+            name: None,
+            role,
+            created_at: Utc::now(),
+        };
+        let caller = |user| Caller { user, scopes: Scopes::full(), tool_policy: None };
+
+        let member = caller(user(2, UserRole::Member));
+        assert!(member.can_access(Some(2)));
+        assert!(!member.can_access(Some(3)));
+        assert!(!member.can_access(None));
+        let admin = caller(user(1, UserRole::Admin));
+        assert!(admin.can_access(Some(2)) && admin.can_access(None));
+        assert_eq!(member.owner_filter(), Some(2));
+        assert_eq!(admin.owner_filter(), None);
+
+        let scopes = UserRole::Member.login_scopes();
+        assert!(scopes.allows(Scope::Chat) && scopes.allows(Scope::ToolsExec));
+        assert!(!scopes.allows(Scope::Admin));
+        assert!(UserRole::Admin.login_scopes().is_full());
+        assert_eq!(UserRole::from_str("Member"), Some(UserRole::Member));
+    }
+}
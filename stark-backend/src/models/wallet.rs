@@ -0,0 +1,73 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+/// Where a wallet's private key lives
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum WalletKind {
+    /// Key sealed in the database under the master key
+    Local,
+    /// Key held by an external JSON-RPC signer
+    External,
+}
+
+impl WalletKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            WalletKind::Local => "local",
+            WalletKind::External => "external",
+        }
+    }
+
+    pub fn from_str(s: &str) -> Option<Self> {
+        match s {
+            "local" => Some(WalletKind::Local),
+            "external" => Some(WalletKind::External),
+            _ => None,
+        }
+    }
+}
+
+/// A wallet the bot can sign with (never includes the private key)
+#[derive(Debug, Clone, Serialize)]
+pub struct Wallet {
+    pub id: i64,
+    pub name: String,
+    pub kind: WalletKind,
+    pub address: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub signer_url: Option<String>,
+    /// The default wallet signs x402 payments and transactions
+    pub is_default: bool,
+    pub created_at: DateTime<Utc>,
+}
+
+/// Request to add a wallet: import a key, generate one, or connect a signer
+#[derive(Debug, Clone, Deserialize)]
+pub struct CreateWalletRequest {
+    pub name: String,
+    /// Hex private key to import
+    #[serde(default)]
+    pub private_key: Option<String>,
+    /// Generate a new private key
+    #[serde(default)]
+    pub generate: bool,
+    /// JSON-RPC endpoint of an external signer
+    #[serde(default)]
+    pub signer_url: Option<String>,
+    /// Account to use on the external signer (defaults to its first account)
+    #[serde(default)]
+    pub address: Option<String>,
+    #[serde(default)]
+    pub make_default: bool,
+}
+
+/// Response for a newly added wallet
+#[derive(Debug, Clone, Serialize)]
+pub struct CreatedWallet {
+    #[serde(flatten)]
+    pub wallet: Wallet,
+    /// The generated private key, returned once so it can be backed up
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub private_key: Option<String>,
+}
@@ -0,0 +1,76 @@
+use serde::{Deserialize, Serialize};
+
+/// An inbound webhook: each signed POST to `/api/hooks/{hook_id}` queues an
+/// agent job with the payload rendered into `prompt_template`. The signing
+/// secret is never part of this struct; see `Database::get_webhook_secret`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Webhook {
+    pub id: i64,
+    /// Random public id used in the trigger URL
+    pub hook_id: String,
+    pub name: String,
+    /// Prompt for the job; `{{payload}}`, `{{event}}` and `{{hook}}` are substituted
+    pub prompt_template: String,
+    /// Tools the job may use; empty means the default CodeEngineer tools
+    pub tools: Vec<String>,
+    /// Workspace shared by every run triggered by this hook
+    pub workspace: String,
+    pub max_iterations: i64,
+    pub enabled: bool,
+    pub last_triggered_at: Option<String>,
+    /// Agent job queued by the most recent delivery
+    pub last_job_id: Option<String>,
+    pub created_at: String,
+    pub updated_at: String,
+    /// User who created the hook; its jobs run as them. None: admins only.
+    pub user_id: Option<i64>,
+}
+
+impl Webhook {
+    /// Workspace name used when a hook doesn't set one
+    pub fn default_workspace(hook_id: &str) -> String {
+        format!("hook-{}", hook_id)
+    }
+}
+
+fn default_enabled() -> bool {
+    true
+}
+
+/// Request to create a webhook
+#[derive(Debug, Clone, Deserialize)]
+pub struct CreateWebhookRequest {
+    pub name: String,
+    pub prompt_template: String,
+    /// Signing secret shared with the sender; a random one is generated when omitted
+    #[serde(default)]
+    pub secret: Option<String>,
+    #[serde(default)]
+    pub tools: Vec<String>,
+    #[serde(default)]
+    pub workspace: Option<String>,
+    #[serde(default)]
+    pub max_iterations: Option<usize>,
+    #[serde(default = "default_enabled")]
+    pub enabled: bool,
+}
+
+/// Request to update a webhook; omitted fields are left unchanged
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct UpdateWebhookRequest {
+    #[serde(default)]
+    pub name: Option<String>,
+    #[serde(default)]
+    pub prompt_template: Option<String>,
+    /// Replace the signing secret
+    #[serde(default)]
+    pub secret: Option<String>,
+    #[serde(default)]
+    pub tools: Option<Vec<String>>,
+    #[serde(default)]
+    pub workspace: Option<String>,
+    #[serde(default)]
+    pub max_iterations: Option<usize>,
+    #[serde(default)]
+    pub enabled: Option<bool>,
+}
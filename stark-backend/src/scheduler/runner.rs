@@ -4,6 +4,7 @@ use crate::db::Database;
 use crate::gateway::events::EventBroadcaster;
 use crate::gateway::protocol::GatewayEvent;
 use crate::models::{CronJob, HeartbeatConfig, JobStatus, ScheduleType};
+use crate::utils::truncate_str;
 use chrono::{DateTime, Duration, Local, NaiveTime, Utc, Weekday, Datelike};
 use std::sync::Arc;
 use tokio::sync::oneshot;
@@ -58,7 +59,7 @@ impl Scheduler {
 
     /// Start the scheduler background task
     pub async fn start(self: Arc<Self>, mut shutdown_rx: oneshot::Receiver<()>) {
-        log::info!(
+        tracing::info!(
             "Scheduler started (cron: {}, heartbeat: {}, poll: {}s)",
             self.config.cron_enabled,
             self.config.heartbeat_enabled,
@@ -70,7 +71,7 @@ impl Scheduler {
         loop {
             tokio::select! {
                 _ = &mut shutdown_rx => {
-                    log::info!("Scheduler received shutdown signal");
+                    tracing::info!("Scheduler received shutdown signal");
                     break;
                 }
                 _ = poll_interval.tick() => {
@@ -79,7 +80,7 @@ impl Scheduler {
             }
         }
 
-        log::info!("Scheduler stopped");
+        tracing::info!("Scheduler stopped");
     }
 
     /// Process one tick of the scheduler
@@ -87,14 +88,14 @@ impl Scheduler {
         // Process cron jobs
         if self.config.cron_enabled {
             if let Err(e) = self.process_cron_jobs().await {
-                log::error!("Error processing cron jobs: {}", e);
+                tracing::error!("Error processing cron jobs: {}", e);
             }
         }
 
         // Process heartbeats
         if self.config.heartbeat_enabled {
             if let Err(e) = self.process_heartbeats().await {
-                log::error!("Error processing heartbeats: {}", e);
+                tracing::error!("Error processing heartbeats: {}", e);
             }
         }
     }
@@ -103,14 +104,14 @@ impl Scheduler {
     async fn process_cron_jobs(&self) -> Result<(), String> {
         let due_jobs = self
             .db
-            .list_due_cron_jobs()
+            .list_due_cron_jobs().await
             .map_err(|e| format!("Failed to list due jobs: {}", e))?;
 
         for job in due_jobs {
             let scheduler = Arc::clone(&Arc::new(self.clone_inner()));
             tokio::spawn(async move {
                 if let Err(e) = scheduler.execute_cron_job(&job).await {
-                    log::error!("Cron job '{}' failed: {}", job.name, e);
+                    tracing::error!("Cron job '{}' failed: {}", job.name, e);
                 }
             });
         }
@@ -132,14 +133,14 @@ impl Scheduler {
         let started_at = Utc::now();
         let started_at_str = started_at.to_rfc3339();
 
-        log::info!("Executing cron job '{}' ({})", job.name, job.job_id);
+        tracing::info!("Executing cron job '{}' ({})", job.name, job.job_id);
 
         // IMPORTANT: Calculate and set next_run_at BEFORE execution to prevent race conditions
         // where the same job could be picked up twice if execution takes longer than poll interval
         let next_run = self.calculate_next_run(job);
         let next_run_str = next_run.map(|dt| dt.to_rfc3339());
-        if let Err(e) = self.db.mark_cron_job_started(job.id, next_run_str.as_deref()) {
-            log::error!("Failed to mark cron job as started: {}", e);
+        if let Err(e) = self.db.mark_cron_job_started(job.id, next_run_str.as_deref()).await {
+            tracing::error!("Failed to mark cron job as started: {}", e);
             // Continue anyway - the job should still run
         }
 
@@ -179,7 +180,7 @@ impl Scheduler {
             })
         };
 
-        log::info!(
+        tracing::info!(
             "Cron job '{}' using channel_id {} (session_mode: {})",
             job.name,
             cron_channel_id,
@@ -206,6 +207,15 @@ impl Scheduler {
             text: message_text,
             message_id: Some(format!("cron-run-{}", started_at.timestamp())),
             session_mode: Some(job.session_mode.clone()),
+            seed: None,
+            model_archetype: None,
+            model_overrides: None,
+            use_tools: None,
+            response_format: None,
+            images: Vec::new(),
+            scopes: None,
+            tool_policy: None,
+            owner_id: None,
         };
 
         // Execute the job
@@ -224,7 +234,7 @@ impl Scheduler {
                 next_run_str.as_deref(),
                 success,
                 result.error.as_deref(),
-            )
+            ).await
             .map_err(|e| format!("Failed to update job status: {}", e))?;
 
         // Log the run
@@ -236,12 +246,12 @@ impl Scheduler {
             Some(&result.response),
             result.error.as_deref(),
             Some(duration_ms),
-        );
+        ).await;
 
         // Handle delete_after_run for one-shot jobs
         if success && job.delete_after_run {
-            log::info!("Deleting one-shot cron job '{}' after successful run", job.name);
-            let _ = self.db.delete_cron_job(job.id);
+            tracing::info!("Deleting one-shot cron job '{}' after successful run", job.name);
+            let _ = self.db.delete_cron_job(job.id).await;
         }
 
         // Handle delivery if configured
@@ -269,7 +279,7 @@ impl Scheduler {
             ));
         }
 
-        log::info!(
+        tracing::info!(
             "Cron job '{}' completed in {}ms (success: {})",
             job.name,
             duration_ms,
@@ -306,16 +316,12 @@ impl Scheduler {
     async fn deliver_result(&self, job: &CronJob, response: &str) -> Result<(), String> {
         // For now, we just log that we would deliver
         // In a full implementation, this would send to the channel
-        log::info!(
+        tracing::info!(
             "Would deliver cron job '{}' result to channel {} (to: {:?}): {}",
             job.name,
             job.channel_id.unwrap_or(0),
             job.deliver_to,
-            if response.len() > 100 {
-                format!("{}...", &response[..100])
-            } else {
-                response.to_string()
-            }
+            truncate_str(response, 100)
         );
 
         // TODO: Implement actual channel delivery
@@ -328,7 +334,7 @@ impl Scheduler {
     async fn process_heartbeats(&self) -> Result<(), String> {
         let due_configs = self
             .db
-            .list_due_heartbeat_configs()
+            .list_due_heartbeat_configs().await
             .map_err(|e| format!("Failed to list due heartbeats: {}", e))?;
 
         for config in due_configs {
@@ -340,7 +346,7 @@ impl Scheduler {
             let scheduler = self.clone_inner();
             tokio::spawn(async move {
                 if let Err(e) = scheduler.execute_heartbeat(&config).await {
-                    log::error!("Heartbeat failed: {}", e);
+                    tracing::error!("Heartbeat failed: {}", e);
                 }
             });
         }
@@ -390,7 +396,7 @@ impl Scheduler {
         let now = Utc::now();
         let now_str = now.to_rfc3339();
 
-        log::info!("Executing heartbeat (config_id: {})", config.id);
+        tracing::info!("Executing heartbeat (config_id: {})", config.id);
 
         // Broadcast heartbeat start event
         self.broadcaster.broadcast(GatewayEvent::custom(
@@ -415,6 +421,15 @@ impl Scheduler {
             text: message_text,
             message_id: Some(format!("heartbeat-{}", now.timestamp())),
             session_mode: Some("isolated".to_string()),
+            seed: None,
+            model_archetype: None,
+            model_overrides: None,
+            use_tools: None,
+            response_format: None,
+            images: Vec::new(),
+            scopes: None,
+            tool_policy: None,
+            owner_id: None,
         };
 
         // Execute the heartbeat
@@ -426,12 +441,12 @@ impl Scheduler {
 
         // Update heartbeat status
         self.db
-            .update_heartbeat_last_beat(config.id, &now_str, &next_beat_str)
+            .update_heartbeat_last_beat(config.id, &now_str, &next_beat_str).await
             .map_err(|e| format!("Failed to update heartbeat status: {}", e))?;
 
         // Check for HEARTBEAT_OK suppression
         if result.response.contains("HEARTBEAT_OK") {
-            log::debug!("Heartbeat response contains HEARTBEAT_OK, suppressing output");
+            tracing::debug!("Heartbeat response contains HEARTBEAT_OK, suppressing output");
         }
 
         // Broadcast heartbeat completion event
@@ -444,7 +459,7 @@ impl Scheduler {
             }),
         ));
 
-        log::info!("Heartbeat completed (config_id: {})", config.id);
+        tracing::info!("Heartbeat completed (config_id: {})", config.id);
 
         Ok(())
     }
@@ -453,7 +468,7 @@ impl Scheduler {
     pub async fn run_job_now(&self, job_id: &str) -> Result<String, String> {
         let job = self
             .db
-            .get_cron_job_by_job_id(job_id)
+            .get_cron_job_by_job_id(job_id).await
             .map_err(|e| format!("Database error: {}", e))?
             .ok_or_else(|| format!("Job not found: {}", job_id))?;
 
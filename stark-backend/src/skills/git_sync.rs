@@ -0,0 +1,238 @@
+//! Skill marketplace sync from a git repository
+//!
+//! A fleet of instances can share a curated skill set by pointing
+//! `STARK_SKILLS_GIT_URL` at the same repository. Each sync clones (or pulls)
+//! it into `{skills_dir}/_marketplace`, validates every skill's frontmatter,
+//! imports the valid ones into the database and records the commit and skill
+//! versions in `skill_syncs`.
+//!
+//! Skills live in the repository's `skills/` directory if it has one, otherwise
+//! at its root, in the same layout as the local skills directory: `<name>/SKILL.md`
+//! or a single `<name>.md` with frontmatter.
+
+use std::path::{Path, PathBuf};
+use std::process::Stdio;
+
+use tokio::process::Command;
+use tokio::sync::Mutex;
+
+use crate::db::Database;
+use crate::models::{SkillSync, SyncedSkill};
+use crate::skills::loader::parse_skill_file;
+use crate::skills::registry::SkillRegistry;
+use crate::skills::types::{Skill, SkillSource};
+
+/// Checkout directory under the skills directory. The leading underscore keeps
+/// the local skill loader from picking it up a second time.
+pub const MARKETPLACE_DIR: &str = "_marketplace";
+
+/// Scheduled and API-triggered syncs share one checkout
+static SYNC_LOCK: Mutex<()> = Mutex::const_new(());
+
+/// Clone or pull `repo_url` at `branch`, import its skills and record the sync.
+///
+/// A git failure is recorded as a sync without a commit rather than returned as
+/// an error; `Err` means the sync couldn't be recorded at all.
+pub async fn sync_skills_from_git(
+    registry: &SkillRegistry,
+    db: &Database,
+    repo_url: &str,
+    branch: &str,
+) -> Result<SkillSync, String> {
+    let _guard = SYNC_LOCK.lock().await;
+    let checkout = PathBuf::from(crate::config::skills_dir()).join(MARKETPLACE_DIR);
+
+    let mut errors = Vec::new();
+    let mut synced = Vec::new();
+    let commit_sha = match checkout_repo(&checkout, repo_url, branch).await {
+        Ok(sha) => Some(sha),
+        Err(e) => {
+            errors.push(e);
+            None
+        }
+    };
+
+    if commit_sha.is_some() {
+        let root = if checkout.join("skills").is_dir() {
+            checkout.join("skills")
+        } else {
+            checkout.clone()
+        };
+        let (skills, invalid) = collect_skills(&root).await;
+        errors.extend(invalid);
+
+        for skill in skills {
+            match registry.import_file_skill(&skill).await {
+                Ok(()) => synced.push(SyncedSkill {
+                    name: skill.metadata.name.clone(),
+                    version: skill.metadata.version.clone(),
+                }),
+                Err(e) => errors.push(format!("{}: {}", skill.metadata.name, e)),
+            }
+        }
+    }
+
+    match &commit_sha {
+        Some(sha) => log::info!(
+            "Synced {} skill(s) from {}@{} ({}), {} error(s)",
+            synced.len(),
+            repo_url,
+            branch,
+            sha,
+            errors.len()
+        ),
+        None => log::warn!("Skill sync from {}@{} failed: {}", repo_url, branch, errors.join("; ")),
+    }
+
+    db.record_skill_sync(repo_url, branch, commit_sha.as_deref(), &synced, &errors)
+        .await
+        .map_err(|e| format!("Failed to record skill sync: {}", e))
+}
+
+/// Bring the checkout to the tip of `branch`, returning the commit it's at
+async fn checkout_repo(checkout: &Path, repo_url: &str, branch: &str) -> Result<String, String> {
+    if checkout.join(".git").is_dir() {
+        // The URL may have changed since the first clone
+        run_git(checkout, &["remote", "set-url", "origin", repo_url]).await?;
+        run_git(checkout, &["fetch", "--depth", "1", "origin", branch]).await?;
+        run_git(checkout, &["reset", "--hard", "FETCH_HEAD"]).await?;
+        run_git(checkout, &["clean", "-fdx"]).await?;
+    } else {
+        if checkout.exists() {
+            tokio::fs::remove_dir_all(checkout)
+                .await
+                .map_err(|e| format!("Failed to clear {}: {}", checkout.display(), e))?;
+        }
+        let parent = checkout.parent().unwrap_or(Path::new("."));
+        tokio::fs::create_dir_all(parent)
+            .await
+            .map_err(|e| format!("Failed to create {}: {}", parent.display(), e))?;
+        // Relative to `parent`, which is where git runs
+        let target = checkout.file_name().map(|n| n.to_string_lossy()).unwrap_or_default();
+        run_git(parent, &["clone", "--depth", "1", "--branch", branch, repo_url, &target]).await?;
+    }
+
+    run_git(checkout, &["rev-parse", "HEAD"]).await
+}
+
+async fn run_git(dir: &Path, args: &[&str]) -> Result<String, String> {
+    let output = Command::new("git")
+        .args(args)
+        .current_dir(dir)
+        // Fail instead of waiting on a credential prompt nobody will answer
+        .env("GIT_TERMINAL_PROMPT", "0")
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .output()
+        .await
+        .map_err(|e| format!("Failed to execute git: {}", e))?;
+
+    if output.status.success() {
+        Ok(String::from_utf8_lossy(&output.stdout).trim().to_string())
+    } else {
+        Err(format!(
+            "git {} failed: {}",
+            args.first().unwrap_or(&""),
+            String::from_utf8_lossy(&output.stderr).trim()
+        ))
+    }
+}
+
+/// Parse every skill under `root`, returning the valid skills and an error per invalid one.
+///
+/// Unlike the local loader, a `SKILL.md` that fails to parse is reported rather
+/// than logged, and so is a skill name used twice. Top-level `.md` files without
+/// frontmatter (a README) are not skills and are skipped.
+async fn collect_skills(root: &Path) -> (Vec<Skill>, Vec<String>) {
+    let mut skills: Vec<Skill> = Vec::new();
+    let mut errors = Vec::new();
+
+    let mut entries = match tokio::fs::read_dir(root).await {
+        Ok(entries) => entries,
+        Err(e) => return (skills, vec![format!("Failed to read {}: {}", root.display(), e)]),
+    };
+
+    let mut files = Vec::new();
+    while let Ok(Some(entry)) = entries.next_entry().await {
+        let path = entry.path();
+        let name = entry.file_name().to_string_lossy().to_string();
+        if name.starts_with('.') || name.starts_with('_') {
+            continue;
+        }
+        if path.is_dir() {
+            if name != "inactive" && name != "disabled" && path.join("SKILL.md").is_file() {
+                files.push((path.join("SKILL.md"), true));
+            }
+        } else if name.ends_with(".md") {
+            files.push((path, false));
+        }
+    }
+    // Directory order isn't stable; keep duplicate detection deterministic
+    files.sort();
+
+    for (path, is_skill_file) in files {
+        let relative = path.strip_prefix(root).unwrap_or(&path).display().to_string();
+        let content = match tokio::fs::read_to_string(&path).await {
+            Ok(content) => content,
+            Err(e) => {
+                errors.push(format!("{}: {}", relative, e));
+                continue;
+            }
+        };
+        if !is_skill_file && !content.trim_start().starts_with("---") {
+            continue;
+        }
+        match parse_skill_file(&content, &path.to_string_lossy(), SkillSource::Managed) {
+            Ok(skill) if skills.iter().any(|s| s.metadata.name == skill.metadata.name) => {
+                errors.push(format!("{}: duplicate skill name '{}'", relative, skill.metadata.name));
+            }
+            Ok(skill) => skills.push(skill),
+            Err(e) => errors.push(format!("{}: {}", relative, e)),
+        }
+    }
+
+    (skills, errors)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[tokio::test]
+    async fn test_collect_skills_validates_frontmatter() {
+        let dir = TempDir::new().unwrap();
+        let root = dir.path();
+        std::fs::write(root.join("README.md"), "# Curated skills\n").unwrap();
+        std::fs::write(
+            root.join("weather.md"),
+            "---\nname: weather\ndescription: Get the weather\nversion: 1.2.0\n---\nCheck the weather.",
+        )
+        .unwrap();
+        std::fs::create_dir(root.join("github")).unwrap();
+        std::fs::write(
+            root.join("github/SKILL.md"),
+            "---\nname: github\ndescription: Work with GitHub\n---\nUse gh.",
+        )
+        .unwrap();
+        std::fs::create_dir(root.join("broken")).unwrap();
+        std::fs::write(root.join("broken/SKILL.md"), "no frontmatter here").unwrap();
+        std::fs::create_dir(root.join("weather_copy")).unwrap();
+        std::fs::write(
+            root.join("weather_copy/SKILL.md"),
+            "---\nname: weather\ndescription: Same name\n---\nDuplicate.",
+        )
+        .unwrap();
+
+        let (skills, errors) = collect_skills(root).await;
+        let mut names: Vec<&str> = skills.iter().map(|s| s.metadata.name.as_str()).collect();
+        names.sort();
+        assert_eq!(names, vec!["github", "weather"]);
+        assert_eq!(skills.iter().find(|s| s.metadata.name == "weather").unwrap().metadata.version, "1.2.0");
+
+        assert_eq!(errors.len(), 2);
+        assert!(errors.iter().any(|e| e.starts_with("broken/SKILL.md")));
+        assert!(errors.iter().any(|e| e.contains("duplicate skill name 'weather'")));
+    }
+}
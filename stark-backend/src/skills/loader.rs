@@ -188,6 +188,9 @@ fn serde_yaml_parse(yaml: &str) -> Result<SkillMetadata, String> {
                             metadata.tags = parse_inline_list(value);
                         }
                     }
+                    "triggers" if value.starts_with('[') => {
+                        metadata.triggers = parse_inline_list(value);
+                    }
                     _ => {}
                 }
             }
@@ -199,6 +202,7 @@ fn serde_yaml_parse(yaml: &str) -> Result<SkillMetadata, String> {
                     "requires_tools" => metadata.requires_tools.push(unquote(value)),
                     "requires_binaries" => metadata.requires_binaries.push(unquote(value)),
                     "tags" => metadata.tags.push(unquote(value)),
+                    "triggers" => metadata.triggers.push(unquote(value)),
                     _ => {}
                 }
             } else if in_arguments {
@@ -1,8 +1,10 @@
+pub mod git_sync;
 pub mod loader;
 pub mod registry;
 pub mod types;
 pub mod zip_parser;
 
+pub use git_sync::sync_skills_from_git;
 pub use loader::{load_skill_from_file, load_skills_from_directory, parse_skill_file};
 pub use registry::{create_default_registry, SkillRegistry};
 pub use types::{DbSkill, DbSkillScript, InstalledSkill, Skill, SkillArgument, SkillMetadata, SkillSource};
@@ -40,16 +40,16 @@ impl SkillRegistry {
     }
 
     /// Get a skill by name
-    pub fn get(&self, name: &str) -> Option<Skill> {
-        match self.db.get_skill(name) {
+    pub async fn get(&self, name: &str) -> Option<Skill> {
+        match self.db.get_skill(name).await {
             Ok(Some(db_skill)) => Some(db_skill.into_skill()),
             _ => None,
         }
     }
 
     /// List all registered skills
-    pub fn list(&self) -> Vec<Skill> {
-        match self.db.list_skills() {
+    pub async fn list(&self) -> Vec<Skill> {
+        match self.db.list_skills().await {
             Ok(skills) => skills.into_iter().map(|s| s.into_skill()).collect(),
             Err(e) => {
                 log::error!("Failed to list skills: {}", e);
@@ -59,8 +59,8 @@ impl SkillRegistry {
     }
 
     /// List enabled skills
-    pub fn list_enabled(&self) -> Vec<Skill> {
-        match self.db.list_enabled_skills() {
+    pub async fn list_enabled(&self) -> Vec<Skill> {
+        match self.db.list_enabled_skills().await {
             Ok(skills) => skills.into_iter().map(|s| s.into_skill()).collect(),
             Err(e) => {
                 log::error!("Failed to list enabled skills: {}", e);
@@ -70,8 +70,8 @@ impl SkillRegistry {
     }
 
     /// Enable or disable a skill
-    pub fn set_enabled(&self, name: &str, enabled: bool) -> bool {
-        match self.db.set_skill_enabled(name, enabled) {
+    pub async fn set_enabled(&self, name: &str, enabled: bool) -> bool {
+        match self.db.set_skill_enabled(name, enabled).await {
             Ok(success) => success,
             Err(e) => {
                 log::error!("Failed to set skill enabled status: {}", e);
@@ -81,28 +81,28 @@ impl SkillRegistry {
     }
 
     /// Check if a skill exists
-    pub fn has_skill(&self, name: &str) -> bool {
-        self.get(name).is_some()
+    pub async fn has_skill(&self, name: &str) -> bool {
+        self.get(name).await.is_some()
     }
 
     /// Get count of registered skills
-    pub fn len(&self) -> usize {
-        self.list().len()
+    pub async fn len(&self) -> usize {
+        self.list().await.len()
     }
 
     /// Check if registry is empty
-    pub fn is_empty(&self) -> bool {
-        self.len() == 0
+    pub async fn is_empty(&self) -> bool {
+        self.len().await == 0
     }
 
     /// Create a skill from a parsed ZIP file
-    pub fn create_skill_from_zip(&self, data: &[u8]) -> Result<DbSkill, String> {
+    pub async fn create_skill_from_zip(&self, data: &[u8]) -> Result<DbSkill, String> {
         let parsed = parse_skill_zip(data)?;
-        self.create_skill_from_parsed(parsed)
+        self.create_skill_from_parsed(parsed).await
     }
 
     /// Create a skill from markdown content (SKILL.md format)
-    pub fn create_skill_from_markdown(&self, content: &str) -> Result<DbSkill, String> {
+    pub async fn create_skill_from_markdown(&self, content: &str) -> Result<DbSkill, String> {
         let (metadata, body) = parse_skill_md(content)?;
 
         let parsed = ParsedSkill {
@@ -117,14 +117,15 @@ impl SkillRegistry {
             requires_binaries: metadata.requires_binaries,
             arguments: metadata.arguments,
             tags: metadata.tags,
+            triggers: metadata.triggers,
             scripts: Vec::new(), // No scripts for plain markdown
         };
 
-        self.create_skill_from_parsed(parsed)
+        self.create_skill_from_parsed(parsed).await
     }
 
     /// Create a skill from parsed skill data
-    pub fn create_skill_from_parsed(&self, parsed: ParsedSkill) -> Result<DbSkill, String> {
+    pub async fn create_skill_from_parsed(&self, parsed: ParsedSkill) -> Result<DbSkill, String> {
         let now = chrono::Utc::now().to_rfc3339();
 
         let db_skill = DbSkill {
@@ -143,10 +144,11 @@ impl SkillRegistry {
             tags: parsed.tags,
             created_at: now.clone(),
             updated_at: now.clone(),
+            triggers: parsed.triggers,
         };
 
         // Insert skill into database
-        let skill_id = self.db.create_skill(&db_skill)
+        let skill_id = self.db.create_skill(&db_skill).await
             .map_err(|e| format!("Failed to create skill: {}", e))?;
 
         // Insert scripts
@@ -159,25 +161,25 @@ impl SkillRegistry {
                 language: script.language,
                 created_at: now.clone(),
             };
-            self.db.create_skill_script(&db_script)
+            self.db.create_skill_script(&db_script).await
                 .map_err(|e| format!("Failed to create skill script: {}", e))?;
         }
 
         // Return the created skill
-        self.db.get_skill(&parsed.name)
+        self.db.get_skill(&parsed.name).await
             .map_err(|e| format!("Failed to retrieve created skill: {}", e))?
             .ok_or_else(|| "Skill not found after creation".to_string())
     }
 
     /// Delete a skill and its scripts
-    pub fn delete_skill(&self, name: &str) -> Result<bool, String> {
-        self.db.delete_skill(name)
+    pub async fn delete_skill(&self, name: &str) -> Result<bool, String> {
+        self.db.delete_skill(name).await
             .map_err(|e| format!("Failed to delete skill: {}", e))
     }
 
     /// Get scripts for a skill
-    pub fn get_skill_scripts(&self, skill_name: &str) -> Vec<DbSkillScript> {
-        match self.db.get_skill_scripts_by_name(skill_name) {
+    pub async fn get_skill_scripts(&self, skill_name: &str) -> Vec<DbSkillScript> {
+        match self.db.get_skill_scripts_by_name(skill_name).await {
             Ok(scripts) => scripts,
             Err(e) => {
                 log::error!("Failed to get skill scripts: {}", e);
@@ -198,7 +200,7 @@ impl SkillRegistry {
             match load_skills_from_directory(path, SkillSource::Bundled).await {
                 Ok(skills) => {
                     for skill in skills {
-                        if let Err(e) = self.import_file_skill(&skill) {
+                        if let Err(e) = self.import_file_skill(&skill).await {
                             log::warn!("Failed to import bundled skill {}: {}", skill.metadata.name, e);
                         } else {
                             loaded += 1;
@@ -214,7 +216,7 @@ impl SkillRegistry {
             match load_skills_from_directory(path, SkillSource::Managed).await {
                 Ok(skills) => {
                     for skill in skills {
-                        if let Err(e) = self.import_file_skill(&skill) {
+                        if let Err(e) = self.import_file_skill(&skill).await {
                             log::warn!("Failed to import managed skill {}: {}", skill.metadata.name, e);
                         } else {
                             loaded += 1;
@@ -230,7 +232,7 @@ impl SkillRegistry {
             match load_skills_from_directory(path, SkillSource::Workspace).await {
                 Ok(skills) => {
                     for skill in skills {
-                        if let Err(e) = self.import_file_skill(&skill) {
+                        if let Err(e) = self.import_file_skill(&skill).await {
                             log::warn!("Failed to import workspace skill {}: {}", skill.metadata.name, e);
                         } else {
                             loaded += 1;
@@ -241,12 +243,12 @@ impl SkillRegistry {
             }
         }
 
-        log::info!("Loaded {} skills total ({} unique)", loaded, self.len());
+        log::info!("Loaded {} skills total ({} unique)", loaded, self.len().await);
         Ok(loaded)
     }
 
-    /// Import a file-based Skill into the database
-    fn import_file_skill(&self, skill: &Skill) -> Result<(), String> {
+    /// Import a file-based Skill into the database, replacing any skill with the same name
+    pub async fn import_file_skill(&self, skill: &Skill) -> Result<(), String> {
         let now = chrono::Utc::now().to_rfc3339();
 
         let db_skill = DbSkill {
@@ -265,9 +267,10 @@ impl SkillRegistry {
             tags: skill.metadata.tags.clone(),
             created_at: now.clone(),
             updated_at: now,
+            triggers: skill.metadata.triggers.clone(),
         };
 
-        self.db.create_skill(&db_skill)
+        self.db.create_skill(&db_skill).await
             .map_err(|e| format!("Failed to create skill in database: {}", e))?;
 
         Ok(())
@@ -281,8 +284,8 @@ impl SkillRegistry {
     }
 
     /// Get skills that require specific tools
-    pub fn get_skills_requiring_tools(&self, tool_names: &[String]) -> Vec<Skill> {
-        self.list()
+    pub async fn get_skills_requiring_tools(&self, tool_names: &[String]) -> Vec<Skill> {
+        self.list().await
             .into_iter()
             .filter(|s| {
                 s.metadata
@@ -294,9 +297,9 @@ impl SkillRegistry {
     }
 
     /// Search skills by name or tag
-    pub fn search(&self, query: &str) -> Vec<Skill> {
+    pub async fn search(&self, query: &str) -> Vec<Skill> {
         let query_lower = query.to_lowercase();
-        self.list()
+        self.list().await
             .into_iter()
             .filter(|s| {
                 s.metadata.name.to_lowercase().contains(&query_lower)
@@ -66,6 +66,9 @@ pub struct SkillMetadata {
     pub arguments: HashMap<String, SkillArgument>,
     #[serde(default)]
     pub tags: Vec<String>,
+    /// Phrases in a user message that suggest this skill applies
+    #[serde(default)]
+    pub triggers: Vec<String>,
     #[serde(default)]
     pub author: Option<String>,
     #[serde(default)]
@@ -88,6 +91,7 @@ impl Default for SkillMetadata {
             requires_binaries: vec![],
             arguments: HashMap::new(),
             tags: vec![],
+            triggers: vec![],
             author: None,
             homepage: None,
             metadata: None,
@@ -150,6 +154,15 @@ impl Skill {
         }
     }
 
+    /// Whether a message contains one of the skill's triggers (case-insensitive)
+    pub fn matches_trigger(&self, message: &str) -> bool {
+        let message = message.to_lowercase();
+        self.metadata
+            .triggers
+            .iter()
+            .any(|t| !t.trim().is_empty() && message.contains(&t.trim().to_lowercase()))
+    }
+
     /// Validate required arguments are provided
     pub fn validate_args(&self, args: &HashMap<String, String>) -> Result<(), Vec<String>> {
         let missing: Vec<String> = self
@@ -187,6 +200,7 @@ pub struct DbSkill {
     pub tags: Vec<String>,
     pub created_at: String,
     pub updated_at: String,
+    pub triggers: Vec<String>,
 }
 
 impl DbSkill {
@@ -201,6 +215,7 @@ impl DbSkill {
                 requires_binaries: self.requires_binaries,
                 arguments: self.arguments,
                 tags: self.tags,
+                triggers: self.triggers,
                 author: self.author,
                 homepage: self.homepage,
                 metadata: self.metadata,
@@ -275,6 +290,25 @@ mod tests {
         assert_eq!(skill.render_prompt(&empty_args), "Review code at .");
     }
 
+    #[test]
+    fn test_matches_trigger() {
+        let skill = Skill {
+            metadata: SkillMetadata {
+                name: "swap".to_string(),
+                description: "Swap tokens".to_string(),
+                triggers: vec!["swap".to_string(), "trade for".to_string()],
+                ..Default::default()
+            },
+            prompt_template: String::new(),
+            source: SkillSource::Managed,
+            path: String::new(),
+            enabled: true,
+        };
+        assert!(skill.matches_trigger("Can you SWAP my usdc?"));
+        assert!(skill.matches_trigger("trade for some eth"));
+        assert!(!skill.matches_trigger("what's the weather"));
+    }
+
     #[test]
     fn test_skill_source_priority() {
         assert!(SkillSource::Workspace.priority() > SkillSource::Managed.priority());
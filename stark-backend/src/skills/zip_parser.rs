@@ -17,6 +17,7 @@ pub struct ParsedSkill {
     pub requires_binaries: Vec<String>,
     pub arguments: HashMap<String, SkillArgument>,
     pub tags: Vec<String>,
+    pub triggers: Vec<String>,
     pub scripts: Vec<ParsedScript>,
 }
 
@@ -144,6 +145,7 @@ pub fn parse_skill_zip(data: &[u8]) -> Result<ParsedSkill, String> {
         requires_binaries: metadata.requires_binaries,
         arguments: metadata.arguments,
         tags: metadata.tags,
+        triggers: metadata.triggers,
         scripts,
     })
 }
@@ -239,6 +241,9 @@ fn parse_yaml_frontmatter(yaml: &str) -> Result<SkillMetadata, String> {
                             metadata.tags = parse_inline_list(value);
                         }
                     }
+                    "triggers" if value.starts_with('[') => {
+                        metadata.triggers = parse_inline_list(value);
+                    }
                     _ => {}
                 }
             }
@@ -250,6 +255,7 @@ fn parse_yaml_frontmatter(yaml: &str) -> Result<SkillMetadata, String> {
                     "requires_tools" => metadata.requires_tools.push(unquote(value)),
                     "requires_binaries" => metadata.requires_binaries.push(unquote(value)),
                     "tags" => metadata.tags.push(unquote(value)),
+                    "triggers" => metadata.triggers.push(unquote(value)),
                     _ => {}
                 }
             } else if in_arguments {
@@ -324,6 +330,9 @@ name: code-review
 description: Review code and provide feedback
 version: 1.0.0
 requires_tools: [read_file, exec]
+triggers:
+  - review
+  - "look over"
 arguments:
   path:
     description: "Path to review"
@@ -337,6 +346,7 @@ You are a code reviewer. Review the code at {{path}} and provide feedback.
         assert_eq!(metadata.description, "Review code and provide feedback");
         assert_eq!(metadata.version, "1.0.0");
         assert_eq!(metadata.requires_tools, vec!["read_file", "exec"]);
+        assert_eq!(metadata.triggers, vec!["review", "look over"]);
         assert!(metadata.arguments.contains_key("path"));
         assert!(body.contains("You are a code reviewer"));
     }
@@ -0,0 +1,387 @@
+//! Tracing for agent runs
+//!
+//! Every HTTP request, background job and channel message runs in a root span
+//! carrying a request ID, and the spans opened under it (the agent loop, tool
+//! executions, provider calls) share that ID. Events recorded with `tracing`
+//! go to the regular logger prefixed with the ID, so the log lines of one run
+//! can be grepped together. When an OTLP endpoint is configured, finished spans
+//! are also exported (see [`otlp`]) so a run can be inspected in Jaeger.
+
+pub mod otlp;
+
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::SystemTime;
+
+use tokio::sync::mpsc::UnboundedSender;
+use tracing::field::{Field, Visit};
+use tracing::span::{Attributes, Id, Record};
+use tracing::{Event, Level, Metadata, Subscriber};
+
+use crate::config;
+use otlp::{FieldValue, FinishedSpan, SpanEvent};
+
+/// Field a root span sets to choose its request ID; one is generated otherwise
+pub const REQUEST_ID_FIELD: &str = "request_id";
+
+/// Most events kept per exported span; later ones are only logged
+const MAX_EVENTS_PER_SPAN: usize = 128;
+
+/// Spans are only tracked for this crate, not for the libraries it uses
+const CRATE_TARGET: &str = env!("CARGO_CRATE_NAME");
+
+thread_local! {
+    /// Spans entered on this thread, innermost last
+    static CURRENT: RefCell<Vec<u64>> = const { RefCell::new(Vec::new()) };
+}
+
+/// Install the subscriber, exporting spans if an OTLP endpoint is configured.
+/// Must run inside the Tokio runtime, after the logger is initialized.
+pub fn init() {
+    let exporter = config::otlp_traces_endpoint().map(|endpoint| {
+        log::info!("Exporting traces to {}", endpoint);
+        otlp::spawn_exporter(endpoint, config::otel_service_name())
+    });
+    if tracing::subscriber::set_global_default(Telemetry::new(exporter)).is_err() {
+        log::warn!("A tracing subscriber was already installed; request IDs are not tracked");
+    }
+}
+
+/// A new request ID; 32 hex characters, so it doubles as the OTLP trace ID
+pub fn new_request_id() -> String {
+    uuid::Uuid::new_v4().simple().to_string()
+}
+
+/// Request ID of the innermost span entered on this thread, if any
+pub fn current_request_id() -> Option<String> {
+    let id = Telemetry::current()?;
+    tracing::dispatcher::get_default(|dispatch| {
+        let telemetry = dispatch.downcast_ref::<Telemetry>()?;
+        telemetry.spans().get(&id).map(|span| span.request_id.clone())
+    })
+}
+
+/// Whether a client-supplied request ID is safe to adopt (and echo back)
+pub fn is_valid_request_id(id: &str) -> bool {
+    !id.is_empty()
+        && id.len() <= 128
+        && id.chars().all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.'))
+}
+
+struct SpanData {
+    metadata: &'static Metadata<'static>,
+    request_id: String,
+    trace_id: [u8; 16],
+    span_id: [u8; 8],
+    parent_span_id: Option<[u8; 8]>,
+    fields: Fields,
+    events: Vec<SpanEvent>,
+    start: SystemTime,
+    refs: usize,
+}
+
+/// Tracks open spans, logs events and hands finished spans to the exporter.
+///
+/// New spans and events find their parent through the spans entered on the
+/// current thread; `Span::current()` is not supported.
+pub struct Telemetry {
+    next_id: AtomicU64,
+    spans: Mutex<HashMap<u64, SpanData>>,
+    exporter: Option<UnboundedSender<FinishedSpan>>,
+}
+
+impl Telemetry {
+    pub fn new(exporter: Option<UnboundedSender<FinishedSpan>>) -> Self {
+        Telemetry {
+            next_id: AtomicU64::new(1),
+            spans: Mutex::new(HashMap::new()),
+            exporter,
+        }
+    }
+
+    fn spans(&self) -> std::sync::MutexGuard<'_, HashMap<u64, SpanData>> {
+        self.spans.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    fn current() -> Option<u64> {
+        CURRENT.with(|stack| stack.borrow().last().copied())
+    }
+}
+
+impl Subscriber for Telemetry {
+    fn enabled(&self, metadata: &Metadata<'_>) -> bool {
+        if metadata.is_span() {
+            return metadata.target().split("::").next() == Some(CRATE_TARGET);
+        }
+        // Exported spans keep this crate's events even when they aren't logged
+        logged(metadata) || (self.exporter.is_some() && metadata.target().split("::").next() == Some(CRATE_TARGET))
+    }
+
+    fn new_span(&self, attrs: &Attributes<'_>) -> Id {
+        let parent = match attrs.parent() {
+            Some(parent) => Some(parent.into_u64()),
+            None if attrs.is_contextual() => Self::current(),
+            None => None,
+        };
+        let mut fields = Fields::default();
+        attrs.record(&mut fields);
+        let requested_id = fields.take(REQUEST_ID_FIELD).map(|value| value.to_string());
+
+        let mut spans = self.spans();
+        let parent = parent.and_then(|id| spans.get(&id));
+        let request_id = requested_id
+            .or_else(|| parent.map(|p| p.request_id.clone()))
+            .unwrap_or_else(new_request_id);
+        let trace_id = match parent {
+            Some(p) => p.trace_id,
+            None => trace_id_for(&request_id),
+        };
+        let data = SpanData {
+            metadata: attrs.metadata(),
+            trace_id,
+            parent_span_id: parent.map(|p| p.span_id),
+            span_id: rand::random(),
+            request_id,
+            fields,
+            events: Vec::new(),
+            start: SystemTime::now(),
+            refs: 1,
+        };
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed);
+        spans.insert(id, data);
+        Id::from_u64(id)
+    }
+
+    fn record(&self, span: &Id, values: &Record<'_>) {
+        if let Some(data) = self.spans().get_mut(&span.into_u64()) {
+            values.record(&mut data.fields);
+        }
+    }
+
+    fn record_follows_from(&self, _span: &Id, _follows: &Id) {}
+
+    fn event(&self, event: &Event<'_>) {
+        let span = match event.parent() {
+            Some(parent) => Some(parent.into_u64()),
+            None if event.is_contextual() => Self::current(),
+            None => None,
+        };
+        let mut fields = Fields::default();
+        event.record(&mut fields);
+        let message = fields.take("message").map(|value| value.to_string()).unwrap_or_default();
+        let metadata = event.metadata();
+
+        let request_id = span.and_then(|id| {
+            let mut spans = self.spans();
+            let data = spans.get_mut(&id)?;
+            if self.exporter.is_some() && data.events.len() < MAX_EVENTS_PER_SPAN {
+                data.events.push(SpanEvent {
+                    time: SystemTime::now(),
+                    level: metadata.level().as_str(),
+                    message: message.clone(),
+                    fields: fields.0.clone(),
+                });
+            }
+            Some(data.request_id.clone())
+        });
+
+        if !logged(metadata) {
+            return;
+        }
+        let prefix = request_id.map(|id| format!("[{}] ", id)).unwrap_or_default();
+        log::logger().log(
+            &log::Record::builder()
+                .level(log_level(metadata.level()))
+                .target(metadata.target())
+                .module_path(metadata.module_path())
+                .file(metadata.file())
+                .line(metadata.line())
+                .args(format_args!("{}{}{}", prefix, message, fields))
+                .build(),
+        );
+    }
+
+    fn enter(&self, span: &Id) {
+        CURRENT.with(|stack| stack.borrow_mut().push(span.into_u64()));
+    }
+
+    fn exit(&self, span: &Id) {
+        let id = span.into_u64();
+        CURRENT.with(|stack| {
+            let mut stack = stack.borrow_mut();
+            if let Some(pos) = stack.iter().rposition(|&entered| entered == id) {
+                stack.remove(pos);
+            }
+        });
+    }
+
+    fn clone_span(&self, span: &Id) -> Id {
+        if let Some(data) = self.spans().get_mut(&span.into_u64()) {
+            data.refs += 1;
+        }
+        span.clone()
+    }
+
+    fn try_close(&self, span: Id) -> bool {
+        let mut spans = self.spans();
+        let id = span.into_u64();
+        let Some(data) = spans.get_mut(&id) else {
+            return false;
+        };
+        data.refs -= 1;
+        if data.refs > 0 {
+            return false;
+        }
+        let data = spans.remove(&id).expect("span is present");
+        drop(spans);
+
+        if let Some(exporter) = &self.exporter {
+            // The exporter only goes away at shutdown
+            let _ = exporter.send(FinishedSpan {
+                name: data.metadata.name(),
+                target: data.metadata.target(),
+                request_id: data.request_id,
+                trace_id: data.trace_id,
+                span_id: data.span_id,
+                parent_span_id: data.parent_span_id,
+                start: data.start,
+                end: SystemTime::now(),
+                fields: data.fields.0,
+                events: data.events,
+            });
+        }
+        true
+    }
+}
+
+/// A request ID that is a UUID (such as a job ID) is used as the trace ID, so
+/// either can be searched for; others get a random trace ID
+fn trace_id_for(request_id: &str) -> [u8; 16] {
+    let mut trace_id = [0u8; 16];
+    match hex::decode(request_id.replace('-', "")) {
+        Ok(bytes) if bytes.len() == 16 => trace_id.copy_from_slice(&bytes),
+        _ => trace_id = rand::random(),
+    }
+    trace_id
+}
+
+/// Whether the logger would take an event
+fn logged(metadata: &Metadata<'_>) -> bool {
+    let level = log_level(metadata.level());
+    level <= log::max_level()
+        && log::logger().enabled(&log::Metadata::builder().level(level).target(metadata.target()).build())
+}
+
+fn log_level(level: &Level) -> log::Level {
+    match *level {
+        Level::ERROR => log::Level::Error,
+        Level::WARN => log::Level::Warn,
+        Level::INFO => log::Level::Info,
+        Level::DEBUG => log::Level::Debug,
+        _ => log::Level::Trace,
+    }
+}
+
+/// Field values recorded on a span or event, in the order first recorded
+#[derive(Debug, Default)]
+struct Fields(Vec<(&'static str, FieldValue)>);
+
+impl Fields {
+    fn set(&mut self, name: &'static str, value: FieldValue) {
+        match self.0.iter_mut().find(|(existing, _)| *existing == name) {
+            Some((_, existing)) => *existing = value,
+            None => self.0.push((name, value)),
+        }
+    }
+
+    fn take(&mut self, name: &str) -> Option<FieldValue> {
+        let pos = self.0.iter().position(|(existing, _)| *existing == name)?;
+        Some(self.0.remove(pos).1)
+    }
+}
+
+impl Visit for Fields {
+    fn record_debug(&mut self, field: &Field, value: &dyn fmt::Debug) {
+        self.set(field.name(), FieldValue::Str(format!("{:?}", value)));
+    }
+
+    fn record_str(&mut self, field: &Field, value: &str) {
+        self.set(field.name(), FieldValue::Str(value.to_string()));
+    }
+
+    fn record_i64(&mut self, field: &Field, value: i64) {
+        self.set(field.name(), FieldValue::Int(value));
+    }
+
+    fn record_u64(&mut self, field: &Field, value: u64) {
+        self.set(field.name(), FieldValue::Int(i64::try_from(value).unwrap_or(i64::MAX)));
+    }
+
+    fn record_f64(&mut self, field: &Field, value: f64) {
+        self.set(field.name(), FieldValue::Float(value));
+    }
+
+    fn record_bool(&mut self, field: &Field, value: bool) {
+        self.set(field.name(), FieldValue::Bool(value));
+    }
+}
+
+/// ` key=value` for each field, after an event's message
+impl fmt::Display for Fields {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (name, value) in &self.0 {
+            write!(f, " {}={}", name, value)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tracing::field::Empty;
+
+    #[test]
+    fn test_spans_share_request_id_and_trace() {
+        let (tx, mut rx) = tokio::sync::mpsc::unbounded_channel();
+        tracing::subscriber::with_default(Telemetry::new(Some(tx)), || {
+            let request_id = "0123456789abcdef0123456789abcdef";
+            let request = tracing::info_span!("http_request", request_id = request_id, path = "/api/chat");
+            let _entered = request.enter();
+            let tool = tracing::info_span!("tool", tool = "exec", error = Empty);
+            tool.in_scope(|| tracing::warn!(code = 1, "exit status {}", 1));
+            tool.record("error", "exit status 1");
+            drop(tool);
+
+            // A span started outside any other begins its own run
+            let job = tracing::info_span!(parent: None, "agent_job");
+            drop(job);
+        });
+
+        let tool = rx.try_recv().unwrap();
+        let job = rx.try_recv().unwrap();
+        let request = rx.try_recv().unwrap();
+        assert!(rx.try_recv().is_err());
+
+        assert_eq!(request.name, "http_request");
+        assert_eq!(request.request_id, "0123456789abcdef0123456789abcdef");
+        assert_eq!(hex::encode(request.trace_id), request.request_id);
+        assert_eq!(request.parent_span_id, None);
+        assert_eq!(request.fields, vec![("path", FieldValue::Str("/api/chat".to_string()))]);
+
+        assert_eq!(tool.request_id, request.request_id);
+        assert_eq!(tool.trace_id, request.trace_id);
+        assert_eq!(tool.parent_span_id, Some(request.span_id));
+        assert_eq!(tool.fields[1], ("error", FieldValue::Str("exit status 1".to_string())));
+        assert_eq!(tool.events.len(), 1);
+        assert_eq!(tool.events[0].message, "exit status 1");
+        assert_eq!(tool.events[0].fields, vec![("code", FieldValue::Int(1))]);
+
+        assert_ne!(job.request_id, request.request_id);
+        assert_eq!(job.request_id.len(), 32);
+        assert_eq!(job.parent_span_id, None);
+    }
+}
@@ -0,0 +1,258 @@
+//! Trace export over OTLP/HTTP with the JSON encoding
+//!
+//! Finished spans are batched and posted to the collector's `/v1/traces`
+//! (Jaeger accepts them on port 4318). Spans that can't be delivered are
+//! dropped rather than queued, so a missing collector costs nothing but the
+//! traces.
+
+use std::fmt;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use serde_json::{json, Value};
+use tokio::sync::mpsc::{self, UnboundedReceiver, UnboundedSender};
+
+use crate::controllers::health::VERSION;
+
+/// How often queued spans are sent
+const FLUSH_INTERVAL: Duration = Duration::from_secs(5);
+
+/// Most spans sent in one request
+const MAX_BATCH: usize = 512;
+
+const EXPORT_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// OTLP span kind for work inside the service
+const SPAN_KIND_INTERNAL: u8 = 1;
+const STATUS_ERROR: u8 = 2;
+
+/// A value recorded on a span or event
+#[derive(Debug, Clone, PartialEq)]
+pub enum FieldValue {
+    Str(String),
+    Int(i64),
+    Float(f64),
+    Bool(bool),
+}
+
+impl FieldValue {
+    fn to_otlp(&self) -> Value {
+        match self {
+            // 64-bit integers are strings in OTLP/JSON
+            FieldValue::Str(s) => json!({ "stringValue": s }),
+            FieldValue::Int(i) => json!({ "intValue": i.to_string() }),
+            FieldValue::Float(f) => json!({ "doubleValue": f }),
+            FieldValue::Bool(b) => json!({ "boolValue": b }),
+        }
+    }
+}
+
+impl fmt::Display for FieldValue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FieldValue::Str(s) => f.write_str(s),
+            FieldValue::Int(i) => write!(f, "{}", i),
+            FieldValue::Float(x) => write!(f, "{}", x),
+            FieldValue::Bool(b) => write!(f, "{}", b),
+        }
+    }
+}
+
+/// An event recorded while a span was open
+#[derive(Debug, Clone)]
+pub struct SpanEvent {
+    pub time: SystemTime,
+    pub level: &'static str,
+    pub message: String,
+    pub fields: Vec<(&'static str, FieldValue)>,
+}
+
+/// A closed span, ready to export
+#[derive(Debug)]
+pub struct FinishedSpan {
+    pub name: &'static str,
+    pub target: &'static str,
+    pub request_id: String,
+    pub trace_id: [u8; 16],
+    pub span_id: [u8; 8],
+    pub parent_span_id: Option<[u8; 8]>,
+    pub start: SystemTime,
+    pub end: SystemTime,
+    pub fields: Vec<(&'static str, FieldValue)>,
+    pub events: Vec<SpanEvent>,
+}
+
+impl FinishedSpan {
+    /// The span as an OTLP/JSON `Span`. An `error` field marks it failed.
+    fn to_otlp(&self) -> Value {
+        let mut attributes = vec![
+            attribute("request_id", &FieldValue::Str(self.request_id.clone())),
+            attribute("code.namespace", &FieldValue::Str(self.target.to_string())),
+        ];
+        attributes.extend(self.fields.iter().map(|(name, value)| attribute(name, value)));
+
+        let events: Vec<Value> = self
+            .events
+            .iter()
+            .map(|event| {
+                let mut attributes = vec![attribute("level", &FieldValue::Str(event.level.to_string()))];
+                attributes.extend(event.fields.iter().map(|(name, value)| attribute(name, value)));
+                json!({
+                    "timeUnixNano": unix_nanos(event.time),
+                    "name": event.message,
+                    "attributes": attributes,
+                })
+            })
+            .collect();
+
+        let status = match self.fields.iter().find(|(name, _)| *name == "error") {
+            Some((_, error)) => json!({ "code": STATUS_ERROR, "message": error.to_string() }),
+            None => json!({}),
+        };
+
+        json!({
+            "traceId": hex::encode(self.trace_id),
+            "spanId": hex::encode(self.span_id),
+            "parentSpanId": self.parent_span_id.map(hex::encode).unwrap_or_default(),
+            "name": self.name,
+            "kind": SPAN_KIND_INTERNAL,
+            "startTimeUnixNano": unix_nanos(self.start),
+            "endTimeUnixNano": unix_nanos(self.end),
+            "attributes": attributes,
+            "events": events,
+            "status": status,
+        })
+    }
+}
+
+/// The body of an export request for a batch of spans
+fn export_request(service_name: &str, spans: &[FinishedSpan]) -> Value {
+    json!({
+        "resourceSpans": [{
+            "resource": {
+                "attributes": [
+                    attribute("service.name", &FieldValue::Str(service_name.to_string())),
+                    attribute("service.version", &FieldValue::Str(VERSION.to_string())),
+                ]
+            },
+            "scopeSpans": [{
+                "scope": { "name": env!("CARGO_CRATE_NAME"), "version": VERSION },
+                "spans": spans.iter().map(FinishedSpan::to_otlp).collect::<Vec<_>>(),
+            }]
+        }]
+    })
+}
+
+fn attribute(key: &str, value: &FieldValue) -> Value {
+    json!({ "key": key, "value": value.to_otlp() })
+}
+
+fn unix_nanos(time: SystemTime) -> String {
+    time.duration_since(UNIX_EPOCH).unwrap_or_default().as_nanos().to_string()
+}
+
+/// Start the task that posts finished spans to `endpoint`
+pub fn spawn_exporter(endpoint: String, service_name: String) -> UnboundedSender<FinishedSpan> {
+    let (tx, rx) = mpsc::unbounded_channel();
+    tokio::spawn(run_exporter(endpoint, service_name, rx));
+    tx
+}
+
+async fn run_exporter(endpoint: String, service_name: String, mut rx: UnboundedReceiver<FinishedSpan>) {
+    let client = reqwest::Client::builder()
+        .timeout(EXPORT_TIMEOUT)
+        .build()
+        .unwrap_or_default();
+    let mut interval = tokio::time::interval(FLUSH_INTERVAL);
+    // Logged once per outage rather than every flush
+    let mut failing = false;
+
+    loop {
+        interval.tick().await;
+        loop {
+            let mut batch = Vec::new();
+            let mut closed = false;
+            while batch.len() < MAX_BATCH {
+                match rx.try_recv() {
+                    Ok(span) => batch.push(span),
+                    Err(mpsc::error::TryRecvError::Empty) => break,
+                    Err(mpsc::error::TryRecvError::Disconnected) => {
+                        closed = true;
+                        break;
+                    }
+                }
+            }
+            if !batch.is_empty() {
+                let result = client
+                    .post(&endpoint)
+                    .json(&export_request(&service_name, &batch))
+                    .send()
+                    .await
+                    .and_then(|response| response.error_for_status());
+                match result {
+                    Ok(_) if failing => {
+                        log::info!("Trace export to {} recovered", endpoint);
+                        failing = false;
+                    }
+                    Ok(_) => {}
+                    Err(e) if !failing => {
+                        log::warn!("Trace export to {} failed, dropping spans until it recovers: {}", endpoint, e);
+                        failing = true;
+                    }
+                    Err(_) => {}
+                }
+            }
+            if closed {
+                return;
+            }
+            if batch.len() < MAX_BATCH {
+                break;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_export_request_encoding() {
+        let start = UNIX_EPOCH + Duration::from_millis(1_500);
+        let span = FinishedSpan {
+            name: "tool",
+            target: "stark_backend::tools::registry",
+            request_id: "req-1".to_string(),
+            trace_id: [0xab; 16],
+            span_id: [0x01; 8],
+            parent_span_id: Some([0x02; 8]),
+            start,
+            end: start + Duration::from_millis(250),
+            fields: vec![
+                ("tool", FieldValue::Str("exec".to_string())),
+                ("error", FieldValue::Str("exit status 1".to_string())),
+            ],
+            events: vec![SpanEvent {
+                time: start,
+                level: "WARN",
+                message: "retrying".to_string(),
+                fields: vec![("attempt", FieldValue::Int(2))],
+            }],
+        };
+
+        let body = export_request("stark-test", &[span]);
+        let resource = &body["resourceSpans"][0];
+        assert_eq!(resource["resource"]["attributes"][0]["value"]["stringValue"], "stark-test");
+
+        let span = &resource["scopeSpans"][0]["spans"][0];
+        assert_eq!(span["traceId"], "ab".repeat(16));
+        assert_eq!(span["spanId"], "0101010101010101");
+        assert_eq!(span["parentSpanId"], "0202020202020202");
+        assert_eq!(span["startTimeUnixNano"], "1500000000");
+        assert_eq!(span["endTimeUnixNano"], "1750000000");
+        assert_eq!(span["attributes"][0], json!({ "key": "request_id", "value": { "stringValue": "req-1" } }));
+        assert_eq!(span["attributes"][2]["key"], "tool");
+        assert_eq!(span["status"], json!({ "code": 2, "message": "exit status 1" }));
+        assert_eq!(span["events"][0]["name"], "retrying");
+        assert_eq!(span["events"][0]["attributes"][1], json!({ "key": "attempt", "value": { "intValue": "2" } }));
+    }
+}
@@ -2,6 +2,7 @@ use crate::tools::registry::Tool;
 use crate::tools::types::{
     PropertySchema, ToolContext, ToolDefinition, ToolGroup, ToolInputSchema, ToolResult,
 };
+use crate::tools::workspace_path::WorkspacePath;
 use async_trait::async_trait;
 use serde::Deserialize;
 use serde_json::{json, Value};
@@ -366,37 +367,8 @@ fn apply_at_position(content: &str, hunk: &Hunk, match_start: usize) -> Result<S
     Ok(result.join("\n"))
 }
 
-fn resolve_and_validate_path(
-    requested_path: &str,
-    workspace: &Path,
-    canonical_workspace: &Path,
-) -> Result<PathBuf, String> {
-    let path = Path::new(requested_path);
-    let full_path = if path.is_absolute() {
-        path.to_path_buf()
-    } else {
-        workspace.join(path)
-    };
-
-    // For paths that don't exist yet, validate the parent
-    let check_path = if full_path.exists() {
-        full_path.clone()
-    } else {
-        full_path.parent().map(|p| p.to_path_buf()).unwrap_or(full_path.clone())
-    };
-
-    // Try to canonicalize (may fail if path doesn't exist)
-    if check_path.exists() {
-        let canonical = check_path.canonicalize().map_err(|e| e.to_string())?;
-        if !canonical.starts_with(canonical_workspace) {
-            return Err(format!(
-                "Access denied: path '{}' is outside the workspace directory",
-                requested_path
-            ));
-        }
-    }
-
-    Ok(full_path)
+fn resolve_and_validate_path(requested_path: &str, workspace: &Path) -> Result<PathBuf, String> {
+    WorkspacePath::resolve(workspace, requested_path).map(|p| p.path().to_path_buf())
 }
 
 #[async_trait]
@@ -405,6 +377,10 @@ impl Tool for ApplyPatchTool {
         self.definition.clone()
     }
 
+    fn writes_workspace(&self) -> bool {
+        true
+    }
+
     async fn execute(&self, params: Value, context: &ToolContext) -> ToolResult {
         let params: ApplyPatchParams = match serde_json::from_value(params) {
             Ok(p) => p,
@@ -428,14 +404,6 @@ impl Tool for ApplyPatchTool {
             .map(PathBuf::from)
             .unwrap_or_else(|| std::env::current_dir().unwrap_or_else(|_| PathBuf::from(".")));
 
-        // Canonicalize workspace for comparison
-        let canonical_workspace = match workspace.canonicalize() {
-            Ok(p) => p,
-            Err(e) => {
-                return ToolResult::error(format!("Cannot resolve workspace directory: {}", e))
-            }
-        };
-
         let mut results = Vec::new();
         let mut files_added = 0;
         let mut files_updated = 0;
@@ -445,7 +413,7 @@ impl Tool for ApplyPatchTool {
         for operation in operations {
             match operation {
                 PatchOperation::AddFile { path, content } => {
-                    let full_path = match resolve_and_validate_path(&path, &workspace, &canonical_workspace) {
+                    let full_path = match resolve_and_validate_path(&path, &workspace) {
                         Ok(p) => p,
                         Err(e) => {
                             results.push(format!("FAILED Add '{}': {}", path, e));
@@ -473,7 +441,7 @@ impl Tool for ApplyPatchTool {
                 }
 
                 PatchOperation::UpdateFile { path, hunks, move_to } => {
-                    let full_path = match resolve_and_validate_path(&path, &workspace, &canonical_workspace) {
+                    let full_path = match resolve_and_validate_path(&path, &workspace) {
                         Ok(p) => p,
                         Err(e) => {
                             results.push(format!("FAILED Update '{}': {}", path, e));
@@ -512,7 +480,7 @@ impl Tool for ApplyPatchTool {
 
                     // Handle move operation
                     let target_path = if let Some(ref new_path) = move_to {
-                        match resolve_and_validate_path(new_path, &workspace, &canonical_workspace) {
+                        match resolve_and_validate_path(new_path, &workspace) {
                             Ok(p) => p,
                             Err(e) => {
                                 results.push(format!("FAILED Move '{}' to '{}': {}", path, new_path, e));
@@ -556,7 +524,7 @@ impl Tool for ApplyPatchTool {
                 }
 
                 PatchOperation::DeleteFile { path } => {
-                    let full_path = match resolve_and_validate_path(&path, &workspace, &canonical_workspace) {
+                    let full_path = match resolve_and_validate_path(&path, &workspace) {
                         Ok(p) => p,
                         Err(e) => {
                             results.push(format!("FAILED Delete '{}': {}", path, e));
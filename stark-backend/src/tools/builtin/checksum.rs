@@ -0,0 +1,301 @@
+use crate::tools::registry::Tool;
+use crate::tools::types::{
+    PropertySchema, ToolContext, ToolDefinition, ToolGroup, ToolInputSchema, ToolResult,
+};
+use crate::tools::workspace_path::WorkspacePath;
+use async_trait::async_trait;
+use md5::Md5;
+use serde::Deserialize;
+use serde_json::{json, Value};
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use tokio::io::AsyncReadExt;
+
+/// Chunk size used when streaming files through the hashers
+const READ_CHUNK_SIZE: usize = 64 * 1024;
+
+/// Checksum tool - computes SHA-256 (and optionally MD5) of a workspace file,
+/// or verifies it against an expected hash
+pub struct ChecksumTool {
+    definition: ToolDefinition,
+}
+
+impl ChecksumTool {
+    pub fn new() -> Self {
+        let mut properties = HashMap::new();
+        properties.insert(
+            "path".to_string(),
+            PropertySchema {
+                schema_type: "string".to_string(),
+                description: "Path to the file (relative to workspace directory)".to_string(),
+                default: None,
+                items: None,
+                enum_values: None,
+            },
+        );
+        properties.insert(
+            "include_md5".to_string(),
+            PropertySchema {
+                schema_type: "boolean".to_string(),
+                description: "Also compute the MD5 hash (default: false)".to_string(),
+                default: Some(json!(false)),
+                items: None,
+                enum_values: None,
+            },
+        );
+        properties.insert(
+            "expected".to_string(),
+            PropertySchema {
+                schema_type: "string".to_string(),
+                description: "Expected hash to verify against (hex, SHA-256 or MD5). When set, the result reports match or mismatch.".to_string(),
+                default: None,
+                items: None,
+                enum_values: None,
+            },
+        );
+
+        ChecksumTool {
+            definition: ToolDefinition {
+                name: "checksum".to_string(),
+                description: "Compute the SHA-256 (and optionally MD5) checksum of a file, or verify a file against an expected hash. Use this to check downloads and build artifacts.".to_string(),
+                input_schema: ToolInputSchema {
+                    schema_type: "object".to_string(),
+                    properties,
+                    required: vec!["path".to_string()],
+                },
+                group: ToolGroup::Filesystem,
+            },
+        }
+    }
+
+    /// Stream the file through the hashers. Returns (size, sha256, md5).
+    async fn hash_file(path: &Path, include_md5: bool) -> std::io::Result<(u64, String, Option<String>)> {
+        let mut file = tokio::fs::File::open(path).await?;
+        let mut buf = vec![0u8; READ_CHUNK_SIZE];
+        let mut sha256 = Sha256::new();
+        let mut md5 = include_md5.then(Md5::new);
+        let mut size = 0u64;
+
+        loop {
+            let n = file.read(&mut buf).await?;
+            if n == 0 {
+                break;
+            }
+            sha256.update(&buf[..n]);
+            if let Some(ref mut md5) = md5 {
+                md5.update(&buf[..n]);
+            }
+            size += n as u64;
+        }
+
+        Ok((
+            size,
+            hex::encode(sha256.finalize()),
+            md5.map(|h| hex::encode(h.finalize())),
+        ))
+    }
+}
+
+impl Default for ChecksumTool {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct ChecksumParams {
+    path: String,
+    #[serde(default)]
+    include_md5: bool,
+    expected: Option<String>,
+}
+
+/// Normalize an expected hash: trim, lowercase, drop an "sha256:"/"md5:" prefix
+fn normalize_expected(expected: &str) -> String {
+    let trimmed = expected.trim().to_lowercase();
+    trimmed
+        .strip_prefix("sha256:")
+        .or_else(|| trimmed.strip_prefix("md5:"))
+        .unwrap_or(&trimmed)
+        .to_string()
+}
+
+#[async_trait]
+impl Tool for ChecksumTool {
+    fn definition(&self) -> ToolDefinition {
+        self.definition.clone()
+    }
+
+    async fn execute(&self, params: Value, context: &ToolContext) -> ToolResult {
+        let params: ChecksumParams = match serde_json::from_value(params) {
+            Ok(p) => p,
+            Err(e) => return ToolResult::error(format!("Invalid parameters: {}", e)),
+        };
+
+        // Work out which algorithm the expected hash is for before reading anything
+        let expected = params.expected.as_deref().map(normalize_expected);
+        let verify_md5 = match expected.as_deref() {
+            None => false,
+            Some(e) if !e.chars().all(|c| c.is_ascii_hexdigit()) => {
+                return ToolResult::error(format!(
+                    "Expected hash '{}' is not a hex string",
+                    params.expected.unwrap_or_default()
+                ))
+            }
+            Some(e) if e.len() == 64 => false,
+            Some(e) if e.len() == 32 => true,
+            Some(e) => {
+                return ToolResult::error(format!(
+                    "Expected hash has {} hex characters; SHA-256 has 64 and MD5 has 32",
+                    e.len()
+                ))
+            }
+        };
+
+        // Get workspace directory from context or use current directory
+        let workspace = context
+            .workspace_dir
+            .as_ref()
+            .map(PathBuf::from)
+            .unwrap_or_else(|| std::env::current_dir().unwrap_or_else(|_| PathBuf::from(".")));
+
+        // Resolve the path (symlinks and `..` included) and ensure it stays within workspace
+        let resolved = match WorkspacePath::resolve(&workspace, &params.path) {
+            Ok(p) => p,
+            Err(e) => return ToolResult::error(e),
+        };
+        let canonical_path = resolved.path().to_path_buf();
+
+        if !canonical_path.is_file() {
+            return ToolResult::error(format!("'{}' is not a file", params.path));
+        }
+
+        let include_md5 = params.include_md5 || verify_md5;
+        let (size, sha256, md5) = match Self::hash_file(&canonical_path, include_md5).await {
+            Ok(r) => r,
+            Err(e) => return ToolResult::error(format!("Failed to read file: {}", e)),
+        };
+
+        let mut metadata = json!({
+            "path": params.path,
+            "size_bytes": size,
+            "sha256": sha256,
+        });
+        if let Some(ref md5) = md5 {
+            metadata["md5"] = json!(md5);
+        }
+
+        let mut summary = format!("{} ({} bytes)\nSHA-256: {}", params.path, size, sha256);
+        if let Some(ref md5) = md5 {
+            summary.push_str(&format!("\nMD5: {}", md5));
+        }
+
+        if let Some(expected) = expected {
+            let (algorithm, actual) = if verify_md5 {
+                ("md5", md5.as_deref().unwrap_or_default())
+            } else {
+                ("sha256", sha256.as_str())
+            };
+            let matches = actual == expected;
+            metadata["verify"] = json!({
+                "algorithm": algorithm,
+                "expected": expected,
+                "match": matches,
+            });
+            summary.push_str(&if matches {
+                format!("\n✅ {} matches the expected hash", algorithm.to_uppercase())
+            } else {
+                format!(
+                    "\n❌ {} MISMATCH: expected {}, got {}",
+                    algorithm.to_uppercase(),
+                    expected,
+                    actual
+                )
+            });
+        }
+
+        ToolResult::success(summary).with_metadata(metadata)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    const HELLO_SHA256: &str = "2cf24dba5fb0a30e26e83b2ac5b9e29e1b161e5c1fa7425e73043362938b9824";
+    const HELLO_MD5: &str = "5d41402abc4b2a76b9719d911017c592";
+
+    fn context_for(dir: &TempDir) -> ToolContext {
+        ToolContext::new().with_workspace(dir.path().to_string_lossy().to_string())
+    }
+
+    #[tokio::test]
+    async fn test_checksum_computes_hashes() {
+        let tool = ChecksumTool::new();
+        let temp_dir = TempDir::new().unwrap();
+        std::fs::write(temp_dir.path().join("hello.txt"), "hello").unwrap();
+
+        let result = tool
+            .execute(
+                json!({ "path": "hello.txt", "include_md5": true }),
+                &context_for(&temp_dir),
+            )
+            .await;
+
+        assert!(result.success);
+        let meta = result.metadata.unwrap();
+        assert_eq!(meta["sha256"], HELLO_SHA256);
+        assert_eq!(meta["md5"], HELLO_MD5);
+        assert_eq!(meta["size_bytes"], 5);
+    }
+
+    #[tokio::test]
+    async fn test_checksum_verify_match_and_mismatch() {
+        let tool = ChecksumTool::new();
+        let temp_dir = TempDir::new().unwrap();
+        std::fs::write(temp_dir.path().join("hello.txt"), "hello").unwrap();
+        let context = context_for(&temp_dir);
+
+        let result = tool
+            .execute(
+                json!({ "path": "hello.txt", "expected": format!("SHA256:{}", HELLO_SHA256.to_uppercase()) }),
+                &context,
+            )
+            .await;
+        assert_eq!(result.metadata.unwrap()["verify"]["match"], true);
+
+        let result = tool
+            .execute(json!({ "path": "hello.txt", "expected": HELLO_MD5.replace('5', "6") }), &context)
+            .await;
+        assert!(result.success);
+        assert!(result.content.contains("MISMATCH"));
+        let meta = result.metadata.unwrap();
+        assert_eq!(meta["verify"]["algorithm"], "md5");
+        assert_eq!(meta["verify"]["match"], false);
+
+        let result = tool
+            .execute(json!({ "path": "hello.txt", "expected": "abc" }), &context)
+            .await;
+        assert!(!result.success);
+    }
+
+    #[tokio::test]
+    async fn test_checksum_outside_workspace() {
+        let tool = ChecksumTool::new();
+        let workspace = TempDir::new().unwrap();
+        let outside = TempDir::new().unwrap();
+        let secret = outside.path().join("secret.txt");
+        std::fs::write(&secret, "nope").unwrap();
+
+        let result = tool
+            .execute(
+                json!({ "path": secret.to_string_lossy() }),
+                &context_for(&workspace),
+            )
+            .await;
+        assert!(!result.success);
+        assert!(result.error.unwrap().contains("outside the workspace"));
+    }
+}
@@ -0,0 +1,164 @@
+//! Semantic code search tool
+//!
+//! Finds the chunks of the workspace's code closest in meaning to a query,
+//! from the index built by `index_codebase`.
+
+use crate::memory::embeddings::create_provider;
+use crate::memory::EmbeddingConfig;
+use crate::tools::cache::CachePolicy;
+use crate::tools::code_index;
+use crate::tools::registry::Tool;
+use crate::tools::types::{
+    PropertySchema, ToolContext, ToolDefinition, ToolGroup, ToolInputSchema, ToolResult,
+};
+use crate::utils::truncate_chars;
+use async_trait::async_trait;
+use serde::Deserialize;
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::time::Duration;
+
+/// Longest chunk text shown per result, in characters
+const MAX_SNIPPET_CHARS: usize = 2500;
+
+/// Tool for finding code by what it does rather than by exact text
+pub struct CodeSearchTool {
+    definition: ToolDefinition,
+}
+
+impl CodeSearchTool {
+    pub fn new() -> Self {
+        let mut properties = HashMap::new();
+
+        properties.insert(
+            "query".to_string(),
+            PropertySchema {
+                schema_type: "string".to_string(),
+                description: "What the code does, in natural language (e.g. 'where retries are scheduled after a rate limit')".to_string(),
+                default: None,
+                items: None,
+                enum_values: None,
+            },
+        );
+
+        properties.insert(
+            "limit".to_string(),
+            PropertySchema {
+                schema_type: "integer".to_string(),
+                description: "Maximum results (default: 5, max: 20)".to_string(),
+                default: Some(json!(5)),
+                items: None,
+                enum_values: None,
+            },
+        );
+
+        CodeSearchTool {
+            definition: ToolDefinition {
+                name: "code_search".to_string(),
+                description: "Search the workspace's code by meaning, returning the most relevant snippets with their file and line range. Needs an index built by index_codebase. Use grep instead for exact names or strings.".to_string(),
+                input_schema: ToolInputSchema {
+                    schema_type: "object".to_string(),
+                    properties,
+                    required: vec!["query".to_string()],
+                },
+                group: ToolGroup::Development,
+            },
+        }
+    }
+}
+
+impl Default for CodeSearchTool {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct CodeSearchParams {
+    query: String,
+    limit: Option<usize>,
+}
+
+#[async_trait]
+impl Tool for CodeSearchTool {
+    fn definition(&self) -> ToolDefinition {
+        self.definition.clone()
+    }
+
+    fn is_read_only(&self) -> bool {
+        true
+    }
+
+    /// Listings are reused until the workspace changes
+    fn cache_policy(&self, _params: &Value) -> CachePolicy {
+        CachePolicy::UntilWorkspaceChanges(Duration::from_secs(120))
+    }
+
+    async fn execute(&self, params: Value, context: &ToolContext) -> ToolResult {
+        let params: CodeSearchParams = match serde_json::from_value(params) {
+            Ok(p) => p,
+            Err(e) => return ToolResult::error(format!("Invalid parameters: {}", e)),
+        };
+        if params.query.trim().is_empty() {
+            return ToolResult::error("query must not be empty");
+        }
+
+        let Some(db) = context.database.clone() else {
+            return ToolResult::error("Database not available. Code search requires database access.");
+        };
+        let workspace = context
+            .workspace_dir
+            .as_ref()
+            .map(PathBuf::from)
+            .unwrap_or_else(|| std::env::current_dir().unwrap_or_else(|_| PathBuf::from(".")));
+        let root = match workspace.canonicalize() {
+            Ok(root) => root,
+            Err(e) => return ToolResult::error(format!("Cannot resolve workspace directory: {}", e)),
+        };
+
+        let config = match EmbeddingConfig::for_api(&db).await {
+            Ok(config) if config.is_enabled() => config,
+            Ok(_) => return ToolResult::error("No embeddings API is configured. Use grep and glob instead."),
+            Err(e) => return ToolResult::error(format!("Failed to load agent settings: {}", e)),
+        };
+        let query = match create_provider(&config).embed(&params.query).await {
+            Ok(query) => query,
+            Err(e) => return ToolResult::error(format!("Failed to embed the query: {}", e)),
+        };
+
+        let limit = params.limit.unwrap_or(5).clamp(1, 20);
+        let collection = code_index::collection(&root);
+        let matches = match db.search_embeddings(&collection, &query.model, &query.vector, limit).await {
+            Ok(matches) => matches,
+            Err(e) => return ToolResult::error(format!("Code search failed: {}", e)),
+        };
+        if matches.is_empty() {
+            return ToolResult::success("The workspace has no code index yet. Run index_codebase first, or use grep.");
+        }
+
+        let mut output = format!("## Code Search Results\nQuery: '{}', {} result(s)\n", params.query, matches.len());
+        let mut results = Vec::with_capacity(matches.len());
+        for (i, m) in matches.iter().enumerate() {
+            let meta = m.embedding.metadata.as_ref();
+            let path = meta.and_then(|m| m["path"].as_str()).unwrap_or("?");
+            let start = meta.and_then(|m| m["start_line"].as_u64()).unwrap_or(0);
+            let end = meta.and_then(|m| m["end_line"].as_u64()).unwrap_or(0);
+            output.push_str(&format!(
+                "\n{}. {}:{}-{} (score {:.2})\n```\n{}\n```\n",
+                i + 1,
+                path,
+                start,
+                end,
+                m.score,
+                truncate_chars(&m.embedding.content, MAX_SNIPPET_CHARS)
+            ));
+            results.push(json!({ "path": path, "start_line": start, "end_line": end, "score": m.score }));
+        }
+
+        ToolResult::success(output).with_metadata(json!({
+            "query": params.query,
+            "results": results,
+        }))
+    }
+}
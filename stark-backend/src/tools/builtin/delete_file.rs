@@ -2,11 +2,12 @@ use crate::tools::registry::Tool;
 use crate::tools::types::{
     PropertySchema, ToolContext, ToolDefinition, ToolGroup, ToolInputSchema, ToolResult,
 };
+use crate::tools::workspace_path::WorkspacePath;
 use async_trait::async_trait;
 use serde::Deserialize;
 use serde_json::{json, Value};
 use std::collections::HashMap;
-use std::path::{Path, PathBuf};
+use std::path::PathBuf;
 
 /// Delete file tool - removes files or directories within a sandboxed directory
 pub struct DeleteFileTool {
@@ -88,37 +89,15 @@ impl Tool for DeleteFileTool {
             .map(PathBuf::from)
             .unwrap_or_else(|| std::env::current_dir().unwrap_or_else(|_| PathBuf::from(".")));
 
-        // Resolve the path
-        let requested_path = Path::new(&params.path);
-        let full_path = if requested_path.is_absolute() {
-            requested_path.to_path_buf()
-        } else {
-            workspace.join(requested_path)
-        };
-
-        // Canonicalize paths for comparison
-        let canonical_workspace = match workspace.canonicalize() {
+        // Resolve the path (symlinks and `..` included) and ensure it stays within workspace
+        let resolved = match WorkspacePath::resolve(&workspace, &params.path) {
             Ok(p) => p,
-            Err(e) => {
-                return ToolResult::error(format!("Cannot resolve workspace directory: {}", e))
-            }
+            Err(e) => return ToolResult::error(e),
         };
-
-        let canonical_path = match full_path.canonicalize() {
-            Ok(p) => p,
-            Err(e) => return ToolResult::error(format!("Cannot resolve path: {}", e)),
-        };
-
-        // Security check: ensure path is within workspace
-        if !canonical_path.starts_with(&canonical_workspace) {
-            return ToolResult::error(format!(
-                "Access denied: path '{}' is outside the workspace directory",
-                params.path
-            ));
-        }
+        let canonical_path = resolved.path().to_path_buf();
 
         // Don't allow deleting the workspace itself
-        if canonical_path == canonical_workspace {
+        if resolved.is_root() {
             return ToolResult::error("Cannot delete the workspace root directory");
         }
 
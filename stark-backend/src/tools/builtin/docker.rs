@@ -0,0 +1,743 @@
+//! Docker tool for building and running containers
+//!
+//! Images are checked against an allowlist before anything is pulled: for
+//! `run` the image itself, for `build` every `FROM` line of the Dockerfile and
+//! for `compose_up` every `image:` of the compose file. Images built by this
+//! tool are labelled and may always be run. Containers it starts carry the
+//! same label, and `logs`/`stop` refuse containers without it, so the agent
+//! can't touch containers it didn't start.
+
+use crate::tools::registry::Tool;
+use crate::tools::sandbox::SandboxConfig;
+use crate::tools::types::{
+    PropertySchema, ToolContext, ToolDefinition, ToolGroup, ToolInputSchema, ToolResult,
+};
+use crate::tools::workspace_path::WorkspacePath;
+use async_trait::async_trait;
+use globset::Glob;
+use serde::Deserialize;
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::process::Stdio;
+use std::time::Duration;
+use tokio::process::Command;
+
+/// Environment variables read by `DockerTool::new`
+pub mod env_vars {
+    /// Comma-separated image patterns that may be used (`*` allows any image)
+    pub const ALLOWED_IMAGES: &str = "STARK_DOCKER_ALLOWED_IMAGES";
+}
+
+/// Official images allowed when no allowlist is configured, any tag
+const DEFAULT_ALLOWED_IMAGES: &[&str] = &[
+    "node", "python", "rust", "golang", "eclipse-temurin", "ruby", "php", "debian", "ubuntu", "alpine",
+    "busybox", "nginx", "httpd", "postgres", "mysql", "mariadb", "redis", "mongo",
+];
+
+/// Label put on images and containers created by this tool
+const MANAGED_LABEL: &str = "stark.managed";
+
+/// Longest output returned, in characters (the end is kept)
+const MAX_OUTPUT: usize = 15000;
+
+/// Docker tool with image allowlisting
+pub struct DockerTool {
+    definition: ToolDefinition,
+    /// Allowed image patterns
+    allowed_images: Vec<String>,
+    /// Memory and process limits applied to started containers
+    sandbox: SandboxConfig,
+}
+
+impl DockerTool {
+    pub fn new() -> Self {
+        let allowed_images = std::env::var(env_vars::ALLOWED_IMAGES)
+            .ok()
+            .map(|v| {
+                v.split(',')
+                    .map(|p| p.trim().to_string())
+                    .filter(|p| !p.is_empty())
+                    .collect::<Vec<_>>()
+            })
+            .filter(|patterns| !patterns.is_empty())
+            .unwrap_or_else(|| DEFAULT_ALLOWED_IMAGES.iter().map(|s| s.to_string()).collect());
+        Self::with_allowed_images(allowed_images)
+    }
+
+    pub fn with_allowed_images(allowed_images: Vec<String>) -> Self {
+        let mut properties = HashMap::new();
+
+        properties.insert(
+            "operation".to_string(),
+            PropertySchema {
+                schema_type: "string".to_string(),
+                description: "Docker operation: build, run, ps, logs, stop, compose_up, compose_down".to_string(),
+                default: None,
+                items: None,
+                enum_values: Some(vec![
+                    "build".to_string(),
+                    "run".to_string(),
+                    "ps".to_string(),
+                    "logs".to_string(),
+                    "stop".to_string(),
+                    "compose_up".to_string(),
+                    "compose_down".to_string(),
+                ]),
+            },
+        );
+
+        properties.insert(
+            "path".to_string(),
+            PropertySchema {
+                schema_type: "string".to_string(),
+                description: "For build: the build context; for compose_up/compose_down: the directory of the compose file (default: workspace root)".to_string(),
+                default: Some(json!(".")),
+                items: None,
+                enum_values: None,
+            },
+        );
+
+        properties.insert(
+            "file".to_string(),
+            PropertySchema {
+                schema_type: "string".to_string(),
+                description: "Dockerfile for build, or compose file for compose_up/compose_down, relative to path (default: Dockerfile / compose.yaml / docker-compose.yml)".to_string(),
+                default: None,
+                items: None,
+                enum_values: None,
+            },
+        );
+
+        properties.insert(
+            "tag".to_string(),
+            PropertySchema {
+                schema_type: "string".to_string(),
+                description: "For build: image name and tag to create, e.g. 'myapp:dev'".to_string(),
+                default: None,
+                items: None,
+                enum_values: None,
+            },
+        );
+
+        properties.insert(
+            "image".to_string(),
+            PropertySchema {
+                schema_type: "string".to_string(),
+                description: "For run: image to start (must be allowlisted or built with this tool)".to_string(),
+                default: None,
+                items: None,
+                enum_values: None,
+            },
+        );
+
+        properties.insert(
+            "name".to_string(),
+            PropertySchema {
+                schema_type: "string".to_string(),
+                description: "Container name (for run, logs, stop)".to_string(),
+                default: None,
+                items: None,
+                enum_values: None,
+            },
+        );
+
+        properties.insert(
+            "ports".to_string(),
+            PropertySchema {
+                schema_type: "array".to_string(),
+                description: "For run: port mappings 'host:container' (e.g. '8080:80'). Bound to 127.0.0.1.".to_string(),
+                default: Some(json!([])),
+                items: Some(Box::new(PropertySchema {
+                    schema_type: "string".to_string(),
+                    description: "host:container".to_string(),
+                    default: None,
+                    items: None,
+                    enum_values: None,
+                })),
+                enum_values: None,
+            },
+        );
+
+        properties.insert(
+            "env".to_string(),
+            PropertySchema {
+                schema_type: "object".to_string(),
+                description: "For run: environment variables for the container".to_string(),
+                default: Some(json!({})),
+                items: None,
+                enum_values: None,
+            },
+        );
+
+        properties.insert(
+            "command".to_string(),
+            PropertySchema {
+                schema_type: "array".to_string(),
+                description: "For run: command and arguments overriding the image's default".to_string(),
+                default: None,
+                items: Some(Box::new(PropertySchema {
+                    schema_type: "string".to_string(),
+                    description: "Argument".to_string(),
+                    default: None,
+                    items: None,
+                    enum_values: None,
+                })),
+                enum_values: None,
+            },
+        );
+
+        properties.insert(
+            "detach".to_string(),
+            PropertySchema {
+                schema_type: "boolean".to_string(),
+                description: "For run: keep the container running in the background (default: true). When false, waits for it to exit, returns its output and removes it.".to_string(),
+                default: Some(json!(true)),
+                items: None,
+                enum_values: None,
+            },
+        );
+
+        properties.insert(
+            "mount_workspace".to_string(),
+            PropertySchema {
+                schema_type: "boolean".to_string(),
+                description: "For run: mount the workspace at /workspace and start there (default: false)".to_string(),
+                default: Some(json!(false)),
+                items: None,
+                enum_values: None,
+            },
+        );
+
+        properties.insert(
+            "tail".to_string(),
+            PropertySchema {
+                schema_type: "integer".to_string(),
+                description: "For logs: number of lines from the end (default: 200)".to_string(),
+                default: Some(json!(200)),
+                items: None,
+                enum_values: None,
+            },
+        );
+
+        properties.insert(
+            "timeout".to_string(),
+            PropertySchema {
+                schema_type: "integer".to_string(),
+                description: "Timeout in seconds (default: 600 for build and compose_up, 120 otherwise; max: 1800)".to_string(),
+                default: None,
+                items: None,
+                enum_values: None,
+            },
+        );
+
+        DockerTool {
+            definition: ToolDefinition {
+                name: "docker".to_string(),
+                description: format!(
+                    "Build images and run containers to test what you build. Operations: build, run (ports are bound to 127.0.0.1), ps, logs, stop, compose_up, compose_down. Only these base images are allowed: {}. Images you build can always be run; logs and stop only work on containers this tool started.",
+                    allowed_images.join(", ")
+                ),
+                input_schema: ToolInputSchema {
+                    schema_type: "object".to_string(),
+                    properties,
+                    required: vec!["operation".to_string()],
+                },
+                group: ToolGroup::Exec,
+            },
+            allowed_images,
+            sandbox: SandboxConfig::from_env(),
+        }
+    }
+
+    /// Whether `image` matches the allowlist
+    fn is_image_allowed(&self, image: &str) -> bool {
+        image_allowed(image, &self.allowed_images)
+    }
+
+    /// Run docker and return its combined output
+    async fn run_docker(&self, args: &[String], dir: &Path, timeout_secs: u64) -> Result<String, String> {
+        let mut cmd = Command::new("docker");
+        cmd.args(args)
+            .current_dir(dir)
+            .stdin(Stdio::null())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .kill_on_drop(true);
+        // Plain progress output instead of the interactive TTY view
+        cmd.env("BUILDKIT_PROGRESS", "plain");
+
+        log::info!("[DOCKER] docker {}", args.join(" "));
+        let output = match tokio::time::timeout(Duration::from_secs(timeout_secs), cmd.output()).await {
+            Ok(Ok(output)) => output,
+            Ok(Err(e)) => return Err(format!("Failed to execute docker: {}. Is Docker installed?", e)),
+            Err(_) => return Err(format!("docker {} timed out after {} seconds", args[0], timeout_secs)),
+        };
+
+        let stdout = String::from_utf8_lossy(&output.stdout);
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        let combined = match (stdout.trim().is_empty(), stderr.trim().is_empty()) {
+            (_, true) => stdout.trim_end().to_string(),
+            (true, false) => stderr.trim_end().to_string(),
+            (false, false) => format!("{}\n{}", stdout.trim_end(), stderr.trim_end()),
+        };
+        let combined = tail(&combined, MAX_OUTPUT);
+
+        if output.status.success() {
+            Ok(combined)
+        } else {
+            Err(format!("docker {} failed:\n{}", args[0], combined))
+        }
+    }
+
+    /// Whether an image or container carries the label this tool sets
+    async fn is_managed(&self, object: &str, dir: &Path) -> bool {
+        let format = format!("{{{{index .Config.Labels \"{}\"}}}}", MANAGED_LABEL);
+        let args = vec!["inspect".to_string(), "--format".to_string(), format, object.to_string()];
+        matches!(self.run_docker(&args, dir, 30).await, Ok(value) if value.trim() == "true")
+    }
+}
+
+impl Default for DockerTool {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct DockerParams {
+    operation: String,
+    path: Option<String>,
+    file: Option<String>,
+    tag: Option<String>,
+    image: Option<String>,
+    name: Option<String>,
+    ports: Option<Vec<String>>,
+    env: Option<HashMap<String, String>>,
+    command: Option<Vec<String>>,
+    detach: Option<bool>,
+    mount_workspace: Option<bool>,
+    tail: Option<u64>,
+    timeout: Option<u64>,
+}
+
+/// Split an image reference into its repository and tag, dropping the
+/// implicit Docker Hub prefixes. A digest counts as the tag.
+fn split_image(image: &str) -> (String, String) {
+    let image = image
+        .strip_prefix("docker.io/library/")
+        .or_else(|| image.strip_prefix("docker.io/"))
+        .or_else(|| image.strip_prefix("library/"))
+        .unwrap_or(image);
+    if let Some((name, digest)) = image.split_once('@') {
+        return (name.to_string(), digest.to_string());
+    }
+    match image.rsplit_once(':') {
+        // A colon before the last slash is a registry port, not a tag
+        Some((name, tag)) if !tag.contains('/') => (name.to_string(), tag.to_string()),
+        _ => (image.to_string(), "latest".to_string()),
+    }
+}
+
+/// Whether `image` matches one of the patterns. A pattern without a tag
+/// matches the repository with any tag; `*` wildcards are allowed.
+fn image_allowed(image: &str, patterns: &[String]) -> bool {
+    let (name, tag) = split_image(image.trim());
+    let full = format!("{}:{}", name, tag);
+    patterns.iter().any(|pattern| {
+        let (target, pattern) = if pattern.contains(':') { (&full, pattern.as_str()) } else { (&name, pattern.as_str()) };
+        Glob::new(pattern)
+            .map(|g| g.compile_matcher().is_match(target.as_str()))
+            .unwrap_or(false)
+    })
+}
+
+/// Base images named by the `FROM` lines of a Dockerfile, skipping earlier
+/// build stages and `scratch`
+fn dockerfile_base_images(dockerfile: &str) -> Result<Vec<String>, String> {
+    let mut stages: Vec<String> = Vec::new();
+    let mut images = Vec::new();
+    for line in dockerfile.lines() {
+        let words: Vec<&str> = line.split_whitespace().collect();
+        if !words.first().is_some_and(|w| w.eq_ignore_ascii_case("FROM")) {
+            continue;
+        }
+        let mut rest = words[1..].iter().filter(|w| !w.starts_with("--"));
+        let Some(image) = rest.next() else { continue };
+        if image.contains('$') {
+            return Err(format!("FROM {} uses a build argument, so the image can't be checked against the allowlist", image));
+        }
+        let lower = image.to_lowercase();
+        if lower != "scratch" && !stages.contains(&lower) {
+            images.push(image.to_string());
+        }
+        let alias = rest.next().filter(|w| w.eq_ignore_ascii_case("AS")).and_then(|_| rest.next());
+        if let Some(stage) = alias {
+            stages.push(stage.to_lowercase());
+        }
+    }
+    Ok(images)
+}
+
+/// Images named by `image:` keys in a compose file
+fn compose_images(compose: &str) -> Vec<String> {
+    compose
+        .lines()
+        .filter_map(|line| line.trim().strip_prefix("image:"))
+        .map(|image| image.trim().trim_matches(|c| c == '"' || c == '\'').to_string())
+        .filter(|image| !image.is_empty())
+        .collect()
+}
+
+/// `host:container` port mapping as a docker `-p` value bound to localhost
+fn port_mapping(mapping: &str) -> Result<String, String> {
+    let (host, container) = mapping
+        .split_once(':')
+        .ok_or_else(|| format!("Invalid port mapping '{}': use host:container, e.g. 8080:80", mapping))?;
+    let (container_port, protocol) = match container.split_once('/') {
+        Some((port, proto @ ("tcp" | "udp"))) => (port, Some(proto)),
+        Some(_) => return Err(format!("Invalid port mapping '{}': protocol must be tcp or udp", mapping)),
+        None => (container, None),
+    };
+    for port in [host, container_port] {
+        if port.parse::<u16>().map(|p| p == 0).unwrap_or(true) {
+            return Err(format!("Invalid port mapping '{}': ports must be 1-65535", mapping));
+        }
+    }
+    Ok(match protocol {
+        Some(proto) => format!("127.0.0.1:{}:{}/{}", host, container_port, proto),
+        None => format!("127.0.0.1:{}:{}", host, container_port),
+    })
+}
+
+/// Container, image and project names: letters, digits and `_.-` (plus `/:` for images)
+fn valid_name(name: &str, extra: &[char]) -> bool {
+    !name.is_empty()
+        && !name.starts_with('-')
+        && name.chars().all(|c| c.is_ascii_alphanumeric() || "_.-".contains(c) || extra.contains(&c))
+}
+
+/// The last `max_chars` characters of `text`
+fn tail(text: &str, max_chars: usize) -> String {
+    let skip = text.chars().count().saturating_sub(max_chars);
+    match text.char_indices().nth(skip) {
+        Some((i, _)) if skip > 0 => format!("[... earlier output omitted]\n{}", &text[i..]),
+        _ => text.to_string(),
+    }
+}
+
+#[async_trait]
+impl Tool for DockerTool {
+    fn definition(&self) -> ToolDefinition {
+        self.definition.clone()
+    }
+
+    fn writes_workspace(&self) -> bool {
+        true
+    }
+
+    async fn execute(&self, params: Value, context: &ToolContext) -> ToolResult {
+        let params: DockerParams = match serde_json::from_value(params) {
+            Ok(p) => p,
+            Err(e) => return ToolResult::error(format!("Invalid parameters: {}", e)),
+        };
+
+        let workspace = context
+            .workspace_dir
+            .as_ref()
+            .map(PathBuf::from)
+            .unwrap_or_else(|| std::env::current_dir().unwrap_or_else(|_| PathBuf::from(".")));
+        let resolved = match WorkspacePath::resolve(&workspace, params.path.as_deref().unwrap_or(".")) {
+            Ok(p) => p,
+            Err(e) => return ToolResult::error(e),
+        };
+        let root = resolved.root().to_path_buf();
+        let dir = resolved.path().to_path_buf();
+        let long_op = matches!(params.operation.as_str(), "build" | "compose_up");
+        let timeout_secs = params.timeout.unwrap_or(if long_op { 600 } else { 120 }).clamp(1, 1800);
+        let s = |v: &str| v.to_string();
+
+        match params.operation.as_str() {
+            "build" => {
+                let tag = match params.tag.as_deref() {
+                    Some(t) if valid_name(t, &['/', ':']) => t,
+                    Some(t) => return ToolResult::error(format!("Invalid image tag: '{}'", t)),
+                    None => return ToolResult::error("tag is required for build (e.g. 'myapp:dev')"),
+                };
+                let dockerfile = match WorkspacePath::resolve(&dir, params.file.as_deref().unwrap_or("Dockerfile")) {
+                    Ok(p) if p.path().starts_with(&root) => p.path().to_path_buf(),
+                    Ok(_) => return ToolResult::error("The Dockerfile must be inside the workspace"),
+                    Err(e) => return ToolResult::error(e),
+                };
+                let contents = match std::fs::read_to_string(&dockerfile) {
+                    Ok(c) => c,
+                    Err(e) => return ToolResult::error(format!("Cannot read {}: {}", dockerfile.display(), e)),
+                };
+                let bases = match dockerfile_base_images(&contents) {
+                    Ok(b) => b,
+                    Err(e) => return ToolResult::error(e),
+                };
+                if let Some(image) = bases.iter().find(|image| !self.is_image_allowed(image)) {
+                    return ToolResult::error(format!(
+                        "Base image '{}' is not allowlisted. Allowed: {}",
+                        image,
+                        self.allowed_images.join(", ")
+                    ));
+                }
+
+                let args = vec![
+                    s("build"),
+                    s("-t"),
+                    s(tag),
+                    s("--label"),
+                    format!("{}=true", MANAGED_LABEL),
+                    s("-f"),
+                    dockerfile.to_string_lossy().to_string(),
+                    dir.to_string_lossy().to_string(),
+                ];
+                match self.run_docker(&args, &dir, timeout_secs).await {
+                    Ok(output) => ToolResult::success(format!("Built image {}:\n{}", tag, output))
+                        .with_metadata(json!({ "tag": tag, "base_images": bases })),
+                    Err(e) => ToolResult::error(e),
+                }
+            }
+
+            "run" => {
+                let image = match params.image.as_deref() {
+                    Some(i) if valid_name(i, &['/', ':', '@']) => i,
+                    Some(i) => return ToolResult::error(format!("Invalid image: '{}'", i)),
+                    None => return ToolResult::error("image is required for run"),
+                };
+                if !self.is_image_allowed(image) && !self.is_managed(image, &dir).await {
+                    return ToolResult::error(format!(
+                        "Image '{}' is not allowlisted and wasn't built by this tool. Allowed: {}",
+                        image,
+                        self.allowed_images.join(", ")
+                    ));
+                }
+
+                let detach = params.detach.unwrap_or(true);
+                let mut args = vec![s("run"), s("--label"), format!("{}=true", MANAGED_LABEL)];
+                args.push(s(if detach { "-d" } else { "--rm" }));
+                if let Some(ref name) = params.name {
+                    if !valid_name(name, &[]) {
+                        return ToolResult::error(format!("Invalid container name: '{}'", name));
+                    }
+                    args.extend([s("--name"), name.clone()]);
+                }
+                for mapping in params.ports.iter().flatten() {
+                    match port_mapping(mapping) {
+                        Ok(p) => args.extend([s("-p"), p]),
+                        Err(e) => return ToolResult::error(e),
+                    }
+                }
+                for (key, value) in params.env.iter().flatten() {
+                    args.extend([s("-e"), format!("{}={}", key, value)]);
+                }
+                if params.mount_workspace.unwrap_or(false) {
+                    args.extend([s("-v"), format!("{}:/workspace", root.display()), s("-w"), s("/workspace")]);
+                }
+                args.extend([s("--pids-limit"), self.sandbox.max_processes.to_string()]);
+                if let Some(mb) = self.sandbox.memory_mb {
+                    args.extend([s("--memory"), format!("{}m", mb)]);
+                }
+                args.push(s(image));
+                args.extend(params.command.iter().flatten().cloned());
+
+                match self.run_docker(&args, &dir, timeout_secs).await {
+                    Ok(output) if detach => {
+                        let id: String = output.trim().lines().last().unwrap_or("").chars().take(12).collect();
+                        let name = params.name.clone().unwrap_or_else(|| id.clone());
+                        ToolResult::success(format!(
+                            "Started container {} from {}. Use logs with name '{}' to see its output and stop when done.",
+                            name, image, name
+                        ))
+                        .with_metadata(json!({ "container": name, "id": id, "image": image }))
+                    }
+                    Ok(output) => ToolResult::success(if output.is_empty() {
+                        format!("Container from {} exited successfully (no output)", image)
+                    } else {
+                        output
+                    }),
+                    Err(e) => ToolResult::error(e),
+                }
+            }
+
+            "ps" => {
+                let args = vec![
+                    s("ps"),
+                    s("-a"),
+                    s("--filter"),
+                    format!("label={}=true", MANAGED_LABEL),
+                    s("--format"),
+                    s("{{.Names}}\t{{.Image}}\t{{.Status}}\t{{.Ports}}"),
+                ];
+                match self.run_docker(&args, &dir, timeout_secs).await {
+                    Ok(output) if output.trim().is_empty() => ToolResult::success("No containers started by this tool."),
+                    Ok(output) => ToolResult::success(format!("NAME\tIMAGE\tSTATUS\tPORTS\n{}", output)),
+                    Err(e) => ToolResult::error(e),
+                }
+            }
+
+            "logs" | "stop" => {
+                let name = match params.name.as_deref() {
+                    Some(n) if valid_name(n, &[]) => n,
+                    Some(n) => return ToolResult::error(format!("Invalid container name: '{}'", n)),
+                    None => return ToolResult::error(format!("name is required for {}", params.operation)),
+                };
+                if !self.is_managed(name, &dir).await {
+                    return ToolResult::error(format!(
+                        "Container '{}' doesn't exist or wasn't started by this tool. Use ps to list yours.",
+                        name
+                    ));
+                }
+
+                if params.operation == "logs" {
+                    let tail_lines = params.tail.unwrap_or(200).clamp(1, 5000).to_string();
+                    return match self.run_docker(&[s("logs"), s("--tail"), tail_lines, s(name)], &dir, timeout_secs).await {
+                        Ok(output) if output.is_empty() => ToolResult::success(format!("No output from {} yet.", name)),
+                        Ok(output) => ToolResult::success(output),
+                        Err(e) => ToolResult::error(e),
+                    };
+                }
+                match self.run_docker(&[s("rm"), s("-f"), s(name)], &dir, timeout_secs).await {
+                    Ok(_) => ToolResult::success(format!("Stopped and removed container {}", name)),
+                    Err(e) => ToolResult::error(e),
+                }
+            }
+
+            "compose_up" | "compose_down" => {
+                let file = match params.file.as_deref() {
+                    Some(f) => f.to_string(),
+                    None => match ["compose.yaml", "compose.yml", "docker-compose.yml", "docker-compose.yaml"]
+                        .iter()
+                        .find(|f| dir.join(f).is_file())
+                    {
+                        Some(f) => f.to_string(),
+                        None => return ToolResult::error(format!("No compose file found in {}", dir.display())),
+                    },
+                };
+                let compose_file = match WorkspacePath::resolve(&dir, &file) {
+                    Ok(p) if p.path().starts_with(&root) => p.path().to_path_buf(),
+                    Ok(_) => return ToolResult::error("The compose file must be inside the workspace"),
+                    Err(e) => return ToolResult::error(e),
+                };
+                // One compose project per directory, so down finds what up started
+                let project = format!(
+                    "stark-{}",
+                    dir.file_name().map(|n| n.to_string_lossy().to_lowercase()).unwrap_or_else(|| "workspace".to_string())
+                )
+                .replace(|c: char| !c.is_ascii_alphanumeric() && c != '-' && c != '_', "-");
+                let mut args = vec![s("compose"), s("-f"), compose_file.to_string_lossy().to_string(), s("-p"), project.clone()];
+
+                if params.operation == "compose_down" {
+                    args.push(s("down"));
+                    return match self.run_docker(&args, &dir, timeout_secs).await {
+                        Ok(output) => ToolResult::success(format!("Stopped compose project {}:\n{}", project, output)),
+                        Err(e) => ToolResult::error(e),
+                    };
+                }
+
+                let contents = match std::fs::read_to_string(&compose_file) {
+                    Ok(c) => c,
+                    Err(e) => return ToolResult::error(format!("Cannot read {}: {}", compose_file.display(), e)),
+                };
+                if let Some(image) = compose_images(&contents).iter().find(|image| !self.is_image_allowed(image)) {
+                    return ToolResult::error(format!(
+                        "Compose image '{}' is not allowlisted. Allowed: {}",
+                        image,
+                        self.allowed_images.join(", ")
+                    ));
+                }
+                args.extend([s("up"), s("-d"), s("--build")]);
+                match self.run_docker(&args, &dir, timeout_secs).await {
+                    Ok(output) => ToolResult::success(format!(
+                        "Started compose project {}:\n{}\n\nRun compose_down with the same path when done.",
+                        project, output
+                    ))
+                    .with_metadata(json!({ "project": project })),
+                    Err(e) => ToolResult::error(e),
+                }
+            }
+
+            _ => ToolResult::error(format!(
+                "Unknown operation: {}. Supported: build, run, ps, logs, stop, compose_up, compose_down",
+                params.operation
+            )),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn patterns(list: &[&str]) -> Vec<String> {
+        list.iter().map(|s| s.to_string()).collect()
+    }
+
+    #[test]
+    fn test_image_allowlist() {
+        let allowed = patterns(&["node", "python:3.12*", "ghcr.io/acme/*"]);
+        assert!(image_allowed("node", &allowed));
+        assert!(image_allowed("node:20-alpine", &allowed));
+        assert!(image_allowed("docker.io/library/node:20", &allowed));
+        assert!(image_allowed("python:3.12-slim", &allowed));
+        assert!(!image_allowed("python:3.11", &allowed));
+        assert!(!image_allowed("python", &allowed));
+        assert!(image_allowed("ghcr.io/acme/api:1.0", &allowed));
+        assert!(!image_allowed("ghcr.io/evil/node", &allowed));
+        assert!(!image_allowed("nodejs/node", &allowed));
+        assert!(image_allowed("anything/at:all", &patterns(&["*"])));
+
+        assert_eq!(split_image("localhost:5000/app"), ("localhost:5000/app".to_string(), "latest".to_string()));
+        assert_eq!(split_image("redis@sha256:abc"), ("redis".to_string(), "sha256:abc".to_string()));
+    }
+
+    #[test]
+    fn test_dockerfile_base_images() {
+        let dockerfile = "\
+# syntax=docker/dockerfile:1
+FROM --platform=linux/amd64 rust:1.80 AS builder
+RUN cargo build --release
+from builder as tested
+FROM scratch
+FROM debian:bookworm-slim
+COPY --from=builder /app /app
+";
+        assert_eq!(dockerfile_base_images(dockerfile).unwrap(), vec!["rust:1.80", "debian:bookworm-slim"]);
+        assert!(dockerfile_base_images("ARG BASE=node\nFROM ${BASE}").is_err());
+    }
+
+    #[test]
+    fn test_compose_images_and_ports() {
+        let compose = "services:\n  db:\n    image: \"postgres:16\"\n  app:\n    build: .\n";
+        assert_eq!(compose_images(compose), vec!["postgres:16"]);
+
+        assert_eq!(port_mapping("8080:80").unwrap(), "127.0.0.1:8080:80");
+        assert_eq!(port_mapping("5353:53/udp").unwrap(), "127.0.0.1:5353:53/udp");
+        assert!(port_mapping("0.0.0.0:80:80").is_err());
+        assert!(port_mapping("80").is_err());
+        assert!(port_mapping("99999:80").is_err());
+
+        assert!(valid_name("my-app_1", &[]));
+        assert!(!valid_name("--privileged", &[]));
+        assert!(!valid_name("a;b", &[]));
+    }
+
+    #[tokio::test]
+    async fn test_build_refuses_unlisted_base_image() {
+        let dir = TempDir::new().unwrap();
+        std::fs::write(dir.path().join("Dockerfile"), "FROM evil/miner:latest\n").unwrap();
+        let context = ToolContext::new().with_workspace(dir.path().to_string_lossy().to_string());
+        let tool = DockerTool::with_allowed_images(patterns(&["node"]));
+
+        let result = tool.execute(json!({ "operation": "build", "tag": "app:dev" }), &context).await;
+        assert!(result.error.unwrap().contains("'evil/miner:latest' is not allowlisted"));
+        let result = tool
+            .execute(json!({ "operation": "run", "image": "node", "ports": ["80"] }), &context)
+            .await;
+        assert!(result.error.unwrap().contains("Invalid port mapping"));
+    }
+}
@@ -0,0 +1,262 @@
+use crate::tools::registry::Tool;
+use crate::tools::types::{
+    PropertySchema, ToolContext, ToolDefinition, ToolGroup, ToolInputSchema, ToolResult,
+};
+use async_trait::async_trait;
+use futures_util::future::join_all;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::process::Stdio;
+use std::time::Duration;
+use tokio::process::Command;
+use tokio::time::timeout;
+
+/// Toolchains checked when no explicit list is given
+const DEFAULT_TOOLS: &[&str] = &[
+    "git", "node", "npm", "cargo", "rustc", "python3", "pip3", "go", "make", "docker", "gh",
+];
+
+/// How long a single `--version` probe may take
+const PROBE_TIMEOUT_SECS: u64 = 5;
+
+/// Doctor tool - probes the exec environment for common toolchains and their versions
+pub struct DoctorTool {
+    definition: ToolDefinition,
+}
+
+impl DoctorTool {
+    pub fn new() -> Self {
+        let mut properties = HashMap::new();
+        properties.insert(
+            "tools".to_string(),
+            PropertySchema {
+                schema_type: "array".to_string(),
+                description: format!(
+                    "Command names to check (default: {})",
+                    DEFAULT_TOOLS.join(", ")
+                ),
+                default: None,
+                items: Some(Box::new(PropertySchema {
+                    schema_type: "string".to_string(),
+                    description: "Command name, e.g. 'node'".to_string(),
+                    default: None,
+                    items: None,
+                    enum_values: None,
+                })),
+                enum_values: None,
+            },
+        );
+
+        DoctorTool {
+            definition: ToolDefinition {
+                name: "doctor".to_string(),
+                description: "Check which developer toolchains (git, node, npm, cargo, python3, go, docker, ...) are installed in the exec environment and report their versions. Run this before build/install commands to catch missing tools early.".to_string(),
+                input_schema: ToolInputSchema {
+                    schema_type: "object".to_string(),
+                    properties,
+                    required: vec![],
+                },
+                group: ToolGroup::Development,
+            },
+        }
+    }
+
+    /// Locate a command on PATH and ask it for its version
+    async fn probe(name: String) -> ToolCheck {
+        let path = match which::which(&name) {
+            Ok(p) => p,
+            Err(_) => {
+                return ToolCheck {
+                    name,
+                    installed: false,
+                    path: None,
+                    version: None,
+                    error: None,
+                }
+            }
+        };
+
+        // Go has no --version flag
+        let args: &[&str] = if name == "go" { &["version"] } else { &["--version"] };
+
+        let output = timeout(
+            Duration::from_secs(PROBE_TIMEOUT_SECS),
+            Command::new(&path)
+                .args(args)
+                .stdin(Stdio::null())
+                .kill_on_drop(true)
+                .output(),
+        )
+        .await;
+
+        let (version, error) = match output {
+            Ok(Ok(out)) => {
+                let version = first_line(&out.stdout).or_else(|| first_line(&out.stderr));
+                let error = if out.status.success() {
+                    None
+                } else {
+                    Some(format!("version probe exited with {}", out.status))
+                };
+                (version, error)
+            }
+            Ok(Err(e)) => (None, Some(format!("failed to run: {}", e))),
+            Err(_) => (None, Some(format!("timed out after {}s", PROBE_TIMEOUT_SECS))),
+        };
+
+        ToolCheck {
+            name,
+            installed: true,
+            path: Some(path.to_string_lossy().to_string()),
+            version,
+            error,
+        }
+    }
+}
+
+impl Default for DoctorTool {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct DoctorParams {
+    tools: Option<Vec<String>>,
+}
+
+#[derive(Debug, Serialize)]
+struct ToolCheck {
+    name: String,
+    installed: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    path: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    version: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<String>,
+}
+
+/// First non-empty line of command output
+fn first_line(bytes: &[u8]) -> Option<String> {
+    String::from_utf8_lossy(bytes)
+        .lines()
+        .map(str::trim)
+        .find(|l| !l.is_empty())
+        .map(str::to_string)
+}
+
+/// Only plain command names may be probed (no paths, flags or shell syntax)
+fn is_valid_command_name(name: &str) -> bool {
+    !name.is_empty()
+        && !name.starts_with('-')
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '.' | '_' | '-' | '+'))
+}
+
+#[async_trait]
+impl Tool for DoctorTool {
+    fn definition(&self) -> ToolDefinition {
+        self.definition.clone()
+    }
+
+    async fn execute(&self, params: Value, _context: &ToolContext) -> ToolResult {
+        let params: DoctorParams = match serde_json::from_value(params) {
+            Ok(p) => p,
+            Err(e) => return ToolResult::error(format!("Invalid parameters: {}", e)),
+        };
+
+        let names: Vec<String> = match params.tools {
+            Some(tools) if !tools.is_empty() => tools,
+            _ => DEFAULT_TOOLS.iter().map(|s| s.to_string()).collect(),
+        };
+
+        if let Some(bad) = names.iter().find(|n| !is_valid_command_name(n)) {
+            return ToolResult::error(format!(
+                "Invalid tool name '{}': use plain command names like 'node' or 'cargo'",
+                bad
+            ));
+        }
+
+        // Probe everything in parallel so the check stays fast
+        let checks = join_all(names.into_iter().map(Self::probe)).await;
+
+        let missing: Vec<&str> = checks
+            .iter()
+            .filter(|c| !c.installed)
+            .map(|c| c.name.as_str())
+            .collect();
+
+        let mut report = String::from("Environment check:\n");
+        for check in &checks {
+            let line = if !check.installed {
+                format!("❌ {}: not installed", check.name)
+            } else if let Some(ref err) = check.error {
+                format!(
+                    "⚠️ {}: installed ({}) but {}",
+                    check.name,
+                    check.path.as_deref().unwrap_or("?"),
+                    err
+                )
+            } else {
+                format!(
+                    "✅ {}: {}",
+                    check.name,
+                    check.version.as_deref().unwrap_or("installed")
+                )
+            };
+            report.push_str(&line);
+            report.push('\n');
+        }
+        if missing.is_empty() {
+            report.push_str("\nAll checked tools are installed.");
+        } else {
+            report.push_str(&format!("\nMissing: {}", missing.join(", ")));
+        }
+
+        ToolResult::success(report).with_metadata(json!({
+            "tools": checks,
+            "missing": missing,
+        }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_command_name_validation() {
+        assert!(is_valid_command_name("node"));
+        assert!(is_valid_command_name("python3.11"));
+        assert!(is_valid_command_name("g++"));
+        assert!(!is_valid_command_name(""));
+        assert!(!is_valid_command_name("--help"));
+        assert!(!is_valid_command_name("/bin/sh"));
+        assert!(!is_valid_command_name("node; rm -rf /"));
+    }
+
+    #[test]
+    fn test_first_line_skips_blank_lines() {
+        assert_eq!(first_line(b"\n  v20.11.0\nextra"), Some("v20.11.0".to_string()));
+        assert_eq!(first_line(b"   \n"), None);
+    }
+
+    #[tokio::test]
+    async fn test_reports_missing_tool() {
+        let tool = DoctorTool::new();
+        let result = tool
+            .execute(
+                json!({ "tools": ["definitely-not-installed-xyz"] }),
+                &ToolContext::new(),
+            )
+            .await;
+
+        assert!(result.success);
+        assert!(result.content.contains("not installed"));
+        let meta = result.metadata.unwrap();
+        assert_eq!(meta["missing"], json!(["definitely-not-installed-xyz"]));
+        assert_eq!(meta["tools"][0]["installed"], false);
+    }
+}
@@ -2,14 +2,21 @@ use crate::tools::registry::Tool;
 use crate::tools::types::{
     PropertySchema, ToolContext, ToolDefinition, ToolGroup, ToolInputSchema, ToolResult,
 };
+use crate::tools::text_edit::{apply_replacements, apply_unified_diff, parse_search_replace_blocks};
+use crate::tools::workspace_path::WorkspacePath;
+use crate::utils::truncate_chars;
 use async_trait::async_trait;
 use serde::Deserialize;
 use serde_json::{json, Value};
 use std::collections::HashMap;
 use std::path::{Path, PathBuf};
 
-/// Edit file tool for precise string replacement
-/// Similar to Claude Code's edit tool - requires exact match of old_text
+/// Edit file tool for targeted changes without rewriting the whole file.
+///
+/// Takes one of: `old_text`/`new_text` (exact string replacement), `diff` (a
+/// unified diff for this file) or `blocks` (SEARCH/REPLACE blocks). Diffs and
+/// blocks apply atomically: a hunk that no longer matches the file is reported
+/// as a conflict and nothing is written.
 pub struct EditFileTool {
     definition: ToolDefinition,
 }
@@ -30,6 +37,28 @@ impl EditFileTool {
             },
         );
 
+        properties.insert(
+            "diff".to_string(),
+            PropertySchema {
+                schema_type: "string".to_string(),
+                description: "Unified diff for this file ('@@ -12,3 +12,4 @@' hunks with ' ', '-' and '+' lines). Context and '-' lines must match the file; line numbers are a hint.".to_string(),
+                default: None,
+                items: None,
+                enum_values: None,
+            },
+        );
+
+        properties.insert(
+            "blocks".to_string(),
+            PropertySchema {
+                schema_type: "string".to_string(),
+                description: "One or more search/replace blocks: '<<<<<<< SEARCH', the exact current lines, '=======', the new lines, '>>>>>>> REPLACE'. Each search text must match exactly one place.".to_string(),
+                default: None,
+                items: None,
+                enum_values: None,
+            },
+        );
+
         properties.insert(
             "old_text".to_string(),
             PropertySchema {
@@ -70,15 +99,11 @@ impl EditFileTool {
         EditFileTool {
             definition: ToolDefinition {
                 name: "edit_file".to_string(),
-                description: "Edit a file by replacing exact text. old_text must match exactly (including whitespace). Returns the edited section with context. For large changes, prefer write_file or apply_patch.".to_string(),
+                description: "Edit part of a file instead of rewriting it with write_file. Give exactly one of: old_text + new_text (replace exact text), diff (unified diff for this file), or blocks (SEARCH/REPLACE blocks for several edits at once). Diffs and blocks apply all-or-nothing; if the file changed since you read it you get a conflict and nothing is written. Returns the changed lines with context. For multi-file changes use apply_patch.".to_string(),
                 input_schema: ToolInputSchema {
                     schema_type: "object".to_string(),
                     properties,
-                    required: vec![
-                        "path".to_string(),
-                        "old_text".to_string(),
-                        "new_text".to_string(),
-                    ],
+                    required: vec!["path".to_string()],
                 },
                 group: ToolGroup::Development,
             },
@@ -142,9 +167,28 @@ impl Default for EditFileTool {
 #[derive(Debug, Deserialize)]
 struct EditFileParams {
     path: String,
-    old_text: String,
-    new_text: String,
+    old_text: Option<String>,
+    new_text: Option<String>,
     occurrence: Option<String>,
+    diff: Option<String>,
+    blocks: Option<String>,
+}
+
+/// Replace `path` with `content` via a temporary file and a rename, so a
+/// failed write never leaves the file half-written
+async fn write_atomically(path: &Path, content: &str) -> std::io::Result<()> {
+    let file_name = path.file_name().map(|n| n.to_string_lossy().to_string()).unwrap_or_default();
+    let temp = path.with_file_name(format!(".{}.{}.tmp", file_name, std::process::id()));
+
+    tokio::fs::write(&temp, content).await?;
+    if let Ok(metadata) = tokio::fs::metadata(path).await {
+        let _ = tokio::fs::set_permissions(&temp, metadata.permissions()).await;
+    }
+    if let Err(e) = tokio::fs::rename(&temp, path).await {
+        let _ = tokio::fs::remove_file(&temp).await;
+        return Err(e);
+    }
+    Ok(())
 }
 
 #[async_trait]
@@ -153,19 +197,19 @@ impl Tool for EditFileTool {
         self.definition.clone()
     }
 
+    fn writes_workspace(&self) -> bool {
+        true
+    }
+
     async fn execute(&self, params: Value, context: &ToolContext) -> ToolResult {
         let params: EditFileParams = match serde_json::from_value(params) {
             Ok(p) => p,
             Err(e) => return ToolResult::error(format!("Invalid parameters: {}", e)),
         };
 
-        // Validate inputs
-        if params.old_text.is_empty() {
-            return ToolResult::error("old_text cannot be empty");
-        }
-
-        if params.old_text == params.new_text {
-            return ToolResult::error("old_text and new_text are identical - no change needed");
+        let modes = [params.old_text.is_some(), params.diff.is_some(), params.blocks.is_some()];
+        if modes.iter().filter(|&&m| m).count() != 1 {
+            return ToolResult::error("Provide exactly one of: old_text + new_text, diff, or blocks");
         }
 
         // Get workspace directory
@@ -175,34 +219,12 @@ impl Tool for EditFileTool {
             .map(PathBuf::from)
             .unwrap_or_else(|| std::env::current_dir().unwrap_or_else(|_| PathBuf::from(".")));
 
-        // Resolve the path
-        let requested_path = Path::new(&params.path);
-        let full_path = if requested_path.is_absolute() {
-            requested_path.to_path_buf()
-        } else {
-            workspace.join(requested_path)
-        };
-
-        // Canonicalize paths for comparison
-        let canonical_workspace = match workspace.canonicalize() {
-            Ok(p) => p,
-            Err(e) => {
-                return ToolResult::error(format!("Cannot resolve workspace directory: {}", e))
-            }
-        };
-
-        let canonical_path = match full_path.canonicalize() {
+        // Resolve the path (symlinks and `..` included) and ensure it stays within workspace
+        let resolved = match WorkspacePath::resolve(&workspace, &params.path) {
             Ok(p) => p,
-            Err(e) => return ToolResult::error(format!("Cannot resolve file path: {}", e)),
+            Err(e) => return ToolResult::error(e),
         };
-
-        // Security check: ensure path is within workspace
-        if !canonical_path.starts_with(&canonical_workspace) {
-            return ToolResult::error(format!(
-                "Access denied: path '{}' is outside the workspace directory",
-                params.path
-            ));
-        }
+        let canonical_path = resolved.path().to_path_buf();
 
         // Check if file exists
         if !canonical_path.exists() {
@@ -219,12 +241,58 @@ impl Tool for EditFileTool {
             Err(e) => return ToolResult::error(format!("Failed to read file: {}", e)),
         };
 
+        let patched = match (&params.diff, &params.blocks) {
+            (Some(diff), _) => Some(("diff", apply_unified_diff(&content, diff))),
+            (None, Some(blocks)) => Some((
+                "blocks",
+                parse_search_replace_blocks(blocks).and_then(|b| apply_replacements(&content, &b)),
+            )),
+            (None, None) => None,
+        };
+        if let Some((mode, outcome)) = patched {
+            let outcome = match outcome {
+                Ok(outcome) => outcome,
+                Err(e) => return ToolResult::error(e),
+            };
+            if outcome.content == content {
+                return ToolResult::error("The edit leaves the file unchanged");
+            }
+            if let Err(e) = write_atomically(&canonical_path, &outcome.content).await {
+                return ToolResult::error(format!("Failed to write file: {}", e));
+            }
+
+            return ToolResult::success(format!(
+                "Applied {} hunk(s) to {}.\n\nContext after edit:\n{}",
+                outcome.hunks.len(),
+                params.path,
+                outcome.render(3)
+            ))
+            .with_metadata(json!({
+                "path": params.path,
+                "mode": mode,
+                "hunks_applied": outcome.hunks.len(),
+                "changed_lines": outcome.hunks.iter().map(|(s, e)| json!([s + 1, e])).collect::<Vec<_>>()
+            }));
+        }
+
+        let old_text = params.old_text.unwrap_or_default();
+        let new_text = match params.new_text {
+            Some(text) => text,
+            None => return ToolResult::error("new_text is required with old_text"),
+        };
+        if old_text.is_empty() {
+            return ToolResult::error("old_text cannot be empty");
+        }
+        if old_text == new_text {
+            return ToolResult::error("old_text and new_text are identical - no change needed");
+        }
+
         // Check for exact match
-        if !content.contains(&params.old_text) {
+        if !content.contains(&old_text) {
             // Try to find similar text to help user debug
-            let old_text_trimmed = params.old_text.trim();
+            let old_text_trimmed = old_text.trim();
             let similar_found = if old_text_trimmed.len() > 10 {
-                let search_text = &old_text_trimmed[..old_text_trimmed.len().min(20)];
+                let search_text = truncate_chars(old_text_trimmed, 20);
                 content.contains(search_text)
             } else {
                 false
@@ -243,20 +311,20 @@ impl Tool for EditFileTool {
         }
 
         // Count occurrences
-        let count = content.matches(&params.old_text).count();
+        let count = content.matches(&old_text).count();
         let occurrence = params.occurrence.as_deref().unwrap_or("first");
 
         // Perform the replacement
         let (new_content, replaced_count, edit_position) = match occurrence {
             "all" => {
-                let new_content = content.replace(&params.old_text, &params.new_text);
-                let pos = content.find(&params.old_text).unwrap_or(0);
+                let new_content = content.replace(&old_text, &new_text);
+                let pos = content.find(&old_text).unwrap_or(0);
                 (new_content, count, pos)
             }
             "last" => {
-                if let Some(pos) = content.rfind(&params.old_text) {
+                if let Some(pos) = content.rfind(&old_text) {
                     let mut new_content = content.clone();
-                    new_content.replace_range(pos..pos + params.old_text.len(), &params.new_text);
+                    new_content.replace_range(pos..pos + old_text.len(), &new_text);
                     (new_content, 1, pos)
                 } else {
                     return ToolResult::error("old_text not found");
@@ -264,9 +332,9 @@ impl Tool for EditFileTool {
             }
             _ => {
                 // "first" (default)
-                if let Some(pos) = content.find(&params.old_text) {
+                if let Some(pos) = content.find(&old_text) {
                     let mut new_content = content.clone();
-                    new_content.replace_range(pos..pos + params.old_text.len(), &params.new_text);
+                    new_content.replace_range(pos..pos + old_text.len(), &new_text);
                     (new_content, 1, pos)
                 } else {
                     return ToolResult::error("old_text not found");
@@ -275,13 +343,13 @@ impl Tool for EditFileTool {
         };
 
         // Write the file
-        if let Err(e) = tokio::fs::write(&canonical_path, &new_content).await {
+        if let Err(e) = write_atomically(&canonical_path, &new_content).await {
             return ToolResult::error(format!("Failed to write file: {}", e));
         }
 
         // Generate output
-        let diff = Self::generate_diff(&params.old_text, &params.new_text, 3);
-        let context_view = Self::show_context(&new_content, edit_position, &params.new_text, 3);
+        let diff = Self::generate_diff(&old_text, &new_text, 3);
+        let context_view = Self::show_context(&new_content, edit_position, &new_text, 3);
 
         let message = if count > 1 && occurrence != "all" {
             format!(
@@ -390,6 +458,48 @@ mod tests {
         assert_eq!(content, "qux bar qux baz qux");
     }
 
+    #[tokio::test]
+    async fn test_edit_file_unified_diff_and_conflict() {
+        let tool = EditFileTool::new();
+        let temp_dir = TempDir::new().unwrap();
+
+        let test_file = temp_dir.path().join("app.py");
+        std::fs::write(&test_file, "import os\n\ndef main():\n    print('hi')\n").unwrap();
+
+        let context =
+            ToolContext::new().with_workspace(temp_dir.path().to_string_lossy().to_string());
+
+        let result = tool
+            .execute(
+                json!({
+                    "path": "app.py",
+                    "diff": "@@ -3,2 +3,3 @@\n def main():\n-    print('hi')\n+    name = os.getenv('USER')\n+    print(f'hi {name}')\n"
+                }),
+                &context,
+            )
+            .await;
+
+        assert!(result.success);
+        assert!(result.content.contains(">    4│     name = os.getenv('USER')"));
+        let content = std::fs::read_to_string(&test_file).unwrap();
+        assert_eq!(content, "import os\n\ndef main():\n    name = os.getenv('USER')\n    print(f'hi {name}')\n");
+
+        // The same diff no longer matches, and the file is left alone
+        let result = tool
+            .execute(
+                json!({
+                    "path": "app.py",
+                    "blocks": "<<<<<<< SEARCH\nimport os\n=======\nimport sys\n>>>>>>> REPLACE\n<<<<<<< SEARCH\n    print('hi')\n=======\n    print('bye')\n>>>>>>> REPLACE\n"
+                }),
+                &context,
+            )
+            .await;
+
+        assert!(!result.success);
+        assert!(result.error.unwrap().starts_with("Conflict"));
+        assert_eq!(std::fs::read_to_string(&test_file).unwrap(), content);
+    }
+
     #[tokio::test]
     async fn test_edit_file_outside_workspace() {
         let tool = EditFileTool::new();
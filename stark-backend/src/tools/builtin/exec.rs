@@ -1,18 +1,28 @@
 use crate::controllers::api_keys::ApiKeyId;
+use crate::execution::{isolate, kill_tree, preview_owner, stream_lines, OutputLine, ProcessStatus};
 use crate::tools::registry::Tool;
+use crate::tools::sandbox::SandboxConfig;
 use crate::tools::types::{
     PropertySchema, ToolContext, ToolDefinition, ToolGroup, ToolInputSchema, ToolResult,
 };
+use crate::utils::truncate_chars;
 use async_trait::async_trait;
 use serde::{Deserialize, Deserializer};
 use serde_json::{json, Value};
 use std::collections::HashMap;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::process::Stdio;
 use std::time::Duration;
-use tokio::process::Command;
+use tokio::process::{Child, Command};
+use tokio::sync::mpsc::UnboundedReceiver;
 use tokio::time::timeout;
 
+/// Longest output, in characters, returned from a foreground command
+const MAX_OUTPUT: usize = 15000;
+
+/// How long to wait for the last output after a command exits
+const OUTPUT_DRAIN: Duration = Duration::from_secs(1);
+
 /// Deserialize a u64 from either a number or a string
 fn deserialize_u64_lenient<'de, D>(deserializer: D) -> Result<Option<u64>, D::Error>
 where
@@ -32,8 +42,10 @@ pub struct ExecTool {
     definition: ToolDefinition,
     /// Maximum execution time in seconds
     max_timeout: u64,
-    /// Security mode: "full" (shell allowed), "restricted" (no shell), "sandbox" (future)
+    /// Security mode: "full" (shell allowed), "restricted" (no shell metacharacters)
     security_mode: String,
+    /// Isolation backend, quotas and denylist for spawned commands
+    sandbox: SandboxConfig,
 }
 
 impl ExecTool {
@@ -76,6 +88,16 @@ impl ExecTool {
                 enum_values: None,
             },
         );
+        properties.insert(
+            "on_timeout".to_string(),
+            PropertySchema {
+                schema_type: "string".to_string(),
+                description: "What to do when the timeout is reached: 'background' keeps the command running as a background process (see process_status), 'kill' stops it. Either way the output so far is returned.".to_string(),
+                default: Some(json!("background")),
+                items: None,
+                enum_values: Some(vec!["background".to_string(), "kill".to_string()]),
+            },
+        );
         properties.insert(
             "env".to_string(),
             PropertySchema {
@@ -96,11 +118,25 @@ impl ExecTool {
                 enum_values: None,
             },
         );
+        properties.insert(
+            "preview".to_string(),
+            PropertySchema {
+                schema_type: "boolean".to_string(),
+                description: "With background=true: reserve a free port, passed to the command as the PORT environment variable, and return a URL the user can open to see the running server. The server must listen on $PORT. Defaults to true for recognised dev server commands.".to_string(),
+                default: None,
+                items: None,
+                enum_values: None,
+            },
+        );
 
+        let sandbox = SandboxConfig::from_env();
         ExecTool {
             definition: ToolDefinition {
                 name: "exec".to_string(),
-                description: "Execute a shell command in the workspace. Supports full shell syntax including pipes, redirects, and command chaining. Use for running CLI tools, scripts, and system commands.".to_string(),
+                description: format!(
+                    "Execute a shell command in the workspace. Supports full shell syntax including pipes, redirects, and command chaining. Use for running CLI tools, scripts, and system commands. Commands run in {}.",
+                    sandbox.shell.as_str()
+                ),
                 input_schema: ToolInputSchema {
                     schema_type: "object".to_string(),
                     properties,
@@ -110,6 +146,7 @@ impl ExecTool {
             },
             max_timeout,
             security_mode,
+            sandbox,
         }
     }
 
@@ -155,23 +192,8 @@ impl ExecTool {
         let lower = command.to_lowercase();
 
         // Block commands that could damage the system
-        let dangerous_patterns = [
-            ("rm -rf /", "Attempted to delete root filesystem"),
-            ("rm -rf /*", "Attempted to delete root filesystem"),
-            ("mkfs", "Filesystem formatting not allowed"),
-            ("dd if=", "Raw disk operations not allowed"),
-            (":(){:|:&};:", "Fork bomb detected"),
-            ("chmod -R 777 /", "Dangerous permission change"),
-            ("shutdown", "System shutdown not allowed"),
-            ("reboot", "System reboot not allowed"),
-            ("init 0", "System halt not allowed"),
-            ("init 6", "System reboot not allowed"),
-        ];
-
-        for (pattern, msg) in dangerous_patterns {
-            if lower.contains(pattern) {
-                return Some(msg.to_string());
-            }
+        if let Some(reason) = self.sandbox.denied_reason(command) {
+            return Some(reason);
         }
 
         // Block interactive commands that require user input
@@ -296,13 +318,7 @@ impl ExecTool {
                 // Fallback: spawn without ProcessManager (fire-and-forget)
                 log::warn!("ProcessManager not available, using fire-and-forget background execution");
 
-                let shell = if cfg!(target_os = "windows") { "cmd" } else { "sh" };
-                let shell_arg = if cfg!(target_os = "windows") { "/C" } else { "-c" };
-
-                match Command::new(shell)
-                    .arg(shell_arg)
-                    .arg(&params.command)
-                    .current_dir(&working_dir)
+                match Command::from(self.sandbox.command(&params.command, &working_dir, &[]))
                     .stdout(Stdio::null())
                     .stderr(Stdio::null())
                     .spawn()
@@ -352,46 +368,192 @@ impl ExecTool {
             }
         }
 
-        // Spawn via ProcessManager
-        match process_manager
-            .spawn(
-                &params.command,
-                &working_dir,
-                channel_id,
-                Some(&env_vars),
-            )
-            .await
-        {
+        // Servers get a free port, handed over as PORT
+        let ports = process_manager.ports();
+        let lease = if params.preview.unwrap_or_else(|| Self::is_server_command(&params.command)) {
+            let owner = preview_owner(context.job_id.as_deref(), context.session_id, channel_id);
+            match ports.allocate(&owner) {
+                Ok(lease) => {
+                    env_vars.insert("PORT".to_string(), lease.port.to_string());
+                    Some(lease)
+                }
+                Err(e) => return ToolResult::error(format!("Failed to start background process: {}", e)),
+            }
+        } else {
+            None
+        };
+
+        // Spawn via ProcessManager, owned by the agent job or chat session
+        let spawned = match &context.job_id {
+            Some(job_id) => {
+                process_manager
+                    .spawn_for_job(&params.command, &working_dir, job_id, Some(&env_vars))
+                    .await
+            }
+            None => {
+                process_manager
+                    .spawn_for_session(
+                        &params.command,
+                        &working_dir,
+                        channel_id,
+                        context.session_id,
+                        Some(&env_vars),
+                    )
+                    .await
+            }
+        };
+        match spawned {
             Ok(process_id) => {
                 // Get process info for response
                 let info = process_manager.get(&process_id);
                 let pid = info.as_ref().and_then(|i| i.pid).unwrap_or(0);
 
+                let mut preview = String::new();
+                let mut metadata = json!({
+                    "process_id": process_id,
+                    "pid": pid,
+                    "command": params.command,
+                    "background": true,
+                    "working_dir": working_dir.to_string_lossy()
+                });
+                if let Some(lease) = lease {
+                    ports.attach(lease.port, &process_id);
+                    // Exited before the lease was attached, so nothing will release it
+                    if process_manager.status(&process_id) != Some(ProcessStatus::Running) {
+                        ports.release_port(lease.port);
+                    }
+                    let url = ports.url(&lease);
+                    preview = format!(
+                        "Port: {} (passed as PORT; the server must listen on it)\nPreview URL: {}\n",
+                        lease.port, url
+                    );
+                    metadata["port"] = json!(lease.port);
+                    metadata["preview_url"] = json!(url);
+                }
+
                 ToolResult::success(format!(
                     "Started background process\n\
                     Process ID: {}\n\
                     PID: {}\n\
                     Command: {}\n\
-                    Working directory: {}\n\n\
+                    Working directory: {}\n\
+                    {}\n\
                     Use `process_status` tool with id=\"{}\" to check status or get output.",
                     process_id,
                     pid,
                     params.command,
                     working_dir.display(),
+                    preview,
                     process_id
-                )).with_metadata(json!({
-                    "process_id": process_id,
-                    "pid": pid,
-                    "command": params.command,
-                    "background": true,
-                    "working_dir": working_dir.to_string_lossy()
-                }))
+                )).with_metadata(metadata)
+            }
+            Err(e) => {
+                if let Some(lease) = lease {
+                    ports.release_port(lease.port);
+                }
+                ToolResult::error(format!("Failed to start background process: {}", e))
+            }
+        }
+    }
+}
+
+impl ExecTool {
+    /// The command ran past its timeout: hand it to the ProcessManager to
+    /// keep running in the background, or kill it, returning the output so far
+    #[allow(clippy::too_many_arguments)]
+    async fn handle_timeout(
+        &self,
+        params: &ExecParams,
+        context: &ToolContext,
+        working_dir: &Path,
+        timeout_secs: u64,
+        mut child: Child,
+        lines: UnboundedReceiver<OutputLine>,
+        output: Vec<OutputLine>,
+    ) -> ToolResult {
+        let partial = truncate_output(combined_output(&output));
+        let metadata = json!({
+            "command": params.command,
+            "timed_out": true,
+            "timeout_secs": timeout_secs,
+            "working_dir": working_dir.to_string_lossy(),
+            "sandbox": self.sandbox.backend.as_str()
+        });
+
+        let keep_running = params.on_timeout.as_deref().unwrap_or("background") != "kill";
+        let moved = match (&context.process_manager, keep_running) {
+            (Some(manager), true) => Some(
+                manager
+                    .adopt(
+                        child,
+                        lines,
+                        &params.command,
+                        working_dir,
+                        context.channel_id.unwrap_or(0),
+                        context.session_id,
+                        context.job_id.as_deref(),
+                        output,
+                    )
+                    .await,
+            ),
+            _ => {
+                kill_tree(&mut child).await;
+                None
             }
-            Err(e) => ToolResult::error(format!("Failed to start background process: {}", e)),
+        };
+
+        log::info!("Command timed out after {}s: {}", timeout_secs, params.command);
+        match moved {
+            Some(Ok(process_id)) => ToolResult::success(format!(
+                "Command still running after {} seconds; moved to background as process {}.\n\
+                Use `process_status` with id=\"{}\" to follow its output or kill it.\n\n\
+                --- output so far ---\n{}",
+                timeout_secs, process_id, process_id, partial
+            ))
+            .with_metadata(json!({ "background": true, "process_id": process_id, "details": metadata })),
+            Some(Err(e)) => ToolResult::error(format!(
+                "Command timed out after {} seconds and was killed ({}).\n\n--- output so far ---\n{}",
+                timeout_secs, e, partial
+            ))
+            .with_metadata(metadata),
+            None => ToolResult::error(format!(
+                "Command timed out after {} seconds and was killed. Consider increasing timeout or running in background.\n\n\
+                --- output so far ---\n{}",
+                timeout_secs, partial
+            ))
+            .with_metadata(metadata),
         }
     }
 }
 
+/// Stdout lines, then stderr lines under a separator
+fn combined_output(output: &[OutputLine]) -> String {
+    let join = |stderr: bool| {
+        output
+            .iter()
+            .filter(|l| l.stderr == stderr)
+            .map(|l| l.line.as_str())
+            .collect::<Vec<_>>()
+            .join("\n")
+    };
+    let (stdout, stderr) = (join(false), join(true));
+    match (stdout.is_empty(), stderr.is_empty()) {
+        (_, true) => stdout,
+        (true, false) => stderr,
+        (false, false) => format!("{}\n--- stderr ---\n{}", stdout, stderr),
+    }
+}
+
+/// Cap output handed back to the model (kept small to avoid context bloat for smaller models)
+fn truncate_output(text: String) -> String {
+    let shown = truncate_chars(&text, MAX_OUTPUT);
+    if shown.len() < text.len() {
+        format!("{}\n\n[Output truncated at {} characters]", shown, MAX_OUTPUT)
+    } else {
+        text
+    }
+}
+
 impl Default for ExecTool {
     fn default() -> Self {
         Self::new()
@@ -407,6 +569,10 @@ struct ExecParams {
     env: Option<HashMap<String, String>>,
     #[serde(default)]
     background: Option<bool>,
+    /// Reserve a port and report a preview URL (default: for server commands)
+    preview: Option<bool>,
+    /// "background" (default) or "kill"
+    on_timeout: Option<String>,
 }
 
 #[async_trait]
@@ -415,6 +581,10 @@ impl Tool for ExecTool {
         self.definition.clone()
     }
 
+    fn writes_workspace(&self) -> bool {
+        true
+    }
+
     async fn execute(&self, params: Value, context: &ToolContext) -> ToolResult {
         let params: ExecParams = match serde_json::from_value(params) {
             Ok(p) => p,
@@ -473,57 +643,45 @@ impl Tool for ExecTool {
             }
         }
 
-        // Build the command using shell
-        let shell = if cfg!(target_os = "windows") {
-            "cmd"
-        } else {
-            "sh"
-        };
-
-        let shell_arg = if cfg!(target_os = "windows") {
-            "/C"
-        } else {
-            "-c"
-        };
-
-        let mut cmd = Command::new(shell);
-        cmd.arg(shell_arg)
-            .arg(&params.command)
-            .current_dir(&working_dir)
-            .stdout(Stdio::piped())
-            .stderr(Stdio::piped());
-
-        // Set environment variables from context (API keys)
+        // Collect environment variables from context (API keys)
         // Track which keys are available for diagnostic output
+        let mut env: Vec<(String, String)> = Vec::new();
         let mut available_env_vars: Vec<String> = Vec::new();
         for key_id in ApiKeyId::all() {
             if let Some(value) = context.get_api_key_by_id(key_id) {
                 // Set all configured env vars for this key
                 if let Some(env_vars) = key_id.env_vars() {
                     for env_var in env_vars {
-                        cmd.env(env_var, &value);
+                        env.push((env_var.to_string(), value.clone()));
                         available_env_vars.push(env_var.to_string());
                     }
                 }
 
                 // Special git configuration for GitHub token
                 if key_id.requires_git_config() {
-                    // Disable git terminal prompts (would hang in non-interactive mode)
-                    cmd.env("GIT_TERMINAL_PROMPT", "0");
-                    // Configure git to rewrite github HTTPS URLs to include the token
-                    // This allows git clone/push to authenticate automatically
-                    cmd.env("GIT_CONFIG_COUNT", "2");
-                    cmd.env("GIT_CONFIG_KEY_0", format!("url.https://x-access-token:{}@github.com/.insteadOf", value));
-                    cmd.env("GIT_CONFIG_VALUE_0", "https://github.com/");
-                    cmd.env("GIT_CONFIG_KEY_1", format!("url.https://x-access-token:{}@github.com/.insteadOf", value));
-                    cmd.env("GIT_CONFIG_VALUE_1", "git@github.com:");
-                    // Set git author/committer info for commits (from bot config)
+                    let git_url = format!("url.https://x-access-token:{}@github.com/.insteadOf", value);
                     let bot_name = context.get_bot_name();
                     let bot_email = context.get_bot_email();
-                    cmd.env("GIT_AUTHOR_NAME", &bot_name);
-                    cmd.env("GIT_AUTHOR_EMAIL", &bot_email);
-                    cmd.env("GIT_COMMITTER_NAME", &bot_name);
-                    cmd.env("GIT_COMMITTER_EMAIL", &bot_email);
+                    env.extend(
+                        [
+                            // Disable git terminal prompts (would hang in non-interactive mode)
+                            ("GIT_TERMINAL_PROMPT", "0".to_string()),
+                            // Configure git to rewrite github HTTPS URLs to include the token
+                            // This allows git clone/push to authenticate automatically
+                            ("GIT_CONFIG_COUNT", "2".to_string()),
+                            ("GIT_CONFIG_KEY_0", git_url.clone()),
+                            ("GIT_CONFIG_VALUE_0", "https://github.com/".to_string()),
+                            ("GIT_CONFIG_KEY_1", git_url),
+                            ("GIT_CONFIG_VALUE_1", "git@github.com:".to_string()),
+                            // Set git author/committer info for commits (from bot config)
+                            ("GIT_AUTHOR_NAME", bot_name.clone()),
+                            ("GIT_AUTHOR_EMAIL", bot_email.clone()),
+                            ("GIT_COMMITTER_NAME", bot_name),
+                            ("GIT_COMMITTER_EMAIL", bot_email),
+                        ]
+                        .into_iter()
+                        .map(|(k, v)| (k.to_string(), v)),
+                    );
                 }
             }
         }
@@ -531,45 +689,68 @@ impl Tool for ExecTool {
         // Set custom environment variables from params
         if let Some(ref env_vars) = params.env {
             for (key, value) in env_vars {
-                cmd.env(key, value);
+                env.push((key.clone(), value.clone()));
             }
         }
 
-        // Execute with timeout
+        // Build the command inside the configured sandbox, in its own process
+        // group so a timeout kills everything it started
+        let mut cmd = self.sandbox.command(&params.command, &working_dir, &env);
+        isolate(&mut cmd);
+        let mut cmd = Command::from(cmd);
+        cmd.stdout(Stdio::piped()).stderr(Stdio::piped()).kill_on_drop(true);
+
+        // Execute with timeout, streaming output lines as they arrive
         let start = std::time::Instant::now();
-        log::info!("Executing command: {} (timeout: {}s, workdir: {:?})",
-            params.command, timeout_secs, working_dir);
-
-        let output = match timeout(Duration::from_secs(timeout_secs), cmd.output()).await {
-            Ok(Ok(output)) => output,
-            Ok(Err(e)) => return ToolResult::error(format!("Failed to execute command: {}", e)),
-            Err(_) => {
-                return ToolResult::error(format!(
-                    "Command timed out after {} seconds. Consider increasing timeout or running in background.",
-                    timeout_secs
-                ))
+        log::info!("Executing command: {} (timeout: {}s, workdir: {:?}, sandbox: {})",
+            params.command, timeout_secs, working_dir, self.sandbox.backend.as_str());
+
+        let mut child = match cmd.spawn() {
+            Ok(child) => child,
+            Err(e) => return ToolResult::error(format!("Failed to execute command: {}", e)),
+        };
+        let mut lines = stream_lines(&mut child);
+        let mut output: Vec<OutputLine> = Vec::new();
+        let emit = |line: &OutputLine| {
+            if let Some(broadcaster) = &context.broadcaster {
+                broadcaster.broadcast(line.event(context.channel_id.unwrap_or(0), context.job_id.as_deref()));
             }
         };
-        let duration_ms = start.elapsed().as_millis() as i64;
 
-        let stdout = String::from_utf8_lossy(&output.stdout).to_string();
-        let stderr = String::from_utf8_lossy(&output.stderr).to_string();
-        let exit_code = output.status.code().unwrap_or(-1);
-
-        // Build response
-        let success = output.status.success();
-        let mut result_text = String::new();
+        let deadline = tokio::time::sleep(Duration::from_secs(timeout_secs));
+        tokio::pin!(deadline);
+        let status = loop {
+            tokio::select! {
+                Some(line) = lines.recv() => {
+                    emit(&line);
+                    output.push(line);
+                }
+                status = child.wait() => break Some(status),
+                _ = &mut deadline => break None,
+            }
+        };
 
-        if !stdout.is_empty() {
-            result_text.push_str(&stdout);
-        }
+        let status = match status {
+            Some(Ok(status)) => status,
+            Some(Err(e)) => return ToolResult::error(format!("Failed to execute command: {}", e)),
+            None => return self.handle_timeout(&params, context, &working_dir, timeout_secs, child, lines, output).await,
+        };
 
-        if !stderr.is_empty() {
-            if !result_text.is_empty() {
-                result_text.push_str("\n--- stderr ---\n");
+        // Collect what is left in the pipes. Processes the command left running
+        // in the background may hold them open, so don't wait long.
+        let _ = timeout(OUTPUT_DRAIN, async {
+            while let Some(line) = lines.recv().await {
+                emit(&line);
+                output.push(line);
             }
-            result_text.push_str(&stderr);
-        }
+        })
+        .await;
+        let duration_ms = start.elapsed().as_millis() as i64;
+        let exit_code = status.code().unwrap_or(-1);
+
+        // Build response
+        let success = status.success();
+        let mut result_text = combined_output(&output);
 
         if result_text.is_empty() {
             // Provide diagnostic info when there's no output
@@ -614,15 +795,7 @@ impl Tool for ExecTool {
             };
         }
 
-        // Truncate if too long (keep small to avoid context bloat for smaller models)
-        const MAX_OUTPUT: usize = 15000;
-        if result_text.len() > MAX_OUTPUT {
-            result_text = format!(
-                "{}\n\n[Output truncated at {} characters]",
-                &result_text[..MAX_OUTPUT],
-                MAX_OUTPUT
-            );
-        }
+        let result_text = truncate_output(result_text);
 
         log::info!("Command completed: exit_code={}, duration={}ms, output_len={}",
             exit_code, duration_ms, result_text.len());
@@ -637,7 +810,8 @@ impl Tool for ExecTool {
             "command": params.command,
             "exit_code": exit_code,
             "duration_ms": duration_ms,
-            "working_dir": working_dir.to_string_lossy()
+            "working_dir": working_dir.to_string_lossy(),
+            "sandbox": self.sandbox.backend.as_str()
         }))
     }
 }
@@ -707,4 +881,77 @@ mod tests {
         assert!(result.success);
         assert!(result.content.contains("HELLO WORLD"));
     }
+
+    #[tokio::test]
+    async fn test_exec_timeout_kills_and_returns_partial_output() {
+        let tool = ExecTool::new();
+        let context = ToolContext::new();
+        let started = std::time::Instant::now();
+
+        let result = tool
+            .execute(json!({ "command": "echo started; sleep 30; echo never", "timeout": 1 }), &context)
+            .await;
+
+        assert!(!result.success);
+        assert!(started.elapsed() < Duration::from_secs(10));
+        assert!(result.content.contains("timed out after 1 seconds"));
+        assert!(result.content.contains("started"));
+        assert!(!result.content.contains("never"));
+        assert_eq!(result.metadata.unwrap()["timed_out"], true);
+    }
+
+    #[tokio::test]
+    async fn test_exec_timeout_moves_command_to_background() {
+        let broadcaster = std::sync::Arc::new(crate::gateway::events::EventBroadcaster::new());
+        let manager = std::sync::Arc::new(crate::execution::ProcessManager::new(broadcaster));
+        let context = ToolContext::new().with_process_manager(manager.clone());
+
+        let result = ExecTool::new()
+            .execute(json!({ "command": "echo first; sleep 1.5; echo second", "timeout": 1 }), &context)
+            .await;
+
+        assert!(result.success);
+        assert!(result.content.contains("moved to background"));
+        let process_id = result.metadata.unwrap()["process_id"].as_str().unwrap().to_string();
+
+        tokio::time::sleep(Duration::from_millis(1500)).await;
+        let output = manager.output(&process_id, 10).unwrap();
+        assert_eq!(output.lines, vec!["first", "second"]);
+        assert_eq!(
+            manager.status(&process_id),
+            Some(crate::execution::ProcessStatus::Completed { exit_code: Some(0) })
+        );
+    }
+
+    #[tokio::test]
+    async fn test_background_server_gets_a_port_until_it_exits() {
+        let broadcaster = std::sync::Arc::new(crate::gateway::events::EventBroadcaster::new());
+        let manager = std::sync::Arc::new(crate::execution::ProcessManager::new(broadcaster));
+        let context = ToolContext { job_id: Some("job-preview".to_string()), ..Default::default() }
+            .with_process_manager(manager.clone());
+
+        let result = ExecTool::new()
+            .execute(json!({ "command": "echo $PORT; sleep 1", "background": true, "preview": true }), &context)
+            .await;
+
+        assert!(result.success, "{}", result.content);
+        let metadata = result.metadata.unwrap();
+        let port = metadata["port"].as_u64().unwrap();
+        assert_eq!(metadata["preview_url"], format!("http://127.0.0.1:{}/", port));
+        assert_eq!(manager.ports().preview("job-preview").unwrap().port as u64, port);
+
+        tokio::time::sleep(Duration::from_millis(1500)).await;
+        let process_id = metadata["process_id"].as_str().unwrap();
+        assert_eq!(manager.output(process_id, 10).unwrap().lines, vec![port.to_string()]);
+        assert_eq!(manager.ports().preview("job-preview"), None);
+    }
+
+    #[test]
+    fn test_combined_output_keeps_streams_apart() {
+        let line = |line: &str, stderr| OutputLine { line: line.to_string(), stderr };
+        let output = vec![line("compiling", false), line("warning: unused", true), line("done", false)];
+        assert_eq!(combined_output(&output), "compiling\ndone\n--- stderr ---\nwarning: unused");
+        assert_eq!(combined_output(&output[1..2]), "warning: unused");
+        assert_eq!(combined_output(&[]), "");
+    }
 }
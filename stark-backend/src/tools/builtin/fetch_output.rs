@@ -0,0 +1,181 @@
+use crate::tools::output_store::{page_end, INLINE_OUTPUT_BYTES};
+use crate::tools::registry::Tool;
+use crate::tools::types::{
+    PropertySchema, ToolContext, ToolDefinition, ToolGroup, ToolInputSchema, ToolResult,
+};
+use async_trait::async_trait;
+use serde::Deserialize;
+use serde_json::{json, Value};
+use std::collections::HashMap;
+
+/// Fetch output tool - pages through tool output that was too long to return
+/// in full, by the output_ref given in its truncated preview
+pub struct FetchOutputTool {
+    definition: ToolDefinition,
+}
+
+impl FetchOutputTool {
+    pub fn new() -> Self {
+        let mut properties = HashMap::new();
+        properties.insert(
+            "output_ref".to_string(),
+            PropertySchema {
+                schema_type: "string".to_string(),
+                description: "Reference from a truncated tool output, e.g. \"out-3\"".to_string(),
+                default: None,
+                items: None,
+                enum_values: None,
+            },
+        );
+        properties.insert(
+            "offset".to_string(),
+            PropertySchema {
+                schema_type: "integer".to_string(),
+                description: "Byte offset to start reading at, as given in the preview or the previous page (default: 0)".to_string(),
+                default: Some(json!(0)),
+                items: None,
+                enum_values: None,
+            },
+        );
+        properties.insert(
+            "max_bytes".to_string(),
+            PropertySchema {
+                schema_type: "integer".to_string(),
+                description: format!(
+                    "Maximum size of the page in bytes (default and max: {}). Pages end at the last whole line that fits.",
+                    INLINE_OUTPUT_BYTES
+                ),
+                default: Some(json!(INLINE_OUTPUT_BYTES)),
+                items: None,
+                enum_values: None,
+            },
+        );
+
+        FetchOutputTool {
+            definition: ToolDefinition {
+                name: "fetch_output".to_string(),
+                description: "Read the full output of an earlier tool call that was truncated. Truncated outputs end with an output_ref and the offset of the omitted part; pass them here and keep passing the returned offset to page through the rest. Prefer narrowing the original command (grep, tail) when you only need a few lines.".to_string(),
+                input_schema: ToolInputSchema {
+                    schema_type: "object".to_string(),
+                    properties,
+                    required: vec!["output_ref".to_string()],
+                },
+                group: ToolGroup::Development,
+            },
+        }
+    }
+}
+
+impl Default for FetchOutputTool {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct FetchOutputParams {
+    output_ref: String,
+    offset: Option<usize>,
+    max_bytes: Option<usize>,
+}
+
+#[async_trait]
+impl Tool for FetchOutputTool {
+    fn definition(&self) -> ToolDefinition {
+        self.definition.clone()
+    }
+
+    fn is_read_only(&self) -> bool {
+        true
+    }
+
+    async fn execute(&self, params: Value, context: &ToolContext) -> ToolResult {
+        let params: FetchOutputParams = match serde_json::from_value(params) {
+            Ok(p) => p,
+            Err(e) => return ToolResult::error(format!("Invalid parameters: {}", e)),
+        };
+
+        let Some(store) = &context.output_store else {
+            return ToolResult::error("No stored outputs here: tool output is only truncated in agent runs");
+        };
+        let content = match store.get(&params.output_ref).await {
+            Ok(Some(content)) => content,
+            Ok(None) => return ToolResult::error(format!("No output stored as '{}'", params.output_ref)),
+            Err(e) => return ToolResult::error(e),
+        };
+
+        let start = params.offset.unwrap_or(0);
+        if start >= content.len() {
+            return ToolResult::success(format!(
+                "[Empty: offset {} is past the end of the output ({} bytes)]",
+                start,
+                content.len()
+            ));
+        }
+        if !content.is_char_boundary(start) {
+            return ToolResult::error(format!(
+                "Offset {} is inside a character; use an offset given in a preview or a previous page",
+                start
+            ));
+        }
+
+        let max_bytes = params.max_bytes.unwrap_or(INLINE_OUTPUT_BYTES).clamp(1, INLINE_OUTPUT_BYTES);
+        let end = page_end(&content, start, max_bytes);
+        let mut result = content[start..end].trim_end_matches('\n').to_string();
+        if end < content.len() {
+            result.push_str(&format!(
+                "\n\n[Showing bytes {}-{} of {}. To read on, call fetch_output again with offset: {}]",
+                start,
+                end,
+                content.len(),
+                end
+            ));
+        }
+
+        ToolResult::success(result).with_metadata(json!({
+            "output_ref": params.output_ref,
+            "total_bytes": content.len(),
+            "offset": start,
+            "end": end,
+            "next_offset": (end < content.len()).then_some(end)
+        }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tools::OutputStore;
+    use tempfile::TempDir;
+
+    #[tokio::test]
+    async fn test_fetch_output_pages() {
+        let temp_dir = TempDir::new().unwrap();
+        let store = OutputStore::new(temp_dir.path());
+        let output_ref = store.put("alpha\nbeta\ngamma\n").await.unwrap();
+        let context = ToolContext::new().with_output_store(store);
+        let tool = FetchOutputTool::new();
+
+        let result = tool
+            .execute(json!({ "output_ref": output_ref, "max_bytes": 12 }), &context)
+            .await;
+        assert!(result.success);
+        assert!(result.content.starts_with("alpha\nbeta\n\n[Showing bytes 0-11 of 17."));
+        assert_eq!(result.metadata.unwrap()["next_offset"], 11);
+
+        let result = tool
+            .execute(json!({ "output_ref": output_ref, "offset": 11 }), &context)
+            .await;
+        assert_eq!(result.content, "gamma");
+
+        let result = tool.execute(json!({ "output_ref": "out-7" }), &context).await;
+        assert!(!result.success);
+    }
+
+    #[tokio::test]
+    async fn test_fetch_output_without_store() {
+        let tool = FetchOutputTool::new();
+        let result = tool.execute(json!({ "output_ref": "out-1" }), &ToolContext::new()).await;
+        assert!(!result.success);
+    }
+}
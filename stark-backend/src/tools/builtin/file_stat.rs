@@ -0,0 +1,243 @@
+use super::read_file::looks_binary;
+use crate::tools::registry::Tool;
+use crate::tools::types::{
+    PropertySchema, ToolContext, ToolDefinition, ToolGroup, ToolInputSchema, ToolResult,
+};
+use crate::tools::workspace_path::WorkspacePath;
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use serde::Deserialize;
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use tokio::io::AsyncReadExt;
+
+/// Chunk size used when sniffing and counting lines
+const READ_CHUNK_SIZE: usize = 64 * 1024;
+
+/// File stat tool - reports size, line count, modification time and binary/text
+/// classification so the agent can decide how to read a file
+pub struct FileStatTool {
+    definition: ToolDefinition,
+}
+
+impl FileStatTool {
+    pub fn new() -> Self {
+        let mut properties = HashMap::new();
+        properties.insert(
+            "path".to_string(),
+            PropertySchema {
+                schema_type: "string".to_string(),
+                description: "Path to the file or directory (relative to workspace directory)"
+                    .to_string(),
+                default: None,
+                items: None,
+                enum_values: None,
+            },
+        );
+
+        FileStatTool {
+            definition: ToolDefinition {
+                name: "file_stat".to_string(),
+                description: "Get information about a file without reading it: whether it exists, size in bytes, line count (text files), last-modified time, and whether it is binary or text. Use this before reading files that might be large or binary.".to_string(),
+                input_schema: ToolInputSchema {
+                    schema_type: "object".to_string(),
+                    properties,
+                    required: vec!["path".to_string()],
+                },
+                group: ToolGroup::Filesystem,
+            },
+        }
+    }
+
+    /// Read the file in chunks, classifying it from the first chunk and
+    /// counting lines for text files. Returns (is_binary, line_count).
+    async fn classify_and_count_lines(path: &Path) -> std::io::Result<(bool, Option<u64>)> {
+        let mut file = tokio::fs::File::open(path).await?;
+        let mut buf = vec![0u8; READ_CHUNK_SIZE];
+
+        let first = file.read(&mut buf).await?;
+        if first == 0 {
+            return Ok((false, Some(0)));
+        }
+        if looks_binary(&buf[..first]) {
+            return Ok((true, None));
+        }
+
+        let mut newlines = bytecount_newlines(&buf[..first]);
+        let mut last_byte = buf[first - 1];
+        loop {
+            let n = file.read(&mut buf).await?;
+            if n == 0 {
+                break;
+            }
+            newlines += bytecount_newlines(&buf[..n]);
+            last_byte = buf[n - 1];
+        }
+
+        // A final line without a trailing newline still counts
+        let lines = if last_byte == b'\n' { newlines } else { newlines + 1 };
+        Ok((false, Some(lines)))
+    }
+}
+
+fn bytecount_newlines(bytes: &[u8]) -> u64 {
+    bytes.iter().filter(|&&b| b == b'\n').count() as u64
+}
+
+impl Default for FileStatTool {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct FileStatParams {
+    path: String,
+}
+
+#[async_trait]
+impl Tool for FileStatTool {
+    fn definition(&self) -> ToolDefinition {
+        self.definition.clone()
+    }
+
+    async fn execute(&self, params: Value, context: &ToolContext) -> ToolResult {
+        let params: FileStatParams = match serde_json::from_value(params) {
+            Ok(p) => p,
+            Err(e) => return ToolResult::error(format!("Invalid parameters: {}", e)),
+        };
+
+        // Get workspace directory from context or use current directory
+        let workspace = context
+            .workspace_dir
+            .as_ref()
+            .map(PathBuf::from)
+            .unwrap_or_else(|| std::env::current_dir().unwrap_or_else(|_| PathBuf::from(".")));
+
+        // Resolve the path (symlinks and `..` included) and ensure it stays within workspace
+        let resolved = match WorkspacePath::resolve(&workspace, &params.path) {
+            Ok(p) => p,
+            Err(e) => return ToolResult::error(e),
+        };
+        let canonical_path = resolved.path().to_path_buf();
+
+        if !canonical_path.exists() {
+            return ToolResult::success(format!("{} does not exist", params.path)).with_metadata(
+                json!({
+                    "path": params.path,
+                    "exists": false
+                }),
+            );
+        }
+
+        let metadata = match tokio::fs::metadata(&canonical_path).await {
+            Ok(m) => m,
+            Err(e) => return ToolResult::error(format!("Failed to stat path: {}", e)),
+        };
+
+        let modified = metadata
+            .modified()
+            .ok()
+            .map(|t| DateTime::<Utc>::from(t).to_rfc3339());
+
+        if metadata.is_dir() {
+            return ToolResult::success(format!("{} is a directory", params.path)).with_metadata(
+                json!({
+                    "path": params.path,
+                    "exists": true,
+                    "kind": "directory",
+                    "modified": modified
+                }),
+            );
+        }
+
+        let size = metadata.len();
+        let (is_binary, line_count) = match Self::classify_and_count_lines(&canonical_path).await {
+            Ok(r) => r,
+            Err(e) => return ToolResult::error(format!("Failed to read file: {}", e)),
+        };
+
+        let mut summary = format!(
+            "{}: {} file, {} bytes",
+            params.path,
+            if is_binary { "binary" } else { "text" },
+            size
+        );
+        if let Some(lines) = line_count {
+            summary.push_str(&format!(", {} lines", lines));
+        }
+        if let Some(ref m) = modified {
+            summary.push_str(&format!(", modified {}", m));
+        }
+
+        ToolResult::success(summary).with_metadata(json!({
+            "path": params.path,
+            "exists": true,
+            "kind": "file",
+            "size_bytes": size,
+            "line_count": line_count,
+            "binary": is_binary,
+            "modified": modified
+        }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn context_for(dir: &TempDir) -> ToolContext {
+        ToolContext::new().with_workspace(dir.path().to_string_lossy().to_string())
+    }
+
+    #[tokio::test]
+    async fn test_file_stat_text_file() {
+        let tool = FileStatTool::new();
+        let temp_dir = TempDir::new().unwrap();
+        std::fs::write(temp_dir.path().join("notes.txt"), "one\ntwo\nthree").unwrap();
+
+        let result = tool
+            .execute(json!({ "path": "notes.txt" }), &context_for(&temp_dir))
+            .await;
+
+        assert!(result.success);
+        let meta = result.metadata.unwrap();
+        assert_eq!(meta["exists"], true);
+        assert_eq!(meta["binary"], false);
+        assert_eq!(meta["size_bytes"], 13);
+        assert_eq!(meta["line_count"], 3);
+        assert!(meta["modified"].is_string());
+    }
+
+    #[tokio::test]
+    async fn test_file_stat_binary_and_missing() {
+        let tool = FileStatTool::new();
+        let temp_dir = TempDir::new().unwrap();
+        std::fs::write(temp_dir.path().join("blob.bin"), [0u8, 159, 146, 150, 0, 1]).unwrap();
+        let context = context_for(&temp_dir);
+
+        let result = tool.execute(json!({ "path": "blob.bin" }), &context).await;
+        let meta = result.metadata.unwrap();
+        assert_eq!(meta["binary"], true);
+        assert!(meta["line_count"].is_null());
+
+        let result = tool.execute(json!({ "path": "nope.txt" }), &context).await;
+        assert!(result.success);
+        assert_eq!(result.metadata.unwrap()["exists"], false);
+    }
+
+    #[tokio::test]
+    async fn test_file_stat_outside_workspace() {
+        let tool = FileStatTool::new();
+        let temp_dir = TempDir::new().unwrap();
+        let context = context_for(&temp_dir);
+
+        let result = tool
+            .execute(json!({ "path": "../definitely-missing.txt" }), &context)
+            .await;
+        assert!(!result.success);
+        assert!(result.error.unwrap().contains("outside the workspace"));
+    }
+}
@@ -1,15 +1,15 @@
+use crate::tools::cache::CachePolicy;
 use crate::tools::registry::Tool;
+use crate::tools::search::{self, FileHit, WalkOptions};
 use crate::tools::types::{
     PropertySchema, ToolContext, ToolDefinition, ToolGroup, ToolInputSchema, ToolResult,
 };
 use async_trait::async_trait;
-use glob::glob as glob_match;
 use serde::Deserialize;
 use serde_json::{json, Value};
 use std::collections::HashMap;
-use std::fs;
 use std::path::{Path, PathBuf};
-use std::time::SystemTime;
+use std::time::Duration;
 
 /// Glob tool for file pattern matching
 /// Returns files matching a glob pattern, sorted by modification time
@@ -85,6 +85,18 @@ impl GlobTool {
             },
         );
 
+        properties.insert(
+            "respect_gitignore".to_string(),
+            PropertySchema {
+                schema_type: "boolean".to_string(),
+                description: "If true, skip files ignored by .gitignore and .ignore files (default: true)"
+                    .to_string(),
+                default: Some(json!(true)),
+                items: None,
+                enum_values: None,
+            },
+        );
+
         GlobTool {
             definition: ToolDefinition {
                 name: "glob".to_string(),
@@ -113,12 +125,7 @@ struct GlobParams {
     sort_by: Option<String>,
     limit: Option<usize>,
     include_hidden: Option<bool>,
-}
-
-struct FileEntry {
-    path: PathBuf,
-    modified: SystemTime,
-    size: u64,
+    respect_gitignore: Option<bool>,
 }
 
 #[async_trait]
@@ -127,6 +134,15 @@ impl Tool for GlobTool {
         self.definition.clone()
     }
 
+    fn is_read_only(&self) -> bool {
+        true
+    }
+
+    /// Listings are reused until the workspace changes
+    fn cache_policy(&self, _params: &Value) -> CachePolicy {
+        CachePolicy::UntilWorkspaceChanges(Duration::from_secs(120))
+    }
+
     async fn execute(&self, params: Value, context: &ToolContext) -> ToolResult {
         let params: GlobParams = match serde_json::from_value(params) {
             Ok(p) => p,
@@ -172,47 +188,22 @@ impl Tool for GlobTool {
             ));
         }
 
-        // Build full glob pattern
-        let full_pattern = canonical_base.join(&params.pattern);
-        let pattern_str = full_pattern.to_string_lossy();
-
         // Collect matching files
-        let include_hidden = params.include_hidden.unwrap_or(false);
-        let mut files: Vec<FileEntry> = Vec::new();
-
-        match glob_match(&pattern_str) {
-            Ok(paths) => {
-                for entry in paths.filter_map(Result::ok) {
-                    // Security: verify each path is within workspace
-                    if let Ok(canonical) = entry.canonicalize() {
-                        if !canonical.starts_with(&canonical_workspace) {
-                            continue;
-                        }
-                    }
-
-                    // Skip hidden files unless requested
-                    if !include_hidden {
-                        if let Some(name) = entry.file_name() {
-                            if name.to_string_lossy().starts_with('.') {
-                                continue;
-                            }
-                        }
-                    }
-
-                    // Get file metadata
-                    if let Ok(metadata) = fs::metadata(&entry) {
-                        if metadata.is_file() {
-                            files.push(FileEntry {
-                                path: entry,
-                                modified: metadata.modified().unwrap_or(SystemTime::UNIX_EPOCH),
-                                size: metadata.len(),
-                            });
-                        }
-                    }
-                }
-            }
-            Err(e) => return ToolResult::error(format!("Invalid glob pattern: {}", e)),
-        }
+        let options = WalkOptions {
+            include_hidden: params.include_hidden.unwrap_or(false),
+            respect_gitignore: params.respect_gitignore.unwrap_or(true),
+        };
+        let pattern = params.pattern.clone();
+        let (root, base) = (canonical_workspace.clone(), canonical_base.clone());
+        let mut files: Vec<FileHit> = match tokio::task::spawn_blocking(move || {
+            search::find_files(&root, &base, &pattern, options)
+        })
+        .await
+        {
+            Ok(Ok(files)) => files,
+            Ok(Err(e)) => return ToolResult::error(e),
+            Err(e) => return ToolResult::error(format!("Search failed: {}", e)),
+        };
 
         // Sort files
         let sort_by = params.sort_by.as_deref().unwrap_or("modified");
@@ -241,10 +232,6 @@ impl Tool for GlobTool {
         let output: Vec<String> = files
             .iter()
             .map(|f| {
-                let relative = f
-                    .path
-                    .strip_prefix(&canonical_workspace)
-                    .unwrap_or(&f.path);
                 let size_str = if f.size < 1024 {
                     format!("{}B", f.size)
                 } else if f.size < 1024 * 1024 {
@@ -252,7 +239,7 @@ impl Tool for GlobTool {
                 } else {
                     format!("{:.1}MB", f.size as f64 / (1024.0 * 1024.0))
                 };
-                format!("{} ({})", relative.display(), size_str)
+                format!("{} ({})", f.relative, size_str)
             })
             .collect();
 
@@ -261,7 +248,8 @@ impl Tool for GlobTool {
             "base_path": canonical_base.display().to_string(),
             "count": total_found,
             "sort_by": sort_by,
-            "limited": total_found == limit
+            "limited": total_found == limit,
+            "files": files.iter().map(|f| json!({ "path": f.relative, "size": f.size })).collect::<Vec<_>>()
         }))
     }
 }
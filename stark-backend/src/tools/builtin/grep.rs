@@ -1,19 +1,19 @@
+use crate::tools::cache::CachePolicy;
 use crate::tools::registry::Tool;
+use crate::tools::search::{self, GrepOptions, WalkOptions};
 use crate::tools::types::{
     PropertySchema, ToolContext, ToolDefinition, ToolGroup, ToolInputSchema, ToolResult,
 };
+use crate::utils::truncate_chars;
 use async_trait::async_trait;
-use regex::Regex;
 use serde::Deserialize;
 use serde_json::{json, Value};
 use std::collections::HashMap;
 use std::path::{Path, PathBuf};
-use std::process::Stdio;
-use tokio::process::Command;
-use walkdir::WalkDir;
+use std::time::Duration;
 
 /// Grep tool for content search within files
-/// Uses ripgrep (rg) if available, falls back to Rust-native search
+/// Searches natively (no rg/grep binary needed) and skips gitignored and binary files
 pub struct GrepTool {
     definition: ToolDefinition,
 }
@@ -107,6 +107,18 @@ impl GrepTool {
             },
         );
 
+        properties.insert(
+            "respect_gitignore".to_string(),
+            PropertySchema {
+                schema_type: "boolean".to_string(),
+                description: "If true, skip files ignored by .gitignore and .ignore files (default: true)"
+                    .to_string(),
+                default: Some(json!(true)),
+                items: None,
+                enum_values: None,
+            },
+        );
+
         GrepTool {
             definition: ToolDefinition {
                 name: "grep".to_string(),
@@ -120,204 +132,6 @@ impl GrepTool {
             },
         }
     }
-
-    /// Check if ripgrep is available
-    async fn has_ripgrep() -> bool {
-        Command::new("rg")
-            .arg("--version")
-            .stdout(Stdio::null())
-            .stderr(Stdio::null())
-            .status()
-            .await
-            .map(|s| s.success())
-            .unwrap_or(false)
-    }
-
-    /// Run search using ripgrep
-    async fn search_with_ripgrep(
-        &self,
-        pattern: &str,
-        search_path: &Path,
-        params: &GrepParams,
-    ) -> Result<String, String> {
-        let mut cmd = Command::new("rg");
-
-        // Add pattern and path
-        cmd.arg(pattern).arg(search_path);
-
-        // Output mode
-        match params.output_mode.as_deref() {
-            Some("files_with_matches") => {
-                cmd.arg("-l");
-            }
-            Some("count") => {
-                cmd.arg("-c");
-            }
-            _ => {
-                cmd.arg("-n"); // Line numbers
-                if let Some(ctx) = params.context {
-                    if ctx > 0 {
-                        cmd.arg("-C").arg(ctx.to_string());
-                    }
-                }
-            }
-        }
-
-        // Case insensitivity
-        if params.case_insensitive.unwrap_or(false) {
-            cmd.arg("-i");
-        }
-
-        // Glob filter
-        if let Some(ref glob) = params.glob {
-            cmd.arg("-g").arg(glob);
-        }
-
-        // Max results
-        let max_results = params.max_results.unwrap_or(100);
-        cmd.arg("-m").arg(max_results.to_string());
-
-        // Run the command
-        let output = cmd
-            .output()
-            .await
-            .map_err(|e| format!("Failed to run ripgrep: {}", e))?;
-
-        let stdout = String::from_utf8_lossy(&output.stdout);
-        let stderr = String::from_utf8_lossy(&output.stderr);
-
-        if !output.status.success() && stdout.is_empty() {
-            if stderr.contains("No such file or directory") {
-                return Err("Path not found".to_string());
-            }
-            // rg returns exit code 1 for "no matches" which is normal
-            if output.status.code() == Some(1) {
-                return Ok("No matches found.".to_string());
-            }
-            return Err(format!("ripgrep error: {}", stderr));
-        }
-
-        if stdout.is_empty() {
-            return Ok("No matches found.".to_string());
-        }
-
-        Ok(stdout.to_string())
-    }
-
-    /// Run search using native Rust implementation (fallback)
-    fn search_native(
-        &self,
-        pattern: &str,
-        search_path: &Path,
-        params: &GrepParams,
-    ) -> Result<String, String> {
-        let regex = if params.case_insensitive.unwrap_or(false) {
-            Regex::new(&format!("(?i){}", pattern))
-        } else {
-            Regex::new(pattern)
-        }
-        .map_err(|e| format!("Invalid regex pattern: {}", e))?;
-
-        let max_results = params.max_results.unwrap_or(100);
-        let context = params.context.unwrap_or(0);
-        let output_mode = params.output_mode.as_deref().unwrap_or("content");
-
-        let glob_pattern = params.glob.as_ref().map(|g| {
-            glob::Pattern::new(g).unwrap_or_else(|_| glob::Pattern::new("*").unwrap())
-        });
-
-        let mut results = Vec::new();
-        let mut match_count = 0;
-
-        for entry in WalkDir::new(search_path)
-            .follow_links(false)
-            .into_iter()
-            .filter_map(|e| e.ok())
-        {
-            if match_count >= max_results {
-                break;
-            }
-
-            let path = entry.path();
-            if !path.is_file() {
-                continue;
-            }
-
-            // Check glob pattern
-            if let Some(ref gp) = glob_pattern {
-                let file_name = path.file_name().unwrap_or_default().to_string_lossy();
-                if !gp.matches(&file_name) {
-                    continue;
-                }
-            }
-
-            // Skip binary files
-            if let Ok(content) = std::fs::read_to_string(path) {
-                let lines: Vec<&str> = content.lines().collect();
-                let mut file_matches = Vec::new();
-                let mut file_count = 0;
-
-                for (line_num, line) in lines.iter().enumerate() {
-                    if regex.is_match(line) {
-                        file_count += 1;
-                        match_count += 1;
-
-                        if match_count > max_results {
-                            break;
-                        }
-
-                        match output_mode {
-                            "files_with_matches" => {
-                                // Will handle after loop
-                            }
-                            "count" => {
-                                // Will handle after loop
-                            }
-                            _ => {
-                                // Content mode with context
-                                let start = line_num.saturating_sub(context);
-                                let end = (line_num + context + 1).min(lines.len());
-
-                                for i in start..end {
-                                    let prefix = if i == line_num { ">" } else { " " };
-                                    file_matches.push(format!(
-                                        "{}{}:{}: {}",
-                                        prefix,
-                                        path.display(),
-                                        i + 1,
-                                        lines[i]
-                                    ));
-                                }
-                                if context > 0 && end < lines.len() {
-                                    file_matches.push("--".to_string());
-                                }
-                            }
-                        }
-                    }
-                }
-
-                if file_count > 0 {
-                    match output_mode {
-                        "files_with_matches" => {
-                            results.push(path.display().to_string());
-                        }
-                        "count" => {
-                            results.push(format!("{}:{}", path.display(), file_count));
-                        }
-                        _ => {
-                            results.extend(file_matches);
-                        }
-                    }
-                }
-            }
-        }
-
-        if results.is_empty() {
-            return Ok("No matches found.".to_string());
-        }
-
-        Ok(results.join("\n"))
-    }
 }
 
 impl Default for GrepTool {
@@ -335,6 +149,7 @@ struct GrepParams {
     context: Option<usize>,
     case_insensitive: Option<bool>,
     max_results: Option<usize>,
+    respect_gitignore: Option<bool>,
 }
 
 #[async_trait]
@@ -343,6 +158,15 @@ impl Tool for GrepTool {
         self.definition.clone()
     }
 
+    fn is_read_only(&self) -> bool {
+        true
+    }
+
+    /// Listings are reused until the workspace changes
+    fn cache_policy(&self, _params: &Value) -> CachePolicy {
+        CachePolicy::UntilWorkspaceChanges(Duration::from_secs(120))
+    }
+
     async fn execute(&self, params: Value, context: &ToolContext) -> ToolResult {
         let params: GrepParams = match serde_json::from_value(params) {
             Ok(p) => p,
@@ -389,30 +213,67 @@ impl Tool for GrepTool {
         }
 
         // Run search
-        let result = if Self::has_ripgrep().await {
-            self.search_with_ripgrep(&params.pattern, &canonical_path, &params)
-                .await
-        } else {
-            self.search_native(&params.pattern, &canonical_path, &params)
+        let output_mode = params.output_mode.clone().unwrap_or_else(|| "content".to_string());
+        let options = GrepOptions {
+            case_insensitive: params.case_insensitive.unwrap_or(false),
+            glob: params.glob.clone(),
+            context: if output_mode == "content" { params.context.unwrap_or(0) } else { 0 },
+            max_results: params.max_results.unwrap_or(100),
+            first_match_only: output_mode == "files_with_matches",
+            walk: WalkOptions {
+                include_hidden: false,
+                respect_gitignore: params.respect_gitignore.unwrap_or(true),
+            },
+        };
+        let pattern = params.pattern.clone();
+        let outcome = match tokio::task::spawn_blocking(move || {
+            search::grep(&canonical_workspace, &canonical_path, &pattern, &options)
+        })
+        .await
+        {
+            Ok(Ok(outcome)) => outcome,
+            Ok(Err(e)) => return ToolResult::error(e),
+            Err(e) => return ToolResult::error(format!("Search failed: {}", e)),
         };
 
-        match result {
-            Ok(output) => {
-                // Truncate if too long (keep small to avoid context bloat)
-                let max_output = 12000;
-                if output.len() > max_output {
-                    let truncated = &output[..max_output];
-                    ToolResult::success(format!(
-                        "{}\n\n[Output truncated. {} more characters not shown. Use more specific patterns.]",
-                        truncated,
-                        output.len() - max_output
-                    ))
-                } else {
-                    ToolResult::success(output)
-                }
-            }
-            Err(e) => ToolResult::error(e),
-        }
+        let metadata = json!({
+            "pattern": params.pattern,
+            "count": outcome.matches.len(),
+            "truncated": outcome.truncated,
+            "matches": outcome.matches,
+        });
+        let output = match output_mode.as_str() {
+            _ if outcome.matches.is_empty() => "No matches found.".to_string(),
+            "files_with_matches" => outcome
+                .counts()
+                .into_iter()
+                .map(|(file, _)| file)
+                .collect::<Vec<_>>()
+                .join("\n"),
+            "count" => outcome
+                .counts()
+                .into_iter()
+                .map(|(file, count)| format!("{}:{}", file, count))
+                .collect::<Vec<_>>()
+                .join("\n"),
+            _ => outcome.render().trim_end().to_string(),
+        };
+
+        // Truncate if too long (keep small to avoid context bloat)
+        let max_output = 12000;
+        let truncated = truncate_chars(&output, max_output);
+        let content = if truncated.len() < output.len() {
+            format!(
+                "{}\n\n[Output truncated. {} more characters not shown. Use more specific patterns.]",
+                truncated,
+                output[truncated.len()..].chars().count()
+            )
+        } else if outcome.truncated {
+            format!("{}\n\n[Stopped at max_results. Use more specific patterns or a glob filter.]", output)
+        } else {
+            output
+        };
+        ToolResult::success(content).with_metadata(metadata)
     }
 }
 
@@ -436,7 +297,11 @@ mod tests {
         let result = tool.execute(json!({ "pattern": "main" }), &context).await;
 
         assert!(result.success);
-        assert!(result.content.contains("main"));
+        assert_eq!(result.content, "test.rs:1:fn main() {");
+        let metadata = result.metadata.unwrap();
+        assert_eq!(metadata["matches"][0]["file"], "test.rs");
+        assert_eq!(metadata["matches"][0]["line"], 1);
+        assert_eq!(metadata["matches"][0]["match"], "fn main() {");
     }
 
     #[tokio::test]
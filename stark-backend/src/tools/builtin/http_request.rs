@@ -0,0 +1,477 @@
+//! HTTP request tool for REST APIs
+//!
+//! Unlike web_fetch, which extracts readable content from pages, this returns
+//! the status, the useful response headers and the body as-is. Credentials
+//! come from auth profiles (`config/http_auth_profiles.ron`): the model names
+//! a profile, never the secret, and each profile's secret is only sent to the
+//! hosts it lists.
+
+use crate::tools::http_retry::{is_reqwest_error_retryable, HttpRetryManager};
+use crate::tools::presets;
+use crate::tools::registry::Tool;
+use crate::tools::types::{
+    PropertySchema, ToolContext, ToolDefinition, ToolGroup, ToolInputSchema, ToolResult,
+};
+use crate::tools::url_guard::validate_public_url;
+use async_trait::async_trait;
+use serde::Deserialize;
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+
+/// Largest response body read, in bytes
+const MAX_RESPONSE_BYTES: usize = 200_000;
+
+/// Response headers worth showing to the model
+const SHOWN_HEADERS: &[&str] = &[
+    "content-type",
+    "location",
+    "link",
+    "retry-after",
+    "x-ratelimit-remaining",
+    "x-ratelimit-reset",
+    "ratelimit-remaining",
+    "ratelimit-reset",
+];
+
+/// Tool for calling REST APIs
+pub struct HttpRequestTool {
+    definition: ToolDefinition,
+}
+
+impl HttpRequestTool {
+    pub fn new() -> Self {
+        let mut properties = HashMap::new();
+
+        properties.insert(
+            "method".to_string(),
+            PropertySchema {
+                schema_type: "string".to_string(),
+                description: "HTTP method (default: GET)".to_string(),
+                default: Some(json!("GET")),
+                items: None,
+                enum_values: Some(vec![
+                    "GET".to_string(),
+                    "POST".to_string(),
+                    "PUT".to_string(),
+                    "PATCH".to_string(),
+                    "DELETE".to_string(),
+                    "HEAD".to_string(),
+                ]),
+            },
+        );
+
+        properties.insert(
+            "url".to_string(),
+            PropertySchema {
+                schema_type: "string".to_string(),
+                description: "Request URL (http or https, public hosts only)".to_string(),
+                default: None,
+                items: None,
+                enum_values: None,
+            },
+        );
+
+        properties.insert(
+            "query".to_string(),
+            PropertySchema {
+                schema_type: "object".to_string(),
+                description: "Query parameters to append to the URL".to_string(),
+                default: None,
+                items: None,
+                enum_values: None,
+            },
+        );
+
+        properties.insert(
+            "headers".to_string(),
+            PropertySchema {
+                schema_type: "object".to_string(),
+                description: "Request headers".to_string(),
+                default: None,
+                items: None,
+                enum_values: None,
+            },
+        );
+
+        properties.insert(
+            "body".to_string(),
+            PropertySchema {
+                schema_type: "object".to_string(),
+                description: "Request body. Objects and arrays are sent as JSON; a string is sent as-is (set Content-Type in headers).".to_string(),
+                default: None,
+                items: None,
+                enum_values: None,
+            },
+        );
+
+        properties.insert(
+            "auth".to_string(),
+            PropertySchema {
+                schema_type: "string".to_string(),
+                description: format!(
+                    "Auth profile that adds the stored credentials. Available: {}",
+                    profile_names().join(", ")
+                ),
+                default: None,
+                items: None,
+                enum_values: None,
+            },
+        );
+
+        properties.insert(
+            "timeout".to_string(),
+            PropertySchema {
+                schema_type: "integer".to_string(),
+                description: "Timeout in seconds (default: 30, max: 120)".to_string(),
+                default: Some(json!(30)),
+                items: None,
+                enum_values: None,
+            },
+        );
+
+        properties.insert(
+            "max_bytes".to_string(),
+            PropertySchema {
+                schema_type: "integer".to_string(),
+                description: format!(
+                    "Maximum response body bytes to return (default: 20000, max: {})",
+                    MAX_RESPONSE_BYTES
+                ),
+                default: Some(json!(20000)),
+                items: None,
+                enum_values: None,
+            },
+        );
+
+        HttpRequestTool {
+            definition: ToolDefinition {
+                name: "http_request".to_string(),
+                description: "Call a REST API and get the status, key headers and raw body back (JSON is pretty-printed). Use auth to add stored credentials instead of putting secrets in headers. Use web_fetch to read web pages instead.".to_string(),
+                input_schema: ToolInputSchema {
+                    schema_type: "object".to_string(),
+                    properties,
+                    required: vec!["url".to_string()],
+                },
+                group: ToolGroup::Web,
+            },
+        }
+    }
+}
+
+impl Default for HttpRequestTool {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct HttpRequestParams {
+    method: Option<String>,
+    url: String,
+    query: Option<HashMap<String, Value>>,
+    headers: Option<HashMap<String, String>>,
+    body: Option<Value>,
+    auth: Option<String>,
+    timeout: Option<u64>,
+    max_bytes: Option<usize>,
+}
+
+/// Sorted names of the configured auth profiles
+fn profile_names() -> Vec<String> {
+    let mut names: Vec<String> = presets::http_auth_profiles().keys().cloned().collect();
+    names.sort();
+    names
+}
+
+/// Replace every occurrence of `secret` with a placeholder
+fn redact(text: &str, secret: Option<&str>) -> String {
+    match secret {
+        Some(s) if !s.is_empty() => text.replace(s, "[REDACTED]"),
+        _ => text.to_string(),
+    }
+}
+
+/// Render a response body: pretty JSON when complete and parseable,
+/// otherwise text, or a note for binary content
+fn render_body(bytes: &[u8], content_type: &str, truncated: bool) -> String {
+    if bytes.is_empty() {
+        return "(empty body)".to_string();
+    }
+    let is_text = content_type.is_empty()
+        || content_type.starts_with("text/")
+        || ["json", "xml", "javascript", "x-www-form-urlencoded", "yaml", "csv"]
+            .iter()
+            .any(|t| content_type.contains(t));
+    if !is_text && std::str::from_utf8(bytes).is_err() {
+        return format!("({} bytes of binary content)", bytes.len());
+    }
+    let json = Some(bytes)
+        .filter(|_| !truncated && content_type.contains("json"))
+        .and_then(|b| serde_json::from_slice::<Value>(b).ok());
+    if let Some(value) = json {
+        return serde_json::to_string_pretty(&value).unwrap_or_default();
+    }
+    let mut text = String::from_utf8_lossy(bytes).into_owned();
+    if truncated {
+        text.push_str(&format!("\n\n[Body truncated at {} bytes; raise max_bytes or request less data]", bytes.len()));
+    }
+    text
+}
+
+#[async_trait]
+impl Tool for HttpRequestTool {
+    fn definition(&self) -> ToolDefinition {
+        self.definition.clone()
+    }
+
+    async fn execute(&self, params: Value, context: &ToolContext) -> ToolResult {
+        let params: HttpRequestParams = match serde_json::from_value(params) {
+            Ok(p) => p,
+            Err(e) => return ToolResult::error(format!("Invalid parameters: {}", e)),
+        };
+
+        let mut url = match url::Url::parse(&params.url) {
+            Ok(u) if matches!(u.scheme(), "http" | "https") => u,
+            Ok(_) => return ToolResult::error("URL must start with http:// or https://"),
+            Err(e) => return ToolResult::error(format!("Invalid URL: {}", e)),
+        };
+        if let Err(e) = validate_public_url(&url) {
+            return ToolResult::error(e);
+        }
+        let host = url.host_str().unwrap_or_default().to_string();
+
+        let method = params.method.as_deref().unwrap_or("GET").to_uppercase();
+        let method = match reqwest::Method::from_bytes(method.as_bytes()) {
+            Ok(m) if matches!(method.as_str(), "GET" | "POST" | "PUT" | "PATCH" | "DELETE" | "HEAD") => m,
+            _ => return ToolResult::error(format!("Unsupported method: {}. Use GET, POST, PUT, PATCH, DELETE or HEAD.", method)),
+        };
+
+        if let Some(ref query) = params.query {
+            let mut pairs = url.query_pairs_mut();
+            for (key, value) in query {
+                let value = match value {
+                    Value::String(s) => s.clone(),
+                    other => other.to_string(),
+                };
+                pairs.append_pair(key, &value);
+            }
+        }
+
+        // Resolve the auth profile before anything is sent
+        let mut secret = None;
+        let mut auth_header = None;
+        if let Some(ref name) = params.auth {
+            let profile = match presets::http_auth_profiles().get(name) {
+                Some(p) => p,
+                None => {
+                    return ToolResult::error(format!(
+                        "Unknown auth profile '{}'. Available: {}",
+                        name,
+                        profile_names().join(", ")
+                    ))
+                }
+            };
+            if !profile.allows_host(&host) {
+                return ToolResult::error(format!(
+                    "Auth profile '{}' may only be used with {}, not {}",
+                    name,
+                    profile.hosts.join(", "),
+                    host
+                ));
+            }
+            let value = match context.get_api_key(&profile.key) {
+                Some(v) if !v.is_empty() => v,
+                _ => {
+                    return ToolResult::error(format!(
+                        "Auth profile '{}' needs the {} API key. Ask the user to add it on the API Keys page.",
+                        name, profile.key
+                    ))
+                }
+            };
+            match profile.query_param {
+                Some(ref param) => {
+                    url.query_pairs_mut().append_pair(param, &value);
+                }
+                None => auth_header = Some((profile.header.clone(), format!("{}{}", profile.prefix, value))),
+            }
+            secret = Some(value);
+        }
+
+        let timeout_secs = params.timeout.unwrap_or(30).clamp(1, 120);
+        let max_bytes = params.max_bytes.unwrap_or(20_000).clamp(1, MAX_RESPONSE_BYTES);
+
+        // Redirects are followed only on the same host, so credentials never
+        // travel to another server and a redirect can't reach an internal one
+        let origin = host.clone();
+        let client = match reqwest::Client::builder()
+            .timeout(Duration::from_secs(timeout_secs))
+            .user_agent("StarkBot/1.0 (HTTP Request Tool)")
+            .redirect(reqwest::redirect::Policy::custom(move |attempt| {
+                if attempt.previous().len() >= 5 || attempt.url().host_str() != Some(origin.as_str()) {
+                    attempt.stop()
+                } else {
+                    attempt.follow()
+                }
+            }))
+            .build()
+        {
+            Ok(c) => c,
+            Err(e) => return ToolResult::error(format!("Failed to create HTTP client: {}", e)),
+        };
+
+        let mut request = client.request(method.clone(), url.clone());
+        for (key, value) in params.headers.iter().flatten() {
+            let name = match reqwest::header::HeaderName::from_bytes(key.as_bytes()) {
+                Ok(n) => n,
+                Err(_) => return ToolResult::error(format!("Invalid header name: {}", key)),
+            };
+            match reqwest::header::HeaderValue::from_str(value) {
+                Ok(v) => request = request.header(name, v),
+                Err(_) => return ToolResult::error(format!("Invalid value for header {}", key)),
+            }
+        }
+        if let Some((name, value)) = auth_header {
+            let mut value = match reqwest::header::HeaderValue::from_str(&value) {
+                Ok(v) => v,
+                Err(_) => return ToolResult::error("The stored API key can't be used in a header"),
+            };
+            value.set_sensitive(true);
+            request = request.header(name.as_str(), value);
+        }
+        match params.body {
+            None | Some(Value::Null) => {}
+            Some(Value::String(text)) => request = request.body(text),
+            Some(value) => request = request.json(&value),
+        }
+
+        let retry_manager = HttpRetryManager::global();
+        let idempotent = matches!(method.as_str(), "GET" | "HEAD");
+        let start = Instant::now();
+        let mut response = match request.send().await {
+            Ok(r) => r,
+            Err(e) => {
+                let message = redact(&format!("Request failed: {}", e), secret.as_deref());
+                if idempotent && is_reqwest_error_retryable(&e) {
+                    let delay = retry_manager.record_error(&host);
+                    return ToolResult::retryable_error(message, delay);
+                }
+                return ToolResult::error(message);
+            }
+        };
+
+        let status = response.status();
+        let headers = response.headers().clone();
+        let content_type = headers
+            .get("content-type")
+            .and_then(|v| v.to_str().ok())
+            .unwrap_or("")
+            .to_lowercase();
+
+        let mut body = Vec::new();
+        let mut truncated = false;
+        loop {
+            match response.chunk().await {
+                Ok(Some(chunk)) => {
+                    let room = max_bytes - body.len();
+                    if chunk.len() > room {
+                        body.extend_from_slice(&chunk[..room]);
+                        truncated = true;
+                        break;
+                    }
+                    body.extend_from_slice(&chunk);
+                }
+                Ok(None) => break,
+                Err(e) => {
+                    return ToolResult::error(redact(&format!("Failed to read response body: {}", e), secret.as_deref()))
+                }
+            }
+        }
+        let elapsed_ms = start.elapsed().as_millis() as u64;
+
+        let mut text = format!("HTTP {} ({} ms)", status, elapsed_ms);
+        for name in SHOWN_HEADERS {
+            if let Some(value) = headers.get(*name).and_then(|v| v.to_str().ok()) {
+                text.push_str(&format!("\n{}: {}", name, value));
+            }
+        }
+        if method != reqwest::Method::HEAD {
+            text.push_str("\n\n");
+            text.push_str(&render_body(&body, &content_type, truncated));
+        }
+        let text = redact(&text, secret.as_deref());
+
+        let metadata = json!({
+            "status": status.as_u16(),
+            "url": redact(url.as_str(), secret.as_deref()),
+            "content_type": content_type,
+            "bytes": body.len(),
+            "truncated": truncated,
+            "elapsed_ms": elapsed_ms,
+            "auth": params.auth,
+        });
+        if status.is_success() || status.is_redirection() {
+            retry_manager.record_success(&host);
+            return ToolResult::success(text).with_metadata(metadata);
+        }
+        if HttpRetryManager::is_retryable_status(status.as_u16()) && (idempotent || status.as_u16() == 429) {
+            let delay = retry_manager.record_error(&host);
+            return ToolResult::retryable_error(text, delay).with_metadata(metadata);
+        }
+        ToolResult::error(text).with_metadata(metadata)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_render_body() {
+        assert_eq!(render_body(br#"{"a":[1]}"#, "application/json; charset=utf-8", false), "{\n  \"a\": [\n    1\n  ]\n}");
+        assert_eq!(render_body(br#"{"a":"#, "application/json", true), "{\"a\":\n\n[Body truncated at 5 bytes; raise max_bytes or request less data]");
+        assert_eq!(render_body(&[0xff, 0x00, 0x10], "image/png", false), "(3 bytes of binary content)");
+        assert_eq!(render_body(b"", "text/plain", false), "(empty body)");
+        assert_eq!(redact("token abc123 here", Some("abc123")), "token [REDACTED] here");
+    }
+
+    #[test]
+    fn test_shipped_profiles() {
+        let config = include_str!("../../../../config/http_auth_profiles.ron");
+        let profiles: HashMap<String, presets::HttpAuthProfile> = ron::from_str(config).unwrap();
+        let github = &profiles["github"];
+        assert_eq!((github.header.as_str(), github.prefix.as_str()), ("Authorization", "Bearer "));
+        assert!(github.allows_host("API.github.com"));
+        assert!(!github.allows_host("api.github.com.evil.io"));
+        assert_eq!(profiles["coingecko"].header, "x-cg-demo-api-key");
+
+        let wildcard = presets::HttpAuthProfile { hosts: vec!["*.example.com".to_string()], ..github.clone() };
+        assert!(wildcard.allows_host("example.com") && wildcard.allows_host("eu.api.example.com"));
+        assert!(!wildcard.allows_host("badexample.com"));
+    }
+
+    #[tokio::test]
+    async fn test_auth_profile_checks() {
+        let tool = HttpRequestTool::new();
+        let context = ToolContext::new();
+
+        let result = tool
+            .execute(json!({ "url": "https://api.github.com/user", "auth": "nope" }), &context)
+            .await;
+        assert!(result.error.unwrap().contains("Unknown auth profile"));
+        let result = tool
+            .execute(json!({ "url": "https://example.com/steal", "auth": "github" }), &context)
+            .await;
+        assert!(result.error.unwrap().contains("may only be used with api.github.com"));
+        let result = tool
+            .execute(json!({ "url": "https://api.github.com/user", "auth": "github" }), &context)
+            .await;
+        assert!(result.error.unwrap().contains("needs the GITHUB_TOKEN API key"));
+        let result = tool.execute(json!({ "url": "http://127.0.0.1:8080/admin" }), &context).await;
+        assert!(result.error.unwrap().contains("blocked"));
+        let result = tool
+            .execute(json!({ "url": "https://example.com", "method": "TRACE" }), &context)
+            .await;
+        assert!(result.error.unwrap().contains("Unsupported method"));
+    }
+}
@@ -0,0 +1,163 @@
+//! Codebase indexing tool
+//!
+//! Embeds the workspace's source files for `code_search` (see
+//! `tools::code_index`). Only files that changed since the last run are
+//! embedded again.
+
+use crate::memory::embeddings::create_provider;
+use crate::memory::EmbeddingConfig;
+use crate::tools::code_index::{self, MAX_CHUNKS_PER_RUN};
+use crate::tools::registry::Tool;
+use crate::tools::types::{
+    PropertySchema, ToolContext, ToolDefinition, ToolGroup, ToolInputSchema, ToolResult,
+};
+use crate::tools::workspace_path::WorkspacePath;
+use async_trait::async_trait;
+use serde::Deserialize;
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::path::PathBuf;
+
+/// Tool that builds or refreshes the semantic index of the workspace
+pub struct IndexCodebaseTool {
+    definition: ToolDefinition,
+}
+
+impl IndexCodebaseTool {
+    pub fn new() -> Self {
+        let mut properties = HashMap::new();
+
+        properties.insert(
+            "path".to_string(),
+            PropertySchema {
+                schema_type: "string".to_string(),
+                description: "Directory to index, relative to the workspace (default: the whole workspace)".to_string(),
+                default: Some(json!(".")),
+                items: None,
+                enum_values: None,
+            },
+        );
+
+        properties.insert(
+            "force".to_string(),
+            PropertySchema {
+                schema_type: "boolean".to_string(),
+                description: "Embed every file again, not just the changed ones (default: false)".to_string(),
+                default: Some(json!(false)),
+                items: None,
+                enum_values: None,
+            },
+        );
+
+        IndexCodebaseTool {
+            definition: ToolDefinition {
+                name: "index_codebase".to_string(),
+                description: "Index the workspace's source files for code_search. Run it once before searching a large repo, and again after big changes; only changed files are re-embedded. Skips gitignored, hidden, binary and very large files.".to_string(),
+                input_schema: ToolInputSchema {
+                    schema_type: "object".to_string(),
+                    properties,
+                    required: vec![],
+                },
+                group: ToolGroup::Development,
+            },
+        }
+    }
+}
+
+impl Default for IndexCodebaseTool {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct IndexCodebaseParams {
+    path: Option<String>,
+    force: Option<bool>,
+}
+
+#[async_trait]
+impl Tool for IndexCodebaseTool {
+    fn definition(&self) -> ToolDefinition {
+        self.definition.clone()
+    }
+
+    async fn execute(&self, params: Value, context: &ToolContext) -> ToolResult {
+        let params: IndexCodebaseParams = match serde_json::from_value(params) {
+            Ok(p) => p,
+            Err(e) => return ToolResult::error(format!("Invalid parameters: {}", e)),
+        };
+
+        let Some(db) = context.database.clone() else {
+            return ToolResult::error("Database not available. Indexing requires database access.");
+        };
+        let workspace = context
+            .workspace_dir
+            .as_ref()
+            .map(PathBuf::from)
+            .unwrap_or_else(|| std::env::current_dir().unwrap_or_else(|_| PathBuf::from(".")));
+        let resolved = match WorkspacePath::resolve(&workspace, params.path.as_deref().unwrap_or(".")) {
+            Ok(p) => p,
+            Err(e) => return ToolResult::error(e),
+        };
+        if !resolved.path().is_dir() {
+            return ToolResult::error(format!("'{}' is not a directory", resolved.path().display()));
+        }
+
+        let config = match EmbeddingConfig::for_api(&db).await {
+            Ok(config) if config.is_enabled() => config,
+            Ok(_) => {
+                return ToolResult::error(
+                    "No embeddings API is configured, so the codebase can't be indexed. Use grep and glob instead.",
+                )
+            }
+            Err(e) => return ToolResult::error(format!("Failed to load agent settings: {}", e)),
+        };
+        let provider = create_provider(&config);
+
+        let root = resolved.root().to_path_buf();
+        let base = resolved.path().to_path_buf();
+        let prefix = base.strip_prefix(&root).unwrap_or(&base).to_string_lossy().replace('\\', "/");
+        let files = match tokio::task::spawn_blocking(move || code_index::source_files(&root, &base)).await {
+            Ok(Ok(files)) => files,
+            Ok(Err(e)) => return ToolResult::error(e),
+            Err(e) => return ToolResult::error(format!("Indexing failed: {}", e)),
+        };
+
+        let collection = code_index::collection(resolved.root());
+        let stats = match code_index::index(
+            &db,
+            provider.as_ref(),
+            &collection,
+            files,
+            &prefix,
+            params.force.unwrap_or(false),
+            config.batch_size,
+        )
+        .await
+        {
+            Ok(stats) => stats,
+            Err(e) => return ToolResult::error(format!("Indexing failed: {}", e)),
+        };
+
+        let mut output = format!(
+            "Indexed {} source file(s): {} embedded ({} chunks), {} unchanged, {} removed from the index.",
+            stats.files, stats.embedded_files, stats.chunks, stats.unchanged_files, stats.removed_files
+        );
+        if stats.deferred_files > 0 {
+            output.push_str(&format!(
+                " {} changed file(s) were left out to stay under {} chunks per run; run index_codebase again to add them.",
+                stats.deferred_files, MAX_CHUNKS_PER_RUN
+            ));
+        }
+        ToolResult::success(output).with_metadata(json!({
+            "files": stats.files,
+            "embedded_files": stats.embedded_files,
+            "chunks": stats.chunks,
+            "unchanged_files": stats.unchanged_files,
+            "removed_files": stats.removed_files,
+            "deferred_files": stats.deferred_files,
+            "model": provider.model_name(),
+        }))
+    }
+}
@@ -1,12 +1,18 @@
+use crate::tools::cache::CachePolicy;
 use crate::tools::registry::Tool;
 use crate::tools::types::{
     PropertySchema, ToolContext, ToolDefinition, ToolGroup, ToolInputSchema, ToolResult,
 };
+use crate::tools::workspace_path::{relative_to, WorkspacePath};
+use crate::utils::truncate_chars;
 use async_trait::async_trait;
+use ignore::WalkBuilder;
 use serde::Deserialize;
 use serde_json::{json, Value};
+use std::cmp::Ordering;
 use std::collections::HashMap;
 use std::path::{Path, PathBuf};
+use std::time::Duration;
 
 /// Intrinsic files that appear in all workspaces
 const INTRINSIC_FILES: &[(&str, &str)] = &[
@@ -20,6 +26,25 @@ const MAX_LIMIT: usize = 50;
 /// Maximum output size in bytes before truncation
 const MAX_OUTPUT_SIZE: usize = 4000;
 
+/// Recursive listings are trees meant to orient the agent in one call, so they get bigger pages
+const DEFAULT_TREE_LIMIT: usize = 200;
+const MAX_TREE_LIMIT: usize = 500;
+const MAX_TREE_OUTPUT_SIZE: usize = 12000;
+/// Deepest recursive listing allowed
+const MAX_DEPTH_LIMIT: usize = 10;
+
+/// Dependency and build directories that are listed but never expanded
+const NOISE_DIRS: &[&str] = &[
+    ".git",
+    "node_modules",
+    "target",
+    "__pycache__",
+    ".venv",
+    "venv",
+    ".next",
+    ".tox",
+];
+
 /// List files tool - lists directory contents within a sandboxed directory
 pub struct ListFilesTool {
     definition: ToolDefinition,
@@ -43,7 +68,7 @@ impl ListFilesTool {
             "recursive".to_string(),
             PropertySchema {
                 schema_type: "boolean".to_string(),
-                description: "If true, list files recursively (default: false)".to_string(),
+                description: "If true, list recursively as an indented tree (default: false). Dependency and build directories such as node_modules and target are shown but not expanded.".to_string(),
                 default: Some(json!(false)),
                 items: None,
                 enum_values: None,
@@ -53,7 +78,10 @@ impl ListFilesTool {
             "max_depth".to_string(),
             PropertySchema {
                 schema_type: "integer".to_string(),
-                description: "Maximum depth for recursive listing (default: 3)".to_string(),
+                description: format!(
+                    "How many levels below path to descend when recursive (default: 3, max: {})",
+                    MAX_DEPTH_LIMIT
+                ),
                 default: Some(json!(3)),
                 items: None,
                 enum_values: None,
@@ -70,6 +98,17 @@ impl ListFilesTool {
                 enum_values: None,
             },
         );
+        properties.insert(
+            "respect_gitignore".to_string(),
+            PropertySchema {
+                schema_type: "boolean".to_string(),
+                description: "If true, skip files ignored by .gitignore and .ignore files (default: true)"
+                    .to_string(),
+                default: Some(json!(true)),
+                items: None,
+                enum_values: None,
+            },
+        );
         properties.insert(
             "pattern".to_string(),
             PropertySchema {
@@ -86,8 +125,8 @@ impl ListFilesTool {
             PropertySchema {
                 schema_type: "integer".to_string(),
                 description: format!(
-                    "Maximum number of entries to return (default: {}, max: {}). Use with offset for pagination.",
-                    DEFAULT_LIMIT, MAX_LIMIT
+                    "Maximum number of entries to return (default: {}, max: {}; recursive: default {}, max {}). Use with offset for pagination.",
+                    DEFAULT_LIMIT, MAX_LIMIT, DEFAULT_TREE_LIMIT, MAX_TREE_LIMIT
                 ),
                 default: Some(json!(DEFAULT_LIMIT)),
                 items: None,
@@ -111,7 +150,8 @@ impl ListFilesTool {
                 description: format!(
                     "List files and directories with pagination. Returns up to {} entries by default. \
                      Use 'offset' parameter with 'next_offset' from response to page through large directories. \
-                     Can list recursively and filter by pattern. The path must be within the allowed workspace directory.",
+                     Set recursive=true to get an indented tree of the project in one call (files ignored by \
+                     .gitignore are skipped). Can filter by pattern. The path must be within the allowed workspace directory.",
                     DEFAULT_LIMIT
                 ),
                 input_schema: ToolInputSchema {
@@ -137,6 +177,7 @@ struct ListFilesParams {
     recursive: Option<bool>,
     max_depth: Option<usize>,
     include_hidden: Option<bool>,
+    respect_gitignore: Option<bool>,
     pattern: Option<String>,
     limit: Option<usize>,
     offset: Option<usize>,
@@ -148,12 +189,136 @@ struct FileEntry {
     is_dir: bool,
     size: u64,
     depth: usize,
+    /// A noise directory whose contents were not listed
+    collapsed: bool,
 }
 
-/// Work item for iterative directory traversal
-struct DirWorkItem {
-    path: PathBuf,
-    depth: usize,
+struct WalkOptions {
+    recursive: bool,
+    max_depth: usize,
+    include_hidden: bool,
+    respect_gitignore: bool,
+    pattern: Option<String>,
+}
+
+fn is_noise_dir(name: &str) -> bool {
+    NOISE_DIRS.contains(&name)
+}
+
+/// Directories first, then by name
+fn compare_entries(a: &Path, b: &Path) -> Ordering {
+    b.is_dir().cmp(&a.is_dir()).then_with(|| a.file_name().cmp(&b.file_name()))
+}
+
+/// Walk `dir` in tree order (each directory followed by its contents), applying
+/// ignore rules. Depth 0 is the directory's direct children.
+fn walk(dir: &Path, workspace: &Path, options: &WalkOptions) -> Vec<FileEntry> {
+    let levels = if options.recursive { options.max_depth.max(1) } else { 1 };
+    let walker = WalkBuilder::new(dir)
+        .max_depth(Some(levels))
+        .hidden(!options.include_hidden)
+        .git_ignore(options.respect_gitignore)
+        .git_exclude(options.respect_gitignore)
+        .ignore(options.respect_gitignore)
+        .parents(options.respect_gitignore)
+        .git_global(false)
+        // Honour .gitignore in workspaces that aren't git repositories
+        .require_git(false)
+        .follow_links(false)
+        .sort_by_file_path(compare_entries)
+        // Noise directories are listed but their contents are not
+        .filter_entry(|e| {
+            !e.path()
+                .parent()
+                .and_then(|p| p.file_name())
+                .is_some_and(|n| is_noise_dir(&n.to_string_lossy()))
+                || e.depth() == 0
+        })
+        .build();
+
+    let mut entries = Vec::new();
+    for entry in walker.flatten() {
+        if entry.depth() == 0 {
+            continue;
+        }
+        let file_name = entry.file_name().to_string_lossy().to_string();
+        let is_dir = entry.file_type().is_some_and(|t| t.is_dir());
+        if !is_dir
+            && let Some(pat) = &options.pattern
+            && !matches_glob(&file_name, pat)
+        {
+            continue;
+        }
+
+        let depth = entry.depth() - 1;
+        let path = if options.recursive || depth > 0 {
+            relative_to(workspace, entry.path())
+        } else {
+            file_name.clone()
+        };
+        entries.push(FileEntry {
+            path,
+            is_dir,
+            size: if is_dir { 0 } else { entry.metadata().map(|m| m.len()).unwrap_or(0) },
+            depth,
+            collapsed: is_dir && options.recursive && is_noise_dir(&file_name),
+        });
+    }
+
+    if options.recursive && options.pattern.is_some() {
+        prune_empty_dirs(&mut entries);
+    }
+    entries
+}
+
+/// Drop directories with no files left under them (after pattern filtering)
+fn prune_empty_dirs(entries: &mut Vec<FileEntry>) {
+    let mut keep = vec![true; entries.len()];
+    // pending[d]: a kept entry at depth d was seen since the last directory above it
+    let mut pending: Vec<bool> = Vec::new();
+    for i in (0..entries.len()).rev() {
+        let depth = entries[i].depth;
+        if pending.len() < depth + 2 {
+            pending.resize(depth + 2, false);
+        }
+        if entries[i].is_dir {
+            keep[i] = pending[depth + 1];
+            pending[depth + 1..].iter_mut().for_each(|p| *p = false);
+        }
+        if keep[i] {
+            pending[depth] = true;
+        }
+    }
+    let mut keep = keep.into_iter();
+    entries.retain(|_| keep.next().unwrap_or(true));
+}
+
+/// Tree-drawing prefix (`│   ├── `) for each entry, from the depths of the whole listing
+fn tree_prefixes(entries: &[FileEntry]) -> Vec<String> {
+    // Whether each entry is the last child of its directory
+    let mut is_last = vec![false; entries.len()];
+    let mut sibling_follows: Vec<bool> = Vec::new();
+    for (i, entry) in entries.iter().enumerate().rev() {
+        sibling_follows.resize(entry.depth + 1, false);
+        is_last[i] = !sibling_follows[entry.depth];
+        sibling_follows[entry.depth] = true;
+    }
+
+    let mut ancestors_last: Vec<bool> = Vec::new();
+    entries
+        .iter()
+        .zip(&is_last)
+        .map(|(entry, &last)| {
+            ancestors_last.truncate(entry.depth);
+            let mut prefix: String = ancestors_last
+                .iter()
+                .map(|&l| if l { "    " } else { "│   " })
+                .collect();
+            prefix.push_str(if last { "└── " } else { "├── " });
+            ancestors_last.push(last);
+            prefix
+        })
+        .collect()
 }
 
 #[async_trait]
@@ -162,6 +327,15 @@ impl Tool for ListFilesTool {
         self.definition.clone()
     }
 
+    fn is_read_only(&self) -> bool {
+        true
+    }
+
+    /// Listings are reused until the workspace changes
+    fn cache_policy(&self, _params: &Value) -> CachePolicy {
+        CachePolicy::UntilWorkspaceChanges(Duration::from_secs(120))
+    }
+
     async fn execute(&self, params: Value, context: &ToolContext) -> ToolResult {
         let params: ListFilesParams = match serde_json::from_value(params) {
             Ok(p) => p,
@@ -170,10 +344,15 @@ impl Tool for ListFilesTool {
 
         let path = params.path.unwrap_or_else(|| ".".to_string());
         let recursive = params.recursive.unwrap_or(false);
-        let max_depth = params.max_depth.unwrap_or(3);
+        let max_depth = params.max_depth.unwrap_or(3).min(MAX_DEPTH_LIMIT);
         let include_hidden = params.include_hidden.unwrap_or(false);
+        let respect_gitignore = params.respect_gitignore.unwrap_or(true);
         let pattern = params.pattern;
-        let limit = params.limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT);
+        let limit = if recursive {
+            params.limit.unwrap_or(DEFAULT_TREE_LIMIT).min(MAX_TREE_LIMIT)
+        } else {
+            params.limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT)
+        };
         let offset = params.offset.unwrap_or(0);
 
         // Get workspace directory from context or use current directory
@@ -183,34 +362,13 @@ impl Tool for ListFilesTool {
             .map(PathBuf::from)
             .unwrap_or_else(|| std::env::current_dir().unwrap_or_else(|_| PathBuf::from(".")));
 
-        // Resolve the path
-        let requested_path = Path::new(&path);
-        let full_path = if requested_path.is_absolute() {
-            requested_path.to_path_buf()
-        } else {
-            workspace.join(requested_path)
-        };
-
-        // Canonicalize paths for comparison
-        let canonical_workspace = match workspace.canonicalize() {
+        // Resolve the path (symlinks and `..` included) and ensure it stays within workspace
+        let resolved = match WorkspacePath::resolve(&workspace, &path) {
             Ok(p) => p,
-            Err(e) => {
-                return ToolResult::error(format!("Cannot resolve workspace directory: {}", e))
-            }
+            Err(e) => return ToolResult::error(e),
         };
-
-        let canonical_path = match full_path.canonicalize() {
-            Ok(p) => p,
-            Err(e) => return ToolResult::error(format!("Cannot resolve path: {}", e)),
-        };
-
-        // Security check: ensure path is within workspace
-        if !canonical_path.starts_with(&canonical_workspace) {
-            return ToolResult::error(format!(
-                "Access denied: path '{}' is outside the workspace directory",
-                path
-            ));
-        }
+        let canonical_workspace = resolved.root().to_path_buf();
+        let canonical_path = resolved.path().to_path_buf();
 
         // Check if path exists and is a directory
         if !canonical_path.exists() {
@@ -221,76 +379,18 @@ impl Tool for ListFilesTool {
             return ToolResult::error(format!("Path is not a directory: {}", path));
         }
 
-        // Collect files using iterative approach
-        let mut entries = Vec::new();
-        let mut work_stack = vec![DirWorkItem {
-            path: canonical_path.clone(),
-            depth: 0,
-        }];
-
-        while let Some(work_item) = work_stack.pop() {
-            let mut read_dir = match tokio::fs::read_dir(&work_item.path).await {
-                Ok(rd) => rd,
-                Err(e) => {
-                    log::warn!("Failed to read directory {:?}: {}", work_item.path, e);
-                    continue;
-                }
-            };
-
-            while let Ok(Some(entry)) = read_dir.next_entry().await {
-                let entry_path = entry.path();
-                let file_name = match entry.file_name().to_str() {
-                    Some(n) => n.to_string(),
-                    None => continue,
-                };
-
-                // Skip hidden files unless requested
-                if !include_hidden && file_name.starts_with('.') {
-                    continue;
-                }
-
-                // Get metadata
-                let metadata = match entry.metadata().await {
-                    Ok(m) => m,
-                    Err(_) => continue,
-                };
-
-                let is_dir = metadata.is_dir();
-
-                // Apply pattern filter for files
-                if let Some(ref pat) = pattern {
-                    if !is_dir && !matches_glob(&file_name, pat) {
-                        continue;
-                    }
-                }
-
-                // Get relative path from workspace
-                let relative_path = entry_path
-                    .strip_prefix(&canonical_workspace)
-                    .unwrap_or(&entry_path)
-                    .to_string_lossy()
-                    .to_string();
-
-                entries.push(FileEntry {
-                    path: if work_item.depth == 0 {
-                        file_name.clone()
-                    } else {
-                        relative_path.clone()
-                    },
-                    is_dir,
-                    size: if is_dir { 0 } else { metadata.len() },
-                    depth: work_item.depth,
-                });
-
-                // Add directories to work stack for recursive processing
-                if is_dir && recursive && work_item.depth < max_depth {
-                    work_stack.push(DirWorkItem {
-                        path: entry_path,
-                        depth: work_item.depth + 1,
-                    });
-                }
-            }
-        }
+        let options = WalkOptions {
+            recursive,
+            max_depth,
+            include_hidden,
+            respect_gitignore,
+            pattern,
+        };
+        let walk_dir = canonical_path.clone();
+        let mut entries = match tokio::task::spawn_blocking(move || walk(&walk_dir, &canonical_workspace, &options)).await {
+            Ok(entries) => entries,
+            Err(e) => return ToolResult::error(format!("Failed to list directory: {}", e)),
+        };
 
         // Add intrinsic files when listing root directory
         let is_root = path == "." || path == "/" || path.is_empty();
@@ -303,19 +403,23 @@ impl Tool for ListFilesTool {
                         is_dir: false,
                         size: 0, // Virtual file, size unknown
                         depth: 0,
+                        collapsed: false,
                     });
                 }
             }
         }
 
-        // Sort entries: directories first, then by name
-        entries.sort_by(|a, b| {
-            if a.is_dir != b.is_dir {
-                b.is_dir.cmp(&a.is_dir)
-            } else {
-                a.path.cmp(&b.path)
-            }
-        });
+        // Sort entries: directories first, then by name. A tree is already in
+        // walk order, and sorting would separate entries from their directories.
+        if !recursive {
+            entries.sort_by(|a, b| {
+                if a.is_dir != b.is_dir {
+                    b.is_dir.cmp(&a.is_dir)
+                } else {
+                    a.path.cmp(&b.path)
+                }
+            });
+        }
 
         // Calculate total counts before pagination
         let total_entries = entries.len();
@@ -349,26 +453,45 @@ impl Tool for ListFilesTool {
         let mut page_files = 0;
         let mut page_size = 0u64;
 
-        let formatted: Vec<String> = paginated_entries
-            .iter()
-            .map(|e| {
-                let indent = "  ".repeat(e.depth);
-                let type_indicator = if e.is_dir {
-                    page_dirs += 1;
-                    "📁"
-                } else {
-                    page_files += 1;
-                    page_size += e.size;
-                    "📄"
-                };
-                let size_str = if e.is_dir {
-                    String::new()
+        let formatted: Vec<String> = if recursive {
+            let prefixes = tree_prefixes(&entries);
+            let mut lines = vec![format!("{}/", path.trim_end_matches('/'))];
+            lines.extend(entries.iter().zip(prefixes).skip(offset).take(limit).map(|(e, prefix)| {
+                let name = Path::new(&e.path)
+                    .file_name()
+                    .map(|n| n.to_string_lossy().to_string())
+                    .unwrap_or_else(|| e.path.clone());
+                if e.collapsed {
+                    format!("{}{}/ (not expanded)", prefix, name)
+                } else if e.is_dir {
+                    format!("{}{}/", prefix, name)
                 } else {
-                    format!(" ({})", format_size(e.size))
-                };
-                format!("{}{} {}{}", indent, type_indicator, e.path, size_str)
-            })
-            .collect();
+                    format!("{}{} ({})", prefix, name, format_size(e.size))
+                }
+            }));
+            lines
+        } else {
+            paginated_entries
+                .iter()
+                .map(|e| {
+                    let indent = "  ".repeat(e.depth);
+                    let type_indicator = if e.is_dir {
+                        page_dirs += 1;
+                        "📁"
+                    } else {
+                        page_files += 1;
+                        page_size += e.size;
+                        "📄"
+                    };
+                    let size_str = if e.is_dir {
+                        String::new()
+                    } else {
+                        format!(" ({})", format_size(e.size))
+                    };
+                    format!("{}{} {}{}", indent, type_indicator, e.path, size_str)
+                })
+                .collect()
+        };
 
         // Build pagination info string
         let pagination_info = if has_more {
@@ -404,8 +527,9 @@ impl Tool for ListFilesTool {
         let mut output = format!("{}{}", formatted.join("\n"), pagination_info);
 
         // Truncate if output is too large to prevent context bloat
-        if output.len() > MAX_OUTPUT_SIZE {
-            output.truncate(MAX_OUTPUT_SIZE);
+        let max_output = if recursive { MAX_TREE_OUTPUT_SIZE } else { MAX_OUTPUT_SIZE };
+        if output.len() > max_output {
+            output = truncate_chars(&output, max_output).to_string();
             output.push_str("\n\n⚠️ [Output truncated - use pagination or filter with pattern]");
         }
 
@@ -489,6 +613,45 @@ mod tests {
         assert!(!matches_glob("test.rs", "foo.*"));
     }
 
+    #[tokio::test]
+    async fn test_recursive_tree_respects_ignore_rules() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let root = temp_dir.path();
+        std::fs::write(root.join(".gitignore"), "*.log\n").unwrap();
+        std::fs::write(root.join("debug.log"), "noise").unwrap();
+        std::fs::create_dir_all(root.join("node_modules/left-pad")).unwrap();
+        std::fs::write(root.join("node_modules/left-pad/index.js"), "").unwrap();
+        std::fs::create_dir_all(root.join("src/lib")).unwrap();
+        std::fs::write(root.join("src/main.rs"), "fn main() {}").unwrap();
+        std::fs::write(root.join("src/lib/util.rs"), "").unwrap();
+        std::fs::create_dir_all(root.join("docs")).unwrap();
+        std::fs::write(root.join("docs/guide.md"), "").unwrap();
+
+        let tool = ListFilesTool::new();
+        let context = ToolContext::new().with_workspace(root.to_string_lossy().to_string());
+        let result = tool
+            .execute(json!({ "path": "src/..", "recursive": true }), &context)
+            .await;
+        assert!(result.success);
+        let tree = result.content;
+        assert!(tree.contains("├── node_modules/ (not expanded)\n"));
+        assert!(tree.contains("└── src/\n    ├── lib/\n    │   └── util.rs (0 B)\n    └── main.rs (12 B)\n"));
+        assert!(!tree.contains("debug.log"));
+        assert!(!tree.contains("left-pad"));
+
+        // Pattern filtering drops directories left without matching files
+        let result = tool
+            .execute(json!({ "path": "src/..", "recursive": true, "pattern": "*.md" }), &context)
+            .await;
+        assert!(result.content.contains("└── docs/\n    └── guide.md"));
+        assert!(!result.content.contains("main.rs"));
+
+        let result = tool
+            .execute(json!({ "path": "src/..", "recursive": true, "respect_gitignore": false }), &context)
+            .await;
+        assert!(result.content.contains("debug.log"));
+    }
+
     #[test]
     fn test_format_size() {
         assert_eq!(format_size(500), "500 B");
@@ -0,0 +1,170 @@
+//! Load skill tool for pulling a skill's full content into context
+//!
+//! The system prompt only names skills. This tool returns a skill's complete
+//! instructions, requirements and scripts so the agent can read it before
+//! deciding how to act, without activating it the way `use_skill` does.
+
+use crate::tools::registry::Tool;
+use crate::tools::types::{
+    PropertySchema, ToolContext, ToolDefinition, ToolGroup, ToolInputSchema, ToolResult,
+};
+use async_trait::async_trait;
+use serde::Deserialize;
+use serde_json::{json, Value};
+use std::collections::HashMap;
+
+/// Tool that loads a skill's content on demand
+pub struct LoadSkillTool {
+    definition: ToolDefinition,
+}
+
+impl LoadSkillTool {
+    pub fn new() -> Self {
+        let mut properties = HashMap::new();
+
+        properties.insert(
+            "name".to_string(),
+            PropertySchema {
+                schema_type: "string".to_string(),
+                description: "Name of the skill to load".to_string(),
+                default: None,
+                items: None,
+                enum_values: None,
+            },
+        );
+
+        LoadSkillTool {
+            definition: ToolDefinition {
+                name: "load_skill".to_string(),
+                description: "Load the full instructions of an enabled skill into context, with the tools it needs and its scripts. Use it to read a skill before following it; use_skill runs it directly.".to_string(),
+                input_schema: ToolInputSchema {
+                    schema_type: "object".to_string(),
+                    properties,
+                    required: vec!["name".to_string()],
+                },
+                group: ToolGroup::System,
+            },
+        }
+    }
+}
+
+impl Default for LoadSkillTool {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct LoadSkillParams {
+    name: String,
+}
+
+#[async_trait]
+impl Tool for LoadSkillTool {
+    fn definition(&self) -> ToolDefinition {
+        self.definition.clone()
+    }
+
+    async fn execute(&self, params: Value, context: &ToolContext) -> ToolResult {
+        let params: LoadSkillParams = match serde_json::from_value(params) {
+            Ok(p) => p,
+            Err(e) => return ToolResult::error(format!("Invalid parameters: {}", e)),
+        };
+
+        let registry = match &context.skill_registry {
+            Some(r) => r,
+            None => return ToolResult::error("Skill registry not available"),
+        };
+
+        let skill = match registry.get(&params.name).await {
+            Some(skill) if skill.enabled => skill,
+            _ => {
+                let available: Vec<String> = registry
+                    .list_enabled()
+                    .await
+                    .into_iter()
+                    .map(|s| s.metadata.name)
+                    .collect();
+                return ToolResult::error(format!(
+                    "Skill '{}' not found or not enabled. Available skills: {}",
+                    params.name,
+                    if available.is_empty() { "none".to_string() } else { available.join(", ") }
+                ));
+            }
+        };
+
+        let skill_base_dir = format!("{}/{}", crate::config::skills_dir(), skill.metadata.name);
+        let instructions = skill
+            .render_prompt(&HashMap::new())
+            .replace("{baseDir}", &skill_base_dir);
+        let scripts: Vec<String> = registry
+            .get_skill_scripts(&skill.metadata.name)
+            .await
+            .into_iter()
+            .map(|s| format!("{} ({})", s.name, s.language))
+            .collect();
+
+        let mut content = format!("## Skill: {}\n\n", skill.metadata.name);
+        content.push_str(&format!("Description: {}\n", skill.metadata.description));
+        if !skill.metadata.requires_tools.is_empty() {
+            content.push_str(&format!("Requires tools: {}\n", skill.metadata.requires_tools.join(", ")));
+        }
+        if !skill.metadata.arguments.is_empty() {
+            let mut arguments: Vec<String> = skill
+                .metadata
+                .arguments
+                .iter()
+                .map(|(name, arg)| format!("{} ({})", name, arg.description))
+                .collect();
+            arguments.sort();
+            content.push_str(&format!("Arguments: {}\n", arguments.join(", ")));
+        }
+        if !scripts.is_empty() {
+            content.push_str(&format!("Scripts in {}/scripts: {}\n", skill_base_dir, scripts.join(", ")));
+        }
+        content.push_str("\n### Instructions:\n");
+        content.push_str(&instructions);
+
+        log::info!("[load_skill] Loaded skill '{}' ({} chars)", skill.metadata.name, instructions.len());
+
+        ToolResult::success(content).with_metadata(json!({
+            "name": skill.metadata.name,
+            "version": skill.metadata.version,
+            "requires_tools": skill.metadata.requires_tools,
+            "triggers": skill.metadata.triggers,
+            "scripts": scripts,
+        }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::Database;
+    use crate::skills::SkillRegistry;
+    use std::sync::Arc;
+
+    #[tokio::test]
+    async fn test_load_skill() {
+        let db = Arc::new(Database::new(":memory:").unwrap());
+        let registry = Arc::new(SkillRegistry::new(db));
+        registry
+            .create_skill_from_markdown(
+                "---\nname: weather\ndescription: Look up the weather\nrequires_tools: [web_fetch]\ntriggers: [weather, forecast]\n---\nFetch the forecast from {baseDir}/api.",
+            )
+            .await
+            .unwrap();
+        let context = ToolContext::new().with_skill_registry(registry.clone());
+        let tool = LoadSkillTool::new();
+
+        let result = tool.execute(json!({ "name": "weather" }), &context).await;
+        assert!(result.success);
+        assert!(result.content.contains("Requires tools: web_fetch"));
+        assert!(result.content.contains("/weather/api."));
+        assert!(!result.content.contains("{baseDir}"));
+
+        registry.set_enabled("weather", false).await;
+        let result = tool.execute(json!({ "name": "weather" }), &context).await;
+        assert!(!result.success);
+    }
+}
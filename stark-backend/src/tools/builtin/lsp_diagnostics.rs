@@ -0,0 +1,345 @@
+//! Language server diagnostics tool
+//!
+//! Starts rust-analyzer, typescript-language-server or pyright against the
+//! project, collects the errors and warnings it publishes (see `tools::lsp`)
+//! and shuts it down again. Faster than a full build for checking an edit.
+
+use crate::tools::lsp::{self, severity_rank, Diagnostic, Language, LspSession};
+use crate::tools::registry::Tool;
+use crate::tools::search::{self, WalkOptions};
+use crate::tools::types::{
+    PropertySchema, ToolContext, ToolDefinition, ToolGroup, ToolInputSchema, ToolResult,
+};
+use crate::tools::workspace_path::WorkspacePath;
+use async_trait::async_trait;
+use serde::Deserialize;
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::process::Stdio;
+use std::time::{Duration, Instant};
+use tokio::io::AsyncReadExt;
+
+/// Most files opened when the server only checks open files
+const MAX_OPEN_FILES: usize = 200;
+/// Largest file opened, in bytes
+const MAX_FILE_BYTES: u64 = 256 * 1024;
+/// Most diagnostics listed in the output
+const MAX_DIAGNOSTICS: usize = 200;
+
+/// Tool that reports compiler and type errors from a language server
+pub struct LspDiagnosticsTool {
+    definition: ToolDefinition,
+}
+
+impl LspDiagnosticsTool {
+    pub fn new() -> Self {
+        let mut properties = HashMap::new();
+
+        properties.insert(
+            "path".to_string(),
+            PropertySchema {
+                schema_type: "string".to_string(),
+                description: "File or directory to check, relative to the workspace (default: the whole workspace)".to_string(),
+                default: Some(json!(".")),
+                items: None,
+                enum_values: None,
+            },
+        );
+
+        properties.insert(
+            "language".to_string(),
+            PropertySchema {
+                schema_type: "string".to_string(),
+                description: "Language server to use (default: from the file extension or project files)".to_string(),
+                default: None,
+                items: None,
+                enum_values: Some(vec!["rust".to_string(), "typescript".to_string(), "python".to_string()]),
+            },
+        );
+
+        properties.insert(
+            "severity".to_string(),
+            PropertySchema {
+                schema_type: "string".to_string(),
+                description: "Least severe level to report (default: warning)".to_string(),
+                default: Some(json!("warning")),
+                items: None,
+                enum_values: Some(vec![
+                    "error".to_string(),
+                    "warning".to_string(),
+                    "information".to_string(),
+                    "hint".to_string(),
+                ]),
+            },
+        );
+
+        properties.insert(
+            "timeout".to_string(),
+            PropertySchema {
+                schema_type: "integer".to_string(),
+                description: "Seconds to wait for the server to finish checking (default: 60, max: 300)".to_string(),
+                default: Some(json!(60)),
+                items: None,
+                enum_values: None,
+            },
+        );
+
+        LspDiagnosticsTool {
+            definition: ToolDefinition {
+                name: "lsp_diagnostics".to_string(),
+                description: "Get compiler and type errors for a file or the whole project from a language server (rust-analyzer, typescript-language-server or pyright). Cheaper than a full build through exec for checking your edits.".to_string(),
+                input_schema: ToolInputSchema {
+                    schema_type: "object".to_string(),
+                    properties,
+                    required: vec![],
+                },
+                group: ToolGroup::Development,
+            },
+        }
+    }
+}
+
+impl Default for LspDiagnosticsTool {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct LspDiagnosticsParams {
+    path: Option<String>,
+    language: Option<String>,
+    severity: Option<String>,
+    timeout: Option<u64>,
+}
+
+/// Nearest directory from `start` up to `root` that looks like a project in `language`
+fn project_root(root: &Path, start: &Path, language: Language) -> PathBuf {
+    start
+        .ancestors()
+        .take_while(|dir| dir.starts_with(root))
+        .find(|dir| Language::detect(dir) == Some(language))
+        .unwrap_or(root)
+        .to_path_buf()
+}
+
+#[async_trait]
+impl Tool for LspDiagnosticsTool {
+    fn definition(&self) -> ToolDefinition {
+        self.definition.clone()
+    }
+
+    async fn execute(&self, params: Value, context: &ToolContext) -> ToolResult {
+        let params: LspDiagnosticsParams = match serde_json::from_value(params) {
+            Ok(p) => p,
+            Err(e) => return ToolResult::error(format!("Invalid parameters: {}", e)),
+        };
+
+        let workspace = context
+            .workspace_dir
+            .as_ref()
+            .map(PathBuf::from)
+            .unwrap_or_else(|| std::env::current_dir().unwrap_or_else(|_| PathBuf::from(".")));
+        let resolved = match WorkspacePath::resolve(&workspace, params.path.as_deref().unwrap_or(".")) {
+            Ok(p) => p,
+            Err(e) => return ToolResult::error(e),
+        };
+        let root = resolved.root().to_path_buf();
+        let target = resolved.path().to_path_buf();
+        if !target.exists() {
+            return ToolResult::error(format!("'{}' does not exist", params.path.as_deref().unwrap_or(".")));
+        }
+        let target_dir = if target.is_file() { target.parent().unwrap_or(&root).to_path_buf() } else { target.clone() };
+
+        let language = match params.language.as_deref() {
+            Some(name) => match Language::from_str(name) {
+                Some(l) => l,
+                None => return ToolResult::error(format!("Unknown language: {}. Use rust, typescript or python.", name)),
+            },
+            None => match Language::for_file(&target)
+                .or_else(|| target_dir.ancestors().take_while(|d| d.starts_with(&root)).find_map(Language::detect))
+            {
+                Some(l) => l,
+                None => return ToolResult::error("Could not tell the project's language. Pass language explicitly."),
+            },
+        };
+        let min_rank = severity_rank(params.severity.as_deref().unwrap_or("warning"));
+
+        let (server, args) = language.server_command();
+        let server_path = match which::which(server) {
+            Ok(p) => p,
+            Err(_) => {
+                return ToolResult::error(format!(
+                    "{} is not installed, so {} files can't be checked. Install it with: {}",
+                    server,
+                    language.as_str(),
+                    language.install_hint()
+                ))
+            }
+        };
+
+        // Files to open: the target file, or every source file under the
+        // target directory for servers that only check open files
+        let files: Vec<PathBuf> = if target.is_file() {
+            vec![target.clone()]
+        } else if language.checks_whole_project() {
+            Vec::new()
+        } else {
+            let (search_root, base) = (root.clone(), target.clone());
+            match tokio::task::spawn_blocking(move || search::find_files(&search_root, &base, "**", WalkOptions::default())).await {
+                Ok(Ok(hits)) => hits
+                    .into_iter()
+                    .filter(|hit| hit.size <= MAX_FILE_BYTES && Language::for_file(&hit.path) == Some(language))
+                    .take(MAX_OPEN_FILES)
+                    .map(|hit| hit.path)
+                    .collect(),
+                Ok(Err(e)) => return ToolResult::error(e),
+                Err(e) => return ToolResult::error(format!("Failed to list files: {}", e)),
+            }
+        };
+
+        let project = project_root(&root, &target_dir, language);
+        let mut child = match tokio::process::Command::new(&server_path)
+            .args(args)
+            .current_dir(&project)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .kill_on_drop(true)
+            .spawn()
+        {
+            Ok(child) => child,
+            Err(e) => return ToolResult::error(format!("Failed to start {}: {}", server, e)),
+        };
+        let (Some(stdin), Some(stdout), Some(mut stderr)) = (child.stdin.take(), child.stdout.take(), child.stderr.take()) else {
+            return ToolResult::error(format!("Failed to connect to {}", server));
+        };
+        // Drain stderr so the server can't block on a full pipe; keep it for errors
+        let stderr_task = tokio::spawn(async move {
+            let mut text = String::new();
+            let _ = stderr.read_to_string(&mut text).await;
+            text
+        });
+
+        let timeout_secs = params.timeout.unwrap_or(60).clamp(5, 300);
+        let deadline = Instant::now() + Duration::from_secs(timeout_secs);
+        // rust-analyzer pauses between loading the workspace and running cargo check
+        let settle = Duration::from_millis(if language == Language::Rust { 3000 } else { 1500 });
+        log::info!("[LSP] {} on {:?} ({} file(s) opened)", server, project, files.len());
+
+        let mut session = LspSession::new(stdout, stdin);
+        let outcome = async {
+            session.initialize(&project, deadline).await?;
+            for file in &files {
+                let text = tokio::fs::read_to_string(file).await.unwrap_or_default();
+                session.open(file, language.language_id(file), &text).await?;
+            }
+            session.collect(settle, deadline).await
+        }
+        .await;
+        session.shutdown().await;
+        let _ = tokio::time::timeout(Duration::from_secs(2), child.wait()).await;
+        let _ = child.kill().await;
+
+        let complete = match outcome {
+            Ok(complete) => complete,
+            Err(e) => {
+                let stderr = stderr_task.await.unwrap_or_default();
+                let stderr = crate::utils::truncate_chars(stderr.trim(), 2000);
+                return ToolResult::error(if stderr.is_empty() {
+                    format!("{}: {}", server, e)
+                } else {
+                    format!("{}: {}\n\nServer output:\n{}", server, e, stderr)
+                });
+            }
+        };
+
+        let mut diagnostics: Vec<Diagnostic> = Vec::new();
+        for (uri, items) in session.diagnostics() {
+            let Some(path) = lsp::uri_to_path(uri) else { continue };
+            // Only the requested file or directory; never dependencies outside the workspace
+            let in_scope = if target.is_file() { path == target } else { path.starts_with(&target) };
+            if !in_scope {
+                continue;
+            }
+            let relative = path.strip_prefix(&root).unwrap_or(&path).to_string_lossy().replace('\\', "/");
+            diagnostics.extend(
+                items
+                    .iter()
+                    .map(|item| Diagnostic::from_lsp(&relative, item))
+                    .filter(|d| d.rank() <= min_rank),
+            );
+        }
+        diagnostics.sort_by(|a, b| (a.rank(), &a.file, a.line, a.column).cmp(&(b.rank(), &b.file, b.line, b.column)));
+
+        let errors = diagnostics.iter().filter(|d| d.severity == "error").count();
+        let warnings = diagnostics.iter().filter(|d| d.severity == "warning").count();
+        let file_count = diagnostics.iter().map(|d| d.file.as_str()).collect::<std::collections::HashSet<_>>().len();
+        let mut output = if diagnostics.is_empty() {
+            format!("{}: no problems found.", server)
+        } else {
+            format!(
+                "{}: {} error(s), {} warning(s), {} other in {} file(s)\n",
+                server,
+                errors,
+                warnings,
+                diagnostics.len() - errors - warnings,
+                file_count
+            )
+        };
+        for d in diagnostics.iter().take(MAX_DIAGNOSTICS) {
+            let code = d.code.as_ref().map(|c| format!("[{}]", c)).unwrap_or_default();
+            output.push_str(&format!("\n{}:{}:{}: {}{}: {}", d.file, d.line, d.column, d.severity, code, d.message));
+        }
+        if diagnostics.len() > MAX_DIAGNOSTICS {
+            output.push_str(&format!("\n\n[{} more not shown]", diagnostics.len() - MAX_DIAGNOSTICS));
+        }
+        if !complete {
+            output.push_str(&format!(
+                "\n\nNote: {} was still working after {}s, so results may be incomplete. Retry with a longer timeout.",
+                server, timeout_secs
+            ));
+        }
+
+        diagnostics.truncate(MAX_DIAGNOSTICS);
+        ToolResult::success(output).with_metadata(json!({
+            "language": language,
+            "server": server,
+            "errors": errors,
+            "warnings": warnings,
+            "complete": complete,
+            "diagnostics": diagnostics,
+        }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_project_root_finds_nearest_project() {
+        let dir = TempDir::new().unwrap();
+        let root = dir.path().canonicalize().unwrap();
+        std::fs::create_dir_all(root.join("backend/src")).unwrap();
+        std::fs::write(root.join("backend/Cargo.toml"), "").unwrap();
+
+        assert_eq!(project_root(&root, &root.join("backend/src"), Language::Rust), root.join("backend"));
+        // Falls back to the workspace root
+        assert_eq!(project_root(&root, &root.join("backend/src"), Language::Python), root);
+    }
+
+    #[tokio::test]
+    async fn test_lsp_diagnostics_rejects_unknown_language() {
+        let dir = TempDir::new().unwrap();
+        let context = ToolContext::new().with_workspace(dir.path().to_string_lossy().to_string());
+        let tool = LspDiagnosticsTool::new();
+
+        let result = tool.execute(json!({ "language": "cobol" }), &context).await;
+        assert!(result.error.unwrap().contains("Unknown language"));
+        let result = tool.execute(json!({}), &context).await;
+        assert!(result.error.unwrap().contains("Could not tell"));
+    }
+}
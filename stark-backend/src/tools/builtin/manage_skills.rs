@@ -150,9 +150,9 @@ impl Tool for ManageSkillsTool {
         match params.action.as_str() {
             "list" => {
                 let skills = if params.filter_enabled.unwrap_or(false) {
-                    registry.list_enabled()
+                    registry.list_enabled().await
                 } else {
-                    registry.list()
+                    registry.list().await
                 };
 
                 let skill_list: Vec<Value> = skills
@@ -181,7 +181,7 @@ impl Tool for ManageSkillsTool {
                     None => return ToolResult::error("'name' parameter is required for 'get' action"),
                 };
 
-                match registry.get(&name) {
+                match registry.get(&name).await {
                     Some(skill) => {
                         let detail = json!({
                             "name": skill.metadata.name,
@@ -215,7 +215,7 @@ impl Tool for ManageSkillsTool {
                     return ToolResult::error("Either 'url' or 'markdown' parameter is required for 'install' action");
                 };
 
-                match registry.create_skill_from_markdown(&markdown_content) {
+                match registry.create_skill_from_markdown(&markdown_content).await {
                     Ok(skill) => {
                         let result = json!({
                             "success": true,
@@ -239,11 +239,11 @@ impl Tool for ManageSkillsTool {
                     None => return ToolResult::error("'name' parameter is required for 'enable' action"),
                 };
 
-                if !registry.has_skill(&name) {
+                if !registry.has_skill(&name).await {
                     return ToolResult::error(format!("Skill '{}' not found", name));
                 }
 
-                registry.set_enabled(&name, true);
+                registry.set_enabled(&name, true).await;
                 ToolResult::success(format!("Skill '{}' enabled", name))
             }
 
@@ -253,11 +253,11 @@ impl Tool for ManageSkillsTool {
                     None => return ToolResult::error("'name' parameter is required for 'disable' action"),
                 };
 
-                if !registry.has_skill(&name) {
+                if !registry.has_skill(&name).await {
                     return ToolResult::error(format!("Skill '{}' not found", name));
                 }
 
-                registry.set_enabled(&name, false);
+                registry.set_enabled(&name, false).await;
                 ToolResult::success(format!("Skill '{}' disabled", name))
             }
 
@@ -267,7 +267,7 @@ impl Tool for ManageSkillsTool {
                     None => return ToolResult::error("'name' parameter is required for 'delete' action"),
                 };
 
-                match registry.delete_skill(&name) {
+                match registry.delete_skill(&name).await {
                     Ok(true) => ToolResult::success(format!("Skill '{}' deleted", name)),
                     Ok(false) => ToolResult::error(format!("Skill '{}' not found", name)),
                     Err(e) => ToolResult::error(format!("Failed to delete skill: {}", e)),
@@ -280,7 +280,7 @@ impl Tool for ManageSkillsTool {
                     None => return ToolResult::error("'query' parameter is required for 'search' action"),
                 };
 
-                let results = registry.search(&query);
+                let results = registry.search(&query).await;
                 let skill_list: Vec<Value> = results
                     .iter()
                     .map(|s| {
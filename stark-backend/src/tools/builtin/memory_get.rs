@@ -10,6 +10,7 @@ use crate::tools::registry::Tool;
 use crate::tools::types::{
     PropertySchema, ToolContext, ToolDefinition, ToolGroup, ToolInputSchema, ToolResult,
 };
+use crate::utils::truncate_str;
 use async_trait::async_trait;
 use serde::Deserialize;
 use serde_json::{json, Value};
@@ -172,7 +173,7 @@ impl Tool for MemoryGetTool {
 
         // If ID is provided, get specific memory
         if let Some(id) = params.id {
-            match db.get_memory(id) {
+            match db.get_memory(id).await {
                 Ok(Some(memory)) => {
                     let mut output = format!(
                         "## Memory #{}\n\
@@ -236,7 +237,7 @@ impl Tool for MemoryGetTool {
                 params.entity_name.as_deref(),
                 identity_id,
                 limit,
-            ) {
+            ).await {
                 Ok(memories) => {
                     return format_memory_list(
                         memories,
@@ -267,7 +268,8 @@ impl Tool for MemoryGetTool {
             include_superseded,
             limit,
             0, // offset
-        ) {
+            None, // any owner
+        ).await {
             Ok(memories) => {
                 let title = match &params.memory_type {
                     Some(t) => format!("Memories of type: {}", t),
@@ -320,11 +322,7 @@ fn format_memory_list(memories: Vec<crate::models::Memory>, title: &str) -> Tool
         }
 
         // Truncate long content
-        let content = if memory.content.len() > 300 {
-            format!("{}...", &memory.content[..300])
-        } else {
-            memory.content.clone()
-        };
+        let content = truncate_str(&memory.content, 300);
         output.push_str(&format!("\n{}\n\n---\n\n", content));
     }
 
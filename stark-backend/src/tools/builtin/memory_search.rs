@@ -0,0 +1,195 @@
+//! Semantic memory search tool
+//!
+//! Searches stored memories by meaning as well as keywords: full-text (BM25)
+//! results are merged with embedding similarity when vector search is enabled,
+//! and it falls back to full-text only otherwise.
+
+use crate::memory::HybridSearcher;
+use crate::models::MemoryType;
+use crate::tools::registry::Tool;
+use crate::tools::types::{
+    PropertySchema, ToolContext, ToolDefinition, ToolGroup, ToolInputSchema, ToolResult,
+};
+use crate::utils::truncate_str;
+use async_trait::async_trait;
+use serde::Deserialize;
+use serde_json::{json, Value};
+use std::collections::HashMap;
+
+/// Tool for finding memories related to a question or topic
+pub struct MemorySearchTool {
+    definition: ToolDefinition,
+}
+
+impl MemorySearchTool {
+    pub fn new() -> Self {
+        let mut properties = HashMap::new();
+
+        properties.insert(
+            "query".to_string(),
+            PropertySchema {
+                schema_type: "string".to_string(),
+                description: "What to look for, in natural language (e.g. 'which wallet does the user prefer for trading').".to_string(),
+                default: None,
+                items: None,
+                enum_values: None,
+            },
+        );
+
+        properties.insert(
+            "memory_type".to_string(),
+            PropertySchema {
+                schema_type: "string".to_string(),
+                description: "Optional filter by memory type.".to_string(),
+                default: None,
+                items: None,
+                enum_values: Some(vec![
+                    "daily_log".to_string(),
+                    "long_term".to_string(),
+                    "preference".to_string(),
+                    "fact".to_string(),
+                    "task".to_string(),
+                    "entity".to_string(),
+                    "session_summary".to_string(),
+                ]),
+            },
+        );
+
+        properties.insert(
+            "limit".to_string(),
+            PropertySchema {
+                schema_type: "integer".to_string(),
+                description: "Maximum results (default: 5, max: 20).".to_string(),
+                default: Some(json!(5)),
+                items: None,
+                enum_values: None,
+            },
+        );
+
+        MemorySearchTool {
+            definition: ToolDefinition {
+                name: "memory_search".to_string(),
+                description: "Search stored memories by meaning, not just exact words. Use it to recall facts, preferences and past decisions related to the current request. For several unrelated terms at once use multi_memory_search. If nothing is found, accept it and move on.".to_string(),
+                input_schema: ToolInputSchema {
+                    schema_type: "object".to_string(),
+                    properties,
+                    required: vec!["query".to_string()],
+                },
+                group: ToolGroup::System,
+            },
+        }
+    }
+}
+
+impl Default for MemorySearchTool {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct MemorySearchParams {
+    query: String,
+    memory_type: Option<String>,
+    limit: Option<i32>,
+}
+
+#[async_trait]
+impl Tool for MemorySearchTool {
+    fn definition(&self) -> ToolDefinition {
+        self.definition.clone()
+    }
+
+    async fn execute(&self, params: Value, context: &ToolContext) -> ToolResult {
+        let params: MemorySearchParams = match serde_json::from_value(params) {
+            Ok(p) => p,
+            Err(e) => return ToolResult::error(format!("Invalid parameters: {}", e)),
+        };
+
+        if params.query.trim().is_empty() {
+            return ToolResult::error("query must not be empty");
+        }
+
+        let db = match &context.database {
+            Some(db) => db.clone(),
+            None => {
+                return ToolResult::error(
+                    "Database not available. Memory search requires database access.",
+                );
+            }
+        };
+
+        let memory_type = params.memory_type.as_deref().and_then(MemoryType::from_str);
+        let limit = params.limit.unwrap_or(5).clamp(1, 20);
+
+        let searcher = HybridSearcher::configured(db, &crate::config::memory_config()).await;
+        let results = match searcher
+            .search(&params.query, memory_type, context.identity_id.as_deref(), limit)
+            .await
+        {
+            Ok(results) => results,
+            Err(e) => return ToolResult::error(format!("Memory search failed: {}", e)),
+        };
+
+        if results.is_empty() {
+            return ToolResult::success(format!("No memories found for '{}'.", params.query));
+        }
+
+        let mode = if searcher.vector_search_enabled() { "semantic + keyword" } else { "keyword" };
+        let mut output = format!(
+            "## Memory Search Results\nQuery: '{}' ({}), {} result(s)\n\n",
+            params.query,
+            mode,
+            results.len()
+        );
+        for (i, result) in results.iter().enumerate() {
+            let memory = &result.memory;
+            output.push_str(&format!(
+                "{}. [{}] (ID: {}, importance {}) {}\n",
+                i + 1,
+                memory.memory_type.as_str(),
+                memory.id,
+                memory.importance,
+                truncate_str(&memory.content, 400)
+            ));
+        }
+
+        ToolResult::success(output).with_metadata(json!({
+            "query": params.query,
+            "vector_search": searcher.vector_search_enabled(),
+            "memory_ids": results.iter().map(|r| r.memory.id).collect::<Vec<_>>()
+        }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::Database;
+    use std::sync::Arc;
+
+    #[tokio::test]
+    async fn test_memory_search_falls_back_to_keywords() {
+        let db = Arc::new(Database::new(":memory:").unwrap());
+        db.create_memory(
+            MemoryType::Preference,
+            "User prefers the Base network for swaps",
+            None, None, 7, Some("user-1"), None, None, None, None, None,
+        )
+        .await
+        .unwrap();
+        let context = ToolContext {
+            database: Some(db),
+            identity_id: Some("user-1".to_string()),
+            ..ToolContext::default()
+        };
+
+        let tool = MemorySearchTool::new();
+        let result = tool.execute(json!({"query": "which network for swaps?"}), &context).await;
+        assert!(result.success);
+        assert!(result.content.contains("Base network"));
+
+        let result = tool.execute(json!({"query": "   "}), &context).await;
+        assert!(!result.success);
+    }
+}
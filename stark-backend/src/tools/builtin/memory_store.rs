@@ -210,7 +210,7 @@ impl Tool for MemoryStoreTool {
             None, // valid_from
             None, // valid_until
             None, // temporal_type
-        );
+        ).await;
 
         match result {
             Ok(memory) => {
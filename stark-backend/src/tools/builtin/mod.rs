@@ -2,19 +2,30 @@ mod agent_send;
 mod api_keys_check;
 mod apply_patch;
 mod ask_user;
+mod checksum;
+mod code_search;
 mod committer;
 mod delete_file;
 mod deploy;
 mod discord_lookup;
+mod docker;
+mod doctor;
 mod edit_file;
 mod exec;
+mod fetch_output;
+mod file_stat;
 mod git;
 mod github_user;
 mod glob;
 mod grep;
+mod http_request;
+mod index_codebase;
 mod list_files;
+mod lsp_diagnostics;
+mod load_skill;
 mod manage_skills;
 mod memory_get;
+mod memory_search;
 mod memory_store;
 mod modify_soul;
 mod multi_memory_search;
@@ -22,13 +33,21 @@ mod pr_quality;
 mod process_status;
 mod read_file;
 mod register_set;
+mod registers;
 mod rename_file;
+mod run_tests;
 mod say_to_user;
+mod send_email;
 mod set_agent_subtype;
+mod sql_query;
 mod subagent;
+mod swap;
 mod task_complete;
 pub mod token_lookup;
+mod token_price;
 mod twitter_post;
+mod tx_lookup;
+mod wallet;
 mod web_fetch;
 mod web3_function_call;
 mod web3_tx;
@@ -42,19 +61,30 @@ pub use agent_send::AgentSendTool;
 pub use api_keys_check::ApiKeysCheckTool;
 pub use apply_patch::ApplyPatchTool;
 pub use ask_user::AskUserTool;
+pub use checksum::ChecksumTool;
+pub use code_search::CodeSearchTool;
 pub use committer::CommitterTool;
 pub use delete_file::DeleteFileTool;
 pub use deploy::DeployTool;
 pub use discord_lookup::DiscordLookupTool;
+pub use docker::DockerTool;
+pub use doctor::DoctorTool;
 pub use edit_file::EditFileTool;
 pub use exec::ExecTool;
+pub use fetch_output::FetchOutputTool;
+pub use file_stat::FileStatTool;
 pub use git::GitTool;
 pub use github_user::GithubUserTool;
 pub use glob::GlobTool;
 pub use grep::GrepTool;
+pub use http_request::HttpRequestTool;
+pub use index_codebase::IndexCodebaseTool;
 pub use list_files::ListFilesTool;
+pub use lsp_diagnostics::LspDiagnosticsTool;
+pub use load_skill::LoadSkillTool;
 pub use manage_skills::ManageSkillsTool;
 pub use memory_get::MemoryGetTool;
+pub use memory_search::MemorySearchTool;
 pub use memory_store::MemoryStoreTool;
 pub use modify_soul::ModifySoulTool;
 pub use multi_memory_search::MultiMemorySearchTool;
@@ -62,13 +92,21 @@ pub use pr_quality::PrQualityTool;
 pub use process_status::ProcessStatusTool;
 pub use read_file::ReadFileTool;
 pub use register_set::RegisterSetTool;
+pub use registers::RegistersTool;
 pub use rename_file::RenameFileTool;
+pub use run_tests::RunTestsTool;
 pub use say_to_user::SayToUserTool;
+pub use send_email::SendEmailTool;
 pub use set_agent_subtype::SetAgentSubtypeTool;
+pub use sql_query::SqlQueryTool;
 pub use subagent::{SubagentStatusTool, SubagentTool};
+pub use swap::SwapTool;
 pub use task_complete::TaskFullyCompletedTool;
 pub use token_lookup::{load_tokens, TokenLookupTool};
+pub use token_price::TokenPriceTool;
 pub use twitter_post::TwitterPostTool;
+pub use tx_lookup::TxLookupTool;
+pub use wallet::WalletTool;
 pub use web_fetch::WebFetchTool;
 pub use web3_function_call::Web3FunctionCallTool;
 pub use web3_tx::Web3TxTool;
@@ -77,3 +115,86 @@ pub use x402_agent_invoke::X402AgentInvokeTool;
 pub use x402_fetch::X402FetchTool;
 pub use x402_post::X402PostTool;
 pub use x402_rpc::X402RpcTool;
+
+use super::Tool;
+use std::sync::Arc;
+
+/// Every built-in tool. New tools only need to be added here to be picked up
+/// by `ToolRegistryBuilder::with_builtin_tools`.
+pub fn all_tools() -> Vec<Arc<dyn Tool>> {
+    vec![
+        // System tools (always available)
+        Arc::new(SubagentTool::new()),
+        Arc::new(SubagentStatusTool::new()),
+        Arc::new(SetAgentSubtypeTool::new()),
+        Arc::new(AskUserTool::new()),
+        Arc::new(SayToUserTool::new()),
+        Arc::new(MemorySearchTool::new()),
+        Arc::new(MultiMemorySearchTool::new()),
+        // Arc::new(MemoryGetTool::new()), // temporarily disabled
+        Arc::new(MemoryStoreTool::new()),
+        Arc::new(ModifySoulTool::new()),
+        Arc::new(ApiKeysCheckTool::new()),
+        Arc::new(TaskFullyCompletedTool::new()),
+        Arc::new(ManageSkillsTool::new()),
+        Arc::new(LoadSkillTool::new()),
+
+        // Web tools (shared)
+        Arc::new(WebFetchTool::new()),
+        Arc::new(HttpRequestTool::new()),
+
+        // Finance tools (crypto/DeFi operations)
+        Arc::new(X402RpcTool::new()),
+        Arc::new(X402FetchTool::new()),
+        Arc::new(X402AgentInvokeTool::new()),
+        Arc::new(X402PostTool::new()),
+        Arc::new(Web3TxTool::new()),
+        Arc::new(Web3FunctionCallTool::new()),
+        Arc::new(TokenLookupTool::new()),
+        Arc::new(TokenPriceTool::new()),
+        Arc::new(SwapTool::new()),
+        Arc::new(TxLookupTool::new()),
+        Arc::new(WalletTool::new()),
+        Arc::new(RegisterSetTool::new()),
+        Arc::new(RegistersTool::new()),
+
+        // Filesystem tools (read-only, shared)
+        Arc::new(ReadFileTool::new()),
+        Arc::new(FileStatTool::new()),
+        Arc::new(ChecksumTool::new()),
+        Arc::new(ListFilesTool::new()),
+
+        // Development tools (code editing, git, search)
+        Arc::new(WriteFileTool::new()),
+        Arc::new(ApplyPatchTool::new()),
+        Arc::new(EditFileTool::new()),
+        Arc::new(DeleteFileTool::new()),
+        Arc::new(RenameFileTool::new()),
+        Arc::new(GrepTool::new()),
+        Arc::new(GlobTool::new()),
+        Arc::new(IndexCodebaseTool::new()),
+        Arc::new(CodeSearchTool::new()),
+        Arc::new(LspDiagnosticsTool::new()),
+        Arc::new(SqlQueryTool::new()),
+        Arc::new(FetchOutputTool::new()),
+        Arc::new(GitTool::new()),
+        Arc::new(GithubUserTool::new()),
+
+        // Advanced development tools (scoped commits, deployment, PR quality)
+        Arc::new(CommitterTool::new()),
+        Arc::new(DeployTool::new()),
+        Arc::new(PrQualityTool::new()),
+
+        // Exec tool (Development mode)
+        Arc::new(ExecTool::new()),
+        Arc::new(RunTestsTool::new()),
+        Arc::new(DockerTool::new()),
+        Arc::new(DoctorTool::new()),
+
+        // Messaging tools
+        Arc::new(AgentSendTool::new()),
+        Arc::new(DiscordLookupTool::new()),
+        Arc::new(SendEmailTool::new()),
+        Arc::new(TwitterPostTool::new()),
+    ]
+}
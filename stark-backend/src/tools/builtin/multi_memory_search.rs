@@ -3,11 +3,13 @@
 //! This tool allows the agent to search through stored memories using multiple queries
 //! in a single call, reducing repeated tool calls.
 
+use crate::memory::search::escape_fts5_query;
 use crate::models::MemoryType;
 use crate::tools::registry::Tool;
 use crate::tools::types::{
     PropertySchema, ToolContext, ToolDefinition, ToolGroup, ToolInputSchema, ToolResult,
 };
+use crate::utils::truncate_str;
 use async_trait::async_trait;
 use serde::Deserialize;
 use serde_json::{json, Value};
@@ -163,7 +165,8 @@ impl Tool for MultiMemorySearchTool {
                 None, // category filter not exposed in multi-search for simplicity
                 params.min_importance,
                 limit_per_query,
-            ) {
+                None, // any owner
+            ).await {
                 Ok(results) => {
                     let count = results.len();
                     let mut added = 0;
@@ -242,11 +245,7 @@ impl Tool for MultiMemorySearchTool {
             ));
 
             // Add content (truncate if too long)
-            let content = if memory.content.len() > 400 {
-                format!("{}...", &memory.content[..400])
-            } else {
-                memory.content.clone()
-            };
+            let content = truncate_str(&memory.content, 400);
             output.push_str(&format!("{}\n\n---\n\n", content));
         }
 
@@ -258,22 +257,6 @@ impl Tool for MultiMemorySearchTool {
     }
 }
 
-/// Escape special characters for FTS5 query syntax
-fn escape_fts5_query(query: &str) -> String {
-    let cleaned: String = query
-        .chars()
-        .filter(|c| c.is_alphanumeric() || c.is_whitespace() || *c == '-' || *c == '_')
-        .collect();
-
-    // If the query has multiple words, use OR for more flexible matching
-    let words: Vec<&str> = cleaned.split_whitespace().collect();
-    if words.len() > 1 {
-        words.join(" OR ")
-    } else {
-        cleaned
-    }
-}
-
 #[cfg(test)]
 mod tests {
     use super::*;
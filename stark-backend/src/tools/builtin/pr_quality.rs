@@ -2,6 +2,7 @@ use crate::tools::registry::Tool;
 use crate::tools::types::{
     PropertySchema, ToolContext, ToolDefinition, ToolGroup, ToolInputSchema, ToolResult,
 };
+use crate::utils::truncate_str;
 use async_trait::async_trait;
 use regex::Regex;
 use serde::Deserialize;
@@ -274,11 +275,7 @@ impl PrQualityTool {
                         let has_issue = Regex::new(r"#\d+|issue|ticket|jira", ).map(|r| r.is_match(line)).unwrap_or(false);
                         if !has_issue {
                             let trimmed = line.trim();
-                            let preview = if trimmed.len() > 60 {
-                                format!("{}...", &trimmed[..60])
-                            } else {
-                                trimmed.to_string()
-                            };
+                            let preview = truncate_str(trimmed, 60);
                             findings.push((preview, line_num + 1));
                         }
                     }
@@ -2,7 +2,7 @@
 //!
 //! This tool allows agents to:
 //! - Check the status of a background process
-//! - Get recent output from a process
+//! - Get recent output from a process, or follow it incrementally with a cursor
 //! - Kill a running process
 //! - List all processes for the current channel
 
@@ -10,6 +10,7 @@ use crate::tools::registry::Tool;
 use crate::tools::types::{
     PropertySchema, ToolContext, ToolDefinition, ToolGroup, ToolInputSchema, ToolResult,
 };
+use crate::utils::truncate_str;
 use async_trait::async_trait;
 use serde::Deserialize;
 use serde_json::{json, Value};
@@ -62,6 +63,17 @@ impl ProcessStatusTool {
             },
         );
 
+        properties.insert(
+            "since".to_string(),
+            PropertySchema {
+                schema_type: "integer".to_string(),
+                description: "Cursor returned by a previous 'output' call. Only lines written after it are returned, so output can be followed without repeats.".to_string(),
+                default: None,
+                items: None,
+                enum_values: None,
+            },
+        );
+
         ProcessStatusTool {
             definition: ToolDefinition {
                 name: "process_status".to_string(),
@@ -89,6 +101,8 @@ struct ProcessStatusParams {
     process_id: Option<String>,
     #[serde(default)]
     lines: Option<usize>,
+    #[serde(default)]
+    since: Option<u64>,
 }
 
 #[async_trait]
@@ -159,28 +173,50 @@ impl Tool for ProcessStatusTool {
 
                 let lines = params.lines.unwrap_or(50);
 
-                match process_manager.output(&process_id, lines) {
-                    Some(output_lines) => {
-                        if output_lines.is_empty() {
-                            ToolResult::success(format!(
-                                "No output captured yet for process '{}'",
-                                process_id
-                            ))
-                        } else {
-                            let output = output_lines.join("\n");
-                            ToolResult::success(format!(
-                                "Output from process '{}' (last {} lines):\n\n{}",
-                                process_id,
-                                output_lines.len(),
-                                output
-                            )).with_metadata(json!({
-                                "process_id": process_id,
-                                "line_count": output_lines.len()
-                            }))
-                        }
+                let chunk = match params.since {
+                    Some(cursor) => process_manager.output_since(&process_id, cursor, lines),
+                    None => process_manager.output(&process_id, lines),
+                };
+                let chunk = match chunk {
+                    Some(chunk) => chunk,
+                    None => return ToolResult::error(format!("Process '{}' not found", process_id)),
+                };
+
+                let mut result = if chunk.lines.is_empty() {
+                    if params.since.is_some() {
+                        format!("No new output from process '{}'", process_id)
+                    } else {
+                        format!("No output captured yet for process '{}'", process_id)
                     }
-                    None => ToolResult::error(format!("Process '{}' not found", process_id)),
+                } else if params.since.is_some() {
+                    format!(
+                        "New output from process '{}' ({} lines):\n\n{}",
+                        process_id,
+                        chunk.lines.len(),
+                        chunk.lines.join("\n")
+                    )
+                } else {
+                    format!(
+                        "Output from process '{}' (last {} lines):\n\n{}",
+                        process_id,
+                        chunk.lines.len(),
+                        chunk.lines.join("\n")
+                    )
+                };
+                if chunk.skipped > 0 {
+                    result.push_str(&format!(
+                        "\n\n({} earlier lines were dropped from the buffer)",
+                        chunk.skipped
+                    ));
                 }
+                result.push_str(&format!("\n\nNext cursor: {} (pass as `since` to follow)", chunk.cursor));
+
+                ToolResult::success(result).with_metadata(json!({
+                    "process_id": process_id,
+                    "line_count": chunk.lines.len(),
+                    "cursor": chunk.cursor,
+                    "skipped": chunk.skipped
+                }))
             }
 
             "kill" => {
@@ -210,24 +246,28 @@ impl Tool for ProcessStatusTool {
             }
 
             "list" => {
-                let processes = process_manager.list_for_channel(channel_id);
+                // Background jobs only see their own processes
+                let processes = match &context.job_id {
+                    Some(job_id) => process_manager.list_for_job(job_id),
+                    None => process_manager.list_for_channel(channel_id),
+                };
 
                 if processes.is_empty() {
-                    return ToolResult::success("No background processes found for this channel.");
+                    return ToolResult::success(match context.job_id {
+                        Some(_) => "No background processes found for this job.",
+                        None => "No background processes found for this channel.",
+                    });
                 }
 
                 let mut result = String::from("Background processes:\n\n");
                 for proc in &processes {
                     result.push_str(&format!(
-                        "- {} ({}): {}\n  Command: {}\n  Duration: {}ms\n\n",
+                        "- {} ({}): {}\n  Owner: {}\n  Command: {}\n  Duration: {}ms\n\n",
                         proc.id,
                         proc.pid.map(|p| format!("PID {}", p)).unwrap_or_else(|| "no PID".to_string()),
                         proc.status,
-                        if proc.command.len() > 50 {
-                            format!("{}...", &proc.command[..47])
-                        } else {
-                            proc.command.clone()
-                        },
+                        proc.owner(),
+                        truncate_str(&proc.command, 47),
                         proc.duration_ms
                     ));
                 }
@@ -260,6 +300,7 @@ mod tests {
         assert!(def.input_schema.properties.contains_key("operation"));
         assert!(def.input_schema.properties.contains_key("process_id"));
         assert!(def.input_schema.properties.contains_key("lines"));
+        assert!(def.input_schema.properties.contains_key("since"));
     }
 
     #[tokio::test]
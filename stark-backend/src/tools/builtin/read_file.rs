@@ -3,20 +3,72 @@ use crate::tools::registry::Tool;
 use crate::tools::types::{
     PropertySchema, ToolContext, ToolDefinition, ToolGroup, ToolInputSchema, ToolResult,
 };
+use crate::tools::workspace_path::WorkspacePath;
 use async_trait::async_trait;
 use serde::Deserialize;
 use serde_json::{json, Value};
 use std::collections::HashMap;
-use std::path::{Path, PathBuf};
+use std::path::PathBuf;
 
 /// Intrinsic files mapping: (virtual_name, actual_path_from_repo_root)
 const INTRINSIC_FILES: &[(&str, &str)] = &[
     ("SOUL.md", "SOUL.md"),
 ];
 
-/// Maximum output size in characters to prevent context bloat
+/// Default output size in bytes, to prevent context bloat
 const MAX_OUTPUT_SIZE: usize = 12000;
 
+/// Largest `max_bytes` a caller may ask for
+const MAX_OUTPUT_SIZE_LIMIT: usize = 50000;
+
+/// Default number of lines per page
+const DEFAULT_MAX_LINES: usize = 500;
+
+/// Number of leading bytes inspected when classifying a file as binary
+const BINARY_SNIFF_LEN: usize = 8192;
+
+/// Heuristic binary check: NUL bytes or invalid UTF-8 in the leading chunk
+pub(crate) fn looks_binary(bytes: &[u8]) -> bool {
+    let sample = &bytes[..bytes.len().min(BINARY_SNIFF_LEN)];
+    if sample.contains(&0) {
+        return true;
+    }
+    match std::str::from_utf8(sample) {
+        Ok(_) => false,
+        // A multibyte character cut off at the end of the sample is still text
+        Err(e) => e.error_len().is_some(),
+    }
+}
+
+/// Where the next page of a file starts, handed back to the model as an opaque
+/// token. It carries a fingerprint of the file so a page read after the file
+/// changed can be flagged.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Continuation {
+    /// 0-based line the next page starts at
+    next_line: usize,
+    fingerprint: u64,
+}
+
+impl Continuation {
+    fn encode(&self) -> String {
+        format!("L{}-{:016x}", self.next_line, self.fingerprint)
+    }
+
+    fn decode(token: &str) -> Option<Self> {
+        let (line, fingerprint) = token.strip_prefix('L')?.split_once('-')?;
+        Some(Continuation {
+            next_line: line.parse().ok()?,
+            fingerprint: u64::from_str_radix(fingerprint, 16).ok()?,
+        })
+    }
+}
+
+/// FNV-1a hash of the content, enough to notice it changed between pages
+fn fingerprint(content: &str) -> u64 {
+    content.bytes().fold(0xcbf29ce484222325, |hash, b| (hash ^ b as u64).wrapping_mul(0x100000001b3))
+}
+
 /// Read file tool - reads contents of files within a sandboxed directory
 pub struct ReadFileTool {
     definition: ToolDefinition,
@@ -36,12 +88,55 @@ impl ReadFileTool {
                 enum_values: None,
             },
         );
+        properties.insert(
+            "start_line".to_string(),
+            PropertySchema {
+                schema_type: "integer".to_string(),
+                description: "First line to read, 1-based (default: 1)".to_string(),
+                default: None,
+                items: None,
+                enum_values: None,
+            },
+        );
+        properties.insert(
+            "end_line".to_string(),
+            PropertySchema {
+                schema_type: "integer".to_string(),
+                description: "Last line to read, inclusive (default: start_line + max_lines - 1)".to_string(),
+                default: None,
+                items: None,
+                enum_values: None,
+            },
+        );
         properties.insert(
             "max_lines".to_string(),
             PropertySchema {
                 schema_type: "integer".to_string(),
-                description: "Maximum number of lines to read (default: 500)".to_string(),
-                default: Some(json!(500)),
+                description: "Maximum number of lines to read when end_line is not given (default: 500)".to_string(),
+                default: Some(json!(DEFAULT_MAX_LINES)),
+                items: None,
+                enum_values: None,
+            },
+        );
+        properties.insert(
+            "max_bytes".to_string(),
+            PropertySchema {
+                schema_type: "integer".to_string(),
+                description: format!(
+                    "Maximum size of the output in bytes (default: {}, max: {}). Output stops at the last whole line that fits.",
+                    MAX_OUTPUT_SIZE, MAX_OUTPUT_SIZE_LIMIT
+                ),
+                default: Some(json!(MAX_OUTPUT_SIZE)),
+                items: None,
+                enum_values: None,
+            },
+        );
+        properties.insert(
+            "continuation".to_string(),
+            PropertySchema {
+                schema_type: "string".to_string(),
+                description: "Token from a previous read_file result to read the next page of the same file".to_string(),
+                default: None,
                 items: None,
                 enum_values: None,
             },
@@ -50,8 +145,8 @@ impl ReadFileTool {
             "offset".to_string(),
             PropertySchema {
                 schema_type: "integer".to_string(),
-                description: "Line number to start reading from (0-based, default: 0)".to_string(),
-                default: Some(json!(0)),
+                description: "Line number to start reading from (0-based). Prefer start_line.".to_string(),
+                default: None,
                 items: None,
                 enum_values: None,
             },
@@ -60,7 +155,7 @@ impl ReadFileTool {
         ReadFileTool {
             definition: ToolDefinition {
                 name: "read_file".to_string(),
-                description: "Read the contents of a file. The path must be within the allowed workspace directory.".to_string(),
+                description: "Read a file with line numbers. The path must be within the allowed workspace directory. Large files are returned a page at a time: read a range with start_line/end_line, or pass the continuation token from the previous result to get the next page. Use grep to find the lines you need first.".to_string(),
                 input_schema: ToolInputSchema {
                     schema_type: "object".to_string(),
                     properties,
@@ -81,7 +176,11 @@ impl Default for ReadFileTool {
 #[derive(Debug, Deserialize)]
 struct ReadFileParams {
     path: String,
+    start_line: Option<usize>,
+    end_line: Option<usize>,
     max_lines: Option<usize>,
+    max_bytes: Option<usize>,
+    continuation: Option<String>,
     offset: Option<usize>,
 }
 
@@ -91,14 +190,31 @@ impl Tool for ReadFileTool {
         self.definition.clone()
     }
 
+    fn is_read_only(&self) -> bool {
+        true
+    }
+
     async fn execute(&self, params: Value, context: &ToolContext) -> ToolResult {
         let params: ReadFileParams = match serde_json::from_value(params) {
             Ok(p) => p,
             Err(e) => return ToolResult::error(format!("Invalid parameters: {}", e)),
         };
 
-        let max_lines = params.max_lines.unwrap_or(500);
-        let offset = params.offset.unwrap_or(0);
+        if params.start_line == Some(0) {
+            return ToolResult::error("start_line is 1-based; use 1 for the first line");
+        }
+        if let (Some(start), Some(end)) = (params.start_line, params.end_line)
+            && end < start
+        {
+            return ToolResult::error(format!("end_line ({}) is before start_line ({})", end, start));
+        }
+        let continuation = match params.continuation.as_deref().map(Continuation::decode) {
+            Some(None) => return ToolResult::error("Invalid continuation token; pass it exactly as returned"),
+            Some(Some(c)) => Some(c),
+            None => None,
+        };
+        let max_lines = params.max_lines.unwrap_or(DEFAULT_MAX_LINES).max(1);
+        let max_bytes = params.max_bytes.unwrap_or(MAX_OUTPUT_SIZE).clamp(1, MAX_OUTPUT_SIZE_LIMIT);
 
         // Check if this is an intrinsic file (e.g., SOUL.md)
         let intrinsic_match = INTRINSIC_FILES.iter().find(|(name, _)| *name == params.path);
@@ -132,37 +248,19 @@ impl Tool for ReadFileTool {
             let journal = PathBuf::from(journal_dir());
 
             // Resolve the path - check if it starts with "journal/" to use journal dir
-            let requested_path = Path::new(&params.path);
-            let (full_path, base_dir) = if params.path.starts_with("journal/") || params.path == "journal" {
+            let resolved = if params.path.starts_with("journal/") || params.path == "journal" {
                 // Strip "journal/" prefix and use journal directory
-                let relative = params.path.strip_prefix("journal/").unwrap_or(&params.path);
-                (journal.join(relative), journal.clone())
-            } else if requested_path.is_absolute() {
-                (requested_path.to_path_buf(), workspace.clone())
+                let relative = params.path.strip_prefix("journal/").unwrap_or("");
+                WorkspacePath::resolve(&journal, relative)
             } else {
-                (workspace.join(requested_path), workspace.clone())
-            };
-
-            // Canonicalize paths for comparison
-            let canonical_base = match base_dir.canonicalize() {
-                Ok(p) => p,
-                Err(e) => {
-                    return ToolResult::error(format!("Cannot resolve base directory: {}", e))
-                }
-            };
-
-            let canonical_path = match full_path.canonicalize() {
-                Ok(p) => p,
-                Err(e) => return ToolResult::error(format!("Cannot resolve file path: {}", e)),
+                WorkspacePath::resolve(&workspace, &params.path)
             };
 
             // Security check: ensure path is within allowed directory (workspace or journal)
-            if !canonical_path.starts_with(&canonical_base) {
-                return ToolResult::error(format!(
-                    "Access denied: path '{}' is outside the allowed directory",
-                    params.path
-                ));
-            }
+            let canonical_path = match resolved {
+                Ok(p) => p.path().to_path_buf(),
+                Err(e) => return ToolResult::error(e),
+            };
 
             // Check if file exists and is a file
             if !canonical_path.exists() {
@@ -174,64 +272,107 @@ impl Tool for ReadFileTool {
             }
 
             // Read the file
-            match tokio::fs::read_to_string(&canonical_path).await {
-                Ok(c) => c,
+            let bytes = match tokio::fs::read(&canonical_path).await {
+                Ok(b) => b,
                 Err(e) => return ToolResult::error(format!("Failed to read file: {}", e)),
+            };
+            if looks_binary(&bytes) {
+                return ToolResult::error(format!(
+                    "File appears to be binary ({} bytes): {}. Use file_stat to inspect it.",
+                    bytes.len(),
+                    params.path
+                ));
             }
+            String::from_utf8_lossy(&bytes).into_owned()
         };
 
-        // Apply offset and max_lines
         let lines: Vec<&str> = content.lines().collect();
         let total_lines = lines.len();
+        let file_fingerprint = fingerprint(&content);
 
-        if offset >= total_lines {
+        let mut notes = Vec::new();
+        let start = match (&continuation, params.start_line, params.offset) {
+            (Some(c), _, _) => {
+                if c.fingerprint != file_fingerprint {
+                    notes.push("⚠️ The file changed since the previous page; line numbers may have shifted.".to_string());
+                }
+                c.next_line
+            }
+            (None, Some(line), _) => line - 1,
+            (None, None, offset) => offset.unwrap_or(0),
+        };
+
+        if start >= total_lines {
             return ToolResult::success(format!(
-                "[Empty: offset {} exceeds total lines {}]",
-                offset, total_lines
+                "[Empty: line {} is past the end of the file ({} lines)]",
+                start + 1,
+                total_lines
             ))
             .with_metadata(json!({
                 "path": params.path,
                 "total_lines": total_lines,
-                "offset": offset,
+                "start_line": start + 1,
                 "lines_returned": 0
             }));
         }
 
-        let end = (offset + max_lines).min(total_lines);
-        let selected_lines: Vec<String> = lines[offset..end]
-            .iter()
-            .enumerate()
-            .map(|(i, line)| format!("{:>5}│ {}", offset + i + 1, line))
-            .collect();
-
-        let result = selected_lines.join("\n");
-        let truncated = end < total_lines;
-
-        let mut output = if truncated {
-            format!(
-                "{}\n\n[Showing lines {}-{} of {}. Use offset parameter to read more.]",
-                result,
-                offset + 1,
-                end,
-                total_lines
-            )
-        } else {
-            result
-        };
+        let requested_end = match params.end_line {
+            Some(end_line) if continuation.is_none() => end_line,
+            _ => start + max_lines,
+        }
+        .min(total_lines);
+
+        // Whole lines only, until the byte budget runs out
+        let mut output = String::new();
+        let mut end = start;
+        while end < requested_end {
+            let line = format!("{:>5}│ {}\n", end + 1, lines[end]);
+            if output.len() + line.len() > max_bytes {
+                if end == start {
+                    // A single line longer than the budget: show its beginning
+                    let mut cut = max_bytes.min(line.len());
+                    while !line.is_char_boundary(cut) {
+                        cut -= 1;
+                    }
+                    output.push_str(&line[..cut]);
+                    output.push_str("…\n");
+                    notes.push(format!("⚠️ Line {} is longer than max_bytes and was cut off.", end + 1));
+                    end += 1;
+                }
+                break;
+            }
+            output.push_str(&line);
+            end += 1;
+        }
 
-        // Truncate if output is too large to prevent context bloat
-        let size_truncated = output.len() > MAX_OUTPUT_SIZE;
-        if size_truncated {
-            output.truncate(MAX_OUTPUT_SIZE);
-            output.push_str("\n\n⚠️ [Output truncated due to size - use offset/max_lines for smaller chunks]");
+        let next = (end < total_lines).then_some(Continuation {
+            next_line: end,
+            fingerprint: file_fingerprint,
+        });
+        let mut result = output.trim_end_matches('\n').to_string();
+        if let Some(next) = &next {
+            result.push_str(&format!(
+                "\n\n[Showing lines {}-{} of {}. To read on, call read_file again with continuation: \"{}\"]",
+                start + 1,
+                end,
+                total_lines,
+                next.encode()
+            ));
+        }
+        for note in &notes {
+            result.push('\n');
+            result.push_str(note);
         }
 
-        ToolResult::success(output).with_metadata(json!({
+        ToolResult::success(result).with_metadata(json!({
             "path": params.path,
             "total_lines": total_lines,
-            "offset": offset,
-            "lines_returned": end - offset,
-            "truncated": truncated
+            "start_line": start + 1,
+            "end_line": end,
+            "lines_returned": end - start,
+            "truncated": next.is_some(),
+            "next_line": next.as_ref().map(|n| n.next_line + 1),
+            "continuation": next.as_ref().map(Continuation::encode)
         }))
     }
 }
@@ -254,4 +395,53 @@ mod tests {
         assert!(!result.success);
         assert!(result.error.unwrap().contains("outside the workspace"));
     }
+
+    #[tokio::test]
+    async fn test_read_file_ranges_and_continuation() {
+        let tool = ReadFileTool::new();
+        let temp_dir = TempDir::new().unwrap();
+        let content: String = (1..=30).map(|i| format!("line {}\n", i)).collect();
+        std::fs::write(temp_dir.path().join("big.txt"), &content).unwrap();
+        let context = ToolContext::new().with_workspace(temp_dir.path().to_string_lossy().to_string());
+
+        let result = tool
+            .execute(json!({ "path": "big.txt", "start_line": 10, "end_line": 12 }), &context)
+            .await;
+        assert!(result.success);
+        assert!(result.content.starts_with("   10│ line 10\n   11│ line 11\n   12│ line 12"));
+
+        // Numbered lines are 16 bytes here, so 40 bytes hold two of them
+        let result = tool
+            .execute(json!({ "path": "big.txt", "max_bytes": 40 }), &context)
+            .await;
+        let metadata = result.metadata.unwrap();
+        assert_eq!(metadata["lines_returned"], 2);
+        let token = metadata["continuation"].as_str().unwrap().to_string();
+
+        let result = tool
+            .execute(json!({ "path": "big.txt", "continuation": token, "max_lines": 100 }), &context)
+            .await;
+        assert!(result.content.starts_with("    3│ line 3"));
+        assert_eq!(result.metadata.unwrap()["truncated"], false);
+
+        // A page read after the file changed is flagged
+        std::fs::write(temp_dir.path().join("big.txt"), format!("new\n{}", content)).unwrap();
+        let result = tool
+            .execute(json!({ "path": "big.txt", "continuation": token }), &context)
+            .await;
+        assert!(result.content.contains("file changed"));
+
+        let result = tool
+            .execute(json!({ "path": "big.txt", "start_line": 5, "end_line": 2 }), &context)
+            .await;
+        assert!(!result.success);
+    }
+
+    #[test]
+    fn test_looks_binary() {
+        assert!(!looks_binary(b"fn main() {}\n"));
+        assert!(!looks_binary("héllo wörld".as_bytes()));
+        assert!(looks_binary(&[0x7f, b'E', b'L', b'F', 0x00, 0x01]));
+        assert!(looks_binary(&[0xff, 0xfe, 0xfd, b'a']));
+    }
 }
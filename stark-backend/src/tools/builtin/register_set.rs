@@ -7,6 +7,7 @@ use crate::tools::registry::Tool;
 use crate::tools::types::{
     PropertySchema, ToolContext, ToolDefinition, ToolGroup, ToolInputSchema, ToolResult,
 };
+use crate::utils::truncate_str;
 use async_trait::async_trait;
 use serde::Deserialize;
 use serde_json::{json, Value};
@@ -201,20 +202,12 @@ impl Tool for RegisterSetTool {
             (Some(jv), _) => {
                 // Store JSON object directly
                 let display = serde_json::to_string(jv).unwrap_or_else(|_| "{}".to_string());
-                let truncated = if display.len() > 50 {
-                    format!("{}...", &display[..50])
-                } else {
-                    display.clone()
-                };
+                let truncated = truncate_str(&display, 50);
                 (jv.clone(), truncated)
             }
             (None, Some(v)) => {
                 // Store string as JSON string
-                let truncated = if v.len() > 50 {
-                    format!("{}...", &v[..50])
-                } else {
-                    v.clone()
-                };
+                let truncated = truncate_str(v, 50);
                 (json!(v), truncated)
             }
             (None, None) => {
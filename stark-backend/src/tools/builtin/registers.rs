@@ -0,0 +1,193 @@
+//! Registers tool for inspecting the register store
+//!
+//! Lets the agent see which values earlier tool calls cached (token addresses,
+//! quotes, amounts) before it runs a tool that reads them. Registers persist
+//! per conversation, so this also shows what survived from earlier turns.
+
+use crate::tools::registry::Tool;
+use crate::tools::types::{
+    PropertySchema, ToolContext, ToolDefinition, ToolGroup, ToolInputSchema, ToolResult,
+};
+use crate::utils::truncate_str;
+use async_trait::async_trait;
+use serde::Deserialize;
+use serde_json::{json, Value};
+use std::collections::HashMap;
+
+/// Registers tool
+pub struct RegistersTool {
+    definition: ToolDefinition,
+}
+
+impl RegistersTool {
+    pub fn new() -> Self {
+        let mut properties = HashMap::new();
+
+        properties.insert(
+            "action".to_string(),
+            PropertySchema {
+                schema_type: "string".to_string(),
+                description: "'list' shows every register with its source and age; 'get' returns the full value of one register".to_string(),
+                default: Some(json!("list")),
+                items: None,
+                enum_values: Some(vec!["list".to_string(), "get".to_string()]),
+            },
+        );
+
+        properties.insert(
+            "key".to_string(),
+            PropertySchema {
+                schema_type: "string".to_string(),
+                description: "Register key to read (required for 'get')".to_string(),
+                default: None,
+                items: None,
+                enum_values: None,
+            },
+        );
+
+        RegistersTool {
+            definition: ToolDefinition {
+                name: "registers".to_string(),
+                description: "Inspect the registers cached by earlier tool calls in this conversation (e.g. sell_token/buy_token from token_lookup, swap quotes). Use it to check what is already set before calling tools that read registers.".to_string(),
+                input_schema: ToolInputSchema {
+                    schema_type: "object".to_string(),
+                    properties,
+                    required: vec![],
+                },
+                group: ToolGroup::Finance,
+            },
+        }
+    }
+}
+
+impl Default for RegistersTool {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct RegistersParams {
+    #[serde(default = "default_action")]
+    action: String,
+    key: Option<String>,
+}
+
+fn default_action() -> String {
+    "list".to_string()
+}
+
+#[async_trait]
+impl Tool for RegistersTool {
+    fn definition(&self) -> ToolDefinition {
+        self.definition.clone()
+    }
+
+    async fn execute(&self, params: Value, context: &ToolContext) -> ToolResult {
+        let params: RegistersParams = match serde_json::from_value(params) {
+            Ok(p) => p,
+            Err(e) => return ToolResult::error(format!("Invalid parameters: {}", e)),
+        };
+
+        match params.action.as_str() {
+            "list" => {
+                let mut keys = context.registers.keys();
+                keys.sort();
+                if keys.is_empty() {
+                    return ToolResult::success(
+                        "No registers are set. (wallet_address is always available as an intrinsic register.)",
+                    )
+                    .with_metadata(json!({ "registers": [] }));
+                }
+
+                let mut lines = Vec::new();
+                let mut registers = Vec::new();
+                for key in keys {
+                    let Some(entry) = context.registers.get_entry(&key) else {
+                        continue;
+                    };
+                    let age_secs = entry.created_at.elapsed().as_secs();
+                    let preview = entry.value.to_string();
+                    lines.push(format!(
+                        "- {} = {} (from {}, {}s ago)",
+                        key,
+                        truncate_str(&preview, 80),
+                        entry.source_tool,
+                        age_secs
+                    ));
+                    registers.push(json!({
+                        "key": key,
+                        "source_tool": entry.source_tool,
+                        "age_secs": age_secs,
+                    }));
+                }
+
+                ToolResult::success(format!("{} registers:\n{}", registers.len(), lines.join("\n")))
+                    .with_metadata(json!({ "registers": registers }))
+            }
+            "get" => {
+                let Some(key) = params.key else {
+                    return ToolResult::error("'key' is required for action 'get'");
+                };
+                match context.registers.get_entry_or_intrinsic(&key) {
+                    Some(entry) => {
+                        let pretty = serde_json::to_string_pretty(&entry.value)
+                            .unwrap_or_else(|_| entry.value.to_string());
+                        ToolResult::success(format!(
+                            "Register '{}' (from {}, {}s ago):\n{}",
+                            key,
+                            entry.source_tool,
+                            entry.created_at.elapsed().as_secs(),
+                            pretty
+                        ))
+                        .with_metadata(json!({
+                            "key": key,
+                            "value": entry.value,
+                            "source_tool": entry.source_tool,
+                        }))
+                    }
+                    None => {
+                        let mut available = context.registers.keys();
+                        available.sort();
+                        ToolResult::error(format!(
+                            "Register '{}' is not set. Available registers: {:?}",
+                            key, available
+                        ))
+                    }
+                }
+            }
+            other => ToolResult::error(format!("Unknown action '{}'. Use 'list' or 'get'.", other)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tools::RegisterStore;
+
+    #[tokio::test]
+    async fn test_list_and_get() {
+        let tool = RegistersTool::new();
+        let registers = RegisterStore::new();
+        let context = ToolContext::new().with_registers(registers.clone());
+
+        let result = tool.execute(json!({}), &context).await;
+        assert!(result.success);
+        assert!(result.content.contains("No registers"));
+
+        registers.set("sell_token", json!("0x833589fCD6eDb6E08f4c7C32D4f71b54bdA02913"), "token_lookup");
+        let result = tool.execute(json!({ "action": "list" }), &context).await;
+        assert!(result.success);
+        assert!(result.content.contains("sell_token"));
+        assert!(result.content.contains("token_lookup"));
+
+        let result = tool.execute(json!({ "action": "get", "key": "sell_token" }), &context).await;
+        assert!(result.success);
+        assert!(result.content.contains("0x833589fCD6eDb6E08f4c7C32D4f71b54bdA02913"));
+
+        let result = tool.execute(json!({ "action": "get", "key": "buy_token" }), &context).await;
+        assert!(!result.success);
+        assert!(result.content.contains("sell_token"));
+    }
+}
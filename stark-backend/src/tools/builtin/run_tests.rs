@@ -0,0 +1,285 @@
+//! Test runner tool
+//!
+//! Runs the project's tests with the right runner and reports counts and the
+//! failing tests (see `tools::test_report`) rather than the full log.
+
+use crate::tools::registry::Tool;
+use crate::tools::sandbox::SandboxConfig;
+use crate::tools::test_report::{self, Framework, MAX_FAILURES};
+use crate::tools::types::{
+    PropertySchema, ToolContext, ToolDefinition, ToolGroup, ToolInputSchema, ToolResult,
+};
+use crate::tools::workspace_path::WorkspacePath;
+use async_trait::async_trait;
+use serde::Deserialize;
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::process::Stdio;
+use std::time::Duration;
+use tokio::time::timeout;
+
+/// Longest output tail shown when no results could be parsed, in characters
+const MAX_RAW_OUTPUT: usize = 4000;
+
+/// Tool that runs a project's test suite and summarizes the results
+pub struct RunTestsTool {
+    definition: ToolDefinition,
+    /// Maximum run time in seconds
+    max_timeout: u64,
+    sandbox: SandboxConfig,
+}
+
+impl RunTestsTool {
+    pub fn new() -> Self {
+        let max_timeout = 1800;
+        let mut properties = HashMap::new();
+
+        properties.insert(
+            "framework".to_string(),
+            PropertySchema {
+                schema_type: "string".to_string(),
+                description: "Test runner to use (default: auto, detected from Cargo.toml, package.json or Python project files)".to_string(),
+                default: Some(json!("auto")),
+                items: None,
+                enum_values: Some(vec![
+                    "auto".to_string(),
+                    "cargo".to_string(),
+                    "npm".to_string(),
+                    "pytest".to_string(),
+                ]),
+            },
+        );
+
+        properties.insert(
+            "path".to_string(),
+            PropertySchema {
+                schema_type: "string".to_string(),
+                description: "Project directory, relative to the workspace (default: the workspace root)".to_string(),
+                default: Some(json!(".")),
+                items: None,
+                enum_values: None,
+            },
+        );
+
+        properties.insert(
+            "filter".to_string(),
+            PropertySchema {
+                schema_type: "string".to_string(),
+                description: "Only run tests whose name matches (cargo test filter, jest/vitest -t, pytest -k)".to_string(),
+                default: None,
+                items: None,
+                enum_values: None,
+            },
+        );
+
+        properties.insert(
+            "timeout".to_string(),
+            PropertySchema {
+                schema_type: "integer".to_string(),
+                description: format!("Timeout in seconds (default: 300, max: {})", max_timeout),
+                default: Some(json!(300)),
+                items: None,
+                enum_values: None,
+            },
+        );
+
+        RunTestsTool {
+            definition: ToolDefinition {
+                name: "run_tests".to_string(),
+                description: "Run the project's tests (cargo test, npm test or pytest) and get back pass/fail/skip counts and the failing tests with their messages, instead of the full log. Prefer this over exec for running tests.".to_string(),
+                input_schema: ToolInputSchema {
+                    schema_type: "object".to_string(),
+                    properties,
+                    required: vec![],
+                },
+                group: ToolGroup::Exec,
+            },
+            max_timeout,
+            sandbox: SandboxConfig::from_env(),
+        }
+    }
+}
+
+impl Default for RunTestsTool {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct RunTestsParams {
+    framework: Option<String>,
+    path: Option<String>,
+    filter: Option<String>,
+    timeout: Option<u64>,
+}
+
+/// The last `max_chars` characters of `text`
+fn tail(text: &str, max_chars: usize) -> &str {
+    let skip = text.chars().count().saturating_sub(max_chars);
+    match text.char_indices().nth(skip) {
+        Some((i, _)) => &text[i..],
+        None => text,
+    }
+}
+
+#[async_trait]
+impl Tool for RunTestsTool {
+    fn definition(&self) -> ToolDefinition {
+        self.definition.clone()
+    }
+
+    fn writes_workspace(&self) -> bool {
+        true
+    }
+
+    async fn execute(&self, params: Value, context: &ToolContext) -> ToolResult {
+        let params: RunTestsParams = match serde_json::from_value(params) {
+            Ok(p) => p,
+            Err(e) => return ToolResult::error(format!("Invalid parameters: {}", e)),
+        };
+
+        let workspace = context
+            .workspace_dir
+            .as_ref()
+            .map(PathBuf::from)
+            .unwrap_or_else(|| std::env::current_dir().unwrap_or_else(|_| PathBuf::from(".")));
+        let resolved = match WorkspacePath::resolve(&workspace, params.path.as_deref().unwrap_or(".")) {
+            Ok(p) => p,
+            Err(e) => return ToolResult::error(e),
+        };
+        let dir = resolved.path().to_path_buf();
+        if !dir.is_dir() {
+            return ToolResult::error(format!("'{}' is not a directory", dir.display()));
+        }
+
+        let framework = match params.framework.as_deref().unwrap_or("auto") {
+            "auto" => match Framework::detect(&dir) {
+                Some(f) => f,
+                None => {
+                    return ToolResult::error(
+                        "Could not detect a test framework (no Cargo.toml, package.json test script or Python project files). Pass framework explicitly or use exec.",
+                    )
+                }
+            },
+            other => match Framework::from_str(other) {
+                Some(f) => f,
+                None => return ToolResult::error(format!("Unknown framework: {}. Use auto, cargo, npm or pytest.", other)),
+            },
+        };
+
+        let command = framework.command(params.filter.as_deref());
+        let timeout_secs = params.timeout.unwrap_or(300).min(self.max_timeout);
+        let env: Vec<(String, String)> = [("CI", "true"), ("NO_COLOR", "1"), ("FORCE_COLOR", "0"), ("CARGO_TERM_COLOR", "never")]
+            .into_iter()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect();
+        let mut cmd = tokio::process::Command::from(self.sandbox.command(&command, &dir, &env));
+        cmd.stdin(Stdio::null()).stdout(Stdio::piped()).stderr(Stdio::piped()).kill_on_drop(true);
+
+        log::info!("Running tests: {} (timeout: {}s, dir: {:?})", command, timeout_secs, dir);
+        let start = std::time::Instant::now();
+        let output = match timeout(Duration::from_secs(timeout_secs), cmd.output()).await {
+            Ok(Ok(output)) => output,
+            Ok(Err(e)) => return ToolResult::error(format!("Failed to run tests: {}", e)),
+            Err(_) => {
+                return ToolResult::error(format!(
+                    "Tests timed out after {} seconds. Use filter to run fewer tests, or raise timeout.",
+                    timeout_secs
+                ))
+            }
+        };
+        let duration_ms = start.elapsed().as_millis() as i64;
+        let exit_code = output.status.code().unwrap_or(-1);
+        let combined = format!(
+            "{}\n{}",
+            String::from_utf8_lossy(&output.stdout),
+            String::from_utf8_lossy(&output.stderr)
+        );
+
+        let summary = test_report::parse(framework, &combined);
+        let passed = output.status.success() && summary.failed == 0;
+
+        let text = if summary.parsed {
+            let mut text = format!(
+                "{} ({}): {} passed, {} failed, {} skipped in {:.1}s",
+                framework.as_str(),
+                command,
+                summary.passed,
+                summary.failed,
+                summary.skipped,
+                duration_ms as f64 / 1000.0
+            );
+            if !summary.failures.is_empty() {
+                text.push_str("\n\nFailing tests:");
+                for failure in &summary.failures {
+                    text.push_str(&format!("\n- {}", failure.name));
+                    if let Some(ref message) = failure.message {
+                        for line in message.lines() {
+                            text.push_str(&format!("\n    {}", line));
+                        }
+                    }
+                }
+                if summary.failed > summary.failures.len() && summary.failures.len() >= MAX_FAILURES {
+                    text.push_str(&format!("\n(only the first {} are listed)", MAX_FAILURES));
+                }
+            } else if !passed {
+                text.push_str(&format!(
+                    "\n\nThe run exited with code {}. Last part of the output:\n{}",
+                    exit_code,
+                    tail(combined.trim_end(), MAX_RAW_OUTPUT)
+                ));
+            }
+            text
+        } else {
+            // Usually a build error or a missing runner: show what happened
+            format!(
+                "{} ({}) exited with code {} and no test results were found. Last part of the output:\n{}",
+                framework.as_str(),
+                command,
+                exit_code,
+                tail(combined.trim_end(), MAX_RAW_OUTPUT)
+            )
+        };
+
+        let result = if passed { ToolResult::success(text) } else { ToolResult::error(text) };
+        result.with_metadata(json!({
+            "framework": framework,
+            "command": command,
+            "exit_code": exit_code,
+            "duration_ms": duration_ms,
+            "parsed": summary.parsed,
+            "passed": summary.passed,
+            "failed": summary.failed,
+            "skipped": summary.skipped,
+            "failures": summary.failures,
+        }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_tail() {
+        assert_eq!(tail("héllo", 3), "llo");
+        assert_eq!(tail("hi", 10), "hi");
+    }
+
+    #[tokio::test]
+    async fn test_run_tests_needs_a_framework() {
+        let dir = TempDir::new().unwrap();
+        let context = ToolContext::new().with_workspace(dir.path().to_string_lossy().to_string());
+        let tool = RunTestsTool::new();
+
+        let result = tool.execute(json!({}), &context).await;
+        assert!(result.error.unwrap().contains("Could not detect"));
+        let result = tool.execute(json!({ "framework": "maven" }), &context).await;
+        assert!(result.error.unwrap().contains("Unknown framework"));
+        let result = tool.execute(json!({ "path": "../" }), &context).await;
+        assert!(!result.success);
+    }
+}
@@ -0,0 +1,207 @@
+//! Email sending tool
+//!
+//! Sends plain-text mail through Resend or SMTP (see `integrations::email`),
+//! only to recipients in the domains listed in `STARK_EMAIL_ALLOWED_DOMAINS`.
+
+use crate::controllers::api_keys::ApiKeyId;
+use crate::integrations::email::{self, Credentials, EmailConfig, OutgoingEmail};
+use crate::tools::registry::Tool;
+use crate::tools::types::{
+    PropertySchema, ToolContext, ToolDefinition, ToolGroup, ToolInputSchema, ToolResult,
+};
+use async_trait::async_trait;
+use serde::Deserialize;
+use serde_json::{json, Value};
+use std::collections::HashMap;
+
+/// Longest body the tool will send, in bytes
+const MAX_BODY_BYTES: usize = 100_000;
+/// Most To and Cc recipients per message
+const MAX_RECIPIENTS: usize = 20;
+
+/// Tool for sending email to allowlisted domains
+pub struct SendEmailTool {
+    definition: ToolDefinition,
+    config: EmailConfig,
+}
+
+impl SendEmailTool {
+    pub fn new() -> Self {
+        let mut properties = HashMap::new();
+        let address_list = |description: &str| PropertySchema {
+            schema_type: "array".to_string(),
+            description: description.to_string(),
+            default: None,
+            items: Some(Box::new(PropertySchema {
+                schema_type: "string".to_string(),
+                description: "Email address, optionally as Name <user@example.com>".to_string(),
+                default: None,
+                items: None,
+                enum_values: None,
+            })),
+            enum_values: None,
+        };
+
+        properties.insert("to".to_string(), address_list("Recipients"));
+        properties.insert("cc".to_string(), address_list("Optional: Cc recipients"));
+
+        properties.insert(
+            "subject".to_string(),
+            PropertySchema {
+                schema_type: "string".to_string(),
+                description: "Subject line".to_string(),
+                default: None,
+                items: None,
+                enum_values: None,
+            },
+        );
+
+        properties.insert(
+            "body".to_string(),
+            PropertySchema {
+                schema_type: "string".to_string(),
+                description: "Plain-text message body".to_string(),
+                default: None,
+                items: None,
+                enum_values: None,
+            },
+        );
+
+        properties.insert(
+            "reply_to".to_string(),
+            PropertySchema {
+                schema_type: "string".to_string(),
+                description: "Optional: Reply-To address".to_string(),
+                default: None,
+                items: None,
+                enum_values: None,
+            },
+        );
+
+        let config = EmailConfig::from_env();
+        let domains = if config.allowed_domains.is_empty() {
+            "none are configured yet".to_string()
+        } else {
+            config.allowed_domains.join(", ")
+        };
+
+        SendEmailTool {
+            definition: ToolDefinition {
+                name: "send_email".to_string(),
+                description: format!(
+                    "Send a plain-text email. Recipients must be in an allowlisted domain ({}). Only send mail the user asked for.",
+                    domains
+                ),
+                input_schema: ToolInputSchema {
+                    schema_type: "object".to_string(),
+                    properties,
+                    required: vec!["to".to_string(), "subject".to_string(), "body".to_string()],
+                },
+                group: ToolGroup::Messaging,
+            },
+            config,
+        }
+    }
+}
+
+impl Default for SendEmailTool {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct SendEmailParams {
+    to: Vec<String>,
+    #[serde(default)]
+    cc: Vec<String>,
+    subject: String,
+    body: String,
+    reply_to: Option<String>,
+}
+
+#[async_trait]
+impl Tool for SendEmailTool {
+    fn definition(&self) -> ToolDefinition {
+        self.definition.clone()
+    }
+
+    async fn execute(&self, params: Value, context: &ToolContext) -> ToolResult {
+        let params: SendEmailParams = match serde_json::from_value(params) {
+            Ok(p) => p,
+            Err(e) => return ToolResult::error(format!("Invalid parameters: {}", e)),
+        };
+
+        let message = OutgoingEmail {
+            to: params.to,
+            cc: params.cc,
+            reply_to: params.reply_to.filter(|r| !r.trim().is_empty()),
+            subject: params.subject.trim().to_string(),
+            body: params.body,
+            ..Default::default()
+        };
+        if message.to.is_empty() {
+            return ToolResult::error("to must list at least one recipient");
+        }
+        if message.to.len() + message.cc.len() > MAX_RECIPIENTS {
+            return ToolResult::error(format!("At most {} recipients per email", MAX_RECIPIENTS));
+        }
+        if message.body.len() > MAX_BODY_BYTES {
+            return ToolResult::error(format!("Body is too long (max {} bytes)", MAX_BODY_BYTES));
+        }
+        if let Err(e) = message.validate() {
+            return ToolResult::error(e);
+        }
+        let blocked: Vec<&str> = message.recipients().filter(|r| !self.config.recipient_allowed(r)).collect();
+        if !blocked.is_empty() {
+            return ToolResult::error(format!(
+                "Recipient domain not allowed: {}. Allowed domains are set in {}.",
+                blocked.join(", "),
+                email::env_vars::ALLOWED_DOMAINS
+            ));
+        }
+
+        let credentials = Credentials {
+            resend_api_key: context.get_api_key_by_id(ApiKeyId::ResendApiKey),
+            smtp_password: context.get_api_key_by_id(ApiKeyId::SmtpPassword),
+        };
+        let recipients: Vec<&str> = message.recipients().collect();
+        log::info!("[SEND_EMAIL] Sending '{}' to {}", message.subject, recipients.join(", "));
+        match self.config.send(&credentials, &message).await {
+            Ok(id) => ToolResult::success(format!("Email sent to {}", recipients.join(", "))).with_metadata(json!({
+                "message_id": id,
+                "recipients": recipients,
+                "transport": if credentials.resend_api_key.is_some() { "resend" } else { "smtp" },
+            })),
+            Err(e) => ToolResult::error(e),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_send_email_checks() {
+        let tool = SendEmailTool {
+            config: EmailConfig {
+                from: Some("Bot <bot@stark.dev>".to_string()),
+                allowed_domains: vec!["example.com".to_string()],
+                smtp: None,
+            },
+            ..SendEmailTool::new()
+        };
+        let context = ToolContext::new();
+        let send = |to: &str, subject: &str| json!({ "to": [to], "subject": subject, "body": "hi" });
+
+        let result = tool.execute(send("eve@evil.com", "Hi"), &context).await;
+        assert!(result.error.unwrap().contains("not allowed: eve@evil.com"));
+        let result = tool.execute(send("ann@example.com", "Hi\nBcc: eve@evil.com"), &context).await;
+        assert!(result.error.unwrap().contains("line breaks"));
+        let result = tool.execute(send("ann", "Hi"), &context).await;
+        assert!(result.error.unwrap().contains("Invalid email address"));
+        let result = tool.execute(send("Ann <ann@example.com>", "Hi"), &context).await;
+        assert!(result.error.unwrap().contains("No email transport configured"));
+    }
+}
@@ -71,9 +71,12 @@ impl SetAgentSubtypeTool {
                  • web3_tx - Execute blockchain transactions\n\
                  • web3_function_call - Read smart contract data (use presets like erc20_balance)\n\
                  • token_lookup - Get token info and addresses\n\
+                 • wallet - Wallet address, balances and nonce\n\
+                 • swap - Quote, simulate and execute token swaps\n\
                  • x402_rpc - RPC calls (get_balance, gas_price, etc.)\n\
                  • x402_fetch - Payment protocol fetch operations\n\
                  • register_set - Store transaction data safely\n\
+                 • registers - See which registers are already set\n\
                  • ask_user - Ask user for clarification (e.g., which network)\n\n\
                  Note: wallet_address is an intrinsic register - always available.\n\n\
                  Skills: swap, transfer, bankr, token_price, weth, local_wallet"
@@ -89,7 +92,10 @@ impl SetAgentSubtypeTool {
                  • delete_file - Remove files/directories\n\
                  • rename_file - Move/rename files\n\
                  • git - Git operations (status, diff, commit, branch)\n\
-                 • exec - Run shell commands\n\n\
+                 • exec - Run shell commands\n\
+                 • run_tests - Run the test suite and summarize failures\n\
+                 • docker - Build images and run containers to test the app\n\
+                 • lsp_diagnostics - Compiler/type errors from a language server\n\n\
                  Skills: plan, commit, test, debug, code-review, github"
                     .to_string()
             }
@@ -97,6 +103,7 @@ impl SetAgentSubtypeTool {
                 "📱 Secretary toolbox activated.\n\n\
                  Tools now available:\n\
                  • agent_send - Send messages to other channels\n\
+                 • send_email - Email allowlisted recipients\n\
                  • (Social tools for MoltX, scheduling coming soon)\n\n\
                  Skills: moltx, moltbook, scheduling"
                     .to_string()
@@ -0,0 +1,263 @@
+//! SQL query tool
+//!
+//! Queries the database configured as `SQL_DATABASE_URL` on the API keys page,
+//! or a SQLite file in the workspace. Read-only unless `write` is set; see
+//! `tools::sql_client` for how that is enforced.
+
+use crate::controllers::api_keys::ApiKeyId;
+use crate::tools::registry::Tool;
+use crate::tools::sql_client::{self, Dsn};
+use crate::tools::types::{
+    PropertySchema, ToolContext, ToolDefinition, ToolGroup, ToolInputSchema, ToolResult,
+};
+use crate::tools::workspace_path::WorkspacePath;
+use async_trait::async_trait;
+use serde::Deserialize;
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::time::Duration;
+
+/// Most rows a query may return
+const MAX_LIMIT: usize = 1000;
+
+/// Tool for querying user-provided SQL databases
+pub struct SqlQueryTool {
+    definition: ToolDefinition,
+}
+
+impl SqlQueryTool {
+    pub fn new() -> Self {
+        let mut properties = HashMap::new();
+
+        properties.insert(
+            "operation".to_string(),
+            PropertySchema {
+                schema_type: "string".to_string(),
+                description: "query: run a SQL statement; tables: list tables and views; describe: list the columns of a table".to_string(),
+                default: Some(json!("query")),
+                items: None,
+                enum_values: Some(vec![
+                    "query".to_string(),
+                    "tables".to_string(),
+                    "describe".to_string(),
+                ]),
+            },
+        );
+
+        properties.insert(
+            "query".to_string(),
+            PropertySchema {
+                schema_type: "string".to_string(),
+                description: "For query: one SQL statement".to_string(),
+                default: None,
+                items: None,
+                enum_values: None,
+            },
+        );
+
+        properties.insert(
+            "table".to_string(),
+            PropertySchema {
+                schema_type: "string".to_string(),
+                description: "For describe: table name (schema.table on Postgres)".to_string(),
+                default: None,
+                items: None,
+                enum_values: None,
+            },
+        );
+
+        properties.insert(
+            "database".to_string(),
+            PropertySchema {
+                schema_type: "string".to_string(),
+                description: "Path of a SQLite file in the workspace to query instead of the configured database".to_string(),
+                default: None,
+                items: None,
+                enum_values: None,
+            },
+        );
+
+        properties.insert(
+            "write".to_string(),
+            PropertySchema {
+                schema_type: "boolean".to_string(),
+                description: "Allow statements that modify data or schema (default: false). Only set this when the user asked for the change.".to_string(),
+                default: Some(json!(false)),
+                items: None,
+                enum_values: None,
+            },
+        );
+
+        properties.insert(
+            "limit".to_string(),
+            PropertySchema {
+                schema_type: "integer".to_string(),
+                description: format!("Maximum rows to return (default: 100, max: {})", MAX_LIMIT),
+                default: Some(json!(100)),
+                items: None,
+                enum_values: None,
+            },
+        );
+
+        properties.insert(
+            "timeout".to_string(),
+            PropertySchema {
+                schema_type: "integer".to_string(),
+                description: "Statement timeout in seconds (default: 30, max: 300)".to_string(),
+                default: Some(json!(30)),
+                items: None,
+                enum_values: None,
+            },
+        );
+
+        SqlQueryTool {
+            definition: ToolDefinition {
+                name: "sql_query".to_string(),
+                description: "Query the user's SQL database (Postgres, MySQL or SQLite, configured as SQL_DATABASE_URL) or a SQLite file in the workspace. Use tables and describe to learn the schema before writing queries. Queries are read-only unless write is true. Results come back as a table, limited to 100 rows by default; aggregate in SQL rather than fetching raw rows.".to_string(),
+                input_schema: ToolInputSchema {
+                    schema_type: "object".to_string(),
+                    properties,
+                    required: vec![],
+                },
+                group: ToolGroup::Development,
+            },
+        }
+    }
+}
+
+impl Default for SqlQueryTool {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct SqlQueryParams {
+    operation: Option<String>,
+    query: Option<String>,
+    table: Option<String>,
+    database: Option<String>,
+    write: Option<bool>,
+    limit: Option<usize>,
+    timeout: Option<u64>,
+}
+
+#[async_trait]
+impl Tool for SqlQueryTool {
+    fn definition(&self) -> ToolDefinition {
+        self.definition.clone()
+    }
+
+    async fn execute(&self, params: Value, context: &ToolContext) -> ToolResult {
+        let params: SqlQueryParams = match serde_json::from_value(params) {
+            Ok(p) => p,
+            Err(e) => return ToolResult::error(format!("Invalid parameters: {}", e)),
+        };
+
+        let dsn = if let Some(ref database) = params.database {
+            let workspace = context
+                .workspace_dir
+                .as_ref()
+                .map(PathBuf::from)
+                .unwrap_or_else(|| std::env::current_dir().unwrap_or_else(|_| PathBuf::from(".")));
+            match WorkspacePath::resolve(&workspace, database) {
+                Ok(p) => Dsn::Sqlite(p.path().to_path_buf()),
+                Err(e) => return ToolResult::error(e),
+            }
+        } else {
+            match context.get_api_key_by_id(ApiKeyId::SqlDatabaseUrl) {
+                Some(url) => match Dsn::parse(&url) {
+                    Ok(dsn) => dsn,
+                    Err(e) => return ToolResult::error(format!("SQL_DATABASE_URL: {}", e)),
+                },
+                None => {
+                    return ToolResult::error(
+                        "No database configured. Ask the user to add SQL_DATABASE_URL on the API Keys page, or pass database for a SQLite file in the workspace.",
+                    )
+                }
+            }
+        };
+
+        let operation = params.operation.as_deref().unwrap_or("query");
+        let (sql, write) = match operation {
+            "query" => match params.query.as_deref().map(str::trim) {
+                Some(q) if !q.is_empty() => (q.to_string(), params.write.unwrap_or(false)),
+                _ => return ToolResult::error("query is required"),
+            },
+            "tables" => (sql_client::tables_sql(&dsn), false),
+            "describe" => match params.table.as_deref().map(str::trim) {
+                Some(t) if !t.is_empty() => (sql_client::columns_sql(&dsn, t), false),
+                _ => return ToolResult::error("table is required for describe"),
+            },
+            other => {
+                return ToolResult::error(format!(
+                    "Unknown operation: {}. Supported: query, tables, describe",
+                    other
+                ))
+            }
+        };
+        let limit = params.limit.unwrap_or(100).clamp(1, MAX_LIMIT);
+        let timeout = Duration::from_secs(params.timeout.unwrap_or(30).clamp(1, 300));
+
+        log::info!("[SQL_QUERY] {} {} (write: {})", dsn.backend(), operation, write);
+        match sql_client::run(&dsn, &sql, write, limit, timeout).await {
+            Ok(result) => {
+                let text = if operation == "describe" && result.rows.is_empty() {
+                    format!("Table '{}' not found. Use tables to list them.", params.table.unwrap_or_default())
+                } else {
+                    result.to_table()
+                };
+                ToolResult::success(text).with_metadata(json!({
+                    "backend": dsn.backend(),
+                    "columns": result.columns,
+                    "row_count": result.rows.len(),
+                    "truncated": result.truncated,
+                    "rows_affected": result.rows_affected,
+                }))
+            }
+            Err(e) => ToolResult::error(e),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[tokio::test]
+    async fn test_sql_query_sources() {
+        let dir = TempDir::new().unwrap();
+        rusqlite::Connection::open(dir.path().join("app.db"))
+            .unwrap()
+            .execute_batch("CREATE TABLE users (id INTEGER PRIMARY KEY, name TEXT); INSERT INTO users (name) VALUES ('ann');")
+            .unwrap();
+        let context = ToolContext::new().with_workspace(dir.path().to_string_lossy().to_string());
+        let tool = SqlQueryTool::new();
+
+        let result = tool.execute(json!({ "query": "SELECT 1" }), &context).await;
+        assert!(result.error.unwrap().contains("No database configured"));
+
+        let result = tool
+            .execute(json!({ "database": "app.db", "query": "SELECT name FROM users" }), &context)
+            .await;
+        assert_eq!(result.content, "| name |\n|---|\n| ann |\n\n1 row(s)");
+        let result = tool
+            .execute(json!({ "database": "app.db", "operation": "describe", "table": "missing" }), &context)
+            .await;
+        assert!(result.content.contains("not found"));
+        let result = tool.execute(json!({ "database": "../outside.db", "operation": "tables" }), &context).await;
+        assert!(!result.success);
+
+        let db_url = format!("sqlite://{}", dir.path().join("app.db").display());
+        let context = context.with_api_key_id(ApiKeyId::SqlDatabaseUrl, db_url);
+        let result = tool.execute(json!({ "query": "UPDATE users SET name = 'bob'" }), &context).await;
+        assert!(result.error.unwrap().contains("Only read statements"));
+        let result = tool
+            .execute(json!({ "query": "UPDATE users SET name = 'bob'", "write": true }), &context)
+            .await;
+        assert!(result.success);
+        assert_eq!(result.metadata.unwrap()["rows_affected"], 1);
+    }
+}
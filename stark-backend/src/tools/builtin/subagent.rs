@@ -9,6 +9,7 @@ use crate::tools::registry::Tool;
 use crate::tools::types::{
     PropertySchema, ToolContext, ToolDefinition, ToolGroup, ToolInputSchema, ToolResult,
 };
+use crate::utils::{truncate_chars, truncate_str};
 use async_trait::async_trait;
 use serde::Deserialize;
 use serde_json::{json, Value};
@@ -222,11 +223,7 @@ impl Tool for SubagentTool {
         log::info!(
             "[SUBAGENT] Spawning subagent '{}' with task: {}",
             subagent_id,
-            if params.task.len() > 100 {
-                &params.task[..100]
-            } else {
-                &params.task
-            }
+            truncate_chars(&params.task, 100)
         );
 
         // Check if we have access to the SubAgentManager via the context
@@ -265,7 +262,7 @@ impl Tool for SubagentTool {
                             loop {
                                 tokio::time::sleep(std::time::Duration::from_millis(500)).await;
 
-                                match manager.get_status(&id) {
+                                match manager.get_status(&id).await {
                                     Ok(Some(status)) => {
                                         if status.status.is_terminal() {
                                             match status.status {
@@ -336,11 +333,7 @@ impl Tool for SubagentTool {
                                  Use `subagent_status` with id '{}' to check progress.",
                                 id,
                                 label,
-                                if params.task.len() > 100 {
-                                    format!("{}...", &params.task[..100])
-                                } else {
-                                    params.task.clone()
-                                },
+                                truncate_str(&params.task, 100),
                                 timeout_secs,
                                 id
                             ))
@@ -434,11 +427,7 @@ impl Tool for SubagentTool {
                 thinking_level.as_deref().unwrap_or("default"),
                 channel_id,
                 channel_type,
-                if full_task.len() > 200 {
-                    &full_task[..200]
-                } else {
-                    &full_task
-                }
+                truncate_chars(&full_task, 200)
             );
 
             let duration = start.elapsed();
@@ -498,11 +487,7 @@ impl Tool for SubagentTool {
                  Use `subagent_status` with id '{}' to check progress.",
                 subagent_id,
                 label,
-                if params.task.len() > 100 {
-                    format!("{}...", &params.task[..100])
-                } else {
-                    params.task.clone()
-                },
+                truncate_str(&params.task, 100),
                 timeout_secs,
                 subagent_id
             ))
@@ -622,7 +607,7 @@ impl Tool for SubagentStatusTool {
                     }
 
                     // Get specific subagent status
-                    match manager.get_status(&id) {
+                    match manager.get_status(&id).await {
                         Ok(Some(status)) => {
                             let mut result = format!(
                                 "## Subagent: {}\n\
@@ -673,7 +658,7 @@ impl Tool for SubagentStatusTool {
                 } else {
                     // List all subagents for this channel
                     let channel_id = context.channel_id.unwrap_or(0);
-                    match manager.list_by_channel(channel_id) {
+                    match manager.list_by_channel(channel_id).await {
                         Ok(agents) => {
                             if agents.is_empty() {
                                 return ToolResult::success("No subagents found.");
@@ -687,11 +672,7 @@ impl Tool for SubagentStatusTool {
                                     status.id,
                                     status.label,
                                     status.status,
-                                    if status.task.len() > 50 {
-                                        format!("{}...", &status.task[..50])
-                                    } else {
-                                        status.task.clone()
-                                    }
+                                    truncate_str(&status.task, 50)
                                 ));
                             }
 
@@ -772,11 +753,7 @@ impl Tool for SubagentStatusTool {
                     status.id,
                     status.label,
                     status.status,
-                    if status.task.len() > 50 {
-                        format!("{}...", &status.task[..50])
-                    } else {
-                        status.task.clone()
-                    }
+                    truncate_str(&status.task, 50)
                 ));
             }
 
@@ -0,0 +1,676 @@
+//! Swap tool: quote, simulate and (on confirmation) execute a token swap
+//!
+//! Reads the `sell_token` and `buy_token` registers set by `token_lookup`, so
+//! token addresses are never typed by the model. Quotes come from 0x (direct
+//! with a `ZEROEX_API_KEY`, otherwise through DeFi Relay with an x402 payment)
+//! or from 1inch. Every quote is simulated with `eth_call` from the wallet;
+//! the transaction is only broadcast when `confirm` is true, and a confirmed
+//! call re-quotes and re-simulates first so it never sends a stale quote.
+
+use super::web3_tx::{parse_u256, TxRequest, Web3TxTool};
+use crate::controllers::api_keys::ApiKeyId;
+use crate::domain_types::DomainUint256;
+use crate::models::{NewTransaction, Scope, TransactionKind, TransactionStatus};
+use crate::tools::builtin::token_lookup::{evm_rpc, TokenLookupTool};
+use crate::tools::network::{parse_network, supported_networks, Network};
+use crate::tools::registry::Tool;
+use crate::tools::spending;
+use crate::tools::types::{
+    PropertySchema, ToolContext, ToolDefinition, ToolGroup, ToolInputSchema, ToolResult,
+};
+use crate::x402::{erc20, X402Client};
+use async_trait::async_trait;
+use ethers::types::{Address, U256};
+use ethers::utils::format_units;
+use serde::Deserialize;
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::str::FromStr;
+use std::time::Duration;
+
+const ZEROEX_API: &str = "https://api.0x.org/swap/allowance-holder/quote";
+const DEFIRELAY_QUOTER: &str = "https://quoter.defirelay.com/swap/allowance-holder/quote";
+const ONEINCH_API: &str = "https://api.1inch.dev/swap/v6.0";
+
+/// Address both aggregators use for the network's native coin
+const NATIVE_ADDRESS: &str = "0xEeeeeEeeeEeEeeEeEeEeeEEEeeeeEeeeeeeeEEeE";
+
+/// Slippage above this is almost certainly a mistake
+const MAX_SLIPPAGE_BPS: u32 = 5_000;
+
+/// Swap tool
+pub struct SwapTool {
+    definition: ToolDefinition,
+}
+
+impl SwapTool {
+    pub fn new() -> Self {
+        let mut properties = HashMap::new();
+
+        properties.insert(
+            "sell_amount".to_string(),
+            PropertySchema {
+                schema_type: "string".to_string(),
+                description: "Amount to sell in the token's smallest unit (e.g., '1000000' for 1 USDC). Defaults to the 'sell_amount' register.".to_string(),
+                default: None,
+                items: None,
+                enum_values: None,
+            },
+        );
+
+        properties.insert(
+            "network".to_string(),
+            PropertySchema {
+                schema_type: "string".to_string(),
+                description: format!("Network: {}", supported_networks()),
+                default: Some(json!("base")),
+                items: None,
+                enum_values: Some(Network::ALL.iter().map(|n| n.as_str().to_string()).collect()),
+            },
+        );
+
+        properties.insert(
+            "provider".to_string(),
+            PropertySchema {
+                schema_type: "string".to_string(),
+                description: "Aggregator to quote from".to_string(),
+                default: Some(json!("0x")),
+                items: None,
+                enum_values: Some(vec!["0x".to_string(), "1inch".to_string()]),
+            },
+        );
+
+        properties.insert(
+            "slippage_bps".to_string(),
+            PropertySchema {
+                schema_type: "integer".to_string(),
+                description: "Maximum slippage in basis points (100 = 1%)".to_string(),
+                default: Some(json!(100)),
+                items: None,
+                enum_values: None,
+            },
+        );
+
+        properties.insert(
+            "confirm".to_string(),
+            PropertySchema {
+                schema_type: "boolean".to_string(),
+                description: "Set to true only after the user has approved the quote. Without it the swap is quoted and simulated but not sent.".to_string(),
+                default: Some(json!(false)),
+                items: None,
+                enum_values: None,
+            },
+        );
+
+        properties.insert(
+            "max_fee_per_gas".to_string(),
+            PropertySchema {
+                schema_type: "string".to_string(),
+                description: "Max fee per gas in wei (optional, estimated if omitted)".to_string(),
+                default: None,
+                items: None,
+                enum_values: None,
+            },
+        );
+
+        SwapTool {
+            definition: ToolDefinition {
+                name: "swap".to_string(),
+                description: "Swap tokens through the 0x or 1inch aggregator. Set the 'sell_token' and 'buy_token' registers with token_lookup first. Without confirm: true it only returns a simulated quote; show it to the user and call again with confirm: true once they agree.".to_string(),
+                input_schema: ToolInputSchema {
+                    schema_type: "object".to_string(),
+                    properties,
+                    required: vec![],
+                },
+                group: ToolGroup::Finance,
+            },
+        }
+    }
+}
+
+impl Default for SwapTool {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct SwapParams {
+    sell_amount: Option<String>,
+    #[serde(default = "default_network")]
+    network: String,
+    #[serde(default = "default_provider")]
+    provider: String,
+    #[serde(default = "default_slippage_bps")]
+    slippage_bps: u32,
+    #[serde(default)]
+    confirm: bool,
+    max_fee_per_gas: Option<DomainUint256>,
+}
+
+fn default_network() -> String {
+    "base".to_string()
+}
+
+fn default_provider() -> String {
+    "0x".to_string()
+}
+
+fn default_slippage_bps() -> u32 {
+    100
+}
+
+/// A quote normalized across aggregators
+#[derive(Debug, Clone, PartialEq)]
+struct SwapQuote {
+    to: Address,
+    data: Vec<u8>,
+    value: U256,
+    gas: Option<U256>,
+    buy_amount: U256,
+    min_buy_amount: U256,
+    /// Contract the sell token must be approved for
+    spender: Address,
+}
+
+#[async_trait]
+impl Tool for SwapTool {
+    fn definition(&self) -> ToolDefinition {
+        self.definition.clone()
+    }
+
+    fn required_scope(&self) -> Option<Scope> {
+        Some(Scope::WalletSign)
+    }
+
+    fn moves_value(&self) -> bool {
+        true
+    }
+
+    async fn execute(&self, params: Value, context: &ToolContext) -> ToolResult {
+        let params: SwapParams = match serde_json::from_value(params) {
+            Ok(p) => p,
+            Err(e) => return ToolResult::error(format!("Invalid parameters: {}", e)),
+        };
+
+        let network = match parse_network(&params.network) {
+            Ok(n) => n,
+            Err(e) => return ToolResult::error(e),
+        };
+        if params.slippage_bps == 0 || params.slippage_bps > MAX_SLIPPAGE_BPS {
+            return ToolResult::error(format!(
+                "slippage_bps must be between 1 and {}",
+                MAX_SLIPPAGE_BPS
+            ));
+        }
+
+        let (sell_token, buy_token) = match (token_register(context, "sell_token"), token_register(context, "buy_token")) {
+            (Ok(sell), Ok(buy)) => (sell, buy),
+            (Err(e), _) | (_, Err(e)) => return ToolResult::error(e),
+        };
+        if sell_token == buy_token {
+            return ToolResult::error("sell_token and buy_token are the same token");
+        }
+
+        let sell_amount = match params
+            .sell_amount
+            .clone()
+            .or_else(|| context.registers.get("sell_amount").and_then(|v| register_string(&v)))
+        {
+            Some(amount) => match parse_u256(&amount) {
+                Ok(a) if !a.is_zero() => a,
+                Ok(_) => return ToolResult::error("sell_amount must be greater than zero"),
+                Err(e) => return ToolResult::error(format!("Invalid sell_amount: {}", e)),
+            },
+            None => return ToolResult::error("Provide sell_amount or set the 'sell_amount' register"),
+        };
+
+        let signer = match crate::wallet::require_signer() {
+            Ok(s) => s,
+            Err(e) => return ToolResult::error(e),
+        };
+        let taker = signer.address();
+
+        log::info!(
+            "[swap] Quoting {} {:?} -> {:?} on {} via {} (slippage {} bps)",
+            sell_amount, sell_token, buy_token, network, params.provider, params.slippage_bps
+        );
+        let request = QuoteRequest {
+            network,
+            sell_token,
+            buy_token,
+            sell_amount,
+            taker,
+            slippage_bps: params.slippage_bps,
+        };
+        let quote = match params.provider.to_lowercase().as_str() {
+            "0x" | "zeroex" => zeroex_quote(&request, context).await,
+            "1inch" | "oneinch" => oneinch_quote(&request, context).await,
+            other => Err(format!("Unknown provider '{}'. Use '0x' or '1inch'", other)),
+        };
+        let quote = match quote {
+            Ok(q) => q,
+            Err(e) => return ToolResult::error(format!("Quote failed: {}", e)),
+        };
+
+        let rpc = match evm_rpc(network.as_str(), context) {
+            Ok(r) => r,
+            Err(e) => return ToolResult::error(e),
+        };
+
+        // An ERC-20 sell needs an allowance for the aggregator's spender
+        if !is_native(sell_token) {
+            let allowance = rpc
+                .call(sell_token, &erc20::encode_allowance(taker, quote.spender))
+                .await
+                .and_then(|data| erc20::decode_balance(&data));
+            match allowance {
+                Ok(allowance) if allowance < sell_amount => {
+                    return ToolResult::error(format!(
+                        "Allowance too low: {:?} may spend {} of the sell token but the swap needs {}.\n\
+                         Approve it first: register_set token_address = {:?} and spender_address = {:?}, \
+                         then run web3_function_call with preset erc20_approve.",
+                        quote.spender, allowance, sell_amount, sell_token, quote.spender
+                    ))
+                    .with_metadata(json!({
+                        "allowance": allowance.to_string(),
+                        "required": sell_amount.to_string(),
+                        "spender": format!("{:?}", quote.spender),
+                        "token": format!("{:?}", sell_token)
+                    }));
+                }
+                Ok(_) => {}
+                Err(e) => log::warn!("[swap] Could not read allowance, relying on simulation: {}", e),
+            }
+        }
+
+        if let Err(e) = rpc.simulate(taker, quote.to, &quote.data, quote.value).await {
+            return ToolResult::error(format!(
+                "Simulation failed, the swap was not sent: {}\n\
+                 Common causes: insufficient balance, missing approval, or the price moved beyond slippage.",
+                e
+            ));
+        }
+
+        // Cache the simulated transaction so it can be inspected (or sent with web3_tx)
+        context.set_register(
+            "swap_quote",
+            json!({
+                "to": format!("{:?}", quote.to),
+                "data": format!("0x{}", hex::encode(&quote.data)),
+                "value": quote.value.to_string(),
+                "gas": quote.gas.map(|g| g.to_string()),
+                "buyAmount": quote.buy_amount.to_string(),
+                "minBuyAmount": quote.min_buy_amount.to_string(),
+            }),
+            "swap",
+        );
+
+        let sell_label = token_label(context, "sell_token");
+        let buy_label = token_label(context, "buy_token");
+        let sell_display = format_amount(sell_amount, sell_token, network, context).await;
+        let buy_display = format_amount(quote.buy_amount, buy_token, network, context).await;
+        let min_display = format_amount(quote.min_buy_amount, buy_token, network, context).await;
+        let summary = format!(
+            "Sell: {} {}\nBuy (quoted): {} {}\nBuy (minimum after {}% slippage): {} {}\nProvider: {}\nNetwork: {}",
+            sell_display,
+            sell_label,
+            buy_display,
+            buy_label,
+            params.slippage_bps as f64 / 100.0,
+            min_display,
+            buy_label,
+            params.provider,
+            network
+        );
+        let quote_metadata = json!({
+            "provider": params.provider,
+            "network": network.as_str(),
+            "sell_token": format!("{:?}", sell_token),
+            "buy_token": format!("{:?}", buy_token),
+            "sell_amount": sell_amount.to_string(),
+            "buy_amount": quote.buy_amount.to_string(),
+            "min_buy_amount": quote.min_buy_amount.to_string(),
+            "slippage_bps": params.slippage_bps,
+        });
+
+        if !params.confirm {
+            return ToolResult::success(format!(
+                "Swap quote (simulated successfully, NOT sent)\n\n{}\n\n\
+                 Show this to the user. To execute, call swap again with confirm: true.",
+                summary
+            ))
+            .with_metadata(json!({ "quote": quote_metadata, "executed": false }));
+        }
+
+        let amount_usd = spending::price_usd(&format!("{:?}", sell_token), sell_amount, network, context).await;
+        let description = format!(
+            "Swap {} {} for at least {} {} on {} via {}",
+            sell_display, sell_label, min_display, buy_label, network, params.provider
+        );
+        if let Err(e) = spending::authorize_spend(context, "swap", amount_usd, &description).await {
+            return ToolResult::error(format!("Swap not sent: {}", e))
+                .with_metadata(json!({ "quote": quote_metadata, "executed": false }));
+        }
+
+        let mut audit = NewTransaction::new(TransactionKind::Swap, TransactionStatus::Pending, network.as_str());
+        audit.details = Some(quote_metadata.clone());
+        audit.value_usd = amount_usd;
+        let (to, data, value) = (format!("{:?}", quote.to), format!("0x{}", hex::encode(&quote.data)), quote.value.to_string());
+        let tx = TxRequest {
+            network: network.as_str(),
+            to: &to,
+            data: &data,
+            value: &value,
+            gas_limit: quote.gas.map(|g| g * 120 / 100),
+            max_fee_per_gas: params.max_fee_per_gas.as_ref().map(|g| g.0),
+            max_priority_fee_per_gas: None,
+        };
+        let result = match Web3TxTool::send_transaction(
+            tx,
+            context,
+            "swap",
+            audit,
+        )
+        .await
+        {
+            Ok(r) => r,
+            Err(e) => return ToolResult::error(format!("Swap transaction failed: {}", e)),
+        };
+
+        let status_emoji = if result.status == "confirmed" { "✅" } else { "❌" };
+        ToolResult::success(format!(
+            "{} SWAP {}\n\n{}\n\nHash: {}\nExplorer: {}",
+            status_emoji,
+            result.status.to_uppercase(),
+            summary,
+            result.tx_hash,
+            result.explorer_url
+        ))
+        .with_metadata(json!({
+            "quote": quote_metadata,
+            "executed": true,
+            "tx_hash": result.tx_hash,
+            "status": result.status,
+            "explorer_url": result.explorer_url,
+            "gas_used": result.gas_used,
+            "block_number": result.block_number
+        }))
+    }
+}
+
+struct QuoteRequest {
+    network: Network,
+    sell_token: Address,
+    buy_token: Address,
+    sell_amount: U256,
+    taker: Address,
+    slippage_bps: u32,
+}
+
+/// Token address from a register set by `token_lookup`
+fn token_register(context: &ToolContext, name: &str) -> Result<Address, String> {
+    let value = context
+        .registers
+        .get(name)
+        .and_then(|v| register_string(&v))
+        .ok_or_else(|| format!("Register '{}' is not set. Run token_lookup with cache_as: \"{}\" first.", name, name))?;
+    Address::from_str(&value).map_err(|e| format!("Register '{}' is not an address: {}", name, e))
+}
+
+/// Registers may hold a string or a number
+fn register_string(value: &Value) -> Option<String> {
+    match value {
+        Value::String(s) => Some(s.clone()),
+        Value::Number(n) => Some(n.to_string()),
+        _ => None,
+    }
+}
+
+fn is_native(token: Address) -> bool {
+    Address::from_str(NATIVE_ADDRESS).is_ok_and(|native| native == token)
+}
+
+/// Symbol stored next to a token register, or "tokens"
+fn token_label(context: &ToolContext, register: &str) -> String {
+    context
+        .registers
+        .get(&format!("{}_symbol", register))
+        .and_then(|v| v.as_str().map(str::to_string))
+        .unwrap_or_else(|| "tokens".to_string())
+}
+
+/// Human-readable amount, falling back to base units if decimals are unknown
+async fn format_amount(amount: U256, token: Address, network: Network, context: &ToolContext) -> String {
+    let decimals = if is_native(token) {
+        Some(18)
+    } else {
+        TokenLookupTool::resolve(&format!("{:?}", token), network.as_str(), context)
+            .await
+            .ok()
+            .map(|(_, info, _)| info.decimals as u32)
+    };
+    decimals
+        .and_then(|d| format_units(amount, d).ok())
+        .map(|s| s.trim_end_matches('0').trim_end_matches('.').to_string())
+        .unwrap_or_else(|| format!("{} (base units)", amount))
+}
+
+fn http_client() -> reqwest::Client {
+    reqwest::Client::builder()
+        .timeout(Duration::from_secs(30))
+        .build()
+        .unwrap_or_else(|_| reqwest::Client::new())
+}
+
+/// Parse a JSON number or decimal/hex string
+fn json_u256(value: Option<&Value>, field: &str) -> Result<U256, String> {
+    match value {
+        Some(Value::String(s)) => parse_u256(s),
+        Some(Value::Number(n)) => parse_u256(&n.to_string()),
+        _ => Err(format!("Quote is missing '{}'", field)),
+    }
+}
+
+fn json_address(value: Option<&Value>, field: &str) -> Result<Address, String> {
+    value
+        .and_then(|v| v.as_str())
+        .ok_or_else(|| format!("Quote is missing '{}'", field))
+        .and_then(|s| Address::from_str(s).map_err(|e| format!("Invalid '{}' in quote: {}", field, e)))
+}
+
+fn json_bytes(value: Option<&Value>, field: &str) -> Result<Vec<u8>, String> {
+    let data = value
+        .and_then(|v| v.as_str())
+        .ok_or_else(|| format!("Quote is missing '{}'", field))?;
+    hex::decode(data.trim_start_matches("0x")).map_err(|e| format!("Invalid '{}' in quote: {}", field, e))
+}
+
+async fn zeroex_quote(request: &QuoteRequest, context: &ToolContext) -> Result<SwapQuote, String> {
+    let query = format!(
+        "chainId={}&sellToken={:?}&buyToken={:?}&sellAmount={}&taker={:?}&slippageBps={}",
+        request.network.chain_id(),
+        request.sell_token,
+        request.buy_token,
+        request.sell_amount,
+        request.taker,
+        request.slippage_bps
+    );
+
+    let api_key = context
+        .get_api_key_by_id(ApiKeyId::ZeroexApiKey)
+        .or_else(|| std::env::var("ZEROEX_API_KEY").ok())
+        .filter(|k| !k.is_empty());
+    let (status, body) = match api_key {
+        Some(key) => {
+            let response = http_client()
+                .get(format!("{}?{}", ZEROEX_API, query))
+                .header("0x-api-key", key)
+                .header("0x-version", "v2")
+                .send()
+                .await
+                .map_err(|e| format!("0x request failed: {}", e))?;
+            (response.status(), response.text().await.unwrap_or_default())
+        }
+        None => {
+            // No key: pay DeFi Relay per quote, as the swap_quote preset does
+            let client = X402Client::with_signer(crate::wallet::require_signer()?)?;
+            let response = client.get_with_payment(&format!("{}?{}", DEFIRELAY_QUOTER, query)).await?;
+            let status = response.response.status();
+            (status, response.response.text().await.unwrap_or_default())
+        }
+    };
+    if !status.is_success() {
+        return Err(format!("0x returned {}: {}", status, body));
+    }
+    let body: Value = serde_json::from_str(&body).map_err(|e| format!("Invalid 0x response: {}", e))?;
+    parse_zeroex_quote(&body)
+}
+
+/// Parse a 0x v2 allowance-holder quote
+fn parse_zeroex_quote(body: &Value) -> Result<SwapQuote, String> {
+    if body.get("liquidityAvailable").and_then(|v| v.as_bool()) == Some(false) {
+        return Err("No liquidity available for this pair".to_string());
+    }
+    let tx = body.get("transaction").ok_or("Quote has no transaction")?;
+    let to = json_address(tx.get("to"), "transaction.to")?;
+    let buy_amount = json_u256(body.get("buyAmount"), "buyAmount")?;
+    let spender = body
+        .pointer("/issues/allowance/spender")
+        .or_else(|| body.get("allowanceTarget"))
+        .filter(|v| !v.is_null())
+        .map(|v| json_address(Some(v), "allowance spender"))
+        .transpose()?
+        .unwrap_or(to);
+
+    Ok(SwapQuote {
+        to,
+        data: json_bytes(tx.get("data"), "transaction.data")?,
+        value: json_u256(tx.get("value"), "transaction.value").unwrap_or_default(),
+        gas: json_u256(tx.get("gas"), "transaction.gas").ok(),
+        min_buy_amount: json_u256(body.get("minBuyAmount"), "minBuyAmount").unwrap_or(buy_amount),
+        buy_amount,
+        spender,
+    })
+}
+
+async fn oneinch_quote(request: &QuoteRequest, context: &ToolContext) -> Result<SwapQuote, String> {
+    let api_key = context
+        .get_api_key_by_id(ApiKeyId::OneinchApiKey)
+        .or_else(|| std::env::var("ONEINCH_API_KEY").ok())
+        .filter(|k| !k.is_empty())
+        .ok_or("ONEINCH_API_KEY is not configured. Add it in API Keys or use provider '0x'.")?;
+
+    let url = format!(
+        "{}/{}/swap?src={:?}&dst={:?}&amount={}&from={:?}&origin={:?}&slippage={}&disableEstimate=true",
+        ONEINCH_API,
+        request.network.chain_id(),
+        request.sell_token,
+        request.buy_token,
+        request.sell_amount,
+        request.taker,
+        request.taker,
+        request.slippage_bps as f64 / 100.0
+    );
+    let response = http_client()
+        .get(&url)
+        .bearer_auth(api_key)
+        .send()
+        .await
+        .map_err(|e| format!("1inch request failed: {}", e))?;
+    let status = response.status();
+    let body = response.text().await.unwrap_or_default();
+    if !status.is_success() {
+        return Err(format!("1inch returned {}: {}", status, body));
+    }
+    let body: Value = serde_json::from_str(&body).map_err(|e| format!("Invalid 1inch response: {}", e))?;
+    parse_oneinch_quote(&body, request.slippage_bps)
+}
+
+/// Parse a 1inch v6 swap response; the router is also the spender
+fn parse_oneinch_quote(body: &Value, slippage_bps: u32) -> Result<SwapQuote, String> {
+    let tx = body.get("tx").ok_or("Quote has no tx")?;
+    let to = json_address(tx.get("to"), "tx.to")?;
+    let buy_amount = json_u256(body.get("dstAmount"), "dstAmount")?;
+
+    Ok(SwapQuote {
+        to,
+        data: json_bytes(tx.get("data"), "tx.data")?,
+        value: json_u256(tx.get("value"), "tx.value").unwrap_or_default(),
+        gas: json_u256(tx.get("gas"), "tx.gas").ok().filter(|g| !g.is_zero()),
+        min_buy_amount: buy_amount * U256::from(10_000 - slippage_bps) / U256::from(10_000),
+        buy_amount,
+        spender: to,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tools::RegisterStore;
+
+    const ROUTER: &str = "0x0000000000001ff3684f28c67538d4d072c22734";
+    const PERMIT2: &str = "0x000000000022d473030f116ddee9f6b43ac78ba3";
+
+    #[test]
+    fn test_parse_zeroex_quote() {
+        let body = json!({
+            "liquidityAvailable": true,
+            "buyAmount": "2500000",
+            "minBuyAmount": "2475000",
+            "issues": { "allowance": { "actual": "0", "spender": PERMIT2 }, "balance": null },
+            "transaction": { "to": ROUTER, "data": "0x1234abcd", "value": "0", "gas": "331157" }
+        });
+        let quote = parse_zeroex_quote(&body).unwrap();
+        assert_eq!(quote.to, Address::from_str(ROUTER).unwrap());
+        assert_eq!(quote.data, vec![0x12, 0x34, 0xab, 0xcd]);
+        assert_eq!(quote.value, U256::zero());
+        assert_eq!(quote.gas, Some(U256::from(331157u64)));
+        assert_eq!(quote.buy_amount, U256::from(2_500_000u64));
+        assert_eq!(quote.min_buy_amount, U256::from(2_475_000u64));
+        assert_eq!(quote.spender, Address::from_str(PERMIT2).unwrap());
+
+        // No allowance issue: the transaction target is the spender
+        let body = json!({
+            "buyAmount": "10",
+            "issues": { "allowance": null },
+            "transaction": { "to": ROUTER, "data": "0x", "value": "100" }
+        });
+        let quote = parse_zeroex_quote(&body).unwrap();
+        assert_eq!(quote.spender, quote.to);
+        assert_eq!(quote.min_buy_amount, U256::from(10u64));
+        assert_eq!(quote.value, U256::from(100u64));
+        assert_eq!(quote.gas, None);
+
+        assert!(parse_zeroex_quote(&json!({ "liquidityAvailable": false })).is_err());
+        assert!(parse_zeroex_quote(&json!({ "buyAmount": "1" })).is_err());
+    }
+
+    #[test]
+    fn test_parse_oneinch_quote() {
+        let body = json!({
+            "dstAmount": "1000000",
+            "tx": { "from": PERMIT2, "to": ROUTER, "data": "0xabcd", "value": "5", "gas": 0, "gasPrice": "1" }
+        });
+        let quote = parse_oneinch_quote(&body, 50).unwrap();
+        assert_eq!(quote.buy_amount, U256::from(1_000_000u64));
+        assert_eq!(quote.min_buy_amount, U256::from(995_000u64));
+        assert_eq!(quote.value, U256::from(5u64));
+        assert_eq!(quote.gas, None);
+        assert_eq!(quote.spender, Address::from_str(ROUTER).unwrap());
+    }
+
+    #[test]
+    fn test_token_registers() {
+        let registers = RegisterStore::new();
+        registers.set("sell_token", json!(NATIVE_ADDRESS), "token_lookup");
+        registers.set("buy_token", json!("not an address"), "register_set");
+        let context = ToolContext::new().with_registers(registers);
+
+        let sell = token_register(&context, "sell_token").unwrap();
+        assert!(is_native(sell));
+        assert!(token_register(&context, "buy_token").unwrap_err().contains("not an address"));
+        assert!(token_register(&context, "missing").unwrap_err().contains("token_lookup"));
+        assert_eq!(token_label(&context, "sell_token"), "tokens");
+    }
+}
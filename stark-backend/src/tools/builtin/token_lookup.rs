@@ -3,17 +3,36 @@
 //! Provides a lookup table for known tokens on supported networks.
 //! Token data is loaded from config/tokens.ron at startup.
 //! This prevents hallucination of token addresses for common tokens.
+//!
+//! Given a contract address that isn't in the table, the tool reads the
+//! ERC-20 `symbol()`, `name()` and `decimals()` over RPC and caches the result
+//! in the database, so later lookups by that symbol succeed too.
 
+use crate::tools::cache::CachePolicy;
+use crate::tools::network::{parse_network, supported_networks, Network};
 use crate::tools::registry::Tool;
+use crate::tools::rpc_config;
 use crate::tools::types::{
     PropertySchema, ToolContext, ToolDefinition, ToolGroup, ToolInputSchema, ToolResult,
 };
+use crate::x402::X402EvmRpc;
 use async_trait::async_trait;
+use ethers::abi::{self, ParamType, Token};
+use ethers::types::{Address, U256};
 use serde::Deserialize;
 use serde_json::{json, Value};
 use std::collections::HashMap;
 use std::path::Path;
+use std::str::FromStr;
 use std::sync::OnceLock;
+use std::time::Duration;
+
+/// ERC-20 `symbol()` selector
+const SYMBOL_SELECTOR: [u8; 4] = [0x95, 0xd8, 0x9b, 0x41];
+/// ERC-20 `name()` selector
+const NAME_SELECTOR: [u8; 4] = [0x06, 0xfd, 0xde, 0x03];
+/// ERC-20 `decimals()` selector
+const DECIMALS_SELECTOR: [u8; 4] = [0x31, 0x3c, 0xe5, 0x67];
 
 /// Global token storage (loaded once at startup)
 static TOKENS: OnceLock<HashMap<String, HashMap<String, TokenInfo>>> = OnceLock::new();
@@ -24,6 +43,9 @@ pub struct TokenInfo {
     pub address: String,
     pub decimals: u8,
     pub name: String,
+    /// Chainlink USD price feed, if one is configured
+    #[serde(default)]
+    pub price_feed: Option<String>,
 }
 
 /// Load tokens from config directory. Panics if config file is missing or invalid.
@@ -69,7 +91,7 @@ impl TokenLookupTool {
             "symbol".to_string(),
             PropertySchema {
                 schema_type: "string".to_string(),
-                description: "Token symbol (e.g., 'ETH', 'USDC', 'WETH'). Case-insensitive.".to_string(),
+                description: "Token symbol (e.g., 'ETH', 'USDC', 'WETH'), case-insensitive, or a contract address. Addresses of unlisted tokens are read on-chain.".to_string(),
                 default: None,
                 items: None,
                 enum_values: None,
@@ -80,10 +102,10 @@ impl TokenLookupTool {
             "network".to_string(),
             PropertySchema {
                 schema_type: "string".to_string(),
-                description: "Network: 'base' or 'mainnet'".to_string(),
+                description: format!("Network: {}", supported_networks()),
                 default: Some(json!("base")),
                 items: None,
-                enum_values: Some(vec!["base".to_string(), "mainnet".to_string()]),
+                enum_values: Some(Network::ALL.iter().map(|n| n.as_str().to_string()).collect()),
             },
         );
 
@@ -101,7 +123,7 @@ impl TokenLookupTool {
         TokenLookupTool {
             definition: ToolDefinition {
                 name: "token_lookup".to_string(),
-                description: "Look up a token's contract address by symbol. Returns address, decimals, and name. The address is cached in a register (default: 'token_address') for use by other tools.".to_string(),
+                description: "Look up a token's contract address by symbol, or a token's symbol by contract address. Returns address, decimals, and name. The address is cached in a register (default: 'token_address') for use by other tools.".to_string(),
                 input_schema: ToolInputSchema {
                     schema_type: "object".to_string(),
                     properties,
@@ -123,13 +145,18 @@ impl TokenLookupTool {
 
         let result = tokens
             .get(network)
-            .or_else(|| tokens.get("base"))
             .and_then(|network_tokens| {
                 log::debug!(
                     "[token_lookup] Network '{}' has tokens: {:?}",
                     network, network_tokens.keys().collect::<Vec<_>>()
                 );
-                network_tokens.get(&symbol_upper)
+                network_tokens.get(&symbol_upper).or_else(|| {
+                    // Mixed-case keys such as "cbBTC"
+                    network_tokens
+                        .iter()
+                        .find(|(key, _)| key.eq_ignore_ascii_case(symbol))
+                        .map(|(_, token)| token)
+                })
             })
             .cloned();
 
@@ -137,12 +164,113 @@ impl TokenLookupTool {
         result
     }
 
+    /// Find a configured token by contract address, returning its symbol too
+    fn lookup_address(address: &str, network: &str) -> Option<(String, TokenInfo)> {
+        get_tokens().get(network).and_then(|network_tokens| {
+            network_tokens
+                .iter()
+                .find(|(_, token)| token.address.eq_ignore_ascii_case(address))
+                .map(|(symbol, token)| (symbol.clone(), token.clone()))
+        })
+    }
+
+    /// Read a token's ERC-20 metadata from the chain
+    async fn fetch_onchain(
+        address: &str,
+        network: &str,
+        context: &ToolContext,
+    ) -> Result<(String, TokenInfo), String> {
+        let contract = Address::from_str(address)
+            .map_err(|e| format!("Invalid token address '{}': {}", address, e))?;
+
+        let rpc = evm_rpc(network, context)?;
+
+        log::info!("[token_lookup] Reading ERC-20 metadata of {} on {}", address, network);
+
+        let symbol = decode_string(&rpc.call(contract, &SYMBOL_SELECTOR).await?)
+            .ok_or_else(|| format!("{} on {} did not return a symbol; is it an ERC-20 token?", address, network))?;
+        let decimals = decode_decimals(&rpc.call(contract, &DECIMALS_SELECTOR).await?)
+            .ok_or_else(|| format!("{} on {} did not return valid decimals", address, network))?;
+        // Some tokens don't implement name(); fall back to the symbol
+        let name = match rpc.call(contract, &NAME_SELECTOR).await {
+            Ok(data) => decode_string(&data).unwrap_or_else(|| symbol.clone()),
+            Err(_) => symbol.clone(),
+        };
+
+        Ok((
+            symbol,
+            TokenInfo {
+                address: address.to_string(),
+                decimals,
+                name,
+                price_feed: None,
+            },
+        ))
+    }
+
+    /// Resolve a symbol or address: config first, then the DB cache, then (for addresses) the chain
+    pub(crate) async fn resolve(
+        query: &str,
+        network: &str,
+        context: &ToolContext,
+    ) -> Result<(String, TokenInfo, &'static str), String> {
+        let by_address = is_address(query);
+
+        let configured = if by_address {
+            Self::lookup_address(query, network)
+        } else {
+            Self::lookup(query, network).map(|token| (query.to_uppercase(), token))
+        };
+        if let Some((symbol, token)) = configured {
+            return Ok((symbol, token, "config"));
+        }
+
+        if let Some(db) = &context.database {
+            let cached = if by_address {
+                db.get_cached_token_by_address(network, query).await
+            } else {
+                db.get_cached_token_by_symbol(network, query).await
+            };
+            match cached {
+                Ok(Some(cached)) => {
+                    let token = TokenInfo {
+                        address: cached.address,
+                        decimals: cached.decimals,
+                        name: cached.name,
+                        price_feed: None,
+                    };
+                    return Ok((cached.symbol, token, "cache"));
+                }
+                Ok(None) => {}
+                Err(e) => log::warn!("[token_lookup] Token cache lookup failed: {}", e),
+            }
+        }
+
+        if !by_address {
+            return Err(format!(
+                "Token '{}' not found on {}. Available tokens: {}. For other tokens, pass the contract address as the symbol.",
+                query,
+                network,
+                Self::list_available(network).join(", ")
+            ));
+        }
+
+        let (symbol, token) = Self::fetch_onchain(query, network, context).await?;
+        if let Some(db) = &context.database
+            && let Err(e) = db
+                .cache_token(network, &token.address, &symbol, &token.name, token.decimals)
+                .await
+        {
+            log::warn!("[token_lookup] Failed to cache token {}: {}", token.address, e);
+        }
+        Ok((symbol, token, "onchain"))
+    }
+
     fn list_available(network: &str) -> Vec<String> {
         let tokens = get_tokens();
 
         tokens
             .get(network)
-            .or_else(|| tokens.get("base"))
             .map(|network_tokens| {
                 let mut symbols: Vec<String> = network_tokens.keys().cloned().collect();
                 symbols.sort();
@@ -181,59 +309,131 @@ impl Tool for TokenLookupTool {
         self.definition.clone()
     }
 
+    fn is_read_only(&self) -> bool {
+        true
+    }
+
+    fn cache_policy(&self, _params: &Value) -> CachePolicy {
+        CachePolicy::For(Duration::from_secs(600))
+    }
+
+    /// A reused lookup still fills the registers this call asked for
+    fn on_cache_hit(&self, params: &Value, result: &ToolResult, context: &ToolContext) {
+        let Ok(params) = serde_json::from_value::<TokenLookupParams>(params.clone()) else {
+            return;
+        };
+        let Some(metadata) = &result.metadata else {
+            return;
+        };
+        if let (Some(address), Some(symbol)) = (metadata.get("address"), metadata.get("symbol")) {
+            context.set_register(&params.cache_as, address.clone(), "token_lookup");
+            context.set_register(&format!("{}_symbol", params.cache_as), symbol.clone(), "token_lookup");
+        }
+    }
+
     async fn execute(&self, params: Value, context: &ToolContext) -> ToolResult {
-        let params: TokenLookupParams = match serde_json::from_value(params) {
+        let mut params: TokenLookupParams = match serde_json::from_value(params) {
             Ok(p) => p,
             Err(e) => return ToolResult::error(format!("Invalid parameters: {}", e)),
         };
 
-        match Self::lookup(&params.symbol, &params.network) {
-            Some(token) => {
+        // Normalize network (accepts aliases like "Base" or "ethereum")
+        params.network = match parse_network(&params.network) {
+            Ok(n) => n.as_str().to_string(),
+            Err(e) => return ToolResult::error(e),
+        };
+
+        match Self::resolve(params.symbol.trim(), &params.network, context).await {
+            Ok((symbol, token, source)) => {
                 // Store address in the main register (e.g., "sell_token")
                 context.set_register(&params.cache_as, json!(&token.address), "token_lookup");
 
                 // Also store symbol in a separate register (e.g., "sell_token_symbol")
                 let symbol_register = format!("{}_symbol", params.cache_as);
-                context.set_register(&symbol_register, json!(params.symbol.to_uppercase()), "token_lookup");
+                context.set_register(&symbol_register, json!(symbol.to_uppercase()), "token_lookup");
 
                 log::info!(
-                    "[token_lookup] Cached {} in registers: '{}'={}, '{}'={}",
-                    params.symbol,
+                    "[token_lookup] Cached {} in registers: '{}'={}, '{}'={} (from {})",
+                    symbol,
                     params.cache_as,
                     token.address,
                     symbol_register,
-                    params.symbol.to_uppercase()
+                    symbol.to_uppercase(),
+                    source
                 );
 
                 ToolResult::success(format!(
-                    "{} ({}) on {}\nAddress: {}\nCached in register: '{}'",
+                    "{} ({}) on {}\nAddress: {}\nDecimals: {}\nCached in register: '{}'",
                     token.name,
-                    params.symbol.to_uppercase(),
+                    symbol.to_uppercase(),
                     params.network,
                     token.address,
+                    token.decimals,
                     params.cache_as
                 )).with_metadata(json!({
-                    "symbol": params.symbol.to_uppercase(),
+                    "symbol": symbol.to_uppercase(),
                     "address": token.address,
                     "decimals": token.decimals,
                     "name": token.name,
                     "network": params.network,
+                    "source": source,
                     "cached_in_register": params.cache_as
                 }))
             }
-            None => {
-                let available = Self::list_available(&params.network);
-                ToolResult::error(format!(
-                    "Token '{}' not found on {}. Available tokens: {}",
-                    params.symbol,
-                    params.network,
-                    available.join(", ")
-                ))
-            }
+            Err(e) => ToolResult::error(e),
         }
     }
 }
 
+/// RPC client for `network`, using the provider configured in bot settings
+pub(crate) fn evm_rpc(network: &str, context: &ToolContext) -> Result<X402EvmRpc, String> {
+    let rpc_provider = context
+        .extra
+        .get("rpc_provider")
+        .and_then(|v| v.as_str())
+        .unwrap_or("defirelay");
+    let custom_endpoints: Option<HashMap<String, String>> = context
+        .extra
+        .get("custom_rpc_endpoints")
+        .and_then(|v| serde_json::from_value(v.clone()).ok());
+    let (url, use_x402) = rpc_config::resolve_rpc_config(rpc_provider, custom_endpoints.as_ref(), network)
+        .unwrap_or_else(|| (format!("https://rpc.defirelay.com/rpc/light/{}", network), true));
+
+    let signer = crate::wallet::require_signer()?;
+    X402EvmRpc::new_with_config(signer, network, Some(url), use_x402)
+}
+
+pub(crate) fn is_address(s: &str) -> bool {
+    s.len() == 42
+        && s.starts_with("0x")
+        && s[2..].chars().all(|c| c.is_ascii_hexdigit())
+}
+
+/// Decode an ABI `string` return value, or a `bytes32` one (used by e.g. MKR)
+fn decode_string(data: &[u8]) -> Option<String> {
+    let decoded = match abi::decode(&[ParamType::String], data) {
+        Ok(tokens) => match tokens.into_iter().next() {
+            Some(Token::String(s)) => s,
+            _ => return None,
+        },
+        Err(_) if data.len() == 32 => {
+            let end = data.iter().position(|b| *b == 0).unwrap_or(32);
+            String::from_utf8(data[..end].to_vec()).ok()?
+        }
+        Err(_) => return None,
+    };
+    let trimmed = decoded.trim_matches(char::from(0)).trim();
+    (!trimmed.is_empty()).then(|| trimmed.to_string())
+}
+
+fn decode_decimals(data: &[u8]) -> Option<u8> {
+    if data.len() < 32 {
+        return None;
+    }
+    let value = U256::from_big_endian(&data[..32]);
+    (value <= U256::from(u8::MAX)).then(|| value.as_u32() as u8)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -279,4 +479,72 @@ mod tests {
         setup();
         assert!(TokenLookupTool::lookup("UNKNOWN_TOKEN_XYZ", "base").is_none());
     }
+
+    #[test]
+    fn test_unknown_network_does_not_fall_back_to_base() {
+        setup();
+        assert!(TokenLookupTool::lookup("USDC", "bsae").is_none());
+    }
+
+    #[test]
+    fn test_every_network_has_tokens() {
+        setup();
+        for network in Network::ALL {
+            assert!(
+                !TokenLookupTool::list_available(network.as_str()).is_empty(),
+                "no tokens for {}",
+                network
+            );
+        }
+        let usdc = TokenLookupTool::lookup("USDC", "arbitrum").unwrap();
+        assert_eq!(usdc.address, "0xaf88d065e77c8cC2239327C5EDb3A432268e5831");
+        assert_eq!(TokenLookupTool::lookup("usdc.e", "polygon").unwrap().decimals, 6);
+        assert_eq!(TokenLookupTool::lookup("USDT", "bsc").unwrap().decimals, 18);
+    }
+
+    #[test]
+    fn test_mixed_case_symbol_and_address_lookup() {
+        setup();
+        assert!(TokenLookupTool::lookup("cbbtc", "base").is_some());
+
+        let (symbol, token) =
+            TokenLookupTool::lookup_address("0x4200000000000000000000000000000000000042", "optimism").unwrap();
+        assert_eq!(symbol, "OP");
+        assert_eq!(token.decimals, 18);
+        assert!(TokenLookupTool::lookup_address("0x4200000000000000000000000000000000000042", "base").is_none());
+    }
+
+    #[test]
+    fn test_decode_erc20_return_values() {
+        let encoded = abi::encode(&[Token::String("MOON".to_string())]);
+        assert_eq!(decode_string(&encoded).as_deref(), Some("MOON"));
+
+        // bytes32 symbol, as returned by MKR
+        let mut bytes32 = [0u8; 32];
+        bytes32[..3].copy_from_slice(b"MKR");
+        assert_eq!(decode_string(&bytes32).as_deref(), Some("MKR"));
+        assert_eq!(decode_string(&[]), None);
+
+        let decimals = abi::encode(&[Token::Uint(U256::from(6))]);
+        assert_eq!(decode_decimals(&decimals), Some(6));
+        assert_eq!(decode_decimals(&abi::encode(&[Token::Uint(U256::from(1000))])), None);
+    }
+
+    #[tokio::test]
+    async fn test_resolve_uses_db_cache() {
+        setup();
+        let db = std::sync::Arc::new(crate::db::Database::new(":memory:").unwrap());
+        let address = "0x1111111111111111111111111111111111111111";
+        db.cache_token("arbitrum", address, "MOON", "Moon Token", 9).await.unwrap();
+        let context = ToolContext::new().with_database(db);
+
+        let (symbol, token, source) = TokenLookupTool::resolve("moon", "arbitrum", &context).await.unwrap();
+        assert_eq!((symbol.as_str(), token.decimals, source), ("MOON", 9, "cache"));
+
+        let (_, token, source) = TokenLookupTool::resolve(address, "arbitrum", &context).await.unwrap();
+        assert_eq!((token.address.as_str(), source), (address, "cache"));
+
+        let err = TokenLookupTool::resolve("MOON", "base", &context).await.unwrap_err();
+        assert!(err.contains("pass the contract address"));
+    }
 }
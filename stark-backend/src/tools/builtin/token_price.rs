@@ -0,0 +1,460 @@
+//! Token Price tool for spot prices of registry tokens
+//!
+//! Tokens are resolved the same way as `token_lookup` (config, DB cache, then
+//! on-chain for addresses). Prices come from CoinGecko, falling back to the
+//! token's Chainlink USD feed when one is configured in config/tokens.ron.
+//! With an `amount` and a `quote` token the tool converts between the two, so
+//! the agent never has to guess an exchange rate.
+
+use crate::controllers::api_keys::ApiKeyId;
+use crate::tools::builtin::token_lookup::{evm_rpc, TokenInfo, TokenLookupTool};
+use crate::tools::network::{parse_network, supported_networks, Network};
+use crate::tools::registry::Tool;
+use crate::tools::types::{
+    PropertySchema, ToolContext, ToolDefinition, ToolGroup, ToolInputSchema, ToolResult,
+};
+use async_trait::async_trait;
+use ethers::types::{Address, U256};
+use serde::Deserialize;
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::str::FromStr;
+use std::time::Duration;
+
+const COINGECKO_API: &str = "https://api.coingecko.com/api/v3";
+
+/// Address used in tokens.ron for a network's native coin
+pub(crate) const NATIVE_ADDRESS: &str = "0xEeeeeEeeeEeEeeEeEeEeeEEEeeeeEeeeeeeeEEeE";
+
+/// Chainlink `latestRoundData()` selector
+const LATEST_ROUND_DATA_SELECTOR: [u8; 4] = [0xfe, 0xaf, 0x96, 0x8c];
+/// Chainlink `decimals()` selector
+const DECIMALS_SELECTOR: [u8; 4] = [0x31, 0x3c, 0xe5, 0x67];
+
+/// Feeds update at least daily; older answers are treated as unavailable
+const MAX_FEED_AGE_SECS: u64 = 26 * 60 * 60;
+
+/// Token Price tool
+pub struct TokenPriceTool {
+    definition: ToolDefinition,
+}
+
+impl TokenPriceTool {
+    pub fn new() -> Self {
+        let mut properties = HashMap::new();
+
+        properties.insert(
+            "symbol".to_string(),
+            PropertySchema {
+                schema_type: "string".to_string(),
+                description: "Token to price: a symbol (e.g., 'ETH', 'USDC') or a contract address".to_string(),
+                default: None,
+                items: None,
+                enum_values: None,
+            },
+        );
+
+        properties.insert(
+            "network".to_string(),
+            PropertySchema {
+                schema_type: "string".to_string(),
+                description: format!("Network: {}", supported_networks()),
+                default: Some(json!("base")),
+                items: None,
+                enum_values: Some(Network::ALL.iter().map(|n| n.as_str().to_string()).collect()),
+            },
+        );
+
+        properties.insert(
+            "amount".to_string(),
+            PropertySchema {
+                schema_type: "number".to_string(),
+                description: "Amount of the token to value, in whole tokens (e.g., 0.5). Defaults to 1.".to_string(),
+                default: Some(json!(1)),
+                items: None,
+                enum_values: None,
+            },
+        );
+
+        properties.insert(
+            "quote".to_string(),
+            PropertySchema {
+                schema_type: "string".to_string(),
+                description: "Currency to express the value in: 'USD' or a token symbol/address on the same network (e.g., 'USDC')".to_string(),
+                default: Some(json!("USD")),
+                items: None,
+                enum_values: None,
+            },
+        );
+
+        properties.insert(
+            "cache_as".to_string(),
+            PropertySchema {
+                schema_type: "string".to_string(),
+                description: "Register name to cache the value in. Defaults to 'token_price'; the price of one token is also stored as '<cache_as>_unit'.".to_string(),
+                default: Some(json!("token_price")),
+                items: None,
+                enum_values: None,
+            },
+        );
+
+        TokenPriceTool {
+            definition: ToolDefinition {
+                name: "token_price".to_string(),
+                description: "Get the current spot price of a token in USD or another token, optionally for a given amount (e.g., 'how much is 0.5 ETH in USDC'). Always use this instead of estimating prices. The value is cached in a register (default: 'token_price').".to_string(),
+                input_schema: ToolInputSchema {
+                    schema_type: "object".to_string(),
+                    properties,
+                    required: vec!["symbol".to_string()],
+                },
+                group: ToolGroup::Finance,
+            },
+        }
+    }
+
+    /// USD price of one token, and where it came from
+    async fn usd_price(
+        token: &TokenInfo,
+        network: Network,
+        context: &ToolContext,
+    ) -> Result<(f64, &'static str), String> {
+        let coingecko_err = match coingecko_price(token, network, context).await {
+            Ok(price) => return Ok((price, "coingecko")),
+            Err(e) => e,
+        };
+
+        let Some(feed) = &token.price_feed else {
+            return Err(coingecko_err);
+        };
+        log::warn!(
+            "[token_price] CoinGecko failed for {} ({}), trying Chainlink feed {}",
+            token.address, coingecko_err, feed
+        );
+        match chainlink_price(feed, network, context).await {
+            Ok(price) => Ok((price, "chainlink")),
+            Err(e) => Err(format!("{}; Chainlink: {}", coingecko_err, e)),
+        }
+    }
+
+    /// USD value of `amount` base units of a token (symbol or address)
+    pub(crate) async fn usd_value(
+        token: &str,
+        amount: U256,
+        network: Network,
+        context: &ToolContext,
+    ) -> Result<f64, String> {
+        let (_, info, _) = TokenLookupTool::resolve(token, network.as_str(), context).await?;
+        let (price, _) = Self::usd_price(&info, network, context).await?;
+        let units = amount.to_string().parse::<f64>().map_err(|e| e.to_string())?
+            / 10f64.powi(i32::from(info.decimals));
+        Ok(units * price)
+    }
+}
+
+impl Default for TokenPriceTool {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct TokenPriceParams {
+    symbol: String,
+    #[serde(default = "default_network")]
+    network: String,
+    amount: Option<f64>,
+    #[serde(default = "default_quote")]
+    quote: String,
+    #[serde(default = "default_cache_as")]
+    cache_as: String,
+}
+
+fn default_network() -> String {
+    "base".to_string()
+}
+
+fn default_quote() -> String {
+    "USD".to_string()
+}
+
+fn default_cache_as() -> String {
+    "token_price".to_string()
+}
+
+#[async_trait]
+impl Tool for TokenPriceTool {
+    fn definition(&self) -> ToolDefinition {
+        self.definition.clone()
+    }
+
+    async fn execute(&self, params: Value, context: &ToolContext) -> ToolResult {
+        let params: TokenPriceParams = match serde_json::from_value(params) {
+            Ok(p) => p,
+            Err(e) => return ToolResult::error(format!("Invalid parameters: {}", e)),
+        };
+
+        let network = match parse_network(&params.network) {
+            Ok(n) => n,
+            Err(e) => return ToolResult::error(e),
+        };
+        let amount = params.amount.unwrap_or(1.0);
+        if !amount.is_finite() || amount < 0.0 {
+            return ToolResult::error("amount must be a non-negative number");
+        }
+
+        let (symbol, token, _) =
+            match TokenLookupTool::resolve(params.symbol.trim(), network.as_str(), context).await {
+                Ok(resolved) => resolved,
+                Err(e) => return ToolResult::error(e),
+            };
+        let symbol = symbol.to_uppercase();
+        let (usd_price, source) = match Self::usd_price(&token, network, context).await {
+            Ok(price) => price,
+            Err(e) => return ToolResult::error(format!("Could not price {}: {}", symbol, e)),
+        };
+
+        // Price of one token in the quote currency
+        let quote_input = params.quote.trim();
+        let (quote, unit_price) = if quote_input.eq_ignore_ascii_case("USD") {
+            ("USD".to_string(), usd_price)
+        } else {
+            let (quote_symbol, quote_token, _) =
+                match TokenLookupTool::resolve(quote_input, network.as_str(), context).await {
+                    Ok(resolved) => resolved,
+                    Err(e) => return ToolResult::error(e),
+                };
+            let quote_symbol = quote_symbol.to_uppercase();
+            match Self::usd_price(&quote_token, network, context).await {
+                Ok((quote_usd, _)) if quote_usd > 0.0 => (quote_symbol, usd_price / quote_usd),
+                Ok(_) => return ToolResult::error(format!("{} has no usable price", quote_symbol)),
+                Err(e) => return ToolResult::error(format!("Could not price {}: {}", quote_symbol, e)),
+            }
+        };
+        let value = amount * unit_price;
+
+        context.set_register(&params.cache_as, json!(value), "token_price");
+        let unit_register = format!("{}_unit", params.cache_as);
+        context.set_register(&unit_register, json!(unit_price), "token_price");
+
+        log::info!(
+            "[token_price] {} {} on {} = {} {} (from {})",
+            amount, symbol, network, value, quote, source
+        );
+
+        ToolResult::success(format!(
+            "{} {} = {} {} on {}\n1 {} = {} {} (source: {})\nCached in register: '{}'",
+            format_amount(amount),
+            symbol,
+            format_amount(value),
+            quote,
+            network,
+            symbol,
+            format_amount(unit_price),
+            quote,
+            source,
+            params.cache_as
+        ))
+        .with_metadata(json!({
+            "symbol": symbol,
+            "address": token.address,
+            "network": network.as_str(),
+            "amount": amount,
+            "quote": quote,
+            "unit_price": unit_price,
+            "value": value,
+            "usd_price": usd_price,
+            "source": source,
+            "cached_in_register": params.cache_as
+        }))
+    }
+}
+
+/// CoinGecko asset platform id for token prices
+fn coingecko_platform(network: Network) -> &'static str {
+    match network {
+        Network::Base => "base",
+        Network::Mainnet => "ethereum",
+        Network::Arbitrum => "arbitrum-one",
+        Network::Optimism => "optimistic-ethereum",
+        Network::Polygon => "polygon-pos",
+        Network::Bsc => "binance-smart-chain",
+    }
+}
+
+/// CoinGecko coin id of the network's native coin
+fn coingecko_native_id(network: Network) -> &'static str {
+    match network {
+        Network::Base | Network::Mainnet | Network::Arbitrum | Network::Optimism => "ethereum",
+        Network::Polygon => "polygon-ecosystem-token",
+        Network::Bsc => "binancecoin",
+    }
+}
+
+async fn coingecko_price(
+    token: &TokenInfo,
+    network: Network,
+    context: &ToolContext,
+) -> Result<f64, String> {
+    let (url, key) = if token.address.eq_ignore_ascii_case(NATIVE_ADDRESS) {
+        let id = coingecko_native_id(network);
+        (format!("{}/simple/price?ids={}&vs_currencies=usd", COINGECKO_API, id), id.to_string())
+    } else {
+        let address = token.address.to_lowercase();
+        (
+            format!(
+                "{}/simple/token_price/{}?contract_addresses={}&vs_currencies=usd",
+                COINGECKO_API,
+                coingecko_platform(network),
+                address
+            ),
+            address,
+        )
+    };
+
+    let client = reqwest::Client::builder()
+        .timeout(Duration::from_secs(15))
+        .build()
+        .unwrap_or_else(|_| reqwest::Client::new());
+    let mut request = client.get(&url).header("Accept", "application/json");
+    if let Some(api_key) = context
+        .get_api_key_by_id(ApiKeyId::CoingeckoApiKey)
+        .or_else(|| std::env::var("COINGECKO_API_KEY").ok())
+        .filter(|k| !k.is_empty())
+    {
+        request = request.header("x-cg-demo-api-key", api_key);
+    }
+
+    let response = request
+        .send()
+        .await
+        .map_err(|e| format!("CoinGecko request failed: {}", e))?;
+    let status = response.status();
+    if !status.is_success() {
+        return Err(format!("CoinGecko returned {}", status));
+    }
+    let body: Value = response
+        .json()
+        .await
+        .map_err(|e| format!("Invalid CoinGecko response: {}", e))?;
+
+    parse_coingecko_usd(&body, &key).ok_or_else(|| format!("CoinGecko has no price for {}", token.address))
+}
+
+/// Extract `{ "<key>": { "usd": <price> } }`
+fn parse_coingecko_usd(body: &Value, key: &str) -> Option<f64> {
+    body.get(key)?.get("usd")?.as_f64().filter(|p| *p >= 0.0)
+}
+
+async fn chainlink_price(feed: &str, network: Network, context: &ToolContext) -> Result<f64, String> {
+    let feed_address = Address::from_str(feed).map_err(|e| format!("Invalid price feed '{}': {}", feed, e))?;
+    let rpc = evm_rpc(network.as_str(), context)?;
+
+    let decimals_data = rpc.call(feed_address, &DECIMALS_SELECTOR).await?;
+    if decimals_data.len() < 32 {
+        return Err("price feed returned no decimals".to_string());
+    }
+    let decimals = U256::from_big_endian(&decimals_data[..32]);
+    if decimals > U256::from(36) {
+        return Err("price feed returned invalid decimals".to_string());
+    }
+
+    let round = rpc.call(feed_address, &LATEST_ROUND_DATA_SELECTOR).await?;
+    let now = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0);
+    decode_round_data(&round, decimals.as_u32(), now)
+}
+
+/// Decode `latestRoundData()` into a price, rejecting negative or stale answers
+fn decode_round_data(data: &[u8], decimals: u32, now: u64) -> Result<f64, String> {
+    // (uint80 roundId, int256 answer, uint256 startedAt, uint256 updatedAt, uint80 answeredInRound)
+    if data.len() < 160 {
+        return Err("price feed returned truncated round data".to_string());
+    }
+    let answer = &data[32..64];
+    if answer[0] & 0x80 != 0 {
+        return Err("price feed returned a negative answer".to_string());
+    }
+    let answer = U256::from_big_endian(answer);
+    if answer.is_zero() {
+        return Err("price feed returned no answer".to_string());
+    }
+
+    let updated_at = U256::from_big_endian(&data[96..128]);
+    if updated_at > U256::from(u64::MAX) || now.saturating_sub(updated_at.as_u64()) > MAX_FEED_AGE_SECS {
+        return Err("price feed answer is stale".to_string());
+    }
+
+    let answer: f64 = answer
+        .to_string()
+        .parse()
+        .map_err(|_| "price feed answer out of range".to_string())?;
+    Ok(answer / 10f64.powi(decimals as i32))
+}
+
+/// Two decimals for amounts of 1 or more, up to six significant decimals below that
+fn format_amount(value: f64) -> String {
+    if value.abs() >= 1.0 || value == 0.0 {
+        return format!("{:.2}", value);
+    }
+    let formatted = format!("{:.8}", value);
+    let trimmed = formatted.trim_end_matches('0').trim_end_matches('.');
+    trimmed.to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ethers::abi::{encode, Token};
+
+    #[test]
+    fn test_parse_coingecko_usd() {
+        let body = json!({ "ethereum": { "usd": 3150.42 } });
+        assert_eq!(parse_coingecko_usd(&body, "ethereum"), Some(3150.42));
+        assert_eq!(parse_coingecko_usd(&body, "binancecoin"), None);
+        assert_eq!(parse_coingecko_usd(&json!({ "0xabc": {} }), "0xabc"), None);
+    }
+
+    #[test]
+    fn test_decode_round_data() {
+        let round = |answer: U256, updated_at: u64| {
+            encode(&[
+                Token::Uint(U256::from(1)),
+                Token::Int(answer),
+                Token::Uint(U256::from(updated_at)),
+                Token::Uint(U256::from(updated_at)),
+                Token::Uint(U256::from(1)),
+            ])
+        };
+        let now = 1_700_000_000;
+
+        // ETH/USD at $3,150.42 with 8 decimals
+        let price = decode_round_data(&round(U256::from(315_042_000_000u64), now - 60), 8, now).unwrap();
+        assert!((price - 3150.42).abs() < 1e-9);
+
+        assert!(decode_round_data(&round(U256::from(1), now - MAX_FEED_AGE_SECS - 1), 8, now)
+            .unwrap_err()
+            .contains("stale"));
+        assert!(decode_round_data(&round(U256::MAX, now), 8, now).unwrap_err().contains("negative"));
+        assert!(decode_round_data(&round(U256::zero(), now), 8, now).is_err());
+        assert!(decode_round_data(&[0u8; 64], 8, now).is_err());
+    }
+
+    #[test]
+    fn test_format_amount() {
+        assert_eq!(format_amount(1575.2049), "1575.20");
+        assert_eq!(format_amount(0.5), "0.5");
+        assert_eq!(format_amount(0.000012345678), "0.00001235");
+        assert_eq!(format_amount(0.0), "0.00");
+    }
+
+    #[test]
+    fn test_coingecko_ids_cover_every_network() {
+        for network in Network::ALL {
+            assert!(!coingecko_platform(network).is_empty());
+            assert!(!coingecko_native_id(network).is_empty());
+        }
+        assert_eq!(coingecko_native_id(Network::Bsc), "binancecoin");
+    }
+}
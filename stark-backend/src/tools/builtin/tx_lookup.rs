@@ -0,0 +1,599 @@
+//! Transaction lookup tool
+//!
+//! Fetches a transaction and its receipt from the configured RPC provider and
+//! returns a structured summary (status, from/to, gas, logs). Mined receipts are
+//! immutable, so they are cached for a while; lookups are spaced out to stay
+//! within free-tier rate limits.
+
+use crate::tools::http_retry::HttpRetryManager;
+use crate::tools::network::parse_network;
+use crate::tools::registry::Tool;
+use crate::tools::rpc_config;
+use crate::tools::types::{
+    PropertySchema, ToolContext, ToolDefinition, ToolGroup, ToolInputSchema, ToolResult,
+};
+use crate::x402::X402Client;
+use async_trait::async_trait;
+use ethers::types::U256;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// How long a mined transaction's summary stays cached
+const CACHE_TTL: Duration = Duration::from_secs(10 * 60);
+
+/// Upper bound on cached summaries
+const CACHE_CAPACITY: usize = 256;
+
+/// Minimum spacing between RPC requests (~5 req/s)
+const MIN_REQUEST_INTERVAL: Duration = Duration::from_millis(200);
+
+/// Maximum number of logs included in a summary
+const MAX_LOGS: usize = 25;
+
+/// JSON-RPC request structure
+#[derive(Debug, Serialize)]
+struct JsonRpcRequest {
+    jsonrpc: &'static str,
+    method: &'static str,
+    params: Value,
+    id: u64,
+}
+
+/// JSON-RPC response structure
+#[derive(Debug, Deserialize)]
+struct JsonRpcResponse {
+    result: Option<Value>,
+    error: Option<JsonRpcError>,
+}
+
+#[derive(Debug, Deserialize)]
+struct JsonRpcError {
+    code: i64,
+    message: String,
+}
+
+/// Lookup outcome for a transaction hash
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+enum TxStatus {
+    Success,
+    Failed,
+    Pending,
+    NotFound,
+}
+
+impl TxStatus {
+    fn is_final(&self) -> bool {
+        matches!(self, TxStatus::Success | TxStatus::Failed)
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+struct TxLog {
+    address: Option<String>,
+    topics: Vec<String>,
+    data: Option<String>,
+}
+
+/// Structured summary returned in the tool metadata
+#[derive(Debug, Clone, Serialize)]
+struct TxSummary {
+    hash: String,
+    network: String,
+    status: TxStatus,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    block_number: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    from: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    to: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    contract_address: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    value_wei: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    nonce: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    gas_used: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    effective_gas_price_wei: Option<String>,
+    log_count: usize,
+    logs: Vec<TxLog>,
+}
+
+impl TxSummary {
+    fn describe(&self) -> String {
+        let mut lines = vec![format!("Transaction {} on {}", self.hash, self.network)];
+        lines.push(format!(
+            "Status: {}",
+            match self.status {
+                TxStatus::Success => "✅ success",
+                TxStatus::Failed => "❌ failed (reverted)",
+                TxStatus::Pending => "⏳ pending (not yet mined)",
+                TxStatus::NotFound => "not found (unknown hash, dropped, or wrong network)",
+            }
+        ));
+        if let Some(block) = self.block_number {
+            lines.push(format!("Block: {}", block));
+        }
+        if let Some(ref from) = self.from {
+            lines.push(format!("From: {}", from));
+        }
+        match (&self.to, &self.contract_address) {
+            (Some(to), _) => lines.push(format!("To: {}", to)),
+            (None, Some(created)) => lines.push(format!("Contract created: {}", created)),
+            _ => {}
+        }
+        if let Some(ref value) = self.value_wei {
+            lines.push(format!("Value: {} wei", value));
+        }
+        if let Some(gas) = self.gas_used {
+            lines.push(format!("Gas used: {}", gas));
+        }
+        if let Some(ref price) = self.effective_gas_price_wei {
+            lines.push(format!("Effective gas price: {} wei", price));
+        }
+        if self.status.is_final() {
+            lines.push(format!("Logs: {}", self.log_count));
+        }
+        lines.join("\n")
+    }
+}
+
+/// Parse a hex quantity ("0x1a") as u64
+fn hex_u64(value: Option<&Value>) -> Option<u64> {
+    value
+        .and_then(|v| v.as_str())
+        .and_then(|s| u64::from_str_radix(s.trim_start_matches("0x"), 16).ok())
+}
+
+/// Parse a hex quantity as a decimal string (values may exceed u64)
+fn hex_decimal(value: Option<&Value>) -> Option<String> {
+    value
+        .and_then(|v| v.as_str())
+        .and_then(|s| U256::from_str_radix(s.trim_start_matches("0x"), 16).ok())
+        .map(|n| n.to_string())
+}
+
+fn string_field(value: Option<&Value>) -> Option<String> {
+    value.and_then(|v| v.as_str()).map(str::to_string)
+}
+
+fn is_valid_tx_hash(hash: &str) -> bool {
+    hash.len() == 66
+        && hash.starts_with("0x")
+        && hash[2..].chars().all(|c| c.is_ascii_hexdigit())
+}
+
+/// Build a summary from the eth_getTransactionByHash and eth_getTransactionReceipt results
+fn summarize(network: &str, hash: &str, tx: &Value, receipt: &Value) -> TxSummary {
+    let status = if tx.is_null() && receipt.is_null() {
+        TxStatus::NotFound
+    } else if receipt.is_null() {
+        TxStatus::Pending
+    } else if hex_u64(receipt.get("status")) == Some(0) {
+        TxStatus::Failed
+    } else {
+        TxStatus::Success
+    };
+
+    let raw_logs = receipt
+        .get("logs")
+        .and_then(|l| l.as_array())
+        .cloned()
+        .unwrap_or_default();
+    let logs = raw_logs
+        .iter()
+        .take(MAX_LOGS)
+        .map(|log| TxLog {
+            address: string_field(log.get("address")),
+            topics: log
+                .get("topics")
+                .and_then(|t| t.as_array())
+                .map(|t| t.iter().filter_map(|v| v.as_str().map(str::to_string)).collect())
+                .unwrap_or_default(),
+            data: string_field(log.get("data")),
+        })
+        .collect();
+
+    TxSummary {
+        hash: hash.to_string(),
+        network: network.to_string(),
+        status,
+        block_number: hex_u64(receipt.get("blockNumber")).or_else(|| hex_u64(tx.get("blockNumber"))),
+        from: string_field(tx.get("from")).or_else(|| string_field(receipt.get("from"))),
+        to: string_field(tx.get("to")).or_else(|| string_field(receipt.get("to"))),
+        contract_address: string_field(receipt.get("contractAddress")),
+        value_wei: hex_decimal(tx.get("value")),
+        nonce: hex_u64(tx.get("nonce")),
+        gas_used: hex_u64(receipt.get("gasUsed")),
+        effective_gas_price_wei: hex_decimal(receipt.get("effectiveGasPrice")),
+        log_count: raw_logs.len(),
+        logs,
+    }
+}
+
+/// Short-lived cache of mined transaction summaries, keyed by (network, hash)
+struct TxCache {
+    entries: Mutex<HashMap<(String, String), (Instant, TxSummary)>>,
+    ttl: Duration,
+}
+
+impl TxCache {
+    fn new(ttl: Duration) -> Self {
+        Self {
+            entries: Mutex::new(HashMap::new()),
+            ttl,
+        }
+    }
+
+    fn get(&self, network: &str, hash: &str) -> Option<TxSummary> {
+        let entries = self.entries.lock().unwrap();
+        entries
+            .get(&(network.to_string(), hash.to_string()))
+            .filter(|(at, _)| at.elapsed() < self.ttl)
+            .map(|(_, summary)| summary.clone())
+    }
+
+    fn insert(&self, summary: TxSummary) {
+        let mut entries = self.entries.lock().unwrap();
+        if entries.len() >= CACHE_CAPACITY {
+            let ttl = self.ttl;
+            entries.retain(|_, (at, _)| at.elapsed() < ttl);
+            if entries.len() >= CACHE_CAPACITY {
+                entries.clear();
+            }
+        }
+        entries.insert(
+            (summary.network.clone(), summary.hash.clone()),
+            (Instant::now(), summary),
+        );
+    }
+}
+
+/// Spaces out requests so that at most one starts per `min_interval`
+struct RateLimiter {
+    next_slot: tokio::sync::Mutex<Instant>,
+    min_interval: Duration,
+}
+
+impl RateLimiter {
+    fn new(min_interval: Duration) -> Self {
+        Self {
+            next_slot: tokio::sync::Mutex::new(Instant::now()),
+            min_interval,
+        }
+    }
+
+    /// Wait until the next request slot is free
+    async fn acquire(&self) {
+        let wait = {
+            let mut next = self.next_slot.lock().await;
+            let now = Instant::now();
+            let start = (*next).max(now);
+            *next = start + self.min_interval;
+            start - now
+        };
+        if !wait.is_zero() {
+            tokio::time::sleep(wait).await;
+        }
+    }
+}
+
+/// Transaction lookup tool (status, receipt, logs) with caching and rate limiting
+pub struct TxLookupTool {
+    definition: ToolDefinition,
+    cache: TxCache,
+    limiter: RateLimiter,
+}
+
+impl TxLookupTool {
+    pub fn new() -> Self {
+        let mut properties = HashMap::new();
+
+        properties.insert(
+            "hash".to_string(),
+            PropertySchema {
+                schema_type: "string".to_string(),
+                description: "Transaction hash (0x followed by 64 hex characters)".to_string(),
+                default: None,
+                items: None,
+                enum_values: None,
+            },
+        );
+
+        properties.insert(
+            "network".to_string(),
+            PropertySchema {
+                schema_type: "string".to_string(),
+                description: "Network: 'base' or 'mainnet'".to_string(),
+                default: Some(json!("base")),
+                items: None,
+                enum_values: Some(vec!["base".to_string(), "mainnet".to_string()]),
+            },
+        );
+
+        TxLookupTool {
+            definition: ToolDefinition {
+                name: "tx_lookup".to_string(),
+                description: "Look up an on-chain transaction by hash. Returns status (success, failed, pending, not_found), block, from/to, value, gas used and event logs.".to_string(),
+                input_schema: ToolInputSchema {
+                    schema_type: "object".to_string(),
+                    properties,
+                    required: vec!["hash".to_string()],
+                },
+                group: ToolGroup::Finance,
+            },
+            cache: TxCache::new(CACHE_TTL),
+            limiter: RateLimiter::new(MIN_REQUEST_INTERVAL),
+        }
+    }
+
+    /// Make a single JSON-RPC call through the configured provider
+    async fn rpc_call(
+        &self,
+        client: &X402Client,
+        url: &str,
+        use_x402: bool,
+        method: &'static str,
+        params: Value,
+    ) -> Result<Value, ToolResult> {
+        self.limiter.acquire().await;
+
+        let request = JsonRpcRequest {
+            jsonrpc: "2.0",
+            method,
+            params,
+            id: 1,
+        };
+
+        let retry_key = format!("tx_lookup:{}", url);
+        let retry_manager = HttpRetryManager::global();
+
+        let response = if use_x402 {
+            client.post_with_payment(url, &request).await
+        } else {
+            client.post_regular(url, &request).await
+        };
+
+        let response = match response {
+            Ok(r) => r,
+            Err(e) => {
+                let error_msg = format!("RPC request failed: {}", e);
+                if HttpRetryManager::is_retryable_error(&error_msg) {
+                    let delay = retry_manager.record_error(&retry_key);
+                    return Err(ToolResult::retryable_error(error_msg, delay));
+                }
+                return Err(ToolResult::error(error_msg));
+            }
+        };
+
+        let status = response.response.status();
+        if !status.is_success() {
+            let body = response.response.text().await.unwrap_or_default();
+            let error_msg = format!("HTTP error {}: {}", status, body);
+            if HttpRetryManager::is_retryable_status(status.as_u16()) {
+                let delay = retry_manager.record_error(&retry_key);
+                return Err(ToolResult::retryable_error(error_msg, delay));
+            }
+            return Err(ToolResult::error(error_msg));
+        }
+
+        retry_manager.record_success(&retry_key);
+
+        let body = response
+            .response
+            .text()
+            .await
+            .map_err(|e| ToolResult::error(format!("Failed to read response: {}", e)))?;
+
+        let rpc_response: JsonRpcResponse = serde_json::from_str(&body).map_err(|e| {
+            ToolResult::error(format!("Invalid JSON-RPC response: {} - Body: {}", e, body))
+        })?;
+
+        if let Some(error) = rpc_response.error {
+            return Err(ToolResult::error(format!(
+                "RPC error {}: {}",
+                error.code, error.message
+            )));
+        }
+
+        Ok(rpc_response.result.unwrap_or(Value::Null))
+    }
+}
+
+impl Default for TxLookupTool {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct TxLookupParams {
+    hash: String,
+    #[serde(default = "default_network")]
+    network: String,
+}
+
+fn default_network() -> String {
+    "base".to_string()
+}
+
+#[async_trait]
+impl Tool for TxLookupTool {
+    fn definition(&self) -> ToolDefinition {
+        self.definition.clone()
+    }
+
+    async fn execute(&self, params: Value, context: &ToolContext) -> ToolResult {
+        let params: TxLookupParams = match serde_json::from_value(params) {
+            Ok(p) => p,
+            Err(e) => return ToolResult::error(format!("Invalid parameters: {}", e)),
+        };
+
+        let network = match parse_network(&params.network) {
+            Ok(n) => n.as_str().to_string(),
+            Err(e) => return ToolResult::error(e),
+        };
+
+        let hash = params.hash.trim().to_lowercase();
+        if !is_valid_tx_hash(&hash) {
+            return ToolResult::error(format!(
+                "Invalid transaction hash '{}': expected 0x followed by 64 hex characters",
+                params.hash
+            ));
+        }
+
+        if let Some(summary) = self.cache.get(&network, &hash) {
+            log::debug!("[tx_lookup] Cache hit for {} on {}", hash, network);
+            let mut metadata = json!(summary);
+            metadata["cached"] = json!(true);
+            return ToolResult::success(summary.describe()).with_metadata(metadata);
+        }
+
+        // Get RPC configuration from context (set by dispatcher from bot_settings)
+        let rpc_provider = context
+            .extra
+            .get("rpc_provider")
+            .and_then(|v| v.as_str())
+            .unwrap_or("defirelay");
+
+        let custom_endpoints: Option<HashMap<String, String>> = context
+            .extra
+            .get("custom_rpc_endpoints")
+            .and_then(|v| serde_json::from_value(v.clone()).ok());
+
+        let (url, use_x402) =
+            rpc_config::resolve_rpc_config(rpc_provider, custom_endpoints.as_ref(), &network)
+                .unwrap_or_else(|| {
+                    (format!("https://rpc.defirelay.com/rpc/light/{}", network), true)
+                });
+
+        let signer = match crate::wallet::require_signer() {
+            Ok(s) => s,
+            Err(e) => return ToolResult::error(e),
+        };
+        let client = match X402Client::with_signer(signer) {
+            Ok(c) => c,
+            Err(e) => return ToolResult::error(e),
+        };
+
+        log::info!("[tx_lookup] Looking up {} on {} via {}", hash, network, url);
+
+        let tx = match self
+            .rpc_call(&client, &url, use_x402, "eth_getTransactionByHash", json!([hash]))
+            .await
+        {
+            Ok(v) => v,
+            Err(result) => return result,
+        };
+        let receipt = match self
+            .rpc_call(&client, &url, use_x402, "eth_getTransactionReceipt", json!([hash]))
+            .await
+        {
+            Ok(v) => v,
+            Err(result) => return result,
+        };
+
+        let summary = summarize(&network, &hash, &tx, &receipt);
+
+        // Only mined transactions are immutable; pending/unknown hashes must be re-queried
+        if summary.status.is_final() {
+            self.cache.insert(summary.clone());
+        }
+
+        let mut metadata = json!(summary);
+        metadata["cached"] = json!(false);
+        ToolResult::success(summary.describe()).with_metadata(metadata)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const HASH: &str = "0x5c504ed432cb51138bcf09aa5e8a410dd4a1e204ef84bfed1be16dfba1b22060";
+
+    #[test]
+    fn test_tx_hash_validation() {
+        assert!(is_valid_tx_hash(HASH));
+        assert!(!is_valid_tx_hash("0x1234"));
+        assert!(!is_valid_tx_hash(&HASH.replace("0x", "")));
+        assert!(!is_valid_tx_hash(&HASH.replace('5', "z")));
+    }
+
+    #[test]
+    fn test_summarize_mined_transaction() {
+        let tx = json!({
+            "from": "0xaaa",
+            "to": "0xbbb",
+            "value": "0xde0b6b3a7640000",
+            "nonce": "0x2a",
+            "blockNumber": "0x10"
+        });
+        let receipt = json!({
+            "status": "0x1",
+            "blockNumber": "0x10",
+            "gasUsed": "0x5208",
+            "effectiveGasPrice": "0x3b9aca00",
+            "logs": [{
+                "address": "0xccc",
+                "topics": ["0xddf252ad"],
+                "data": "0x01"
+            }]
+        });
+
+        let summary = summarize("base", HASH, &tx, &receipt);
+        assert_eq!(summary.status, TxStatus::Success);
+        assert_eq!(summary.block_number, Some(16));
+        assert_eq!(summary.value_wei.as_deref(), Some("1000000000000000000"));
+        assert_eq!(summary.nonce, Some(42));
+        assert_eq!(summary.gas_used, Some(21000));
+        assert_eq!(summary.effective_gas_price_wei.as_deref(), Some("1000000000"));
+        assert_eq!(summary.log_count, 1);
+        assert_eq!(summary.logs[0].topics, vec!["0xddf252ad".to_string()]);
+    }
+
+    #[test]
+    fn test_summarize_failed_pending_and_missing() {
+        let tx = json!({ "from": "0xaaa", "to": "0xbbb" });
+
+        let failed = summarize("base", HASH, &tx, &json!({ "status": "0x0", "logs": [] }));
+        assert_eq!(failed.status, TxStatus::Failed);
+
+        let pending = summarize("base", HASH, &tx, &Value::Null);
+        assert_eq!(pending.status, TxStatus::Pending);
+        assert!(pending.describe().contains("pending"));
+
+        let missing = summarize("base", HASH, &Value::Null, &Value::Null);
+        assert_eq!(missing.status, TxStatus::NotFound);
+        assert!(!missing.status.is_final());
+    }
+
+    #[test]
+    fn test_cache_expires_entries() {
+        let summary = summarize("base", HASH, &json!({}), &json!({ "status": "0x1" }));
+
+        let cache = TxCache::new(Duration::from_secs(60));
+        cache.insert(summary.clone());
+        assert!(cache.get("base", HASH).is_some());
+        assert!(cache.get("mainnet", HASH).is_none());
+
+        let expired = TxCache::new(Duration::ZERO);
+        expired.insert(summary);
+        assert!(expired.get("base", HASH).is_none());
+    }
+
+    #[tokio::test]
+    async fn test_rate_limiter_spaces_requests() {
+        let limiter = RateLimiter::new(Duration::from_millis(50));
+        let start = Instant::now();
+        limiter.acquire().await;
+        limiter.acquire().await;
+        limiter.acquire().await;
+        assert!(start.elapsed() >= Duration::from_millis(100));
+    }
+}
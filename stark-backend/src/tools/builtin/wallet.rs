@@ -0,0 +1,187 @@
+//! Wallet tool: address, balance and nonce of the active wallet
+//!
+//! The active wallet is the default one in `/api/wallets`, or the
+//! `BURNER_WALLET_BOT_PRIVATE_KEY` fallback. Balances are native by default;
+//! pass `token` (symbol or address) for an ERC-20 balance.
+
+use crate::tools::builtin::token_lookup::{evm_rpc, TokenLookupTool};
+use crate::tools::network::{parse_network, supported_networks, Network};
+use crate::tools::registry::Tool;
+use crate::tools::types::{
+    PropertySchema, ToolContext, ToolDefinition, ToolGroup, ToolInputSchema, ToolResult,
+};
+use crate::x402::erc20;
+use async_trait::async_trait;
+use ethers::types::Address;
+use ethers::utils::format_units;
+use serde::Deserialize;
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::str::FromStr;
+
+/// Wallet tool
+pub struct WalletTool {
+    definition: ToolDefinition,
+}
+
+impl WalletTool {
+    pub fn new() -> Self {
+        let mut properties = HashMap::new();
+
+        properties.insert(
+            "query".to_string(),
+            PropertySchema {
+                schema_type: "string".to_string(),
+                description: "What to look up: 'address', 'balance' or 'nonce'".to_string(),
+                default: None,
+                items: None,
+                enum_values: Some(vec![
+                    "address".to_string(),
+                    "balance".to_string(),
+                    "nonce".to_string(),
+                ]),
+            },
+        );
+
+        properties.insert(
+            "network".to_string(),
+            PropertySchema {
+                schema_type: "string".to_string(),
+                description: format!("Network: {}", supported_networks()),
+                default: Some(json!("base")),
+                items: None,
+                enum_values: Some(Network::ALL.iter().map(|n| n.as_str().to_string()).collect()),
+            },
+        );
+
+        properties.insert(
+            "token".to_string(),
+            PropertySchema {
+                schema_type: "string".to_string(),
+                description: "For 'balance': token symbol or contract address (e.g., 'USDC'). Omit for the native coin.".to_string(),
+                default: None,
+                items: None,
+                enum_values: None,
+            },
+        );
+
+        WalletTool {
+            definition: ToolDefinition {
+                name: "wallet".to_string(),
+                description: "Query the bot's active wallet: its address, its native or token balance, or its transaction count (nonce) on a network.".to_string(),
+                input_schema: ToolInputSchema {
+                    schema_type: "object".to_string(),
+                    properties,
+                    required: vec!["query".to_string()],
+                },
+                group: ToolGroup::Finance,
+            },
+        }
+    }
+}
+
+impl Default for WalletTool {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct WalletParams {
+    query: String,
+    #[serde(default = "default_network")]
+    network: String,
+    token: Option<String>,
+}
+
+fn default_network() -> String {
+    "base".to_string()
+}
+
+#[async_trait]
+impl Tool for WalletTool {
+    fn definition(&self) -> ToolDefinition {
+        self.definition.clone()
+    }
+
+    async fn execute(&self, params: Value, context: &ToolContext) -> ToolResult {
+        let params: WalletParams = match serde_json::from_value(params) {
+            Ok(p) => p,
+            Err(e) => return ToolResult::error(format!("Invalid parameters: {}", e)),
+        };
+
+        let signer = match crate::wallet::require_signer() {
+            Ok(s) => s,
+            Err(e) => return ToolResult::error(e),
+        };
+        let address = signer.address();
+        let address_str = format!("{:?}", address);
+
+        if params.query == "address" {
+            return ToolResult::success(address_str.clone())
+                .with_metadata(json!({ "address": address_str }));
+        }
+
+        let network = match parse_network(&params.network) {
+            Ok(n) => n,
+            Err(e) => return ToolResult::error(e),
+        };
+        let rpc = match evm_rpc(network.as_str(), context) {
+            Ok(r) => r,
+            Err(e) => return ToolResult::error(e),
+        };
+
+        match params.query.as_str() {
+            "nonce" => match rpc.get_transaction_count(address).await {
+                Ok(nonce) => ToolResult::success(format!("Nonce of {} on {}: {}", address_str, network, nonce))
+                    .with_metadata(json!({
+                        "address": address_str,
+                        "network": network.as_str(),
+                        "nonce": nonce.as_u64()
+                    })),
+                Err(e) => ToolResult::error(format!("Failed to get nonce: {}", e)),
+            },
+            "balance" => {
+                let (symbol, raw, decimals) = match params.token.as_deref().map(str::trim) {
+                    None | Some("") => match rpc.get_balance(address).await {
+                        Ok(balance) => (network.native_symbol().to_string(), balance, 18),
+                        Err(e) => return ToolResult::error(format!("Failed to get balance: {}", e)),
+                    },
+                    Some(token) => {
+                        let (symbol, info, _) =
+                            match TokenLookupTool::resolve(token, network.as_str(), context).await {
+                                Ok(resolved) => resolved,
+                                Err(e) => return ToolResult::error(e),
+                            };
+                        let contract = match Address::from_str(&info.address) {
+                            Ok(a) => a,
+                            Err(e) => return ToolResult::error(format!("Invalid token address: {}", e)),
+                        };
+                        let balance = match rpc.call(contract, &erc20::encode_balance_of(address)).await {
+                            Ok(data) => erc20::decode_balance(&data),
+                            Err(e) => Err(e),
+                        };
+                        match balance {
+                            Ok(balance) => (symbol.to_uppercase(), balance, info.decimals as u32),
+                            Err(e) => return ToolResult::error(format!("Failed to get {} balance: {}", symbol, e)),
+                        }
+                    }
+                };
+                let formatted = format_units(raw, decimals).unwrap_or_else(|_| raw.to_string());
+                ToolResult::success(format!("{} {} on {} ({})", formatted, symbol, network, address_str))
+                    .with_metadata(json!({
+                        "address": address_str,
+                        "network": network.as_str(),
+                        "symbol": symbol,
+                        "balance": formatted,
+                        "raw_balance": raw.to_string(),
+                        "decimals": decimals
+                    }))
+            }
+            other => ToolResult::error(format!(
+                "Unknown query '{}'. Use 'address', 'balance' or 'nonce'",
+                other
+            )),
+        }
+    }
+}
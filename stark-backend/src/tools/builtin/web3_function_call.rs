@@ -6,11 +6,14 @@
 //! Supports presets for common operations (weth_deposit, weth_withdraw, etc.)
 //! that read parameters from registers.
 
-use crate::gateway::events::EventBroadcaster;
 use crate::gateway::protocol::GatewayEvent;
+use crate::models::{NewTransaction, Scope, TransactionKind, TransactionStatus};
+use crate::tools::builtin::token_price::NATIVE_ADDRESS;
 use crate::tools::builtin::web3_tx::parse_u256;
+use crate::tools::network::parse_network;
 use crate::tools::presets::{get_web3_preset, list_web3_presets};
 use crate::tools::registry::Tool;
+use crate::tools::spending;
 use crate::tools::types::{
     PropertySchema, ToolContext, ToolDefinition, ToolGroup, ToolInputSchema, ToolResult,
 };
@@ -24,7 +27,6 @@ use serde::Deserialize;
 use serde_json::{json, Value};
 use std::collections::HashMap;
 use std::path::PathBuf;
-use std::sync::Arc;
 use std::time::Duration;
 
 /// Web3 function call tool
@@ -297,31 +299,13 @@ impl Web3FunctionCallTool {
             .map_err(|e| format!("Failed to encode function call: {}", e))
     }
 
-    /// Get wallet from environment
-    fn get_wallet(chain_id: u64) -> Result<LocalWallet, String> {
-        let private_key = crate::config::burner_wallet_private_key()
-            .ok_or("BURNER_WALLET_BOT_PRIVATE_KEY not set")?;
-
-        private_key
-            .parse::<LocalWallet>()
-            .map(|w| w.with_chain_id(chain_id))
-            .map_err(|e| format!("Invalid private key: {}", e))
-    }
-
-    /// Get private key from environment
-    fn get_private_key() -> Result<String, String> {
-        crate::config::burner_wallet_private_key()
-            .ok_or_else(|| "BURNER_WALLET_BOT_PRIVATE_KEY not set".to_string())
-    }
-
     /// Execute a read-only call
     async fn call_function(
         network: &str,
         to: Address,
         calldata: Vec<u8>,
     ) -> Result<Vec<u8>, String> {
-        let private_key = Self::get_private_key()?;
-        let rpc = X402EvmRpc::new(&private_key, network)?;
+        let rpc = X402EvmRpc::new(crate::wallet::require_signer()?, network)?;
 
         rpc.call(to, &calldata).await
     }
@@ -332,14 +316,15 @@ impl Web3FunctionCallTool {
         to: Address,
         calldata: Vec<u8>,
         value: U256,
-        broadcaster: Option<&Arc<EventBroadcaster>>,
-        channel_id: Option<i64>,
+        context: &ToolContext,
+        mut audit: NewTransaction,
     ) -> Result<(String, String, String), String> {
-        let private_key = Self::get_private_key()?;
-        let rpc = X402EvmRpc::new(&private_key, network)?;
+        let broadcaster = context.broadcaster.as_ref();
+        let channel_id = context.channel_id;
+        let wallet = crate::wallet::require_signer()?;
+        let rpc = X402EvmRpc::new(wallet.clone(), network)?;
         let chain_id = rpc.chain_id();
 
-        let wallet = Self::get_wallet(chain_id)?;
         let from_address = wallet.address();
         let from_str = format!("{:?}", from_address);
 
@@ -370,22 +355,31 @@ impl Web3FunctionCallTool {
             .max_priority_fee_per_gas(priority_fee)
             .chain_id(chain_id);
 
-        // Sign the transaction
+        // Sign the transaction with the active wallet
         let typed_tx: TypedTransaction = tx.into();
-        let signature = wallet
-            .sign_transaction(&typed_tx)
-            .await
-            .map_err(|e| format!("Failed to sign transaction: {}", e))?;
+        let signed_tx = wallet.sign_transaction(&typed_tx).await?;
 
-        // Serialize the signed transaction
-        let signed_tx = typed_tx.rlp_signed(&signature);
+        audit.from_address = Some(from_str.clone());
+        audit.to_address = Some(format!("{:?}", to));
+        audit.value = value.to_string();
 
         // Broadcast via x402 RPC
-        let tx_hash = rpc.send_raw_transaction(&signed_tx).await?;
+        let tx_hash = match rpc.send_raw_transaction(&signed_tx).await {
+            Ok(hash) => hash,
+            Err(e) => {
+                audit.status = TransactionStatus::Failed;
+                audit.details = Some(json!({ "error": e, "details": audit.details.take() }));
+                context.record_transaction("web3_function_call", audit).await;
+                return Err(e);
+            }
+        };
         let tx_hash_str = format!("{:?}", tx_hash);
 
         log::info!("[web3_function_call] Transaction sent: {}", tx_hash_str);
 
+        audit.tx_hash = Some(tx_hash_str.clone());
+        let audit_id = context.record_transaction("web3_function_call", audit).await;
+
         // Get explorer URL
         let explorer = if network == "mainnet" {
             "https://etherscan.io/tx"
@@ -408,10 +402,20 @@ impl Web3FunctionCallTool {
         let receipt = rpc.wait_for_receipt(tx_hash, Duration::from_secs(120)).await?;
 
         let status = if receipt.status == Some(U64::from(1)) {
-            "confirmed".to_string()
+            TransactionStatus::Confirmed
         } else {
-            "reverted".to_string()
+            TransactionStatus::Reverted
         };
+        let gas_used = receipt.gas_used.map(|g| g.to_string());
+        context
+            .update_transaction(
+                audit_id,
+                status,
+                gas_used.as_deref(),
+                receipt.block_number.map(|b| b.as_u64() as i64),
+            )
+            .await;
+        let status = status.as_str().to_string();
 
         // Emit tx.confirmed event
         if let (Some(broadcaster), Some(ch_id)) = (broadcaster, channel_id) {
@@ -508,16 +512,25 @@ impl Tool for Web3FunctionCallTool {
         self.definition.clone()
     }
 
+    fn required_scope(&self) -> Option<Scope> {
+        Some(Scope::WalletSign)
+    }
+
+    fn moves_value(&self) -> bool {
+        true
+    }
+
     async fn execute(&self, params: Value, context: &ToolContext) -> ToolResult {
-        let params: Web3FunctionCallParams = match serde_json::from_value(params) {
+        let mut params: Web3FunctionCallParams = match serde_json::from_value(params) {
             Ok(p) => p,
             Err(e) => return ToolResult::error(format!("Invalid parameters: {}", e)),
         };
 
-        // Validate network
-        if params.network != "base" && params.network != "mainnet" {
-            return ToolResult::error("Network must be 'base' or 'mainnet'");
-        }
+        // Normalize network (accepts aliases like "Base" or "ethereum")
+        params.network = match parse_network(&params.network) {
+            Ok(n) => n.as_str().to_string(),
+            Err(e) => return ToolResult::error(e),
+        };
 
         // Resolve preset or use direct params
         let (abi_name, contract_addr, function_name, call_params, value) = if let Some(ref preset_name) = params.preset {
@@ -707,13 +720,58 @@ impl Tool for Web3FunctionCallTool {
                 Err(e) => return ToolResult::error(format!("Invalid value: {} - {}", value, e)),
             };
 
+            // Value moved: the native value, plus the amount of an ERC-20 transfer
+            let network = match parse_network(&params.network) {
+                Ok(n) => n,
+                Err(e) => return ToolResult::error(e),
+            };
+            let mut amount_usd = spending::price_usd(NATIVE_ADDRESS, tx_value, network, context).await;
+            let mut description = format!(
+                "Call {}::{}() on {} ({}) with {} wei attached",
+                abi_name, function_name, contract_addr, params.network, tx_value
+            );
+            if function_name == "transfer" && call_params.len() == 2 {
+                let amount = match &call_params[1] {
+                    Value::String(s) => parse_u256(s).ok(),
+                    other => parse_u256(&other.to_string()).ok(),
+                };
+                let token_usd = match amount {
+                    Some(amount) => spending::price_usd(&contract_addr, amount, network, context).await,
+                    None => None,
+                };
+                amount_usd = amount_usd.zip(token_usd).map(|(n, t)| n + t);
+                description = format!(
+                    "Transfer {} base units of token {} to {} on {}",
+                    amount.map(|a| a.to_string()).unwrap_or_else(|| call_params[1].to_string()),
+                    contract_addr,
+                    call_params[0].as_str().unwrap_or_default(),
+                    params.network
+                );
+            }
+            if let Err(e) = spending::authorize_spend(context, "web3_function_call", amount_usd, &description).await {
+                return ToolResult::error(format!("Transaction not sent: {}", e));
+            }
+
+            let kind = if function_name == "transfer" {
+                TransactionKind::Transfer
+            } else {
+                TransactionKind::ContractCall
+            };
+            let mut audit = NewTransaction::new(kind, TransactionStatus::Pending, &params.network);
+            audit.details = Some(json!({
+                "abi": abi_name,
+                "function": function_name,
+                "preset": params.preset,
+            }));
+            audit.value_usd = amount_usd;
+
             match Self::send_transaction(
                 &params.network,
                 contract,
                 calldata,
                 tx_value,
-                context.broadcaster.as_ref(),
-                context.channel_id,
+                context,
+                audit,
             ).await {
                 Ok((from, tx_hash, status)) => {
                     let explorer = if params.network == "mainnet" {
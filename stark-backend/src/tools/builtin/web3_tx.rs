@@ -5,9 +5,12 @@
 //! All RPC calls go through defirelay.com with x402 payments.
 
 use crate::domain_types::DomainUint256;
-use crate::gateway::events::EventBroadcaster;
 use crate::gateway::protocol::GatewayEvent;
+use crate::models::{NewTransaction, Scope, TransactionKind, TransactionStatus};
+use crate::tools::builtin::token_price::NATIVE_ADDRESS;
+use crate::tools::network::{parse_network, Network};
 use crate::tools::registry::Tool;
+use crate::tools::spending;
 use crate::tools::types::{
     PropertySchema, ToolContext, ToolDefinition, ToolGroup, ToolInputSchema, ToolResult,
 };
@@ -19,25 +22,35 @@ use ethers::types::transaction::eip2718::TypedTransaction;
 use serde::Deserialize;
 use serde_json::{json, Value};
 use std::collections::HashMap;
-use std::sync::Arc;
 use std::time::Duration;
 
+/// A transaction to send; unset gas fields are estimated from the network
+pub(crate) struct TxRequest<'a> {
+    pub(crate) network: &'a str,
+    pub(crate) to: &'a str,
+    pub(crate) data: &'a str,
+    pub(crate) value: &'a str,
+    pub(crate) gas_limit: Option<U256>,
+    pub(crate) max_fee_per_gas: Option<U256>,
+    pub(crate) max_priority_fee_per_gas: Option<U256>,
+}
+
 /// Transaction result with all the details an agent needs
 #[derive(Debug)]
-struct TxResult {
-    from: String,
-    to: String,
-    tx_hash: String,
-    status: String,
-    network: String,
-    value_wei: String,
-    gas_limit: String,
-    gas_used: Option<String>,
-    max_fee_per_gas: String,
-    max_priority_fee_per_gas: String,
-    effective_gas_price: Option<String>,
-    block_number: Option<u64>,
-    explorer_url: String,
+pub(crate) struct TxResult {
+    pub(crate) from: String,
+    pub(crate) to: String,
+    pub(crate) tx_hash: String,
+    pub(crate) status: String,
+    pub(crate) network: String,
+    pub(crate) value_wei: String,
+    pub(crate) gas_limit: String,
+    pub(crate) gas_used: Option<String>,
+    pub(crate) max_fee_per_gas: String,
+    pub(crate) max_priority_fee_per_gas: String,
+    pub(crate) effective_gas_price: Option<String>,
+    pub(crate) block_number: Option<u64>,
+    pub(crate) explorer_url: String,
 }
 
 /// Web3 transaction tool
@@ -107,40 +120,23 @@ impl Web3TxTool {
         }
     }
 
-    /// Get the wallet from environment
-    fn get_wallet(chain_id: u64) -> Result<LocalWallet, String> {
-        let private_key = crate::config::burner_wallet_private_key()
-            .ok_or("BURNER_WALLET_BOT_PRIVATE_KEY not set")?;
-
-        private_key
-            .parse::<LocalWallet>()
-            .map(|w| w.with_chain_id(chain_id))
-            .map_err(|e| format!("Invalid private key: {}", e))
-    }
-
-    /// Get the private key from environment
-    fn get_private_key() -> Result<String, String> {
-        crate::config::burner_wallet_private_key()
-            .ok_or_else(|| "BURNER_WALLET_BOT_PRIVATE_KEY not set".to_string())
-    }
-
-    /// Send a transaction via x402 RPC
-    async fn send_transaction(
-        network: &str,
-        to: &str,
-        data: &str,
-        value: &str,
-        gas_limit: Option<U256>,
-        max_fee_per_gas: Option<U256>,
-        max_priority_fee_per_gas: Option<U256>,
-        broadcaster: Option<&Arc<EventBroadcaster>>,
-        channel_id: Option<i64>,
+    /// Send a transaction via x402 RPC and wait for its receipt (also used by `swap`).
+    ///
+    /// `audit` carries the kind and details for the transactions audit trail; hash,
+    /// addresses, value and outcome are filled in here.
+    pub(crate) async fn send_transaction(
+        tx: TxRequest<'_>,
+        context: &ToolContext,
+        tool_name: &str,
+        mut audit: NewTransaction,
     ) -> Result<TxResult, String> {
-        let private_key = Self::get_private_key()?;
-        let rpc = X402EvmRpc::new(&private_key, network)?;
+        let TxRequest { network, to, data, value, gas_limit, max_fee_per_gas, max_priority_fee_per_gas } = tx;
+        let broadcaster = context.broadcaster.as_ref();
+        let channel_id = context.channel_id;
+        let wallet = crate::wallet::require_signer()?;
+        let rpc = X402EvmRpc::new(wallet.clone(), network)?;
         let chain_id = rpc.chain_id();
 
-        let wallet = Self::get_wallet(chain_id)?;
         let from_address = wallet.address();
         let from_str = format!("{:?}", from_address);
 
@@ -222,22 +218,33 @@ impl Web3TxTool {
             .max_priority_fee_per_gas(priority_fee)
             .chain_id(chain_id);
 
-        // Sign the transaction locally
+        // Sign the transaction with the active wallet
         let typed_tx: TypedTransaction = tx.into();
-        let signature = wallet
-            .sign_transaction(&typed_tx)
-            .await
-            .map_err(|e| format!("Failed to sign transaction: {}", e))?;
+        let signed_tx = wallet.sign_transaction(&typed_tx).await?;
 
-        // Serialize the signed transaction
-        let signed_tx = typed_tx.rlp_signed(&signature);
+        audit.network = network.to_string();
+        audit.from_address = Some(from_str.clone());
+        audit.to_address = Some(to.to_string());
+        audit.value = tx_value.to_string();
 
         // Broadcast via x402 RPC
-        let tx_hash = rpc.send_raw_transaction(&signed_tx).await?;
+        let tx_hash = match rpc.send_raw_transaction(&signed_tx).await {
+            Ok(hash) => hash,
+            Err(e) => {
+                audit.status = TransactionStatus::Failed;
+                audit.details = Some(json!({ "error": e, "details": audit.details.take() }));
+                context.record_transaction(tool_name, audit).await;
+                return Err(e);
+            }
+        };
         let tx_hash_str = format!("{:?}", tx_hash);
 
         log::info!("[web3_tx] Transaction sent: {}", tx_hash_str);
 
+        audit.status = TransactionStatus::Pending;
+        audit.tx_hash = Some(tx_hash_str.clone());
+        let audit_id = context.record_transaction(tool_name, audit).await;
+
         // Get explorer URL for the tx
         let explorer = if network == "mainnet" {
             "https://etherscan.io/tx"
@@ -261,10 +268,16 @@ impl Web3TxTool {
         let receipt = rpc.wait_for_receipt(tx_hash, Duration::from_secs(120)).await?;
 
         let status = if receipt.status == Some(U64::from(1)) {
-            "confirmed".to_string()
+            TransactionStatus::Confirmed
         } else {
-            "reverted".to_string()
+            TransactionStatus::Reverted
         };
+        let gas_used = receipt.gas_used.map(|g| g.to_string());
+        let block_number = receipt.block_number.map(|b| b.as_u64());
+        context
+            .update_transaction(audit_id, status, gas_used.as_deref(), block_number.map(|b| b as i64))
+            .await;
+        let status = status.as_str().to_string();
 
         // Emit tx.confirmed event when the transaction is mined
         if let (Some(broadcaster), Some(ch_id)) = (broadcaster, channel_id) {
@@ -285,15 +298,47 @@ impl Web3TxTool {
             network: network.to_string(),
             value_wei: tx_value.to_string(),
             gas_limit: gas.to_string(),
-            gas_used: receipt.gas_used.map(|g| g.to_string()),
+            gas_used,
             max_fee_per_gas: max_fee.to_string(),
             max_priority_fee_per_gas: priority_fee.to_string(),
             effective_gas_price: receipt.effective_gas_price.map(|p| p.to_string()),
-            block_number: receipt.block_number.map(|b| b.as_u64()),
+            block_number,
             explorer_url,
         })
     }
 
+    /// USD a transaction spends, and a one-line description of it for approvals.
+    /// Calldata other than an ERC-20 `transfer` is opaque, so it is left unpriced.
+    async fn spend_of(tx_data: &ResolvedTxData, network: Network, context: &ToolContext) -> (Option<f64>, String) {
+        let value = parse_u256(&tx_data.value).unwrap_or_default();
+        let native_usd = spending::price_usd(NATIVE_ADDRESS, value, network, context).await;
+        let data = tx_data.data.trim_start_matches("0x");
+
+        if data.is_empty() {
+            let description = format!("Send {} to {} on {}", Self::format_eth(&value.to_string()), tx_data.to, network);
+            return (native_usd, description);
+        }
+        // transfer(address,uint256): selector, then two 32-byte words
+        if data.len() >= 136 && data[..8].eq_ignore_ascii_case("a9059cbb") {
+            let recipient = format!("0x{}", &data[32..72]);
+            if let Ok(amount) = U256::from_str_radix(&data[72..136], 16) {
+                let token_usd = spending::price_usd(&tx_data.to, amount, network, context).await;
+                let description = format!(
+                    "Transfer {} base units of token {} to {} on {}",
+                    amount, tx_data.to, recipient, network
+                );
+                return (native_usd.zip(token_usd).map(|(n, t)| n + t), description);
+            }
+        }
+        let description = format!(
+            "Contract call to {} on {} with {} attached",
+            tx_data.to,
+            network,
+            Self::format_eth(&value.to_string())
+        );
+        (None, description)
+    }
+
     /// Format wei as human-readable ETH
     fn format_eth(wei: &str) -> String {
         if let Ok(w) = wei.parse::<u128>() {
@@ -487,11 +532,19 @@ impl Tool for Web3TxTool {
         self.definition.clone()
     }
 
+    fn required_scope(&self) -> Option<Scope> {
+        Some(Scope::WalletSign)
+    }
+
+    fn moves_value(&self) -> bool {
+        true
+    }
+
     async fn execute(&self, params: Value, context: &ToolContext) -> ToolResult {
         // Debug: log raw params to see what's actually arriving
         log::info!("[web3_tx] Raw params received: {}", params);
 
-        let params: Web3TxParams = match serde_json::from_value(params.clone()) {
+        let mut params: Web3TxParams = match serde_json::from_value(params.clone()) {
             Ok(p) => p,
             Err(e) => return ToolResult::error(format!("Invalid parameters: {}", e)),
         };
@@ -512,21 +565,42 @@ impl Tool for Web3TxTool {
             params.max_fee_per_gas, params.max_priority_fee_per_gas
         );
 
-        // Validate network
-        if params.network != "base" && params.network != "mainnet" {
-            return ToolResult::error("Network must be 'base' or 'mainnet'");
+        // Normalize network (accepts aliases like "Base" or "ethereum")
+        let network = match parse_network(&params.network) {
+            Ok(n) => n,
+            Err(e) => return ToolResult::error(e),
+        };
+        params.network = network.as_str().to_string();
+
+        let (amount_usd, description) = Self::spend_of(&tx_data, network, context).await;
+        if let Err(e) = spending::authorize_spend(context, "web3_tx", amount_usd, &description).await {
+            return ToolResult::error(format!("Transaction not sent: {}", e));
         }
 
+        // Plain value sends are transfers; anything with calldata is a contract call
+        let kind = if tx_data.data.trim_start_matches("0x").is_empty() {
+            TransactionKind::Transfer
+        } else {
+            TransactionKind::ContractCall
+        };
+        let mut audit = NewTransaction::new(kind, TransactionStatus::Pending, &params.network);
+        audit.details = Some(json!({ "source": tx_data.source }));
+        audit.value_usd = amount_usd;
+
+        let tx = TxRequest {
+            network: &params.network,
+            to: &tx_data.to,
+            data: &tx_data.data,
+            value: &tx_data.value,
+            gas_limit: tx_data.gas_limit,
+            max_fee_per_gas: params.max_fee_per_gas.as_ref().map(|g| g.0),
+            max_priority_fee_per_gas: params.max_priority_fee_per_gas.as_ref().map(|g| g.0),
+        };
         match Self::send_transaction(
-            &params.network,
-            &tx_data.to,
-            &tx_data.data,
-            &tx_data.value,
-            tx_data.gas_limit,
-            params.max_fee_per_gas.as_ref().map(|g| g.0),
-            params.max_priority_fee_per_gas.as_ref().map(|g| g.0),
-            context.broadcaster.as_ref(),
-            context.channel_id,
+            tx,
+            context,
+            "web3_tx",
+            audit,
         ).await {
             Ok(result) => {
                 let status_emoji = if result.status == "confirmed" { "✅" } else { "❌" };
@@ -1,15 +1,16 @@
+use crate::tools::cache::CachePolicy;
 use crate::tools::http_retry::{is_reqwest_error_retryable, HttpRetryManager};
 use crate::tools::registry::Tool;
 use crate::tools::types::{
     PropertySchema, ToolContext, ToolDefinition, ToolGroup, ToolInputSchema, ToolResult,
 };
+use crate::tools::url_guard::validate_public_url;
+use crate::utils::truncate_chars;
 use async_trait::async_trait;
 use serde::{Deserialize, Deserializer};
 use serde_json::{json, Value};
 use std::collections::HashMap;
-use std::net::{IpAddr, ToSocketAddrs};
-use std::sync::RwLock;
-use std::time::{Duration, Instant};
+use std::time::Duration;
 
 /// Deserialize a usize from either a number or a string
 fn deserialize_usize_lenient<'de, D>(deserializer: D) -> Result<Option<usize>, D::Error>
@@ -25,58 +26,9 @@ where
     }
 }
 
-/// Cache entry for fetch results
-struct CacheEntry {
-    result: ToolResult,
-    expires_at: Instant,
-}
-
-/// Simple in-memory cache with TTL
-struct FetchCache {
-    entries: RwLock<HashMap<String, CacheEntry>>,
-    ttl: Duration,
-}
-
-impl FetchCache {
-    fn new(ttl_secs: u64) -> Self {
-        FetchCache {
-            entries: RwLock::new(HashMap::new()),
-            ttl: Duration::from_secs(ttl_secs),
-        }
-    }
-
-    fn get(&self, key: &str) -> Option<ToolResult> {
-        let entries = self.entries.read().ok()?;
-        if let Some(entry) = entries.get(key) {
-            if entry.expires_at > Instant::now() {
-                return Some(entry.result.clone());
-            }
-        }
-        None
-    }
-
-    fn set(&self, key: String, result: ToolResult) {
-        if let Ok(mut entries) = self.entries.write() {
-            // Clean expired entries occasionally
-            if entries.len() > 50 {
-                let now = Instant::now();
-                entries.retain(|_, v| v.expires_at > now);
-            }
-            entries.insert(
-                key,
-                CacheEntry {
-                    result,
-                    expires_at: Instant::now() + self.ttl,
-                },
-            );
-        }
-    }
-}
-
 /// Web fetch tool to retrieve and parse content from URLs
 pub struct WebFetchTool {
     definition: ToolDefinition,
-    cache: FetchCache,
 }
 
 impl WebFetchTool {
@@ -165,7 +117,6 @@ impl WebFetchTool {
                 },
                 group: ToolGroup::Web,
             },
-            cache: FetchCache::new(900), // 15 minute cache
         }
     }
 }
@@ -217,6 +168,16 @@ impl Tool for WebFetchTool {
         self.definition.clone()
     }
 
+    /// GET results are reused for 15 minutes; other methods may have effects
+    fn cache_policy(&self, params: &Value) -> CachePolicy {
+        let method = params.get("method").and_then(|m| m.as_str()).unwrap_or("GET");
+        if method.eq_ignore_ascii_case("GET") {
+            CachePolicy::For(Duration::from_secs(900))
+        } else {
+            CachePolicy::Never
+        }
+    }
+
     async fn execute(&self, params: Value, _context: &ToolContext) -> ToolResult {
         let params: WebFetchParams = match serde_json::from_value(params) {
             Ok(p) => p,
@@ -250,18 +211,7 @@ impl Tool for WebFetchTool {
             return ToolResult::error(e);
         }
 
-        // Build cache key (include method - don't cache POST/PUT/PATCH/DELETE)
         let method = params.method.as_deref().unwrap_or("GET").to_uppercase();
-        let should_cache = method == "GET";
-        let cache_key = format!("{}:{}:{}", params.url, extract_mode, max_chars);
-
-        // Check cache first (only for GET requests)
-        if should_cache {
-            if let Some(cached) = self.cache.get(&cache_key) {
-                log::debug!("web_fetch: returning cached result for URL '{}'", params.url);
-                return cached;
-            }
-        }
 
         let client = reqwest::Client::builder()
             .timeout(std::time::Duration::from_secs(30))
@@ -323,8 +273,9 @@ impl Tool for WebFetchTool {
         if !status.is_success() {
             // Extract the response body to include in the error message (truncate to avoid huge HTML pages)
             let body = response.text().await.unwrap_or_default();
-            let truncated_body = if body.len() > 2000 {
-                format!("{}...\n[truncated, {} total bytes]", &body[..2000], body.len())
+            let shown = truncate_chars(&body, 2000);
+            let truncated_body = if shown.len() < body.len() {
+                format!("{}...\n[truncated, {} total bytes]", shown, body.len())
             } else {
                 body
             };
@@ -366,19 +317,20 @@ impl Tool for WebFetchTool {
         };
 
         // Truncate if necessary
-        let truncated = content.len() > max_chars;
+        let shown = truncate_chars(&content, max_chars);
+        let truncated = shown.len() < content.len();
         let final_content = if truncated {
             format!(
                 "{}\n\n[Content truncated at {} characters. Original length: {} characters]",
-                &content[..max_chars],
+                shown,
                 max_chars,
-                content.len()
+                content.chars().count()
             )
         } else {
             content
         };
 
-        let result = ToolResult::success(final_content).with_metadata(json!({
+        ToolResult::success(final_content).with_metadata(json!({
             "url": params.url,
             "final_url": final_url,
             "content_type": content_type,
@@ -386,80 +338,7 @@ impl Tool for WebFetchTool {
             "truncated": truncated,
             "original_length": original_length,
             "cached": false
-        }));
-
-        // Cache successful GET results only
-        if should_cache {
-            self.cache.set(cache_key, result.clone());
-        }
-
-        result
-    }
-}
-
-/// Validate that a URL points to a public host (not private/internal)
-fn validate_public_url(url: &url::Url) -> Result<(), String> {
-    let host = url.host_str().ok_or("URL has no host")?;
-
-    // Block localhost and common internal hostnames
-    let blocked_hosts = [
-        "localhost",
-        "127.0.0.1",
-        "0.0.0.0",
-        "::1",
-        "[::1]",
-        "metadata.google.internal",
-        "metadata.google",
-        "169.254.169.254", // AWS/GCP metadata
-    ];
-
-    let host_lower = host.to_lowercase();
-    if blocked_hosts.contains(&host_lower.as_str()) {
-        return Err(format!("Access to internal host '{}' is blocked", host));
-    }
-
-    // Block .local, .internal, .localhost TLDs
-    if host_lower.ends_with(".local")
-        || host_lower.ends_with(".internal")
-        || host_lower.ends_with(".localhost")
-        || host_lower.ends_with(".lan")
-    {
-        return Err(format!("Access to internal domain '{}' is blocked", host));
-    }
-
-    // Try to resolve and check if it's a private IP
-    let port = url.port().unwrap_or(if url.scheme() == "https" { 443 } else { 80 });
-    if let Ok(addrs) = format!("{}:{}", host, port).to_socket_addrs() {
-        for addr in addrs {
-            if is_private_ip(addr.ip()) {
-                return Err(format!(
-                    "URL resolves to private IP address '{}', access blocked",
-                    addr.ip()
-                ));
-            }
-        }
-    }
-
-    Ok(())
-}
-
-/// Check if an IP address is private/internal
-fn is_private_ip(ip: IpAddr) -> bool {
-    match ip {
-        IpAddr::V4(ipv4) => {
-            ipv4.is_private()           // 10.x, 172.16-31.x, 192.168.x
-                || ipv4.is_loopback()   // 127.x
-                || ipv4.is_link_local() // 169.254.x
-                || ipv4.is_broadcast()
-                || ipv4.is_documentation()
-                || ipv4.is_unspecified()
-                // Cloud metadata IPs
-                || ipv4.octets()[0] == 169 && ipv4.octets()[1] == 254
-        }
-        IpAddr::V6(ipv6) => {
-            ipv6.is_loopback() || ipv6.is_unspecified()
-            // Note: is_unique_local() and is_unicast_link_local() are unstable
-        }
+        }))
     }
 }
 
@@ -848,14 +727,4 @@ mod tests {
         assert!(md.contains("*italic*"));
         assert!(md.contains("- Item 1"));
     }
-
-    #[test]
-    fn test_private_ip_detection() {
-        assert!(is_private_ip("127.0.0.1".parse().unwrap()));
-        assert!(is_private_ip("192.168.1.1".parse().unwrap()));
-        assert!(is_private_ip("10.0.0.1".parse().unwrap()));
-        assert!(is_private_ip("172.16.0.1".parse().unwrap()));
-        assert!(!is_private_ip("8.8.8.8".parse().unwrap()));
-        assert!(!is_private_ip("1.1.1.1".parse().unwrap()));
-    }
 }
@@ -3,11 +3,12 @@ use crate::tools::registry::Tool;
 use crate::tools::types::{
     PropertySchema, ToolContext, ToolDefinition, ToolGroup, ToolInputSchema, ToolResult,
 };
+use crate::tools::workspace_path::WorkspacePath;
 use async_trait::async_trait;
 use serde::Deserialize;
 use serde_json::{json, Value};
 use std::collections::HashMap;
-use std::path::{Path, PathBuf};
+use std::path::PathBuf;
 
 /// Write file tool - writes contents to files within a sandboxed directory
 pub struct WriteFileTool {
@@ -95,6 +96,10 @@ impl Tool for WriteFileTool {
         self.definition.clone()
     }
 
+    fn writes_workspace(&self) -> bool {
+        true
+    }
+
     async fn execute(&self, params: Value, context: &ToolContext) -> ToolResult {
         let params: WriteFileParams = match serde_json::from_value(params) {
             Ok(p) => p,
@@ -114,86 +119,44 @@ impl Tool for WriteFileTool {
         // Get journal directory
         let journal = PathBuf::from(journal_dir());
 
-        // Resolve the path - check if it starts with "journal/" to use journal dir
-        let requested_path = Path::new(&params.path);
-        let (full_path, base_dir) = if params.path.starts_with("journal/") || params.path == "journal" {
-            // Strip "journal/" prefix and use journal directory
-            let relative = params.path.strip_prefix("journal/").unwrap_or(&params.path);
-            (journal.join(relative), journal.clone())
-        } else if requested_path.is_absolute() {
-            (requested_path.to_path_buf(), workspace.clone())
-        } else {
-            (workspace.join(requested_path), workspace.clone())
-        };
+        let is_journal = params.path.starts_with("journal/") || params.path == "journal";
 
-        // Canonicalize base directory for comparison
         // For journal, create it if it doesn't exist
-        if params.path.starts_with("journal") && !base_dir.exists() {
-            if let Err(e) = tokio::fs::create_dir_all(&base_dir).await {
-                return ToolResult::error(format!("Cannot create journal directory: {}", e));
-            }
+        if is_journal
+            && !journal.exists()
+            && let Err(e) = tokio::fs::create_dir_all(&journal).await
+        {
+            return ToolResult::error(format!("Cannot create journal directory: {}", e));
         }
 
-        let canonical_base = match base_dir.canonicalize() {
-            Ok(p) => p,
-            Err(e) => {
-                return ToolResult::error(format!("Cannot resolve base directory: {}", e))
-            }
+        // Resolve the path - paths starting with "journal/" go to the journal dir.
+        // Security check: ensure path is within allowed directory (workspace or journal)
+        let resolved = if is_journal {
+            let relative = params.path.strip_prefix("journal/").unwrap_or("");
+            WorkspacePath::resolve(&journal, relative)
+        } else {
+            WorkspacePath::resolve(&workspace, &params.path)
+        };
+        let final_path = match resolved {
+            Ok(p) => p.path().to_path_buf(),
+            Err(e) => return ToolResult::error(e),
         };
 
-        // For new files, we need to check the parent directory
-        let parent = match full_path.parent() {
+        if final_path.exists() && !final_path.is_file() {
+            return ToolResult::error(format!("Path exists but is not a file: {}", params.path));
+        }
+
+        // Create parent directories if needed and allowed (only once the path is known to be inside)
+        let parent = match final_path.parent() {
             Some(p) => p.to_path_buf(),
             None => return ToolResult::error("Invalid file path: no parent directory"),
         };
-
-        // Create parent directories if needed and allowed
         if create_dirs && !parent.exists() {
             if let Err(e) = tokio::fs::create_dir_all(&parent).await {
                 return ToolResult::error(format!("Failed to create directories: {}", e));
             }
         }
 
-        // Now canonicalize the parent to verify it's within allowed directory
-        let canonical_parent = match parent.canonicalize() {
-            Ok(p) => p,
-            Err(e) => return ToolResult::error(format!("Cannot resolve parent directory: {}", e)),
-        };
-
-        // Security check: ensure parent is within allowed directory (workspace or journal)
-        if !canonical_parent.starts_with(&canonical_base) {
-            return ToolResult::error(format!(
-                "Access denied: path '{}' is outside the allowed directory",
-                params.path
-            ));
-        }
-
-        // Construct the final path
-        let file_name = match full_path.file_name() {
-            Some(n) => n,
-            None => return ToolResult::error("Invalid file path: no file name"),
-        };
-        let final_path = canonical_parent.join(file_name);
-
-        // Additional check if file exists
-        if final_path.exists() {
-            let canonical_path = match final_path.canonicalize() {
-                Ok(p) => p,
-                Err(e) => return ToolResult::error(format!("Cannot resolve file path: {}", e)),
-            };
-
-            if !canonical_path.starts_with(&canonical_base) {
-                return ToolResult::error(format!(
-                    "Access denied: path '{}' is outside the allowed directory",
-                    params.path
-                ));
-            }
-
-            if !canonical_path.is_file() {
-                return ToolResult::error(format!("Path exists but is not a file: {}", params.path));
-            }
-        }
-
         // Write the file
         let result = if append {
             use tokio::io::AsyncWriteExt;
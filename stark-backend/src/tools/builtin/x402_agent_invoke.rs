@@ -1,12 +1,19 @@
 //! x402 Agent Invoke tool for making paid requests to x402-enabled AI agents
 //!
 //! Unlike x402_fetch (preset-based), this tool works with any x402 agent endpoint.
+//! On a 402 it picks a payment option from `accepts`, signs it with the active
+//! wallet (EIP-3009 for "exact", EIP-2612 for "permit"), retries with the
+//! `X-PAYMENT` header and records the payment in `x402_payments`.
 
+use crate::models::Scope;
 use crate::tools::registry::Tool;
+use crate::tools::spending;
 use crate::tools::types::{
     PropertySchema, ToolContext, ToolDefinition, ToolGroup, ToolInputSchema, ToolResult,
 };
-use crate::x402::X402Signer;
+use crate::gateway::protocol::GatewayEvent;
+use crate::x402::{settlement_tx_hash, PaymentRequirements, X402PaymentInfo, X402Signer};
+use crate::utils::truncate_chars;
 use async_trait::async_trait;
 use reqwest::{header, Client};
 use serde::{Deserialize, Serialize};
@@ -83,10 +90,7 @@ impl X402AgentInvokeTool {
 
     /// Get or create the x402 signer
     fn get_signer(&self) -> Result<X402Signer, String> {
-        let private_key = crate::config::burner_wallet_private_key()
-            .ok_or("BURNER_WALLET_BOT_PRIVATE_KEY environment variable not set")?;
-
-        X402Signer::new(&private_key)
+        Ok(X402Signer::from_wallet(crate::wallet::require_signer()?))
     }
 }
 
@@ -210,7 +214,15 @@ impl Tool for X402AgentInvokeTool {
         self.definition.clone()
     }
 
-    async fn execute(&self, params: Value, _context: &ToolContext) -> ToolResult {
+    fn required_scope(&self) -> Option<Scope> {
+        Some(Scope::WalletSign)
+    }
+
+    fn moves_value(&self) -> bool {
+        true
+    }
+
+    async fn execute(&self, params: Value, context: &ToolContext) -> ToolResult {
         let params: X402AgentInvokeParams = match serde_json::from_value(params) {
             Ok(p) => p,
             Err(e) => return ToolResult::error(format!("Invalid parameters: {}", e)),
@@ -291,20 +303,19 @@ impl Tool for X402AgentInvokeTool {
             }
         };
 
-        // Find a matching payment option for our network
-        let payment_option = payment_info
-            .accepts
-            .iter()
-            .find(|opt| {
-                opt.network == params.network ||
-                opt.network == "base" && params.network == "base" ||
-                opt.network == "base-sepolia" && params.network == "base-sepolia"
-            })
-            .or_else(|| payment_info.accepts.first());
-
-        let payment_option = match payment_option {
+        let payment_option = match select_payment_option(&payment_info.accepts, &params.network) {
             Some(opt) => opt.clone(),
-            None => return ToolResult::error("No compatible payment option found in 402 response"),
+            None => {
+                let offered: Vec<String> = payment_info
+                    .accepts
+                    .iter()
+                    .map(|opt| format!("{} on {}", opt.scheme, opt.network))
+                    .collect();
+                return ToolResult::error(format!(
+                    "No supported payment option in 402 response (offered: {})",
+                    if offered.is_empty() { "none".to_string() } else { offered.join(", ") }
+                ));
+            }
         };
 
         log::info!(
@@ -314,6 +325,23 @@ impl Tool for X402AgentInvokeTool {
             payment_option.network
         );
 
+        let requirements = to_requirements(&payment_option, &url);
+        let quoted = X402PaymentInfo::from_requirements(&requirements);
+        let description = format!(
+            "Pay {} USDC to {} on {} for {}",
+            quoted.amount_formatted, payment_option.pay_to, payment_option.network, url
+        );
+        if let Err(e) = spending::authorize_spend(
+            context,
+            "x402_agent_invoke",
+            quoted.amount_formatted.parse().ok(),
+            &description,
+        )
+        .await
+        {
+            return ToolResult::error(format!("Payment not sent: {}", e));
+        }
+
         // Get signer
         let signer = match self.get_signer() {
             Ok(s) => s,
@@ -325,7 +353,7 @@ impl Tool for X402AgentInvokeTool {
 
         // Sign the payment using EIP-3009
         let x402_version = payment_info.x402_version;
-        let payment_payload = match sign_agent_payment(&signer, &payment_option, x402_version).await {
+        let payment_payload = match sign_agent_payment(&signer, &payment_option, &requirements, x402_version).await {
             Ok(p) => p,
             Err(e) => return ToolResult::error(format!("Failed to sign payment: {}", e)),
         };
@@ -342,7 +370,7 @@ impl Tool for X402AgentInvokeTool {
 
         log::info!("[x402_agent] Retrying request with X-PAYMENT header");
         log::info!("[x402_agent] Payment JSON: {}", payment_json);
-        log::info!("[x402_agent] Payment header (first 100 chars): {}...", &payment_header[..payment_header.len().min(100)]);
+        log::info!("[x402_agent] Payment header (first 100 chars): {}...", truncate_chars(&payment_header, 100));
 
         // Retry with payment
         let paid_response = match client
@@ -358,8 +386,16 @@ impl Tool for X402AgentInvokeTool {
         };
 
         let paid_status = paid_response.status();
+        let tx_hash = settlement_tx_hash(paid_response.headers());
         let paid_body = paid_response.text().await.unwrap_or_default();
 
+        let payment = match tx_hash {
+            Some(hash) => quoted.with_tx_hash(hash),
+            None if paid_status.is_success() => quoted.mark_confirmed(),
+            None => quoted.mark_failed(),
+        };
+        record_payment(context, &payment, &requirements.network).await;
+
         if !paid_status.is_success() {
             return ToolResult::error(format!(
                 "Payment request failed with HTTP {}: {}",
@@ -390,17 +426,29 @@ impl Tool for X402AgentInvokeTool {
                 "pay_to": payment_option.pay_to,
                 "network": payment_option.network,
                 "wallet": wallet_address,
+                "tx_hash": payment.tx_hash,
             }
         }))
     }
 }
 
-/// Sign payment using EIP-2612 (permit) or EIP-3009 (exact) based on scheme
-async fn sign_agent_payment(
-    signer: &X402Signer,
-    option: &AgentPaymentOption,
-    x402_version: u8,
-) -> Result<PaymentPayload, String> {
+/// Payment schemes `X402Signer::sign_payment` can produce
+const SUPPORTED_SCHEMES: [&str; 2] = ["exact", "permit"];
+
+/// Pick the option to pay with: a supported scheme on the requested network,
+/// else a supported scheme on any network
+fn select_payment_option<'a>(accepts: &'a [AgentPaymentOption], network: &str) -> Option<&'a AgentPaymentOption> {
+    let supported = |opt: &&AgentPaymentOption| SUPPORTED_SCHEMES.contains(&opt.scheme.as_str());
+    accepts
+        .iter()
+        .filter(supported)
+        .find(|opt| opt.network == network)
+        .or_else(|| accepts.iter().find(supported))
+}
+
+/// Payment requirements in the format the signer expects; `resource`
+/// defaults to the invoked URL
+fn to_requirements(option: &AgentPaymentOption, url: &str) -> PaymentRequirements {
     // Convert local extra to the x402 types extra
     let extra = option.extra.as_ref().map(|e| crate::x402::PaymentExtra {
         token: e.token.clone(),
@@ -411,19 +459,60 @@ async fn sign_agent_payment(
         facilitator_signer: e.facilitator_signer.clone(),
     });
 
-    // Create payment requirements in the format the signer expects
-    let requirements = crate::x402::PaymentRequirements {
+    PaymentRequirements {
         scheme: option.scheme.clone(),
         network: option.network.clone(),
         max_amount_required: option.max_amount_required.clone(),
         pay_to_address: option.pay_to.clone(),
         asset: option.asset.clone(),
         max_timeout_seconds: option.max_timeout_seconds.unwrap_or(300),
-        resource: option.resource.clone(),
+        resource: Some(option.resource.clone().unwrap_or_else(|| url.to_string())),
         description: option.description.clone(),
         extra,
-    };
+    }
+}
+
+/// Save the payment to `x402_payments` and the transactions audit trail, and tell the channel about it
+async fn record_payment(context: &ToolContext, payment: &X402PaymentInfo, network: &str) {
+    if let Some(db) = &context.database
+        && let Err(e) = db
+            .record_x402_payment(
+                context.channel_id,
+                Some("x402_agent_invoke"),
+                payment.resource.as_deref(),
+                &payment.amount,
+                &payment.amount_formatted,
+                &payment.asset,
+                &payment.pay_to,
+                payment.tx_hash.as_deref(),
+                &payment.status.to_string(),
+            )
+            .await
+    {
+        log::error!("[x402_agent] Failed to record payment: {}", e);
+    }
+    context
+        .record_transaction("x402_agent_invoke", payment.to_transaction(network))
+        .await;
+    if let (Some(broadcaster), Some(channel_id)) = (&context.broadcaster, context.channel_id) {
+        broadcaster.broadcast(GatewayEvent::x402_payment(
+            channel_id,
+            &payment.amount,
+            &payment.amount_formatted,
+            &payment.asset,
+            &payment.pay_to,
+            payment.resource.as_deref(),
+        ));
+    }
+}
 
+/// Sign payment using EIP-2612 (permit) or EIP-3009 (exact) based on scheme
+async fn sign_agent_payment(
+    signer: &X402Signer,
+    option: &AgentPaymentOption,
+    requirements: &PaymentRequirements,
+    x402_version: u8,
+) -> Result<PaymentPayload, String> {
     log::info!(
         "[x402_agent_invoke] Signing {} payment for {} on {}",
         option.scheme,
@@ -432,7 +521,7 @@ async fn sign_agent_payment(
     );
 
     // Use the existing signer to create the payment
-    let signed = signer.sign_payment(&requirements).await?;
+    let signed = signer.sign_payment(requirements).await?;
 
     // Convert authorization based on scheme type
     let authorization = match signed.payload.authorization {
@@ -480,3 +569,75 @@ fn format_usdc(raw: &str) -> String {
         format!("{} units", raw)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PAYMENT_REQUIRED: &str = r#"{
+        "x402Version": 1,
+        "error": "X-PAYMENT header is required",
+        "accepts": [
+            {
+                "scheme": "upto",
+                "network": "base",
+                "maxAmountRequired": "5000",
+                "payTo": "0x1111111111111111111111111111111111111111",
+                "asset": "0x833589fCD6eDb6E08f4c7C32D4f71b54bdA02913"
+            },
+            {
+                "scheme": "exact",
+                "network": "base-sepolia",
+                "maxAmountRequired": "1000",
+                "payTo": "0x2222222222222222222222222222222222222222",
+                "asset": "0x036CbD53842c5426634e7929541eC2318f3dCF7e"
+            },
+            {
+                "scheme": "exact",
+                "network": "base",
+                "maxAmountRequired": "1500",
+                "payTo": "0x3333333333333333333333333333333333333333",
+                "asset": "0x833589fCD6eDb6E08f4c7C32D4f71b54bdA02913",
+                "maxTimeoutSeconds": 60,
+                "extra": { "name": "USD Coin", "version": "2" }
+            }
+        ]
+    }"#;
+
+    #[test]
+    fn test_select_payment_option() {
+        let response: Agent402Response = serde_json::from_str(PAYMENT_REQUIRED).unwrap();
+        assert_eq!(response.x402_version, 1);
+
+        // Unsupported schemes are skipped even on the requested network
+        let option = select_payment_option(&response.accepts, "base").unwrap();
+        assert_eq!(option.max_amount_required, "1500");
+        let option = select_payment_option(&response.accepts, "base-sepolia").unwrap();
+        assert_eq!(option.max_amount_required, "1000");
+        // No option on the requested network: fall back to any supported one
+        let option = select_payment_option(&response.accepts, "polygon").unwrap();
+        assert_eq!(option.network, "base-sepolia");
+
+        assert!(select_payment_option(&response.accepts[..1], "base").is_none());
+    }
+
+    #[test]
+    fn test_to_requirements() {
+        let response: Agent402Response = serde_json::from_str(PAYMENT_REQUIRED).unwrap();
+        let url = "https://agent.example/entrypoints/joke/invoke";
+
+        let requirements = to_requirements(&response.accepts[2], url);
+        assert_eq!(requirements.pay_to_address, "0x3333333333333333333333333333333333333333");
+        assert_eq!(requirements.max_timeout_seconds, 60);
+        assert_eq!(requirements.resource.as_deref(), Some(url));
+        assert_eq!(requirements.extra.unwrap().name.as_deref(), Some("USD Coin"));
+
+        let requirements = to_requirements(&response.accepts[1], url);
+        assert_eq!(requirements.max_timeout_seconds, 300);
+        assert!(requirements.extra.is_none());
+
+        let payment = X402PaymentInfo::from_requirements(&requirements);
+        assert_eq!(payment.amount_formatted, "0.001");
+        assert_eq!(format_usdc(&payment.amount), "0.001 USDC");
+    }
+}
@@ -2,7 +2,9 @@
 //!
 //! Uses presets to build URLs from register values, preventing hallucination.
 
+use crate::models::Scope;
 use crate::tools::http_retry::HttpRetryManager;
+use crate::tools::network::parse_network;
 use crate::tools::presets::{get_chain_id, get_fetch_preset, get_network_name, list_fetch_presets};
 use crate::tools::registry::Tool;
 use crate::tools::types::{
@@ -72,10 +74,7 @@ impl X402FetchTool {
 
     /// Get or create the x402 client
     fn get_client(&self) -> Result<X402Client, String> {
-        let private_key = crate::config::burner_wallet_private_key()
-            .ok_or("BURNER_WALLET_BOT_PRIVATE_KEY environment variable not set")?;
-
-        X402Client::new(&private_key)
+        X402Client::with_signer(crate::wallet::require_signer()?)
     }
 
     /// Apply a simple jq-like filter to extract fields from JSON
@@ -199,12 +198,22 @@ impl Tool for X402FetchTool {
         self.definition.clone()
     }
 
+    fn required_scope(&self) -> Option<Scope> {
+        Some(Scope::WalletSign)
+    }
+
     async fn execute(&self, params: Value, context: &ToolContext) -> ToolResult {
-        let params: X402FetchParams = match serde_json::from_value(params) {
+        let mut params: X402FetchParams = match serde_json::from_value(params) {
             Ok(p) => p,
             Err(e) => return ToolResult::error(format!("Invalid parameters: {}", e)),
         };
 
+        // Normalize network (accepts aliases like "Base" or "ethereum")
+        params.network = match parse_network(&params.network) {
+            Ok(n) => n.as_str().to_string(),
+            Err(e) => return ToolResult::error(e),
+        };
+
         // Get preset configuration
         let preset = match get_fetch_preset(&params.preset) {
             Some(p) => p,
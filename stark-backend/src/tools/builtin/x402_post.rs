@@ -6,11 +6,13 @@
 //! For x402book.com endpoints, automatically injects the X402BOOK_TOKEN as Bearer auth.
 
 use crate::controllers::api_keys::ApiKeyId;
+use crate::models::{NewTransaction, Scope, TransactionKind, TransactionStatus};
 use crate::tools::registry::Tool;
+use crate::tools::spending;
 use crate::tools::types::{
     PropertySchema, ToolContext, ToolDefinition, ToolGroup, ToolInputSchema, ToolResult,
 };
-use crate::x402::X402Signer;
+use crate::x402::{settlement_tx_hash, X402Signer};
 use async_trait::async_trait;
 use reqwest::{header, Client};
 use serde::{Deserialize, Serialize};
@@ -94,10 +96,7 @@ impl X402PostTool {
 
     /// Get or create the x402 signer
     fn get_signer(&self) -> Result<X402Signer, String> {
-        let private_key = crate::config::burner_wallet_private_key()
-            .ok_or("BURNER_WALLET_BOT_PRIVATE_KEY environment variable not set")?;
-
-        X402Signer::new(&private_key)
+        Ok(X402Signer::from_wallet(crate::wallet::require_signer()?))
     }
 
     /// Get an API credential from context, with env var fallback (like Twitter pattern)
@@ -258,6 +257,14 @@ impl Tool for X402PostTool {
         self.definition.clone()
     }
 
+    fn required_scope(&self) -> Option<Scope> {
+        Some(Scope::WalletSign)
+    }
+
+    fn moves_value(&self) -> bool {
+        true
+    }
+
     async fn execute(&self, params: Value, context: &ToolContext) -> ToolResult {
         let params: X402PostParams = match serde_json::from_value(params) {
             Ok(p) => p,
@@ -365,6 +372,16 @@ impl Tool for X402PostTool {
             payment_option.network
         );
 
+        let amount_formatted = format_usdc(&payment_option.max_amount_required);
+        let amount_usd: Option<f64> = amount_formatted.parse().ok();
+        let description = format!(
+            "Pay {} USDC to {} on {} for {}",
+            amount_formatted, payment_option.pay_to, payment_option.network, params.url
+        );
+        if let Err(e) = spending::authorize_spend(context, "x402_post", amount_usd, &description).await {
+            return ToolResult::error(format!("Payment not sent: {}", e));
+        }
+
         // Get signer
         let signer = match self.get_signer() {
             Ok(s) => s,
@@ -411,8 +428,26 @@ impl Tool for X402PostTool {
         };
 
         let paid_status = paid_response.status();
+        let tx_hash = settlement_tx_hash(paid_response.headers());
         let paid_body = paid_response.text().await.unwrap_or_default();
 
+        let status = if paid_status.is_success() {
+            TransactionStatus::Confirmed
+        } else {
+            TransactionStatus::Failed
+        };
+        let mut audit = NewTransaction::new(TransactionKind::X402Payment, status, &payment_option.network);
+        audit.tx_hash = tx_hash;
+        audit.to_address = Some(payment_option.pay_to.clone());
+        audit.value = payment_option.max_amount_required.clone();
+        audit.value_usd = amount_usd;
+        audit.details = Some(json!({
+            "asset": payment_option.asset,
+            "amount_formatted": amount_formatted,
+            "resource": params.url,
+        }));
+        context.record_transaction("x402_post", audit).await;
+
         if !paid_status.is_success() {
             return ToolResult::error(format!(
                 "Payment request failed with HTTP {}: {}",
@@ -422,8 +457,6 @@ impl Tool for X402PostTool {
 
         log::info!("[x402_post] Success! Status: {}", paid_status);
 
-        let amount_formatted = format_usdc(&payment_option.max_amount_required);
-
         let result_content = if let Ok(json_val) = serde_json::from_str::<Value>(&paid_body) {
             serde_json::to_string_pretty(&json_val).unwrap_or(paid_body.clone())
         } else {
@@ -3,7 +3,9 @@
 //! Uses presets to build RPC params from register values, preventing hallucination.
 //! Supports configurable RPC endpoints via bot settings.
 
+use crate::models::Scope;
 use crate::tools::http_retry::HttpRetryManager;
+use crate::tools::network::parse_network;
 use crate::tools::presets::{get_rpc_preset, list_rpc_presets};
 use crate::tools::registry::Tool;
 use crate::tools::rpc_config;
@@ -100,10 +102,7 @@ impl X402RpcTool {
 
     /// Get or create the x402 client
     async fn get_client(&self) -> Result<X402Client, String> {
-        let private_key = crate::config::burner_wallet_private_key()
-            .ok_or("BURNER_WALLET_BOT_PRIVATE_KEY environment variable not set")?;
-
-        X402Client::new(&private_key)
+        X402Client::with_signer(crate::wallet::require_signer()?)
     }
 }
 
@@ -130,16 +129,21 @@ impl Tool for X402RpcTool {
         self.definition.clone()
     }
 
+    fn required_scope(&self) -> Option<Scope> {
+        Some(Scope::WalletSign)
+    }
+
     async fn execute(&self, params: Value, context: &ToolContext) -> ToolResult {
-        let params: X402RpcParams = match serde_json::from_value(params) {
+        let mut params: X402RpcParams = match serde_json::from_value(params) {
             Ok(p) => p,
             Err(e) => return ToolResult::error(format!("Invalid parameters: {}", e)),
         };
 
-        // Validate network
-        if params.network != "base" && params.network != "mainnet" {
-            return ToolResult::error("Network must be 'base' or 'mainnet'");
-        }
+        // Normalize network (accepts aliases like "Base" or "ethereum")
+        params.network = match parse_network(&params.network) {
+            Ok(n) => n.as_str().to_string(),
+            Err(e) => return ToolResult::error(e),
+        };
 
         // Get preset configuration
         let preset = match get_rpc_preset(&params.preset) {
@@ -0,0 +1,211 @@
+//! Reuse of results of idempotent tool calls
+//!
+//! Agents often repeat a call they already made (the same `glob`, the same
+//! `token_lookup`) a few iterations later. Tools opt in through
+//! [`Tool::cache_policy`](crate::tools::Tool::cache_policy); their successful
+//! results are kept per job (or chat session) under the tool name and the
+//! normalized arguments. Results that depend on the workspace are dropped as
+//! soon as a tool that may change the workspace runs in the same job.
+
+use crate::tools::types::{ToolContext, ToolResult};
+use serde_json::{json, Map, Value};
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// Most results kept across all jobs; the oldest go first
+const MAX_ENTRIES: usize = 1000;
+
+/// How long a tool's results may be reused
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CachePolicy {
+    /// Always run the tool
+    Never,
+    /// Reuse for this long; the data lives outside the workspace
+    For(Duration),
+    /// Reuse for this long, unless the workspace may have changed since
+    UntilWorkspaceChanges(Duration),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+struct CacheKey {
+    scope: String,
+    tool: String,
+    arguments: String,
+}
+
+struct CacheEntry {
+    result: ToolResult,
+    stored_at: Instant,
+    expires_at: Instant,
+    workspace: bool,
+}
+
+/// Results of cacheable tool calls, keyed by job, tool and arguments
+#[derive(Default)]
+pub struct ToolCache {
+    entries: Mutex<HashMap<CacheKey, CacheEntry>>,
+}
+
+impl ToolCache {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// A stored result of this call, marked as cached
+    pub fn get(&self, tool: &str, params: &Value, context: &ToolContext) -> Option<ToolResult> {
+        let key = cache_key(tool, params, context)?;
+        let mut entries = self.entries.lock().unwrap();
+        let entry = entries.get(&key)?;
+        if entry.expires_at <= Instant::now() {
+            entries.remove(&key);
+            return None;
+        }
+        let age = entry.stored_at.elapsed().as_secs();
+        let mut result = entry.result.clone();
+        let mut metadata = match result.metadata.take() {
+            Some(Value::Object(map)) => map,
+            Some(other) => Map::from_iter([("result".to_string(), other)]),
+            None => Map::new(),
+        };
+        metadata.insert("cached".to_string(), json!(true));
+        metadata.insert("cached_age_secs".to_string(), json!(age));
+        result.metadata = Some(Value::Object(metadata));
+        Some(result)
+    }
+
+    /// Keep a successful result of a call made under `policy`
+    pub fn put(&self, tool: &str, params: &Value, context: &ToolContext, policy: CachePolicy, result: &ToolResult) {
+        let (ttl, workspace) = match policy {
+            CachePolicy::Never => return,
+            CachePolicy::For(ttl) => (ttl, false),
+            CachePolicy::UntilWorkspaceChanges(ttl) => (ttl, true),
+        };
+        if !result.success {
+            return;
+        }
+        let Some(key) = cache_key(tool, params, context) else {
+            return;
+        };
+
+        let now = Instant::now();
+        let mut entries = self.entries.lock().unwrap();
+        if entries.len() >= MAX_ENTRIES {
+            entries.retain(|_, entry| entry.expires_at > now);
+        }
+        if entries.len() >= MAX_ENTRIES
+            && let Some(oldest) = entries.iter().min_by_key(|(_, e)| e.stored_at).map(|(k, _)| k.clone())
+        {
+            entries.remove(&oldest);
+        }
+        entries.insert(
+            key,
+            CacheEntry {
+                result: result.clone(),
+                stored_at: now,
+                expires_at: now + ttl,
+                workspace,
+            },
+        );
+    }
+
+    /// Drop the workspace-dependent results of the job `context` runs for.
+    /// Returns how many were dropped.
+    pub fn invalidate_workspace(&self, context: &ToolContext) -> usize {
+        let Some(scope) = cache_scope(context) else {
+            return 0;
+        };
+        let mut entries = self.entries.lock().unwrap();
+        let before = entries.len();
+        entries.retain(|key, entry| !(entry.workspace && key.scope == scope));
+        before - entries.len()
+    }
+}
+
+/// Job, or failing that chat session, that results are shared within.
+/// Calls outside either aren't cached.
+fn cache_scope(context: &ToolContext) -> Option<String> {
+    match (&context.job_id, context.session_id) {
+        (Some(job_id), _) => Some(format!("job:{}", job_id)),
+        (None, Some(session_id)) => Some(format!("session:{}", session_id)),
+        (None, None) => None,
+    }
+}
+
+fn cache_key(tool: &str, params: &Value, context: &ToolContext) -> Option<CacheKey> {
+    Some(CacheKey {
+        scope: cache_scope(context)?,
+        tool: tool.to_string(),
+        arguments: normalize_arguments(params).to_string(),
+    })
+}
+
+/// Arguments with object keys sorted and null fields dropped, so calls that
+/// only differ in key order or explicit nulls share an entry
+pub fn normalize_arguments(params: &Value) -> Value {
+    match params {
+        Value::Object(map) => {
+            let mut keys: Vec<&String> = map.keys().filter(|k| !map[*k].is_null()).collect();
+            keys.sort();
+            Value::Object(keys.into_iter().map(|k| (k.clone(), normalize_arguments(&map[k]))).collect())
+        }
+        Value::Array(items) => Value::Array(items.iter().map(normalize_arguments).collect()),
+        other => other.clone(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn job(id: &str) -> ToolContext {
+        ToolContext { job_id: Some(id.to_string()), ..Default::default() }
+    }
+
+    #[test]
+    fn test_normalized_arguments_share_an_entry() {
+        let cache = ToolCache::new();
+        let minute = CachePolicy::For(Duration::from_secs(60));
+        let first = json!({ "pattern": "**/*.rs", "path": "src", "limit": null });
+        cache.put("glob", &first, &job("a"), minute, &ToolResult::success("src/main.rs"));
+
+        let reordered = json!({ "path": "src", "pattern": "**/*.rs" });
+        let hit = cache.get("glob", &reordered, &job("a")).unwrap();
+        assert_eq!(hit.content, "src/main.rs");
+        assert_eq!(hit.metadata.unwrap()["cached"], true);
+
+        // Other jobs, tools and arguments miss, as do calls outside a job or session
+        assert!(cache.get("glob", &reordered, &job("b")).is_none());
+        assert!(cache.get("grep", &reordered, &job("a")).is_none());
+        assert!(cache.get("glob", &json!({ "pattern": "*.md" }), &job("a")).is_none());
+        assert!(cache.get("glob", &reordered, &ToolContext::default()).is_none());
+    }
+
+    #[test]
+    fn test_errors_expired_and_uncached_policies_are_not_kept() {
+        let cache = ToolCache::new();
+        let args = json!({ "url": "https://example.com" });
+        cache.put("web_fetch", &args, &job("a"), CachePolicy::For(Duration::from_secs(60)), &ToolResult::error("timeout"));
+        cache.put("web_fetch", &args, &job("a"), CachePolicy::Never, &ToolResult::success("page"));
+        assert!(cache.entries.lock().unwrap().is_empty());
+
+        cache.put("web_fetch", &args, &job("a"), CachePolicy::For(Duration::ZERO), &ToolResult::success("page"));
+        assert!(cache.get("web_fetch", &args, &job("a")).is_none());
+        assert!(cache.entries.lock().unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_workspace_change_drops_only_that_jobs_workspace_results() {
+        let cache = ToolCache::new();
+        let minute = Duration::from_secs(60);
+        let args = json!({ "pattern": "*.rs" });
+        cache.put("glob", &args, &job("a"), CachePolicy::UntilWorkspaceChanges(minute), &ToolResult::success("a.rs"));
+        cache.put("glob", &args, &job("b"), CachePolicy::UntilWorkspaceChanges(minute), &ToolResult::success("b.rs"));
+        cache.put("token_lookup", &args, &job("a"), CachePolicy::For(minute), &ToolResult::success("USDC"));
+
+        assert_eq!(cache.invalidate_workspace(&job("a")), 1);
+        assert!(cache.get("glob", &args, &job("a")).is_none());
+        assert!(cache.get("glob", &args, &job("b")).is_some());
+        assert!(cache.get("token_lookup", &args, &job("a")).is_some());
+    }
+}
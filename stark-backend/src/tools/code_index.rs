@@ -0,0 +1,317 @@
+//! Semantic index of a workspace's code for the index_codebase and
+//! code_search tools
+//!
+//! Source files are split into overlapping windows of lines, and each window
+//! is embedded and stored in the `embeddings` table, in one collection per
+//! workspace. Every chunk keeps the hash of its file, so indexing again only
+//! embeds files that changed and drops the chunks of files that are gone.
+
+use crate::db::Database;
+use crate::memory::embeddings::EmbeddingProvider;
+use crate::tools::search::{self, WalkOptions};
+use serde_json::{json, Value};
+use sha2::{Digest, Sha256};
+use std::collections::{HashMap, HashSet};
+use std::path::Path;
+
+/// Lines per chunk
+pub const CHUNK_LINES: usize = 60;
+/// Lines shared by consecutive chunks, so code on a boundary is in one whole
+pub const CHUNK_OVERLAP: usize = 10;
+/// Most chunks embedded by one index run; the remaining files wait for the next
+pub const MAX_CHUNKS_PER_RUN: usize = 2000;
+/// Larger files are skipped: they are usually generated or vendored
+const MAX_FILE_BYTES: u64 = 256 * 1024;
+
+/// Extensions of the files that are indexed
+const SOURCE_EXTENSIONS: &[&str] = &[
+    "rs", "py", "js", "jsx", "ts", "tsx", "mjs", "go", "java", "kt", "scala", "c", "h", "cc", "cpp", "hpp",
+    "cs", "rb", "php", "swift", "sol", "sh", "sql", "lua", "ex", "exs", "hs", "ml", "vue", "svelte", "md",
+    "toml", "yaml", "yml",
+];
+
+/// Collection holding the index of the workspace at `root`
+pub fn collection(root: &Path) -> String {
+    format!("code:{}", root.display())
+}
+
+/// Lines `start_line..=end_line` (1-based) of a file
+#[derive(Debug, Clone, PartialEq)]
+pub struct CodeChunk {
+    pub path: String,
+    pub start_line: usize,
+    pub end_line: usize,
+    pub text: String,
+}
+
+impl CodeChunk {
+    /// What is embedded: the path too, so file and directory names count
+    fn embedding_text(&self) -> String {
+        format!("{}\n{}", self.path, self.text)
+    }
+}
+
+/// Split a file into chunks of [`CHUNK_LINES`] lines overlapping by
+/// [`CHUNK_OVERLAP`]. Chunks with nothing but whitespace are left out.
+pub fn chunk_file(path: &str, content: &str) -> Vec<CodeChunk> {
+    let lines: Vec<&str> = content.lines().collect();
+    let mut chunks = Vec::new();
+    let mut start = 0;
+    while start < lines.len() {
+        let end = (start + CHUNK_LINES).min(lines.len());
+        let window = &lines[start..end];
+        if window.iter().any(|line| !line.trim().is_empty()) {
+            chunks.push(CodeChunk {
+                path: path.to_string(),
+                start_line: start + 1,
+                end_line: end,
+                text: window.join("\n"),
+            });
+        }
+        if end == lines.len() {
+            break;
+        }
+        start = end - CHUNK_OVERLAP;
+    }
+    chunks
+}
+
+/// A file to index, relative to the workspace root
+#[derive(Debug, Clone)]
+pub struct SourceFile {
+    pub path: String,
+    pub hash: String,
+    pub content: String,
+}
+
+fn is_source_file(path: &Path, size: u64) -> bool {
+    size > 0
+        && size <= MAX_FILE_BYTES
+        && path
+            .extension()
+            .and_then(|e| e.to_str())
+            .is_some_and(|e| SOURCE_EXTENSIONS.contains(&e.to_ascii_lowercase().as_str()))
+}
+
+/// Source files under `base`, skipping gitignored, hidden, large and non-UTF-8 ones
+pub fn source_files(root: &Path, base: &Path) -> Result<Vec<SourceFile>, String> {
+    let hits = search::find_files(root, base, "**", WalkOptions::default())?;
+    Ok(hits
+        .into_iter()
+        .filter(|hit| is_source_file(&hit.path, hit.size))
+        .filter_map(|hit| {
+            let content = std::fs::read_to_string(&hit.path).ok()?;
+            let hash = hex::encode(Sha256::digest(content.as_bytes()));
+            Some(SourceFile { path: hit.relative, hash, content })
+        })
+        .collect())
+}
+
+/// What an index run did
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct IndexStats {
+    /// Source files found
+    pub files: usize,
+    /// Files (re-)embedded in this run
+    pub embedded_files: usize,
+    pub chunks: usize,
+    pub unchanged_files: usize,
+    /// Files whose chunks were dropped because they are gone
+    pub removed_files: usize,
+    /// Changed files left for the next run because of [`MAX_CHUNKS_PER_RUN`]
+    pub deferred_files: usize,
+}
+
+/// Stored chunks of one indexed file
+#[derive(Default)]
+struct IndexedFile {
+    hash: String,
+    ids: Vec<i64>,
+}
+
+/// Bring the index of the files under `base` up to date. `prefix` is `base`
+/// relative to the workspace root ("" for the root itself); only files under
+/// it are added or dropped. With `force`, unchanged files are embedded again.
+pub async fn index(
+    db: &Database,
+    provider: &dyn EmbeddingProvider,
+    collection: &str,
+    files: Vec<SourceFile>,
+    prefix: &str,
+    force: bool,
+    batch_size: usize,
+) -> Result<IndexStats, String> {
+    let in_scope = |path: &str| prefix.is_empty() || path == prefix || path.starts_with(&format!("{}/", prefix));
+    let mut indexed: HashMap<String, IndexedFile> = HashMap::new();
+    for stored in db.list_embeddings(collection).await.map_err(|e| e.to_string())? {
+        let meta = stored.metadata.as_ref();
+        let (Some(path), Some(hash)) = (meta_str(meta, "path"), meta_str(meta, "hash")) else {
+            continue;
+        };
+        if in_scope(path) {
+            let file = indexed.entry(path.to_string()).or_default();
+            file.hash = hash.to_string();
+            file.ids.push(stored.id);
+        }
+    }
+
+    let mut stats = IndexStats { files: files.len(), ..Default::default() };
+    let found: HashSet<&str> = files.iter().map(|f| f.path.as_str()).collect();
+    let mut stale: Vec<i64> = Vec::new();
+    for (path, file) in &indexed {
+        if !found.contains(path.as_str()) {
+            stats.removed_files += 1;
+            stale.extend(&file.ids);
+        }
+    }
+
+    let mut pending: Vec<(CodeChunk, Value)> = Vec::new();
+    for file in &files {
+        let previous = indexed.get(&file.path);
+        if !force && previous.is_some_and(|p| p.hash == file.hash) {
+            stats.unchanged_files += 1;
+            continue;
+        }
+        let chunks = chunk_file(&file.path, &file.content);
+        if pending.len() + chunks.len() > MAX_CHUNKS_PER_RUN {
+            stats.deferred_files += 1;
+            continue;
+        }
+        if let Some(previous) = previous {
+            stale.extend(&previous.ids);
+        }
+        stats.embedded_files += 1;
+        pending.extend(chunks.into_iter().map(|chunk| {
+            let metadata = json!({
+                "path": chunk.path,
+                "start_line": chunk.start_line,
+                "end_line": chunk.end_line,
+                "hash": file.hash,
+            });
+            (chunk, metadata)
+        }));
+    }
+
+    // Embed everything before touching the index, so a failed request leaves it as it was
+    let mut embeddings = Vec::with_capacity(pending.len());
+    for batch in pending.chunks(batch_size.max(1)) {
+        let texts: Vec<String> = batch.iter().map(|(chunk, _)| chunk.embedding_text()).collect();
+        let refs: Vec<&str> = texts.iter().map(String::as_str).collect();
+        let batch_embeddings = provider.embed_batch(&refs).await?;
+        if batch_embeddings.len() != batch.len() {
+            return Err(format!("Expected {} embeddings, got {}", batch.len(), batch_embeddings.len()));
+        }
+        embeddings.extend(batch_embeddings);
+    }
+
+    db.delete_embeddings(&stale).await.map_err(|e| e.to_string())?;
+    for ((chunk, metadata), embedding) in pending.iter().zip(&embeddings) {
+        let external_id = format!("{}#L{}", chunk.path, chunk.start_line);
+        db.store_embedding(collection, Some(&external_id), &chunk.text, Some(metadata), embedding)
+            .await
+            .map_err(|e| e.to_string())?;
+    }
+    stats.chunks = pending.len();
+    Ok(stats)
+}
+
+fn meta_str<'a>(metadata: Option<&'a Value>, key: &str) -> Option<&'a str> {
+    metadata?.get(key)?.as_str()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::memory::embeddings::Embedding;
+    use async_trait::async_trait;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    /// Embeds texts by their length, counting how many it was asked for
+    #[derive(Default)]
+    struct CountingProvider {
+        embedded: AtomicUsize,
+    }
+
+    #[async_trait]
+    impl EmbeddingProvider for CountingProvider {
+        async fn embed(&self, text: &str) -> Result<Embedding, String> {
+            Ok(self.embed_batch(&[text]).await?.remove(0))
+        }
+
+        async fn embed_batch(&self, texts: &[&str]) -> Result<Vec<Embedding>, String> {
+            self.embedded.fetch_add(texts.len(), Ordering::SeqCst);
+            Ok(texts
+                .iter()
+                .map(|t| Embedding { vector: vec![t.len() as f32, 1.0], model: "test".to_string(), dimensions: 2 })
+                .collect())
+        }
+
+        fn model_name(&self) -> &str {
+            "test"
+        }
+
+        fn dimensions(&self) -> usize {
+            2
+        }
+    }
+
+    fn source(path: &str, content: &str) -> SourceFile {
+        SourceFile {
+            path: path.to_string(),
+            hash: hex::encode(Sha256::digest(content.as_bytes())),
+            content: content.to_string(),
+        }
+    }
+
+    #[test]
+    fn test_chunks_overlap_and_skip_blank_windows() {
+        let content: String = (1..=130).map(|i| format!("line {}\n", i)).collect();
+        let chunks = chunk_file("src/lib.rs", &content);
+        let ranges: Vec<(usize, usize)> = chunks.iter().map(|c| (c.start_line, c.end_line)).collect();
+        assert_eq!(ranges, vec![(1, 60), (51, 110), (101, 130)]);
+        assert!(chunks[1].text.starts_with("line 51\n"));
+        assert!(chunks[2].text.ends_with("line 130"));
+
+        assert!(chunk_file("empty.rs", "\n  \n\n").is_empty());
+    }
+
+    #[test]
+    fn test_source_files_skip_ignored_and_unknown_types() {
+        let dir = tempfile::TempDir::new().unwrap();
+        std::fs::create_dir_all(dir.path().join("src")).unwrap();
+        std::fs::write(dir.path().join("src/main.rs"), "fn main() {}").unwrap();
+        std::fs::write(dir.path().join("logo.png"), [0u8, 1, 2]).unwrap();
+        std::fs::write(dir.path().join(".gitignore"), "target/\n").unwrap();
+        std::fs::create_dir_all(dir.path().join("target")).unwrap();
+        std::fs::write(dir.path().join("target/gen.rs"), "fn gen() {}").unwrap();
+
+        let files = source_files(dir.path(), dir.path()).unwrap();
+        let paths: Vec<&str> = files.iter().map(|f| f.path.as_str()).collect();
+        assert_eq!(paths, vec!["src/main.rs"]);
+    }
+
+    #[tokio::test]
+    async fn test_reindex_only_embeds_changed_files() {
+        let db = Database::new(":memory:").unwrap();
+        let provider = CountingProvider::default();
+        let files = vec![source("src/a.rs", "fn a() {}"), source("src/b.rs", "fn b() {}"), source("README.md", "hi")];
+
+        let stats = index(&db, &provider, "code:/ws", files, "", false, 2).await.unwrap();
+        assert_eq!((stats.files, stats.embedded_files, stats.chunks), (3, 3, 3));
+
+        // a.rs changed, b.rs is gone; README.md is outside the re-indexed directory
+        let files = vec![source("src/a.rs", "fn a() { todo!() }")];
+        let stats = index(&db, &provider, "code:/ws", files, "src", false, 2).await.unwrap();
+        assert_eq!(stats, IndexStats { files: 1, embedded_files: 1, chunks: 1, removed_files: 1, ..Default::default() });
+        assert_eq!(provider.embedded.load(Ordering::SeqCst), 4);
+
+        let stored = db.list_embeddings("code:/ws").await.unwrap();
+        let mut ids: Vec<&str> = stored.iter().filter_map(|s| s.external_id.as_deref()).collect();
+        ids.sort();
+        assert_eq!(ids, vec!["README.md#L1", "src/a.rs#L1"]);
+
+        let files = vec![source("src/a.rs", "fn a() { todo!() }"), source("README.md", "hi")];
+        let stats = index(&db, &provider, "code:/ws", files, "", false, 2).await.unwrap();
+        assert_eq!((stats.unchanged_files, stats.chunks), (2, 0));
+    }
+}
@@ -0,0 +1,255 @@
+//! Structured view of `git diff` output for the git tool
+//!
+//! Parses unified diff text into per-file stats and hunks. Hunk text is capped
+//! per hunk and across the whole diff so a large change can't flood the
+//! context, but insertions and deletions are always counted in full.
+
+use serde::Serialize;
+
+/// Longest hunk text kept, in characters
+pub const MAX_HUNK_CHARS: usize = 4000;
+/// Most hunk text kept across the whole diff, in characters
+pub const MAX_DIFF_CHARS: usize = 30000;
+
+/// A whole diff: totals plus one entry per changed file
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct DiffSummary {
+    pub files_changed: usize,
+    pub insertions: usize,
+    pub deletions: usize,
+    pub files: Vec<FileDiff>,
+    /// Whether any hunk text was cut or left out
+    pub truncated: bool,
+}
+
+/// One changed file
+#[derive(Debug, Clone, Serialize)]
+pub struct FileDiff {
+    pub path: String,
+    /// Previous path of a renamed file
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub old_path: Option<String>,
+    /// added, deleted, renamed or modified
+    pub status: &'static str,
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub binary: bool,
+    pub insertions: usize,
+    pub deletions: usize,
+    pub hunks: Vec<Hunk>,
+    /// Hunks left out because the diff hit [`MAX_DIFF_CHARS`]
+    #[serde(skip_serializing_if = "is_zero")]
+    pub omitted_hunks: usize,
+}
+
+/// One `@@` hunk of a file
+#[derive(Debug, Clone, Serialize)]
+pub struct Hunk {
+    /// The `@@ -a,b +c,d @@` line
+    pub header: String,
+    pub text: String,
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub truncated: bool,
+}
+
+fn is_zero(n: &usize) -> bool {
+    *n == 0
+}
+
+/// Parse `git diff` output (without color) into a [`DiffSummary`]
+pub fn parse(diff: &str) -> DiffSummary {
+    let mut summary = DiffSummary::default();
+    let mut budget = MAX_DIFF_CHARS;
+    let mut file: Option<FileDiff> = None;
+    let mut hunk: Option<(String, String)> = None;
+
+    for line in diff.lines() {
+        if let Some(rest) = line.strip_prefix("diff --git ") {
+            flush_hunk(&mut file, &mut hunk, &mut budget, &mut summary.truncated);
+            if let Some(done) = file.take() {
+                summary.files.push(done);
+            }
+            file = Some(FileDiff {
+                path: git_header_path(rest),
+                old_path: None,
+                status: "modified",
+                binary: false,
+                insertions: 0,
+                deletions: 0,
+                hunks: Vec::new(),
+                omitted_hunks: 0,
+            });
+            continue;
+        }
+        let Some(current) = file.as_mut() else { continue };
+
+        if line.starts_with("@@") {
+            flush_hunk(&mut file, &mut hunk, &mut budget, &mut summary.truncated);
+            hunk = Some((line.to_string(), String::new()));
+            continue;
+        }
+        if let Some((_, text)) = hunk.as_mut() {
+            if line.starts_with('+') {
+                current.insertions += 1;
+            } else if line.starts_with('-') {
+                current.deletions += 1;
+            }
+            text.push_str(line);
+            text.push('\n');
+            continue;
+        }
+
+        // Extended header lines between `diff --git` and the first hunk
+        if line.starts_with("new file mode") {
+            current.status = "added";
+        } else if line.starts_with("deleted file mode") {
+            current.status = "deleted";
+        } else if let Some(from) = line.strip_prefix("rename from ") {
+            current.status = "renamed";
+            current.old_path = Some(unquote(from));
+        } else if let Some(to) = line.strip_prefix("rename to ") {
+            current.path = unquote(to);
+        } else if line.starts_with("Binary files ") || line == "GIT binary patch" {
+            current.binary = true;
+        } else if let Some(to) = line.strip_prefix("+++ ").filter(|to| *to != "/dev/null") {
+            current.path = strip_side(&unquote(to));
+        }
+    }
+    flush_hunk(&mut file, &mut hunk, &mut budget, &mut summary.truncated);
+    if let Some(done) = file.take() {
+        summary.files.push(done);
+    }
+
+    summary.files_changed = summary.files.len();
+    summary.insertions = summary.files.iter().map(|f| f.insertions).sum();
+    summary.deletions = summary.files.iter().map(|f| f.deletions).sum();
+    summary
+}
+
+/// Attach the hunk being read to its file, within the size caps
+fn flush_hunk(file: &mut Option<FileDiff>, hunk: &mut Option<(String, String)>, budget: &mut usize, truncated: &mut bool) {
+    let (Some(file), Some((header, text))) = (file.as_mut(), hunk.take()) else { return };
+    if *budget == 0 {
+        file.omitted_hunks += 1;
+        *truncated = true;
+        return;
+    }
+
+    let cap = MAX_HUNK_CHARS.min(*budget);
+    let kept = crate::utils::truncate_chars(&text, cap);
+    let cut = kept.len() < text.len();
+    *budget -= kept.chars().count().min(*budget);
+    *truncated |= cut;
+    file.hunks.push(Hunk {
+        header,
+        text: kept.to_string(),
+        truncated: cut,
+    });
+}
+
+/// The new-side path from `a/old b/new` on a `diff --git` line
+fn git_header_path(rest: &str) -> String {
+    match rest.rfind(" b/") {
+        Some(i) => rest[i + 3..].trim_end_matches('"').to_string(),
+        None => rest.to_string(),
+    }
+}
+
+/// Drop the `a/` or `b/` prefix git puts on diff paths
+fn strip_side(path: &str) -> String {
+    path.strip_prefix("a/").or_else(|| path.strip_prefix("b/")).unwrap_or(path).to_string()
+}
+
+/// Remove the quotes git puts around paths with unusual characters
+fn unquote(path: &str) -> String {
+    path.strip_prefix('"').and_then(|p| p.strip_suffix('"')).unwrap_or(path).to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DIFF: &str = "\
+diff --git a/src/lib.rs b/src/lib.rs
+index 1111111..2222222 100644
+--- a/src/lib.rs
++++ b/src/lib.rs
+@@ -1,3 +1,4 @@ fn main
+ fn main() {
+-    old();
++    new();
++    more();
+ }
+@@ -10,2 +11,2 @@
+-a
++b
+diff --git a/notes.txt b/notes.txt
+new file mode 100644
+index 0000000..3333333
+--- /dev/null
++++ b/notes.txt
+@@ -0,0 +1 @@
++hello
+diff --git a/gone.txt b/gone.txt
+deleted file mode 100644
+index 4444444..0000000
+--- a/gone.txt
++++ /dev/null
+@@ -1 +0,0 @@
+-bye
+diff --git a/old_name.rs b/new_name.rs
+similarity index 100%
+rename from old_name.rs
+rename to new_name.rs
+diff --git a/logo.png b/logo.png
+index 5555555..6666666 100644
+Binary files a/logo.png and b/logo.png differ
+";
+
+    #[test]
+    fn test_parse_stats_statuses_and_hunks() {
+        let summary = parse(DIFF);
+        assert_eq!((summary.files_changed, summary.insertions, summary.deletions), (5, 4, 3));
+        assert!(!summary.truncated);
+
+        let lib = &summary.files[0];
+        assert_eq!((lib.path.as_str(), lib.status, lib.insertions, lib.deletions), ("src/lib.rs", "modified", 3, 2));
+        assert_eq!(lib.hunks.len(), 2);
+        assert_eq!(lib.hunks[0].header, "@@ -1,3 +1,4 @@ fn main");
+        assert_eq!(lib.hunks[0].text, " fn main() {\n-    old();\n+    new();\n+    more();\n }\n");
+
+        let statuses: Vec<(&str, &str)> = summary.files.iter().map(|f| (f.path.as_str(), f.status)).collect();
+        assert_eq!(
+            statuses,
+            vec![
+                ("src/lib.rs", "modified"),
+                ("notes.txt", "added"),
+                ("gone.txt", "deleted"),
+                ("new_name.rs", "renamed"),
+                ("logo.png", "modified"),
+            ]
+        );
+        assert_eq!(summary.files[3].old_path.as_deref(), Some("old_name.rs"));
+        assert!(summary.files[4].binary && summary.files[4].hunks.is_empty());
+        assert!(parse("").files.is_empty());
+    }
+
+    #[test]
+    fn test_parse_caps_hunk_text_but_counts_every_line() {
+        let mut diff = String::new();
+        for f in 0..10 {
+            diff.push_str(&format!("diff --git a/f{0} b/f{0}\n--- a/f{0}\n+++ b/f{0}\n@@ -1 +1,500 @@\n", f));
+            for i in 0..500 {
+                diff.push_str(&format!("+line number {}\n", i));
+            }
+        }
+
+        let summary = parse(&diff);
+        assert_eq!(summary.insertions, 5000);
+        assert!(summary.truncated);
+        assert!(summary.files[0].hunks[0].truncated);
+        assert!(summary.files[0].hunks[0].text.chars().count() <= MAX_HUNK_CHARS);
+        let kept: usize = summary.files.iter().flat_map(|f| &f.hunks).map(|h| h.text.chars().count()).sum();
+        assert!(kept <= MAX_DIFF_CHARS);
+        assert!(summary.files[9].hunks.is_empty() && summary.files[9].omitted_hunks == 1);
+    }
+}
@@ -0,0 +1,445 @@
+//! Minimal language server client for the lsp_diagnostics tool
+//!
+//! Speaks just enough JSON-RPC over stdio to initialize a server, open files
+//! and collect the `textDocument/publishDiagnostics` notifications it sends.
+//! Requests from the server (progress tokens, configuration) are answered
+//! with empty results so it never stalls waiting on us.
+
+use std::collections::{HashMap, HashSet};
+use std::path::Path;
+use std::time::{Duration, Instant};
+
+use serde::Serialize;
+use serde_json::{json, Value};
+use tokio::io::{AsyncBufRead, AsyncBufReadExt, AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt, BufReader};
+use tokio::sync::mpsc;
+
+/// A language with a supported server
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Language {
+    Rust,
+    TypeScript,
+    Python,
+}
+
+impl Language {
+    pub fn from_str(s: &str) -> Option<Self> {
+        match s.to_lowercase().as_str() {
+            "rust" | "rs" => Some(Language::Rust),
+            "typescript" | "ts" | "javascript" | "js" => Some(Language::TypeScript),
+            "python" | "py" => Some(Language::Python),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Language::Rust => "rust",
+            Language::TypeScript => "typescript",
+            Language::Python => "python",
+        }
+    }
+
+    /// Language of a source file, by extension
+    pub fn for_file(path: &Path) -> Option<Self> {
+        match path.extension()?.to_str()? {
+            "rs" => Some(Language::Rust),
+            "ts" | "tsx" | "js" | "jsx" | "mts" | "cts" | "mjs" | "cjs" => Some(Language::TypeScript),
+            "py" | "pyi" => Some(Language::Python),
+            _ => None,
+        }
+    }
+
+    /// Guess the project's language from the files in `dir`
+    pub fn detect(dir: &Path) -> Option<Self> {
+        if dir.join("Cargo.toml").is_file() {
+            Some(Language::Rust)
+        } else if dir.join("tsconfig.json").is_file() || dir.join("package.json").is_file() {
+            Some(Language::TypeScript)
+        } else if ["pyproject.toml", "setup.py", "setup.cfg", "pyrightconfig.json", "requirements.txt"]
+            .iter()
+            .any(|m| dir.join(m).is_file())
+        {
+            Some(Language::Python)
+        } else {
+            None
+        }
+    }
+
+    /// Server binary and arguments
+    pub fn server_command(&self) -> (&'static str, &'static [&'static str]) {
+        match self {
+            Language::Rust => ("rust-analyzer", &[]),
+            Language::TypeScript => ("typescript-language-server", &["--stdio"]),
+            Language::Python => ("pyright-langserver", &["--stdio"]),
+        }
+    }
+
+    pub fn install_hint(&self) -> &'static str {
+        match self {
+            Language::Rust => "rustup component add rust-analyzer",
+            Language::TypeScript => "npm install -g typescript typescript-language-server",
+            Language::Python => "npm install -g pyright",
+        }
+    }
+
+    /// LSP `languageId` for a file of this language
+    pub fn language_id(&self, path: &Path) -> &'static str {
+        match (self, path.extension().and_then(|e| e.to_str())) {
+            (Language::Rust, _) => "rust",
+            (Language::Python, _) => "python",
+            (Language::TypeScript, Some("tsx")) => "typescriptreact",
+            (Language::TypeScript, Some("jsx")) => "javascriptreact",
+            (Language::TypeScript, Some("js" | "mjs" | "cjs")) => "javascript",
+            (Language::TypeScript, _) => "typescript",
+        }
+    }
+
+    /// Whether the server reports on the whole project by itself. The others
+    /// only report on files that were opened.
+    pub fn checks_whole_project(&self) -> bool {
+        matches!(self, Language::Rust)
+    }
+}
+
+/// One diagnostic, with 1-based positions
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Diagnostic {
+    /// Path relative to the workspace root
+    pub file: String,
+    pub line: u64,
+    pub column: u64,
+    pub severity: &'static str,
+    pub message: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub source: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub code: Option<String>,
+}
+
+impl Diagnostic {
+    /// Convert an LSP diagnostic published for `file`
+    pub fn from_lsp(file: &str, diagnostic: &Value) -> Self {
+        let start = &diagnostic["range"]["start"];
+        Diagnostic {
+            file: file.to_string(),
+            line: start["line"].as_u64().unwrap_or(0) + 1,
+            column: start["character"].as_u64().unwrap_or(0) + 1,
+            // Severity is optional in LSP; servers treat a missing one as an error
+            severity: match diagnostic["severity"].as_u64() {
+                Some(2) => "warning",
+                Some(3) => "information",
+                Some(4) => "hint",
+                _ => "error",
+            },
+            message: diagnostic["message"].as_str().unwrap_or("").to_string(),
+            source: diagnostic["source"].as_str().map(String::from),
+            code: match &diagnostic["code"] {
+                Value::String(s) => Some(s.clone()),
+                Value::Number(n) => Some(n.to_string()),
+                _ => None,
+            },
+        }
+    }
+
+    /// 1 for errors up to 4 for hints, like LSP severities
+    pub fn rank(&self) -> u8 {
+        severity_rank(self.severity)
+    }
+}
+
+pub fn severity_rank(severity: &str) -> u8 {
+    match severity {
+        "error" => 1,
+        "warning" => 2,
+        "information" | "info" => 3,
+        _ => 4,
+    }
+}
+
+/// Write one framed JSON-RPC message
+pub async fn write_message<W: AsyncWrite + Unpin>(writer: &mut W, message: &Value) -> std::io::Result<()> {
+    let body = message.to_string();
+    writer
+        .write_all(format!("Content-Length: {}\r\n\r\n{}", body.len(), body).as_bytes())
+        .await?;
+    writer.flush().await
+}
+
+/// Read one framed JSON-RPC message. `None` at end of stream.
+pub async fn read_message<R: AsyncBufRead + Unpin>(reader: &mut R) -> std::io::Result<Option<Value>> {
+    let mut length = None;
+    loop {
+        let mut line = String::new();
+        if reader.read_line(&mut line).await? == 0 {
+            return Ok(None);
+        }
+        let line = line.trim_end();
+        if line.is_empty() {
+            break;
+        }
+        if let Some((_, value)) = line.split_once(':').filter(|(name, _)| name.eq_ignore_ascii_case("content-length")) {
+            length = value.trim().parse::<usize>().ok();
+        }
+    }
+    let length = length.ok_or_else(|| std::io::Error::new(std::io::ErrorKind::InvalidData, "missing Content-Length"))?;
+    let mut body = vec![0; length];
+    reader.read_exact(&mut body).await?;
+    serde_json::from_slice(&body)
+        .map(Some)
+        .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e))
+}
+
+/// A session with a running server
+pub struct LspSession<W> {
+    writer: W,
+    incoming: mpsc::UnboundedReceiver<Value>,
+    next_id: i64,
+    /// Latest diagnostics per document URI (each publish replaces the last)
+    diagnostics: HashMap<String, Vec<Value>>,
+    /// Work-done progress that has begun but not ended
+    active_progress: HashSet<String>,
+}
+
+impl<W: AsyncWrite + Unpin> LspSession<W> {
+    /// Start a session over the server's stdout and stdin
+    pub fn new<R: AsyncRead + Unpin + Send + 'static>(reader: R, writer: W) -> Self {
+        let (tx, incoming) = mpsc::unbounded_channel();
+        tokio::spawn(async move {
+            let mut reader = BufReader::new(reader);
+            while let Ok(Some(message)) = read_message(&mut reader).await {
+                if tx.send(message).is_err() {
+                    break;
+                }
+            }
+        });
+        LspSession {
+            writer,
+            incoming,
+            next_id: 1,
+            diagnostics: HashMap::new(),
+            active_progress: HashSet::new(),
+        }
+    }
+
+    pub async fn notify(&mut self, method: &str, params: Value) -> Result<(), String> {
+        write_message(&mut self.writer, &json!({ "jsonrpc": "2.0", "method": method, "params": params }))
+            .await
+            .map_err(|e| format!("Failed to write to the language server: {}", e))
+    }
+
+    /// Send a request and wait for its response, handling everything else
+    /// the server sends in the meantime
+    pub async fn request(&mut self, method: &str, params: Value, deadline: Instant) -> Result<Value, String> {
+        let id = self.next_id;
+        self.next_id += 1;
+        write_message(&mut self.writer, &json!({ "jsonrpc": "2.0", "id": id, "method": method, "params": params }))
+            .await
+            .map_err(|e| format!("Failed to write to the language server: {}", e))?;
+
+        loop {
+            let message = match tokio::time::timeout_at(deadline.into(), self.incoming.recv()).await {
+                Ok(Some(message)) => message,
+                Ok(None) => return Err("The language server exited".to_string()),
+                Err(_) => return Err(format!("Timed out waiting for the language server to answer {}", method)),
+            };
+            if message.get("method").is_none() && message["id"] == json!(id) {
+                if let Some(error) = message.get("error") {
+                    return Err(format!("{} failed: {}", method, error["message"].as_str().unwrap_or("unknown error")));
+                }
+                return Ok(message["result"].clone());
+            }
+            self.handle(message).await?;
+        }
+    }
+
+    /// Initialize the server for the workspace at `root`
+    pub async fn initialize(&mut self, root: &Path, deadline: Instant) -> Result<(), String> {
+        let root_uri = path_to_uri(root);
+        let name = root.file_name().map(|n| n.to_string_lossy().to_string()).unwrap_or_default();
+        self.request(
+            "initialize",
+            json!({
+                "processId": std::process::id(),
+                "rootUri": root_uri,
+                "workspaceFolders": [{ "uri": root_uri, "name": name }],
+                "capabilities": {
+                    "textDocument": { "publishDiagnostics": { "relatedInformation": false } },
+                    "window": { "workDoneProgress": true },
+                    "workspace": { "configuration": true, "workspaceFolders": true },
+                },
+            }),
+            deadline,
+        )
+        .await?;
+        self.notify("initialized", json!({})).await
+    }
+
+    pub async fn open(&mut self, path: &Path, language_id: &str, text: &str) -> Result<(), String> {
+        self.notify(
+            "textDocument/didOpen",
+            json!({ "textDocument": { "uri": path_to_uri(path), "languageId": language_id, "version": 1, "text": text } }),
+        )
+        .await
+    }
+
+    /// Wait until the server has gone quiet for `settle` with no work in
+    /// progress (or `deadline` passes), then return the diagnostics by URI.
+    /// Returns whether the server settled before the deadline.
+    pub async fn collect(&mut self, settle: Duration, deadline: Instant) -> Result<bool, String> {
+        loop {
+            let quiet_until = (Instant::now() + settle).min(deadline);
+            match tokio::time::timeout_at(quiet_until.into(), self.incoming.recv()).await {
+                Ok(Some(message)) => self.handle(message).await?,
+                Ok(None) => return Err("The language server exited".to_string()),
+                Err(_) if Instant::now() >= deadline => return Ok(false),
+                Err(_) if self.active_progress.is_empty() => return Ok(true),
+                Err(_) => {}
+            }
+        }
+    }
+
+    pub fn diagnostics(&self) -> &HashMap<String, Vec<Value>> {
+        &self.diagnostics
+    }
+
+    /// Ask the server to shut down and exit, without waiting long
+    pub async fn shutdown(&mut self) {
+        let deadline = Instant::now() + Duration::from_secs(2);
+        let _ = self.request("shutdown", Value::Null, deadline).await;
+        let _ = self.notify("exit", Value::Null).await;
+    }
+
+    async fn handle(&mut self, message: Value) -> Result<(), String> {
+        let method = message["method"].as_str().unwrap_or_default();
+        // Requests from the server need an answer, or some servers wait forever
+        if let Some(id) = message.get("id").filter(|_| !method.is_empty()) {
+            let result = match method {
+                "workspace/configuration" => {
+                    let items = message["params"]["items"].as_array().map(|i| i.len()).unwrap_or(0);
+                    Value::Array(vec![Value::Null; items])
+                }
+                _ => Value::Null,
+            };
+            return write_message(&mut self.writer, &json!({ "jsonrpc": "2.0", "id": id, "result": result }))
+                .await
+                .map_err(|e| format!("Failed to write to the language server: {}", e));
+        }
+
+        let params = &message["params"];
+        match method {
+            "textDocument/publishDiagnostics" => {
+                if let Some(uri) = params["uri"].as_str() {
+                    let items = params["diagnostics"].as_array().cloned().unwrap_or_default();
+                    self.diagnostics.insert(uri.to_string(), items);
+                }
+            }
+            "$/progress" => {
+                let token = params["token"].to_string();
+                match params["value"]["kind"].as_str() {
+                    Some("begin") => {
+                        self.active_progress.insert(token);
+                    }
+                    Some("end") => {
+                        self.active_progress.remove(&token);
+                    }
+                    _ => {}
+                }
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+}
+
+pub fn path_to_uri(path: &Path) -> String {
+    url::Url::from_file_path(path)
+        .map(|u| u.to_string())
+        .unwrap_or_else(|_| format!("file://{}", path.display()))
+}
+
+pub fn uri_to_path(uri: &str) -> Option<std::path::PathBuf> {
+    url::Url::parse(uri).ok()?.to_file_path().ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_message_framing_round_trip() {
+        let mut buffer = Vec::new();
+        write_message(&mut buffer, &json!({ "id": 1, "text": "héllo" })).await.unwrap();
+        write_message(&mut buffer, &json!({ "id": 2 })).await.unwrap();
+
+        let mut reader = BufReader::new(buffer.as_slice());
+        assert_eq!(read_message(&mut reader).await.unwrap(), Some(json!({ "id": 1, "text": "héllo" })));
+        assert_eq!(read_message(&mut reader).await.unwrap(), Some(json!({ "id": 2 })));
+        assert_eq!(read_message(&mut reader).await.unwrap(), None);
+    }
+
+    #[tokio::test]
+    async fn test_session_answers_server_requests_and_waits_for_progress() {
+        let (client_io, server_io) = tokio::io::duplex(64 * 1024);
+        let (client_read, client_write) = tokio::io::split(client_io);
+        let (server_read, mut server_write) = tokio::io::split(server_io);
+
+        // A scripted server: answers initialize, asks for a progress token and
+        // configuration, then reports diagnostics while work is in progress
+        let server = tokio::spawn(async move {
+            let mut reader = BufReader::new(server_read);
+            let init = read_message(&mut reader).await.unwrap().unwrap();
+            assert_eq!(init["method"], "initialize");
+            write_message(&mut server_write, &json!({ "jsonrpc": "2.0", "id": init["id"], "result": { "capabilities": {} } }))
+                .await
+                .unwrap();
+            assert_eq!(read_message(&mut reader).await.unwrap().unwrap()["method"], "initialized");
+
+            write_message(&mut server_write, &json!({ "jsonrpc": "2.0", "id": 100, "method": "window/workDoneProgress/create", "params": { "token": "check" } })).await.unwrap();
+            write_message(&mut server_write, &json!({ "jsonrpc": "2.0", "id": 101, "method": "workspace/configuration", "params": { "items": [{}, {}] } })).await.unwrap();
+            let answers = [read_message(&mut reader).await.unwrap().unwrap(), read_message(&mut reader).await.unwrap().unwrap()];
+            assert_eq!(answers[0]["id"], 100);
+            assert_eq!(answers[1]["result"], json!([null, null]));
+
+            write_message(&mut server_write, &json!({ "jsonrpc": "2.0", "method": "$/progress", "params": { "token": "check", "value": { "kind": "begin" } } })).await.unwrap();
+            for message in ["stale", "fresh"] {
+                write_message(&mut server_write, &json!({ "jsonrpc": "2.0", "method": "textDocument/publishDiagnostics", "params": {
+                    "uri": "file:///ws/src/main.rs",
+                    "diagnostics": [{ "range": { "start": { "line": 2, "character": 4 } }, "severity": 1, "message": message, "code": "E0425" }],
+                } })).await.unwrap();
+            }
+            // Quiet for longer than the settle period, but still in progress
+            tokio::time::sleep(Duration::from_millis(150)).await;
+            write_message(&mut server_write, &json!({ "jsonrpc": "2.0", "method": "$/progress", "params": { "token": "check", "value": { "kind": "end" } } })).await.unwrap();
+            tokio::time::sleep(Duration::from_secs(5)).await;
+        });
+
+        let mut session = LspSession::new(client_read, client_write);
+        let deadline = Instant::now() + Duration::from_secs(5);
+        session.initialize(Path::new("/ws"), deadline).await.unwrap();
+        assert!(session.collect(Duration::from_millis(50), deadline).await.unwrap());
+
+        let published = &session.diagnostics()["file:///ws/src/main.rs"];
+        assert_eq!(published.len(), 1);
+        let diagnostic = Diagnostic::from_lsp("src/main.rs", &published[0]);
+        assert_eq!((diagnostic.line, diagnostic.column, diagnostic.severity), (3, 5, "error"));
+        assert_eq!((diagnostic.message.as_str(), diagnostic.code.as_deref()), ("fresh", Some("E0425")));
+        server.abort();
+    }
+
+    #[test]
+    fn test_language_detection() {
+        assert_eq!(Language::for_file(Path::new("a/b.tsx")), Some(Language::TypeScript));
+        assert_eq!(Language::for_file(Path::new("main.rs")), Some(Language::Rust));
+        assert_eq!(Language::for_file(Path::new("README.md")), None);
+        assert_eq!(Language::TypeScript.language_id(Path::new("x.jsx")), "javascriptreact");
+
+        let dir = tempfile::TempDir::new().unwrap();
+        assert_eq!(Language::detect(dir.path()), None);
+        std::fs::write(dir.path().join("requirements.txt"), "").unwrap();
+        assert_eq!(Language::detect(dir.path()), Some(Language::Python));
+        std::fs::write(dir.path().join("Cargo.toml"), "").unwrap();
+        assert_eq!(Language::detect(dir.path()), Some(Language::Rust));
+    }
+}
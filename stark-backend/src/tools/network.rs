@@ -0,0 +1,137 @@
+//! Network name validation
+//!
+//! Tools accept a free-form `network` parameter from the model. Everything that
+//! takes one should run it through `normalize_network` so that aliases and
+//! casing ("Base", "ethereum", "eth") resolve to the same canonical name and
+//! typos are rejected instead of silently falling back to Base.
+
+use std::fmt;
+
+/// EVM networks supported by the web3/x402 tools
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Network {
+    Base,
+    Mainnet,
+    Arbitrum,
+    Optimism,
+    Polygon,
+    Bsc,
+}
+
+impl Network {
+    /// All supported networks, in the order they are listed to users
+    pub const ALL: [Network; 6] = [
+        Network::Base,
+        Network::Mainnet,
+        Network::Arbitrum,
+        Network::Optimism,
+        Network::Polygon,
+        Network::Bsc,
+    ];
+
+    /// Canonical name used as the key in presets, token lists and RPC configs
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Network::Base => "base",
+            Network::Mainnet => "mainnet",
+            Network::Arbitrum => "arbitrum",
+            Network::Optimism => "optimism",
+            Network::Polygon => "polygon",
+            Network::Bsc => "bsc",
+        }
+    }
+
+    pub fn chain_id(&self) -> u64 {
+        match self {
+            Network::Base => 8453,
+            Network::Mainnet => 1,
+            Network::Arbitrum => 42161,
+            Network::Optimism => 10,
+            Network::Polygon => 137,
+            Network::Bsc => 56,
+        }
+    }
+
+    /// Symbol of the coin gas is paid in
+    pub fn native_symbol(&self) -> &'static str {
+        match self {
+            Network::Base | Network::Mainnet | Network::Arbitrum | Network::Optimism => "ETH",
+            Network::Polygon => "POL",
+            Network::Bsc => "BNB",
+        }
+    }
+}
+
+impl fmt::Display for Network {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// Map a user/model supplied network name to a supported network.
+/// Matching is case-insensitive and ignores surrounding whitespace.
+pub fn normalize_network(name: &str) -> Option<Network> {
+    match name.trim().to_lowercase().as_str() {
+        "base" | "base-mainnet" | "base_mainnet" => Some(Network::Base),
+        "mainnet" | "ethereum" | "eth" | "ethereum-mainnet" | "eth-mainnet" => {
+            Some(Network::Mainnet)
+        }
+        "arbitrum" | "arbitrum-one" | "arb" => Some(Network::Arbitrum),
+        "optimism" | "op" | "op-mainnet" => Some(Network::Optimism),
+        "polygon" | "polygon-pos" | "matic" => Some(Network::Polygon),
+        "bsc" | "bnb" | "binance" | "bnb-smart-chain" => Some(Network::Bsc),
+        _ => None,
+    }
+}
+
+/// Comma-separated list of canonical network names
+pub fn supported_networks() -> String {
+    Network::ALL
+        .iter()
+        .map(|n| n.as_str())
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+/// Like `normalize_network`, but returns the standard error message for
+/// unknown networks so every tool reports them the same way.
+pub fn parse_network(name: &str) -> Result<Network, String> {
+    normalize_network(name).ok_or_else(|| {
+        format!(
+            "Unsupported network '{}'. Supported networks: {}",
+            name,
+            supported_networks()
+        )
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_normalize_network_aliases_and_case() {
+        assert_eq!(normalize_network("base"), Some(Network::Base));
+        assert_eq!(normalize_network("Base"), Some(Network::Base));
+        assert_eq!(normalize_network(" BASE "), Some(Network::Base));
+        assert_eq!(normalize_network("mainnet"), Some(Network::Mainnet));
+        assert_eq!(normalize_network("Ethereum"), Some(Network::Mainnet));
+        assert_eq!(normalize_network("eth"), Some(Network::Mainnet));
+        assert_eq!(normalize_network("Arbitrum-One"), Some(Network::Arbitrum));
+        assert_eq!(normalize_network("op"), Some(Network::Optimism));
+        assert_eq!(normalize_network("matic"), Some(Network::Polygon));
+        assert_eq!(normalize_network("BNB"), Some(Network::Bsc));
+    }
+
+    #[test]
+    fn test_unknown_network_is_rejected() {
+        assert_eq!(normalize_network("bsae"), None);
+        assert_eq!(normalize_network(""), None);
+
+        let err = parse_network("solana").unwrap_err();
+        assert_eq!(
+            err,
+            "Unsupported network 'solana'. Supported networks: base, mainnet, arbitrum, optimism, polygon, bsc"
+        );
+    }
+}
@@ -0,0 +1,194 @@
+//! Full tool outputs kept out of the model's context
+//!
+//! Agent runs cap the tool output they hand back to the model. Anything longer
+//! is written to the run's [`OutputStore`] and replaced by a preview (its
+//! beginning and end) that names an `output_ref`; the `fetch_output` tool pages
+//! through the stored output by that reference. Background jobs keep their
+//! store under `.outputs/<job_id>` in the workspace root, so the full outputs
+//! can be downloaded with the job afterwards.
+
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+
+/// Longest tool output, in bytes, handed to the model as is. Above a default
+/// `read_file` page, so paged reads come through whole.
+pub const INLINE_OUTPUT_BYTES: usize = 16000;
+
+/// Stored outputs of one agent job or run, one file per output
+#[derive(Debug, Clone)]
+pub struct OutputStore {
+    dir: PathBuf,
+    next: Arc<AtomicUsize>,
+}
+
+impl OutputStore {
+    /// Store in `dir`, created when the first output is stored
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        Self {
+            dir: dir.into(),
+            next: Arc::new(AtomicUsize::new(1)),
+        }
+    }
+
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    /// Store `content`, returning the reference to fetch it by
+    pub async fn put(&self, content: &str) -> Result<String, String> {
+        let output_ref = format!("out-{}", self.next.fetch_add(1, Ordering::SeqCst));
+        tokio::fs::create_dir_all(&self.dir)
+            .await
+            .map_err(|e| format!("Failed to create output store {}: {}", self.dir.display(), e))?;
+        tokio::fs::write(self.path(&output_ref)?, content)
+            .await
+            .map_err(|e| format!("Failed to store output {}: {}", output_ref, e))?;
+        Ok(output_ref)
+    }
+
+    /// A stored output, or None if there is no output by that reference
+    pub async fn get(&self, output_ref: &str) -> Result<Option<String>, String> {
+        match tokio::fs::read_to_string(self.path(output_ref)?).await {
+            Ok(content) => Ok(Some(content)),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(format!("Failed to read output {}: {}", output_ref, e)),
+        }
+    }
+
+    /// Delete the store and everything in it
+    pub async fn remove(&self) -> Result<(), String> {
+        match tokio::fs::remove_dir_all(&self.dir).await {
+            Ok(()) => Ok(()),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(()),
+            Err(e) => Err(format!("Failed to delete output store {}: {}", self.dir.display(), e)),
+        }
+    }
+
+    /// File of an output. References come back from the model, so only the
+    /// shape handed out by `put` is accepted.
+    fn path(&self, output_ref: &str) -> Result<PathBuf, String> {
+        let valid = output_ref
+            .strip_prefix("out-")
+            .is_some_and(|n| !n.is_empty() && n.chars().all(|c| c.is_ascii_digit()));
+        if !valid {
+            return Err(format!("Invalid output_ref '{}'; pass it exactly as returned", output_ref));
+        }
+        Ok(self.dir.join(format!("{}.txt", output_ref)))
+    }
+}
+
+/// End of the page of `content` starting at byte `start`: the last whole
+/// line within `max_bytes`, or a character boundary when not even one line fits
+pub fn page_end(content: &str, start: usize, max_bytes: usize) -> usize {
+    let end = start.saturating_add(max_bytes.max(1));
+    if end >= content.len() {
+        return content.len();
+    }
+    let mut cut = end;
+    while !content.is_char_boundary(cut) {
+        cut -= 1;
+    }
+    if let Some(newline) = content[start..cut].rfind('\n') {
+        return start + newline + 1;
+    }
+    if cut > start {
+        return cut;
+    }
+    // A character wider than the page: take it whole
+    cut = end;
+    while !content.is_char_boundary(cut) {
+        cut += 1;
+    }
+    cut
+}
+
+/// What the model sees of an output longer than [`INLINE_OUTPUT_BYTES`]: about
+/// two thirds of the budget from its start, a third from its end, and how to
+/// fetch the bytes in between
+pub fn preview(content: &str, output_ref: &str) -> String {
+    let head_end = page_end(content, 0, INLINE_OUTPUT_BYTES * 2 / 3);
+    let mut tail_start = content.len().saturating_sub(INLINE_OUTPUT_BYTES / 3).max(head_end);
+    // Start the tail on a line of its own where there is one
+    match content[tail_start..].find('\n') {
+        Some(newline) if tail_start + newline + 1 < content.len() => tail_start += newline + 1,
+        _ => {
+            while !content.is_char_boundary(tail_start) {
+                tail_start += 1;
+            }
+        }
+    }
+
+    format!(
+        "{}\n... [bytes {}-{} omitted] ...\n{}\n\n[Output truncated: {} of {} bytes shown. The full output is stored as \
+         output_ref \"{}\"; call fetch_output with it and offset: {} to read the omitted part.]",
+        content[..head_end].trim_end_matches('\n'),
+        head_end,
+        tail_start,
+        content[tail_start..].trim_end_matches('\n'),
+        head_end + content.len() - tail_start,
+        content.len(),
+        output_ref,
+        head_end
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[tokio::test]
+    async fn test_put_and_get() {
+        let temp_dir = TempDir::new().unwrap();
+        let store = OutputStore::new(temp_dir.path().join("job-1"));
+
+        let first = store.put("first output").await.unwrap();
+        let second = store.put("second output").await.unwrap();
+        assert_eq!((first.as_str(), second.as_str()), ("out-1", "out-2"));
+        assert_eq!(store.get(&first).await.unwrap().as_deref(), Some("first output"));
+        assert_eq!(store.get("out-9").await.unwrap(), None);
+        assert!(store.get("../../etc/passwd").await.is_err());
+        assert!(store.get("out-").await.is_err());
+
+        store.remove().await.unwrap();
+        assert!(!store.dir().exists());
+    }
+
+    #[test]
+    fn test_page_end() {
+        let content = "one\ntwo\nthree\n";
+        assert_eq!(page_end(content, 0, 9), 8);
+        assert_eq!(page_end(content, 4, 100), content.len());
+        // No whole line fits: cut at a character boundary
+        assert_eq!(page_end(content, 8, 3), 11);
+        assert_eq!(page_end("héllo", 0, 2), 1);
+        assert_eq!(page_end("é", 0, 1), 2);
+    }
+
+    #[test]
+    fn test_preview_keeps_head_and_tail() {
+        let content: String = (1..=5000).map(|i| format!("line {}\n", i)).collect();
+        let preview = preview(&content, "out-3");
+
+        assert!(preview.len() < INLINE_OUTPUT_BYTES + 300);
+        assert!(preview.starts_with("line 1\nline 2\n"));
+        assert!(preview.contains("line 4999\nline 5000\n\n[Output truncated:"));
+        assert!(preview.contains("output_ref \"out-3\""));
+
+        // The head ends on a whole line, and the offset to read on is where it ends
+        let offset: usize = preview.split("offset: ").nth(1).unwrap().split(' ').next().unwrap().parse().unwrap();
+        assert!(content[..offset].ends_with('\n'));
+        assert!(preview.contains(&format!("{}... [bytes {}-", &content[offset - 10..offset], offset)));
+    }
+
+    #[test]
+    fn test_preview_of_a_single_long_line() {
+        let content = "x".repeat(100_000);
+        let preview = preview(&content, "out-1");
+
+        assert!(preview.len() < INLINE_OUTPUT_BYTES + 300);
+        assert!(preview.contains("[Output truncated: 15999 of 100000 bytes shown."));
+        assert!(preview.contains("offset: 10666 "));
+    }
+}
@@ -13,6 +13,7 @@ static FETCH_PRESETS: OnceLock<HashMap<String, FetchPreset>> = OnceLock::new();
 static RPC_PRESETS: OnceLock<HashMap<String, RpcPreset>> = OnceLock::new();
 static WEB3_PRESETS: OnceLock<HashMap<String, Web3Preset>> = OnceLock::new();
 static NETWORKS: OnceLock<HashMap<String, NetworkConfig>> = OnceLock::new();
+static HTTP_AUTH_PROFILES: OnceLock<HashMap<String, HttpAuthProfile>> = OnceLock::new();
 
 /// x402_fetch preset configuration
 #[derive(Debug, Clone, Deserialize)]
@@ -71,6 +72,49 @@ pub struct NetworkConfig {
     pub explorer: String,
 }
 
+/// http_request authentication profile
+///
+/// The secret comes from the API key named `key`. It is only attached to
+/// requests whose host is listed in `hosts`.
+#[derive(Debug, Clone, Deserialize)]
+pub struct HttpAuthProfile {
+    /// API key (as stored on the API keys page) holding the secret
+    pub key: String,
+    /// Header carrying the secret
+    #[serde(default = "default_auth_header")]
+    pub header: String,
+    /// Text before the secret in the header, e.g. "Bearer "
+    #[serde(default)]
+    pub prefix: String,
+    /// Send the secret as this query parameter instead of a header
+    #[serde(default)]
+    pub query_param: Option<String>,
+    /// Hosts the secret may be sent to; "*.example.com" also matches subdomains
+    pub hosts: Vec<String>,
+    pub description: String,
+    /// Where to get the key, linked from the API keys page
+    #[serde(default)]
+    pub url: String,
+}
+
+fn default_auth_header() -> String {
+    "Authorization".to_string()
+}
+
+impl HttpAuthProfile {
+    /// Whether the secret may be sent to `host`
+    pub fn allows_host(&self, host: &str) -> bool {
+        let host = host.to_lowercase();
+        self.hosts.iter().any(|pattern| {
+            let pattern = pattern.to_lowercase();
+            match pattern.strip_prefix("*.") {
+                Some(domain) => host == domain || host.ends_with(&format!(".{}", domain)),
+                None => host == pattern,
+            }
+        })
+    }
+}
+
 /// Load presets from config directory
 pub fn load_presets(config_dir: &Path) {
     // Load fetch presets
@@ -158,6 +202,26 @@ pub fn load_presets(config_dir: &Path) {
         log::warn!("[presets] Networks file not found: {:?}, using defaults", networks_path);
         let _ = NETWORKS.set(default_networks());
     }
+
+    // Load http_request auth profiles
+    let profiles_path = config_dir.join("http_auth_profiles.ron");
+    if profiles_path.exists() {
+        match std::fs::read_to_string(&profiles_path) {
+            Ok(content) => {
+                match ron::from_str::<HashMap<String, HttpAuthProfile>>(&content) {
+                    Ok(profiles) => {
+                        log::info!("[presets] Loaded {} HTTP auth profiles from {:?}", profiles.len(), profiles_path);
+                        let _ = HTTP_AUTH_PROFILES.set(profiles);
+                    }
+                    Err(e) => log::error!("[presets] Failed to parse HTTP auth profiles: {}", e),
+                }
+            }
+            Err(e) => log::error!("[presets] Failed to read HTTP auth profiles file: {}", e),
+        }
+    } else {
+        log::warn!("[presets] HTTP auth profiles file not found: {:?}, using defaults", profiles_path);
+        let _ = HTTP_AUTH_PROFILES.set(default_http_auth_profiles());
+    }
 }
 
 /// Get networks, loading defaults if not already loaded
@@ -222,6 +286,11 @@ pub fn list_web3_presets() -> Vec<String> {
         .unwrap_or_else(|| vec!["weth_deposit".to_string(), "weth_withdraw".to_string()])
 }
 
+/// All http_request auth profiles, loading defaults if not already loaded
+pub fn http_auth_profiles() -> &'static HashMap<String, HttpAuthProfile> {
+    HTTP_AUTH_PROFILES.get_or_init(default_http_auth_profiles)
+}
+
 /// List available network names
 pub fn list_networks() -> Vec<String> {
     get_networks().keys().cloned().collect()
@@ -328,6 +397,21 @@ fn default_networks() -> HashMap<String, NetworkConfig> {
     map
 }
 
+/// Default http_request auth profiles (fallback if config not found)
+fn default_http_auth_profiles() -> HashMap<String, HttpAuthProfile> {
+    let mut map = HashMap::new();
+    map.insert("github".to_string(), HttpAuthProfile {
+        key: "GITHUB_TOKEN".to_string(),
+        header: default_auth_header(),
+        prefix: "Bearer ".to_string(),
+        query_param: None,
+        hosts: vec!["api.github.com".to_string(), "uploads.github.com".to_string()],
+        description: "GitHub REST API".to_string(),
+        url: "https://github.com/settings/tokens".to_string(),
+    });
+    map
+}
+
 /// Get chain ID for network (returns string for URL params)
 pub fn get_chain_id(network: &str) -> String {
     get_networks()
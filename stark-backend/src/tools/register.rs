@@ -20,11 +20,13 @@
 //! let to = quote.get("to").unwrap();
 //! ```
 
-use ethers::prelude::*;
 use serde::{Deserialize, Deserializer};
 use serde_json::{json, Value};
 use std::collections::HashMap;
 use std::sync::{Arc, RwLock};
+use tokio::sync::mpsc;
+
+use crate::db::Database;
 
 /// A monad for tool parameters that can either use a preset (reading from registers)
 /// or custom raw parameters provided by the agent.
@@ -130,8 +132,7 @@ impl IntrinsicRegister {
     pub fn resolve(&self) -> Option<Value> {
         match self {
             Self::WalletAddress => {
-                let pk = crate::config::burner_wallet_private_key()?;
-                let wallet: LocalWallet = pk.parse().ok()?;
+                let wallet = crate::wallet::active_signer()?;
                 Some(json!(format!("{:?}", wallet.address())))
             }
         }
@@ -143,9 +144,22 @@ impl IntrinsicRegister {
 ///
 /// This is critical for financial transactions where data integrity
 /// must be preserved (e.g., swap calldata from 0x quotes).
+///
+/// A store opened with [`RegisterStore::persistent`] also writes every change
+/// to the session's rows in the database, so registers outlive the dispatch.
 #[derive(Debug, Clone, Default)]
 pub struct RegisterStore {
     inner: Arc<RwLock<HashMap<String, RegisterEntry>>>,
+    /// Queue to the task writing changes to the database, in order
+    persist: Option<mpsc::UnboundedSender<RegisterWrite>>,
+}
+
+/// A change to mirror into the database
+#[derive(Debug)]
+enum RegisterWrite {
+    Set { key: String, value: Value, source_tool: String },
+    Remove(String),
+    Clear,
 }
 
 /// A single register entry with metadata
@@ -164,6 +178,64 @@ impl RegisterStore {
     pub fn new() -> Self {
         Self {
             inner: Arc::new(RwLock::new(HashMap::new())),
+            persist: None,
+        }
+    }
+
+    /// Open the persisted registers of a chat session. Changes are written
+    /// back in the background.
+    pub async fn persistent(db: Arc<Database>, session_id: i64) -> Self {
+        let mut entries = HashMap::new();
+        match db.list_session_registers(session_id).await {
+            Ok(registers) => {
+                for register in registers {
+                    // Keep the age so staleness checks still hold after a restart
+                    let age = chrono::DateTime::parse_from_rfc3339(&register.updated_at)
+                        .ok()
+                        .and_then(|t| (chrono::Utc::now() - t.with_timezone(&chrono::Utc)).to_std().ok())
+                        .unwrap_or_default();
+                    let now = std::time::Instant::now();
+                    entries.insert(
+                        register.key,
+                        RegisterEntry {
+                            value: register.value,
+                            source_tool: register.source_tool,
+                            created_at: now.checked_sub(age).unwrap_or(now),
+                        },
+                    );
+                }
+            }
+            Err(e) => log::warn!("[REGISTER] Could not load registers of session {}: {}", session_id, e),
+        }
+        if !entries.is_empty() {
+            log::info!("[REGISTER] Loaded {} registers for session {}", entries.len(), session_id);
+        }
+
+        let (tx, mut rx) = mpsc::unbounded_channel::<RegisterWrite>();
+        tokio::spawn(async move {
+            while let Some(write) = rx.recv().await {
+                let result = match write {
+                    RegisterWrite::Set { key, value, source_tool } => db
+                        .set_session_register(session_id, &key, &value, &source_tool)
+                        .await,
+                    RegisterWrite::Remove(key) => db.delete_session_register(session_id, &key).await.map(|_| ()),
+                    RegisterWrite::Clear => db.clear_session_registers(session_id).await.map(|_| ()),
+                };
+                if let Err(e) = result {
+                    log::warn!("[REGISTER] Could not persist register of session {}: {}", session_id, e);
+                }
+            }
+        });
+
+        Self {
+            inner: Arc::new(RwLock::new(entries)),
+            persist: Some(tx),
+        }
+    }
+
+    fn persist(&self, write: RegisterWrite) {
+        if let Some(tx) = &self.persist {
+            let _ = tx.send(write);
         }
     }
 
@@ -184,11 +256,16 @@ impl RegisterStore {
             store.insert(
                 key.to_string(),
                 RegisterEntry {
-                    value,
+                    value: value.clone(),
                     source_tool: source_tool.to_string(),
                     created_at: std::time::Instant::now(),
                 },
             );
+            self.persist(RegisterWrite::Set {
+                key: key.to_string(),
+                value,
+                source_tool: source_tool.to_string(),
+            });
         }
     }
 
@@ -254,21 +331,27 @@ impl RegisterStore {
             .unwrap_or(false)
     }
 
-    /// Clear all registers (at end of execution)
+    /// Clear all registers
     pub fn clear(&self) {
         if let Ok(mut store) = self.inner.write() {
             log::info!("[REGISTER] Clearing all registers");
             store.clear();
+            self.persist(RegisterWrite::Clear);
         }
     }
 
     /// Remove a specific register
     pub fn remove(&self, key: &str) -> Option<Value> {
-        self.inner
+        let removed = self
+            .inner
             .write()
             .ok()
             .and_then(|mut s| s.remove(key))
-            .map(|e| e.value)
+            .map(|e| e.value);
+        if removed.is_some() {
+            self.persist(RegisterWrite::Remove(key.to_string()));
+        }
+        removed
     }
 
     /// List all register keys (for debugging)
@@ -371,6 +454,34 @@ mod tests {
         assert_eq!(store2.get("shared").unwrap(), json!("data"));
     }
 
+    #[tokio::test]
+    async fn test_persistent_registers() {
+        let db = Arc::new(Database::new(":memory:").unwrap());
+
+        let store = RegisterStore::persistent(db.clone(), 1).await;
+        store.set("sell_token", json!("0xabc"), "token_lookup");
+        store.set("buy_token", json!("0xdef"), "token_lookup");
+        store.remove("buy_token");
+
+        // Writes happen in the background
+        for _ in 0..50 {
+            if db.list_session_registers(1).await.unwrap().len() == 1 {
+                break;
+            }
+            tokio::time::sleep(std::time::Duration::from_millis(10)).await;
+        }
+        let persisted = db.list_session_registers(1).await.unwrap();
+        assert_eq!(persisted.len(), 1);
+        assert_eq!(persisted[0].key, "sell_token");
+
+        // A new dispatch of the same session sees the value; others do not
+        let reopened = RegisterStore::persistent(db.clone(), 1).await;
+        assert_eq!(reopened.get("sell_token").unwrap(), json!("0xabc"));
+        assert_eq!(reopened.get_entry("sell_token").unwrap().source_tool, "token_lookup");
+        assert!(!reopened.exists("buy_token"));
+        assert!(!RegisterStore::persistent(db, 2).await.exists("sell_token"));
+    }
+
     #[test]
     fn test_register_entry_metadata() {
         let store = RegisterStore::new();
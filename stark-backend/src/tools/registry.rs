@@ -1,9 +1,19 @@
 use crate::ai::multi_agent::types::AgentSubtype;
+use crate::metrics::Metrics;
+use crate::models::tool_invocation::{sanitize_arguments, summarize_result};
+use crate::models::{NewToolInvocation, Scope, Scopes, ToolOutcome, ToolPolicy};
+use crate::telemetry;
+use crate::tools::cache::{CachePolicy, ToolCache};
 use crate::tools::types::{ToolConfig, ToolContext, ToolDefinition, ToolGroup, ToolResult};
+use crate::utils::truncate_str;
+use crate::workspace::WorkspaceManager;
 use async_trait::async_trait;
 use serde_json::Value;
-use std::collections::HashMap;
-use std::sync::Arc;
+use std::collections::{HashMap, HashSet};
+use std::path::PathBuf;
+use std::sync::{Arc, RwLock};
+use std::time::Instant;
+use tracing::Instrument;
 
 /// Trait that all tools must implement
 #[async_trait]
@@ -23,12 +33,54 @@ pub trait Tool: Send + Sync {
     fn group(&self) -> ToolGroup {
         self.definition().group
     }
+
+    /// Scope an API token needs for the agent to use this tool on its behalf
+    fn required_scope(&self) -> Option<Scope> {
+        match self.group() {
+            ToolGroup::Exec => Some(Scope::ToolsExec),
+            _ => None,
+        }
+    }
+
+    /// Whether the tool can move value (swaps, transfers, payments). These tools
+    /// must pass each spend through `spending::authorize_spend` before signing.
+    fn moves_value(&self) -> bool {
+        false
+    }
+
+    /// Whether the tool can add data to the workspace (writing files, running
+    /// commands). These are refused once the workspace is over its quota; tools
+    /// that only read or delete stay available so space can be freed.
+    fn writes_workspace(&self) -> bool {
+        false
+    }
+
+    /// Whether the tool only reads (files, chain data), so that calls to it can
+    /// run concurrently with each other
+    fn is_read_only(&self) -> bool {
+        false
+    }
+
+    /// Whether and how long a successful result of this call may be reused
+    /// for an identical call in the same job. Tools that may change the
+    /// workspace drop the job's `UntilWorkspaceChanges` results whenever they run.
+    fn cache_policy(&self, _params: &Value) -> CachePolicy {
+        CachePolicy::Never
+    }
+
+    /// Called when a call is answered from the cache instead of running the
+    /// tool, to repeat side effects such as setting registers
+    fn on_cache_hit(&self, _params: &Value, _result: &ToolResult, _context: &ToolContext) {}
 }
 
 /// Registry that holds all available tools
 pub struct ToolRegistry {
     tools: HashMap<String, Arc<dyn Tool>>,
     default_config: ToolConfig,
+    /// Groups switched off by the operator; applies on top of every ToolConfig
+    disabled_groups: RwLock<HashSet<ToolGroup>>,
+    /// Results of idempotent calls, reused within a job
+    cache: ToolCache,
 }
 
 impl ToolRegistry {
@@ -36,6 +88,8 @@ impl ToolRegistry {
         ToolRegistry {
             tools: HashMap::new(),
             default_config: ToolConfig::default(),
+            disabled_groups: RwLock::new(HashSet::new()),
+            cache: ToolCache::new(),
         }
     }
 
@@ -43,6 +97,8 @@ impl ToolRegistry {
         ToolRegistry {
             tools: HashMap::new(),
             default_config: config,
+            disabled_groups: RwLock::new(HashSet::new()),
+            cache: ToolCache::new(),
         }
     }
 
@@ -62,11 +118,75 @@ impl ToolRegistry {
         self.tools.values().collect()
     }
 
+    /// List registered tools belonging to a group
+    pub fn list_group(&self, group: ToolGroup) -> Vec<Arc<dyn Tool>> {
+        self.tools
+            .values()
+            .filter(|tool| tool.group() == group)
+            .cloned()
+            .collect()
+    }
+
+    /// Names of tools needing a scope that `scopes` doesn't grant
+    pub fn tools_outside_scopes(&self, scopes: &Scopes) -> Vec<String> {
+        let mut names: Vec<String> = self
+            .tools
+            .values()
+            .filter(|tool| tool.required_scope().is_some_and(|scope| !scopes.allows(scope)))
+            .map(|tool| tool.name())
+            .collect();
+        names.sort();
+        names
+    }
+
+    /// Names of tools `policy` doesn't allow
+    pub fn tools_outside_policy(&self, policy: &ToolPolicy) -> Vec<String> {
+        let mut names: Vec<String> = self
+            .tools
+            .values()
+            .filter(|tool| !policy.is_tool_allowed(&tool.name(), tool.group()))
+            .map(|tool| tool.name())
+            .collect();
+        names.sort();
+        names
+    }
+
+    /// Whether a group is enabled (all groups are enabled unless switched off)
+    pub fn is_group_enabled(&self, group: ToolGroup) -> bool {
+        !self.disabled_groups.read().unwrap().contains(&group)
+    }
+
+    /// Enable or disable every tool in a group at runtime
+    pub fn set_group_enabled(&self, group: ToolGroup, enabled: bool) {
+        let mut disabled = self.disabled_groups.write().unwrap();
+        if enabled {
+            disabled.remove(&group);
+        } else {
+            disabled.insert(group);
+        }
+        tracing::info!(
+            "[REGISTRY] Tool group '{}' {}",
+            group.as_str(),
+            if enabled { "enabled" } else { "disabled" }
+        );
+    }
+
+    /// Groups currently switched off
+    pub fn disabled_groups(&self) -> Vec<ToolGroup> {
+        self.disabled_groups.read().unwrap().iter().copied().collect()
+    }
+
+    /// Whether a tool may be offered/executed under `config` and the enabled groups
+    fn is_available(&self, tool: &Arc<dyn Tool>, config: &ToolConfig) -> bool {
+        let group = tool.group();
+        self.is_group_enabled(group) && config.is_tool_allowed(&tool.definition().name, group)
+    }
+
     /// Get tools that are allowed by a configuration
     pub fn get_allowed_tools(&self, config: &ToolConfig) -> Vec<Arc<dyn Tool>> {
         self.tools
             .values()
-            .filter(|tool| config.is_tool_allowed(&tool.definition().name, tool.group()))
+            .filter(|tool| self.is_available(tool, config))
             .cloned()
             .collect()
     }
@@ -86,8 +206,8 @@ impl ToolRegistry {
                 // System tools are always available
                 let group_allowed =
                     group == ToolGroup::System || allowed_groups.contains(&group);
-                // Also check against the tool config
-                group_allowed && config.is_tool_allowed(&tool.definition().name, group)
+                // Also check against the tool config and disabled groups
+                group_allowed && self.is_available(tool, config)
             })
             .cloned()
             .collect()
@@ -123,14 +243,22 @@ impl ToolRegistry {
         for tool_name in required_tools {
             if !tool_names.contains(tool_name) {
                 if let Some(tool) = self.get(tool_name) {
-                    log::info!(
+                    if !self.is_group_enabled(tool.group()) {
+                        tracing::warn!(
+                            "[REGISTRY] Required tool '{}' not included: group '{}' is disabled",
+                            tool_name,
+                            tool.group().as_str()
+                        );
+                        continue;
+                    }
+                    tracing::info!(
                         "[REGISTRY] Force-including required tool '{}' for active skill",
                         tool_name
                     );
                     tools.push(tool);
                     tool_names.insert(tool_name.clone());
                 } else {
-                    log::warn!(
+                    tracing::warn!(
                         "[REGISTRY] Required tool '{}' not found in registry",
                         tool_name
                     );
@@ -154,7 +282,8 @@ impl ToolRegistry {
         self.get_tool_definitions(&self.default_config)
     }
 
-    /// Execute a tool by name
+    /// Execute a tool by name. The call is added to the audit log when the
+    /// context has a database.
     pub async fn execute(
         &self,
         name: &str,
@@ -162,21 +291,126 @@ impl ToolRegistry {
         context: &ToolContext,
         config: Option<&ToolConfig>,
     ) -> ToolResult {
+        let Some(db) = &context.database else {
+            return self.run(name, params, context, config).await.0;
+        };
+
+        let started = Instant::now();
+        let arguments = sanitize_arguments(&params);
+        let (result, outcome) = self.run(name, params, context, config).await;
+        let invocation = NewToolInvocation {
+            tool_name: name.to_string(),
+            tool_group: self.get(name).map(|tool| tool.group().as_str().to_string()),
+            arguments,
+            outcome,
+            result_summary: summarize_result(result.error.as_deref().unwrap_or(&result.content)),
+            duration_ms: started.elapsed().as_millis() as i64,
+            channel_id: context.channel_id,
+            channel_type: context.channel_type.clone(),
+            session_id: context.session_id,
+            job_id: context.job_id.clone(),
+            user_id: context.user_id.clone(),
+            tool_call_id: context.get_tool_call_id(),
+            request_id: telemetry::current_request_id(),
+        };
+        if let Err(e) = db.record_tool_invocation(&invocation).await {
+            tracing::error!("Failed to add {} call to the audit log: {}", name, e);
+        }
+        result
+    }
+
+    /// Check and run a tool call, returning its result and how it ended
+    async fn run(
+        &self,
+        name: &str,
+        params: Value,
+        context: &ToolContext,
+        config: Option<&ToolConfig>,
+    ) -> (ToolResult, ToolOutcome) {
         let effective_config = config.unwrap_or(&self.default_config);
 
         // Get the tool
         let tool = match self.get(name) {
             Some(t) => t,
-            None => return ToolResult::error(format!("Tool '{}' not found", name)),
+            None => return (ToolResult::error(format!("Tool '{}' not found", name)), ToolOutcome::Denied),
         };
 
+        // Check if tool's group is switched off
+        if !self.is_group_enabled(tool.group()) {
+            let message = format!(
+                "Tool '{}' is not available: the {} group is disabled",
+                name,
+                tool.group().as_str()
+            );
+            return (ToolResult::error(message), ToolOutcome::Denied);
+        }
+
         // Check if tool is allowed
         if !effective_config.is_tool_allowed(name, tool.group()) {
-            return ToolResult::error(format!("Tool '{}' is not allowed", name));
+            return (ToolResult::error(format!("Tool '{}' is not allowed", name)), ToolOutcome::Denied);
+        }
+
+        // Check the token's and session's tool policies
+        if !context.policy_allows(name, tool.group()) {
+            let message = format!("Tool '{}' is not allowed by the tool policy", name);
+            return (ToolResult::error(message), ToolOutcome::Denied);
+        }
+
+        // Check the workspace still has room
+        if tool.writes_workspace()
+            && let Some(workspace) = &context.workspace_dir
+        {
+            let manager = WorkspaceManager::from_env();
+            if manager.quota_bytes().is_some() {
+                let path = PathBuf::from(workspace);
+                let checked = tokio::task::spawn_blocking(move || manager.check_quota(&path)).await;
+                if let Ok(Err(e)) = checked {
+                    return (ToolResult::error(e), ToolOutcome::Error);
+                }
+            }
         }
 
+        // Answer repeated idempotent calls from the cache; anything that may
+        // change the workspace makes the job's cached file listings stale
+        let cache_policy = match tool.cache_policy(&params) {
+            // Builds and dev servers running in the background change the
+            // workspace without going through the registry
+            CachePolicy::UntilWorkspaceChanges(_) if background_processes_running(context) => {
+                self.cache.invalidate_workspace(context);
+                CachePolicy::Never
+            }
+            policy => policy,
+        };
+        let cached_params = match cache_policy {
+            CachePolicy::Never => {
+                if !tool.is_read_only() {
+                    self.cache.invalidate_workspace(context);
+                }
+                None
+            }
+            _ => {
+                if let Some(result) = self.cache.get(name, &params, context) {
+                    tracing::debug!("[REGISTRY] Reusing cached result of '{}'", name);
+                    tool.on_cache_hit(&params, &result, context);
+                    return (result, ToolOutcome::Success);
+                }
+                Some(params.clone())
+            }
+        };
+
         // Execute the tool
-        tool.execute(params, context).await
+        let span = tracing::info_span!("tool", tool = name, error = tracing::field::Empty);
+        let result = tool.execute(params, context).instrument(span.clone()).await;
+        if let Some(params) = cached_params {
+            self.cache.put(name, &params, context, cache_policy, &result);
+        }
+        if !result.success {
+            let error = result.error.as_deref().unwrap_or(&result.content);
+            span.record("error", truncate_str(error, 500).as_str());
+        }
+        Metrics::global().record_tool_call(name, result.success);
+        let outcome = if result.success { ToolOutcome::Success } else { ToolOutcome::Error };
+        (result, outcome)
     }
 
     /// Get default configuration
@@ -194,6 +428,11 @@ impl ToolRegistry {
         self.tools.contains_key(name)
     }
 
+    /// Whether `name` is a registered read-only tool
+    pub fn is_read_only(&self, name: &str) -> bool {
+        self.tools.get(name).is_some_and(|tool| tool.is_read_only())
+    }
+
     /// Get count of registered tools
     pub fn len(&self) -> usize {
         self.tools.len()
@@ -211,6 +450,61 @@ impl Default for ToolRegistry {
     }
 }
 
+/// Builds a ToolRegistry from the built-in tool catalog plus any extra tools
+pub struct ToolRegistryBuilder {
+    tools: Vec<Arc<dyn Tool>>,
+    disabled_groups: HashSet<ToolGroup>,
+}
+
+impl ToolRegistryBuilder {
+    pub fn new() -> Self {
+        Self {
+            tools: Vec::new(),
+            disabled_groups: HashSet::new(),
+        }
+    }
+
+    /// Add every tool from `tools::builtin::all_tools`
+    pub fn with_builtin_tools(mut self) -> Self {
+        self.tools.extend(crate::tools::builtin::all_tools());
+        self
+    }
+
+    /// Start with these groups switched off
+    pub fn with_disabled_groups(mut self, groups: impl IntoIterator<Item = ToolGroup>) -> Self {
+        self.disabled_groups.extend(groups);
+        self
+    }
+
+    pub fn build(self) -> ToolRegistry {
+        let mut registry = ToolRegistry::new();
+        for tool in self.tools {
+            let name = tool.name();
+            if registry.has_tool(&name) {
+                tracing::warn!("[REGISTRY] Duplicate tool '{}' replaces earlier registration", name);
+            }
+            registry.register(tool);
+        }
+        *registry.disabled_groups.get_mut().unwrap() = self.disabled_groups;
+        registry
+    }
+}
+
+impl Default for ToolRegistryBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Whether the calling job or session has a background process that may still
+/// be writing to its workspace
+fn background_processes_running(context: &ToolContext) -> bool {
+    context
+        .process_manager
+        .as_ref()
+        .is_some_and(|manager| manager.has_running(context.job_id.as_deref(), context.session_id))
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -255,6 +549,74 @@ mod tests {
         assert_eq!(registry.len(), 1);
     }
 
+    #[test]
+    fn test_builder_includes_every_builtin_tool() {
+        let builtin = crate::tools::builtin::all_tools();
+        let registry = ToolRegistryBuilder::new().with_builtin_tools().build();
+        // No two built-in tools share a name
+        assert_eq!(registry.len(), builtin.len());
+        assert!(registry.has_tool("write_file"));
+        assert!(!registry.list_group(ToolGroup::Development).is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_disabled_group_hides_and_blocks_tools() {
+        let mut registry = ToolRegistry::new();
+        registry.register(Arc::new(MockTool::new("fetch", ToolGroup::Web)));
+        registry.register(Arc::new(MockTool::new("run", ToolGroup::Exec)));
+        registry.set_group_enabled(ToolGroup::Exec, false);
+        let config = ToolConfig::default();
+
+        let names: Vec<String> = registry
+            .get_tool_definitions(&config)
+            .into_iter()
+            .map(|d| d.name)
+            .collect();
+        assert_eq!(names, vec!["fetch".to_string()]);
+
+        let result = registry.execute("run", Value::Null, &ToolContext::default(), None).await;
+        assert!(!result.success);
+
+        registry.set_group_enabled(ToolGroup::Exec, true);
+        assert!(registry.is_group_enabled(ToolGroup::Exec));
+        let result = registry.execute("run", Value::Null, &ToolContext::default(), None).await;
+        assert!(result.success);
+
+        registry.set_group_enabled(ToolGroup::Web, false);
+        assert_eq!(registry.disabled_groups(), vec![ToolGroup::Web]);
+    }
+
+    #[tokio::test]
+    async fn test_execute_records_audit_log() {
+        let db = Arc::new(crate::db::Database::new(":memory:").unwrap());
+        let mut registry = ToolRegistry::new();
+        registry.register(Arc::new(MockTool::new("run", ToolGroup::Exec)));
+        let context = ToolContext::new()
+            .with_database(Arc::clone(&db))
+            .with_session(7)
+            .with_tool_call_id("call_1");
+        let deny_run = ToolConfig {
+            deny_list: vec!["run".to_string()],
+            ..Default::default()
+        };
+
+        let args = serde_json::json!({ "command": "ls", "password": "hunter2" });
+        assert!(registry.execute("run", args, &context, None).await.success);
+        assert!(!registry.execute("run", Value::Null, &context, Some(&deny_run)).await.success);
+        assert!(!registry.execute("missing", Value::Null, &context, None).await.success);
+        // Without a database nothing is recorded
+        registry.execute("run", Value::Null, &ToolContext::default(), None).await;
+
+        let (rows, total) = db.list_tool_invocations(&Default::default()).await.unwrap();
+        assert_eq!(total, 3);
+        assert_eq!((rows[0].tool_name.as_str(), rows[0].outcome, rows[0].tool_group.as_deref()), ("missing", ToolOutcome::Denied, None));
+        assert_eq!((rows[1].outcome, rows[1].tool_group.as_deref()), (ToolOutcome::Denied, Some("exec")));
+        assert_eq!(rows[2].outcome, ToolOutcome::Success);
+        assert_eq!(rows[2].arguments, serde_json::json!({ "command": "ls", "password": "[REDACTED]" }));
+        assert_eq!(rows[2].result_summary, "mock result");
+        assert_eq!((rows[2].session_id, rows[2].tool_call_id.as_deref()), (Some(7), Some("call_1")));
+    }
+
     #[test]
     fn test_tool_config_allows() {
         let config = ToolConfig {
@@ -283,4 +645,84 @@ mod tests {
         // Other tools should be allowed
         assert!(config.is_tool_allowed("safe_tool", ToolGroup::System));
     }
+
+    #[test]
+    fn test_tools_outside_scopes() {
+        let mut registry = ToolRegistry::new();
+        registry.register(Arc::new(MockTool::new("fetch", ToolGroup::Web)));
+        registry.register(Arc::new(MockTool::new("run", ToolGroup::Exec)));
+
+        let chat_only = Scopes::new([Scope::Chat]);
+        assert_eq!(registry.tools_outside_scopes(&chat_only), vec!["run".to_string()]);
+
+        let exec = Scopes::new([Scope::Chat, Scope::ToolsExec]);
+        assert!(registry.tools_outside_scopes(&exec).is_empty());
+        assert!(registry.tools_outside_scopes(&Scopes::full()).is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_tool_policy_enforced() {
+        let mut registry = ToolRegistry::new();
+        registry.register(Arc::new(MockTool::new("fetch", ToolGroup::Web)));
+        registry.register(Arc::new(MockTool::new("run", ToolGroup::Exec)));
+        let policy = ToolPolicy {
+            groups: [("web".to_string(), true)].into(),
+            ..Default::default()
+        };
+        assert_eq!(registry.tools_outside_policy(&policy), vec!["run".to_string()]);
+
+        let context = ToolContext::new().with_tool_policy(policy);
+        assert!(registry.execute("fetch", Value::Null, &context, None).await.success);
+        let denied = registry.execute("run", Value::Null, &context, None).await;
+        assert!(denied.error.unwrap().contains("tool policy"));
+        assert!(registry.execute("run", Value::Null, &ToolContext::new(), None).await.success);
+    }
+
+    struct CountingTool {
+        definition: ToolDefinition,
+        policy: CachePolicy,
+        runs: std::sync::atomic::AtomicUsize,
+    }
+
+    #[async_trait]
+    impl Tool for CountingTool {
+        fn definition(&self) -> ToolDefinition {
+            self.definition.clone()
+        }
+
+        fn cache_policy(&self, _params: &Value) -> CachePolicy {
+            self.policy
+        }
+
+        async fn execute(&self, _params: Value, _context: &ToolContext) -> ToolResult {
+            let runs = self.runs.fetch_add(1, std::sync::atomic::Ordering::SeqCst) + 1;
+            ToolResult::success(format!("run {}", runs))
+        }
+    }
+
+    #[tokio::test]
+    async fn test_cached_calls_skip_the_tool_until_the_workspace_changes() {
+        let counting = |name: &str, policy| {
+            Arc::new(CountingTool {
+                definition: MockTool::new(name, ToolGroup::Filesystem).definition,
+                policy,
+                runs: Default::default(),
+            })
+        };
+        let glob = counting("glob", CachePolicy::UntilWorkspaceChanges(std::time::Duration::from_secs(60)));
+        let mut registry = ToolRegistry::new();
+        registry.register(glob.clone());
+        registry.register(Arc::new(MockTool::new("write_file", ToolGroup::Filesystem)));
+        let context = ToolContext { job_id: Some("job-1".to_string()), ..Default::default() };
+        let args = serde_json::json!({ "pattern": "*.rs" });
+
+        assert_eq!(registry.execute("glob", args.clone(), &context, None).await.content, "run 1");
+        let repeated = registry.execute("glob", args.clone(), &context, None).await;
+        assert_eq!(repeated.content, "run 1");
+        assert_eq!(repeated.metadata.unwrap()["cached"], true);
+
+        registry.execute("write_file", Value::Null, &context, None).await;
+        assert_eq!(registry.execute("glob", args, &context, None).await.content, "run 2");
+        assert_eq!(glob.runs.load(std::sync::atomic::Ordering::SeqCst), 2);
+    }
 }
@@ -0,0 +1,356 @@
+//! Native file search for the glob and grep tools
+//!
+//! Both walk the tree with the `ignore` crate, so `.gitignore`/`.ignore` rules
+//! and hidden files are handled the same way as in `list_files`, and neither
+//! depends on `find`, `grep` or `rg` being installed. Paths in results are
+//! relative to the workspace root with `/` separators on every platform.
+//! This module has no dependencies on the rest of the crate so agent_test can
+//! share it.
+
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+use globset::{GlobBuilder, GlobMatcher};
+use grep_matcher::Matcher;
+use grep_regex::{RegexMatcher, RegexMatcherBuilder};
+use grep_searcher::{BinaryDetection, Searcher, SearcherBuilder, Sink, SinkContext, SinkContextKind, SinkMatch};
+use ignore::WalkBuilder;
+use serde::Serialize;
+
+/// Which files a search walks over
+#[derive(Debug, Clone, Copy)]
+pub struct WalkOptions {
+    pub include_hidden: bool,
+    pub respect_gitignore: bool,
+}
+
+impl Default for WalkOptions {
+    fn default() -> Self {
+        WalkOptions {
+            include_hidden: false,
+            respect_gitignore: true,
+        }
+    }
+}
+
+/// A file found by [`find_files`]
+#[derive(Debug, Clone)]
+pub struct FileHit {
+    pub path: PathBuf,
+    /// Path relative to the workspace root
+    pub relative: String,
+    pub size: u64,
+    pub modified: SystemTime,
+}
+
+/// One matching line found by [`grep`]
+#[derive(Debug, Clone, Serialize)]
+pub struct GrepMatch {
+    /// Path relative to the workspace root
+    pub file: String,
+    /// 1-based line number
+    pub line: u64,
+    /// 1-based byte column of the first match on the line
+    pub column: usize,
+    /// The matching line, without its line terminator
+    #[serde(rename = "match")]
+    pub text: String,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub before: Vec<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub after: Vec<String>,
+}
+
+/// Options for [`grep`]
+#[derive(Debug, Clone, Default)]
+pub struct GrepOptions {
+    pub case_insensitive: bool,
+    /// Only search files whose name matches this glob, e.g. `*.rs` or `*.{ts,tsx}`.
+    /// A glob containing `/` is matched against the path instead.
+    pub glob: Option<String>,
+    /// Lines of context to capture before and after each match
+    pub context: usize,
+    /// Stop after this many matches in total
+    pub max_results: usize,
+    /// Record only the first match in each file
+    pub first_match_only: bool,
+    pub walk: WalkOptions,
+}
+
+/// Result of a [`grep`] run
+#[derive(Debug, Clone, Default)]
+pub struct GrepOutcome {
+    pub matches: Vec<GrepMatch>,
+    /// True if the search stopped after reaching `max_results`
+    pub truncated: bool,
+}
+
+impl GrepOutcome {
+    /// Files with matches and how many each had, in search order
+    pub fn counts(&self) -> Vec<(String, usize)> {
+        let mut counts: Vec<(String, usize)> = Vec::new();
+        for m in &self.matches {
+            match counts.last_mut() {
+                Some((file, count)) if *file == m.file => *count += 1,
+                _ => counts.push((m.file.clone(), 1)),
+            }
+        }
+        counts
+    }
+
+    /// Render matches like `rg -n`: `file:line:text` for matches,
+    /// `file-line-text` for context lines and `--` between separate groups
+    pub fn render(&self) -> String {
+        let mut out = String::new();
+        let has_context = self.matches.iter().any(|m| !m.before.is_empty() || !m.after.is_empty());
+        let mut last: Option<(&str, u64)> = None;
+        for m in &self.matches {
+            let first_line = m.line - m.before.len() as u64;
+            if has_context
+                && let Some((file, line)) = last
+                && (file != m.file || line + 1 < first_line)
+            {
+                out.push_str("--\n");
+            }
+            for (i, text) in m.before.iter().enumerate() {
+                out.push_str(&format!("{}-{}-{}\n", m.file, first_line + i as u64, text));
+            }
+            out.push_str(&format!("{}:{}:{}\n", m.file, m.line, m.text));
+            for (i, text) in m.after.iter().enumerate() {
+                out.push_str(&format!("{}-{}-{}\n", m.file, m.line + 1 + i as u64, text));
+            }
+            last = Some((&m.file, m.line + m.after.len() as u64));
+        }
+        out
+    }
+}
+
+/// Compile a glob where `*` stays within one path component and `**` crosses directories
+pub fn compile_glob(pattern: &str) -> Result<GlobMatcher, String> {
+    GlobBuilder::new(pattern)
+        .literal_separator(true)
+        .build()
+        .map(|glob| glob.compile_matcher())
+        .map_err(|e| format!("Invalid glob pattern '{}': {}", pattern, e))
+}
+
+fn relative_to(root: &Path, path: &Path) -> String {
+    let relative = path.strip_prefix(root).unwrap_or(path);
+    relative.to_string_lossy().replace('\\', "/")
+}
+
+fn walker(base: &Path, options: WalkOptions) -> ignore::Walk {
+    let respect = options.respect_gitignore;
+    WalkBuilder::new(base)
+        .hidden(!options.include_hidden)
+        .git_ignore(respect)
+        .git_exclude(respect)
+        .git_global(false)
+        .ignore(respect)
+        .parents(respect)
+        .require_git(false)
+        .follow_links(false)
+        .sort_by_file_path(|a, b| a.cmp(b))
+        .filter_entry(|entry| entry.depth() == 0 || entry.file_name() != ".git")
+        .build()
+}
+
+/// Files under `base` whose path relative to `base` matches `pattern`, in path order
+pub fn find_files(root: &Path, base: &Path, pattern: &str, options: WalkOptions) -> Result<Vec<FileHit>, String> {
+    let matcher = compile_glob(pattern.trim_start_matches("./"))?;
+    let mut hits = Vec::new();
+
+    for entry in walker(base, options).flatten() {
+        if !entry.file_type().is_some_and(|t| t.is_file()) {
+            continue;
+        }
+        let path = entry.path();
+        let Ok(within_base) = path.strip_prefix(base) else {
+            continue;
+        };
+        if !matcher.is_match(within_base) {
+            continue;
+        }
+        let Ok(metadata) = entry.metadata() else {
+            continue;
+        };
+        hits.push(FileHit {
+            path: path.to_path_buf(),
+            relative: relative_to(root, path),
+            size: metadata.len(),
+            modified: metadata.modified().unwrap_or(SystemTime::UNIX_EPOCH),
+        });
+    }
+
+    Ok(hits)
+}
+
+/// Collects matches (and their context) for one file
+struct MatchSink<'a> {
+    matcher: &'a RegexMatcher,
+    file: &'a str,
+    matches: &'a mut Vec<GrepMatch>,
+    pending_before: Vec<String>,
+    remaining: usize,
+    first_match_only: bool,
+}
+
+fn line_text(bytes: &[u8]) -> String {
+    let text = String::from_utf8_lossy(bytes);
+    text.trim_end_matches(['\n', '\r']).to_string()
+}
+
+impl Sink for MatchSink<'_> {
+    type Error = io::Error;
+
+    fn matched(&mut self, _searcher: &Searcher, mat: &SinkMatch<'_>) -> Result<bool, io::Error> {
+        let column = self
+            .matcher
+            .find(mat.bytes())
+            .ok()
+            .flatten()
+            .map(|m| m.start() + 1)
+            .unwrap_or(1);
+        self.matches.push(GrepMatch {
+            file: self.file.to_string(),
+            line: mat.line_number().unwrap_or(0),
+            column,
+            text: line_text(mat.bytes()),
+            before: std::mem::take(&mut self.pending_before),
+            after: Vec::new(),
+        });
+        self.remaining -= 1;
+        Ok(self.remaining > 0 && !self.first_match_only)
+    }
+
+    fn context(&mut self, _searcher: &Searcher, ctx: &SinkContext<'_>) -> Result<bool, io::Error> {
+        match ctx.kind() {
+            SinkContextKind::Before => self.pending_before.push(line_text(ctx.bytes())),
+            SinkContextKind::After => {
+                if let Some(last) = self.matches.last_mut() {
+                    last.after.push(line_text(ctx.bytes()));
+                }
+            }
+            SinkContextKind::Other => {}
+        }
+        Ok(true)
+    }
+}
+
+/// Search files under `base` for the regex `pattern`. Binary files are skipped.
+pub fn grep(root: &Path, base: &Path, pattern: &str, options: &GrepOptions) -> Result<GrepOutcome, String> {
+    let matcher = RegexMatcherBuilder::new()
+        .case_insensitive(options.case_insensitive)
+        .build(pattern)
+        .map_err(|e| format!("Invalid regex pattern: {}", e))?;
+    let filter = match &options.glob {
+        Some(glob) => Some((compile_glob(glob)?, glob.contains('/'))),
+        None => None,
+    };
+    let mut searcher = SearcherBuilder::new()
+        .line_number(true)
+        .binary_detection(BinaryDetection::quit(b'\x00'))
+        .before_context(options.context)
+        .after_context(options.context)
+        .build();
+
+    let mut outcome = GrepOutcome::default();
+    let max_results = options.max_results.max(1);
+
+    for entry in walker(base, options.walk).flatten() {
+        if !entry.file_type().is_some_and(|t| t.is_file()) {
+            continue;
+        }
+        let path = entry.path();
+        if let Some((glob, match_path)) = &filter {
+            let candidate = if *match_path {
+                path.strip_prefix(base).unwrap_or(path)
+            } else {
+                Path::new(path.file_name().unwrap_or_default())
+            };
+            if !glob.is_match(candidate) {
+                continue;
+            }
+        }
+        let file = relative_to(root, path);
+        let mut sink = MatchSink {
+            matcher: &matcher,
+            file: &file,
+            remaining: max_results - outcome.matches.len(),
+            matches: &mut outcome.matches,
+            pending_before: Vec::new(),
+            first_match_only: options.first_match_only,
+        };
+        // Unreadable files are skipped like binary ones
+        let _ = searcher.search_path(&matcher, path, &mut sink);
+        if outcome.matches.len() >= max_results {
+            outcome.truncated = true;
+            break;
+        }
+    }
+
+    Ok(outcome)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_find_files_and_grep() {
+        let dir = TempDir::new().unwrap();
+        let root = dir.path();
+        fs::create_dir_all(root.join("src/nested")).unwrap();
+        fs::create_dir_all(root.join("target")).unwrap();
+        fs::write(root.join(".gitignore"), "target/\n").unwrap();
+        fs::write(root.join("main.rs"), "fn main() {\n    run();\n}\n").unwrap();
+        fs::write(root.join("src/lib.rs"), "// TODO: one\nfn a() {}\n// todo: two\n").unwrap();
+        fs::write(root.join("src/nested/deep.rs"), "fn run() {}\n").unwrap();
+        fs::write(root.join("target/out.rs"), "// TODO: generated\n").unwrap();
+        fs::write(root.join("src/blob.bin"), b"TODO\x00\x01").unwrap();
+
+        let names = |hits: Vec<FileHit>| hits.into_iter().map(|h| h.relative).collect::<Vec<_>>();
+        let all = find_files(root, root, "**/*.rs", WalkOptions::default()).unwrap();
+        assert_eq!(names(all), vec!["main.rs", "src/lib.rs", "src/nested/deep.rs"]);
+        let top = find_files(root, root, "*.rs", WalkOptions::default()).unwrap();
+        assert_eq!(names(top), vec!["main.rs"]);
+        let src = root.join("src");
+        let nested = find_files(root, &src, "nested/*.rs", WalkOptions::default()).unwrap();
+        assert_eq!(names(nested), vec!["src/nested/deep.rs"]);
+
+        let options = GrepOptions {
+            case_insensitive: true,
+            max_results: 100,
+            ..Default::default()
+        };
+        let outcome = grep(root, root, "todo", &options).unwrap();
+        let found: Vec<(&str, u64)> = outcome.matches.iter().map(|m| (m.file.as_str(), m.line)).collect();
+        assert_eq!(found, vec![("src/lib.rs", 1), ("src/lib.rs", 3)]);
+        assert_eq!(outcome.matches[1].column, 4);
+        assert_eq!(outcome.counts(), vec![("src/lib.rs".to_string(), 2)]);
+
+        let options = GrepOptions {
+            glob: Some("main.*".to_string()),
+            context: 1,
+            max_results: 100,
+            ..Default::default()
+        };
+        let outcome = grep(root, root, r"run\(", &options).unwrap();
+        assert_eq!(outcome.render(), "main.rs-1-fn main() {\nmain.rs:2:    run();\nmain.rs-3-}\n");
+        let json = serde_json::to_value(&outcome.matches[0]).unwrap();
+        assert_eq!(json["match"], "    run();");
+        assert_eq!(json["before"][0], "fn main() {");
+
+        let options = GrepOptions {
+            max_results: 1,
+            ..Default::default()
+        };
+        let outcome = grep(root, root, "fn", &options).unwrap();
+        assert_eq!(outcome.matches.len(), 1);
+        assert!(outcome.truncated);
+        assert!(grep(root, root, "(", &options).is_err());
+    }
+}
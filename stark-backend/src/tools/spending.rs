@@ -0,0 +1,317 @@
+//! Spending policy for value-moving tools
+//!
+//! Tools that move value (swaps, transfers, x402 payments) call
+//! `authorize_spend` right before they sign. The limits come from the active
+//! agent settings: a per-call and a per-UTC-day USD cap, which reject the call,
+//! and an approval threshold above which the call waits until an operator
+//! decides through `POST /api/approvals/{id}`.
+
+use chrono::{NaiveTime, Utc};
+use ethers::types::U256;
+use serde_json::json;
+use std::time::{Duration, Instant};
+
+use crate::db::Database;
+use crate::gateway::protocol::GatewayEvent;
+use crate::models::{AgentSettings, ApprovalStatus, NewApproval};
+use crate::tools::builtin::TokenPriceTool;
+use crate::tools::network::Network;
+use crate::tools::types::ToolContext;
+
+/// How long a tool waits for an operator to decide
+pub const APPROVAL_TIMEOUT: Duration = Duration::from_secs(600);
+
+/// How often a waiting tool checks its approval
+const APPROVAL_POLL_INTERVAL: Duration = Duration::from_secs(2);
+
+/// USD limits for value-moving tools (unset means no limit)
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct SpendingPolicy {
+    pub max_tx_usd: Option<f64>,
+    pub max_daily_usd: Option<f64>,
+    pub approval_usd: Option<f64>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum SpendDecision {
+    Allow,
+    /// Wait for an operator; carries the reason shown to them
+    NeedsApproval(String),
+    /// Over a hard limit; carries the reason returned to the agent
+    Deny(String),
+}
+
+impl SpendingPolicy {
+    pub fn from_settings(settings: &AgentSettings) -> Self {
+        Self {
+            max_tx_usd: settings.spend_max_tx_usd,
+            max_daily_usd: settings.spend_max_daily_usd,
+            approval_usd: settings.spend_approval_usd,
+        }
+    }
+
+    pub fn is_unlimited(&self) -> bool {
+        self.max_tx_usd.is_none() && self.max_daily_usd.is_none() && self.approval_usd.is_none()
+    }
+
+    /// Decide a spend of `amount_usd` (None when it could not be priced),
+    /// given what tools already spent today
+    pub fn evaluate(&self, amount_usd: Option<f64>, spent_today_usd: f64) -> SpendDecision {
+        if self.is_unlimited() {
+            return SpendDecision::Allow;
+        }
+        let Some(amount) = amount_usd else {
+            return SpendDecision::NeedsApproval("the amount could not be priced in USD".to_string());
+        };
+        if let Some(max) = self.max_tx_usd
+            && amount > max
+        {
+            return SpendDecision::Deny(format!(
+                "${:.2} is over the ${:.2} per-transaction limit",
+                amount, max
+            ));
+        }
+        if let Some(max) = self.max_daily_usd
+            && spent_today_usd + amount > max
+        {
+            return SpendDecision::Deny(format!(
+                "${:.2} would bring today's spend to ${:.2}, over the ${:.2} daily limit",
+                amount,
+                spent_today_usd + amount,
+                max
+            ));
+        }
+        if let Some(threshold) = self.approval_usd
+            && amount > threshold
+        {
+            return SpendDecision::NeedsApproval(format!(
+                "${:.2} is above the ${:.2} approval threshold",
+                amount, threshold
+            ));
+        }
+        SpendDecision::Allow
+    }
+}
+
+/// USD value of `amount` base units of `token` (symbol or address), or None
+/// if it cannot be priced right now
+pub async fn price_usd(token: &str, amount: U256, network: Network, context: &ToolContext) -> Option<f64> {
+    if amount.is_zero() {
+        return Some(0.0);
+    }
+    match TokenPriceTool::usd_value(token, amount, network, context).await {
+        Ok(value) => Some(value),
+        Err(e) => {
+            log::warn!("[spending] Could not price {} of {} on {}: {}", amount, token, network, e);
+            None
+        }
+    }
+}
+
+/// Check a spend against the policy of the active agent settings, waiting for
+/// an operator when it needs approval. Returns the reason when it may not go ahead.
+pub async fn authorize_spend(
+    context: &ToolContext,
+    tool_name: &str,
+    amount_usd: Option<f64>,
+    description: &str,
+) -> Result<(), String> {
+    let Some(db) = &context.database else {
+        return Ok(());
+    };
+    let settings = db.get_active_agent_settings().await.ok().flatten().unwrap_or_default();
+    let policy = SpendingPolicy::from_settings(&settings);
+    if policy.is_unlimited() {
+        return Ok(());
+    }
+
+    let today = Utc::now().date_naive().and_time(NaiveTime::MIN).and_utc();
+    let spent_today = db
+        .spent_usd_since(today)
+        .await
+        .map_err(|e| format!("Could not check today's spend: {}", e))?;
+
+    match policy.evaluate(amount_usd, spent_today) {
+        SpendDecision::Allow => Ok(()),
+        SpendDecision::Deny(reason) => {
+            log::warn!("[spending] Denied {} ({}): {}", tool_name, description, reason);
+            Err(format!("Spending limit: {}", reason))
+        }
+        SpendDecision::NeedsApproval(reason) => {
+            wait_for_approval(context, db, tool_name, amount_usd, description, &reason).await
+        }
+    }
+}
+
+/// User whose conversation or background job the tool runs for
+async fn requester(context: &ToolContext, db: &Database) -> Option<i64> {
+    if let Some(session_id) = context.session_id {
+        return db.get_chat_session(session_id).await.ok().flatten().and_then(|session| session.user_id);
+    }
+    let job_id = context.job_id.as_deref()?;
+    db.get_agent_job(job_id).await.ok().flatten().and_then(|job| job.user_id)
+}
+
+async fn wait_for_approval(
+    context: &ToolContext,
+    db: &Database,
+    tool_name: &str,
+    amount_usd: Option<f64>,
+    description: &str,
+    reason: &str,
+) -> Result<(), String> {
+    let approval = db
+        .create_approval(&NewApproval {
+            tool_name: tool_name.to_string(),
+            tool_call_id: context.get_tool_call_id(),
+            channel_id: context.channel_id,
+            session_id: context.session_id,
+            description: description.to_string(),
+            amount_usd,
+            reason: reason.to_string(),
+            user_id: requester(context, db).await,
+        })
+        .await
+        .map_err(|e| format!("Could not request approval: {}", e))?;
+    let approval_id = approval.id.to_string();
+    log::info!(
+        "[spending] {} waiting for approval #{}: {} ({})",
+        tool_name, approval.id, description, reason
+    );
+
+    let broadcast = |event: GatewayEvent| {
+        if let Some(broadcaster) = &context.broadcaster {
+            broadcaster.broadcast(event);
+        }
+    };
+    if let Some(channel_id) = context.channel_id {
+        broadcast(GatewayEvent::confirmation_required(
+            channel_id,
+            &approval_id,
+            tool_name,
+            description,
+            &json!({ "approval_id": approval.id, "amount_usd": amount_usd, "reason": reason }),
+        ));
+    }
+
+    let deadline = Instant::now() + APPROVAL_TIMEOUT;
+    loop {
+        tokio::time::sleep(APPROVAL_POLL_INTERVAL).await;
+
+        let status = match db.get_approval(approval.id).await {
+            Ok(Some(current)) => current.status,
+            Ok(None) => return Err(format!("Approval #{} was deleted", approval.id)),
+            Err(e) => {
+                log::warn!("[spending] Could not read approval #{}: {}", approval.id, e);
+                ApprovalStatus::Pending
+            }
+        };
+        match status {
+            ApprovalStatus::Approved => {
+                if let Some(channel_id) = context.channel_id {
+                    broadcast(GatewayEvent::confirmation_approved(channel_id, &approval_id, tool_name));
+                }
+                log::info!("[spending] Approval #{} granted", approval.id);
+                return Ok(());
+            }
+            ApprovalStatus::Rejected => {
+                if let Some(channel_id) = context.channel_id {
+                    broadcast(GatewayEvent::confirmation_rejected(channel_id, &approval_id, tool_name));
+                }
+                return Err(format!(
+                    "An operator rejected this spend (approval #{}). Do not retry it unless the user asks.",
+                    approval.id
+                ));
+            }
+            ApprovalStatus::Expired => {
+                return Err(format!("Approval #{} expired", approval.id));
+            }
+            ApprovalStatus::Pending => {}
+        }
+
+        // Only expire it if nobody decided in the meantime
+        if Instant::now() >= deadline
+            && db.decide_approval(approval.id, ApprovalStatus::Expired).await.unwrap_or(false)
+        {
+            if let Some(channel_id) = context.channel_id {
+                broadcast(GatewayEvent::confirmation_expired(channel_id, &approval_id, tool_name));
+            }
+            return Err(format!(
+                "No operator approved this spend within {} minutes (approval #{})",
+                APPROVAL_TIMEOUT.as_secs() / 60,
+                approval.id
+            ));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_evaluate() {
+        assert_eq!(SpendingPolicy::default().evaluate(None, 1e9), SpendDecision::Allow);
+
+        let policy = SpendingPolicy {
+            max_tx_usd: Some(1000.0),
+            max_daily_usd: Some(1500.0),
+            approval_usd: Some(100.0),
+        };
+        assert_eq!(policy.evaluate(Some(50.0), 0.0), SpendDecision::Allow);
+        assert_eq!(policy.evaluate(Some(100.0), 0.0), SpendDecision::Allow);
+        assert!(matches!(policy.evaluate(Some(500.0), 0.0), SpendDecision::NeedsApproval(_)));
+        assert!(matches!(policy.evaluate(None, 0.0), SpendDecision::NeedsApproval(_)));
+
+        // Hard limits win over approval
+        let SpendDecision::Deny(reason) = policy.evaluate(Some(1200.0), 0.0) else {
+            panic!("expected the per-transaction limit to deny");
+        };
+        assert!(reason.contains("per-transaction"));
+        let SpendDecision::Deny(reason) = policy.evaluate(Some(50.0), 1480.0) else {
+            panic!("expected the daily limit to deny");
+        };
+        assert!(reason.contains("daily"));
+
+        let approval_only = SpendingPolicy { approval_usd: Some(10.0), ..Default::default() };
+        assert_eq!(approval_only.evaluate(Some(5.0), 1e6), SpendDecision::Allow);
+    }
+
+    #[tokio::test]
+    async fn test_authorize_spend() {
+        use crate::models::UpdateAgentSettingsRequest;
+        use std::sync::Arc;
+
+        let db = Arc::new(Database::new(":memory:").unwrap());
+        let context = ToolContext::new().with_database(db.clone());
+        assert!(authorize_spend(&context, "swap", None, "Swap").await.is_ok());
+
+        db.save_agent_settings(&UpdateAgentSettingsRequest {
+            endpoint: "https://a.example".to_string(),
+            model_archetype: "kimi".to_string(),
+            max_tokens: 1000,
+            secret_key: None,
+            budget_max_tokens: None,
+            budget_max_usd: None,
+            session_budget_max_tokens: None,
+            session_budget_max_usd: None,
+            session_ttl_hours: None,
+            session_sliding: true,
+            allowed_models: Vec::new(),
+            spend_max_tx_usd: Some(100.0),
+            spend_max_daily_usd: None,
+            spend_approval_usd: None,
+            max_context_tokens: None,
+            fallback_endpoint: None,
+            fallback_model_archetype: None,
+            fallback_model: None,
+            fallback_secret_key: None,
+            default_persona: None,
+        })
+        .await
+        .unwrap();
+        assert!(authorize_spend(&context, "swap", Some(20.0), "Swap").await.is_ok());
+        let err = authorize_spend(&context, "swap", Some(200.0), "Swap").await.unwrap_err();
+        assert!(err.contains("per-transaction"));
+    }
+}
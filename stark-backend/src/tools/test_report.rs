@@ -0,0 +1,367 @@
+//! Test framework detection and result parsing for the run_tests tool
+//!
+//! Knows how to invoke `cargo test`, `npm test` and `pytest`, and turns their
+//! output into pass/fail/skip counts plus the failing tests, so the model
+//! gets a short summary instead of the raw log.
+
+use std::collections::HashMap;
+use std::path::Path;
+
+use once_cell::sync::Lazy;
+use regex::Regex;
+use serde::Serialize;
+
+/// Longest failure message kept per test, in characters
+const MAX_MESSAGE_CHARS: usize = 600;
+/// Most failing tests listed
+pub const MAX_FAILURES: usize = 30;
+
+static ANSI_ESCAPE: Lazy<Regex> = Lazy::new(|| Regex::new(r"\x1b\[[0-9;]*[A-Za-z]").unwrap());
+static COUNT: Lazy<Regex> = Lazy::new(|| Regex::new(r"(\d+) ([a-z]+)").unwrap());
+static CARGO_TEST_LINE: Lazy<Regex> = Lazy::new(|| Regex::new(r"^test (\S+)(?: - .*)? \.\.\. (\w+)").unwrap());
+static PYTEST_FAILURE: Lazy<Regex> = Lazy::new(|| Regex::new(r"^(?:FAILED|ERROR) (\S+)(?: - (.*))?$").unwrap());
+static PYTEST_SUMMARY: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"^=*\s*(\d+ [a-z]+(?:, \d+ [a-z]+)*) in [\d.]+s").unwrap());
+static VITEST_SUMMARY: Lazy<Regex> = Lazy::new(|| Regex::new(r"^\s*Tests\s+(.*\d+ [a-z]+.*)$").unwrap());
+static MOCHA_COUNT: Lazy<Regex> = Lazy::new(|| Regex::new(r"^\s*(\d+) (passing|failing|pending)").unwrap());
+
+/// A supported test runner
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Framework {
+    Cargo,
+    Npm,
+    Pytest,
+}
+
+impl Framework {
+    pub fn from_str(s: &str) -> Option<Self> {
+        match s.to_lowercase().as_str() {
+            "cargo" | "rust" => Some(Framework::Cargo),
+            "npm" | "node" | "jest" | "vitest" | "mocha" => Some(Framework::Npm),
+            "pytest" | "python" => Some(Framework::Pytest),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Framework::Cargo => "cargo",
+            Framework::Npm => "npm",
+            Framework::Pytest => "pytest",
+        }
+    }
+
+    /// Guess the framework from the project files in `dir`
+    pub fn detect(dir: &Path) -> Option<Self> {
+        if dir.join("Cargo.toml").is_file() {
+            return Some(Framework::Cargo);
+        }
+        if let Ok(manifest) = std::fs::read_to_string(dir.join("package.json")) {
+            let has_test_script = serde_json::from_str::<serde_json::Value>(&manifest)
+                .ok()
+                .and_then(|m| m["scripts"]["test"].as_str().map(|s| !s.contains("no test specified")))
+                .unwrap_or(false);
+            if has_test_script {
+                return Some(Framework::Npm);
+            }
+        }
+        let python_markers = ["pytest.ini", "conftest.py", "pyproject.toml", "setup.cfg", "tox.ini", "setup.py"];
+        if python_markers.iter().any(|m| dir.join(m).is_file()) {
+            return Some(Framework::Pytest);
+        }
+        None
+    }
+
+    /// Shell command running the tests, optionally only those matching `filter`
+    pub fn command(&self, filter: Option<&str>) -> String {
+        let filter = filter.filter(|f| !f.trim().is_empty()).map(shell_quote);
+        match (self, filter) {
+            (Framework::Cargo, None) => "cargo test --no-fail-fast".to_string(),
+            (Framework::Cargo, Some(f)) => format!("cargo test --no-fail-fast -- {}", f),
+            (Framework::Npm, None) => "npm test".to_string(),
+            // jest and vitest both take a name pattern after `--`
+            (Framework::Npm, Some(f)) => format!("npm test -- -t {}", f),
+            (Framework::Pytest, None) => "python3 -m pytest -rfE".to_string(),
+            (Framework::Pytest, Some(f)) => format!("python3 -m pytest -rfE -k {}", f),
+        }
+    }
+}
+
+/// One failing test
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct FailedTest {
+    pub name: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub message: Option<String>,
+}
+
+/// Counts and failures parsed from a test run
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct TestSummary {
+    pub passed: usize,
+    pub failed: usize,
+    pub skipped: usize,
+    pub failures: Vec<FailedTest>,
+    /// Whether a result summary was found. When false (e.g. the build failed)
+    /// the counts are meaningless and the raw output should be shown instead.
+    pub parsed: bool,
+}
+
+impl TestSummary {
+    fn push_failure(&mut self, name: &str, message: Option<&str>) {
+        if self.failures.len() >= MAX_FAILURES || self.failures.iter().any(|f| f.name == name) {
+            return;
+        }
+        self.failures.push(FailedTest {
+            name: name.to_string(),
+            message: message
+                .map(|m| crate::utils::truncate_chars(m.trim(), MAX_MESSAGE_CHARS).to_string())
+                .filter(|m| !m.is_empty()),
+        });
+    }
+}
+
+/// Parse a run's combined stdout and stderr
+pub fn parse(framework: Framework, output: &str) -> TestSummary {
+    let output = ANSI_ESCAPE.replace_all(output, "");
+    match framework {
+        Framework::Cargo => parse_cargo(&output),
+        Framework::Npm => parse_npm(&output),
+        Framework::Pytest => parse_pytest(&output),
+    }
+}
+
+fn parse_cargo(output: &str) -> TestSummary {
+    let mut summary = TestSummary::default();
+
+    // Panic output per failing test, from the `---- name stdout ----` blocks
+    let mut messages: HashMap<&str, String> = HashMap::new();
+    let mut current: Option<&str> = None;
+    for line in output.lines() {
+        if let Some(name) = line.strip_prefix("---- ").and_then(|l| l.strip_suffix(" stdout ----")) {
+            current = Some(name);
+            continue;
+        }
+        if line == "failures:" || line.starts_with("test result:") {
+            current = None;
+        }
+        let keep = !line.trim().is_empty() && !line.starts_with("note: run with `RUST_BACKTRACE");
+        if let Some(name) = current.filter(|_| keep) {
+            let message = messages.entry(name).or_default();
+            message.push_str(line);
+            message.push('\n');
+        }
+    }
+
+    for line in output.lines() {
+        if let Some(caps) = CARGO_TEST_LINE.captures(line) {
+            if &caps[2] == "FAILED" {
+                summary.push_failure(&caps[1], messages.get(&caps[1]).map(String::as_str));
+            }
+        } else if let Some(rest) = line.strip_prefix("test result: ") {
+            summary.parsed = true;
+            for (n, word) in counts(rest) {
+                match word {
+                    "passed" => summary.passed += n,
+                    "failed" => summary.failed += n,
+                    "ignored" => summary.skipped += n,
+                    _ => {}
+                }
+            }
+        }
+    }
+    summary
+}
+
+fn parse_pytest(output: &str) -> TestSummary {
+    let mut summary = TestSummary::default();
+    for line in output.lines() {
+        if let Some(caps) = PYTEST_FAILURE.captures(line) {
+            summary.push_failure(&caps[1], caps.get(2).map(|m| m.as_str()));
+        } else if let Some(caps) = PYTEST_SUMMARY.captures(line.trim()) {
+            // Only the final summary line counts; earlier matches are overwritten
+            summary.parsed = true;
+            summary.passed = 0;
+            summary.failed = 0;
+            summary.skipped = 0;
+            for (n, word) in counts(&caps[1]) {
+                match word {
+                    "passed" | "xpassed" => summary.passed += n,
+                    "failed" | "error" | "errors" => summary.failed += n,
+                    "skipped" | "xfailed" => summary.skipped += n,
+                    _ => {}
+                }
+            }
+        }
+    }
+    summary
+}
+
+/// `npm test` runs whatever the project uses; jest, vitest and mocha are understood
+fn parse_npm(output: &str) -> TestSummary {
+    let mut summary = TestSummary::default();
+    for line in output.lines() {
+        let trimmed = line.trim();
+        if let Some(rest) = trimmed.strip_prefix("Tests:") {
+            // jest: "Tests:       1 failed, 2 skipped, 5 passed, 8 total"
+            summary.parsed = true;
+            add_npm_counts(&mut summary, rest);
+        } else if let Some(caps) = VITEST_SUMMARY.captures(line) {
+            // vitest: "Tests  1 failed | 5 passed (6)"
+            summary.parsed = true;
+            add_npm_counts(&mut summary, &caps[1]);
+        } else if let Some(caps) = MOCHA_COUNT.captures(line) {
+            summary.parsed = true;
+            let n: usize = caps[1].parse().unwrap_or(0);
+            match &caps[2] {
+                "passing" => summary.passed += n,
+                "failing" => summary.failed += n,
+                _ => summary.skipped += n,
+            }
+        } else if let Some(name) = trimmed.strip_prefix("● ") {
+            // jest failure heading: "● Suite › test name"
+            if !name.starts_with("Console") {
+                summary.push_failure(name, None);
+            }
+        } else if let Some(name) = trimmed.strip_prefix("FAIL ").filter(|n| n.contains(" > ")) {
+            // vitest failure: "FAIL  src/a.test.ts > suite > test name"
+            summary.push_failure(name.trim(), None);
+        }
+    }
+    summary
+}
+
+fn add_npm_counts(summary: &mut TestSummary, text: &str) {
+    for (n, word) in counts(text) {
+        match word {
+            "passed" => summary.passed += n,
+            "failed" => summary.failed += n,
+            "skipped" | "todo" | "pending" => summary.skipped += n,
+            _ => {}
+        }
+    }
+}
+
+/// Every "<number> <word>" pair in a summary line
+fn counts(text: &str) -> impl Iterator<Item = (usize, &str)> {
+    COUNT
+        .captures_iter(text)
+        .filter_map(|caps| Some((caps[1].parse().ok()?, caps.get(2)?.as_str())))
+}
+
+/// Quote a value as a single shell word
+fn shell_quote(value: &str) -> String {
+    format!("'{}'", value.replace('\'', r"'\''"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_detect_and_command() {
+        let dir = TempDir::new().unwrap();
+        assert_eq!(Framework::detect(dir.path()), None);
+        std::fs::write(dir.path().join("package.json"), r#"{"scripts":{"test":"echo \"Error: no test specified\""}}"#).unwrap();
+        assert_eq!(Framework::detect(dir.path()), None);
+        std::fs::write(dir.path().join("pyproject.toml"), "").unwrap();
+        assert_eq!(Framework::detect(dir.path()), Some(Framework::Pytest));
+        std::fs::write(dir.path().join("package.json"), r#"{"scripts":{"test":"jest"}}"#).unwrap();
+        assert_eq!(Framework::detect(dir.path()), Some(Framework::Npm));
+        std::fs::write(dir.path().join("Cargo.toml"), "").unwrap();
+        assert_eq!(Framework::detect(dir.path()), Some(Framework::Cargo));
+
+        assert_eq!(Framework::Cargo.command(Some("it's")), r"cargo test --no-fail-fast -- 'it'\''s'");
+        assert_eq!(Framework::Pytest.command(Some(" ")), "python3 -m pytest -rfE");
+    }
+
+    #[test]
+    fn test_parse_cargo() {
+        let output = "\
+running 3 tests
+test tests::adds ... ok
+test tests::slow ... ignored
+test tests::subtracts ... FAILED
+
+failures:
+
+---- tests::subtracts stdout ----
+thread 'tests::subtracts' panicked at src/lib.rs:10:5:
+assertion `left == right` failed
+  left: 1
+ right: 2
+note: run with `RUST_BACKTRACE=1` environment variable to display a backtrace
+
+
+failures:
+    tests::subtracts
+
+test result: FAILED. 1 passed; 1 failed; 1 ignored; 0 measured; 0 filtered out; finished in 0.00s
+
+running 1 test
+test src/lib.rs - add (line 3) ... \x1b[32mok\x1b[0m
+
+test result: ok. 1 passed; 0 failed; 0 ignored; 0 measured; 0 filtered out; finished in 0.01s
+";
+        let summary = parse(Framework::Cargo, output);
+        assert!(summary.parsed);
+        assert_eq!((summary.passed, summary.failed, summary.skipped), (2, 1, 1));
+        assert_eq!(summary.failures.len(), 1);
+        assert_eq!(summary.failures[0].name, "tests::subtracts");
+        let message = summary.failures[0].message.as_deref().unwrap();
+        assert!(message.starts_with("thread 'tests::subtracts' panicked") && message.contains("right: 2"));
+        assert!(!message.contains("RUST_BACKTRACE"));
+
+        // A build error has no result lines
+        assert!(!parse(Framework::Cargo, "error[E0425]: cannot find value `x`").parsed);
+    }
+
+    #[test]
+    fn test_parse_pytest() {
+        let output = "\
+tests/test_math.py .F.s                                                 [100%]
+=========================== short test summary info ============================
+FAILED tests/test_math.py::test_div - ZeroDivisionError: division by zero
+ERROR tests/test_db.py::test_conn
+=============== 1 failed, 2 passed, 1 skipped, 1 error in 0.12s ================
+";
+        let summary = parse(Framework::Pytest, output);
+        assert!(summary.parsed);
+        assert_eq!((summary.passed, summary.failed, summary.skipped), (2, 2, 1));
+        assert_eq!(
+            summary.failures[0],
+            FailedTest {
+                name: "tests/test_math.py::test_div".to_string(),
+                message: Some("ZeroDivisionError: division by zero".to_string()),
+            }
+        );
+        assert_eq!(summary.failures[1].message, None);
+        assert!(parse(Framework::Pytest, "4 passed in 0.01s").passed == 4);
+    }
+
+    #[test]
+    fn test_parse_npm_runners() {
+        let jest = "\
+  ● math › divides by zero
+
+    expect(received).toBe(expected)
+
+Tests:       1 failed, 1 skipped, 5 passed, 7 total
+";
+        let summary = parse(Framework::Npm, jest);
+        assert_eq!((summary.passed, summary.failed, summary.skipped), (5, 1, 1));
+        assert_eq!(summary.failures[0].name, "math › divides by zero");
+
+        let vitest = " FAIL  src/math.test.ts > math > divides\n      Tests  1 failed | 3 passed (4)\n";
+        let summary = parse(Framework::Npm, vitest);
+        assert_eq!((summary.passed, summary.failed), (3, 1));
+        assert_eq!(summary.failures[0].name, "src/math.test.ts > math > divides");
+
+        let mocha = "  4 passing (12ms)\n  1 pending\n  2 failing\n";
+        let summary = parse(Framework::Npm, mocha);
+        assert_eq!((summary.passed, summary.failed, summary.skipped), (4, 2, 1));
+        assert!(!parse(Framework::Npm, "sh: jest: command not found").parsed);
+    }
+}
@@ -0,0 +1,386 @@
+//! Applying model-written edits to file contents
+//!
+//! Edits come in two forms, both of which describe what the text looks like
+//! before the change so a stale edit is detected instead of silently applied:
+//!
+//! - a unified diff for a single file (`@@ -12,4 +12,5 @@` hunks)
+//! - search/replace blocks:
+//!
+//! ```text
+//! <<<<<<< SEARCH
+//! old lines
+//! =======
+//! new lines
+//! >>>>>>> REPLACE
+//! ```
+//!
+//! Every hunk or block is located in the original text before anything is
+//! changed, so either all of them apply or none do. This module has no
+//! dependencies on the rest of the crate so agent_test can share it.
+
+/// One search/replace edit: `old` must occur exactly once in the file
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Replacement {
+    pub old: String,
+    pub new: String,
+}
+
+/// The edited text and where it changed
+#[derive(Debug, Clone)]
+pub struct EditOutcome {
+    pub content: String,
+    /// Changed line ranges in `content`, 0-based and end-exclusive, in file order
+    pub hunks: Vec<(usize, usize)>,
+}
+
+impl EditOutcome {
+    /// The changed lines (marked with `>`) with `context` lines around each hunk
+    pub fn render(&self, context: usize) -> String {
+        let lines: Vec<&str> = self.content.lines().collect();
+        let mut windows: Vec<(usize, usize)> = Vec::new();
+        for &(start, end) in &self.hunks {
+            let window = (start.saturating_sub(context), (end + context).min(lines.len()));
+            match windows.last_mut() {
+                Some(last) if window.0 <= last.1 => last.1 = last.1.max(window.1),
+                _ => windows.push(window),
+            }
+        }
+
+        let mut output = String::new();
+        for (i, &(start, end)) in windows.iter().enumerate() {
+            if i > 0 {
+                output.push_str("   ...\n");
+            }
+            for (n, line) in lines.iter().enumerate().take(end).skip(start) {
+                let changed = self.hunks.iter().any(|&(s, e)| n >= s && n < e);
+                output.push_str(&format!("{}{:>5}│ {}\n", if changed { ">" } else { " " }, n + 1, line));
+            }
+        }
+        output
+    }
+}
+
+/// A hunk of a unified diff
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct DiffHunk {
+    /// 1-based line the hunk starts at in the original, from its `@@` header
+    old_start: Option<usize>,
+    /// Context and removed lines, i.e. what the original looks like
+    before: Vec<String>,
+    /// Context and added lines, i.e. what it should look like afterwards
+    after: Vec<String>,
+}
+
+/// Parse a unified diff touching a single file. `---`/`+++` headers are optional.
+fn parse_unified_diff(diff: &str) -> Result<Vec<DiffHunk>, String> {
+    let mut hunks: Vec<DiffHunk> = Vec::new();
+    let mut target: Option<&str> = None;
+    let lines: Vec<&str> = diff.lines().collect();
+
+    for (i, &line) in lines.iter().enumerate() {
+        // File headers come as a `---`/`+++` pair; inside a hunk a lone `--- x`
+        // is a removed line that happens to start with `--`
+        let next = lines.get(i + 1).copied().unwrap_or("");
+        let prev = if i > 0 { lines[i - 1] } else { "" };
+        if line.starts_with("--- ") && (hunks.is_empty() || next.starts_with("+++ ")) {
+            continue;
+        }
+        if let Some(path) = line.strip_prefix("+++ ")
+            && (hunks.is_empty() || prev.starts_with("--- "))
+        {
+            let path = path.split('\t').next().unwrap_or("").trim();
+            if let Some(previous) = target
+                && previous != path
+            {
+                return Err("The diff changes more than one file; edit one file at a time".to_string());
+            }
+            target = Some(path);
+            continue;
+        }
+        if line.starts_with("diff ") || line.starts_with("index ") {
+            continue;
+        }
+        if line.starts_with("@@") {
+            hunks.push(DiffHunk {
+                old_start: parse_old_start(line),
+                before: Vec::new(),
+                after: Vec::new(),
+            });
+            continue;
+        }
+        let Some(hunk) = hunks.last_mut() else {
+            // Preamble before the first hunk
+            continue;
+        };
+        match line.chars().next() {
+            Some('-') => hunk.before.push(line[1..].to_string()),
+            Some('+') => hunk.after.push(line[1..].to_string()),
+            Some(' ') => {
+                hunk.before.push(line[1..].to_string());
+                hunk.after.push(line[1..].to_string());
+            }
+            // "\ No newline at end of file"
+            Some('\\') => {}
+            // A blank context line whose leading space was stripped
+            None => {
+                hunk.before.push(String::new());
+                hunk.after.push(String::new());
+            }
+            Some(_) => return Err(format!("Unexpected line in diff: '{}'", line)),
+        }
+    }
+
+    hunks.retain(|h| h.before != h.after);
+    if hunks.is_empty() {
+        return Err("The diff contains no changes (expected '@@' hunks with '-' and '+' lines)".to_string());
+    }
+    Ok(hunks)
+}
+
+/// The original start line from a `@@ -12,4 +12,5 @@` header, if it has one
+fn parse_old_start(header: &str) -> Option<usize> {
+    let old = header.trim_start_matches('@').split_whitespace().next()?.strip_prefix('-')?;
+    old.split(',').next()?.parse().ok()
+}
+
+/// Lines of `content` without their terminators, and whether it uses CRLF
+fn split_lines(content: &str) -> (Vec<&str>, bool) {
+    let crlf = content.contains("\r\n");
+    (content.lines().collect(), crlf)
+}
+
+fn join_lines(lines: &[String], crlf: bool, trailing_newline: bool) -> String {
+    let newline = if crlf { "\r\n" } else { "\n" };
+    let mut content = lines.join(newline);
+    if trailing_newline && !lines.is_empty() {
+        content.push_str(newline);
+    }
+    content
+}
+
+/// Trailing whitespace is the one difference models routinely introduce, so it's ignored
+fn lines_match(actual: &[&str], expected: &[String]) -> bool {
+    actual.len() == expected.len() && actual.iter().zip(expected).all(|(a, e)| a.trim_end() == e.trim_end())
+}
+
+/// Apply a single-file unified diff to `content`
+pub fn apply_unified_diff(content: &str, diff: &str) -> Result<EditOutcome, String> {
+    let hunks = parse_unified_diff(diff)?;
+    let (lines, crlf) = split_lines(content);
+
+    // Locate every hunk first; nothing is changed unless all of them match
+    let mut positions = Vec::with_capacity(hunks.len());
+    let mut earliest = 0;
+    for (i, hunk) in hunks.iter().enumerate() {
+        let hint = hunk.old_start.map(|s| s.saturating_sub(1)).unwrap_or(earliest);
+        let position = if hunk.before.is_empty() {
+            // Pure insertion: `@@ -N,0 @@` adds after line N
+            let at = hunk.old_start.unwrap_or(lines.len()).min(lines.len());
+            (at >= earliest).then_some(at)
+        } else {
+            (earliest..=lines.len().saturating_sub(hunk.before.len()))
+                .filter(|&p| p + hunk.before.len() <= lines.len())
+                .filter(|&p| lines_match(&lines[p..p + hunk.before.len()], &hunk.before))
+                .min_by_key(|&p| p.abs_diff(hint))
+        };
+        let Some(position) = position else {
+            return Err(hunk_conflict(i + 1, hunk, &lines, hint));
+        };
+        positions.push(position);
+        earliest = position + hunk.before.len();
+    }
+
+    let mut output: Vec<String> = Vec::with_capacity(lines.len());
+    let mut changed = Vec::with_capacity(hunks.len());
+    let mut cursor = 0;
+    for (hunk, &position) in hunks.iter().zip(&positions) {
+        output.extend(lines[cursor..position].iter().map(|l| l.to_string()));
+        let start = output.len();
+        output.extend(hunk.after.iter().cloned());
+        changed.push((start, output.len().max(start + 1)));
+        cursor = position + hunk.before.len();
+    }
+    output.extend(lines[cursor..].iter().map(|l| l.to_string()));
+
+    let trailing_newline = content.ends_with('\n') || content.is_empty();
+    Ok(EditOutcome {
+        content: join_lines(&output, crlf, trailing_newline),
+        hunks: changed,
+    })
+}
+
+fn hunk_conflict(number: usize, hunk: &DiffHunk, lines: &[&str], hint: usize) -> String {
+    let expected = hunk.before.iter().map(|l| format!("  {}", l)).collect::<Vec<_>>().join("\n");
+    let found_start = hint.min(lines.len());
+    let found_end = (found_start + hunk.before.len().max(1)).min(lines.len());
+    let found = lines[found_start..found_end]
+        .iter()
+        .enumerate()
+        .map(|(i, l)| format!("{:>5}│ {}", found_start + i + 1, l))
+        .collect::<Vec<_>>()
+        .join("\n");
+    format!(
+        "Conflict: hunk {} does not match the file, which may have changed since it was read. \
+         No changes were made.\n\nExpected:\n{}\n\nFile at line {}:\n{}",
+        number,
+        expected,
+        found_start + 1,
+        if found.is_empty() { "  (end of file)".to_string() } else { found }
+    )
+}
+
+/// Parse `<<<<<<< SEARCH` / `=======` / `>>>>>>> REPLACE` blocks
+pub fn parse_search_replace_blocks(text: &str) -> Result<Vec<Replacement>, String> {
+    enum State {
+        Outside,
+        Search(Vec<String>),
+        Replace(Vec<String>, Vec<String>),
+    }
+
+    let mut blocks = Vec::new();
+    let mut state = State::Outside;
+    for line in text.lines() {
+        let marker = line.trim();
+        state = match state {
+            State::Outside if marker.starts_with("<<<<<<<") && marker.contains("SEARCH") => State::Search(Vec::new()),
+            State::Outside => State::Outside,
+            State::Search(old) if marker.starts_with("=======") => State::Replace(old, Vec::new()),
+            State::Search(mut old) => {
+                old.push(line.to_string());
+                State::Search(old)
+            }
+            State::Replace(old, new) if marker.starts_with(">>>>>>>") && marker.contains("REPLACE") => {
+                blocks.push(Replacement {
+                    old: old.join("\n"),
+                    new: new.join("\n"),
+                });
+                State::Outside
+            }
+            State::Replace(old, mut new) => {
+                new.push(line.to_string());
+                State::Replace(old, new)
+            }
+        };
+    }
+
+    if !matches!(state, State::Outside) {
+        return Err("Unterminated search/replace block: each block needs '=======' and '>>>>>>> REPLACE'".to_string());
+    }
+    if blocks.is_empty() {
+        return Err("No search/replace blocks found (expected '<<<<<<< SEARCH' ... '=======' ... '>>>>>>> REPLACE')".to_string());
+    }
+    Ok(blocks)
+}
+
+/// Apply search/replace edits to `content`. Each search text must match exactly
+/// one place, and no two edits may overlap.
+pub fn apply_replacements(content: &str, replacements: &[Replacement]) -> Result<EditOutcome, String> {
+    let crlf = content.contains("\r\n");
+    let mut spans = Vec::with_capacity(replacements.len());
+
+    for (i, replacement) in replacements.iter().enumerate() {
+        if replacement.old.is_empty() {
+            return Err(format!("Edit {}: the search text is empty", i + 1));
+        }
+        // Blocks are written with \n; match the file's line endings
+        let (old, new) = if crlf {
+            (replacement.old.replace("\r\n", "\n").replace('\n', "\r\n"), replacement.new.replace("\r\n", "\n").replace('\n', "\r\n"))
+        } else {
+            (replacement.old.clone(), replacement.new.clone())
+        };
+        let matches: Vec<usize> = content.match_indices(&old).map(|(pos, _)| pos).collect();
+        match matches.as_slice() {
+            [] => {
+                return Err(format!(
+                    "Conflict: edit {} search text not found. It must match the file exactly, including whitespace. \
+                     No changes were made.\n\nSearch text:\n{}",
+                    i + 1,
+                    replacement.old
+                ));
+            }
+            [pos] => spans.push((*pos, *pos + old.len(), new, i + 1)),
+            many => {
+                return Err(format!(
+                    "Edit {}: search text matches {} places; include more surrounding lines so it matches one. \
+                     No changes were made.",
+                    i + 1,
+                    many.len()
+                ));
+            }
+        }
+    }
+
+    spans.sort_by_key(|s| s.0);
+    for pair in spans.windows(2) {
+        if pair[1].0 < pair[0].1 {
+            return Err(format!("Edits {} and {} overlap. No changes were made.", pair[0].3, pair[1].3));
+        }
+    }
+
+    let mut output = String::with_capacity(content.len());
+    let mut hunks = Vec::with_capacity(spans.len());
+    let mut cursor = 0;
+    for (start, end, new, _) in &spans {
+        output.push_str(&content[cursor..*start]);
+        let first_line = output.matches('\n').count();
+        output.push_str(new);
+        let last_line = first_line + new.matches('\n').count();
+        hunks.push((first_line, last_line + 1));
+        cursor = *end;
+    }
+    output.push_str(&content[cursor..]);
+
+    Ok(EditOutcome { content: output, hunks })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const FILE: &str = "fn main() {\n    let a = 1;\n    let b = 2;\n    println!(\"{}\", a + b);\n}\n";
+
+    #[test]
+    fn test_unified_diff() {
+        let diff = "--- a/main.rs\n+++ b/main.rs\n@@ -2,3 +2,3 @@\n     let a = 1;\n-    let b = 2;\n+    let b = 40;\n     println!(\"{}\", a + b);\n";
+        let outcome = apply_unified_diff(FILE, diff).unwrap();
+        assert_eq!(outcome.content, FILE.replace("= 2", "= 40"));
+        assert_eq!(outcome.hunks, vec![(1, 4)]);
+        assert!(outcome.render(1).contains(">    3│     let b = 40;"));
+
+        // Wrong line numbers are tolerated as long as the text matches
+        let moved = diff.replace("@@ -2,3 +2,3 @@", "@@ -40,3 +40,3 @@");
+        assert_eq!(apply_unified_diff(FILE, &moved).unwrap().content, outcome.content);
+    }
+
+    #[test]
+    fn test_unified_diff_conflict_changes_nothing() {
+        let diff = "@@ -2,1 +2,1 @@\n-    let a = 1;\n+    let a = 10;\n@@ -3,1 +3,1 @@\n-    let b = 3;\n+    let b = 30;\n";
+        let err = apply_unified_diff(FILE, diff).unwrap_err();
+        assert!(err.starts_with("Conflict: hunk 2"));
+
+        let two_files = "--- a/a.rs\n+++ b/a.rs\n@@\n-x\n+y\n--- a/b.rs\n+++ b/b.rs\n@@\n-x\n+y\n";
+        assert!(apply_unified_diff("x\n", two_files).unwrap_err().contains("more than one file"));
+
+        // A removed SQL comment isn't mistaken for a file header
+        let sql = "@@ -1,2 +1,1 @@\n--- drop me\n SELECT 1;\n";
+        assert_eq!(apply_unified_diff("-- drop me\nSELECT 1;\n", sql).unwrap().content, "SELECT 1;\n");
+    }
+
+    #[test]
+    fn test_search_replace_blocks() {
+        let blocks = parse_search_replace_blocks(
+            "<<<<<<< SEARCH\n    let a = 1;\n=======\n    let a = 5;\n>>>>>>> REPLACE\n\
+             <<<<<<< SEARCH\n    let b = 2;\n=======\n    let b = 6;\n    let c = 7;\n>>>>>>> REPLACE\n",
+        )
+        .unwrap();
+        assert_eq!(blocks.len(), 2);
+
+        let outcome = apply_replacements(FILE, &blocks).unwrap();
+        assert!(outcome.content.contains("let a = 5;\n    let b = 6;\n    let c = 7;\n"));
+        assert_eq!(outcome.hunks, vec![(1, 2), (2, 4)]);
+
+        let ambiguous = [Replacement { old: "let".to_string(), new: "const".to_string() }];
+        assert!(apply_replacements(FILE, &ambiguous).unwrap_err().contains("matches 2 places"));
+        assert!(parse_search_replace_blocks("<<<<<<< SEARCH\nx\n=======\ny\n").is_err());
+    }
+}
@@ -4,7 +4,9 @@ use crate::db::Database;
 use crate::execution::ProcessManager;
 use crate::gateway::events::EventBroadcaster;
 use crate::gateway::protocol::GatewayEvent;
+use crate::models::{NewTransaction, ToolPolicy, TransactionStatus};
 use crate::skills::SkillRegistry;
+use crate::tools::output_store::OutputStore;
 use crate::tools::register::RegisterStore;
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
@@ -320,6 +322,8 @@ pub struct ToolContext {
     pub user_id: Option<String>,
     pub session_id: Option<i64>,
     pub identity_id: Option<String>,
+    /// Background agent job the tools run for
+    pub job_id: Option<String>,
     /// Base directory for file operations (sandbox root)
     pub workspace_dir: Option<String>,
     /// Additional context data
@@ -337,6 +341,10 @@ pub struct ToolContext {
     pub process_manager: Option<Arc<ProcessManager>>,
     /// Skill registry for managing skills
     pub skill_registry: Option<Arc<SkillRegistry>>,
+    /// Full outputs of tool calls the model only got a preview of
+    pub output_store: Option<OutputStore>,
+    /// Policies of the API token and chat session; a call must pass all of them
+    pub tool_policies: Vec<ToolPolicy>,
 }
 
 impl std::fmt::Debug for ToolContext {
@@ -347,6 +355,7 @@ impl std::fmt::Debug for ToolContext {
             .field("user_id", &self.user_id)
             .field("session_id", &self.session_id)
             .field("identity_id", &self.identity_id)
+            .field("job_id", &self.job_id)
             .field("workspace_dir", &self.workspace_dir)
             .field("extra", &self.extra)
             .field("broadcaster", &self.broadcaster.is_some())
@@ -355,6 +364,8 @@ impl std::fmt::Debug for ToolContext {
             .field("subagent_manager", &self.subagent_manager.is_some())
             .field("process_manager", &self.process_manager.is_some())
             .field("skill_registry", &self.skill_registry.is_some())
+            .field("output_store", &self.output_store.as_ref().map(OutputStore::dir))
+            .field("tool_policies", &self.tool_policies)
             .finish()
     }
 }
@@ -367,6 +378,7 @@ impl Default for ToolContext {
             user_id: None,
             session_id: None,
             identity_id: None,
+            job_id: None,
             workspace_dir: None,
             extra: HashMap::new(),
             broadcaster: None,
@@ -375,6 +387,8 @@ impl Default for ToolContext {
             subagent_manager: None,
             process_manager: None,
             skill_registry: None,
+            output_store: None,
+            tool_policies: Vec::new(),
         }
     }
 }
@@ -405,11 +419,29 @@ impl ToolContext {
         self
     }
 
+    /// Mark the context as running for a background agent job (background
+    /// processes it starts are killed when the job is cancelled)
+    pub fn with_job(mut self, job_id: String) -> Self {
+        self.job_id = Some(job_id);
+        self
+    }
+
     pub fn with_workspace(mut self, workspace_dir: String) -> Self {
         self.workspace_dir = Some(workspace_dir);
         self
     }
 
+    /// Restrict the tools this context may run to those `policy` allows
+    pub fn with_tool_policy(mut self, policy: ToolPolicy) -> Self {
+        self.tool_policies.push(policy);
+        self
+    }
+
+    /// Whether every tool policy of the context allows the tool
+    pub fn policy_allows(&self, tool_name: &str, group: ToolGroup) -> bool {
+        self.tool_policies.iter().all(|policy| policy.is_tool_allowed(tool_name, group))
+    }
+
     /// Add an API key to the context by string name (for backwards compatibility)
     /// Keys are stored by their exact name (e.g., "GITHUB_TOKEN", "MOLTX_API_KEY")
     pub fn with_api_key(mut self, key_name: &str, key_value: String) -> Self {
@@ -482,6 +514,12 @@ impl ToolContext {
         self
     }
 
+    /// Add an OutputStore to the context (for agent runs that truncate tool output)
+    pub fn with_output_store(mut self, store: OutputStore) -> Self {
+        self.output_store = Some(store);
+        self
+    }
+
     /// Set a register value and broadcast the update to connected clients.
     /// This is the preferred way to set registers when you want real-time updates in the UI.
     pub fn set_register(&self, key: &str, value: Value, source_tool: &str) {
@@ -511,6 +549,52 @@ impl ToolContext {
         Value::Object(map)
     }
 
+    /// Tag the context with the id of the tool call being executed
+    pub fn with_tool_call_id(mut self, tool_call_id: &str) -> Self {
+        self.extra.insert("tool_call_id".to_string(), serde_json::json!(tool_call_id));
+        self
+    }
+
+    /// Id of the tool call being executed, when the model provided one
+    pub fn get_tool_call_id(&self) -> Option<String> {
+        self.extra.get("tool_call_id")
+            .and_then(|v| v.as_str())
+            .map(|s| s.to_string())
+    }
+
+    /// Add an on-chain action to the transactions audit trail, tagged with this
+    /// context's channel, session and tool call. Returns the row id (None without a database).
+    pub async fn record_transaction(&self, tool_name: &str, mut tx: NewTransaction) -> Option<i64> {
+        let db = self.database.as_ref()?;
+        tx.channel_id = tx.channel_id.or(self.channel_id);
+        tx.session_id = tx.session_id.or(self.session_id);
+        tx.tool_name = Some(tool_name.to_string());
+        tx.tool_call_id = self.get_tool_call_id();
+        match db.record_transaction(&tx).await {
+            Ok(id) => Some(id),
+            Err(e) => {
+                log::error!("[{}] Failed to record transaction: {}", tool_name, e);
+                None
+            }
+        }
+    }
+
+    /// Update the outcome of a transaction recorded with `record_transaction`
+    pub async fn update_transaction(
+        &self,
+        id: Option<i64>,
+        status: TransactionStatus,
+        gas_used: Option<&str>,
+        block_number: Option<i64>,
+    ) {
+        let (Some(db), Some(id)) = (&self.database, id) else {
+            return;
+        };
+        if let Err(e) = db.update_transaction_status(id, status, gas_used, block_number).await {
+            log::error!("Failed to update transaction {}: {}", id, e);
+        }
+    }
+
     /// Get bot name from the context
     pub fn get_bot_name(&self) -> String {
         self.extra.get("bot_name")
@@ -582,6 +666,18 @@ impl ToolConfig {
             _ => self.profile.allowed_groups().contains(&tool_group),
         }
     }
+
+    /// Deny every group outside `groups` (System stays available). Narrows
+    /// the configuration; groups it already denies stay denied.
+    pub fn restrict_to_groups(&mut self, groups: &[String]) {
+        let keep: Vec<ToolGroup> = groups.iter().filter_map(|g| ToolGroup::from_str(g)).collect();
+        for group in ToolGroup::all() {
+            let name = group.as_str().to_string();
+            if group != ToolGroup::System && !keep.contains(&group) && !self.denied_groups.contains(&name) {
+                self.denied_groups.push(name);
+            }
+        }
+    }
 }
 
 /// Tool execution record for audit logging
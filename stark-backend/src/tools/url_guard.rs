@@ -0,0 +1,87 @@
+//! Refuse URLs that point at private or internal hosts
+//!
+//! Tools that fetch model-supplied URLs check them here first so the agent
+//! can't reach localhost, the LAN or cloud metadata endpoints.
+
+use std::net::{IpAddr, ToSocketAddrs};
+
+/// Validate that a URL points to a public host (not private/internal)
+pub fn validate_public_url(url: &url::Url) -> Result<(), String> {
+    let host = url.host_str().ok_or("URL has no host")?;
+
+    // Block localhost and common internal hostnames
+    let blocked_hosts = [
+        "localhost",
+        "127.0.0.1",
+        "0.0.0.0",
+        "::1",
+        "[::1]",
+        "metadata.google.internal",
+        "metadata.google",
+        "169.254.169.254", // AWS/GCP metadata
+    ];
+
+    let host_lower = host.to_lowercase();
+    if blocked_hosts.contains(&host_lower.as_str()) {
+        return Err(format!("Access to internal host '{}' is blocked", host));
+    }
+
+    // Block .local, .internal, .localhost TLDs
+    if host_lower.ends_with(".local")
+        || host_lower.ends_with(".internal")
+        || host_lower.ends_with(".localhost")
+        || host_lower.ends_with(".lan")
+    {
+        return Err(format!("Access to internal domain '{}' is blocked", host));
+    }
+
+    // Try to resolve and check if it's a private IP
+    let port = url.port().unwrap_or(if url.scheme() == "https" { 443 } else { 80 });
+    if let Ok(addrs) = format!("{}:{}", host, port).to_socket_addrs() {
+        for addr in addrs {
+            if is_private_ip(addr.ip()) {
+                return Err(format!(
+                    "URL resolves to private IP address '{}', access blocked",
+                    addr.ip()
+                ));
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Check if an IP address is private/internal
+pub fn is_private_ip(ip: IpAddr) -> bool {
+    match ip {
+        IpAddr::V4(ipv4) => {
+            ipv4.is_private()           // 10.x, 172.16-31.x, 192.168.x
+                || ipv4.is_loopback()   // 127.x
+                || ipv4.is_link_local() // 169.254.x
+                || ipv4.is_broadcast()
+                || ipv4.is_documentation()
+                || ipv4.is_unspecified()
+                // Cloud metadata IPs
+                || ipv4.octets()[0] == 169 && ipv4.octets()[1] == 254
+        }
+        IpAddr::V6(ipv6) => {
+            ipv6.is_loopback() || ipv6.is_unspecified()
+            // Note: is_unique_local() and is_unicast_link_local() are unstable
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_private_ip_detection() {
+        assert!(is_private_ip("127.0.0.1".parse().unwrap()));
+        assert!(is_private_ip("192.168.1.1".parse().unwrap()));
+        assert!(is_private_ip("10.0.0.1".parse().unwrap()));
+        assert!(is_private_ip("172.16.0.1".parse().unwrap()));
+        assert!(!is_private_ip("8.8.8.8".parse().unwrap()));
+        assert!(!is_private_ip("1.1.1.1".parse().unwrap()));
+    }
+}
@@ -0,0 +1,216 @@
+//! Confinement of file-tool paths to the workspace
+//!
+//! `WorkspacePath::resolve` turns a path supplied by the model into an
+//! absolute path with every symlink and `..` segment resolved, and refuses it
+//! unless the result stays under the workspace root. `..` segments are applied
+//! to the text of the path first, so `missing/../link` is checked as `link`.
+//! Paths that do not exist yet (files about to be written) are then resolved
+//! through their deepest existing ancestor, so a symlinked directory cannot be
+//! used to escape either.
+//!
+//! Models write `src\main.rs` and `src/main.rs` interchangeably whatever the
+//! host, so both separators are accepted everywhere, and paths shown back to
+//! the model always use `/`.
+
+use std::ffi::OsString;
+use std::path::{Component, Path, PathBuf};
+
+/// A path verified to lie inside a root directory
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WorkspacePath {
+    /// Canonical root directory
+    root: PathBuf,
+    /// Resolved absolute path (symlinks in existing components followed)
+    path: PathBuf,
+}
+
+impl WorkspacePath {
+    /// Resolve `requested` (relative to `root`, or absolute) and verify it stays inside `root`
+    pub fn resolve(root: &Path, requested: &str) -> Result<Self, String> {
+        let root = root
+            .canonicalize()
+            .map_err(|e| format!("Cannot resolve workspace directory: {}", e))?;
+
+        let normalized = normalize_separators(requested);
+        let requested_path = Path::new(&normalized);
+        let full_path = if requested_path.is_absolute() {
+            requested_path.to_path_buf()
+        } else {
+            root.join(requested_path)
+        };
+
+        let path = resolve_through_existing_ancestor(&remove_dot_segments(&full_path))
+            .map_err(|e| format!("Cannot resolve path '{}': {}", requested, e))?;
+
+        if !path.starts_with(&root) {
+            return Err(format!(
+                "Access denied: path '{}' is outside the workspace",
+                requested
+            ));
+        }
+
+        Ok(Self { root, path })
+    }
+
+    /// The resolved absolute path
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// The canonical root directory
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    pub fn is_root(&self) -> bool {
+        self.path == self.root
+    }
+}
+
+/// `path` relative to `root` for display, with `/` separators on every platform
+pub fn relative_to(root: &Path, path: &Path) -> String {
+    let relative = path.strip_prefix(root).unwrap_or(path);
+    relative.to_string_lossy().replace('\\', "/")
+}
+
+/// A requested path with `\` read as a separator. On Windows `/` already is
+/// one; elsewhere a backslash in a model-supplied path is nearly always meant
+/// as one rather than as part of a file name.
+fn normalize_separators(requested: &str) -> String {
+    if cfg!(windows) {
+        requested.replace('/', "\\")
+    } else {
+        requested.replace('\\', "/")
+    }
+}
+
+/// `path` with `.` and `..` segments applied to its text; `..` never climbs
+/// above the filesystem root
+fn remove_dot_segments(path: &Path) -> PathBuf {
+    let mut normalized = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir => {
+                normalized.pop();
+            }
+            other => normalized.push(other.as_os_str()),
+        }
+    }
+    normalized
+}
+
+/// Canonicalize the deepest existing ancestor of `path` and re-apply the
+/// remaining components. `path` has no `..` segments, so those components
+/// don't exist and cannot be symlinks.
+fn resolve_through_existing_ancestor(path: &Path) -> std::io::Result<PathBuf> {
+    let mut existing = path.to_path_buf();
+    let mut remainder: Vec<OsString> = Vec::new();
+
+    // symlink_metadata so a dangling symlink counts as existing (and then
+    // fails to canonicalize) rather than being written through
+    while existing.symlink_metadata().is_err() {
+        let parent = match existing.parent() {
+            Some(p) => p.to_path_buf(),
+            None => break,
+        };
+        if let Some(name) = existing.components().next_back() {
+            remainder.push(name.as_os_str().to_os_string());
+        }
+        existing = parent;
+    }
+
+    let mut resolved = existing.canonicalize()?;
+    for name in remainder.iter().rev() {
+        if let Some(Component::Normal(name)) = Path::new(name).components().next() {
+            resolved.push(name);
+        }
+    }
+    Ok(resolved)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn workspace() -> TempDir {
+        let dir = TempDir::new().unwrap();
+        std::fs::create_dir_all(dir.path().join("src")).unwrap();
+        std::fs::write(dir.path().join("src/main.rs"), "fn main() {}").unwrap();
+        dir
+    }
+
+    #[test]
+    fn test_resolves_paths_inside_workspace() {
+        let ws = workspace();
+        let root = ws.path().canonicalize().unwrap();
+
+        let existing = WorkspacePath::resolve(ws.path(), "src/main.rs").unwrap();
+        assert_eq!(existing.path(), root.join("src/main.rs"));
+
+        let new_file = WorkspacePath::resolve(ws.path(), "new/dir/file.txt").unwrap();
+        assert_eq!(new_file.path(), root.join("new/dir/file.txt"));
+
+        let dotted = WorkspacePath::resolve(ws.path(), "./src/../src/./main.rs").unwrap();
+        assert_eq!(dotted.path(), root.join("src/main.rs"));
+
+        let absolute = WorkspacePath::resolve(ws.path(), root.join("src").to_str().unwrap()).unwrap();
+        assert_eq!(absolute.path(), root.join("src"));
+
+        assert!(WorkspacePath::resolve(ws.path(), ".").unwrap().is_root());
+    }
+
+    #[test]
+    fn test_either_separator_is_accepted_and_shown_as_slash() {
+        let ws = workspace();
+        let root = ws.path().canonicalize().unwrap();
+
+        for requested in ["src/main.rs", "src\\main.rs", ".\\src/main.rs"] {
+            let path = WorkspacePath::resolve(ws.path(), requested).unwrap();
+            assert_eq!(path.path(), root.join("src").join("main.rs"), "{}", requested);
+            assert_eq!(relative_to(path.root(), path.path()), "src/main.rs");
+        }
+
+        let err = WorkspacePath::resolve(ws.path(), "src\\..\\..\\outside.txt").unwrap_err();
+        assert!(err.contains("outside the workspace"), "{}", err);
+    }
+
+    #[test]
+    fn test_rejects_parent_dir_escapes() {
+        let ws = workspace();
+        for path in ["../outside.txt", "src/../../outside.txt", "../../etc/passwd", "missing/../../x", "/etc/passwd"] {
+            let err = WorkspacePath::resolve(ws.path(), path).unwrap_err();
+            assert!(err.contains("outside the workspace"), "{}: {}", path, err);
+        }
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_rejects_symlink_escapes() {
+        let ws = workspace();
+        let outside = TempDir::new().unwrap();
+        std::fs::write(outside.path().join("secret.txt"), "secret").unwrap();
+
+        std::os::unix::fs::symlink(outside.path(), ws.path().join("link_dir")).unwrap();
+        std::os::unix::fs::symlink(outside.path().join("secret.txt"), ws.path().join("link_file")).unwrap();
+        std::os::unix::fs::symlink(outside.path().join("gone.txt"), ws.path().join("dangling")).unwrap();
+
+        // Through a symlinked directory, to existing and new files
+        assert!(WorkspacePath::resolve(ws.path(), "link_dir/secret.txt").is_err());
+        assert!(WorkspacePath::resolve(ws.path(), "link_dir/new.txt").is_err());
+        // A symlinked file
+        assert!(WorkspacePath::resolve(ws.path(), "link_file").is_err());
+        // A dangling symlink must not be written through
+        assert!(WorkspacePath::resolve(ws.path(), "dangling").is_err());
+        // Nor reached by stepping back out of a directory that doesn't exist
+        assert!(WorkspacePath::resolve(ws.path(), "missing/../link_file").is_err());
+        assert!(WorkspacePath::resolve(ws.path(), "missing/../link_dir/x").is_err());
+        assert!(WorkspacePath::resolve(ws.path(), "a/b/../../link_dir/new.txt").is_err());
+
+        // Symlinks that stay inside the workspace are fine
+        std::os::unix::fs::symlink(ws.path().join("src"), ws.path().join("src_link")).unwrap();
+        let inside = WorkspacePath::resolve(ws.path(), "src_link/main.rs").unwrap();
+        assert_eq!(inside.path(), inside.root().join("src/main.rs"));
+    }
+}
@@ -0,0 +1,113 @@
+//! Time-based one-time passwords (RFC 6238) for the admin second factor
+//!
+//! With `STARK_ADMIN_TOTP_SECRET` set, signing in and creating admin-scoped
+//! API tokens also need the current 6-digit code from an authenticator app
+//! enrolled with that secret. Codes from the previous and next 30-second step
+//! are accepted for clock drift, and each step's code works only once per
+//! user. The last step used is kept with the user in the shared database, so
+//! a code can't be replayed on another instance either.
+
+use hmac::{Hmac, Mac};
+use sha1::Sha1;
+
+use crate::config;
+use crate::db::Database;
+
+const STEP_SECS: u64 = 30;
+const DIGITS: u32 = 6;
+
+/// Steps before and after the current one whose codes are accepted
+const SKEW_STEPS: u64 = 1;
+
+/// Shortest secret accepted (RFC 4226 asks for at least 128 bits)
+pub const MIN_SECRET_BYTES: usize = 16;
+
+/// Decode an RFC 4648 base32 secret as shown by authenticator apps (case,
+/// spaces and padding are ignored)
+pub fn decode_base32(secret: &str) -> Option<Vec<u8>> {
+    const ALPHABET: &[u8] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZ234567";
+    let mut bytes = Vec::new();
+    let (mut buffer, mut bits) = (0u32, 0u32);
+    for c in secret.bytes().filter(|c| !c.is_ascii_whitespace() && *c != b'=') {
+        let value = ALPHABET.iter().position(|&a| a == c.to_ascii_uppercase())? as u32;
+        buffer = (buffer << 5) | value;
+        bits += 5;
+        if bits >= 8 {
+            bits -= 8;
+            bytes.push((buffer >> bits) as u8);
+            buffer &= (1 << bits) - 1;
+        }
+    }
+    Some(bytes)
+}
+
+/// HOTP code (RFC 4226) for `counter`
+fn code_at(key: &[u8], counter: u64) -> u32 {
+    let mut mac = Hmac::<Sha1>::new_from_slice(key).expect("HMAC accepts any key length");
+    mac.update(&counter.to_be_bytes());
+    let hash = mac.finalize().into_bytes();
+    let offset = (hash[hash.len() - 1] & 0x0f) as usize;
+    let value = u32::from_be_bytes([hash[offset], hash[offset + 1], hash[offset + 2], hash[offset + 3]]) & 0x7fff_ffff;
+    value % 10u32.pow(DIGITS)
+}
+
+/// The step whose code is `code` at `unix_secs`, if any within the skew
+fn matching_step(key: &[u8], code: &str, unix_secs: u64) -> Option<u64> {
+    let code = code.trim();
+    if code.len() != DIGITS as usize || !code.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+    let code: u32 = code.parse().ok()?;
+    let current = unix_secs / STEP_SECS;
+    (current.saturating_sub(SKEW_STEPS)..=current + SKEW_STEPS).find(|&step| code_at(key, step) == code)
+}
+
+/// Whether a second factor is configured
+pub fn is_enabled() -> bool {
+    config::admin_totp_secret().is_some()
+}
+
+/// Check `code` against the admin secret for `user_id`. Always passes when no
+/// secret is configured.
+pub async fn verify_admin_code(db: &Database, user_id: i64, code: Option<&str>) -> Result<(), String> {
+    let Some(secret) = config::admin_totp_secret() else {
+        return Ok(());
+    };
+    let key = decode_base32(&secret).ok_or("The configured TOTP secret is not valid base32")?;
+    let code = code.ok_or("A TOTP code is required")?;
+    let now = chrono::Utc::now().timestamp().max(0) as u64;
+    let step = matching_step(&key, code, now).ok_or("Invalid TOTP code")?;
+    match db.claim_totp_step(user_id, step as i64).await {
+        Ok(true) => Ok(()),
+        Ok(false) => Err("This TOTP code was already used".to_string()),
+        Err(e) => {
+            log::error!("Failed to record TOTP use for user {}: {}", user_id, e);
+            Err("Could not check the TOTP code".to_string())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rfc6238_vectors() {
+        // RFC 6238 appendix B, SHA-1 key "12345678901234567890", last 6 digits
+        let key = decode_base32("GEZDGNBVGY3TQOJQGEZDGNBVGY3TQOJQ").unwrap();
+        assert_eq!(key, b"12345678901234567890");
+        assert_eq!(code_at(&key, 59 / STEP_SECS), 287082);
+        assert_eq!(code_at(&key, 1111111109 / STEP_SECS), 81804);
+        assert_eq!(code_at(&key, 1234567890 / STEP_SECS), 5924);
+
+        assert_eq!(matching_step(&key, "287082", 59), Some(1));
+        // One step of clock drift either way
+        assert_eq!(matching_step(&key, "287082", 89), Some(1));
+        assert_eq!(matching_step(&key, "081804", 1111111109 + 30), Some(1111111109 / STEP_SECS));
+        assert_eq!(matching_step(&key, "287082", 200), None);
+        assert_eq!(matching_step(&key, "28708", 59), None);
+
+        assert_eq!(decode_base32("gezd gnbv gy3t qojq"), decode_base32("GEZDGNBVGY3TQOJQ"));
+        assert!(decode_base32("not base32!").is_none());
+    }
+}
@@ -0,0 +1,106 @@
+//! Small string helpers shared across modules
+//!
+//! Slicing a `&str` by byte index (`&s[..100]`) panics when the index falls
+//! inside a multi-byte UTF-8 character, which tool output, chat messages and
+//! memories routinely contain. Use these instead when shortening text.
+//!
+//! This module only depends on std so the standalone `agent_test` binary can
+//! include it with `#[path]`.
+
+/// Longest prefix of `s` containing at most `max_chars` characters
+pub fn truncate_chars(s: &str, max_chars: usize) -> &str {
+    match s.char_indices().nth(max_chars) {
+        Some((idx, _)) => &s[..idx],
+        None => s,
+    }
+}
+
+/// Shorten `s` to at most `max_chars` characters, appending "..." if anything was cut
+pub fn truncate_str(s: &str, max_chars: usize) -> String {
+    let prefix = truncate_chars(s, max_chars);
+    if prefix.len() < s.len() {
+        format!("{}...", prefix)
+    } else {
+        s.to_string()
+    }
+}
+
+/// Suffix of `s` containing at most `n` characters
+pub fn last_chars(s: &str, n: usize) -> &str {
+    let count = s.chars().count();
+    if count <= n {
+        return s;
+    }
+    match s.char_indices().nth(count - n) {
+        Some((idx, _)) => &s[idx..],
+        // n == 0
+        None => "",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_truncate_str_ascii() {
+        assert_eq!(truncate_str("hello world", 5), "hello...");
+        assert_eq!(truncate_str("hello", 5), "hello");
+        assert_eq!(truncate_str("", 5), "");
+    }
+
+    #[test]
+    fn test_truncate_str_multibyte_boundary() {
+        // Byte-index slicing at 1 or 2 would land inside "é" / the emoji and panic
+        assert_eq!(truncate_str("héllo", 2), "hé...");
+        assert_eq!(truncate_str("🦀🦀🦀", 2), "🦀🦀...");
+        assert_eq!(truncate_str("日本語テキスト", 3), "日本語...");
+        assert_eq!(truncate_chars("a🦀b", 1), "a");
+    }
+
+    #[test]
+    fn test_last_chars() {
+        assert_eq!(last_chars("sk-abcdef", 4), "cdef");
+        assert_eq!(last_chars("ab", 4), "ab");
+        assert_eq!(last_chars("キーの末尾", 2), "末尾");
+    }
+
+    mod properties {
+        use super::*;
+        use proptest::prelude::*;
+
+        /// Text mixing ASCII with 2-, 3- and 4-byte characters, like build output
+        fn mixed_text() -> impl Strategy<Value = String> {
+            proptest::collection::vec(prop_oneof![any::<char>(), Just('é'), Just('語'), Just('🦀'), Just('\n')], 0..64)
+                .prop_map(|chars| chars.into_iter().collect())
+        }
+
+        proptest! {
+            #[test]
+            fn truncate_chars_is_a_bounded_prefix(s in mixed_text(), max in 0usize..80) {
+                let prefix = truncate_chars(&s, max);
+                prop_assert!(s.starts_with(prefix));
+                prop_assert_eq!(prefix.chars().count(), s.chars().count().min(max));
+            }
+
+            #[test]
+            fn truncate_str_marks_only_real_cuts(s in mixed_text(), max in 0usize..80) {
+                let shortened = truncate_str(&s, max);
+                if s.chars().count() <= max {
+                    prop_assert_eq!(shortened, s);
+                } else {
+                    let kept = shortened.strip_suffix("...").unwrap();
+                    prop_assert!(s.starts_with(kept));
+                    prop_assert_eq!(kept.chars().count(), max);
+                }
+            }
+
+            #[test]
+            fn last_chars_is_a_bounded_suffix(s in mixed_text(), n in 0usize..80) {
+                let suffix = last_chars(&s, n);
+                prop_assert!(s.ends_with(suffix));
+                prop_assert_eq!(suffix.chars().count(), s.chars().count().min(n));
+            }
+        }
+    }
+}
@@ -0,0 +1,79 @@
+//! Wallets the bot signs with
+//!
+//! Wallets live in the `wallets` table: either a private key sealed under the
+//! master key, or an account on an external JSON-RPC signer. The default
+//! wallet is the active signer for x402 payments and transactions. Without
+//! one, the key in `BURNER_WALLET_BOT_PRIVATE_KEY` is used as before.
+
+mod signer;
+
+pub use signer::{ExternalSigner, LocalSigner, WalletSigner};
+
+use ethers::types::Address;
+use std::str::FromStr;
+use std::sync::{Arc, RwLock};
+
+use crate::db::Database;
+use crate::models::{Wallet, WalletKind};
+
+/// Error for signing when neither a default wallet nor the env key is set
+pub const NO_WALLET: &str =
+    "No wallet configured: add one via /api/wallets or set BURNER_WALLET_BOT_PRIVATE_KEY";
+
+/// Signer of the default wallet, set by `activate_default`
+static ACTIVE: RwLock<Option<Arc<dyn WalletSigner>>> = RwLock::new(None);
+
+/// The default wallet's signer, or one for `BURNER_WALLET_BOT_PRIVATE_KEY`
+pub fn active_signer() -> Option<Arc<dyn WalletSigner>> {
+    if let Some(signer) = ACTIVE.read().unwrap().clone() {
+        return Some(signer);
+    }
+    let private_key = crate::config::burner_wallet_private_key()?;
+    match LocalSigner::new(&private_key) {
+        Ok(signer) => Some(Arc::new(signer)),
+        Err(e) => {
+            log::warn!("[wallet] BURNER_WALLET_BOT_PRIVATE_KEY is unusable: {}", e);
+            None
+        }
+    }
+}
+
+/// `active_signer`, or the error to show when there is none
+pub fn require_signer() -> Result<Arc<dyn WalletSigner>, String> {
+    active_signer().ok_or_else(|| NO_WALLET.to_string())
+}
+
+/// Build the signer for a stored wallet
+pub async fn open(db: &Database, wallet: &Wallet) -> Result<Arc<dyn WalletSigner>, String> {
+    match wallet.kind {
+        WalletKind::Local => {
+            let private_key = db
+                .get_wallet_private_key(wallet.id)
+                .await
+                .map_err(|e| format!("Failed to read key of wallet '{}': {}", wallet.name, e))?
+                .ok_or_else(|| format!("Wallet '{}' has no stored key", wallet.name))?;
+            Ok(Arc::new(LocalSigner::new(&private_key)?))
+        }
+        WalletKind::External => {
+            let url = wallet
+                .signer_url
+                .as_deref()
+                .ok_or_else(|| format!("Wallet '{}' has no signer URL", wallet.name))?;
+            let address = Address::from_str(&wallet.address)
+                .map_err(|e| format!("Wallet '{}' has an invalid address: {}", wallet.name, e))?;
+            Ok(Arc::new(ExternalSigner::connect(url, Some(address)).await?))
+        }
+    }
+}
+
+/// Make the default wallet in the database the active signer, or fall back
+/// to the env key if there is no default. Returns the wallet now in use.
+pub async fn activate_default(db: &Database) -> Result<Option<Wallet>, String> {
+    let wallet = db.get_default_wallet().await.map_err(|e| e.to_string())?;
+    let signer = match &wallet {
+        Some(wallet) => Some(open(db, wallet).await?),
+        None => None,
+    };
+    *ACTIVE.write().unwrap() = signer;
+    Ok(wallet)
+}
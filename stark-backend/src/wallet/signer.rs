@@ -0,0 +1,205 @@
+//! Signing backends: a key held in memory, or an external JSON-RPC signer
+
+use async_trait::async_trait;
+use ethers::signers::{LocalWallet, Signer};
+use ethers::types::transaction::eip2718::TypedTransaction;
+use ethers::types::transaction::eip712::TypedData;
+use ethers::types::{Address, Bytes, Signature};
+use serde_json::{json, Value};
+use std::str::FromStr;
+use std::time::Duration;
+
+/// Something that can sign for one EVM address
+#[async_trait]
+pub trait WalletSigner: Send + Sync {
+    fn address(&self) -> Address;
+
+    /// Sign EIP-712 typed data (x402 permits and transfer authorizations)
+    async fn sign_typed_data(&self, data: &TypedData) -> Result<Signature, String>;
+
+    /// Sign a transaction, returning it RLP-encoded and ready for `eth_sendRawTransaction`
+    async fn sign_transaction(&self, tx: &TypedTransaction) -> Result<Bytes, String>;
+}
+
+/// A private key held in memory
+pub struct LocalSigner {
+    wallet: LocalWallet,
+}
+
+impl LocalSigner {
+    /// Parse a hex private key, with or without the 0x prefix
+    pub fn new(private_key: &str) -> Result<Self, String> {
+        let wallet = private_key
+            .trim()
+            .trim_start_matches("0x")
+            .parse::<LocalWallet>()
+            .map_err(|e| format!("Invalid private key: {}", e))?;
+        Ok(Self { wallet })
+    }
+
+    /// A fresh random key
+    pub fn generate() -> Self {
+        Self {
+            wallet: LocalWallet::new(&mut rand::thread_rng()),
+        }
+    }
+
+    /// The private key as 0x-prefixed hex
+    pub fn private_key_hex(&self) -> String {
+        format!("0x{}", hex::encode(self.wallet.signer().to_bytes()))
+    }
+}
+
+#[async_trait]
+impl WalletSigner for LocalSigner {
+    fn address(&self) -> Address {
+        self.wallet.address()
+    }
+
+    async fn sign_typed_data(&self, data: &TypedData) -> Result<Signature, String> {
+        self.wallet
+            .sign_typed_data(data)
+            .await
+            .map_err(|e| format!("Failed to sign typed data: {}", e))
+    }
+
+    async fn sign_transaction(&self, tx: &TypedTransaction) -> Result<Bytes, String> {
+        let signature = self
+            .wallet
+            .sign_transaction_sync(tx)
+            .map_err(|e| format!("Failed to sign transaction: {}", e))?;
+        Ok(tx.rlp_signed(&signature))
+    }
+}
+
+/// An account on an external signer (e.g. Clef or Web3Signer) reached over
+/// JSON-RPC with `eth_accounts`, `eth_signTypedData_v4` and `eth_signTransaction`
+pub struct ExternalSigner {
+    url: String,
+    address: Address,
+    client: reqwest::Client,
+}
+
+impl ExternalSigner {
+    /// Connect to a signer and check it holds `address` (or take its first account)
+    pub async fn connect(url: &str, address: Option<Address>) -> Result<Self, String> {
+        let client = reqwest::Client::builder()
+            .timeout(Duration::from_secs(60))
+            .build()
+            .map_err(|e| format!("Failed to create HTTP client: {}", e))?;
+        let mut signer = Self {
+            url: url.to_string(),
+            address: address.unwrap_or_default(),
+            client,
+        };
+
+        let accounts: Vec<Address> = serde_json::from_value(signer.rpc("eth_accounts", json!([])).await?)
+            .map_err(|e| format!("Invalid eth_accounts response: {}", e))?;
+        signer.address = match address {
+            Some(address) if accounts.contains(&address) => address,
+            Some(address) => return Err(format!("Signer at {} does not hold {:?}", url, address)),
+            None => *accounts.first().ok_or_else(|| format!("Signer at {} has no accounts", url))?,
+        };
+        Ok(signer)
+    }
+
+    async fn rpc(&self, method: &str, params: Value) -> Result<Value, String> {
+        let response: Value = self
+            .client
+            .post(&self.url)
+            .json(&json!({ "jsonrpc": "2.0", "id": 1, "method": method, "params": params }))
+            .send()
+            .await
+            .map_err(|e| format!("Signer request failed: {}", e))?
+            .json()
+            .await
+            .map_err(|e| format!("Invalid signer response: {}", e))?;
+
+        if let Some(error) = response.get("error") {
+            let message = error.get("message").and_then(|m| m.as_str()).unwrap_or("unknown error");
+            return Err(format!("Signer rejected {}: {}", method, message));
+        }
+        response
+            .get("result")
+            .cloned()
+            .ok_or_else(|| format!("Signer returned no result for {}", method))
+    }
+}
+
+#[async_trait]
+impl WalletSigner for ExternalSigner {
+    fn address(&self) -> Address {
+        self.address
+    }
+
+    async fn sign_typed_data(&self, data: &TypedData) -> Result<Signature, String> {
+        let data = serde_json::to_string(data).map_err(|e| format!("Failed to encode typed data: {}", e))?;
+        let result = self
+            .rpc("eth_signTypedData_v4", json!([format!("{:?}", self.address), data]))
+            .await?;
+        let signature = result.as_str().ok_or("Signer returned a non-string signature")?;
+        Signature::from_str(signature).map_err(|e| format!("Invalid signature from signer: {}", e))
+    }
+
+    async fn sign_transaction(&self, tx: &TypedTransaction) -> Result<Bytes, String> {
+        let mut tx = tx.clone();
+        tx.set_from(self.address);
+        let result = self.rpc("eth_signTransaction", json!([tx])).await?;
+        parse_signed_transaction(&result)
+    }
+}
+
+/// `eth_signTransaction` returns the raw transaction, or (Clef/Geth) an object with it under `raw`
+fn parse_signed_transaction(result: &Value) -> Result<Bytes, String> {
+    let raw = result
+        .as_str()
+        .or_else(|| result.get("raw").and_then(|r| r.as_str()))
+        .ok_or("Signer returned no signed transaction")?;
+    Bytes::from_str(raw).map_err(|e| format!("Invalid signed transaction from signer: {}", e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ethers::types::{Eip1559TransactionRequest, U256};
+
+    const HARDHAT_KEY: &str = "0xac0974bec39a17e36ba4a6b4d238ff944bacb478cbed5efcae784d7bf4f2ff80";
+
+    #[tokio::test]
+    async fn test_local_signer() {
+        let signer = LocalSigner::new(HARDHAT_KEY).unwrap();
+        assert_eq!(
+            format!("{:?}", signer.address()),
+            "0xf39fd6e51aad88f6f4ce6ab8827279cfffb92266"
+        );
+        assert_eq!(signer.private_key_hex(), HARDHAT_KEY);
+        assert!(LocalSigner::new("not a key").is_err());
+
+        let generated = LocalSigner::generate();
+        let reloaded = LocalSigner::new(&generated.private_key_hex()).unwrap();
+        assert_eq!(reloaded.address(), generated.address());
+
+        let tx: TypedTransaction = Eip1559TransactionRequest::new()
+            .to(signer.address())
+            .value(U256::from(1))
+            .nonce(0)
+            .gas(21_000)
+            .max_fee_per_gas(1_000_000_000u64)
+            .max_priority_fee_per_gas(1_000_000u64)
+            .chain_id(8453u64)
+            .into();
+        let raw = signer.sign_transaction(&tx).await.unwrap();
+        let (decoded, signature) = TypedTransaction::decode_signed(&ethers::utils::rlp::Rlp::new(&raw)).unwrap();
+        assert_eq!(signature.recover(decoded.sighash()).unwrap(), signer.address());
+    }
+
+    #[test]
+    fn test_parse_signed_transaction() {
+        assert_eq!(parse_signed_transaction(&json!("0x02f8")).unwrap().to_vec(), vec![0x02, 0xf8]);
+        assert_eq!(
+            parse_signed_transaction(&json!({ "raw": "0x02f8", "tx": {} })).unwrap().to_vec(),
+            vec![0x02, 0xf8]
+        );
+        assert!(parse_signed_transaction(&json!({})).is_err());
+    }
+}
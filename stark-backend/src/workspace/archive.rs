@@ -0,0 +1,144 @@
+use std::fs;
+use std::io::{Seek, Write};
+use std::path::Path;
+
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use glob::Pattern;
+use walkdir::WalkDir;
+use zip::write::FileOptions;
+use zip::ZipWriter;
+
+/// Archive formats a workspace can be downloaded in
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ArchiveFormat {
+    Zip,
+    TarGz,
+}
+
+impl ArchiveFormat {
+    pub fn from_str(s: &str) -> Option<Self> {
+        match s.to_lowercase().as_str() {
+            "zip" => Some(ArchiveFormat::Zip),
+            "tar.gz" | "tgz" | "tar_gz" => Some(ArchiveFormat::TarGz),
+            _ => None,
+        }
+    }
+
+    pub fn extension(&self) -> &'static str {
+        match self {
+            ArchiveFormat::Zip => "zip",
+            ArchiveFormat::TarGz => "tar.gz",
+        }
+    }
+
+    pub fn content_type(&self) -> &'static str {
+        match self {
+            ArchiveFormat::Zip => "application/zip",
+            ArchiveFormat::TarGz => "application/gzip",
+        }
+    }
+}
+
+/// Parse a glob filter for archive entries, e.g. `dist/**` or `*.py`.
+/// `*` also matches across directories, so `*.py` selects Python files at any depth.
+pub fn parse_filter(glob: &str) -> Result<Pattern, String> {
+    Pattern::new(glob).map_err(|e| format!("Invalid glob '{}': {}", glob, e))
+}
+
+/// Entries under `dir` to archive, as (path, relative name, is_dir).
+///
+/// Symlinks are skipped. With a filter only matching files are included;
+/// their directories are implied by the paths.
+fn entries(dir: &Path, filter: Option<&Pattern>) -> Vec<(std::path::PathBuf, String, bool)> {
+    WalkDir::new(dir)
+        .min_depth(1)
+        .sort_by_file_name()
+        .into_iter()
+        .flatten()
+        .filter_map(|entry| {
+            let relative = entry.path().strip_prefix(dir).ok()?;
+            let name = relative.to_string_lossy().replace('\\', "/");
+            let is_dir = entry.file_type().is_dir();
+            let include = match filter {
+                _ if !is_dir && !entry.file_type().is_file() => false,
+                Some(pattern) => !is_dir && pattern.matches(&name),
+                None => true,
+            };
+            include.then(|| (entry.path().to_path_buf(), name, is_dir))
+        })
+        .collect()
+}
+
+/// Zip the contents of `dir`, with paths relative to it
+pub fn write_zip<W: Write + Seek>(dir: &Path, filter: Option<&Pattern>, writer: W) -> Result<(), String> {
+    let mut zip = ZipWriter::new(writer);
+    let options = FileOptions::default().compression_method(zip::CompressionMethod::Deflated);
+
+    for (path, name, is_dir) in entries(dir, filter) {
+        if is_dir {
+            zip.add_directory(name, options).map_err(|e| format!("Failed to write archive: {}", e))?;
+        } else {
+            let contents = fs::read(&path).map_err(|e| format!("Failed to read {}: {}", name, e))?;
+            zip.start_file(name, options).map_err(|e| format!("Failed to write archive: {}", e))?;
+            zip.write_all(&contents).map_err(|e| format!("Failed to write archive: {}", e))?;
+        }
+    }
+
+    zip.finish().map_err(|e| format!("Failed to write archive: {}", e))?;
+    Ok(())
+}
+
+/// Write the contents of `dir` as a gzipped tarball. Unlike zip this needs no
+/// seeking, so the output can be streamed as it's produced.
+pub fn write_tar_gz<W: Write>(dir: &Path, filter: Option<&Pattern>, writer: W) -> Result<(), String> {
+    let mut tar = tar::Builder::new(GzEncoder::new(writer, Compression::default()));
+
+    for (path, name, is_dir) in entries(dir, filter) {
+        let added = if is_dir {
+            tar.append_dir(&name, &path)
+        } else {
+            tar.append_path_with_name(&path, &name)
+        };
+        added.map_err(|e| format!("Failed to write archive entry {}: {}", name, e))?;
+    }
+
+    tar.into_inner()
+        .and_then(|gz| gz.finish())
+        .and_then(|mut w| w.flush())
+        .map_err(|e| format!("Failed to write archive: {}", e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use flate2::read::GzDecoder;
+    use std::io::Cursor;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_tar_gz_with_filter() {
+        let dir = TempDir::new().unwrap();
+        fs::create_dir_all(dir.path().join("app/src")).unwrap();
+        fs::write(dir.path().join("app/src/main.py"), "print('hi')").unwrap();
+        fs::write(dir.path().join("app/README.md"), "# App").unwrap();
+        fs::write(dir.path().join("setup.py"), "").unwrap();
+
+        let mut buffer = Vec::new();
+        let filter = parse_filter("*.py").unwrap();
+        write_tar_gz(dir.path(), Some(&filter), &mut buffer).unwrap();
+
+        let mut archive = tar::Archive::new(GzDecoder::new(Cursor::new(buffer)));
+        let mut names: Vec<String> = archive
+            .entries()
+            .unwrap()
+            .map(|e| e.unwrap().path().unwrap().to_string_lossy().to_string())
+            .collect();
+        names.sort();
+        assert_eq!(names, vec!["app/src/main.py", "setup.py"]);
+
+        assert!(parse_filter("[").is_err());
+        assert_eq!(ArchiveFormat::from_str("TGZ"), Some(ArchiveFormat::TarGz));
+        assert_eq!(ArchiveFormat::from_str("rar"), None);
+    }
+}
@@ -0,0 +1,607 @@
+use std::fs;
+use std::io::{Cursor, Read, Write};
+use std::path::{Path, PathBuf};
+
+use chrono::{DateTime, Utc};
+use glob::Pattern;
+use serde::Serialize;
+use walkdir::WalkDir;
+use zip::ZipArchive;
+
+use super::archive::{write_tar_gz, write_zip};
+use super::upload::{extract_archive, is_archive, UploadLimits, UploadedFile};
+use crate::tools::workspace_path::WorkspacePath;
+
+/// Directory under the workspace root holding snapshot archives
+const SNAPSHOTS_DIR: &str = ".snapshots";
+
+/// Directory under the workspace root holding the full tool outputs of agent runs
+const OUTPUTS_DIR: &str = ".outputs";
+
+/// Directory under the workspace root holding upload manifests and uploads in progress
+const UPLOADS_DIR: &str = ".uploads";
+
+/// What a workspace belongs to
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum WorkspaceKind {
+    /// A chat session, named by its session id
+    Session,
+    /// An `/api/agent/run` call or background agent job, named by its workspace name
+    AgentRun,
+}
+
+impl WorkspaceKind {
+    pub fn all() -> [WorkspaceKind; 2] {
+        [WorkspaceKind::Session, WorkspaceKind::AgentRun]
+    }
+
+    /// Directory under the workspace root, also used in `/api/workspaces` paths
+    pub fn dir_name(&self) -> &'static str {
+        match self {
+            WorkspaceKind::Session => "sessions",
+            WorkspaceKind::AgentRun => "agent-runs",
+        }
+    }
+
+    pub fn from_dir_name(s: &str) -> Option<Self> {
+        Self::all().into_iter().find(|k| k.dir_name() == s)
+    }
+}
+
+/// A workspace on disk
+#[derive(Debug, Clone, Serialize)]
+pub struct WorkspaceInfo {
+    pub kind: WorkspaceKind,
+    pub name: String,
+    pub size_bytes: u64,
+    pub file_count: u64,
+    pub modified_at: Option<DateTime<Utc>>,
+    pub snapshots: Vec<SnapshotInfo>,
+}
+
+/// A saved copy of a workspace that it can be restored to
+#[derive(Debug, Clone, Serialize)]
+pub struct SnapshotInfo {
+    pub id: String,
+    pub size_bytes: u64,
+    pub created_at: Option<DateTime<Utc>>,
+}
+
+/// Allocates, measures, archives and removes per-conversation workspaces
+#[derive(Debug, Clone)]
+pub struct WorkspaceManager {
+    root: PathBuf,
+    quota_bytes: Option<u64>,
+}
+
+impl WorkspaceManager {
+    pub fn new(root: impl Into<PathBuf>, quota_bytes: Option<u64>) -> Self {
+        Self {
+            root: root.into(),
+            quota_bytes,
+        }
+    }
+
+    /// Manager for the configured workspace root and quota
+    pub fn from_env() -> Self {
+        Self::new(crate::config::workspace_dir(), crate::config::workspace_quota_bytes())
+    }
+
+    /// Size limit for a single workspace (None = unlimited)
+    pub fn quota_bytes(&self) -> Option<u64> {
+        self.quota_bytes
+    }
+
+    /// Directory of a workspace, without creating it
+    pub fn path(&self, kind: WorkspaceKind, name: &str) -> Result<PathBuf, String> {
+        if !is_valid_name(name) {
+            return Err(
+                "Workspace name may only contain letters, digits, '-' and '_' (max 64 characters)".to_string(),
+            );
+        }
+        Ok(self.root.join(kind.dir_name()).join(name))
+    }
+
+    /// Directory of a workspace, created if it doesn't exist yet
+    pub fn allocate(&self, kind: WorkspaceKind, name: &str) -> Result<PathBuf, String> {
+        let path = self.path(kind, name)?;
+        fs::create_dir_all(&path)
+            .map_err(|e| format!("Failed to create workspace {}: {}", path.display(), e))?;
+        Ok(path)
+    }
+
+    /// Workspace of a chat session, created if needed
+    pub fn for_session(&self, session_id: i64) -> Result<PathBuf, String> {
+        self.allocate(WorkspaceKind::Session, &session_id.to_string())
+    }
+
+    /// Directory for the stored tool outputs of an agent job or run, without creating it
+    pub fn outputs_dir(&self, owner: &str) -> Result<PathBuf, String> {
+        if !is_valid_name(owner) {
+            return Err(format!("Invalid output store name '{}'", owner));
+        }
+        Ok(self.root.join(OUTPUTS_DIR).join(owner))
+    }
+
+    /// All workspaces, largest first
+    pub fn list(&self) -> Vec<WorkspaceInfo> {
+        let mut workspaces = Vec::new();
+        for kind in WorkspaceKind::all() {
+            let Ok(entries) = fs::read_dir(self.root.join(kind.dir_name())) else {
+                continue;
+            };
+            for entry in entries.flatten() {
+                let name = entry.file_name().to_string_lossy().to_string();
+                if entry.path().is_dir() && is_valid_name(&name) {
+                    workspaces.push(self.info(kind, &name, &entry.path()));
+                }
+            }
+        }
+        workspaces.sort_by_key(|w| std::cmp::Reverse(w.size_bytes));
+        workspaces
+    }
+
+    /// One workspace, or None if it doesn't exist
+    pub fn get(&self, kind: WorkspaceKind, name: &str) -> Result<Option<WorkspaceInfo>, String> {
+        let path = self.path(kind, name)?;
+        Ok(path.is_dir().then(|| self.info(kind, name, &path)))
+    }
+
+    fn info(&self, kind: WorkspaceKind, name: &str, path: &Path) -> WorkspaceInfo {
+        let (size_bytes, file_count) = dir_size(path);
+        WorkspaceInfo {
+            kind,
+            name: name.to_string(),
+            size_bytes,
+            file_count,
+            modified_at: fs::metadata(path).and_then(|m| m.modified()).ok().map(DateTime::<Utc>::from),
+            snapshots: self.list_snapshots(kind, name),
+        }
+    }
+
+    /// Delete a workspace and its snapshots. Returns false if it didn't exist.
+    pub fn delete(&self, kind: WorkspaceKind, name: &str) -> Result<bool, String> {
+        let path = self.path(kind, name)?;
+        let snapshots = self.snapshots_dir(kind, name);
+        if snapshots.exists() {
+            fs::remove_dir_all(&snapshots)
+                .map_err(|e| format!("Failed to delete snapshots of {}: {}", name, e))?;
+        }
+        let _ = fs::remove_file(self.manifest_path(kind, name));
+        if !path.exists() {
+            return Ok(false);
+        }
+        fs::remove_dir_all(&path).map_err(|e| format!("Failed to delete workspace {}: {}", name, e))?;
+        Ok(true)
+    }
+
+    /// Error if the workspace at `path` is over the quota; otherwise the bytes it uses
+    pub fn check_quota(&self, path: &Path) -> Result<u64, String> {
+        let (used, _) = dir_size(path);
+        match self.quota_bytes {
+            Some(quota) if used > quota => Err(format!(
+                "Workspace quota exceeded: {} used of {}. Delete files before writing more.",
+                format_size(used),
+                format_size(quota)
+            )),
+            _ => Ok(used),
+        }
+    }
+
+    /// The workspace as a zip archive, optionally only the files matching `filter`
+    pub fn zip(&self, kind: WorkspaceKind, name: &str, filter: Option<&Pattern>) -> Result<Vec<u8>, String> {
+        let path = self.existing(kind, name)?;
+        let mut buffer = Cursor::new(Vec::new());
+        write_zip(&path, filter, &mut buffer)?;
+        Ok(buffer.into_inner())
+    }
+
+    /// Write the workspace to `writer` as a gzipped tarball, optionally only the files matching `filter`
+    pub fn write_tar_gz(
+        &self,
+        kind: WorkspaceKind,
+        name: &str,
+        filter: Option<&Pattern>,
+        writer: impl Write,
+    ) -> Result<(), String> {
+        let path = self.existing(kind, name)?;
+        write_tar_gz(&path, filter, writer)
+    }
+
+    /// A regular file inside the workspace; `relative` can't escape it through `..` or symlinks
+    pub fn file(&self, kind: WorkspaceKind, name: &str, relative: &str) -> Result<PathBuf, String> {
+        let root = self.existing(kind, name)?;
+        let resolved = WorkspacePath::resolve(&root, relative)?;
+        if !resolved.path().is_file() {
+            return Err(format!("File '{}' not found", relative));
+        }
+        Ok(resolved.path().to_path_buf())
+    }
+
+    /// Save the workspace's current files as a snapshot
+    pub fn snapshot(&self, kind: WorkspaceKind, name: &str) -> Result<SnapshotInfo, String> {
+        let path = self.existing(kind, name)?;
+        let dir = self.snapshots_dir(kind, name);
+        fs::create_dir_all(&dir).map_err(|e| format!("Failed to create snapshot directory: {}", e))?;
+
+        let id = Utc::now().format("%Y%m%dT%H%M%S%3fZ").to_string();
+        let archive = dir.join(format!("{}.zip", id));
+        let mut file = fs::File::create(&archive).map_err(|e| format!("Failed to create snapshot: {}", e))?;
+        if let Err(e) = write_zip(&path, None, &mut file) {
+            let _ = fs::remove_file(&archive);
+            return Err(e);
+        }
+        Ok(snapshot_info(&archive).unwrap_or(SnapshotInfo {
+            id,
+            size_bytes: 0,
+            created_at: None,
+        }))
+    }
+
+    /// Replace the workspace's files with those of a snapshot
+    pub fn restore(&self, kind: WorkspaceKind, name: &str, snapshot_id: &str) -> Result<(), String> {
+        let archive = self.snapshot_path(kind, name, snapshot_id)?;
+        let file = fs::File::open(&archive).map_err(|_| format!("Snapshot '{}' not found", snapshot_id))?;
+        let mut zip = ZipArchive::new(file).map_err(|e| format!("Invalid snapshot: {}", e))?;
+
+        let path = self.path(kind, name)?;
+        if path.exists() {
+            fs::remove_dir_all(&path).map_err(|e| format!("Failed to clear workspace: {}", e))?;
+        }
+        fs::create_dir_all(&path).map_err(|e| format!("Failed to create workspace: {}", e))?;
+
+        for i in 0..zip.len() {
+            let mut entry = zip.by_index(i).map_err(|e| format!("Invalid snapshot: {}", e))?;
+            // enclosed_name rejects absolute paths and `..`
+            let Some(relative) = entry.enclosed_name().map(Path::to_path_buf) else {
+                continue;
+            };
+            let target = path.join(relative);
+            if entry.is_dir() {
+                fs::create_dir_all(&target).map_err(|e| format!("Failed to restore {}: {}", target.display(), e))?;
+                continue;
+            }
+            if let Some(parent) = target.parent() {
+                fs::create_dir_all(parent).map_err(|e| format!("Failed to restore {}: {}", parent.display(), e))?;
+            }
+            let mut contents = Vec::new();
+            entry
+                .read_to_end(&mut contents)
+                .map_err(|e| format!("Failed to read snapshot: {}", e))?;
+            fs::write(&target, contents).map_err(|e| format!("Failed to restore {}: {}", target.display(), e))?;
+        }
+        Ok(())
+    }
+
+    /// Delete one snapshot. Returns false if it didn't exist.
+    pub fn delete_snapshot(&self, kind: WorkspaceKind, name: &str, snapshot_id: &str) -> Result<bool, String> {
+        let archive = self.snapshot_path(kind, name, snapshot_id)?;
+        if !archive.exists() {
+            return Ok(false);
+        }
+        fs::remove_file(&archive).map_err(|e| format!("Failed to delete snapshot: {}", e))?;
+        Ok(true)
+    }
+
+    /// Snapshots of a workspace, newest first
+    pub fn list_snapshots(&self, kind: WorkspaceKind, name: &str) -> Vec<SnapshotInfo> {
+        let Ok(entries) = fs::read_dir(self.snapshots_dir(kind, name)) else {
+            return Vec::new();
+        };
+        let mut snapshots: Vec<SnapshotInfo> = entries.flatten().filter_map(|e| snapshot_info(&e.path())).collect();
+        // Ids are timestamps, so they sort chronologically
+        snapshots.sort_by(|a, b| b.id.cmp(&a.id));
+        snapshots
+    }
+
+    /// A new, empty directory to receive uploaded files before `import_upload`
+    pub fn staging_dir(&self) -> Result<PathBuf, String> {
+        let dir = self.root.join(UPLOADS_DIR).join("staging").join(uuid::Uuid::new_v4().to_string());
+        fs::create_dir_all(&dir).map_err(|e| format!("Failed to create upload directory: {}", e))?;
+        Ok(dir)
+    }
+
+    /// Move the files under `staged` into the workspace (created if needed)
+    /// below `dest`, unpacking zip and tar archives first when `extract` is
+    /// set, and record them as uploads. The staging directory is removed
+    /// either way; nothing is moved if the upload breaks a limit or the quota.
+    pub fn import_upload(
+        &self,
+        kind: WorkspaceKind,
+        name: &str,
+        staged: &Path,
+        dest: &str,
+        extract: bool,
+        limits: &UploadLimits,
+    ) -> Result<Vec<UploadedFile>, String> {
+        let result = self.import_staged(kind, name, staged, dest, extract, limits);
+        let _ = fs::remove_dir_all(staged);
+        result
+    }
+
+    fn import_staged(
+        &self,
+        kind: WorkspaceKind,
+        name: &str,
+        staged: &Path,
+        dest: &str,
+        extract: bool,
+        limits: &UploadLimits,
+    ) -> Result<Vec<UploadedFile>, String> {
+        if extract {
+            let archives: Vec<PathBuf> = staged_files(staged)
+                .into_iter()
+                .filter(|p| is_archive(&p.to_string_lossy()))
+                .collect();
+            let plain_bytes = dir_size(staged).0 - archives.iter().map(|a| file_size(a)).sum::<u64>();
+            let mut budget = limits.max_extracted_bytes.saturating_sub(plain_bytes);
+            for archive in archives {
+                let parent = archive.parent().unwrap_or(staged).to_path_buf();
+                extract_archive(&archive, &parent, &mut budget)?;
+                fs::remove_file(&archive).map_err(|e| format!("Failed to remove archive: {}", e))?;
+            }
+        }
+
+        let files = staged_files(staged);
+        if files.is_empty() {
+            return Err("No files uploaded".to_string());
+        }
+        let workspace = self.allocate(kind, name)?;
+        let incoming = dir_size(staged).0;
+        if let Some(quota) = self.quota_bytes {
+            let used = dir_size(&workspace).0;
+            if used + incoming > quota {
+                return Err(format!(
+                    "Workspace quota exceeded: the upload needs {} but only {} of {} is free",
+                    format_size(incoming),
+                    format_size(quota.saturating_sub(used)),
+                    format_size(quota)
+                ));
+            }
+        }
+
+        let now = Utc::now();
+        let mut uploaded = Vec::new();
+        for file in files {
+            let Ok(relative) = file.strip_prefix(staged) else {
+                continue;
+            };
+            let relative = Path::new(dest).join(relative).to_string_lossy().replace('\\', "/");
+            // Resolving through the workspace keeps symlinked directories from redirecting the write
+            let target = WorkspacePath::resolve(&workspace, &relative)?;
+            if target.path().is_dir() {
+                return Err(format!("'{}' is a directory in the workspace", relative));
+            }
+            if let Some(parent) = target.path().parent() {
+                fs::create_dir_all(parent).map_err(|e| format!("Failed to create {}: {}", parent.display(), e))?;
+            }
+            fs::rename(&file, target.path()).map_err(|e| format!("Failed to store {}: {}", relative, e))?;
+            let display = target.path().strip_prefix(target.root()).unwrap_or(target.path());
+            uploaded.push(UploadedFile {
+                path: display.to_string_lossy().replace('\\', "/"),
+                size_bytes: file_size(target.path()),
+                uploaded_at: now,
+            });
+        }
+
+        let mut manifest = self.read_manifest(kind, name);
+        manifest.retain(|f| !uploaded.iter().any(|u| u.path == f.path));
+        manifest.extend(uploaded.iter().cloned());
+        self.write_manifest(kind, name, &manifest)?;
+        Ok(uploaded)
+    }
+
+    /// Files uploaded into a workspace that are still there, oldest first
+    pub fn uploads(&self, kind: WorkspaceKind, name: &str) -> Vec<UploadedFile> {
+        let Ok(workspace) = self.path(kind, name) else {
+            return Vec::new();
+        };
+        let mut files = self.read_manifest(kind, name);
+        files.retain(|f| workspace.join(&f.path).is_file());
+        files
+    }
+
+    fn manifest_path(&self, kind: WorkspaceKind, name: &str) -> PathBuf {
+        self.root.join(UPLOADS_DIR).join(kind.dir_name()).join(format!("{}.json", name))
+    }
+
+    fn read_manifest(&self, kind: WorkspaceKind, name: &str) -> Vec<UploadedFile> {
+        fs::read(self.manifest_path(kind, name))
+            .ok()
+            .and_then(|bytes| serde_json::from_slice(&bytes).ok())
+            .unwrap_or_default()
+    }
+
+    fn write_manifest(&self, kind: WorkspaceKind, name: &str, files: &[UploadedFile]) -> Result<(), String> {
+        let path = self.manifest_path(kind, name);
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent).map_err(|e| format!("Failed to record uploads: {}", e))?;
+        }
+        let json = serde_json::to_vec_pretty(files).map_err(|e| format!("Failed to record uploads: {}", e))?;
+        fs::write(&path, json).map_err(|e| format!("Failed to record uploads: {}", e))
+    }
+
+    fn existing(&self, kind: WorkspaceKind, name: &str) -> Result<PathBuf, String> {
+        let path = self.path(kind, name)?;
+        if !path.is_dir() {
+            return Err(format!("Workspace '{}' not found", name));
+        }
+        Ok(path)
+    }
+
+    fn snapshots_dir(&self, kind: WorkspaceKind, name: &str) -> PathBuf {
+        self.root.join(SNAPSHOTS_DIR).join(kind.dir_name()).join(name)
+    }
+
+    fn snapshot_path(&self, kind: WorkspaceKind, name: &str, snapshot_id: &str) -> Result<PathBuf, String> {
+        self.path(kind, name)?;
+        if !is_valid_name(snapshot_id) {
+            return Err(format!("Invalid snapshot id '{}'", snapshot_id));
+        }
+        Ok(self.snapshots_dir(kind, name).join(format!("{}.zip", snapshot_id)))
+    }
+}
+
+/// Workspace and snapshot names become directory names, so keep them to a safe character set
+fn is_valid_name(name: &str) -> bool {
+    !name.is_empty()
+        && name.len() <= 64
+        && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+}
+
+/// Total size in bytes and number of files under `path`. Symlinks are not followed.
+pub fn dir_size(path: &Path) -> (u64, u64) {
+    WalkDir::new(path)
+        .into_iter()
+        .flatten()
+        .filter(|e| e.file_type().is_file())
+        .fold((0, 0), |(bytes, files), e| {
+            (bytes + e.metadata().map(|m| m.len()).unwrap_or(0), files + 1)
+        })
+}
+
+/// Regular files under `dir`, sorted
+fn staged_files(dir: &Path) -> Vec<PathBuf> {
+    WalkDir::new(dir)
+        .sort_by_file_name()
+        .into_iter()
+        .flatten()
+        .filter(|e| e.file_type().is_file())
+        .map(|e| e.into_path())
+        .collect()
+}
+
+fn file_size(path: &Path) -> u64 {
+    fs::metadata(path).map(|m| m.len()).unwrap_or(0)
+}
+
+fn format_size(bytes: u64) -> String {
+    format!("{:.1} MB", bytes as f64 / (1024.0 * 1024.0))
+}
+
+fn snapshot_info(archive: &Path) -> Option<SnapshotInfo> {
+    if archive.extension()? != "zip" {
+        return None;
+    }
+    let id = archive.file_stem()?.to_string_lossy().to_string();
+    let metadata = fs::metadata(archive).ok()?;
+    Some(SnapshotInfo {
+        created_at: metadata.modified().ok().map(DateTime::<Utc>::from),
+        size_bytes: metadata.len(),
+        id,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_workspaces_are_isolated_and_listed() {
+        let root = TempDir::new().unwrap();
+        let manager = WorkspaceManager::new(root.path(), None);
+
+        let a = manager.for_session(1).unwrap();
+        let b = manager.allocate(WorkspaceKind::AgentRun, "build").unwrap();
+        assert_ne!(a, b);
+        fs::write(a.join("notes.txt"), "hello").unwrap();
+
+        let workspaces = manager.list();
+        assert_eq!(workspaces.len(), 2);
+        assert_eq!(workspaces[0].kind, WorkspaceKind::Session);
+        assert_eq!(workspaces[0].size_bytes, 5);
+        assert_eq!(workspaces[0].file_count, 1);
+
+        assert!(manager.path(WorkspaceKind::AgentRun, "../etc").is_err());
+        assert!(manager.delete(WorkspaceKind::Session, "1").unwrap());
+        assert!(!manager.delete(WorkspaceKind::Session, "1").unwrap());
+        assert!(manager.get(WorkspaceKind::Session, "1").unwrap().is_none());
+    }
+
+    #[test]
+    fn test_quota() {
+        let root = TempDir::new().unwrap();
+        let manager = WorkspaceManager::new(root.path(), Some(10));
+        let dir = manager.for_session(7).unwrap();
+
+        fs::write(dir.join("small.txt"), "12345").unwrap();
+        assert_eq!(manager.check_quota(&dir).unwrap(), 5);
+
+        fs::write(dir.join("big.txt"), "1234567890").unwrap();
+        assert!(manager.check_quota(&dir).unwrap_err().contains("quota exceeded"));
+    }
+
+    #[test]
+    fn test_snapshot_restore_and_zip() {
+        let root = TempDir::new().unwrap();
+        let manager = WorkspaceManager::new(root.path(), None);
+        let dir = manager.allocate(WorkspaceKind::AgentRun, "app").unwrap();
+        fs::create_dir(dir.join("src")).unwrap();
+        fs::write(dir.join("src/main.rs"), "fn main() {}").unwrap();
+
+        let snapshot = manager.snapshot(WorkspaceKind::AgentRun, "app").unwrap();
+        fs::write(dir.join("src/main.rs"), "broken").unwrap();
+        fs::write(dir.join("extra.txt"), "x").unwrap();
+
+        manager.restore(WorkspaceKind::AgentRun, "app", &snapshot.id).unwrap();
+        assert_eq!(fs::read_to_string(dir.join("src/main.rs")).unwrap(), "fn main() {}");
+        assert!(!dir.join("extra.txt").exists());
+        assert_eq!(manager.list_snapshots(WorkspaceKind::AgentRun, "app").len(), 1);
+
+        let archive = manager.zip(WorkspaceKind::AgentRun, "app", None).unwrap();
+        let mut zip = ZipArchive::new(Cursor::new(archive)).unwrap();
+        let mut contents = String::new();
+        zip.by_name("src/main.rs").unwrap().read_to_string(&mut contents).unwrap();
+        assert_eq!(contents, "fn main() {}");
+
+        assert!(manager.file(WorkspaceKind::AgentRun, "app", "src/main.rs").is_ok());
+        assert!(manager.file(WorkspaceKind::AgentRun, "app", "../../etc/passwd").is_err());
+        assert!(manager.file(WorkspaceKind::AgentRun, "app", "src").is_err());
+
+        assert!(manager.restore(WorkspaceKind::AgentRun, "app", "missing").is_err());
+        assert!(manager.delete_snapshot(WorkspaceKind::AgentRun, "app", &snapshot.id).unwrap());
+        assert!(manager.list_snapshots(WorkspaceKind::AgentRun, "app").is_empty());
+    }
+
+    #[test]
+    fn test_import_upload() {
+        let root = TempDir::new().unwrap();
+        let manager = WorkspaceManager::new(root.path(), Some(1000));
+        let limits = UploadLimits { max_bytes: 1000, max_extracted_bytes: 500 };
+
+        let staged = manager.staging_dir().unwrap();
+        fs::write(staged.join("sales.csv"), "a,b\n1,2\n").unwrap();
+        let mut zip = zip::ZipWriter::new(fs::File::create(staged.join("src.zip")).unwrap());
+        zip.start_file("lib/app.py", zip::write::FileOptions::default()).unwrap();
+        zip.write_all(b"print(1)").unwrap();
+        zip.finish().unwrap();
+
+        let files = manager
+            .import_upload(WorkspaceKind::Session, "3", &staged, "input", true, &limits)
+            .unwrap();
+        let paths: Vec<&str> = files.iter().map(|f| f.path.as_str()).collect();
+        assert_eq!(paths, vec!["input/lib/app.py", "input/sales.csv"]);
+        assert!(!staged.exists());
+        let dir = manager.path(WorkspaceKind::Session, "3").unwrap();
+        assert_eq!(fs::read_to_string(dir.join("input/lib/app.py")).unwrap(), "print(1)");
+        assert!(!dir.join("input/src.zip").exists());
+
+        fs::remove_file(dir.join("input/sales.csv")).unwrap();
+        let uploads = manager.uploads(WorkspaceKind::Session, "3");
+        assert_eq!(uploads.len(), 1);
+        assert_eq!(uploads[0].size_bytes, 8);
+
+        let staged = manager.staging_dir().unwrap();
+        fs::write(staged.join("big.bin"), vec![0u8; 1000]).unwrap();
+        let err = manager
+            .import_upload(WorkspaceKind::Session, "3", &staged, "", true, &limits)
+            .unwrap_err();
+        assert!(err.contains("quota exceeded"));
+        assert!(!staged.exists() && !dir.join("big.bin").exists());
+
+        assert!(manager.delete(WorkspaceKind::Session, "3").unwrap());
+        assert!(manager.uploads(WorkspaceKind::Session, "3").is_empty());
+    }
+}
@@ -0,0 +1,22 @@
+//! Per-conversation workspaces
+//!
+//! Every chat session and agent run gets its own directory under the workspace
+//! root, so files from one conversation never leak into another:
+//!
+//! - `sessions/<session_id>` for chat sessions (and their subagents)
+//! - `agent-runs/<name>` for `/api/agent/run` and background agent jobs
+//!
+//! The [`WorkspaceManager`] allocates these, enforces the size quota from
+//! `STARK_WORKSPACE_QUOTA_MB`, zips them for download and keeps snapshots
+//! under `.snapshots/` that a workspace can be restored from. Tool outputs too
+//! long for the model are kept per job under `.outputs/`, and the files users
+//! upload into a workspace are listed under `.uploads/` so runs in that
+//! workspace are told about them.
+
+mod archive;
+mod manager;
+mod upload;
+
+pub use archive::{parse_filter, ArchiveFormat};
+pub use manager::{SnapshotInfo, WorkspaceInfo, WorkspaceKind, WorkspaceManager};
+pub use upload::{safe_relative, uploads_prompt, UploadLimits, UploadedFile};
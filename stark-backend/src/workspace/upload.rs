@@ -0,0 +1,212 @@
+use std::fs;
+use std::io::{self, Read};
+use std::path::{Path, PathBuf};
+
+use chrono::{DateTime, Utc};
+use flate2::read::GzDecoder;
+use serde::{Deserialize, Serialize};
+use zip::ZipArchive;
+
+/// Most entries extracted from a single archive
+const MAX_ARCHIVE_ENTRIES: usize = 10_000;
+/// Most uploaded files listed in a system prompt
+const MAX_PROMPT_FILES: usize = 50;
+
+/// A file the user uploaded into a workspace
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct UploadedFile {
+    /// Path relative to the workspace root
+    pub path: String,
+    pub size_bytes: u64,
+    pub uploaded_at: DateTime<Utc>,
+}
+
+/// Size limits for one upload request
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct UploadLimits {
+    /// Bytes of the uploaded files themselves
+    pub max_bytes: u64,
+    /// Bytes of the files once archives are extracted
+    pub max_extracted_bytes: u64,
+}
+
+impl UploadLimits {
+    pub fn from_env() -> Self {
+        Self {
+            max_bytes: crate::config::upload_max_bytes(),
+            max_extracted_bytes: crate::config::upload_max_extracted_bytes(),
+        }
+    }
+}
+
+/// A system prompt section listing the files uploaded into the workspace, or
+/// None when there are none
+pub fn uploads_prompt(files: &[UploadedFile]) -> Option<String> {
+    if files.is_empty() {
+        return None;
+    }
+    let mut section = String::from(
+        "## Uploaded Files\nThe user uploaded these files into your workspace (paths are relative to the workspace root):\n",
+    );
+    for file in files.iter().take(MAX_PROMPT_FILES) {
+        section.push_str(&format!("- {} ({} bytes)\n", file.path, file.size_bytes));
+    }
+    if files.len() > MAX_PROMPT_FILES {
+        section.push_str(&format!("- ...and {} more\n", files.len() - MAX_PROMPT_FILES));
+    }
+    Some(section)
+}
+
+/// Archives that are unpacked on upload, by file name
+pub fn is_archive(name: &str) -> bool {
+    let name = name.to_ascii_lowercase();
+    [".zip", ".tar", ".tar.gz", ".tgz"].iter().any(|ext| name.ends_with(ext))
+}
+
+/// A client-supplied path as a relative path, or None if it is empty or climbs
+/// out with `..`. Backslashes count as separators and leading slashes are dropped.
+pub fn safe_relative(name: &str) -> Option<PathBuf> {
+    let mut path = PathBuf::new();
+    for part in name.split(['/', '\\']) {
+        match part {
+            "" | "." => {}
+            ".." => return None,
+            part => path.push(part),
+        }
+    }
+    (!path.as_os_str().is_empty()).then_some(path)
+}
+
+/// Unpack a zip or (gzipped) tarball into `dest`. Only regular files and
+/// directories are extracted; links and entries escaping `dest` are refused.
+/// `budget` is the number of bytes that may still be written and is reduced
+/// by what the archive extracts to.
+pub fn extract_archive(archive: &Path, dest: &Path, budget: &mut u64) -> Result<(), String> {
+    let name = archive.file_name().map(|n| n.to_string_lossy().to_ascii_lowercase()).unwrap_or_default();
+    let file = fs::File::open(archive).map_err(|e| format!("Failed to open {}: {}", name, e))?;
+    if name.ends_with(".zip") {
+        extract_zip(file, dest, budget)
+    } else if name.ends_with(".tar") {
+        extract_tar(file, dest, budget)
+    } else {
+        extract_tar(GzDecoder::new(file), dest, budget)
+    }
+    .map_err(|e| format!("Failed to extract {}: {}", name, e))
+}
+
+fn extract_zip(file: fs::File, dest: &Path, budget: &mut u64) -> Result<(), String> {
+    let mut zip = ZipArchive::new(file).map_err(|e| e.to_string())?;
+    if zip.len() > MAX_ARCHIVE_ENTRIES {
+        return Err(format!("more than {} entries", MAX_ARCHIVE_ENTRIES));
+    }
+    for i in 0..zip.len() {
+        let mut entry = zip.by_index(i).map_err(|e| e.to_string())?;
+        let Some(relative) = safe_relative(entry.name()) else {
+            return Err(format!("unsafe path '{}'", entry.name()));
+        };
+        if entry.is_dir() {
+            fs::create_dir_all(dest.join(&relative)).map_err(|e| e.to_string())?;
+        } else if entry.unix_mode().is_some_and(|mode| mode & 0o170000 == 0o120000) {
+            return Err(format!("'{}' is a symlink", entry.name()));
+        } else {
+            write_entry(&mut entry, &dest.join(&relative), budget)?;
+        }
+    }
+    Ok(())
+}
+
+fn extract_tar(reader: impl Read, dest: &Path, budget: &mut u64) -> Result<(), String> {
+    let mut archive = tar::Archive::new(reader);
+    let entries = archive.entries().map_err(|e| e.to_string())?;
+    for (count, entry) in entries.enumerate() {
+        if count >= MAX_ARCHIVE_ENTRIES {
+            return Err(format!("more than {} entries", MAX_ARCHIVE_ENTRIES));
+        }
+        let mut entry = entry.map_err(|e| e.to_string())?;
+        let name = entry.path().map_err(|e| e.to_string())?.to_string_lossy().to_string();
+        let kind = entry.header().entry_type();
+        if kind.is_pax_global_extensions() || kind.is_pax_local_extensions() || kind.is_gnu_longname() {
+            continue;
+        }
+        let Some(relative) = safe_relative(&name) else {
+            // A bare "./" root entry is harmless
+            if kind.is_dir() {
+                continue;
+            }
+            return Err(format!("unsafe path '{}'", name));
+        };
+        if kind.is_dir() {
+            fs::create_dir_all(dest.join(&relative)).map_err(|e| e.to_string())?;
+        } else if kind.is_file() {
+            write_entry(&mut entry, &dest.join(&relative), budget)?;
+        } else {
+            return Err(format!("'{}' is not a regular file or directory", name));
+        }
+    }
+    Ok(())
+}
+
+/// Copy one archive entry to `target`, charging it against `budget`
+fn write_entry(reader: &mut impl Read, target: &Path, budget: &mut u64) -> Result<(), String> {
+    if let Some(parent) = target.parent() {
+        fs::create_dir_all(parent).map_err(|e| e.to_string())?;
+    }
+    let mut out = fs::File::create(target).map_err(|e| e.to_string())?;
+    // Read one byte past the budget to tell "exactly fits" from "too big"
+    let written = io::copy(&mut reader.take(*budget + 1), &mut out).map_err(|e| e.to_string())?;
+    if written > *budget {
+        return Err("the extracted files exceed the upload size limit".to_string());
+    }
+    *budget -= written;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+    use tempfile::TempDir;
+    use zip::write::FileOptions;
+
+    #[test]
+    fn test_safe_relative() {
+        assert_eq!(safe_relative("data/a.csv"), Some(PathBuf::from("data/a.csv")));
+        assert_eq!(safe_relative("./src\\main.rs"), Some(PathBuf::from("src/main.rs")));
+        assert_eq!(safe_relative("../etc/passwd"), None);
+        assert_eq!(safe_relative("a/../../b"), None);
+        assert_eq!(safe_relative("/etc/passwd"), Some(PathBuf::from("etc/passwd")));
+        assert_eq!(safe_relative("C:"), Some(PathBuf::from("C:")));
+        assert_eq!(safe_relative("./"), None);
+        assert!(is_archive("src.TAR.GZ") && is_archive("a.zip") && !is_archive("a.gz"));
+    }
+
+    #[test]
+    fn test_extract_archives_with_limits() {
+        let dir = TempDir::new().unwrap();
+        let zip_path = dir.path().join("app.zip");
+        let mut zip = zip::ZipWriter::new(fs::File::create(&zip_path).unwrap());
+        zip.add_directory("app/", FileOptions::default()).unwrap();
+        zip.start_file("app/main.py", FileOptions::default()).unwrap();
+        zip.write_all(b"print('hi')").unwrap();
+        zip.finish().unwrap();
+
+        let dest = dir.path().join("out");
+        let mut budget = 100;
+        extract_archive(&zip_path, &dest, &mut budget).unwrap();
+        assert_eq!(fs::read_to_string(dest.join("app/main.py")).unwrap(), "print('hi')");
+        assert_eq!(budget, 89);
+        let mut budget = 5;
+        assert!(extract_archive(&zip_path, &dest, &mut budget).unwrap_err().contains("size limit"));
+
+        let tar_path = dir.path().join("evil.tar");
+        let mut builder = tar::Builder::new(fs::File::create(&tar_path).unwrap());
+        let mut header = tar::Header::new_gnu();
+        header.set_size(0);
+        header.set_entry_type(tar::EntryType::Symlink);
+        builder.append_link(&mut header, "link", "/etc/passwd").unwrap();
+        builder.finish().unwrap();
+        let mut budget = 100;
+        assert!(extract_archive(&tar_path, &dest, &mut budget).unwrap_err().contains("not a regular file"));
+        assert!(!dest.join("link").exists());
+    }
+}
@@ -1,12 +1,13 @@
 //! x402-aware HTTP client
 
-use reqwest::{header, Client, Response};
+use reqwest::{header, header::HeaderMap, Client, Response};
 use serde::Serialize;
 use std::sync::Arc;
 use std::time::Duration;
 
 use super::signer::X402Signer;
 use super::types::{PaymentRequired, X402PaymentInfo};
+use crate::wallet::WalletSigner;
 
 /// Result of a request that may have required payment
 pub struct X402Response {
@@ -21,15 +22,14 @@ pub struct X402Client {
 }
 
 impl X402Client {
-    /// Create a new x402 client with a burner wallet private key
-    pub fn new(private_key: &str) -> Result<Self, String> {
+    /// Create a new x402 client that pays with `signer` (see `crate::wallet`)
+    pub fn with_signer(signer: Arc<dyn WalletSigner>) -> Result<Self, String> {
+        let signer = X402Signer::from_wallet(signer);
         let client = Client::builder()
             .timeout(Duration::from_secs(120))
             .build()
             .map_err(|e| format!("Failed to create HTTP client: {}", e))?;
 
-        let signer = X402Signer::new(private_key)?;
-
         log::info!("[X402] Initialized with wallet address: {}", signer.address());
 
         Ok(Self {
@@ -154,18 +154,7 @@ impl X402Client {
 
         log::info!("[X402] Payment sent, response status: {}", paid_response.status());
 
-        // Try to extract transaction hash from response headers
-        // x402 servers may return it in various headers
-        let tx_hash = paid_response
-            .headers()
-            .get("x-payment-transaction")
-            .or_else(|| paid_response.headers().get("X-Payment-Transaction"))
-            .or_else(|| paid_response.headers().get("x-transaction-hash"))
-            .or_else(|| paid_response.headers().get("X-Transaction-Hash"))
-            .or_else(|| paid_response.headers().get("x-payment-tx"))
-            .or_else(|| paid_response.headers().get("X-Payment-Tx"))
-            .and_then(|h| h.to_str().ok())
-            .map(|s| s.to_string());
+        let tx_hash = settlement_tx_hash(paid_response.headers());
 
         // Update payment info with tx_hash if available
         let payment_info = if let Some(hash) = tx_hash {
@@ -188,6 +177,26 @@ impl X402Client {
     }
 }
 
+/// Settlement transaction hash from a paid response: the base64 JSON
+/// `X-PAYMENT-RESPONSE` header from the x402 spec, or one of the plain
+/// headers some servers send instead
+pub fn settlement_tx_hash(headers: &HeaderMap) -> Option<String> {
+    let settlement = headers
+        .get("x-payment-response")
+        .and_then(|h| h.to_str().ok())
+        .and_then(|h| base64::Engine::decode(&base64::engine::general_purpose::STANDARD, h).ok())
+        .and_then(|json| serde_json::from_slice::<serde_json::Value>(&json).ok())
+        .and_then(|v| v.get("transaction").and_then(|t| t.as_str()).map(str::to_string))
+        .filter(|hash| !hash.is_empty());
+    settlement.or_else(|| {
+        ["x-payment-transaction", "x-transaction-hash", "x-payment-tx"]
+            .iter()
+            .find_map(|name| headers.get(*name))
+            .and_then(|h| h.to_str().ok())
+            .map(|s| s.to_string())
+    })
+}
+
 impl X402Client {
     /// Make a regular POST request without x402 payment handling
     /// Used for custom RPC endpoints that don't require payment
@@ -236,3 +245,25 @@ impl X402Client {
 pub fn is_x402_endpoint(url: &str) -> bool {
     url.contains("defirelay.com") || url.contains("defirelay.io")
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use reqwest::header::HeaderValue;
+
+    #[test]
+    fn test_settlement_tx_hash() {
+        let hash = "0x9f3c5a1be8d2c6f04a7b1e5d93c28f6a0b4e7d1c5f2a8b3e6d9c0f1a2b3c4d5e";
+        let settlement = base64::Engine::encode(
+            &base64::engine::general_purpose::STANDARD,
+            serde_json::json!({ "success": true, "transaction": hash, "network": "base" }).to_string(),
+        );
+
+        let mut headers = HeaderMap::new();
+        assert_eq!(settlement_tx_hash(&headers), None);
+        headers.insert("X-Transaction-Hash", HeaderValue::from_static("0xabc"));
+        assert_eq!(settlement_tx_hash(&headers).as_deref(), Some("0xabc"));
+        headers.insert("X-PAYMENT-RESPONSE", HeaderValue::from_str(&settlement).unwrap());
+        assert_eq!(settlement_tx_hash(&headers).as_deref(), Some(hash));
+    }
+}
@@ -18,6 +18,9 @@ const SYMBOL_SELECTOR: [u8; 4] = [0x95, 0xd8, 0x9b, 0x41];
 /// Function selector for nonces(address) - EIP-2612
 const NONCES_SELECTOR: [u8; 4] = [0x7e, 0xce, 0xbe, 0x00];
 
+/// Function selector for allowance(address,address)
+const ALLOWANCE_SELECTOR: [u8; 4] = [0xdd, 0x62, 0xed, 0x3e];
+
 /// Encode a balanceOf(address) call
 pub fn encode_balance_of(address: Address) -> Vec<u8> {
     let mut data = BALANCE_OF_SELECTOR.to_vec();
@@ -78,6 +81,13 @@ pub fn encode_nonces(address: Address) -> Vec<u8> {
     data
 }
 
+/// Encode an allowance(owner, spender) call; the response decodes with `decode_balance`
+pub fn encode_allowance(owner: Address, spender: Address) -> Vec<u8> {
+    let mut data = ALLOWANCE_SELECTOR.to_vec();
+    data.extend_from_slice(&ethers::abi::encode(&[Token::Address(owner), Token::Address(spender)]));
+    data
+}
+
 /// Decode a nonces response (uint256)
 pub fn decode_nonces(data: &[u8]) -> Result<U256, String> {
     if data.len() < 32 {
@@ -118,6 +128,22 @@ mod tests {
             SYMBOL_SELECTOR,
             keccak256(b"symbol()")[0..4]
         );
+        assert_eq!(
+            ALLOWANCE_SELECTOR,
+            keccak256(b"allowance(address,address)")[0..4]
+        );
+    }
+
+    #[test]
+    fn test_encode_allowance() {
+        let owner = Address::from_str("0xf39Fd6e51aad88F6F4ce6aB8827279cffFb92266").unwrap();
+        let spender = Address::from_str("0x000000000022D473030F116dDEE9F6B43aC78BA3").unwrap();
+        let encoded = encode_allowance(owner, spender);
+
+        assert_eq!(encoded.len(), 68);
+        assert_eq!(&encoded[0..4], &ALLOWANCE_SELECTOR);
+        assert_eq!(&encoded[16..36], owner.as_bytes());
+        assert_eq!(&encoded[48..68], spender.as_bytes());
     }
 
     #[test]
@@ -6,9 +6,12 @@
 use ethers::types::{Address, Bytes, H256, U256, U64};
 use serde::{Deserialize, Serialize};
 use serde_json::{json, Value};
+use std::sync::Arc;
 use std::time::Duration;
 
 use super::client::X402Client;
+use crate::tools::network::normalize_network;
+use crate::wallet::WalletSigner;
 
 /// Default RPC endpoints for defirelay (used when no custom config)
 const DEFAULT_RPC_BASE: &str = "https://rpc.defirelay.com/rpc/light/base";
@@ -65,8 +68,8 @@ pub struct TransactionReceipt {
 
 impl X402EvmRpc {
     /// Create a new X402 EVM RPC client with default settings (x402 enabled)
-    pub fn new(private_key: &str, network: &str) -> Result<Self, String> {
-        let client = X402Client::new(private_key)?;
+    pub fn new(signer: Arc<dyn WalletSigner>, network: &str) -> Result<Self, String> {
+        let client = X402Client::with_signer(signer)?;
         Ok(Self {
             client,
             network: network.to_string(),
@@ -77,12 +80,12 @@ impl X402EvmRpc {
 
     /// Create a new X402 EVM RPC client with custom configuration
     pub fn new_with_config(
-        private_key: &str,
+        signer: Arc<dyn WalletSigner>,
         network: &str,
         rpc_url: Option<String>,
         use_x402: bool,
     ) -> Result<Self, String> {
-        let client = X402Client::new(private_key)?;
+        let client = X402Client::with_signer(signer)?;
         Ok(Self {
             client,
             network: network.to_string(),
@@ -98,7 +101,8 @@ impl X402EvmRpc {
         } else {
             match self.network.as_str() {
                 "mainnet" => DEFAULT_RPC_MAINNET.to_string(),
-                _ => DEFAULT_RPC_BASE.to_string(),
+                "base" => DEFAULT_RPC_BASE.to_string(),
+                other => format!("https://rpc.defirelay.com/rpc/light/{}", other),
             }
         }
     }
@@ -110,10 +114,9 @@ impl X402EvmRpc {
 
     /// Get the chain ID for the current network
     pub fn chain_id(&self) -> u64 {
-        match self.network.as_str() {
-            "mainnet" => 1,
-            _ => 8453, // Base
-        }
+        normalize_network(&self.network)
+            .map(|n| n.chain_id())
+            .unwrap_or(8453) // Base
     }
 
     /// Make a JSON-RPC call via x402 or regular HTTP depending on config
@@ -192,6 +195,36 @@ impl X402EvmRpc {
         Ok(Bytes::from(bytes))
     }
 
+    /// Simulate a transaction from `from` with eth_call, returning its output.
+    /// A revert comes back as an error containing "execution reverted".
+    pub async fn simulate(
+        &self,
+        from: Address,
+        to: Address,
+        data: &[u8],
+        value: U256,
+    ) -> Result<Bytes, String> {
+        let params = json!([
+            {
+                "from": format!("{:?}", from),
+                "to": format!("{:?}", to),
+                "data": format!("0x{}", hex::encode(data)),
+                "value": format!("0x{:x}", value)
+            },
+            "latest"
+        ]);
+
+        let result = self.rpc_call("eth_call", params).await?;
+
+        let hex_str = result.as_str()
+            .ok_or_else(|| "Invalid eth_call response".to_string())?;
+
+        let bytes = hex::decode(hex_str.trim_start_matches("0x"))
+            .map_err(|e| format!("Failed to decode eth_call result: {}", e))?;
+
+        Ok(Bytes::from(bytes))
+    }
+
     /// Estimate gas for a transaction
     pub async fn estimate_gas(
         &self,
@@ -18,6 +18,6 @@ mod evm_rpc;
 pub mod erc20;
 
 pub use types::*;
-pub use client::{X402Client, X402Response, is_x402_endpoint};
+pub use client::{X402Client, is_x402_endpoint, settlement_tx_hash};
 pub use signer::X402Signer;
 pub use evm_rpc::X402EvmRpc;
@@ -4,43 +4,31 @@
 //! - "permit" (EIP-2612): Permit signature allowing facilitator to transfer tokens
 //! - "exact" (EIP-3009): TransferWithAuthorization for direct transfers
 
-use ethers::core::k256::ecdsa::SigningKey;
-use ethers::signers::{LocalWallet, Signer};
-use ethers::types::{H256, U256};
+use ethers::types::transaction::eip712::TypedData;
+use ethers::types::{Address, H256, U256};
 use ethers::utils::keccak256;
+use serde_json::{json, Value};
+use std::sync::Arc;
 use std::time::{SystemTime, UNIX_EPOCH};
 
 use super::erc20;
 use super::types::*;
+use crate::wallet::WalletSigner;
 
-/// x402 payment signer using a local wallet
+/// x402 payment signer backed by a wallet
 pub struct X402Signer {
-    wallet: LocalWallet,
-    private_key: String,
+    signer: Arc<dyn WalletSigner>,
 }
 
 impl X402Signer {
-    /// Create a new signer from a private key (hex string with or without 0x prefix)
-    pub fn new(private_key: &str) -> Result<Self, String> {
-        let key_hex = private_key.strip_prefix("0x").unwrap_or(private_key);
-        let key_bytes = hex::decode(key_hex)
-            .map_err(|e| format!("Invalid private key hex: {}", e))?;
-
-        let signing_key = SigningKey::from_bytes(key_bytes.as_slice().into())
-            .map_err(|e| format!("Invalid private key: {}", e))?;
-
-        // Default chain ID, will be overridden per-signature based on network
-        let wallet = LocalWallet::from(signing_key).with_chain_id(BASE_CHAIN_ID);
-
-        Ok(Self {
-            wallet,
-            private_key: private_key.to_string(),
-        })
+    /// Sign with any wallet, e.g. `crate::wallet::active_signer()`
+    pub fn from_wallet(signer: Arc<dyn WalletSigner>) -> Self {
+        Self { signer }
     }
 
     /// Get the wallet address
     pub fn address(&self) -> String {
-        format!("{:?}", self.wallet.address()).to_lowercase()
+        format!("{:?}", self.signer.address()).to_lowercase()
     }
 
     /// Generate a cryptographically secure nonce (for EIP-3009)
@@ -63,7 +51,7 @@ impl X402Signer {
         };
 
         // Encode the nonces(address) call
-        let call_data = erc20::encode_nonces(self.wallet.address());
+        let call_data = erc20::encode_nonces(self.signer.address());
 
         // Build JSON-RPC request
         let request = serde_json::json!({
@@ -153,22 +141,23 @@ impl X402Signer {
             from, requirements.network, nonce_u256
         );
 
-        // Build EIP-712 domain from token metadata
-        let domain = Eip712Domain::from_token_metadata(token_metadata)?;
-
-        // Build permit message
-        let message = PermitMessage {
-            owner: self.wallet.address(),
-            spender: spender.parse()
-                .map_err(|e| format!("Invalid facilitatorSigner address: {}", e))?,
-            value: U256::from_dec_str(&requirements.max_amount_required)
-                .map_err(|e| format!("Invalid amount: {}", e))?,
-            nonce: nonce_u256,
-            deadline: U256::from(deadline),
-        };
-
-        // Sign the typed data
-        let signature = self.sign_eip712(&domain, message.struct_hash()).await?;
+        let spender_address: Address = spender.parse()
+            .map_err(|e| format!("Invalid facilitatorSigner address: {}", e))?;
+        U256::from_dec_str(&value).map_err(|e| format!("Invalid amount: {}", e))?;
+
+        let typed_data = build_typed_data(
+            token_metadata,
+            "Permit",
+            PERMIT_FIELDS,
+            json!({
+                "owner": from,
+                "spender": format!("{:?}", spender_address),
+                "value": value,
+                "nonce": nonce_u256.to_string(),
+                "deadline": deadline.to_string(),
+            }),
+        )?;
+        let signature = self.sign_typed_data(&typed_data).await?;
 
         // Build EIP-2612 authorization format
         let authorization = Eip2612Authorization {
@@ -218,23 +207,24 @@ impl X402Signer {
         let nonce = Self::generate_nonce();
         let nonce_hex = format!("{:?}", nonce);
 
-        // Build EIP-712 domain from token metadata
-        let domain = Eip712Domain::from_token_metadata(token_metadata)?;
-
-        let message = TransferWithAuthorizationMessage {
-            from: self.wallet.address(),
-            to: requirements.pay_to_address.parse()
-                .map_err(|e| format!("Invalid pay_to_address: {}", e))?,
-            value: U256::from_dec_str(&requirements.max_amount_required)
-                .map_err(|e| format!("Invalid amount: {}", e))?,
-            valid_after: U256::zero(),
-            valid_before: U256::from_dec_str(&valid_before)
-                .map_err(|e| format!("Invalid valid_before: {}", e))?,
-            nonce,
-        };
-
-        // Sign the typed data
-        let signature = self.sign_eip712(&domain, message.struct_hash()).await?;
+        let to_address: Address = requirements.pay_to_address.parse()
+            .map_err(|e| format!("Invalid pay_to_address: {}", e))?;
+        U256::from_dec_str(&value).map_err(|e| format!("Invalid amount: {}", e))?;
+
+        let typed_data = build_typed_data(
+            token_metadata,
+            "TransferWithAuthorization",
+            TRANSFER_WITH_AUTHORIZATION_FIELDS,
+            json!({
+                "from": from,
+                "to": format!("{:?}", to_address),
+                "value": value,
+                "validAfter": valid_after,
+                "validBefore": valid_before,
+                "nonce": nonce_hex,
+            }),
+        )?;
+        let signature = self.sign_typed_data(&typed_data).await?;
 
         // Build EIP-3009 authorization format
         let authorization = Eip3009Authorization {
@@ -265,131 +255,64 @@ impl X402Signer {
         Ok(payload)
     }
 
-    /// Sign EIP-712 typed data with a pre-computed struct hash
-    async fn sign_eip712(
-        &self,
-        domain: &Eip712Domain,
-        struct_hash: H256,
-    ) -> Result<String, String> {
-        // Calculate domain separator
-        let domain_separator = domain.separator();
-
-        // Calculate final hash: keccak256("\x19\x01" ++ domainSeparator ++ structHash)
-        let mut to_sign = Vec::with_capacity(66);
-        to_sign.push(0x19);
-        to_sign.push(0x01);
-        to_sign.extend_from_slice(domain_separator.as_bytes());
-        to_sign.extend_from_slice(struct_hash.as_bytes());
-        let digest = H256::from(keccak256(&to_sign));
-
-        // Sign the digest
-        let signature = self.wallet
-            .sign_hash(digest)
-            .map_err(|e| format!("Failed to sign: {}", e))?;
-
+    /// Sign EIP-712 typed data, returning the 65-byte signature as hex
+    async fn sign_typed_data(&self, typed_data: &TypedData) -> Result<String, String> {
+        let signature = self.signer.sign_typed_data(typed_data).await?;
         Ok(format!("0x{}", hex::encode(signature.to_vec())))
     }
 }
 
-/// EIP-712 domain for token signatures
-struct Eip712Domain {
-    name: String,
-    version: String,
-    chain_id: u64,
-    verifying_contract: ethers::types::Address,
-}
-
-impl Eip712Domain {
-    /// Create domain from token metadata (dynamic, not hardcoded)
-    fn from_token_metadata(metadata: &TokenMetadata) -> Result<Self, String> {
-        Ok(Eip712Domain {
-            name: metadata.name.clone(),
-            version: metadata.version.clone(),
-            chain_id: metadata.chain_id,
-            verifying_contract: metadata.address.parse()
-                .map_err(|e| format!("Invalid token address: {}", e))?,
-        })
-    }
-
-    fn separator(&self) -> H256 {
-        let type_hash = keccak256(
-            b"EIP712Domain(string name,string version,uint256 chainId,address verifyingContract)"
-        );
-
-        let name_hash = keccak256(self.name.as_bytes());
-        let version_hash = keccak256(self.version.as_bytes());
-
-        let mut encoded = Vec::new();
-        encoded.extend_from_slice(&type_hash);
-        encoded.extend_from_slice(&name_hash);
-        encoded.extend_from_slice(&version_hash);
-        encoded.extend_from_slice(&ethers::abi::encode(&[
-            ethers::abi::Token::Uint(U256::from(self.chain_id)),
-        ]));
-        encoded.extend_from_slice(&ethers::abi::encode(&[
-            ethers::abi::Token::Address(self.verifying_contract),
-        ]));
-
-        H256::from(keccak256(&encoded))
-    }
-}
-
-/// EIP-2612 Permit message
-struct PermitMessage {
-    owner: ethers::types::Address,
-    spender: ethers::types::Address,
-    value: U256,
-    nonce: U256,
-    deadline: U256,
-}
-
-impl PermitMessage {
-    fn struct_hash(&self) -> H256 {
-        let type_hash = keccak256(
-            b"Permit(address owner,address spender,uint256 value,uint256 nonce,uint256 deadline)"
-        );
-
-        let encoded = ethers::abi::encode(&[
-            ethers::abi::Token::FixedBytes(type_hash.to_vec()),
-            ethers::abi::Token::Address(self.owner),
-            ethers::abi::Token::Address(self.spender),
-            ethers::abi::Token::Uint(self.value),
-            ethers::abi::Token::Uint(self.nonce),
-            ethers::abi::Token::Uint(self.deadline),
-        ]);
-
-        H256::from(keccak256(&encoded))
-    }
-}
-
-/// TransferWithAuthorization message for EIP-3009
-struct TransferWithAuthorizationMessage {
-    from: ethers::types::Address,
-    to: ethers::types::Address,
-    value: U256,
-    valid_after: U256,
-    valid_before: U256,
-    nonce: H256,
-}
-
-impl TransferWithAuthorizationMessage {
-    fn struct_hash(&self) -> H256 {
-        let type_hash = keccak256(
-            b"TransferWithAuthorization(address from,address to,uint256 value,uint256 validAfter,uint256 validBefore,bytes32 nonce)"
-        );
-
-        let encoded = ethers::abi::encode(&[
-            ethers::abi::Token::FixedBytes(type_hash.to_vec()),
-            ethers::abi::Token::Address(self.from),
-            ethers::abi::Token::Address(self.to),
-            ethers::abi::Token::Uint(self.value),
-            ethers::abi::Token::Uint(self.valid_after),
-            ethers::abi::Token::Uint(self.valid_before),
-            ethers::abi::Token::FixedBytes(self.nonce.as_bytes().to_vec()),
-        ]);
-
-        H256::from(keccak256(&encoded))
-    }
+const PERMIT_FIELDS: &[(&str, &str)] = &[
+    ("owner", "address"),
+    ("spender", "address"),
+    ("value", "uint256"),
+    ("nonce", "uint256"),
+    ("deadline", "uint256"),
+];
+
+const TRANSFER_WITH_AUTHORIZATION_FIELDS: &[(&str, &str)] = &[
+    ("from", "address"),
+    ("to", "address"),
+    ("value", "uint256"),
+    ("validAfter", "uint256"),
+    ("validBefore", "uint256"),
+    ("nonce", "bytes32"),
+];
+
+/// EIP-712 typed data for a message signed against the token contract's domain
+fn build_typed_data(
+    metadata: &TokenMetadata,
+    primary_type: &str,
+    fields: &[(&str, &str)],
+    message: Value,
+) -> Result<TypedData, String> {
+    let verifying_contract: Address = metadata.address.parse()
+        .map_err(|e| format!("Invalid token address: {}", e))?;
+    let fields: Vec<Value> = fields
+        .iter()
+        .map(|(name, kind)| json!({ "name": name, "type": kind }))
+        .collect();
+
+    serde_json::from_value(json!({
+        "types": {
+            "EIP712Domain": [
+                { "name": "name", "type": "string" },
+                { "name": "version", "type": "string" },
+                { "name": "chainId", "type": "uint256" },
+                { "name": "verifyingContract", "type": "address" },
+            ],
+            primary_type: fields,
+        },
+        "primaryType": primary_type,
+        "domain": {
+            "name": metadata.name,
+            "version": metadata.version,
+            "chainId": metadata.chain_id,
+            "verifyingContract": format!("{:?}", verifying_contract),
+        },
+        "message": message,
+    }))
+    .map_err(|e| format!("Failed to build typed data: {}", e))
 }
 
 #[cfg(test)]
@@ -400,8 +323,62 @@ mod tests {
     fn test_address_derivation() {
         // Test with a known private key
         let private_key = "0xac0974bec39a17e36ba4a6b4d238ff944bacb478cbed5efcae784d7bf4f2ff80";
-        let signer = X402Signer::new(private_key).unwrap();
+        let signer = X402Signer::from_wallet(Arc::new(crate::wallet::LocalSigner::new(private_key).unwrap()));
         // This is Hardhat's first default account
         assert_eq!(signer.address(), "0xf39fd6e51aad88f6f4ce6ab8827279cfffb92266");
     }
+
+    #[test]
+    fn test_transfer_authorization_digest() {
+        use ethers::abi::{encode, Token};
+        use ethers::types::transaction::eip712::Eip712;
+
+        let metadata = TokenMetadata {
+            name: "USD Coin".to_string(),
+            version: "2".to_string(),
+            address: "0x833589fCD6eDb6E08f4c7C32D4f71b54bdA02913".to_string(),
+            chain_id: BASE_CHAIN_ID,
+            decimals: 6,
+        };
+        let from: Address = "0xf39fd6e51aad88f6f4ce6ab8827279cfffb92266".parse().unwrap();
+        let to: Address = "0x70997970c51812dc3a010c7d01b50e0d17dc79c8".parse().unwrap();
+        let nonce = H256::repeat_byte(0xab);
+        let typed_data = build_typed_data(
+            &metadata,
+            "TransferWithAuthorization",
+            TRANSFER_WITH_AUTHORIZATION_FIELDS,
+            json!({
+                "from": format!("{:?}", from),
+                "to": format!("{:?}", to),
+                "value": "10000",
+                "validAfter": "0",
+                "validBefore": "1700000000",
+                "nonce": format!("{:?}", nonce),
+            }),
+        )
+        .unwrap();
+
+        // keccak256("\x19\x01" ++ domainSeparator ++ structHash), computed by hand
+        let domain_separator = keccak256(encode(&[
+            Token::FixedBytes(keccak256(b"EIP712Domain(string name,string version,uint256 chainId,address verifyingContract)").to_vec()),
+            Token::FixedBytes(keccak256(b"USD Coin").to_vec()),
+            Token::FixedBytes(keccak256(b"2").to_vec()),
+            Token::Uint(U256::from(BASE_CHAIN_ID)),
+            Token::Address(metadata.address.parse().unwrap()),
+        ]));
+        let struct_hash = keccak256(encode(&[
+            Token::FixedBytes(keccak256(b"TransferWithAuthorization(address from,address to,uint256 value,uint256 validAfter,uint256 validBefore,bytes32 nonce)").to_vec()),
+            Token::Address(from),
+            Token::Address(to),
+            Token::Uint(U256::from(10000)),
+            Token::Uint(U256::zero()),
+            Token::Uint(U256::from(1_700_000_000u64)),
+            Token::FixedBytes(nonce.as_bytes().to_vec()),
+        ]));
+        let mut to_sign = vec![0x19, 0x01];
+        to_sign.extend_from_slice(&domain_separator);
+        to_sign.extend_from_slice(&struct_hash);
+
+        assert_eq!(typed_data.encode_eip712().unwrap(), keccak256(&to_sign));
+    }
 }
@@ -2,6 +2,8 @@
 
 use serde::{Deserialize, Serialize};
 
+use crate::models::{NewTransaction, TransactionKind, TransactionStatus};
+
 /// USDC contract address on Base mainnet (default fallback)
 pub const USDC_ADDRESS: &str = "0x833589fCD6eDb6E08f4c7C32D4f71b54bdA02913";
 
@@ -266,6 +268,27 @@ impl X402PaymentInfo {
         self.status = PaymentStatus::Failed;
         self
     }
+
+    /// Entry for the transactions audit trail, paid on `network`
+    pub fn to_transaction(&self, network: &str) -> NewTransaction {
+        let status = match self.status {
+            PaymentStatus::Pending => TransactionStatus::Pending,
+            PaymentStatus::Confirmed => TransactionStatus::Confirmed,
+            PaymentStatus::Failed => TransactionStatus::Failed,
+        };
+        let mut tx = NewTransaction::new(TransactionKind::X402Payment, status, network);
+        tx.tx_hash = self.tx_hash.clone();
+        tx.to_address = Some(self.pay_to.clone());
+        tx.value = self.amount.clone();
+        // x402 prices are USDC, so the formatted amount is the USD value
+        tx.value_usd = self.amount_formatted.parse().ok();
+        tx.details = Some(serde_json::json!({
+            "asset": self.asset,
+            "amount_formatted": self.amount_formatted,
+            "resource": self.resource,
+        }));
+        tx
+    }
 }
 
 /// Format USDC amount from raw value (6 decimals) to human-readable string
@@ -40,7 +40,9 @@ export async function apiFetch<T>(
 }
 
 // Auth API - SIWE (Sign In With Ethereum)
-export async function generateChallenge(publicAddress: string): Promise<{ challenge: string }> {
+export async function generateChallenge(
+  publicAddress: string
+): Promise<{ challenge: string; totpRequired: boolean }> {
   const response = await fetch(`${API_BASE}/auth/generate_challenge`, {
     method: 'POST',
     headers: { 'Content-Type': 'application/json' },
@@ -57,13 +59,14 @@ export async function generateChallenge(publicAddress: string): Promise<{ challe
     throw new Error(data.error || 'Failed to generate challenge');
   }
 
-  return { challenge: data.challenge };
+  return { challenge: data.challenge, totpRequired: Boolean(data.totp_required) };
 }
 
 export async function validateAuth(
   publicAddress: string,
   challenge: string,
-  signature: string
+  signature: string,
+  totpCode?: string
 ): Promise<{ token: string; expires_at: number }> {
   const response = await fetch(`${API_BASE}/auth/validate_auth`, {
     method: 'POST',
@@ -72,6 +75,7 @@ export async function validateAuth(
       public_address: publicAddress,
       challenge,
       signature,
+      totp_code: totpCode,
     }),
   });
 
@@ -951,17 +955,18 @@ export async function getPendingConfirmation(channelId: number): Promise<Pending
   return apiFetch(`/confirmation/pending/${channelId}`);
 }
 
-export async function confirmTransaction(channelId: number): Promise<ConfirmationResponse> {
-  return apiFetch('/confirmation/confirm', {
+// Spends above the approval threshold wait on POST /approvals/{id}
+export async function confirmTransaction(approvalId: string): Promise<ConfirmationResponse> {
+  return apiFetch(`/approvals/${approvalId}`, {
     method: 'POST',
-    body: JSON.stringify({ channel_id: channelId }),
+    body: JSON.stringify({ approve: true }),
   });
 }
 
-export async function cancelTransaction(channelId: number): Promise<ConfirmationResponse> {
-  return apiFetch('/confirmation/cancel', {
+export async function cancelTransaction(approvalId: string): Promise<ConfirmationResponse> {
+  return apiFetch(`/approvals/${approvalId}`, {
     method: 'POST',
-    body: JSON.stringify({ channel_id: channelId }),
+    body: JSON.stringify({ approve: false }),
   });
 }
 
@@ -634,11 +634,13 @@ export default function AgentChat() {
     on('confirmation.required', handleConfirmationRequired);
     on('confirmation.approved', handleConfirmationApproved);
     on('confirmation.rejected', handleConfirmationRejected);
+    on('confirmation.expired', handleConfirmationRejected);
 
     return () => {
       off('confirmation.required', handleConfirmationRequired);
       off('confirmation.approved', handleConfirmationApproved);
       off('confirmation.rejected', handleConfirmationRejected);
+      off('confirmation.expired', handleConfirmationRejected);
     };
   }, [on, off]);
 
@@ -923,7 +925,7 @@ export default function AgentChat() {
       }
       try {
         addMessage('system', 'Confirming transaction...');
-        const result = await confirmTransaction(pendingConfirmation.channel_id);
+        const result = await confirmTransaction(pendingConfirmation.confirmation_id);
         if (result.success) {
           addMessage('system', result.message || 'Transaction confirmed and executing.');
           setPendingConfirmation(null);
@@ -940,7 +942,7 @@ export default function AgentChat() {
         return;
       }
       try {
-        const result = await cancelTransaction(pendingConfirmation.channel_id);
+        const result = await cancelTransaction(pendingConfirmation.confirmation_id);
         if (result.success) {
           addMessage('system', result.message || 'Transaction cancelled.');
           setPendingConfirmation(null);
@@ -1368,7 +1370,7 @@ export default function AgentChat() {
             confirmation={pendingConfirmation}
             onConfirm={async (confirmationId) => {
               console.log('[Confirmation] Confirming:', confirmationId);
-              const result = await confirmTransaction(pendingConfirmation.channel_id);
+              const result = await confirmTransaction(pendingConfirmation.confirmation_id);
               if (result.success) {
                 addMessage('system', result.message || 'Transaction confirmed and executing.');
                 setPendingConfirmation(null);
@@ -1378,7 +1380,7 @@ export default function AgentChat() {
             }}
             onCancel={async (confirmationId) => {
               console.log('[Confirmation] Cancelling:', confirmationId);
-              const result = await cancelTransaction(pendingConfirmation.channel_id);
+              const result = await cancelTransaction(pendingConfirmation.confirmation_id);
               if (result.success) {
                 addMessage('system', result.message || 'Transaction cancelled.');
                 setPendingConfirmation(null);
@@ -1,5 +1,5 @@
 import { useState, useEffect } from 'react';
-import { MessageSquare, Hash, Plus, Play, Square, Trash2, Save } from 'lucide-react';
+import { MessageSquare, Hash, Radio, Plus, Play, Square, Trash2, Save } from 'lucide-react';
 import Card, { CardContent, CardHeader, CardTitle } from '@/components/ui/Card';
 import Button from '@/components/ui/Button';
 import Input from '@/components/ui/Input';
@@ -17,8 +17,19 @@ const CHANNEL_TYPES = [
   { value: 'telegram', label: 'Telegram', icon: MessageSquare, color: 'blue' },
   { value: 'slack', label: 'Slack', icon: Hash, color: 'purple' },
   { value: 'discord', label: 'Discord', icon: MessageSquare, color: 'indigo' },
+  { value: 'farcaster', label: 'Farcaster', icon: Radio, color: 'violet' },
 ];
 
+// These can leave the token empty to use DISCORD_BOT_TOKEN / NEYNAR_API_KEY from API Keys
+const SHARED_TOKEN_TYPES = ['discord', 'farcaster'];
+
+const TOKEN_PLACEHOLDERS: Record<string, string> = {
+  telegram: '123456:ABC-DEF...',
+  slack: 'xoxb-...',
+  discord: 'MTIz... (optional)',
+  farcaster: 'Neynar API key (optional)',
+};
+
 interface ChannelFormData {
   channel_type: string;
   name: string;
@@ -60,7 +71,8 @@ export default function Channels() {
   }, []);
 
   const handleCreate = async () => {
-    if (!newChannel.name.trim() || !newChannel.bot_token.trim()) {
+    const tokenOptional = SHARED_TOKEN_TYPES.includes(newChannel.channel_type);
+    if (!newChannel.name.trim() || (!tokenOptional && !newChannel.bot_token.trim())) {
       setError('Name and bot token are required');
       return;
     }
@@ -70,13 +82,18 @@ export default function Channels() {
       return;
     }
 
+    if (newChannel.channel_type === 'farcaster' && !newChannel.app_token.trim()) {
+      setError('Farcaster requires a Neynar signer UUID');
+      return;
+    }
+
     setActionLoading(-1);
     try {
       await createChannel({
         channel_type: newChannel.channel_type,
         name: newChannel.name,
         bot_token: newChannel.bot_token,
-        app_token: newChannel.channel_type === 'slack' ? newChannel.app_token : undefined,
+        app_token: ['slack', 'farcaster'].includes(newChannel.channel_type) ? newChannel.app_token : undefined,
       });
       setNewChannel(emptyForm);
       setShowAddForm(false);
@@ -222,7 +239,7 @@ export default function Channels() {
                 label="Bot Token"
                 value={newChannel.bot_token}
                 onChange={(e) => setNewChannel({ ...newChannel, bot_token: e.target.value })}
-                placeholder={newChannel.channel_type === 'telegram' ? '123456:ABC-DEF...' : 'xoxb-...'}
+                placeholder={TOKEN_PLACEHOLDERS[newChannel.channel_type]}
               />
               {newChannel.channel_type === 'slack' && (
                 <Input
@@ -232,6 +249,14 @@ export default function Channels() {
                   placeholder="xapp-..."
                 />
               )}
+              {newChannel.channel_type === 'farcaster' && (
+                <Input
+                  label="Neynar Signer UUID"
+                  value={newChannel.app_token}
+                  onChange={(e) => setNewChannel({ ...newChannel, app_token: e.target.value })}
+                  placeholder="19d0c5fd-9b33-4a48-a0e2-bc7b0555baec"
+                />
+              )}
               <div className="flex gap-2 justify-end">
                 <Button variant="secondary" onClick={() => setShowAddForm(false)}>
                   Cancel
@@ -333,6 +358,13 @@ export default function Channels() {
                           onChange={(e) => setEditForm({ ...editForm, app_token: e.target.value })}
                         />
                       )}
+                      {channel.channel_type === 'farcaster' && (
+                        <Input
+                          label="Neynar Signer UUID"
+                          value={editForm.app_token}
+                          onChange={(e) => setEditForm({ ...editForm, app_token: e.target.value })}
+                        />
+                      )}
                       <div className="flex gap-2 justify-end">
                         <Button variant="secondary" onClick={() => setEditingId(null)}>
                           Cancel
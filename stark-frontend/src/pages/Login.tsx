@@ -3,6 +3,7 @@ import { useNavigate } from 'react-router-dom';
 import { BrowserProvider } from 'ethers';
 import { generateChallenge, validateAuth } from '@/lib/api';
 import Button from '@/components/ui/Button';
+import Input from '@/components/ui/Input';
 import Card, { CardContent } from '@/components/ui/Card';
 
 type LoginState = 'idle' | 'connecting' | 'signing' | 'verifying';
@@ -11,6 +12,13 @@ export default function Login() {
   const [error, setError] = useState('');
   const [state, setState] = useState<LoginState>('idle');
   const [connectedAddress, setConnectedAddress] = useState<string | null>(null);
+  // Signed challenge waiting for the second-factor code
+  const [pendingLogin, setPendingLogin] = useState<{
+    address: string;
+    challenge: string;
+    signature: string;
+  } | null>(null);
+  const [totpCode, setTotpCode] = useState('');
   const navigate = useNavigate();
 
   const getStateMessage = () => {
@@ -26,8 +34,33 @@ export default function Login() {
     }
   };
 
+  const showError = (err: unknown) => {
+    console.error('Login error:', err);
+    if (err instanceof Error) {
+      // Handle user rejection
+      if (err.message.includes('user rejected') || err.message.includes('User denied')) {
+        setError('Signature request was rejected');
+      } else {
+        setError(err.message);
+      }
+    } else {
+      setError('Login failed');
+    }
+    setState('idle');
+  };
+
+  const completeLogin = async (address: string, challenge: string, signature: string, code?: string) => {
+    setState('verifying');
+    const result = await validateAuth(address, challenge, signature, code);
+
+    // Store token and navigate
+    localStorage.setItem('stark_token', result.token);
+    navigate('/dashboard');
+  };
+
   const handleConnect = async () => {
     setError('');
+    setPendingLogin(null);
     setState('connecting');
 
     try {
@@ -48,38 +81,40 @@ export default function Login() {
       setConnectedAddress(address);
 
       // Generate challenge from server
-      const { challenge } = await generateChallenge(address);
+      const { challenge, totpRequired } = await generateChallenge(address);
 
       // Request signature
       setState('signing');
       const signer = await provider.getSigner();
       const signature = await signer.signMessage(challenge);
 
-      // Verify with server
-      setState('verifying');
-      const result = await validateAuth(address, challenge, signature);
+      if (totpRequired) {
+        // Ask for the authenticator code before verifying
+        setTotpCode('');
+        setPendingLogin({ address, challenge, signature });
+        setState('idle');
+        return;
+      }
 
-      // Store token and navigate
-      localStorage.setItem('stark_token', result.token);
-      navigate('/dashboard');
+      await completeLogin(address, challenge, signature);
     } catch (err) {
-      console.error('Login error:', err);
-      if (err instanceof Error) {
-        // Handle user rejection
-        if (err.message.includes('user rejected') || err.message.includes('User denied')) {
-          setError('Signature request was rejected');
-        } else {
-          setError(err.message);
-        }
-      } else {
-        setError('Login failed');
-      }
-      setState('idle');
+      showError(err);
+    }
+  };
+
+  const handleVerifyCode = async () => {
+    if (!pendingLogin) return;
+    setError('');
+    try {
+      await completeLogin(pendingLogin.address, pendingLogin.challenge, pendingLogin.signature, totpCode.trim());
+    } catch (err) {
+      showError(err);
     }
   };
 
   const handleDisconnect = () => {
     setConnectedAddress(null);
+    setPendingLogin(null);
     setError('');
     setState('idle');
   };
@@ -146,14 +181,40 @@ export default function Login() {
                 </div>
               )}
 
-              <Button
-                onClick={handleConnect}
-                className="w-full"
-                size="lg"
-                disabled={isLoading}
-              >
-                {isLoading ? 'Connecting...' : 'Connect Wallet'}
-              </Button>
+              {pendingLogin && !isLoading ? (
+                <div className="space-y-3">
+                  <Input
+                    id="totp-code"
+                    label="Authenticator code"
+                    inputMode="numeric"
+                    autoComplete="one-time-code"
+                    maxLength={6}
+                    value={totpCode}
+                    onChange={(e) => setTotpCode(e.target.value.replace(/\D/g, ''))}
+                    onKeyDown={(e) => e.key === 'Enter' && totpCode.length === 6 && handleVerifyCode()}
+                    className="font-mono text-center text-lg tracking-widest"
+                    placeholder="123456"
+                    autoFocus
+                  />
+                  <Button
+                    onClick={handleVerifyCode}
+                    className="w-full"
+                    size="lg"
+                    disabled={totpCode.length !== 6}
+                  >
+                    Verify
+                  </Button>
+                </div>
+              ) : (
+                <Button
+                  onClick={handleConnect}
+                  className="w-full"
+                  size="lg"
+                  disabled={isLoading}
+                >
+                  {isLoading ? 'Connecting...' : 'Connect Wallet'}
+                </Button>
+              )}
 
               <p className="text-xs text-slate-500 text-center">
                 Sign in with your Ethereum wallet using SIWE (Sign In With Ethereum)
@@ -238,7 +238,7 @@ export interface LogEntry {
 
 export interface Channel {
   id: string;
-  type: 'telegram' | 'slack' | 'discord';
+  type: 'telegram' | 'slack' | 'discord' | 'farcaster';
   name: string;
   enabled: boolean;
   config?: Record<string, unknown>;